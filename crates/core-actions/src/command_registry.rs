@@ -0,0 +1,510 @@
+//! User-defined ex command registry (Commands Step 1).
+//!
+//! Plugins and configuration register named ex commands here together with
+//! an argument arity spec (`ArgSpec`, mirroring Vim's `-nargs`) and a
+//! completion hint consumed by command-line completion. The command parser
+//! consults the registry for an exact name *before* built-ins, so a
+//! registered command may shadow a built-in name, and for a prefix only
+//! after no built-in matched.
+//!
+//! Design notes:
+//! * The registry is owned by the runtime and passed into
+//!   `dispatcher::dispatch_with_commands`; `dispatch` keeps its signature and
+//!   uses an empty registry so existing callers are unaffected.
+//! * Handlers receive the parsed invocation plus mutable state/view and
+//!   return an ordinary `DispatchResult`, keeping render scheduling uniform.
+//! * Names resolve exactly first, then by unique prefix (Vim abbreviation
+//!   rule for user commands).
+//! * User commands (config aliases, plugins) must start with an uppercase
+//!   letter (E183), so neither a name nor a prefix of one can be mistaken
+//!   for a built-in like `:w`. Only the editor's own commands, registered
+//!   with `register_builtin`, may be lowercase.
+//!
+//! Forward roadmap:
+//! * Ranges (`-range`) and register arguments once ex ranges exist.
+//! * Async handlers (plugin host) completing through a follow-up event.
+
+use crate::dispatcher::DispatchResult;
+use core_model::View;
+use core_state::EditorState;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Accepted argument count (Vim `-nargs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArgSpec {
+    /// `-nargs=0`: no arguments.
+    #[default]
+    None,
+    /// `-nargs=1`: exactly one argument (the whole tail, spaces included).
+    One,
+    /// `-nargs=?`: zero or one argument.
+    Optional,
+    /// `-nargs=*`: any number of whitespace separated arguments.
+    Any,
+    /// `-nargs=+`: at least one argument.
+    AtLeastOne,
+}
+
+impl ArgSpec {
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            ArgSpec::None => count == 0,
+            ArgSpec::One => count == 1,
+            ArgSpec::Optional => count <= 1,
+            ArgSpec::Any => true,
+            ArgSpec::AtLeastOne => count >= 1,
+        }
+    }
+
+    fn splits_words(self) -> bool {
+        matches!(self, ArgSpec::Any | ArgSpec::AtLeastOne)
+    }
+}
+
+/// Completion source hint for command-line completion (Vim `-complete`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum CompletionHint {
+    #[default]
+    None,
+    File,
+    Option,
+    Command,
+    Buffer,
+    /// Fixed candidate list supplied at registration.
+    Words(Vec<String>),
+}
+
+/// Static description of a registered command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: String,
+    pub args: ArgSpec,
+    pub completion: CompletionHint,
+    pub bang: bool,
+    pub description: String,
+}
+
+impl CommandSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            args: ArgSpec::None,
+            completion: CompletionHint::None,
+            bang: false,
+            description: String::new(),
+        }
+    }
+
+    pub fn args(mut self, args: ArgSpec) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub fn completion(mut self, completion: CompletionHint) -> Self {
+        self.completion = completion;
+        self
+    }
+
+    pub fn bang(mut self, bang: bool) -> Self {
+        self.bang = bang;
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+}
+
+/// Parsed invocation passed to a handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandInvocation {
+    /// Canonical registered name (abbreviations already resolved).
+    pub name: String,
+    pub bang: bool,
+    /// Arguments split according to the command's `ArgSpec`.
+    pub args: Vec<String>,
+    /// Unsplit argument tail (leading/trailing whitespace trimmed).
+    pub raw_args: String,
+}
+
+pub type CommandHandler =
+    Arc<dyn Fn(&CommandInvocation, &mut EditorState, &mut View) -> DispatchResult + Send + Sync>;
+
+/// Command body: a native handler or a textual expansion (config aliases).
+#[derive(Clone)]
+pub enum CommandBody {
+    Handler(CommandHandler),
+    /// Ex command line (without ':') executed with the invocation's
+    /// arguments appended.
+    Alias(String),
+}
+
+#[derive(Clone)]
+pub struct RegisteredCommand {
+    pub spec: CommandSpec,
+    pub body: CommandBody,
+}
+
+impl fmt::Debug for RegisteredCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("RegisteredCommand");
+        d.field("spec", &self.spec);
+        if let CommandBody::Alias(expansion) = &self.body {
+            d.field("alias", expansion);
+        }
+        d.finish_non_exhaustive()
+    }
+}
+
+impl RegisteredCommand {
+    /// Expanded command line for alias commands (`None` for handlers).
+    pub fn expand(&self, invocation: &CommandInvocation) -> Option<String> {
+        match &self.body {
+            CommandBody::Alias(expansion) if invocation.raw_args.is_empty() => {
+                Some(format!(":{expansion}"))
+            }
+            CommandBody::Alias(expansion) => Some(format!(":{expansion} {}", invocation.raw_args)),
+            CommandBody::Handler(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    InvalidName(String),
+    NotUppercase(String),
+    AlreadyExists(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::InvalidName(name) => write!(f, "E182: Invalid command name: {name}"),
+            RegistryError::NotUppercase(name) => write!(
+                f,
+                "E183: User defined commands must start with an uppercase letter: {name}"
+            ),
+            RegistryError::AlreadyExists(name) => {
+                write!(
+                    f,
+                    "E174: Command already exists: add ! to replace it: {name}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Errors raised while validating an invocation against its spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvocationError {
+    BangNotAllowed,
+    ArgumentRequired,
+    TrailingCharacters,
+}
+
+impl fmt::Display for InvocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvocationError::BangNotAllowed => write!(f, "E477: No ! allowed"),
+            InvocationError::ArgumentRequired => write!(f, "E471: Argument required"),
+            InvocationError::TrailingCharacters => write!(f, "E488: Trailing characters"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<String, RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    pub const fn new() -> Self {
+        Self {
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// Register a new command. Fails if the name is invalid or taken.
+    pub fn register<F>(&mut self, spec: CommandSpec, handler: F) -> Result<(), RegistryError>
+    where
+        F: Fn(&CommandInvocation, &mut EditorState, &mut View) -> DispatchResult
+            + Send
+            + Sync
+            + 'static,
+    {
+        if self.commands.contains_key(&spec.name) {
+            return Err(RegistryError::AlreadyExists(spec.name));
+        }
+        self.replace(spec, handler)
+    }
+
+    /// Register or overwrite a command (`:command!` semantics).
+    pub fn replace<F>(&mut self, spec: CommandSpec, handler: F) -> Result<(), RegistryError>
+    where
+        F: Fn(&CommandInvocation, &mut EditorState, &mut View) -> DispatchResult
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.insert(spec, CommandBody::Handler(Arc::new(handler)), false)
+    }
+
+    /// Register one of the editor's own commands, which like built-ins may
+    /// start with a lowercase letter. Fails if the name is invalid or taken.
+    pub fn register_builtin<F>(
+        &mut self,
+        spec: CommandSpec,
+        handler: F,
+    ) -> Result<(), RegistryError>
+    where
+        F: Fn(&CommandInvocation, &mut EditorState, &mut View) -> DispatchResult
+            + Send
+            + Sync
+            + 'static,
+    {
+        if self.commands.contains_key(&spec.name) {
+            return Err(RegistryError::AlreadyExists(spec.name));
+        }
+        self.insert(spec, CommandBody::Handler(Arc::new(handler)), true)
+    }
+
+    /// Register a textual alias (e.g. from the `[commands]` config table).
+    /// Aliases accept any arguments, which are appended to the expansion.
+    pub fn register_alias(
+        &mut self,
+        name: impl Into<String>,
+        expansion: impl Into<String>,
+    ) -> Result<(), RegistryError> {
+        let expansion: String = expansion.into();
+        let spec = CommandSpec::new(name)
+            .args(ArgSpec::Any)
+            .bang(true)
+            .description(format!(":{expansion}"));
+        if self.commands.contains_key(&spec.name) {
+            return Err(RegistryError::AlreadyExists(spec.name));
+        }
+        let expansion = expansion.trim_start_matches(':').to_string();
+        self.insert(spec, CommandBody::Alias(expansion), false)
+    }
+
+    fn insert(
+        &mut self,
+        spec: CommandSpec,
+        body: CommandBody,
+        builtin: bool,
+    ) -> Result<(), RegistryError> {
+        if !valid_name(&spec.name) {
+            return Err(RegistryError::InvalidName(spec.name));
+        }
+        if !builtin && !spec.name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return Err(RegistryError::NotUppercase(spec.name));
+        }
+        tracing::debug!(target: "actions.commands", name = %spec.name, args = ?spec.args, "command_registered");
+        self.commands
+            .insert(spec.name.clone(), RegisteredCommand { spec, body });
+        Ok(())
+    }
+
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.commands.remove(name).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Resolve an exact name or an unambiguous prefix.
+    pub fn resolve(&self, name: &str) -> Option<&RegisteredCommand> {
+        if name.is_empty() {
+            return None;
+        }
+        if let Some(cmd) = self.commands.get(name) {
+            return Some(cmd);
+        }
+        let mut matches = self
+            .commands
+            .range(name.to_string()..)
+            .take_while(|(k, _)| k.starts_with(name));
+        match (matches.next(), matches.next()) {
+            (Some((_, cmd)), None) => Some(cmd),
+            _ => None,
+        }
+    }
+
+    /// Registered names beginning with `prefix` (sorted) for completion.
+    pub fn complete_names(&self, prefix: &str) -> Vec<&str> {
+        self.commands
+            .range(prefix.to_string()..)
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// Completion hint for argument completion of the named command.
+    pub fn completion_hint(&self, name: &str) -> Option<&CompletionHint> {
+        self.resolve(name).map(|c| &c.spec.completion)
+    }
+
+    pub fn specs(&self) -> impl Iterator<Item = &CommandSpec> {
+        self.commands.values().map(|c| &c.spec)
+    }
+
+    /// Try to parse a command body (without leading ':') as a registered
+    /// command. Returns `None` when the head does not resolve.
+    pub fn parse(&self, body: &str) -> Option<CommandInvocation> {
+        self.parse_head(body, |head| self.resolve(head))
+    }
+
+    /// Like `parse`, but the head must be a registered name in full.
+    pub fn parse_exact(&self, body: &str) -> Option<CommandInvocation> {
+        self.parse_head(body, |head| self.commands.get(head))
+    }
+
+    fn parse_head<'a>(
+        &self,
+        body: &str,
+        resolve: impl FnOnce(&str) -> Option<&'a RegisteredCommand>,
+    ) -> Option<CommandInvocation> {
+        let body = body.trim_start();
+        let head_end = body
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(body.len());
+        let (head, rest) = body.split_at(head_end);
+        let cmd = resolve(head)?;
+        let (bang, tail) = match rest.strip_prefix('!') {
+            Some(t) => (true, t),
+            None => (false, rest),
+        };
+        if !tail.is_empty() && !tail.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let raw_args = tail.trim().to_string();
+        let args = if raw_args.is_empty() {
+            Vec::new()
+        } else if cmd.spec.args.splits_words() {
+            raw_args.split_whitespace().map(str::to_string).collect()
+        } else {
+            vec![raw_args.clone()]
+        };
+        Some(CommandInvocation {
+            name: cmd.spec.name.clone(),
+            bang,
+            args,
+            raw_args,
+        })
+    }
+
+    /// Validate an invocation against its registered spec.
+    pub fn validate(&self, invocation: &CommandInvocation) -> Result<(), InvocationError> {
+        let Some(cmd) = self.commands.get(&invocation.name) else {
+            return Ok(());
+        };
+        if invocation.bang && !cmd.spec.bang {
+            return Err(InvocationError::BangNotAllowed);
+        }
+        let count = invocation.args.len();
+        if !cmd.spec.args.accepts(count) {
+            return Err(if count == 0 {
+                InvocationError::ArgumentRequired
+            } else {
+                InvocationError::TrailingCharacters
+            });
+        }
+        Ok(())
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop(_: &CommandInvocation, _: &mut EditorState, _: &mut View) -> DispatchResult {
+        DispatchResult::clean()
+    }
+
+    #[test]
+    fn register_rejects_duplicates_and_bad_names() {
+        let mut reg = CommandRegistry::new();
+        reg.register(CommandSpec::new("Format"), noop).unwrap();
+        assert_eq!(
+            reg.register(CommandSpec::new("Format"), noop),
+            Err(RegistryError::AlreadyExists("Format".into()))
+        );
+        assert!(reg.replace(CommandSpec::new("Format"), noop).is_ok());
+        assert!(matches!(
+            reg.register(CommandSpec::new("1bad"), noop),
+            Err(RegistryError::InvalidName(_))
+        ));
+        assert_eq!(
+            reg.register_alias("wipe", "bd"),
+            Err(RegistryError::NotUppercase("wipe".into()))
+        );
+        assert!(reg.register_builtin(CommandSpec::new("wipe"), noop).is_ok());
+    }
+
+    #[test]
+    fn resolve_accepts_unique_prefix_only() {
+        let mut reg = CommandRegistry::new();
+        reg.register(CommandSpec::new("Grep"), noop).unwrap();
+        reg.register(CommandSpec::new("GitBlame"), noop).unwrap();
+        assert_eq!(reg.resolve("Gr").unwrap().spec.name, "Grep");
+        assert!(reg.resolve("G").is_none());
+        assert_eq!(reg.complete_names("G"), vec!["GitBlame", "Grep"]);
+    }
+
+    #[test]
+    fn parse_splits_arguments_per_spec() {
+        let mut reg = CommandRegistry::new();
+        reg.register(CommandSpec::new("Many").args(ArgSpec::Any), noop)
+            .unwrap();
+        reg.register(CommandSpec::new("One").args(ArgSpec::One), noop)
+            .unwrap();
+        let many = reg.parse("Many a  b").unwrap();
+        assert_eq!(many.args, vec!["a", "b"]);
+        let one = reg.parse("One! a  b").unwrap();
+        assert!(one.bang);
+        assert_eq!(one.args, vec!["a  b"]);
+        assert!(reg.parse("Onex").is_none());
+    }
+
+    #[test]
+    fn alias_expands_with_arguments() {
+        let mut reg = CommandRegistry::new();
+        reg.register_alias("W", ":w").unwrap();
+        let inv = reg.parse("W out.txt").unwrap();
+        let cmd = reg.resolve("W").unwrap();
+        assert_eq!(cmd.expand(&inv).as_deref(), Some(":w out.txt"));
+    }
+
+    #[test]
+    fn validate_reports_vim_errors() {
+        let mut reg = CommandRegistry::new();
+        reg.register(CommandSpec::new("Need").args(ArgSpec::AtLeastOne), noop)
+            .unwrap();
+        reg.register(CommandSpec::new("Zero"), noop).unwrap();
+        let need = reg.parse("Need").unwrap();
+        assert_eq!(reg.validate(&need), Err(InvocationError::ArgumentRequired));
+        let zero = reg.parse("Zero x").unwrap();
+        assert_eq!(
+            reg.validate(&zero),
+            Err(InvocationError::TrailingCharacters)
+        );
+        let bang = reg.parse("Zero!").unwrap();
+        assert_eq!(reg.validate(&bang), Err(InvocationError::BangNotAllowed));
+    }
+}
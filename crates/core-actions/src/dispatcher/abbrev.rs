@@ -0,0 +1,79 @@
+//! `:iabbrev`, `:iunabbrev`, `:abclear` and the expansion of Insert-mode
+//! abbreviations (`core_state::abbrev`).
+//!
+//! The edit handlers call `expand` before inserting a non-keyword
+//! character or a line break, and the mode handler before leaving Insert
+//! mode. The expansion joins the insert run, so one `u` undoes it with the
+//! typing.
+
+use super::DispatchResult;
+use core_model::View;
+use core_state::{EditorState, Mode};
+use core_text::Position;
+use std::time::Duration;
+
+/// Replace the abbreviation ending at the cursor, if any. Returns whether
+/// one was expanded.
+pub(super) fn expand(state: &mut EditorState, view: &mut View) -> bool {
+    if !matches!(state.mode, Mode::Insert) || state.abbreviations.is_empty() {
+        return false;
+    }
+    let cursor = view.cursor;
+    let Some(line) = state.active_buffer().line(cursor.line) else {
+        return false;
+    };
+    let Some(before) = line.get(..cursor.byte) else {
+        return false;
+    };
+    let Some((len, rhs)) = state.abbreviations.expansion(before) else {
+        return false;
+    };
+    let rhs = rhs.to_string();
+    state.begin_insert_coalescing(cursor);
+    state.note_insert_edit();
+    let buffer = state.active_buffer_mut();
+    let at = buffer.line_to_byte(cursor.line) + cursor.byte;
+    buffer.delete_bytes(at - len, at);
+    buffer.insert_str(at - len, &rhs);
+    view.cursor = Position::new(cursor.line, cursor.byte - len + rhs.len());
+    tracing::trace!(target: "actions.dispatch", op="abbreviation", line=cursor.line, byte=cursor.byte, lhs_bytes=len, rhs=%rhs, "edit");
+    true
+}
+
+/// `:ia[bbrev]` lists the abbreviations, `:ia {lhs}` those starting with
+/// `lhs`, `:ia {lhs} {rhs}` defines one.
+pub(super) fn define(args: &str, state: &mut EditorState) -> DispatchResult {
+    let args = args.trim();
+    let (lhs, rhs) = match args.split_once(char::is_whitespace) {
+        Some((lhs, rhs)) => (lhs, rhs.trim()),
+        None => (args, ""),
+    };
+    if !rhs.is_empty() {
+        if let Err(msg) = state.abbreviations.define(lhs, rhs) {
+            state.set_ephemeral(msg, Duration::from_secs(3));
+        }
+        return DispatchResult::dirty();
+    }
+    let lines: Vec<String> = state
+        .abbreviations
+        .iter()
+        .filter(|(l, _)| l.starts_with(lhs))
+        .map(|(l, r)| format!("i  {l:<12} {r}"))
+        .collect();
+    match lines.len() {
+        0 => state.set_ephemeral("No abbreviation found", Duration::from_secs(3)),
+        count => state.show_message_lines(lines, count),
+    }
+    DispatchResult::dirty()
+}
+
+/// `:iuna[bbrev] {lhs}`.
+pub(super) fn remove(lhs: &str, state: &mut EditorState) -> DispatchResult {
+    let lhs = lhs.trim();
+    if lhs.is_empty() {
+        state.set_ephemeral("E471: Argument required", Duration::from_secs(3));
+    } else if !state.abbreviations.remove(lhs) {
+        state.set_ephemeral("E24: No such abbreviation", Duration::from_secs(3));
+    }
+    DispatchResult::dirty()
+}
@@ -0,0 +1,210 @@
+//! `:[range]align[!] {delimiter} [r][s]` and the `gl` / `gL` operators.
+//!
+//! Pure helpers in the style of `sort`: argument parsing and the alignment
+//! itself work on owned line strings; `command.rs` resolves the range and
+//! applies the result as a single undo snapshot.
+//!
+//! Every occurrence of the delimiter starts a new column. Columns are lined
+//! up one after the other by inserting spaces, never by removing any:
+//! * left alignment (the default) pads just before the delimiter, so the
+//!   text before it stays where it is;
+//! * right alignment (`!` or `r`) pads at the start of the field before the
+//!   delimiter (after the indentation, or after the previous delimiter and
+//!   the blanks following it), right-justifying the field.
+//!
+//! Delimiters inside `"..."` and `'...'` strings (a `'` only opens one when
+//! a closing `'` follows on the line) are skipped unless `s` is given.
+//! Lines with fewer delimiters take part in the columns they have.
+
+use super::DispatchResult;
+use core_model::View;
+use core_state::EditorState;
+use core_text::{Position, grapheme};
+
+/// `gl` / `gL` over lines `first..=last`: open the command line on
+/// `:.,.+N align` with the cursor on `first`, as Vim's `!{motion}` does,
+/// for the delimiter to be typed.
+pub(super) fn prompt(
+    first: usize,
+    last: usize,
+    right: bool,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if view.cursor.line != first {
+        view.cursor = Position::new(first, 0);
+    }
+    let range = match last.saturating_sub(first) {
+        0 => ".".to_string(),
+        n => format!(".,.+{n}"),
+    };
+    let bang = if right { "!" } else { "" };
+    state.command_line.begin();
+    for ch in format!("{range}align{bang} ").chars() {
+        state.command_line.push_char(ch);
+    }
+    DispatchResult::dirty()
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AlignFlags {
+    pub right: bool,
+    /// Also align on delimiters inside string literals.
+    pub in_strings: bool,
+}
+
+/// Parse `{delimiter} [flags]` into the delimiter and its flags.
+pub fn parse_args(bang: bool, args: &str) -> Result<(String, AlignFlags), String> {
+    let mut words = args.split_whitespace();
+    let Some(delimiter) = words.next() else {
+        return Err("E471: Argument required".to_string());
+    };
+    let mut flags = AlignFlags {
+        right: bang,
+        ..AlignFlags::default()
+    };
+    for ch in words.flat_map(str::chars) {
+        match ch {
+            'r' => flags.right = true,
+            's' => flags.in_strings = true,
+            _ => return Err(format!("E474: Invalid argument: {}", args.trim())),
+        }
+    }
+    Ok((delimiter.to_string(), flags))
+}
+
+/// `lines` with every column of `delimiter` lined up.
+pub fn align_lines(mut lines: Vec<String>, delimiter: &str, flags: AlignFlags) -> Vec<String> {
+    for column in 0.. {
+        let targets: Vec<Option<(usize, usize)>> = lines
+            .iter()
+            .map(|line| {
+                let found = delimiters(line, delimiter, flags.in_strings);
+                let at = *found.get(column)?;
+                let field = match column {
+                    0 => 0,
+                    _ => found[column - 1] + delimiter.len(),
+                };
+                let start = field + line[field..at].len() - line[field..at].trim_start().len();
+                Some((start, at))
+            })
+            .collect();
+        let Some(goal) = lines
+            .iter()
+            .zip(&targets)
+            .filter_map(|(line, target)| target.map(|(_, at)| grapheme::visual_col(line, at)))
+            .max()
+        else {
+            break;
+        };
+        for (line, target) in lines.iter_mut().zip(targets) {
+            let Some((start, at)) = target else {
+                continue;
+            };
+            let pad = goal - grapheme::visual_col(line, at);
+            let insert_at = if flags.right { start } else { at };
+            line.insert_str(insert_at, &" ".repeat(pad));
+        }
+    }
+    lines
+}
+
+/// Byte offsets of the occurrences of `delimiter` in `line`, skipping
+/// those inside string literals unless `in_strings`.
+fn delimiters(line: &str, delimiter: &str, in_strings: bool) -> Vec<usize> {
+    let mut found = Vec::new();
+    if delimiter.is_empty() {
+        return found;
+    }
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(_) if c == '\\' => {
+                chars.next();
+            }
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if line[i..].starts_with(delimiter) => {
+                found.push(i);
+                // Skip the rest of the delimiter.
+                for _ in delimiter.chars().skip(1) {
+                    chars.next();
+                }
+            }
+            None if !in_strings && (c == '"' || (c == '\'' && line[i + 1..].contains('\''))) => {
+                quote = Some(c);
+            }
+            None => {}
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn align(lines: &[&str], delimiter: &str, flags: AlignFlags) -> Vec<String> {
+        align_lines(
+            lines.iter().map(|l| l.to_string()).collect(),
+            delimiter,
+            flags,
+        )
+    }
+
+    #[test]
+    fn columns_line_up_left_or_right_skipping_strings() {
+        let left = AlignFlags::default();
+        assert_eq!(
+            align(
+                &["  a = 1", "  long_name = 2", "", "  b == \"=\""],
+                "=",
+                left
+            ),
+            [
+                "  a         = 1",
+                "  long_name = 2",
+                "",
+                "  b         == \"=\""
+            ]
+        );
+        assert_eq!(
+            align(&["| a | bb |", "| ccc | d |"], "|", left),
+            ["| a   | bb |", "| ccc | d  |"]
+        );
+        let right = AlignFlags {
+            right: true,
+            ..left
+        };
+        assert_eq!(
+            align(&["x: 1", "long: 22"], ":", right),
+            ["   x: 1", "long: 22"]
+        );
+        let in_strings = AlignFlags {
+            in_strings: true,
+            ..left
+        };
+        assert_eq!(
+            align(&["\"a:b\": 1", "k: 2"], ":", in_strings),
+            ["\"a:b\": 1", "k : 2"]
+        );
+        assert_eq!(
+            align(&["'a': 1", "don't: 2"], ":", left),
+            ["'a'  : 1", "don't: 2"]
+        );
+
+        assert_eq!(
+            parse_args(true, "= s"),
+            Ok((
+                "=".into(),
+                AlignFlags {
+                    right: true,
+                    in_strings: true
+                }
+            ))
+        );
+        assert!(parse_args(false, "").is_err());
+        assert!(parse_args(false, "= x").is_err());
+    }
+}
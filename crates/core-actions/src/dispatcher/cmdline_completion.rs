@@ -0,0 +1,120 @@
+//! `Tab` completion on the command line.
+//!
+//! The last word of a `:` command line is completed as the argument of
+//! its command: `CommandRegistry::completion_hint` says what a registered
+//! command takes, `CommandParser::completion_hint` what a built-in one
+//! does, and `candidates` provides the matches for each `CompletionHint`.
+//! They go to a `core_state::Wildmenu`, which further `Tab`s cycle. When
+//! the only match is a directory, the next `Tab` completes inside it.
+
+use super::DispatchResult;
+use super::command_parser::{CommandParser, split_head};
+use super::ex_range::split_range;
+use crate::command_registry::{CommandRegistry, CompletionHint};
+use core_state::{EditorState, Wildmenu};
+
+/// `Tab` (`Shift-Tab` with `backward`): show the next match of the open
+/// wildmenu, or complete the word before the end of the line.
+pub(super) fn complete(
+    backward: bool,
+    state: &mut EditorState,
+    commands: &CommandRegistry,
+) -> DispatchResult {
+    let line = &mut state.command_line;
+    if line.wildmenu().is_some_and(|menu| menu.matches().len() > 1) {
+        line.step_wildmenu(backward);
+        return DispatchResult::dirty();
+    }
+    let buf = line.buffer().to_string();
+    let Some(body) = buf.strip_prefix(':') else {
+        return DispatchResult::clean();
+    };
+    let (head, tail) = split_head(split_range(body).1);
+    // Still typing the command name.
+    if tail.is_empty() {
+        return DispatchResult::clean();
+    }
+    let hint = match commands.completion_hint(head.trim_end_matches('!')) {
+        Some(hint) => hint.clone(),
+        None => CommandParser::completion_hint(head),
+    };
+    let (prefix, word) = buf.split_at(buf.trim_end_matches(|c: char| !c.is_whitespace()).len());
+    let matches = candidates(&hint, word, state, commands);
+    tracing::debug!(target: "actions.command", ?hint, word, matches = matches.len(), "cmdline_complete");
+    if matches.is_empty() {
+        return DispatchResult::clean();
+    }
+    let menu = Wildmenu::new(prefix, word, matches);
+    state.command_line.open_wildmenu(menu, backward);
+    DispatchResult::dirty()
+}
+
+/// The completion provider: what `word` can be completed to as an
+/// argument of kind `hint`, sorted.
+fn candidates(
+    hint: &CompletionHint,
+    word: &str,
+    state: &EditorState,
+    commands: &CommandRegistry,
+) -> Vec<String> {
+    match hint {
+        CompletionHint::File => file_matches(word),
+        CompletionHint::Buffer => {
+            let mut names: Vec<String> = state
+                .buffers
+                .iter()
+                .map(|entry| entry.buffer.name.clone())
+                .filter(|name| name.starts_with(word))
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        }
+        CompletionHint::Command => commands
+            .complete_names(word)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        CompletionHint::Words(words) => {
+            let mut words: Vec<String> = words
+                .iter()
+                .filter(|w| w.starts_with(word))
+                .cloned()
+                .collect();
+            words.sort();
+            words
+        }
+        CompletionHint::Option => state.options.complete(word),
+        CompletionHint::None => Vec::new(),
+    }
+}
+
+/// Paths starting with `word`, relative to the working directory as
+/// `:edit` takes them; directories end in `/`. Names starting with `.` are
+/// left out unless `word`'s last component starts with one.
+fn file_matches(word: &str) -> Vec<String> {
+    let (dir, name) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+    let Ok(entries) = std::fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file = entry.file_name().into_string().ok()?;
+            if !file.starts_with(name) || (file.starts_with('.') && !name.starts_with('.')) {
+                return None;
+            }
+            // Follow symlinks so a link to a directory descends like one.
+            let slash = match std::fs::metadata(entry.path()) {
+                Ok(meta) if meta.is_dir() => "/",
+                _ => "",
+            };
+            Some(format!("{dir}{file}{slash}"))
+        })
+        .collect();
+    matches.sort();
+    matches
+}
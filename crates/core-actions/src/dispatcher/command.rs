@@ -1,4 +1,4 @@
-//! Command line editing & execution (:q, :e <file>, :w).
+//! Command line editing & execution (:q, :e <file>, :w, :set).
 //!
 //! Scope (R3 Step 1): behavior-neutral extraction. Parsing is still string
 //! prefix matching; Step 2 introduces a structured `ParsedCommand`.
@@ -20,16 +20,22 @@
 
 use super::DispatchResult;
 use super::command_parser::{CommandParser, ParsedCommand};
+use super::ex_range::{LineRange, RangeContext, RangeSpec};
+use super::explorer::FileOp;
+use super::sort::{SortFlags, sort_lines};
 use crate::Action;
+use crate::command_registry::{CommandBody, CommandInvocation, CommandRegistry};
 use crate::io_ops::{OpenFileResult, WriteFileResult, open_file, write_file};
+use core_config::theme::Theme;
 use core_model::View;
-use core_state::EditorState;
+use core_state::{EditorState, Mode, PasteSource, RegisterKind, ShellOrigin, ShellTarget};
 use core_text::Position;
 
 pub(crate) fn handle_command_action(
     action: Action,
     state: &mut EditorState,
     view: &mut View,
+    commands: &CommandRegistry,
 ) -> DispatchResult {
     match action {
         Action::CommandStart => {
@@ -45,32 +51,130 @@ pub(crate) fn handle_command_action(
             DispatchResult::dirty()
         }
         Action::CommandCancel => {
+            if state.command_line.buffer().starts_with(':') {
+                leave_visual(state);
+            }
             state.command_line.clear();
+            state.expr_paste = None;
             DispatchResult::dirty()
         }
-        Action::CommandExecute(cmd) => execute_command(cmd, state, view),
+        Action::CommandComplete { backward } => {
+            super::cmdline_completion::complete(backward, state, commands)
+        }
+        Action::CommandExecute(cmd) if state.command_line.is_expression() => {
+            execute_expression(&cmd, state, view)
+        }
+        Action::CommandExecute(cmd) if state.command_line.is_search() => {
+            super::search::execute(&cmd, state, view)
+        }
+        Action::CommandExecute(cmd) => {
+            if let Some(body) = cmd.trim().strip_prefix(':') {
+                state.command_line.record_history(body);
+            }
+            let result = execute_command(cmd, state, view, commands);
+            leave_visual(state);
+            result
+        }
+        Action::CmdlineWindowOpen => open_cmdline_window(state, view),
+        Action::CmdlineWindowExecute => execute_cmdline_window_line(state, view, commands),
+        Action::CmdlineWindowClose => {
+            if close_cmdline_window(state, view) {
+                DispatchResult::buffer_replaced()
+            } else {
+                DispatchResult::clean()
+            }
+        }
         _ => unreachable!("non-command action routed to command handler"),
     }
 }
 
-fn execute_command(raw: String, state: &mut EditorState, view: &mut View) -> DispatchResult {
-    let parsed = CommandParser::parse(&raw);
+/// An ex command started from Visual mode ends it, as in Vim, once the
+/// command has seen the selection.
+fn leave_visual(state: &mut EditorState) {
+    if state.mode == Mode::VisualChar {
+        state.selection.clear();
+        state.mode = Mode::Normal;
+    }
+}
+
+fn execute_command(
+    raw: String,
+    state: &mut EditorState,
+    view: &mut View,
+    commands: &CommandRegistry,
+) -> DispatchResult {
+    let parsed = CommandParser::parse_with(&raw, commands);
+    if state.cmdline_window_active() {
+        match parsed {
+            ParsedCommand::Quit { .. } => {
+                state.command_line.clear();
+                close_cmdline_window(state, view);
+                return DispatchResult::buffer_replaced();
+            }
+            ParsedCommand::Write { .. }
+            | ParsedCommand::Edit { .. }
+            | ParsedCommand::Split { .. }
+            | ParsedCommand::TabNew { .. }
+            | ParsedCommand::Close { .. }
+            | ParsedCommand::DiffSplit { .. }
+            | ParsedCommand::DiffOff
+            | ParsedCommand::Grep { .. }
+            | ParsedCommand::QuickfixJump { .. }
+            | ParsedCommand::QuickfixStep { .. }
+            | ParsedCommand::QuickfixWindow { .. }
+            | ParsedCommand::LocationDiagnostics
+            | ParsedCommand::Make { .. }
+            | ParsedCommand::Job { .. }
+            | ParsedCommand::Explore { .. } => {
+                state.command_line.clear();
+                state.set_ephemeral(
+                    "E11: Invalid in command-line window; <CR> executes, CTRL-C quits",
+                    std::time::Duration::from_secs(3),
+                );
+                return DispatchResult::dirty();
+            }
+            _ => {}
+        }
+    }
+    if (state.hex_view().is_some()
+        || state.explorer().is_some()
+        || state.quickfix_window().is_some())
+        && matches!(
+            parsed,
+            ParsedCommand::Sort { .. }
+                | ParsedCommand::Align { .. }
+                | ParsedCommand::ReadShell { .. }
+                | ParsedCommand::Shell { range: Some(_), .. }
+        )
+    {
+        state.command_line.clear();
+        state.set_ephemeral(super::NOT_MODIFIABLE_MSG, std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    if state.explorer().is_some() && matches!(parsed, ParsedCommand::Write { .. }) {
+        state.command_line.clear();
+        state.set_ephemeral(
+            "E382: Cannot write, 'buftype' option is set",
+            std::time::Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
     let result = match parsed {
         ParsedCommand::Quit { force } => handle_quit(force, state),
         ParsedCommand::Write { force, path } => handle_write(force, path, state),
         ParsedCommand::Edit { force, path } => handle_edit(force, path, state, view),
         ParsedCommand::Metrics => {
-            use core_state::{METRICS_OVERLAY_DEFAULT_LINES, OverlayMode};
-            let new_mode = state.toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES);
+            use core_state::OverlayMode;
+            let new_mode = state.toggle_metrics_overlay();
             match new_mode {
-                OverlayMode::Metrics { lines } => {
+                OverlayMode::Metrics { section } => {
                     // Emit a concise one-line ephemeral so overlay rows remain the source of detail.
                     state.set_ephemeral(
-                        format!("Metrics overlay ON ({} lines)", lines),
+                        format!("Metrics overlay ON ({})", section.name()),
                         std::time::Duration::from_secs(2),
                     );
                 }
-                OverlayMode::None => {
+                OverlayMode::None | OverlayMode::Message { .. } => {
                     state.set_ephemeral("Metrics overlay OFF", std::time::Duration::from_secs(2));
                 }
             }
@@ -78,14 +182,599 @@ fn execute_command(raw: String, state: &mut EditorState, view: &mut View) -> Dis
             tracing::info!(target: "runtime.metrics", kind=":metrics_toggle", mode=?new_mode);
             DispatchResult::dirty()
         }
+        ParsedCommand::MetricsPage { arg } => handle_metrics_page(&arg, state),
+        ParsedCommand::MetricsDump { path } => handle_metrics_dump(path, state),
+        ParsedCommand::Set { args } => handle_set(&args, state, view),
+        ParsedCommand::User(invocation) => {
+            // Clear first so handlers may leave their own command-line state behind.
+            state.command_line.clear();
+            return execute_user_command(invocation, state, view, commands, 0);
+        }
+        ParsedCommand::Shell { range, command } => handle_shell(range, command, state, view),
+        ParsedCommand::ReadShell { range, command } => {
+            handle_read_shell(range, command, state, view)
+        }
+        ParsedCommand::Sort {
+            range,
+            reverse,
+            flags,
+        } => handle_sort(range, reverse, &flags, state, view),
+        ParsedCommand::Align { range, right, args } => {
+            handle_align(range, right, &args, state, view)
+        }
+        ParsedCommand::ShellInteractive => {
+            state.shell.interactive = true;
+            DispatchResult::dirty()
+        }
+        ParsedCommand::Recover { discard } => handle_recover(discard, state, view),
+        ParsedCommand::Colorscheme { name } => handle_colorscheme(name, state),
+        ParsedCommand::NoHlsearch => {
+            state.suspend_search_highlight();
+            DispatchResult::dirty()
+        }
+        ParsedCommand::Diagnostics => super::diagnostics::list(state),
+        ParsedCommand::Abbreviate { args } => super::abbrev::define(&args, state),
+        ParsedCommand::Unabbreviate { lhs } => super::abbrev::remove(&lhs, state),
+        ParsedCommand::Blame => handle_blame(state),
+        ParsedCommand::Jobs => super::job::list(state),
+        ParsedCommand::JobStop { arg } => super::job::stop(&arg, state),
+        ParsedCommand::Timer { repeat, args } => super::timer::start(repeat, &args, state),
+        ParsedCommand::Timers => super::timer::list(state),
+        ParsedCommand::TimerStop { arg } => super::timer::stop(&arg, state),
+        ParsedCommand::Explore { path } => super::explorer::explore(path, state, view),
+        ParsedCommand::Mkdir { name } => {
+            super::explorer::file_op(FileOp::Mkdir, &name, state, view)
+        }
+        ParsedCommand::Rename { name } => {
+            super::explorer::file_op(FileOp::Rename, &name, state, view)
+        }
+        ParsedCommand::Remove { name } => {
+            super::explorer::file_op(FileOp::Remove, &name, state, view)
+        }
+        ParsedCommand::AbClear => {
+            state.abbreviations.clear();
+            DispatchResult::dirty()
+        }
+        ParsedCommand::UndoTime { later, arg } => match super::undo::parse_undo_time(later, &arg) {
+            Ok(travel) => super::undo::handle_undo_travel(travel, state, view),
+            Err(msg) => {
+                state.set_ephemeral(msg, std::time::Duration::from_secs(3));
+                DispatchResult::dirty()
+            }
+        },
+        // Window commands reshape the view set and are routed to
+        // `super::window` before the model is split; only alias expansions
+        // and command-line window lines end up here.
+        ParsedCommand::Split { .. }
+        | ParsedCommand::TabNew { .. }
+        | ParsedCommand::Close { .. }
+        | ParsedCommand::DiffSplit { .. }
+        | ParsedCommand::DiffOff
+        | ParsedCommand::Grep { .. }
+        | ParsedCommand::QuickfixJump { .. }
+        | ParsedCommand::QuickfixStep { .. }
+        | ParsedCommand::QuickfixWindow { .. }
+        | ParsedCommand::LocationDiagnostics
+        | ParsedCommand::Make { .. }
+        | ParsedCommand::Job { .. } => DispatchResult::dirty(),
         ParsedCommand::Unknown(_) => DispatchResult::dirty(),
     };
     state.command_line.clear();
     result
 }
 
+/// Confirm the `=` prompt: evaluate into `"=` (an empty expression reuses
+/// the previous result, as in Vim) and run the pending paste.
+fn execute_expression(cmd: &str, state: &mut EditorState, view: &mut View) -> DispatchResult {
+    state.command_line.clear();
+    let pending = state.expr_paste.take();
+    let src = cmd.strip_prefix('=').unwrap_or(cmd).trim();
+    if !src.is_empty() {
+        match super::expr::evaluate(src) {
+            Ok(value) => state.registers.set_expression(value),
+            Err(msg) => {
+                state.set_ephemeral(msg, std::time::Duration::from_secs(3));
+                return DispatchResult::dirty();
+            }
+        }
+    }
+    let Some(paste) = pending else {
+        return DispatchResult::dirty();
+    };
+    let result = super::paste_from_source(
+        PasteSource::Expression,
+        paste.before,
+        paste.count,
+        state,
+        view,
+    );
+    // Even a failed paste must repaint the (now closed) prompt.
+    if result.dirty {
+        result
+    } else {
+        DispatchResult::dirty()
+    }
+}
+
+/// `q:` — swap the history scratch buffer into the active view.
+fn open_cmdline_window(state: &mut EditorState, view: &mut View) -> DispatchResult {
+    match state.open_cmdline_window(view.cursor, view.viewport_first_line) {
+        Some((index, cursor)) => {
+            view.buffer_id = index;
+            view.cursor = cursor;
+            view.viewport_first_line = 0;
+            DispatchResult::buffer_replaced()
+        }
+        None => {
+            state.set_ephemeral(
+                "E11: Invalid in command-line window; <CR> executes, CTRL-C quits",
+                std::time::Duration::from_secs(3),
+            );
+            DispatchResult::dirty()
+        }
+    }
+}
+
+/// Restore the origin buffer placement. Returns false when not open.
+fn close_cmdline_window(state: &mut EditorState, view: &mut View) -> bool {
+    let Some(ret) = state.close_cmdline_window() else {
+        return false;
+    };
+    view.buffer_id = ret.buffer;
+    view.cursor = ret.cursor;
+    view.viewport_first_line = ret.viewport_first_line;
+    true
+}
+
+/// <CR> in Normal mode inside the command-line window: close it and run the
+/// line under the cursor (outside the window `<CR>` is routed as a motion).
+fn execute_cmdline_window_line(
+    state: &mut EditorState,
+    view: &mut View,
+    commands: &CommandRegistry,
+) -> DispatchResult {
+    let line = state
+        .active_buffer()
+        .line(view.cursor.line)
+        .map(|l| l.trim_end_matches(['\n', '\r']).trim().to_string())
+        .unwrap_or_default();
+    close_cmdline_window(state, view);
+    let body = line.strip_prefix(':').unwrap_or(&line);
+    if body.is_empty() {
+        return DispatchResult::buffer_replaced();
+    }
+    state.command_line.record_history(body);
+    let mut result = execute_command(format!(":{body}"), state, view, commands);
+    result.dirty = true;
+    result.buffer_replaced = true;
+    result
+}
+
+/// Resolution context for ex ranges at the current cursor.
+pub(crate) fn range_context(state: &EditorState, view: &View) -> RangeContext {
+    RangeContext {
+        cursor_line: view.cursor.line,
+        last_line: state.last_content_line(),
+        visual: state
+            .selection()
+            .map(|s| (s.start.line.min(s.end.line), s.start.line.max(s.end.line))),
+    }
+}
+
+fn handle_shell(
+    range: Option<RangeSpec>,
+    command: String,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if command.is_empty() {
+        state.set_ephemeral("E471: Argument required", std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let Some(range) = range else {
+        state.shell.push(command, None, ShellTarget::Message);
+        return DispatchResult::dirty();
+    };
+    match range.resolve(&range_context(state, view)) {
+        Ok(lines) => {
+            let buffer = state.active_buffer();
+            let start_b = buffer.line_to_byte(lines.start);
+            let end_b = buffer.line_to_byte(lines.end + 1);
+            let mut input = buffer.slice_bytes(start_b, end_b);
+            if !input.ends_with('\n') {
+                input.push('\n');
+            }
+            state.shell.push(
+                command,
+                Some(input),
+                ShellTarget::Filter {
+                    origin: ShellOrigin::active(state, view.id.0),
+                    start: lines.start,
+                    end: lines.end,
+                },
+            );
+        }
+        Err(e) => state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3)),
+    }
+    DispatchResult::dirty()
+}
+
+/// `:sort`: without a range, sorts the visual selection when one is active,
+/// otherwise the whole buffer (Vim's default range for `:sort` is `%`).
+fn handle_sort(
+    range: Option<RangeSpec>,
+    reverse: bool,
+    flags: &str,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let flags = match SortFlags::parse(reverse, flags) {
+        Ok(f) => f,
+        Err(msg) => {
+            state.set_ephemeral(msg, std::time::Duration::from_secs(3));
+            return DispatchResult::dirty();
+        }
+    };
+    let ctx = range_context(state, view);
+    let lines = match range {
+        Some(range) => range.resolve(&ctx),
+        None => Ok(match ctx.visual {
+            Some((start, end)) => LineRange { start, end },
+            None => LineRange {
+                start: 0,
+                end: ctx.last_line,
+            },
+        }),
+    };
+    let lines = match lines {
+        Ok(l) => l,
+        Err(e) => {
+            state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+            return DispatchResult::dirty();
+        }
+    };
+    if state.mode == Mode::VisualChar {
+        state.selection.clear();
+        state.mode = Mode::Normal;
+    }
+    let original: Vec<String> = {
+        let buffer = state.active_buffer();
+        (lines.start..=lines.end)
+            .map(|i| {
+                buffer
+                    .line(i)
+                    .map(|l| l.trim_end_matches(['\n', '\r']).to_string())
+                    .unwrap_or_default()
+            })
+            .collect()
+    };
+    let outcome = sort_lines(original.clone(), flags);
+    if outcome.lines == original {
+        return DispatchResult::dirty();
+    }
+    let mut text = outcome.lines.join("\n");
+    text.push('\n');
+    let mut cursor = view.cursor;
+    state.replace_lines_with_snapshot(&mut cursor, lines.start, lines.end, &text);
+    view.cursor = cursor;
+    if !outcome.removed.is_empty() {
+        let mut removed = outcome.removed.join("\n");
+        removed.push('\n');
+        state
+            .registers_facade()
+            .write_delete(removed, RegisterKind::Linewise, None);
+        let n = outcome.removed.len();
+        let msg = if n == 1 {
+            "1 fewer line".to_string()
+        } else {
+            format!("{n} fewer lines")
+        };
+        state.set_ephemeral(msg, std::time::Duration::from_secs(3));
+    }
+    tracing::debug!(
+        target: "actions.commands",
+        start = lines.start,
+        end = lines.end,
+        removed = outcome.removed.len(),
+        ?flags,
+        "sort_applied"
+    );
+    DispatchResult::buffer_replaced()
+}
+
+/// `:align`: without a range, aligns the visual selection when one is
+/// active, otherwise the cursor line.
+fn handle_align(
+    range: Option<RangeSpec>,
+    right: bool,
+    args: &str,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let (delimiter, flags) = match super::align::parse_args(right, args) {
+        Ok(parsed) => parsed,
+        Err(msg) => {
+            state.set_ephemeral(msg, std::time::Duration::from_secs(3));
+            return DispatchResult::dirty();
+        }
+    };
+    let ctx = range_context(state, view);
+    let lines = match range {
+        Some(range) => range.resolve(&ctx),
+        None => Ok(match ctx.visual {
+            Some((start, end)) => LineRange { start, end },
+            None => LineRange {
+                start: ctx.cursor_line,
+                end: ctx.cursor_line,
+            },
+        }),
+    };
+    let lines = match lines {
+        Ok(l) => l,
+        Err(e) => {
+            state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+            return DispatchResult::dirty();
+        }
+    };
+    if state.mode == Mode::VisualChar {
+        state.selection.clear();
+        state.mode = Mode::Normal;
+    }
+    let original: Vec<String> = {
+        let buffer = state.active_buffer();
+        (lines.start..=lines.end)
+            .map(|i| {
+                buffer
+                    .line(i)
+                    .map(|l| l.trim_end_matches(['\n', '\r']).to_string())
+                    .unwrap_or_default()
+            })
+            .collect()
+    };
+    let aligned = super::align::align_lines(original.clone(), &delimiter, flags);
+    if aligned == original {
+        return DispatchResult::dirty();
+    }
+    let mut text = aligned.join("\n");
+    text.push('\n');
+    let mut cursor = view.cursor;
+    state.replace_lines_with_snapshot(&mut cursor, lines.start, lines.end, &text);
+    view.cursor = cursor;
+    tracing::debug!(
+        target: "actions.commands",
+        start = lines.start,
+        end = lines.end,
+        delimiter = %delimiter,
+        ?flags,
+        "align_applied"
+    );
+    DispatchResult::buffer_replaced()
+}
+
+fn handle_read_shell(
+    range: Option<RangeSpec>,
+    command: String,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if command.is_empty() {
+        state.set_ephemeral("E471: Argument required", std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let line = match range.map(|r| r.resolve(&range_context(state, view))) {
+        None => view.cursor.line,
+        Some(Ok(lines)) => lines.end,
+        Some(Err(e)) => {
+            state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+            return DispatchResult::dirty();
+        }
+    };
+    let origin = ShellOrigin::active(state, view.id.0);
+    state
+        .shell
+        .push(command, None, ShellTarget::ReadAfter { origin, line });
+    DispatchResult::dirty()
+}
+
+/// Alias chains deeper than this are treated as recursive definitions.
+const MAX_ALIAS_DEPTH: usize = 8;
+
+fn execute_user_command(
+    invocation: CommandInvocation,
+    state: &mut EditorState,
+    view: &mut View,
+    commands: &CommandRegistry,
+    depth: usize,
+) -> DispatchResult {
+    if let Err(e) = commands.validate(&invocation) {
+        state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let Some(cmd) = commands.resolve(&invocation.name) else {
+        return DispatchResult::dirty();
+    };
+    tracing::debug!(target: "runtime.command", name = %invocation.name, args = ?invocation.args, depth, "user_command");
+    match &cmd.body {
+        CommandBody::Handler(handler) => handler(&invocation, state, view),
+        CommandBody::Alias(_) => {
+            if depth >= MAX_ALIAS_DEPTH {
+                state.set_ephemeral(
+                    "E169: Command too recursive",
+                    std::time::Duration::from_secs(3),
+                );
+                return DispatchResult::dirty();
+            }
+            let expanded = cmd.expand(&invocation).unwrap_or_default();
+            match CommandParser::parse_with(&expanded, commands) {
+                ParsedCommand::User(next) => {
+                    execute_user_command(next, state, view, commands, depth + 1)
+                }
+                _ => execute_command(expanded, state, view, &CommandRegistry::new()),
+            }
+        }
+    }
+}
+
+/// `:metrics {section}` shows that page of the overlay (opening it);
+/// `next` / `prev` page from the current one. `:metrics profile` toggles
+/// per-stage frame profiling, showing its page when turned on.
+fn handle_metrics_page(arg: &str, state: &mut EditorState) -> DispatchResult {
+    use core_state::MetricsSection;
+    let current = state.metrics_section();
+    if arg == "profile" {
+        state.profile_frames = !state.profile_frames;
+        if state.profile_frames {
+            state.show_metrics_section(MetricsSection::Profile);
+        } else {
+            state.last_frame_profile.clear();
+        }
+        tracing::info!(target: "runtime.metrics", kind = ":metrics_profile", on = state.profile_frames);
+        return DispatchResult::dirty();
+    }
+    let section = match arg {
+        "next" => current.map_or(MetricsSection::default(), MetricsSection::next),
+        "prev" => current.map_or(MetricsSection::default(), MetricsSection::prev),
+        name => match MetricsSection::from_name(name) {
+            Some(section) => section,
+            None => {
+                state.set_ephemeral(
+                    format!("E475: Invalid argument: {arg}"),
+                    std::time::Duration::from_secs(3),
+                );
+                return DispatchResult::dirty();
+            }
+        },
+    };
+    state.show_metrics_section(section);
+    tracing::info!(target: "runtime.metrics", kind = ":metrics_page", section = section.name());
+    DispatchResult::dirty()
+}
+
+/// `:metrics dump {file}` writes the JSON snapshot (one line, as the
+/// `[metrics]` sink does); without a file it is shown in the message area.
+fn handle_metrics_dump(
+    path: Option<std::path::PathBuf>,
+    state: &mut EditorState,
+) -> DispatchResult {
+    let unix_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let Some(path) = path else {
+        let json = core_state::metrics_json(state, unix_ms, true);
+        let lines: Vec<String> = json.lines().map(str::to_string).collect();
+        let count = lines.len();
+        state.show_message_lines(lines, count);
+        return DispatchResult::dirty();
+    };
+    let json = core_state::metrics_json(state, unix_ms, false) + "\n";
+    match std::fs::write(&path, &json) {
+        Ok(()) => {
+            tracing::info!(target: "runtime.metrics", kind = ":metrics_dump", path = %path.display(), bytes = json.len());
+            state.set_ephemeral(
+                format!("\"{}\" {}B written", path.display(), json.len()),
+                std::time::Duration::from_secs(3),
+            );
+        }
+        Err(e) => {
+            tracing::error!(target: "runtime.metrics", ?e, path = %path.display(), "metrics_dump_failed");
+            state.set_ephemeral(
+                "E212: Can't open file for writing",
+                std::time::Duration::from_secs(3),
+            );
+        }
+    }
+    DispatchResult::dirty()
+}
+
+fn handle_set(args: &str, state: &mut EditorState, view: &mut View) -> DispatchResult {
+    let result = handle_set_options(args, state);
+    // `:set [no]binary` swaps the buffer between hex rows and raw text.
+    if state.sync_binary_view() {
+        view.cursor = Position::origin();
+        view.viewport_first_line = 0;
+        return DispatchResult::buffer_replaced();
+    }
+    result
+}
+
+fn handle_set_options(args: &str, state: &mut EditorState) -> DispatchResult {
+    match state.options.apply_set(args) {
+        Ok(Some(echo)) => {
+            state.set_ephemeral(echo, std::time::Duration::from_secs(3));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::debug!(target: "runtime.command", error = %e, "set_failed");
+            state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+        }
+    }
+    DispatchResult::dirty()
+}
+
+/// `:blame`: toggle the annotations; the runtime runs `git blame` on the
+/// active file while they are on.
+fn handle_blame(state: &mut EditorState) -> DispatchResult {
+    if !state.git.blame_on() && state.file_name().is_none() {
+        state.set_ephemeral("E32: No file name", std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    state.git.toggle_blame();
+    // The annotation comes or goes on whichever line the cursor is on.
+    DispatchResult::buffer_replaced()
+}
+
+fn handle_colorscheme(name: Option<String>, state: &mut EditorState) -> DispatchResult {
+    let Some(name) = name else {
+        let current = state.theme().name.clone();
+        state.set_ephemeral(current, std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    match Theme::load(&name) {
+        Ok(theme) => {
+            tracing::info!(target: "runtime.command", theme = %name, "colorscheme_loaded");
+            state.set_theme(theme);
+        }
+        Err(e) => {
+            tracing::debug!(target: "runtime.command", error = %e, "colorscheme_failed");
+            state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+        }
+    }
+    DispatchResult::dirty()
+}
+
+fn handle_recover(discard: bool, state: &mut EditorState, view: &mut View) -> DispatchResult {
+    let result = if discard {
+        state.discard_stale_swap()
+    } else {
+        let mut cursor = view.cursor;
+        let result = state.recover_swap(&mut cursor);
+        view.cursor = cursor;
+        result
+    };
+    match result {
+        Ok(swap) => {
+            let name = swap.file_name().unwrap_or_default().to_string_lossy();
+            let msg = if discard {
+                format!("Deleted swap file {name}")
+            } else {
+                format!("Recovered from {name}; :w to keep the changes")
+            };
+            state.set_ephemeral(msg, std::time::Duration::from_secs(3));
+            if discard {
+                DispatchResult::dirty()
+            } else {
+                DispatchResult::buffer_replaced()
+            }
+        }
+        Err(e) => {
+            state.set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+            DispatchResult::dirty()
+        }
+    }
+}
+
 fn handle_quit(force: bool, state: &mut EditorState) -> DispatchResult {
-    if state.dirty && !force {
+    if state.dirty() && !force {
         state.set_ephemeral(
             "E37: No write since last change (add ! to override)",
             std::time::Duration::from_secs(3),
@@ -102,29 +791,60 @@ fn handle_edit(
     state: &mut EditorState,
     view: &mut View,
 ) -> DispatchResult {
-    if state.dirty && !force {
+    if state.dirty() && !force {
         state.set_ephemeral(
             "E37: No write since last change (add ! to override)",
             std::time::Duration::from_secs(3),
         );
         return DispatchResult::dirty();
     }
-    let target_path = match path.or_else(|| state.file_name.clone()) {
+    let target_path = match path.or_else(|| state.file_name().map(|p| p.to_path_buf())) {
         Some(p) => p,
         None => {
             state.set_ephemeral("E32: No file name", std::time::Duration::from_secs(3));
             return DispatchResult::dirty();
         }
     };
-    match open_file(&target_path) {
+    state.remember_cursor(state.active, view.cursor);
+    load_file(&target_path, state, view)
+}
+
+/// Read `path` into the active buffer, replacing its text and file metadata.
+pub(super) fn load_file(
+    path: &std::path::Path,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if path.is_dir() {
+        return super::explorer::open(path, None, state, view);
+    }
+    match open_file(path) {
         OpenFileResult::Success(s) => {
+            state.file_watch.record(path);
             state.buffers[state.active] = s.buffer;
             view.cursor = Position::origin();
-            state.file_name = Some(s.file_name);
-            state.dirty = false;
-            state.original_line_ending = s.original_line_ending;
-            state.had_trailing_newline = s.had_trailing_newline;
-            state.set_ephemeral("Opened", std::time::Duration::from_secs(3));
+            state.set_file_name(Some(s.file_name));
+            state.set_dirty(false);
+            state.active_meta_mut().original_line_ending = s.original_line_ending;
+            state.active_meta_mut().had_trailing_newline = s.had_trailing_newline;
+            state.active_meta_mut().binary = None;
+            state.active_meta_mut().hex_view = false;
+            state.active_meta_mut().explorer = None;
+            state.active_meta_mut().quickfix = None;
+            state.active_meta_mut().last_insert = None;
+            view.viewport_first_line = 0;
+            if let Some(bytes) = s.binary {
+                state.load_binary(bytes);
+                state.set_ephemeral(
+                    core_state::binary::BINARY_OPENED_MSG,
+                    std::time::Duration::from_secs(3),
+                );
+            } else {
+                if let Some(pos) = state.restored_cursor() {
+                    view.cursor = pos;
+                }
+                state.set_ephemeral("Opened", std::time::Duration::from_secs(3));
+            }
             if s.mixed_line_endings {
                 tracing::warn!(target: "io", "mixed_line_endings_detected");
             }
@@ -147,13 +867,28 @@ fn handle_write(
         (WriteFileResult::Success, maybe_path) => {
             state.set_ephemeral("Wrote", std::time::Duration::from_secs(3));
             if let Some(p) = maybe_path {
-                state.file_name = Some(p);
+                state.set_file_name(Some(p));
+            }
+        }
+        (WriteFileResult::SuccessWithoutBackup(err), maybe_path) => {
+            state.set_ephemeral(
+                format!("Wrote (W: cannot write backup file: {err})"),
+                std::time::Duration::from_secs(3),
+            );
+            if let Some(p) = maybe_path {
+                state.set_file_name(Some(p));
             }
         }
         (WriteFileResult::NoFilename, _) => {
             tracing::error!(target: "runtime.command", "write_no_filename");
             state.set_ephemeral("E32: No file name", std::time::Duration::from_secs(3));
         }
+        (WriteFileResult::Lossy, _) => {
+            state.set_ephemeral(
+                "E513: Write error, conversion failed (edited raw view of a non-UTF-8 file)",
+                std::time::Duration::from_secs(3),
+            );
+        }
         (WriteFileResult::Error, _) => {
             state.set_ephemeral(
                 "E212: Can't open file for writing",
@@ -171,6 +906,15 @@ mod tests {
     use core_text::Buffer;
     use std::fs;
 
+    // Built-in command tests run without user-registered commands.
+    fn handle_command_action(
+        action: Action,
+        state: &mut EditorState,
+        view: &mut View,
+    ) -> DispatchResult {
+        super::handle_command_action(action, state, view, &CommandRegistry::new())
+    }
+
     // Helper to construct minimal editor state + view for command tests
     fn mk_state() -> (EditorState, core_model::View) {
         let st = EditorState::new(Buffer::from_str("test", "abc\n").unwrap());
@@ -201,10 +945,260 @@ mod tests {
         );
     }
 
+    #[test]
+    fn metrics_command_pages_sections() {
+        use core_state::MetricsSection;
+        let (mut st, mut view) = mk_state();
+        let mut run = |st: &mut EditorState, cmd: &str| {
+            handle_command_action(Action::CommandExecute(cmd.to_string()), st, &mut view);
+        };
+        run(&mut st, ":metrics prev");
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Render));
+        run(&mut st, ":metrics prev");
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Profile));
+        run(&mut st, ":metrics next");
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Render));
+        run(&mut st, ":metrics sched");
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Scheduler));
+        run(&mut st, ":metrics bogus");
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Scheduler));
+        let eph = st.ephemeral_status.as_ref().unwrap();
+        assert_eq!(eph.text, "E475: Invalid argument: bogus");
+        run(&mut st, ":metrics");
+        assert_eq!(st.metrics_section(), None);
+
+        run(&mut st, ":metrics profile");
+        assert!(st.profile_frames);
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Profile));
+        run(&mut st, ":metrics profile");
+        assert!(!st.profile_frames);
+        assert_eq!(st.metrics_section(), Some(MetricsSection::Profile));
+    }
+
+    #[test]
+    fn metrics_dump_writes_json_or_shows_it() {
+        let (mut st, mut view) = mk_state();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.json");
+        handle_command_action(
+            Action::CommandExecute(format!(":metrics dump {}", path.display())),
+            &mut st,
+            &mut view,
+        );
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(json.starts_with("{\"version\":2,"));
+        assert!(json.ends_with("\"input\":null}\n") && json.lines().count() == 1);
+        assert!(
+            st.ephemeral_status
+                .as_ref()
+                .unwrap()
+                .text
+                .ends_with("B written")
+        );
+
+        handle_command_action(
+            Action::CommandExecute(":metrics dump".to_string()),
+            &mut st,
+            &mut view,
+        );
+        assert_eq!(st.message_lines.len(), 5);
+        assert!(st.message_lines[3].starts_with("\"operators\":"));
+
+        handle_command_action(
+            Action::CommandExecute(format!(":metrics dump {}", dir.path().display())),
+            &mut st,
+            &mut view,
+        );
+        let eph = st.ephemeral_status.as_ref().unwrap();
+        assert_eq!(eph.text, "E212: Can't open file for writing");
+    }
+
+    #[test]
+    fn set_command_updates_options_and_queues_change() {
+        let (mut st, mut view) = mk_state();
+        let res = handle_command_action(
+            Action::CommandExecute(":set number shiftwidth=4".to_string()),
+            &mut st,
+            &mut view,
+        );
+        assert!(res.dirty);
+        assert!(st.options.get_bool("number"));
+        assert_eq!(st.options.get_number("shiftwidth"), 4);
+        assert_eq!(st.options.take_changes().len(), 2);
+
+        let _ = handle_command_action(
+            Action::CommandExecute(":set sw?".to_string()),
+            &mut st,
+            &mut view,
+        );
+        let eph = st.ephemeral_status.as_ref().expect("query echoes value");
+        assert_eq!(eph.text, "shiftwidth=4");
+    }
+
+    #[test]
+    fn set_unknown_option_reports_error() {
+        let (mut st, mut view) = mk_state();
+        let _ = handle_command_action(
+            Action::CommandExecute(":set nosuchthing".to_string()),
+            &mut st,
+            &mut view,
+        );
+        let eph = st.ephemeral_status.as_ref().expect("ephemeral message set");
+        assert_eq!(eph.text, "E518: Unknown option: nosuchthing");
+    }
+
+    #[test]
+    fn user_command_runs_handler_with_arguments() {
+        use crate::command_registry::{ArgSpec, CommandSpec};
+        let (mut st, mut view) = mk_state();
+        let mut registry = CommandRegistry::new();
+        registry
+            .register(
+                CommandSpec::new("Greet").args(ArgSpec::AtLeastOne),
+                |inv, state, _view| {
+                    state.set_ephemeral(
+                        format!("hello {}", inv.args.join(",")),
+                        std::time::Duration::from_secs(1),
+                    );
+                    DispatchResult::dirty()
+                },
+            )
+            .unwrap();
+        let res = super::handle_command_action(
+            Action::CommandExecute(":Gre a b".to_string()),
+            &mut st,
+            &mut view,
+            &registry,
+        );
+        assert!(res.dirty);
+        assert_eq!(st.ephemeral_status.as_ref().unwrap().text, "hello a,b");
+        assert!(st.command_line.buffer().is_empty());
+
+        let _ = super::handle_command_action(
+            Action::CommandExecute(":Greet".to_string()),
+            &mut st,
+            &mut view,
+            &registry,
+        );
+        assert_eq!(
+            st.ephemeral_status.as_ref().unwrap().text,
+            "E471: Argument required"
+        );
+    }
+
+    #[test]
+    fn alias_command_expands_to_builtin() {
+        let (mut st, mut view) = mk_state();
+        let mut registry = CommandRegistry::new();
+        registry.register_alias("Loop", "Loop").unwrap();
+        registry.register_alias("Nu", "set number").unwrap();
+        let _ = super::handle_command_action(
+            Action::CommandExecute(":Nu".to_string()),
+            &mut st,
+            &mut view,
+            &registry,
+        );
+        assert!(st.options.get_bool("number"));
+        let _ = super::handle_command_action(
+            Action::CommandExecute(":Loop".to_string()),
+            &mut st,
+            &mut view,
+            &registry,
+        );
+        assert_eq!(
+            st.ephemeral_status.as_ref().unwrap().text,
+            "E169: Command too recursive"
+        );
+    }
+
+    #[test]
+    fn shell_commands_queue_requests() {
+        let (mut st, mut view) = mk_state();
+        st.buffers[st.active] = Buffer::from_str("t", "b\na\nc\n").unwrap();
+        let _ = handle_command_action(
+            Action::CommandExecute(":!echo hi".to_string()),
+            &mut st,
+            &mut view,
+        );
+        let _ = handle_command_action(
+            Action::CommandExecute(":1,2!sort".to_string()),
+            &mut st,
+            &mut view,
+        );
+        let _ = handle_command_action(
+            Action::CommandExecute(":r !date".to_string()),
+            &mut st,
+            &mut view,
+        );
+        let queued = st.shell.take();
+        assert_eq!(queued.len(), 3);
+        assert_eq!(queued[0].target, ShellTarget::Message);
+        let origin = ShellOrigin::active(&st, view.id.0);
+        assert_eq!(
+            queued[1].target,
+            ShellTarget::Filter {
+                origin,
+                start: 0,
+                end: 1
+            }
+        );
+        assert_eq!(queued[1].stdin.as_deref(), Some("b\na\n"));
+        assert_eq!(queued[2].target, ShellTarget::ReadAfter { origin, line: 0 });
+    }
+
+    #[test]
+    fn shell_filter_invalid_range_reports_error() {
+        let (mut st, mut view) = mk_state();
+        let _ = handle_command_action(
+            Action::CommandExecute(":1,9!sort".to_string()),
+            &mut st,
+            &mut view,
+        );
+        assert!(!st.shell.has_pending());
+        assert_eq!(
+            st.ephemeral_status.as_ref().unwrap().text,
+            "E16: Invalid range"
+        );
+    }
+
+    #[test]
+    fn sort_unique_is_one_undo_step_and_fills_register() {
+        let (mut st, mut view) = mk_state();
+        st.buffers[st.active] = Buffer::from_str("t", "c\na\nc\nb\nz\n").unwrap();
+        let res = handle_command_action(
+            Action::CommandExecute(":1,4sort u".to_string()),
+            &mut st,
+            &mut view,
+        );
+        assert!(res.buffer_replaced);
+        let text: String = (0..st.active_buffer().line_count())
+            .filter_map(|i| st.active_buffer().line(i))
+            .collect();
+        assert_eq!(text, "a\nb\nc\nz\n");
+        assert_eq!(st.undo_depth(), 1);
+        assert_eq!(st.registers.unnamed, "c\n");
+        assert_eq!(st.ephemeral_status.as_ref().unwrap().text, "1 fewer line");
+    }
+
+    #[test]
+    fn sort_rejects_unknown_flag() {
+        let (mut st, mut view) = mk_state();
+        let _ = handle_command_action(
+            Action::CommandExecute(":sort q".to_string()),
+            &mut st,
+            &mut view,
+        );
+        assert_eq!(
+            st.ephemeral_status.as_ref().unwrap().text,
+            "E474: Invalid argument: q"
+        );
+        assert_eq!(st.undo_depth(), 0);
+    }
+
     #[test]
     fn quit_dirty_requires_force() {
         let (mut st, mut view) = mk_state();
-        st.dirty = true;
+        st.set_dirty(true);
         let res =
             handle_command_action(Action::CommandExecute(":q".to_string()), &mut st, &mut view);
         assert!(res.dirty, "command should trigger UI refresh");
@@ -219,7 +1213,7 @@ mod tests {
     #[test]
     fn quit_force_allows_exit() {
         let (mut st, mut view) = mk_state();
-        st.dirty = true;
+        st.set_dirty(true);
         let res = handle_command_action(
             Action::CommandExecute(":q!".to_string()),
             &mut st,
@@ -231,7 +1225,7 @@ mod tests {
     #[test]
     fn edit_dirty_requires_force() {
         let (mut st, mut view) = mk_state();
-        st.dirty = true;
+        st.set_dirty(true);
         let res = handle_command_action(
             Action::CommandExecute(":e some-file".to_string()),
             &mut st,
@@ -249,14 +1243,14 @@ mod tests {
     #[test]
     fn edit_force_opens_path_and_resets_dirty() {
         let (mut st, mut view) = mk_state();
-        st.dirty = true;
+        st.set_dirty(true);
         let dir = tempfile::tempdir().unwrap();
         let file_path = dir.path().join("reload.txt");
         fs::write(&file_path, "reloaded\n").unwrap();
         let cmd = format!(":e! {}", file_path.display());
         let res = handle_command_action(Action::CommandExecute(cmd), &mut st, &mut view);
         assert!(res.buffer_replaced, "forced edit should swap buffer");
-        assert!(!st.dirty, "buffer should be clean after reload");
+        assert!(!st.dirty(), "buffer should be clean after reload");
         assert_eq!(
             st.active_buffer().line(0).unwrap(),
             "reloaded\n",
@@ -279,7 +1273,7 @@ mod tests {
     #[test]
     fn write_without_filename_reports_error() {
         let (mut st, mut view) = mk_state();
-        st.dirty = true;
+        st.set_dirty(true);
         let res =
             handle_command_action(Action::CommandExecute(":w".to_string()), &mut st, &mut view);
         assert!(res.dirty);
@@ -287,10 +1281,67 @@ mod tests {
         assert_eq!(eph.text, "E32: No file name");
     }
 
+    #[test]
+    fn recover_restores_stale_swap() {
+        let (mut st, mut view) = mk_state();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("r.txt");
+        std::fs::write(
+            core_state::swap::swap_path(&path),
+            core_state::swap::encode_swap(&path, "lost\n"),
+        )
+        .unwrap();
+        st.set_file_name(Some(path));
+        let res = handle_command_action(Action::CommandExecute(":rec".into()), &mut st, &mut view);
+        let eph = st.ephemeral_status.as_ref().unwrap();
+        assert!(eph.text.starts_with("E305: No swap file found for"));
+        assert!(!res.buffer_replaced, "nothing detected yet");
+
+        st.detect_stale_swap();
+        let res = handle_command_action(Action::CommandExecute(":rec".into()), &mut st, &mut view);
+        assert!(res.buffer_replaced);
+        assert_eq!(st.active_buffer().line(0).unwrap(), "lost\n");
+        assert!(st.dirty());
+    }
+
+    #[test]
+    fn write_in_raw_binary_view_keeps_invalid_bytes() {
+        let (mut st, mut view) = mk_state();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blob.bin");
+        let bytes = vec![b'a', 0xff, 0xfe, b'\n', 0, b'z'];
+        fs::write(&path, &bytes).unwrap();
+        let cmd = |st: &mut EditorState, view: &mut View, c: &str| {
+            handle_command_action(Action::CommandExecute(c.to_string()), st, view);
+        };
+        cmd(&mut st, &mut view, &format!(":e {}", path.display()));
+        cmd(&mut st, &mut view, ":set binary");
+        assert!(st.hex_view().is_none());
+        assert!(st.active_buffer().line(0).unwrap().contains('\u{fffd}'));
+        cmd(&mut st, &mut view, ":w");
+        assert_eq!(st.ephemeral_status.as_ref().unwrap().text, "Wrote");
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        // Edited, the decoded text is all there is: refuse to save it.
+        let mut pos = core_text::Position::new(0, 0);
+        st.active_buffer_mut().insert_grapheme(&mut pos, "x");
+        st.set_dirty(true);
+        cmd(&mut st, &mut view, ":w");
+        assert!(
+            st.ephemeral_status
+                .as_ref()
+                .unwrap()
+                .text
+                .starts_with("E513")
+        );
+        assert!(st.dirty());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
     #[test]
     fn write_with_path_saves_and_updates_filename() {
         let (mut st, mut view) = mk_state();
-        st.dirty = true;
+        st.set_dirty(true);
         let dir = tempfile::tempdir().unwrap();
         let file_path = dir.path().join("out.txt");
         let cmd = format!(":w {}", file_path.display());
@@ -298,8 +1349,8 @@ mod tests {
         assert!(res.dirty);
         let eph = st.ephemeral_status.as_ref().expect("ephemeral message set");
         assert_eq!(eph.text, "Wrote");
-        assert_eq!(st.file_name.as_ref(), Some(&file_path));
-        assert!(!st.dirty, "state should be clean after write");
+        assert_eq!(st.file_name(), Some(file_path.as_path()));
+        assert!(!st.dirty(), "state should be clean after write");
         let written = fs::read_to_string(&file_path).unwrap();
         assert_eq!(written, "abc\n");
     }
@@ -19,20 +19,206 @@
 //! * Async commands (e.g. LSP-driven) will use a follow-up event once
 //!   implemented—parser remains pure.
 
+use super::ex_range::{RangeSpec, split_range};
+use crate::command_registry::{CommandInvocation, CommandRegistry, CompletionHint};
+use core_model::SplitAxis;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsedCommand {
-    Quit { force: bool },
-    Write { force: bool, path: Option<PathBuf> },
-    Edit { force: bool, path: Option<PathBuf> },
+    Quit {
+        force: bool,
+    },
+    Write {
+        force: bool,
+        path: Option<PathBuf>,
+    },
+    Edit {
+        force: bool,
+        path: Option<PathBuf>,
+    },
     Metrics, // placeholder for Step 11
+    // `:metrics dump [file]`: JSON snapshot to a file or the message area
+    MetricsDump {
+        path: Option<PathBuf>,
+    },
+    // `:metrics {section}` / `:metrics next` / `:metrics prev`
+    MetricsPage {
+        arg: String,
+    },
+    Set {
+        args: String,
+    }, // argument grammar owned by `core_config::options`
+    User(CommandInvocation), // resolved through the user command registry
+    // `:!cmd` (no range) or `:{range}!cmd` (filter lines through cmd)
+    Shell {
+        range: Option<RangeSpec>,
+        command: String,
+    },
+    // `:[line]r !cmd`
+    ReadShell {
+        range: Option<RangeSpec>,
+        command: String,
+    },
+    ShellInteractive, // `:sh[ell]`
+    // `:[range]sor[t][!] [flags]`
+    Sort {
+        range: Option<RangeSpec>,
+        reverse: bool,
+        flags: String,
+    },
+    // `:[range]align[!] {delimiter} [flags]`
+    Align {
+        range: Option<RangeSpec>,
+        right: bool,
+        args: String,
+    },
+    // `:rec[over]` restores a stale swap file, `:rec[over]!` discards it
+    Recover {
+        discard: bool,
+    },
+    // `:ea[rlier] {N}[smhd]` / `:lat[er] {N}[smhd]` undo-tree time travel
+    UndoTime {
+        later: bool,
+        arg: String,
+    },
+    // `:sp[lit] [file]` opens a window above the current one, `:vs[plit]
+    // [file]` one to its left
+    Split {
+        axis: SplitAxis,
+        path: Option<PathBuf>,
+    },
+    // `:tabnew [file]` opens a tab page after the current one
+    TabNew {
+        path: Option<PathBuf>,
+    },
+    // `:diffs[plit] {file}` compares the current buffer with `file` shown
+    // beside it
+    DiffSplit {
+        path: Option<PathBuf>,
+    },
+    // `:diffo[ff]` ends the comparison
+    DiffOff,
+    // `:clo[se][!]` closes the current window
+    Close {
+        force: bool,
+    },
+    // `:colo[rscheme] [name]` loads a color scheme (no name echoes the current one)
+    Colorscheme {
+        name: Option<String>,
+    },
+    // `:noh[lsearch]` hides search match highlighting until the next search
+    NoHlsearch,
+    // `:diag` lists the active buffer's diagnostics
+    Diagnostics,
+    // `:ia[bbrev] [lhs [rhs]]` defines or lists Insert-mode abbreviations
+    Abbreviate {
+        args: String,
+    },
+    // `:iuna[bbrev] {lhs}` removes one
+    Unabbreviate {
+        lhs: String,
+    },
+    // `:abc[lear]` / `:iabc[lear]` removes them all
+    AbClear,
+    // `:blame` toggles the cursor line's `git blame` annotation
+    Blame,
+    // `:E[xplore] [dir]` lists a directory in the current window
+    Explore {
+        path: Option<PathBuf>,
+    },
+    // In a directory listing: `:mkdir {name}`, `:rename {name}` (the entry
+    // under the cursor) and `:remove [name]`
+    Mkdir {
+        name: String,
+    },
+    Rename {
+        name: String,
+    },
+    Remove {
+        name: String,
+    },
+    // `:gr[ep][!] {args}` runs 'grepprg' into the quickfix list,
+    // `:grepa[dd][!] {args}` adds to it; `!` skips the jump to the first
+    // match. `:lgr[ep]` / `:lgrepa[dd]` fill the window's location list.
+    Grep {
+        location: bool,
+        add: bool,
+        bang: bool,
+        args: String,
+    },
+    // `:cc [nr]` / `:ll [nr]` jump to list item `nr` (the current one without)
+    QuickfixJump {
+        location: bool,
+        nr: Option<usize>,
+    },
+    // `:cn[ext]` / `:cp[revious]` (`:cN[ext]`), `:lne[xt]` / `:lp[revious]`
+    // (`:lN[ext]`)
+    QuickfixStep {
+        location: bool,
+        forward: bool,
+    },
+    // `:cope[n]` / `:ccl[ose]`, `:lop[en]` / `:lcl[ose]` show or close the
+    // list window
+    QuickfixWindow {
+        location: bool,
+        open: bool,
+    },
+    // `:ldiag` fills the window's location list with the buffer's diagnostics
+    LocationDiagnostics,
+    // `:mak[e][!] [args]` runs 'makeprg' in the background into the quickfix
+    // list; `!` skips the jump to the first error
+    Make {
+        bang: bool,
+        args: String,
+    },
+    // `:job {cmd}` runs `cmd` in the background into an output buffer
+    Job {
+        command: String,
+    },
+    // `:jobs` lists the running jobs, `:jobstop [id]` stops one (all without)
+    Jobs,
+    JobStop {
+        arg: String,
+    },
+    // `:defer {ms} {cmd}` runs `cmd` once after `ms` milliseconds, `:timer
+    // {ms} {cmd}` every `ms` milliseconds
+    Timer {
+        repeat: bool,
+        args: String,
+    },
+    // `:timers` lists the active timers, `:timerstop [id]` stops one (all
+    // without)
+    Timers,
+    TimerStop {
+        arg: String,
+    },
     Unknown(String),
 }
 
 pub struct CommandParser;
 
 impl CommandParser {
+    /// Parse consulting `registry` before built-ins so registered commands
+    /// may shadow built-in names. A prefix of a registered name only
+    /// resolves when no built-in matched.
+    pub fn parse_with(raw: &str, registry: &CommandRegistry) -> ParsedCommand {
+        let body = raw
+            .trim()
+            .strip_prefix(':')
+            .filter(|_| !registry.is_empty());
+        if let Some(invocation) = body.and_then(|b| registry.parse_exact(b)) {
+            return ParsedCommand::User(invocation);
+        }
+        match Self::parse(raw) {
+            ParsedCommand::Unknown(unknown) => match body.and_then(|b| registry.parse(b)) {
+                Some(invocation) => ParsedCommand::User(invocation),
+                None => ParsedCommand::Unknown(unknown),
+            },
+            parsed => parsed,
+        }
+    }
+
     pub fn parse(raw: &str) -> ParsedCommand {
         let s = raw.trim();
         if !s.starts_with(':') {
@@ -43,7 +229,43 @@ impl CommandParser {
         if body.is_empty() {
             return ParsedCommand::Unknown(String::new());
         }
-        let (head, tail) = split_head(body);
+        let (range, rest) = split_range(body);
+        if let Some(command) = rest.strip_prefix('!') {
+            return ParsedCommand::Shell {
+                range,
+                command: command.trim().to_string(),
+            };
+        }
+        let (head, tail) = split_head(rest);
+        if let Some(command) = read_shell_command(head, tail) {
+            return ParsedCommand::ReadShell { range, command };
+        }
+        if let Some(reverse) = sort_head(head) {
+            return ParsedCommand::Sort {
+                range,
+                reverse,
+                flags: tail.trim().to_string(),
+            };
+        }
+        if let Some(right) = align_head(head) {
+            return ParsedCommand::Align {
+                range,
+                right,
+                args: tail.trim().to_string(),
+            };
+        }
+        if range.is_some() {
+            // Remaining commands do not accept a range yet.
+            return ParsedCommand::Unknown(body.to_string());
+        }
+        if let Some((location, add, bang)) = grep_head(head) {
+            return ParsedCommand::Grep {
+                location,
+                add,
+                bang,
+                args: tail.trim().to_string(),
+            };
+        }
         match head {
             "q" => ParsedCommand::Quit { force: false },
             "q!" => ParsedCommand::Quit { force: true },
@@ -64,12 +286,226 @@ impl CommandParser {
                 path: parse_path(tail),
             },
             "metrics" if tail.trim().is_empty() => ParsedCommand::Metrics,
+            "metrics" => match tail.trim().split_once(char::is_whitespace) {
+                Some(("dump", rest)) => ParsedCommand::MetricsDump {
+                    path: parse_path(rest),
+                },
+                _ if tail.trim() == "dump" => ParsedCommand::MetricsDump { path: None },
+                _ => ParsedCommand::MetricsPage {
+                    arg: tail.trim().to_string(),
+                },
+            },
+            "set" | "se" => ParsedCommand::Set {
+                args: tail.trim().to_string(),
+            },
+            "sh" | "shell" if tail.trim().is_empty() => ParsedCommand::ShellInteractive,
+            "rec" | "reco" | "recov" | "recove" | "recover" if tail.trim().is_empty() => {
+                ParsedCommand::Recover { discard: false }
+            }
+            "rec!" | "reco!" | "recov!" | "recove!" | "recover!" if tail.trim().is_empty() => {
+                ParsedCommand::Recover { discard: true }
+            }
+            "ea" | "ear" | "earl" | "earli" | "earlie" | "earlier" => ParsedCommand::UndoTime {
+                later: false,
+                arg: tail.trim().to_string(),
+            },
+            "lat" | "late" | "later" => ParsedCommand::UndoTime {
+                later: true,
+                arg: tail.trim().to_string(),
+            },
+            "sp" | "spl" | "spli" | "split" => ParsedCommand::Split {
+                axis: SplitAxis::Horizontal,
+                path: parse_path(tail),
+            },
+            "vs" | "vsp" | "vspl" | "vspli" | "vsplit" => ParsedCommand::Split {
+                axis: SplitAxis::Vertical,
+                path: parse_path(tail),
+            },
+            "tabnew" => ParsedCommand::TabNew {
+                path: parse_path(tail),
+            },
+            "diffs" | "diffsp" | "diffspl" | "diffspli" | "diffsplit" => ParsedCommand::DiffSplit {
+                path: parse_path(tail),
+            },
+            "diffo" | "diffof" | "diffoff" if tail.trim().is_empty() => ParsedCommand::DiffOff,
+            "clo" | "clos" | "close" if tail.trim().is_empty() => {
+                ParsedCommand::Close { force: false }
+            }
+            "clo!" | "clos!" | "close!" if tail.trim().is_empty() => {
+                ParsedCommand::Close { force: true }
+            }
+            "colo" | "color" | "colors" | "colorsc" | "colorsch" | "colorsche" | "colorschem"
+            | "colorscheme" => ParsedCommand::Colorscheme {
+                name: Some(tail.trim())
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string),
+            },
+            "noh" | "nohl" | "nohls" | "nohlse" | "nohlsea" | "nohlsear" | "nohlsearc"
+            | "nohlsearch"
+                if tail.trim().is_empty() =>
+            {
+                ParsedCommand::NoHlsearch
+            }
+            "diag" if tail.trim().is_empty() => ParsedCommand::Diagnostics,
+            "blame" if tail.trim().is_empty() => ParsedCommand::Blame,
+            "E" | "Ex" | "Exp" | "Expl" | "Explo" | "Explor" | "Explore" => {
+                ParsedCommand::Explore {
+                    path: parse_path(tail),
+                }
+            }
+            "cc" | "ll" => match tail.trim() {
+                "" => ParsedCommand::QuickfixJump {
+                    location: head == "ll",
+                    nr: None,
+                },
+                nr => match nr.parse() {
+                    Ok(nr) => ParsedCommand::QuickfixJump {
+                        location: head == "ll",
+                        nr: Some(nr),
+                    },
+                    Err(_) => ParsedCommand::Unknown(body.to_string()),
+                },
+            },
+            "cn" | "cne" | "cnex" | "cnext" | "lne" | "lnex" | "lnext"
+                if tail.trim().is_empty() =>
+            {
+                ParsedCommand::QuickfixStep {
+                    location: head.starts_with('l'),
+                    forward: true,
+                }
+            }
+            "cp" | "cpr" | "cpre" | "cprev" | "cprevi" | "cprevio" | "cpreviou" | "cprevious"
+            | "cN" | "cNe" | "cNex" | "cNext" | "lp" | "lpr" | "lpre" | "lprev" | "lprevi"
+            | "lprevio" | "lpreviou" | "lprevious" | "lN" | "lNe" | "lNex" | "lNext"
+                if tail.trim().is_empty() =>
+            {
+                ParsedCommand::QuickfixStep {
+                    location: head.starts_with('l'),
+                    forward: false,
+                }
+            }
+            "cope" | "copen" | "ccl" | "cclo" | "cclos" | "cclose" | "lop" | "lope" | "lopen"
+            | "lcl" | "lclo" | "lclos" | "lclose"
+                if tail.trim().is_empty() =>
+            {
+                ParsedCommand::QuickfixWindow {
+                    location: head.starts_with('l'),
+                    open: !head[1..].starts_with("cl"),
+                }
+            }
+            "ldiag" if tail.trim().is_empty() => ParsedCommand::LocationDiagnostics,
+            "mak" | "make" | "mak!" | "make!" => ParsedCommand::Make {
+                bang: head.ends_with('!'),
+                args: tail.trim().to_string(),
+            },
+            "job" => ParsedCommand::Job {
+                command: tail.trim().to_string(),
+            },
+            "jobs" if tail.trim().is_empty() => ParsedCommand::Jobs,
+            "jobstop" => ParsedCommand::JobStop {
+                arg: tail.trim().to_string(),
+            },
+            "defer" | "timer" => ParsedCommand::Timer {
+                repeat: head == "timer",
+                args: tail.trim().to_string(),
+            },
+            "timers" if tail.trim().is_empty() => ParsedCommand::Timers,
+            "timerstop" => ParsedCommand::TimerStop {
+                arg: tail.trim().to_string(),
+            },
+            "mkdir" => ParsedCommand::Mkdir {
+                name: tail.trim().to_string(),
+            },
+            "rename" => ParsedCommand::Rename {
+                name: tail.trim().to_string(),
+            },
+            "remove" => ParsedCommand::Remove {
+                name: tail.trim().to_string(),
+            },
+            "ia" | "iab" | "iabb" | "iabbr" | "iabbre" | "iabbrev" => ParsedCommand::Abbreviate {
+                args: tail.trim().to_string(),
+            },
+            "iuna" | "iunab" | "iunabb" | "iunabbr" | "iunabbre" | "iunabbrev" => {
+                ParsedCommand::Unabbreviate {
+                    lhs: tail.trim().to_string(),
+                }
+            }
+            "abc" | "abcl" | "abcle" | "abclea" | "abclear" | "iabc" | "iabcl" | "iabcle"
+            | "iabclea" | "iabclear"
+                if tail.trim().is_empty() =>
+            {
+                ParsedCommand::AbClear
+            }
             _ => ParsedCommand::Unknown(body.to_string()),
         }
     }
+
+    /// What the arguments of built-in command `head` (as typed, `!`
+    /// included) complete to on the command line.
+    pub fn completion_hint(head: &str) -> CompletionHint {
+        match head.strip_suffix('!').unwrap_or(head) {
+            "w" | "e" | "sp" | "spl" | "spli" | "split" | "vs" | "vsp" | "vspl" | "vspli"
+            | "vsplit" | "tabnew" | "diffs" | "diffsp" | "diffspl" | "diffspli" | "diffsplit"
+            | "E" | "Ex" | "Exp" | "Expl" | "Explo" | "Explor" | "Explore" => CompletionHint::File,
+            "set" | "se" => CompletionHint::Option,
+            _ => CompletionHint::None,
+        }
+    }
+}
+
+/// `sor`, `sort` (optionally with `!`) -> `Some(reverse)`.
+fn sort_head(head: &str) -> Option<bool> {
+    let (name, bang) = match head.strip_suffix('!') {
+        Some(name) => (name, true),
+        None => (head, false),
+    };
+    matches!(name, "sor" | "sort").then_some(bang)
 }
 
-fn split_head(body: &str) -> (&str, &str) {
+/// `gr[ep]`, `grepa[dd]`, `lgr[ep]`, `lgrepa[dd]` (optionally with `!`) ->
+/// `Some((location, add, bang))`.
+fn grep_head(head: &str) -> Option<(bool, bool, bool)> {
+    let (name, bang) = match head.strip_suffix('!') {
+        Some(name) => (name, true),
+        None => (head, false),
+    };
+    let (name, location) = match name.strip_prefix('l') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    match name {
+        "gr" | "gre" | "grep" => Some((location, false, bang)),
+        "grepa" | "grepad" | "grepadd" => Some((location, true, bang)),
+        _ => None,
+    }
+}
+
+/// `align` (optionally with `!`) -> `Some(right)`.
+fn align_head(head: &str) -> Option<bool> {
+    match head {
+        "align" => Some(false),
+        "align!" => Some(true),
+        _ => None,
+    }
+}
+
+/// `:r !cmd`, `:r!cmd`, `:read !cmd` -> `Some(cmd)`.
+fn read_shell_command(head: &str, tail: &str) -> Option<String> {
+    for name in ["r", "read"] {
+        if head == name {
+            return tail
+                .trim_start()
+                .strip_prefix('!')
+                .map(|c| c.trim().to_string());
+        }
+        if let Some(inline) = head.strip_prefix(name).and_then(|h| h.strip_prefix('!')) {
+            return Some(format!("{inline}{tail}").trim().to_string());
+        }
+    }
+    None
+}
+
+pub(super) fn split_head(body: &str) -> (&str, &str) {
     let mut idx = 0usize;
     for (offset, ch) in body.char_indices() {
         if ch.is_whitespace() {
@@ -150,6 +586,36 @@ mod tests {
     #[test]
     fn parse_metrics() {
         assert_eq!(CommandParser::parse(":metrics"), ParsedCommand::Metrics);
+        assert_eq!(
+            CommandParser::parse(":metrics next"),
+            ParsedCommand::MetricsPage { arg: "next".into() }
+        );
+        assert_eq!(
+            CommandParser::parse(":metrics dump"),
+            ParsedCommand::MetricsDump { path: None }
+        );
+        assert_eq!(
+            CommandParser::parse(":metrics dump out/m.json"),
+            ParsedCommand::MetricsDump {
+                path: Some(PathBuf::from("out/m.json"))
+            }
+        );
+    }
+
+    #[test]
+    fn parse_set_with_arguments() {
+        assert_eq!(
+            CommandParser::parse(":set  number sw=4"),
+            ParsedCommand::Set {
+                args: "number sw=4".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":se"),
+            ParsedCommand::Set {
+                args: String::new()
+            }
+        );
     }
 
     #[test]
@@ -163,6 +629,416 @@ mod tests {
         );
     }
 
+    #[test]
+    fn registry_commands_take_precedence() {
+        use crate::command_registry::CommandSpec;
+        use crate::dispatcher::DispatchResult;
+        let mut registry = CommandRegistry::new();
+        registry
+            .register_builtin(CommandSpec::new("metrics").bang(true), |_, _, _| {
+                DispatchResult::clean()
+            })
+            .unwrap();
+        match CommandParser::parse_with(":metrics!", &registry) {
+            ParsedCommand::User(inv) => {
+                assert_eq!(inv.name, "metrics");
+                assert!(inv.bang);
+            }
+            other => panic!("expected user command, got {other:?}"),
+        }
+        assert_eq!(
+            CommandParser::parse_with(":q", &registry),
+            ParsedCommand::Quit { force: false }
+        );
+    }
+
+    #[test]
+    fn registry_prefixes_do_not_shadow_builtins() {
+        use crate::command_registry::CommandSpec;
+        use crate::dispatcher::DispatchResult;
+        let mut registry = CommandRegistry::new();
+        registry.register_alias("Wipe", "bd").unwrap();
+        registry
+            .register_builtin(CommandSpec::new("wipeall"), |_, _, _| {
+                DispatchResult::clean()
+            })
+            .unwrap();
+        assert!(registry.register_alias("wipe", "bd").is_err());
+        assert_eq!(
+            CommandParser::parse_with(":w", &registry),
+            ParsedCommand::Write {
+                force: false,
+                path: None
+            }
+        );
+        // Once no built-in matches, prefixes resolve.
+        assert!(matches!(
+            CommandParser::parse_with(":Wi", &registry),
+            ParsedCommand::User(inv) if inv.name == "Wipe"
+        ));
+        assert!(matches!(
+            CommandParser::parse_with(":wipea", &registry),
+            ParsedCommand::User(inv) if inv.name == "wipeall"
+        ));
+    }
+
+    #[test]
+    fn parse_shell_forms() {
+        assert_eq!(
+            CommandParser::parse(":!ls -la"),
+            ParsedCommand::Shell {
+                range: None,
+                command: "ls -la".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":%!sort"),
+            ParsedCommand::Shell {
+                range: Some(RangeSpec::Whole),
+                command: "sort".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":r !date +%Y"),
+            ParsedCommand::ReadShell {
+                range: None,
+                command: "date +%Y".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":read!echo hi"),
+            ParsedCommand::ReadShell {
+                range: None,
+                command: "echo hi".into()
+            }
+        );
+        assert_eq!(CommandParser::parse(":sh"), ParsedCommand::ShellInteractive);
+    }
+
+    #[test]
+    fn parse_align_with_range_and_bang() {
+        assert_eq!(
+            CommandParser::parse(":%align! = s"),
+            ParsedCommand::Align {
+                range: Some(RangeSpec::Whole),
+                right: true,
+                args: "= s".into(),
+            }
+        );
+        assert!(matches!(
+            CommandParser::parse(":align |"),
+            ParsedCommand::Align {
+                range: None,
+                right: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_sort_with_range_bang_and_flags() {
+        assert_eq!(
+            CommandParser::parse(":%sort! nu"),
+            ParsedCommand::Sort {
+                range: Some(RangeSpec::Whole),
+                reverse: true,
+                flags: "nu".into(),
+            }
+        );
+        assert!(matches!(
+            CommandParser::parse(":sor"),
+            ParsedCommand::Sort {
+                range: None,
+                reverse: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_abbreviation_commands() {
+        assert_eq!(
+            CommandParser::parse(":iab teh  the"),
+            ParsedCommand::Abbreviate {
+                args: "teh  the".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":iunabbrev teh"),
+            ParsedCommand::Unabbreviate { lhs: "teh".into() }
+        );
+        assert_eq!(CommandParser::parse(":abc"), ParsedCommand::AbClear);
+        assert_eq!(CommandParser::parse(":iabclear"), ParsedCommand::AbClear);
+    }
+
+    #[test]
+    fn parse_recover() {
+        assert_eq!(
+            CommandParser::parse(":rec"),
+            ParsedCommand::Recover { discard: false }
+        );
+        assert_eq!(
+            CommandParser::parse(":recover!"),
+            ParsedCommand::Recover { discard: true }
+        );
+    }
+
+    #[test]
+    fn parse_earlier_later() {
+        assert_eq!(
+            CommandParser::parse(":earlier 10s"),
+            ParsedCommand::UndoTime {
+                later: false,
+                arg: "10s".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":lat"),
+            ParsedCommand::UndoTime {
+                later: true,
+                arg: String::new()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_split_and_close() {
+        assert_eq!(
+            CommandParser::parse(":sp"),
+            ParsedCommand::Split {
+                axis: SplitAxis::Horizontal,
+                path: None
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":vs"),
+            ParsedCommand::Split {
+                axis: SplitAxis::Vertical,
+                path: None
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":split other.txt"),
+            ParsedCommand::Split {
+                axis: SplitAxis::Horizontal,
+                path: Some(PathBuf::from("other.txt"))
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":tabnew"),
+            ParsedCommand::TabNew { path: None }
+        );
+        assert_eq!(
+            CommandParser::parse(":diffs b.txt"),
+            ParsedCommand::DiffSplit {
+                path: Some(PathBuf::from("b.txt"))
+            }
+        );
+        assert_eq!(CommandParser::parse(":diffoff"), ParsedCommand::DiffOff);
+        assert_eq!(
+            CommandParser::parse(":clo"),
+            ParsedCommand::Close { force: false }
+        );
+        assert_eq!(
+            CommandParser::parse(":close!"),
+            ParsedCommand::Close { force: true }
+        );
+    }
+
+    #[test]
+    fn parse_colorscheme() {
+        assert_eq!(
+            CommandParser::parse(":colo"),
+            ParsedCommand::Colorscheme { name: None }
+        );
+        assert_eq!(
+            CommandParser::parse(":colorscheme  night "),
+            ParsedCommand::Colorscheme {
+                name: Some("night".into())
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":col night"),
+            ParsedCommand::Unknown("col night".into())
+        );
+    }
+
+    #[test]
+    fn parse_nohlsearch() {
+        assert_eq!(CommandParser::parse(":noh"), ParsedCommand::NoHlsearch);
+        assert_eq!(
+            CommandParser::parse(":nohlsearch"),
+            ParsedCommand::NoHlsearch
+        );
+        assert_eq!(
+            CommandParser::parse(":no"),
+            ParsedCommand::Unknown("no".into())
+        );
+    }
+
+    #[test]
+    fn parse_explore_and_listing_commands() {
+        assert_eq!(
+            CommandParser::parse(":Ex src"),
+            ParsedCommand::Explore {
+                path: Some(PathBuf::from("src"))
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":E"),
+            ParsedCommand::Explore { path: None }
+        );
+        assert_eq!(
+            CommandParser::parse(":rename my notes.txt"),
+            ParsedCommand::Rename {
+                name: "my notes.txt".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":remove"),
+            ParsedCommand::Remove {
+                name: String::new()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_grep_and_quickfix_jump() {
+        assert_eq!(
+            CommandParser::parse(":grep -w foo src"),
+            ParsedCommand::Grep {
+                location: false,
+                add: false,
+                bang: false,
+                args: "-w foo src".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":grepa! bar"),
+            ParsedCommand::Grep {
+                location: false,
+                add: true,
+                bang: true,
+                args: "bar".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":lgr foo"),
+            ParsedCommand::Grep {
+                location: true,
+                add: false,
+                bang: false,
+                args: "foo".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":cc 3"),
+            ParsedCommand::QuickfixJump {
+                location: false,
+                nr: Some(3)
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":ll"),
+            ParsedCommand::QuickfixJump {
+                location: true,
+                nr: None
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":cN"),
+            ParsedCommand::QuickfixStep {
+                location: false,
+                forward: false
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":lne"),
+            ParsedCommand::QuickfixStep {
+                location: true,
+                forward: true
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":copen"),
+            ParsedCommand::QuickfixWindow {
+                location: false,
+                open: true
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":lcl"),
+            ParsedCommand::QuickfixWindow {
+                location: true,
+                open: false
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":cc x"),
+            ParsedCommand::Unknown("cc x".into())
+        );
+    }
+
+    #[test]
+    fn parse_make_and_job_commands() {
+        assert_eq!(
+            CommandParser::parse(":mak! -j4"),
+            ParsedCommand::Make {
+                bang: true,
+                args: "-j4".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":make"),
+            ParsedCommand::Make {
+                bang: false,
+                args: String::new()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":job cargo test"),
+            ParsedCommand::Job {
+                command: "cargo test".into()
+            }
+        );
+        assert_eq!(CommandParser::parse(":jobs"), ParsedCommand::Jobs);
+        assert_eq!(
+            CommandParser::parse(":jobstop 2"),
+            ParsedCommand::JobStop { arg: "2".into() }
+        );
+    }
+
+    #[test]
+    fn parse_timer_commands() {
+        assert_eq!(
+            CommandParser::parse(":defer 500 w"),
+            ParsedCommand::Timer {
+                repeat: false,
+                args: "500 w".into()
+            }
+        );
+        assert_eq!(
+            CommandParser::parse(":timer 1000 w"),
+            ParsedCommand::Timer {
+                repeat: true,
+                args: "1000 w".into()
+            }
+        );
+        assert_eq!(CommandParser::parse(":timers"), ParsedCommand::Timers);
+        assert_eq!(
+            CommandParser::parse(":timerstop"),
+            ParsedCommand::TimerStop { arg: String::new() }
+        );
+    }
+
+    #[test]
+    fn range_on_unsupported_command_is_unknown() {
+        assert_eq!(
+            CommandParser::parse(":1,2q"),
+            ParsedCommand::Unknown("1,2q".into())
+        );
+    }
+
     #[test]
     fn parse_unknown() {
         assert_eq!(
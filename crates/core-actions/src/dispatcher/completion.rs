@@ -0,0 +1,178 @@
+//! Insert-mode completion keys (`Ctrl-N` / `Ctrl-P` / `Ctrl-Space`,
+//! `Ctrl-Y`, `Ctrl-E`) and how typing affects an open completion.
+//!
+//! Candidates, selection and the queued server request live in
+//! `core_state::completion`; this module edits the buffer to match the
+//! selection. The replacements join the running Insert undo step.
+
+use super::DispatchResult;
+use crate::{Action, CompletionCommand, EditKind};
+use core_model::View;
+use core_state::completion::{CaseRules, is_keyword_char};
+use core_state::{EditorState, Mode};
+use core_text::Position;
+use std::time::Duration;
+
+/// Whether `action` keeps an open completion: its own keys, and typing or
+/// deleting (which `after_edit` turns into narrowing or closing).
+pub(crate) fn continues(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Completion(_) | Action::Edit(EditKind::InsertGrapheme(_) | EditKind::Backspace)
+    )
+}
+
+pub(crate) fn command(
+    command: CompletionCommand,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if !matches!(state.mode, Mode::Insert) {
+        return DispatchResult::clean();
+    }
+    match command {
+        CompletionCommand::Next | CompletionCommand::Prev => {
+            let forward = command == CompletionCommand::Next;
+            if !state.completion.is_active() {
+                open(state, view);
+            }
+            let Some(completion) = state.completion.active() else {
+                return DispatchResult::dirty();
+            };
+            let old_len = completion.current_text().len();
+            if completion.match_count() == 0 {
+                // Still waiting for the server.
+                return DispatchResult::dirty();
+            }
+            let text = state
+                .completion
+                .select(forward)
+                .unwrap_or_default()
+                .to_string();
+            replace(state, view, old_len, &text);
+        }
+        CompletionCommand::Accept => {
+            if !state.completion.close() {
+                return DispatchResult::clean();
+            }
+        }
+        CompletionCommand::Cancel => {
+            let Some(completion) = state.completion.active() else {
+                return DispatchResult::clean();
+            };
+            let old_len = completion.current_text().len();
+            let prefix = completion.prefix.clone();
+            replace(state, view, old_len, &prefix);
+            state.completion.close();
+        }
+    }
+    DispatchResult::dirty()
+}
+
+/// Open a completion for the keyword before the cursor. Without buffer
+/// matches or a server to ask, report it and leave nothing open.
+fn open(state: &mut EditorState, view: &View) {
+    let cursor = view.cursor;
+    let line = state.active_buffer().line(cursor.line).unwrap_or_default();
+    let before = &line[..cursor.byte.min(line.len())];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_keyword_char(c))
+        .last()
+        .map_or(cursor.byte, |(i, _)| i);
+    let prefix = before[start..].to_string();
+    state.completion.case = CaseRules {
+        ignore: state.options.get_bool("ignorecase"),
+        smart: state.options.get_bool("smartcase"),
+        infer: state.options.get_bool("infercase"),
+    };
+    let case = state.completion.case;
+    let words = state
+        .completion
+        .words
+        .words(&state.buffers, state.active, cursor, &prefix, case);
+    tracing::trace!(target: "actions.dispatch", prefix = %prefix, words = words.len(), "completion_open");
+    let found = !words.is_empty();
+    let buffer = state.active;
+    state.completion.open(
+        buffer,
+        Position::new(cursor.line, start),
+        prefix,
+        words,
+        None,
+    );
+    if !found && !state.completion.is_attached(buffer) {
+        state.completion.close();
+        state.set_ephemeral("Pattern not found", Duration::from_secs(3));
+    }
+}
+
+/// Replace the `old_len` bytes after the completion start with `text`,
+/// leaving the cursor after it.
+fn replace(state: &mut EditorState, view: &mut View, old_len: usize, text: &str) {
+    let Some(start) = state.completion.active().map(|c| c.start) else {
+        return;
+    };
+    state.begin_insert_coalescing(view.cursor);
+    state.note_insert_edit();
+    let buffer = state.active_buffer_mut();
+    let at = buffer.line_to_byte(start.line) + start.byte;
+    buffer.delete_bytes(at, at + old_len);
+    buffer.insert_str(at, text);
+    view.cursor = Position::new(start.line, start.byte + text.len());
+    if !state.dirty() {
+        state.set_dirty(true);
+    }
+}
+
+/// After an Insert-mode edit: narrow an open completion to what is now
+/// typed after its start, or close it once the cursor left the word. A
+/// server trigger character opens a new one.
+pub(crate) fn after_edit(kind: &EditKind, state: &mut EditorState, view: &View) {
+    if !matches!(state.mode, Mode::Insert) {
+        state.completion.close();
+        return;
+    }
+    let cursor = view.cursor;
+    if let Some(completion) = state.completion.active() {
+        let start = completion.start;
+        let line = state.active_buffer().line(cursor.line).unwrap_or_default();
+        let typed = (completion.buffer == state.active
+            && cursor.line == start.line
+            && cursor.byte > start.byte)
+            .then(|| line.get(start.byte..cursor.byte))
+            .flatten()
+            .filter(|typed| {
+                // Past a trigger-opened start only keyword characters follow.
+                typed.chars().all(is_keyword_char)
+            });
+        match typed {
+            Some(typed) => {
+                state.completion.set_prefix(typed.to_string());
+                if state
+                    .completion
+                    .active()
+                    .is_some_and(|c| c.match_count() == 0)
+                    && !state.completion.is_attached(state.active)
+                {
+                    state.completion.close();
+                }
+                return;
+            }
+            None => {
+                state.completion.close();
+            }
+        }
+    }
+    if let EditKind::InsertGrapheme(g) = kind
+        && let Some(c) = g.chars().next()
+        && state.completion.is_trigger(state.active, c)
+    {
+        tracing::trace!(target: "actions.dispatch", trigger = %c, "completion_trigger");
+        let buffer = state.active;
+        state
+            .completion
+            .open(buffer, cursor, String::new(), Vec::new(), Some(c));
+    }
+}
@@ -0,0 +1,69 @@
+//! Diagnostic navigation: `]d` / `[d` and `:diag`.
+//!
+//! Both read the active buffer's set in `core_state::diagnostics`, filled
+//! by whatever produces diagnostics (a language server via `core-lsp`).
+
+use super::DispatchResult;
+use core_model::View;
+use core_state::EditorState;
+use std::time::Duration;
+
+/// `]d` (or `[d` with `backward`): move to the start of the `count`th
+/// next diagnostic, wrapping around the buffer like a search.
+pub(crate) fn jump(
+    backward: bool,
+    count: u32,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let mut at = view.cursor;
+    let mut wrapped = false;
+    for _ in 0..count.max(1) {
+        match state.diagnostics.next_start(state.active, at, !backward) {
+            Some((position, wrap)) => {
+                at = position;
+                wrapped |= wrap;
+            }
+            None => {
+                state.set_ephemeral("No diagnostics", Duration::from_secs(3));
+                return DispatchResult::dirty();
+            }
+        }
+    }
+    tracing::trace!(target: "actions.dispatch", line = at.line, byte = at.byte, wrapped, "diagnostic_jump");
+    state.set_jump_mark(view.cursor);
+    view.cursor = at;
+    if wrapped {
+        let msg = if backward {
+            "diagnostics hit TOP, continuing at BOTTOM"
+        } else {
+            "diagnostics hit BOTTOM, continuing at TOP"
+        };
+        state.set_ephemeral(msg, Duration::from_secs(3));
+    }
+    DispatchResult::dirty()
+}
+
+/// `:diag`: list the active buffer's diagnostics in the message area, one
+/// `line:col severity: message` row each (1-based, byte columns).
+pub(crate) fn list(state: &mut EditorState) -> DispatchResult {
+    let lines: Vec<String> = state
+        .diagnostics
+        .get(state.active)
+        .iter()
+        .map(|d| {
+            let severity = d.severity.name();
+            let message = d.message.lines().next().unwrap_or_default();
+            format!(
+                "{}:{} {severity}: {message}",
+                d.start.line + 1,
+                d.start.byte + 1
+            )
+        })
+        .collect();
+    match lines.len() {
+        0 => state.set_ephemeral("No diagnostics", Duration::from_secs(3)),
+        count => state.show_message_lines(lines, count),
+    }
+    DispatchResult::dirty()
+}
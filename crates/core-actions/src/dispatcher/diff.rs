@@ -0,0 +1,133 @@
+//! `:diffs[plit] {file}`, `:diffo[ff]` and `do` / `dp` (`core_state::diff`).
+//!
+//! `:diffsplit` opens the file in a window beside the current one (side by
+//! side, as Vim's `diffopt+=vertical`), compares the two buffers and sets
+//! `'scrollbind'` in both windows, lining the new one up with the old.
+//! `do` ("diff obtain") replaces the hunk at the cursor with the other
+//! buffer's lines, `dp` ("diff put") the other way round; each is one undo
+//! step in the buffer it changes.
+
+use super::DispatchResult;
+use core_config::options::OptionValue;
+use core_model::{EditorModel, SplitAxis, View};
+use core_state::EditorState;
+use core_text::Position;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub(super) fn split(path: Option<PathBuf>, model: &mut EditorModel) -> DispatchResult {
+    let Some(path) = path else {
+        model
+            .state_mut()
+            .set_ephemeral("E471: Argument required", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    let old = model.active_view();
+    let (old_buffer, old_first, old_line) =
+        (old.buffer_id, old.viewport_first_line, old.cursor.line);
+    model.set_scroll_bind(true);
+    let result = super::window::split(SplitAxis::Vertical, Some(path), model);
+    model.set_scroll_bind(true);
+    let (state, view) = model.split_state_and_active_view();
+    let _ = state.options.set("scrollbind", OptionValue::Bool(true));
+    let buffer = view.buffer_id;
+    state.diff.start(old_buffer, buffer);
+    state.diff.refresh(&state.buffers);
+    let diff = &state.diff;
+    let count = state.active_buffer().line_count();
+    view.viewport_first_line = diff.line_at_row(buffer, diff.top_row(old_buffer, old_first), count);
+    view.cursor = Position::new(
+        diff.line_at_row(buffer, diff.row_of(old_buffer, old_line), count),
+        0,
+    );
+    tracing::debug!(target: "actions.dispatch", a = old_buffer.0, b = buffer.0, hunks = diff.hunks().len(), "diff_split");
+    result
+}
+
+/// End diff mode and reset `'scrollbind'` in the windows of the compared
+/// buffers.
+pub(super) fn off(model: &mut EditorModel) -> DispatchResult {
+    let Some(buffers) = model.state().diff.buffers() else {
+        return DispatchResult::dirty();
+    };
+    let views: Vec<_> = model
+        .views()
+        .iter()
+        .filter(|v| buffers.contains(&v.buffer_id))
+        .map(|v| v.id)
+        .collect();
+    for id in views {
+        model.set_view_scroll_bind(id, false);
+    }
+    let state = model.state_mut();
+    state.diff.stop();
+    let _ = state.options.set("scrollbind", OptionValue::Bool(false));
+    DispatchResult::buffer_replaced()
+}
+
+/// `do` / `dp` (`put`).
+pub(super) fn hunk(put: bool, state: &mut EditorState, view: &mut View) -> DispatchResult {
+    let buffer = state.active;
+    let Some(other) = state.diff.other(buffer) else {
+        state.set_ephemeral(
+            "E99: Current buffer is not in diff mode",
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    };
+    state.diff.refresh(&state.buffers);
+    let Some(hunk) = state.diff.hunk_at(buffer, view.cursor.line).cloned() else {
+        return DispatchResult::clean();
+    };
+    let side = usize::from(state.diff.buffers().is_some_and(|pair| pair[1] == buffer));
+    let (this, that) = (hunk.range(side).clone(), hunk.range(1 - side).clone());
+    if put {
+        let lines = lines_of(state, buffer, this);
+        state.switch_buffer(other);
+        let mut cursor = Position::new(that.start, 0);
+        splice(state, &mut cursor, that, &lines);
+        state.switch_buffer(buffer);
+    } else {
+        let lines = lines_of(state, other, that);
+        let mut cursor = view.cursor;
+        splice(state, &mut cursor, this, &lines);
+        view.cursor = cursor;
+    }
+    state.diff.refresh(&state.buffers);
+    tracing::debug!(target: "actions.dispatch", put, a = ?hunk.a, b = ?hunk.b, "diff_hunk");
+    DispatchResult::buffer_replaced()
+}
+
+fn lines_of(state: &EditorState, buffer: core_state::BufferId, range: Range<usize>) -> Vec<String> {
+    let Some(entry) = state.buffers.get(buffer) else {
+        return Vec::new();
+    };
+    range
+        .map(|i| {
+            let line = entry.buffer.line(i).unwrap_or_default();
+            line.trim_end_matches(['\n', '\r']).to_string()
+        })
+        .collect()
+}
+
+/// Replace lines `range` of the active buffer with `lines` as one undo
+/// step, moving `cursor` to the first of them.
+fn splice(state: &mut EditorState, cursor: &mut Position, range: Range<usize>, lines: &[String]) {
+    let mut text: String = lines.iter().map(|l| format!("{l}\n")).collect();
+    if !range.is_empty() {
+        state.replace_lines_with_snapshot(cursor, range.start, range.end - 1, &text);
+        return;
+    }
+    state.push_discrete_edit_snapshot(*cursor);
+    let buffer = state.active_buffer_mut();
+    if range.start >= buffer.line_count() && text.pop().is_some() {
+        // After a last line without a newline: end it instead.
+        text.insert(0, '\n');
+    }
+    let at = buffer.line_to_byte(range.start);
+    buffer.insert_str(at, &text);
+    let last = buffer.line_count().saturating_sub(1);
+    *cursor = Position::new(range.start.min(last), 0);
+    state.set_dirty(true);
+}
@@ -1,4 +1,4 @@
-//! Text edit action handling (insert/backspace/delete/newline).
+//! Text edit action handling (insert/paste/backspace/delete/newline).
 //!
 //! Scope (R3 Step 1):
 //! * Behavior-neutral extraction from monolithic dispatcher.
@@ -24,7 +24,9 @@
 use super::DispatchResult;
 use crate::EditKind;
 use core_model::View;
-use core_state::{EditorState, Mode};
+use core_state::completion::is_keyword_char;
+use core_state::{EditorState, Mode, RegisterKind};
+use core_text::grapheme;
 
 pub(crate) fn handle_edit(
     kind: EditKind,
@@ -34,6 +36,9 @@ pub(crate) fn handle_edit(
     match kind {
         EditKind::InsertGrapheme(g) => {
             if matches!(state.mode, Mode::Insert) {
+                if !g.starts_with(is_keyword_char) {
+                    super::abbrev::expand(state, view);
+                }
                 let before = view.cursor;
                 state.begin_insert_coalescing(view.cursor);
                 state.note_insert_edit();
@@ -41,16 +46,69 @@ pub(crate) fn handle_edit(
                 state.active_buffer_mut().insert_grapheme(&mut pos, &g);
                 view.cursor = pos;
                 tracing::trace!(target: "actions.dispatch", op="insert_grapheme", grapheme=%g, line=before.line, byte=before.byte, to_line=view.cursor.line, to_byte=view.cursor.byte, "edit");
-                if !state.dirty {
-                    state.dirty = true;
+                if !state.dirty() {
+                    state.set_dirty(true);
                 }
                 DispatchResult::dirty()
             } else {
                 DispatchResult::clean()
             }
         }
+        EditKind::InsertText(text) => {
+            if !matches!(state.mode, Mode::Insert) || text.is_empty() {
+                return DispatchResult::clean();
+            }
+            let before = view.cursor;
+            // A run of its own: the text undoes in one step, apart from
+            // what was typed before or after it.
+            state.end_insert_coalescing();
+            state.begin_insert_coalescing(before);
+            state.note_insert_edit();
+            let buffer = state.active_buffer_mut();
+            let at = buffer.line_to_byte(before.line) + before.byte;
+            buffer.insert_str(at, &text);
+            state.end_insert_coalescing();
+            let lines = text.matches('\n').count();
+            view.cursor = match text.rfind('\n') {
+                Some(last) => core_text::Position::new(before.line + lines, text.len() - last - 1),
+                None => core_text::Position::new(before.line, before.byte + text.len()),
+            };
+            tracing::trace!(target: "actions.dispatch", op="insert_text", bytes=text.len(), lines, line=before.line, byte=before.byte, to_line=view.cursor.line, to_byte=view.cursor.byte, "edit");
+            if !state.dirty() {
+                state.set_dirty(true);
+            }
+            // New lines shift everything below, so the rows from the cursor
+            // line down all change.
+            if lines > 0 {
+                DispatchResult::buffer_replaced()
+            } else {
+                DispatchResult::dirty()
+            }
+        }
+        EditKind::InsertTab => {
+            if !matches!(state.mode, Mode::Insert) {
+                return DispatchResult::clean();
+            }
+            super::abbrev::expand(state, view);
+            let before = view.cursor;
+            state.begin_insert_coalescing(before);
+            state.note_insert_edit();
+            let (start, text) = tab_text(state, view);
+            let buffer = state.active_buffer_mut();
+            let at = buffer.line_to_byte(before.line);
+            buffer.delete_bytes(at + start, at + before.byte);
+            let mut pos = core_text::Position::new(before.line, start);
+            buffer.insert_grapheme(&mut pos, &text);
+            view.cursor = pos;
+            tracing::trace!(target: "actions.dispatch", op="insert_tab", text=?text, line=before.line, byte=before.byte, to_line=view.cursor.line, to_byte=view.cursor.byte, "edit");
+            if !state.dirty() {
+                state.set_dirty(true);
+            }
+            DispatchResult::dirty()
+        }
         EditKind::InsertNewline => {
             if matches!(state.mode, Mode::Insert) {
+                super::abbrev::expand(state, view);
                 let before = view.cursor;
                 let before_line_count = state.active_buffer().line_count();
                 state.begin_insert_coalescing(view.cursor);
@@ -66,8 +124,8 @@ pub(crate) fn handle_edit(
                 let structural =
                     after_line_count > before_line_count || view.cursor.line > before.line;
                 tracing::trace!(target: "actions.dispatch", op="insert_newline", line=before.line, byte=before.byte, to_line=view.cursor.line, to_byte=view.cursor.byte, structural, "edit");
-                if !state.dirty {
-                    state.dirty = true;
+                if !state.dirty() {
+                    state.set_dirty(true);
                 }
                 if structural {
                     DispatchResult::buffer_replaced()
@@ -84,17 +142,67 @@ pub(crate) fn handle_edit(
                 state.begin_insert_coalescing(view.cursor);
                 state.note_insert_edit();
                 let mut pos = view.cursor;
-                state.active_buffer_mut().delete_grapheme_before(&mut pos);
+                let soft_start = soft_tab_start(state, view);
+                let buffer = state.active_buffer_mut();
+                match soft_start {
+                    Some(start) => {
+                        let at = buffer.line_to_byte(pos.line);
+                        buffer.delete_bytes(at + start, at + pos.byte);
+                        pos.byte = start;
+                    }
+                    None => buffer.delete_grapheme_before(&mut pos),
+                }
                 view.cursor = pos;
                 tracing::trace!(target: "actions.dispatch", op="backspace", line=before.line, byte=before.byte, to_line=view.cursor.line, to_byte=view.cursor.byte, "edit");
-                if !state.dirty {
-                    state.dirty = true;
+                if !state.dirty() {
+                    state.set_dirty(true);
                 }
                 DispatchResult::dirty()
             } else {
                 DispatchResult::clean()
             }
         }
+        EditKind::DeleteWordBefore | EditKind::DeleteToLineStart => {
+            if !matches!(state.mode, Mode::Insert) {
+                return DispatchResult::clean();
+            }
+            let before = view.cursor;
+            // Part of the running insert: one `u` undoes it with the typing.
+            state.begin_insert_coalescing(before);
+            state.note_insert_edit();
+            let buffer = state.active_buffer_mut();
+            let mut pos = before;
+            let joined = pos.byte == 0;
+            if joined {
+                // Like Backspace, column 0 joins with the line above.
+                buffer.delete_grapheme_before(&mut pos);
+            } else {
+                let line = buffer.line(pos.line).unwrap_or_default();
+                let content = line.trim_end_matches(['\n', '\r']);
+                let start = if matches!(kind, EditKind::DeleteWordBefore) {
+                    core_text::motion::word_start_before(content, pos.byte)
+                } else {
+                    let indent = content.len() - content.trim_start().len();
+                    if pos.byte > indent { indent } else { 0 }
+                };
+                let at = buffer.line_to_byte(pos.line);
+                buffer.delete_bytes(at + start, at + pos.byte);
+                pos.byte = start;
+            }
+            view.cursor = pos;
+            tracing::trace!(target: "actions.dispatch", op="delete_before", kind=?kind, line=before.line, byte=before.byte, to_line=view.cursor.line, to_byte=view.cursor.byte, "edit");
+            if view.cursor == before {
+                return DispatchResult::clean();
+            }
+            if !state.dirty() {
+                state.set_dirty(true);
+            }
+            if joined {
+                DispatchResult::buffer_replaced()
+            } else {
+                DispatchResult::dirty()
+            }
+        }
         EditKind::DeleteUnder { count, register } => {
             if !matches!(state.mode, Mode::Normal) {
                 return DispatchResult::clean();
@@ -122,11 +230,11 @@ pub(crate) fn handle_edit(
             if any {
                 if !removed.is_empty() {
                     let mut regs = state.registers_facade();
-                    regs.write_delete(removed.clone(), target_register);
+                    regs.write_delete(removed.clone(), RegisterKind::Charwise, target_register);
                 }
                 tracing::trace!(target: "actions.dispatch", op="delete_under", count=repeat, structural, "edit");
-                if !state.dirty {
-                    state.dirty = true;
+                if !state.dirty() {
+                    state.set_dirty(true);
                 }
                 if structural {
                     DispatchResult::buffer_replaced()
@@ -166,11 +274,11 @@ pub(crate) fn handle_edit(
                 }
                 if !removed.is_empty() {
                     let mut regs = state.registers_facade();
-                    regs.write_delete(removed, target_register);
+                    regs.write_delete(removed, RegisterKind::Charwise, target_register);
                 }
                 tracing::trace!(target: "actions.dispatch", op="delete_left", count=repeat, "edit");
-                if !state.dirty {
-                    state.dirty = true;
+                if !state.dirty() {
+                    state.set_dirty(true);
                 }
                 DispatchResult::dirty()
             } else {
@@ -180,6 +288,76 @@ pub(crate) fn handle_edit(
     }
 }
 
+/// `'softtabstop'` in effect: negative means `'shiftwidth'`, itself
+/// `'tabstop'` when zero.
+fn soft_tabstop(state: &EditorState) -> usize {
+    match state.options.get_number("softtabstop") {
+        n if n < 0 => match state.options.get_number("shiftwidth") {
+            sw if sw > 0 => sw as usize,
+            _ => grapheme::tabstop(),
+        },
+        n => n as usize,
+    }
+}
+
+/// What `<Tab>` puts at the cursor, and the byte it replaces from. Without
+/// `'softtabstop'` and `'expandtab'` that is a tab. Otherwise the cursor
+/// moves to the next soft (or, with only `'expandtab'`, real) tab stop:
+/// with `'expandtab'` in spaces, else the blanks before the cursor are
+/// redone as tabs where they reach a tab stop and spaces for the rest.
+fn tab_text(state: &EditorState, view: &View) -> (usize, String) {
+    let cursor = view.cursor;
+    let expand = state.options.get_bool("expandtab");
+    let soft = soft_tabstop(state);
+    if soft == 0 && !expand {
+        return (cursor.byte, "\t".into());
+    }
+    let ts = grapheme::tabstop();
+    let step = if soft > 0 { soft } else { ts };
+    let line = state.active_buffer().line(cursor.line).unwrap_or_default();
+    let col = grapheme::visual_col(&line, cursor.byte);
+    let target = (col / step + 1) * step;
+    if expand {
+        return (cursor.byte, " ".repeat(target - col));
+    }
+    let start = line[..cursor.byte].trim_end_matches([' ', '\t']).len();
+    let mut col = grapheme::visual_col(&line, start);
+    let mut text = String::new();
+    while col < target {
+        let stop = (col / ts + 1) * ts;
+        if stop > target {
+            text.push_str(&" ".repeat(target - col));
+            break;
+        }
+        text.push('\t');
+        col = stop;
+    }
+    (start, text)
+}
+
+/// Where Backspace deletes from under `'softtabstop'`: back over the spaces
+/// before the cursor to the previous soft tab stop, `None` when no space
+/// precedes the cursor or the option is off.
+fn soft_tab_start(state: &EditorState, view: &View) -> Option<usize> {
+    let soft = soft_tabstop(state);
+    let cursor = view.cursor;
+    if soft == 0 || cursor.byte == 0 {
+        return None;
+    }
+    let line = state.active_buffer().line(cursor.line)?;
+    if !line[..cursor.byte].ends_with(' ') {
+        return None;
+    }
+    let mut col = grapheme::visual_col(&line, cursor.byte);
+    let stop = (col - 1) / soft * soft;
+    let mut start = cursor.byte;
+    while col > stop && line[..start].ends_with(' ') {
+        start -= 1;
+        col -= 1;
+    }
+    Some(start)
+}
+
 fn has_grapheme_under(state: &EditorState, view: &View) -> bool {
     let cursor = view.cursor;
     let buffer = state.active_buffer();
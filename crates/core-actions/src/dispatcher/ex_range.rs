@@ -0,0 +1,225 @@
+//! Ex line ranges (`:{range}cmd`).
+//!
+//! Grammar (subset of Vim): `%`, or one or two addresses separated by `,`
+//! (or `;`, treated like `,`). An address is `.`, `$`, a line number, or a
+//! visual mark (`'<` / `'>`), optionally followed by `+N` / `-N` offsets
+//! (a bare `+`/`-` means 1). Parsing is pure; resolution against the buffer
+//! happens at execution time so the parser stays side-effect free.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressBase {
+    Current,
+    Last,
+    Line(usize), // 1-based as typed
+    VisualStart,
+    VisualEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub base: AddressBase,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeSpec {
+    /// `%`
+    Whole,
+    Single(Address),
+    Pair(Address, Address),
+}
+
+/// Resolved 0-based inclusive line range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Buffer facts needed to resolve addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeContext {
+    pub cursor_line: usize,
+    pub last_line: usize,
+    /// Visual marks as 0-based lines (`'<`, `'>`).
+    pub visual: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeError {
+    Invalid,
+    MarkNotSet,
+}
+
+impl std::fmt::Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RangeError::Invalid => write!(f, "E16: Invalid range"),
+            RangeError::MarkNotSet => write!(f, "E20: Mark not set"),
+        }
+    }
+}
+
+/// Split a leading range from a command body. Returns the range (if any)
+/// and the remaining command text.
+pub fn split_range(body: &str) -> (Option<RangeSpec>, &str) {
+    if let Some(rest) = body.strip_prefix('%') {
+        return (Some(RangeSpec::Whole), rest);
+    }
+    let Some((first, rest)) = parse_address(body) else {
+        return (None, body);
+    };
+    if let Some(after_sep) = rest.strip_prefix([',', ';'])
+        && let Some((second, rest)) = parse_address(after_sep)
+    {
+        return (Some(RangeSpec::Pair(first, second)), rest);
+    }
+    (Some(RangeSpec::Single(first)), rest)
+}
+
+fn parse_address(s: &str) -> Option<(Address, &str)> {
+    let (base, mut rest) = if let Some(r) = s.strip_prefix('.') {
+        (AddressBase::Current, r)
+    } else if let Some(r) = s.strip_prefix('$') {
+        (AddressBase::Last, r)
+    } else if let Some(r) = s.strip_prefix("'<") {
+        (AddressBase::VisualStart, r)
+    } else if let Some(r) = s.strip_prefix("'>") {
+        (AddressBase::VisualEnd, r)
+    } else if s.starts_with(|c: char| c.is_ascii_digit()) {
+        let (n, r) = take_number(s);
+        (AddressBase::Line(n), r)
+    } else if s.starts_with(['+', '-']) {
+        // Offset relative to the current line (`:+2`).
+        (AddressBase::Current, s)
+    } else {
+        return None;
+    };
+    let mut offset = 0i64;
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let after = &rest[1..];
+        let (n, r) = if after.starts_with(|c: char| c.is_ascii_digit()) {
+            take_number(after)
+        } else {
+            (1, after)
+        };
+        if sign == '+' {
+            offset += n as i64;
+        } else {
+            offset -= n as i64;
+        }
+        rest = r;
+    }
+    Some((Address { base, offset }, rest))
+}
+
+fn take_number(s: &str) -> (usize, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    (s[..end].parse().unwrap_or(usize::MAX), &s[end..])
+}
+
+impl Address {
+    fn resolve(&self, ctx: &RangeContext) -> Result<usize, RangeError> {
+        let base: i64 = match self.base {
+            AddressBase::Current => ctx.cursor_line as i64,
+            AddressBase::Last => ctx.last_line as i64,
+            // Line 0 is accepted as "before the first line" and clamps to 0.
+            AddressBase::Line(n) => (n as i64 - 1).max(0),
+            AddressBase::VisualStart => ctx.visual.ok_or(RangeError::MarkNotSet)?.0 as i64,
+            AddressBase::VisualEnd => ctx.visual.ok_or(RangeError::MarkNotSet)?.1 as i64,
+        };
+        let line = base + self.offset;
+        if line < 0 || line > ctx.last_line as i64 {
+            return Err(RangeError::Invalid);
+        }
+        Ok(line as usize)
+    }
+}
+
+impl RangeSpec {
+    pub fn resolve(&self, ctx: &RangeContext) -> Result<LineRange, RangeError> {
+        match self {
+            RangeSpec::Whole => Ok(LineRange {
+                start: 0,
+                end: ctx.last_line,
+            }),
+            RangeSpec::Single(a) => {
+                let l = a.resolve(ctx)?;
+                Ok(LineRange { start: l, end: l })
+            }
+            RangeSpec::Pair(a, b) => {
+                let (x, y) = (a.resolve(ctx)?, b.resolve(ctx)?);
+                // Vim asks "Backwards range given, OK to swap"; we swap silently.
+                Ok(LineRange {
+                    start: x.min(y),
+                    end: x.max(y),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> RangeContext {
+        RangeContext {
+            cursor_line: 4,
+            last_line: 9,
+            visual: Some((2, 3)),
+        }
+    }
+
+    #[test]
+    fn whole_and_numeric_ranges() {
+        let (r, rest) = split_range("%!sort");
+        assert_eq!(rest, "!sort");
+        assert_eq!(
+            r.unwrap().resolve(&ctx()),
+            Ok(LineRange { start: 0, end: 9 })
+        );
+        let (r, rest) = split_range("2,4!tr a b");
+        assert_eq!(rest, "!tr a b");
+        assert_eq!(
+            r.unwrap().resolve(&ctx()),
+            Ok(LineRange { start: 1, end: 3 })
+        );
+    }
+
+    #[test]
+    fn relative_and_mark_addresses() {
+        let (r, _) = split_range(".,.+2sort");
+        assert_eq!(
+            r.unwrap().resolve(&ctx()),
+            Ok(LineRange { start: 4, end: 6 })
+        );
+        let (r, _) = split_range("'<,'>sort");
+        assert_eq!(
+            r.unwrap().resolve(&ctx()),
+            Ok(LineRange { start: 2, end: 3 })
+        );
+        let (r, _) = split_range("$-1");
+        assert_eq!(
+            r.unwrap().resolve(&ctx()),
+            Ok(LineRange { start: 8, end: 8 })
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_is_invalid() {
+        let (r, _) = split_range("5,20d");
+        assert_eq!(r.unwrap().resolve(&ctx()), Err(RangeError::Invalid));
+        let no_marks = RangeContext {
+            visual: None,
+            ..ctx()
+        };
+        let (r, _) = split_range("'<");
+        assert_eq!(r.unwrap().resolve(&no_marks), Err(RangeError::MarkNotSet));
+    }
+
+    #[test]
+    fn no_range_leaves_body_untouched() {
+        assert_eq!(split_range("write"), (None, "write"));
+    }
+}
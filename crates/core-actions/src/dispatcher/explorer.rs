@@ -0,0 +1,240 @@
+//! Directory listings (`core_state::explorer`): `:E[xplore]`, `<CR>` on an
+//! entry, the listing keys and the `:mkdir` / `:rename` / `:remove`
+//! commands they prompt with.
+//!
+//! A listing lives in the buffer that showed the directory and is replaced
+//! in place, the way `:edit` replaces a file: `<CR>` on a directory (or
+//! `../`, or `-`) lists that one instead, `<CR>` on a file loads it. Keys
+//! that need a name open the command line on the command that does the
+//! work, as `gl` does for `:align`, so the name is typed and confirmed with
+//! `<CR>` (`D` fills in the name to delete and waits for that `<CR>`).
+//! The commands only exist in a listing and act on its directory.
+
+use super::DispatchResult;
+use crate::ExplorerCommand;
+use core_model::View;
+use core_state::{EXPLORER_HEADER_LINES, EditorState, Explorer};
+use core_text::Position;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// `:E[xplore] [dir]`: list `dir`, or the directory of the current file
+/// (the working directory without one).
+pub(super) fn explore(
+    path: Option<PathBuf>,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if state.dirty() {
+        state.set_ephemeral(
+            "E37: No write since last change (add ! to override)",
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    let dir = path
+        .or_else(|| state.explorer().map(|e| e.dir.clone()))
+        .or_else(|| {
+            state
+                .file_name()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf)
+        })
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("."));
+    open(&dir, None, state, view)
+}
+
+/// List `dir` in the active buffer with the cursor on `select` (the first
+/// entry without it), keeping the order of a listing already shown there.
+pub(super) fn open(
+    dir: &Path,
+    select: Option<&str>,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let read = match state.explorer().cloned() {
+        Some(mut explorer) => explorer.read_dir(dir).map(|()| explorer),
+        None => Explorer::read(dir),
+    };
+    let explorer = match read {
+        Ok(explorer) => explorer,
+        Err(e) => {
+            tracing::error!(target: "io", ?e, dir = %dir.display(), "explorer_read_error");
+            state.set_ephemeral(
+                format!("E484: Can't open file {}", dir.display()),
+                Duration::from_secs(3),
+            );
+            return DispatchResult::dirty();
+        }
+    };
+    let line = select
+        .and_then(|name| explorer.line_of(name))
+        .unwrap_or(EXPLORER_HEADER_LINES);
+    state.load_directory(explorer);
+    let last = state.active_buffer().line_count().saturating_sub(1);
+    view.cursor = Position::new(line.min(last), 0);
+    view.viewport_first_line = 0;
+    DispatchResult::buffer_replaced()
+}
+
+/// `<CR>`: open the directory or file under the cursor.
+pub(super) fn enter(state: &mut EditorState, view: &mut View) -> DispatchResult {
+    let Some(explorer) = state.explorer() else {
+        return DispatchResult::clean();
+    };
+    let Some(target) = explorer.target_at(view.cursor.line) else {
+        return DispatchResult::clean();
+    };
+    if !target.is_dir() {
+        return super::command::load_file(&target, state, view);
+    }
+    // Going up lands on the directory just left.
+    let left = (view.cursor.line == EXPLORER_HEADER_LINES - 1)
+        .then(|| {
+            explorer
+                .dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+        })
+        .flatten();
+    open(&target, left.as_deref(), state, view)
+}
+
+pub(super) fn command(
+    cmd: ExplorerCommand,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let Some(explorer) = state.explorer() else {
+        return DispatchResult::clean();
+    };
+    let dir = explorer.dir.clone();
+    let entry = explorer.entry_at(view.cursor.line).map(|e| e.name.clone());
+    match cmd {
+        ExplorerCommand::Parent => {
+            let Some(parent) = dir.parent() else {
+                return DispatchResult::clean();
+            };
+            let left = dir.file_name().map(|n| n.to_string_lossy().into_owned());
+            open(parent, left.as_deref(), state, view)
+        }
+        ExplorerCommand::NewFile => prompt(&format!("edit {}", dir.join("").display()), state),
+        ExplorerCommand::Mkdir => prompt("mkdir ", state),
+        ExplorerCommand::Delete | ExplorerCommand::Rename => match entry {
+            Some(name) if cmd == ExplorerCommand::Delete => {
+                prompt(&format!("remove {name}"), state)
+            }
+            Some(name) => prompt(&format!("rename {name}"), state),
+            None => DispatchResult::clean(),
+        },
+        ExplorerCommand::Sort | ExplorerCommand::Reverse | ExplorerCommand::ToggleHidden => {
+            let Some(explorer) = state.active_meta_mut().explorer.as_mut() else {
+                return DispatchResult::clean();
+            };
+            match cmd {
+                ExplorerCommand::Sort => explorer.sort = explorer.sort.next(),
+                ExplorerCommand::Reverse => explorer.reverse = !explorer.reverse,
+                _ => explorer.show_hidden = !explorer.show_hidden,
+            }
+            tracing::debug!(target: "actions.dispatch", sort = ?explorer.sort, reverse = explorer.reverse, hidden = explorer.show_hidden, "explorer_order");
+            state.refresh_explorer(false);
+            reselect(entry.as_deref(), view.cursor.line, state, view);
+            DispatchResult::buffer_replaced()
+        }
+    }
+}
+
+/// Open the command line on `:{text}`.
+fn prompt(text: &str, state: &mut EditorState) -> DispatchResult {
+    state.command_line.begin();
+    for ch in text.chars() {
+        state.command_line.push_char(ch);
+    }
+    DispatchResult::dirty()
+}
+
+/// Put the cursor on entry `name` of the redrawn listing, or back on
+/// `line` when it is gone.
+fn reselect(name: Option<&str>, line: usize, state: &mut EditorState, view: &mut View) {
+    let found = name.and_then(|name| state.explorer()?.line_of(name));
+    let last = state.active_buffer().line_count().saturating_sub(1);
+    view.cursor = Position::new(found.unwrap_or(line).min(last), 0);
+}
+
+/// Which of the listing commands runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FileOp {
+    Mkdir,
+    Rename,
+    Remove,
+}
+
+/// `:mkdir {name}`, `:rename {name}` (the entry under the cursor) and
+/// `:remove [name]` (the entry under the cursor without one; directories
+/// must be empty), relative to the listed directory.
+pub(super) fn file_op(
+    op: FileOp,
+    name: &str,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let Some(explorer) = state.explorer() else {
+        let command = match op {
+            FileOp::Mkdir => "mkdir",
+            FileOp::Rename => "rename",
+            FileOp::Remove => "remove",
+        };
+        let msg = format!("E492: Not an editor command: {command} {name}");
+        state.set_ephemeral(msg.trim_end(), Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    let dir = explorer.dir.clone();
+    let entry = explorer.entry_at(view.cursor.line).map(|e| e.name.clone());
+    let name = name.trim();
+    let (result, select) = match op {
+        FileOp::Mkdir if name.is_empty() => (Err("E471: Argument required".to_string()), None),
+        FileOp::Mkdir => (
+            std::fs::create_dir_all(dir.join(name))
+                .map_err(|_| format!("E739: Cannot create directory: {name}")),
+            Some(name),
+        ),
+        FileOp::Rename => match entry.as_deref() {
+            None => (Err("E474: Invalid argument".to_string()), None),
+            Some(_) if name.is_empty() => (Err("E471: Argument required".to_string()), None),
+            Some(old) => (
+                std::fs::rename(dir.join(old), dir.join(name))
+                    .map_err(|e| format!("Cannot rename {old}: {e}")),
+                Some(name),
+            ),
+        },
+        FileOp::Remove => match (name, entry.as_deref()) {
+            ("", None) => (Err("E474: Invalid argument".to_string()), None),
+            ("", Some(entry)) | (entry, _) => {
+                let path = dir.join(entry);
+                let removed = if path.is_dir() && !path.is_symlink() {
+                    std::fs::remove_dir(&path)
+                } else {
+                    std::fs::remove_file(&path)
+                };
+                (
+                    removed.map_err(|e| format!("Cannot delete {entry}: {e}")),
+                    None,
+                )
+            }
+        },
+    };
+    tracing::debug!(target: "actions.dispatch", ?op, name, ok = result.is_ok(), "explorer_file_op");
+    if let Err(msg) = result {
+        state.set_ephemeral(msg, Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let line = view.cursor.line;
+    state.refresh_explorer(true);
+    // A new `a/b` selects `a`, the entry the listing shows.
+    let select = select
+        .and_then(|s| Path::new(s).components().next())
+        .map(|c| c.as_os_str().to_string_lossy().into_owned());
+    reselect(select.as_deref(), line, state, view);
+    DispatchResult::buffer_replaced()
+}
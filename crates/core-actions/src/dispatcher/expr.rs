@@ -0,0 +1,233 @@
+//! Expression evaluation for the `"=` register prompt.
+//!
+//! A deliberately small subset of Vim script expressions, enough for
+//! `"=` pastes: integer arithmetic and string literals.
+//!
+//! * Numbers: decimal integers (`42`); arithmetic is 64-bit and checked.
+//! * Strings: `"..."` with `\n`, `\t`, `\"`, `\\` escapes, or `'...'` with
+//!   `''` for a literal quote.
+//! * Operators by precedence: unary `-` / `+`; `*` `/` `%`; `+` `-` `.`
+//!   (string concatenation), left to right. Parentheses group.
+//! * Like Vim, strings used in arithmetic convert to the number at their
+//!   start (`"12ab" + 1` is 13, `"ab"` is 0); numbers concatenate as text.
+//!
+//! `command.rs` stores the result in the expression register and performs
+//! the pending paste.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Number(i64),
+    Str(String),
+}
+
+impl Value {
+    fn number(&self) -> i64 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Str(s) => {
+                let s = s.trim_start();
+                let (sign, digits) = match s.strip_prefix('-') {
+                    Some(rest) => (-1, rest),
+                    None => (1, s),
+                };
+                let end = digits
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(digits.len());
+                digits[..end].parse::<i64>().map_or(0, |n| sign * n)
+            }
+        }
+    }
+
+    fn into_string(self) -> String {
+        match self {
+            Value::Number(n) => n.to_string(),
+            Value::Str(s) => s,
+        }
+    }
+}
+
+/// Evaluate `src`, returning the resulting text.
+pub fn evaluate(src: &str) -> Result<String, String> {
+    let mut parser = Parser {
+        src,
+        chars: src.char_indices().peekable(),
+    };
+    let value = parser.sum()?;
+    parser.skip_ws();
+    if parser.chars.peek().is_some() {
+        return Err(parser.invalid());
+    }
+    Ok(value.into_string())
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn invalid(&self) -> String {
+        format!("E15: Invalid expression: \"{}\"", self.src)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        self.skip_ws();
+        self.chars.next_if(|(_, c)| *c == ch).is_some()
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut lhs = self.term()?;
+        loop {
+            if self.eat('.') {
+                let rhs = self.term()?;
+                lhs = Value::Str(lhs.into_string() + &rhs.into_string());
+                continue;
+            }
+            let op = if self.eat('+') {
+                i64::checked_add
+            } else if self.eat('-') {
+                i64::checked_sub
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.term()?;
+            lhs = self.arith(op, &lhs, &rhs)?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                i64::checked_mul
+            } else if self.eat('/') {
+                i64::checked_div
+            } else if self.eat('%') {
+                i64::checked_rem
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.unary()?;
+            lhs = self.arith(op, &lhs, &rhs)?;
+        }
+    }
+
+    fn arith(
+        &self,
+        op: fn(i64, i64) -> Option<i64>,
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Value, String> {
+        op(lhs.number(), rhs.number())
+            .map(Value::Number)
+            .ok_or_else(|| self.invalid())
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.eat('-') {
+            let v = self.unary()?.number();
+            return v
+                .checked_neg()
+                .map(Value::Number)
+                .ok_or_else(|| self.invalid());
+        }
+        if self.eat('+') {
+            return Ok(Value::Number(self.unary()?.number()));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.chars.next() {
+            Some((_, '(')) => {
+                let v = self.sum()?;
+                if self.eat(')') {
+                    Ok(v)
+                } else {
+                    Err(self.invalid())
+                }
+            }
+            Some((start, c)) if c.is_ascii_digit() => {
+                let mut end = start + 1;
+                while let Some((i, _)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    end = i + 1;
+                }
+                self.src[start..end]
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| self.invalid())
+            }
+            Some((_, '"')) => self.double_quoted(),
+            Some((_, '\'')) => self.single_quoted(),
+            _ => Err(self.invalid()),
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<Value, String> {
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(Value::Str(out)),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, c)) => out.push(c),
+                    None => return Err(self.invalid()),
+                },
+                Some((_, c)) => out.push(c),
+                None => return Err(self.invalid()),
+            }
+        }
+    }
+
+    fn single_quoted(&mut self) -> Result<Value, String> {
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '\'')) => {
+                    if self.chars.next_if(|(_, c)| *c == '\'').is_some() {
+                        out.push('\'');
+                    } else {
+                        return Ok(Value::Str(out));
+                    }
+                }
+                Some((_, c)) => out.push(c),
+                None => return Err(self.invalid()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_precedence_and_grouping() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(evaluate("-7 / 2").unwrap(), "-3");
+        assert_eq!(evaluate("17 % 5 - -1").unwrap(), "3");
+    }
+
+    #[test]
+    fn strings_and_concatenation() {
+        assert_eq!(evaluate(r#""a\tb" . 'it''s'"#).unwrap(), "a\tbit's");
+        assert_eq!(evaluate("'n=' . 6 * 7").unwrap(), "n=42");
+        assert_eq!(evaluate("1 + 2 . 3").unwrap(), "33");
+        assert_eq!(evaluate("\"12ab\" + 1").unwrap(), "13");
+    }
+
+    #[test]
+    fn errors_are_e15() {
+        for bad in ["", "1 +", "(2", "1 / 0", "'open", "2 3"] {
+            let err = evaluate(bad).unwrap_err();
+            assert!(err.starts_with("E15:"), "{bad}: {err}");
+        }
+    }
+}
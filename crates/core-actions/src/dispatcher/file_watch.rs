@@ -0,0 +1,113 @@
+//! Files changed on disk (`core_state::file_watch`): what a
+//! `core_events::FileWatchSource` report does to the buffers showing them.
+//!
+//! A listing is read again. A file the editor did not write itself is read
+//! again into its buffer when `'autoread'` is set and the buffer has no
+//! changes; otherwise the status line warns (`W11`, or `W12` when the
+//! buffer was changed too) and `:e!` loads the new text. A deleted file is
+//! reported with `E211`.
+
+use super::DispatchResult;
+use crate::io_ops::{OpenFileResult, open_file};
+use core_model::EditorModel;
+use core_state::BufferId;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long the warnings stay on the status line.
+const WARNING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// React to `path` having changed on disk.
+pub fn apply_file_change(path: &Path, model: &mut EditorModel) -> DispatchResult {
+    let state = model.state_mut();
+    let buffers: Vec<(BufferId, bool, bool)> = state
+        .buffers
+        .iter()
+        .filter(|entry| entry.meta.path.as_deref() == Some(path))
+        .map(|entry| (entry.id(), entry.meta.explorer.is_some(), entry.meta.dirty))
+        .collect();
+    let mut reloaded = Vec::new();
+    if buffers.iter().any(|&(_, listing, _)| listing) {
+        for &(id, _, _) in buffers.iter().filter(|(_, listing, _)| *listing) {
+            if state.reload_listing(id) {
+                reloaded.push(id);
+            }
+        }
+    } else {
+        if buffers.is_empty() || !state.file_watch.changed(path) {
+            return DispatchResult::clean();
+        }
+        let name = path.display();
+        let modified = buffers.iter().any(|&(_, _, dirty)| dirty);
+        tracing::debug!(target: "actions.watch", path = %name, modified, "file_changed_on_disk");
+        let msg = if !path.exists() {
+            Some(format!("E211: File \"{name}\" no longer available"))
+        } else if modified {
+            Some(format!(
+                "W12: Warning: File \"{name}\" has changed and the buffer was changed as well"
+            ))
+        } else if state.options.get_bool("autoread") {
+            for &(id, _, _) in &buffers {
+                match open_file(path) {
+                    OpenFileResult::Success(file) if file.binary.is_none() => {
+                        let (ending, newline) =
+                            (file.original_line_ending, file.had_trailing_newline);
+                        if state.reload_buffer(id, file.buffer, ending, newline) {
+                            reloaded.push(id);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (reloaded.len() < buffers.len()).then(|| w11(path))
+        } else {
+            Some(w11(path))
+        };
+        if let Some(msg) = msg {
+            state.set_ephemeral(msg, WARNING_TIMEOUT);
+        }
+    }
+    if reloaded.is_empty() {
+        return DispatchResult::dirty();
+    }
+    clamp_cursors(model, &reloaded);
+    match model
+        .views()
+        .iter()
+        .any(|v| reloaded.contains(&v.buffer_id))
+    {
+        true => DispatchResult::buffer_replaced(),
+        false => DispatchResult::dirty(),
+    }
+}
+
+fn w11(path: &Path) -> String {
+    format!(
+        "W11: Warning: File \"{}\" has changed since editing started (:e! to load it)",
+        path.display()
+    )
+}
+
+/// Keep the cursors of the windows on `buffers` inside their new text.
+fn clamp_cursors(model: &mut EditorModel, buffers: &[BufferId]) {
+    let views: Vec<_> = model
+        .views()
+        .iter()
+        .filter(|v| buffers.contains(&v.buffer_id))
+        .map(|v| (v.id, v.buffer_id, v.cursor.line))
+        .collect();
+    for (view, buffer, line) in views {
+        let Some(text) = model.state().buffers.get(buffer).map(|e| &e.buffer) else {
+            continue;
+        };
+        // Not onto the empty line after a final newline.
+        let mut lines = text.line_count();
+        if lines > 1 && text.line_byte_len(lines - 1) == 0 {
+            lines -= 1;
+        }
+        let last = text.line_byte_len(line.min(lines.saturating_sub(1)));
+        if let Some(view) = model.view_mut(view) {
+            view.cursor.clamp_to(lines, |_| last.saturating_sub(1));
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Manual folds: `zf{motion}`, `{Visual}zf`, `zo`, `zc` and `za`.
+//!
+//! Folds live on the active view (`core_model::fold`); this module applies
+//! the commands and reports Vim's E490. A cursor left inside a closed fold
+//! moves to the fold's first line, where the summary row is drawn. Layout
+//! changes reach the renderer through its row comparison, so every command
+//! only reports `dirty`.
+
+use super::DispatchResult;
+use crate::FoldCommand;
+use core_model::View;
+use core_state::EditorState;
+use core_text::Position;
+use std::time::Duration;
+
+/// `zf`: create a closed fold over lines `first..=last`.
+pub(crate) fn create(
+    first: usize,
+    last: usize,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let max = state.active_buffer().line_count().saturating_sub(1);
+    let lines = view.folds.create(first.min(max), last.min(max));
+    tracing::debug!(target: "actions.dispatch", first = lines.start(), last = lines.end(), "fold_create");
+    rest_on_fold(view);
+    DispatchResult::dirty()
+}
+
+/// `zo` / `zc` / `za` on the fold at the cursor line.
+pub(crate) fn command(
+    cmd: FoldCommand,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    let line = view.cursor.line;
+    let result = match cmd {
+        FoldCommand::Open => view.folds.open(line),
+        FoldCommand::Close => view.folds.close(line),
+        FoldCommand::Toggle => view.folds.toggle(line),
+    };
+    if let Err(e) = result {
+        state.set_ephemeral(e.to_string(), Duration::from_secs(3));
+    }
+    rest_on_fold(view);
+    DispatchResult::dirty()
+}
+
+fn rest_on_fold(view: &mut View) {
+    if let Some(fold) = view.folds.closed_at(view.cursor.line)
+        && *fold.start() != view.cursor.line
+    {
+        view.cursor = Position::new(*fold.start(), 0);
+    }
+}
@@ -0,0 +1,212 @@
+//! `gd` / `gr` / `<C-w>d` and the tag stack (`<C-t>` / `<C-o>`).
+//!
+//! With a language server attached to the buffer the lookup is queued in
+//! `core_state::tags` and finished by `apply_goto_answer` once the runtime
+//! has the server's answer; otherwise (and when the server finds nothing)
+//! the identifier is looked up in the `tags` file next to the buffer's file
+//! or in the working directory. Like the window commands these can change
+//! the view's buffer or add a view, so they take the whole model.
+
+use super::DispatchResult;
+use super::window;
+use core_model::{EditorModel, SplitAxis};
+use core_state::completion::is_keyword_char;
+use core_state::tags::{GotoRequest, Location, TagEntry, find_tags};
+use core_text::Position;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Most rows the location list of a multi-result jump takes.
+const LOCATION_LIST_MAX_LINES: usize = 10;
+
+pub(super) fn request(references: bool, split: bool, model: &mut EditorModel) -> DispatchResult {
+    let (state, view) = model.split_state_and_active_view();
+    let line = state
+        .active_buffer()
+        .line(view.cursor.line)
+        .unwrap_or_default();
+    let Some(word) = identifier_at(&line, view.cursor.byte) else {
+        state.set_ephemeral("E349: No identifier under cursor", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    let request = GotoRequest {
+        references,
+        split,
+        word: word.to_string(),
+        origin: TagEntry {
+            buffer: state.active,
+            position: view.cursor,
+        },
+    };
+    tracing::trace!(target: "actions.dispatch", word = %request.word, references, split, server = state.tags.is_attached(state.active), "goto");
+    if state.tags.is_attached(state.active) {
+        // Dirty so the runtime syncs the server, which sends it.
+        state.tags.request(request);
+        return DispatchResult::dirty();
+    }
+    jump(request, Vec::new(), model)
+}
+
+/// Finish the lookup whose server answer is waiting in `state.tags`. The
+/// answer is dropped when the window has since shown another buffer.
+pub fn apply_goto_answer(model: &mut EditorModel) -> DispatchResult {
+    let Some(answer) = model.state_mut().tags.take_answer() else {
+        return DispatchResult::clean();
+    };
+    if model.active_view().buffer_id != answer.request.origin.buffer {
+        return DispatchResult::clean();
+    }
+    jump(answer.request, answer.locations, model)
+}
+
+/// Jump to the first of `locations` (or of the `tags` matches when there
+/// are none), remembering the origin on the tag stack. Several locations
+/// are listed in the message area.
+fn jump(
+    request: GotoRequest,
+    mut locations: Vec<Location>,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    if locations.is_empty() {
+        locations = tag_matches(model, &request.word);
+    }
+    let Some(first) = locations.first().cloned() else {
+        model.state_mut().set_ephemeral(
+            format!("E426: Tag not found: {}", request.word),
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    };
+    if request.split {
+        let views = model.views().len();
+        window::split(SplitAxis::Horizontal, None, model);
+        if model.views().len() == views {
+            return DispatchResult::dirty();
+        }
+    }
+    model.state_mut().tags.push(request.origin);
+    let path = open_path(model.state(), &first.path);
+    let same_file = model.state().active_meta().path.as_deref() == Some(path.as_path());
+    if same_file {
+        let origin = model.active_view().cursor;
+        model.state_mut().set_jump_mark(origin);
+    } else {
+        window::show_path(&path, model);
+    }
+    let (state, view) = model.split_state_and_active_view();
+    view.cursor = byte_position(state, &first);
+    if locations.len() > 1 {
+        let lines = locations
+            .iter()
+            .map(|location| {
+                let place = format!("{}:{}", location.path.display(), location.line + 1);
+                // Text is shown for files already open in a buffer.
+                match state
+                    .buffers
+                    .find_by_path(&open_path(state, &location.path))
+                    .and_then(|id| state.buffers.get(id))
+                    .and_then(|entry| entry.buffer.line(location.line))
+                {
+                    Some(text) => format!("{place}: {}", text.trim()),
+                    None => place,
+                }
+            })
+            .collect();
+        state.show_message_lines(lines, LOCATION_LIST_MAX_LINES);
+    }
+    DispatchResult::buffer_replaced()
+}
+
+/// `<C-t>` / `<C-o>`: back to the origin of the `count`th last jump.
+pub(super) fn pop(count: u32, model: &mut EditorModel) -> DispatchResult {
+    let state = model.state_mut();
+    let Some(entry) = state.tags.pop(count) else {
+        state.set_ephemeral("E73: Tag stack empty", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    let (state, view) = model.split_state_and_active_view();
+    if !state.switch_buffer(entry.buffer) {
+        state.set_ephemeral("E86: Buffer does not exist", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    view.buffer_id = entry.buffer;
+    let buffer = state.active_buffer();
+    let line = entry
+        .position
+        .line
+        .min(buffer.line_count().saturating_sub(1));
+    let len = buffer
+        .line(line)
+        .map_or(0, |l| l.trim_end_matches(['\n', '\r']).len());
+    view.cursor = Position::new(line, entry.position.byte.min(len));
+    DispatchResult::buffer_replaced()
+}
+
+/// The identifier under the cursor, or the first one after it on the line.
+pub(super) fn identifier_at(line: &str, byte: usize) -> Option<&str> {
+    let byte = byte.min(line.len());
+    let start = line[..byte]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_keyword_char(c))
+        .last()
+        .map(|(i, _)| i)
+        .or_else(|| line[byte..].find(is_keyword_char).map(|i| byte + i))?;
+    let end = line[start..]
+        .find(|c| !is_keyword_char(c))
+        .map_or(line.len(), |i| start + i);
+    Some(&line[start..end]).filter(|word| !word.is_empty())
+}
+
+/// Definitions of `word` in the `tags` file beside the buffer's file, else
+/// in the working directory's.
+fn tag_matches(model: &EditorModel, word: &str) -> Vec<Location> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    if let Some(dir) = model
+        .state()
+        .active_meta()
+        .path
+        .as_deref()
+        .and_then(|p| p.parent())
+    {
+        files.push(dir.join("tags"));
+    }
+    files.push(PathBuf::from("tags"));
+    files
+        .iter()
+        .map(|file| find_tags(file, word))
+        .find(|found| !found.is_empty())
+        .unwrap_or_default()
+}
+
+/// `path` as an open buffer stores it when one has the same file (tags
+/// and servers spell paths their own way), else `path` itself.
+pub(super) fn open_path(state: &core_state::EditorState, path: &Path) -> PathBuf {
+    let absolute = |p: &Path| std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf());
+    let wanted = absolute(path);
+    state
+        .buffers
+        .iter()
+        .filter_map(|entry| entry.meta.path.as_deref())
+        .find(|open| absolute(open) == wanted)
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// Byte position of `location` in the (now active) buffer, clamped to it.
+fn byte_position(state: &core_state::EditorState, location: &Location) -> Position {
+    let buffer = state.active_buffer();
+    let line = location.line.min(buffer.line_count().saturating_sub(1));
+    let text = buffer.line(line).unwrap_or_default();
+    let text = text.trim_end_matches(['\n', '\r']);
+    let mut units = 0u64;
+    let byte = text
+        .char_indices()
+        .find(|&(_, c)| {
+            let reached = units >= location.character;
+            units += c.len_utf16() as u64;
+            reached
+        })
+        .map_or(text.len(), |(i, _)| i);
+    Position::new(line, byte)
+}
@@ -0,0 +1,53 @@
+//! `K`: hover documentation from the buffer's language server.
+//!
+//! The request is queued in `core_state::hover` for `core-lsp` to send;
+//! the answer shows up in a popup without going through the dispatcher.
+//! While it is shown `Ctrl-D` / `Ctrl-U` / `Ctrl-F` / `Ctrl-B` scroll the
+//! popup instead of the window, and any other action closes it.
+
+use super::DispatchResult;
+use super::goto::identifier_at;
+use crate::{Action, MotionKind};
+use core_model::View;
+use core_state::EditorState;
+use core_state::hover::HOVER_ROWS;
+use std::time::Duration;
+
+pub(super) fn request(state: &mut EditorState, view: &View) -> DispatchResult {
+    let line = state
+        .active_buffer()
+        .line(view.cursor.line)
+        .unwrap_or_default();
+    let Some(word) = identifier_at(&line, view.cursor.byte) else {
+        state.set_ephemeral("E349: No identifier under cursor", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    if !state.hover.is_attached(state.active) {
+        state.set_ephemeral(
+            format!("E149: Sorry, no help for {word}"),
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    tracing::trace!(target: "actions.dispatch", word, "hover");
+    let buffer = state.active;
+    state.hover.request(buffer, view.cursor);
+    // Dirty so the runtime syncs the server, which sends it.
+    DispatchResult::dirty()
+}
+
+/// Lines a page scroll key moves a shown hover by.
+pub(super) fn scroll_delta(action: &Action) -> Option<isize> {
+    let motion = match action {
+        Action::Motion(motion) | Action::MotionWithCount { motion, .. } => motion,
+        _ => return None,
+    };
+    let rows = HOVER_ROWS as isize;
+    Some(match motion {
+        MotionKind::PageHalfDown => rows / 2,
+        MotionKind::PageHalfUp => -rows / 2,
+        MotionKind::PageDown => rows,
+        MotionKind::PageUp => -rows,
+        _ => return None,
+    })
+}
@@ -0,0 +1,116 @@
+//! Character inspection: `ga` and `g8`.
+//!
+//! Both describe the grapheme cluster under the cursor (as segmented by
+//! `core_text::grapheme`) in the message area, followed by its display
+//! width. `ga` lists each codepoint the way Vim does (`<é> 233, Hex 00e9,
+//! Oct 351`); `g8` lists the UTF-8 bytes in hex, codepoints joined by `+`.
+//! An empty line or the end of a line reads `NUL`.
+
+use super::DispatchResult;
+use core_model::View;
+use core_state::EditorState;
+use core_text::grapheme;
+use std::time::Duration;
+
+pub(crate) fn char_under_cursor(
+    utf8: bool,
+    state: &mut EditorState,
+    view: &View,
+) -> DispatchResult {
+    let line = state
+        .active_buffer()
+        .line(view.cursor.line)
+        .unwrap_or_default();
+    let content = line.trim_end_matches(['\n', '\r']);
+    let start = view.cursor.byte.min(content.len());
+    let cluster = &content[start..grapheme::next_boundary(content, start)];
+    let msg = if cluster.is_empty() {
+        "NUL".to_string()
+    } else if utf8 {
+        describe_bytes(cluster)
+    } else {
+        describe_codepoints(cluster)
+    };
+    tracing::debug!(target: "actions.dispatch", utf8, len = cluster.len(), "inspect_char");
+    state.set_ephemeral(msg, Duration::from_secs(3));
+    DispatchResult::dirty()
+}
+
+/// `ga`: every codepoint of `cluster`, then its width in cells.
+pub(crate) fn describe_codepoints(cluster: &str) -> String {
+    let parts: Vec<String> = cluster
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let cp = c as u32;
+            let hex = match cp {
+                0..0x80 => format!("{cp:02x}"),
+                0x80..0x1_0000 => format!("{cp:04x}"),
+                _ => format!("{cp:08x}"),
+            };
+            format!("<{}> {cp}, Hex {hex}, Oct {cp:03o}", shown(c, i > 0))
+        })
+        .collect();
+    format!(
+        "{}, Width {}",
+        parts.join(" "),
+        grapheme::cluster_width(cluster)
+    )
+}
+
+/// `g8`: the UTF-8 bytes of `cluster`, then its width in cells.
+pub(crate) fn describe_bytes(cluster: &str) -> String {
+    let parts: Vec<String> = cluster
+        .chars()
+        .map(|c| {
+            let mut buf = [0u8; 4];
+            let bytes: Vec<String> = c
+                .encode_utf8(&mut buf)
+                .bytes()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            bytes.join(" ")
+        })
+        .collect();
+    format!(
+        "{}, Width {}",
+        parts.join(" + "),
+        grapheme::cluster_width(cluster)
+    )
+}
+
+/// How a codepoint is printed between `<` and `>`: control characters in
+/// caret notation, the ones that extend a cluster (combining marks, ZWJ)
+/// after a space so they have a base.
+fn shown(c: char, extends: bool) -> String {
+    match c {
+        '\0'..='\x1f' => format!("^{}", (c as u8 + b'@') as char),
+        '\x7f' => "^?".to_string(),
+        _ if extends => format!(" {c}"),
+        _ => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_codepoints_bytes_and_width() {
+        assert_eq!(describe_codepoints("a"), "<a> 97, Hex 61, Oct 141, Width 1");
+        assert_eq!(
+            describe_codepoints("e\u{301}"),
+            "<e> 101, Hex 65, Oct 145 < \u{301}> 769, Hex 0301, Oct 1401, Width 1"
+        );
+        assert_eq!(
+            describe_codepoints("😀"),
+            "<😀> 128512, Hex 0001f600, Oct 373000, Width 2"
+        );
+        assert_eq!(
+            describe_codepoints("\t"),
+            "<^I> 9, Hex 09, Oct 011, Width 1"
+        );
+        assert_eq!(describe_bytes("é"), "c3 a9, Width 1");
+        assert_eq!(describe_bytes("e\u{301}"), "65 + cc 81, Width 1");
+    }
+}
@@ -0,0 +1,99 @@
+//! Background jobs (`core_state::job`): `:job`, `:jobs` and `:jobstop`,
+//! and the output of every job including `:make`'s.
+//!
+//! `:job {cmd}` opens a `[Job N]` buffer in a window at the bottom and
+//! returns straight away; the runtime runs `cmd` and its output is
+//! appended to the buffer as it streams in (`apply_job_output`). The
+//! status line reports how each job exited.
+
+use super::DispatchResult;
+use core_events::JobOutput;
+use core_model::EditorModel;
+use core_state::{EditorState, JobSink};
+use core_text::Buffer;
+use std::time::Duration;
+
+/// `:job {cmd}`.
+pub(super) fn run(command: &str, model: &mut EditorModel) -> DispatchResult {
+    let state = model.state_mut();
+    if command.is_empty() {
+        state.set_ephemeral("E471: Argument required", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let Ok(buffer) = Buffer::from_str("[Job]", "") else {
+        return DispatchResult::dirty();
+    };
+    let buffer = state.buffers.open(buffer, None);
+    let id = state.start_job(command.to_string(), JobSink::Buffer(buffer));
+    if let Some(entry) = state.buffers.get_mut(buffer) {
+        entry.buffer.name = format!("[Job {id}] {command}");
+    }
+    if model.open_bottom_view(buffer).is_err() {
+        return DispatchResult::dirty();
+    }
+    DispatchResult::buffer_replaced()
+}
+
+/// `:jobs`: the running jobs, one `id  command` line each.
+pub(super) fn list(state: &mut EditorState) -> DispatchResult {
+    let lines: Vec<String> = state
+        .jobs
+        .running()
+        .map(|(id, command)| format!("{id:>3}  {command}"))
+        .collect();
+    match lines.len() {
+        0 => state.set_ephemeral("No jobs running", Duration::from_secs(3)),
+        count => state.show_message_lines(lines, count),
+    }
+    DispatchResult::dirty()
+}
+
+/// `:jobstop [id]`: stop job `id`, or all of them.
+pub(super) fn stop(arg: &str, state: &mut EditorState) -> DispatchResult {
+    let ids: Vec<u64> = match arg {
+        "" => state.jobs.running().map(|(id, _)| id).collect(),
+        arg => match arg.parse() {
+            Ok(id) => vec![id],
+            Err(_) => Vec::new(),
+        },
+    };
+    let mut stopped = 0;
+    for id in ids {
+        stopped += usize::from(state.cancel_job(id));
+    }
+    let msg = match stopped {
+        0 if arg.is_empty() => "No jobs running".to_string(),
+        0 => format!("E900: Invalid job id: {arg}"),
+        _ => state.jobs.summary().unwrap_or_default().to_string(),
+    };
+    state.set_ephemeral(msg, Duration::from_secs(3));
+    DispatchResult::dirty()
+}
+
+/// Add a batch of job output to where it goes and, with the last one,
+/// report how the job exited. Output of a stopped job is dropped.
+pub fn apply_job_output(output: &JobOutput, model: &mut EditorModel) -> DispatchResult {
+    let Some(sink) = model.state_mut().job_output(output.id, &output.lines) else {
+        return DispatchResult::clean();
+    };
+    let finished = output.done.as_ref().and_then(|done| {
+        model
+            .state_mut()
+            .finish_job(output.id, done.status, done.error.as_deref())
+    });
+    let added = !output.lines.is_empty();
+    let mut result = match sink {
+        JobSink::Quickfix { jump } => {
+            let jump = jump && finished.is_some();
+            super::quickfix::make_output(added, jump, model)
+        }
+        JobSink::Buffer(buffer) if added && model.views().iter().any(|v| v.buffer_id == buffer) => {
+            DispatchResult::buffer_replaced()
+        }
+        JobSink::Buffer(_) => DispatchResult::clean(),
+    };
+    if finished.is_some() {
+        result.dirty = true;
+    }
+    result
+}
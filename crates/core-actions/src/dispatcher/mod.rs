@@ -6,9 +6,18 @@
 //! decomposed into focused sub-modules:
 //! * `motion`  - cursor movement semantics
 //! * `mode`    - mode transitions (Normal <-> Insert)
-//! * `command` - command line editing & execution (:q, :e, :w)
+//! * `command` - command line editing & execution (:q, :e, :w, :set, user commands)
 //! * `edit`    - text mutation (insert/delete/backspace/newline)
 //! * `undo`    - undo / redo dispatch
+//! * `shell`   - completion of queued external commands (`:!`, `:r !`)
+//! * `sort`    - `:sort` flag parsing and line ordering
+//! * `align`   - `:align` / `gl` / `gL` column alignment on a delimiter
+//! * `search`  - `/` and `?` searches, `n` / `N`
+//! * `fold`    - manual folds (`zf`, `zo`, `zc`, `za`)
+//! * `inspect` - `ga` / `g8` character inspection
+//! * `completion` - Insert-mode completion keys and narrowing while typing
+//! * `goto`    - `gd` / `gr` through a language server or `tags`, and the tag stack
+//! * `hover`   - `K` documentation popup and scrolling it
 //!
 //! The public surface (`dispatch`, `DispatchResult`) remains unchanged.
 //! Borrow splitting (raw pointer for `EditorState` + mutable active view
@@ -19,16 +28,39 @@
 //! parity. Subsequent refactor steps (command parser extraction, etc.)
 //! will build on this structure.
 
+use crate::command_registry::CommandRegistry;
 use crate::{Action, ActionObserver, MotionKind};
 use core_model::EditorModel;
-use core_state::PasteSource;
+use core_model::View;
+use core_state::{EditorState, PasteSource, RegisterKind};
 
+mod abbrev;
+mod align;
+mod cmdline_completion;
 mod command;
 mod command_parser;
+mod completion;
+mod diagnostics;
+mod diff;
 mod edit;
+pub mod ex_range;
+mod explorer;
+mod expr;
+pub mod file_watch;
+mod fold;
+pub mod goto;
+mod hover;
+mod inspect;
+pub mod job;
 mod mode;
 mod motion;
+pub mod quickfix;
+mod search;
+pub mod shell;
+mod sort;
+mod timer;
 mod undo;
+mod window;
 
 /// Result of dispatching a single `Action`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -80,6 +112,10 @@ fn paste_source_from_register(register: Option<char>) -> PasteSource {
                 Some(PasteSource::Named(c))
             } else if c.is_ascii_digit() {
                 Some(PasteSource::Numbered((c as u8 - b'0') as usize))
+            } else if c == '-' {
+                Some(PasteSource::SmallDelete)
+            } else if c == '=' {
+                Some(PasteSource::Expression)
             } else {
                 None
             }
@@ -87,6 +123,45 @@ fn paste_source_from_register(register: Option<char>) -> PasteSource {
         .unwrap_or(PasteSource::Unnamed)
 }
 
+/// `p` / `P` with an optional register, repeated `count` times. `"=` first
+/// opens the expression prompt; the paste runs once it is confirmed.
+fn paste_repeated(
+    register: Option<char>,
+    before: bool,
+    count: u32,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if register == Some('=') {
+        state.command_line.begin_expression();
+        state.expr_paste = Some(core_state::PendingExprPaste { before, count });
+        return DispatchResult::dirty();
+    }
+    paste_from_source(
+        paste_source_from_register(register),
+        before,
+        count,
+        state,
+        view,
+    )
+}
+
+fn paste_from_source(
+    source: PasteSource,
+    before: bool,
+    count: u32,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    match state.paste_repeated(source, before, count, &mut view.cursor) {
+        Ok(true) => DispatchResult::buffer_replaced(),
+        Ok(false) => DispatchResult::dirty(),
+        Err(_) => DispatchResult::clean(),
+    }
+}
+
+static NO_USER_COMMANDS: CommandRegistry = CommandRegistry::new();
+
 /// Apply an action to editor state. Returns `DispatchResult` describing whether
 /// a render is needed (`dirty`) or the editor should exit (`quit`).
 pub fn dispatch(
@@ -95,16 +170,134 @@ pub fn dispatch(
     sticky_visual_col: &mut Option<usize>,
     observers: &[Box<dyn ActionObserver>],
 ) -> DispatchResult {
-    // Safe split borrow (encapsulated unsafety lives in `EditorModel::split_state_and_active_view`).
-    let (state, view) = model.split_state_and_active_view();
+    dispatch_with_commands(
+        action,
+        model,
+        sticky_visual_col,
+        observers,
+        &NO_USER_COMMANDS,
+    )
+}
+
+/// `dispatch` variant consulting a user command registry when executing
+/// `:` commands (Commands Step 1). Folds are moved with whatever the
+/// action edited (`EditorModel::follow_edits`).
+///
+/// With the `check-invariants` feature every dispatch is followed by
+/// `EditorModel::check_invariants`, panicking with the action that broke it.
+pub fn dispatch_with_commands(
+    action: Action,
+    model: &mut EditorModel,
+    sticky_visual_col: &mut Option<usize>,
+    observers: &[Box<dyn ActionObserver>],
+    commands: &CommandRegistry,
+) -> DispatchResult {
+    #[cfg(feature = "check-invariants")]
+    let label = format!("{action:?}");
+    let result = dispatch_unchecked(action, model, sticky_visual_col, observers, commands);
+    model.follow_edits();
+    #[cfg(feature = "check-invariants")]
+    if let Err(violation) = model.check_invariants() {
+        panic!("{label} broke a buffer invariant: {violation}");
+    }
+    result
+}
+
+/// Shown when an edit targets a read-only buffer (the binary hex view).
+pub const NOT_MODIFIABLE_MSG: &str = "E21: Cannot make changes, 'modifiable' is off";
+
+/// Actions that change buffer text (rejected while the hex view is shown).
+fn modifies_buffer(action: &Action) -> bool {
+    use crate::OperatorKind;
+    match action {
+        Action::Edit(_)
+        | Action::Undo { .. }
+        | Action::Redo { .. }
+        | Action::UndoTravel(_)
+        | Action::PasteAfter { .. }
+        | Action::PasteBefore { .. }
+        | Action::VisualPaste { .. }
+        | Action::Completion(_)
+        | Action::DiffHunk { .. } => true,
+        Action::ApplyOperator { op, .. }
+        | Action::LinewiseOperator { op, .. }
+        | Action::ApplyOperatorTextObject { op, .. }
+        | Action::VisualOperator { op, .. } => !matches!(
+            op,
+            OperatorKind::Yank | OperatorKind::Fold | OperatorKind::Align { .. }
+        ),
+        _ => false,
+    }
+}
 
+fn dispatch_unchecked(
+    action: Action,
+    model: &mut EditorModel,
+    sticky_visual_col: &mut Option<usize>,
+    observers: &[Box<dyn ActionObserver>],
+    commands: &CommandRegistry,
+) -> DispatchResult {
     // Notify observers (pre-dispatch).
     for obs in observers {
         obs.on_action(&action);
     }
 
+    // A shown hover takes the page scroll keys; anything else closes it.
+    if let Some(delta) = hover::scroll_delta(&action)
+        && model.state_mut().hover.scroll(delta)
+    {
+        return DispatchResult::dirty();
+    }
+    if !matches!(action, Action::Hover) {
+        model.state_mut().hover.close();
+    }
+
+    // Window commands add, remove or switch views, so they run before the
+    // model is split into state + active view.
+    if let Action::CommandExecute(cmd) = &action
+        && let Some(command) = window::window_command(cmd, model, commands)
+    {
+        return window::execute(cmd, command, model);
+    }
+
+    match action {
+        Action::WindowFocus { direction, count } => {
+            return window::focus(direction, count, model);
+        }
+        Action::TabSwitch { backward, count } => return window::switch_tab(backward, count, model),
+        Action::Goto { references, split } => return goto::request(references, split, model),
+        Action::TagPop { count } => return goto::pop(count, model),
+        Action::ListEnter if model.state().quickfix_window().is_some() => {
+            return quickfix::enter(model);
+        }
+        _ => {}
+    }
+
+    // Safe split borrow (encapsulated unsafety lives in `EditorModel::split_state_and_active_view`).
+    let (state, view) = model.split_state_and_active_view();
+
+    if (state.hex_view().is_some()
+        || state.explorer().is_some()
+        || state.quickfix_window().is_some())
+        && modifies_buffer(&action)
+    {
+        state.set_ephemeral(NOT_MODIFIABLE_MSG, std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+
+    if state.completion.is_active() && !completion::continues(&action) {
+        state.completion.close();
+    }
+
     match action {
         Action::Motion(kind) => motion::handle_motion(kind, state, view, sticky_visual_col),
+        Action::MotionWithCount {
+            motion: kind @ (MotionKind::PageHalfDown | MotionKind::PageHalfUp),
+            count,
+        } => {
+            motion::set_half_page(state, count);
+            motion::handle_motion(kind, state, view, sticky_visual_col)
+        }
         Action::MotionWithCount {
             motion: kind,
             count,
@@ -120,12 +313,43 @@ pub fn dispatch(
             result
         }
         Action::ModeChange(mc) => mode::handle_mode_change(mc, state, view),
+        Action::ListEnter if state.explorer().is_some() => explorer::enter(state, view),
+        // Bound to <CR> only in the windows above and the command-line window.
+        Action::ListEnter => DispatchResult::clean(),
+        Action::CmdlineWindowExecute if !state.cmdline_window_active() => DispatchResult::clean(),
+        Action::Explorer(cmd) => explorer::command(cmd, state, view),
+        Action::SearchStart { forward } => {
+            state.command_line.begin_search(forward);
+            DispatchResult::dirty()
+        }
+        Action::SearchNext { reverse, count } => search::repeat(reverse, count, state, view),
+        Action::DiagnosticJump { backward, count } => {
+            diagnostics::jump(backward, count, state, view)
+        }
+        Action::Fold(cmd) => fold::command(cmd, state, view),
+        Action::InspectChar { utf8 } => inspect::char_under_cursor(utf8, state, view),
+        Action::Hover => hover::request(state, view),
+        Action::DiffHunk { put } => diff::hunk(put, state, view),
+        Action::ScrollCursor { to, line } => {
+            motion::scroll_cursor(to, line, state, view, sticky_visual_col)
+        }
         Action::CommandStart
         | Action::CommandChar(_)
         | Action::CommandBackspace
         | Action::CommandCancel
-        | Action::CommandExecute(_) => command::handle_command_action(action, state, view),
-        Action::Edit(kind) => edit::handle_edit(kind, state, view),
+        | Action::CommandExecute(_)
+        | Action::CommandComplete { .. }
+        | Action::CmdlineWindowOpen
+        | Action::CmdlineWindowExecute
+        | Action::CmdlineWindowClose => {
+            command::handle_command_action(action, state, view, commands)
+        }
+        Action::Edit(kind) => {
+            let result = edit::handle_edit(kind.clone(), state, view);
+            completion::after_edit(&kind, state, view);
+            result
+        }
+        Action::Completion(command) => completion::command(command, state, view),
         Action::Undo { count } => {
             let mut dirty = false;
             let mut structural = false;
@@ -173,61 +397,20 @@ pub fn dispatch(
                 DispatchResult::clean()
             }
         }
+        Action::UndoTravel(travel) => undo::handle_undo_travel(travel, state, view),
         Action::PasteAfter { count, register } => {
-            let source = paste_source_from_register(register);
-            let mut dirty = false;
-            let mut structural = false;
-            let repeats = count.max(1);
-            for idx in 0..repeats {
-                match state.paste(source, false, &mut view.cursor) {
-                    Ok(is_structural) => {
-                        dirty = true;
-                        structural |= is_structural;
-                    }
-                    Err(_) => {
-                        if idx == 0 {
-                            return DispatchResult::clean();
-                        }
-                        break;
-                    }
-                }
-            }
-            if structural {
-                DispatchResult::buffer_replaced()
-            } else if dirty {
-                DispatchResult::dirty()
-            } else {
-                DispatchResult::clean()
-            }
+            paste_repeated(register, false, count, state, view)
         }
         Action::PasteBefore { count, register } => {
-            let source = paste_source_from_register(register);
-            let mut dirty = false;
-            let mut structural = false;
-            let repeats = count.max(1);
-            for idx in 0..repeats {
-                match state.paste(source, true, &mut view.cursor) {
-                    Ok(is_structural) => {
-                        dirty = true;
-                        structural |= is_structural;
-                    }
-                    Err(_) => {
-                        if idx == 0 {
-                            return DispatchResult::clean();
-                        }
-                        break;
-                    }
-                }
-            }
-            if structural {
-                DispatchResult::buffer_replaced()
-            } else if dirty {
-                DispatchResult::dirty()
-            } else {
-                DispatchResult::clean()
-            }
+            paste_repeated(register, true, count, state, view)
         }
         Action::Quit => DispatchResult::quit(),
+        Action::WindowFocus { .. }
+        | Action::TabSwitch { .. }
+        | Action::Goto { .. }
+        | Action::TagPop { .. } => {
+            unreachable!("window, tab and goto actions routed before the split borrow")
+        }
         Action::BeginOperator(_) => DispatchResult::clean(),
         Action::ApplyOperator {
             op,
@@ -238,6 +421,22 @@ pub fn dispatch(
             use crate::OperatorKind;
             use crate::span_resolver::resolve_selection;
             match op {
+                OperatorKind::Fold | OperatorKind::Align { .. } => {
+                    let sel = resolve_selection(state, view.cursor, motion, count);
+                    // An end at the start of a later line (linewise spans,
+                    // exclusive motions) does not include that line.
+                    let last = if sel.end.byte == 0 && sel.end.line > sel.start.line {
+                        sel.end.line - 1
+                    } else {
+                        sel.end.line
+                    };
+                    match op {
+                        OperatorKind::Align { right } => {
+                            align::prompt(sel.start.line, last, right, state, view)
+                        }
+                        _ => fold::create(sel.start.line, last, state, view),
+                    }
+                }
                 OperatorKind::Delete => {
                     let start_pos = view.cursor;
                     let sel = resolve_selection(state, start_pos, motion, count);
@@ -255,11 +454,11 @@ pub fn dispatch(
                         || matches!(sel.kind, core_state::SelectionKind::Linewise);
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_delete(removed.clone(), register);
+                        regs.write_delete(removed.clone(), sel.kind.into(), register);
                     }
                     view.cursor = cursor;
-                    if !state.dirty {
-                        state.dirty = true;
+                    if !state.dirty() {
+                        state.set_dirty(true);
                     }
                     if structural {
                         DispatchResult::buffer_replaced()
@@ -311,7 +510,7 @@ pub fn dispatch(
                     };
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_yank(collected.clone(), register);
+                        regs.write_yank(collected.clone(), sel.kind.into(), register);
                     }
                     DispatchResult::dirty()
                 }
@@ -337,13 +536,13 @@ pub fn dispatch(
                         || matches!(sel.kind, core_state::SelectionKind::Linewise);
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_change(removed.clone(), register);
+                        regs.write_change(removed.clone(), sel.kind.into(), register);
                     }
                     // Change enters insert at beginning of span (linewise: first line start; charwise: absolute start)
                     view.cursor = sel.start; // sel.start already normalized
                     state.mode = core_state::Mode::Insert;
-                    if !state.dirty {
-                        state.dirty = true;
+                    if !state.dirty() {
+                        state.set_dirty(true);
                     }
                     if structural {
                         DispatchResult::buffer_replaced()
@@ -353,6 +552,28 @@ pub fn dispatch(
                 }
             }
         }
+        Action::ApplyOperatorTextObject {
+            op,
+            object,
+            around,
+            count,
+            register,
+        } => {
+            let sel = crate::text_object::resolve_text_object(state, view.cursor, object, around);
+            tracing::debug!(
+                target: "actions.dispatch",
+                ?op,
+                ?object,
+                around,
+                count,
+                ?register,
+                empty = sel.is_empty(),
+                "operator_text_object"
+            );
+            // Objects do not resolve yet (see `text_object`); like a Vim
+            // object that finds nothing, the operator leaves the buffer alone.
+            DispatchResult::clean()
+        }
         Action::LinewiseOperator {
             op,
             count,
@@ -364,21 +585,32 @@ pub fn dispatch(
             else {
                 return DispatchResult::clean();
             };
+            let last = end_exclusive.saturating_sub(1);
+            match op {
+                OperatorKind::Fold => return fold::create(start_line, last, state, view),
+                OperatorKind::Align { right } => {
+                    return align::prompt(start_line, last, right, state, view);
+                }
+                _ => {}
+            }
             if abs_start == abs_end {
                 return DispatchResult::clean();
             }
             match op {
+                OperatorKind::Fold | OperatorKind::Align { .. } => {
+                    unreachable!("folds and alignment handled above")
+                }
                 OperatorKind::Delete => {
                     let mut cursor = view.cursor;
                     let removed = state.delete_span_with_snapshot(&mut cursor, abs_start, abs_end);
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_delete(removed.clone(), register);
+                        regs.write_delete(removed.clone(), RegisterKind::Linewise, register);
                     }
                     cursor.byte = 0;
                     view.cursor = cursor;
-                    if !state.dirty {
-                        state.dirty = true;
+                    if !state.dirty() {
+                        state.set_dirty(true);
                     }
                     DispatchResult::buffer_replaced()
                 }
@@ -392,7 +624,7 @@ pub fn dispatch(
                     }
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_yank(collected.clone(), register);
+                        regs.write_yank(collected.clone(), RegisterKind::Linewise, register);
                     }
                     DispatchResult::dirty()
                 }
@@ -401,7 +633,7 @@ pub fn dispatch(
                     let removed = state.delete_span_with_snapshot(&mut cursor, abs_start, abs_end);
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_change(removed.clone(), register);
+                        regs.write_change(removed.clone(), RegisterKind::Linewise, register);
                     }
                     let lines_to_insert =
                         std::cmp::max(1, end_exclusive.saturating_sub(start_line));
@@ -423,8 +655,8 @@ pub fn dispatch(
                     }
                     view.cursor = new_cursor;
                     state.mode = core_state::Mode::Insert;
-                    if !state.dirty {
-                        state.dirty = true;
+                    if !state.dirty() {
+                        state.set_dirty(true);
                     }
                     DispatchResult::buffer_replaced()
                 }
@@ -443,6 +675,16 @@ pub fn dispatch(
             let Some(span) = state.selection.active else {
                 return DispatchResult::clean();
             };
+            if let OperatorKind::Fold | OperatorKind::Align { .. } = op {
+                let (first, last) = (span.start.line, span.end.line);
+                let (first, last) = (first.min(last), first.max(last));
+                state.clear_selection();
+                state.mode = core_state::Mode::Normal;
+                return match op {
+                    OperatorKind::Align { right } => align::prompt(first, last, right, state, view),
+                    _ => fold::create(first, last, state, view),
+                };
+            }
             if span.start == span.end {
                 return DispatchResult::clean();
             }
@@ -459,6 +701,9 @@ pub fn dispatch(
                 return DispatchResult::clean();
             }
             match op {
+                OperatorKind::Fold | OperatorKind::Align { .. } => {
+                    unreachable!("folds and alignment handled above")
+                }
                 OperatorKind::Delete => {
                     let mut cursor = view.cursor;
                     let removed = state.delete_span_with_snapshot(&mut cursor, abs_start, abs_end);
@@ -466,14 +711,14 @@ pub fn dispatch(
                         removed.contains('\n') || matches!(span.kind, SelectionKind::Linewise);
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_delete(removed.clone(), register);
+                        regs.write_delete(removed.clone(), span.kind.into(), register);
                     }
                     // Cursor placement: start of resulting span (normalized span.start)
                     view.cursor = span.start;
                     state.clear_selection();
                     state.mode = core_state::Mode::Normal;
-                    if !state.dirty {
-                        state.dirty = true;
+                    if !state.dirty() {
+                        state.set_dirty(true);
                     }
                     if structural {
                         DispatchResult::buffer_replaced()
@@ -518,7 +763,7 @@ pub fn dispatch(
                     };
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_yank(collected.clone(), register);
+                        regs.write_yank(collected.clone(), span.kind.into(), register);
                     }
                     // Cursor stays at active end? Vim leaves at start for charwise.
                     view.cursor = span.start;
@@ -533,13 +778,13 @@ pub fn dispatch(
                         removed.contains('\n') || matches!(span.kind, SelectionKind::Linewise);
                     {
                         let mut regs = state.registers_facade();
-                        regs.write_change(removed.clone(), register);
+                        regs.write_change(removed.clone(), span.kind.into(), register);
                     }
                     view.cursor = span.start; // enter insert at start
                     state.clear_selection();
                     state.mode = core_state::Mode::Insert;
-                    if !state.dirty {
-                        state.dirty = true;
+                    if !state.dirty() {
+                        state.set_dirty(true);
                     }
                     if structural {
                         DispatchResult::buffer_replaced()
@@ -566,7 +811,7 @@ pub fn dispatch(
                 return DispatchResult::clean();
             }
             let source = paste_source_from_register(register);
-            let text = {
+            let (mut text, kind) = {
                 let regs = state.registers_facade();
                 match regs.read_paste(source) {
                     Ok(t) => t,
@@ -578,10 +823,18 @@ pub fn dispatch(
                 return DispatchResult::clean();
             }
             let repeats = count.max(1) as usize;
-            let mut payload = String::with_capacity(text.len().saturating_mul(repeats));
-            for _ in 0..repeats {
-                payload.push_str(&text);
+            if kind == RegisterKind::Linewise && !text.ends_with('\n') {
+                text.push('\n');
             }
+            let payload = if kind == RegisterKind::Blockwise {
+                // A counted block paste repeats each row side by side.
+                text.split('\n')
+                    .map(|row| row.repeat(repeats))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                text.repeat(repeats)
+            };
             let (abs_start, abs_end) = if matches!(span.kind, SelectionKind::Characterwise) {
                 span.inclusive_byte_range(state.active_buffer())
             } else {
@@ -597,7 +850,7 @@ pub fn dispatch(
                 removed.contains('\n') || matches!(span.kind, SelectionKind::Linewise);
             if !removed.is_empty() {
                 let mut regs = state.registers_facade();
-                regs.write_delete(removed.clone(), None);
+                regs.write_delete(removed.clone(), span.kind.into(), None);
             }
             state.clear_selection();
             state.mode = core_state::Mode::Normal;
@@ -607,7 +860,8 @@ pub fn dispatch(
             } else {
                 before
             };
-            let structural_insert = state.paste_with_text(&payload, paste_before, &mut view.cursor);
+            let structural_insert =
+                state.paste_with_text(&payload, kind, paste_before, &mut view.cursor);
             let structural = structural_delete || structural_insert;
             if structural {
                 DispatchResult::buffer_replaced()
@@ -716,7 +970,7 @@ mod tests {
     use core_events::{KeyCode, KeyEvent, KeyModifiers};
     use core_model::EditorModel;
     use core_state::Mode;
-    use core_text::Buffer;
+    use core_text::{Buffer, Position};
     use std::cell::RefCell;
 
     thread_local! {
@@ -770,108 +1024,455 @@ mod tests {
     }
 
     #[test]
-    fn quit_command_execute() {
+    fn hex_view_rejects_edits() {
+        let buffer = Buffer::from_str("b", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        model.state_mut().load_binary(vec![0, 1, 2]);
+        let before = model.state().active_buffer().line(0);
+        let mut sticky = None;
+        let act = Action::Edit(EditKind::DeleteUnder {
+            count: 1,
+            register: None,
+        });
+        dispatch(act, &mut model, &mut sticky, &[]);
+        assert_eq!(model.state().active_buffer().line(0), before);
+        let eph = model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(eph.text, NOT_MODIFIABLE_MSG);
+        assert!(!model.state().dirty());
+    }
+
+    #[test]
+    fn cmdline_window_edit_and_execute_line() {
         reset_translator();
-        let buffer = Buffer::from_str("t", "abc").unwrap();
-        let state = core_state::EditorState::new(buffer);
-        let mut model = EditorModel::new(state);
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
         let mut sticky = None;
-        // Simulate entering :q
-        dispatch(Action::CommandStart, &mut model, &mut sticky, &[]);
-        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        dispatch(
+            Action::CommandExecute(":set nu".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        let res = dispatch(Action::CmdlineWindowOpen, &mut model, &mut sticky, &[]);
+        assert!(res.buffer_replaced);
+        assert!(model.state().cmdline_window_active());
+        assert_eq!(model.active_view().buffer_id, core_state::BufferId(2));
+        assert_eq!(model.active_view().cursor.line, 1);
+
+        // Edit the history line (`set nu` -> `set nonu`) and run it.
+        dispatch(Action::Motion(MotionKind::Up), &mut model, &mut sticky, &[]);
+        for _ in 0..4 {
+            dispatch(
+                Action::Motion(MotionKind::Right),
+                &mut model,
+                &mut sticky,
+                &[],
+            );
+        }
+        dispatch(
+            Action::ModeChange(ModeChange::EnterInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        for ch in ["n", "o"] {
+            dispatch(
+                Action::Edit(EditKind::InsertGrapheme(ch.into())),
+                &mut model,
+                &mut sticky,
+                &[],
+            );
+        }
+        dispatch(
+            Action::ModeChange(ModeChange::LeaveInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(Action::CmdlineWindowExecute, &mut model, &mut sticky, &[]);
+
+        assert!(!model.state().cmdline_window_active());
+        assert_eq!(model.state().buffers.len(), 1);
+        assert_eq!(model.active_view().buffer_id, core_state::BufferId(1));
+        assert!(!model.state().options.get_bool("number"));
+        assert_eq!(
+            model.state().command_line.history(),
+            ["set nu".to_string(), "set nonu".to_string()]
+        );
+    }
+
+    #[test]
+    fn cmdline_window_quit_closes_instead_of_exiting() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        dispatch(Action::CmdlineWindowOpen, &mut model, &mut sticky, &[]);
         let res = dispatch(
             Action::CommandExecute(":q".into()),
             &mut model,
             &mut sticky,
             &[],
         );
-        assert!(res.quit && res.dirty);
+        assert!(!res.quit);
+        assert!(!model.state().cmdline_window_active());
+        // Outside the window <CR> is a downward motion.
+        let res = dispatch(Action::CmdlineWindowExecute, &mut model, &mut sticky, &[]);
+        assert!(!res.buffer_replaced);
     }
 
     #[test]
-    fn delete_under_count_updates_registers() {
+    fn split_then_quit_closes_window() {
         reset_translator();
-        let buffer = Buffer::from_str("t", "abcdef\n").unwrap();
-        let state = core_state::EditorState::new(buffer);
-        let mut model = EditorModel::new(state);
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
         let mut sticky = None;
         let res = dispatch(
+            Action::CommandExecute(":split".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(res.buffer_replaced);
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.state().command_line.history(), ["split".to_string()]);
+
+        // Both windows show the modified buffer, so closing one is allowed.
+        dispatch(
             Action::Edit(EditKind::DeleteUnder {
-                count: 3,
+                count: 1,
                 register: None,
             }),
             &mut model,
             &mut sticky,
             &[],
         );
-        assert!(res.dirty);
-        assert!(!res.buffer_replaced);
-        assert_eq!(model.state().active_buffer().line(0).unwrap(), "def\n");
-        assert_eq!(model.state().registers.unnamed, "abc");
+        let res = dispatch(
+            Action::CommandExecute(":q".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(!res.quit);
+        assert_eq!(model.views().len(), 1);
+
+        let res = dispatch(
+            Action::CommandExecute(":close".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(!res.quit);
+        let eph = model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(eph.text, "E444: Cannot close last window");
     }
 
     #[test]
-    fn delete_under_named_register_prefix() {
+    fn colon_from_visual_runs_the_command_over_the_selection() {
+        use core_events::{KeyEventExt, KeyToken, NamedKey};
+        let buffer = Buffer::from_str("t", "c\nb\na\nz\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut translator = NgiTranslator::new();
+        let cfg = core_config::Config::default();
+        let mut sticky = None;
+        let mut tokens: Vec<KeyToken> = "jvj:sort".chars().map(KeyToken::Char).collect();
+        tokens.push(KeyToken::Named(NamedKey::Enter));
+        for token in tokens {
+            let key = KeyEventExt::from_parts(token, false, std::time::Instant::now());
+            let resolution = crate::translate_keypress(
+                &mut translator,
+                model.state().mode,
+                model.state().command_line.buffer(),
+                &key,
+                &cfg,
+            );
+            if let Some(action) = resolution.action {
+                dispatch(action, &mut model, &mut sticky, &[]);
+            }
+        }
+        let b = model.state().active_buffer();
+        let text: String = (0..b.line_count()).filter_map(|i| b.line(i)).collect();
+        assert_eq!(text, "c\na\nb\nz\n");
+        assert_eq!(model.state().mode, Mode::Normal);
+        assert!(model.state().selection().is_none());
+    }
+
+    #[test]
+    fn forced_quit_discards_a_modified_buffer_shown_nowhere_else() {
         reset_translator();
-        let buffer = Buffer::from_str("t", "wxyz\n").unwrap();
-        let state = core_state::EditorState::new(buffer);
-        let mut model = EditorModel::new(state);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("other.txt");
+        std::fs::write(&path, "one\n").unwrap();
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
         let mut sticky = None;
-        let _ = dispatch(
+        for action in [
+            Action::CommandExecute(format!(":split {}", path.display())),
             Action::Edit(EditKind::DeleteUnder {
-                count: 2,
-                register: Some('b'),
+                count: 1,
+                register: None,
             }),
+            Action::CommandExecute(":q".into()),
+        ] {
+            dispatch(action, &mut model, &mut sticky, &[]);
+        }
+        assert_eq!(model.views().len(), 2);
+        let eph = model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(
+            eph.text,
+            "E37: No write since last change (add ! to override)"
+        );
+
+        let res = dispatch(
+            Action::CommandExecute(":q!".into()),
             &mut model,
             &mut sticky,
             &[],
         );
-        assert_eq!(model.state().active_buffer().line(0).unwrap(), "yz\n");
-        assert_eq!(model.state().registers.unnamed, "wx");
-        let named = model.state().registers.get_named('b').unwrap_or("");
-        assert_eq!(named, "wx");
+        assert!(!res.quit);
+        assert_eq!(model.views().len(), 1);
+        assert_eq!(model.state().buffers.len(), 1);
+        assert!(model.state().buffers.find_by_path(&path).is_none());
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "abc\n");
     }
 
     #[test]
-    fn delete_left_count_and_register() {
+    fn window_focus_switches_active_buffer_and_keeps_cursors() {
         reset_translator();
-        let buffer = Buffer::from_str("t", "abcdef").unwrap();
-        let state = core_state::EditorState::new(buffer);
-        let mut model = EditorModel::new(state);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("below.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
         let mut sticky = None;
-        // Move cursor to the end of "c"
         dispatch(
-            Action::Motion(MotionKind::Right),
+            Action::CommandExecute(format!(":split {}", path.display())),
             &mut model,
             &mut sticky,
             &[],
         );
         dispatch(
-            Action::Motion(MotionKind::Right),
+            Action::Motion(MotionKind::Down),
             &mut model,
             &mut sticky,
             &[],
         );
-        dispatch(
-            Action::Motion(MotionKind::Right),
+        let top = model.active_view().id;
+        let focus = |direction| Action::WindowFocus {
+            direction,
+            count: 1,
+        };
+        let res = dispatch(
+            focus(core_model::FocusDirection::Down),
             &mut model,
             &mut sticky,
             &[],
         );
+        assert!(res.dirty);
+        assert_ne!(model.active_view().id, top);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "abc\n");
+        // Already at the bottom edge: nothing to do.
         let res = dispatch(
-            Action::Edit(EditKind::DeleteLeft {
-                count: 2,
-                register: Some('a'),
-            }),
+            focus(core_model::FocusDirection::Down),
             &mut model,
             &mut sticky,
             &[],
         );
-        assert!(res.dirty);
-        assert_eq!(model.state().active_buffer().line(0).unwrap(), "adef");
-        assert_eq!(model.state().registers.unnamed, "bc");
-        let named = model.state().registers.get_named('a').unwrap_or("");
-        assert_eq!(named, "bc");
+        assert!(!res.dirty);
+        dispatch(
+            focus(core_model::FocusDirection::Next),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert_eq!(model.active_view().id, top);
+        assert_eq!(model.active_view().cursor.line, 1);
+        assert_eq!(model.state().file_name(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn tab_pages_switch_and_close() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        for cmd in [":vsplit", ":tabnew", ":tabnew"] {
+            dispatch(
+                Action::CommandExecute(cmd.into()),
+                &mut model,
+                &mut sticky,
+                &[],
+            );
+        }
+        assert_eq!(model.tabs().len(), 3);
+        assert_eq!(model.current_tab(), 2);
+        assert_eq!(model.state().active_buffer().line_count(), 1);
+        let switch = |backward, count| Action::TabSwitch { backward, count };
+        dispatch(switch(false, None), &mut model, &mut sticky, &[]);
+        assert_eq!(model.current_tab(), 0, "gt wraps around");
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "abc\n");
+        dispatch(switch(false, Some(2)), &mut model, &mut sticky, &[]);
+        assert_eq!(model.current_tab(), 1);
+        dispatch(switch(true, Some(2)), &mut model, &mut sticky, &[]);
+        assert_eq!(model.current_tab(), 2);
+
+        // `:q` in a tab's only window closes the tab, not the editor.
+        let res = dispatch(
+            Action::CommandExecute(":q".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(!res.quit);
+        assert_eq!(model.tabs().len(), 2);
+        assert_eq!(model.current_tab(), 1);
+    }
+
+    #[test]
+    fn split_with_file_opens_buffer_in_new_window() {
+        reset_translator();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("other.txt");
+        std::fs::write(&path, "other\n").unwrap();
+        let buffer = Buffer::from_str("t", "abc\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        dispatch(
+            Action::Edit(EditKind::DeleteUnder {
+                count: 1,
+                register: None,
+            }),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::CommandExecute(format!(":sp {}", path.display())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "other\n");
+        assert_eq!(model.state().file_name(), Some(path.as_path()));
+        assert!(!model.state().dirty());
+
+        // The first window's buffer is untouched and still modified.
+        dispatch(
+            Action::CommandExecute(":q".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert_eq!(model.views().len(), 1);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "bc\n");
+        assert!(model.state().dirty());
+    }
+
+    #[test]
+    fn quit_command_execute() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "abc").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        // Simulate entering :q
+        dispatch(Action::CommandStart, &mut model, &mut sticky, &[]);
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        let res = dispatch(
+            Action::CommandExecute(":q".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(res.quit && res.dirty);
+    }
+
+    #[test]
+    fn delete_under_count_updates_registers() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "abcdef\n").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let res = dispatch(
+            Action::Edit(EditKind::DeleteUnder {
+                count: 3,
+                register: None,
+            }),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(res.dirty);
+        assert!(!res.buffer_replaced);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "def\n");
+        assert_eq!(model.state().registers.unnamed, "abc");
+    }
+
+    #[test]
+    fn delete_under_named_register_prefix() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "wxyz\n").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let _ = dispatch(
+            Action::Edit(EditKind::DeleteUnder {
+                count: 2,
+                register: Some('b'),
+            }),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "yz\n");
+        assert_eq!(model.state().registers.unnamed, "wx");
+        let named = model.state().registers.get_named('b').unwrap_or("");
+        assert_eq!(named, "wx");
+    }
+
+    #[test]
+    fn delete_left_count_and_register() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "abcdef").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        // Move cursor to the end of "c"
+        dispatch(
+            Action::Motion(MotionKind::Right),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::Motion(MotionKind::Right),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::Motion(MotionKind::Right),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        let res = dispatch(
+            Action::Edit(EditKind::DeleteLeft {
+                count: 2,
+                register: Some('a'),
+            }),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(res.dirty);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "adef");
+        assert_eq!(model.state().registers.unnamed, "bc");
+        let named = model.state().registers.get_named('a').unwrap_or("");
+        assert_eq!(named, "bc");
     }
 
     #[test]
@@ -882,7 +1483,7 @@ mod tests {
         let mut model = EditorModel::new(state);
         {
             let mut regs = model.state_mut().registers_facade();
-            regs.write_yank("Z", None);
+            regs.write_yank("Z", RegisterKind::Charwise, None);
         }
         let mut sticky = None;
         let res = dispatch(
@@ -908,7 +1509,7 @@ mod tests {
         let mut model = EditorModel::new(state);
         {
             let mut regs = model.state_mut().registers_facade();
-            regs.write_yank("A", Some('c'));
+            regs.write_yank("A", RegisterKind::Charwise, Some('c'));
         }
         let mut sticky = None;
         // Move cursor to index 2 (on '3')
@@ -947,7 +1548,7 @@ mod tests {
         let mut model = EditorModel::new(state);
         {
             let mut regs = model.state_mut().registers_facade();
-            regs.write_yank("  paste\n", None);
+            regs.write_yank("  paste\n", RegisterKind::Linewise, None);
         }
         let mut sticky = None;
         let res = dispatch(
@@ -974,7 +1575,7 @@ mod tests {
         let mut model = EditorModel::new(state);
         {
             let mut regs = model.state_mut().registers_facade();
-            regs.write_yank("block\n", None);
+            regs.write_yank("block\n", RegisterKind::Linewise, None);
         }
         model.active_view_mut().cursor = core_text::Position { line: 1, byte: 0 };
         let mut sticky = None;
@@ -1056,6 +1657,71 @@ mod tests {
         assert_eq!(model.state().active_buffer().line(0).unwrap(), "cd");
     }
 
+    #[test]
+    fn g_minus_reaches_branch_abandoned_by_new_edit() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "abcd").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let x = Action::Edit(EditKind::DeleteUnder {
+            count: 1,
+            register: None,
+        });
+        dispatch(x.clone(), &mut model, &mut sticky, &[]); // "bcd"
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        dispatch(
+            Action::Edit(EditKind::DeleteUnder {
+                count: 2,
+                register: None,
+            }),
+            &mut model,
+            &mut sticky,
+            &[],
+        ); // "cd": new branch
+        let older = Action::UndoTravel(core_state::UndoTravel::Steps(-1));
+        let res = dispatch(older, &mut model, &mut sticky, &[]);
+        assert!(res.buffer_replaced);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "bcd");
+
+        let res = dispatch(
+            Action::CommandExecute(":later".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(res.dirty);
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "cd");
+    }
+
+    #[test]
+    fn reopened_files_get_their_last_cursor_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        std::fs::write(&a, "one\ntwo words\n").unwrap();
+        std::fs::write(&b, "b\n").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut ex = |cmd: String, model: &mut EditorModel| {
+            dispatch(Action::CommandExecute(cmd), model, &mut sticky, &[]);
+        };
+        ex(format!(":e {}", a.display()), &mut model);
+        model.active_view_mut().cursor = Position::new(1, 4);
+        ex(format!(":e {}", b.display()), &mut model);
+        assert_eq!(model.active_view().cursor, Position::origin());
+        ex(format!(":e {}", a.display()), &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(1, 4));
+
+        // Clamped to the file as it is now; off with `restore_cursor`.
+        std::fs::write(&a, "one\nxy\n").unwrap();
+        ex(":e".to_string(), &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(1, 1));
+        model.state_mut().file_positions.restore = false;
+        ex(":e".to_string(), &mut model);
+        assert_eq!(model.active_view().cursor, Position::origin());
+    }
+
     #[test]
     fn edit_command_opens_file() {
         reset_translator();
@@ -1082,7 +1748,7 @@ mod tests {
             &[],
         );
         assert!(res.dirty);
-        assert!(model.state().file_name.as_ref().is_some());
+        assert!(model.state().file_name().is_some());
         assert!(
             model
                 .state()
@@ -1091,7 +1757,7 @@ mod tests {
                 .unwrap()
                 .starts_with("Hello Edit Command")
         );
-        assert!(!model.state().dirty, "buffer must be clean after load");
+        assert!(!model.state().dirty(), "buffer must be clean after load");
         assert!(
             model
                 .state()
@@ -1246,8 +1912,8 @@ mod tests {
         let state = core_state::EditorState::new(initial);
         let mut model = EditorModel::new(state);
         let mut sticky = None;
-        model.state_mut().file_name = Some(file_path.clone());
-        model.state_mut().dirty = true; // pretend modified
+        model.state_mut().set_file_name(Some(file_path.clone()));
+        model.state_mut().set_dirty(true); // pretend modified
         dispatch(Action::CommandStart, &mut model, &mut sticky, &[]);
         dispatch(Action::CommandChar('w'), &mut model, &mut sticky, &[]);
         let res = dispatch(
@@ -1257,7 +1923,10 @@ mod tests {
             &[],
         );
         assert!(res.dirty);
-        assert!(!model.state().dirty, "dirty flag should clear after write");
+        assert!(
+            !model.state().dirty(),
+            "dirty flag should clear after write"
+        );
         let mut f = std::fs::File::open(&file_path).unwrap();
         let mut s = String::new();
         f.read_to_string(&mut s).unwrap();
@@ -1342,7 +2011,7 @@ mod tests {
         let buffer = Buffer::from_str("t", "scratch buffer").unwrap();
         let state = core_state::EditorState::new(buffer);
         let mut model = EditorModel::new(state);
-        model.state_mut().dirty = true;
+        model.state_mut().set_dirty(true);
         let mut sticky = None;
         dispatch(Action::CommandStart, &mut model, &mut sticky, &[]);
         dispatch(Action::CommandChar('w'), &mut model, &mut sticky, &[]);
@@ -1354,7 +2023,7 @@ mod tests {
         );
         assert!(res.dirty);
         assert!(
-            model.state().dirty,
+            model.state().dirty(),
             "dirty flag should remain when no filename"
         );
         assert!(
@@ -1395,38 +2064,54 @@ mod tests {
     }
 
     #[test]
-    fn dirty_flag_sets_on_first_insert() {
+    fn colorscheme_command_switches_or_reports_missing_schemes() {
         reset_translator();
-        let buffer = Buffer::from_str("t", "").unwrap();
-        let state = core_state::EditorState::new(buffer);
-        let mut model = EditorModel::new(state);
+        let buffer = Buffer::from_str("t", "initial").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
         let mut sticky = None;
-        assert!(!model.state().dirty, "initial dirty should be false");
+        let ephemeral = |model: &EditorModel| {
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.clone())
+        };
         dispatch(
-            Action::ModeChange(ModeChange::EnterInsert),
+            Action::CommandExecute(":colorscheme no_such_scheme_12345".into()),
             &mut model,
             &mut sticky,
             &[],
         );
+        assert_eq!(
+            ephemeral(&model).as_deref(),
+            Some("E185: Cannot find color scheme 'no_such_scheme_12345'")
+        );
+        assert!(!model.state_mut().take_theme_change());
+
         dispatch(
-            Action::Edit(EditKind::InsertGrapheme("a".into())),
+            Action::CommandExecute(":colo default".into()),
             &mut model,
             &mut sticky,
             &[],
         );
-        assert!(
-            model.state().dirty,
-            "dirty should be true after first mutation"
+        assert!(model.state_mut().take_theme_change());
+        dispatch(
+            Action::CommandExecute(":colo".into()),
+            &mut model,
+            &mut sticky,
+            &[],
         );
+        assert_eq!(ephemeral(&model).as_deref(), Some("default"));
     }
 
     #[test]
-    fn undo_does_not_clear_dirty() {
+    fn dirty_flag_sets_on_first_insert() {
         reset_translator();
         let buffer = Buffer::from_str("t", "").unwrap();
         let state = core_state::EditorState::new(buffer);
         let mut model = EditorModel::new(state);
         let mut sticky = None;
+        assert!(!model.state().dirty(), "initial dirty should be false");
         dispatch(
             Action::ModeChange(ModeChange::EnterInsert),
             &mut model,
@@ -1439,68 +2124,327 @@ mod tests {
             &mut sticky,
             &[],
         );
-        dispatch(
-            Action::ModeChange(ModeChange::LeaveInsert),
-            &mut model,
-            &mut sticky,
-            &[],
+        assert!(
+            model.state().dirty(),
+            "dirty should be true after first mutation"
         );
-        assert!(model.state().dirty);
-        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
-        assert!(model.state().dirty, "dirty should remain true after undo");
     }
 
     #[test]
-    fn write_clears_then_new_edit_sets_dirty_again() {
+    fn counts_on_x_p_and_u_are_single_undo_steps() {
         reset_translator();
-        let dir = tempfile::tempdir().unwrap();
-        let file_path = dir.path().join("dirty_cycle.txt");
-        let buffer = Buffer::from_str("t", "start").unwrap();
+        let buffer = Buffer::from_str("t", "abcdef\n").unwrap();
         let state = core_state::EditorState::new(buffer);
         let mut model = EditorModel::new(state);
-        model.state_mut().file_name = Some(file_path.clone());
         let mut sticky = None;
-        dispatch(
-            Action::ModeChange(ModeChange::EnterInsert),
-            &mut model,
-            &mut sticky,
-            &[],
-        );
-        dispatch(
-            Action::Edit(EditKind::InsertGrapheme("x".into())),
-            &mut model,
-            &mut sticky,
-            &[],
-        );
-        dispatch(
-            Action::ModeChange(ModeChange::LeaveInsert),
-            &mut model,
-            &mut sticky,
-            &[],
-        );
-        assert!(model.state().dirty);
-        dispatch(Action::CommandStart, &mut model, &mut sticky, &[]);
-        dispatch(Action::CommandChar('w'), &mut model, &mut sticky, &[]);
-        dispatch(
-            Action::CommandExecute(":w".into()),
-            &mut model,
-            &mut sticky,
-            &[],
-        );
-        assert!(!model.state().dirty, "dirty should clear after write");
-        dispatch(
-            Action::ModeChange(ModeChange::EnterInsert),
-            &mut model,
-            &mut sticky,
-            &[],
-        );
-        dispatch(
-            Action::Edit(EditKind::InsertGrapheme("y".into())),
+        let text = |m: &EditorModel| m.state().active_buffer().line(0).unwrap();
+        let mut keys = |model: &mut EditorModel, keys: &str| {
+            for c in keys.chars() {
+                let act = translate_key(
+                    model.state().mode,
+                    model.state().command_line.buffer(),
+                    &key_evt(c),
+                );
+                if let Some(act) = act {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        keys(&mut model, "3x");
+        assert_eq!(text(&model), "def\n");
+        assert_eq!(model.state().registers.unnamed, "abc");
+        keys(&mut model, "2p");
+        assert_eq!(text(&model), "dabcabcef\n");
+        keys(&mut model, "u");
+        assert_eq!(text(&model), "def\n", "one undo removes both copies");
+        keys(&mut model, "p2u");
+        assert_eq!(text(&model), "abcdef\n", "2u undoes the paste and the 3x");
+    }
+
+    #[test]
+    fn gi_resumes_insert_where_it_was_left() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "ab\ncd\n").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let mut keys = |model: &mut EditorModel, keys: &str| {
+            for c in keys.chars() {
+                let key = match c {
+                    '\x1b' => KeyEvent {
+                        code: KeyCode::Esc,
+                        mods: KeyModifiers::empty(),
+                    },
+                    c => key_evt(c),
+                };
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        // Without a last insert position `gi` is `i`.
+        keys(&mut model, "lgi\x1b");
+        assert_eq!(model.active_view().cursor, Position::new(0, 1));
+        keys(&mut model, "jlixy\x1b");
+        assert_eq!(model.active_view().cursor, Position::new(1, 2));
+        keys(&mut model, "kgiz\x1b");
+        assert_eq!(model.state().active_buffer().line(1).unwrap(), "cxyzd\n");
+
+        // The position is clamped to text that has since shrunk.
+        keys(&mut model, "0D");
+        keys(&mut model, "gi");
+        assert_eq!(model.state().mode, Mode::Insert);
+        assert_eq!(model.active_view().cursor, Position::new(1, 0));
+    }
+
+    #[test]
+    fn insert_ctrl_w_and_ctrl_u_delete_within_the_insert_run() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "one\n  x\n").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let text = |m: &EditorModel| {
+            let buf = m.state().active_buffer();
+            buf.slice_bytes(0, buf.len_bytes())
+        };
+        let mut press = |model: &mut EditorModel, code: KeyCode, mods: KeyModifiers| {
+            let act = translate_key(
+                model.state().mode,
+                model.state().command_line.buffer(),
+                &KeyEvent { code, mods },
+            )
+            .unwrap();
+            dispatch(act, model, &mut sticky, &[])
+        };
+        model.active_view_mut().cursor = Position::new(1, 3);
+        press(&mut model, KeyCode::Char('i'), KeyModifiers::empty());
+        for c in " foo.bar".chars() {
+            press(&mut model, KeyCode::Char(c), KeyModifiers::empty());
+        }
+        assert_eq!(text(&model), "one\n  x foo.bar\n");
+        press(&mut model, KeyCode::Char('w'), KeyModifiers::CTRL);
+        assert_eq!(text(&model), "one\n  x foo.\n");
+        press(&mut model, KeyCode::Char('w'), KeyModifiers::CTRL);
+        assert_eq!(text(&model), "one\n  x foo\n");
+        // Ctrl-U keeps the indent, then clears it on a second press.
+        press(&mut model, KeyCode::Char('u'), KeyModifiers::CTRL);
+        assert_eq!(text(&model), "one\n  \n");
+        assert_eq!(model.active_view().cursor, Position::new(1, 2));
+        press(&mut model, KeyCode::Char('u'), KeyModifiers::CTRL);
+        assert_eq!(text(&model), "one\n\n");
+        // At column 0 it joins with the line above, like Backspace.
+        let joined = press(&mut model, KeyCode::Char('w'), KeyModifiers::CTRL);
+        assert!(joined.buffer_replaced);
+        assert_eq!(text(&model), "one\n");
+        assert_eq!(model.active_view().cursor, Position::new(0, 3));
+        press(&mut model, KeyCode::Esc, KeyModifiers::empty());
+        // The deletions belong to the insert run: one undo restores all.
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        assert_eq!(text(&model), "one\n  x\n");
+    }
+
+    #[test]
+    fn insert_tab_follows_expandtab_and_softtabstop() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "x\n").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let line = |m: &EditorModel| m.state().active_buffer().line(0).unwrap();
+        let mut press = |model: &mut EditorModel, code: KeyCode| {
+            let act = translate_key(
+                model.state().mode,
+                model.state().command_line.buffer(),
+                &KeyEvent {
+                    code,
+                    mods: KeyModifiers::empty(),
+                },
+            )
+            .unwrap();
+            dispatch(act, model, &mut sticky, &[])
+        };
+        model.active_view_mut().cursor.byte = 1;
+        press(&mut model, KeyCode::Char('i'));
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "x\t\n");
+        press(&mut model, KeyCode::Backspace);
+
+        // Spaces up to the soft stop; reaching a tab stop, they become a tab.
+        model.state_mut().options.apply_set("sts=4").unwrap();
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "x   \n");
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "x\t\n");
+        assert_eq!(model.active_view().cursor, Position::new(0, 2));
+        press(&mut model, KeyCode::Backspace);
+
+        model.state_mut().options.apply_set("et").unwrap();
+        press(&mut model, KeyCode::Tab);
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "x       \n");
+        press(&mut model, KeyCode::Char('y'));
+        press(&mut model, KeyCode::Backspace);
+        // Backspace deletes the spaces back to the previous soft stop.
+        press(&mut model, KeyCode::Backspace);
+        assert_eq!(line(&model), "x   \n");
+        press(&mut model, KeyCode::Backspace);
+        assert_eq!(line(&model), "x\n");
+        press(&mut model, KeyCode::Backspace);
+        assert_eq!(line(&model), "\n");
+
+        // Without 'softtabstop', 'expandtab' fills to the next tab stop.
+        model.state_mut().options.apply_set("sts=0").unwrap();
+        press(&mut model, KeyCode::Char('a'));
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "a       \n");
+        press(&mut model, KeyCode::Backspace);
+        assert_eq!(line(&model), "a      \n");
+
+        // A negative 'softtabstop' follows 'shiftwidth'.
+        model.state_mut().options.apply_set("sts=-1").unwrap();
+        model.state_mut().options.apply_set("sw=2").unwrap();
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "a       \n");
+        press(&mut model, KeyCode::Tab);
+        assert_eq!(line(&model), "a         \n");
+        press(&mut model, KeyCode::Backspace);
+        assert_eq!(line(&model), "a       \n");
+    }
+
+    #[test]
+    fn insert_text_is_one_edit_and_one_undo_step() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "xy\n").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let text = |m: &EditorModel| {
+            let buf = m.state().active_buffer();
+            buf.slice_bytes(0, buf.len_bytes())
+        };
+        model.active_view_mut().cursor.byte = 1;
+        dispatch(
+            Action::ModeChange(ModeChange::EnterInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::Edit(EditKind::InsertGrapheme("a".into())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        let result = dispatch(
+            Action::Edit(EditKind::InsertText("12\n\u{e9}34".into())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(
+            result.buffer_replaced,
+            "new lines repaint from the cursor down"
+        );
+        assert_eq!(text(&model), "xa12\n\u{e9}34y\n");
+        assert_eq!(model.active_view().cursor, Position::new(1, 4));
+        dispatch(
+            Action::ModeChange(ModeChange::LeaveInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        assert_eq!(text(&model), "xay\n");
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        assert_eq!(text(&model), "xy\n");
+    }
+
+    #[test]
+    fn undo_does_not_clear_dirty() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        dispatch(
+            Action::ModeChange(ModeChange::EnterInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::Edit(EditKind::InsertGrapheme("a".into())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::ModeChange(ModeChange::LeaveInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(model.state().dirty());
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        assert!(model.state().dirty(), "dirty should remain true after undo");
+    }
+
+    #[test]
+    fn write_clears_then_new_edit_sets_dirty_again() {
+        reset_translator();
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("dirty_cycle.txt");
+        let buffer = Buffer::from_str("t", "start").unwrap();
+        let state = core_state::EditorState::new(buffer);
+        let mut model = EditorModel::new(state);
+        model.state_mut().set_file_name(Some(file_path.clone()));
+        let mut sticky = None;
+        dispatch(
+            Action::ModeChange(ModeChange::EnterInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::Edit(EditKind::InsertGrapheme("x".into())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::ModeChange(ModeChange::LeaveInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(model.state().dirty());
+        dispatch(Action::CommandStart, &mut model, &mut sticky, &[]);
+        dispatch(Action::CommandChar('w'), &mut model, &mut sticky, &[]);
+        dispatch(
+            Action::CommandExecute(":w".into()),
             &mut model,
             &mut sticky,
             &[],
         );
-        assert!(model.state().dirty, "dirty should set again after new edit");
+        assert!(!model.state().dirty(), "dirty should clear after write");
+        dispatch(
+            Action::ModeChange(ModeChange::EnterInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        dispatch(
+            Action::Edit(EditKind::InsertGrapheme("y".into())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(
+            model.state().dirty(),
+            "dirty should set again after new edit"
+        );
     }
 
     #[test]
@@ -2110,31 +3054,34 @@ mod tests {
     #[test]
     fn operator_metrics_numbered_ring_rotation() {
         reset_translator();
-        // Build buffer with many distinct words so each yank is unique
-        let text = "w1 w2 w3 w4 w5 w6 w7 w8 w9 w10 w11 w12\n";
+        let text = "w1 w2\nw3\nw4\n";
         let buffer = Buffer::from_str("t", text).unwrap();
         let state = core_state::EditorState::new(buffer);
         let mut model = EditorModel::new(state);
         let mut sticky = None;
-        // Perform more than ring capacity yanks (Registers::MAX == 10)
-        for _ in 0..12 {
+        // Yanks fill "0 and the small delete fills "-; only `dd` shifts "1-"9,
+        // and with "9 still empty nothing rotates out.
+        for keys in ["yw", "yw", "dw", "dd", "dd"] {
+            let mut keys = keys.chars();
             translate_key(
                 model.state().mode,
                 model.state().command_line.buffer(),
-                &key('y'),
+                &key(keys.next().unwrap()),
             );
             let act = translate_key(
                 model.state().mode,
                 model.state().command_line.buffer(),
-                &key('w'),
+                &key(keys.next().unwrap()),
             )
             .unwrap();
             dispatch(act, &mut model, &mut sticky, &[]);
         }
         let snap = model.state().operator_metrics_snapshot();
-        assert_eq!(snap.operator_yank, 12);
-        // Rotations should be >= (yanks - capacity) i.e. at least 2
-        assert!(snap.numbered_ring_rotations >= 2);
+        assert_eq!(snap.operator_yank, 2);
+        assert_eq!(snap.numbered_ring_rotations, 0);
+        let regs = &model.state().registers;
+        assert_eq!(regs.numbered()[..3], ["w1 ", "w3\n", "w2\n"]);
+        assert_eq!(regs.small_delete(), "w1 ");
     }
 
     #[test]
@@ -2266,4 +3213,1126 @@ mod tests {
         assert!(model.state().registers.unnamed.contains("a3"));
         assert!(!model.state().registers.unnamed.contains("a4"));
     }
+
+    #[test]
+    fn search_prompt_n_and_noh() {
+        reset_translator();
+        let buffer = Buffer::from_str(
+            "t",
+            "one foo
+two
+foo three
+",
+        )
+        .unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let key = match ch {
+                    '\n' => KeyEvent {
+                        code: KeyCode::Enter,
+                        mods: KeyModifiers::empty(),
+                    },
+                    c => key_evt(c),
+                };
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        let message = |model: &EditorModel| {
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.clone())
+        };
+
+        keys("/foo\n", &mut model);
+        assert!(!model.state().command_line.is_active());
+        assert_eq!(model.active_view().cursor, Position::new(0, 4));
+        assert_eq!(model.state().registers.search(), "foo");
+        assert!(model.state().search_highlight().is_some());
+        keys("n", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(2, 0));
+        keys("n", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(0, 4));
+        assert_eq!(
+            message(&model).as_deref(),
+            Some("search hit BOTTOM, continuing at TOP")
+        );
+        assert_eq!(model.state().jump_mark(), Some(Position::new(2, 0)));
+        keys("N", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(2, 0));
+
+        // `?` searches backward and `n` then keeps that direction.
+        keys("?two\n", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(1, 0));
+        assert!(!model.state().search_forward());
+
+        keys(":noh\n", &mut model);
+        assert!(model.state().search_highlight().is_none());
+        keys("n", &mut model);
+        assert!(model.state().search_highlight().is_some(), "n resumes");
+
+        keys("/nope\n", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(1, 0));
+        assert_eq!(
+            message(&model).as_deref(),
+            Some("E486: Pattern not found: nope")
+        );
+    }
+
+    #[test]
+    fn bracket_d_and_diag_walk_diagnostics() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "one\ntwo\nthree\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let key = match ch {
+                    '\n' => KeyEvent {
+                        code: KeyCode::Enter,
+                        mods: KeyModifiers::empty(),
+                    },
+                    c => key_evt(c),
+                };
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        keys(":diag\n", &mut model);
+        let state = model.state();
+        let message = state.ephemeral_status.as_ref().map(|m| m.text.as_str());
+        assert_eq!(message, Some("No diagnostics"));
+
+        let active = model.state().active;
+        let diag = |severity, line, byte, message: &str| core_state::Diagnostic {
+            severity,
+            start: Position::new(line, byte),
+            end: Position::new(line, byte + 1),
+            message: message.into(),
+        };
+        model.state_mut().diagnostics.set(
+            active,
+            vec![
+                diag(core_state::Severity::Warning, 1, 1, "odd"),
+                diag(core_state::Severity::Error, 2, 2, "bad\ndetail"),
+            ],
+        );
+        keys("]d", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(1, 1));
+        keys("2]d", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(1, 1), "wraps");
+        keys("[d", &mut model);
+        assert_eq!(model.active_view().cursor, Position::new(2, 2));
+        assert_eq!(model.state().jump_mark(), Some(Position::new(1, 1)));
+
+        keys(":diag\n", &mut model);
+        assert_eq!(
+            model.state().message_lines,
+            ["2:2 warning: odd", "3:3 error: bad"]
+        );
+    }
+
+    #[test]
+    fn ctrl_n_cycles_buffer_words_and_triggers_open_completions() {
+        reset_translator();
+        let buffer = Buffer::from_str(
+            "t",
+            "food fork
+
+",
+        )
+        .unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let text = |m: &EditorModel| m.state().active_buffer().line(1).unwrap();
+        let mut press = |model: &mut EditorModel, c: char, mods: KeyModifiers| {
+            let st = model.state();
+            let key = KeyEvent {
+                code: KeyCode::Char(c),
+                mods,
+            };
+            if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                dispatch(act, model, &mut sticky, &[]);
+            }
+        };
+        model.active_view_mut().cursor = Position::new(1, 0);
+        for c in "ifo".chars() {
+            press(&mut model, c, KeyModifiers::empty());
+        }
+        press(&mut model, 'n', KeyModifiers::CTRL);
+        assert_eq!(text(&model), "food\n");
+        press(&mut model, ' ', KeyModifiers::CTRL);
+        assert_eq!(text(&model), "fork\n");
+        assert_eq!(model.active_view().cursor, Position::new(1, 4));
+        press(&mut model, 'n', KeyModifiers::CTRL);
+        assert_eq!(text(&model), "fo\n", "past the last match: the prefix");
+        press(&mut model, 'p', KeyModifiers::CTRL);
+        assert_eq!(text(&model), "fork\n");
+        // Typing narrows; Ctrl-E restores what was typed.
+        press(&mut model, 'o', KeyModifiers::empty());
+        press(&mut model, 'e', KeyModifiers::CTRL);
+        assert!(!model.state().completion.is_active(), "no match left");
+        assert_eq!(text(&model), "forko\n");
+        press(&mut model, ' ', KeyModifiers::empty());
+        press(&mut model, 'f', KeyModifiers::empty());
+        press(&mut model, 'o', KeyModifiers::empty());
+        press(&mut model, 'o', KeyModifiers::empty());
+        press(&mut model, 'n', KeyModifiers::CTRL);
+        assert_eq!(text(&model), "forko food\n");
+        press(&mut model, 'e', KeyModifiers::CTRL);
+        assert_eq!(text(&model), "forko foo\n");
+        press(&mut model, 'x', KeyModifiers::empty());
+        press(&mut model, 'n', KeyModifiers::CTRL);
+        assert_eq!(
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.as_str()),
+            Some("Pattern not found")
+        );
+
+        // With a server attached, its trigger characters open a completion
+        // that waits for the server's items.
+        let active = model.state().active;
+        model.state_mut().completion.attach(active, vec!['.']);
+        press(&mut model, '.', KeyModifiers::empty());
+        let state = model.state_mut();
+        let request = state.completion.take_lsp_request().unwrap();
+        assert_eq!(
+            (request.position, request.trigger),
+            (Position::new(1, 11), Some('.'))
+        );
+        press(&mut model, 'l', KeyModifiers::empty());
+        assert_eq!(model.state().completion.active().unwrap().prefix, "l");
+        dispatch(
+            Action::ModeChange(crate::ModeChange::LeaveInsert),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert!(!model.state().completion.is_active());
+
+        // The completion edits undo with the rest of the insert.
+        dispatch(Action::Undo { count: 1 }, &mut model, &mut sticky, &[]);
+        assert_eq!(text(&model), "\n");
+    }
+
+    #[test]
+    fn gd_jumps_through_tags_and_ctrl_t_returns() {
+        reset_translator();
+        let dir = std::env::temp_dir().join(format!("oxidized-goto-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main_text = "fn main() {\n    helper();\n}\n";
+        std::fs::write(dir.join("main.rs"), main_text).unwrap();
+        std::fs::write(dir.join("util.rs"), "// util\npub fn helper() {}\n").unwrap();
+        std::fs::write(
+            dir.join("tags"),
+            "helper\tutil.rs\t/^pub fn helper() {}$/\n",
+        )
+        .unwrap();
+        let buffer = Buffer::from_str("main.rs", main_text).unwrap();
+        let mut state = core_state::EditorState::new(buffer);
+        state.active_meta_mut().path = Some(dir.join("main.rs"));
+        let mut model = EditorModel::new(state);
+        let mut sticky = None;
+        let mut keys = |keys: &str, mods: KeyModifiers, model: &mut EditorModel| {
+            for c in keys.chars() {
+                let st = model.state();
+                let key = KeyEvent {
+                    code: KeyCode::Char(c),
+                    mods,
+                };
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        let ephemeral = |model: &EditorModel| {
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.clone())
+        };
+
+        keys("t", KeyModifiers::CTRL, &mut model);
+        assert_eq!(ephemeral(&model).as_deref(), Some("E73: Tag stack empty"));
+        model.active_view_mut().cursor = Position::new(1, 6);
+        keys("gd", KeyModifiers::empty(), &mut model);
+        assert_eq!(model.state().active_meta().path, Some(dir.join("util.rs")));
+        assert_eq!(model.active_view().cursor, Position::new(1, 7));
+        assert_eq!(model.state().tags.depth(), 1);
+        keys("t", KeyModifiers::CTRL, &mut model);
+        assert_eq!(model.state().active_meta().path, Some(dir.join("main.rs")));
+        assert_eq!(model.active_view().cursor, Position::new(1, 6));
+
+        // `<C-w>d` opens the definition in a new window above.
+        keys("w", KeyModifiers::CTRL, &mut model);
+        keys("d", KeyModifiers::empty(), &mut model);
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.state().active_meta().path, Some(dir.join("util.rs")));
+
+        model.active_view_mut().cursor = Position::new(0, 0);
+        keys("gd", KeyModifiers::empty(), &mut model);
+        assert_eq!(
+            ephemeral(&model).as_deref(),
+            Some("E426: Tag not found: util")
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn k_requests_hover_and_page_keys_scroll_it_until_the_cursor_moves() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "let value = 1;\n\n\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut keys = |keys: &str, mods: KeyModifiers, model: &mut EditorModel| {
+            for c in keys.chars() {
+                let st = model.state();
+                let key = KeyEvent {
+                    code: KeyCode::Char(c),
+                    mods,
+                };
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        model.active_view_mut().cursor = Position::new(0, 5);
+        keys("K", KeyModifiers::empty(), &mut model);
+        assert_eq!(
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.as_str()),
+            Some("E149: Sorry, no help for value")
+        );
+
+        let active = model.state().active;
+        model.state_mut().hover.attach(active);
+        keys("K", KeyModifiers::empty(), &mut model);
+        let request = model.state_mut().hover.take_request().unwrap();
+        assert_eq!(request.position, Position::new(0, 5));
+        let lines = (0..50).map(|i| i.to_string()).collect();
+        assert!(model.state_mut().hover.show(request.generation, lines));
+        keys("d", KeyModifiers::CTRL, &mut model);
+        assert_eq!(model.state().hover.active().unwrap().scroll, 10);
+        assert_eq!(
+            model.active_view().cursor,
+            Position::new(0, 5),
+            "window kept"
+        );
+        keys("j", KeyModifiers::empty(), &mut model);
+        assert!(!model.state().hover.is_active());
+        assert_eq!(model.active_view().cursor.line, 1);
+    }
+
+    #[test]
+    fn gl_and_visual_gl_prompt_for_align_over_the_lines() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "a = 1\nlong = 2\nx: 1\nlong: 2\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut key_sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key_evt(ch)) {
+                    dispatch(act, model, &mut key_sticky, &[]);
+                }
+            }
+        };
+        let line = |model: &EditorModel, i| model.state().active_buffer().line(i).unwrap();
+
+        keys("glj", &mut model);
+        assert_eq!(model.state().command_line.buffer(), ":.,.+1align ");
+        keys("=", &mut model);
+        let cmd = model.state().command_line.buffer().to_string();
+        dispatch(Action::CommandExecute(cmd), &mut model, &mut sticky, &[]);
+        assert_eq!(line(&model, 0), "a    = 1\n");
+        assert_eq!(line(&model, 1), "long = 2\n");
+
+        keys("jjvjgL", &mut model);
+        assert_eq!(model.state().mode, core_state::Mode::Normal);
+        assert_eq!(model.state().command_line.buffer(), ":.,.+1align! ");
+        keys(":", &mut model);
+        let cmd = model.state().command_line.buffer().to_string();
+        dispatch(Action::CommandExecute(cmd), &mut model, &mut sticky, &[]);
+        assert_eq!(line(&model, 2), "   x: 1\n");
+        assert_eq!(line(&model, 3), "long: 2\n");
+
+        dispatch(
+            Action::CommandExecute(":align".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert_eq!(
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.as_str()),
+            Some("E471: Argument required")
+        );
+    }
+
+    #[test]
+    fn iabbrev_expands_after_a_non_keyword_and_undoes_with_the_insert() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut key_sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key_evt(ch)) {
+                    dispatch(act, model, &mut key_sticky, &[]);
+                }
+            }
+        };
+        let line = |model: &EditorModel| model.state().active_buffer().line(0).unwrap();
+
+        dispatch(
+            Action::CommandExecute(":iab teh the".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        keys("iteh steh,teh", &mut model);
+        assert_eq!(line(&model), "the steh,teh\n");
+        let esc = KeyEvent {
+            code: KeyCode::Esc,
+            mods: KeyModifiers::empty(),
+        };
+        let act = translate_key(model.state().mode, "", &esc).unwrap();
+        dispatch(act, &mut model, &mut sticky, &[]);
+        assert_eq!(line(&model), "the steh,the\n");
+        keys("u", &mut model);
+        assert_eq!(line(&model), "\n");
+
+        dispatch(
+            Action::CommandExecute(":abclear".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        keys("iteh ", &mut model);
+        assert_eq!(line(&model), "teh \n");
+        dispatch(
+            Action::CommandExecute(":iunabbrev teh".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        assert_eq!(
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.as_str()),
+            Some("E24: No such abbreviation")
+        );
+    }
+
+    #[test]
+    fn zf_zo_zc_za_fold_lines_and_motions_skip_them() {
+        reset_translator();
+        let buffer = Buffer::from_str("t", "a\nb\nc\nd\ne\nf\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key_evt(ch)) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        let closed = |model: &EditorModel, line| model.active_view().folds.closed_at(line);
+
+        keys("jzf2j", &mut model);
+        assert_eq!(closed(&model, 3), Some(1..=3));
+        assert_eq!(model.active_view().cursor, Position::new(1, 0));
+        // `j` / `k` step over the closed fold as one line.
+        keys("j", &mut model);
+        assert_eq!(model.active_view().cursor.line, 4);
+        keys("k", &mut model);
+        assert_eq!(model.active_view().cursor.line, 1);
+        keys("kj", &mut model);
+        assert_eq!(model.active_view().cursor.line, 1);
+
+        keys("zo", &mut model);
+        assert_eq!(closed(&model, 3), None);
+        keys("jjzc", &mut model);
+        assert_eq!(closed(&model, 1), Some(1..=3));
+        assert_eq!(model.active_view().cursor.line, 1, "zc rests on the fold");
+        keys("za", &mut model);
+        assert_eq!(closed(&model, 1), None);
+
+        // Visual `zf` folds the selected lines and leaves Visual mode.
+        keys("3jvjzf", &mut model);
+        assert_eq!(closed(&model, 5), Some(4..=5));
+        assert_eq!(model.state().mode, core_state::Mode::Normal);
+
+        keys("kkkkzo", &mut model);
+        assert_eq!(model.active_view().cursor.line, 0);
+        assert_eq!(
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.as_str()),
+            Some("E490: No fold found")
+        );
+
+        // Deleting the line above moves both folds up with their text.
+        keys("dd", &mut model);
+        assert_eq!(
+            model.state().active_buffer().line(0).as_deref(),
+            Some("b\n")
+        );
+        let folds: Vec<_> = model
+            .active_view()
+            .folds
+            .iter()
+            .map(|f| f.start..=f.end)
+            .collect();
+        assert_eq!(folds, [0..=2, 3..=4]);
+    }
+
+    #[test]
+    fn diffsplit_compares_buffers_and_do_dp_copy_hunks() {
+        reset_translator();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+        std::fs::write(&path, "a\nB\nc\nd\ne\n").unwrap();
+        let buffer = Buffer::from_str("t", "a\nb\nc\nd\n").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut cmd_sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key_evt(ch)) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        let text = |model: &EditorModel, id| {
+            model
+                .state()
+                .buffers
+                .get(core_state::BufferId(id))
+                .unwrap()
+                .buffer
+                .slice_bytes(0, usize::MAX)
+        };
+        let bound = |model: &EditorModel| {
+            model
+                .views()
+                .iter()
+                .filter(|v| model.view_manager().is_scroll_bound(v.id))
+                .count()
+        };
+
+        keys("dp", &mut model);
+        assert_eq!(
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.as_str()),
+            Some("E99: Current buffer is not in diff mode")
+        );
+        dispatch(
+            Action::CommandExecute(format!(":diffsplit {}", path.display())),
+            &mut model,
+            &mut cmd_sticky,
+            &[],
+        );
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(bound(&model), 2);
+        let diff = &model.state().diff;
+        assert_eq!(diff.hunks().len(), 2);
+
+        // `do` takes the other buffer's line, `dp` puts the added one back.
+        keys("jdo", &mut model);
+        assert_eq!(text(&model, 2), "a\nb\nc\nd\ne\n");
+        keys("jjjdp", &mut model);
+        assert_eq!(text(&model, 1), "a\nb\nc\nd\ne\n");
+        assert!(model.state().diff.hunks().is_empty());
+        keys("u", &mut model);
+        assert_eq!(text(&model, 2), "a\nB\nc\nd\ne\n");
+        // The runtime refreshes the comparison after every edit.
+        let state = model.state_mut();
+        assert!(state.diff.refresh(&state.buffers));
+        assert_eq!(model.state().diff.hunks().len(), 1);
+
+        dispatch(
+            Action::CommandExecute(":diffoff".into()),
+            &mut model,
+            &mut cmd_sticky,
+            &[],
+        );
+        assert!(!model.state().diff.is_active());
+        assert_eq!(bound(&model), 0);
+    }
+
+    #[test]
+    fn grep_queues_grepprg_and_cc_opens_quickfix_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        std::fs::write(&a, "foo\n").unwrap();
+        std::fs::write(&b, "x\ny\n  foo\n").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut ex = |cmd: &str, model: &mut EditorModel| {
+            dispatch(Action::CommandExecute(cmd.into()), model, &mut sticky, &[]);
+            let msg = model.state().ephemeral_status.as_ref();
+            msg.map(|m| m.text.clone()).unwrap_or_default()
+        };
+
+        assert_eq!(ex(":cc", &mut model), "E42: No Errors");
+        assert_eq!(ex(":grep", &mut model), "E471: Argument required");
+        let state = model.state_mut();
+        let grepprg = core_config::options::OptionValue::String("rg --vimgrep".into());
+        state.options.set("grepprg", grepprg).unwrap();
+        ex(":grep! -w foo", &mut model);
+        let state = model.state_mut();
+        let request = state.grep.take().unwrap();
+        assert_eq!(request.command, "rg --vimgrep -w foo");
+        let lines = [
+            format!("{}:1:1:foo", a.display()),
+            format!("{}:3:3:  foo", b.display()),
+        ];
+        let output = core_events::GrepOutput {
+            id: request.id,
+            lines: lines.to_vec(),
+            done: None,
+        };
+        quickfix::apply_grep_output(&output, &mut model);
+        // `!`: no jump to the first match.
+        assert_eq!(model.state().file_name(), None);
+        assert_eq!(model.state().quickfix.len(), 2);
+
+        assert_eq!(ex(":cc 2", &mut model), "(2 of 2): foo");
+        assert_eq!(model.state().file_name(), Some(b.as_path()));
+        assert_eq!(model.active_view().cursor, Position::new(2, 2));
+        ex(":cc 1", &mut model);
+        assert_eq!(model.state().file_name(), Some(a.as_path()));
+        // Past the end is the last item; the other file's buffer is reused.
+        let buffers = model.state().buffers.len();
+        ex(":cc 9", &mut model);
+        assert_eq!(model.state().quickfix.index, 1);
+        assert_eq!(model.state().buffers.len(), buffers);
+    }
+
+    #[test]
+    fn make_fills_quickfix_when_done_and_jobs_stream_into_a_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.c");
+        std::fs::write(&src, "int\nmain\n").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut ex = |cmd: &str, model: &mut EditorModel| {
+            dispatch(Action::CommandExecute(cmd.into()), model, &mut sticky, &[]);
+            let msg = model.state().ephemeral_status.as_ref();
+            msg.map(|m| m.text.clone()).unwrap_or_default()
+        };
+        let batch = |id, lines: &[String], status: Option<i32>| core_events::JobOutput {
+            id,
+            lines: lines.to_vec(),
+            done: status.map(|status| core_events::JobDone {
+                status: Some(status),
+                error: None,
+            }),
+        };
+
+        ex(":make -k", &mut model);
+        let request = model.state_mut().jobs.take().pop().unwrap();
+        assert_eq!(request.command, "make -k");
+        let lines = [
+            "cc -c a.c".to_string(),
+            format!("{}:2:3: error: bad", src.display()),
+        ];
+        job::apply_job_output(&batch(request.id, &lines, None), &mut model);
+        assert_eq!(model.state().quickfix.len(), 1);
+        // The first error opens once make has finished.
+        assert_eq!(model.state().file_name(), None);
+        job::apply_job_output(&batch(request.id, &[], Some(2)), &mut model);
+        assert_eq!(model.state().file_name(), Some(src.as_path()));
+        assert_eq!(model.active_view().cursor, Position::new(1, 2));
+        assert_eq!(
+            model.state().jobs.summary(),
+            Some("make -k: exit 2, 1 error")
+        );
+
+        assert_eq!(ex(":job", &mut model), "E471: Argument required");
+        ex(":job ./run.sh", &mut model);
+        let request = model.state_mut().jobs.take().pop().unwrap();
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(
+            model.state().active_buffer().name,
+            format!("[Job {}] ./run.sh", request.id)
+        );
+        let out = ["building".to_string(), "ok".to_string()];
+        let result = job::apply_job_output(&batch(request.id, &out, None), &mut model);
+        assert!(result.buffer_replaced);
+        assert_eq!(
+            model.state().active_buffer().line(1).as_deref(),
+            Some("ok\n")
+        );
+        ex(":jobs", &mut model);
+        assert_eq!(
+            model.state().message_lines,
+            [format!("{:>3}  ./run.sh", request.id)]
+        );
+        assert_eq!(ex(":jobstop 99", &mut model), "E900: Invalid job id: 99");
+        assert_eq!(ex(":jobstop", &mut model), "./run.sh: cancelled");
+        assert_eq!(model.state_mut().jobs.take_cancelled(), [request.id]);
+        let late = job::apply_job_output(&batch(request.id, &out, Some(0)), &mut model);
+        assert!(!late.dirty);
+    }
+
+    #[test]
+    fn defer_and_timer_queue_ex_callbacks_until_stopped() {
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut ex = |cmd: &str, model: &mut EditorModel| {
+            dispatch(Action::CommandExecute(cmd.into()), model, &mut sticky, &[]);
+            let msg = model.state().ephemeral_status.as_ref();
+            msg.map(|m| m.text.clone()).unwrap_or_default()
+        };
+
+        assert_eq!(ex(":defer 500", &mut model), "E471: Argument required");
+        assert_eq!(
+            ex(":timer soon w", &mut model),
+            "E475: Invalid argument: soon"
+        );
+        assert_eq!(ex(":defer 500 :w", &mut model), "Timer 1 started");
+        assert_eq!(ex(":timer 1000 set number", &mut model), "Timer 2 started");
+        let requests = model.state_mut().timers.take();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            (requests[1].delay, requests[1].repeat),
+            (std::time::Duration::from_secs(1), true)
+        );
+        ex(":timers", &mut model);
+        assert_eq!(
+            model.state().message_lines,
+            [
+                format!("  1  {:<16}  :w", "in 500 ms"),
+                format!("  2  {:<16}  :set number", "every 1000 ms"),
+            ]
+        );
+        let state = model.state_mut();
+        assert_eq!(
+            state.timer_fired(1),
+            Some(core_state::TimerCallback::Ex("w".into()))
+        );
+        assert_eq!(ex(":timerstop 1", &mut model), "E475: Invalid argument: 1");
+        assert_eq!(ex(":timerstop", &mut model), "1 timer stopped");
+        assert_eq!(model.state_mut().timers.take_cancelled(), [2]);
+        assert_eq!(ex(":timers", &mut model), "No timers active");
+    }
+
+    #[test]
+    fn files_changed_on_disk_reload_or_warn_but_own_writes_do_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        dispatch(
+            Action::CommandExecute(format!(":e {}", path.display())),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        let state = model.state_mut();
+        let (files, _) = state.watched_paths();
+        assert_eq!(files, [path.clone()].into());
+        state.file_watch.track(&files);
+        model.active_view_mut().cursor = Position::new(2, 3);
+        let changed = |model: &mut EditorModel| {
+            let result = file_watch::apply_file_change(&path, model);
+            let msg = model.state_mut().ephemeral_status.take();
+            (result, msg.map(|m| m.text).unwrap_or_default())
+        };
+
+        dispatch(
+            Action::CommandExecute(":w".into()),
+            &mut model,
+            &mut sticky,
+            &[],
+        );
+        let (result, _) = changed(&mut model);
+        assert!(!result.dirty, "the editor's own write");
+
+        std::fs::write(&path, "changed\n").unwrap();
+        let (_, msg) = changed(&mut model);
+        assert!(msg.starts_with("W11: Warning: File"), "{msg}");
+        assert_eq!(
+            model.state().active_buffer().line(0).as_deref(),
+            Some("one\n")
+        );
+
+        model.state_mut().options.apply_set("autoread").unwrap();
+        std::fs::write(&path, "x\n").unwrap();
+        let (result, msg) = changed(&mut model);
+        assert!(result.buffer_replaced);
+        assert_eq!(msg, "");
+        assert_eq!(
+            model.state().active_buffer().line(0).as_deref(),
+            Some("x\n")
+        );
+        assert_eq!(model.active_view().cursor, Position::new(0, 0));
+
+        model.state_mut().set_dirty(true);
+        std::fs::write(&path, "longer text\n").unwrap();
+        let (_, msg) = changed(&mut model);
+        assert!(msg.starts_with("W12: Warning: File"), "{msg}");
+        std::fs::remove_file(&path).unwrap();
+        let (_, msg) = changed(&mut model);
+        assert!(msg.starts_with("E211: File"), "{msg}");
+    }
+
+    #[test]
+    fn tab_completes_paths_and_descends_into_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src").join("main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("setup.txt"), "").unwrap();
+        std::fs::write(dir.path().join(".secret"), "").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut send = |action: Action, model: &mut EditorModel| {
+            dispatch(action, model, &mut sticky, &[]);
+            model.state().command_line.buffer().to_string()
+        };
+        let tab = Action::CommandComplete { backward: false };
+        let root = format!("{}/", dir.path().display());
+        send(Action::CommandStart, &mut model);
+        for ch in format!("e {root}s").chars() {
+            send(Action::CommandChar(ch), &mut model);
+        }
+        assert_eq!(send(tab.clone(), &mut model), format!(":e {root}setup.txt"));
+        let menu = model.state().command_line.wildmenu().unwrap();
+        assert_eq!(menu.row(80), " [setup.txt] src/ ");
+        assert_eq!(send(tab.clone(), &mut model), format!(":e {root}src/"));
+        // Past the last match the typed word comes back; Shift-Tab goes back.
+        assert_eq!(send(tab.clone(), &mut model), format!(":e {root}s"));
+        let back = Action::CommandComplete { backward: true };
+        assert_eq!(send(back, &mut model), format!(":e {root}src/"));
+
+        // A lone directory match descends on the next Tab.
+        send(Action::CommandBackspace, &mut model);
+        assert!(model.state().command_line.wildmenu().is_none());
+        assert_eq!(send(tab.clone(), &mut model), format!(":e {root}src/"));
+        assert_eq!(
+            send(tab.clone(), &mut model),
+            format!(":e {root}src/main.rs")
+        );
+
+        // Dotfiles only when asked for; commands without file arguments
+        // and the command name itself are left alone.
+        send(Action::CommandStart, &mut model);
+        for ch in format!("w {root}.").chars() {
+            send(Action::CommandChar(ch), &mut model);
+        }
+        assert_eq!(send(tab.clone(), &mut model), format!(":w {root}.secret"));
+        send(Action::CommandStart, &mut model);
+        for ch in "noh x".chars() {
+            send(Action::CommandChar(ch), &mut model);
+        }
+        assert_eq!(send(tab.clone(), &mut model), ":noh x");
+        assert!(model.state().command_line.wildmenu().is_none());
+    }
+
+    #[test]
+    fn tab_completes_set_options_and_values() {
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut send = |action: Action, model: &mut EditorModel| {
+            dispatch(action, model, &mut sticky, &[]);
+            model.state().command_line.buffer().to_string()
+        };
+        let keys = |line: &str| -> Vec<Action> {
+            let chars = line.chars().map(Action::CommandChar);
+            std::iter::once(Action::CommandStart).chain(chars).collect()
+        };
+        let tab = Action::CommandComplete { backward: false };
+        for action in keys("set nu invrel") {
+            send(action, &mut model);
+        }
+        assert_eq!(send(tab.clone(), &mut model), ":set nu invrelativenumber");
+        for action in keys("se tm=") {
+            send(action, &mut model);
+        }
+        assert_eq!(send(tab.clone(), &mut model), ":se tm=1000");
+        // Enter runs the completed line.
+        for action in keys("set nowr") {
+            send(action, &mut model);
+        }
+        let line = send(tab.clone(), &mut model);
+        assert_eq!(line, ":set nowrap");
+        send(Action::CommandExecute(line), &mut model);
+        assert!(!model.state().options.get_bool("wrap"));
+    }
+
+    #[test]
+    fn list_windows_step_and_location_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        std::fs::write(&a, "foo\nbar\n").unwrap();
+        std::fs::write(&b, "x\nfoo\n").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut ex = |cmd: &str, model: &mut EditorModel| {
+            model.state_mut().ephemeral_status = None;
+            dispatch(Action::CommandExecute(cmd.into()), model, &mut sticky, &[]);
+            let msg = model.state().ephemeral_status.as_ref();
+            msg.map(|m| m.text.clone()).unwrap_or_default()
+        };
+        let state = model.state_mut();
+        let grepprg = core_config::options::OptionValue::String("rg --vimgrep".into());
+        state.options.set("grepprg", grepprg).unwrap();
+        let grep_lines = |model: &mut EditorModel, lines: Vec<String>| {
+            let id = model.state_mut().grep.take().unwrap().id;
+            let done = core_events::GrepDone {
+                status: Some(0),
+                stderr: String::new(),
+                error: None,
+            };
+            let output = core_events::GrepOutput {
+                id,
+                lines,
+                done: Some(done),
+            };
+            quickfix::apply_grep_output(&output, model);
+        };
+
+        ex(":grep! foo", &mut model);
+        let lines = vec![
+            format!("{}:1:1:foo", a.display()),
+            format!("{}:2:1:foo", b.display()),
+        ];
+        grep_lines(&mut model, lines);
+        assert_eq!(ex(":cprev", &mut model), "E553: No more items");
+        assert_eq!(ex(":cnext", &mut model), "(2 of 2): foo");
+        assert_eq!(model.state().file_name(), Some(b.as_path()));
+        assert_eq!(ex(":cn", &mut model), "E553: No more items");
+
+        // `:copen` lists the items below the other windows, on the current
+        // one; the list is read-only and `<CR>` opens the item in the
+        // window above.
+        ex(":copen", &mut model);
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(
+            model.state().quickfix_window(),
+            Some(core_state::ListKind::Quickfix)
+        );
+        let first = model.state().active_buffer().line(0);
+        assert_eq!(first, Some(format!("{}|1 col 1| foo\n", a.display())));
+        assert_eq!(model.active_view().cursor.line, 1);
+        dispatch(
+            Action::MotionWithCount {
+                motion: MotionKind::Up,
+                count: 1,
+            },
+            &mut model,
+            &mut None,
+            &[],
+        );
+        let act = Action::Edit(EditKind::DeleteUnder {
+            count: 1,
+            register: None,
+        });
+        dispatch(act, &mut model, &mut None, &[]);
+        assert_eq!(model.state().active_buffer().line(0), first);
+        let msg = model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(msg.text, NOT_MODIFIABLE_MSG);
+        // `<CR>` is bound to the list window's entries only there.
+        assert_eq!(
+            crate::NormalKeys::for_state(model.state()),
+            crate::NormalKeys::ListWindow
+        );
+        dispatch(Action::ListEnter, &mut model, &mut None, &[]);
+        assert_eq!(model.state().file_name(), Some(a.as_path()));
+        assert_eq!(model.state().quickfix.index, 0);
+        assert_eq!(model.views().len(), 2);
+        // Jumps move the list window's cursor along.
+        ex(":cnext", &mut model);
+        let list = model
+            .views()
+            .iter()
+            .find(|v| v.buffer_id != model.active_view().buffer_id);
+        assert_eq!(list.unwrap().cursor.line, 1);
+        ex(":cclose", &mut model);
+        assert_eq!(model.views().len(), 1);
+
+        // `:lgrep` fills the window's own list, leaving the quickfix list.
+        assert_eq!(ex(":lopen", &mut model), "E776: No location list");
+        ex(":lgrep bar", &mut model);
+        grep_lines(&mut model, vec![format!("{}:2:1:bar", a.display())]);
+        assert_eq!(model.active_view().location_list.len(), 1);
+        assert_eq!(model.state().quickfix.len(), 2);
+        assert_eq!(model.active_view().cursor, Position::new(1, 0));
+        assert_eq!(ex(":lnext", &mut model), "E553: No more items");
+        ex(":lopen", &mut model);
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.state().active_buffer().line_count(), 2);
+        ex(":lclose", &mut model);
+        assert_eq!(model.views().len(), 1);
+
+        // `:ldiag` lists the buffer's diagnostics instead.
+        let state = model.state_mut();
+        let active = state.active;
+        let diagnostic = core_state::diagnostics::Diagnostic {
+            severity: core_state::diagnostics::Severity::Error,
+            start: Position::new(0, 1),
+            end: Position::new(0, 2),
+            message: "bad\ndetail".into(),
+        };
+        state.diagnostics.set(active, vec![diagnostic]);
+        ex(":ldiag", &mut model);
+        assert_eq!(ex(":ll", &mut model), "(1 of 1): error: bad");
+        assert_eq!(model.active_view().cursor, Position::new(0, 1));
+    }
+
+    #[test]
+    fn explorer_lists_directories_and_its_keys_manage_files() {
+        reset_translator();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "alpha\n").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+        let buffer = Buffer::from_str("t", "").unwrap();
+        let mut model = EditorModel::new(core_state::EditorState::new(buffer));
+        let mut sticky = None;
+        let mut cmd_sticky = None;
+        let mut keys = |keys: &str, model: &mut EditorModel| {
+            for ch in keys.chars() {
+                let key = match ch {
+                    '\n' => KeyEvent {
+                        code: KeyCode::Enter,
+                        mods: KeyModifiers::empty(),
+                    },
+                    c => key_evt(c),
+                };
+                let normal_keys = crate::NormalKeys::for_state(model.state());
+                TRANSLATOR.with(|t| t.borrow_mut().set_normal_keys(normal_keys));
+                let st = model.state();
+                if let Some(act) = translate_key(st.mode, st.command_line.buffer(), &key) {
+                    dispatch(act, model, &mut sticky, &[]);
+                }
+            }
+        };
+        let lines = |model: &EditorModel| -> Vec<String> {
+            let buf = model.state().active_buffer();
+            (1..buf.line_count())
+                .filter_map(|i| buf.line(i))
+                .map(|l| l.trim_end().to_string())
+                .filter(|l| !l.is_empty())
+                .collect()
+        };
+        let cursor_line = |model: &EditorModel| {
+            let line = model.active_view().cursor.line;
+            model
+                .state()
+                .active_buffer()
+                .line(line)
+                .unwrap()
+                .trim_end()
+                .to_string()
+        };
+        let message = |model: &EditorModel| {
+            model
+                .state()
+                .ephemeral_status
+                .as_ref()
+                .map(|m| m.text.clone())
+        };
+
+        dispatch(
+            Action::CommandExecute(format!(":Explore {}", dir.path().display())),
+            &mut model,
+            &mut cmd_sticky,
+            &[],
+        );
+        assert_eq!(lines(&model), ["../", "sub/", "a.txt"]);
+        assert_eq!(cursor_line(&model), "sub/");
+        keys("x", &mut model);
+        assert_eq!(message(&model).as_deref(), Some(NOT_MODIFIABLE_MSG));
+
+        // `d` prompts for the directory, `R` for the new name, `D` to delete.
+        keys("d", &mut model);
+        assert_eq!(model.state().command_line.buffer(), ":mkdir ");
+        keys("new\n", &mut model);
+        assert!(dir.path().join("new").is_dir());
+        assert_eq!(cursor_line(&model), "new/");
+        keys("R", &mut model);
+        assert_eq!(model.state().command_line.buffer(), ":rename new");
+        keys("er\n", &mut model);
+        assert!(dir.path().join("newer").is_dir());
+        assert_eq!(lines(&model), ["../", "newer/", "sub/", "a.txt"]);
+        keys("jjD", &mut model);
+        assert_eq!(model.state().command_line.buffer(), ":remove a.txt");
+        keys("\n", &mut model);
+        assert!(!dir.path().join("a.txt").exists());
+        keys("gh", &mut model);
+        assert_eq!(lines(&model)[3], ".hidden");
+
+        // `<CR>` enters a directory, `-` goes back up onto it.
+        keys("k\n", &mut model);
+        assert_eq!(lines(&model), ["../"]);
+        keys("-", &mut model);
+        assert_eq!(cursor_line(&model), "sub/");
+        keys("r", &mut model);
+        assert_eq!(lines(&model), ["../", "sub/", "newer/", ".hidden"]);
+
+        std::fs::write(dir.path().join("b.txt"), "beta\n").unwrap();
+        dispatch(
+            Action::CommandExecute(":E".into()),
+            &mut model,
+            &mut cmd_sticky,
+            &[],
+        );
+        assert_eq!(lines(&model), ["../", "sub/", "newer/", "b.txt", ".hidden"]);
+        keys("r", &mut model);
+        assert_eq!(cursor_line(&model), "sub/", "the cursor stays on its entry");
+        keys("jj\n", &mut model);
+        assert!(model.state().explorer().is_none());
+        assert_eq!(model.state().active_buffer().line(0).unwrap(), "beta\n");
+        dispatch(
+            Action::CommandExecute(":mkdir x".into()),
+            &mut model,
+            &mut cmd_sticky,
+            &[],
+        );
+        assert_eq!(
+            message(&model).as_deref(),
+            Some("E492: Not an editor command: mkdir x")
+        );
+    }
 }
@@ -1,5 +1,9 @@
 //! Mode transition handling (Normal <-> Insert).
 //!
+//! Leaving Insert mode records the cursor (before it steps back onto the
+//! last inserted character) as the buffer's `last_insert`, Vim's `'^`
+//! mark; `gi` enters Insert mode there again.
+//!
 //! Scope (R3 Step 1): minimal synchronous state transition + insert run
 //! coalescing finalization. This keeps mode logic isolated for future
 //! expansions (Visual, Command, Operator-Pending, etc.).
@@ -16,6 +20,7 @@ use crate::ModeChange;
 use core_model::View;
 use core_state::InsertRun;
 use core_state::{EditorState, Mode};
+use core_text::Position;
 
 pub(crate) fn handle_mode_change(
     mc: ModeChange,
@@ -29,7 +34,17 @@ pub(crate) fn handle_mode_change(
             state.mode = Mode::Insert;
             DispatchResult::dirty()
         }
+        ModeChange::ResumeInsert => {
+            if let Some(pos) = state.active_meta().last_insert {
+                view.cursor = clamp_insert_position(state, pos);
+            }
+            state.end_insert_coalescing();
+            state.mode = Mode::Insert;
+            DispatchResult::dirty()
+        }
         ModeChange::LeaveInsert => {
+            super::abbrev::expand(state, view);
+            state.active_meta_mut().last_insert = Some(view.cursor);
             // Determine if we should retreat cursor (Vim parity) BEFORE ending run; consult insert_run.
             let should_retreat =
                 matches!(state.insert_run(), InsertRun::Active { edits, .. } if *edits > 0);
@@ -62,3 +77,18 @@ pub(crate) fn handle_mode_change(
         }
     }
 }
+
+/// `pos` moved onto the buffer's text, which may have changed since it was
+/// recorded: the last line at most, and a character boundary no further
+/// than the end of its line (Insert mode may sit past the last character).
+fn clamp_insert_position(state: &EditorState, pos: Position) -> Position {
+    let buffer = state.active_buffer();
+    let line = pos.line.min(buffer.line_count().saturating_sub(1));
+    let text = buffer.line(line).unwrap_or_default();
+    let text = text.trim_end_matches(['\n', '\r']);
+    let mut byte = pos.byte.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    Position::new(line, byte)
+}
@@ -9,17 +9,20 @@
 //! * Unicode correctness: delegates to `core_text::motion::*` which operate on grapheme clusters.
 //! * Evolution: future operator-pending motions and counts will layer here without touching unrelated code.
 //!
+//! `j` / `k` treat a closed fold as one line: they step over it and land on
+//! its first line.
+//!
 //! Forward Roadmap (beyond R3):
 //! * Count-aware motions (e.g. `5j`) applied before invoking the underlying motion primitive.
 //! * Operator-target resolution (e.g. in `dw`, motion result will be paired with an operator kind).
-//! * Horizontal scroll & fold awareness once those concepts enter `View`.
+//! * Horizontal scroll awareness once it enters `View`.
 //! * Scroll-region optimization hints (when combined with layout + capabilities) to minimize redraw.
 //!
 //! Testing: parity covered indirectly via original dispatcher tests moved intact. Additional
 //! focused motion tests will be added when count/operator semantics arrive.
 
 use super::DispatchResult;
-use crate::MotionKind;
+use crate::{MotionKind, ScrollCursor};
 use core_model::View;
 use core_state::EditorState;
 use core_state::Mode;
@@ -49,14 +52,8 @@ pub(crate) fn handle_motion(
             apply_horizontal_motion(state, &mut view.cursor, motion::line_end);
             *sticky_visual_col = None;
         }
-        MotionKind::Up => {
-            *sticky_visual_col =
-                apply_vertical_motion(state, &mut view.cursor, *sticky_visual_col, motion::up);
-        }
-        MotionKind::Down => {
-            *sticky_visual_col =
-                apply_vertical_motion(state, &mut view.cursor, *sticky_visual_col, motion::down);
-        }
+        MotionKind::Up => vertical_over_folds(state, view, sticky_visual_col, false),
+        MotionKind::Down => vertical_over_folds(state, view, sticky_visual_col, true),
         MotionKind::WordForward => {
             apply_horizontal_motion(state, &mut view.cursor, motion::word_forward);
             *sticky_visual_col = None;
@@ -67,6 +64,8 @@ pub(crate) fn handle_motion(
         }
         MotionKind::PageHalfDown => page_half_down(state, view, sticky_visual_col),
         MotionKind::PageHalfUp => page_half_up(state, view, sticky_visual_col),
+        MotionKind::PageDown => page_down(state, view, sticky_visual_col),
+        MotionKind::PageUp => page_up(state, view, sticky_visual_col),
     }
     // Apply Normal-mode cursor normalization (Vim semantics: block cursor rests on a real cell)
     if matches!(state.mode, Mode::Normal) {
@@ -114,12 +113,73 @@ fn expand_visual_char_selection(
 
 fn resolve_page_metrics(state: &EditorState, total_lines: usize) -> (usize, usize) {
     let height = state.last_text_height.max(1).min(total_lines.max(1));
-    // Vim's 'scroll option defaults to half the window height when unset (0).
-    // Counts are not yet threaded through motions, so we always use the fallback.
-    let jump = (height / 2).max(1);
+    // Vim's 'scroll' option defaults to half the window height when unset
+    // (0); a count on `Ctrl-D` / `Ctrl-U` sets it (see `set_half_page`).
+    let jump = match usize::try_from(state.options.get_number("scroll")) {
+        Ok(lines) if lines > 0 => lines.min(height),
+        _ => (height / 2).max(1),
+    };
     (height, jump)
 }
 
+/// `{count}Ctrl-D` / `{count}Ctrl-U`: the count becomes the `'scroll'`
+/// amount used by later half-page scrolls.
+pub(crate) fn set_half_page(state: &mut EditorState, count: u32) {
+    let _ = state.options.set(
+        "scroll",
+        core_config::options::OptionValue::Number(i64::from(count.max(1))),
+    );
+}
+
+/// `Ctrl-F`: the viewport moves a window height minus two lines of context
+/// (the last page clamps like the half-page scroll); the cursor is pulled
+/// onto the new first line if it was left above it, or to the last line
+/// once the end is already in view.
+fn page_down(state: &mut EditorState, view: &mut View, sticky_visual_col: &mut Option<usize>) {
+    let total_lines = state.active_buffer().line_count();
+    if total_lines == 0 {
+        return;
+    }
+    state.set_jump_mark(view.cursor);
+    let (height, _) = resolve_page_metrics(state, total_lines);
+    let jump = height.saturating_sub(2).max(1);
+    let max_first = total_lines.saturating_sub(height);
+    let new_first = (view.viewport_first_line + jump).min(max_first);
+    let target_line = if new_first == view.viewport_first_line {
+        total_lines - 1
+    } else {
+        view.cursor.line.max(new_first)
+    };
+    view.viewport_first_line = new_first;
+    while view.cursor.line < target_line {
+        *sticky_visual_col =
+            apply_vertical_motion(state, &mut view.cursor, *sticky_visual_col, motion::down);
+    }
+}
+
+/// `Ctrl-B`: the mirror of `page_down`; the cursor is pulled onto the new
+/// last line if it was left below it, or to the first line at the top.
+fn page_up(state: &mut EditorState, view: &mut View, sticky_visual_col: &mut Option<usize>) {
+    let total_lines = state.active_buffer().line_count();
+    if total_lines == 0 {
+        return;
+    }
+    state.set_jump_mark(view.cursor);
+    let (height, _) = resolve_page_metrics(state, total_lines);
+    let jump = height.saturating_sub(2).max(1);
+    let target_line = if view.viewport_first_line == 0 {
+        0
+    } else {
+        let new_first = view.viewport_first_line.saturating_sub(jump);
+        view.viewport_first_line = new_first;
+        view.cursor.line.min(new_first + height - 1)
+    };
+    while view.cursor.line > target_line {
+        *sticky_visual_col =
+            apply_vertical_motion(state, &mut view.cursor, *sticky_visual_col, motion::up);
+    }
+}
+
 fn page_half_down(state: &mut EditorState, view: &mut View, sticky_visual_col: &mut Option<usize>) {
     // New semantics (Phase 5 / Step 0.2): explicit half-page scroll independent of
     // margin-based auto_scroll. We shift the viewport by half the last known text
@@ -165,6 +225,52 @@ fn page_half_up(state: &mut EditorState, view: &mut View, sticky_visual_col: &mu
     }
 }
 
+/// `zz` / `zt` / `zb`: move the viewport so the cursor line sits at the
+/// center, top or bottom, clamped so the last page stays full. With `line`
+/// the cursor first moves to that line (1-based) like `j` / `k` would,
+/// keeping its column.
+pub(crate) fn scroll_cursor(
+    to: ScrollCursor,
+    line: Option<u32>,
+    state: &mut EditorState,
+    view: &mut View,
+    sticky_visual_col: &mut Option<usize>,
+) -> DispatchResult {
+    let total_lines = state.active_buffer().line_count();
+    if total_lines == 0 {
+        return DispatchResult::clean();
+    }
+    let before = (view.viewport_first_line, view.cursor);
+    if let Some(line) = line {
+        let target = (line.max(1) as usize - 1).min(total_lines - 1);
+        while view.cursor.line < target {
+            *sticky_visual_col =
+                apply_vertical_motion(state, &mut view.cursor, *sticky_visual_col, motion::down);
+        }
+        while view.cursor.line > target {
+            *sticky_visual_col =
+                apply_vertical_motion(state, &mut view.cursor, *sticky_visual_col, motion::up);
+        }
+        if matches!(state.mode, Mode::Normal) {
+            motion::normalize_normal_mode_position(state.active_buffer(), &mut view.cursor);
+        }
+    }
+    let (height, _) = resolve_page_metrics(state, total_lines);
+    let cursor_line = view.cursor.line;
+    let first = match to {
+        ScrollCursor::Top => cursor_line,
+        ScrollCursor::Center => cursor_line.saturating_sub((height - 1) / 2),
+        ScrollCursor::Bottom => (cursor_line + 1).saturating_sub(height),
+    };
+    view.viewport_first_line = first.min(total_lines.saturating_sub(height));
+    tracing::trace!(target: "actions.dispatch", ?to, line = cursor_line, first = view.viewport_first_line, "scroll_cursor");
+    if (view.viewport_first_line, view.cursor) != before {
+        DispatchResult::dirty()
+    } else {
+        DispatchResult::clean()
+    }
+}
+
 fn apply_horizontal_motion(
     state: &EditorState,
     cursor: &mut Position,
@@ -172,6 +278,36 @@ fn apply_horizontal_motion(
 ) {
     f(state.active_buffer(), cursor);
 }
+/// One `j` / `k` step in which a closed fold counts as a single line. The
+/// cursor leaves a fold from its last line and enters one on its first.
+fn vertical_over_folds(
+    state: &EditorState,
+    view: &mut View,
+    sticky_visual_col: &mut Option<usize>,
+    down: bool,
+) {
+    let mut pos = view.cursor;
+    if let Some(fold) = view.folds.closed_at(pos.line) {
+        let last = state.active_buffer().line_count().saturating_sub(1);
+        let line = if down {
+            (*fold.end()).min(last)
+        } else {
+            *fold.start()
+        };
+        pos = Position::new(line, 0);
+    }
+    let from = pos.line;
+    let f = if down { motion::down } else { motion::up };
+    *sticky_visual_col = apply_vertical_motion(state, &mut pos, *sticky_visual_col, f);
+    if pos.line == from {
+        return;
+    }
+    if let Some(fold) = view.folds.closed_at(pos.line) {
+        pos = Position::new(*fold.start(), 0);
+    }
+    view.cursor = pos;
+}
+
 fn apply_vertical_motion(
     state: &EditorState,
     cursor: &mut Position,
@@ -192,7 +328,12 @@ mod tests {
         let buffer = Buffer::from_str("t", text).unwrap();
         let state = EditorState::new(buffer);
         // Mirror EditorModel::new logic: single view bound to active buffer index 0, cursor origin
-        let view = View::new(core_model::ViewId(0), 0, Position::origin(), 0);
+        let view = View::new(
+            core_model::ViewId(0),
+            core_state::BufferId(1),
+            Position::origin(),
+            0,
+        );
         (state, view, None)
     }
 
@@ -312,4 +453,70 @@ mod tests {
         handle_motion(MotionKind::PageHalfUp, &mut state, &mut view, &mut sticky);
         assert_eq!(state.jump_mark, Some(after_down));
     }
+
+    #[test]
+    fn page_down_and_up_keep_two_lines_of_context() {
+        // 50 numbered lines plus the empty one after the last newline.
+        let text = mk_buffer(50);
+        let (mut state, mut view, mut sticky) = setup(&text);
+        state.last_text_height = 20;
+        handle_motion(MotionKind::PageDown, &mut state, &mut view, &mut sticky);
+        assert_eq!(view.viewport_first_line, 18);
+        assert_eq!(view.cursor.line, 18, "cursor pulled onto the new page");
+        handle_motion(MotionKind::PageDown, &mut state, &mut view, &mut sticky);
+        assert_eq!(view.viewport_first_line, 31, "clamped to the last page");
+        // The end is in view: the cursor goes to the last line.
+        handle_motion(MotionKind::PageDown, &mut state, &mut view, &mut sticky);
+        assert_eq!(view.viewport_first_line, 31);
+        assert_eq!(view.cursor.line, 50);
+        handle_motion(MotionKind::PageUp, &mut state, &mut view, &mut sticky);
+        assert_eq!(view.viewport_first_line, 13);
+        assert_eq!(view.cursor.line, 32, "cursor pulled onto the new last line");
+        handle_motion(MotionKind::PageUp, &mut state, &mut view, &mut sticky);
+        assert_eq!(view.viewport_first_line, 0);
+        handle_motion(MotionKind::PageUp, &mut state, &mut view, &mut sticky);
+        assert_eq!(view.cursor.line, 0);
+    }
+
+    #[test]
+    fn half_page_count_sets_the_scroll_amount() {
+        let text = mk_buffer(100);
+        let (mut state, mut view, mut sticky) = setup(&text);
+        state.last_text_height = 20;
+        set_half_page(&mut state, 3);
+        handle_motion(MotionKind::PageHalfDown, &mut state, &mut view, &mut sticky);
+        assert_eq!((view.viewport_first_line, view.cursor.line), (3, 3));
+        // The amount sticks for later scrolls in both directions.
+        handle_motion(MotionKind::PageHalfDown, &mut state, &mut view, &mut sticky);
+        handle_motion(MotionKind::PageHalfUp, &mut state, &mut view, &mut sticky);
+        assert_eq!((view.viewport_first_line, view.cursor.line), (3, 3));
+        assert_eq!(state.options.get_number("scroll"), 3);
+    }
+
+    #[test]
+    fn scroll_cursor_centers_tops_and_bottoms_within_range() {
+        // 60 numbered lines plus the empty one after the last newline.
+        let text = mk_buffer(60);
+        let (mut state, mut view, mut sticky) = setup(&text);
+        state.last_text_height = 20;
+        view.cursor.line = 30;
+        let mut z = |to, line, state: &mut EditorState, view: &mut View| {
+            scroll_cursor(to, line, state, view, &mut sticky)
+        };
+        assert!(z(ScrollCursor::Top, None, &mut state, &mut view).dirty);
+        assert_eq!(view.viewport_first_line, 30);
+        z(ScrollCursor::Center, None, &mut state, &mut view);
+        assert_eq!(view.viewport_first_line, 21);
+        z(ScrollCursor::Bottom, None, &mut state, &mut view);
+        assert_eq!(view.viewport_first_line, 11);
+        assert!(!z(ScrollCursor::Bottom, None, &mut state, &mut view).dirty);
+        // Near either end the viewport clamps to the valid range.
+        z(ScrollCursor::Top, Some(58), &mut state, &mut view);
+        assert_eq!(view.cursor.line, 57);
+        assert_eq!(view.viewport_first_line, 41, "last page stays full");
+        z(ScrollCursor::Center, Some(2), &mut state, &mut view);
+        assert_eq!((view.cursor.line, view.viewport_first_line), (1, 0));
+        z(ScrollCursor::Bottom, Some(999), &mut state, &mut view);
+        assert_eq!((view.cursor.line, view.viewport_first_line), (60, 41));
+    }
 }
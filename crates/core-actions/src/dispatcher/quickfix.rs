@@ -0,0 +1,517 @@
+//! Quickfix and location lists (`core_state::quickfix`): `:gr[ep]` /
+//! `:lgr[ep]` and their `add` forms, `:mak[e]`, `:cc` / `:ll`, `:cn[ext]` /
+//! `:cp[revious]`, `:lne[xt]` / `:lp[revious]`, `:cope[n]` / `:ccl[ose]`,
+//! `:lop[en]` / `:lcl[ose]` and `:ldiag`.
+//!
+//! `:grep {args}` runs `'grepprg'` with `args` (in place of `$*`, else
+//! appended) through the runtime and returns straight away; matches fill
+//! the list as the search streams them (`apply_grep_output`). Unless the
+//! command has a `!` or `'grepjump'` is off, the first match is opened when
+//! it arrives. The `l` forms use the current window's location list.
+//! `:make` runs `'makeprg'` the same way as a background job
+//! (`super::job`); its errors open once it has finished.
+//!
+//! A jump opens an item like `gd` opens a definition, reusing a buffer that
+//! already shows the file. From a list window it happens in another window:
+//! the one owning the location list, else the first one not showing a
+//! list. `<CR>` in a list window jumps to the item under the cursor. List
+//! windows are redrawn whenever their list changes, with the cursor on the
+//! current item.
+
+use super::DispatchResult;
+use super::goto::open_path;
+use super::window;
+use core_events::GrepOutput;
+use core_model::{EditorModel, SplitAxis, ViewId};
+use core_state::{GrepTarget, JobSink, ListKind, Mode, QuickfixItem, QuickfixList};
+use core_text::{Buffer, Position};
+use std::time::Duration;
+
+pub(super) enum QuickfixCommand {
+    Grep {
+        location: bool,
+        add: bool,
+        bang: bool,
+        args: String,
+    },
+    Jump {
+        location: bool,
+        nr: Option<usize>,
+    },
+    Step {
+        location: bool,
+        forward: bool,
+    },
+    Window {
+        location: bool,
+        open: bool,
+    },
+    LocationDiagnostics,
+    Make {
+        bang: bool,
+        args: String,
+    },
+}
+
+/// The list a command acts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Quickfix,
+    /// The location list of a window of the current tab page.
+    Location(ViewId),
+}
+
+impl Target {
+    /// The quickfix list, or the active window's location list. In a
+    /// location list window that is the list it shows.
+    fn of(location: bool, model: &EditorModel) -> Self {
+        if !location {
+            return Target::Quickfix;
+        }
+        match model.state().quickfix_window() {
+            Some(ListKind::Location { window }) => Target::Location(ViewId(window)),
+            _ => Target::Location(model.active_view().id),
+        }
+    }
+
+    fn kind(self) -> ListKind {
+        match self {
+            Target::Quickfix => ListKind::Quickfix,
+            Target::Location(id) => ListKind::Location { window: id.0 },
+        }
+    }
+
+    fn list(self, model: &EditorModel) -> Option<&QuickfixList> {
+        match self {
+            Target::Quickfix => Some(&model.state().quickfix),
+            Target::Location(id) => model.view_manager().view(id).map(|v| &v.location_list),
+        }
+    }
+
+    fn list_mut(self, model: &mut EditorModel) -> Option<&mut QuickfixList> {
+        match self {
+            Target::Quickfix => Some(&mut model.state_mut().quickfix),
+            Target::Location(id) => model.view_mut(id).map(|v| &mut v.location_list),
+        }
+    }
+
+    /// The list's error when it has no items.
+    fn empty_msg(self) -> &'static str {
+        match self {
+            Target::Quickfix => "E42: No Errors",
+            Target::Location(_) => "E776: No location list",
+        }
+    }
+}
+
+impl From<ListKind> for Target {
+    fn from(kind: ListKind) -> Self {
+        match kind {
+            ListKind::Quickfix => Target::Quickfix,
+            ListKind::Location { window } => Target::Location(ViewId(window)),
+        }
+    }
+}
+
+pub(super) fn execute(command: QuickfixCommand, model: &mut EditorModel) -> DispatchResult {
+    match command {
+        QuickfixCommand::Grep {
+            location,
+            add,
+            bang,
+            args,
+        } => grep(Target::of(location, model), add, bang, &args, model),
+        QuickfixCommand::Jump { location, nr } => jump(Target::of(location, model), nr, model),
+        QuickfixCommand::Step { location, forward } => {
+            step(Target::of(location, model), forward, model)
+        }
+        QuickfixCommand::Window { location, open } => {
+            let target = Target::of(location, model);
+            if open {
+                open_window(target, model)
+            } else {
+                close_window(target, model)
+            }
+        }
+        QuickfixCommand::LocationDiagnostics => location_diagnostics(model),
+        QuickfixCommand::Make { bang, args } => make(bang, &args, model),
+    }
+}
+
+/// `program` (`'grepprg'`, `'makeprg'`) with `args` in place of `$*`, or
+/// appended.
+fn with_args(program: &str, args: &str) -> String {
+    match (program.contains("$*"), args.is_empty()) {
+        (true, _) => program.replace("$*", args),
+        (false, true) => program.to_string(),
+        (false, false) => format!("{program} {args}"),
+    }
+}
+
+fn grep(
+    target: Target,
+    add: bool,
+    bang: bool,
+    args: &str,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    let state = model.state_mut();
+    if args.is_empty() {
+        state.set_ephemeral("E471: Argument required", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let program = state.options.get_string("grepprg").to_string();
+    let command = with_args(&program, args);
+    let column = program.contains("--vimgrep") || program.contains("--column");
+    let jump = !bang && state.options.get_bool("grepjump");
+    let grep_target = match target {
+        Target::Quickfix => GrepTarget::Quickfix,
+        Target::Location(id) => GrepTarget::Location {
+            tab: model.current_tab(),
+            window: id.0,
+        },
+    };
+    let prefix = if grep_target == GrepTarget::Quickfix {
+        ""
+    } else {
+        "l"
+    };
+    let Some(list) = target.list_mut(model) else {
+        return DispatchResult::dirty();
+    };
+    if !add {
+        list.reset(format!(":{prefix}grep {args}"));
+    }
+    let (first, files) = (list.len(), list.file_count());
+    let state = model.state_mut();
+    state.start_grep(command, args, column, grep_target, first, jump);
+    state.grep.summarize(first, files);
+    refresh_windows(model);
+    DispatchResult::dirty()
+}
+
+/// `:make [args]`: run `'makeprg'` with `args` as a job reading its output
+/// into a fresh quickfix list (`core_state::job`).
+fn make(bang: bool, args: &str, model: &mut EditorModel) -> DispatchResult {
+    let state = model.state_mut();
+    let command = with_args(state.options.get_string("makeprg"), args);
+    let title = match args {
+        "" => ":make".to_string(),
+        args => format!(":make {args}"),
+    };
+    state.quickfix.reset(title);
+    state.start_job(command, JobSink::Quickfix { jump: !bang });
+    refresh_windows(model);
+    DispatchResult::dirty()
+}
+
+/// `:make` output arrived (`super::job::apply_job_output`): redraw the
+/// list windows, and once the job has ended open the first error if asked
+/// to. Like a `:grep` jump, that waits for a user who is not typing.
+pub(super) fn make_output(
+    added: bool,
+    finished_jump: bool,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    let mut result = DispatchResult::clean();
+    if added && refresh_windows(model) {
+        result = DispatchResult::buffer_replaced();
+    }
+    let state = model.state();
+    let busy = state.mode != Mode::Normal
+        || state.command_line.is_active()
+        || state.cmdline_window_active();
+    if finished_jump && !busy && !state.quickfix.is_empty() {
+        result = jump(Target::Quickfix, Some(1), model);
+    }
+    result
+}
+
+/// Add a batch of `:grep` output to its list, open the first match once it
+/// has arrived and report how the search ended. Output of a superseded
+/// search, or for a location list of another tab page, is dropped. A user
+/// who has meanwhile started typing (another mode, the command line) is
+/// not interrupted: the jump is dropped too.
+pub fn apply_grep_output(output: &GrepOutput, model: &mut EditorModel) -> DispatchResult {
+    let Some((grep_target, items)) = model.state_mut().grep_items(output.id, &output.lines) else {
+        return DispatchResult::clean();
+    };
+    let target = match grep_target {
+        GrepTarget::Quickfix => Some(Target::Quickfix),
+        GrepTarget::Location { tab, window } => {
+            (tab == model.current_tab()).then_some(Target::Location(ViewId(window)))
+        }
+    };
+    let added = !items.is_empty();
+    let counts = target.and_then(|target| {
+        let list = target.list_mut(model)?;
+        list.items.extend(items);
+        Some((list.len(), list.file_count()))
+    });
+    let mut result = DispatchResult::clean();
+    if added && counts.is_some() && refresh_windows(model) {
+        result = DispatchResult::buffer_replaced();
+    }
+
+    let state = model.state_mut();
+    let busy = state.mode != Mode::Normal
+        || state.command_line.is_active()
+        || state.cmdline_window_active();
+    if let Some((_, first)) = state.take_grep_jump()
+        && !busy
+        && let Some(target) = target
+    {
+        result = jump(target, Some(first + 1), model);
+    }
+
+    let state = model.state_mut();
+    if let Some(done) = &output.done
+        && state.finish_grep(output.id, done.status, &done.stderr, done.error.as_deref())
+    {
+        result.dirty = true;
+    }
+    if let Some((matches, files)) = counts {
+        state.grep.summarize(matches, files);
+        result.dirty = true;
+    }
+    result
+}
+
+/// `:cc [nr]` / `:ll [nr]`: open item `nr` (1-based, clamped to the list),
+/// or the current one.
+fn jump(target: Target, nr: Option<usize>, model: &mut EditorModel) -> DispatchResult {
+    let count = target.list(model).map_or(0, QuickfixList::len);
+    if count == 0 {
+        model
+            .state_mut()
+            .set_ephemeral(target.empty_msg(), Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let Some(list) = target.list_mut(model) else {
+        return DispatchResult::dirty();
+    };
+    let index = nr.map_or(list.index, |nr| nr.max(1) - 1).min(count - 1);
+    list.index = index;
+    let item = list.items[index].clone();
+    leave_list_window(target, model);
+
+    let state = model.state_mut();
+    let path = open_path(state, &item.path);
+    if state.active_meta().path.as_deref() == Some(path.as_path()) {
+        let origin = model.active_view().cursor;
+        model.state_mut().set_jump_mark(origin);
+    } else {
+        window::show_path(&path, model);
+    }
+    let (state, view) = model.split_state_and_active_view();
+    let buffer = state.active_buffer();
+    let line = item.line.min(buffer.line_count().saturating_sub(1));
+    let text = buffer.line(line).unwrap_or_default();
+    let text = text.trim_end_matches(['\n', '\r']);
+    let mut byte = item.col.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    view.cursor = Position::new(line, byte);
+    tracing::debug!(target: "actions.dispatch", index, count, path = %path.display(), line, byte, "quickfix_jump");
+    state.set_ephemeral(
+        format!("({} of {count}): {}", index + 1, item.text.trim()),
+        Duration::from_secs(3),
+    );
+    refresh_windows(model);
+    DispatchResult::buffer_replaced()
+}
+
+/// `:cnext` / `:cprev` (and the `:l` forms): open the item after or before
+/// the current one.
+fn step(target: Target, forward: bool, model: &mut EditorModel) -> DispatchResult {
+    let next = match target.list(model) {
+        Some(list) if !list.is_empty() => list.step(forward),
+        _ => {
+            model
+                .state_mut()
+                .set_ephemeral(target.empty_msg(), Duration::from_secs(3));
+            return DispatchResult::dirty();
+        }
+    };
+    match next {
+        Some(index) => jump(target, Some(index + 1), model),
+        None => {
+            model
+                .state_mut()
+                .set_ephemeral("E553: No more items", Duration::from_secs(3));
+            DispatchResult::dirty()
+        }
+    }
+}
+
+/// `<CR>` in a list window: open the item on the cursor line.
+pub(super) fn enter(model: &mut EditorModel) -> DispatchResult {
+    let Some(kind) = model.state().quickfix_window() else {
+        return DispatchResult::clean();
+    };
+    let line = model.active_view().cursor.line;
+    jump(kind.into(), Some(line + 1), model)
+}
+
+/// Before a jump from a list window, focus the window the item should open
+/// in: the location list's owner, else the first window not showing a
+/// list, else a new one split off above the list.
+fn leave_list_window(target: Target, model: &mut EditorModel) {
+    if model.state().quickfix_window().is_none() {
+        return;
+    }
+    let owner = match target {
+        Target::Location(id) => model.view_manager().view(id).map(|v| v.id),
+        Target::Quickfix => None,
+    };
+    let state = model.state();
+    let other = owner.or_else(|| {
+        model
+            .views()
+            .iter()
+            .find(|v| {
+                state
+                    .buffers
+                    .get(v.buffer_id)
+                    .is_some_and(|entry| entry.meta.quickfix.is_none())
+            })
+            .map(|v| v.id)
+    });
+    match other {
+        Some(id) => {
+            let _ = model.focus_view_id(id);
+        }
+        None => {
+            model.split_active_view(SplitAxis::Horizontal);
+        }
+    }
+}
+
+/// `:copen` / `:lopen`: show the list in a window spanning the bottom of
+/// the tab page, or focus the one already showing it.
+fn open_window(target: Target, model: &mut EditorModel) -> DispatchResult {
+    if matches!(target, Target::Location(_))
+        && target.list(model).is_none_or(QuickfixList::is_empty)
+    {
+        model
+            .state_mut()
+            .set_ephemeral(target.empty_msg(), Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let kind = target.kind();
+    if let Some(id) = list_windows(model)
+        .into_iter()
+        .find_map(|(id, _, shown)| (shown == kind).then_some(id))
+    {
+        let _ = model.focus_view_id(id);
+        return DispatchResult::buffer_replaced();
+    }
+    let state = model.state_mut();
+    let existing = state
+        .buffers
+        .iter()
+        .find(|entry| entry.meta.quickfix == Some(kind))
+        .map(|entry| entry.id());
+    let buffer = match existing {
+        Some(id) => id,
+        None => {
+            let Ok(buffer) = Buffer::from_str(kind.name(), "") else {
+                return DispatchResult::dirty();
+            };
+            let id = state.buffers.open(buffer, None);
+            state.fill_list_buffer(id, kind, "");
+            id
+        }
+    };
+    if model.open_bottom_view(buffer).is_err() {
+        return DispatchResult::dirty();
+    }
+    tracing::debug!(target: "actions.dispatch", ?kind, "quickfix_window_opened");
+    refresh_windows(model);
+    DispatchResult::buffer_replaced()
+}
+
+/// `:cclose` / `:lclose`: close the windows showing the list.
+fn close_window(target: Target, model: &mut EditorModel) -> DispatchResult {
+    let kind = target.kind();
+    for (id, _, shown) in list_windows(model) {
+        if shown == kind {
+            let _ = model.close_view(id);
+        }
+    }
+    DispatchResult::buffer_replaced()
+}
+
+/// `:ldiag`: fill the window's location list with its buffer's diagnostics.
+fn location_diagnostics(model: &mut EditorModel) -> DispatchResult {
+    let target = Target::of(true, model);
+    let Target::Location(owner) = target else {
+        return DispatchResult::clean();
+    };
+    let Some(buffer) = model.view_manager().view(owner).map(|v| v.buffer_id) else {
+        return DispatchResult::dirty();
+    };
+    let state = model.state_mut();
+    let Some(path) = state.buffers.get(buffer).and_then(|e| e.meta.path.clone()) else {
+        state.set_ephemeral("E32: No file name", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    };
+    let items: Vec<QuickfixItem> = state
+        .diagnostics
+        .get(buffer)
+        .iter()
+        .map(|d| QuickfixItem {
+            path: path.clone(),
+            line: d.start.line,
+            col: d.start.byte,
+            text: format!(
+                "{}: {}",
+                d.severity.name(),
+                d.message.lines().next().unwrap_or_default()
+            ),
+        })
+        .collect();
+    if items.is_empty() {
+        state.set_ephemeral("No diagnostics", Duration::from_secs(3));
+    }
+    if let Some(list) = target.list_mut(model) {
+        list.reset(":ldiag");
+        list.items = items;
+    }
+    refresh_windows(model);
+    DispatchResult::buffer_replaced()
+}
+
+/// The current tab page's list windows: view, buffer and the list shown.
+fn list_windows(model: &EditorModel) -> Vec<(ViewId, core_state::BufferId, ListKind)> {
+    let state = model.state();
+    model
+        .views()
+        .iter()
+        .filter_map(|v| {
+            let kind = state.buffers.get(v.buffer_id)?.meta.quickfix?;
+            Some((v.id, v.buffer_id, kind))
+        })
+        .collect()
+}
+
+/// Redraw every list window of the current tab page from its list, with
+/// the cursor on the current item. Returns false when there is none.
+fn refresh_windows(model: &mut EditorModel) -> bool {
+    let windows = list_windows(model);
+    for &(id, buffer, kind) in &windows {
+        let (listing, index) = Target::from(kind)
+            .list(model)
+            .map(|list| (list.listing(), list.index))
+            .unwrap_or_default();
+        model.state_mut().fill_list_buffer(buffer, kind, &listing);
+        let lines = listing.lines().count().max(1);
+        if let Some(view) = model.view_mut(id) {
+            let line = index.min(lines - 1);
+            view.cursor = Position::new(line, 0);
+            view.viewport_first_line = view.viewport_first_line.min(line);
+        }
+    }
+    !windows.is_empty()
+}
@@ -0,0 +1,86 @@
+//! Pattern search: the `/` and `?` prompts, `n` / `N` and `:noh`.
+//!
+//! Matching lives in `core_state::search`; this module moves the cursor,
+//! records the pattern in the `/` register and reports Vim's messages. The
+//! renderer highlights matches of the recorded pattern (`hlsearch`).
+
+use super::DispatchResult;
+use core_model::View;
+use core_state::EditorState;
+use core_state::search::find_match;
+use std::time::Duration;
+
+/// Run the pattern typed at the `/` or `?` prompt (`cmd` keeps the prompt
+/// character). An empty pattern repeats the previous one in the new
+/// direction.
+pub(crate) fn execute(cmd: &str, state: &mut EditorState, view: &mut View) -> DispatchResult {
+    state.command_line.clear();
+    let forward = !cmd.starts_with('?');
+    let typed = cmd.get(1..).unwrap_or_default();
+    let pattern = if typed.is_empty() {
+        state.registers.search().to_string()
+    } else {
+        typed.to_string()
+    };
+    if pattern.is_empty() {
+        state.set_ephemeral(
+            "E35: No previous regular expression",
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    state.record_search(&pattern, forward);
+    jump(forward, 1, state, view);
+    DispatchResult::dirty()
+}
+
+/// `n` (or `N` with `reverse`): repeat the last search `count` times.
+pub(crate) fn repeat(
+    reverse: bool,
+    count: u32,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if state.registers.search().is_empty() {
+        state.set_ephemeral(
+            "E35: No previous regular expression",
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    state.resume_search_highlight();
+    let forward = state.search_forward() != reverse;
+    jump(forward, count.max(1), state, view);
+    DispatchResult::dirty()
+}
+
+/// Move to the `count`th match of the last pattern, reporting a wrap or a
+/// miss in the message line.
+fn jump(forward: bool, count: u32, state: &mut EditorState, view: &mut View) {
+    let mut at = view.cursor;
+    let mut wrapped = false;
+    for _ in 0..count {
+        match find_match(state.active_buffer(), state.search_pattern(), at, forward) {
+            Some(hit) => {
+                at = hit.position;
+                wrapped |= hit.wrapped;
+            }
+            None => {
+                let msg = format!("E486: Pattern not found: {}", state.registers.search());
+                state.set_ephemeral(msg, Duration::from_secs(3));
+                return;
+            }
+        }
+    }
+    tracing::trace!(target: "actions.dispatch", line = at.line, byte = at.byte, wrapped, "search_jump");
+    state.set_jump_mark(view.cursor);
+    view.cursor = at;
+    if wrapped {
+        let msg = if forward {
+            "search hit BOTTOM, continuing at TOP"
+        } else {
+            "search hit TOP, continuing at BOTTOM"
+        };
+        state.set_ephemeral(msg, Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,265 @@
+//! Apply captured external command output (`:!`, `:r !`, `:{range}!`).
+//!
+//! Commands are queued by `command.rs` and executed asynchronously by the
+//! runtime; this module is the completion half, translating a
+//! `ShellOutput` into buffer / message-area changes according to the
+//! `ShellTarget` recorded when the command was issued. Buffer output is
+//! dropped when its buffer was closed or edited while the command ran.
+
+use super::DispatchResult;
+use core_events::ShellOutput;
+use core_model::{EditorModel, ViewId};
+use core_state::{EditorState, RegisterKind, ShellOrigin, ShellTarget};
+use core_text::Position;
+use std::time::Duration;
+
+/// Maximum rows the message area grows to for `:!cmd` output.
+pub const SHELL_MESSAGE_MAX_LINES: usize = 10;
+
+pub fn apply_shell_output(
+    model: &mut EditorModel,
+    target: &ShellTarget,
+    output: &ShellOutput,
+) -> DispatchResult {
+    let state = model.state_mut();
+    if let Some(err) = &output.error {
+        tracing::warn!(target: "runtime.shell", id = output.id, error = %err, "shell_spawn_failed");
+        state.set_ephemeral(
+            format!("E282: Cannot execute shell: {err}"),
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    match target {
+        ShellTarget::Message => show_output(state, output),
+        ShellTarget::ReadAfter { origin, line } => {
+            if output.stdout.is_empty() {
+                report_status(state, output);
+                return DispatchResult::dirty();
+            }
+            if !origin.is_current(state) {
+                return drop_output(state);
+            }
+            let mut text = output.stdout.clone();
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            edit_origin(model, origin, |state, cursor| {
+                let line = (*line).min(state.last_content_line());
+                state.push_discrete_edit_snapshot(*cursor);
+                *cursor = Position::new(line, 0);
+                state.paste_with_text(&text, RegisterKind::Linewise, false, cursor);
+                report_status(state, output);
+            });
+            DispatchResult::buffer_replaced()
+        }
+        ShellTarget::Filter { origin, start, end } => {
+            if output.status != Some(0) {
+                // Keep the original lines when the filter fails.
+                show_output(state, output);
+                return DispatchResult::dirty();
+            }
+            if !origin.is_current(state) {
+                return drop_output(state);
+            }
+            edit_origin(model, origin, |state, cursor| {
+                let end = (*end).min(state.last_content_line());
+                state.replace_lines_with_snapshot(cursor, *start, end, &output.stdout);
+                let filtered = end - start + 1;
+                state.set_ephemeral(format!("{filtered} lines filtered"), Duration::from_secs(3));
+            });
+            DispatchResult::buffer_replaced()
+        }
+    }
+}
+
+/// Run `edit` on the origin buffer with the cursor of its window (the
+/// buffer's start when the window is gone or shows another buffer), then
+/// restore the active buffer and store the cursor back in the window.
+fn edit_origin(
+    model: &mut EditorModel,
+    origin: &ShellOrigin,
+    edit: impl FnOnce(&mut EditorState, &mut Position),
+) {
+    let window = Some(ViewId(origin.window)).filter(|id| {
+        model
+            .view_manager()
+            .view(*id)
+            .is_some_and(|v| v.buffer_id == origin.buffer)
+    });
+    let mut cursor = window
+        .and_then(|id| model.view_manager().view(id))
+        .map_or(Position::origin(), |v| v.cursor);
+    let state = model.state_mut();
+    let active = state.active;
+    state.switch_buffer(origin.buffer);
+    edit(state, &mut cursor);
+    state.switch_buffer(active);
+    if let Some(view) = window.and_then(|id| model.view_mut(id)) {
+        view.cursor = cursor;
+    }
+}
+
+fn drop_output(state: &mut EditorState) -> DispatchResult {
+    state.set_ephemeral(
+        "Buffer changed while the command ran; output dropped",
+        Duration::from_secs(3),
+    );
+    DispatchResult::dirty()
+}
+
+fn show_output(state: &mut EditorState, output: &ShellOutput) -> DispatchResult {
+    let mut lines: Vec<String> = output
+        .stdout
+        .lines()
+        .chain(output.stderr.lines())
+        .map(|l| l.replace('\t', "    "))
+        .collect();
+    if let Some(code) = output.status.filter(|c| *c != 0) {
+        lines.push(format!("shell returned {code}"));
+    }
+    match lines.len() {
+        0 => {}
+        1 => state.set_ephemeral(lines.remove(0), Duration::from_secs(3)),
+        _ => state.show_message_lines(lines, SHELL_MESSAGE_MAX_LINES),
+    }
+    DispatchResult::dirty()
+}
+
+fn report_status(state: &mut EditorState, output: &ShellOutput) {
+    if let Some(code) = output.status.filter(|c| *c != 0) {
+        state.set_ephemeral(format!("shell returned {code}"), Duration::from_secs(3));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_state::OverlayMode;
+    use core_text::Buffer;
+
+    fn model(text: &str) -> EditorModel {
+        EditorModel::new(EditorState::new(Buffer::from_str("t", text).unwrap()))
+    }
+
+    fn ok(stdout: &str) -> ShellOutput {
+        ShellOutput {
+            id: 1,
+            status: Some(0),
+            stdout: stdout.into(),
+            stderr: String::new(),
+            error: None,
+        }
+    }
+
+    fn text(m: &EditorModel) -> String {
+        let b = m.state().active_buffer();
+        (0..b.line_count()).filter_map(|i| b.line(i)).collect()
+    }
+
+    fn origin(m: &EditorModel) -> ShellOrigin {
+        ShellOrigin::active(m.state(), m.active_view().id.0)
+    }
+
+    #[test]
+    fn read_inserts_below_line_as_single_undo() {
+        let mut m = model("a\nb\n");
+        let target = ShellTarget::ReadAfter {
+            origin: origin(&m),
+            line: 0,
+        };
+        let res = apply_shell_output(&mut m, &target, &ok("x\ny"));
+        assert!(res.buffer_replaced);
+        assert_eq!(text(&m), "a\nx\ny\nb\n");
+        assert_eq!(m.active_view().cursor.line, 1);
+        assert_eq!(m.state().undo_depth(), 1);
+    }
+
+    #[test]
+    fn filter_replaces_range() {
+        let mut m = model("c\nb\na\nz\n");
+        let target = ShellTarget::Filter {
+            origin: origin(&m),
+            start: 0,
+            end: 2,
+        };
+        apply_shell_output(&mut m, &target, &ok("a\nb\nc\n"));
+        assert_eq!(text(&m), "a\nb\nc\nz\n");
+        let eph = m.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(eph.text, "3 lines filtered");
+    }
+
+    #[test]
+    fn failed_filter_keeps_lines() {
+        let mut m = model("a\n");
+        let out = ShellOutput {
+            status: Some(1),
+            stderr: "boom\n".into(),
+            ..ok("")
+        };
+        let target = ShellTarget::Filter {
+            origin: origin(&m),
+            start: 0,
+            end: 0,
+        };
+        apply_shell_output(&mut m, &target, &out);
+        assert_eq!(text(&m), "a\n");
+        assert!(matches!(
+            m.state().overlay_mode(),
+            OverlayMode::Message { lines: 2 }
+        ));
+    }
+
+    #[test]
+    fn output_goes_to_the_buffer_the_command_ran_in() {
+        let mut m = model("c\nb\na\n");
+        let first = m.state().active;
+        let window = m.active_view().id;
+        let target = ShellTarget::Filter {
+            origin: origin(&m),
+            start: 0,
+            end: 2,
+        };
+        let other = m
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("u", "z\n").unwrap(), None);
+        m.open_view(other, core_model::SplitAxis::Horizontal)
+            .unwrap();
+        apply_shell_output(&mut m, &target, &ok("a\nb\nc\n"));
+        assert_eq!(m.state().active, other);
+        assert_eq!(text(&m), "z\n");
+        let b = &m.state().buffers.get(first).unwrap().buffer;
+        assert_eq!(b.line(0).unwrap(), "a\n");
+        assert_eq!(b.line(2).unwrap(), "c\n");
+        assert_eq!(m.view_manager().view(window).unwrap().buffer_id, first);
+    }
+
+    #[test]
+    fn output_is_dropped_when_its_buffer_changed() {
+        let mut m = model("a\nb\n");
+        let target = ShellTarget::ReadAfter {
+            origin: origin(&m),
+            line: 0,
+        };
+        let mut cursor = Position::new(0, 0);
+        m.state_mut()
+            .active_buffer_mut()
+            .insert_grapheme(&mut cursor, "q");
+        let res = apply_shell_output(&mut m, &target, &ok("x\n"));
+        assert!(!res.buffer_replaced);
+        assert_eq!(text(&m), "qa\nb\n");
+        let eph = m.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(
+            eph.text,
+            "Buffer changed while the command ran; output dropped"
+        );
+    }
+
+    #[test]
+    fn single_line_message_uses_ephemeral() {
+        let mut m = model("a\n");
+        apply_shell_output(&mut m, &ShellTarget::Message, &ok("hello\n"));
+        assert_eq!(m.state().ephemeral_status.as_ref().unwrap().text, "hello");
+    }
+}
@@ -0,0 +1,150 @@
+//! `:[range]sor[t][!] [n][i][u][r]` line sorting.
+//!
+//! Pure helpers: flag parsing and the sort itself operate on owned line
+//! strings so they are testable without a buffer. `command.rs` resolves the
+//! range, applies the result as a single undo snapshot and routes lines
+//! dropped by `u` to the registers.
+//!
+//! Semantics follow Vim where implemented:
+//! * `!` reverses the order (`r` is accepted as an alias since pattern
+//!   sorting, Vim's meaning for `r`, is not supported yet).
+//! * `n` sorts on the first decimal number in the line (a preceding `-`
+//!   makes it negative); lines without a number sort first, in original order.
+//! * `i` ignores case; `u` keeps only the first of each run of equal lines
+//!   (equality uses the same key as the sort, so `iu` is case-insensitive).
+//! * The sort is stable.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SortFlags {
+    pub reverse: bool,
+    pub numeric: bool,
+    pub unique: bool,
+    pub ignore_case: bool,
+}
+
+impl SortFlags {
+    /// Parse the flag argument (whitespace is ignored).
+    pub fn parse(bang: bool, args: &str) -> Result<Self, String> {
+        let mut flags = SortFlags {
+            reverse: bang,
+            ..SortFlags::default()
+        };
+        for ch in args.chars().filter(|c| !c.is_whitespace()) {
+            match ch {
+                'n' => flags.numeric = true,
+                'u' => flags.unique = true,
+                'i' => flags.ignore_case = true,
+                'r' => flags.reverse = true,
+                _ => return Err(format!("E474: Invalid argument: {}", args.trim())),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Result of sorting: kept lines in order plus the duplicates removed by `u`
+/// (in original relative order).
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SortOutcome {
+    pub lines: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    // Derived ordering puts `NoNumber` first, matching Vim.
+    NoNumber,
+    Number(i64),
+    Text(String),
+}
+
+fn key(line: &str, flags: SortFlags) -> SortKey {
+    if flags.numeric {
+        return first_number(line).map_or(SortKey::NoNumber, SortKey::Number);
+    }
+    if flags.ignore_case {
+        SortKey::Text(line.to_lowercase())
+    } else {
+        SortKey::Text(line.to_string())
+    }
+}
+
+fn first_number(line: &str) -> Option<i64> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let digits_end = line[start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(line.len(), |e| start + e);
+    let n: i64 = line[start..digits_end].parse().unwrap_or(i64::MAX);
+    if line[..start].ends_with('-') {
+        Some(-n)
+    } else {
+        Some(n)
+    }
+}
+
+pub fn sort_lines(lines: Vec<String>, flags: SortFlags) -> SortOutcome {
+    let mut keyed: Vec<(SortKey, String)> =
+        lines.into_iter().map(|l| (key(&l, flags), l)).collect();
+    // Reverse the comparison rather than the result so equal lines keep
+    // their original order (stable in both directions, as in Vim).
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ord = a.cmp(b);
+        if flags.reverse { ord.reverse() } else { ord }
+    });
+    let mut outcome = SortOutcome::default();
+    let mut last: Option<SortKey> = None;
+    for (k, line) in keyed {
+        if flags.unique && last.as_ref() == Some(&k) {
+            outcome.removed.push(line);
+            continue;
+        }
+        outcome.lines.push(line);
+        last = Some(k);
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn flag_parsing() {
+        let f = SortFlags::parse(true, " nu ").unwrap();
+        assert!(f.reverse && f.numeric && f.unique && !f.ignore_case);
+        assert!(SortFlags::parse(false, "x").is_err());
+    }
+
+    #[test]
+    fn lexical_reverse_and_ignore_case() {
+        let input = lines(&["b", "A", "a", "B"]);
+        let out = sort_lines(input.clone(), SortFlags::default());
+        assert_eq!(out.lines, lines(&["A", "B", "a", "b"]));
+        let flags = SortFlags::parse(false, "i").unwrap();
+        assert_eq!(
+            sort_lines(input.clone(), flags).lines,
+            lines(&["A", "a", "b", "B"])
+        );
+        let flags = SortFlags::parse(true, "").unwrap();
+        assert_eq!(sort_lines(input, flags).lines, lines(&["b", "a", "B", "A"]));
+    }
+
+    #[test]
+    fn numeric_puts_numberless_first() {
+        let input = lines(&["x10", "none", "x-2", "x9", "also"]);
+        let out = sort_lines(input, SortFlags::parse(false, "n").unwrap());
+        assert_eq!(out.lines, lines(&["none", "also", "x-2", "x9", "x10"]));
+    }
+
+    #[test]
+    fn unique_reports_removed_lines() {
+        let input = lines(&["b", "a", "B", "a"]);
+        let out = sort_lines(input, SortFlags::parse(false, "iu").unwrap());
+        assert_eq!(out.lines, lines(&["a", "b"]));
+        assert_eq!(out.removed, lines(&["a", "B"]));
+    }
+}
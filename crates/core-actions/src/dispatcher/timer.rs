@@ -0,0 +1,84 @@
+//! Timers (`core_state::timer`): `:defer`, `:timer`, `:timers` and
+//! `:timerstop`.
+//!
+//! `:defer {ms} {cmd}` runs the ex command `cmd` once, `ms` milliseconds
+//! from now; `:timer {ms} {cmd}` runs it every `ms` milliseconds until
+//! `:timerstop`. The runtime runs the command on the main loop when the
+//! timer fires, as if typed on the command line.
+
+use super::DispatchResult;
+use core_state::{EditorState, TimerCallback};
+use std::time::Duration;
+
+/// `:defer {ms} {cmd}` / `:timer {ms} {cmd}`.
+pub(super) fn start(repeat: bool, args: &str, state: &mut EditorState) -> DispatchResult {
+    let (ms, command) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let command = command.trim().trim_start_matches(':');
+    if command.is_empty() {
+        state.set_ephemeral("E471: Argument required", Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    let Ok(ms) = ms.parse::<u64>() else {
+        state.set_ephemeral(
+            format!("E475: Invalid argument: {ms}"),
+            Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    };
+    let id = state.start_timer(
+        Duration::from_millis(ms),
+        repeat,
+        TimerCallback::Ex(command.to_string()),
+    );
+    state.set_ephemeral(format!("Timer {id} started"), Duration::from_secs(3));
+    DispatchResult::dirty()
+}
+
+/// `:timers`: the active timers, one `id  delay  callback` line each.
+pub(super) fn list(state: &mut EditorState) -> DispatchResult {
+    let lines: Vec<String> = state
+        .timers
+        .active()
+        .iter()
+        .map(|timer| {
+            let ms = timer.delay.as_millis();
+            let when = match timer.repeat {
+                true => format!("every {ms} ms"),
+                false => format!("in {ms} ms"),
+            };
+            let callback = match &timer.callback {
+                TimerCallback::Ex(command) => format!(":{command}"),
+                TimerCallback::Plugin { plugin, token } => format!("plugin {plugin} ({token})"),
+            };
+            format!("{:>3}  {when:<16}  {callback}", timer.id)
+        })
+        .collect();
+    match lines.len() {
+        0 => state.set_ephemeral("No timers active", Duration::from_secs(3)),
+        count => state.show_message_lines(lines, count),
+    }
+    DispatchResult::dirty()
+}
+
+/// `:timerstop [id]`: stop timer `id`, or all of them.
+pub(super) fn stop(arg: &str, state: &mut EditorState) -> DispatchResult {
+    let ids: Vec<u64> = match arg {
+        "" => state.timers.active().iter().map(|timer| timer.id).collect(),
+        arg => match arg.parse() {
+            Ok(id) => vec![id],
+            Err(_) => Vec::new(),
+        },
+    };
+    let mut stopped = 0;
+    for id in ids {
+        stopped += usize::from(state.stop_timer(id));
+    }
+    let msg = match stopped {
+        0 if arg.is_empty() => "No timers active".to_string(),
+        0 => format!("E475: Invalid argument: {arg}"),
+        1 => "1 timer stopped".to_string(),
+        n => format!("{n} timers stopped"),
+    };
+    state.set_ephemeral(msg, Duration::from_secs(3));
+    DispatchResult::dirty()
+}
@@ -6,12 +6,13 @@
 //!
 //! Forward Roadmap:
 //! * Replace snapshot cloning with delta (operation log) model.
-//! * Provide structured metadata for future time-travel / tree history.
+//! * Undo history is a tree; `handle_undo_travel` walks it chronologically
+//!   (`g-`/`g+`, `:earlier`/`:later`).
 //! * Expose observer hook for plugins (e.g. to display undo tree).
 
 use super::DispatchResult;
 use core_model::View;
-use core_state::EditorState;
+use core_state::{EditorState, UndoTravel};
 
 pub(crate) fn handle_undo(state: &mut EditorState, view: &mut View) -> DispatchResult {
     let before = state.active_buffer().line_count();
@@ -42,3 +43,58 @@ pub(crate) fn handle_redo(state: &mut EditorState, view: &mut View) -> DispatchR
         DispatchResult::clean()
     }
 }
+
+/// Parse the `:earlier` / `:later` argument: `N` changes (default 1) or a
+/// time span `Ns`, `Nm`, `Nh`, `Nd`. Vim's `Nf` (file writes) is not tracked.
+pub(crate) fn parse_undo_time(later: bool, arg: &str) -> Result<UndoTravel, String> {
+    let invalid = || format!("E475: Invalid argument: {arg}");
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (digits, unit) = arg.split_at(split);
+    let n: i64 = if digits.is_empty() {
+        if !unit.is_empty() {
+            return Err(invalid());
+        }
+        1
+    } else {
+        digits.parse().map_err(|_| invalid())?
+    };
+    let n = if later { n } else { -n };
+    let secs = match unit {
+        "" => return Ok(UndoTravel::Steps(n)),
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    Ok(UndoTravel::Seconds(n.saturating_mul(secs)))
+}
+
+pub(crate) fn handle_undo_travel(
+    travel: UndoTravel,
+    state: &mut EditorState,
+    view: &mut View,
+) -> DispatchResult {
+    if state.undo_travel(travel, &mut view.cursor) {
+        tracing::trace!(target: "actions.dispatch", op = "undo_travel", ?travel, seq = state.undo_seq(), "undo_travel");
+        // Travel may cross branches with different shapes; always rebuild.
+        DispatchResult::buffer_replaced()
+    } else {
+        DispatchResult::clean()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_time_arguments() {
+        assert_eq!(parse_undo_time(false, ""), Ok(UndoTravel::Steps(-1)));
+        assert_eq!(parse_undo_time(true, "3"), Ok(UndoTravel::Steps(3)));
+        assert_eq!(parse_undo_time(false, "10s"), Ok(UndoTravel::Seconds(-10)));
+        assert_eq!(parse_undo_time(true, "2m"), Ok(UndoTravel::Seconds(120)));
+        assert!(parse_undo_time(false, "1f").is_err());
+        assert!(parse_undo_time(false, "s").is_err());
+    }
+}
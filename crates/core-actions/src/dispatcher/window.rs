@@ -0,0 +1,274 @@
+//! Window and tab page commands (`:split`, `:vsplit`, `:close`, `:quit` with
+//! several windows or tabs, `:tabnew`, `:diffsplit`, `:diffoff`, the
+//! quickfix and location list commands, `:make`, `:job`, `<C-w>` focus
+//! moves and `gt`/`gT`).
+//!
+//! These add, remove or switch views, so unlike other ex commands they
+//! operate on the whole `EditorModel` instead of the state + active view pair.
+//! `dispatch_with_commands` routes them here before splitting the model.
+
+use super::DispatchResult;
+use super::command_parser::{CommandParser, ParsedCommand};
+use super::quickfix::QuickfixCommand;
+use crate::command_registry::CommandRegistry;
+use core_model::{EditorModel, FocusDirection, SplitAxis};
+use core_text::{Buffer, Position};
+use std::path::PathBuf;
+
+pub(super) enum WindowCommand {
+    Split {
+        axis: SplitAxis,
+        path: Option<PathBuf>,
+    },
+    TabNew {
+        path: Option<PathBuf>,
+    },
+    Close {
+        force: bool,
+    },
+    DiffSplit {
+        path: Option<PathBuf>,
+    },
+    DiffOff,
+    Quickfix(QuickfixCommand),
+    Job {
+        command: String,
+    },
+}
+
+/// Classify a `:` command line, returning `None` for commands the regular
+/// command handler owns (including `:q` when only one window is open in the
+/// only tab page).
+pub(super) fn window_command(
+    cmd: &str,
+    model: &EditorModel,
+    commands: &CommandRegistry,
+) -> Option<WindowCommand> {
+    let state = model.state();
+    if state.command_line.is_expression() || state.cmdline_window_active() {
+        return None;
+    }
+    match CommandParser::parse_with(cmd, commands) {
+        ParsedCommand::Split { axis, path } => Some(WindowCommand::Split { axis, path }),
+        ParsedCommand::TabNew { path } => Some(WindowCommand::TabNew { path }),
+        ParsedCommand::Close { force } => Some(WindowCommand::Close { force }),
+        ParsedCommand::DiffSplit { path } => Some(WindowCommand::DiffSplit { path }),
+        ParsedCommand::DiffOff => Some(WindowCommand::DiffOff),
+        ParsedCommand::Grep {
+            location,
+            add,
+            bang,
+            args,
+        } => Some(WindowCommand::Quickfix(QuickfixCommand::Grep {
+            location,
+            add,
+            bang,
+            args,
+        })),
+        ParsedCommand::QuickfixJump { location, nr } => {
+            Some(WindowCommand::Quickfix(QuickfixCommand::Jump {
+                location,
+                nr,
+            }))
+        }
+        ParsedCommand::QuickfixStep { location, forward } => {
+            Some(WindowCommand::Quickfix(QuickfixCommand::Step {
+                location,
+                forward,
+            }))
+        }
+        ParsedCommand::QuickfixWindow { location, open } => {
+            Some(WindowCommand::Quickfix(QuickfixCommand::Window {
+                location,
+                open,
+            }))
+        }
+        ParsedCommand::LocationDiagnostics => Some(WindowCommand::Quickfix(
+            QuickfixCommand::LocationDiagnostics,
+        )),
+        ParsedCommand::Make { bang, args } => {
+            Some(WindowCommand::Quickfix(QuickfixCommand::Make {
+                bang,
+                args,
+            }))
+        }
+        ParsedCommand::Job { command } => Some(WindowCommand::Job { command }),
+        ParsedCommand::Quit { force } if model.views().len() > 1 || model.tabs().len() > 1 => {
+            Some(WindowCommand::Close { force })
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn execute(
+    cmd: &str,
+    command: WindowCommand,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    let state = model.state_mut();
+    if let Some(body) = cmd.trim().strip_prefix(':') {
+        state.command_line.record_history(body);
+    }
+    state.command_line.clear();
+    match command {
+        WindowCommand::Split { axis, path } => split(axis, path, model),
+        WindowCommand::TabNew { path } => tab_new(path, model),
+        WindowCommand::Close { force } => close(force, model),
+        WindowCommand::DiffSplit { path } => super::diff::split(path, model),
+        WindowCommand::DiffOff => super::diff::off(model),
+        WindowCommand::Quickfix(command) => super::quickfix::execute(command, model),
+        WindowCommand::Job { command } => super::job::run(&command, model),
+    }
+}
+
+pub(super) fn split(
+    axis: SplitAxis,
+    path: Option<PathBuf>,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    // Each window needs at least one text row plus its status line.
+    let height = model.state().last_text_height;
+    if axis == SplitAxis::Horizontal && height > 0 && height < 3 {
+        model
+            .state_mut()
+            .set_ephemeral("E36: Not enough room", std::time::Duration::from_secs(3));
+        return DispatchResult::dirty();
+    }
+    model.split_active_view(axis);
+    if let Some(path) = path {
+        show_path(&path, model);
+    }
+    DispatchResult::buffer_replaced()
+}
+
+fn tab_new(path: Option<PathBuf>, model: &mut EditorModel) -> DispatchResult {
+    let buffer = match path {
+        Some(_) => model.state().active,
+        None => {
+            let Ok(buffer) = Buffer::from_str("untitled", "") else {
+                return DispatchResult::dirty();
+            };
+            model.state_mut().buffers.open(buffer, None)
+        }
+    };
+    model.open_tab(buffer);
+    if let Some(path) = path {
+        show_path(&path, model);
+    }
+    DispatchResult::buffer_replaced()
+}
+
+/// Show `path` in the active view: reuse its buffer when already open,
+/// otherwise read it into a new buffer (or start an empty one for a file
+/// that does not exist yet).
+pub(super) fn show_path(path: &std::path::Path, model: &mut EditorModel) {
+    let (state, view) = model.split_state_and_active_view();
+    state.remember_cursor(view.buffer_id, view.cursor);
+    let existing = state.buffers.find_by_path(path);
+    let id = match existing {
+        Some(id) => id,
+        None => {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let Ok(buffer) = Buffer::from_str(name, "") else {
+                return;
+            };
+            state.buffers.open(buffer, Some(path.to_path_buf()))
+        }
+    };
+    state.switch_buffer(id);
+    view.buffer_id = id;
+    view.cursor = Position::origin();
+    view.viewport_first_line = 0;
+    if existing.is_none() {
+        if path.exists() {
+            super::command::load_file(path, state, view);
+        } else {
+            state.set_ephemeral(
+                format!("\"{}\" [New]", path.display()),
+                std::time::Duration::from_secs(3),
+            );
+        }
+    }
+}
+
+/// Close the active window; closing the last window of a tab page closes
+/// the tab. With `force`, a modified buffer shown in no other window is
+/// unloaded, so its changes are discarded rather than left hidden.
+fn close(force: bool, model: &mut EditorModel) -> DispatchResult {
+    if model.views().len() == 1 && model.tabs().len() == 1 {
+        model.state_mut().set_ephemeral(
+            "E444: Cannot close last window",
+            std::time::Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    let buffer = model.active_view().buffer_id;
+    let shown_elsewhere = model
+        .tabs()
+        .iter()
+        .flat_map(|tab| tab.view_manager().views())
+        .filter(|v| v.buffer_id == buffer)
+        .count()
+        > 1;
+    let discard = model.state().dirty() && !shown_elsewhere;
+    if discard && !force {
+        model.state_mut().set_ephemeral(
+            "E37: No write since last change (add ! to override)",
+            std::time::Duration::from_secs(3),
+        );
+        return DispatchResult::dirty();
+    }
+    if model.views().len() == 1 {
+        model.close_tab();
+    } else {
+        model.close_active_view();
+    }
+    if discard {
+        let _ = model.state_mut().close_buffer(buffer, true);
+    }
+    DispatchResult::buffer_replaced()
+}
+
+/// Move focus `count` steps. Both views keep their cursor and viewport; the
+/// runtime repaints the two regions as the cursor moves between them.
+pub(super) fn focus(
+    direction: FocusDirection,
+    count: u32,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    let mut moved = false;
+    for _ in 0..count.max(1) {
+        if !model.focus_view(direction) {
+            break;
+        }
+        moved = true;
+    }
+    if moved {
+        DispatchResult::dirty()
+    } else {
+        DispatchResult::clean()
+    }
+}
+
+/// `gt` / `gT` (see `Action::TabSwitch`). A count past the last tab is
+/// ignored.
+pub(super) fn switch_tab(
+    backward: bool,
+    count: Option<u32>,
+    model: &mut EditorModel,
+) -> DispatchResult {
+    let tabs = model.tabs().len();
+    let current = model.current_tab();
+    let target = match (backward, count) {
+        (false, Some(n)) => (n as usize).saturating_sub(1),
+        (false, None) => (current + 1) % tabs,
+        (true, n) => (current + tabs - n.unwrap_or(1) as usize % tabs) % tabs,
+    };
+    if target == current || !model.goto_tab(target) {
+        return DispatchResult::clean();
+    }
+    DispatchResult::buffer_replaced()
+}
@@ -4,8 +4,13 @@
 //! normalization + reconstruction logic so the dispatcher focuses on command semantics.
 //! Future (Phase 3+) replacements can provide async versions with identical signatures.
 
-use core_state::{EditorState, LineEnding, normalize_line_endings};
+use core_state::binary::is_binary;
+use core_state::{
+    BufferMeta, CMDLINE_WINDOW_NAME, EditorState, LineEnding, normalize_line_endings,
+};
 use core_text::Buffer;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 /// Result of attempting to open a file.
 #[derive(Debug)]
@@ -20,6 +25,9 @@ pub struct OpenSuccess {
     pub original_line_ending: LineEnding,
     pub had_trailing_newline: bool,
     pub mixed_line_endings: bool,
+    /// Raw bytes when the file was detected as binary; `buffer` is then
+    /// empty and callers hand the bytes to `EditorState::load_binary`.
+    pub binary: Option<Vec<u8>>,
 }
 
 impl std::fmt::Debug for OpenSuccess {
@@ -29,6 +37,7 @@ impl std::fmt::Debug for OpenSuccess {
             .field("original_line_ending", &self.original_line_ending)
             .field("had_trailing_newline", &self.had_trailing_newline)
             .field("mixed_line_endings", &self.mixed_line_endings)
+            .field("binary_len", &self.binary.as_ref().map(Vec::len))
             .finish()
     }
 }
@@ -36,26 +45,45 @@ impl std::fmt::Debug for OpenSuccess {
 /// Open a file path into a new Buffer applying line ending normalization.
 /// Returns structured metadata required to update EditorState.
 pub fn open_file(path: &std::path::Path) -> OpenFileResult {
-    match std::fs::read_to_string(path) {
-        Ok(content) => {
-            let norm = normalize_line_endings(&content);
-            let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("file");
-            match Buffer::from_str(name, &norm.normalized) {
-                Ok(buffer) => OpenFileResult::Success(OpenSuccess {
-                    buffer,
-                    file_name: path.to_path_buf(),
-                    original_line_ending: norm.original,
-                    had_trailing_newline: norm.had_trailing_newline,
-                    mixed_line_endings: norm.mixed,
-                }),
-                Err(e) => {
-                    tracing::error!(target: "io", ?e, "buffer_create_failed");
-                    OpenFileResult::Error
-                }
-            }
-        }
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
         Err(e) => {
             tracing::error!(target: "io", ?e, "file_open_error");
+            return OpenFileResult::Error;
+        }
+    };
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("file");
+    if is_binary(&bytes) {
+        tracing::debug!(target: "io", file = %path.display(), size_bytes = bytes.len(), "binary_file_detected");
+        return match Buffer::from_str(name, "") {
+            Ok(buffer) => OpenFileResult::Success(OpenSuccess {
+                buffer,
+                file_name: path.to_path_buf(),
+                original_line_ending: LineEnding::Lf,
+                had_trailing_newline: false,
+                mixed_line_endings: false,
+                binary: Some(bytes),
+            }),
+            Err(e) => {
+                tracing::error!(target: "io", ?e, "buffer_create_failed");
+                OpenFileResult::Error
+            }
+        };
+    }
+    // `is_binary` rejects invalid UTF-8, so this cannot fail.
+    let content = String::from_utf8_lossy(&bytes);
+    let norm = normalize_line_endings(&content);
+    match Buffer::from_str(name, &norm.normalized) {
+        Ok(buffer) => OpenFileResult::Success(OpenSuccess {
+            buffer,
+            file_name: path.to_path_buf(),
+            original_line_ending: norm.original,
+            had_trailing_newline: norm.had_trailing_newline,
+            mixed_line_endings: norm.mixed,
+            binary: None,
+        }),
+        Err(e) => {
+            tracing::error!(target: "io", ?e, "buffer_create_failed");
             OpenFileResult::Error
         }
     }
@@ -65,48 +93,252 @@ pub fn open_file(path: &std::path::Path) -> OpenFileResult {
 #[derive(Debug)]
 pub enum WriteFileResult {
     Success,
+    /// Written, but the `'backup'` copy could not be made (error text).
+    SuccessWithoutBackup(String),
     NoFilename,
+    /// Refused: the raw view of a binary file that is not valid UTF-8 was
+    /// edited, and its text no longer holds the original bytes.
+    Lossy,
     Error,
 }
 
+/// Backup location for `path`: `'backupdir'` (when set) or the file's own
+/// directory, with `'backupext'` appended to the file name.
+pub fn backup_path(path: &std::path::Path, backupdir: &str, backupext: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(backupext);
+    if backupdir.is_empty() {
+        path.with_file_name(name)
+    } else {
+        std::path::Path::new(backupdir).join(name)
+    }
+}
+
+/// Copy the existing file aside when `'backup'` is set. `fs::copy` carries
+/// the permission bits over. Returns `Ok(None)` when disabled or there is
+/// nothing to back up yet.
+fn backup_before_write(
+    state: &EditorState,
+    path: &std::path::Path,
+) -> std::io::Result<Option<PathBuf>> {
+    if !state.options.get_bool("backup") || !path.is_file() {
+        return Ok(None);
+    }
+    let target = backup_path(
+        path,
+        state.options.get_string("backupdir"),
+        state.options.get_string("backupext"),
+    );
+    std::fs::copy(path, &target)?;
+    tracing::debug!(target: "io", backup = %target.display(), "backup_written");
+    Ok(Some(target))
+}
+
 /// Serialize the active buffer out to its associated file name (or provided target)
 /// honoring original line ending style and trailing newline presence.
 pub fn write_file(state: &mut EditorState, target: Option<&std::path::Path>) -> WriteFileResult {
     let path = if let Some(p) = target {
         p.to_path_buf()
-    } else if let Some(existing) = state.file_name.clone() {
+    } else if let Some(existing) = state.file_name().map(|p| p.to_path_buf()) {
         existing
     } else {
         return WriteFileResult::NoFilename;
     };
-    // Re-expand line endings based on original metadata
+    // The hex view is read-only: write the original bytes, never the rows.
+    // The raw view decoded them lossily, so unless it was edited the bytes
+    // go back as they were; an edit of invalid UTF-8 cannot be saved.
+    let meta = state.active_meta();
+    let raw = meta.binary.is_some() && !meta.hex_view;
+    let content = match (state.hex_view(), meta.binary.as_deref()) {
+        (Some(bytes), _) => bytes.to_vec(),
+        (None, Some(bytes)) if !meta.dirty => bytes.to_vec(),
+        (None, Some(bytes)) if std::str::from_utf8(bytes).is_err() => {
+            tracing::warn!(target: "io", path = %path.display(), "binary_lossy_write_refused");
+            return WriteFileResult::Lossy;
+        }
+        _ => serialize_buffer(state.active_buffer(), meta).into_bytes(),
+    };
+    // A failed backup must not block the write; it is surfaced as a warning.
+    let backup_error = backup_before_write(state, &path).err().map(|e| {
+        tracing::warn!(target: "io", ?e, path = %path.display(), kind = "backup_failed", "backup_failed");
+        e.to_string()
+    });
+    match std::fs::write(&path, &content) {
+        Ok(_) => {
+            if raw {
+                // What the hex view and the next clean write start from.
+                state.active_meta_mut().binary = Some(content);
+            }
+            state.set_dirty(false); // mark clean after successful write
+            state.file_watch.record(&path);
+            state.git.request_refresh();
+            match backup_error {
+                Some(err) => WriteFileResult::SuccessWithoutBackup(err),
+                None => WriteFileResult::Success,
+            }
+        }
+        Err(e) => {
+            tracing::error!(target: "io", ?e, "file_write_error");
+            WriteFileResult::Error
+        }
+    }
+}
+
+/// Re-expand line endings based on the buffer's original metadata.
+fn serialize_buffer(buffer: &Buffer, meta: &BufferMeta) -> String {
     let mut content = String::new();
-    let line_ending = state.original_line_ending.as_str();
-    let last_index = state.active_buffer().line_count();
+    let line_ending = meta.original_line_ending.as_str();
+    let last_index = buffer.line_count();
     for i in 0..last_index {
-        if let Some(mut l) = state.active_buffer().line(i) {
-            let ends_nl = l.ends_with('\n');
-            if ends_nl {
+        if let Some(mut l) = buffer.line(i) {
+            if l.ends_with('\n') {
                 l.pop();
             }
             content.push_str(&l);
-            if (i + 1 < last_index) || (state.had_trailing_newline && i + 1 == last_index) {
+            if (i + 1 < last_index) || (meta.had_trailing_newline && i + 1 == last_index) {
                 content.push_str(line_ending);
             }
         }
     }
-    match std::fs::write(&path, content.as_bytes()) {
-        Ok(_) => {
-            state.dirty = false; // mark clean after successful write
-            WriteFileResult::Success
+    content
+}
+
+/// Idle timer driving background persistence (autosave via
+/// `[files] autosave_ms`, swap updates via `'updatetime'`).
+///
+/// The runtime calls `note_activity` on input and `poll` on every Tick; `poll`
+/// fires once per idle period (after `interval` without activity), so an
+/// unnamed buffer that stays dirty is not rewritten on every tick.
+#[derive(Debug, Clone)]
+pub struct IdleTimer {
+    interval: Option<Duration>,
+    last_activity: Instant,
+    armed: bool,
+}
+
+impl IdleTimer {
+    /// `interval_ms == 0` disables the timer.
+    pub fn new(interval_ms: u64, now: Instant) -> Self {
+        Self {
+            interval: (interval_ms > 0).then(|| Duration::from_millis(interval_ms)),
+            last_activity: now,
+            armed: false,
         }
-        Err(e) => {
-            tracing::error!(target: "io", ?e, "file_write_error");
-            WriteFileResult::Error
+    }
+
+    /// Change the idle interval (e.g. after `:set updatetime`).
+    pub fn set_interval_ms(&mut self, interval_ms: u64) {
+        self.interval = (interval_ms > 0).then(|| Duration::from_millis(interval_ms));
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.interval.is_some()
+    }
+
+    pub fn note_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.armed = true;
+    }
+
+    /// True when an autosave should run now.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        match self.interval {
+            Some(interval) if self.armed && now.duration_since(self.last_activity) >= interval => {
+                self.armed = false;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Result of an autosave pass.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AutosaveReport {
+    /// Buffers written back to their own file.
+    pub written: usize,
+    /// Unnamed buffers copied to the recovery directory.
+    pub recovered: usize,
+    pub failed: usize,
+}
+
+impl AutosaveReport {
+    pub fn is_empty(&self) -> bool {
+        self.written + self.recovered + self.failed == 0
+    }
+
+    /// Status-line text describing the pass.
+    pub fn message(&self) -> String {
+        let mut parts = Vec::new();
+        if self.written > 0 {
+            parts.push(format!("{} written", self.written));
+        }
+        if self.recovered > 0 {
+            parts.push(format!("{} recovery", self.recovered));
+        }
+        if self.failed > 0 {
+            parts.push(format!("{} failed", self.failed));
         }
+        format!("[autosave] {}", parts.join(", "))
     }
 }
 
+/// Directory receiving recovery copies of unnamed buffers.
+pub fn recovery_dir() -> PathBuf {
+    std::env::temp_dir().join("oxidized-recovery")
+}
+
+/// Write every dirty buffer: named buffers to their file (clearing dirty),
+/// unnamed buffers to a recovery copy under `recovery_dir` (left dirty, since
+/// the user has not chosen a file yet). The command-line window scratch
+/// buffer is skipped.
+pub fn autosave(state: &mut EditorState, recovery_dir: &std::path::Path) -> AutosaveReport {
+    let mut report = AutosaveReport::default();
+    for entry in state.buffers.iter_mut() {
+        if !entry.meta.dirty || entry.buffer.name == CMDLINE_WINDOW_NAME {
+            continue;
+        }
+        let content = serialize_buffer(&entry.buffer, &entry.meta);
+        let (path, named) = match &entry.meta.path {
+            Some(p) => (p.clone(), true),
+            None => (
+                recovery_dir.join(format!("{}.{}.recover", entry.buffer.name, entry.id())),
+                false,
+            ),
+        };
+        if !named && let Err(e) = std::fs::create_dir_all(recovery_dir) {
+            tracing::error!(target: "io", ?e, "autosave_recovery_dir_failed");
+            report.failed += 1;
+            continue;
+        }
+        match std::fs::write(&path, content.as_bytes()) {
+            Ok(_) if named => {
+                entry.meta.dirty = false;
+                state.file_watch.record(&path);
+                report.written += 1;
+            }
+            Ok(_) => report.recovered += 1,
+            Err(e) => {
+                tracing::error!(target: "io", ?e, path = %path.display(), "autosave_write_error");
+                report.failed += 1;
+            }
+        }
+    }
+    if report.written > 0 {
+        state.git.request_refresh();
+    }
+    if !report.is_empty() {
+        tracing::info!(
+            target: "io",
+            written = report.written,
+            recovered = report.recovered,
+            failed = report.failed,
+            "autosave"
+        );
+    }
+    report
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,24 +371,119 @@ mod tests {
         let mut state = EditorState::new(buffer);
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join("out.txt");
-        state.file_name = Some(path.clone());
-        state.original_line_ending = LineEnding::Crlf;
-        state.had_trailing_newline = true;
-        state.dirty = true;
+        state.set_file_name(Some(path.clone()));
+        state.active_meta_mut().original_line_ending = LineEnding::Crlf;
+        state.active_meta_mut().had_trailing_newline = true;
+        state.set_dirty(true);
         let res = write_file(&mut state, None);
         assert!(matches!(res, WriteFileResult::Success));
-        assert!(!state.dirty, "dirty cleared after write");
+        assert!(!state.dirty(), "dirty cleared after write");
         let written = std::fs::read_to_string(&path).unwrap();
         assert!(written.contains("a\r\nb\r\n"));
     }
 
+    #[test]
+    fn binary_file_opens_as_bytes_and_writes_back_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blob.bin");
+        let bytes = vec![0u8, 1, 0xff, b'\n', b'x'];
+        std::fs::write(&path, &bytes).unwrap();
+        let OpenFileResult::Success(s) = open_file(&path) else {
+            panic!("expected success");
+        };
+        assert_eq!(s.binary.as_deref(), Some(&bytes[..]));
+        let mut state = EditorState::new(s.buffer);
+        state.set_file_name(Some(path.clone()));
+        state.load_binary(s.binary.unwrap());
+        assert!(state.hex_view().is_some());
+        std::fs::write(&path, b"clobbered").unwrap();
+        assert!(matches!(
+            write_file(&mut state, None),
+            WriteFileResult::Success
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    }
+
     #[test]
     fn write_file_no_filename() {
         let buffer = Buffer::from_str("t", "x").unwrap();
         let mut state = EditorState::new(buffer);
-        state.dirty = true;
+        state.set_dirty(true);
         let res = write_file(&mut state, None);
         assert!(matches!(res, WriteFileResult::NoFilename));
-        assert!(state.dirty, "dirty unchanged when no filename");
+        assert!(state.dirty(), "dirty unchanged when no filename");
+    }
+
+    #[test]
+    fn backup_copies_previous_contents_before_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f.txt");
+        std::fs::write(&path, "old").unwrap();
+        let mut state = EditorState::new(Buffer::from_str("f.txt", "new").unwrap());
+        state.set_file_name(Some(path.clone()));
+        state
+            .options
+            .set_default("backup", core_config::options::OptionValue::Bool(true))
+            .unwrap();
+        assert!(matches!(
+            write_file(&mut state, None),
+            WriteFileResult::Success
+        ));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("f.txt~")).unwrap(),
+            "old"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+
+        let missing = dir.path().join("missing").display().to_string();
+        state
+            .options
+            .set_default(
+                "backupdir",
+                core_config::options::OptionValue::String(missing),
+            )
+            .unwrap();
+        let res = write_file(&mut state, None);
+        assert!(
+            matches!(res, WriteFileResult::SuccessWithoutBackup(_)),
+            "write proceeds"
+        );
+    }
+
+    #[test]
+    fn idle_timer_fires_once_per_idle_period() {
+        let t0 = Instant::now();
+        let mut timer = IdleTimer::new(100, t0);
+        assert!(
+            !timer.poll(t0 + Duration::from_millis(500)),
+            "no activity yet"
+        );
+        timer.note_activity(t0);
+        assert!(!timer.poll(t0 + Duration::from_millis(50)));
+        assert!(timer.poll(t0 + Duration::from_millis(100)));
+        assert!(!timer.poll(t0 + Duration::from_millis(300)));
+        assert!(!IdleTimer::new(0, t0).enabled());
+    }
+
+    #[test]
+    fn autosave_writes_named_and_recovers_unnamed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("named.txt");
+        let mut state = EditorState::new(Buffer::from_str("named.txt", "a\n").unwrap());
+        state.set_file_name(Some(path.clone()));
+        state.set_dirty(true);
+        let scratch = state
+            .buffers
+            .open(Buffer::from_str("untitled", "b\n").unwrap(), None);
+        state.buffers.get_mut(scratch).unwrap().meta.dirty = true;
+
+        let recovery = dir.path().join("recover");
+        let report = autosave(&mut state, &recovery);
+        assert_eq!((report.written, report.recovered), (1, 1));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n");
+        assert!(!state.dirty());
+        let copy = recovery.join(format!("untitled.{scratch}.recover"));
+        assert_eq!(std::fs::read_to_string(copy).unwrap(), "b\n");
+        assert!(state.buffers.get(scratch).unwrap().meta.dirty);
     }
 }
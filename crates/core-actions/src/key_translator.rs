@@ -318,6 +318,9 @@ fn operator_char(op: OperatorKind) -> char {
         OperatorKind::Delete => 'd',
         OperatorKind::Yank => 'y',
         OperatorKind::Change => 'c',
+        OperatorKind::Fold => 'z',
+        OperatorKind::Align { right: false } => 'l',
+        OperatorKind::Align { right: true } => 'L',
     }
 }
 
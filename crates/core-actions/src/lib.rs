@@ -46,6 +46,64 @@ pub enum OperatorKind {
     Delete,
     Yank,
     Change,
+    /// `zf`: create a closed fold over the lines the motion spans.
+    Fold,
+    /// `gl` / `gL`: open `:align` (`:align!` when `right`) over the lines
+    /// the motion spans, for the delimiter to be typed.
+    Align {
+        right: bool,
+    },
+}
+
+/// `zo` / `zc` / `za` on the fold under the cursor (`core_model::fold`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldCommand {
+    Open,
+    Close,
+    Toggle,
+}
+
+/// Insert-mode completion keys (`core_state::completion`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionCommand {
+    /// `Ctrl-N` / `Ctrl-Space`: open a completion, or select the next match.
+    Next,
+    /// `Ctrl-P`: open a completion, or select the previous match.
+    Prev,
+    /// `Ctrl-Y`: keep the inserted match and close the popup.
+    Accept,
+    /// `Ctrl-E`: restore the typed prefix and close the popup.
+    Cancel,
+}
+
+/// Keys of a directory listing (`core_state::explorer`); `<CR>` there is
+/// `ListEnter` as in a quickfix window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplorerCommand {
+    /// `-`: list the parent directory.
+    Parent,
+    /// `%`: prompt for a file to edit in the listed directory.
+    NewFile,
+    /// `d`: prompt for a directory to create.
+    Mkdir,
+    /// `D`: prompt to delete the entry under the cursor.
+    Delete,
+    /// `R`: prompt for a new name of the entry under the cursor.
+    Rename,
+    /// `s`: sort by the next of name, time and size.
+    Sort,
+    /// `r`: reverse the sort order.
+    Reverse,
+    /// `gh`: show or hide dotfiles.
+    ToggleHidden,
+}
+
+/// Where `zz` / `zt` / `zb` put the cursor line in the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollCursor {
+    Center,
+    Top,
+    Bottom,
 }
 
 /// Observer hook (Refactor R1 Step 8): allows external components (macro recorder, analytics,
@@ -86,6 +144,34 @@ pub enum Action {
     Redo {
         count: u32,
     },
+    /// Move chronologically through the undo tree (`g-`/`g+`, `:earlier`/`:later`).
+    UndoTravel(core_state::UndoTravel),
+    /// Switch tab page (`gt`/`gT`). `{N}gt` goes to tab N, `{N}gT` goes back
+    /// N tabs; without a count both move one tab, wrapping around.
+    TabSwitch {
+        backward: bool,
+        count: Option<u32>,
+    },
+    /// Move window focus (`<C-w>h/j/k/l/w`), `count` steps.
+    WindowFocus {
+        direction: core_model::FocusDirection,
+        count: u32,
+    },
+    /// Open, close or toggle the fold at the cursor in the active view.
+    Fold(FoldCommand),
+    /// Insert-mode completion popup keys.
+    Completion(CompletionCommand),
+    /// `ga` / `g8`: describe the character under the cursor in the message
+    /// area, as codepoints or (`utf8`) as UTF-8 bytes.
+    InspectChar {
+        utf8: bool,
+    },
+    /// Scroll the active view so the cursor line (or `line`, 1-based, when
+    /// a count was given) sits at the center, top or bottom.
+    ScrollCursor {
+        to: ScrollCursor,
+        line: Option<u32>,
+    },
     /// Paste after cursor (Normal mode 'p'). Supports counts and optional register prefix.
     PasteAfter {
         count: u32,
@@ -117,6 +203,15 @@ pub enum Action {
         count: u32,
         register: Option<char>,
     },
+    /// Apply an operator to a text object (`diw`, `2ca(`). `around` is the `a`
+    /// form; counts and the register prefix are resolved like `ApplyOperator`.
+    ApplyOperatorTextObject {
+        op: OperatorKind,
+        object: text_object::TextObjectKind,
+        around: bool,
+        count: u32,
+        register: Option<char>,
+    },
     /// Apply an operator directly to the current active visual selection (Phase 5 Step 4).
     /// Emitted when pressing d/y/c (or x alias) while in VisualChar mode. The dispatcher will
     /// interpret the current selection span (if non-empty) and perform the operator
@@ -140,6 +235,51 @@ pub enum Action {
     CommandBackspace,       // remove last character or cancel if only ':'
     CommandCancel,          // abort command (Esc)
     CommandExecute(String), // execute full buffer (still includes leading ':')
+    CmdlineWindowOpen,      // `q:` show command history in an editable buffer
+    CmdlineWindowExecute,   // <CR> in the command-line window: run the current line
+    ListEnter, // <CR> in a quickfix / location list window or directory listing: open the entry
+    CmdlineWindowClose, // leave the command-line window without executing
+    /// `Tab` on the command line: complete the last word, or show the next
+    /// match of the open wildmenu (the previous one for `Shift-Tab`).
+    CommandComplete {
+        backward: bool,
+    },
+    /// `/` or `?`: open the search prompt. The pattern is typed with `CommandChar`
+    /// and run by `CommandExecute` like an ex command line.
+    SearchStart {
+        forward: bool,
+    },
+    /// `n` / `N`: repeat the last search `count` times, `reverse` flipping its direction.
+    SearchNext {
+        reverse: bool,
+        count: u32,
+    },
+    /// `]d` / `[d`: move to the `count`th next (previous) diagnostic.
+    DiagnosticJump {
+        backward: bool,
+        count: u32,
+    },
+    /// `gd` / `gr` / `<C-w>d`: jump to the definition (references) of the
+    /// identifier under the cursor, through the buffer's language server
+    /// or a `tags` file; `split` opens it in a new window.
+    Goto {
+        references: bool,
+        split: bool,
+    },
+    /// `<C-t>` / `<C-o>`: return to where the `count`th last goto started.
+    TagPop {
+        count: u32,
+    },
+    /// `K`: ask the buffer's language server for documentation on the
+    /// identifier under the cursor.
+    Hover,
+    /// `do` / `dp` (`put`): copy the diff hunk at the cursor from the other
+    /// compared buffer, or into it.
+    DiffHunk {
+        put: bool,
+    },
+    /// A key of the directory listing in the active buffer.
+    Explorer(ExplorerCommand),
     Quit,
 }
 
@@ -157,20 +297,43 @@ pub enum MotionKind {
     PageHalfDown,
     /// Half page up (Phase 2 Step 11)
     PageHalfUp,
+    /// Page down (`Ctrl-F`), keeping two lines of context.
+    PageDown,
+    /// Page up (`Ctrl-B`), keeping two lines of context.
+    PageUp,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EditKind {
     InsertGrapheme(String),
+    /// Insert a whole (normalized) text, newlines included, as one buffer
+    /// operation and its own undo step (bracketed paste).
+    InsertText(String),
     InsertNewline,
+    /// Insert-mode `<Tab>`: a tab, or spaces by `'expandtab'` and
+    /// `'softtabstop'`.
+    InsertTab,
     Backspace,
-    DeleteUnder { count: u32, register: Option<char> },
-    DeleteLeft { count: u32, register: Option<char> },
+    /// Insert-mode `Ctrl-W`: delete the word before the cursor.
+    DeleteWordBefore,
+    /// Insert-mode `Ctrl-U`: delete back to the indent, or to the line start
+    /// when already there.
+    DeleteToLineStart,
+    DeleteUnder {
+        count: u32,
+        register: Option<char>,
+    },
+    DeleteLeft {
+        count: u32,
+        register: Option<char>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ModeChange {
     EnterInsert,
+    /// `gi`: enter Insert mode where it was last left in this buffer.
+    ResumeInsert,
     LeaveInsert,
     EnterVisualChar,
     LeaveVisualChar,
@@ -197,60 +360,135 @@ pub fn translate_key_with_config(
         .action
 }
 
+pub mod command_registry; // Commands Step 1: user-defined ex commands
 pub mod dispatcher;
 pub mod io_ops; // Refactor R2 Step 5: file IO helpers
 
+pub use command_registry::{ArgSpec, CommandRegistry, CommandSpec, CompletionHint};
 pub use dispatcher::dispatch; // re-export for test convenience (Phase 5 Visual operators)
 
 // -------------------------------------------------------------------------------------------------
 // NGI Adapter: maps key sequences via core-keymap to existing Action enum.
 // -------------------------------------------------------------------------------------------------
 pub mod ngi_adapter {
-    use super::{Action, EditKind, Mode, ModeChange, MotionKind, OperatorKind};
+    use super::{
+        Action, CompletionCommand, EditKind, ExplorerCommand, FoldCommand, Mode, ModeChange,
+        MotionKind, OperatorKind, ScrollCursor,
+    };
     use core_config::Config; // for timeout settings (passed in future wiring)
-    use core_events::{KeyCode, KeyEvent, KeyEventExt, KeyModifiers, KeyToken, ModMask, NamedKey};
+    use core_config::{KeymapConfig, MappingValue};
+    use core_events::{
+        KeyCode, KeyEvent, KeyEventExt, KeyModifiers, KeyToken, MAPPING_EXPANSIONS_ABORTED,
+        ModMask, NamedKey,
+    };
     use core_keymap::{
-        ComposedAction, MappingTrie, PendingContext, baseline_normal_specs, compose_with_context,
+        ComposedAction, KeyTokenPattern, MAX_MAP_DEPTH, MapMode, MappingIssue, MappingLayers,
+        MappingOutput, MappingSpec, MappingTrie, PendingContext, Resolution, baseline_normal_specs,
+        baseline_operator_pending_specs, canonical, cmdline_window_specs, compile_user_specs,
+        compose_with_context, explorer_specs, key_display, list_window_specs,
+        user::{DEFAULT_LEADER, parse_leader},
     };
+    use std::collections::{BTreeMap, VecDeque};
+    use std::sync::atomic::Ordering;
     use std::time::{Duration, Instant};
-    use tracing::{debug, trace};
+    use tracing::{debug, trace, warn};
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     enum PartialKind {
         Generic,
     }
 
+    /// Insert-mode `Ctrl-V` waiting for the key to insert literally, or for
+    /// the hex digits of `Ctrl-V u{4}` / `Ctrl-V U{8}`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum LiteralInput {
+        Key,
+        Hex { prefix: char, digits: String },
+    }
+
+    /// Which Normal keys the active window uses: some buffers bind `<CR>`
+    /// (and a directory listing more) in place of the usual keys.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum NormalKeys {
+        #[default]
+        Default,
+        /// `<CR>` runs the line (`cmdline_window_specs`).
+        CmdlineWindow,
+        /// `<CR>` opens the entry (`list_window_specs`).
+        ListWindow,
+        /// `explorer_specs`, without user mappings.
+        Explorer,
+    }
+
+    impl NormalKeys {
+        /// The keys of the window `state` is in.
+        pub fn for_state(state: &core_state::EditorState) -> Self {
+            if state.cmdline_window_active() {
+                NormalKeys::CmdlineWindow
+            } else if state.explorer().is_some() {
+                NormalKeys::Explorer
+            } else if state.quickfix_window().is_some() {
+                NormalKeys::ListWindow
+            } else {
+                NormalKeys::Default
+            }
+        }
+    }
+
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum PendingState {
         Idle,
         AwaitingMore { buffered_len: usize },
     }
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    enum KeypressMapping {
-        Supported {
-            key_event: KeyEvent,
-            dropped_mods: ModMask,
-        },
-        Unsupported {
-            dropped_mods: ModMask,
-        },
-    }
-
-    fn map_keypress(keypress: &KeyEventExt) -> KeypressMapping {
-        let (base_token, accumulated_mods) = flatten_token(&keypress.token);
-        let (key_mods, dropped_mods) = convert_mod_mask(accumulated_mods);
-
-        match keycode_from_token(&base_token) {
-            Some(code) => KeypressMapping::Supported {
-                key_event: KeyEvent {
-                    code,
-                    mods: key_mods,
-                },
-                dropped_mods,
-            },
-            None => KeypressMapping::Unsupported { dropped_mods },
+    /// `token` in `canonical` form with the modifiers the translator
+    /// ignores (Meta, Super) removed, plus the removed ones.
+    fn supported_token(token: &KeyToken) -> (KeyToken, ModMask) {
+        let (base, mods) = flatten_token(token);
+        let (_, dropped_mods) = convert_mod_mask(mods);
+        let token = canonical(&KeyToken::Chord {
+            base: Box::new(base),
+            mods: mods - dropped_mods,
+        });
+        (token, dropped_mods)
+    }
+
+    /// The `KeyEvent` for `token`, when one can name it (not `<F5>`,
+    /// `<Home>`).
+    fn key_event(token: &KeyToken) -> Option<KeyEvent> {
+        let (base, mods) = flatten_token(token);
+        let (mods, dropped_mods) = convert_mod_mask(mods);
+        let code = keycode_from_token(&base)?;
+        dropped_mods.is_empty().then_some(KeyEvent { code, mods })
+    }
+
+    /// The `canonical` token for `key`, as the mapping tries compare it.
+    fn key_token(key: &KeyEvent) -> KeyToken {
+        let base = match key.code {
+            KeyCode::Char(c) => KeyToken::Char(c),
+            KeyCode::Enter => KeyToken::Named(NamedKey::Enter),
+            KeyCode::Esc => KeyToken::Named(NamedKey::Esc),
+            KeyCode::Backspace => KeyToken::Named(NamedKey::Backspace),
+            KeyCode::Tab => KeyToken::Named(NamedKey::Tab),
+            KeyCode::Up => KeyToken::Named(NamedKey::Up),
+            KeyCode::Down => KeyToken::Named(NamedKey::Down),
+            KeyCode::Left => KeyToken::Named(NamedKey::Left),
+            KeyCode::Right => KeyToken::Named(NamedKey::Right),
+        };
+        let mut mods = ModMask::empty();
+        for (key_mod, mod_mask) in [
+            (KeyModifiers::CTRL, ModMask::CTRL),
+            (KeyModifiers::ALT, ModMask::ALT),
+            (KeyModifiers::SHIFT, ModMask::SHIFT),
+        ] {
+            if key.mods.contains(key_mod) {
+                mods |= mod_mask;
+            }
         }
+        canonical(&KeyToken::Chord {
+            base: Box::new(base),
+            mods,
+        })
     }
 
     fn flatten_token(token: &KeyToken) -> (KeyToken, ModMask) {
@@ -331,6 +569,39 @@ pub mod ngi_adapter {
             ));
         }
 
+        #[test]
+        fn ingest_ctrl_w_chord_sequence_focuses_window() {
+            let mut translator = NgiTranslator::new();
+            let ctrl_w = KeyEventExt::new(KeyToken::Chord {
+                base: Box::new(KeyToken::Char('w')),
+                mods: ModMask::CTRL,
+            });
+            let cfg = Config::default();
+            let first = translator.ingest_keypress(Mode::Normal, "", &ctrl_w, &cfg);
+            assert!(first.action.is_none());
+            assert!(matches!(
+                first.pending_state,
+                PendingState::AwaitingMore { .. }
+            ));
+            let keypress = KeyEventExt::new(KeyToken::Char('j'));
+            let resolution = translator.ingest_keypress(Mode::Normal, "", &keypress, &cfg);
+            assert!(matches!(
+                resolution.action,
+                Some(Action::WindowFocus {
+                    direction: core_model::FocusDirection::Down,
+                    count: 1
+                })
+            ));
+            // An unmapped Ctrl chord is dropped without leaving pending input.
+            let ctrl_x = KeyEventExt::new(KeyToken::Chord {
+                base: Box::new(KeyToken::Char('x')),
+                mods: ModMask::CTRL,
+            });
+            let resolution = translator.ingest_keypress(Mode::Normal, "", &ctrl_x, &cfg);
+            assert!(resolution.action.is_none());
+            assert!(matches!(resolution.pending_state, PendingState::Idle));
+        }
+
         #[test]
         fn ingest_unsupported_named_key_yields_no_action() {
             let mut translator = NgiTranslator::new();
@@ -408,20 +679,335 @@ pub mod ngi_adapter {
 
     #[derive(Debug)]
     pub struct NgiTranslator {
-        trie: MappingTrie,
+        /// Built-in Normal keys; right-hand sides of user mappings resolve here.
+        base: MappingTrie,
+        /// Built-in operator-pending keys (text objects in place of `i`).
+        base_pending: MappingTrie,
+        /// Normal layers used in place of the usual one, by `normal_keys`.
+        /// The command-line and list window ones include user mappings; the
+        /// directory listing one does not.
+        cmdline_window: MappingTrie,
+        list_window: MappingTrie,
+        explorer: MappingTrie,
+        normal_keys: NormalKeys,
+        /// Built-in plus user Normal and operator-pending mappings, and the
+        /// user-only layers of the other modes.
+        layers: MappingLayers,
+        /// Left-hand sides of user Normal / operator-pending mappings, for
+        /// prefix waits.
+        user_lhs: Vec<(MapMode, Vec<KeyToken>)>,
         ctx: PendingContext,
-        buffer: Vec<char>,
+        /// Keys typed towards a mapping, as `canonical` tokens.
+        buffer: Vec<KeyToken>,
+        /// Layer the buffered keys were typed in (what a timeout flushes).
+        buffered_in: MapMode,
+        /// Keys user mappings expanded to, in the order they run.
+        mapped: VecDeque<MappedKey>,
+        /// Set while a key from a `noremap` right-hand side is translated.
+        noremap: bool,
+        /// Expansion depth of the key being translated (0 when typed).
+        depth: u32,
+        /// A recursive expansion hit `MAX_MAP_DEPTH` since the last check.
+        expansion_aborted: bool,
+        /// Key after `"` that named no register, since the last check.
+        invalid_register: Option<KeyToken>,
         partial_timer: PartialTimeoutState,
+        /// Insert-mode `Ctrl-V` in progress; the keys it reads skip mappings.
+        literal: Option<LiteralInput>,
+    }
+
+    /// Key produced by a user mapping, waiting to be translated.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MappedKey {
+        pub key: KeyToken,
+        /// Whether user mappings apply to it (`map` rather than `noremap`).
+        pub remap: bool,
+        depth: u32,
     }
 
     impl NgiTranslator {
         pub fn new() -> Self {
+            let mut layers = MappingLayers::new(MappingTrie::build(baseline_normal_specs()));
+            layers.set(
+                MapMode::OperatorPending,
+                MappingTrie::build(baseline_operator_pending_specs()),
+            );
             Self {
-                trie: MappingTrie::build(baseline_normal_specs()),
+                base: MappingTrie::build(baseline_normal_specs()),
+                base_pending: MappingTrie::build(baseline_operator_pending_specs()),
+                cmdline_window: MappingTrie::build(cmdline_window_specs()),
+                list_window: MappingTrie::build(list_window_specs()),
+                explorer: MappingTrie::build(explorer_specs()),
+                normal_keys: NormalKeys::Default,
+                layers,
+                user_lhs: Vec::new(),
                 ctx: PendingContext::default(),
                 buffer: Vec::new(),
+                buffered_in: MapMode::Normal,
+                mapped: VecDeque::new(),
+                noremap: false,
+                depth: 0,
+                expansion_aborted: false,
+                invalid_register: None,
                 partial_timer: PartialTimeoutState::new(),
+                literal: None,
+            }
+        }
+
+        /// Translator with the user mappings of `[keymap]` merged in, plus
+        /// what compiling them reported. An invalid leader falls back to
+        /// `\\`.
+        pub fn from_keymap(keymap: &KeymapConfig) -> (Self, Vec<MappingIssue>) {
+            let mut issues = Vec::new();
+            let leader = parse_leader(&keymap.leader).unwrap_or_else(|error| {
+                issues.push(MappingIssue::Invalid {
+                    lhs: "leader".into(),
+                    error,
+                });
+                KeyToken::Char(DEFAULT_LEADER)
+            });
+            let mut translator = Self::new();
+            // These layers extend the built-in keys. Without operator-pending
+            // mappings of its own, that layer takes the Normal ones.
+            let mut normal_user = Vec::new();
+            for (maps, layer, builtin) in [
+                (&keymap.normal, MapMode::Normal, baseline_normal_specs()),
+                (
+                    &keymap.operator,
+                    MapMode::OperatorPending,
+                    baseline_operator_pending_specs(),
+                ),
+            ] {
+                let user = compile_user_specs(mapping_pairs(maps), &leader, &builtin);
+                issues.extend(user.issues);
+                for spec in &user.specs {
+                    let lhs = spec.sequence.iter().map(KeyTokenPattern::token).collect();
+                    translator.user_lhs.push((layer, lhs));
+                }
+                let user_specs = if layer == MapMode::Normal {
+                    normal_user = user.specs.clone();
+                    if !normal_user.is_empty() {
+                        let with_user = |mut specs: Vec<MappingSpec>| {
+                            specs.extend(normal_user.iter().cloned());
+                            MappingTrie::build(specs)
+                        };
+                        translator.cmdline_window = with_user(cmdline_window_specs());
+                        translator.list_window = with_user(list_window_specs());
+                    }
+                    user.specs
+                } else if user.specs.is_empty() {
+                    std::mem::take(&mut normal_user)
+                } else {
+                    user.specs
+                };
+                if !user_specs.is_empty() {
+                    let mut specs = builtin;
+                    specs.extend(user_specs);
+                    translator.layers.set(layer, MappingTrie::build(specs));
+                }
+            }
+            for (maps, layer) in [
+                (&keymap.insert, MapMode::Insert),
+                (&keymap.visual, MapMode::Visual),
+                (&keymap.command, MapMode::CommandLine),
+            ] {
+                let user = compile_user_specs(mapping_pairs(maps), &leader, &[]);
+                issues.extend(user.issues);
+                if !user.specs.is_empty() {
+                    translator.layers.set(layer, MappingTrie::build(user.specs));
+                }
+            }
+            (translator, issues)
+        }
+
+        /// Use the Normal keys of the active window (`NormalKeys::for_state`).
+        /// The runtime sets this before each key.
+        pub fn set_normal_keys(&mut self, keys: NormalKeys) {
+            self.normal_keys = keys;
+        }
+
+        /// Next key a user mapping produced. The runtime feeds these to
+        /// `translate_mapped` until none is left.
+        pub fn take_mapped_key(&mut self) -> Option<MappedKey> {
+            self.mapped.pop_front()
+        }
+
+        /// True while keys a user mapping produced wait to be translated.
+        pub fn has_mapped_keys(&self) -> bool {
+            !self.mapped.is_empty()
+        }
+
+        /// `translate` for a key from `take_mapped_key`.
+        pub fn translate_mapped(
+            &mut self,
+            mode: Mode,
+            pending_command: &str,
+            mapped: &MappedKey,
+            cfg: &Config,
+            timestamp: Instant,
+        ) -> NgiResolution {
+            self.noremap = !mapped.remap;
+            self.depth = mapped.depth;
+            let key = key_event(&mapped.key);
+            let resolution = self.translate_token(
+                mode,
+                pending_command,
+                mapped.key.clone(),
+                key.as_ref(),
+                cfg,
+                timestamp,
+            );
+            self.noremap = false;
+            self.depth = 0;
+            resolution
+        }
+
+        /// Whether a recursive mapping was aborted (E223) since the last call.
+        pub fn take_expansion_aborted(&mut self) -> bool {
+            std::mem::take(&mut self.expansion_aborted)
+        }
+
+        /// Key typed after `"` that names no register since the last call,
+        /// as Vim echoes it (`^W`); the command it prefixed was dropped.
+        pub fn take_invalid_register(&mut self) -> Option<String> {
+            self.invalid_register.take().map(|key| key_display(&key))
+        }
+
+        /// Drop the pending command after `"` + `key` named no register.
+        fn reject_register(&mut self, key: KeyToken) {
+            debug!(target: "input.context", register = ?key, "invalid_register");
+            self.ctx.reset_transient();
+            self.ctx.register = None;
+            self.buffer.clear();
+            self.partial_timer.clear();
+            self.invalid_register = Some(key);
+        }
+
+        /// Run `keys` before anything already queued, as Vim inserts an
+        /// expansion at the front of the typeahead.
+        fn prepend_keys(&mut self, keys: Vec<MappedKey>) {
+            for key in keys.into_iter().rev() {
+                self.mapped.push_front(key);
+            }
+        }
+
+        fn mapped_keys(&self, keys: &[KeyToken], remap: bool) -> Vec<MappedKey> {
+            keys.iter()
+                .map(|key| MappedKey {
+                    key: key.clone(),
+                    remap,
+                    depth: self.depth,
+                })
+                .collect()
+        }
+
+        /// Buffered keys as typed: the first one is taken literally, the
+        /// rest may start another mapping.
+        fn take_buffer_as_typed(&mut self) -> Vec<MappedKey> {
+            let keys = std::mem::take(&mut self.buffer);
+            self.partial_timer.clear();
+            match keys.split_first() {
+                Some((first, rest)) => {
+                    let mut queued = self.mapped_keys(std::slice::from_ref(first), false);
+                    queued.extend(self.mapped_keys(rest, true));
+                    queued
+                }
+                None => Vec::new(),
+            }
+        }
+
+        fn requeue_buffer(&mut self) {
+            let queued = self.take_buffer_as_typed();
+            self.prepend_keys(queued);
+        }
+
+        /// Expand a matched user mapping: its right-hand side, then the keys
+        /// typed after it. A recursive right-hand side nested past
+        /// `MAX_MAP_DEPTH` drops all pending input instead.
+        fn expand_mapping(&mut self, consumed: usize, rhs: &[KeyToken], remap: bool) {
+            let depth = self.depth + 1;
+            if depth > MAX_MAP_DEPTH {
+                MAPPING_EXPANSIONS_ABORTED.fetch_add(1, Ordering::Relaxed);
+                warn!(target: "input.map", depth, "mapping_recursion_aborted");
+                self.mapped.clear();
+                self.buffer.clear();
+                self.partial_timer.clear();
+                self.expansion_aborted = true;
+                return;
+            }
+            debug!(target: "input.map", consumed, rhs_len = rhs.len(), remap, depth, "user_mapping_expanded");
+            let lhs: Vec<KeyToken> = self.buffer.drain(0..consumed).collect();
+            // `:map ab abc` runs its leading `ab` as built-in keys.
+            let literal = if remap && rhs.starts_with(&lhs) {
+                lhs.len()
+            } else {
+                0
+            };
+            let mut queued: Vec<MappedKey> = rhs
+                .iter()
+                .enumerate()
+                .map(|(i, key)| MappedKey {
+                    key: key.clone(),
+                    remap: remap && i >= literal,
+                    depth,
+                })
+                .collect();
+            let rest = std::mem::take(&mut self.buffer);
+            queued.extend(self.mapped_keys(&rest, true));
+            self.prepend_keys(queued);
+            self.partial_timer.clear();
+        }
+
+        /// Whether the buffer is a strict prefix of a user mapping of
+        /// `layer` (Normal or operator-pending).
+        fn awaits_user_mapping(&self, layer: MapMode) -> bool {
+            !self.noremap
+                && self.user_lhs.iter().any(|(l, lhs)| {
+                    *l == layer && lhs.len() > self.buffer.len() && lhs.starts_with(&self.buffer)
+                })
+        }
+
+        /// User mappings of the Insert, Visual and command-line layers,
+        /// which see keys before the built-in handling does. `Some` when the
+        /// key was buffered or expanded.
+        fn prefilter_user_mapping(
+            &mut self,
+            layer: MapMode,
+            key: &KeyToken,
+            cfg: &Config,
+            timestamp: Instant,
+        ) -> Option<NgiResolution> {
+            if layer == MapMode::Normal {
+                return None;
             }
+            let trie = self.layers.get(layer)?;
+            // A register name after `"` is never mapped.
+            if self.noremap || self.ctx.awaiting_register {
+                return None;
+            }
+            self.buffer.push(key.clone());
+            self.buffered_in = layer;
+            match trie.resolve(&self.buffer) {
+                Resolution::Matched {
+                    consumed,
+                    ref output,
+                    ambiguous: false,
+                } if let Some((rhs, remap)) = mapping_keys(output) => {
+                    self.expand_mapping(consumed, rhs, remap)
+                }
+                Resolution::Matched { .. } | Resolution::NeedMore => {
+                    self.partial_timer.start(PartialKind::Generic, timestamp);
+                }
+                Resolution::FallbackLiteral(_) if self.buffer.len() == 1 => {
+                    self.buffer.clear();
+                    self.partial_timer.clear();
+                    return None;
+                }
+                Resolution::FallbackLiteral(_) | Resolution::InvalidRegister(_) => {
+                    self.requeue_buffer()
+                }
+            }
+            Some(self.finalize_resolution(None, cfg))
         }
 
         pub fn reset_for_mode(&mut self, mode: Mode) {
@@ -435,6 +1021,53 @@ pub mod ngi_adapter {
             let _ = compose_with_context(&mut self.ctx, &core_keymap::MappingOutput::Esc);
             self.buffer.clear();
             self.partial_timer.clear();
+            self.literal = None;
+        }
+
+        /// Next key of an Insert-mode `Ctrl-V`. Any key but `u` / `U` is
+        /// inserted as itself (a Ctrl chord as its control character);
+        /// after `u` / `U` up to 4 / 8 hex digits name a codepoint, and the
+        /// first other key ends the code early and is then typed as usual.
+        /// A value that is not a Unicode scalar inserts nothing.
+        fn continue_literal(&mut self, literal: LiteralInput, key: &KeyToken) -> Option<Action> {
+            let insert = |c: char| Some(Action::Edit(EditKind::InsertGrapheme(c.to_string())));
+            match literal {
+                LiteralInput::Key => match key {
+                    KeyToken::Char(prefix @ ('u' | 'U')) => {
+                        self.literal = Some(LiteralInput::Hex {
+                            prefix: *prefix,
+                            digits: String::new(),
+                        });
+                        None
+                    }
+                    _ => literal_char(key).and_then(insert),
+                },
+                LiteralInput::Hex { prefix, mut digits } => {
+                    let max = if prefix == 'u' { 4 } else { 8 };
+                    match key {
+                        KeyToken::Char(c) if c.is_ascii_hexdigit() => {
+                            digits.push(*c);
+                            if digits.len() < max {
+                                self.literal = Some(LiteralInput::Hex { prefix, digits });
+                                return None;
+                            }
+                        }
+                        _ => self.prepend_keys(vec![MappedKey {
+                            key: key.clone(),
+                            remap: true,
+                            depth: self.depth,
+                        }]),
+                    }
+                    if digits.is_empty() {
+                        return insert(prefix);
+                    }
+                    let codepoint = u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32);
+                    debug!(target: "actions.translate", %digits, valid = codepoint.is_some(), "literal_codepoint");
+                    codepoint.and_then(insert)
+                }
+            }
         }
 
         pub fn ingest_keypress(
@@ -444,40 +1077,31 @@ pub mod ngi_adapter {
             keypress: &KeyEventExt,
             cfg: &Config,
         ) -> NgiResolution {
-            match map_keypress(keypress) {
-                KeypressMapping::Supported {
-                    key_event,
-                    dropped_mods,
-                } => {
-                    if !dropped_mods.is_empty() {
-                        debug!(
-                            target: "actions.translate",
-                            dropped_mods = ?dropped_mods,
-                            chord = ?keypress.token,
-                            "keypress_mods_dropped"
-                        );
-                    }
-                    trace!(
-                        target: "actions.translate",
-                        kind = "keypress_ingest",
-                        repeat = keypress.repeat,
-                        timestamp = ?keypress.timestamp,
-                        chord = ?keypress.token
-                    );
-                    self.translate(mode, pending_command, &key_event, cfg, keypress.timestamp)
-                }
-                KeypressMapping::Unsupported { dropped_mods } => {
-                    trace!(
-                        target: "actions.translate",
-                        kind = "keypress_unsupported",
-                        repeat = keypress.repeat,
-                        timestamp = ?keypress.timestamp,
-                        chord = ?keypress.token,
-                        dropped_mods = ?dropped_mods
-                    );
-                    self.finalize_resolution(None, cfg)
-                }
+            let (token, dropped_mods) = supported_token(&keypress.token);
+            if !dropped_mods.is_empty() {
+                debug!(
+                    target: "actions.translate",
+                    dropped_mods = ?dropped_mods,
+                    chord = ?keypress.token,
+                    "keypress_mods_dropped"
+                );
             }
+            trace!(
+                target: "actions.translate",
+                kind = "keypress_ingest",
+                repeat = keypress.repeat,
+                timestamp = ?keypress.timestamp,
+                chord = ?keypress.token
+            );
+            let key = key_event(&token);
+            self.translate_token(
+                mode,
+                pending_command,
+                token,
+                key.as_ref(),
+                cfg,
+                keypress.timestamp,
+            )
         }
 
         pub fn translate(
@@ -488,7 +1112,45 @@ pub mod ngi_adapter {
             cfg: &Config,
             timestamp: Instant,
         ) -> NgiResolution {
-            if pending_command.starts_with(':') {
+            self.translate_token(
+                mode,
+                pending_command,
+                key_token(key),
+                Some(key),
+                cfg,
+                timestamp,
+            )
+        }
+
+        /// `translate` for `token`, with `key` its `KeyEvent` when it has
+        /// one. Keys without one (`<F5>`, `<Home>`) only reach mappings.
+        fn translate_token(
+            &mut self,
+            mode: Mode,
+            pending_command: &str,
+            token: KeyToken,
+            key: Option<&KeyEvent>,
+            cfg: &Config,
+            timestamp: Instant,
+        ) -> NgiResolution {
+            let layer = map_mode(mode, pending_command);
+            if layer == MapMode::Insert
+                && let Some(literal) = self.literal.take()
+            {
+                let action = self.continue_literal(literal, &token);
+                return self.finalize_resolution(action, cfg);
+            }
+            if let Some(resolution) = self.prefilter_user_mapping(layer, &token, cfg, timestamp) {
+                return resolution;
+            }
+            let Some(key) = key else {
+                if layer == MapMode::Normal {
+                    return self.resolve_normal(token, pending_command, cfg, timestamp);
+                }
+                return self.finalize_resolution(None, cfg);
+            };
+
+            if layer == MapMode::CommandLine {
                 let action = match key.code {
                     KeyCode::Char(c)
                         if !key.mods.contains(KeyModifiers::CTRL)
@@ -513,6 +1175,11 @@ pub mod ngi_adapter {
                         trace!(target: "actions.translate", kind = "command_cancel");
                         Some(Action::CommandCancel)
                     }
+                    KeyCode::Tab => {
+                        let backward = key.mods.contains(KeyModifiers::SHIFT);
+                        trace!(target: "actions.translate", kind = "command_complete", backward);
+                        Some(Action::CommandComplete { backward })
+                    }
                     _ => None,
                 };
                 return self.finalize_resolution(action, cfg);
@@ -522,6 +1189,10 @@ pub mod ngi_adapter {
                 self.buffer.clear();
                 self.partial_timer.clear();
                 let ctx = &mut self.ctx;
+                // `z` waits for the `f` of `zf`, `g` for the `l` / `L` of
+                // `gl` / `gL`; any other key drops them.
+                let prefix = ctx.operator.take();
+                let (z_prefix, g_prefix) = (prefix == Some('z'), prefix == Some('g'));
                 let action = if key.mods.contains(KeyModifiers::CTRL) {
                     match key.code {
                         KeyCode::Char('d') => {
@@ -532,6 +1203,14 @@ pub mod ngi_adapter {
                             trace!(target: "actions.translate", motion = ?MotionKind::PageHalfUp, "visual_half_page");
                             Some(emit_visual_motion(MotionKind::PageHalfUp, ctx))
                         }
+                        KeyCode::Char('f') => {
+                            trace!(target: "actions.translate", motion = ?MotionKind::PageDown, "visual_page");
+                            Some(emit_visual_motion(MotionKind::PageDown, ctx))
+                        }
+                        KeyCode::Char('b') => {
+                            trace!(target: "actions.translate", motion = ?MotionKind::PageUp, "visual_page");
+                            Some(emit_visual_motion(MotionKind::PageUp, ctx))
+                        }
                         _ => None,
                     }
                 } else {
@@ -553,10 +1232,12 @@ pub mod ngi_adapter {
                             None
                         }
                         KeyCode::Char(c) if ctx.awaiting_register => {
-                            if c.is_ascii_alphanumeric() {
+                            if core_keymap::is_register_name(c) {
                                 ctx.register = Some(c);
                                 ctx.awaiting_register = false;
                                 debug!(target: "input.context", register = %c, "visual_register_set");
+                            } else {
+                                self.reject_register(KeyToken::Char(c));
                             }
                             None
                         }
@@ -564,6 +1245,33 @@ pub mod ngi_adapter {
                             extend_visual_count(ctx, c);
                             None
                         }
+                        KeyCode::Char('z') => {
+                            ctx.operator = Some('z');
+                            None
+                        }
+                        KeyCode::Char('f') if z_prefix => {
+                            let (count, register) = take_visual_prefix(ctx);
+                            trace!(target: "actions.translate", op = ?OperatorKind::Fold, "visual_operator");
+                            Some(Action::VisualOperator {
+                                op: OperatorKind::Fold,
+                                register,
+                                count,
+                            })
+                        }
+                        KeyCode::Char('g') => {
+                            ctx.operator = Some('g');
+                            None
+                        }
+                        KeyCode::Char(c @ ('l' | 'L')) if g_prefix => {
+                            let (count, register) = take_visual_prefix(ctx);
+                            let op = OperatorKind::Align { right: c == 'L' };
+                            trace!(target: "actions.translate", op = ?op, "visual_operator");
+                            Some(Action::VisualOperator {
+                                op,
+                                register,
+                                count,
+                            })
+                        }
                         KeyCode::Char('0') => {
                             if ctx.count_prefix.is_some() {
                                 extend_visual_count(ctx, '0');
@@ -628,6 +1336,14 @@ pub mod ngi_adapter {
                             ctx.register = None;
                             Some(Action::ModeChange(ModeChange::LeaveVisualChar))
                         }
+                        // The selection stays until the command runs, so
+                        // its range (and `'<,'>`) can still see it.
+                        KeyCode::Char(':') => {
+                            ctx.reset_transient();
+                            ctx.register = None;
+                            trace!(target: "actions.translate", kind = "visual_command_start");
+                            Some(Action::CommandStart)
+                        }
                         _ => None,
                     }
                 };
@@ -647,10 +1363,39 @@ pub mod ngi_adapter {
                         trace!(target: "actions.translate", kind = "insert_newline");
                         Some(Action::Edit(EditKind::InsertNewline))
                     }
+                    KeyCode::Tab => {
+                        trace!(target: "actions.translate", kind = "insert_tab");
+                        Some(Action::Edit(EditKind::InsertTab))
+                    }
                     KeyCode::Backspace => {
                         trace!(target: "actions.translate", kind = "backspace");
                         Some(Action::Edit(EditKind::Backspace))
                     }
+                    KeyCode::Char('w') if key.mods.contains(KeyModifiers::CTRL) => {
+                        trace!(target: "actions.translate", kind = "delete_word_before");
+                        Some(Action::Edit(EditKind::DeleteWordBefore))
+                    }
+                    KeyCode::Char('u') if key.mods.contains(KeyModifiers::CTRL) => {
+                        trace!(target: "actions.translate", kind = "delete_to_line_start");
+                        Some(Action::Edit(EditKind::DeleteToLineStart))
+                    }
+                    KeyCode::Char(c @ ('n' | 'p' | ' ' | 'y' | 'e'))
+                        if key.mods.contains(KeyModifiers::CTRL) =>
+                    {
+                        let command = match c {
+                            'p' => CompletionCommand::Prev,
+                            'y' => CompletionCommand::Accept,
+                            'e' => CompletionCommand::Cancel,
+                            _ => CompletionCommand::Next,
+                        };
+                        trace!(target: "actions.translate", kind = "completion", ?command);
+                        Some(Action::Completion(command))
+                    }
+                    KeyCode::Char('v') if key.mods.contains(KeyModifiers::CTRL) => {
+                        trace!(target: "actions.translate", kind = "literal_start");
+                        self.literal = Some(LiteralInput::Key);
+                        None
+                    }
                     KeyCode::Esc => {
                         trace!(target: "actions.translate", kind = "leave_insert");
                         Some(Action::ModeChange(ModeChange::LeaveInsert))
@@ -669,90 +1414,72 @@ pub mod ngi_adapter {
                 return self.finalize_resolution(Some(Action::CommandStart), cfg);
             }
 
-            if matches!(mode, Mode::Normal) {
-                match key.code {
-                    KeyCode::Left => {
-                        trace!(target: "actions.translate", motion = ?MotionKind::Left, "normal_named_motion");
-                        return self
-                            .finalize_resolution(Some(Action::Motion(MotionKind::Left)), cfg);
-                    }
-                    KeyCode::Right => {
-                        trace!(target: "actions.translate", motion = ?MotionKind::Right, "normal_named_motion");
-                        return self
-                            .finalize_resolution(Some(Action::Motion(MotionKind::Right)), cfg);
-                    }
-                    KeyCode::Up => {
-                        trace!(target: "actions.translate", motion = ?MotionKind::Up, "normal_named_motion");
-                        return self.finalize_resolution(Some(Action::Motion(MotionKind::Up)), cfg);
-                    }
-                    KeyCode::Down => {
-                        trace!(target: "actions.translate", motion = ?MotionKind::Down, "normal_named_motion");
-                        return self
-                            .finalize_resolution(Some(Action::Motion(MotionKind::Down)), cfg);
-                    }
-                    KeyCode::Char('d') if key.mods.contains(KeyModifiers::CTRL) => {
-                        trace!(target: "actions.translate", motion = ?MotionKind::PageHalfDown, "normal_half_page");
-                        return self.finalize_resolution(
-                            Some(Action::Motion(MotionKind::PageHalfDown)),
-                            cfg,
-                        );
-                    }
-                    KeyCode::Char('u') if key.mods.contains(KeyModifiers::CTRL) => {
-                        trace!(target: "actions.translate", motion = ?MotionKind::PageHalfUp, "normal_half_page");
-                        return self.finalize_resolution(
-                            Some(Action::Motion(MotionKind::PageHalfUp)),
-                            cfg,
-                        );
-                    }
-                    KeyCode::Char('r') if key.mods.contains(KeyModifiers::CTRL) => {
-                        let count = self.ctx.count_prefix.take().unwrap_or(1).max(1);
-                        self.ctx.operator = None;
-                        self.ctx.post_op_count = None;
-                        self.ctx.register = None;
-                        self.ctx.awaiting_register = false;
-                        trace!(target: "actions.translate", kind = "redo", count);
-                        return self.finalize_resolution(Some(Action::Redo { count }), cfg);
-                    }
-                    KeyCode::Esc => {
-                        let _ =
-                            compose_with_context(&mut self.ctx, &core_keymap::MappingOutput::Esc);
-                        self.buffer.clear();
-                        self.partial_timer.clear();
-                        trace!(target: "actions.translate", kind = "esc_clear");
-                        return self.finalize_resolution(None, cfg);
-                    }
-                    _ => {}
-                }
+            if matches!(mode, Mode::Normal)
+                && let KeyCode::Char(prompt @ ('/' | '?')) = key.code
+                && key.mods.is_empty()
+                && self.buffer.is_empty()
+            {
+                self.ctx.reset_transient();
+                trace!(target: "actions.translate", kind = "search_start", %prompt);
+                return self.finalize_resolution(
+                    Some(Action::SearchStart {
+                        forward: prompt == '/',
+                    }),
+                    cfg,
+                );
             }
 
-            if !matches!(mode, Mode::Normal) {
+            if matches!(mode, Mode::Normal) && key.code == KeyCode::Esc {
+                let _ = compose_with_context(&mut self.ctx, &core_keymap::MappingOutput::Esc);
+                self.buffer.clear();
+                self.partial_timer.clear();
+                trace!(target: "actions.translate", kind = "esc_clear");
                 return self.finalize_resolution(None, cfg);
             }
 
-            let KeyCode::Char(ch) = key.code else {
-                return self.finalize_resolution(None, cfg);
-            };
-            if key.mods.contains(KeyModifiers::CTRL) {
+            if !matches!(mode, Mode::Normal) {
                 return self.finalize_resolution(None, cfg);
             }
+            self.resolve_normal(token, pending_command, cfg, timestamp)
+        }
 
-            self.buffer.push(ch);
-
-            if self.ctx.awaiting_register && ch.is_ascii_alphanumeric() {
-                let _ = compose_with_context(
-                    &mut self.ctx,
-                    &core_keymap::MappingOutput::RegisterName(ch),
-                );
-                self.buffer.clear();
-                self.partial_timer.clear();
-                return self.finalize_resolution(None, cfg);
-            }
+        /// Buffer `token` and resolve the Normal (or operator-pending)
+        /// layer over the buffered keys.
+        fn resolve_normal(
+            &mut self,
+            token: KeyToken,
+            pending_command: &str,
+            cfg: &Config,
+            timestamp: Instant,
+        ) -> NgiResolution {
+            self.buffer.push(token);
+            // An operator waiting for its motion reads the operator-pending layer.
+            let layer = MapMode::for_normal(&self.ctx);
+            self.buffered_in = layer;
 
             loop {
-                match self.trie.resolve(&self.buffer) {
-                    core_keymap::Resolution::Matched {
-                        consumed,
-                        output,
+                let scoped = match self.normal_keys {
+                    _ if layer != MapMode::Normal => None,
+                    NormalKeys::Default => None,
+                    NormalKeys::CmdlineWindow => Some(&self.cmdline_window),
+                    NormalKeys::ListWindow => Some(&self.list_window),
+                    NormalKeys::Explorer => Some(&self.explorer),
+                };
+                let trie = if let Some(trie) = scoped {
+                    trie
+                } else if self.noremap {
+                    if layer == MapMode::OperatorPending {
+                        &self.base_pending
+                    } else {
+                        &self.base
+                    }
+                } else {
+                    self.layers.get(layer).unwrap_or(&self.base)
+                };
+                match trie.resolve_in(&self.buffer, &self.ctx) {
+                    core_keymap::Resolution::Matched {
+                        consumed,
+                        output,
                         ambiguous,
                     } => {
                         trace!(
@@ -762,71 +1489,24 @@ pub mod ngi_adapter {
                             ?output,
                             "ngi_resolve_matched"
                         );
-                        let action = match compose_with_context(&mut self.ctx, &output) {
-                            ComposedAction::None => None,
-                            ComposedAction::Motion { motion, count } => {
-                                if let Some(mk) = map_motion(motion) {
-                                    if count == 1 {
-                                        Some(Action::Motion(mk))
-                                    } else {
-                                        Some(Action::MotionWithCount { motion: mk, count })
-                                    }
-                                } else {
-                                    None
-                                }
-                            }
-                            ComposedAction::ApplyOperator {
-                                op,
-                                motion,
-                                count,
-                                register,
-                            } => {
-                                if let (Some(opk), Some(mk)) =
-                                    (map_operator(op), map_motion(motion))
-                                {
-                                    Some(Action::ApplyOperator {
-                                        op: opk,
-                                        motion: mk,
-                                        count,
-                                        register,
-                                    })
-                                } else {
-                                    None
-                                }
-                            }
-                            ComposedAction::LinewiseOperator {
-                                op,
-                                count,
-                                register,
-                            } => map_operator(op).map(|opk| Action::LinewiseOperator {
-                                op: opk,
-                                count,
-                                register,
-                            }),
-                            ComposedAction::PasteAfter { count, register } => {
-                                Some(Action::PasteAfter { count, register })
-                            }
-                            ComposedAction::PasteBefore { count, register } => {
-                                Some(Action::PasteBefore { count, register })
-                            }
-                            ComposedAction::EnterInsert => {
-                                Some(Action::ModeChange(ModeChange::EnterInsert))
-                            }
-                            ComposedAction::Undo { count } => Some(Action::Undo { count }),
-                            ComposedAction::ModeToggleVisualChar => {
-                                Some(Action::ModeChange(ModeChange::EnterVisualChar))
-                            }
-                            ComposedAction::DeleteUnder { count, register } => {
-                                Some(Action::Edit(EditKind::DeleteUnder { count, register }))
-                            }
-                            ComposedAction::DeleteLeft { count, register } => {
-                                Some(Action::Edit(EditKind::DeleteLeft { count, register }))
-                            }
-                            ComposedAction::Literal(c) => Some(Action::CommandChar(c)),
-                        };
+                        if ambiguous && self.awaits_user_mapping(layer) {
+                            self.partial_timer.start(PartialKind::Generic, timestamp);
+                            break;
+                        }
+                        if let Some((rhs, remap)) = mapping_keys(&output) {
+                            self.expand_mapping(consumed, rhs, remap);
+                            return self.finalize_resolution(None, cfg);
+                        }
+                        let action = compose_action(&mut self.ctx, &output);
 
                         self.buffer.drain(0..consumed);
                         if let Some(action) = action {
+                            // Keys typed past a shorter match start over.
+                            if !self.buffer.is_empty() {
+                                let rest = std::mem::take(&mut self.buffer);
+                                let queued = self.mapped_keys(&rest, true);
+                                self.prepend_keys(queued);
+                            }
                             self.partial_timer.clear();
                             return self.finalize_resolution(Some(action), cfg);
                         }
@@ -835,17 +1515,10 @@ pub mod ngi_adapter {
                             break;
                         }
                     }
-                    core_keymap::Resolution::FallbackLiteral(c) => {
+                    core_keymap::Resolution::FallbackLiteral(KeyToken::Char(c))
+                        if !c.is_control() =>
+                    {
                         trace!(target: "input.map", literal = %c, "ngi_resolve_fallback");
-                        if self.ctx.awaiting_register && c.is_ascii_alphanumeric() {
-                            let _ = compose_with_context(
-                                &mut self.ctx,
-                                &core_keymap::MappingOutput::RegisterName(c),
-                            );
-                            self.buffer.clear();
-                            self.partial_timer.clear();
-                            return self.finalize_resolution(None, cfg);
-                        }
                         if pending_command.starts_with(':') {
                             let action = Some(Action::CommandChar(c));
                             self.buffer.clear();
@@ -855,6 +1528,17 @@ pub mod ngi_adapter {
                         self.partial_timer.start(PartialKind::Generic, timestamp);
                         break;
                     }
+                    core_keymap::Resolution::FallbackLiteral(key) => {
+                        // Unmapped chord or named key (`<C-x>`, `<F5>`): dropped.
+                        trace!(target: "input.map", ?key, "ngi_resolve_key_unmapped");
+                        self.buffer.clear();
+                        self.partial_timer.clear();
+                        return self.finalize_resolution(None, cfg);
+                    }
+                    core_keymap::Resolution::InvalidRegister(key) => {
+                        self.reject_register(key);
+                        return self.finalize_resolution(None, cfg);
+                    }
                     core_keymap::Resolution::NeedMore => {
                         self.partial_timer.start(PartialKind::Generic, timestamp);
                         break;
@@ -873,8 +1557,38 @@ pub mod ngi_adapter {
             if self.buffer.is_empty() {
                 return None;
             }
-            let ch = self.buffer.remove(0);
-            trace!(target: "actions.translate", kind = "timeout_flush", ch = %ch);
+            // A complete mapping that waited for a longer one fires; keys
+            // waiting on an Insert / Visual mapping are typed as they are.
+            let trie = self.layers.get(self.buffered_in);
+            if let Some(Resolution::Matched {
+                consumed, output, ..
+            }) = trie.map(|t| t.resolve(&self.buffer))
+            {
+                trace!(target: "actions.translate", kind = "timeout_mapping", consumed);
+                let action = if let Some((rhs, remap)) = mapping_keys(&output) {
+                    self.expand_mapping(consumed, rhs, remap);
+                    None
+                } else {
+                    self.buffer.drain(0..consumed);
+                    let rest = std::mem::take(&mut self.buffer);
+                    let queued = self.mapped_keys(&rest, true);
+                    self.prepend_keys(queued);
+                    self.partial_timer.clear();
+                    compose_action(&mut self.ctx, &output)
+                };
+                return Some(NgiResolution::new(action, PendingState::Idle, None));
+            }
+            if !matches!(self.buffered_in, MapMode::Normal | MapMode::OperatorPending) {
+                self.requeue_buffer();
+                return Some(NgiResolution::new(None, PendingState::Idle, None));
+            }
+            let key = self.buffer.remove(0);
+            trace!(target: "actions.translate", kind = "timeout_flush", ?key);
+            // An incomplete chord prefix (`<C-w>` alone) is not a literal.
+            let action = match key {
+                KeyToken::Char(c) if !c.is_control() => Some(Action::CommandChar(c)),
+                _ => None,
+            };
             let pending_state = if self.buffer.is_empty() {
                 self.partial_timer.clear();
                 PendingState::Idle
@@ -888,11 +1602,7 @@ pub mod ngi_adapter {
                 PendingState::Idle => None,
                 PendingState::AwaitingMore { .. } => self.partial_timer.deadline(cfg),
             };
-            Some(NgiResolution::new(
-                Some(Action::CommandChar(ch)),
-                pending_state,
-                deadline,
-            ))
+            Some(NgiResolution::new(action, pending_state, deadline))
         }
 
         fn finalize_resolution(&mut self, action: Option<Action>, cfg: &Config) -> NgiResolution {
@@ -949,6 +1659,172 @@ pub mod ngi_adapter {
         translator.flush_pending_literal(cfg, now)
     }
 
+    fn mapping_pairs(
+        maps: &BTreeMap<String, MappingValue>,
+    ) -> impl Iterator<Item = (&str, &str, bool)> {
+        maps.iter()
+            .map(|(lhs, rhs)| (lhs.as_str(), rhs.keys(), rhs.remap()))
+    }
+
+    /// Mapping layer for `mode`; an open command line (`:`, `/`, `?`, `=`)
+    /// takes precedence.
+    fn map_mode(mode: Mode, pending_command: &str) -> MapMode {
+        if pending_command.starts_with([':', '=', '/', '?']) {
+            return MapMode::CommandLine;
+        }
+        match mode {
+            Mode::Normal => MapMode::Normal,
+            Mode::Insert => MapMode::Insert,
+            Mode::VisualChar => MapMode::Visual,
+        }
+    }
+
+    /// Right-hand side of a user mapping, and whether it is remapped.
+    fn mapping_keys(output: &MappingOutput) -> Option<(&[KeyToken], bool)> {
+        match output {
+            MappingOutput::Keys(keys) => Some((keys, false)),
+            MappingOutput::RemapKeys(keys) => Some((keys, true)),
+            _ => None,
+        }
+    }
+
+    /// Character Insert-mode `Ctrl-V` inserts for `key`: a Ctrl letter as
+    /// its control code, `<CR>` as `\r` and so on; `None` for keys without
+    /// one (arrows, `<F5>`, Alt chords).
+    fn literal_char(key: &KeyToken) -> Option<char> {
+        match key {
+            KeyToken::Char(c) => Some(*c),
+            KeyToken::Named(NamedKey::Enter) => Some('\r'),
+            KeyToken::Named(NamedKey::Esc) => Some('\x1b'),
+            KeyToken::Named(NamedKey::Backspace) => Some('\x08'),
+            KeyToken::Named(NamedKey::Tab) => Some('\t'),
+            KeyToken::Chord { base, mods } if *mods == ModMask::CTRL => match **base {
+                KeyToken::Char(c) if c.is_ascii_alphabetic() => {
+                    Some(char::from(c.to_ascii_lowercase() as u8 & 0x1f))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Action for a composed Normal mapping, if the runtime has one.
+    fn compose_action(ctx: &mut PendingContext, output: &MappingOutput) -> Option<Action> {
+        match compose_with_context(ctx, output) {
+            ComposedAction::None => None,
+            ComposedAction::Motion { motion, count } => {
+                if let Some(mk) = map_motion(motion) {
+                    if count == 1 {
+                        Some(Action::Motion(mk))
+                    } else {
+                        Some(Action::MotionWithCount { motion: mk, count })
+                    }
+                } else {
+                    None
+                }
+            }
+            ComposedAction::ApplyOperator {
+                op,
+                motion,
+                count,
+                register,
+            } => {
+                if let (Some(opk), Some(mk)) = (map_operator(op), map_motion(motion)) {
+                    Some(Action::ApplyOperator {
+                        op: opk,
+                        motion: mk,
+                        count,
+                        register,
+                    })
+                } else {
+                    None
+                }
+            }
+            ComposedAction::LinewiseOperator {
+                op,
+                count,
+                register,
+            } => map_operator(op).map(|opk| Action::LinewiseOperator {
+                op: opk,
+                count,
+                register,
+            }),
+            ComposedAction::ApplyOperatorTextObject {
+                op,
+                object,
+                around,
+                count,
+                register,
+            } => Some(Action::ApplyOperatorTextObject {
+                op: map_operator(op)?,
+                object: crate::text_object::TextObjectKind::from_key(object)?,
+                around,
+                count,
+                register,
+            }),
+            ComposedAction::PasteAfter { count, register } => {
+                Some(Action::PasteAfter { count, register })
+            }
+            ComposedAction::PasteBefore { count, register } => {
+                Some(Action::PasteBefore { count, register })
+            }
+            ComposedAction::EnterInsert => Some(Action::ModeChange(ModeChange::EnterInsert)),
+            ComposedAction::ResumeInsert => Some(Action::ModeChange(ModeChange::ResumeInsert)),
+            ComposedAction::Undo { count } => Some(Action::Undo { count }),
+            ComposedAction::Redo { count } => Some(Action::Redo { count }),
+            ComposedAction::ModeToggleVisualChar => {
+                Some(Action::ModeChange(ModeChange::EnterVisualChar))
+            }
+            ComposedAction::DeleteUnder { count, register } => {
+                Some(Action::Edit(EditKind::DeleteUnder { count, register }))
+            }
+            ComposedAction::DeleteLeft { count, register } => {
+                Some(Action::Edit(EditKind::DeleteLeft { count, register }))
+            }
+            ComposedAction::CmdlineWindow => Some(Action::CmdlineWindowOpen),
+            ComposedAction::CmdlineWindowExecute => Some(Action::CmdlineWindowExecute),
+            ComposedAction::ListEnter => Some(Action::ListEnter),
+            ComposedAction::UndoChrono { newer, count } => {
+                let steps = i64::from(count);
+                Some(Action::UndoTravel(core_state::UndoTravel::Steps(
+                    if newer { steps } else { -steps },
+                )))
+            }
+            ComposedAction::TabSwitch { backward, count } => {
+                Some(Action::TabSwitch { backward, count })
+            }
+            ComposedAction::SearchNext { reverse, count } => {
+                Some(Action::SearchNext { reverse, count })
+            }
+            ComposedAction::DiagnosticJump { backward, count } => {
+                Some(Action::DiagnosticJump { backward, count })
+            }
+            ComposedAction::Goto { references, split } => Some(Action::Goto { references, split }),
+            ComposedAction::TagPop { count } => Some(Action::TagPop { count }),
+            ComposedAction::Hover => Some(Action::Hover),
+            ComposedAction::WindowCommand { cmd, count } => {
+                map_window_command(cmd).map(|direction| Action::WindowFocus { direction, count })
+            }
+            ComposedAction::Scroll { cmd, count } => {
+                let motion = map_scroll(cmd)?;
+                // A count on a half page becomes the new `'scroll'` amount
+                // in the dispatcher; on a full page it repeats.
+                Some(match count {
+                    Some(count) => Action::MotionWithCount { motion, count },
+                    None => Action::Motion(motion),
+                })
+            }
+            ComposedAction::Fold { cmd } => map_fold_command(cmd).map(Action::Fold),
+            ComposedAction::InspectChar { cmd } => Some(Action::InspectChar { utf8: cmd == '8' }),
+            ComposedAction::DiffHunk { cmd } => Some(Action::DiffHunk { put: cmd == 'p' }),
+            ComposedAction::Explorer { cmd } => map_explorer_command(cmd).map(Action::Explorer),
+            ComposedAction::ScrollCursor { cmd, count } => {
+                map_scroll_cursor(cmd).map(|to| Action::ScrollCursor { to, line: count })
+            }
+            ComposedAction::Literal(c) => Some(Action::CommandChar(c)),
+        }
+    }
+
     fn map_motion(c: char) -> Option<MotionKind> {
         Some(match c {
             'h' => MotionKind::Left,
@@ -963,11 +1839,68 @@ pub mod ngi_adapter {
         })
     }
 
+    fn map_scroll(c: char) -> Option<MotionKind> {
+        Some(match c {
+            'd' => MotionKind::PageHalfDown,
+            'u' => MotionKind::PageHalfUp,
+            'f' => MotionKind::PageDown,
+            'b' => MotionKind::PageUp,
+            _ => return None,
+        })
+    }
+
+    fn map_scroll_cursor(c: char) -> Option<ScrollCursor> {
+        Some(match c {
+            'z' => ScrollCursor::Center,
+            't' => ScrollCursor::Top,
+            'b' => ScrollCursor::Bottom,
+            _ => return None,
+        })
+    }
+
+    fn map_window_command(c: char) -> Option<core_model::FocusDirection> {
+        use core_model::FocusDirection;
+        Some(match c {
+            'h' => FocusDirection::Left,
+            'j' => FocusDirection::Down,
+            'k' => FocusDirection::Up,
+            'l' => FocusDirection::Right,
+            'w' => FocusDirection::Next,
+            _ => return None,
+        })
+    }
+
+    fn map_explorer_command(c: char) -> Option<ExplorerCommand> {
+        Some(match c {
+            '-' => ExplorerCommand::Parent,
+            '%' => ExplorerCommand::NewFile,
+            'd' => ExplorerCommand::Mkdir,
+            'D' => ExplorerCommand::Delete,
+            'R' => ExplorerCommand::Rename,
+            's' => ExplorerCommand::Sort,
+            'r' => ExplorerCommand::Reverse,
+            'h' => ExplorerCommand::ToggleHidden,
+            _ => return None,
+        })
+    }
+
+    fn map_fold_command(c: char) -> Option<FoldCommand> {
+        Some(match c {
+            'o' => FoldCommand::Open,
+            'c' => FoldCommand::Close,
+            'a' => FoldCommand::Toggle,
+            _ => return None,
+        })
+    }
+
     fn map_operator(c: char) -> Option<OperatorKind> {
         Some(match c {
             'd' => OperatorKind::Delete,
             'y' => OperatorKind::Yank,
             'c' => OperatorKind::Change,
+            'z' => OperatorKind::Fold,
+            'l' => OperatorKind::Align { right: false },
+            'L' => OperatorKind::Align { right: true },
             _ => return None,
         })
     }
@@ -1007,8 +1940,8 @@ pub mod ngi_adapter {
 }
 
 pub use ngi_adapter::{
-    NgiResolution, NgiTranslator, PendingState, flush_pending_literal, translate_keypress,
-    translate_ngi,
+    MappedKey, NgiResolution, NgiTranslator, NormalKeys, PendingState, flush_pending_literal,
+    translate_keypress, translate_ngi,
 };
 
 #[cfg(test)]
@@ -1028,6 +1961,503 @@ mod tests {
         }
     }
 
+    fn mapped_translator(mode: &str, lhs: &str, rhs: &str) -> NgiTranslator {
+        let mut keymap = core_config::KeymapConfig::default();
+        let maps = match mode {
+            "normal" => &mut keymap.normal,
+            "command" => &mut keymap.command,
+            "operator" => &mut keymap.operator,
+            "visual" => &mut keymap.visual,
+            _ => &mut keymap.insert,
+        };
+        maps.insert(lhs.into(), rhs.into());
+        NgiTranslator::from_keymap(&keymap).0
+    }
+
+    /// Replay the translator's mapped keys, tracking the command line.
+    fn replay(
+        translator: &mut NgiTranslator,
+        mode: Mode,
+        pending: &mut String,
+        cfg: &Config,
+    ) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while let Some(mapped) = translator.take_mapped_key() {
+            let res = translator.translate_mapped(mode, pending, &mapped, cfg, Instant::now());
+            match &res.action {
+                Some(Action::CommandStart) => pending.push(':'),
+                Some(Action::CommandChar(c)) => pending.push(*c),
+                _ => {}
+            }
+            actions.extend(res.action);
+        }
+        actions
+    }
+
+    #[test]
+    fn register_name_after_quote_skips_mappings_and_rejects_invalid_names() {
+        let cfg = Config::default();
+        let now = Instant::now();
+        let mut translator = mapped_translator("normal", "a", "x");
+        let mut feed = |keys: &str, mode: Mode| -> Vec<Action> {
+            keys.chars()
+                .filter_map(|c| translator.translate(mode, "", &kc(c), &cfg, now).action)
+                .collect()
+        };
+        // `a` is mapped and `y` starts an operator, yet both are register names here.
+        assert!(matches!(
+            feed("\"ayy", Mode::Normal)[..],
+            [Action::LinewiseOperator {
+                register: Some('a'),
+                ..
+            }]
+        ));
+        assert!(matches!(
+            feed("\"Ydw", Mode::Normal)[..],
+            [Action::ApplyOperator {
+                register: Some('Y'),
+                ..
+            }]
+        ));
+        // An invalid name drops the pending count and operator.
+        assert!(feed("2d\"!", Mode::Normal).is_empty());
+        assert!(matches!(
+            feed("dw", Mode::Normal)[..],
+            [Action::ApplyOperator {
+                count: 1,
+                register: None,
+                ..
+            }]
+        ));
+        assert!(feed("\"?", Mode::VisualChar).is_empty());
+        assert_eq!(translator.take_invalid_register().as_deref(), Some("?"));
+        assert_eq!(translator.take_invalid_register(), None);
+    }
+
+    #[test]
+    fn ctrl_v_inserts_the_next_key_or_a_hex_codepoint() {
+        let cfg = Config::default();
+        let now = Instant::now();
+        let ctrl = |c| KeyEvent {
+            code: KeyCode::Char(c),
+            mods: KeyModifiers::CTRL,
+        };
+        let esc = KeyEvent {
+            code: KeyCode::Esc,
+            mods: KeyModifiers::empty(),
+        };
+        let inserted = |res: NgiResolution| match res.action {
+            Some(Action::Edit(EditKind::InsertGrapheme(g))) => Some(g),
+            _ => None,
+        };
+        // The literal key skips Insert mappings.
+        let mut translator = mapped_translator("insert", "<C-a>", "x");
+        let mut type_key = |key: &KeyEvent| translator.translate(Mode::Insert, "", key, &cfg, now);
+        assert_eq!(inserted(type_key(&ctrl('v'))), None);
+        assert_eq!(inserted(type_key(&ctrl('a'))).as_deref(), Some("\x01"));
+        type_key(&ctrl('v'));
+        assert_eq!(inserted(type_key(&esc)).as_deref(), Some("\x1b"));
+        type_key(&ctrl('v'));
+        for c in "u00e".chars() {
+            assert_eq!(inserted(type_key(&kc(c))), None);
+        }
+        assert_eq!(inserted(type_key(&kc('9'))).as_deref(), Some("\u{e9}"));
+        // `U` reads up to 8 digits; a non-digit ends the code early and
+        // is typed after it.
+        type_key(&ctrl('v'));
+        for c in "U1f600".chars() {
+            type_key(&kc(c));
+        }
+        assert_eq!(inserted(type_key(&esc)).as_deref(), Some("\u{1f600}"));
+        // A surrogate is not a character: nothing is inserted.
+        type_key(&ctrl('v'));
+        for c in "ud800".chars() {
+            assert_eq!(inserted(type_key(&kc(c))), None);
+        }
+        assert!(matches!(
+            translator.take_mapped_key(),
+            Some(MappedKey {
+                key: KeyToken::Named(NamedKey::Esc),
+                ..
+            })
+        ));
+        assert_eq!(translator.take_mapped_key(), None);
+    }
+
+    #[test]
+    fn page_scroll_chords_take_a_count_and_drop_an_operator() {
+        let cfg = Config::default();
+        let now = Instant::now();
+        let ctrl = |c| KeyEvent {
+            code: KeyCode::Char(c),
+            mods: KeyModifiers::CTRL,
+        };
+        let mut translator = NgiTranslator::new();
+        translator.translate(Mode::Normal, "", &kc('4'), &cfg, now);
+        let res = translator.translate(Mode::Normal, "", &ctrl('d'), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::MotionWithCount {
+                motion: MotionKind::PageHalfDown,
+                count: 4
+            })
+        ));
+        translator.translate(Mode::Normal, "", &kc('d'), &cfg, now);
+        let res = translator.translate(Mode::Normal, "", &ctrl('f'), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::Motion(MotionKind::PageDown))
+        ));
+        // The operator is gone: `x` deletes one character, not a range.
+        let res = translator.translate(Mode::Normal, "", &kc('x'), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::Edit(EditKind::DeleteUnder { count: 1, .. }))
+        ));
+        let res = translator.translate(Mode::VisualChar, "", &ctrl('b'), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::Motion(MotionKind::PageUp))
+        ));
+    }
+
+    #[test]
+    fn arrow_keys_and_ctrl_r_resolve_through_the_trie() {
+        let cfg = Config::default();
+        let now = Instant::now();
+        let key = |code| KeyEvent {
+            code,
+            mods: KeyModifiers::empty(),
+        };
+        let mut translator = NgiTranslator::new();
+        translator.translate(Mode::Normal, "", &kc('3'), &cfg, now);
+        let res = translator.translate(Mode::Normal, "", &key(KeyCode::Right), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::MotionWithCount {
+                motion: MotionKind::Right,
+                count: 3
+            })
+        ));
+        translator.translate(Mode::Normal, "", &kc('d'), &cfg, now);
+        let res = translator.translate(Mode::Normal, "", &key(KeyCode::Left), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::ApplyOperator {
+                op: OperatorKind::Delete,
+                motion: MotionKind::Left,
+                ..
+            })
+        ));
+        translator.translate(Mode::Normal, "", &kc('2'), &cfg, now);
+        let ctrl_r = KeyEvent {
+            code: KeyCode::Char('r'),
+            mods: KeyModifiers::CTRL,
+        };
+        let res = translator.translate(Mode::Normal, "", &ctrl_r, &cfg, now);
+        assert!(matches!(res.action, Some(Action::Redo { count: 2 })));
+
+        // Arrows are mappable like any other key.
+        let mut translator = mapped_translator("normal", "<Down>", "x");
+        let res = translator.translate(Mode::Normal, "", &key(KeyCode::Down), &cfg, now);
+        assert!(res.action.is_none());
+        let mut pending = String::new();
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::Edit(EditKind::DeleteUnder { count: 1, .. })]
+        ));
+    }
+
+    #[test]
+    fn function_keys_and_alt_chords_resolve_as_tokens() {
+        let cfg = Config::default();
+        let f5 = KeyEventExt::new(KeyToken::Named(NamedKey::F(5)));
+        let alt_j = KeyEventExt::new(KeyToken::Chord {
+            base: Box::new(KeyToken::Char('j')),
+            mods: core_events::ModMask::ALT,
+        });
+        // Without a mapping both are dropped without pending input.
+        let mut translator = NgiTranslator::new();
+        for key in [&f5, &alt_j] {
+            let res = translator.ingest_keypress(Mode::Normal, "", key, &cfg);
+            assert!(res.action.is_none());
+            assert!(matches!(res.pending_state, PendingState::Idle));
+        }
+
+        let mut translator = mapped_translator("normal", "<F5>", "x");
+        let res = translator.ingest_keypress(Mode::Normal, "", &f5, &cfg);
+        assert!(res.action.is_none());
+        let mut pending = String::new();
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::Edit(EditKind::DeleteUnder { count: 1, .. })]
+        ));
+        // Alt chords are mapping keys too.
+        let mut translator = mapped_translator("insert", "<M-j>", "<Esc>j");
+        translator.ingest_keypress(Mode::Insert, "", &alt_j, &cfg);
+        let actions = replay(&mut translator, Mode::Insert, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::ModeChange(ModeChange::LeaveInsert), ..]
+        ));
+    }
+
+    #[test]
+    fn leader_mapping_expands_to_command_line() {
+        let mut translator = mapped_translator("normal", "<leader>w", ":w<CR>");
+        let cfg = Config::default();
+        let now = Instant::now();
+        let res = translator.translate(Mode::Normal, "", &kc('\\'), &cfg, now);
+        assert!(res.action.is_none());
+        assert!(matches!(
+            res.pending_state,
+            ngi_adapter::PendingState::AwaitingMore { buffered_len: 1 }
+        ));
+        let res = translator.translate(Mode::Normal, "", &kc('w'), &cfg, now);
+        assert!(res.action.is_none());
+        let mut pending = String::new();
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [
+                Action::CommandStart,
+                Action::CommandChar('w'),
+                Action::CommandExecute(cmd)
+            ] if cmd == ":w"
+        ));
+    }
+
+    #[test]
+    fn remap_expands_through_user_mappings_and_noremap_does_not() {
+        let mut keymap = core_config::KeymapConfig::default();
+        let remap = |keys: &str| core_config::MappingValue::Table {
+            keys: keys.into(),
+            remap: true,
+        };
+        keymap.normal.insert("<leader>a".into(), "x".into());
+        keymap.normal.insert("Q".into(), remap("<leader>a"));
+        keymap.normal.insert("R".into(), "<leader>a".into());
+        // Leading own left-hand side is not remapped: `x` deletes, `l` moves.
+        keymap.normal.insert("x".into(), remap("xl"));
+        let (mut translator, _) = NgiTranslator::from_keymap(&keymap);
+        let cfg = Config::default();
+        let now = Instant::now();
+        let mut pending = String::new();
+        let mut run = |translator: &mut NgiTranslator, c: char| {
+            translator.translate(Mode::Normal, "", &kc(c), &cfg, now);
+            replay(translator, Mode::Normal, &mut pending, &cfg)
+        };
+        // `Q` reaches `<leader>a`, whose `noremap` `x` skips the `x` mapping.
+        assert!(matches!(
+            run(&mut translator, 'Q').as_slice(),
+            [Action::Edit(EditKind::DeleteUnder { count: 1, .. })]
+        ));
+        // `R` reaches only built-in keys, where `\` starts nothing.
+        assert!(run(&mut translator, 'R').is_empty());
+        translator.cancel_pending();
+        assert!(matches!(
+            run(&mut translator, 'x').as_slice(),
+            [
+                Action::Edit(EditKind::DeleteUnder { count: 1, .. }),
+                Action::Motion(MotionKind::Right)
+            ]
+        ));
+        assert!(!translator.take_expansion_aborted());
+    }
+
+    #[test]
+    fn insert_mapping_waits_then_expands_or_types() {
+        let mut translator = mapped_translator("insert", "jk", "<Esc>");
+        let cfg = Config::default();
+        let now = Instant::now();
+        let mut pending = String::new();
+        // `j` waits; `k` completes the mapping, which leaves Insert.
+        assert!(
+            translator
+                .translate(Mode::Insert, "", &kc('j'), &cfg, now)
+                .action
+                .is_none()
+        );
+        assert!(
+            translator
+                .translate(Mode::Insert, "", &kc('k'), &cfg, now)
+                .action
+                .is_none()
+        );
+        let actions = replay(&mut translator, Mode::Insert, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::ModeChange(ModeChange::LeaveInsert)]
+        ));
+        // `j` then `x` types both.
+        translator.translate(Mode::Insert, "", &kc('j'), &cfg, now);
+        translator.translate(Mode::Insert, "", &kc('x'), &cfg, now);
+        let actions = replay(&mut translator, Mode::Insert, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [
+                Action::Edit(EditKind::InsertGrapheme(a)),
+                Action::Edit(EditKind::InsertGrapheme(b))
+            ] if a == "j" && b == "x"
+        ));
+        // A lone `j` is typed when the timeout expires.
+        translator.translate(Mode::Insert, "", &kc('j'), &cfg, now);
+        assert!(translator.flush_pending_literal(&cfg, now).is_some());
+        let actions = replay(&mut translator, Mode::Insert, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::Edit(EditKind::InsertGrapheme(a))] if a == "j"
+        ));
+    }
+
+    #[test]
+    fn command_line_layer_maps_only_on_the_command_line() {
+        let mut translator = mapped_translator("command", "ww", "w!");
+        let cfg = Config::default();
+        let now = Instant::now();
+        let mut pending = String::from(":");
+        translator.translate(Mode::Normal, &pending, &kc('w'), &cfg, now);
+        translator.translate(Mode::Normal, &pending, &kc('w'), &cfg, now);
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::CommandChar('w'), Action::CommandChar('!')]
+        ));
+        // A lone `w` is typed into the command line on timeout.
+        let mut pending = String::from(":");
+        translator.translate(Mode::Normal, &pending, &kc('w'), &cfg, now);
+        translator.flush_pending_literal(&cfg, now);
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(actions.as_slice(), [Action::CommandChar('w')]));
+        // The Normal layer is untouched: `w` is a motion there.
+        let res = translator.translate(Mode::Normal, "", &kc('w'), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::Motion(MotionKind::WordForward))
+        ));
+    }
+
+    #[test]
+    fn text_objects_follow_an_operator_and_i_still_inserts() {
+        use crate::text_object::TextObjectKind;
+        let cfg = Config::default();
+        let now = Instant::now();
+        // A Normal mapping still applies while an operator waits.
+        let mut translator = mapped_translator("normal", "Q", "x");
+        let mut feed = |keys: &str| -> Vec<Action> {
+            keys.chars()
+                .filter_map(|c| {
+                    translator
+                        .translate(Mode::Normal, "", &kc(c), &cfg, now)
+                        .action
+                })
+                .collect()
+        };
+        assert!(matches!(
+            feed("\"b2da(")[..],
+            [Action::ApplyOperatorTextObject {
+                op: OperatorKind::Delete,
+                object: TextObjectKind::Parens,
+                around: true,
+                count: 2,
+                register: Some('b'),
+            }]
+        ));
+        assert!(matches!(
+            feed("yiW")[..],
+            [Action::ApplyOperatorTextObject {
+                op: OperatorKind::Yank,
+                object: TextObjectKind::BigWord,
+                around: false,
+                count: 1,
+                register: None,
+            }]
+        ));
+        assert!(matches!(
+            feed("i")[..],
+            [Action::ModeChange(ModeChange::EnterInsert)]
+        ));
+        assert!(feed("dQ").is_empty());
+        assert!(translator.take_mapped_key().is_some());
+    }
+
+    #[test]
+    fn operator_pending_and_visual_layers_differ_from_normal() {
+        let cfg = Config::default();
+        let now = Instant::now();
+        let mut pending = String::new();
+        let mut translator = mapped_translator("operator", "L", "$");
+        translator.translate(Mode::Normal, "", &kc('d'), &cfg, now);
+        translator.translate(Mode::Normal, "", &kc('L'), &cfg, now);
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::ApplyOperator {
+                op: OperatorKind::Delete,
+                motion: MotionKind::LineEnd,
+                count: 1,
+                ..
+            }]
+        ));
+        // Without an operator `L` is not mapped.
+        let res = translator.translate(Mode::Normal, "", &kc('L'), &cfg, now);
+        assert!(res.action.is_none());
+        assert!(translator.take_mapped_key().is_none());
+
+        let mut translator = mapped_translator("visual", "x", "y");
+        translator.translate(Mode::VisualChar, "", &kc('x'), &cfg, now);
+        let actions = replay(&mut translator, Mode::VisualChar, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::VisualOperator {
+                op: OperatorKind::Yank,
+                ..
+            }]
+        ));
+        let res = translator.translate(Mode::Normal, "", &kc('x'), &cfg, now);
+        assert!(matches!(
+            res.action,
+            Some(Action::Edit(EditKind::DeleteUnder { .. }))
+        ));
+    }
+
+    #[test]
+    fn normal_prefix_of_user_mapping_waits_for_timeout() {
+        let mut translator = mapped_translator("normal", "jk", "x");
+        let cfg = Config::default();
+        let now = Instant::now();
+        assert!(
+            translator
+                .translate(Mode::Normal, "", &kc('j'), &cfg, now)
+                .action
+                .is_none()
+        );
+        let flushed = translator.flush_pending_literal(&cfg, now).unwrap();
+        assert!(matches!(
+            flushed.action,
+            Some(Action::Motion(MotionKind::Down))
+        ));
+        // `jj` moves once and waits on the second `j`.
+        translator.translate(Mode::Normal, "", &kc('j'), &cfg, now);
+        let res = translator.translate(Mode::Normal, "", &kc('j'), &cfg, now);
+        assert!(matches!(res.action, Some(Action::Motion(MotionKind::Down))));
+        let mut pending = String::new();
+        assert!(replay(&mut translator, Mode::Normal, &mut pending, &cfg).is_empty());
+        assert!(
+            translator
+                .translate(Mode::Normal, "", &kc('k'), &cfg, now)
+                .action
+                .is_none()
+        );
+        let actions = replay(&mut translator, Mode::Normal, &mut pending, &cfg);
+        assert!(matches!(
+            actions.as_slice(),
+            [Action::Edit(EditKind::DeleteUnder { count: 1, .. })]
+        ));
+    }
+
     #[test]
     fn normal_mode_motion() {
         let mut translator = new_translator();
@@ -1047,6 +2477,35 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn enter_is_bound_per_window() {
+        let mut translator = new_translator();
+        let enter = KeyEvent {
+            code: KeyCode::Enter,
+            mods: KeyModifiers::empty(),
+        };
+        let mut press = |keys| {
+            translator.set_normal_keys(keys);
+            translate_key(&mut translator, Mode::Normal, "", &enter)
+        };
+        assert!(matches!(
+            press(NormalKeys::Default),
+            Some(Action::Motion(MotionKind::Down))
+        ));
+        assert!(matches!(
+            press(NormalKeys::CmdlineWindow),
+            Some(Action::CmdlineWindowExecute)
+        ));
+        assert!(matches!(
+            press(NormalKeys::ListWindow),
+            Some(Action::ListEnter)
+        ));
+        assert!(matches!(
+            press(NormalKeys::Explorer),
+            Some(Action::ListEnter)
+        ));
+    }
+
     #[test]
     fn command_sequence_translation() {
         let mut translator = new_translator();
@@ -1089,6 +2548,19 @@ mod tests {
             },
         );
         assert!(matches!(bs, Some(Action::CommandBackspace)));
+        // Tab completes, Shift-Tab backwards
+        for (mods, backward) in [(KeyModifiers::empty(), false), (KeyModifiers::SHIFT, true)] {
+            let tab = translate_key(
+                &mut translator,
+                Mode::Normal,
+                ":e ",
+                &KeyEvent {
+                    code: KeyCode::Tab,
+                    mods,
+                },
+            );
+            assert!(matches!(tab, Some(Action::CommandComplete { backward: b }) if b == backward));
+        }
     }
 
     #[test]
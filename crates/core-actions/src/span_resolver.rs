@@ -124,10 +124,10 @@ fn apply_motion_once(buffer: &Buffer, pos: &mut Position, kind: MotionKind) {
         MotionKind::Down => {
             let _ = motion::down(buffer, pos, None);
         }
-        MotionKind::PageHalfUp => {
+        MotionKind::PageHalfUp | MotionKind::PageUp => {
             let _ = motion::up(buffer, pos, None);
         } // simplified
-        MotionKind::PageHalfDown => {
+        MotionKind::PageHalfDown | MotionKind::PageDown => {
             let _ = motion::down(buffer, pos, None);
         }
     }
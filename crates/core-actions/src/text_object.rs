@@ -1,10 +1,11 @@
 //! Text object scaffold (Refactor R4 Step 15).
 //!
-//! Breadth-first placeholder introducing the `TextObject` trait and a small
-//! enumerated set of future Vim/Neovim-style text object kinds. No behavior is
-//! integrated yet; operators and key translation do not reference these. This
-//! establishes a stable trait surface so subsequent steps (paste / visual mode
-//! groundwork, object-aware motions) can evolve without a disruptive refactor.
+//! Breadth-first placeholder introducing the `TextObject` trait and the
+//! Vim/Neovim-style text object kinds. Key translation parses `i{object}` /
+//! `a{object}` after an operator into `Action::ApplyOperatorTextObject`; the
+//! dispatcher resolves it here, and since resolution is still a stub the
+//! operator finds nothing to act on. This establishes a stable trait surface
+//! so concrete objects can land without a disruptive refactor.
 //!
 //! Design notes:
 //! * The trait is intentionally minimal: `name()` for diagnostics / logging and
@@ -16,32 +17,58 @@
 //!   exclusive semantics flags.
 //!
 //! Out of scope for this scaffold:
-//! * Parsing of text object commands (e.g. `diw`, `ci"`).
 //! * Inner vs. around delimiter resolution rules.
 //! * Paragraph / sentence boundary detection heuristics.
-//! * Integration with operator dispatcher.
+//! * Applying operators to a resolved object span.
 //!
-//! Subsequent work will introduce concrete implementors and translation paths.
-//! The dispatcher and key translator intentionally omit references until the
-//! initial operator + motion parity is fully preserved under selection model
-//! integration (visual mode enablement).
+//! Subsequent work will introduce concrete implementors.
 
 use core_state::{EditorState, SelectionKind, SelectionSpan};
 use core_text::Position;
 
-/// Future text object kinds (placeholder). Variants are *not* constructed by
-/// any public API yet. They indicate intended coverage and may be reordered or
-/// renamed prior to first semantic integration without breaking changes.
+/// Text object kinds, named by the key after `i` / `a`. Inner vs. around is
+/// carried separately (`around` on `Action::ApplyOperatorTextObject`).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextObjectKind {
-    /// Inner word (excluding surrounding whitespace) – planned `iw`.
-    WordInner,
-    /// A word including contiguous surrounding whitespace – planned `aw`.
-    WordA,
-    /// Paragraph – planned `ip` / `ap` (blank-line delimited).
-    Paragraph,
-    /// Sentence – planned `is` / `as` (simple punctuation heuristic first pass).
+    /// `w`: word; `aw` adds the surrounding whitespace.
+    Word,
+    /// `W`: WORD (blank-delimited).
+    BigWord,
+    /// `s`: sentence (simple punctuation heuristic first pass).
     Sentence,
+    /// `p`: paragraph (blank-line delimited).
+    Paragraph,
+    /// `(`, `)`, `b`.
+    Parens,
+    /// `{`, `}`, `B`.
+    Braces,
+    /// `[`, `]`.
+    Brackets,
+    /// `<`, `>`.
+    AngleBrackets,
+    /// `t`: XML/HTML tag block.
+    Tag,
+    /// `"`, `'`, `` ` ``: quoted string within the line.
+    Quote(char),
+}
+
+impl TextObjectKind {
+    /// Kind named by `key` (one of `core_keymap::TEXT_OBJECT_KEYS`).
+    pub fn from_key(key: char) -> Option<Self> {
+        Some(match key {
+            'w' => TextObjectKind::Word,
+            'W' => TextObjectKind::BigWord,
+            's' => TextObjectKind::Sentence,
+            'p' => TextObjectKind::Paragraph,
+            '(' | ')' | 'b' => TextObjectKind::Parens,
+            '{' | '}' | 'B' => TextObjectKind::Braces,
+            '[' | ']' => TextObjectKind::Brackets,
+            '<' | '>' => TextObjectKind::AngleBrackets,
+            't' => TextObjectKind::Tag,
+            '"' | '\'' | '`' => TextObjectKind::Quote(key),
+            _ => return None,
+        })
+    }
 }
 
 /// Core trait every text object implementation will satisfy. Implementations
@@ -64,13 +91,14 @@ impl<T: TextObject + ?Sized> TextObject for &T {
     }
 }
 
-/// Scaffold helper resolving a requested `TextObjectKind` using a trivial
-/// placeholder implementation so call sites can be introduced ahead of real
-/// semantics. Returns an empty characterwise span at the cursor.
+/// Scaffold helper resolving a requested `TextObjectKind` (inner, or `around`)
+/// using a trivial placeholder implementation so call sites can be introduced
+/// ahead of real semantics. Returns an empty characterwise span at the cursor.
 pub fn resolve_text_object(
     _state: &EditorState,
     cursor: Position,
     _kind: TextObjectKind,
+    _around: bool,
 ) -> SelectionSpan {
     SelectionSpan::new(cursor, cursor, SelectionKind::Characterwise)
 }
@@ -109,7 +137,16 @@ mod tests {
         let buf = Buffer::from_str("dummy", "alpha\n").unwrap();
         let state = EditorState::new(buf);
         let cursor = Position::origin();
-        let sel = resolve_text_object(&state, cursor, TextObjectKind::WordInner);
+        let sel = resolve_text_object(&state, cursor, TextObjectKind::Word, false);
         assert!(sel.is_empty());
     }
+
+    #[test]
+    fn every_object_key_names_a_kind() {
+        for key in core_keymap::TEXT_OBJECT_KEYS {
+            assert!(TextObjectKind::from_key(key).is_some(), "{key}");
+        }
+        assert_eq!(TextObjectKind::from_key('b'), Some(TextObjectKind::Parens));
+        assert_eq!(TextObjectKind::from_key('x'), None);
+    }
 }
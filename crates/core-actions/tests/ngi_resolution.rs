@@ -62,7 +62,7 @@ fn awaiting_more_deadline_uses_keypress_timestamp() {
     cfg.file.input.timeoutlen = 1500;
 
     let start = Instant::now();
-    let resolution = translate_ngi_at(Mode::Normal, "", &kc('z'), &cfg, start);
+    let resolution = translate_ngi_at(Mode::Normal, "", &kc('Q'), &cfg, start);
     match resolution.pending_state {
         PendingState::AwaitingMore { buffered_len } => assert_eq!(buffered_len, 1),
         other => panic!("expected AwaitingMore state, got {:?}", other),
@@ -26,27 +26,22 @@ fn feed(model: &mut EditorModel, seq: &str) {
 }
 
 #[test]
-fn explicit_numbered_register_paste_1p() {
+fn numbered_registers_split_yanks_from_line_deletes() {
     reset_translator();
-    // Build a buffer with three words to exercise deletes.
-    let buf = Buffer::from_str("t", "alpha beta gamma delta\n").unwrap();
+    let buf = Buffer::from_str("t", "alpha\nbeta\ngamma delta end\n").unwrap();
     let state = core_state::EditorState::new(buf);
     let mut model = EditorModel::new(state);
 
-    // Sequence: delete first word (dw), then delete next (dw), then yank next (yw)
-    // This yields numbered ring (newest first):
-    // 0: yank payload, 1: second delete payload, 2: first delete payload.
-    feed(&mut model, "dw"); // delete 'alpha '
-    feed(&mut model, "dw"); // delete 'beta '
-    feed(&mut model, "yw"); // yank 'gamma'
-    // Explicit paste from ring[1] should yield second delete payload ('beta ')
-    feed(&mut model, "0\"1p");
-    let line0 = model.state().active_buffer().line(0).unwrap();
-    assert!(
-        line0.contains("beta"),
-        "expected ring[1] payload 'beta ' inserted, got {}",
-        line0
-    );
+    // Line deletes shift through "1-"9; the small delete and the yank do not.
+    feed(&mut model, "dd"); // "1 = 'alpha'
+    feed(&mut model, "dd"); // "1 = 'beta', "2 = 'alpha'
+    feed(&mut model, "dw"); // "- = 'gamma '
+    feed(&mut model, "yw"); // "0 = 'delta '
+    let regs = &model.state().registers;
+    assert_eq!(regs.numbered()[..3], ["delta ", "beta\n", "alpha\n"]);
+    assert_eq!(regs.small_delete(), "gamma ");
+    feed(&mut model, "\"2p");
+    assert_eq!(model.state().active_buffer().line(1).unwrap(), "alpha\n");
 }
 
 #[test]
@@ -56,12 +51,13 @@ fn explicit_numbered_register_zero_paste_latest() {
     let state = core_state::EditorState::new(buf);
     let mut model = EditorModel::new(state);
 
-    // Populate ring: delete first two words then yank third.
+    // Delete the first two words then yank the third.
     feed(&mut model, "dw"); // 'one '
     feed(&mut model, "dw"); // 'two '
     feed(&mut model, "yw"); // yank 'three'
-    // ring[0] = "three" (yank), ring[1] = "two ", ring[2] = "one "
-    feed(&mut model, "0\"0p"); // paste latest (yank payload)
+    // "0 keeps the yank while the unnamed register follows later deletes.
+    feed(&mut model, "dw");
+    feed(&mut model, "0\"0p");
     let line0 = model.state().active_buffer().line(0).unwrap();
     assert!(
         line0.contains("three"),
@@ -69,3 +65,53 @@ fn explicit_numbered_register_zero_paste_latest() {
         line0
     );
 }
+
+fn feed_key(model: &mut EditorModel, code: KeyCode) {
+    let ev = KeyEvent {
+        code,
+        mods: KeyModifiers::empty(),
+    };
+    if let Some(act) = translate_key(model.state().mode, model.state().command_line.buffer(), &ev) {
+        core_actions::dispatcher::dispatch(act, model, &mut None, &[]);
+    }
+}
+
+#[test]
+fn small_delete_register_holds_sub_line_delete() {
+    reset_translator();
+    let buf = Buffer::from_str("t", "one two\nthree\n").unwrap();
+    let mut model = EditorModel::new(core_state::EditorState::new(buf));
+    feed(&mut model, "dw"); // 'one ' (within the line) -> "-
+    feed(&mut model, "dd"); // linewise: "- keeps 'one '
+    assert_eq!(model.state().registers.small_delete(), "one ");
+    feed(&mut model, "\"-P");
+    assert_eq!(
+        model.state().active_buffer().line(0).unwrap(),
+        "one three\n"
+    );
+}
+
+#[test]
+fn expression_register_prompts_then_pastes() {
+    reset_translator();
+    let buf = Buffer::from_str("t", "x\n").unwrap();
+    let mut model = EditorModel::new(core_state::EditorState::new(buf));
+    feed(&mut model, "\"=p");
+    assert_eq!(model.state().command_line.buffer(), "=");
+    feed(&mut model, "6*7");
+    feed_key(&mut model, KeyCode::Enter);
+    assert!(!model.state().command_line.is_active());
+    assert_eq!(model.state().active_buffer().line(0).unwrap(), "x42\n");
+    assert_eq!(model.state().registers.expression(), "42");
+
+    feed(&mut model, "\"=P");
+    feed(&mut model, "1 +");
+    feed_key(&mut model, KeyCode::Enter);
+    let eph = model
+        .state()
+        .ephemeral_status
+        .as_ref()
+        .expect("error shown");
+    assert!(eph.text.starts_with("E15"), "{}", eph.text);
+    assert_eq!(model.state().active_buffer().line(0).unwrap(), "x42\n");
+}
@@ -107,7 +107,7 @@ fn unicode_linewise_change_and_paste_regression() {
         Some("χαρά μέρα\n")
     );
     assert!(state.registers.get_named('a').unwrap_or("").is_empty());
-    assert!(state.dirty);
+    assert!(state.dirty());
     assert!(state.undo_depth() >= 2);
     assert_eq!(state.redo_depth(), 0);
 }
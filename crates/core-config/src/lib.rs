@@ -10,11 +10,29 @@
 //! Breadth-first: only vertical margin implemented; horizontal and other
 //! scroll behaviors deferred. Unknown fields are ignored (TOML deserialization
 //! tolerance) to allow forward evolution without immediate warnings.
+//!
+//! Options Step 1: an `[options]` table seeds the runtime option table
+//! (`options::OptionTable`) consulted by `:set`. Legacy tables (`[input]`,
+//! `[scroll.margin]`) seed their option counterparts first so existing
+//! configuration keeps working; `[options]` entries win when both are set.
+//!
+//! Themes: a top-level `colorscheme = "name"` selects the scheme loaded at
+//! startup (`theme::Theme::load`). A top-level `colors = "truecolor" | "256"
+//! | "16"` overrides the terminal's detected color depth.
+
+pub mod listchars;
+pub mod options;
+pub mod theme;
 
 use anyhow::Result;
+use options::{OptionTable, OptionValue};
 use serde::Deserialize;
-use std::{fs, path::PathBuf};
-use tracing::info;
+use std::collections::BTreeMap;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct ConfigPlatformTraits {
@@ -85,12 +103,332 @@ pub struct ScrollConfig {
     pub margin: MarginConfig,
 }
 
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct FilesConfig {
+    /// Idle milliseconds before dirty buffers are autosaved (0 disables).
+    #[serde(default)]
+    pub autosave_ms: u64,
+    /// Copy the existing file aside before `:w` overwrites it.
+    #[serde(default)]
+    pub backup: bool,
+    /// Directory for backups (default: next to the file).
+    #[serde(default)]
+    pub backupdir: Option<String>,
+    /// Suffix appended to the backup file name (default `~`).
+    #[serde(default)]
+    pub backupext: Option<String>,
+}
+
+/// `[shada]`: registers, command history and file positions kept across
+/// sessions.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShadaConfig {
+    #[serde(default = "ShadaConfig::default_enabled")]
+    pub enabled: bool,
+    /// State file location (default: platform data dir `oxidized/shada`).
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Registers larger than this many KiB are not saved.
+    #[serde(default = "ShadaConfig::default_max_item_kb")]
+    pub max_item_kb: usize,
+    /// Registers with more lines than this are not saved.
+    #[serde(default = "ShadaConfig::default_max_lines")]
+    pub max_lines: usize,
+    /// Newest command history entries saved.
+    #[serde(default = "ShadaConfig::default_history")]
+    pub history: usize,
+    /// Most recently left files whose last cursor position is saved.
+    #[serde(default = "ShadaConfig::default_files")]
+    pub files: usize,
+    /// Put the cursor back at its last position when a file is reopened.
+    #[serde(default = "ShadaConfig::default_enabled")]
+    pub restore_cursor: bool,
+}
+
+impl Default for ShadaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            path: None,
+            max_item_kb: Self::default_max_item_kb(),
+            max_lines: Self::default_max_lines(),
+            history: Self::default_history(),
+            files: Self::default_files(),
+            restore_cursor: Self::default_enabled(),
+        }
+    }
+}
+
+impl ShadaConfig {
+    const fn default_enabled() -> bool {
+        true
+    }
+    const fn default_max_item_kb() -> usize {
+        10
+    }
+    const fn default_max_lines() -> usize {
+        50
+    }
+    const fn default_history() -> usize {
+        50
+    }
+    const fn default_files() -> usize {
+        100
+    }
+
+    /// State file path when enabled.
+    pub fn resolved_path(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        match &self.path {
+            Some(p) => Some(PathBuf::from(p)),
+            None => dirs::data_local_dir().map(|d| d.join("oxidized").join("shada")),
+        }
+    }
+}
+
+/// `[plugins]`: WebAssembly plugins loaded at startup.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PluginsConfig {
+    #[serde(default = "PluginsConfig::default_enabled")]
+    pub enabled: bool,
+    /// Directory scanned for `*.wasm` (default: platform config dir
+    /// `oxidized/plugins`).
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            dir: None,
+        }
+    }
+}
+
+impl PluginsConfig {
+    const fn default_enabled() -> bool {
+        true
+    }
+
+    /// Plugin directory when enabled.
+    pub fn resolved_dir(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        match &self.dir {
+            Some(d) => Some(PathBuf::from(d)),
+            None => dirs::config_dir().map(|d| d.join("oxidized").join("plugins")),
+        }
+    }
+}
+
+/// `[statusline]`: provider-contributed segments.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StatusLineConfig {
+    /// Segment names to show, in order; unset shows every segment.
+    #[serde(default)]
+    pub segments: Option<Vec<String>>,
+    /// Milliseconds a provider may take before its answer is dropped.
+    #[serde(default = "StatusLineConfig::default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for StatusLineConfig {
+    fn default() -> Self {
+        Self {
+            segments: None,
+            timeout_ms: Self::default_timeout_ms(),
+        }
+    }
+}
+
+impl StatusLineConfig {
+    const fn default_timeout_ms() -> u64 {
+        100
+    }
+}
+
+/// `[metrics]`: periodic JSON snapshots of the metrics (`:metrics dump`
+/// format), to analyse long sessions or headless runs afterwards.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetricsConfig {
+    /// Sink for one JSON line per interval: `stderr` or a file path
+    /// (appended). Unset disables the sink.
+    #[serde(default)]
+    pub export: Option<String>,
+    /// Milliseconds between snapshots.
+    #[serde(default = "MetricsConfig::default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            export: None,
+            interval_ms: Self::default_interval_ms(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    const fn default_interval_ms() -> u64 {
+        1000
+    }
+}
+
+/// `[lsp]`: language servers, started on demand for files they handle.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LspConfig {
+    /// Servers by name, e.g. `[lsp.servers.rust]`.
+    #[serde(default)]
+    pub servers: BTreeMap<String, LspServerConfig>,
+}
+
+/// `[lsp.servers.{name}]`: how to run one server.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct LspServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// File extensions (without the dot) the server handles.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// `languageId` of the documents it opens (default: the server name).
+    #[serde(default)]
+    pub language_id: Option<String>,
+}
+
+impl LspConfig {
+    /// First server (by name) handling `path`'s extension.
+    pub fn server_for(&self, path: &Path) -> Option<(&str, &LspServerConfig)> {
+        let ext = path.extension()?.to_str()?;
+        self.servers
+            .iter()
+            .find(|(_, server)| server.extensions.iter().any(|e| e == ext))
+            .map(|(name, server)| (name.as_str(), server))
+    }
+}
+
+/// `[diagnostics]`: how diagnostics are shown besides signs and underlines.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DiagnosticsConfig {
+    /// Show the most severe message of a line after its end.
+    #[serde(default)]
+    pub virtual_text: bool,
+}
+
+/// Right-hand side of a `[keymap]` entry.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum MappingValue {
+    /// `"lhs" = "rhs"`: not remapped (`:noremap`).
+    Keys(String),
+    /// `"lhs" = { keys = "rhs", remap = true }`: `remap` makes it `:map`.
+    Table {
+        keys: String,
+        #[serde(default)]
+        remap: bool,
+    },
+}
+
+impl MappingValue {
+    pub fn keys(&self) -> &str {
+        match self {
+            MappingValue::Keys(keys) | MappingValue::Table { keys, .. } => keys,
+        }
+    }
+
+    pub fn remap(&self) -> bool {
+        matches!(self, MappingValue::Table { remap: true, .. })
+    }
+}
+
+impl From<&str> for MappingValue {
+    fn from(keys: &str) -> Self {
+        MappingValue::Keys(keys.to_string())
+    }
+}
+
+/// `[keymap]`: user key mappings per mode, `"lhs" = rhs` in Vim key
+/// notation (`core_keymap::user`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct KeymapConfig {
+    /// Key `<leader>` stands for.
+    #[serde(default = "KeymapConfig::default_leader")]
+    pub leader: String,
+    #[serde(default)]
+    pub normal: BTreeMap<String, MappingValue>,
+    #[serde(default)]
+    pub insert: BTreeMap<String, MappingValue>,
+    #[serde(default)]
+    pub visual: BTreeMap<String, MappingValue>,
+    /// Operator-pending: after `d`, `c`, `y`, ... before the motion.
+    #[serde(default)]
+    pub operator: BTreeMap<String, MappingValue>,
+    /// Command-line mode (`:` commands and `/` `?` searches).
+    #[serde(default)]
+    pub command: BTreeMap<String, MappingValue>,
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        Self {
+            leader: Self::default_leader(),
+            normal: BTreeMap::new(),
+            insert: BTreeMap::new(),
+            visual: BTreeMap::new(),
+            operator: BTreeMap::new(),
+            command: BTreeMap::new(),
+        }
+    }
+}
+
+impl KeymapConfig {
+    fn default_leader() -> String {
+        "\\".into()
+    }
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct ConfigFile {
+    /// Color scheme loaded at startup (`:colorscheme` at runtime).
+    #[serde(default)]
+    pub colorscheme: Option<String>,
+    /// Color depth forcing terminal detection: `truecolor`, `256` or `16`.
+    #[serde(default)]
+    pub colors: Option<String>,
     #[serde(default)]
     pub scroll: ScrollConfig,
     #[serde(default)]
     pub input: InputConfig,
+    #[serde(default)]
+    pub options: BTreeMap<String, OptionValue>,
+    #[serde(default)]
+    pub files: FilesConfig,
+    #[serde(default)]
+    pub shada: ShadaConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub statusline: StatusLineConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub keymap: KeymapConfig,
+    #[serde(default)]
+    pub lsp: LspConfig,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    /// User command aliases: `Name = "ex command"` (Commands Step 1). Names
+    /// start with an uppercase letter, as in Vim.
+    #[serde(default)]
+    pub commands: BTreeMap<String, String>,
+    /// Insert-mode abbreviations: `lhs = "rhs"`, as `:iabbrev lhs rhs`.
+    #[serde(default)]
+    pub abbreviations: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -140,6 +478,19 @@ pub fn discover() -> PathBuf {
     PathBuf::from("oxidized.toml")
 }
 
+/// Whether `program` is an executable found on `PATH`.
+fn on_path(program: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    let name = if cfg!(windows) {
+        format!("{program}.exe")
+    } else {
+        program.to_string()
+    };
+    std::env::split_paths(&paths).any(|dir| dir.join(&name).is_file())
+}
+
 pub fn load_from(path: Option<PathBuf>) -> Result<Config> {
     let path = path.unwrap_or_else(discover);
     if let Ok(content) = fs::read_to_string(&path) {
@@ -159,6 +510,19 @@ pub fn load_from(path: Option<PathBuf>) -> Result<Config> {
     }
 }
 
+/// Read `path` again for a hot reload. Unlike `load_from`, a file that
+/// does not parse is an error, so a half-edited config leaves the running
+/// one alone.
+pub fn reload(path: &Path) -> Result<Config> {
+    let content = fs::read_to_string(path)?;
+    let file = toml::from_str::<ConfigFile>(&content)?;
+    Ok(Config {
+        raw: Some(content),
+        file,
+        effective_vertical_margin: 0,
+    })
+}
+
 impl Config {
     /// Apply viewport + platform context to compute clamped vertical margin.
     /// Returns the effective (possibly clamped) value.
@@ -204,6 +568,50 @@ impl Config {
         if current != prev { Some(current) } else { None }
     }
 
+    /// Build the runtime option table with configuration-derived defaults.
+    /// Unknown names or mismatched value types are logged and skipped.
+    pub fn option_table(&self) -> OptionTable {
+        let mut table = OptionTable::default();
+        let input = &self.file.input;
+        let seeded = [
+            ("timeout", OptionValue::Bool(input.timeout)),
+            ("timeoutlen", OptionValue::Number(input.timeoutlen as i64)),
+            (
+                "scrolloff",
+                OptionValue::Number(self.file.scroll.margin.vertical as i64),
+            ),
+        ];
+        for (name, value) in seeded {
+            let _ = table.set_default(name, value);
+        }
+        let files = &self.file.files;
+        if files.backup {
+            let _ = table.set_default("backup", OptionValue::Bool(true));
+        }
+        if let Some(dir) = &files.backupdir {
+            let _ = table.set_default("backupdir", OptionValue::String(dir.clone()));
+        }
+        if let Some(ext) = &files.backupext {
+            let _ = table.set_default("backupext", OptionValue::String(ext.clone()));
+        }
+        // Like Vim, 'shell' defaults to $SHELL when set.
+        if let Ok(shell) = std::env::var("SHELL")
+            && !shell.is_empty()
+        {
+            let _ = table.set_default("shell", OptionValue::String(shell));
+        }
+        // `:grep` prefers ripgrep when it is installed.
+        if on_path("rg") {
+            let _ = table.set_default("grepprg", OptionValue::String("rg --vimgrep".into()));
+        }
+        for (name, value) in &self.file.options {
+            if let Err(e) = table.set_default(name, value.clone()) {
+                warn!(target: "config", option = %name, error = %e, "config_option_rejected");
+            }
+        }
+        table
+    }
+
     /// Back-compat wrapper using viewport height only.
     pub fn recompute_after_resize(&mut self, new_viewport_height: u16) -> Option<u16> {
         self.recompute_with_context(ConfigContext::from_viewport_height(new_viewport_height))
@@ -355,4 +763,189 @@ mod tests {
         assert_eq!(cfg.file.input.timeoutlen, 250);
         assert_eq!(cfg.file.scroll.margin.vertical, 3);
     }
+
+    #[test]
+    fn options_table_seeds_option_defaults() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[input]\ntimeoutlen = 300\n[options]\nnumber = true\nshiftwidth = 4\nbogus = 1\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        let table = cfg.option_table();
+        assert!(table.get_bool("number"));
+        assert_eq!(table.get_number("shiftwidth"), 4);
+        assert_eq!(table.get_number("timeoutlen"), 300);
+        assert!(!table.has_pending_changes());
+    }
+
+    #[test]
+    fn parses_command_aliases_and_abbreviations() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[commands]\nW = \"w\"\n[abbreviations]\nteh = \"the\"\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.commands.get("W").map(String::as_str), Some("w"));
+        assert_eq!(
+            cfg.file.abbreviations.get("teh").map(String::as_str),
+            Some("the")
+        );
+    }
+
+    #[test]
+    fn parses_keymap_tables() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[keymap]\nleader = \"<Space>\"\n[keymap.normal]\n\"<leader>w\" = \":w<CR>\"\nY = { keys = \"y$\", remap = true }\n[keymap.insert]\njk = \"<Esc>\"\n[keymap.command]\nww = \"w!\"\n[keymap.operator]\nL = \"$\"\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        let keymap = &cfg.file.keymap;
+        assert_eq!(keymap.leader, "<Space>");
+        assert_eq!(
+            keymap.normal.get("<leader>w").map(MappingValue::keys),
+            Some(":w<CR>")
+        );
+        assert_eq!(
+            keymap.insert.get("jk").map(MappingValue::keys),
+            Some("<Esc>")
+        );
+        let y = &keymap.normal["Y"];
+        assert_eq!((y.keys(), y.remap()), ("y$", true));
+        assert!(!keymap.insert["jk"].remap());
+        assert!(keymap.visual.is_empty());
+        assert_eq!(keymap.command.len(), 1);
+        assert_eq!(keymap.operator.get("L").map(MappingValue::keys), Some("$"));
+        assert_eq!(KeymapConfig::default().leader, "\\");
+    }
+
+    #[test]
+    fn parses_color_depth_override() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), "colorscheme = \"night\"\ncolors = \"16\"\n").unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.colors.as_deref(), Some("16"));
+    }
+
+    #[test]
+    fn parses_files_autosave_interval() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), "[files]\nautosave_ms = 1500\n").unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.files.autosave_ms, 1500);
+        assert_eq!(Config::default().file.files.autosave_ms, 0);
+    }
+
+    #[test]
+    fn files_backup_keys_seed_options() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[files]\nbackup = true\nbackupdir = \"/tmp/bk\"\nbackupext = \".bak\"\n",
+        )
+        .unwrap();
+        let table = load_from(Some(tmp.path().to_path_buf()))
+            .unwrap()
+            .option_table();
+        assert!(table.get_bool("backup"));
+        assert_eq!(table.get_string("backupdir"), "/tmp/bk");
+        assert_eq!(table.get_string("backupext"), ".bak");
+    }
+
+    #[test]
+    fn shada_table_toggle_and_limits() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[shada]\npath = \"/tmp/ox-shada\"\nmax_lines = 5\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(
+            cfg.file.shada.resolved_path(),
+            Some(PathBuf::from("/tmp/ox-shada"))
+        );
+        assert_eq!(cfg.file.shada.max_lines, 5);
+        assert_eq!(cfg.file.shada.max_item_kb, 10);
+        assert_eq!(cfg.file.shada.files, 100);
+        assert!(cfg.file.shada.restore_cursor);
+
+        std::fs::write(
+            tmp.path(),
+            "[shada]\nenabled = false\nrestore_cursor = false\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.shada.resolved_path(), None);
+        assert!(!cfg.file.shada.restore_cursor);
+    }
+
+    #[test]
+    fn statusline_table_orders_segments() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[statusline]\nsegments = [\"wc\", \"lsp\"]\ntimeout_ms = 20\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(
+            cfg.file.statusline.segments,
+            Some(vec!["wc".to_string(), "lsp".to_string()])
+        );
+        assert_eq!(cfg.file.statusline.timeout_ms, 20);
+        assert_eq!(ConfigFile::default().statusline.timeout_ms, 100);
+    }
+
+    #[test]
+    fn plugins_table_sets_dir_or_disables() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), "[plugins]\ndir = \"/tmp/ox-plugins\"\n").unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(
+            cfg.file.plugins.resolved_dir(),
+            Some(PathBuf::from("/tmp/ox-plugins"))
+        );
+
+        std::fs::write(tmp.path(), "[plugins]\nenabled = false\n").unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.plugins.resolved_dir(), None);
+    }
+
+    #[test]
+    fn metrics_table_sets_sink_and_interval() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), "[metrics]\nexport = \"stderr\"\n").unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.metrics.export.as_deref(), Some("stderr"));
+        assert_eq!(cfg.file.metrics.interval_ms, 1000);
+
+        std::fs::write(tmp.path(), "[metrics]\ninterval_ms = 250\n").unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        assert_eq!(cfg.file.metrics.export, None);
+        assert_eq!(cfg.file.metrics.interval_ms, 250);
+    }
+
+    #[test]
+    fn lsp_servers_match_by_extension() {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            tmp.path(),
+            "[lsp.servers.rust]\ncommand = \"rust-analyzer\"\nextensions = [\"rs\"]\n\n\
+             [diagnostics]\nvirtual_text = true\n",
+        )
+        .unwrap();
+        let cfg = load_from(Some(tmp.path().to_path_buf())).unwrap();
+        let (name, server) = cfg.file.lsp.server_for(Path::new("src/main.rs")).unwrap();
+        assert_eq!((name, server.command.as_str()), ("rust", "rust-analyzer"));
+        assert!(server.args.is_empty() && server.language_id.is_none());
+        assert!(cfg.file.lsp.server_for(Path::new("README.md")).is_none());
+        assert!(cfg.file.diagnostics.virtual_text);
+        assert!(!ConfigFile::default().diagnostics.virtual_text);
+    }
 }
@@ -0,0 +1,89 @@
+//! `'listchars'`: the glyphs `'list'` paints over invisible whitespace.
+//!
+//! The value is a comma list of `name:glyphs` entries, as in Vim:
+//!
+//! * `eol:c` after the last character of every line;
+//! * `tab:xy` or `tab:xyz` over a tab: `x`, then `y` for the remaining
+//!   cells; with `z`, `z` ends the tab (and fills it alone when the tab
+//!   is one cell wide);
+//! * `trail:c` over trailing spaces;
+//! * `nbsp:c` over non-breaking spaces (U+00A0, U+202F).
+//!
+//! Every glyph must be a single-width character. An absent entry leaves
+//! that kind of whitespace as it is.
+
+/// Parsed `'listchars'` value.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ListChars {
+    pub eol: Option<char>,
+    pub tab: Option<(char, char, Option<char>)>,
+    pub trail: Option<char>,
+    pub nbsp: Option<char>,
+}
+
+impl ListChars {
+    /// Parse an option value; `None` for unknown names, repeated or missing
+    /// glyphs and glyphs that are not one cell wide.
+    pub fn parse(value: &str) -> Option<ListChars> {
+        let mut out = ListChars::default();
+        for entry in value.split(',').filter(|e| !e.is_empty()) {
+            let (name, glyphs) = entry.split_once(':')?;
+            let glyphs: Vec<char> = glyphs.chars().collect();
+            if glyphs
+                .iter()
+                .any(|c| core_text::grapheme::cluster_width(c.encode_utf8(&mut [0; 4])) != 1)
+            {
+                return None;
+            }
+            match (name, glyphs.as_slice()) {
+                ("eol", [c]) => out.eol = Some(*c),
+                ("trail", [c]) => out.trail = Some(*c),
+                ("nbsp", [c]) => out.nbsp = Some(*c),
+                ("tab", [x, y]) => out.tab = Some((*x, *y, None)),
+                ("tab", [x, y, z]) => out.tab = Some((*x, *y, Some(*z))),
+                _ => return None,
+            }
+        }
+        Some(out)
+    }
+
+    /// Glyphs covering a tab `width` cells wide.
+    pub fn tab_glyphs(&self, width: usize) -> Option<String> {
+        let (x, y, z) = self.tab?;
+        let width = width.max(1);
+        Some(match z {
+            Some(z) if width == 1 => z.to_string(),
+            Some(z) => std::iter::once(x)
+                .chain(std::iter::repeat_n(y, width - 2))
+                .chain([z])
+                .collect(),
+            None => std::iter::once(x)
+                .chain(std::iter::repeat_n(y, width - 1))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries_and_rejects_bad_glyphs() {
+        let lcs = ListChars::parse("tab:>-,trail:~,eol:$,nbsp:+").unwrap();
+        assert_eq!(lcs.eol, Some('$'));
+        assert_eq!(lcs.trail, Some('~'));
+        assert_eq!(lcs.nbsp, Some('+'));
+        assert_eq!(lcs.tab_glyphs(1).as_deref(), Some(">"));
+        assert_eq!(lcs.tab_glyphs(4).as_deref(), Some(">---"));
+        let lcs = ListChars::parse("tab:<->").unwrap();
+        assert_eq!(lcs.tab_glyphs(1).as_deref(), Some(">"));
+        assert_eq!(lcs.tab_glyphs(4).as_deref(), Some("<-->"));
+        assert_eq!(ListChars::parse(""), Some(ListChars::default()));
+        assert_eq!(ListChars::parse("eol:$").unwrap().tab_glyphs(2), None);
+
+        for bad in ["eol", "eol:", "eol:$$", "tab:>", "bogus:x", "trail:界"] {
+            assert_eq!(ListChars::parse(bad), None, "{bad}");
+        }
+    }
+}
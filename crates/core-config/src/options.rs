@@ -0,0 +1,919 @@
+//! Runtime option table backing `:set` (Options Step 1).
+//!
+//! Scope: a single flat table of typed options (boolean / number / string).
+//! Each option is described by a static [`OptionSpec`] carrying its full
+//! name, optional Vim abbreviation, built-in default, and the subsystem that
+//! must react when the value changes ([`OptionEffect`]).
+//!
+//! Layering:
+//! * Built-in defaults come from `BUILTIN_OPTIONS`.
+//! * Configuration (`oxidized.toml`) overrides defaults: legacy tables such as
+//!   `[input] timeoutlen` seed their option counterparts and the `[options]`
+//!   table may set any option by name. These become the *default* values
+//!   that `:set option&` restores.
+//! * `:set` directives apply runtime overrides on top.
+//!
+//! Change notification: every effective mutation is queued as an
+//! [`OptionChange`]. The runtime drains the queue after each dispatch
+//! (`take_changes`) so render (line numbers, wrap) and input (timeoutlen)
+//! pick up new values without a restart. A queue keeps this crate free of
+//! callback lifetimes and matches the single-threaded event loop.
+//!
+//! Forward roadmap:
+//! * Buffer/window local scopes (`:setlocal`).
+//! * Comma list options with `+=`/`-=` element semantics.
+
+use crate::listchars::ListChars;
+use serde::Deserialize;
+use std::fmt;
+
+/// Typed option value. Deserialized untagged so `[options]` entries accept
+/// plain TOML booleans, integers and strings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Bool(bool),
+    Number(i64),
+    String(String),
+}
+
+impl OptionValue {
+    pub fn kind(&self) -> OptionKind {
+        match self {
+            OptionValue::Bool(_) => OptionKind::Bool,
+            OptionValue::Number(_) => OptionKind::Number,
+            OptionValue::String(_) => OptionKind::String,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Bool,
+    Number,
+    String,
+}
+
+/// Subsystem that must react to a change of the option value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionEffect {
+    /// Value is read on demand; no notification consumer required.
+    None,
+    /// Rendering output depends on the value (full repaint required).
+    Render,
+    /// Input translation (mapping timeouts) depends on the value.
+    Input,
+    /// Scroll margin clamping depends on the value.
+    Scroll,
+    /// Window-local flag: the value belongs to the focused window and is
+    /// reloaded from the next window on a focus change.
+    Window,
+}
+
+/// Built-in default as a `const`-friendly literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionDefault {
+    Bool(bool),
+    Number(i64),
+    String(&'static str),
+}
+
+impl OptionDefault {
+    fn to_value(self) -> OptionValue {
+        match self {
+            OptionDefault::Bool(b) => OptionValue::Bool(b),
+            OptionDefault::Number(n) => OptionValue::Number(n),
+            OptionDefault::String(s) => OptionValue::String(s.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    pub short: Option<&'static str>,
+    pub default: OptionDefault,
+    pub effect: OptionEffect,
+}
+
+/// Built-in option registry. Order is the display order for `:set all`.
+pub const BUILTIN_OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        name: "autoread",
+        short: Some("ar"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "backup",
+        short: Some("bk"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "backupdir",
+        short: Some("bdir"),
+        default: OptionDefault::String(""),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "backupext",
+        short: Some("bex"),
+        default: OptionDefault::String("~"),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "binary",
+        short: Some("bin"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "colorcolumn",
+        short: Some("cc"),
+        default: OptionDefault::String(""),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "cursorcolumn",
+        short: Some("cuc"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "cursorline",
+        short: Some("cul"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "errorformat",
+        short: Some("efm"),
+        default: OptionDefault::String("%f:%l:%c: %m,%f:%l: %m"),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "expandtab",
+        short: Some("et"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "foldcolumn",
+        short: Some("fdc"),
+        default: OptionDefault::Number(0),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "grepjump",
+        short: None,
+        default: OptionDefault::Bool(true),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "grepprg",
+        short: Some("gp"),
+        default: OptionDefault::String("grep -rnH"),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "hlsearch",
+        short: Some("hls"),
+        default: OptionDefault::Bool(true),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "ignorecase",
+        short: Some("ic"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "infercase",
+        short: Some("inf"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "list",
+        short: None,
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "listchars",
+        short: Some("lcs"),
+        default: OptionDefault::String("eol:$"),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "makeprg",
+        short: Some("mp"),
+        default: OptionDefault::String("make"),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "mousescroll",
+        short: None,
+        default: OptionDefault::String("ver:3,hor:6"),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "number",
+        short: Some("nu"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "relativenumber",
+        short: Some("rnu"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "scroll",
+        short: Some("scr"),
+        default: OptionDefault::Number(0),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "scrollbind",
+        short: Some("scb"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::Window,
+    },
+    OptionSpec {
+        name: "scrolloff",
+        short: Some("so"),
+        default: OptionDefault::Number(0),
+        effect: OptionEffect::Scroll,
+    },
+    OptionSpec {
+        name: "shell",
+        short: Some("sh"),
+        default: OptionDefault::String(if cfg!(windows) { "cmd.exe" } else { "sh" }),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "shiftwidth",
+        short: Some("sw"),
+        default: OptionDefault::Number(8),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "showbreak",
+        short: Some("sbr"),
+        default: OptionDefault::String(""),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "smartcase",
+        short: Some("scs"),
+        default: OptionDefault::Bool(false),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "softtabstop",
+        short: Some("sts"),
+        default: OptionDefault::Number(0),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "swapfile",
+        short: Some("swf"),
+        default: OptionDefault::Bool(true),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "tabstop",
+        short: Some("ts"),
+        default: OptionDefault::Number(8),
+        effect: OptionEffect::Render,
+    },
+    OptionSpec {
+        name: "timeout",
+        short: Some("to"),
+        default: OptionDefault::Bool(true),
+        effect: OptionEffect::Input,
+    },
+    OptionSpec {
+        name: "timeoutlen",
+        short: Some("tm"),
+        default: OptionDefault::Number(1000),
+        effect: OptionEffect::Input,
+    },
+    OptionSpec {
+        name: "updatetime",
+        short: Some("ut"),
+        default: OptionDefault::Number(4000),
+        effect: OptionEffect::None,
+    },
+    OptionSpec {
+        name: "wrap",
+        short: None,
+        default: OptionDefault::Bool(true),
+        effect: OptionEffect::Render,
+    },
+];
+
+/// A single applied option mutation, queued for runtime consumers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionChange {
+    pub name: &'static str,
+    pub effect: OptionEffect,
+    pub old: OptionValue,
+    pub new: OptionValue,
+}
+
+/// Errors surfaced by `:set` using Vim's message numbering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionError {
+    Unknown(String),
+    InvalidArgument(String),
+    NumberRequired(String),
+    MustBePositive(String),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionError::Unknown(arg) => write!(f, "E518: Unknown option: {arg}"),
+            OptionError::InvalidArgument(arg) => write!(f, "E474: Invalid argument: {arg}"),
+            OptionError::NumberRequired(arg) => write!(f, "E521: Number required after =: {arg}"),
+            OptionError::MustBePositive(arg) => {
+                write!(f, "E487: Argument must be positive: {arg}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assign {
+    Set,
+    Add,
+    Subtract,
+    Prepend,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptionTable {
+    specs: Vec<OptionSpec>,
+    defaults: Vec<OptionValue>,
+    values: Vec<OptionValue>,
+    changes: Vec<OptionChange>,
+}
+
+impl Default for OptionTable {
+    fn default() -> Self {
+        Self::with_specs(BUILTIN_OPTIONS)
+    }
+}
+
+impl OptionTable {
+    pub fn with_specs(specs: &[OptionSpec]) -> Self {
+        let defaults: Vec<OptionValue> = specs.iter().map(|s| s.default.to_value()).collect();
+        Self {
+            specs: specs.to_vec(),
+            values: defaults.clone(),
+            defaults,
+            changes: Vec::new(),
+        }
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.specs
+            .iter()
+            .position(|s| s.name == name || s.short == Some(name))
+    }
+
+    /// Resolve a full name or abbreviation to its spec.
+    pub fn spec(&self, name: &str) -> Option<&OptionSpec> {
+        self.index_of(name).map(|i| &self.specs[i])
+    }
+
+    pub fn specs(&self) -> &[OptionSpec] {
+        &self.specs
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.index_of(name).map(|i| &self.values[i])
+    }
+
+    /// Boolean accessor; returns `false` for unknown or non-boolean options.
+    pub fn get_bool(&self, name: &str) -> bool {
+        matches!(self.get(name), Some(OptionValue::Bool(true)))
+    }
+
+    /// Number accessor; returns `0` for unknown or non-number options.
+    pub fn get_number(&self, name: &str) -> i64 {
+        match self.get(name) {
+            Some(OptionValue::Number(n)) => *n,
+            _ => 0,
+        }
+    }
+
+    /// String accessor; returns `""` for unknown or non-string options.
+    pub fn get_string(&self, name: &str) -> &str {
+        match self.get(name) {
+            Some(OptionValue::String(s)) => s.as_str(),
+            _ => "",
+        }
+    }
+
+    /// Override the default (and current) value of an option. Used while
+    /// seeding from configuration; does not queue change notifications.
+    pub fn set_default(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        let idx = self
+            .index_of(name)
+            .ok_or_else(|| OptionError::Unknown(name.to_string()))?;
+        if self.defaults[idx].kind() != value.kind() {
+            return Err(OptionError::InvalidArgument(name.to_string()));
+        }
+        self.defaults[idx] = value.clone();
+        self.values[idx] = value;
+        Ok(())
+    }
+
+    /// Programmatic runtime override, queuing a change notification when
+    /// the value differs.
+    pub fn set(&mut self, name: &str, value: OptionValue) -> Result<(), OptionError> {
+        let idx = self
+            .index_of(name)
+            .ok_or_else(|| OptionError::Unknown(name.to_string()))?;
+        if self.values[idx].kind() != value.kind() {
+            return Err(OptionError::InvalidArgument(name.to_string()));
+        }
+        self.assign(idx, value);
+        Ok(())
+    }
+
+    /// Take the defaults of `seeded`, the table of a reloaded configuration.
+    /// An option still at its old default moves to the new one, queuing a
+    /// change notification; one set since keeps its value.
+    pub fn reseed(&mut self, seeded: &OptionTable) {
+        for idx in 0..self.specs.len() {
+            let Some(new) = seeded.index_of(self.specs[idx].name) else {
+                continue;
+            };
+            let new = seeded.defaults[new].clone();
+            if self.defaults[idx] == new || self.defaults[idx].kind() != new.kind() {
+                continue;
+            }
+            let old = std::mem::replace(&mut self.defaults[idx], new.clone());
+            if self.values[idx] == old {
+                self.assign(idx, new);
+            }
+        }
+    }
+
+    fn assign(&mut self, idx: usize, value: OptionValue) {
+        if self.values[idx] == value {
+            return;
+        }
+        let old = std::mem::replace(&mut self.values[idx], value.clone());
+        let spec = self.specs[idx];
+        tracing::debug!(target: "config.options", name = spec.name, ?old, new = ?value, "option_changed");
+        self.changes.push(OptionChange {
+            name: spec.name,
+            effect: spec.effect,
+            old,
+            new: value,
+        });
+    }
+
+    /// Drain queued change notifications (oldest first).
+    pub fn take_changes(&mut self) -> Vec<OptionChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    pub fn has_pending_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Vim-style display form: `number`, `nonumber`, `shiftwidth=4`.
+    pub fn display(&self, name: &str) -> Option<String> {
+        let idx = self.index_of(name)?;
+        Some(self.display_index(idx))
+    }
+
+    fn display_index(&self, idx: usize) -> String {
+        let name = self.specs[idx].name;
+        match &self.values[idx] {
+            OptionValue::Bool(true) => name.to_string(),
+            OptionValue::Bool(false) => format!("no{name}"),
+            OptionValue::Number(n) => format!("{name}={n}"),
+            OptionValue::String(s) => format!("{name}={s}"),
+        }
+    }
+
+    /// Execute the argument string of a `:set` command. Returns the text to
+    /// echo (queries and listings), or `None` when nothing needs display.
+    /// Processing stops at the first failing argument, matching Vim.
+    pub fn apply_set(&mut self, args: &str) -> Result<Option<String>, OptionError> {
+        let args = args.trim();
+        if args.is_empty() {
+            let changed: Vec<String> = (0..self.specs.len())
+                .filter(|&i| self.values[i] != self.defaults[i])
+                .map(|i| self.display_index(i))
+                .collect();
+            return Ok(Some(format!("--- Options --- {}", changed.join("  "))));
+        }
+        if args == "all" {
+            let all: Vec<String> = (0..self.specs.len())
+                .map(|i| self.display_index(i))
+                .collect();
+            return Ok(Some(all.join("  ")));
+        }
+        let mut echoed = Vec::new();
+        for arg in args.split_whitespace() {
+            if let Some(text) = self.apply_set_arg(arg)? {
+                echoed.push(text);
+            }
+        }
+        Ok(if echoed.is_empty() {
+            None
+        } else {
+            Some(echoed.join("  "))
+        })
+    }
+
+    fn apply_set_arg(&mut self, arg: &str) -> Result<Option<String>, OptionError> {
+        // `name?` query.
+        if let Some(name) = arg.strip_suffix('?') {
+            let idx = self.lookup(name, arg)?;
+            return Ok(Some(self.display_index(idx)));
+        }
+        // `name&` reset to default.
+        if let Some(name) = arg.strip_suffix('&') {
+            let idx = self.lookup(name, arg)?;
+            let default = self.defaults[idx].clone();
+            self.assign(idx, default);
+            return Ok(None);
+        }
+        // `name!` toggle.
+        if let Some(name) = arg.strip_suffix('!') {
+            return self.toggle(name, arg).map(|_| None);
+        }
+        if let Some(split) = arg.find(['=', ':']) {
+            let (lhs, rhs) = (&arg[..split], &arg[split + 1..]);
+            let (name, assign) = if let Some(n) = lhs.strip_suffix('+') {
+                (n, Assign::Add)
+            } else if let Some(n) = lhs.strip_suffix('-') {
+                (n, Assign::Subtract)
+            } else if let Some(n) = lhs.strip_suffix('^') {
+                (n, Assign::Prepend)
+            } else {
+                (lhs, Assign::Set)
+            };
+            let idx = self.lookup(name, arg)?;
+            let value = self.assigned_value(idx, assign, rhs, arg)?;
+            self.assign(idx, value);
+            return Ok(None);
+        }
+        if let Some(idx) = self.index_of(arg) {
+            // Bare name: enable booleans, show the value of others.
+            return match self.values[idx] {
+                OptionValue::Bool(_) => {
+                    self.assign(idx, OptionValue::Bool(true));
+                    Ok(None)
+                }
+                _ => Ok(Some(self.display_index(idx))),
+            };
+        }
+        if let Some(name) = arg.strip_prefix("no") {
+            let idx = self.lookup_bool(name, arg)?;
+            self.assign(idx, OptionValue::Bool(false));
+            return Ok(None);
+        }
+        if let Some(name) = arg.strip_prefix("inv") {
+            return self.toggle(name, arg).map(|_| None);
+        }
+        Err(OptionError::Unknown(arg.to_string()))
+    }
+
+    /// Completions of the `:set` argument `arg`, sorted: option names, with
+    /// `no` / `inv` in front of boolean ones when typed. After `name=` the
+    /// current value is offered while nothing is typed, then the entries a
+    /// list option's value is made of (`'listchars'`, `'mousescroll'`).
+    pub fn complete(&self, arg: &str) -> Vec<String> {
+        if let Some(split) = arg.find(['=', ':']) {
+            let (lhs, typed) = arg.split_at(split + 1);
+            let name = lhs[..split].trim_end_matches(['+', '-', '^']);
+            let Some(idx) = self.index_of(name) else {
+                return Vec::new();
+            };
+            if typed.is_empty() {
+                // `:set` splits its arguments at blanks, so such a value
+                // could not be typed back.
+                return match &self.values[idx] {
+                    OptionValue::Number(n) => vec![format!("{lhs}{n}")],
+                    OptionValue::String(s) if !s.is_empty() && !s.contains(char::is_whitespace) => {
+                        vec![format!("{lhs}{s}")]
+                    }
+                    _ => Vec::new(),
+                };
+            }
+            let (done, entry) = typed.split_at(typed.rfind(',').map_or(0, |i| i + 1));
+            return list_entries(self.specs[idx].name)
+                .iter()
+                .filter(|e| e.starts_with(entry))
+                .map(|e| format!("{lhs}{done}{e}"))
+                .collect();
+        }
+        let mut names = Vec::new();
+        for (spec, value) in self.specs.iter().zip(&self.values) {
+            if spec.name.starts_with(arg) {
+                names.push(spec.name.to_string());
+            }
+            if value.kind() != OptionKind::Bool {
+                continue;
+            }
+            for prefix in ["no", "inv"] {
+                if arg
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| spec.name.starts_with(rest))
+                {
+                    names.push(format!("{prefix}{}", spec.name));
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn lookup(&self, name: &str, arg: &str) -> Result<usize, OptionError> {
+        self.index_of(name)
+            .ok_or_else(|| OptionError::Unknown(arg.to_string()))
+    }
+
+    fn lookup_bool(&self, name: &str, arg: &str) -> Result<usize, OptionError> {
+        let idx = self.lookup(name, arg)?;
+        if self.values[idx].kind() != OptionKind::Bool {
+            return Err(OptionError::InvalidArgument(arg.to_string()));
+        }
+        Ok(idx)
+    }
+
+    fn toggle(&mut self, name: &str, arg: &str) -> Result<(), OptionError> {
+        let idx = self.lookup_bool(name, arg)?;
+        let current = matches!(self.values[idx], OptionValue::Bool(true));
+        self.assign(idx, OptionValue::Bool(!current));
+        Ok(())
+    }
+
+    fn assigned_value(
+        &self,
+        idx: usize,
+        assign: Assign,
+        rhs: &str,
+        arg: &str,
+    ) -> Result<OptionValue, OptionError> {
+        match &self.values[idx] {
+            OptionValue::Bool(_) => Err(OptionError::InvalidArgument(arg.to_string())),
+            OptionValue::Number(current) => {
+                let n: i64 = rhs
+                    .parse()
+                    .map_err(|_| OptionError::NumberRequired(arg.to_string()))?;
+                let next = match assign {
+                    Assign::Set => n,
+                    Assign::Add => current.saturating_add(n),
+                    Assign::Subtract => current.saturating_sub(n),
+                    Assign::Prepend => current.saturating_mul(n),
+                };
+                // A tab must reach somewhere; a negative 'softtabstop'
+                // stands for 'shiftwidth'.
+                let name = self.specs[idx].name;
+                if (next < 0 && name != "softtabstop") || (next == 0 && name == "tabstop") {
+                    return Err(OptionError::MustBePositive(arg.to_string()));
+                }
+                Ok(OptionValue::Number(next))
+            }
+            OptionValue::String(current) => {
+                let next = match assign {
+                    Assign::Set => rhs.to_string(),
+                    Assign::Add => format!("{current}{rhs}"),
+                    Assign::Subtract => current.replacen(rhs, "", 1),
+                    Assign::Prepend => format!("{rhs}{current}"),
+                };
+                let valid = match self.specs[idx].name {
+                    "listchars" => ListChars::parse(&next).is_some(),
+                    "colorcolumn" => color_columns(&next).is_some(),
+                    "mousescroll" => mouse_scroll_lines(&next).is_some(),
+                    _ => true,
+                };
+                if !valid {
+                    return Err(OptionError::InvalidArgument(arg.to_string()));
+                }
+                Ok(OptionValue::String(next))
+            }
+        }
+    }
+}
+
+/// Entries of a comma-list option's value, offered by `:set name=<Tab>`.
+fn list_entries(name: &str) -> &'static [&'static str] {
+    match name {
+        "listchars" => &["eol:", "nbsp:", "tab:", "trail:"],
+        "mousescroll" => &["hor:", "ver:"],
+        _ => &[],
+    }
+}
+
+/// Columns of a `'colorcolumn'` value (`80,100`): 1-based text columns,
+/// sorted and deduplicated. `None` when an entry is not a positive number;
+/// Vim's `+N` / `-N` entries need `'textwidth'`, which does not exist yet.
+pub fn color_columns(value: &str) -> Option<Vec<u16>> {
+    let mut columns = value
+        .split(',')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let digits = entry.bytes().all(|b| b.is_ascii_digit());
+            entry.parse::<u16>().ok().filter(|c| digits && *c > 0)
+        })
+        .collect::<Option<Vec<u16>>>()?;
+    columns.sort_unstable();
+    columns.dedup();
+    Some(columns)
+}
+
+/// Lines one mouse wheel step scrolls, from the `ver:N` entry of a
+/// `'mousescroll'` value (`ver:3,hor:6`); 0 without one (Vim disables
+/// vertical wheel scrolling then). `None` for an unknown entry or a count
+/// that is not a number. Horizontal scrolling (`hor:N`) is accepted but
+/// there is no horizontal scroll to apply it to.
+pub fn mouse_scroll_lines(value: &str) -> Option<usize> {
+    let mut lines = 0;
+    for entry in value.split(',').filter(|entry| !entry.is_empty()) {
+        let (key, count) = entry.split_once(':')?;
+        if !count.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let count: usize = count.parse().ok()?;
+        match key {
+            "ver" => lines = count,
+            "hor" => {}
+            _ => return None,
+        }
+    }
+    Some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_names_prefixes_and_values() {
+        let t = OptionTable::default();
+        assert_eq!(t.complete("rel"), ["relativenumber"]);
+        assert_eq!(t.complete("sc"), ["scroll", "scrollbind", "scrolloff"]);
+        // `no` / `inv` only go in front of booleans.
+        assert_eq!(t.complete("nosc"), ["noscrollbind"]);
+        assert_eq!(t.complete("invw"), ["invwrap"]);
+        assert!(t.complete("no").contains(&"nonumber".to_string()));
+        assert!(t.complete("no").iter().all(|n| n.starts_with("no")));
+
+        assert_eq!(t.complete("tm="), ["tm=1000"]);
+        assert_eq!(t.complete("listchars="), ["listchars=eol:$"]);
+        assert_eq!(
+            t.complete("lcs+=eol:$,t"),
+            ["lcs+=eol:$,tab:", "lcs+=eol:$,trail:"]
+        );
+        assert_eq!(t.complete("mousescroll:v"), ["mousescroll:ver:"]);
+        assert!(t.complete("grepprg=").is_empty());
+        assert!(t.complete("bogus=").is_empty());
+    }
+
+    #[test]
+    fn bare_boolean_enables_and_no_prefix_disables() {
+        let mut t = OptionTable::default();
+        assert!(!t.get_bool("number"));
+        t.apply_set("number").unwrap();
+        assert!(t.get_bool("number"));
+        t.apply_set("nonu").unwrap();
+        assert!(!t.get_bool("number"));
+    }
+
+    #[test]
+    fn number_assignment_and_query() {
+        let mut t = OptionTable::default();
+        t.apply_set("shiftwidth=4").unwrap();
+        assert_eq!(t.get_number("sw"), 4);
+        assert_eq!(
+            t.apply_set("shiftwidth?").unwrap(),
+            Some("shiftwidth=4".into())
+        );
+        t.apply_set("sw+=2").unwrap();
+        assert_eq!(t.get_number("shiftwidth"), 6);
+        t.apply_set("sw&").unwrap();
+        assert_eq!(t.get_number("shiftwidth"), 8);
+        assert_eq!(
+            t.apply_set("ts=0"),
+            Err(OptionError::MustBePositive("ts=0".into()))
+        );
+        t.apply_set("sts=-1").unwrap();
+        assert_eq!(t.get_number("softtabstop"), -1);
+    }
+
+    #[test]
+    fn reseed_moves_options_left_at_their_default() {
+        let mut t = OptionTable::default();
+        t.apply_set("sw=2").unwrap();
+        t.take_changes();
+        let mut seeded = OptionTable::default();
+        seeded
+            .set_default("shiftwidth", OptionValue::Number(4))
+            .unwrap();
+        seeded
+            .set_default("timeoutlen", OptionValue::Number(300))
+            .unwrap();
+        t.reseed(&seeded);
+        assert_eq!(t.get_number("shiftwidth"), 2);
+        assert_eq!(t.get_number("timeoutlen"), 300);
+        let changes = t.take_changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].name, "timeoutlen");
+        // `&` now goes back to the reloaded default.
+        t.apply_set("sw&").unwrap();
+        assert_eq!(t.get_number("shiftwidth"), 4);
+    }
+
+    #[test]
+    fn boolean_query_uses_no_prefix() {
+        let mut t = OptionTable::default();
+        t.apply_set("noignorecase").unwrap();
+        assert_eq!(t.apply_set("ic?").unwrap(), Some("noignorecase".into()));
+        t.apply_set("invic").unwrap();
+        assert_eq!(t.apply_set("ic?").unwrap(), Some("ignorecase".into()));
+    }
+
+    #[test]
+    fn errors_use_vim_numbering() {
+        let mut t = OptionTable::default();
+        assert_eq!(
+            t.apply_set("bogus").unwrap_err().to_string(),
+            "E518: Unknown option: bogus"
+        );
+        assert_eq!(
+            t.apply_set("sw=x").unwrap_err().to_string(),
+            "E521: Number required after =: sw=x"
+        );
+        assert_eq!(
+            t.apply_set("number=1").unwrap_err().to_string(),
+            "E474: Invalid argument: number=1"
+        );
+        assert_eq!(
+            t.apply_set("sw-=99").unwrap_err().to_string(),
+            "E487: Argument must be positive: sw-=99"
+        );
+        assert_eq!(
+            t.apply_set("lcs=tab:>").unwrap_err().to_string(),
+            "E474: Invalid argument: lcs=tab:>"
+        );
+        t.apply_set("lcs+=,trail:-").unwrap();
+        assert_eq!(t.get_string("listchars"), "eol:$,trail:-");
+        assert_eq!(
+            t.apply_set("cc=80,+1").unwrap_err().to_string(),
+            "E474: Invalid argument: cc=80,+1"
+        );
+        t.apply_set("cc=100,80,80").unwrap();
+        assert_eq!(
+            color_columns(t.get_string("colorcolumn")),
+            Some(vec![80, 100])
+        );
+        assert_eq!(mouse_scroll_lines(t.get_string("mousescroll")), Some(3));
+        assert!(t.apply_set("mousescroll=ver:x").is_err());
+        assert!(t.apply_set("mousescroll=up:1").is_err());
+        t.apply_set("mousescroll=hor:2").unwrap();
+        assert_eq!(mouse_scroll_lines(t.get_string("mousescroll")), Some(0));
+    }
+
+    #[test]
+    fn changes_are_queued_only_on_effective_mutation() {
+        let mut t = OptionTable::default();
+        t.apply_set("wrap").unwrap(); // already on
+        assert!(!t.has_pending_changes());
+        t.apply_set("nowrap timeoutlen=200").unwrap();
+        let changes = t.take_changes();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].name, "wrap");
+        assert_eq!(changes[0].effect, OptionEffect::Render);
+        assert_eq!(changes[1].name, "timeoutlen");
+        assert_eq!(changes[1].new, OptionValue::Number(200));
+        assert!(t.take_changes().is_empty());
+    }
+
+    #[test]
+    fn config_defaults_are_reset_targets() {
+        let mut t = OptionTable::default();
+        t.set_default("shiftwidth", OptionValue::Number(2)).unwrap();
+        assert!(!t.has_pending_changes());
+        t.apply_set("sw=6").unwrap();
+        t.apply_set("sw&").unwrap();
+        assert_eq!(t.get_number("shiftwidth"), 2);
+        assert!(t.set_default("number", OptionValue::Number(1)).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+//! Color schemes: named highlight groups mapped to colors and attributes.
+//!
+//! A scheme is a TOML file whose tables are group names:
+//!
+//! ```toml
+//! [Normal]
+//! fg = "#d0d0d0"
+//! bg = "#1c1c1c"
+//!
+//! [Keyword]
+//! fg = "magenta"
+//! bold = true
+//! ```
+//!
+//! Colors are `#rrggbb`, an xterm palette index (`0..=255`) or one of the
+//! sixteen ANSI names (`red`, `brightred`, ...). Groups are either UI groups
+//! (`Normal`, `StatusLine`, `Visual`, `Search`, `CursorLine`, `CursorColumn`,
+//! `ColorColumn`, `Whitespace`, `Folded`, `DiagnosticUnderlineError` /
+//! `Warn` / `Info` / `Hint`, `DiagnosticVirtualTextError` / `Warn` / `Info`
+//! / `Hint`, `GitBlame`, `DiffAdd`, `DiffChange`, `DiffDelete`) or syntax
+//! classes named like `core_syntax::HighlightClass` (`Keyword`, `Comment`, ...); unknown groups are kept but ignored by the renderer. A
+//! group absent from the scheme keeps the terminal's default look. `sp` is
+//! the underline color (Vim's `guisp`), which terminals without colored
+//! underlines ignore.
+//!
+//! `:colorscheme {name}` and the top-level `colorscheme` config key load
+//! `colors/{name}.toml` from the working directory, then from the platform
+//! config dir (`oxidized/colors`). `default` is built in and reproduces the
+//! basic ANSI colors used before schemes existed.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the built-in scheme.
+pub const DEFAULT_THEME: &str = "default";
+
+/// A color as written in a scheme. Translation to SGR parameters (truecolor,
+/// or the 256-color or 16-color fallback) belongs to the renderer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "ColorValue")]
+pub enum Color {
+    /// xterm palette index; `0..16` are the ANSI colors.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+impl Color {
+    pub fn parse(s: &str) -> Option<Color> {
+        let s = s.trim().to_ascii_lowercase();
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 || !hex.is_ascii() {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        if let Ok(index) = s.parse::<u8>() {
+            return Some(Color::Indexed(index));
+        }
+        let (bright, base) = match s.strip_prefix("bright") {
+            Some(base) => (8, base),
+            None => (0, s.as_str()),
+        };
+        if base == "gray" || base == "grey" {
+            // `gray` is bright black; `brightgray` has no ANSI slot.
+            return (bright == 0).then_some(Color::Indexed(8));
+        }
+        ANSI_NAMES
+            .iter()
+            .position(|n| *n == base)
+            .map(|i| Color::Indexed(i as u8 + bright))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorValue {
+    Index(u8),
+    Text(String),
+}
+
+impl TryFrom<ColorValue> for Color {
+    type Error = String;
+
+    fn try_from(value: ColorValue) -> Result<Self, Self::Error> {
+        match value {
+            ColorValue::Index(i) => Ok(Color::Indexed(i)),
+            ColorValue::Text(s) => Color::parse(&s).ok_or_else(|| format!("invalid color: {s}")),
+        }
+    }
+}
+
+/// Colors and attributes of one highlight group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupStyle {
+    #[serde(default)]
+    pub fg: Option<Color>,
+    #[serde(default)]
+    pub bg: Option<Color>,
+    #[serde(default)]
+    pub sp: Option<Color>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub reverse: bool,
+}
+
+impl GroupStyle {
+    pub const fn fg(color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            bg: None,
+            sp: None,
+            bold: false,
+            italic: false,
+            underline: false,
+            reverse: false,
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == GroupStyle::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThemeError {
+    NotFound(String),
+    Invalid { name: String, message: String },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::NotFound(name) => write!(f, "E185: Cannot find color scheme '{name}'"),
+            ThemeError::Invalid { name, message } => {
+                write!(f, "E185: Invalid color scheme '{name}': {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub groups: BTreeMap<String, GroupStyle>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let ansi = |i| GroupStyle::fg(Color::Indexed(i));
+        let shade = GroupStyle {
+            bg: Some(Color::Indexed(236)),
+            ..GroupStyle::default()
+        };
+        let underlined = |i| GroupStyle {
+            sp: Some(Color::Indexed(i)),
+            underline: true,
+            ..GroupStyle::default()
+        };
+        let groups = [
+            ("Keyword", ansi(5)),
+            ("Label", ansi(5)),
+            ("Function", ansi(4)),
+            ("Type", ansi(3)),
+            ("Attribute", ansi(3)),
+            ("String", ansi(2)),
+            ("Escape", ansi(6)),
+            ("Constant", ansi(6)),
+            ("Number", ansi(6)),
+            ("Property", ansi(6)),
+            ("Comment", ansi(8)),
+            (
+                "Heading",
+                GroupStyle {
+                    bold: true,
+                    ..ansi(4)
+                },
+            ),
+            (
+                "Link",
+                GroupStyle {
+                    underline: true,
+                    ..ansi(4)
+                },
+            ),
+            (
+                "Visual",
+                GroupStyle {
+                    reverse: true,
+                    ..GroupStyle::default()
+                },
+            ),
+            (
+                "Search",
+                GroupStyle {
+                    fg: Some(Color::Indexed(0)),
+                    bg: Some(Color::Indexed(3)),
+                    ..GroupStyle::default()
+                },
+            ),
+            ("CursorLine", shade),
+            ("CursorColumn", shade),
+            (
+                "ColorColumn",
+                GroupStyle {
+                    bg: Some(Color::Indexed(52)),
+                    ..GroupStyle::default()
+                },
+            ),
+            ("Whitespace", ansi(8)),
+            ("Folded", ansi(6)),
+            ("DiagnosticUnderlineError", underlined(1)),
+            ("DiagnosticUnderlineWarn", underlined(3)),
+            ("DiagnosticUnderlineInfo", underlined(4)),
+            ("DiagnosticUnderlineHint", underlined(6)),
+            ("DiagnosticVirtualTextError", ansi(1)),
+            ("DiagnosticVirtualTextWarn", ansi(3)),
+            ("DiagnosticVirtualTextInfo", ansi(4)),
+            ("DiagnosticVirtualTextHint", ansi(6)),
+            ("GitBlame", ansi(8)),
+            (
+                "DiffAdd",
+                GroupStyle {
+                    bg: Some(Color::Indexed(22)),
+                    ..GroupStyle::default()
+                },
+            ),
+            (
+                "DiffChange",
+                GroupStyle {
+                    bg: Some(Color::Indexed(17)),
+                    ..GroupStyle::default()
+                },
+            ),
+            ("DiffDelete", ansi(1)),
+        ];
+        Self {
+            name: DEFAULT_THEME.to_string(),
+            groups: groups
+                .into_iter()
+                .map(|(group, style)| (group.to_string(), style))
+                .collect(),
+        }
+    }
+}
+
+impl Theme {
+    /// Parse a scheme file's contents.
+    pub fn from_toml(name: &str, content: &str) -> Result<Theme, ThemeError> {
+        let groups = toml::from_str::<BTreeMap<String, GroupStyle>>(content).map_err(|e| {
+            ThemeError::Invalid {
+                name: name.to_string(),
+                message: e.message().to_string(),
+            }
+        })?;
+        Ok(Theme {
+            name: name.to_string(),
+            groups,
+        })
+    }
+
+    /// Style of `group`, if the scheme sets one.
+    pub fn group(&self, group: &str) -> Option<&GroupStyle> {
+        self.groups.get(group)
+    }
+
+    /// Load scheme `name` from the default search directories.
+    pub fn load(name: &str) -> Result<Theme, ThemeError> {
+        Self::load_from(name, &search_dirs())
+    }
+
+    /// Load scheme `name` from the first of `dirs` holding `{name}.toml`.
+    pub fn load_from(name: &str, dirs: &[PathBuf]) -> Result<Theme, ThemeError> {
+        if name == DEFAULT_THEME {
+            return Ok(Theme::default());
+        }
+        let not_found = || ThemeError::NotFound(name.to_string());
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(not_found());
+        }
+        let file = format!("{name}.toml");
+        let content = dirs
+            .iter()
+            .map(|dir| dir.join(&file))
+            .find_map(|path| std::fs::read_to_string(path).ok())
+            .ok_or_else(not_found)?;
+        Self::from_toml(name, &content)
+    }
+}
+
+/// Directories searched for `{name}.toml`, in order.
+pub fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![Path::new("colors").to_path_buf()];
+    if let Some(dir) = dirs::config_dir() {
+        dirs.push(dir.join("oxidized").join("colors"));
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_parse_hex_indices_and_names() {
+        assert_eq!(Color::parse("#FF8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(Color::parse("208"), Some(Color::Indexed(208)));
+        assert_eq!(Color::parse("magenta"), Some(Color::Indexed(5)));
+        assert_eq!(Color::parse("BrightRed"), Some(Color::Indexed(9)));
+        assert_eq!(Color::parse("gray"), Some(Color::Indexed(8)));
+        assert_eq!(Color::parse("#12345"), None);
+        assert_eq!(Color::parse("mauve"), None);
+    }
+
+    #[test]
+    fn schemes_load_from_search_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("night.toml"),
+            "[Normal]\nfg = \"#d0d0d0\"\nbg = 235\n\n[Keyword]\nfg = \"red\"\nbold = true\n",
+        )
+        .unwrap();
+        let dirs = [PathBuf::from("/nonexistent"), dir.path().to_path_buf()];
+        let theme = Theme::load_from("night", &dirs).unwrap();
+        assert_eq!(theme.name, "night");
+        let normal = theme.group("Normal").unwrap();
+        assert_eq!(normal.fg, Some(Color::Rgb(0xd0, 0xd0, 0xd0)));
+        assert_eq!(normal.bg, Some(Color::Indexed(235)));
+        assert!(theme.group("Keyword").unwrap().bold);
+
+        assert_eq!(
+            Theme::load_from("default", &dirs).unwrap(),
+            Theme::default()
+        );
+        assert_eq!(
+            Theme::load_from("missing", &dirs).unwrap_err().to_string(),
+            "E185: Cannot find color scheme 'missing'"
+        );
+        assert!(Theme::load_from("../night", &dirs).is_err());
+
+        std::fs::write(dir.path().join("bad.toml"), "[Normal]\nfg = \"mauve\"\n").unwrap();
+        assert!(matches!(
+            Theme::load_from("bad", &dirs),
+            Err(ThemeError::Invalid { .. })
+        ));
+    }
+}
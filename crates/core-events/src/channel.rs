@@ -0,0 +1,211 @@
+//! The runtime's event channel: an input lane and a background lane.
+//!
+//! Keys, mouse, paste and resize events travel on the input lane; ticks,
+//! shell and grep output, git probes, status segments, RPC requests and
+//! language server messages on the background lane. Both are bounded, so a
+//! background source that floods its lane blocks on its own sends and never
+//! on the input's. `EventReceiver::recv` always takes a waiting input event
+//! first: however much background work is queued, the next key is handled
+//! as soon as the current event is done.
+//!
+//! Sources still get a plain `Sender<Event>`: `EventChannels::lane` picks
+//! the one for their `AsyncEventSource::priority`.
+//!
+//! `EventReceiver::next_input_if` lets the runtime look at the next queued
+//! input event and take it only if it is what it wants; an event it leaves
+//! stays first in line.
+
+use crate::Event;
+use std::sync::atomic::Ordering;
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TryRecvError};
+
+/// Capacity of the background lane.
+pub const BACKGROUND_CHANNEL_CAP: usize = 1024;
+
+/// Which lane a source's events travel on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventPriority {
+    /// Key, mouse, paste and resize events (and their replay).
+    High,
+    #[default]
+    Low,
+}
+
+/// Sending halves of both lanes.
+#[derive(Debug, Clone)]
+pub struct EventChannels {
+    pub input: Sender<Event>,
+    pub background: Sender<Event>,
+}
+
+impl EventChannels {
+    /// The lane for events of `priority`.
+    pub fn lane(&self, priority: EventPriority) -> &Sender<Event> {
+        match priority {
+            EventPriority::High => &self.input,
+            EventPriority::Low => &self.background,
+        }
+    }
+}
+
+/// Receiving halves, drained with input preference.
+#[derive(Debug)]
+pub struct EventReceiver {
+    input: Receiver<Event>,
+    background: Receiver<Event>,
+    /// Input event looked at by `next_input_if` and left there.
+    peeked: Option<Event>,
+}
+
+/// Both lanes: the input lane holds `EVENT_CHANNEL_CAP` events, the
+/// background lane `BACKGROUND_CHANNEL_CAP`.
+pub fn event_channel() -> (EventChannels, EventReceiver) {
+    with_capacity(crate::EVENT_CHANNEL_CAP, BACKGROUND_CHANNEL_CAP)
+}
+
+/// Both lanes with the given capacities (tests use small ones).
+pub fn with_capacity(input: usize, background: usize) -> (EventChannels, EventReceiver) {
+    let (input_tx, input_rx) = mpsc::channel(input);
+    let (background_tx, background_rx) = mpsc::channel(background);
+    (
+        EventChannels {
+            input: input_tx,
+            background: background_tx,
+        },
+        EventReceiver {
+            input: input_rx,
+            background: background_rx,
+            peeked: None,
+        },
+    )
+}
+
+impl EventReceiver {
+    /// The next event, an input one if any is waiting. `None` once both
+    /// lanes are closed and drained.
+    pub async fn recv(&mut self) -> Option<Event> {
+        if let Some(event) = self.peeked.take() {
+            return Some(event);
+        }
+        let mut input_open = true;
+        let mut background_open = true;
+        loop {
+            tokio::select! {
+                biased;
+                event = self.input.recv(), if input_open => match event {
+                    Some(event) => {
+                        if !self.background.is_empty() {
+                            crate::INPUT_PREFERRED.fetch_add(1, Ordering::Relaxed);
+                        }
+                        return Some(event);
+                    }
+                    None => input_open = false,
+                },
+                event = self.background.recv(), if background_open => match event {
+                    Some(event) => return Some(event),
+                    None => background_open = false,
+                },
+                else => return None,
+            }
+        }
+    }
+
+    /// The next event without waiting, input first.
+    pub fn try_recv(&mut self) -> Result<Event, TryRecvError> {
+        if let Some(event) = self.peeked.take() {
+            return Ok(event);
+        }
+        match self.input.try_recv() {
+            Ok(event) => Ok(event),
+            Err(input) => {
+                self.background
+                    .try_recv()
+                    .map_err(|background| match (input, background) {
+                        (TryRecvError::Disconnected, TryRecvError::Disconnected) => {
+                            TryRecvError::Disconnected
+                        }
+                        _ => TryRecvError::Empty,
+                    })
+            }
+        }
+    }
+
+    /// True when an input event is waiting.
+    pub fn has_input(&self) -> bool {
+        self.peeked.is_some() || !self.input.is_empty()
+    }
+
+    /// True when neither lane holds an event.
+    pub fn is_empty(&self) -> bool {
+        !self.has_input() && self.background.is_empty()
+    }
+
+    /// The next input event if one is already queued and `take` accepts
+    /// it. Without waiting; a refused event is the next one received.
+    pub fn next_input_if(&mut self, take: impl FnOnce(&Event) -> bool) -> Option<Event> {
+        if self.peeked.is_none() {
+            self.peeked = self.input.try_recv().ok();
+        }
+        self.peeked.take_if(|event| take(event))
+    }
+
+    /// Stop accepting events on both lanes; queued ones can still be read.
+    pub fn close(&mut self) {
+        self.input.close();
+        self.background.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InputEvent, KeyEventExt, KeyToken};
+
+    fn key(c: char) -> Event {
+        Event::Input(InputEvent::KeyPress(KeyEventExt::new(KeyToken::Char(c))))
+    }
+
+    #[tokio::test]
+    async fn input_overtakes_queued_background_events() {
+        let (tx, mut rx) = with_capacity(4, 4);
+        for _ in 0..4 {
+            tx.background.send(Event::Tick).await.unwrap();
+        }
+        // The background lane is full; input still gets through, first.
+        tx.input.send(key('a')).await.unwrap();
+        assert!(rx.has_input());
+        assert!(matches!(rx.recv().await, Some(Event::Input(_))));
+        assert!(matches!(rx.try_recv(), Ok(Event::Tick)));
+        tx.input.send(key('b')).await.unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Event::Input(_))));
+
+        // Closed lanes end `recv` once the other one is drained too.
+        drop(tx);
+        let mut ticks = 0;
+        while let Some(event) = rx.recv().await {
+            assert!(matches!(event, Event::Tick));
+            ticks += 1;
+        }
+        assert_eq!(ticks, 3);
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn refused_input_stays_first_in_line() {
+        let (tx, mut rx) = with_capacity(4, 4);
+        assert!(rx.next_input_if(|_| true).is_none());
+        tx.background.send(Event::Tick).await.unwrap();
+        tx.input.send(key('j')).await.unwrap();
+        tx.input.send(key('k')).await.unwrap();
+        let is_j = |event: &Event| matches!(event, Event::Input(InputEvent::KeyPress(k)) if k.token == KeyToken::Char('j'));
+        assert!(rx.next_input_if(is_j).is_some());
+        assert!(rx.next_input_if(is_j).is_none());
+        assert!(rx.has_input());
+        assert!(matches!(
+            rx.recv().await,
+            Some(Event::Input(InputEvent::KeyPress(k))) if k.token == KeyToken::Char('k')
+        ));
+        assert!(!rx.has_input());
+        assert!(matches!(rx.try_recv(), Ok(Event::Tick)));
+    }
+}
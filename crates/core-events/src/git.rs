@@ -0,0 +1,211 @@
+//! Git repository probe for the status line, and `git blame` for `:blame`.
+//!
+//! A one-shot `AsyncEventSource` like `ShellCommandSource`: it runs
+//! `git status --porcelain=v2 --branch` in the active file's directory and
+//! emits a single `Event::GitInfo` carrying the branch and whether tracked
+//! files are modified. Untracked files are not listed (`-uno`), which keeps
+//! the probe cheap in large worktrees. A directory outside a repository, or
+//! a missing `git`, reports no branch.
+//!
+//! `GitBlameSource` is the same kind of source for
+//! `git blame --porcelain`: it emits one `Event::GitBlame` with the raw
+//! output, which `core_state::git` parses, or git's error message.
+
+use crate::{AsyncEventSource, Event};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+/// Probe answer for `dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitInfo {
+    pub dir: PathBuf,
+    /// Branch name, or the abbreviated commit on a detached HEAD; `None`
+    /// outside a repository.
+    pub branch: Option<String>,
+    pub dirty: bool,
+}
+
+/// One-shot source probing the repository containing `dir`.
+pub struct GitInfoSource {
+    dir: PathBuf,
+}
+
+impl GitInfoSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Run the probe. Never fails; any error reads as "not a repository".
+    pub async fn run(self) -> GitInfo {
+        let out = tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(["status", "--porcelain=v2", "--branch", "-uno"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await;
+        let (branch, dirty) = match out {
+            Ok(out) if out.status.success() => parse_status(&String::from_utf8_lossy(&out.stdout)),
+            _ => (None, false),
+        };
+        GitInfo {
+            dir: self.dir,
+            branch,
+            dirty,
+        }
+    }
+}
+
+/// Branch and dirty flag from `git status --porcelain=v2 --branch` output:
+/// `# branch.*` headers, then one line per changed entry.
+pub fn parse_status(out: &str) -> (Option<String>, bool) {
+    let mut oid = None;
+    let mut head = None;
+    let mut dirty = false;
+    for line in out.lines() {
+        if let Some(v) = line.strip_prefix("# branch.oid ") {
+            oid = Some(v);
+        } else if let Some(v) = line.strip_prefix("# branch.head ") {
+            head = Some(v);
+        } else if !line.starts_with('#') && !line.is_empty() {
+            dirty = true;
+        }
+    }
+    let branch = match head {
+        Some("(detached)") => oid.map(|o| o.chars().take(7).collect()),
+        Some(name) => Some(name.to_string()),
+        None => None,
+    };
+    (branch, dirty)
+}
+
+/// `git blame --porcelain` output for `path`, or the first line git wrote
+/// to stderr (an untracked file, no repository, no `git`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitBlame {
+    pub path: PathBuf,
+    pub output: Result<String, String>,
+}
+
+/// One-shot source blaming the file `path` as committed.
+pub struct GitBlameSource {
+    path: PathBuf,
+}
+
+impl GitBlameSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub async fn run(self) -> GitBlame {
+        let dir = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let name = self.path.file_name().unwrap_or(self.path.as_os_str());
+        let out = tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["blame", "--porcelain", "--"])
+            .arg(name)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output()
+            .await;
+        let output = match out {
+            Ok(out) if out.status.success() => {
+                Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+            }
+            Ok(out) => Err(String::from_utf8_lossy(&out.stderr)
+                .lines()
+                .next()
+                .unwrap_or("git blame failed")
+                .to_string()),
+            Err(e) => Err(format!("git: {e}")),
+        };
+        GitBlame {
+            path: self.path,
+            output,
+        }
+    }
+}
+
+impl AsyncEventSource for GitBlameSource {
+    fn name(&self) -> &'static str {
+        "git-blame"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let blame = self.run().await;
+            tracing::debug!(
+                target: "runtime.git",
+                path = %blame.path.display(),
+                ok = blame.output.is_ok(),
+                "git_blame_finished"
+            );
+            let _ = tx.send(Event::GitBlame(blame)).await;
+        })
+    }
+}
+
+impl AsyncEventSource for GitInfoSource {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let info = self.run().await;
+            tracing::debug!(
+                target: "runtime.git",
+                dir = %info.dir.display(),
+                branch = ?info.branch,
+                dirty = info.dirty,
+                "git_probe_finished"
+            );
+            let _ = tx.send(Event::GitInfo(info)).await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_branch_detached_head_and_changes() {
+        let clean =
+            "# branch.oid 1234567890abcdef\n# branch.head main\n# branch.upstream origin/main\n";
+        assert_eq!(parse_status(clean), (Some("main".into()), false));
+        let dirty = "# branch.oid 1234567890abcdef\n# branch.head (detached)\n1 .M N... 100644 100644 100644 a b src/lib.rs\n";
+        assert_eq!(parse_status(dirty), (Some("1234567".into()), true));
+        // A fresh repository has a branch but no commit yet.
+        let initial = "# branch.oid (initial)\n# branch.head main\n";
+        assert_eq!(parse_status(initial), (Some("main".into()), false));
+        assert_eq!(parse_status(""), (None, false));
+    }
+
+    #[tokio::test]
+    async fn missing_directory_reports_no_branch() {
+        let dir = std::env::temp_dir().join(format!("ox-git-missing-{}", std::process::id()));
+        let info = GitInfoSource::new(&dir).run().await;
+        assert_eq!(info.dir, dir);
+        assert_eq!(info.branch, None);
+        assert!(!info.dirty);
+    }
+
+    #[tokio::test]
+    async fn blame_outside_a_repository_reports_the_error() {
+        let dir = std::env::temp_dir().join(format!("ox-blame-missing-{}", std::process::id()));
+        let blame = GitBlameSource::new(dir.join("file.rs")).run().await;
+        assert!(blame.output.is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! `:grep` search event source.
+//!
+//! Unlike `ShellCommandSource`, which reports once the command exits, a
+//! `GrepSource` streams: it runs `'grepprg'` through the platform shell and
+//! sends stdout back in batches of lines as `Event::GrepOutput`, so results
+//! fill the list while a long search is still running. A batch goes out
+//! once it holds `GREP_BATCH_LINES` lines or its first line is
+//! `GREP_BATCH_DELAY` old; the last event carries the exit status. Dropping
+//! the task (a newer `:grep`) kills the search.
+
+use crate::{AsyncEventSource, Event};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Most lines sent in one `Event::GrepOutput`.
+pub const GREP_BATCH_LINES: usize = 256;
+/// Longest a line waits before its batch is sent.
+pub const GREP_BATCH_DELAY: Duration = Duration::from_millis(50);
+
+/// How a finished search exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepDone {
+    /// Exit code (`None` when terminated by a signal or never started).
+    pub status: Option<i32>,
+    pub stderr: String,
+    /// Spawn / IO failure description.
+    pub error: Option<String>,
+}
+
+/// A batch of output lines from search `id`; `done` is set on the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepOutput {
+    pub id: u64,
+    pub lines: Vec<String>,
+    pub done: Option<GrepDone>,
+}
+
+/// Streaming source running `command` via `shell -c` (`/C` on Windows).
+pub struct GrepSource {
+    id: u64,
+    shell: String,
+    command: String,
+}
+
+impl GrepSource {
+    pub fn new(id: u64, shell: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            id,
+            shell: shell.into(),
+            command: command.into(),
+        }
+    }
+
+    /// Run to completion, sending batches to `tx`. Stops early when the
+    /// receiver is gone.
+    pub async fn run(self, tx: Sender<Event>) {
+        let flag = if cfg!(windows) { "/C" } else { "-c" };
+        let child = tokio::process::Command::new(&self.shell)
+            .arg(flag)
+            .arg(&self.command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                self.send(&tx, Vec::new(), Some(failed(e.to_string())))
+                    .await;
+                return;
+            }
+        };
+        let stderr = child.stderr.take().map(|mut pipe| {
+            tokio::spawn(async move {
+                let mut text = String::new();
+                let _ = pipe.read_to_string(&mut text).await;
+                text
+            })
+        });
+        let Some(stdout) = child.stdout.take() else {
+            return;
+        };
+        let mut lines = BufReader::new(stdout).lines();
+        let mut batch = Vec::new();
+        let mut deadline = None;
+        let mut sent = 0usize;
+        loop {
+            let next = match deadline {
+                // `next_line` is cancel safe: a timed out read loses nothing.
+                Some(at) => tokio::time::timeout_at(at, lines.next_line()).await.ok(),
+                None => Some(lines.next_line().await),
+            };
+            match next {
+                Some(Ok(Some(line))) => {
+                    batch.push(line);
+                    deadline.get_or_insert_with(|| Instant::now() + GREP_BATCH_DELAY);
+                    if batch.len() < GREP_BATCH_LINES {
+                        continue;
+                    }
+                }
+                Some(Ok(None) | Err(_)) => break,
+                // The oldest line waited long enough.
+                None => {}
+            }
+            sent += batch.len();
+            deadline = None;
+            if !self.send(&tx, std::mem::take(&mut batch), None).await {
+                return;
+            }
+        }
+        sent += batch.len();
+        let done = match child.wait().await {
+            Ok(status) => GrepDone {
+                status: status.code(),
+                stderr: match stderr {
+                    Some(task) => task.await.unwrap_or_default(),
+                    None => String::new(),
+                },
+                error: None,
+            },
+            Err(e) => failed(e.to_string()),
+        };
+        tracing::debug!(target: "runtime.grep", id = self.id, lines = sent, status = ?done.status, error = ?done.error, "grep_finished");
+        self.send(&tx, batch, Some(done)).await;
+    }
+
+    async fn send(&self, tx: &Sender<Event>, lines: Vec<String>, done: Option<GrepDone>) -> bool {
+        let output = GrepOutput {
+            id: self.id,
+            lines,
+            done,
+        };
+        tx.send(Event::GrepOutput(output)).await.is_ok()
+    }
+}
+
+fn failed(error: String) -> GrepDone {
+    GrepDone {
+        status: None,
+        stderr: String::new(),
+        error: Some(error),
+    }
+}
+
+impl AsyncEventSource for GrepSource {
+    fn name(&self) -> &'static str {
+        "grep"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(self.run(tx))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn streams_lines_then_reports_the_exit_status() {
+        let (tx, mut rx) = mpsc::channel(8);
+        GrepSource::new(4, "sh", "printf 'a:1:x\\nb:2:y\\n'; echo oops >&2; exit 1")
+            .run(tx)
+            .await;
+        let mut lines = Vec::new();
+        let mut done = None;
+        while let Some(Event::GrepOutput(out)) = rx.recv().await {
+            assert_eq!(out.id, 4);
+            assert!(done.is_none(), "output after the last batch");
+            lines.extend(out.lines);
+            done = out.done;
+        }
+        assert_eq!(lines, ["a:1:x", "b:2:y"]);
+        let done = done.expect("final batch");
+        assert_eq!(done.status, Some(1));
+        assert_eq!(done.stderr, "oops\n");
+    }
+}
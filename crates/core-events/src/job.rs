@@ -0,0 +1,184 @@
+//! Background job event source (`:make`, `:job`).
+//!
+//! A `JobSource` runs a command through the platform shell like a
+//! `GrepSource`, but streams stdout and stderr alike: compilers report
+//! errors on stderr, and a job's output buffer shows both as they arrive.
+//! Lines are batched the same way (`JOB_BATCH_LINES` / `JOB_BATCH_DELAY`)
+//! into `Event::JobOutput`; the last event carries the exit status.
+//! Aborting the task (`:jobstop`, a newer `:make`) kills the process.
+
+use crate::{AsyncEventSource, Event};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Most lines sent in one `Event::JobOutput`.
+pub const JOB_BATCH_LINES: usize = 256;
+/// Longest a line waits before its batch is sent.
+pub const JOB_BATCH_DELAY: Duration = Duration::from_millis(50);
+
+/// How a finished job exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobDone {
+    /// Exit code (`None` when terminated by a signal or never started).
+    pub status: Option<i32>,
+    /// Spawn / IO failure description.
+    pub error: Option<String>,
+}
+
+/// A batch of output lines of job `id`, stdout and stderr interleaved as
+/// read; `done` is set on the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobOutput {
+    pub id: u64,
+    pub lines: Vec<String>,
+    pub done: Option<JobDone>,
+}
+
+/// Streaming source running `command` via `shell -c` (`/C` on Windows).
+pub struct JobSource {
+    id: u64,
+    shell: String,
+    command: String,
+}
+
+impl JobSource {
+    pub fn new(id: u64, shell: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            id,
+            shell: shell.into(),
+            command: command.into(),
+        }
+    }
+
+    /// Run to completion, sending batches to `tx`. Stops early when the
+    /// receiver is gone.
+    pub async fn run(self, tx: Sender<Event>) {
+        let flag = if cfg!(windows) { "/C" } else { "-c" };
+        let child = tokio::process::Command::new(&self.shell)
+            .arg(flag)
+            .arg(&self.command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                let done = JobDone {
+                    status: None,
+                    error: Some(e.to_string()),
+                };
+                self.send(&tx, Vec::new(), Some(done)).await;
+                return;
+            }
+        };
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return;
+        };
+        let mut out = BufReader::new(stdout).lines();
+        let mut err = BufReader::new(stderr).lines();
+        let (mut out_open, mut err_open) = (true, true);
+        let mut batch = Vec::new();
+        let mut deadline = None;
+        let mut sent = 0usize;
+        while out_open || err_open {
+            // `next_line` is cancel safe: the read that loses the race, or
+            // times out, loses nothing.
+            let read = async {
+                tokio::select! {
+                    line = out.next_line(), if out_open => (true, line),
+                    line = err.next_line(), if err_open => (false, line),
+                }
+            };
+            let next = match deadline {
+                Some(at) => tokio::time::timeout_at(at, read).await.ok(),
+                None => Some(read.await),
+            };
+            match next {
+                Some((_, Ok(Some(line)))) => {
+                    batch.push(line);
+                    deadline.get_or_insert_with(|| Instant::now() + JOB_BATCH_DELAY);
+                    if batch.len() < JOB_BATCH_LINES {
+                        continue;
+                    }
+                }
+                Some((stdout, Ok(None) | Err(_))) => {
+                    match stdout {
+                        true => out_open = false,
+                        false => err_open = false,
+                    }
+                    continue;
+                }
+                // The oldest line waited long enough.
+                None => {}
+            }
+            sent += batch.len();
+            deadline = None;
+            if !self.send(&tx, std::mem::take(&mut batch), None).await {
+                return;
+            }
+        }
+        sent += batch.len();
+        let done = match child.wait().await {
+            Ok(status) => JobDone {
+                status: status.code(),
+                error: None,
+            },
+            Err(e) => JobDone {
+                status: None,
+                error: Some(e.to_string()),
+            },
+        };
+        tracing::debug!(target: "runtime.job", id = self.id, lines = sent, status = ?done.status, error = ?done.error, "job_finished");
+        self.send(&tx, batch, Some(done)).await;
+    }
+
+    async fn send(&self, tx: &Sender<Event>, lines: Vec<String>, done: Option<JobDone>) -> bool {
+        let output = JobOutput {
+            id: self.id,
+            lines,
+            done,
+        };
+        tx.send(Event::JobOutput(output)).await.is_ok()
+    }
+}
+
+impl AsyncEventSource for JobSource {
+    fn name(&self) -> &'static str {
+        "job"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(self.run(tx))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn streams_stdout_and_stderr_then_the_exit_status() {
+        let (tx, mut rx) = mpsc::channel(8);
+        JobSource::new(2, "sh", "echo out; echo err >&2; exit 3")
+            .run(tx)
+            .await;
+        let mut lines = Vec::new();
+        let mut done = None;
+        while let Some(Event::JobOutput(out)) = rx.recv().await {
+            assert_eq!(out.id, 2);
+            assert!(done.is_none(), "output after the last batch");
+            lines.extend(out.lines);
+            done = out.done;
+        }
+        lines.sort();
+        assert_eq!(lines, ["err", "out"]);
+        assert_eq!(done.expect("final batch").status, Some(3));
+    }
+}
@@ -1,6 +1,36 @@
 //! Core event types and channel helpers for Oxidized.
 //! Phase 0 scope: minimal input + control events.
 
+pub mod channel;
+pub mod git;
+pub mod grep;
+pub mod job;
+pub mod lsp;
+pub mod record;
+pub mod rpc;
+pub mod segments;
+pub mod shell;
+pub mod telemetry;
+pub mod timer;
+pub mod watch;
+pub use channel::{
+    BACKGROUND_CHANNEL_CAP, EventChannels, EventPriority, EventReceiver, event_channel,
+};
+pub use git::{GitBlame, GitBlameSource, GitInfo, GitInfoSource};
+pub use grep::{GrepDone, GrepOutput, GrepSource};
+pub use job::{JobDone, JobOutput, JobSource};
+pub use lsp::{LspClient, LspMessage, LspMessageKind, LspServerSource};
+pub use record::{EventRecorder, ReplayEventSource};
+pub use rpc::{RpcHub, RpcRequest, RpcServerSource};
+pub use segments::{
+    DEFAULT_SEGMENT_TIMEOUT, RefreshHint, SegmentContext, SegmentRunner, SegmentUpdate,
+    StatusSegmentProvider,
+};
+pub use shell::{ShellCommandSource, ShellOutput};
+pub use telemetry::TelemetrySource;
+pub use timer::TimerSource;
+pub use watch::{FileWatchHandle, FileWatchSource, WatchSet};
+
 use std::fmt;
 use std::sync::atomic::AtomicU64;
 use std::time::Instant;
@@ -10,14 +40,12 @@ use tokio::task::JoinHandle;
 // -------------------------------------------------------------------------------------------------
 // Channel Policy (Phase 2 Step 16 – Activated)
 // -------------------------------------------------------------------------------------------------
-// The event loop now uses a bounded mpsc channel sized by `EVENT_CHANNEL_CAP` to provide memory
-// safety and natural producer backpressure. Initial policy: the blocking input thread uses
-// `blocking_send` which will park the thread until space is available rather than dropping events.
-// Rationale: with a single producer (input) and single consumer (main loop) latency remains low and
-// preserving motion / edit fidelity is preferred over lossy drop strategies. Future multi‑producer
-// scenarios (timers, LSP, watchers) may introduce a priority control channel + selective drop of
-// low‑value motion bursts. Telemetry counters record send failures (closed channel) and will later
-// record explicit backpressure timings once multiple producers exist.
+// The event loop reads two bounded mpsc lanes (`channel`): input events on one sized by
+// `EVENT_CHANNEL_CAP`, everything the background sources produce on another, and always takes a
+// waiting input event first. Producers that outrun the loop park on their own lane's backpressure
+// rather than dropping events, so motion / edit fidelity is preserved and a chatty background
+// source (ticks, LSP, watchers, plugins) cannot delay input. Telemetry counters record send
+// failures (closed channel) and how often input overtook queued background events.
 // -------------------------------------------------------------------------------------------------
 pub const EVENT_CHANNEL_CAP: usize = 8192;
 
@@ -42,6 +70,14 @@ pub static ASYNC_INPUT_STOP_SIGNAL: AtomicU64 = AtomicU64::new(0);
 pub static ASYNC_INPUT_STOP_CHANNEL: AtomicU64 = AtomicU64::new(0);
 pub static ASYNC_INPUT_STOP_STREAM: AtomicU64 = AtomicU64::new(0);
 pub static ASYNC_INPUT_STOP_ERROR: AtomicU64 = AtomicU64::new(0);
+// Input events received while background events were queued ahead of them
+pub static INPUT_PREFERRED: AtomicU64 = AtomicU64::new(0);
+// Queued repeats of a motion key folded into the press before them
+pub static MOTIONS_COALESCED: AtomicU64 = AtomicU64::new(0);
+// Resizes replaced by a later one before being laid out
+pub static RESIZES_SUPPRESSED: AtomicU64 = AtomicU64::new(0);
+// User mappings: recursive expansions aborted at `maxmapdepth` (E223)
+pub static MAPPING_EXPANSIONS_ABORTED: AtomicU64 = AtomicU64::new(0);
 
 /// Top-level event enum consumed by the central event loop.
 #[derive(Debug, Clone)]
@@ -52,6 +88,28 @@ pub enum Event {
     /// Periodic monotonic tick (Phase 4 Step 14) used to drive ephemeral expiry
     /// and future lightweight refresh tasks without busy polling.
     Tick,
+    /// Completion of an external command started via `ShellCommandSource`.
+    ShellOutput(ShellOutput),
+    /// Answer of a `GitInfoSource` probe.
+    GitInfo(GitInfo),
+    /// Output of a `GitBlameSource` run.
+    GitBlame(GitBlame),
+    /// A batch of `:grep` results from a `GrepSource`.
+    GrepOutput(GrepOutput),
+    /// A batch of output from a `JobSource` (`:make`, `:job`).
+    JobOutput(JobOutput),
+    /// A file or directory watched by a `FileWatchSource` changed on disk.
+    FileChanged(std::path::PathBuf),
+    /// Timer `id` of a `TimerSource` fired.
+    Timer(u64),
+    /// A `TelemetrySource` asks for a metrics snapshot for the export sink.
+    Telemetry,
+    /// Answer of a `StatusSegmentProvider` run by the `SegmentRunner`.
+    StatusSegment(SegmentUpdate),
+    /// Request or notification from a `RpcServerSource` client.
+    Rpc(RpcRequest),
+    /// Message from a language server started as an `LspServerSource`.
+    Lsp(LspMessage),
     Shutdown,
 }
 
@@ -96,6 +154,11 @@ impl EventHooks for NoopEventHooks {}
 pub trait AsyncEventSource: Send + 'static {
     /// Human-readable stable identifier (used for logging / diagnostics).
     fn name(&self) -> &'static str;
+    /// The lane the source's events travel on (`channel`); only sources of
+    /// user input are `High`.
+    fn priority(&self) -> EventPriority {
+        EventPriority::Low
+    }
     /// Consume self and spawn the background task, returning a JoinHandle. Implementors should
     /// stop when `tx.send(..).await` returns Err (channel closed) or on their own internal stop
     /// condition. They should avoid busy loops by awaiting timers or external IO futures.
@@ -132,7 +195,6 @@ mod tests_async_sources {
     use std::sync::Arc;
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::time::Duration;
-    use tokio::sync::mpsc;
 
     struct MockOnceSource {
         emitted: bool,
@@ -158,7 +220,7 @@ mod tests_async_sources {
 
     #[tokio::test]
     async fn registry_spawns_and_emits() {
-        let (tx, mut rx) = mpsc::channel::<Event>(8);
+        let (tx, mut rx) = channel::with_capacity(8, 8);
         let mut reg = EventSourceRegistry::new();
         reg.register(MockOnceSource::new());
         reg.register(TickEventSource::new(std::time::Duration::from_millis(10)));
@@ -218,7 +280,7 @@ mod tests_async_sources {
 
     #[tokio::test]
     async fn registry_sources_exit_on_channel_drop() {
-        let (tx, rx) = mpsc::channel::<Event>(8);
+        let (tx, rx) = channel::with_capacity(8, 8);
         let mut reg = EventSourceRegistry::new();
         let flag = Arc::new(AtomicBool::new(false));
         reg.register(MockCloseSource::new(flag.clone()));
@@ -265,23 +327,27 @@ impl EventSourceRegistry {
     pub fn register<S: AsyncEventSource>(&mut self, src: S) {
         self.sources.push(Box::new(src));
     }
+    /// Register an already boxed source (plugin host contributions).
+    pub fn register_boxed(&mut self, src: Box<dyn AsyncEventSource>) {
+        self.sources.push(src);
+    }
     /// Spawn all registered sources, returning their JoinHandles. Caller owns the handles (may
-    /// choose to detach or await during shutdown sequence).
-    /// Spawn all registered sources, returning their JoinHandles. The supplied `Sender`
-    /// reference stays owned by the caller; each source receives its own clone so no
-    /// additional strong references linger inside the registry once this call returns.
+    /// choose to detach or await during shutdown sequence). Each source receives its own clone
+    /// of the lane for its `priority`, so no additional strong references linger inside the
+    /// registry once this call returns.
     ///
-    /// Ordering guarantee: call this after constructing the primary runtime channel and
-    /// before the event loop begins consuming events. During shutdown the caller should
-    /// drop its final `Sender` clone before awaiting the returned handles so the sources
-    /// observe the closed channel and exit cooperatively.
-    pub fn spawn_all(&mut self, tx: &Sender<Event>) -> Vec<JoinHandle<()>> {
+    /// Ordering guarantee: call this after constructing the runtime channels and before the
+    /// event loop begins consuming events. During shutdown the caller should drop its final
+    /// `EventChannels` clone before awaiting the returned handles so the sources observe the
+    /// closed lanes and exit cooperatively.
+    pub fn spawn_all(&mut self, channels: &EventChannels) -> Vec<JoinHandle<()>> {
         // Take ownership so duplicate spawns are prevented if called twice.
         let mut out = Vec::with_capacity(self.sources.len());
         for src in self.sources.drain(..) {
             let name = src.name();
-            tracing::info!(target: "runtime.events", source = name, "spawning event source");
-            out.push(src.spawn(tx.clone()));
+            let priority = src.priority();
+            tracing::info!(target: "runtime.events", source = name, ?priority, "spawning event source");
+            out.push(src.spawn(channels.lane(priority).clone()));
         }
         out
     }
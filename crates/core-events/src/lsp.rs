@@ -0,0 +1,412 @@
+//! Language server transport: JSON-RPC 2.0 over a child process's stdio.
+//!
+//! `LspServerSource` is a long-lived `AsyncEventSource`. It starts the
+//! server, then runs a writer task draining the `LspClient` outbox and a
+//! reader loop decoding `Content-Length` framed messages. Notifications and
+//! responses to the editor's requests become `Event::Lsp`; when the server
+//! exits (or its output closes) a final `LspMessageKind::Exited` follows.
+//!
+//! Requests the server sends to the editor are answered here so the server
+//! never stalls on them: `workspace/configuration` with one `null` per item
+//! asked for, everything else with `null`. Interpreting messages (the
+//! handshake, documents, diagnostics) is the job of `core-lsp`.
+//!
+//! The child is killed when the source stops, i.e. when the event channel
+//! closes at shutdown.
+
+use crate::{AsyncEventSource, Event};
+use serde_json::{Value, json};
+use std::io;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{self, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+
+/// A message from a language server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspMessage {
+    /// Name of the server (its `[lsp.servers]` key).
+    pub server: String,
+    pub kind: LspMessageKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LspMessageKind {
+    Notification {
+        method: String,
+        params: Value,
+    },
+    /// Answer to `LspClient::request` `id`; an error carries its message.
+    Response {
+        id: u64,
+        result: Result<Value, String>,
+    },
+    /// The server could not start, exited or closed its output.
+    Exited {
+        error: Option<String>,
+    },
+}
+
+/// Sending half of a server connection. Messages queue until the writer
+/// task picks them up; after the server is gone they are dropped.
+#[derive(Debug, Clone)]
+pub struct LspClient {
+    server: String,
+    outbox: UnboundedSender<Value>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl LspClient {
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    /// Send request `method`; returns the id its `Response` will carry.
+    pub fn request(&self, method: &str, params: Value) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = self.outbox.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }));
+        id
+    }
+
+    /// Send notification `method`; `Value::Null` params are left out
+    /// (`exit` takes none).
+    pub fn notify(&self, method: &str, params: Value) {
+        let mut message = json!({"jsonrpc": "2.0", "method": method});
+        if !params.is_null() {
+            message["params"] = params;
+        }
+        let _ = self.outbox.send(message);
+    }
+
+    fn respond(&self, id: Value, result: Value) {
+        let _ = self.outbox.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result,
+        }));
+    }
+}
+
+/// A language server process, started when the source is spawned.
+pub struct LspServerSource {
+    command: String,
+    args: Vec<String>,
+    root: PathBuf,
+    client: LspClient,
+    outbox: UnboundedReceiver<Value>,
+}
+
+impl LspServerSource {
+    /// `command` with `args`, run in `root`. Reported as `server` in its
+    /// messages.
+    pub fn new(
+        server: impl Into<String>,
+        command: impl Into<String>,
+        args: Vec<String>,
+        root: PathBuf,
+    ) -> Self {
+        let (tx, outbox) = mpsc::unbounded_channel();
+        Self {
+            command: command.into(),
+            args,
+            root,
+            client: LspClient {
+                server: server.into(),
+                outbox: tx,
+                next_id: Arc::new(AtomicU64::new(0)),
+            },
+            outbox,
+        }
+    }
+
+    /// Handle for talking to the server; usable before the source is
+    /// spawned (messages wait in the outbox).
+    pub fn client(&self) -> LspClient {
+        self.client.clone()
+    }
+}
+
+impl AsyncEventSource for LspServerSource {
+    fn name(&self) -> &'static str {
+        "lsp"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let LspServerSource {
+                command,
+                args,
+                root,
+                client,
+                outbox,
+            } = *self;
+            let server = client.server.clone();
+            let spawned = tokio::process::Command::new(&command)
+                .args(&args)
+                .current_dir(&root)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn();
+            let mut child = match spawned {
+                Ok(child) => child,
+                Err(e) => {
+                    tracing::warn!(target: "runtime.lsp", server = %server, command = %command, error = %e, "lsp_spawn_failed");
+                    let error = Some(format!("{command}: {e}"));
+                    let _ = tx
+                        .send(Event::Lsp(LspMessage {
+                            server,
+                            kind: LspMessageKind::Exited { error },
+                        }))
+                        .await;
+                    return;
+                }
+            };
+            tracing::info!(target: "runtime.lsp", server = %server, command = %command, "lsp_started");
+            let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+                return;
+            };
+            let connection = serve(stdout, stdin, client, outbox, tx.clone());
+            tokio::select! {
+                _ = connection => {}
+                _ = tx.closed() => {}
+            }
+            // Dropping the child kills it if it is still running.
+            drop(child);
+        })
+    }
+}
+
+/// Run one connection until the server's output ends: a writer task
+/// framing the outbox onto `write`, and the reader decoding `read`. The
+/// returned task ends after sending `Exited`.
+fn serve<R, W>(
+    read: R,
+    write: W,
+    client: LspClient,
+    mut outbox: UnboundedReceiver<Value>,
+    tx: Sender<Event>,
+) -> JoinHandle<()>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let writer = tokio::spawn(async move {
+        let mut write = write;
+        while let Some(message) = outbox.recv().await {
+            if write.write_all(&encode_frame(&message)).await.is_err()
+                || write.flush().await.is_err()
+            {
+                break;
+            }
+        }
+    });
+    tokio::spawn(async move {
+        let server = client.server.clone();
+        let mut read = tokio::io::BufReader::new(read);
+        let error = loop {
+            let message = match read_frame(&mut read).await {
+                Ok(Some(message)) => message,
+                Ok(None) => break None,
+                Err(e) => break Some(e.to_string()),
+            };
+            let Some(kind) = classify(&client, message) else {
+                continue;
+            };
+            let event = Event::Lsp(LspMessage {
+                server: server.clone(),
+                kind,
+            });
+            if tx.send(event).await.is_err() {
+                writer.abort();
+                return;
+            }
+        };
+        writer.abort();
+        tracing::info!(target: "runtime.lsp", server = %server, error = ?error, "lsp_exited");
+        let _ = tx
+            .send(Event::Lsp(LspMessage {
+                server,
+                kind: LspMessageKind::Exited { error },
+            }))
+            .await;
+    })
+}
+
+/// Turn a decoded message into what the editor sees; server-to-client
+/// requests are answered on the spot and yield nothing.
+fn classify(client: &LspClient, message: Value) -> Option<LspMessageKind> {
+    let method = message.get("method").and_then(Value::as_str);
+    match (message.get("id"), method) {
+        (Some(id), Some(method)) => {
+            let result = match method {
+                "workspace/configuration" => {
+                    let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                    Value::Array(vec![Value::Null; items])
+                }
+                _ => Value::Null,
+            };
+            tracing::debug!(target: "runtime.lsp", server = %client.server, method, "lsp_server_request_answered");
+            client.respond(id.clone(), result);
+            None
+        }
+        (None, Some(method)) => Some(LspMessageKind::Notification {
+            method: method.to_string(),
+            params: message.get("params").cloned().unwrap_or(Value::Null),
+        }),
+        (Some(id), None) => {
+            let id = id.as_u64()?;
+            let result = match message.get("error") {
+                Some(error) => Err(error["message"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_string()),
+                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+            };
+            Some(LspMessageKind::Response { id, result })
+        }
+        (None, None) => None,
+    }
+}
+
+/// `message` with its `Content-Length` header.
+fn encode_frame(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{body}", body.len()).into_bytes()
+}
+
+/// Next framed message; `None` at a clean end of stream.
+async fn read_frame<R>(read: &mut R) -> io::Result<Option<Value>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if read.read_line(&mut line).await? == 0 {
+            return if length.is_none() {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length",
+        ));
+    };
+    let mut body = vec![0; length];
+    read.read_exact(&mut body).await?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn next_message(rx: &mut mpsc::Receiver<Event>) -> LspMessageKind {
+        match tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+            Ok(Some(Event::Lsp(message))) => message.kind,
+            other => panic!("expected an lsp message, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip_and_server_requests_are_answered() {
+        let (editor_side, server_side) = tokio::io::duplex(4096);
+        let (editor_read, editor_write) = tokio::io::split(editor_side);
+        let (server_read, mut server_write) = tokio::io::split(server_side);
+        let mut server_read = tokio::io::BufReader::new(server_read);
+        let (tx, mut rx) = mpsc::channel(8);
+        let source = LspServerSource::new("fake", "unused", Vec::new(), PathBuf::from("."));
+        let client = source.client();
+        let connection = serve(editor_read, editor_write, client.clone(), source.outbox, tx);
+
+        let id = client.request("initialize", json!({"processId": null}));
+        let sent = read_frame(&mut server_read).await.unwrap().unwrap();
+        assert_eq!(sent["method"], "initialize");
+        assert_eq!(sent["id"], id);
+
+        for message in [
+            json!({"jsonrpc": "2.0", "id": id, "result": {"capabilities": {}}}),
+            json!({"jsonrpc": "2.0", "id": 7, "method": "workspace/configuration",
+                   "params": {"items": [{}, {}]}}),
+            json!({"jsonrpc": "2.0", "method": "window/logMessage", "params": {"type": 3}}),
+            json!({"jsonrpc": "2.0", "id": id + 1, "error": {"code": -32601, "message": "nope"}}),
+        ] {
+            server_write
+                .write_all(&encode_frame(&message))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            next_message(&mut rx).await,
+            LspMessageKind::Response {
+                id,
+                result: Ok(json!({"capabilities": {}}))
+            }
+        );
+        let answer = read_frame(&mut server_read).await.unwrap().unwrap();
+        assert_eq!(answer["id"], 7);
+        assert_eq!(answer["result"], json!([null, null]));
+        assert!(matches!(
+            next_message(&mut rx).await,
+            LspMessageKind::Notification { method, .. } if method == "window/logMessage"
+        ));
+        assert_eq!(
+            next_message(&mut rx).await,
+            LspMessageKind::Response {
+                id: id + 1,
+                result: Err("nope".into())
+            }
+        );
+
+        drop(server_write);
+        drop(server_read);
+        assert_eq!(
+            next_message(&mut rx).await,
+            LspMessageKind::Exited { error: None }
+        );
+        connection.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_command_reports_exit() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let source = LspServerSource::new(
+            "fake",
+            "oxidized-no-such-language-server",
+            Vec::new(),
+            PathBuf::from("."),
+        );
+        Box::new(source).spawn(tx).await.unwrap();
+        assert!(matches!(
+            next_message(&mut rx).await,
+            LspMessageKind::Exited { error: Some(_) }
+        ));
+    }
+}
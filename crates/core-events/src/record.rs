@@ -0,0 +1,584 @@
+//! Input recording and deterministic replay.
+//!
+//! `EventRecorder` is an `EventHooks` implementation that appends every
+//! `Event::Input` the runtime handles to a file, stamped with milliseconds
+//! since recording began. `ReplayEventSource` reads such a file back and
+//! sends the same events at the same offsets, so a keymap or dispatch bug can
+//! be reproduced from the file alone. Ticks, shell output and git probes are
+//! not recorded: their sources produce them again during replay.
+//!
+//! The format is line-based text: a `# oxidized-events 1` header, then one
+//! `<ms> <kind> [fields]` line per event. Keys are `<mods> <base>` with the
+//! modifier letters `CASMD` (Ctrl, Alt, Shift, Meta, Super; `-` for none)
+//! and a base of `c:<hex codepoint>` or `n:<named key>`. Text payloads are
+//! hex-encoded UTF-8 so a line never breaks. A paste chunk always records
+//! its byte length; with `redact_paste` the content is left out and the
+//! chunk replays as that many `x`.
+
+use crate::{
+    AsyncEventSource, Event, EventHooks, InputEvent, KeyCode, KeyEvent, KeyEventExt, KeyModifiers,
+    KeyToken, ModMask, MouseButton, MouseEvent, MouseEventKind, NamedKey,
+};
+use anyhow::{Context, anyhow, bail};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+/// First line of every recording.
+pub const RECORDING_HEADER: &str = "# oxidized-events 1";
+
+/// One recorded input event and its offset from the start of the recording.
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    pub offset: Duration,
+    pub event: InputEvent,
+}
+
+/// Records input events to a file as the loop handles them (see the module
+/// docs for the format).
+pub struct EventRecorder {
+    state: Mutex<RecorderState>,
+}
+
+struct RecorderState {
+    out: Option<BufWriter<File>>,
+    start: Instant,
+    redact_paste: bool,
+}
+
+impl EventRecorder {
+    /// Truncate `path` and write the header.
+    pub fn create(path: &Path, redact_paste: bool) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{RECORDING_HEADER}")?;
+        out.flush()?;
+        tracing::info!(target: "runtime.record", path = %path.display(), redact_paste, "recording_started");
+        Ok(Self {
+            state: Mutex::new(RecorderState {
+                out: Some(out),
+                start: Instant::now(),
+                redact_paste,
+            }),
+        })
+    }
+
+    fn record(&self, input: &InputEvent) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let offset = state.start.elapsed();
+        let line = format_event(offset, input, state.redact_paste);
+        let Some(out) = state.out.as_mut() else {
+            return;
+        };
+        // Flushed per line so a crash keeps everything up to it.
+        if let Err(e) = writeln!(out, "{line}").and_then(|()| out.flush()) {
+            tracing::warn!(target: "runtime.record", error = %e, "recording_stopped");
+            state.out = None;
+        }
+    }
+}
+
+impl EventHooks for EventRecorder {
+    fn pre_handle(&self, event: &Event) {
+        if let Event::Input(input) = event {
+            self.record(input);
+        }
+    }
+}
+
+/// One recording line for `input` at `offset`.
+pub fn format_event(offset: Duration, input: &InputEvent, redact_paste: bool) -> String {
+    let body = match input {
+        InputEvent::KeyPress(key) => {
+            let (base, mods) = flatten(&key.token);
+            let repeat = if key.repeat { " repeat" } else { "" };
+            format!("key {} {}{repeat}", format_mods(mods), format_base(&base))
+        }
+        InputEvent::Key(key) => format!(
+            "legacy-key {} {}",
+            format_mods(key_modifiers_mask(key.mods)),
+            format_base(&keycode_token(key.code))
+        ),
+        InputEvent::Resize(w, h) => format!("resize {w} {h}"),
+        InputEvent::CtrlC => "ctrl-c".to_string(),
+        InputEvent::TextCommit(text) => format!("text {}", hex(text.as_bytes())),
+        InputEvent::PasteStart => "paste-start".to_string(),
+        InputEvent::PasteChunk(chunk) if redact_paste => format!("paste {}", chunk.len()),
+        InputEvent::PasteChunk(chunk) => {
+            format!("paste {} {}", chunk.len(), hex(chunk.as_bytes()))
+        }
+        InputEvent::PasteEnd => "paste-end".to_string(),
+        InputEvent::Mouse(mouse) => format!(
+            "mouse {} {} {} {}",
+            format_mouse_kind(mouse.kind),
+            mouse.column,
+            mouse.row,
+            format_mods(mouse.mods)
+        ),
+        InputEvent::FocusGained => "focus-gained".to_string(),
+        InputEvent::FocusLost => "focus-lost".to_string(),
+        InputEvent::RawBytes(bytes) => format!("raw {}", hex(bytes)),
+        InputEvent::CompositionUpdate { preedit } => format!("preedit {}", hex(preedit.as_bytes())),
+    };
+    format!("{} {body}", offset.as_millis())
+}
+
+/// Events of a recording, in file order.
+pub fn parse_recording(text: &str) -> anyhow::Result<Vec<RecordedEvent>> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, RECORDING_HEADER)) => {}
+        _ => bail!("not an event recording (expected {RECORDING_HEADER:?})"),
+    }
+    lines
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line).with_context(|| format!("line {}: {line:?}", i + 1)))
+        .collect()
+}
+
+fn parse_line(line: &str) -> anyhow::Result<RecordedEvent> {
+    let mut fields = line.split(' ');
+    let mut next = |what: &str| fields.next().ok_or_else(|| anyhow!("missing {what}"));
+    let offset = Duration::from_millis(next("offset")?.parse()?);
+    let event = match next("kind")? {
+        "key" => {
+            let mods = parse_mods(next("modifiers")?)?;
+            let base = parse_base(next("key")?)?;
+            let repeat = match fields.next() {
+                None => false,
+                Some("repeat") => true,
+                Some(other) => bail!("unexpected {other:?}"),
+            };
+            let token = if mods.is_empty() {
+                base
+            } else {
+                KeyToken::Chord {
+                    base: Box::new(base),
+                    mods,
+                }
+            };
+            InputEvent::KeyPress(KeyEventExt::with_repeat(token, repeat))
+        }
+        "legacy-key" => {
+            let mods = parse_mods(next("modifiers")?)?;
+            let base = parse_base(next("key")?)?;
+            InputEvent::Key(KeyEvent {
+                code: token_keycode(&base).ok_or_else(|| anyhow!("no key code for {base:?}"))?,
+                mods: mask_key_modifiers(mods),
+            })
+        }
+        "resize" => InputEvent::Resize(next("width")?.parse()?, next("height")?.parse()?),
+        "ctrl-c" => InputEvent::CtrlC,
+        "text" => InputEvent::TextCommit(unhex_utf8(fields.next().unwrap_or(""))?),
+        "paste-start" => InputEvent::PasteStart,
+        "paste" => {
+            let len: usize = next("length")?.parse()?;
+            let chunk = match fields.next() {
+                Some(content) => unhex_utf8(content)?,
+                None => "x".repeat(len),
+            };
+            if chunk.len() != len {
+                bail!("paste is {} bytes, not {len}", chunk.len());
+            }
+            InputEvent::PasteChunk(chunk)
+        }
+        "paste-end" => InputEvent::PasteEnd,
+        "mouse" => InputEvent::Mouse(MouseEvent {
+            kind: parse_mouse_kind(next("mouse kind")?)?,
+            column: next("column")?.parse()?,
+            row: next("row")?.parse()?,
+            mods: parse_mods(next("modifiers")?)?,
+        }),
+        "focus-gained" => InputEvent::FocusGained,
+        "focus-lost" => InputEvent::FocusLost,
+        "raw" => InputEvent::RawBytes(unhex(fields.next().unwrap_or(""))?),
+        "preedit" => InputEvent::CompositionUpdate {
+            preedit: unhex_utf8(fields.next().unwrap_or(""))?,
+        },
+        other => bail!("unknown event {other:?}"),
+    };
+    Ok(RecordedEvent { offset, event })
+}
+
+/// Sends a recording's events at their recorded offsets from the moment it
+/// is spawned, then stops. Keypresses are stamped when sent, so timeouts
+/// behave as they did while recording.
+pub struct ReplayEventSource {
+    events: Vec<RecordedEvent>,
+}
+
+impl ReplayEventSource {
+    pub fn new(events: Vec<RecordedEvent>) -> Self {
+        Self { events }
+    }
+
+    /// Read and parse the recording at `path`.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read recording {}", path.display()))?;
+        let events = parse_recording(&text)
+            .with_context(|| format!("invalid recording {}", path.display()))?;
+        Ok(Self::new(events))
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl AsyncEventSource for ReplayEventSource {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn priority(&self) -> crate::EventPriority {
+        crate::EventPriority::High
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let start = tokio::time::Instant::now();
+            let total = self.events.len();
+            for (sent, recorded) in self.events.into_iter().enumerate() {
+                tokio::time::sleep_until(start + recorded.offset).await;
+                let event = match recorded.event {
+                    InputEvent::KeyPress(key) => InputEvent::KeyPress(KeyEventExt::from_parts(
+                        key.token,
+                        key.repeat,
+                        Instant::now(),
+                    )),
+                    other => other,
+                };
+                if tx.send(Event::Input(event)).await.is_err() {
+                    tracing::debug!(target: "runtime.record", sent, total, "replay_channel_closed");
+                    return;
+                }
+            }
+            tracing::debug!(target: "runtime.record", total, "replay_finished");
+        })
+    }
+}
+
+const MOD_LETTERS: [(ModMask, char); 5] = [
+    (ModMask::CTRL, 'C'),
+    (ModMask::ALT, 'A'),
+    (ModMask::SHIFT, 'S'),
+    (ModMask::META, 'M'),
+    (ModMask::SUPER, 'D'),
+];
+
+fn format_mods(mods: ModMask) -> String {
+    if mods.is_empty() {
+        return "-".to_string();
+    }
+    MOD_LETTERS
+        .iter()
+        .filter(|(flag, _)| mods.contains(*flag))
+        .map(|(_, letter)| letter)
+        .collect()
+}
+
+fn parse_mods(field: &str) -> anyhow::Result<ModMask> {
+    if field == "-" {
+        return Ok(ModMask::empty());
+    }
+    field.chars().try_fold(ModMask::empty(), |mods, c| {
+        let (flag, _) = MOD_LETTERS
+            .iter()
+            .find(|(_, letter)| *letter == c)
+            .ok_or_else(|| anyhow!("unknown modifier {c:?}"))?;
+        Ok(mods | *flag)
+    })
+}
+
+/// `token` without nesting: its innermost key and every modifier around it.
+fn flatten(token: &KeyToken) -> (KeyToken, ModMask) {
+    match token {
+        KeyToken::Chord { base, mods } => {
+            let (inner, inner_mods) = flatten(base);
+            (inner, *mods | inner_mods)
+        }
+        other => (other.clone(), ModMask::empty()),
+    }
+}
+
+const NAMED_KEYS: [(NamedKey, &str); 14] = [
+    (NamedKey::Enter, "Enter"),
+    (NamedKey::Esc, "Esc"),
+    (NamedKey::Backspace, "Backspace"),
+    (NamedKey::Tab, "Tab"),
+    (NamedKey::Up, "Up"),
+    (NamedKey::Down, "Down"),
+    (NamedKey::Left, "Left"),
+    (NamedKey::Right, "Right"),
+    (NamedKey::Home, "Home"),
+    (NamedKey::End, "End"),
+    (NamedKey::PageUp, "PageUp"),
+    (NamedKey::PageDown, "PageDown"),
+    (NamedKey::Insert, "Insert"),
+    (NamedKey::Delete, "Delete"),
+];
+
+fn format_base(base: &KeyToken) -> String {
+    match base {
+        KeyToken::Char(c) => format!("c:{:x}", *c as u32),
+        KeyToken::Named(NamedKey::F(n)) => format!("n:F{n}"),
+        KeyToken::Named(named) => {
+            let (_, name) = NAMED_KEYS
+                .iter()
+                .find(|(key, _)| key == named)
+                .expect("every named key but F has a name");
+            format!("n:{name}")
+        }
+        // `flatten` leaves no chord behind.
+        KeyToken::Chord { .. } => format_base(&flatten(base).0),
+    }
+}
+
+fn parse_base(field: &str) -> anyhow::Result<KeyToken> {
+    if let Some(code) = field.strip_prefix("c:") {
+        let c = u32::from_str_radix(code, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| anyhow!("invalid codepoint {code:?}"))?;
+        return Ok(KeyToken::Char(c));
+    }
+    let name = field
+        .strip_prefix("n:")
+        .ok_or_else(|| anyhow!("invalid key {field:?}"))?;
+    if let Some(n) = name.strip_prefix('F')
+        && let Ok(n) = n.parse()
+    {
+        return Ok(KeyToken::Named(NamedKey::F(n)));
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(_, key_name)| *key_name == name)
+        .map(|(key, _)| KeyToken::Named(*key))
+        .ok_or_else(|| anyhow!("unknown key {name:?}"))
+}
+
+fn keycode_token(code: KeyCode) -> KeyToken {
+    match code {
+        KeyCode::Char(c) => KeyToken::Char(c),
+        KeyCode::Enter => KeyToken::Named(NamedKey::Enter),
+        KeyCode::Esc => KeyToken::Named(NamedKey::Esc),
+        KeyCode::Backspace => KeyToken::Named(NamedKey::Backspace),
+        KeyCode::Tab => KeyToken::Named(NamedKey::Tab),
+        KeyCode::Up => KeyToken::Named(NamedKey::Up),
+        KeyCode::Down => KeyToken::Named(NamedKey::Down),
+        KeyCode::Left => KeyToken::Named(NamedKey::Left),
+        KeyCode::Right => KeyToken::Named(NamedKey::Right),
+    }
+}
+
+fn token_keycode(token: &KeyToken) -> Option<KeyCode> {
+    Some(match token {
+        KeyToken::Char(c) => KeyCode::Char(*c),
+        KeyToken::Named(NamedKey::Enter) => KeyCode::Enter,
+        KeyToken::Named(NamedKey::Esc) => KeyCode::Esc,
+        KeyToken::Named(NamedKey::Backspace) => KeyCode::Backspace,
+        KeyToken::Named(NamedKey::Tab) => KeyCode::Tab,
+        KeyToken::Named(NamedKey::Up) => KeyCode::Up,
+        KeyToken::Named(NamedKey::Down) => KeyCode::Down,
+        KeyToken::Named(NamedKey::Left) => KeyCode::Left,
+        KeyToken::Named(NamedKey::Right) => KeyCode::Right,
+        _ => return None,
+    })
+}
+
+fn key_modifiers_mask(mods: KeyModifiers) -> ModMask {
+    let mut mask = ModMask::empty();
+    mask.set(ModMask::CTRL, mods.contains(KeyModifiers::CTRL));
+    mask.set(ModMask::ALT, mods.contains(KeyModifiers::ALT));
+    mask.set(ModMask::SHIFT, mods.contains(KeyModifiers::SHIFT));
+    mask
+}
+
+fn mask_key_modifiers(mask: ModMask) -> KeyModifiers {
+    let mut mods = KeyModifiers::empty();
+    mods.set(KeyModifiers::CTRL, mask.contains(ModMask::CTRL));
+    mods.set(KeyModifiers::ALT, mask.contains(ModMask::ALT));
+    mods.set(KeyModifiers::SHIFT, mask.contains(ModMask::SHIFT));
+    mods
+}
+
+fn format_mouse_kind(kind: MouseEventKind) -> String {
+    let button = |b: MouseButton| match b {
+        MouseButton::Left => "left",
+        MouseButton::Middle => "middle",
+        MouseButton::Right => "right",
+    };
+    match kind {
+        MouseEventKind::Down(b) => format!("down:{}", button(b)),
+        MouseEventKind::Up(b) => format!("up:{}", button(b)),
+        MouseEventKind::Drag(b) => format!("drag:{}", button(b)),
+        MouseEventKind::ScrollUp => "scroll-up".to_string(),
+        MouseEventKind::ScrollDown => "scroll-down".to_string(),
+        MouseEventKind::Moved => "moved".to_string(),
+    }
+}
+
+fn parse_mouse_kind(field: &str) -> anyhow::Result<MouseEventKind> {
+    let button = |name: &str| match name {
+        "left" => Ok(MouseButton::Left),
+        "middle" => Ok(MouseButton::Middle),
+        "right" => Ok(MouseButton::Right),
+        _ => Err(anyhow!("unknown mouse button {name:?}")),
+    };
+    Ok(match field.split_once(':') {
+        Some(("down", b)) => MouseEventKind::Down(button(b)?),
+        Some(("up", b)) => MouseEventKind::Up(button(b)?),
+        Some(("drag", b)) => MouseEventKind::Drag(button(b)?),
+        _ => match field {
+            "scroll-up" => MouseEventKind::ScrollUp,
+            "scroll-down" => MouseEventKind::ScrollDown,
+            "moved" => MouseEventKind::Moved,
+            _ => bail!("unknown mouse event {field:?}"),
+        },
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(field: &str) -> anyhow::Result<Vec<u8>> {
+    if !field.is_ascii() || !field.len().is_multiple_of(2) {
+        bail!("invalid hex {field:?}");
+    }
+    (0..field.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&field[i..i + 2], 16).map_err(Into::into))
+        .collect()
+}
+
+fn unhex_utf8(field: &str) -> anyhow::Result<String> {
+    Ok(String::from_utf8(unhex(field)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn ctrl(c: char) -> KeyToken {
+        KeyToken::Chord {
+            base: Box::new(KeyToken::Char(c)),
+            mods: ModMask::CTRL,
+        }
+    }
+
+    fn sample() -> Vec<InputEvent> {
+        vec![
+            InputEvent::KeyPress(KeyEventExt::new(KeyToken::Char('é'))),
+            InputEvent::KeyPress(KeyEventExt::with_repeat(ctrl('w'), true)),
+            InputEvent::KeyPress(KeyEventExt::new(KeyToken::Named(NamedKey::F(5)))),
+            InputEvent::Key(KeyEvent {
+                code: KeyCode::Esc,
+                mods: KeyModifiers::SHIFT,
+            }),
+            InputEvent::Resize(80, 24),
+            InputEvent::CtrlC,
+            InputEvent::TextCommit("a b\n".into()),
+            InputEvent::PasteStart,
+            InputEvent::PasteChunk("🙂 x".into()),
+            InputEvent::PasteEnd,
+            InputEvent::Mouse(MouseEvent {
+                kind: MouseEventKind::Drag(MouseButton::Left),
+                column: 3,
+                row: 7,
+                mods: ModMask::ALT | ModMask::SUPER,
+            }),
+            InputEvent::FocusGained,
+            InputEvent::FocusLost,
+            InputEvent::RawBytes(vec![0x1b, b'[']),
+            InputEvent::CompositionUpdate {
+                preedit: String::new(),
+            },
+        ]
+    }
+
+    fn recording(events: &[InputEvent], redact_paste: bool) -> String {
+        let mut text = format!("{RECORDING_HEADER}\n");
+        for (i, event) in events.iter().enumerate() {
+            let offset = Duration::from_millis(i as u64 * 10);
+            text.push_str(&format_event(offset, event, redact_paste));
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn every_input_event_round_trips() {
+        let events = sample();
+        let text = recording(&events, false);
+        let parsed = parse_recording(&text).unwrap();
+        assert_eq!(parsed.len(), events.len());
+        for (i, (recorded, event)) in parsed.iter().zip(&events).enumerate() {
+            assert_eq!(recorded.offset, Duration::from_millis(i as u64 * 10));
+            // Timestamps are not recorded; compare the lines instead.
+            assert_eq!(
+                format_event(recorded.offset, &recorded.event, false),
+                format_event(recorded.offset, event, false)
+            );
+        }
+        assert!(text.contains("10 key C c:77 repeat\n"));
+        assert!(text.contains("20 key - n:F5\n"));
+    }
+
+    #[test]
+    fn redacted_paste_keeps_its_length() {
+        let text = recording(&[InputEvent::PasteChunk("secret🙂".into())], true);
+        assert_eq!(text.lines().nth(1), Some("0 paste 10"));
+        let parsed = parse_recording(&text).unwrap();
+        assert!(matches!(
+            &parsed[0].event,
+            InputEvent::PasteChunk(chunk) if *chunk == "x".repeat(10)
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_header_and_bad_lines() {
+        assert!(parse_recording("0 ctrl-c\n").is_err());
+        let err = parse_recording(&format!("{RECORDING_HEADER}\n0 key - z:1\n")).unwrap_err();
+        assert!(format!("{err:#}").contains("line 2"));
+        assert!(parse_recording(&format!("{RECORDING_HEADER}\n0 paste 3 6869\n")).is_err());
+    }
+
+    #[tokio::test]
+    async fn recorder_and_replay_round_trip_through_a_file() {
+        let path = std::env::temp_dir().join(format!("ox-record-{}.events", std::process::id()));
+        let recorder = EventRecorder::create(&path, false).unwrap();
+        for event in sample() {
+            recorder.pre_handle(&Event::Input(event));
+        }
+        recorder.pre_handle(&Event::Tick);
+        drop(recorder);
+
+        let source = ReplayEventSource::open(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(source.len(), sample().len());
+        let (tx, mut rx) = mpsc::channel(64);
+        let handle = Box::new(source).spawn(tx);
+        let mut replayed = Vec::new();
+        while let Some(Event::Input(event)) = rx.recv().await {
+            replayed.push(format_event(Duration::ZERO, &event, false));
+        }
+        handle.await.unwrap();
+        let expected: Vec<String> = sample()
+            .iter()
+            .map(|event| format_event(Duration::ZERO, event, false))
+            .collect();
+        assert_eq!(replayed, expected);
+    }
+}
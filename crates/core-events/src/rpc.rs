@@ -0,0 +1,597 @@
+//! Remote control server (`--listen`): msgpack-rpc over a unix socket.
+//!
+//! `RpcServerSource` is a long-lived `AsyncEventSource`. Each accepted
+//! connection gets a reader task, which decodes messages, and a writer task,
+//! which drains the connection's outbox. Requests (`[0, msgid, method,
+//! params]`) and notifications (`[2, method, params]`) become
+//! `Event::Rpc`; the editor answers requests through `RpcHub::reply`, which
+//! queues the response (`[1, msgid, error, result]`) on the client's outbox.
+//!
+//! `subscribe` / `unsubscribe` (params: `[event]`) are answered by the server
+//! itself: the hub records the subscription and `RpcHub::notify` sends
+//! `[2, event, params]` to every subscribed client. Everything else is the
+//! editor's to interpret.
+//!
+//! A malformed message, or one past `MAX_MESSAGE_LEN`, closes its
+//! connection; other clients are unaffected. Incoming bytes are framed by
+//! `Frame`, which resumes where the last read ran out, so a message is
+//! scanned once and decoded once however many reads it arrives in.
+//!
+//! The socket file is removed when the source stops. Only unix sockets are
+//! supported; elsewhere `bind` fails with `Unsupported`.
+
+use crate::{AsyncEventSource, Event};
+pub use rmpv::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::mpsc::{self, Sender, UnboundedSender};
+use tokio::task::JoinHandle;
+
+const REQUEST: u64 = 0;
+const RESPONSE: u64 = 1;
+const NOTIFICATION: u64 = 2;
+
+/// Largest message a client may send.
+pub const MAX_MESSAGE_LEN: usize = 16 << 20;
+
+/// A request or notification from a remote client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcRequest {
+    /// Connection the message came from, for `RpcHub::reply`.
+    pub client: u64,
+    /// `None` for notifications, which take no reply.
+    pub msgid: Option<u32>,
+    pub method: String,
+    pub params: Vec<Value>,
+}
+
+impl RpcRequest {
+    /// String parameter `i`, if present.
+    pub fn str_param(&self, i: usize) -> Option<&str> {
+        self.params.get(i).and_then(Value::as_str)
+    }
+}
+
+struct Client {
+    outbox: UnboundedSender<Value>,
+    subscriptions: BTreeSet<String>,
+}
+
+#[derive(Default)]
+struct Clients {
+    next_id: u64,
+    by_id: HashMap<u64, Client>,
+}
+
+/// Connected clients and their subscriptions, shared by the server and
+/// the editor.
+#[derive(Clone, Default)]
+pub struct RpcHub {
+    clients: Arc<Mutex<Clients>>,
+}
+
+impl RpcHub {
+    fn lock(&self) -> MutexGuard<'_, Clients> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn connect(&self, outbox: UnboundedSender<Value>) -> u64 {
+        let mut clients = self.lock();
+        clients.next_id += 1;
+        let id = clients.next_id;
+        clients.by_id.insert(
+            id,
+            Client {
+                outbox,
+                subscriptions: BTreeSet::new(),
+            },
+        );
+        id
+    }
+
+    fn disconnect(&self, client: u64) {
+        self.lock().by_id.remove(&client);
+    }
+
+    fn subscribe(&self, client: u64, event: &str, on: bool) {
+        if let Some(c) = self.lock().by_id.get_mut(&client) {
+            if on {
+                c.subscriptions.insert(event.to_string());
+            } else {
+                c.subscriptions.remove(event);
+            }
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.lock().by_id.len()
+    }
+
+    /// Whether any client subscribed to `event`.
+    pub fn has_subscribers(&self, event: &str) -> bool {
+        self.lock()
+            .by_id
+            .values()
+            .any(|c| c.subscriptions.contains(event))
+    }
+
+    /// Answer request `msgid` of `client`. A client that has gone away is
+    /// skipped.
+    pub fn reply(&self, client: u64, msgid: u32, result: Result<Value, String>) {
+        let (error, result) = match result {
+            Ok(value) => (Value::Nil, value),
+            Err(message) => (Value::from(message), Value::Nil),
+        };
+        let response = Value::Array(vec![
+            Value::from(RESPONSE),
+            Value::from(msgid),
+            error,
+            result,
+        ]);
+        if let Some(c) = self.lock().by_id.get(&client) {
+            let _ = c.outbox.send(response);
+        }
+    }
+
+    /// Send `event` with `params` to every client subscribed to it.
+    pub fn notify(&self, event: &str, params: Vec<Value>) {
+        let message = Value::Array(vec![
+            Value::from(NOTIFICATION),
+            Value::from(event),
+            Value::Array(params),
+        ]);
+        for c in self.lock().by_id.values() {
+            if c.subscriptions.contains(event) {
+                let _ = c.outbox.send(message.clone());
+            }
+        }
+    }
+}
+
+/// Listener for remote clients on a unix socket.
+pub struct RpcServerSource {
+    path: PathBuf,
+    #[cfg(unix)]
+    listener: std::os::unix::net::UnixListener,
+    hub: RpcHub,
+}
+
+impl RpcServerSource {
+    /// Bind the socket at `path`. A stale socket file nobody answers on is
+    /// replaced; a live one is an `AddrInUse` error, and anything else at
+    /// `path` (a file the user meant to keep) an `AlreadyExists` one.
+    #[cfg(unix)]
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+        use std::os::unix::net::{UnixListener, UnixStream};
+        if let Ok(meta) = std::fs::symlink_metadata(path) {
+            if !meta.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is in use by another server", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            listener,
+            hub: RpcHub::default(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        let _ = path;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--listen needs unix domain sockets",
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Handle for replying to and notifying this server's clients.
+    pub fn hub(&self) -> RpcHub {
+        self.hub.clone()
+    }
+}
+
+impl AsyncEventSource for RpcServerSource {
+    fn name(&self) -> &'static str {
+        "rpc"
+    }
+
+    #[cfg(unix)]
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let RpcServerSource {
+                path,
+                listener,
+                hub,
+            } = *self;
+            let listener = match tokio::net::UnixListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::warn!(target: "runtime.rpc", path = %path.display(), error = %e, "rpc_listen_failed");
+                    return;
+                }
+            };
+            tracing::info!(target: "runtime.rpc", path = %path.display(), "rpc_listening");
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            let (read, write) = stream.into_split();
+                            serve(read, write, hub.clone(), tx.clone());
+                        }
+                        Err(e) => {
+                            tracing::warn!(target: "runtime.rpc", error = %e, "rpc_accept_failed");
+                            break;
+                        }
+                    },
+                    _ = tx.closed() => break,
+                }
+            }
+            let _ = std::fs::remove_file(&path);
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn spawn(self: Box<Self>, _tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async {})
+    }
+}
+
+/// Run one connection: a writer task draining its outbox and a reader task
+/// forwarding its messages until EOF, a malformed message or shutdown.
+pub fn serve<R, W>(read: R, write: W, hub: RpcHub, tx: Sender<Event>) -> JoinHandle<()>
+where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    W: tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let (outbox, mut pending) = mpsc::unbounded_channel::<Value>();
+    let client = hub.connect(outbox);
+    tracing::debug!(target: "runtime.rpc", client, "rpc_client_connected");
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        let mut write = write;
+        while let Some(message) = pending.recv().await {
+            let mut bytes = Vec::new();
+            if rmpv::encode::write_value(&mut bytes, &message).is_err()
+                || write.write_all(&bytes).await.is_err()
+            {
+                break;
+            }
+        }
+    });
+    tokio::spawn(async move {
+        let reason = read_messages(read, client, &hub, &tx).await;
+        tracing::debug!(target: "runtime.rpc", client, reason, "rpc_client_disconnected");
+        hub.disconnect(client);
+    })
+}
+
+/// Decode and dispatch messages until the stream ends; returns why.
+async fn read_messages<R>(
+    mut read: R,
+    client: u64,
+    hub: &RpcHub,
+    tx: &Sender<Event>,
+) -> &'static str
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+    let mut buf = Vec::new();
+    let mut frame = Frame::default();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let end = match frame.scan(&buf) {
+            Ok(Some(end)) => end,
+            Ok(None) => match read.read(&mut chunk).await {
+                Ok(0) | Err(_) => return "closed",
+                Ok(n) => {
+                    buf.extend_from_slice(&chunk[..n]);
+                    continue;
+                }
+            },
+            Err(reason) => return reason,
+        };
+        let value = rmpv::decode::read_value(&mut &buf[..end]);
+        buf.drain(..end);
+        frame = Frame::default();
+        let Some(request) = value.ok().and_then(|v| parse_message(client, v)) else {
+            return "malformed_message";
+        };
+        if let Some(event) = request
+            .str_param(0)
+            .filter(|_| matches!(request.method.as_str(), "subscribe" | "unsubscribe"))
+        {
+            hub.subscribe(client, event, request.method == "subscribe");
+            if let Some(msgid) = request.msgid {
+                hub.reply(client, msgid, Ok(Value::Nil));
+            }
+        } else if tx.send(Event::Rpc(request)).await.is_err() {
+            return "shutdown";
+        }
+    }
+}
+
+/// Where the first msgpack value of a growing buffer ends, found by
+/// skipping over headers and payloads without decoding them.
+#[derive(Debug)]
+struct Frame {
+    /// Bytes scanned so far: the start of the next value.
+    pos: usize,
+    /// Values still owed before the message is complete.
+    owed: u64,
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self { pos: 0, owed: 1 }
+    }
+}
+
+impl Frame {
+    /// The end of the message once `buf` holds all of it, `None` while it
+    /// does not yet. Resumes where the previous call stopped, so `buf` may
+    /// only grow between calls.
+    fn scan(&mut self, buf: &[u8]) -> Result<Option<usize>, &'static str> {
+        while self.owed > 0 {
+            let Some(&marker) = buf.get(self.pos) else {
+                return Ok(None);
+            };
+            if marker == 0xc1 {
+                return Err("malformed_message");
+            }
+            let Some((header, payload, nested)) = value_header(&buf[self.pos..]) else {
+                return Ok(None);
+            };
+            let end = (self.pos + header) as u64 + payload;
+            // Every value still owed takes at least a byte.
+            if end + (self.owed - 1) + nested > MAX_MESSAGE_LEN as u64 {
+                return Err("message_too_large");
+            }
+            if end > buf.len() as u64 {
+                return Ok(None);
+            }
+            self.pos = end as usize;
+            self.owed += nested;
+            self.owed -= 1;
+        }
+        Ok(Some(self.pos))
+    }
+}
+
+/// Header bytes, payload bytes and nested values of the msgpack value
+/// `bytes` starts with; `None` while its header is incomplete.
+fn value_header(bytes: &[u8]) -> Option<(usize, u64, u64)> {
+    let marker = bytes[0];
+    let len = |width: usize| -> Option<u64> {
+        let be = bytes.get(1..1 + width)?;
+        Some(be.iter().fold(0, |n, &b| n << 8 | u64::from(b)))
+    };
+    Some(match marker {
+        0x80..=0x8f => (1, 0, 2 * u64::from(marker & 0x0f)),
+        0x90..=0x9f => (1, 0, u64::from(marker & 0x0f)),
+        0xa0..=0xbf => (1, u64::from(marker & 0x1f), 0),
+        0xcc | 0xd0 => (2, 0, 0),
+        0xcd | 0xd1 => (3, 0, 0),
+        0xca | 0xce | 0xd2 => (5, 0, 0),
+        0xcb | 0xcf | 0xd3 => (9, 0, 0),
+        // fixext: type byte and 1, 2, 4, 8 or 16 data bytes
+        0xd4..=0xd8 => (2, 1 << (marker - 0xd4), 0),
+        // bin and str 8/16/32
+        0xc4 | 0xd9 => (2, len(1)?, 0),
+        0xc5 | 0xda => (3, len(2)?, 0),
+        0xc6 | 0xdb => (5, len(4)?, 0),
+        // ext 8/16/32: the length, then a type byte
+        0xc7 => (3, len(1)?, 0),
+        0xc8 => (4, len(2)?, 0),
+        0xc9 => (6, len(4)?, 0),
+        0xdc => (3, 0, len(2)?),
+        0xdd => (5, 0, len(4)?),
+        0xde => (3, 0, 2 * len(2)?),
+        0xdf => (5, 0, 2 * len(4)?),
+        // fixints, nil, booleans
+        _ => (1, 0, 0),
+    })
+}
+
+/// A request or notification; `None` for anything else (responses
+/// included: the editor never calls its clients).
+fn parse_message(client: u64, value: Value) -> Option<RpcRequest> {
+    let Value::Array(parts) = value else {
+        return None;
+    };
+    let params = |v: &Value| match v {
+        Value::Array(items) => Some(items.clone()),
+        _ => None,
+    };
+    match parts.as_slice() {
+        [kind, msgid, method, args] if kind.as_u64() == Some(REQUEST) => Some(RpcRequest {
+            client,
+            msgid: Some(u32::try_from(msgid.as_u64()?).ok()?),
+            method: method.as_str()?.to_string(),
+            params: params(args)?,
+        }),
+        [kind, method, args] if kind.as_u64() == Some(NOTIFICATION) => Some(RpcRequest {
+            client,
+            msgid: None,
+            method: method.as_str()?.to_string(),
+            params: params(args)?,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    fn encode(value: Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &value).unwrap();
+        bytes
+    }
+
+    fn request(msgid: u32, method: &str, params: Vec<Value>) -> Vec<u8> {
+        encode(Value::Array(vec![
+            Value::from(REQUEST),
+            Value::from(msgid),
+            Value::from(method),
+            Value::Array(params),
+        ]))
+    }
+
+    async fn read_value(stream: &mut DuplexStream) -> Value {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 256];
+        loop {
+            if let Ok(value) = rmpv::decode::read_value(&mut io::Cursor::new(&buf[..])) {
+                return value;
+            }
+            let n = tokio::time::timeout(Duration::from_millis(500), stream.read(&mut chunk))
+                .await
+                .expect("no reply")
+                .unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_reach_the_editor_and_replies_return() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let hub = RpcHub::default();
+        let (mut remote, local) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(local);
+        serve(read, write, hub.clone(), tx);
+
+        // A request split across two writes is decoded once complete.
+        let bytes = request(7, "command", vec![Value::from("w")]);
+        remote.write_all(&bytes[..3]).await.unwrap();
+        remote.write_all(&bytes[3..]).await.unwrap();
+        let Some(Event::Rpc(req)) = rx.recv().await else {
+            panic!("expected an rpc event");
+        };
+        assert_eq!((req.msgid, req.method.as_str()), (Some(7), "command"));
+        assert_eq!(req.str_param(0), Some("w"));
+
+        hub.reply(req.client, 7, Err("E32: No file name".into()));
+        assert_eq!(
+            read_value(&mut remote).await,
+            Value::Array(vec![
+                Value::from(RESPONSE),
+                Value::from(7),
+                Value::from("E32: No file name"),
+                Value::Nil
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_handled_by_the_server() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let hub = RpcHub::default();
+        let (mut remote, local) = tokio::io::duplex(1024);
+        let (read, write) = tokio::io::split(local);
+        let reader = serve(read, write, hub.clone(), tx);
+
+        remote
+            .write_all(&request(1, "subscribe", vec![Value::from("mode_changed")]))
+            .await
+            .unwrap();
+        assert_eq!(read_value(&mut remote).await[1], Value::from(1));
+        assert!(hub.has_subscribers("mode_changed"));
+        assert!(rx.try_recv().is_err(), "not forwarded to the editor");
+        hub.notify("buf_enter", vec![]);
+        hub.notify("mode_changed", vec![Value::from("insert")]);
+        assert_eq!(
+            read_value(&mut remote).await,
+            Value::Array(vec![
+                Value::from(NOTIFICATION),
+                Value::from("mode_changed"),
+                Value::Array(vec![Value::from("insert")])
+            ])
+        );
+
+        // A malformed message drops the client.
+        remote.write_all(&encode(Value::from(5))).await.unwrap();
+        reader.await.unwrap();
+        assert_eq!(hub.client_count(), 0);
+    }
+
+    #[test]
+    fn frames_resume_across_reads() {
+        let mut bytes = request(3, "command", vec![Value::from("x".repeat(300))]);
+        bytes.extend(encode(Value::Map(vec![(
+            Value::from(1),
+            Value::Array(vec![Value::from(-1), Value::from(1.5), Value::Nil]),
+        )])));
+        let first = request(3, "command", vec![Value::from("x".repeat(300))]).len();
+        let mut frame = Frame::default();
+        for n in 0..first {
+            assert_eq!(frame.scan(&bytes[..n]), Ok(None));
+        }
+        assert_eq!(frame.scan(&bytes[..first]), Ok(Some(first)));
+        let mut frame = Frame::default();
+        assert_eq!(frame.scan(&bytes[first..]), Ok(Some(bytes.len() - first)));
+
+        // A header promising more than a client may send is refused at once.
+        let mut frame = Frame::default();
+        assert_eq!(
+            frame.scan(&[0xdb, 0xff, 0xff, 0xff, 0xff]),
+            Err("message_too_large")
+        );
+        let mut frame = Frame::default();
+        assert_eq!(frame.scan(&[0xc1]), Err("malformed_message"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn binding_leaves_other_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "keep me").unwrap();
+        assert_eq!(
+            RpcServerSource::bind(&path).err().map(|e| e.kind()),
+            Some(io::ErrorKind::AlreadyExists)
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn binding_replaces_a_stale_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ox.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let server = RpcServerSource::bind(&path).expect("stale socket replaced");
+        assert_eq!(
+            RpcServerSource::bind(&path).err().map(|e| e.kind()),
+            Some(io::ErrorKind::AddrInUse)
+        );
+        let (tx, _rx) = mpsc::channel(8);
+        let handle = Box::new(server).spawn(tx);
+        tokio::net::UnixStream::connect(&path).await.unwrap();
+        drop(_rx);
+        handle.await.unwrap();
+        assert!(!path.exists(), "socket removed on shutdown");
+    }
+}
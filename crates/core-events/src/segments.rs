@@ -0,0 +1,294 @@
+//! Status line segment providers.
+//!
+//! A provider (a plugin, a built-in subsystem) computes one short string for
+//! the status line and says when it wants to be asked again through a
+//! `RefreshHint`. The runtime owns a `SegmentRunner` and calls `poll` from
+//! the event loop; due providers run on the blocking pool, never on the
+//! render path, and their answers return as `Event::StatusSegment`.
+//!
+//! Each run is bounded by a timeout (the provider's own or the runner's
+//! default). A run that overruns is abandoned: its answer is discarded, the
+//! segment keeps its previous text, and the provider is not started again
+//! until the abandoned call returns, so a stuck provider costs one blocking
+//! thread rather than one per poll. Answers carry a generation so a late
+//! answer never overwrites a newer one.
+//!
+//! The git branch predates this interface and keeps its own probe
+//! (`GitInfoSource`), which follows the active file's directory; its
+//! segment renders just before the provided ones.
+
+use crate::Event;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+/// Timeout of a provider run unless the provider or config says otherwise.
+pub const DEFAULT_SEGMENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// When a provider's segment should be recomputed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshHint {
+    /// Once at startup.
+    Once,
+    /// Every interval.
+    Every(Duration),
+    /// After the active buffer is edited, written or switched.
+    OnBufferChange,
+}
+
+/// What a provider may look at. Copied into the blocking task.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SegmentContext {
+    pub file: Option<PathBuf>,
+    pub line_count: usize,
+    pub dirty: bool,
+}
+
+pub trait StatusSegmentProvider: Send + Sync {
+    /// Segment name, used for ordering in `[statusline] segments`.
+    fn name(&self) -> &str;
+    fn refresh_hint(&self) -> RefreshHint;
+    /// Per-provider timeout; `None` uses the runner's default.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+    /// Compute the segment; `None` hides it. May block.
+    fn segment(&self, ctx: &SegmentContext) -> Option<String>;
+}
+
+/// A provider's answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentUpdate {
+    pub name: String,
+    pub text: Option<String>,
+    pub generation: u64,
+}
+
+struct ProviderSlot {
+    provider: Arc<dyn StatusSegmentProvider>,
+    /// Set while a call is in flight, cleared when it returns (even late).
+    busy: Arc<AtomicBool>,
+    due: bool,
+    last_start: Option<Instant>,
+    generation: u64,
+}
+
+/// Clears the busy flag when the blocking call ends, panics included.
+struct BusyGuard(Arc<AtomicBool>);
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+pub struct SegmentRunner {
+    slots: Vec<ProviderSlot>,
+    default_timeout: Duration,
+}
+
+impl SegmentRunner {
+    pub fn new(default_timeout: Duration) -> Self {
+        Self {
+            slots: Vec::new(),
+            default_timeout,
+        }
+    }
+
+    /// Add a provider; it runs on the next `poll`. A second provider with
+    /// the same name is ignored.
+    pub fn register(&mut self, provider: Arc<dyn StatusSegmentProvider>) -> bool {
+        if self
+            .slots
+            .iter()
+            .any(|s| s.provider.name() == provider.name())
+        {
+            return false;
+        }
+        self.slots.push(ProviderSlot {
+            provider,
+            busy: Arc::new(AtomicBool::new(false)),
+            due: true,
+            last_start: None,
+            generation: 0,
+        });
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Provider names in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.slots.iter().map(|s| s.provider.name())
+    }
+
+    /// Mark `OnBufferChange` providers due.
+    pub fn buffer_changed(&mut self) {
+        for slot in &mut self.slots {
+            if slot.provider.refresh_hint() == RefreshHint::OnBufferChange {
+                slot.due = true;
+            }
+        }
+    }
+
+    /// Start every due provider that is not still busy.
+    pub fn poll(
+        &mut self,
+        now: Instant,
+        ctx: &SegmentContext,
+        tx: &Sender<Event>,
+    ) -> Vec<JoinHandle<()>> {
+        let mut started = Vec::new();
+        for slot in &mut self.slots {
+            let interval_due = match (slot.provider.refresh_hint(), slot.last_start) {
+                (RefreshHint::Every(every), Some(last)) => now.duration_since(last) >= every,
+                _ => false,
+            };
+            if !(slot.due || interval_due) || slot.busy.load(Ordering::Acquire) {
+                continue;
+            }
+            slot.due = false;
+            slot.last_start = Some(now);
+            slot.generation += 1;
+            slot.busy.store(true, Ordering::Release);
+            let timeout = slot.provider.timeout().unwrap_or(self.default_timeout);
+            started.push(spawn_run(
+                Arc::clone(&slot.provider),
+                BusyGuard(Arc::clone(&slot.busy)),
+                ctx.clone(),
+                slot.generation,
+                timeout,
+                tx.clone(),
+            ));
+        }
+        started
+    }
+
+    /// Whether `update` is the newest answer of a registered provider.
+    pub fn accepts(&self, update: &SegmentUpdate) -> bool {
+        self.slots
+            .iter()
+            .any(|s| s.provider.name() == update.name && s.generation == update.generation)
+    }
+}
+
+fn spawn_run(
+    provider: Arc<dyn StatusSegmentProvider>,
+    guard: BusyGuard,
+    ctx: SegmentContext,
+    generation: u64,
+    timeout: Duration,
+    tx: Sender<Event>,
+) -> JoinHandle<()> {
+    let name = provider.name().to_string();
+    tokio::spawn(async move {
+        let work = tokio::task::spawn_blocking(move || {
+            let _guard = guard;
+            provider.segment(&ctx)
+        });
+        let text = match tokio::time::timeout(timeout, work).await {
+            Ok(Ok(text)) => text,
+            Ok(Err(e)) => {
+                tracing::warn!(target: "runtime.segments", segment = %name, error = %e, "segment_provider_panicked");
+                None
+            }
+            Err(_) => {
+                tracing::warn!(target: "runtime.segments", segment = %name, timeout_ms = timeout.as_millis() as u64, "segment_provider_timed_out");
+                return;
+            }
+        };
+        let _ = tx
+            .send(Event::StatusSegment(SegmentUpdate {
+                name,
+                text,
+                generation,
+            }))
+            .await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    struct Fixed {
+        name: &'static str,
+        hint: RefreshHint,
+        delay: Duration,
+    }
+
+    impl StatusSegmentProvider for Fixed {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn refresh_hint(&self) -> RefreshHint {
+            self.hint
+        }
+        fn segment(&self, ctx: &SegmentContext) -> Option<String> {
+            std::thread::sleep(self.delay);
+            Some(format!("{}:{}", self.name, ctx.line_count))
+        }
+    }
+
+    fn fixed(name: &'static str, hint: RefreshHint, delay_ms: u64) -> Arc<Fixed> {
+        Arc::new(Fixed {
+            name,
+            hint,
+            delay: Duration::from_millis(delay_ms),
+        })
+    }
+
+    async fn next_update(rx: &mut mpsc::Receiver<Event>) -> Option<SegmentUpdate> {
+        match tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+            Ok(Some(Event::StatusSegment(update))) => Some(update),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn due_providers_answer_with_their_generation() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut runner = SegmentRunner::new(DEFAULT_SEGMENT_TIMEOUT);
+        assert!(runner.register(fixed("wc", RefreshHint::OnBufferChange, 0)));
+        assert!(!runner.register(fixed("wc", RefreshHint::Once, 0)));
+        let ctx = SegmentContext {
+            line_count: 3,
+            ..SegmentContext::default()
+        };
+        let now = Instant::now();
+        assert_eq!(runner.poll(now, &ctx, &tx).len(), 1);
+        let update = next_update(&mut rx).await.unwrap();
+        assert_eq!(update.text.as_deref(), Some("wc:3"));
+        assert!(runner.accepts(&update));
+        assert!(runner.poll(now, &ctx, &tx).is_empty(), "not due again yet");
+
+        runner.buffer_changed();
+        assert_eq!(runner.poll(now, &ctx, &tx).len(), 1);
+        assert!(!runner.accepts(&update), "older generation is stale");
+        assert!(runner.accepts(&next_update(&mut rx).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn slow_provider_times_out_without_piling_up() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut runner = SegmentRunner::new(Duration::from_millis(10));
+        runner.register(fixed("slow", RefreshHint::Every(Duration::ZERO), 150));
+        let ctx = SegmentContext::default();
+        for handle in runner.poll(Instant::now(), &ctx, &tx) {
+            handle.await.unwrap();
+        }
+        assert!(rx.try_recv().is_err(), "timed-out answer is dropped");
+        assert!(
+            runner.poll(Instant::now(), &ctx, &tx).is_empty(),
+            "still busy with the abandoned call"
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(runner.poll(Instant::now(), &ctx, &tx).len(), 1);
+    }
+}
@@ -0,0 +1,171 @@
+//! External command event source (`:!`, `:r !`, `:{range}!`).
+//!
+//! Each request runs as a one-shot `AsyncEventSource`: the spawned task
+//! executes the command through the platform shell with stdout/stderr
+//! captured (stdin is either the supplied filter input or closed), then
+//! emits a single `Event::ShellOutput`. Interactive programs that need the
+//! terminal are *not* run here; the runtime handles `:sh` by suspending the
+//! terminal instead.
+
+use crate::{AsyncEventSource, Event};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+/// Captured result of an external command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellOutput {
+    /// Request id assigned when the command was queued.
+    pub id: u64,
+    /// Exit code (`None` when terminated by a signal or never started).
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Spawn / IO failure description.
+    pub error: Option<String>,
+}
+
+impl ShellOutput {
+    pub fn success(&self) -> bool {
+        self.error.is_none() && self.status == Some(0)
+    }
+}
+
+/// One-shot source running `command` via `shell -c` (`/C` on Windows).
+pub struct ShellCommandSource {
+    id: u64,
+    shell: String,
+    command: String,
+    stdin: Option<String>,
+}
+
+impl ShellCommandSource {
+    pub fn new(id: u64, shell: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            id,
+            shell: shell.into(),
+            command: command.into(),
+            stdin: None,
+        }
+    }
+
+    /// Feed `input` to the command's stdin (filter commands).
+    pub fn with_stdin(mut self, input: impl Into<String>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    /// Run to completion, capturing output. Never fails; errors are
+    /// reported through `ShellOutput::error`.
+    pub async fn run(self) -> ShellOutput {
+        let flag = if cfg!(windows) { "/C" } else { "-c" };
+        let mut cmd = tokio::process::Command::new(&self.shell);
+        cmd.arg(flag)
+            .arg(&self.command)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdin(if self.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .kill_on_drop(true);
+        let mut child = match cmd.spawn() {
+            Ok(c) => c,
+            Err(e) => return self.failed(e.to_string()),
+        };
+        if let (Some(input), Some(mut pipe)) = (self.stdin.as_ref(), child.stdin.take()) {
+            // Write on a separate task so large inputs cannot deadlock against
+            // a child blocked on a full stdout pipe.
+            let input = input.clone();
+            tokio::spawn(async move {
+                let _ = pipe.write_all(input.as_bytes()).await;
+                let _ = pipe.shutdown().await;
+            });
+        }
+        match child.wait_with_output().await {
+            Ok(out) => ShellOutput {
+                id: self.id,
+                status: out.status.code(),
+                stdout: String::from_utf8_lossy(&out.stdout).into_owned(),
+                stderr: String::from_utf8_lossy(&out.stderr).into_owned(),
+                error: None,
+            },
+            Err(e) => self.failed(e.to_string()),
+        }
+    }
+
+    fn failed(&self, error: String) -> ShellOutput {
+        ShellOutput {
+            id: self.id,
+            status: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            error: Some(error),
+        }
+    }
+}
+
+impl AsyncEventSource for ShellCommandSource {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let id = self.id;
+            let output = self.run().await;
+            tracing::debug!(
+                target: "runtime.shell",
+                id,
+                status = ?output.status,
+                stdout_bytes = output.stdout.len(),
+                stderr_bytes = output.stderr.len(),
+                error = ?output.error,
+                "shell_command_finished"
+            );
+            let _ = tx.send(Event::ShellOutput(output)).await;
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn captures_stdout_and_exit_status() {
+        let out = ShellCommandSource::new(1, "sh", "echo hi; exit 3")
+            .run()
+            .await;
+        assert_eq!(out.stdout, "hi\n");
+        assert_eq!(out.status, Some(3));
+        assert!(!out.success());
+    }
+
+    #[tokio::test]
+    async fn filter_input_is_piped_to_stdin() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let source = ShellCommandSource::new(7, "sh", "sort").with_stdin("b\na\n");
+        let handle = Box::new(source).spawn(tx);
+        match rx.recv().await {
+            Some(Event::ShellOutput(out)) => {
+                assert_eq!(out.id, 7);
+                assert_eq!(out.stdout, "a\nb\n");
+                assert!(out.success());
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_shell_reports_error() {
+        let out = ShellCommandSource::new(2, "/nonexistent/shell", "true")
+            .run()
+            .await;
+        assert!(out.error.is_some());
+    }
+}
@@ -0,0 +1,77 @@
+//! Telemetry export event source (the `[metrics] export` sink).
+//!
+//! A `TelemetrySource` sends `Event::Telemetry` every interval. The
+//! runtime answers each one by refreshing the input counters and appending
+//! one compact `core_state::metrics_json` line (render path, scheduler
+//! deltas, input and operator counters) to the sink, so a long session can
+//! be analysed afterwards without the metrics overlay. Being a source of
+//! its own, the export keeps its pace however busy or idle the loop is, and
+//! costs nothing when the sink is not configured.
+
+use crate::{AsyncEventSource, Event};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// Shortest interval between two snapshots.
+pub const TELEMETRY_MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Asks for a metrics snapshot every interval.
+pub struct TelemetrySource {
+    interval: Duration,
+}
+
+impl TelemetrySource {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval: interval.max(TELEMETRY_MIN_INTERVAL),
+        }
+    }
+
+    /// Until the receiver is gone. Snapshots the main loop had no time for
+    /// are skipped rather than written in a burst.
+    pub async fn run(self, tx: Sender<Event>) {
+        let mut ticks = tokio::time::interval_at(Instant::now() + self.interval, self.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            ticks.tick().await;
+            if tx.send(Event::Telemetry).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl AsyncEventSource for TelemetrySource {
+    fn name(&self) -> &'static str {
+        "telemetry"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(self.run(tx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn asks_for_snapshots_until_the_receiver_is_gone() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let start = Instant::now();
+        let task = tokio::spawn(TelemetrySource::new(Duration::from_millis(20)).run(tx));
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+            assert!(matches!(event, Ok(Some(Event::Telemetry))), "{event:?}");
+        }
+        assert!(start.elapsed() >= Duration::from_millis(60));
+        drop(rx);
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("the source stops once the loop is gone")
+            .unwrap();
+    }
+}
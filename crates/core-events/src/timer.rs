@@ -0,0 +1,91 @@
+//! Timer event source (`:defer`, `:timer`, plugin timers).
+//!
+//! A `TimerSource` sleeps for its delay and sends `Event::Timer` with its
+//! id. A repeating one then keeps firing every `delay` (at least
+//! `TIMER_MIN_INTERVAL`) until its task is aborted, which is how a timer
+//! is stopped. The callback runs on the main loop when the event arrives,
+//! never on the timer's task, so timers see editor state only between
+//! other events.
+
+use crate::{AsyncEventSource, Event};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// Shortest period of a repeating timer.
+pub const TIMER_MIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// One-shot or repeating timer `id`.
+pub struct TimerSource {
+    id: u64,
+    delay: Duration,
+    repeat: bool,
+}
+
+impl TimerSource {
+    pub fn new(id: u64, delay: Duration, repeat: bool) -> Self {
+        Self { id, delay, repeat }
+    }
+
+    /// Fire once, or until the receiver is gone when repeating. Ticks the
+    /// main loop had no time for are not made up.
+    pub async fn run(self, tx: Sender<Event>) {
+        if !self.repeat {
+            tokio::time::sleep(self.delay).await;
+            let _ = tx.send(Event::Timer(self.id)).await;
+            return;
+        }
+        let period = self.delay.max(TIMER_MIN_INTERVAL);
+        let mut ticks = tokio::time::interval_at(Instant::now() + period, period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            if tx.send(Event::Timer(self.id)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl AsyncEventSource for TimerSource {
+    fn name(&self) -> &'static str {
+        "timer"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(self.run(tx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    async fn next(rx: &mut mpsc::Receiver<Event>) -> Option<u64> {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+            Ok(Some(Event::Timer(id))) => Some(id),
+            Ok(None) => None,
+            other => panic!("expected a timer, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn one_shot_fires_once_and_repeating_until_aborted() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let start = Instant::now();
+        tokio::spawn(TimerSource::new(1, Duration::from_millis(30), false).run(tx));
+        assert_eq!(next(&mut rx).await, Some(1));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(next(&mut rx).await, None, "a one-shot timer is done");
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let repeat = tokio::spawn(TimerSource::new(2, Duration::ZERO, true).run(tx));
+        for _ in 0..3 {
+            assert_eq!(next(&mut rx).await, Some(2));
+        }
+        repeat.abort();
+        while next(&mut rx).await.is_some() {}
+    }
+}
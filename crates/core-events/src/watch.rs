@@ -0,0 +1,283 @@
+//! File watcher: changes made on disk to the files the editor shows.
+//!
+//! `FileWatchSource` is a long-lived `AsyncEventSource` built on `notify`.
+//! The runtime tells it what to watch through a `FileWatchHandle`: files
+//! (the open buffers', the config file) and directories (explorer listings,
+//! a project root). A file is watched through its directory, not watched
+//! itself, so one saved by writing a new file and renaming it over the old
+//! stays watched. The burst of events one save produces settles for
+//! `WATCH_SETTLE`, then each path is reported once as `Event::FileChanged`,
+//! as it was given: the file, or the directory something in it changed in.
+//!
+//! Every change is reported, the editor's own writes included; telling
+//! those apart is up to the receiver (`core_state::file_watch`).
+
+use crate::{AsyncEventSource, Event};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// How long the events of one change are gathered before it is reported.
+pub const WATCH_SETTLE: Duration = Duration::from_millis(100);
+
+/// What to watch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchSet {
+    pub files: BTreeSet<PathBuf>,
+    /// Reported when an entry directly inside them changes.
+    pub dirs: BTreeSet<PathBuf>,
+}
+
+/// Changes what a running `FileWatchSource` watches.
+#[derive(Debug, Clone)]
+pub struct FileWatchHandle {
+    set: watch::Sender<WatchSet>,
+}
+
+impl FileWatchHandle {
+    /// Watch `set` from now on; false when it is already what is watched.
+    pub fn set(&self, set: WatchSet) -> bool {
+        self.set.send_if_modified(|current| {
+            let modified = *current != set;
+            *current = set;
+            modified
+        })
+    }
+}
+
+/// Watcher for the paths of its `FileWatchHandle`.
+pub struct FileWatchSource {
+    set: watch::Sender<WatchSet>,
+}
+
+impl Default for FileWatchSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileWatchSource {
+    /// A source watching nothing until its handle says what.
+    pub fn new() -> Self {
+        Self {
+            set: watch::Sender::new(WatchSet::default()),
+        }
+    }
+
+    pub fn handle(&self) -> FileWatchHandle {
+        FileWatchHandle {
+            set: self.set.clone(),
+        }
+    }
+
+    /// Watch until the receiver is gone.
+    pub async fn run(self, tx: Sender<Event>) {
+        let (raw_tx, mut raw_rx) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let _ = raw_tx.send(event);
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                tracing::warn!(target: "runtime.watch", error = %e, "watcher_start_failed");
+                return;
+            }
+        };
+        let mut set = self.set.subscribe();
+        set.mark_changed();
+        let mut targets = Targets::default();
+        let mut watched = BTreeSet::new();
+        let mut changed = BTreeSet::new();
+        let mut deadline = None;
+        loop {
+            let settled = async {
+                match deadline {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                update = set.changed() => {
+                    if update.is_err() {
+                        break;
+                    }
+                    targets = Targets::new(&set.borrow_and_update());
+                    watched = rewatch(&mut watcher, watched, targets.dirs_to_watch());
+                }
+                Some(event) = raw_rx.recv() => match event {
+                    // Reads, the editor's included, change nothing.
+                    Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                    Ok(event) => {
+                        changed.extend(event.paths.iter().filter_map(|p| targets.lookup(p)).cloned());
+                        if !changed.is_empty() {
+                            deadline.get_or_insert_with(|| Instant::now() + WATCH_SETTLE);
+                        }
+                    }
+                    Err(e) => tracing::debug!(target: "runtime.watch", error = %e, "watch_error"),
+                },
+                () = settled => {
+                    deadline = None;
+                    for path in std::mem::take(&mut changed) {
+                        tracing::debug!(target: "runtime.watch", path = %path.display(), "file_changed");
+                        if tx.send(Event::FileChanged(path)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                () = tx.closed() => break,
+            }
+        }
+    }
+}
+
+impl AsyncEventSource for FileWatchSource {
+    fn name(&self) -> &'static str {
+        "watch"
+    }
+
+    fn spawn(self: Box<Self>, tx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(self.run(tx))
+    }
+}
+
+/// A `WatchSet` keyed the way `notify` reports paths: under the resolved
+/// directory.
+#[derive(Debug, Default)]
+struct Targets {
+    files: HashMap<PathBuf, PathBuf>,
+    dirs: HashMap<PathBuf, PathBuf>,
+}
+
+impl Targets {
+    fn new(set: &WatchSet) -> Self {
+        let mut targets = Self::default();
+        for file in &set.files {
+            let (Some(parent), Some(name)) = (file.parent(), file.file_name()) else {
+                continue;
+            };
+            targets
+                .files
+                .insert(resolve(parent).join(name), file.clone());
+        }
+        for dir in &set.dirs {
+            targets.dirs.insert(resolve(dir), dir.clone());
+        }
+        targets
+    }
+
+    fn dirs_to_watch(&self) -> BTreeSet<PathBuf> {
+        let parents = self.files.keys().filter_map(|file| file.parent());
+        parents
+            .chain(self.dirs.keys().map(PathBuf::as_path))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    /// The watched path a change to `path` is reported as.
+    fn lookup(&self, path: &Path) -> Option<&PathBuf> {
+        self.files
+            .get(path)
+            .or_else(|| self.dirs.get(path))
+            .or_else(|| path.parent().and_then(|dir| self.dirs.get(dir)))
+    }
+}
+
+/// `dir` the way the platform reports events in it: symlinks resolved
+/// where it exists, at least absolute.
+fn resolve(dir: &Path) -> PathBuf {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    std::fs::canonicalize(dir)
+        .or_else(|_| std::path::absolute(dir))
+        .unwrap_or_else(|_| dir.to_path_buf())
+}
+
+/// Watch `wanted` instead of `watched`, returning what is now watched. A
+/// directory that cannot be watched (not created yet) is tried again on
+/// the next change of the set.
+fn rewatch(
+    watcher: &mut impl Watcher,
+    watched: BTreeSet<PathBuf>,
+    wanted: BTreeSet<PathBuf>,
+) -> BTreeSet<PathBuf> {
+    for dir in watched.difference(&wanted) {
+        let _ = watcher.unwatch(dir);
+    }
+    let mut now = BTreeSet::new();
+    for dir in wanted {
+        if watched.contains(&dir) {
+            now.insert(dir);
+            continue;
+        }
+        match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                now.insert(dir);
+            }
+            Err(e) => {
+                tracing::debug!(target: "runtime.watch", dir = %dir.display(), error = %e, "watch_failed");
+            }
+        }
+    }
+    tracing::debug!(target: "runtime.watch", dirs = now.len(), "watch_set_applied");
+    now
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    async fn next_change(rx: &mut mpsc::Receiver<Event>) -> PathBuf {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+            Ok(Some(Event::FileChanged(path))) => path,
+            other => panic!("expected a file change, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_watched_files_once_per_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "one\n").unwrap();
+        let source = FileWatchSource::new();
+        let handle = source.handle();
+        assert!(handle.set(WatchSet {
+            files: BTreeSet::from([file.clone()]),
+            dirs: BTreeSet::new(),
+        }));
+        let (tx, mut rx) = mpsc::channel(8);
+        let task = tokio::spawn(source.run(tx));
+        tokio::time::sleep(WATCH_SETTLE).await;
+
+        // Other files in the directory are not reported.
+        std::fs::write(dir.path().join("b.txt"), "b\n").unwrap();
+        std::fs::write(&file, "two\n").unwrap();
+        std::fs::write(&file, "three\n").unwrap();
+        assert_eq!(next_change(&mut rx).await, file);
+
+        // Saved by renaming a new file over it.
+        let tmp = dir.path().join("a.txt.tmp");
+        std::fs::write(&tmp, "four\n").unwrap();
+        std::fs::rename(&tmp, &file).unwrap();
+        assert_eq!(next_change(&mut rx).await, file);
+        tokio::time::sleep(WATCH_SETTLE * 2).await;
+        assert!(rx.try_recv().is_err(), "one event per change");
+
+        // A watched directory is reported for its entries.
+        assert!(handle.set(WatchSet {
+            files: BTreeSet::new(),
+            dirs: BTreeSet::from([dir.path().to_path_buf()]),
+        }));
+        tokio::time::sleep(WATCH_SETTLE).await;
+        std::fs::write(dir.path().join("c.txt"), "c\n").unwrap();
+        assert_eq!(next_change(&mut rx).await, dir.path());
+        task.abort();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::key_token::{KeyPressParts, map_key_event};
+use crate::key_token::{KeyPressParts, map_key_event, map_mouse_event};
 use crate::log_paste_chunk_flush;
 use core_events::{
     ASYNC_INPUT_STARTS, ASYNC_INPUT_STOP_CHANNEL, ASYNC_INPUT_STOP_ERROR, ASYNC_INPUT_STOP_SIGNAL,
@@ -188,6 +188,18 @@ where
                         break;
                     }
                 }
+                Ok(CEvent::Mouse(mouse)) => {
+                    let Some(mouse) = map_mouse_event(&mouse) else {
+                        continue;
+                    };
+                    trace!(target: "input.event", kind = ?mouse.kind, col = mouse.column, row = mouse.row, "mouse");
+                    if !self
+                        .send_event(Event::Input(InputEvent::Mouse(mouse)))
+                        .await
+                    {
+                        break;
+                    }
+                }
                 Ok(other) => {
                     if matches!(other, CEvent::Key(_)) {
                         // already handled via Key arm
@@ -656,6 +668,27 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn forwards_mouse_click() {
+        let outputs = run_scenario(vec![CEvent::Mouse(crossterm::event::MouseEvent {
+            kind: crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Left),
+            column: 7,
+            row: 3,
+            modifiers: crossterm::event::KeyModifiers::NONE,
+        })])
+        .await;
+
+        assert!(matches!(
+            outputs.as_slice(),
+            [Event::Input(InputEvent::Mouse(core_events::MouseEvent {
+                kind: core_events::MouseEventKind::Down(core_events::MouseButton::Left),
+                column: 7,
+                row: 3,
+                ..
+            }))]
+        ));
+    }
+
     #[tokio::test]
     async fn handles_bracketed_paste_sequence() {
         let outputs = run_scenario(vec![
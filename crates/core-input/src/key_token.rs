@@ -1,7 +1,8 @@
-use core_events::{KeyToken, ModMask, NamedKey};
+use core_events::{KeyToken, ModMask, MouseButton, MouseEvent, MouseEventKind, NamedKey};
 use crossterm::event::{
     KeyCode as CKeyCode, KeyEvent as CKeyEvent, KeyEventKind as CKeyEventKind,
-    KeyModifiers as CKeyModifiers,
+    KeyModifiers as CKeyModifiers, MouseButton as CMouseButton, MouseEvent as CMouseEvent,
+    MouseEventKind as CMouseKind,
 };
 
 /// Result of translating a terminal key event into NGI token components.
@@ -88,6 +89,30 @@ pub(crate) fn map_mod_mask(mods: CKeyModifiers) -> ModMask {
     out
 }
 
+/// Map a crossterm mouse event; horizontal scrolling is not supported.
+pub(crate) fn map_mouse_event(event: &CMouseEvent) -> Option<MouseEvent> {
+    let button = |b: CMouseButton| match b {
+        CMouseButton::Left => MouseButton::Left,
+        CMouseButton::Middle => MouseButton::Middle,
+        CMouseButton::Right => MouseButton::Right,
+    };
+    let kind = match event.kind {
+        CMouseKind::Down(b) => MouseEventKind::Down(button(b)),
+        CMouseKind::Up(b) => MouseEventKind::Up(button(b)),
+        CMouseKind::Drag(b) => MouseEventKind::Drag(button(b)),
+        CMouseKind::ScrollUp => MouseEventKind::ScrollUp,
+        CMouseKind::ScrollDown => MouseEventKind::ScrollDown,
+        CMouseKind::Moved => MouseEventKind::Moved,
+        CMouseKind::ScrollLeft | CMouseKind::ScrollRight => return None,
+    };
+    Some(MouseEvent {
+        kind,
+        column: event.column,
+        row: event.row,
+        mods: map_mod_mask(event.modifiers),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
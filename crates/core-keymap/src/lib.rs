@@ -11,9 +11,16 @@
 //! existing Normal mode translation logic (counts, operators, motions,
 //! register prefix). It does NOT yet integrate timeout handling or layering.
 
+pub use core_events::{KeyToken, ModMask, NamedKey};
 use smallvec::SmallVec;
 use tracing::{debug, trace};
 
+pub mod user;
+pub use user::{
+    MAX_MAP_DEPTH, MappingIssue, NotationError, UserKeymap, compile_user_specs, key_display,
+    key_notation,
+};
+
 // -------------------------------------------------------------------------------------------------
 // Public Symbolic Output (expanded for PendingContext composition)
 // -------------------------------------------------------------------------------------------------
@@ -21,22 +28,47 @@ use tracing::{debug, trace};
 pub enum MappingOutput {
     CountDigit(char),     // '1'..'9' or '0' when extending an existing count
     LeadingZeroLineStart, // solitary '0' with no prior count (Normal mode semantics)
-    Operator(char),       // e.g. 'd', 'y', 'c'
-    Motion(char),         // placeholder: maps to MotionKind in adapter layer
-    RegisterPrefix,       // '"' awaiting register designator
-    RegisterName(char), // emitted only when trie layer chooses (currently unused; kept for future multi-class tokens)
-    PasteAfter,         // 'p'
-    PasteBefore,        // 'P'
-    Undo,               // 'u'
-    Redo,               // <C-r> not represented yet (requires modifier model)
-    EnterInsert,        // 'i'
+    Operator(char), // e.g. 'd', 'y', 'c'; 'z' for 'zf' (create fold), 'l' / 'L' for 'gl' / 'gL' (align)
+    Motion(char),   // placeholder: maps to MotionKind in adapter layer
+    RegisterPrefix, // '"' awaiting register designator
+    RegisterName(char), // key after '"', captured by `MappingTrie::resolve_in`
+    PasteAfter,     // 'p'
+    PasteBefore,    // 'P'
+    Undo,           // 'u'
+    Redo,           // <C-r>
+    EnterInsert,    // 'i'
+    ResumeInsert,   // 'gi' insert where Insert mode was last left
     ModeToggleVisualChar, // 'v'
-    Esc,                // <Esc>
-    DeleteUnder,        // 'x'
-    DeleteLeft,         // 'X'
-    DeleteToLineEnd,    // 'D' shorthand for d$
-    ChangeToLineEnd,    // 'C' shorthand for c$
+    Esc,            // <Esc>
+    DeleteUnder,    // 'x'
+    DeleteLeft,     // 'X'
+    DeleteToLineEnd, // 'D' shorthand for d$
+    ChangeToLineEnd, // 'C' shorthand for c$
+    CmdlineWindow,  // 'q:' open the command-line window
+    CmdlineWindowExecute, // '<CR>' in the command-line window: run the line (see `cmdline_window_specs`)
+    ListEnter, // '<CR>' in a list window or directory listing: open the entry (see `list_window_specs`)
+    UndoOlder, // 'g-' previous text state chronologically
+    UndoNewer, // 'g+' next text state chronologically
+    WindowCommand(char), // '<C-w>{h,j,k,l,w}' window focus; '<C-w><C-w>' maps to 'w'
+    Scroll(char), // '<C-{d,u,f,b}>' half-page / page scroll, keyed by the letter
+    TabNext,   // 'gt' next tab page (or tab N with a count)
+    TabPrev,   // 'gT' previous tab page
+    SearchNext, // 'n' repeat last search
+    SearchPrev, // 'N' repeat last search in the opposite direction
+    DiagnosticNext, // ']d' next diagnostic
+    DiagnosticPrev, // '[d' previous diagnostic
+    Goto { references: bool, split: bool }, // 'gd' / 'gr' definition / references; '<C-w>d' in a split
+    TagPop,             // '<C-t>' / '<C-o>' back to the location before the last 'gd' / 'gr'
+    Hover,              // 'K' documentation for the identifier under the cursor
+    Fold(char),         // 'z{o,c,a}' open / close / toggle the fold at the cursor
+    ScrollCursor(char), // 'z{z,t,b}' put the cursor line at the center / top / bottom
+    InspectChar(char),  // 'ga' codepoints / 'g8' UTF-8 bytes of the character under the cursor
+    TextObject { object: char, around: bool }, // operator-pending 'i{object}' / 'a{object}'
+    DiffHunk(char),     // operator-pending 'o' / 'p' after 'd': 'do' / 'dp' diff obtain / put
+    Explorer(char),     // directory listing keys '-' '%' 'd' 'D' 'R' 's' 'r', 'gh' as 'h'
     Literal(char),      // fallback literal / command char (':' etc.)
+    Keys(Vec<KeyToken>), // user `noremap` right-hand side, fed back as keys (see `user`)
+    RemapKeys(Vec<KeyToken>), // user `map` right-hand side, fed back through user mappings too
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -80,6 +112,16 @@ pub enum ComposedAction {
         count: u32,
         register: Option<char>,
     },
+    /// Operator over a text object: `diw`, `2ca(`, `"ayap`. `object` is the
+    /// key after `i` / `a` (one of `TEXT_OBJECT_KEYS`); `around` is the `a`
+    /// form.
+    ApplyOperatorTextObject {
+        op: char,
+        object: char,
+        around: bool,
+        count: u32,
+        register: Option<char>,
+    },
     PasteAfter {
         count: u32,
         register: Option<char>,
@@ -89,9 +131,14 @@ pub enum ComposedAction {
         register: Option<char>,
     },
     EnterInsert,
+    /// `gi`: Insert mode at the buffer's last insert position.
+    ResumeInsert,
     Undo {
         count: u32,
     },
+    Redo {
+        count: u32,
+    },
     ModeToggleVisualChar,
     DeleteUnder {
         count: u32,
@@ -101,10 +148,85 @@ pub enum ComposedAction {
         count: u32,
         register: Option<char>,
     },
+    CmdlineWindow,
+    CmdlineWindowExecute,
+    ListEnter,
+    /// `g-` / `g+`: walk the undo tree chronologically `count` states.
+    UndoChrono {
+        newer: bool,
+        count: u32,
+    },
+    /// `gt` / `gT`. `count` is kept optional because `{N}gt` means "tab N"
+    /// rather than N steps.
+    TabSwitch {
+        backward: bool,
+        count: Option<u32>,
+    },
+    /// `n` / `N`: repeat the last search `count` times.
+    SearchNext {
+        reverse: bool,
+        count: u32,
+    },
+    /// `]d` / `[d`: jump `count` diagnostics forward or back.
+    DiagnosticJump {
+        backward: bool,
+        count: u32,
+    },
+    /// `gd` / `gr` (`<C-w>d`: `split`): go to the definition or the
+    /// references of the symbol under the cursor.
+    Goto {
+        references: bool,
+        split: bool,
+    },
+    /// `<C-t>` / `<C-o>`: pop `count` entries off the tag stack.
+    TagPop {
+        count: u32,
+    },
+    /// `K`: hover documentation for the identifier under the cursor.
+    Hover,
+    /// `<C-w>{cmd}` window command repeated `count` times.
+    WindowCommand {
+        cmd: char,
+        count: u32,
+    },
+    /// `<C-d>` / `<C-u>` / `<C-f>` / `<C-b>`. The count stays optional: it
+    /// sets the half-page amount rather than repeating.
+    Scroll {
+        cmd: char,
+        count: Option<u32>,
+    },
+    /// `zo` / `zc` / `za` on the fold at the cursor.
+    Fold {
+        cmd: char,
+    },
+    /// `ga` / `g8`.
+    InspectChar {
+        cmd: char,
+    },
+    /// `do` / `dp`: get the diff hunk at the cursor from the other buffer,
+    /// or put it there.
+    DiffHunk {
+        cmd: char,
+    },
+    /// A key of a directory listing (see `explorer_specs`).
+    Explorer {
+        cmd: char,
+    },
+    /// `zz` / `zt` / `zb`; a count names the line to bring there.
+    ScrollCursor {
+        cmd: char,
+        count: Option<u32>,
+    },
     Literal(char),
     None, // no emission (still accumulating state)
 }
 
+/// Characters accepted after `"` as a register name: named (`a-z`, `A-Z`),
+/// numbered (`0-9`), unnamed (`"`), small delete (`-`) and expression (`=`).
+pub fn is_register_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '"' | '-' | '=')
+}
+
 /// Feed a single MappingOutput through the PendingContext, possibly producing a ComposedAction.
 pub fn compose_with_context(ctx: &mut PendingContext, out: &MappingOutput) -> ComposedAction {
     match out {
@@ -246,14 +368,157 @@ pub fn compose_with_context(ctx: &mut PendingContext, out: &MappingOutput) -> Co
             debug!(target = "input.context", count, "undo_emit");
             ComposedAction::Undo { count }
         }
+        MappingOutput::Redo => {
+            let count = ctx.count_prefix.take().unwrap_or(1).max(1);
+            debug!(target = "input.context", count, "redo_emit");
+            ComposedAction::Redo { count }
+        }
+        MappingOutput::UndoOlder | MappingOutput::UndoNewer => {
+            let count = ctx.count_prefix.take().unwrap_or(1).max(1);
+            let newer = matches!(out, MappingOutput::UndoNewer);
+            debug!(target = "input.context", count, newer, "undo_chrono_emit");
+            ComposedAction::UndoChrono { newer, count }
+        }
+        MappingOutput::WindowCommand(cmd) => {
+            let count = ctx.count_prefix.take().unwrap_or(1).max(1);
+            ctx.reset_transient();
+            debug!(target = "input.context", cmd = %cmd, count, "window_command_emit");
+            ComposedAction::WindowCommand { cmd: *cmd, count }
+        }
+        MappingOutput::Scroll(cmd) => {
+            let count = ctx.count_prefix.take();
+            // Not a motion: a pending operator is dropped, as in Vim.
+            ctx.reset_transient();
+            debug!(target = "input.context", cmd = %cmd, ?count, "scroll_emit");
+            ComposedAction::Scroll { cmd: *cmd, count }
+        }
+        MappingOutput::TabNext | MappingOutput::TabPrev => {
+            let count = ctx.count_prefix.take();
+            let backward = matches!(out, MappingOutput::TabPrev);
+            ctx.reset_transient();
+            debug!(
+                target = "input.context",
+                ?count,
+                backward,
+                "tab_switch_emit"
+            );
+            ComposedAction::TabSwitch { backward, count }
+        }
+        MappingOutput::SearchNext | MappingOutput::SearchPrev => {
+            let count = ctx.count_prefix.take().unwrap_or(1).max(1);
+            let reverse = matches!(out, MappingOutput::SearchPrev);
+            ctx.reset_transient();
+            debug!(target = "input.context", count, reverse, "search_next_emit");
+            ComposedAction::SearchNext { reverse, count }
+        }
+        MappingOutput::DiagnosticNext | MappingOutput::DiagnosticPrev => {
+            let count = ctx.count_prefix.take().unwrap_or(1).max(1);
+            let backward = matches!(out, MappingOutput::DiagnosticPrev);
+            ctx.reset_transient();
+            debug!(
+                target = "input.context",
+                count, backward, "diagnostic_jump_emit"
+            );
+            ComposedAction::DiagnosticJump { backward, count }
+        }
+        MappingOutput::Goto { references, split } => {
+            ctx.reset_transient();
+            debug!(target = "input.context", references, split, "goto_emit");
+            ComposedAction::Goto {
+                references: *references,
+                split: *split,
+            }
+        }
+        MappingOutput::TagPop => {
+            let count = ctx.count_prefix.take().unwrap_or(1).max(1);
+            ctx.reset_transient();
+            debug!(target = "input.context", count, "tag_pop_emit");
+            ComposedAction::TagPop { count }
+        }
+        MappingOutput::Hover => {
+            ctx.reset_transient();
+            debug!(target = "input.context", "hover_emit");
+            ComposedAction::Hover
+        }
         MappingOutput::EnterInsert => {
             debug!(target = "input.context", "enter_insert_emit");
             ComposedAction::EnterInsert
         }
+        MappingOutput::ResumeInsert => {
+            ctx.reset_transient();
+            debug!(target = "input.context", "resume_insert_emit");
+            ComposedAction::ResumeInsert
+        }
         MappingOutput::ModeToggleVisualChar => {
             debug!(target = "input.context", "visual_toggle_emit");
             ComposedAction::ModeToggleVisualChar
         }
+        MappingOutput::Fold(cmd) => {
+            ctx.reset_transient();
+            debug!(target = "input.context", cmd = %cmd, "fold_command_emit");
+            ComposedAction::Fold { cmd: *cmd }
+        }
+        MappingOutput::InspectChar(cmd) => {
+            ctx.reset_transient();
+            debug!(target = "input.context", cmd = %cmd, "inspect_char_emit");
+            ComposedAction::InspectChar { cmd: *cmd }
+        }
+        MappingOutput::TextObject { object, around } => {
+            // Objects are only keys while an operator waits (see
+            // `baseline_operator_pending_specs`).
+            let Some(op) = ctx.operator.take() else {
+                ctx.reset_transient();
+                return ComposedAction::None;
+            };
+            let prefix = ctx.count_prefix.take().unwrap_or(1);
+            let post = ctx.post_op_count.take().unwrap_or(1);
+            let total = prefix.saturating_mul(post).min(999_999);
+            let reg = ctx.register.take();
+            debug!(target = "input.context", op = %op, object = %object, around, count = total, register = ?reg, "apply_operator_text_object");
+            ComposedAction::ApplyOperatorTextObject {
+                op,
+                object: *object,
+                around: *around,
+                count: total,
+                register: reg,
+            }
+        }
+        MappingOutput::DiffHunk(cmd) => {
+            // Only keys after `d` (see `baseline_operator_pending_specs`).
+            let op = ctx.operator.take();
+            ctx.reset_transient();
+            if op != Some('d') {
+                return ComposedAction::None;
+            }
+            debug!(target = "input.context", cmd = %cmd, "diff_hunk_emit");
+            ComposedAction::DiffHunk { cmd: *cmd }
+        }
+        MappingOutput::Explorer(cmd) => {
+            ctx.reset_transient();
+            debug!(target = "input.context", cmd = %cmd, "explorer_emit");
+            ComposedAction::Explorer { cmd: *cmd }
+        }
+        MappingOutput::ScrollCursor(cmd) => {
+            let count = ctx.count_prefix.take();
+            ctx.reset_transient();
+            debug!(target = "input.context", cmd = %cmd, ?count, "scroll_cursor_emit");
+            ComposedAction::ScrollCursor { cmd: *cmd, count }
+        }
+        MappingOutput::CmdlineWindow => {
+            ctx.reset_transient();
+            debug!(target = "input.context", "cmdline_window_emit");
+            ComposedAction::CmdlineWindow
+        }
+        MappingOutput::CmdlineWindowExecute => {
+            ctx.reset_transient();
+            debug!(target = "input.context", "cmdline_window_execute_emit");
+            ComposedAction::CmdlineWindowExecute
+        }
+        MappingOutput::ListEnter => {
+            ctx.reset_transient();
+            debug!(target = "input.context", "list_enter_emit");
+            ComposedAction::ListEnter
+        }
         MappingOutput::DeleteUnder => {
             let count = ctx.count_prefix.take().unwrap_or(1).max(1);
             let reg = ctx.register.take();
@@ -320,7 +585,8 @@ pub fn compose_with_context(ctx: &mut PendingContext, out: &MappingOutput) -> Co
             debug!(target="input.context", ch=%c, "literal_emit");
             ComposedAction::Literal(*c)
         }
-        MappingOutput::Redo => ComposedAction::None, // not yet modeled (modifiers missing)
+        // Expanded by the translator before composition; never composed.
+        MappingOutput::Keys(_) | MappingOutput::RemapKeys(_) => ComposedAction::None,
     }
 }
 
@@ -330,16 +596,76 @@ pub fn compose_with_context(ctx: &mut PendingContext, out: &MappingOutput) -> Co
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum KeyTokenPattern {
     Char(char),
+    /// `<C-{letter}>` chord.
+    Ctrl(char),
+    /// A key without a character (`<Up>`, `<F5>`).
+    Named(NamedKey),
+    /// Any other token, as user notation produces it (`<M-x>`, `<S-Up>`).
+    Token(KeyToken),
 }
 
 impl KeyTokenPattern {
-    fn matches(&self, ch: char) -> bool {
+    /// The token this pattern matches in the input buffer, in `canonical`
+    /// form.
+    pub fn token(&self) -> KeyToken {
         match self {
-            KeyTokenPattern::Char(c) => *c == ch,
+            KeyTokenPattern::Char(c) => KeyToken::Char(*c),
+            KeyTokenPattern::Ctrl(c) => ctrl(*c),
+            KeyTokenPattern::Named(key) => KeyToken::Named(*key),
+            KeyTokenPattern::Token(token) => canonical(token),
         }
     }
 }
 
+/// The `<C-{c}>` chord.
+pub fn ctrl(c: char) -> KeyToken {
+    canonical(&KeyToken::Chord {
+        base: Box::new(KeyToken::Char(c)),
+        mods: ModMask::CTRL,
+    })
+}
+
+/// The one spelling of `token` the trie compares: nested chords flattened
+/// into a single modifier mask, Shift dropped from characters (their case
+/// carries it), Ctrl letters lowercased (`<C-W>` is `<C-w>`, as in Vim) and
+/// a chord without modifiers reduced to its base key.
+pub fn canonical(token: &KeyToken) -> KeyToken {
+    let mut mods = ModMask::empty();
+    let mut base = token;
+    while let KeyToken::Chord {
+        base: inner,
+        mods: m,
+    } = base
+    {
+        mods |= *m;
+        base = inner;
+    }
+    let base = match base {
+        KeyToken::Char(c) => {
+            mods.remove(ModMask::SHIFT);
+            if mods.contains(ModMask::CTRL) {
+                KeyToken::Char(c.to_ascii_lowercase())
+            } else {
+                KeyToken::Char(*c)
+            }
+        }
+        other => other.clone(),
+    };
+    if mods.is_empty() {
+        base
+    } else {
+        KeyToken::Chord {
+            base: Box::new(base),
+            mods,
+        }
+    }
+}
+
+/// Whether `token` is a Ctrl chord (`<C-w>`, `<C-Up>`).
+pub fn is_ctrl_chord(token: &KeyToken) -> bool {
+    matches!(token, KeyToken::Chord { mods, .. } if mods.contains(ModMask::CTRL))
+}
+
 // -------------------------------------------------------------------------------------------------
 // Mapping Specification
 // -------------------------------------------------------------------------------------------------
@@ -354,7 +680,7 @@ pub struct MappingSpec {
 // -------------------------------------------------------------------------------------------------
 #[derive(Debug, Clone)]
 struct Edge {
-    pat: KeyTokenPattern,
+    key: KeyToken,
     next: usize,
 }
 
@@ -388,16 +714,15 @@ impl MappingTrie {
         for (idx, m) in trie.mappings.iter().enumerate() {
             let mut cur = 0usize;
             for pat in &m.sequence {
-                // find or create edge
-                let next = if let Some(e) = trie.nodes[cur].edges.iter().find(|e| e.pat == *pat) {
+                // Patterns matching the same buffered token (`Ctrl('w')` and
+                // `Token(<C-W>)`) share an edge, so later specs override.
+                let key = pat.token();
+                let next = if let Some(e) = trie.nodes[cur].edges.iter().find(|e| e.key == key) {
                     e.next
                 } else {
                     let new_idx = trie.nodes.len();
                     trie.nodes.push(Node::new());
-                    trie.nodes[cur].edges.push(Edge {
-                        pat: pat.clone(),
-                        next: new_idx,
-                    });
+                    trie.nodes[cur].edges.push(Edge { key, next: new_idx });
                     new_idx
                 };
                 cur = next;
@@ -416,15 +741,41 @@ impl MappingTrie {
         trie
     }
 
-    pub fn resolve(&self, buffer: &[char]) -> Resolution {
+    /// `resolve` under the pending context: while `ctx` awaits a register
+    /// name, the first key is that name (`RegisterName`) whatever mappings
+    /// start with it, or `InvalidRegister` when it names none.
+    pub fn resolve_in(&self, buffer: &[KeyToken], ctx: &PendingContext) -> Resolution {
+        match buffer.first() {
+            Some(key) if ctx.awaiting_register => {
+                trace!(target = "input.map", key = ?key, "register_capture");
+                match key {
+                    KeyToken::Char(c) if is_register_name(*c) => Resolution::Matched {
+                        consumed: 1,
+                        output: MappingOutput::RegisterName(*c),
+                        ambiguous: false,
+                    },
+                    _ => Resolution::InvalidRegister(key.clone()),
+                }
+            }
+            _ => self.resolve(buffer),
+        }
+    }
+
+    /// Longest mapping at the start of `buffer`. A match is `ambiguous` only
+    /// when the whole buffer was walked and longer mappings continue it; a
+    /// walk stopped by a key no mapping continues with falls back to the
+    /// first key as a literal rather than waiting for more. The buffer holds
+    /// `canonical` tokens.
+    pub fn resolve(&self, buffer: &[KeyToken]) -> Resolution {
         let mut node_idx = 0usize;
+        let mut walked = 0usize;
         let mut last_terminal: Option<(usize, usize)> = None; // (consumed, mapping index)
-        for (i, ch) in buffer.iter().enumerate() {
+        for (i, key) in buffer.iter().enumerate() {
             let mut advanced = false;
             for edge in &self.nodes[node_idx].edges {
-                if edge.pat.matches(*ch) {
+                if edge.key == *key {
                     node_idx = edge.next;
-                    trace!(target = "input.map", step = i, ch = %ch, node = node_idx, "advance");
+                    trace!(target = "input.map", step = i, key = ?key, node = node_idx, "advance");
                     if let Some(mi) = self.nodes[node_idx].terminal {
                         last_terminal = Some((i + 1, mi));
                     }
@@ -435,21 +786,21 @@ impl MappingTrie {
             if !advanced {
                 break;
             }
+            walked = i + 1;
         }
+        let open = walked == buffer.len() && !self.nodes[node_idx].edges.is_empty();
         if let Some((consumed, mi)) = last_terminal {
             Resolution::Matched {
                 consumed,
                 output: self.mappings[mi].output.clone(),
-                ambiguous: !self.nodes[node_idx].edges.is_empty(),
+                ambiguous: open,
             }
         } else if !buffer.is_empty() {
-            // If we consumed nothing (still at root) and found no edge, fallback literal.
-            if node_idx == 0 {
-                Resolution::FallbackLiteral(buffer[0])
-            } else if !self.nodes[node_idx].edges.is_empty() {
+            // A strict prefix of some mapping waits; anything else is literal.
+            if node_idx != 0 && open {
                 Resolution::NeedMore
             } else {
-                Resolution::FallbackLiteral(buffer[0])
+                Resolution::FallbackLiteral(buffer[0].clone())
             }
         } else {
             Resolution::NeedMore
@@ -468,7 +819,85 @@ pub enum Resolution {
         ambiguous: bool,
     },
     NeedMore, // strict prefix of one or more mappings (ambiguous)
-    FallbackLiteral(char),
+    FallbackLiteral(KeyToken),
+    /// Key after `"` that names no register (`resolve_in` only).
+    InvalidRegister(KeyToken),
+}
+
+// -------------------------------------------------------------------------------------------------
+// Mapping Layers: one trie per mode; the resolver picks the layer for the current mode
+// -------------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapMode {
+    Normal,
+    /// Normal mode with an operator waiting for its motion (`:omap`).
+    OperatorPending,
+    Insert,
+    Visual,
+    /// `:` commands and `/` `?` searches (`:cmap`).
+    CommandLine,
+}
+
+impl MapMode {
+    /// Normal, or operator-pending while `ctx` holds an operator.
+    pub fn for_normal(ctx: &PendingContext) -> Self {
+        if ctx.operator.is_some() {
+            MapMode::OperatorPending
+        } else {
+            MapMode::Normal
+        }
+    }
+}
+
+/// Tries per `MapMode`. Normal always has one (built-in keys plus user
+/// mappings). Operator-pending falls back to it when the user mapped
+/// nothing there; the other modes have no built-in trie, so their layer
+/// exists only once a user mapping is added to it.
+#[derive(Debug)]
+pub struct MappingLayers {
+    normal: MappingTrie,
+    operator_pending: Option<MappingTrie>,
+    insert: Option<MappingTrie>,
+    visual: Option<MappingTrie>,
+    command_line: Option<MappingTrie>,
+}
+
+impl MappingLayers {
+    pub fn new(normal: MappingTrie) -> Self {
+        Self {
+            normal,
+            operator_pending: None,
+            insert: None,
+            visual: None,
+            command_line: None,
+        }
+    }
+
+    pub fn set(&mut self, mode: MapMode, trie: MappingTrie) {
+        match mode {
+            MapMode::Normal => self.normal = trie,
+            MapMode::OperatorPending => self.operator_pending = Some(trie),
+            MapMode::Insert => self.insert = Some(trie),
+            MapMode::Visual => self.visual = Some(trie),
+            MapMode::CommandLine => self.command_line = Some(trie),
+        }
+    }
+
+    pub fn get(&self, mode: MapMode) -> Option<&MappingTrie> {
+        match mode {
+            MapMode::Normal => Some(&self.normal),
+            MapMode::OperatorPending => self.operator_pending.as_ref().or(Some(&self.normal)),
+            MapMode::Insert => self.insert.as_ref(),
+            MapMode::Visual => self.visual.as_ref(),
+            MapMode::CommandLine => self.command_line.as_ref(),
+        }
+    }
+
+    /// `MappingTrie::resolve` on the layer of `mode`; `None` when the mode
+    /// has no layer.
+    pub fn resolve(&self, mode: MapMode, buffer: &[KeyToken]) -> Option<Resolution> {
+        self.get(mode).map(|trie| trie.resolve(buffer))
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -513,6 +942,11 @@ pub fn baseline_normal_specs() -> Vec<MappingSpec> {
             sequence: vec![K::Char('j')],
             output: MappingOutput::Motion('j'),
         },
+        // Vim's first-non-blank adjustment is not applied.
+        MappingSpec {
+            sequence: vec![K::Named(NamedKey::Enter)],
+            output: MappingOutput::Motion('j'),
+        },
         MappingSpec {
             sequence: vec![K::Char('p')],
             output: MappingOutput::PasteAfter,
@@ -525,6 +959,83 @@ pub fn baseline_normal_specs() -> Vec<MappingSpec> {
             sequence: vec![K::Char('u')],
             output: MappingOutput::Undo,
         },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('-')],
+            output: MappingOutput::UndoOlder,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('+')],
+            output: MappingOutput::UndoNewer,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('t')],
+            output: MappingOutput::TabNext,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('T')],
+            output: MappingOutput::TabPrev,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('z'), K::Char('f')],
+            output: MappingOutput::Operator('z'),
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('l')],
+            output: MappingOutput::Operator('l'),
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('L')],
+            output: MappingOutput::Operator('L'),
+        },
+        MappingSpec {
+            sequence: vec![K::Char('n')],
+            output: MappingOutput::SearchNext,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('N')],
+            output: MappingOutput::SearchPrev,
+        },
+        MappingSpec {
+            sequence: vec![K::Char(']'), K::Char('d')],
+            output: MappingOutput::DiagnosticNext,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('['), K::Char('d')],
+            output: MappingOutput::DiagnosticPrev,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('d')],
+            output: MappingOutput::Goto {
+                references: false,
+                split: false,
+            },
+        },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('r')],
+            output: MappingOutput::Goto {
+                references: true,
+                split: false,
+            },
+        },
+        MappingSpec {
+            sequence: vec![K::Ctrl('w'), K::Char('d')],
+            output: MappingOutput::Goto {
+                references: false,
+                split: true,
+            },
+        },
+        MappingSpec {
+            sequence: vec![K::Ctrl('t')],
+            output: MappingOutput::TagPop,
+        },
+        MappingSpec {
+            sequence: vec![K::Ctrl('o')],
+            output: MappingOutput::TagPop,
+        },
+        MappingSpec {
+            sequence: vec![K::Char('K')],
+            output: MappingOutput::Hover,
+        },
         MappingSpec {
             sequence: vec![K::Char('x')],
             output: MappingOutput::DeleteUnder,
@@ -537,6 +1048,10 @@ pub fn baseline_normal_specs() -> Vec<MappingSpec> {
             sequence: vec![K::Char('i')],
             output: MappingOutput::EnterInsert,
         },
+        MappingSpec {
+            sequence: vec![K::Char('g'), K::Char('i')],
+            output: MappingOutput::ResumeInsert,
+        },
         MappingSpec {
             sequence: vec![K::Char('D')],
             output: MappingOutput::DeleteToLineEnd,
@@ -561,7 +1076,61 @@ pub fn baseline_normal_specs() -> Vec<MappingSpec> {
             sequence: vec![K::Char('"')],
             output: MappingOutput::RegisterPrefix,
         },
+        MappingSpec {
+            sequence: vec![K::Char('q'), K::Char(':')],
+            output: MappingOutput::CmdlineWindow,
+        },
+        MappingSpec {
+            sequence: vec![K::Ctrl('w'), K::Ctrl('w')],
+            output: MappingOutput::WindowCommand('w'),
+        },
+        MappingSpec {
+            sequence: vec![K::Ctrl('r')],
+            output: MappingOutput::Redo,
+        },
     ];
+    // Arrow keys are the `hjkl` motions, counts and operators included.
+    for (key, motion) in [
+        (NamedKey::Left, 'h'),
+        (NamedKey::Down, 'j'),
+        (NamedKey::Up, 'k'),
+        (NamedKey::Right, 'l'),
+    ] {
+        v.push(MappingSpec {
+            sequence: vec![K::Named(key)],
+            output: MappingOutput::Motion(motion),
+        });
+    }
+    for c in ['d', 'u', 'f', 'b'] {
+        v.push(MappingSpec {
+            sequence: vec![K::Ctrl(c)],
+            output: MappingOutput::Scroll(c),
+        });
+    }
+    for c in ['h', 'j', 'k', 'l', 'w'] {
+        v.push(MappingSpec {
+            sequence: vec![K::Ctrl('w'), K::Char(c)],
+            output: MappingOutput::WindowCommand(c),
+        });
+    }
+    for c in ['o', 'c', 'a'] {
+        v.push(MappingSpec {
+            sequence: vec![K::Char('z'), K::Char(c)],
+            output: MappingOutput::Fold(c),
+        });
+    }
+    for c in ['a', '8'] {
+        v.push(MappingSpec {
+            sequence: vec![K::Char('g'), K::Char(c)],
+            output: MappingOutput::InspectChar(c),
+        });
+    }
+    for c in ['z', 't', 'b'] {
+        v.push(MappingSpec {
+            sequence: vec![K::Char('z'), K::Char(c)],
+            output: MappingOutput::ScrollCursor(c),
+        });
+    }
     // digits 1-9
     for d in ['1', '2', '3', '4', '5', '6', '7', '8', '9'] {
         v.push(MappingSpec {
@@ -569,8 +1138,90 @@ pub fn baseline_normal_specs() -> Vec<MappingSpec> {
             output: MappingOutput::CountDigit(d),
         });
     }
-    // NOTE: We intentionally do NOT add generic register name tokens to the trie yet.
-    // Register capture after '"' is handled by compose layer inspecting subsequent Literal outputs.
+    // Register names after '"' are not trie keys; `MappingTrie::resolve_in` captures them.
+    v
+}
+
+/// Keys naming a text object after `i` / `a` (Vim's `aw` ... `it`).
+pub const TEXT_OBJECT_KEYS: [char; 18] = [
+    'w', 'W', 's', 'p', '[', ']', '(', ')', 'b', '<', '>', 't', '{', '}', 'B', '"', '\'', '`',
+];
+
+/// Operator-pending keys: the Normal ones, except that `i` and `a` start a
+/// text object (`MappingOutput::TextObject`) instead of Insert mode and `o`
+/// and `p` finish `do` / `dp` (`MappingOutput::DiffHunk`).
+pub fn baseline_operator_pending_specs() -> Vec<MappingSpec> {
+    use KeyTokenPattern as K;
+    let mut v: Vec<MappingSpec> = baseline_normal_specs()
+        .into_iter()
+        .filter(|spec| !matches!(spec.sequence[..], [K::Char('i' | 'a' | 'o' | 'p'), ..]))
+        .collect();
+    for c in ['o', 'p'] {
+        v.push(MappingSpec {
+            sequence: vec![K::Char(c)],
+            output: MappingOutput::DiffHunk(c),
+        });
+    }
+    for (prefix, around) in [('i', false), ('a', true)] {
+        for object in TEXT_OBJECT_KEYS {
+            v.push(MappingSpec {
+                sequence: vec![K::Char(prefix), K::Char(object)],
+                output: MappingOutput::TextObject { object, around },
+            });
+        }
+    }
+    v
+}
+
+/// Normal keys of the command-line window: `<CR>` runs the line under the
+/// cursor instead of moving down.
+pub fn cmdline_window_specs() -> Vec<MappingSpec> {
+    let mut v = baseline_normal_specs();
+    v.push(MappingSpec {
+        sequence: vec![KeyTokenPattern::Named(NamedKey::Enter)],
+        output: MappingOutput::CmdlineWindowExecute,
+    });
+    v
+}
+
+/// Normal keys of a quickfix or location list window: `<CR>` opens the
+/// entry under the cursor instead of moving down.
+pub fn list_window_specs() -> Vec<MappingSpec> {
+    let mut v = baseline_normal_specs();
+    v.push(MappingSpec {
+        sequence: vec![KeyTokenPattern::Named(NamedKey::Enter)],
+        output: MappingOutput::ListEnter,
+    });
+    v
+}
+
+/// Normal keys of a directory listing (`core_state::explorer`), which is
+/// read-only: netrw's `-` (parent directory), `%` (new file), `d` (new
+/// directory), `D` (delete), `R` (rename), `s` / `r` (sort order / reverse
+/// it) and `gh` (toggle dotfiles) replace the Normal keys starting with
+/// them, and `<CR>` opens the entry under the cursor as in a list window;
+/// motions and the rest stay as they are.
+pub fn explorer_specs() -> Vec<MappingSpec> {
+    use KeyTokenPattern as K;
+    const KEYS: [char; 7] = ['-', '%', 'd', 'D', 'R', 's', 'r'];
+    let mut v: Vec<MappingSpec> = list_window_specs()
+        .into_iter()
+        .filter(|spec| match spec.sequence[..] {
+            [K::Char(c), ..] if KEYS.contains(&c) => false,
+            [K::Char('g'), K::Char('h'), ..] => false,
+            _ => true,
+        })
+        .collect();
+    for c in KEYS {
+        v.push(MappingSpec {
+            sequence: vec![K::Char(c)],
+            output: MappingOutput::Explorer(c),
+        });
+    }
+    v.push(MappingSpec {
+        sequence: vec![K::Char('g'), K::Char('h')],
+        output: MappingOutput::Explorer('h'),
+    });
     v
 }
 
@@ -581,50 +1232,52 @@ pub fn baseline_normal_specs() -> Vec<MappingSpec> {
 mod tests {
     use super::*;
 
+    /// Tokens of `seq`, with control codes standing for their Ctrl chords.
+    fn keys(seq: &str) -> Vec<KeyToken> {
+        seq.chars()
+            .map(|c| match c {
+                '\x01'..='\x1a' => ctrl((c as u8 + b'`') as char),
+                _ => KeyToken::Char(c),
+            })
+            .collect()
+    }
+
     fn feed(seq: &str) -> Vec<ComposedAction> {
         let specs = baseline_normal_specs();
         let trie = MappingTrie::build(specs);
         let mut ctx = PendingContext::default();
         let mut out = Vec::new();
-        let chars: Vec<char> = seq.chars().collect();
+        let chars = keys(seq);
         let mut i = 0;
         while i < chars.len() {
             let slice = &chars[i..];
-            match trie.resolve(slice) {
+            match trie.resolve_in(slice, &ctx) {
                 Resolution::Matched {
                     consumed, output, ..
                 } => {
-                    let mut out_tok = output.clone();
-                    if ctx.awaiting_register
-                        && let MappingOutput::Literal(c) = out_tok
-                        && c.is_ascii_alphanumeric()
-                    {
-                        out_tok = MappingOutput::RegisterName(c);
-                    }
-                    let composed = compose_with_context(&mut ctx, &out_tok);
+                    let composed = compose_with_context(&mut ctx, &output);
                     if let ComposedAction::None = composed { /* continue */
                     } else {
                         out.push(composed);
                     }
                     i += consumed;
                 }
-                Resolution::FallbackLiteral(c) => {
-                    if ctx.awaiting_register && c.is_ascii_alphanumeric() {
-                        let composed =
-                            compose_with_context(&mut ctx, &MappingOutput::RegisterName(c));
-                        if let ComposedAction::None = composed {
-                        } else {
-                            out.push(composed);
-                        }
+                Resolution::FallbackLiteral(key) => {
+                    let KeyToken::Char(c) = key else {
+                        panic!("{seq}: unmapped {key:?}");
+                    };
+                    let composed = compose_with_context(&mut ctx, &MappingOutput::Literal(c));
+                    if let ComposedAction::None = composed {
                     } else {
-                        let composed = compose_with_context(&mut ctx, &MappingOutput::Literal(c));
-                        if let ComposedAction::None = composed {
-                        } else {
-                            out.push(composed);
-                        }
+                        out.push(composed);
                     }
                     i += 1;
                 }
+                Resolution::InvalidRegister(_) => {
+                    ctx.reset_transient();
+                    ctx.register = None;
+                    i += 1;
+                }
                 Resolution::NeedMore => {
                     break;
                 }
@@ -635,7 +1288,7 @@ mod tests {
     #[test]
     fn single_key_match() {
         let trie = MappingTrie::build(baseline_normal_specs());
-        let res = trie.resolve(&['w']);
+        let res = trie.resolve(&keys("w"));
         assert_eq!(
             res,
             Resolution::Matched {
@@ -655,7 +1308,7 @@ mod tests {
             output: MappingOutput::Literal('!'),
         });
         let trie = MappingTrie::build(specs);
-        let res = trie.resolve(&['d']);
+        let res = trie.resolve(&keys("d"));
         assert_eq!(
             res,
             Resolution::Matched {
@@ -674,7 +1327,7 @@ mod tests {
             output: MappingOutput::Literal('#'),
         });
         let trie = MappingTrie::build(specs);
-        let res = trie.resolve(&['d', 'w']);
+        let res = trie.resolve(&keys("dw"));
         assert_eq!(
             res,
             Resolution::Matched {
@@ -685,11 +1338,332 @@ mod tests {
         );
     }
 
+    #[test]
+    fn n_and_shift_n_repeat_search_with_count() {
+        assert_eq!(
+            feed("3nN"),
+            vec![
+                ComposedAction::SearchNext {
+                    reverse: false,
+                    count: 3
+                },
+                ComposedAction::SearchNext {
+                    reverse: true,
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bracket_d_jumps_between_diagnostics() {
+        assert_eq!(
+            feed("2]d[d"),
+            vec![
+                ComposedAction::DiagnosticJump {
+                    backward: false,
+                    count: 2
+                },
+                ComposedAction::DiagnosticJump {
+                    backward: true,
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gd_and_gr_compose_goto_commands() {
+        assert_eq!(
+            feed("gdgr"),
+            vec![
+                ComposedAction::Goto {
+                    references: false,
+                    split: false
+                },
+                ComposedAction::Goto {
+                    references: true,
+                    split: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn q_colon_opens_cmdline_window() {
+        let trie = MappingTrie::build(baseline_normal_specs());
+        assert_eq!(trie.resolve(&keys("q")), Resolution::NeedMore);
+        assert_eq!(feed("q:"), vec![ComposedAction::CmdlineWindow]);
+    }
+
+    #[test]
+    fn z_prefix_composes_fold_commands() {
+        assert_eq!(
+            feed("zfjzozcza"),
+            vec![
+                ComposedAction::ApplyOperator {
+                    op: 'z',
+                    motion: 'j',
+                    count: 1,
+                    register: None
+                },
+                ComposedAction::Fold { cmd: 'o' },
+                ComposedAction::Fold { cmd: 'c' },
+                ComposedAction::Fold { cmd: 'a' },
+            ]
+        );
+    }
+
+    #[test]
+    fn z_prefix_composes_scroll_cursor_commands() {
+        assert_eq!(
+            feed("zz12ztzb"),
+            vec![
+                ComposedAction::ScrollCursor {
+                    cmd: 'z',
+                    count: None
+                },
+                ComposedAction::ScrollCursor {
+                    cmd: 't',
+                    count: Some(12)
+                },
+                ComposedAction::ScrollCursor {
+                    cmd: 'b',
+                    count: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ga_and_g8_compose_inspect_commands() {
+        assert_eq!(
+            feed("gag8"),
+            vec![
+                ComposedAction::InspectChar { cmd: 'a' },
+                ComposedAction::InspectChar { cmd: '8' },
+            ]
+        );
+    }
+
+    #[test]
+    fn gt_keeps_explicit_count() {
+        assert_eq!(
+            feed("gt3gT"),
+            vec![
+                ComposedAction::TabSwitch {
+                    backward: false,
+                    count: None
+                },
+                ComposedAction::TabSwitch {
+                    backward: true,
+                    count: Some(3)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn ctrl_w_chords_compose_window_commands() {
+        assert_eq!(
+            feed("2\x17j"),
+            vec![ComposedAction::WindowCommand { cmd: 'j', count: 2 }]
+        );
+        assert_eq!(
+            feed("\x17\x17"),
+            vec![ComposedAction::WindowCommand { cmd: 'w', count: 1 }]
+        );
+    }
+
+    #[test]
+    fn named_keys_and_chords_resolve_as_tokens() {
+        let chord = |base: KeyToken, mods: ModMask| KeyToken::Chord {
+            base: Box::new(base),
+            mods,
+        };
+        assert_eq!(ctrl('W'), chord(KeyToken::Char('w'), ModMask::CTRL));
+        assert_eq!(
+            canonical(&chord(
+                chord(KeyToken::Char('A'), ModMask::SHIFT),
+                ModMask::empty()
+            )),
+            KeyToken::Char('A')
+        );
+        assert_eq!(
+            canonical(&chord(
+                chord(KeyToken::Named(NamedKey::Up), ModMask::SHIFT),
+                ModMask::ALT
+            )),
+            chord(KeyToken::Named(NamedKey::Up), ModMask::ALT | ModMask::SHIFT)
+        );
+
+        let mut specs = baseline_normal_specs();
+        specs.push(MappingSpec {
+            sequence: vec![KeyTokenPattern::Named(NamedKey::F(5))],
+            output: MappingOutput::Keys(keys("dd")),
+        });
+        let trie = MappingTrie::build(specs);
+        assert_eq!(
+            trie.resolve(&[KeyToken::Named(NamedKey::Up)]),
+            Resolution::Matched {
+                consumed: 1,
+                output: MappingOutput::Motion('k'),
+                ambiguous: false
+            }
+        );
+        assert_eq!(
+            trie.resolve(&[KeyToken::Named(NamedKey::F(5))]),
+            Resolution::Matched {
+                consumed: 1,
+                output: MappingOutput::Keys(keys("dd")),
+                ambiguous: false
+            }
+        );
+        // Esc is its own key, not the control code `^[`.
+        let esc = KeyToken::Named(NamedKey::Esc);
+        assert_eq!(
+            trie.resolve(std::slice::from_ref(&esc)),
+            Resolution::FallbackLiteral(esc)
+        );
+    }
+
+    #[test]
+    fn text_objects_compose_only_under_an_operator() {
+        let trie = MappingTrie::build(baseline_operator_pending_specs());
+        let mut ctx = PendingContext::default();
+        let mut feed = |seq: &str| -> Vec<ComposedAction> {
+            let mut out = Vec::new();
+            let chars = keys(seq);
+            let mut i = 0;
+            while i < chars.len() {
+                match trie.resolve_in(&chars[i..], &ctx) {
+                    Resolution::Matched {
+                        consumed, output, ..
+                    } => {
+                        match compose_with_context(&mut ctx, &output) {
+                            ComposedAction::None => {}
+                            composed => out.push(composed),
+                        }
+                        i += consumed;
+                    }
+                    other => panic!("{seq}: {other:?}"),
+                }
+            }
+            out
+        };
+        assert_eq!(
+            feed("\"a2d3aw"),
+            vec![ComposedAction::ApplyOperatorTextObject {
+                op: 'd',
+                object: 'w',
+                around: true,
+                count: 6,
+                register: Some('a')
+            }]
+        );
+        assert_eq!(
+            feed("ci("),
+            vec![ComposedAction::ApplyOperatorTextObject {
+                op: 'c',
+                object: '(',
+                around: false,
+                count: 1,
+                register: None
+            }]
+        );
+        assert_eq!(feed("dp"), vec![ComposedAction::DiffHunk { cmd: 'p' }]);
+        assert_eq!(feed("yo"), vec![]);
+        let explorer = MappingTrie::build(explorer_specs());
+        for (seq, cmd) in [("d", 'd'), ("gh", 'h'), ("-", '-')] {
+            assert!(matches!(
+                explorer.resolve(&keys(seq)),
+                Resolution::Matched { output: MappingOutput::Explorer(c), .. } if c == cmd
+            ));
+        }
+        assert!(matches!(
+            explorer.resolve(&keys("g-")),
+            Resolution::Matched {
+                output: MappingOutput::UndoOlder,
+                ..
+            }
+        ));
+        // `<CR>` moves down except where a window binds it.
+        let enter = [KeyToken::Named(NamedKey::Enter)];
+        for (specs, expected) in [
+            (baseline_normal_specs(), MappingOutput::Motion('j')),
+            (cmdline_window_specs(), MappingOutput::CmdlineWindowExecute),
+            (list_window_specs(), MappingOutput::ListEnter),
+            (explorer_specs(), MappingOutput::ListEnter),
+        ] {
+            assert!(matches!(
+                MappingTrie::build(specs).resolve(&enter),
+                Resolution::Matched { output, .. } if output == expected
+            ));
+        }
+        // Without an operator the object does nothing and clears the count.
+        assert_eq!(feed("2iw"), vec![]);
+        assert_eq!(ctx.count_prefix, None);
+        // `i` waits for its object instead of entering Insert mode.
+        assert_eq!(trie.resolve(&keys("i")), Resolution::NeedMore);
+        let normal = MappingTrie::build(baseline_normal_specs());
+        assert!(matches!(
+            normal.resolve(&keys("i")),
+            Resolution::Matched {
+                output: MappingOutput::EnterInsert,
+                ambiguous: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn page_scroll_chords_keep_an_optional_count() {
+        // `d<C-d>` drops the operator instead of deleting a half page.
+        assert_eq!(
+            feed("\x045\x15d\x06x"),
+            vec![
+                ComposedAction::Scroll {
+                    cmd: 'd',
+                    count: None
+                },
+                ComposedAction::Scroll {
+                    cmd: 'u',
+                    count: Some(5)
+                },
+                ComposedAction::Scroll {
+                    cmd: 'f',
+                    count: None
+                },
+                ComposedAction::DeleteUnder {
+                    count: 1,
+                    register: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn g_minus_plus_walk_undo_tree() {
+        assert_eq!(
+            feed("3g-g+"),
+            vec![
+                ComposedAction::UndoChrono {
+                    newer: false,
+                    count: 3
+                },
+                ComposedAction::UndoChrono {
+                    newer: true,
+                    count: 1
+                },
+            ]
+        );
+    }
+
     #[test]
     fn fallback_literal() {
         let trie = MappingTrie::build(baseline_normal_specs());
-        let res = trie.resolve(&['z']);
-        assert_eq!(res, Resolution::FallbackLiteral('z'));
+        let res = trie.resolve(&keys("Q"));
+        assert_eq!(res, Resolution::FallbackLiteral(KeyToken::Char('Q')));
     }
 
     #[test]
@@ -879,4 +1853,98 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn register_capture_skips_the_trie_and_rejects_invalid_names() {
+        // `y` after `"` names a register rather than starting an operator.
+        assert_eq!(
+            feed("\"yyy"),
+            vec![ComposedAction::LinewiseOperator {
+                op: 'y',
+                count: 1,
+                register: Some('y')
+            }]
+        );
+        assert_eq!(
+            feed("\"Adw"),
+            vec![ComposedAction::ApplyOperator {
+                op: 'd',
+                motion: 'w',
+                count: 1,
+                register: Some('A')
+            }]
+        );
+        // An invalid name drops the whole pending command, count included.
+        assert_eq!(
+            feed("2\"!dw"),
+            vec![ComposedAction::ApplyOperator {
+                op: 'd',
+                motion: 'w',
+                count: 1,
+                register: None
+            }]
+        );
+        let trie = MappingTrie::build(baseline_normal_specs());
+        let ctx = PendingContext {
+            awaiting_register: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            trie.resolve_in(&keys("!"), &ctx),
+            Resolution::InvalidRegister(KeyToken::Char('!'))
+        );
+        assert_eq!(
+            trie.resolve_in(&keys("d"), &PendingContext::default()),
+            trie.resolve(&keys("d"))
+        );
+    }
+
+    #[test]
+    fn layers_exist_per_mode_once_set() {
+        let mut layers = MappingLayers::new(MappingTrie::build(baseline_normal_specs()));
+        assert!(layers.get(MapMode::Normal).is_some());
+        assert!(layers.get(MapMode::CommandLine).is_none());
+        layers.set(
+            MapMode::CommandLine,
+            MappingTrie::build(vec![MappingSpec {
+                sequence: vec![KeyTokenPattern::Char('w'), KeyTokenPattern::Char('w')],
+                output: MappingOutput::Keys(keys("w!")),
+            }]),
+        );
+        assert_eq!(
+            layers.resolve(MapMode::CommandLine, &keys("w")),
+            Some(Resolution::NeedMore)
+        );
+        assert!(layers.get(MapMode::Insert).is_none());
+        assert_eq!(layers.resolve(MapMode::Insert, &keys("w")), None);
+
+        // Operator-pending reads the Normal layer until it has its own.
+        let dollar = || Resolution::Matched {
+            consumed: 1,
+            output: MappingOutput::Motion('$'),
+            ambiguous: false,
+        };
+        assert_eq!(
+            layers.resolve(MapMode::OperatorPending, &keys("$")),
+            Some(dollar())
+        );
+        let mut specs = baseline_normal_specs();
+        specs.push(MappingSpec {
+            sequence: vec![KeyTokenPattern::Char('L')],
+            output: MappingOutput::Motion('$'),
+        });
+        layers.set(MapMode::OperatorPending, MappingTrie::build(specs));
+        assert_eq!(
+            layers.resolve(MapMode::OperatorPending, &keys("L")),
+            Some(dollar())
+        );
+        assert_eq!(
+            layers.resolve(MapMode::Normal, &keys("L")),
+            Some(Resolution::FallbackLiteral(KeyToken::Char('L')))
+        );
+        let mut ctx = PendingContext::default();
+        assert_eq!(MapMode::for_normal(&ctx), MapMode::Normal);
+        ctx.operator = Some('d');
+        assert_eq!(MapMode::for_normal(&ctx), MapMode::OperatorPending);
+    }
 }
@@ -0,0 +1,450 @@
+//! User key mappings (`[keymap]` in `oxidized.toml`).
+//!
+//! Both sides of a mapping are written in Vim key notation: plain
+//! characters plus `<Esc>`, `<CR>` (`<Enter>`, `<Return>`), `<BS>`, `<Tab>`,
+//! `<Space>`, `<lt>`, `<Bslash>`, `<Bar>`, the arrow keys (`<Up>`, `<Down>`,
+//! `<Left>`, `<Right>`), `<Home>`, `<End>`, `<PageUp>`, `<PageDown>`,
+//! `<Insert>`, `<Del>`, `<F1>` to `<F12>` and `<leader>`. Any key but
+//! `<leader>` takes modifier prefixes: `<C-w>`, `<M-x>` (or `<A-x>`),
+//! `<S-Up>`, `<C-S-F5>`. Names are case-insensitive. Each key parses to
+//! the `canonical` `KeyToken` the translator buffers.
+//!
+//! A `noremap` right-hand side becomes a `MappingOutput::Keys` that the
+//! translator feeds back through the built-in keys only, so it can never
+//! recurse. A recursive (`map`) one becomes `MappingOutput::RemapKeys` and
+//! goes through user mappings again, except for a leading copy of its own
+//! left-hand side (Vim's `:map ab abc` rule); expansions nested deeper than
+//! `MAX_MAP_DEPTH` are aborted with E223.
+//!
+//! Compiling reports, without rejecting them, mappings that replace a
+//! built-in sequence, repeat another user mapping, or prefix (or extend)
+//! another mapping, which makes the shorter one wait for `timeoutlen`.
+
+use crate::{KeyToken, KeyTokenPattern, MappingOutput, MappingSpec, ModMask, NamedKey, canonical};
+use std::fmt;
+
+/// Vim's default `mapleader`.
+pub const DEFAULT_LEADER: char = '\\';
+
+/// Vim's default `maxmapdepth`: recursive expansions nested deeper abort.
+pub const MAX_MAP_DEPTH: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    Empty,
+    /// `<name>` is not a key this notation knows.
+    UnknownKey(String),
+    /// The leader must be a single key.
+    NotOneKey(String),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::Empty => write!(f, "empty key sequence"),
+            NotationError::UnknownKey(name) => write!(f, "unknown key <{name}>"),
+            NotationError::NotOneKey(keys) => write!(f, "{keys:?} is not a single key"),
+        }
+    }
+}
+
+/// Keys of `notation`, with `<leader>` standing for `leader`.
+pub fn parse_keys(notation: &str, leader: &KeyToken) -> Result<Vec<KeyToken>, NotationError> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        if c == '<'
+            && let Some(end) = rest.find('>')
+            && end > 1
+            && rest[1..end]
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            let name = &rest[1..end];
+            keys.push(
+                named_key(name, leader).ok_or_else(|| NotationError::UnknownKey(name.into()))?,
+            );
+            rest = &rest[end + 1..];
+        } else {
+            // A `<` that does not open a key name is itself.
+            keys.push(KeyToken::Char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if keys.is_empty() {
+        return Err(NotationError::Empty);
+    }
+    Ok(keys)
+}
+
+/// The single key `notation` names (the `leader` setting).
+pub fn parse_leader(notation: &str) -> Result<KeyToken, NotationError> {
+    match parse_keys(notation, &KeyToken::Char(DEFAULT_LEADER))?.as_slice() {
+        [key] => Ok(key.clone()),
+        _ => Err(NotationError::NotOneKey(notation.into())),
+    }
+}
+
+/// A buffered key as Vim echoes it in messages: control characters and
+/// Ctrl letters in caret notation (`^W`), other keys in key notation
+/// (`<Up>`, `<M-x>`).
+pub fn key_display(key: &KeyToken) -> String {
+    match key {
+        KeyToken::Char(c @ '\0'..='\x1f') => format!("^{}", (*c as u8 + b'@') as char),
+        KeyToken::Char('\x7f') => "^?".to_string(),
+        KeyToken::Chord { base, mods }
+            if *mods == ModMask::CTRL
+                && let KeyToken::Char(c @ 'a'..='z') = **base =>
+        {
+            format!("^{}", c.to_ascii_uppercase())
+        }
+        _ => key_notation(key),
+    }
+}
+
+/// `key` written in key notation, the way `parse_keys` reads it back.
+pub fn key_notation(key: &KeyToken) -> String {
+    match key {
+        KeyToken::Char(c) => match char_name(*c) {
+            Some(name) => format!("<{name}>"),
+            None => c.to_string(),
+        },
+        KeyToken::Named(named) => format!("<{}>", named_name(*named)),
+        KeyToken::Chord { base, mods } => {
+            let mut out = String::from("<");
+            for (flag, prefix) in [
+                (ModMask::CTRL, "C-"),
+                (ModMask::SHIFT, "S-"),
+                (ModMask::ALT, "M-"),
+            ] {
+                if mods.contains(flag) {
+                    out.push_str(prefix);
+                }
+            }
+            match &**base {
+                KeyToken::Char(c) => match char_name(*c) {
+                    Some(name) => out.push_str(name),
+                    None => out.push(*c),
+                },
+                KeyToken::Named(named) => out.push_str(&named_name(*named)),
+                chord => return key_notation(&canonical(chord)),
+            }
+            out.push('>');
+            out
+        }
+    }
+}
+
+fn char_name(c: char) -> Option<&'static str> {
+    Some(match c {
+        ' ' => "Space",
+        '<' => "lt",
+        '\\' => "Bslash",
+        '|' => "Bar",
+        _ => return None,
+    })
+}
+
+fn named_name(key: NamedKey) -> String {
+    match key {
+        NamedKey::Enter => "CR".into(),
+        NamedKey::Esc => "Esc".into(),
+        NamedKey::Backspace => "BS".into(),
+        NamedKey::Tab => "Tab".into(),
+        NamedKey::F(n) => format!("F{n}"),
+        NamedKey::Up => "Up".into(),
+        NamedKey::Down => "Down".into(),
+        NamedKey::Left => "Left".into(),
+        NamedKey::Right => "Right".into(),
+        NamedKey::Home => "Home".into(),
+        NamedKey::End => "End".into(),
+        NamedKey::PageUp => "PageUp".into(),
+        NamedKey::PageDown => "PageDown".into(),
+        NamedKey::Insert => "Insert".into(),
+        NamedKey::Delete => "Del".into(),
+    }
+}
+
+fn named_key(name: &str, leader: &KeyToken) -> Option<KeyToken> {
+    // Modifier prefixes, each a letter and a dash; a trailing `-` is the key.
+    let mut mods = ModMask::empty();
+    let mut rest = name;
+    while let Some((prefix, tail)) = rest.split_once('-')
+        && !tail.is_empty()
+    {
+        mods |= match prefix.to_ascii_lowercase().as_str() {
+            "c" => ModMask::CTRL,
+            "s" => ModMask::SHIFT,
+            "m" | "a" => ModMask::ALT,
+            _ => return None,
+        };
+        rest = tail;
+    }
+    let lower = rest.to_ascii_lowercase();
+    let key = match lower.as_str() {
+        "esc" => KeyToken::Named(NamedKey::Esc),
+        "cr" | "enter" | "return" => KeyToken::Named(NamedKey::Enter),
+        "bs" => KeyToken::Named(NamedKey::Backspace),
+        "tab" => KeyToken::Named(NamedKey::Tab),
+        "up" => KeyToken::Named(NamedKey::Up),
+        "down" => KeyToken::Named(NamedKey::Down),
+        "left" => KeyToken::Named(NamedKey::Left),
+        "right" => KeyToken::Named(NamedKey::Right),
+        "home" => KeyToken::Named(NamedKey::Home),
+        "end" => KeyToken::Named(NamedKey::End),
+        "pageup" => KeyToken::Named(NamedKey::PageUp),
+        "pagedown" => KeyToken::Named(NamedKey::PageDown),
+        "insert" | "ins" => KeyToken::Named(NamedKey::Insert),
+        "del" | "delete" => KeyToken::Named(NamedKey::Delete),
+        "space" => KeyToken::Char(' '),
+        "lt" => KeyToken::Char('<'),
+        "bslash" => KeyToken::Char('\\'),
+        "bar" => KeyToken::Char('|'),
+        "leader" if mods.is_empty() => return Some(leader.clone()),
+        _ if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok())
+            && (1..=12).contains(&n) =>
+        {
+            KeyToken::Named(NamedKey::F(n))
+        }
+        _ => {
+            // A single character needs a modifier: `<C-w>`, `<M-X>`.
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if !mods.is_empty() => {
+                    if mods.contains(ModMask::SHIFT) {
+                        KeyToken::Char(c.to_ascii_uppercase())
+                    } else {
+                        KeyToken::Char(c)
+                    }
+                }
+                _ => return None,
+            }
+        }
+    };
+    Some(canonical(&KeyToken::Chord {
+        base: Box::new(key),
+        mods,
+    }))
+}
+
+/// A mapping compiled with a remark (see the module docs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappingIssue {
+    /// Either side is not valid notation; the mapping is skipped.
+    Invalid { lhs: String, error: NotationError },
+    /// Replaces the built-in meaning of the same keys.
+    Overrides { lhs: String },
+    /// Same keys as another user mapping once notation is resolved; the
+    /// later one (in key order) wins.
+    Duplicate { lhs: String, other: String },
+    /// One is a prefix of the other, so the shorter waits for `timeoutlen`.
+    Ambiguous { lhs: String, other: String },
+}
+
+impl fmt::Display for MappingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingIssue::Invalid { lhs, error } => write!(f, "{lhs}: {error}"),
+            MappingIssue::Overrides { lhs } => write!(f, "{lhs}: overrides a built-in mapping"),
+            MappingIssue::Duplicate { lhs, other } => write!(f, "{lhs}: same keys as {other}"),
+            MappingIssue::Ambiguous { lhs, other } => {
+                write!(f, "{lhs}: ambiguous with {other} (waits for timeoutlen)")
+            }
+        }
+    }
+}
+
+/// User mappings of one mode, ready to merge over the built-in specs.
+#[derive(Debug, Default)]
+pub struct UserKeymap {
+    pub specs: Vec<MappingSpec>,
+    pub issues: Vec<MappingIssue>,
+}
+
+/// Compile `(lhs, rhs, remap)` mappings of one mode against the mode's
+/// `builtin` specs (empty for modes without a trie).
+pub fn compile_user_specs<'a>(
+    maps: impl IntoIterator<Item = (&'a str, &'a str, bool)>,
+    leader: &KeyToken,
+    builtin: &[MappingSpec],
+) -> UserKeymap {
+    let builtin: Vec<(Vec<KeyToken>, String)> = builtin
+        .iter()
+        .map(|spec| {
+            let keys: Vec<KeyToken> = spec.sequence.iter().map(KeyTokenPattern::token).collect();
+            let name = keys.iter().map(key_notation).collect();
+            (keys, name)
+        })
+        .collect();
+    let mut out = UserKeymap::default();
+    let mut compiled: Vec<(Vec<KeyToken>, String)> = Vec::new();
+    for (lhs, rhs, remap) in maps {
+        let parsed = parse_keys(lhs, leader).and_then(|l| Ok((l, parse_keys(rhs, leader)?)));
+        let (keys, rhs) = match parsed {
+            Ok(sides) => sides,
+            Err(error) => {
+                out.issues.push(MappingIssue::Invalid {
+                    lhs: lhs.into(),
+                    error,
+                });
+                continue;
+            }
+        };
+        if builtin.iter().any(|(b, _)| *b == keys) {
+            out.issues.push(MappingIssue::Overrides { lhs: lhs.into() });
+        }
+        if let Some((_, other)) = compiled.iter().find(|(c, _)| *c == keys) {
+            out.issues.push(MappingIssue::Duplicate {
+                lhs: lhs.into(),
+                other: other.clone(),
+            });
+        }
+        for (other, name) in builtin.iter().chain(&compiled) {
+            if other.len() != keys.len() && (other.starts_with(&keys) || keys.starts_with(other)) {
+                out.issues.push(MappingIssue::Ambiguous {
+                    lhs: lhs.into(),
+                    other: name.clone(),
+                });
+            }
+        }
+        out.specs.push(MappingSpec {
+            sequence: keys.iter().cloned().map(KeyTokenPattern::Token).collect(),
+            output: if remap {
+                MappingOutput::RemapKeys(rhs)
+            } else {
+                MappingOutput::Keys(rhs)
+            },
+        });
+        compiled.push((keys, lhs.into()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MappingTrie, Resolution, baseline_normal_specs};
+
+    fn keys(seq: &str) -> Vec<KeyToken> {
+        seq.chars().map(KeyToken::Char).collect()
+    }
+
+    #[test]
+    fn notation_names_special_keys() {
+        let backslash = KeyToken::Char('\\');
+        let ctrl_w = crate::ctrl('w');
+        assert_eq!(parse_keys("jk", &backslash), Ok(keys("jk")));
+        assert_eq!(
+            parse_keys("<leader>w", &KeyToken::Char(' ')),
+            Ok(keys(" w"))
+        );
+        let mut write = keys(":w");
+        write.push(KeyToken::Named(NamedKey::Enter));
+        assert_eq!(parse_keys(":w<CR>", &backslash), Ok(write));
+        assert_eq!(
+            parse_keys("<esc><C-W><lt>", &backslash),
+            Ok(vec![
+                KeyToken::Named(NamedKey::Esc),
+                ctrl_w.clone(),
+                KeyToken::Char('<')
+            ])
+        );
+        // A `<` that opens no key name is literal.
+        assert_eq!(parse_keys("a<b", &backslash), Ok(keys("a<b")));
+        assert_eq!(
+            parse_keys("<up><F5><S-a><M-x>", &backslash),
+            Ok(vec![
+                KeyToken::Named(NamedKey::Up),
+                KeyToken::Named(NamedKey::F(5)),
+                KeyToken::Char('A'),
+                KeyToken::Chord {
+                    base: Box::new(KeyToken::Char('x')),
+                    mods: ModMask::ALT
+                },
+            ])
+        );
+        let c_s_home = parse_keys("<c-s-Home>", &backslash).unwrap();
+        assert_eq!(key_notation(&c_s_home[0]), "<C-S-Home>");
+        assert_eq!(
+            parse_keys(&key_notation(&c_s_home[0]), &backslash),
+            Ok(c_s_home)
+        );
+        assert_eq!(
+            parse_keys("<F13>", &backslash),
+            Err(NotationError::UnknownKey("F13".into()))
+        );
+        assert_eq!(
+            parse_keys("<x>", &backslash),
+            Err(NotationError::UnknownKey("x".into()))
+        );
+        assert_eq!(parse_keys("", &backslash), Err(NotationError::Empty));
+        assert_eq!(parse_leader("<Space>"), Ok(KeyToken::Char(' ')));
+        assert_eq!(key_display(&ctrl_w), "^W");
+        assert_eq!(key_display(&KeyToken::Named(NamedKey::Up)), "<Up>");
+        assert_eq!(key_display(&KeyToken::Char('!')), "!");
+        assert!(parse_leader("ab").is_err());
+    }
+
+    #[test]
+    fn compiles_over_builtin_specs_with_issues() {
+        let base = baseline_normal_specs();
+        let user = compile_user_specs(
+            [
+                ("<leader>w", ":w<CR>", false),
+                ("x", "dd", false),
+                ("jk", "<Esc>", true),
+                ("\\w", "u", false),
+                ("<Nope>", "x", false),
+            ],
+            &KeyToken::Char('\\'),
+            &base,
+        );
+        assert_eq!(user.specs.len(), 4);
+        assert_eq!(
+            user.issues,
+            vec![
+                MappingIssue::Overrides { lhs: "x".into() },
+                MappingIssue::Ambiguous {
+                    lhs: "jk".into(),
+                    other: "j".into()
+                },
+                MappingIssue::Duplicate {
+                    lhs: "\\w".into(),
+                    other: "<leader>w".into()
+                },
+                MappingIssue::Invalid {
+                    lhs: "<Nope>".into(),
+                    error: NotationError::UnknownKey("Nope".into())
+                },
+            ]
+        );
+
+        let mut specs = base;
+        specs.extend(user.specs);
+        let trie = MappingTrie::build(specs);
+        assert_eq!(
+            trie.resolve(&keys("x")),
+            Resolution::Matched {
+                consumed: 1,
+                output: MappingOutput::Keys(keys("dd")),
+                ambiguous: false
+            }
+        );
+        // `j` now waits for a possible `k`, but `jj` is two motions.
+        assert!(matches!(
+            trie.resolve(&keys("j")),
+            Resolution::Matched {
+                ambiguous: true,
+                ..
+            }
+        ));
+        assert_eq!(
+            trie.resolve(&keys("jj")),
+            Resolution::Matched {
+                consumed: 1,
+                output: MappingOutput::Motion('j'),
+                ambiguous: false
+            }
+        );
+    }
+}
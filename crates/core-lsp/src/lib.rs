@@ -0,0 +1,903 @@
+//! Language server sessions for Oxidized.
+//!
+//! `LspSessions` ties the servers of `[lsp.servers]` to editor state. After
+//! a dispatch changed the active buffer (or switched to another) the runtime
+//! calls `sync`: the first file a server handles starts it (an
+//! `LspServerSource` plus the `initialize` handshake), and once it is
+//! initialized the active buffer is kept open on it with full-text
+//! `didOpen` / `didChange` notifications. Buffers that were closed or
+//! renamed get `didClose`. Documents on a server offering completion are
+//! attached in `EditorState::completion`, and a completion request queued
+//! there goes out as `textDocument/completion` right after the document
+//! sync, so the server sees the text being completed. `gd` / `gr` lookups
+//! queued in `EditorState::tags` go out the same way as
+//! `textDocument/definition` / `references`; one that no server can take
+//! is answered empty right away, so the runtime falls back to `tags`. A
+//! `K` request queued in `EditorState::hover` becomes `textDocument/hover`.
+//!
+//! `handle` consumes the server's `Event::Lsp` messages.
+//! `textDocument/publishDiagnostics` replaces the document's set in
+//! `EditorState::diagnostics`, positions converted from UTF-16 code units
+//! to byte offsets against the buffer's current text. Completion answers
+//! become `CompletionItem`s (the `textEdit` text, else `insertText`, else
+//! the label; snippets are not requested and fall back to the label) and
+//! are merged into the completion they were asked for. Hover contents
+//! (markdown, plain text or the older `MarkedString`s) are shown as plain
+//! lines, code fences dropped. A server that fails
+//! to start or exits reports it in the message line, its diagnostics are
+//! cleared and it is not restarted.
+
+use core_config::{LspConfig, LspServerConfig};
+use core_events::{
+    AsyncEventSource, Event, LspClient, LspMessage, LspMessageKind, LspServerSource,
+};
+use core_state::completion::{CompletionItem, CompletionSource, LspCompletionRequest};
+use core_state::diagnostics::{Diagnostic, Severity};
+use core_state::tags::{GotoAnswer, GotoRequest, Location};
+use core_state::{BufferId, EditorState};
+use core_text::{Buffer, Position};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+
+struct Document {
+    path: PathBuf,
+    uri: String,
+    version: i64,
+    text: String,
+}
+
+struct Session {
+    client: LspClient,
+    language_id: String,
+    /// Id of the `initialize` request until it is answered.
+    initializing: Option<u64>,
+    running: bool,
+    documents: HashMap<BufferId, Document>,
+    /// `completionProvider` trigger characters; `None` without completion.
+    triggers: Option<Vec<char>>,
+    /// Outstanding completion request: its id and the completion generation.
+    completion: Option<(u64, u64)>,
+    /// Definitions or references can be asked for.
+    goto: bool,
+    /// Outstanding definition / references request.
+    pending_goto: Option<(u64, GotoRequest)>,
+    /// `hoverProvider` is advertised.
+    hover: bool,
+    /// Outstanding hover request: its id and the hover generation.
+    pending_hover: Option<(u64, u64)>,
+}
+
+pub struct LspSessions {
+    config: LspConfig,
+    root: PathBuf,
+    sessions: BTreeMap<String, Session>,
+}
+
+impl LspSessions {
+    /// Servers of `config`, run with `root` as the workspace.
+    pub fn new(config: LspConfig, root: PathBuf) -> Self {
+        Self {
+            config,
+            root,
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.config.servers.is_empty()
+    }
+
+    /// Names of the servers started so far and whether each still runs.
+    pub fn servers(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.sessions
+            .iter()
+            .map(|(name, session)| (name.as_str(), session.running))
+    }
+
+    /// Bring the servers up to date with the active buffer, starting the
+    /// one that handles it if needed. Returns the started source's task.
+    pub fn sync(&mut self, state: &mut EditorState, tx: &Sender<Event>) -> Vec<JoinHandle<()>> {
+        self.close_stale(state);
+        let mut goto = state.tags.take_request();
+        let started = self.sync_active(state, tx, &mut goto);
+        // A lookup no server took falls back to `tags` files.
+        if let Some(request) = goto {
+            state.tags.answer(GotoAnswer {
+                request,
+                locations: Vec::new(),
+            });
+        }
+        started
+    }
+
+    fn sync_active(
+        &mut self,
+        state: &mut EditorState,
+        tx: &Sender<Event>,
+        goto: &mut Option<GotoRequest>,
+    ) -> Vec<JoinHandle<()>> {
+        let request = state.completion.take_lsp_request();
+        let hover = state.hover.take_request();
+        let active = state.active;
+        let Some(entry) = state.buffers.get(active) else {
+            return Vec::new();
+        };
+        let Some(path) = entry
+            .meta
+            .path
+            .as_deref()
+            .filter(|_| entry.meta.binary.is_none())
+        else {
+            return Vec::new();
+        };
+        let Some((name, server)) = self.config.server_for(path) else {
+            return Vec::new();
+        };
+        let mut started = Vec::new();
+        if !self.sessions.contains_key(name) {
+            let (session, handle) = start(name, server, &self.root, tx);
+            started.push(handle);
+            self.sessions.insert(name.to_string(), session);
+        }
+        let Some(session) = self.sessions.get_mut(name) else {
+            return started;
+        };
+        if !session.running || session.initializing.is_some() {
+            return started;
+        }
+        let text = entry.buffer.slice_bytes(0, entry.buffer.len_bytes());
+        match session.documents.get_mut(&active) {
+            Some(doc) if doc.text == text => {}
+            Some(doc) => {
+                doc.version += 1;
+                session.client.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": {"uri": doc.uri, "version": doc.version},
+                        "contentChanges": [{"text": text}],
+                    }),
+                );
+                doc.text = text;
+            }
+            None => {
+                let uri = path_to_uri(path);
+                tracing::debug!(target: "runtime.lsp", server = name, uri = %uri, "lsp_document_opened");
+                session.client.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": session.language_id,
+                            "version": 1,
+                            "text": text,
+                        }
+                    }),
+                );
+                session.documents.insert(
+                    active,
+                    Document {
+                        path: path.to_path_buf(),
+                        uri,
+                        version: 1,
+                        text,
+                    },
+                );
+                if let Some(triggers) = &session.triggers {
+                    state.completion.attach(active, triggers.clone());
+                }
+                if session.goto {
+                    state.tags.attach(active);
+                }
+                if session.hover {
+                    state.hover.attach(active);
+                }
+            }
+        }
+        if let Some(request) = request.filter(|r| r.buffer == active)
+            && session.triggers.is_some()
+        {
+            request_completion(session, active, request);
+        }
+        if let Some(request) = goto.take_if(|r| r.origin.buffer == active && session.goto) {
+            request_goto(session, active, request);
+        }
+        if let Some(request) = hover.filter(|r| r.buffer == active)
+            && session.hover
+            && let Some(doc) = session.documents.get(&active)
+        {
+            let line = doc
+                .text
+                .split('\n')
+                .nth(request.position.line)
+                .unwrap_or("");
+            let character = byte_to_utf16(line, request.position.byte);
+            let id = session.client.request(
+                "textDocument/hover",
+                json!({
+                    "textDocument": {"uri": doc.uri},
+                    "position": {"line": request.position.line, "character": character},
+                }),
+            );
+            session.pending_hover = Some((id, request.generation));
+        }
+        started
+    }
+
+    /// `didClose` documents whose buffer was closed or now has another path.
+    fn close_stale(&mut self, state: &mut EditorState) {
+        for session in self.sessions.values_mut() {
+            session.documents.retain(|buffer, doc| {
+                let alive = state
+                    .buffers
+                    .get(*buffer)
+                    .is_some_and(|entry| entry.meta.path.as_deref() == Some(doc.path.as_path()));
+                if !alive && session.running {
+                    session.client.notify(
+                        "textDocument/didClose",
+                        json!({"textDocument": {"uri": doc.uri}}),
+                    );
+                }
+                if !alive {
+                    state.completion.detach(*buffer);
+                    state.tags.detach(*buffer);
+                    state.hover.detach(*buffer);
+                }
+                alive
+            });
+        }
+    }
+
+    /// Apply a server message. Returns true when a server finished its
+    /// handshake, so the runtime should `sync` again.
+    pub fn handle(&mut self, message: &LspMessage, state: &mut EditorState) -> bool {
+        let Some(session) = self.sessions.get_mut(&message.server) else {
+            return false;
+        };
+        match &message.kind {
+            LspMessageKind::Response { id, result } if session.initializing == Some(*id) => {
+                session.initializing = None;
+                match result {
+                    Ok(result) => {
+                        let provider = &result["capabilities"]["completionProvider"];
+                        session.triggers = provider.is_object().then(|| {
+                            provider["triggerCharacters"]
+                                .as_array()
+                                .into_iter()
+                                .flatten()
+                                .filter_map(|c| c.as_str()?.chars().next())
+                                .collect()
+                        });
+                        let capabilities = &result["capabilities"];
+                        session.goto = [
+                            &capabilities["definitionProvider"],
+                            &capabilities["referencesProvider"],
+                        ]
+                        .iter()
+                        .any(|provider| provider.is_object() || provider.as_bool() == Some(true));
+                        let provider = &capabilities["hoverProvider"];
+                        session.hover = provider.is_object() || provider.as_bool() == Some(true);
+                        tracing::info!(target: "runtime.lsp", server = %message.server, completion = session.triggers.is_some(), goto = session.goto, hover = session.hover, "lsp_initialized");
+                        session.client.notify("initialized", json!({}));
+                        true
+                    }
+                    Err(error) => {
+                        tracing::warn!(target: "runtime.lsp", server = %message.server, %error, "lsp_initialize_failed");
+                        session.running = false;
+                        session.client.notify("exit", Value::Null);
+                        state.set_ephemeral(
+                            format!("LSP {}: {error}", message.server),
+                            Duration::from_secs(3),
+                        );
+                        false
+                    }
+                }
+            }
+            LspMessageKind::Response { id, result }
+                if session
+                    .completion
+                    .is_some_and(|(pending, _)| pending == *id) =>
+            {
+                let generation = session.completion.take().map_or(0, |(_, g)| g);
+                match result {
+                    Ok(result) => {
+                        let items = completion_items(result);
+                        tracing::debug!(target: "runtime.lsp", server = %message.server, items = items.len(), "lsp_completion");
+                        state.completion.merge_lsp(generation, items);
+                    }
+                    Err(error) => {
+                        tracing::debug!(target: "runtime.lsp", server = %message.server, %error, "lsp_completion_failed");
+                    }
+                }
+                false
+            }
+            LspMessageKind::Response { id, result }
+                if session
+                    .pending_goto
+                    .as_ref()
+                    .is_some_and(|(pending, _)| pending == id) =>
+            {
+                let Some((_, request)) = session.pending_goto.take() else {
+                    return false;
+                };
+                let locations = match result {
+                    Ok(result) => locations(result),
+                    Err(error) => {
+                        tracing::debug!(target: "runtime.lsp", server = %message.server, %error, "lsp_goto_failed");
+                        Vec::new()
+                    }
+                };
+                tracing::debug!(target: "runtime.lsp", server = %message.server, locations = locations.len(), "lsp_goto");
+                state.tags.answer(GotoAnswer { request, locations });
+                false
+            }
+            LspMessageKind::Response { id, result }
+                if session
+                    .pending_hover
+                    .is_some_and(|(pending, _)| pending == *id) =>
+            {
+                let generation = session.pending_hover.take().map_or(0, |(_, g)| g);
+                let lines = match result {
+                    Ok(result) => hover_lines(&result["contents"]),
+                    Err(error) => {
+                        tracing::debug!(target: "runtime.lsp", server = %message.server, %error, "lsp_hover_failed");
+                        Vec::new()
+                    }
+                };
+                let empty = lines.is_empty();
+                if state.hover.show(generation, lines) && empty {
+                    state.set_ephemeral("No information available", Duration::from_secs(3));
+                }
+                false
+            }
+            LspMessageKind::Response { .. } => false,
+            LspMessageKind::Notification { method, params }
+                if method == "textDocument/publishDiagnostics" =>
+            {
+                publish_diagnostics(session, params, state);
+                false
+            }
+            LspMessageKind::Notification { .. } => false,
+            LspMessageKind::Exited { error } => {
+                session.running = false;
+                for buffer in session.documents.keys() {
+                    state.diagnostics.clear(*buffer);
+                    state.completion.detach(*buffer);
+                    state.tags.detach(*buffer);
+                    state.hover.detach(*buffer);
+                }
+                session.completion = None;
+                session.pending_hover = None;
+                if let Some((_, request)) = session.pending_goto.take() {
+                    state.tags.answer(GotoAnswer {
+                        request,
+                        locations: Vec::new(),
+                    });
+                }
+                session.documents.clear();
+                let msg = match error {
+                    Some(error) => format!("LSP {}: {error}", message.server),
+                    None => format!("LSP {} exited", message.server),
+                };
+                state.set_ephemeral(msg, Duration::from_secs(3));
+                false
+            }
+        }
+    }
+}
+
+/// Create the server's source and queue the `initialize` request.
+fn start(
+    name: &str,
+    server: &LspServerConfig,
+    root: &Path,
+    tx: &Sender<Event>,
+) -> (Session, JoinHandle<()>) {
+    let source = LspServerSource::new(name, &server.command, server.args.clone(), root.into());
+    let client = source.client();
+    let root_uri = path_to_uri(root);
+    let initializing = client.request(
+        "initialize",
+        json!({
+            "processId": std::process::id(),
+            "clientInfo": {"name": "oxidized", "version": env!("CARGO_PKG_VERSION")},
+            "rootUri": root_uri,
+            "workspaceFolders": [{"uri": root_uri, "name": root.display().to_string()}],
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": {"dynamicRegistration": false},
+                    "publishDiagnostics": {"relatedInformation": false},
+                    "completion": {"completionItem": {"snippetSupport": false}},
+                    "definition": {"linkSupport": true},
+                    "references": {},
+                    "hover": {"contentFormat": ["markdown", "plaintext"]},
+                },
+                "general": {"positionEncodings": ["utf-16"]},
+            },
+        }),
+    );
+    tracing::info!(target: "runtime.lsp", server = name, command = %server.command, "lsp_starting");
+    let handle = Box::new(source).spawn(tx.clone());
+    let session = Session {
+        client,
+        language_id: server
+            .language_id
+            .clone()
+            .unwrap_or_else(|| name.to_string()),
+        initializing: Some(initializing),
+        running: true,
+        documents: HashMap::new(),
+        triggers: None,
+        completion: None,
+        goto: false,
+        pending_goto: None,
+        hover: false,
+        pending_hover: None,
+    };
+    (session, handle)
+}
+
+/// Send `textDocument/completion` for `request`; an answer to an earlier
+/// one still outstanding is ignored when it arrives.
+fn request_completion(session: &mut Session, buffer: BufferId, request: LspCompletionRequest) {
+    let Some(doc) = session.documents.get(&buffer) else {
+        return;
+    };
+    let line = doc
+        .text
+        .split('\n')
+        .nth(request.position.line)
+        .unwrap_or("");
+    let character = byte_to_utf16(line, request.position.byte);
+    let context = match request.trigger {
+        Some(c) => json!({"triggerKind": 2, "triggerCharacter": c.to_string()}),
+        None => json!({"triggerKind": 1}),
+    };
+    let id = session.client.request(
+        "textDocument/completion",
+        json!({
+            "textDocument": {"uri": doc.uri},
+            "position": {"line": request.position.line, "character": character},
+            "context": context,
+        }),
+    );
+    session.completion = Some((id, request.generation));
+}
+
+/// Send `textDocument/definition` (or `references`) for `request`.
+fn request_goto(session: &mut Session, buffer: BufferId, request: GotoRequest) {
+    let Some(doc) = session.documents.get(&buffer) else {
+        return;
+    };
+    let position = request.origin.position;
+    let line = doc.text.split('\n').nth(position.line).unwrap_or("");
+    let mut params = json!({
+        "textDocument": {"uri": doc.uri},
+        "position": {"line": position.line, "character": byte_to_utf16(line, position.byte)},
+    });
+    let method = if request.references {
+        params["context"] = json!({"includeDeclaration": true});
+        "textDocument/references"
+    } else {
+        "textDocument/definition"
+    };
+    let id = session.client.request(method, params);
+    session.pending_goto = Some((id, request));
+}
+
+/// Targets of a definition / references answer: `null`, a `Location`, or
+/// an array of `Location`s or `LocationLink`s.
+fn locations(result: &Value) -> Vec<Location> {
+    let items = match result {
+        Value::Array(items) => items.as_slice(),
+        Value::Object(_) => std::slice::from_ref(result),
+        _ => &[],
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let (uri, range) = match item.get("targetUri") {
+                Some(uri) => (uri, &item["targetSelectionRange"]),
+                None => (&item["uri"], &item["range"]),
+            };
+            let start = &range["start"];
+            Some(Location {
+                path: uri_to_path(uri.as_str()?)?,
+                line: usize::try_from(start["line"].as_u64()?).ok()?,
+                character: start["character"].as_u64()?,
+            })
+        })
+        .collect()
+}
+
+/// Lines of hover `contents`: `MarkupContent`, a `MarkedString` or an
+/// array of them. Markdown code fences are dropped (their code is kept),
+/// runs of blank lines collapse to one and separate the parts.
+fn hover_lines(contents: &Value) -> Vec<String> {
+    let parts: Vec<&str> = match contents {
+        Value::String(text) => vec![text.as_str()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().or_else(|| item["value"].as_str()))
+            .collect(),
+        Value::Object(_) => contents["value"].as_str().into_iter().collect(),
+        _ => Vec::new(),
+    };
+    let mut lines: Vec<String> = Vec::new();
+    for part in parts {
+        lines.push(String::new());
+        for line in part.lines() {
+            let line = line.trim_end();
+            if line.trim_start().starts_with("```")
+                || (line.is_empty() && lines.last().is_some_and(String::is_empty))
+            {
+                continue;
+            }
+            lines.push(line.to_string());
+        }
+    }
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let leading = lines.iter().take_while(|line| line.is_empty()).count();
+    lines.split_off(leading)
+}
+
+/// Items of a completion answer (`CompletionItem[]` or `CompletionList`).
+fn completion_items(result: &Value) -> Vec<CompletionItem> {
+    let items = match result {
+        Value::Array(items) => items.as_slice(),
+        _ => result["items"].as_array().map_or(&[][..], Vec::as_slice),
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let label = item["label"].as_str()?;
+            let snippet = item["insertTextFormat"].as_u64() == Some(2);
+            let text = item["textEdit"]["newText"]
+                .as_str()
+                .or(item["insertText"].as_str())
+                .filter(|_| !snippet)
+                .unwrap_or(label);
+            Some(CompletionItem {
+                text: text.to_string(),
+                filter: item["filterText"].as_str().unwrap_or(label).to_string(),
+                kind: item["kind"]
+                    .as_u64()
+                    .and_then(kind_name)
+                    .map(str::to_string),
+                source: CompletionSource::Lsp,
+            })
+        })
+        .collect()
+}
+
+/// Name of an LSP `CompletionItemKind`.
+fn kind_name(kind: u64) -> Option<&'static str> {
+    const NAMES: [&str; 25] = [
+        "Text",
+        "Method",
+        "Function",
+        "Constructor",
+        "Field",
+        "Variable",
+        "Class",
+        "Interface",
+        "Module",
+        "Property",
+        "Unit",
+        "Value",
+        "Enum",
+        "Keyword",
+        "Snippet",
+        "Color",
+        "File",
+        "Reference",
+        "Folder",
+        "EnumMember",
+        "Constant",
+        "Struct",
+        "Event",
+        "Operator",
+        "TypeParameter",
+    ];
+    NAMES
+        .get(usize::try_from(kind).ok()?.checked_sub(1)?)
+        .copied()
+}
+
+fn publish_diagnostics(session: &Session, params: &Value, state: &mut EditorState) {
+    let Some(path) = params["uri"].as_str().and_then(uri_to_path) else {
+        return;
+    };
+    let Some((buffer, _)) = session
+        .documents
+        .iter()
+        .find(|(_, doc)| uri_to_path(&doc.uri).as_deref() == Some(path.as_path()))
+    else {
+        tracing::debug!(target: "runtime.lsp", uri = %params["uri"], "lsp_diagnostics_unknown_document");
+        return;
+    };
+    let Some(entry) = state.buffers.get(*buffer) else {
+        return;
+    };
+    let diagnostics = params["diagnostics"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|d| convert_diagnostic(&entry.buffer, d))
+                .collect()
+        })
+        .unwrap_or_default();
+    state.diagnostics.set(*buffer, diagnostics);
+}
+
+/// An LSP `Diagnostic` as a store entry; `None` when it has no range.
+fn convert_diagnostic(buffer: &Buffer, d: &Value) -> Option<Diagnostic> {
+    let range = d.get("range")?;
+    let severity = match d["severity"].as_u64() {
+        Some(2) => Severity::Warning,
+        Some(3) => Severity::Info,
+        Some(4) => Severity::Hint,
+        _ => Severity::Error,
+    };
+    let message = match d["source"].as_str() {
+        Some(source) => format!("{source}: {}", d["message"].as_str().unwrap_or_default()),
+        None => d["message"].as_str().unwrap_or_default().to_string(),
+    };
+    Some(Diagnostic {
+        severity,
+        start: lsp_position(buffer, &range["start"])?,
+        end: lsp_position(buffer, &range["end"])?,
+        message,
+    })
+}
+
+/// Byte position of an LSP position (line, UTF-16 column), clamped to the
+/// end of its line and of the buffer.
+fn lsp_position(buffer: &Buffer, position: &Value) -> Option<Position> {
+    let line = usize::try_from(position["line"].as_u64()?).ok()?;
+    let character = position["character"].as_u64()?;
+    let last = buffer.line_count().saturating_sub(1);
+    let (line, character) = if line > last {
+        (last, u64::MAX)
+    } else {
+        (line, character)
+    };
+    let text = buffer.line(line).unwrap_or_default();
+    let text = text.trim_end_matches(['\n', '\r']);
+    Some(Position::new(line, utf16_to_byte(text, character)))
+}
+
+/// Byte offset of UTF-16 column `character` in `line`; a column inside a
+/// surrogate pair or past the end rounds up to the next boundary.
+fn utf16_to_byte(line: &str, character: u64) -> usize {
+    let mut units = 0u64;
+    for (byte, c) in line.char_indices() {
+        if units >= character {
+            return byte;
+        }
+        units += c.len_utf16() as u64;
+    }
+    line.len()
+}
+
+/// UTF-16 column of byte offset `byte` in `line`.
+fn byte_to_utf16(line: &str, byte: usize) -> u64 {
+    line.char_indices()
+        .take_while(|&(i, _)| i < byte)
+        .map(|(_, c)| c.len_utf16() as u64)
+        .sum()
+}
+
+/// `file://` URI of `path`, made absolute against the working directory.
+pub fn path_to_uri(path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut text = absolute.to_string_lossy().replace('\\', "/");
+    if !text.starts_with('/') {
+        text.insert(0, '/');
+    }
+    let mut uri = String::from("file://");
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'.' | b'_' | b'~') {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{b:02X}"));
+        }
+    }
+    uri
+}
+
+/// Path of a `file://` URI; `None` for other schemes or bad escapes.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    let text = String::from_utf8(bytes).ok()?;
+    // `/C:/dir` on Windows.
+    let text = match text.as_bytes() {
+        [b'/', drive, b':', ..] if cfg!(windows) && drive.is_ascii_alphabetic() => &text[1..],
+        _ => text.as_str(),
+    };
+    Some(PathBuf::from(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uris_round_trip_and_columns_count_utf16() {
+        let path = Path::new("/tmp/a dir/ü.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/a%20dir/%C3%BC.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+        assert_eq!(uri_to_path("untitled:1"), None);
+
+        // `😀` is two UTF-16 units and four bytes.
+        assert_eq!(utf16_to_byte("a😀b", 1), 1);
+        assert_eq!(utf16_to_byte("a😀b", 3), 5);
+        assert_eq!(utf16_to_byte("a😀b", 2), 5);
+        assert_eq!(utf16_to_byte("a😀b", 99), 6);
+        assert_eq!(byte_to_utf16("a😀b", 5), 3);
+    }
+
+    #[test]
+    fn documents_sync_and_diagnostics_land_in_the_store() {
+        let mut state =
+            EditorState::new(Buffer::from_str("t", "let x = 1;\nfn 😀f() {}\n").unwrap());
+        let path = std::env::temp_dir().join("oxidized-lsp-test.rs");
+        let buffer = state.active;
+        state.buffers.get_mut(buffer).unwrap().meta.path = Some(path.clone());
+        let mut config = LspConfig::default();
+        config.servers.insert(
+            "rust".into(),
+            LspServerConfig {
+                command: "unused".into(),
+                args: Vec::new(),
+                extensions: vec!["rs".into()],
+                language_id: None,
+            },
+        );
+        let mut sessions = LspSessions::new(config, std::env::temp_dir());
+        // A session whose source is never spawned: its outbox just queues.
+        let source = LspServerSource::new("rust", "unused", Vec::new(), ".".into());
+        sessions.sessions.insert(
+            "rust".into(),
+            Session {
+                client: source.client(),
+                language_id: "rust".into(),
+                initializing: Some(1),
+                running: true,
+                documents: HashMap::new(),
+                triggers: None,
+                completion: None,
+                goto: false,
+                pending_goto: None,
+                hover: false,
+                pending_hover: None,
+            },
+        );
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let message = |kind| LspMessage {
+            server: "rust".into(),
+            kind,
+        };
+
+        assert!(sessions.sync(&mut state, &tx).is_empty());
+        assert!(
+            sessions.sessions["rust"].documents.is_empty(),
+            "not initialized"
+        );
+        let initialized = LspMessageKind::Response {
+            id: 1,
+            result: Ok(json!({"capabilities": {
+                "completionProvider": {"triggerCharacters": [".", "::"]},
+                "hoverProvider": true,
+            }})),
+        };
+        assert!(sessions.handle(&message(initialized), &mut state));
+        sessions.sync(&mut state, &tx);
+        assert_eq!(sessions.sessions["rust"].documents[&buffer].version, 1);
+        assert!(state.completion.is_trigger(buffer, ':'));
+        state
+            .buffers
+            .get_mut(buffer)
+            .unwrap()
+            .buffer
+            .insert_str(0, "// ");
+        sessions.sync(&mut state, &tx);
+        assert_eq!(sessions.sessions["rust"].documents[&buffer].version, 2);
+
+        let publish = LspMessageKind::Notification {
+            method: "textDocument/publishDiagnostics".into(),
+            params: json!({
+                "uri": path_to_uri(&path),
+                "diagnostics": [
+                    {"range": {"start": {"line": 1, "character": 5},
+                               "end": {"line": 1, "character": 6}},
+                     "severity": 2, "source": "rustc", "message": "odd name"},
+                    {"range": {"start": {"line": 9, "character": 0},
+                               "end": {"line": 9, "character": 1}},
+                     "message": "past the end"},
+                ],
+            }),
+        };
+        sessions.handle(&message(publish), &mut state);
+        let stored = state.diagnostics.get(buffer);
+        assert_eq!(stored.len(), 2);
+        assert_eq!(
+            (stored[0].severity, stored[0].start, stored[0].end),
+            (Severity::Warning, Position::new(1, 7), Position::new(1, 8))
+        );
+        assert_eq!(stored[0].message, "rustc: odd name");
+        assert_eq!(
+            (stored[1].severity, stored[1].start),
+            (Severity::Error, Position::new(2, 0))
+        );
+
+        let generation = state
+            .completion
+            .open(buffer, Position::new(1, 3), "f".into(), Vec::new(), None)
+            .generation;
+        sessions.sync(&mut state, &tx);
+        let (id, asked) = sessions.sessions["rust"].completion.unwrap();
+        assert_eq!(asked, generation);
+        let answer = LspMessageKind::Response {
+            id,
+            result: Ok(json!({"isIncomplete": false, "items": [
+                {"label": "foo", "kind": 3, "textEdit": {"newText": "foo()"}},
+                {"label": "fmt", "insertText": "fmt!($1)", "insertTextFormat": 2},
+                {"label": "bar"},
+            ]})),
+        };
+        sessions.handle(&message(answer), &mut state);
+        let completion = state.completion.active().unwrap();
+        let items: Vec<_> = completion
+            .matches()
+            .map(|m| (m.text.as_str(), m.kind.as_deref()))
+            .collect();
+        assert_eq!(items, [("foo()", Some("Function")), ("fmt", None)]);
+
+        assert!(state.hover.is_attached(buffer));
+        state.hover.request(buffer, Position::new(1, 7));
+        sessions.sync(&mut state, &tx);
+        let (id, _) = sessions.sessions["rust"].pending_hover.unwrap();
+        let answer = LspMessageKind::Response {
+            id,
+            result: Ok(json!({"contents": {
+                "kind": "markdown",
+                "value": "```rust\nfn f()\n```\n\n\nDoes things.\n",
+            }})),
+        };
+        sessions.handle(&message(answer), &mut state);
+        assert_eq!(
+            state.hover.active().unwrap().lines,
+            ["fn f()", "", "Does things."]
+        );
+        assert_eq!(
+            hover_lines(&json!(["one", {"language": "rust", "value": "two"}])),
+            ["one", "", "two"]
+        );
+
+        sessions.handle(&message(LspMessageKind::Exited { error: None }), &mut state);
+        assert!(!state.completion.is_attached(buffer));
+        assert!(!state.diagnostics.has_diagnostics(buffer));
+        assert_eq!(sessions.servers().collect::<Vec<_>>(), [("rust", false)]);
+        assert!(sessions.sync(&mut state, &tx).is_empty(), "not restarted");
+    }
+}
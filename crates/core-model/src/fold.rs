@@ -0,0 +1,283 @@
+//! Manual folds (`zf`, `zo`, `zc`, `za`).
+//!
+//! Folds belong to a view, as in Vim. Each fold is an inclusive range of
+//! buffer lines that is open or closed; a closed fold shows as a single
+//! summary row and hides every fold inside it. Folds nest: a new fold that
+//! crosses the edge of an existing one grows until it contains it.
+//!
+//! Folds follow the buffer's edit log (`Folds::follow`): lines added or
+//! removed before a fold move it, and a fold whose lines are all deleted
+//! goes away. When the edits are not known (undo, reload) the folds stay
+//! where they were and readers clamp them to the buffer.
+
+use core_text::{Buffer, EditCursor, TextEdit};
+use std::ops::RangeInclusive;
+
+/// One fold: lines `start..=end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+    pub closed: bool,
+}
+
+impl Fold {
+    fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+}
+
+/// Fold command errors, numbered like Vim's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldError {
+    NoFold,
+}
+
+impl std::fmt::Display for FoldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FoldError::NoFold => write!(f, "E490: No fold found"),
+        }
+    }
+}
+
+impl std::error::Error for FoldError {}
+
+/// The folds of one view, sorted by start and, for equal starts, outer
+/// (longer) folds first. Folds containing a given line therefore come
+/// outermost first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Folds {
+    folds: Vec<Fold>,
+    /// Where in the buffer's edit log the folds were last moved to.
+    seen: Option<EditCursor>,
+}
+
+impl Folds {
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Fold> {
+        self.folds.iter()
+    }
+
+    /// Create a closed fold over `start..=end` (`zf`) and return the range
+    /// it ended up with after growing over folds it crosses.
+    pub fn create(&mut self, start: usize, end: usize) -> RangeInclusive<usize> {
+        let (mut start, mut end) = (start.min(end), start.max(end));
+        loop {
+            let crossing = self.folds.iter().find(|f| {
+                let overlaps = f.start <= end && f.end >= start;
+                let inside = f.start >= start && f.end <= end;
+                let around = f.start <= start && f.end >= end;
+                overlaps && !inside && !around
+            });
+            match crossing {
+                Some(f) => {
+                    start = start.min(f.start);
+                    end = end.max(f.end);
+                }
+                None => break,
+            }
+        }
+        match self
+            .folds
+            .iter_mut()
+            .find(|f| f.start == start && f.end == end)
+        {
+            Some(fold) => fold.closed = true,
+            None => {
+                self.folds.push(Fold {
+                    start,
+                    end,
+                    closed: true,
+                });
+                self.folds
+                    .sort_by_key(|f| (f.start, std::cmp::Reverse(f.end)));
+            }
+        }
+        start..=end
+    }
+
+    /// Lines of the outermost closed fold containing `line`: the range
+    /// drawn as one summary row.
+    pub fn closed_at(&self, line: usize) -> Option<RangeInclusive<usize>> {
+        self.folds
+            .iter()
+            .find(|f| f.closed && f.contains(line))
+            .map(|f| f.start..=f.end)
+    }
+
+    /// First line of the row showing `line`: the start of the closed fold
+    /// hiding it, or the line itself.
+    pub fn visible_start(&self, line: usize) -> usize {
+        self.closed_at(line).map_or(line, |r| *r.start())
+    }
+
+    /// Innermost fold containing `line`, if any.
+    pub fn innermost(&self, line: usize) -> Option<&Fold> {
+        self.folds.iter().rev().find(|f| f.contains(line))
+    }
+
+    /// Open the closed fold shown at `line` (`zo`). Opening an open fold
+    /// does nothing; E490 when no fold contains the line.
+    pub fn open(&mut self, line: usize) -> Result<(), FoldError> {
+        if !self.folds.iter().any(|f| f.contains(line)) {
+            return Err(FoldError::NoFold);
+        }
+        if let Some(fold) = self.folds.iter_mut().find(|f| f.closed && f.contains(line)) {
+            fold.closed = false;
+        }
+        Ok(())
+    }
+
+    /// Close one more level at `line` (`zc`): the innermost open fold
+    /// that is not already hidden by a closed one.
+    pub fn close(&mut self, line: usize) -> Result<(), FoldError> {
+        let containing: Vec<usize> = (0..self.folds.len())
+            .filter(|&i| self.folds[i].contains(line))
+            .collect();
+        let Some(&innermost) = containing.last() else {
+            return Err(FoldError::NoFold);
+        };
+        let target = match containing.iter().position(|&i| self.folds[i].closed) {
+            Some(0) => return Ok(()),
+            Some(pos) => containing[pos - 1],
+            None => innermost,
+        };
+        self.folds[target].closed = true;
+        Ok(())
+    }
+
+    /// Move the folds with the edits made to `text` since the last call.
+    pub fn follow(&mut self, text: &Buffer) {
+        if let Some(seen) = self.seen
+            && let Some(edits) = text.edits_since(seen)
+        {
+            for edit in edits {
+                self.shift(edit);
+            }
+        }
+        self.seen = Some(text.edit_cursor());
+    }
+
+    /// Move fold edges after `edit` by the lines it added or removed; an
+    /// edge inside deleted text lands on what is left around it.
+    fn shift(&mut self, edit: &TextEdit) {
+        let start = (edit.start.line, edit.start.byte);
+        let old_end = (edit.old_end.line, edit.old_end.byte);
+        // Where the first byte of `line` ended up; `None` if it was deleted.
+        let moved = |line: usize| {
+            if (line, 0) < start {
+                Some(line)
+            } else if (line, 0) >= old_end {
+                Some(line - edit.old_end.line + edit.new_end.line)
+            } else {
+                None
+            }
+        };
+        for fold in &mut self.folds {
+            let first = moved(fold.start).unwrap_or(edit.new_end.line);
+            let last = match moved(fold.end) {
+                Some(line) => Some(line),
+                None if edit.start.byte == 0 => edit.start.line.checked_sub(1),
+                None => Some(edit.start.line),
+            };
+            // A fold left with no lines is marked for removal below.
+            (fold.start, fold.end) = match last {
+                Some(last) if last >= first => (first, last),
+                _ => (usize::MAX, 0),
+            };
+        }
+        self.folds.retain(|f| f.start <= f.end);
+        self.folds
+            .sort_by_key(|f| (f.start, std::cmp::Reverse(f.end)));
+        self.folds.dedup_by_key(|f| (f.start, f.end));
+    }
+
+    /// `za`: open a closed fold at `line`, else close one.
+    pub fn toggle(&mut self, line: usize) -> Result<(), FoldError> {
+        if self.closed_at(line).is_some() {
+            self.open(line)
+        } else {
+            self.close(line)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_grows_over_crossed_folds_and_nests() {
+        let mut folds = Folds::default();
+        assert_eq!(folds.create(4, 2), 2..=4);
+        // Crossing the end of 2..=4 grows the new fold to contain it.
+        assert_eq!(folds.create(3, 6), 2..=6);
+        assert_eq!(folds.create(0, 9), 0..=9);
+        assert_eq!(folds.closed_at(3), Some(0..=9));
+        assert_eq!(folds.visible_start(5), 0);
+        assert_eq!(folds.innermost(3).map(|f| f.start..=f.end), Some(2..=4));
+        assert_eq!(folds.closed_at(12), None);
+    }
+
+    #[test]
+    fn open_close_and_toggle_step_one_level() {
+        let mut folds = Folds::default();
+        folds.create(2, 4);
+        folds.create(0, 9);
+        folds.open(3).unwrap();
+        assert_eq!(folds.closed_at(3), Some(2..=4));
+        folds.open(3).unwrap();
+        assert_eq!(folds.closed_at(3), None);
+        // zc closes the inner fold first, then its parent.
+        folds.close(3).unwrap();
+        assert_eq!(folds.closed_at(3), Some(2..=4));
+        folds.close(3).unwrap();
+        assert_eq!(folds.closed_at(3), Some(0..=9));
+        folds.toggle(3).unwrap();
+        assert_eq!(folds.closed_at(3), Some(2..=4));
+        folds.toggle(7).unwrap();
+        assert_eq!(folds.closed_at(7), Some(0..=9));
+        assert_eq!(folds.open(12), Err(FoldError::NoFold));
+        assert_eq!(
+            folds.close(12).unwrap_err().to_string(),
+            "E490: No fold found"
+        );
+    }
+
+    #[test]
+    fn folds_follow_lines_added_and_removed_above_them() {
+        let text: String = (0..12).map(|i| format!("{i}\n")).collect();
+        let mut buf = Buffer::from_str("t", &text).unwrap();
+        let mut folds = Folds::default();
+        folds.follow(&buf);
+        folds.create(4, 6);
+        folds.create(8, 9);
+        let ranges = |folds: &Folds| -> Vec<_> { folds.iter().map(|f| f.start..=f.end).collect() };
+
+        // Two lines opened above both folds (`2O`).
+        buf.insert_str(buf.line_to_byte(1), "a\nb\n");
+        folds.follow(&buf);
+        assert_eq!(ranges(&folds), [6..=8, 10..=11]);
+
+        // `dd` on the first folded line shrinks that fold only.
+        let line6 = buf.line_to_byte(6);
+        buf.delete_bytes(line6, buf.line_to_byte(7));
+        folds.follow(&buf);
+        assert_eq!(ranges(&folds), [6..=7, 9..=10]);
+
+        // Deleting every line of a fold removes it.
+        buf.delete_bytes(buf.line_to_byte(9), buf.line_to_byte(11));
+        folds.follow(&buf);
+        assert_eq!(ranges(&folds), [6..=7]);
+
+        // Text swapped in wholesale leaves the folds alone.
+        let mut buf = buf.clone();
+        buf.insert_str(0, "x\n");
+        folds.follow(&buf);
+        assert_eq!(ranges(&folds), [6..=7]);
+    }
+}
@@ -1,41 +1,37 @@
-//! Layout scaffolding (Refactor R3 Step 9).
+//! Window layout: a tree of splits resolved into screen regions.
 //!
-//! Breadth-first single-region layout abstraction introduced to decouple
-//! rendering surface geometry from render logic and prepare for future
-//! multi-view splits (horizontal/vertical, grid, stacked, tabbed). For now
-//! a `Layout` always contains exactly one `LayoutRegion` representing the
-//! full terminal viewport. The public API *looks* multi-region capable but
-//! enforces single-region invariants at runtime (debug assertions) so that
-//! later expansion does not require signature churn across crates.
+//! The structure of the window arrangement lives in a `LayoutTree` owned by
+//! the `ViewManager`: leaves name `View`s, interior nodes divide their area
+//! between children along one axis. The tree carries no sizes; a concrete
+//! `Layout` (the regions each view paints into, their status rows and the
+//! separators drawn between them) is computed from the tree for the current
+//! text area every frame, so terminal resizes redistribute space without
+//! touching the tree.
+//!
+//! Space is shared equally between the children of a split (Vim's default
+//! `'equalalways'`), with leftover cells handed to the first children. Once
+//! the area is split every view reserves its bottom row for its own status
+//! line, which also divides vertically stacked views; side-by-side views are
+//! divided by a one-column separator. A lone view has no status row (the
+//! global status line serves it).
 //!
 //! Design Tenets Applied:
 //! * Modularity: Geometry lives in `core-model` with other high level model
 //!   concepts (views) rather than inside the renderer crate.
-//! * Evolution Over Legacy: We intentionally start minimal instead of
-//!   prematurely modeling split containers. Future phases can extend the
-//!   `Layout` struct with region trees or constraints; existing call sites
-//!   will remain source compatible.
 //! * Unicode & Rendering Correctness: Region coordinates are expressed in
 //!   terminal cell units (`u16`) aligning with existing rendering APIs.
-//! * Documentation: Invariants and forward roadmap captured inline.
 //!
-//! Invariants (current phase):
-//! * `regions.len() == 1`.
-//! * Region 0 has origin (0,0).
-//! * Width/height are the terminal reported dimensions.
+//! Invariants:
+//! * Regions, status rows and separators never overlap and stay inside the
+//!   area passed to `LayoutTree::compute`.
+//! * `Layout::single` describes the full terminal as one unassigned region
+//!   (the single-view render paths); computed layouts assign one region per
+//!   leaf, in tree order, plus one status row per leaf when split.
+//! * A split node always has at least two children; removing a leaf
+//!   collapses a parent left with one child into that child.
 //! * Width/height may be 0 (degenerate) but never exceed `u16::MAX`.
-//!
-//! Forward Roadmap (future phases, not yet implemented):
-//! * Multiple regions addressing independent `View` instances.
-//! * Region z-order & per-region status lines.
-//! * Dynamic recompute on split create/close.
-//! * Constraint solver or simple tiling strategy for evenly distributing
-//!   space with minimum size hints.
-//! * Persistent layout serialization in config file.
-//!
-//! Testing Strategy:
-//! * Current test asserts single() constructor sets invariants.
-//! * Future tests will cover split operations & region mapping.
+
+use crate::ViewId;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LayoutRegion {
@@ -56,9 +52,289 @@ impl LayoutRegion {
     }
 }
 
+/// Direction in which a split divides its area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitAxis {
+    /// Children stacked top to bottom (`:split`).
+    Horizontal,
+    /// Children side by side (`:vsplit`).
+    Vertical,
+}
+
+/// Target of a window focus move (`<C-w>h/j/k/l/w`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Down,
+    Up,
+    Right,
+    /// Next view in layout order, wrapping around (`<C-w>w`).
+    Next,
+}
+
+/// One column drawn between two side-by-side regions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Separator {
+    /// Axis of the split the separator belongs to. Only `Vertical` splits
+    /// draw separators today (a column); stacked views are divided by their
+    /// status rows.
+    pub axis: SplitAxis,
+    pub region: LayoutRegion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutNode {
+    Leaf(ViewId),
+    Split {
+        axis: SplitAxis,
+        children: Vec<LayoutNode>,
+    },
+}
+
+/// Split structure of the window arrangement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutTree {
+    root: LayoutNode,
+}
+
+impl LayoutTree {
+    pub fn new(view: ViewId) -> Self {
+        Self {
+            root: LayoutNode::Leaf(view),
+        }
+    }
+
+    pub fn root(&self) -> &LayoutNode {
+        &self.root
+    }
+
+    /// Views in tree order (top-left to bottom-right).
+    pub fn leaves(&self) -> Vec<ViewId> {
+        fn walk(node: &LayoutNode, out: &mut Vec<ViewId>) {
+            match node {
+                LayoutNode::Leaf(id) => out.push(*id),
+                LayoutNode::Split { children, .. } => {
+                    children.iter().for_each(|c| walk(c, out));
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.root, &mut out);
+        out
+    }
+
+    /// Place `new` next to `target`, above (`Horizontal`) or left of
+    /// (`Vertical`) it like Vim's `:split` / `:vsplit`. A split of the same
+    /// axis gains a sibling; otherwise `target` becomes a new split node.
+    /// Returns false when `target` is not in the tree.
+    pub fn split(&mut self, target: ViewId, new: ViewId, axis: SplitAxis) -> bool {
+        fn walk(node: &mut LayoutNode, target: ViewId, new: ViewId, axis: SplitAxis) -> bool {
+            match node {
+                LayoutNode::Leaf(id) if *id == target => {
+                    *node = LayoutNode::Split {
+                        axis,
+                        children: vec![LayoutNode::Leaf(new), LayoutNode::Leaf(target)],
+                    };
+                    true
+                }
+                LayoutNode::Leaf(_) => false,
+                LayoutNode::Split {
+                    axis: node_axis,
+                    children,
+                } => {
+                    if *node_axis == axis
+                        && let Some(pos) =
+                            children.iter().position(|c| *c == LayoutNode::Leaf(target))
+                    {
+                        children.insert(pos, LayoutNode::Leaf(new));
+                        return true;
+                    }
+                    children.iter_mut().any(|c| walk(c, target, new, axis))
+                }
+            }
+        }
+        walk(&mut self.root, target, new, axis)
+    }
+
+    /// Place `new` below everything else, spanning the full width.
+    pub fn push_bottom(&mut self, new: ViewId) {
+        match &mut self.root {
+            LayoutNode::Split {
+                axis: SplitAxis::Horizontal,
+                children,
+            } => children.push(LayoutNode::Leaf(new)),
+            root => {
+                let old = std::mem::replace(root, LayoutNode::Leaf(new));
+                *root = LayoutNode::Split {
+                    axis: SplitAxis::Horizontal,
+                    children: vec![old, LayoutNode::Leaf(new)],
+                };
+            }
+        }
+    }
+
+    /// Remove the leaf for `view`, collapsing single-child splits. The last
+    /// leaf is never removed. Returns false when nothing was removed.
+    pub fn remove(&mut self, view: ViewId) -> bool {
+        fn walk(node: &mut LayoutNode, view: ViewId) -> bool {
+            let LayoutNode::Split { children, .. } = node else {
+                return false;
+            };
+            if let Some(pos) = children.iter().position(|c| *c == LayoutNode::Leaf(view)) {
+                children.remove(pos);
+            } else if !children.iter_mut().any(|c| walk(c, view)) {
+                return false;
+            }
+            if children.len() == 1 {
+                *node = children.pop().expect("one child");
+            }
+            true
+        }
+        walk(&mut self.root, view)
+    }
+
+    /// View reached from `view` by moving focus in `direction`, or `None`
+    /// when `view` is already at that edge of the layout.
+    ///
+    /// Directional moves climb to the nearest split along the matching axis
+    /// with a sibling on that side, then descend into it, entering nested
+    /// splits of the same axis from the near side and other splits through
+    /// their first child.
+    pub fn neighbor(&self, view: ViewId, direction: FocusDirection) -> Option<ViewId> {
+        let (axis, forward) = match direction {
+            FocusDirection::Left => (SplitAxis::Vertical, false),
+            FocusDirection::Right => (SplitAxis::Vertical, true),
+            FocusDirection::Up => (SplitAxis::Horizontal, false),
+            FocusDirection::Down => (SplitAxis::Horizontal, true),
+            FocusDirection::Next => {
+                let order = self.leaves();
+                let pos = order.iter().position(|v| *v == view)?;
+                let next = order[(pos + 1) % order.len()];
+                return (next != view).then_some(next);
+            }
+        };
+        let mut path: Vec<(&LayoutNode, usize)> = Vec::new();
+        if !Self::path_to(&self.root, view, &mut path) {
+            return None;
+        }
+        let mut target = path.iter().rev().find_map(|(node, idx)| match node {
+            LayoutNode::Split {
+                axis: node_axis,
+                children,
+            } if *node_axis == axis => match forward {
+                true => children.get(idx + 1),
+                false => idx.checked_sub(1).map(|i| &children[i]),
+            },
+            _ => None,
+        })?;
+        loop {
+            match target {
+                LayoutNode::Leaf(id) => return Some(*id),
+                LayoutNode::Split {
+                    axis: node_axis,
+                    children,
+                } => {
+                    target = match (*node_axis == axis, forward) {
+                        (true, false) => children.last()?,
+                        _ => children.first()?,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Split nodes from the root down to `view`'s leaf, each with the index
+    /// of the child taken.
+    fn path_to<'a>(
+        node: &'a LayoutNode,
+        view: ViewId,
+        path: &mut Vec<(&'a LayoutNode, usize)>,
+    ) -> bool {
+        match node {
+            LayoutNode::Leaf(id) => *id == view,
+            LayoutNode::Split { children, .. } => {
+                for (i, child) in children.iter().enumerate() {
+                    path.push((node, i));
+                    if Self::path_to(child, view, path) {
+                        return true;
+                    }
+                    path.pop();
+                }
+                false
+            }
+        }
+    }
+
+    /// Resolve the tree into regions covering `area`.
+    pub fn compute(&self, area: LayoutRegion) -> Layout {
+        let mut layout = Layout {
+            regions: Vec::new(),
+            views: Vec::new(),
+            statuses: Vec::new(),
+            separators: Vec::new(),
+        };
+        let with_status = matches!(self.root, LayoutNode::Split { .. });
+        Self::place(&self.root, area, with_status, &mut layout);
+        layout
+    }
+
+    fn place(node: &LayoutNode, area: LayoutRegion, with_status: bool, out: &mut Layout) {
+        let (axis, children) = match node {
+            LayoutNode::Leaf(id) => {
+                if with_status {
+                    let text = area.height.saturating_sub(1);
+                    out.regions
+                        .push(LayoutRegion::new(area.x, area.y, area.width, text));
+                    out.statuses.push(LayoutRegion::new(
+                        area.x,
+                        area.y + text,
+                        area.width,
+                        area.height.min(1),
+                    ));
+                } else {
+                    out.regions.push(area);
+                }
+                out.views.push(*id);
+                return;
+            }
+            LayoutNode::Split { axis, children } => (*axis, children),
+        };
+        let n = children.len() as u16;
+        let (total, gap) = match axis {
+            SplitAxis::Horizontal => (area.height, 0),
+            SplitAxis::Vertical => (area.width, 1),
+        };
+        let available = total.saturating_sub((n - 1) * gap);
+        let (base, extra) = (available / n, available % n);
+        let mut offset = 0u16;
+        for (i, child) in children.iter().enumerate() {
+            let size = base + u16::from((i as u16) < extra);
+            let (child_area, sep) = match axis {
+                SplitAxis::Horizontal => (
+                    LayoutRegion::new(area.x, area.y + offset, area.width, size),
+                    LayoutRegion::new(area.x, area.y + offset + size, area.width, 1),
+                ),
+                SplitAxis::Vertical => (
+                    LayoutRegion::new(area.x + offset, area.y, size, area.height),
+                    LayoutRegion::new(area.x + offset + size, area.y, 1, area.height),
+                ),
+            };
+            Self::place(child, child_area, with_status, out);
+            offset += size;
+            if gap > 0 && (i as u16) + 1 < n && offset < total {
+                out.separators.push(Separator { axis, region: sep });
+                offset += gap;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Layout {
     regions: Vec<LayoutRegion>,
+    views: Vec<ViewId>,
+    statuses: Vec<LayoutRegion>,
+    separators: Vec<Separator>,
 }
 
 impl Layout {
@@ -66,13 +342,15 @@ impl Layout {
     pub fn single(width: u16, height: u16) -> Self {
         Self {
             regions: vec![LayoutRegion::new(0, 0, width, height)],
+            views: Vec::new(),
+            statuses: Vec::new(),
+            separators: Vec::new(),
         }
     }
 
-    /// Return the primary (currently only) region.
+    /// Return the primary region of a single-region layout.
     pub fn primary(&self) -> &LayoutRegion {
-        // Debug assert current single-region invariant.
-        debug_assert!(self.regions.len() == 1, "multi-region not yet enabled");
+        debug_assert!(self.regions.len() == 1, "primary() on a split layout");
         &self.regions[0]
     }
 
@@ -80,11 +358,26 @@ impl Layout {
         &self.regions
     }
 
-    /// Internal (future) helper to push a region. Unused now; retained as a
-    /// placeholder illustrating likely extension point.
-    #[allow(dead_code)]
-    fn push_region(&mut self, region: LayoutRegion) {
-        self.regions.push(region);
+    /// Views assigned to `regions()` (same order); empty for `single`.
+    pub fn views(&self) -> &[ViewId] {
+        &self.views
+    }
+
+    /// Per-view status rows (same order as `views()`); empty unless split.
+    pub fn statuses(&self) -> &[LayoutRegion] {
+        &self.statuses
+    }
+
+    pub fn separators(&self) -> &[Separator] {
+        &self.separators
+    }
+
+    /// Region painted by `view`, if it is part of this layout.
+    pub fn region_of(&self, view: ViewId) -> Option<LayoutRegion> {
+        self.views
+            .iter()
+            .position(|v| *v == view)
+            .map(|i| self.regions[i])
     }
 }
 
@@ -102,4 +395,107 @@ mod tests {
         assert_eq!(r.width, 80);
         assert_eq!(r.height, 24);
     }
+
+    #[test]
+    fn horizontal_splits_share_rows_with_status_lines() {
+        let mut tree = LayoutTree::new(ViewId(0));
+        assert!(tree.split(ViewId(0), ViewId(1), SplitAxis::Horizontal));
+        assert!(tree.split(ViewId(0), ViewId(2), SplitAxis::Horizontal));
+        assert_eq!(tree.leaves(), [ViewId(1), ViewId(2), ViewId(0)]);
+        let layout = tree.compute(LayoutRegion::new(0, 0, 80, 23));
+        // 23 rows: 8 + 8 + 7, each ending in its status row.
+        assert_eq!(
+            layout.regions(),
+            [
+                LayoutRegion::new(0, 0, 80, 7),
+                LayoutRegion::new(0, 8, 80, 7),
+                LayoutRegion::new(0, 16, 80, 6),
+            ]
+        );
+        let rows: Vec<u16> = layout.statuses().iter().map(|s| s.y).collect();
+        assert_eq!(rows, [7, 15, 22]);
+        assert!(layout.separators().is_empty());
+        assert_eq!(
+            layout.region_of(ViewId(0)),
+            Some(LayoutRegion::new(0, 16, 80, 6))
+        );
+        assert!(
+            LayoutTree::new(ViewId(0))
+                .compute(LayoutRegion::new(0, 0, 80, 23))
+                .statuses()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn push_bottom_spans_the_full_width() {
+        let mut tree = LayoutTree::new(ViewId(0));
+        tree.split(ViewId(0), ViewId(1), SplitAxis::Vertical);
+        tree.push_bottom(ViewId(2));
+        let layout = tree.compute(LayoutRegion::new(0, 0, 81, 20));
+        assert_eq!(
+            layout.region_of(ViewId(2)),
+            Some(LayoutRegion::new(0, 10, 81, 9))
+        );
+        tree.push_bottom(ViewId(3));
+        assert_eq!(tree.leaves(), [ViewId(1), ViewId(0), ViewId(2), ViewId(3)]);
+    }
+
+    #[test]
+    fn mixed_layout_nests_axes() {
+        let mut tree = LayoutTree::new(ViewId(0));
+        tree.split(ViewId(0), ViewId(1), SplitAxis::Horizontal);
+        tree.split(ViewId(1), ViewId(2), SplitAxis::Vertical);
+        let layout = tree.compute(LayoutRegion::new(0, 0, 81, 21));
+        assert_eq!(
+            layout.region_of(ViewId(2)),
+            Some(LayoutRegion::new(0, 0, 40, 10))
+        );
+        assert_eq!(
+            layout.region_of(ViewId(1)),
+            Some(LayoutRegion::new(41, 0, 40, 10))
+        );
+        assert_eq!(
+            layout.region_of(ViewId(0)),
+            Some(LayoutRegion::new(0, 11, 81, 9))
+        );
+        assert_eq!(
+            layout.separators(),
+            [Separator {
+                axis: SplitAxis::Vertical,
+                region: LayoutRegion::new(40, 0, 1, 11)
+            }]
+        );
+        assert_eq!(layout.statuses()[1], LayoutRegion::new(41, 10, 40, 1));
+    }
+
+    #[test]
+    fn neighbor_follows_split_geometry() {
+        // +-----+-----+
+        // |  2  |  1  |
+        // +-----+-----+
+        // |     0     |
+        // +-----------+
+        let mut tree = LayoutTree::new(ViewId(0));
+        tree.split(ViewId(0), ViewId(1), SplitAxis::Horizontal);
+        tree.split(ViewId(1), ViewId(2), SplitAxis::Vertical);
+        let n = |v, d| tree.neighbor(ViewId(v), d);
+        assert_eq!(n(2, FocusDirection::Right), Some(ViewId(1)));
+        assert_eq!(n(1, FocusDirection::Left), Some(ViewId(2)));
+        assert_eq!(n(1, FocusDirection::Down), Some(ViewId(0)));
+        assert_eq!(n(0, FocusDirection::Up), Some(ViewId(2)));
+        assert_eq!(n(0, FocusDirection::Down), None);
+        assert_eq!(n(2, FocusDirection::Left), None);
+        assert_eq!(n(0, FocusDirection::Next), Some(ViewId(2)));
+        assert_eq!(n(2, FocusDirection::Next), Some(ViewId(1)));
+    }
+
+    #[test]
+    fn remove_collapses_split() {
+        let mut tree = LayoutTree::new(ViewId(0));
+        tree.split(ViewId(0), ViewId(1), SplitAxis::Horizontal);
+        assert!(tree.remove(ViewId(1)));
+        assert_eq!(tree.root(), &LayoutNode::Leaf(ViewId(0)));
+        assert!(!tree.remove(ViewId(0)), "last leaf stays");
+    }
 }
@@ -16,59 +16,83 @@
 //! * Makes per-view policies (scroll margins, future horizontal offsets, fold
 //!   state, local options) naturally local.
 //!
-//! Breadth-first constraints (Phase 3 / Step 12):
-//! * Exactly one active view (index 0) is created; APIs intentionally *look* as
-//!   if N views could exist (Vec storage) while mutators are private/minimal.
-//! * No rendering fan-out yet: renderer receives only the active view; `Layout`
-//!   currently wraps a single region but signatures accept a layout reference
-//!   so multi-region traversal can remain a local change.
-//! * Undo/redo still operate at buffer granularity; per-view granularity (e.g.
-//!   independent unrelated buffers visible simultaneously) is deferred.
+//! Windows (splits):
+//! * `ViewManager` owns every view plus the `LayoutTree` arranging them.
+//!   `:split` clones the active view (same buffer, cursor and scroll) into a
+//!   new leaf above it and focuses the clone; closing a view collapses its
+//!   leaf.
+//! * Views may show the same or different buffers. The focused view's buffer
+//!   is always `EditorState::active`, so editing code keeps working on "the
+//!   active buffer"; `EditorModel` switches the state's buffer whenever focus
+//!   moves.
+//! * Undo/redo operate at buffer granularity: views sharing a buffer share
+//!   its history.
+//! * `'scrollbind'` is window-local: `ViewManager` keeps the group of bound
+//!   views, the global option value mirrors the focused view, and when a
+//!   bound view scrolls the runtime shifts its bound siblings by the same
+//!   number of lines (`EditorModel::scroll_bound_siblings`).
+//! * Diff mode (`core_state::diff`) is not a view property: the comparison
+//!   lives in `EditorState::diff` and applies to every view showing either
+//!   buffer. Auto-scroll counts its filler rows, and a bound view showing
+//!   the other compared buffer is lined up row for row instead of shifted.
+//!
+//! Tab pages:
+//! * A `TabPage` wraps one `ViewManager`, i.e. an independent window layout.
+//!   `EditorModel` holds the tab list and the current tab; every view
+//!   accessor (`active_view`, `views`, `layout`, ...) reads the current tab.
+//! * All tabs share `EditorState::buffers`. Switching tabs makes the new
+//!   tab's focused view buffer the active one, exactly like focusing a view.
+//! * View ids are unique within a tab only.
+//!
+//! Folds:
+//! * Each `View` carries its own manual folds (`fold::Folds`), so two
+//!   windows on one buffer fold independently. Cursor motions and
+//!   auto-scroll treat a closed fold as a single row; see `fold`.
 //!
 //! Core invariants (must hold after every public call):
+//! * `tabs` is never empty and the current tab index is in range.
 //! * `views` is never empty.
 //! * `active_view_index < views.len()`.
-//! * `views[i].buffer_index` always names an existing buffer inside
-//!   `EditorState` (`EditorState` is the source of truth for buffer storage).
+//! * The layout tree holds exactly one leaf per view.
+//! * The active view's `buffer_id` equals `EditorState::active`.
+//! * `views[i].buffer_id` always names an open buffer inside
+//!   `EditorState::buffers` (the source of truth for buffer storage). Ids are
+//!   stable, so closing another buffer never retargets a view.
 //! * Active view's cursor line is always a valid line index for its buffer
 //!   except transiently inside mutation helpers before re-clamp.
 //! * Auto-scroll never produces a negative / overflow first line; it clamps to
 //!   valid range based on the last known text height.
 //!
-//! Forward roadmap (deferred beyond Phase 3):
-//! * Multiple simultaneously rendered views (grid / stacked layout) with a
-//!   layout manager component; render engine will iterate `LayoutRegion`s and
-//!   schedule per-region partial decisions gated by terminal capabilities.
-//! * Per-view status line (potentially condensed global + local segments).
+//! Forward roadmap:
 //! * Buffer-focus changes as first-class events producing semantic `RenderDelta`.
 //! * View close/open life-cycle with undo isolation (per-buffer or per-view
 //!   stacks depending on chosen UX).
-//! * Horizontal scrolling & pending fold state added to `View`.
+//! * Horizontal scrolling added to `View`.
 //! * Persistent view identity for layout restoration across sessions.
 //!
 //! Safety notes:
-//! * All mutation APIs remain breadth-first minimal; they will likely become a
-//!   `ViewManager` facade once external commands can create/destroy splits.
 //! * We deliberately avoid exposing interior `views: &mut Vec<View>` to keep
 //!   invariants centralized.
 //!
 //! Testing strategy additions (Step 12):
 //! * Existing tests assert auto-scroll behavior & single-view initialization.
-//! * Future steps will add tests around view creation/focus once those APIs
-//!   land; documenting intent now avoids speculative APIs.
+//! * Lifecycle tests cover `open_view` / `close_view` / `focus_view_id`,
+//!   including the `ViewError` cases.
 //!
-//! Non-goals for Phase 3:
-//! * Rendering of more than one view.
-//! * Per-view configuration overrides (options table).
-//! * Cross-view diffing or synchronized scrolling.
+//! Non-goals:
+//! * Per-view configuration overrides beyond `'scrollbind'` (options table).
 //!
 //! Updating this doc is REQUIRED when adding any new field to `View` or any
 //! new invariant affecting view lifecycle. (Enforced by code review checklist.)
 
-use core_state::EditorState;
-use core_text::Position;
+use core_state::{BufferId, EditorState, QuickfixList};
+use core_text::wrap::WrapWidth;
+use core_text::{InvariantViolation, Position};
+pub mod fold;
 mod layout;
-pub use layout::{Layout, LayoutRegion};
+pub use layout::{
+    FocusDirection, Layout, LayoutNode, LayoutRegion, LayoutTree, Separator, SplitAxis,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// Stable identifier for a `View`.
@@ -81,45 +105,202 @@ pub struct ViewId(pub usize);
 #[derive(Debug, Clone)]
 pub struct View {
     pub id: ViewId,
-    pub buffer_index: usize,
+    pub buffer_id: BufferId,
     pub cursor: Position,
     pub viewport_first_line: usize,
+    pub folds: fold::Folds,
+    /// The window's location list (`:lgrep`, `:ldiag`); a split copies it.
+    pub location_list: QuickfixList,
 }
 
 impl View {
     pub fn new(
         id: ViewId,
-        buffer_index: usize,
+        buffer_id: BufferId,
         cursor: Position,
         viewport_first_line: usize,
     ) -> Self {
         Self {
             id,
-            buffer_index,
+            buffer_id,
             cursor,
             viewport_first_line,
+            folds: fold::Folds::default(),
+            location_list: QuickfixList::default(),
         }
     }
 }
 
+/// Errors from the view lifecycle API, numbered like Vim's window errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewError {
+    NoSuchView(ViewId),
+    NoSuchBuffer(BufferId),
+    LastView,
+}
+
+impl std::fmt::Display for ViewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViewError::NoSuchView(id) => write!(f, "E957: Invalid window number: {}", id.0),
+            ViewError::NoSuchBuffer(id) => write!(f, "E86: Buffer {id} does not exist"),
+            ViewError::LastView => write!(f, "E444: Cannot close last window"),
+        }
+    }
+}
+
+impl std::error::Error for ViewError {}
+
 /// Manager responsible for owning and manipulating the collection of `View`
-/// instances. Step 7 introduces this indirection so later phases can extend
-/// view lifecycle (create / close / focus) without re‑plumbing every call site
-/// that today reaches directly into the `EditorModel` fields. Breadth‑first the
-/// manager still enforces a single view invariant.
+/// instances and the layout tree arranging them.
+///
+/// Mutators (`open`, `split_active`, `close`, `focus_id`, `focus`) keep the
+/// invariants listed in the crate docs and check them in debug builds.
 #[derive(Debug)]
 pub struct ViewManager {
     views: Vec<View>,
     active: usize,
+    next_id: usize,
+    tree: LayoutTree,
+    /// Views in the scrollbind group (`'scrollbind'` set in them).
+    scroll_bound: Vec<ViewId>,
 }
 
 impl ViewManager {
     pub fn new_single(initial: View) -> Self {
         Self {
+            next_id: initial.id.0 + 1,
+            tree: LayoutTree::new(initial.id),
             views: vec![initial],
             active: 0,
+            scroll_bound: Vec::new(),
+        }
+    }
+
+    /// Split the active view: a copy (same buffer, cursor, scroll) is placed
+    /// above it (`Horizontal`) or left of it (`Vertical`) and becomes active.
+    pub fn split_active(&mut self, axis: SplitAxis) -> ViewId {
+        let view = self.active_view().clone();
+        let target = view.id;
+        let id = self.insert(view, axis);
+        if self.is_scroll_bound(target) {
+            self.scroll_bound.push(id);
+        }
+        tracing::debug!(target: "model.views", from = target.0, new = id.0, ?axis, "view_split");
+        id
+    }
+
+    /// Open a new view on `buffer` (cursor at the origin) placed like
+    /// `split_active` places its copy, and focus it. The caller guarantees
+    /// `buffer` is open (`EditorModel::open_view` checks it).
+    pub fn open(&mut self, buffer: BufferId, axis: SplitAxis) -> ViewId {
+        let from = self.active_view().id;
+        let id = self.insert(View::new(from, buffer, Position::origin(), 0), axis);
+        tracing::debug!(target: "model.views", from = from.0, new = id.0, buffer = buffer.0, ?axis, "view_open");
+        id
+    }
+
+    /// Open a new view on `buffer` below all others, as wide as the tab
+    /// page (Vim's `:botright split`), and focus it.
+    pub fn open_bottom(&mut self, buffer: BufferId) -> ViewId {
+        let from = self.active_view().id;
+        let id = ViewId(self.next_id);
+        self.next_id += 1;
+        self.tree.push_bottom(id);
+        self.views
+            .push(View::new(id, buffer, Position::origin(), 0));
+        self.active = self.views.len() - 1;
+        self.debug_check_invariants();
+        tracing::debug!(target: "model.views", from = from.0, new = id.0, buffer = buffer.0, "view_open_bottom");
+        id
+    }
+
+    /// Give `view` a fresh id, split the active leaf for it and focus it.
+    fn insert(&mut self, mut view: View, axis: SplitAxis) -> ViewId {
+        let target = self.active_view().id;
+        let id = ViewId(self.next_id);
+        self.next_id += 1;
+        view.id = id;
+        self.tree.split(target, id, axis);
+        self.views.push(view);
+        self.active = self.views.len() - 1;
+        self.debug_check_invariants();
+        id
+    }
+
+    /// Close view `id`, collapsing its leaf. When it was focused, focus moves
+    /// to the view that now occupies its place (the next one in layout
+    /// order, else the previous). The last view is never closed.
+    pub fn close(&mut self, id: ViewId) -> Result<(), ViewError> {
+        let idx = self.position(id)?;
+        if self.views.len() == 1 {
+            return Err(ViewError::LastView);
+        }
+        let focused = self.active_view().id;
+        let next_focus = if id == focused {
+            let order = self.tree.leaves();
+            let pos = order.iter().position(|v| *v == id).unwrap_or(0);
+            order
+                .get(pos + 1)
+                .or(order.get(pos.wrapping_sub(1)))
+                .copied()
+        } else {
+            Some(focused)
+        };
+        self.tree.remove(id);
+        self.scroll_bound.retain(|v| *v != id);
+        self.views.remove(idx);
+        self.active = next_focus
+            .and_then(|v| self.views.iter().position(|view| view.id == v))
+            .unwrap_or(0);
+        self.debug_check_invariants();
+        tracing::debug!(target: "model.views", closed = id.0, focus = self.active_view().id.0, "view_closed");
+        Ok(())
+    }
+
+    /// Close the active view (see `close`). Returns false for the last view.
+    pub fn close_active(&mut self) -> bool {
+        self.close(self.active_view().id).is_ok()
+    }
+
+    /// Focus view `id`.
+    pub fn focus_id(&mut self, id: ViewId) -> Result<(), ViewError> {
+        let from = self.active_view().id;
+        self.active = self.position(id)?;
+        tracing::debug!(target: "model.views", from = from.0, to = id.0, "view_focus");
+        Ok(())
+    }
+
+    /// Focus the view reached by moving in `direction`. Returns false (and
+    /// leaves focus alone) at the edge of the layout.
+    pub fn focus(&mut self, direction: FocusDirection) -> bool {
+        let from = self.active_view().id;
+        let Some(to) = self.tree.neighbor(from, direction) else {
+            return false;
+        };
+        let Some(idx) = self.views.iter().position(|v| v.id == to) else {
+            return false;
+        };
+        self.active = idx;
+        tracing::debug!(target: "model.views", from = from.0, to = to.0, ?direction, "view_focus");
+        true
+    }
+
+    /// Add `view` to (or remove it from) the scrollbind group.
+    pub fn set_scroll_bind(&mut self, view: ViewId, bound: bool) {
+        self.scroll_bound.retain(|v| *v != view);
+        if bound {
+            self.scroll_bound.push(view);
         }
     }
+
+    pub fn is_scroll_bound(&self, view: ViewId) -> bool {
+        self.scroll_bound.contains(&view)
+    }
+
+    pub fn tree(&self) -> &LayoutTree {
+        &self.tree
+    }
     pub fn active_view(&self) -> &View {
         debug_assert!(!self.views.is_empty(), "at least one view must exist");
         debug_assert!(self.active < self.views.len(), "active index in range");
@@ -133,14 +314,61 @@ impl ViewManager {
     pub fn views(&self) -> &[View] {
         &self.views
     }
+    pub fn view(&self, id: ViewId) -> Option<&View> {
+        self.views.iter().find(|v| v.id == id)
+    }
+    pub fn view_mut(&mut self, id: ViewId) -> Option<&mut View> {
+        self.views.iter_mut().find(|v| v.id == id)
+    }
     fn active_index(&self) -> usize {
         self.active
     }
+    fn position(&self, id: ViewId) -> Result<usize, ViewError> {
+        self.views
+            .iter()
+            .position(|v| v.id == id)
+            .ok_or(ViewError::NoSuchView(id))
+    }
+
+    /// Views non-empty, focus in range, one layout leaf per view.
+    fn debug_check_invariants(&self) {
+        debug_assert!(!self.views.is_empty(), "at least one view must exist");
+        debug_assert!(self.active < self.views.len(), "active index in range");
+        debug_assert!(
+            {
+                let leaves = self.tree.leaves();
+                leaves.len() == self.views.len()
+                    && self.views.iter().all(|v| leaves.contains(&v.id))
+            },
+            "layout holds exactly one leaf per view"
+        );
+    }
+}
+
+/// One tab page: an independent window layout over the shared buffer list.
+#[derive(Debug)]
+pub struct TabPage {
+    view_mgr: ViewManager,
+}
+
+impl TabPage {
+    pub fn new(view: View) -> Self {
+        Self {
+            view_mgr: ViewManager::new_single(view),
+        }
+    }
+    pub fn view_manager(&self) -> &ViewManager {
+        &self.view_mgr
+    }
+    pub fn active_view(&self) -> &View {
+        self.view_mgr.active_view()
+    }
 }
 
 pub struct EditorModel {
     state: EditorState,
-    view_mgr: ViewManager,
+    tabs: Vec<TabPage>,
+    tab: usize,
 }
 
 impl EditorModel {
@@ -149,16 +377,18 @@ impl EditorModel {
         let v = View::new(ViewId(0), state.active, Position::origin(), 0);
         Self {
             state,
-            view_mgr: ViewManager::new_single(v),
+            tabs: vec![TabPage::new(v)],
+            tab: 0,
         }
     }
     /// Test/helper constructor allowing an already prepared view (cursor/viewport) to be injected.
     pub fn with_view(mut view: View, state: EditorState) -> Self {
         // Ensure view buffer index aligns with state's active buffer for Phase 3 single-buffer assumption.
-        view.buffer_index = state.active; // enforce consistency
+        view.buffer_id = state.active; // enforce consistency
         Self {
             state,
-            view_mgr: ViewManager::new_single(view),
+            tabs: vec![TabPage::new(view)],
+            tab: 0,
         }
     }
     pub fn state(&self) -> &EditorState {
@@ -169,23 +399,274 @@ impl EditorModel {
     }
 
     pub fn active_view(&self) -> &View {
-        debug_assert_eq!(
-            self.view_mgr.views.len(),
-            1,
-            "single-view invariant (Phase 3)"
-        );
-        self.view_mgr.active_view()
+        self.view_manager().active_view()
     }
     pub fn active_view_mut(&mut self) -> &mut View {
-        debug_assert_eq!(
-            self.view_mgr.views.len(),
-            1,
-            "single-view invariant (Phase 3)"
-        );
-        self.view_mgr.active_view_mut()
+        self.view_manager_mut().active_view_mut()
     }
     pub fn views(&self) -> &[View] {
-        self.view_mgr.views()
+        self.view_manager().views()
+    }
+    /// Views of the current tab page.
+    pub fn view_manager(&self) -> &ViewManager {
+        &self.tabs[self.tab].view_mgr
+    }
+    fn view_manager_mut(&mut self) -> &mut ViewManager {
+        &mut self.tabs[self.tab].view_mgr
+    }
+
+    pub fn tabs(&self) -> &[TabPage] {
+        &self.tabs
+    }
+
+    /// `Buffer::check_invariants` for every buffer, and `check_position` for
+    /// each current-tab view's cursor and the active selection.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        for entry in self.state.buffers.iter() {
+            entry.buffer.check_invariants()?;
+        }
+        for view in self.views() {
+            if let Some(entry) = self.state.buffers.get(view.buffer_id) {
+                entry.buffer.check_position(view.cursor)?;
+            }
+        }
+        if let Some(span) = self.state.selection.active {
+            let buffer = self.state.active_buffer();
+            buffer.check_position(span.start)?;
+            buffer.check_position(span.end)?;
+        }
+        Ok(())
+    }
+    /// Move every view's folds with the edits made to its buffer since the
+    /// last call (`Folds::follow`).
+    pub fn follow_edits(&mut self) {
+        for tab in &mut self.tabs {
+            for view in &mut tab.view_mgr.views {
+                if let Some(entry) = self.state.buffers.get(view.buffer_id) {
+                    view.folds.follow(&entry.buffer);
+                }
+            }
+        }
+    }
+    /// Index of the current tab page.
+    pub fn current_tab(&self) -> usize {
+        self.tab
+    }
+
+    /// Open a tab page after the current one with a single view on `buffer`
+    /// and switch to it. Returns the new tab's index.
+    pub fn open_tab(&mut self, buffer: BufferId) -> usize {
+        let view = View::new(ViewId(0), buffer, Position::origin(), 0);
+        self.tab += 1;
+        self.tabs.insert(self.tab, TabPage::new(view));
+        self.sync_active_buffer();
+        tracing::debug!(target: "model.views", tab = self.tab, buffer = buffer.0, "tab_opened");
+        self.tab
+    }
+
+    /// Switch to tab page `index`. Returns false when out of range.
+    pub fn goto_tab(&mut self, index: usize) -> bool {
+        if index >= self.tabs.len() {
+            return false;
+        }
+        self.tab = index;
+        self.sync_active_buffer();
+        true
+    }
+
+    /// Close the current tab page (with all its views) and switch to the one
+    /// taking its place, else the previous one. The last tab is never
+    /// closed; returns false then.
+    pub fn close_tab(&mut self) -> bool {
+        if self.tabs.len() == 1 {
+            return false;
+        }
+        self.tabs.remove(self.tab);
+        self.tab = self.tab.min(self.tabs.len() - 1);
+        self.sync_active_buffer();
+        tracing::debug!(target: "model.views", tab = self.tab, "tab_closed");
+        true
+    }
+
+    /// Regions for every view inside the text `area` (terminal minus status
+    /// and overlay rows).
+    pub fn layout(&self, area: LayoutRegion) -> Layout {
+        self.view_manager().tree().compute(area)
+    }
+
+    /// Split the active view (see `ViewManager::split_active`).
+    pub fn split_active_view(&mut self, axis: SplitAxis) -> ViewId {
+        self.view_manager_mut().split_active(axis)
+    }
+
+    /// Open a view on `buffer` next to the active one and focus it (see
+    /// `ViewManager::open`).
+    pub fn open_view(&mut self, buffer: BufferId, axis: SplitAxis) -> Result<ViewId, ViewError> {
+        if self.state.buffers.get(buffer).is_none() {
+            return Err(ViewError::NoSuchBuffer(buffer));
+        }
+        let id = self.view_manager_mut().open(buffer, axis);
+        self.sync_active_buffer();
+        Ok(id)
+    }
+
+    /// Open a view on `buffer` below all others of the current tab page and
+    /// focus it (see `ViewManager::open_bottom`).
+    pub fn open_bottom_view(&mut self, buffer: BufferId) -> Result<ViewId, ViewError> {
+        if self.state.buffers.get(buffer).is_none() {
+            return Err(ViewError::NoSuchBuffer(buffer));
+        }
+        let id = self.view_manager_mut().open_bottom(buffer);
+        self.sync_active_buffer();
+        Ok(id)
+    }
+
+    /// View `id` of the current tab page.
+    pub fn view_mut(&mut self, id: ViewId) -> Option<&mut View> {
+        self.view_manager_mut().view_mut(id)
+    }
+
+    /// Close view `id` of the current tab page (see `ViewManager::close`),
+    /// keeping the state's active buffer in step with the focused view.
+    pub fn close_view(&mut self, id: ViewId) -> Result<(), ViewError> {
+        self.view_manager_mut().close(id)?;
+        self.sync_active_buffer();
+        Ok(())
+    }
+
+    /// Focus view `id` of the current tab page, making its buffer the
+    /// active one.
+    pub fn focus_view_id(&mut self, id: ViewId) -> Result<(), ViewError> {
+        self.view_manager_mut().focus_id(id)?;
+        self.sync_active_buffer();
+        Ok(())
+    }
+
+    /// Close the active view and focus its successor, making the successor's
+    /// buffer the active one. Returns false for the last view.
+    pub fn close_active_view(&mut self) -> bool {
+        if !self.view_manager_mut().close_active() {
+            return false;
+        }
+        self.sync_active_buffer();
+        true
+    }
+
+    /// Move focus in `direction`, making the newly focused view's buffer the
+    /// active one. Cursor and viewport of both views are kept. Returns false
+    /// when there is no view in that direction.
+    pub fn focus_view(&mut self, direction: FocusDirection) -> bool {
+        if !self.view_manager_mut().focus(direction) {
+            return false;
+        }
+        self.sync_active_buffer();
+        true
+    }
+
+    /// Set `'scrollbind'` on the active view (see `scroll_bound_siblings`).
+    pub fn set_scroll_bind(&mut self, bound: bool) {
+        let id = self.active_view().id;
+        self.set_view_scroll_bind(id, bound);
+    }
+
+    /// Set `'scrollbind'` on view `id` of the current tab page.
+    pub fn set_view_scroll_bind(&mut self, id: ViewId, bound: bool) {
+        self.view_manager_mut().set_scroll_bind(id, bound);
+    }
+
+    /// After the active view scrolled by `delta` lines, scroll the other
+    /// views of its scrollbind group by the same amount, clamped to their
+    /// buffers, and pull their cursors back inside their regions of `area`.
+    /// A view showing the buffer the active one is diffed against (see
+    /// `core_state::diff`) is instead lined up row for row with it, even
+    /// when `delta` is 0 (an edit may have moved the hunks). Returns the
+    /// views that moved with their previous first lines; empty unless the
+    /// active view is bound.
+    pub fn scroll_bound_siblings(
+        &mut self,
+        delta: isize,
+        area: LayoutRegion,
+    ) -> Vec<(ViewId, usize)> {
+        let active = self.active_view();
+        let (id, buffer) = (active.id, active.buffer_id);
+        let diff = &self.state.diff;
+        let top = diff
+            .other(buffer)
+            .map(|other| (other, diff.top_row(buffer, active.viewport_first_line)));
+        if (delta == 0 && top.is_none()) || !self.view_manager().is_scroll_bound(id) {
+            return Vec::new();
+        }
+        let layout = self.layout(area);
+        let state = &self.state;
+        let view_mgr = &mut self.tabs[self.tab].view_mgr;
+        let mut moved = Vec::new();
+        for view in view_mgr.views.iter_mut() {
+            if view.id == id || !view_mgr.scroll_bound.contains(&view.id) {
+                continue;
+            }
+            let Some(entry) = state.buffers.get(view.buffer_id) else {
+                continue;
+            };
+            let height = layout.region_of(view.id).map_or(1, |r| r.height.max(1)) as usize;
+            let delta = match top {
+                Some((other, row)) if other == view.buffer_id => {
+                    let first = state
+                        .diff
+                        .line_at_row(other, row, entry.buffer.line_count());
+                    first as isize - view.viewport_first_line as isize
+                }
+                _ => delta,
+            };
+            if let Some(old_first) = scroll_lines(view, &entry.buffer, delta, height, 0) {
+                moved.push((view.id, old_first));
+            }
+        }
+        if !moved.is_empty() {
+            tracing::debug!(target: "model.views", from = id.0, delta, moved = moved.len(), "scroll_bind");
+        }
+        moved
+    }
+
+    /// Scroll view `id` of the current tab page by `delta` lines (the mouse
+    /// wheel), clamped to its buffer, and pull its cursor back inside its
+    /// region of `area`, keeping the vertical margin so `auto_scroll` leaves
+    /// the new viewport alone. Focus does not change. Returns the previous
+    /// first line, or `None` when the view did not move.
+    pub fn scroll_view(&mut self, id: ViewId, delta: isize, area: LayoutRegion) -> Option<usize> {
+        let layout = self.layout(area);
+        let height = layout.region_of(id)?.height.max(1) as usize;
+        let active = self.active_view().id;
+        let state = &self.state;
+        let view = self.tabs[self.tab]
+            .view_mgr
+            .views
+            .iter_mut()
+            .find(|v| v.id == id)?;
+        let buf = &state.buffers.get(view.buffer_id)?.buffer;
+        let old_first = scroll_lines(view, buf, delta, height, state.config_vertical_margin)?;
+        if id != active || state.mode != core_state::Mode::Insert {
+            core_text::motion::normalize_normal_mode_position(buf, &mut view.cursor);
+        }
+        tracing::debug!(target: "model.views", view = id.0, delta, first = view.viewport_first_line, "view_scroll");
+        Some(old_first)
+    }
+
+    /// Make the active view's buffer the state's active buffer and clamp the
+    /// view's cursor, which may be stale if the buffer was edited through
+    /// another view. Window-local options are reloaded from the view.
+    fn sync_active_buffer(&mut self) {
+        let bound = self.view_manager().is_scroll_bound(self.active_view().id);
+        let _ = self
+            .state
+            .options
+            .set("scrollbind", core_config::options::OptionValue::Bool(bound));
+        let (state, view) = self.split_state_and_active_view();
+        state.switch_buffer(view.buffer_id);
+        debug_assert_eq!(state.active, view.buffer_id, "view buffer must be open");
+        let buf = state.active_buffer();
+        view.cursor.line = view.cursor.line.min(buf.line_count().saturating_sub(1));
+        view.cursor.byte = view.cursor.byte.min(buf.line_byte_len(view.cursor.line));
+        view.viewport_first_line = view.viewport_first_line.min(view.cursor.line);
     }
 
     /// Safely obtain mutable references to the underlying `EditorState` and the
@@ -203,23 +684,50 @@ impl EditorModel {
         // views slice for the active view. No aliasing occurs because no other
         // &mut to state or the active view is alive when this function returns.
         // The returned references have identical lifetime tied to &mut self.
+        let view_mgr = &mut self.tabs[self.tab].view_mgr;
+        debug_assert!(!view_mgr.views.is_empty(), "at least one view must exist");
         debug_assert!(
-            !self.view_mgr.views.is_empty(),
-            "at least one view must exist"
-        );
-        debug_assert!(
-            self.view_mgr.active < self.view_mgr.views.len(),
+            view_mgr.active < view_mgr.views.len(),
             "active index in range"
         );
         let state_ptr: *mut EditorState = &mut self.state;
         // Obtain raw pointer to start of views Vec then offset by active index to avoid borrow checker conflict.
-        let base_ptr = self.view_mgr.views.as_mut_ptr();
-        let idx = self.view_mgr.active_index();
+        let base_ptr = view_mgr.views.as_mut_ptr();
+        let idx = view_mgr.active_index();
         let view_ptr = unsafe { base_ptr.add(idx) };
         unsafe { (&mut *state_ptr, &mut *view_ptr) }
     }
 }
 
+/// Move `view`'s first line by `delta` (clamped to `buf`) and its cursor
+/// line into the `height` rows below, `margin` lines from edges the buffer
+/// continues past. Returns the previous first line if the view moved.
+fn scroll_lines(
+    view: &mut View,
+    buf: &core_text::Buffer,
+    delta: isize,
+    height: usize,
+    margin: usize,
+) -> Option<usize> {
+    let last = buf.line_count().saturating_sub(1);
+    let old_first = view.viewport_first_line;
+    let first = old_first.saturating_add_signed(delta).min(last);
+    if first == old_first {
+        return None;
+    }
+    view.viewport_first_line = first;
+    let m = margin.min(height.saturating_sub(1) / 2);
+    let top = if first > 0 { first + m } else { first };
+    let bottom = first + height - 1;
+    let bottom = if bottom < last { bottom - m } else { last };
+    let line = view.cursor.line.clamp(top.min(bottom), bottom);
+    if line != view.cursor.line {
+        view.cursor.line = line;
+        view.cursor.byte = view.cursor.byte.min(buf.line_byte_len(line));
+    }
+    Some(old_first)
+}
+
 /// Compute the desired new first visible line to keep the cursor within the
 /// vertical viewport subject to a top/bottom margin.
 ///
@@ -258,16 +766,72 @@ pub fn compute_scroll_intent(
     None
 }
 
+/// `compute_scroll_intent` counting screen rows instead of lines, for
+/// `'wrap'`: `rows(line)` is the number of rows buffer line `line` wraps into
+/// and `cursor_row` the row of the cursor within its line. The viewport still
+/// starts on a line boundary. Scrolling up keeps `margin` lines above the
+/// cursor line; scrolling down moves the first line just far enough that the
+/// cursor row and the `margin` lines below its line fit. With one row per
+/// line this agrees with `compute_scroll_intent`.
+pub fn compute_wrapped_scroll_intent(
+    first: usize,
+    cursor_line: usize,
+    cursor_row: usize,
+    text_height: usize,
+    margin: usize,
+    rows: impl Fn(usize) -> usize,
+) -> Option<usize> {
+    if text_height == 0 {
+        return None;
+    }
+    let m = margin.min(text_height / 2);
+    if cursor_line < first + m {
+        let new_first = cursor_line.saturating_sub(m);
+        return (new_first != first).then_some(new_first);
+    }
+    let below: usize = (cursor_line + 1..=cursor_line + m).map(&rows).sum();
+    let needed = cursor_row + 1 + below;
+    let mut used = needed;
+    for line in first..cursor_line {
+        used += rows(line);
+        if used > text_height {
+            break;
+        }
+    }
+    if used <= text_height {
+        return None;
+    }
+    let mut new_first = cursor_line;
+    let mut used = needed;
+    while new_first > first {
+        let prev = rows(new_first - 1);
+        if used + prev > text_height {
+            break;
+        }
+        used += prev;
+        new_first -= 1;
+    }
+    (new_first != first).then_some(new_first)
+}
+
 impl View {
     /// Auto-scroll this view to keep the cursor within the vertical viewport.
     /// Returns true if the first visible line changed. Updates state's last_text_height.
-    pub fn auto_scroll(&mut self, state: &mut EditorState, text_height: usize) -> bool {
+    /// With `'wrap'` set, lines take as many rows as they wrap into at
+    /// `text_width` columns (the view width minus its gutter); a closed fold
+    /// takes one row.
+    pub fn auto_scroll(
+        &mut self,
+        state: &mut EditorState,
+        text_height: usize,
+        text_width: u16,
+    ) -> bool {
         if text_height == 0 {
             return false;
         }
         // Invariants (debug-only): single-buffer Phase 3 assumption and cursor validity
         debug_assert_eq!(
-            self.buffer_index, state.active,
+            self.buffer_id, state.active,
             "active view must point at active buffer"
         );
         let buf = state.active_buffer();
@@ -283,12 +847,54 @@ impl View {
             );
         }
         state.last_text_height = text_height; // record for page motions
-        let maybe_new = compute_scroll_intent(
-            self.viewport_first_line,
-            self.cursor.line,
-            text_height,
-            state.config_vertical_margin,
-        );
+        let buf = state.active_buffer();
+        let wrap = (state.options.get_bool("wrap") && text_width > 0)
+            .then(|| WrapWidth::new(text_width, state.options.get_string("showbreak")));
+        let diff = &state.diff;
+        let maybe_new = if wrap.is_some() || !self.folds.is_empty() || diff.includes(self.buffer_id)
+        {
+            let folds = &self.folds;
+            let text = |line: usize| buf.line(line).unwrap_or_default();
+            // A closed fold is one row, drawn at its first line. Diff filler
+            // rows go above their line.
+            let fillers = |line: usize| diff.fillers_above(self.buffer_id, line);
+            let rows = |line: usize| match folds.closed_at(line) {
+                Some(fold) if *fold.start() == line => 1 + fillers(line),
+                Some(_) => 0,
+                None => {
+                    fillers(line)
+                        + wrap.as_ref().map_or(1, |wrap| {
+                            wrap.row_count(text(line).trim_end_matches(['\n', '\r']))
+                        })
+                }
+            };
+            let cursor_line = folds.visible_start(self.cursor.line);
+            let cursor_row = fillers(cursor_line)
+                + match &wrap {
+                    Some(wrap) if cursor_line == self.cursor.line => {
+                        let cursor_text = text(self.cursor.line);
+                        wrap.locate(cursor_text.trim_end_matches(['\n', '\r']), self.cursor.byte)
+                            .0
+                    }
+                    _ => 0,
+                };
+            compute_wrapped_scroll_intent(
+                folds.visible_start(self.viewport_first_line),
+                cursor_line,
+                cursor_row,
+                text_height,
+                state.config_vertical_margin,
+                rows,
+            )
+            .map(|first| folds.visible_start(first))
+        } else {
+            compute_scroll_intent(
+                self.viewport_first_line,
+                self.cursor.line,
+                text_height,
+                state.config_vertical_margin,
+            )
+        };
         if let Some(new_first) = maybe_new {
             self.viewport_first_line = new_first;
             true
@@ -310,7 +916,7 @@ mod tests {
         let model = EditorModel::new(st);
         let v = model.active_view();
         assert_eq!(v.id.0, 0);
-        assert_eq!(v.buffer_index, 0);
+        assert_eq!(v.buffer_id, model.state().active);
         assert_eq!(v.viewport_first_line, 0);
     }
 
@@ -327,7 +933,123 @@ mod tests {
         assert_eq!(model.active_view().cursor.byte, 1);
         // split borrow still yields same view pointer semantics
         let (state, view) = model.split_state_and_active_view();
-        assert_eq!(state.active, view.buffer_index);
+        assert_eq!(state.active, view.buffer_id);
+    }
+
+    #[test]
+    fn tabs_keep_independent_layouts() {
+        let st = EditorState::new(Buffer::from_str("t", "a\nb\n").unwrap());
+        let mut model = EditorModel::new(st);
+        let first = model.state().active;
+        model.split_active_view(SplitAxis::Vertical);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "x\n").unwrap(), None);
+        assert_eq!(model.open_tab(other), 1);
+        assert_eq!(model.views().len(), 1);
+        assert_eq!(model.state().active, other);
+
+        assert!(model.goto_tab(0));
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.state().active, first);
+        assert!(!model.goto_tab(2));
+
+        assert!(model.close_tab());
+        assert_eq!(model.tabs().len(), 1);
+        assert_eq!(model.state().active, other);
+        assert!(!model.close_tab(), "last tab stays");
+    }
+
+    #[test]
+    fn view_lifecycle_enforces_invariants() {
+        let st = EditorState::new(Buffer::from_str("t", "a\nb\n").unwrap());
+        let mut model = EditorModel::new(st);
+        let first = model.active_view().id;
+        let main = model.state().active;
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "x\n").unwrap(), None);
+        assert_eq!(
+            model.open_view(BufferId(99), SplitAxis::Vertical),
+            Err(ViewError::NoSuchBuffer(BufferId(99)))
+        );
+        let opened = model.open_view(other, SplitAxis::Vertical).unwrap();
+        assert_eq!(model.active_view().id, opened);
+        assert_eq!(model.state().active, other);
+        assert_eq!(model.view_manager().tree().leaves(), [opened, first]);
+
+        assert_eq!(model.focus_view_id(first), Ok(()));
+        assert_eq!(model.state().active, main);
+        assert_eq!(
+            model.focus_view_id(ViewId(42)),
+            Err(ViewError::NoSuchView(ViewId(42)))
+        );
+
+        // Closing an unfocused view keeps focus where it is.
+        assert_eq!(model.close_view(opened), Ok(()));
+        assert_eq!(model.active_view().id, first);
+        assert_eq!(model.view_manager().tree().leaves(), [first]);
+        assert_eq!(model.close_view(first), Err(ViewError::LastView));
+        assert_eq!(
+            ViewError::LastView.to_string(),
+            "E444: Cannot close last window"
+        );
+    }
+
+    #[test]
+    fn scroll_bind_moves_bound_siblings_only() {
+        let text: String = (0..30).map(|i| format!("{i}\n")).collect();
+        let st = EditorState::new(Buffer::from_str("t", &text).unwrap());
+        let mut model = EditorModel::new(st);
+        let right = model.active_view().id;
+        model.set_scroll_bind(true);
+        let left = model.split_active_view(SplitAxis::Vertical);
+        let free = model.split_active_view(SplitAxis::Horizontal);
+        model.set_scroll_bind(false);
+        assert!(model.focus_view(FocusDirection::Down));
+        assert_eq!(model.active_view().id, left);
+        assert!(model.state().options.get_bool("scrollbind"));
+
+        let area = LayoutRegion::new(0, 0, 40, 21);
+        model.active_view_mut().viewport_first_line = 12;
+        let moved = model.scroll_bound_siblings(12, area);
+        assert_eq!(moved, [(right, 0)]);
+        let view = |id| model.views().iter().find(|v| v.id == id).unwrap().clone();
+        assert_eq!(view(right).viewport_first_line, 12);
+        assert_eq!(view(right).cursor.line, 12, "cursor follows into view");
+        assert_eq!(view(free).viewport_first_line, 0);
+
+        // Scrolling an unbound window leaves the group alone.
+        assert!(model.focus_view(FocusDirection::Up));
+        assert!(!model.state().options.get_bool("scrollbind"));
+        assert!(model.scroll_bound_siblings(3, area).is_empty());
+    }
+
+    #[test]
+    fn split_and_close_keep_active_buffer_in_sync() {
+        let st = EditorState::new(Buffer::from_str("t", "a\nb\nc\n").unwrap());
+        let mut model = EditorModel::new(st);
+        model.active_view_mut().cursor.line = 2;
+        let new = model.split_active_view(SplitAxis::Horizontal);
+        assert_eq!(model.views().len(), 2);
+        assert_eq!(model.active_view().id, new);
+        assert_eq!(model.active_view().cursor.line, 2, "split clones the view");
+        assert_eq!(model.view_manager().tree().leaves(), [new, ViewId(0)]);
+
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "x\n").unwrap(), None);
+        model.state_mut().switch_buffer(other);
+        model.active_view_mut().buffer_id = other;
+        model.active_view_mut().cursor.line = 0;
+
+        assert!(model.close_active_view());
+        assert_eq!(model.active_view().id, ViewId(0));
+        assert_eq!(model.state().active, model.active_view().buffer_id);
+        assert!(!model.close_active_view(), "last view stays");
     }
 
     fn mk(text: &str) -> (EditorState, View) {
@@ -341,17 +1063,17 @@ mod tests {
         let (mut st, mut v) = mk("0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n");
         let h = 5usize;
         // line 0 already visible, no scroll
-        assert!(!v.auto_scroll(&mut st, h));
+        assert!(!v.auto_scroll(&mut st, h, 80));
         v.cursor.line = 4; // still inside 0..5
-        assert!(!v.auto_scroll(&mut st, h));
+        assert!(!v.auto_scroll(&mut st, h, 80));
         v.cursor.line = 5; // triggers scroll to first=1
-        assert!(v.auto_scroll(&mut st, h));
+        assert!(v.auto_scroll(&mut st, h, 80));
         assert_eq!(v.viewport_first_line, 1);
         v.cursor.line = 9; // bottom -> new_first = 9 +1 -5 =5
-        assert!(v.auto_scroll(&mut st, h));
+        assert!(v.auto_scroll(&mut st, h, 80));
         assert_eq!(v.viewport_first_line, 5);
         v.cursor.line = 3; // above first -> clamp to 3
-        assert!(v.auto_scroll(&mut st, h));
+        assert!(v.auto_scroll(&mut st, h, 80));
         assert_eq!(v.viewport_first_line, 3);
     }
 
@@ -360,7 +1082,7 @@ mod tests {
         let (mut st, mut v) = mk("0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n");
         st.config_vertical_margin = 0;
         v.cursor.line = 5;
-        v.auto_scroll(&mut st, 5);
+        v.auto_scroll(&mut st, 5, 80);
         assert_eq!(v.viewport_first_line, 1);
     }
 
@@ -370,10 +1092,10 @@ mod tests {
         st.config_vertical_margin = 2;
         let h = 6usize;
         v.cursor.line = 4; // triggers early scroll because bottom margin violated
-        v.auto_scroll(&mut st, h);
+        v.auto_scroll(&mut st, h, 80);
         assert_eq!(v.viewport_first_line, 1);
         v.cursor.line = 5; // subsequent scroll maintains margin -> new_first = 5 +2 +1 -6 =2
-        v.auto_scroll(&mut st, h);
+        v.auto_scroll(&mut st, h, 80);
         assert_eq!(v.viewport_first_line, 2);
     }
 
@@ -382,7 +1104,7 @@ mod tests {
         let (mut st, mut v) = mk("0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n");
         st.config_vertical_margin = 2;
         v.cursor.line = 9;
-        v.auto_scroll(&mut st, 5); // m = min(2, 2) =2 => 9+2+1-5 =7
+        v.auto_scroll(&mut st, 5, 80); // m = min(2, 2) =2 => 9+2+1-5 =7
         assert_eq!(v.viewport_first_line, 7);
     }
 
@@ -391,8 +1113,62 @@ mod tests {
         let (mut st, mut v) = mk("0\n1\n2\n3\n4\n");
         st.config_vertical_margin = 10; // will clamp to h/2
         v.cursor.line = 2;
-        v.auto_scroll(&mut st, 3); // h/2=1 -> 2+1+1-3=1
+        v.auto_scroll(&mut st, 3, 80); // h/2=1 -> 2+1+1-3=1
+        assert_eq!(v.viewport_first_line, 1);
+    }
+
+    #[test]
+    fn auto_scroll_counts_wrapped_rows() {
+        // Lines 1 and 2 wrap into two rows each at 4 columns.
+        let (mut st, mut v) = mk("0\n11111\n22222\n3\n4\n");
+        v.cursor.line = 2;
+        assert!(!v.auto_scroll(&mut st, 5, 4), "rows 0..5 hold lines 0..=2");
+        v.cursor.line = 3;
+        assert!(v.auto_scroll(&mut st, 5, 4));
         assert_eq!(v.viewport_first_line, 1);
+        // The cursor on the continuation row of line 2 needs both its rows.
+        v.cursor = Position::new(2, 4);
+        v.viewport_first_line = 0;
+        assert!(!v.auto_scroll(&mut st, 5, 4));
+        assert!(v.auto_scroll(&mut st, 4, 4));
+        assert_eq!(v.viewport_first_line, 1);
+        // 'nowrap' counts lines again.
+        st.options.apply_set("nowrap").unwrap();
+        v.cursor = Position::new(3, 0);
+        v.viewport_first_line = 0;
+        assert!(!v.auto_scroll(&mut st, 4, 4));
+    }
+
+    #[test]
+    fn auto_scroll_counts_closed_fold_as_one_row() {
+        let (mut st, mut v) = mk("0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+        v.folds.create(1, 6);
+        // Rows: 0, the fold summary, 7, 8.
+        v.cursor.line = 8;
+        assert!(!v.auto_scroll(&mut st, 4, 80));
+        v.cursor.line = 9;
+        assert!(v.auto_scroll(&mut st, 4, 80));
+        assert_eq!(v.viewport_first_line, 1, "scrolls to the fold start");
+        // Moving back above the fold scrolls up by line again.
+        v.cursor.line = 0;
+        assert!(v.auto_scroll(&mut st, 4, 80));
+        assert_eq!(v.viewport_first_line, 0);
+    }
+
+    #[test]
+    fn compute_wrapped_scroll_intent_keeps_margin_lines_visible() {
+        let rows = |line: usize| if line == 5 { 3 } else { 1 };
+        assert_eq!(compute_wrapped_scroll_intent(0, 3, 0, 6, 1, rows), None);
+        // Line 4 and its three-row margin line 5 leave room for lines 2 and 3.
+        assert_eq!(compute_wrapped_scroll_intent(0, 4, 0, 6, 1, rows), Some(2));
+        assert_eq!(compute_wrapped_scroll_intent(5, 5, 0, 6, 1, rows), Some(4));
+        let single = |_| 1;
+        for cursor in 0..12 {
+            assert_eq!(
+                compute_wrapped_scroll_intent(3, cursor, 0, 5, 2, single),
+                compute_scroll_intent(3, cursor, 5, 2)
+            );
+        }
     }
 
     #[test]
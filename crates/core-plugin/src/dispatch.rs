@@ -0,0 +1,270 @@
+//! Dispatch boundary between the editor and a `PluginHost`.
+//!
+//! Every plugin command and timer call runs under `catch_unwind`. A panic
+//! (in a host implementation or a native plugin) comes back as
+//! `PluginCallError::Panicked` and disables the plugin that owns the
+//! command, since its state may be half-updated; later calls into it fail
+//! fast with `PluginCallError::Disabled`. Ordinary errors
+//! (a wasm trap, fuel exhaustion) leave the plugin enabled.
+//!
+//! The panic hook still runs before the unwind is caught, so the panic is
+//! logged like any other.
+
+use crate::{PluginCommand, PluginEffects, PluginHost, PluginKeymap};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use tracing::error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginCallError {
+    /// No loaded plugin registered the command.
+    UnknownCommand(String),
+    /// The owning plugin panicked earlier and was disabled.
+    Disabled { plugin: String },
+    /// The plugin returned an error.
+    Failed { plugin: String, message: String },
+    /// The plugin panicked during this call.
+    Panicked { plugin: String, message: String },
+}
+
+impl fmt::Display for PluginCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginCallError::UnknownCommand(name) => write!(f, "no plugin command {name}"),
+            PluginCallError::Disabled { plugin } => {
+                write!(f, "Plugin {plugin} is disabled after a panic")
+            }
+            PluginCallError::Failed { plugin, message } => {
+                write!(f, "Plugin {plugin} failed: {message}")
+            }
+            PluginCallError::Panicked { plugin, message } => {
+                write!(f, "Plugin {plugin} panicked and was disabled: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PluginCallError {}
+
+pub struct PluginDispatcher {
+    host: Box<dyn PluginHost>,
+    /// Snapshot taken at construction: hosts register commands while
+    /// loading, never later.
+    commands: Vec<PluginCommand>,
+    disabled: BTreeSet<String>,
+}
+
+impl PluginDispatcher {
+    /// Wrap a host whose plugins are already loaded.
+    pub fn new(host: Box<dyn PluginHost>) -> Self {
+        let commands = host.commands();
+        Self {
+            host,
+            commands,
+            disabled: BTreeSet::new(),
+        }
+    }
+
+    pub fn host_mut(&mut self) -> &mut dyn PluginHost {
+        self.host.as_mut()
+    }
+
+    pub fn commands(&self) -> &[PluginCommand] {
+        &self.commands
+    }
+
+    /// Key bindings whose command belongs to a loaded plugin.
+    pub fn keymaps(&self) -> Vec<PluginKeymap> {
+        self.host
+            .keymaps()
+            .into_iter()
+            .filter(|k| {
+                self.commands
+                    .iter()
+                    .any(|c| c.plugin == k.plugin && c.name == k.command)
+            })
+            .collect()
+    }
+
+    pub fn is_disabled(&self, plugin: &str) -> bool {
+        self.disabled.contains(plugin)
+    }
+
+    /// Run command `name` with `buffer_text`, containing panics.
+    pub fn call(
+        &mut self,
+        name: &str,
+        buffer_text: &str,
+    ) -> Result<PluginEffects, PluginCallError> {
+        let plugin = self
+            .commands
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.plugin.clone())
+            .ok_or_else(|| PluginCallError::UnknownCommand(name.to_string()))?;
+        self.guarded(plugin, name, |host| host.run_command(name, buffer_text))
+    }
+
+    /// Run the timer `plugin` started with `token`, containing panics.
+    pub fn call_timer(
+        &mut self,
+        plugin: &str,
+        token: i32,
+        buffer_text: &str,
+    ) -> Result<PluginEffects, PluginCallError> {
+        self.guarded(plugin.to_string(), "timer", |host| {
+            host.run_timer(plugin, token, buffer_text)
+        })
+    }
+
+    /// Make the `call` into `plugin` unless it is disabled, and disable it
+    /// when the call panics.
+    fn guarded(
+        &mut self,
+        plugin: String,
+        call: &str,
+        f: impl FnOnce(&mut dyn PluginHost) -> anyhow::Result<PluginEffects>,
+    ) -> Result<PluginEffects, PluginCallError> {
+        if self.disabled.contains(&plugin) {
+            return Err(PluginCallError::Disabled { plugin });
+        }
+        let host = self.host.as_mut();
+        match catch_unwind(AssertUnwindSafe(|| f(host))) {
+            Ok(Ok(effects)) => Ok(effects),
+            Ok(Err(e)) => Err(PluginCallError::Failed {
+                plugin,
+                message: format!("{e:#}"),
+            }),
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".into());
+                error!(target: "plugin", plugin = %plugin, call, %message, "plugin_panicked");
+                self.disabled.insert(plugin.clone());
+                Err(PluginCallError::Panicked { plugin, message })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_events::AsyncEventSource;
+
+    /// Native host whose `Boom` command panics and `Echo` command echoes.
+    struct TestHost;
+
+    impl PluginHost for TestHost {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        fn load_all(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn event_sources(&mut self) -> Vec<Box<dyn AsyncEventSource>> {
+            Vec::new()
+        }
+        fn commands(&self) -> Vec<PluginCommand> {
+            [("boom", "Boom"), ("echo", "Echo"), ("echo", "Fail")]
+                .map(|(plugin, name)| PluginCommand {
+                    plugin: plugin.into(),
+                    name: name.into(),
+                })
+                .into()
+        }
+        fn keymaps(&self) -> Vec<PluginKeymap> {
+            [("echo", "Echo"), ("echo", "Missing")]
+                .map(|(plugin, command)| PluginKeymap {
+                    plugin: plugin.into(),
+                    lhs: "<leader>e".into(),
+                    command: command.into(),
+                })
+                .into()
+        }
+        fn run_command(&mut self, name: &str, text: &str) -> anyhow::Result<PluginEffects> {
+            match name {
+                "Boom" => panic!("kaboom"),
+                "Fail" => anyhow::bail!("trap"),
+                _ => Ok(PluginEffects {
+                    status: Some(text.to_string()),
+                    ..PluginEffects::default()
+                }),
+            }
+        }
+        fn run_timer(
+            &mut self,
+            plugin: &str,
+            token: i32,
+            _text: &str,
+        ) -> anyhow::Result<PluginEffects> {
+            match plugin {
+                "boom" => panic!("tick {token}"),
+                _ => Ok(PluginEffects::default()),
+            }
+        }
+    }
+
+    #[test]
+    fn panics_disable_only_the_offending_plugin() {
+        let mut dispatcher = PluginDispatcher::new(Box::new(TestHost));
+        assert_eq!(
+            dispatcher.call("Boom", ""),
+            Err(PluginCallError::Panicked {
+                plugin: "boom".into(),
+                message: "kaboom".into()
+            })
+        );
+        assert!(dispatcher.is_disabled("boom"));
+        assert_eq!(
+            dispatcher.call("Boom", ""),
+            Err(PluginCallError::Disabled {
+                plugin: "boom".into()
+            })
+        );
+        let effects = dispatcher.call("Echo", "hi").unwrap();
+        assert_eq!(effects.status.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn timer_panics_disable_the_plugin_too() {
+        let mut dispatcher = PluginDispatcher::new(Box::new(TestHost));
+        assert!(dispatcher.call_timer("echo", 1, "").is_ok());
+        assert_eq!(
+            dispatcher.call_timer("boom", 7, ""),
+            Err(PluginCallError::Panicked {
+                plugin: "boom".into(),
+                message: "tick 7".into()
+            })
+        );
+        assert!(matches!(
+            dispatcher.call("Boom", ""),
+            Err(PluginCallError::Disabled { .. })
+        ));
+    }
+
+    #[test]
+    fn errors_keep_the_plugin_enabled() {
+        let mut dispatcher = PluginDispatcher::new(Box::new(TestHost));
+        assert!(matches!(
+            dispatcher.call("Fail", ""),
+            Err(PluginCallError::Failed { .. })
+        ));
+        assert!(!dispatcher.is_disabled("echo"));
+        assert_eq!(
+            dispatcher.call("Nope", ""),
+            Err(PluginCallError::UnknownCommand("Nope".into()))
+        );
+    }
+
+    #[test]
+    fn keymaps_must_name_a_plugin_command() {
+        let dispatcher = PluginDispatcher::new(Box::new(TestHost));
+        let keymaps = dispatcher.keymaps();
+        assert_eq!(keymaps.len(), 1);
+        assert_eq!(keymaps[0].command, "Echo");
+    }
+}
@@ -7,8 +7,18 @@
 //! protocol plumbing. It only establishes the trait surface and a `NoopPluginHost`
 //! implementation used by the main runtime until real functionality lands.
 //!
+//! Contributions: besides event sources, a host lists the ex `commands` and
+//! Normal-mode `keymaps` its plugins registered and runs those commands
+//! through `run_command`; `status_segments` hands out status line segment
+//! providers the runtime schedules like any other. Plugins may also start
+//! timers (`PluginEffects::timers`); the runtime fires them back through
+//! `run_timer`. Callers go through `PluginDispatcher` (module
+//! `dispatch`), the boundary that turns a plugin panic into an error and
+//! disables the offending plugin instead of unwinding into the editor loop.
+//!
 //! Design Notes:
-//! - Kept intentionally tiny: name + load_all + event_sources.
+//! - Kept intentionally tiny: name + load_all + event_sources, with the
+//!   contribution methods defaulting to "none".
 //! - `load_all` returns Result<()> to reserve space for IO / parse failures.
 //! - `event_sources` returns owned boxed `AsyncEventSource` objects allowing the
 //!   caller (registry) to spawn them uniformly alongside built-ins.
@@ -16,14 +26,63 @@
 //!   variant (or internal tokio tasks) once real plugin discovery is implemented.
 //! - This crate depends only on `core-events` to avoid cycles.
 //!
-//! Extension Path (Deferred): actual discovery (filesystem scan, config‑declared
-//! manifests, dynamic linking, WASI modules) and sandboxing will populate an
-//! internal collection of plugin descriptors. Each descriptor may expose zero or
+//! Implementations: `WasmPluginHost` (module `wasm`) discovers `.wasm` modules
+//! in a plugins directory and runs them sandboxed in wasmtime behind a small
+//! host API (read buffer text, set ephemeral status, register ex commands).
+//!
+//! Extension Path (Deferred): config‑declared manifests and dynamic linking
+//! would populate further plugin descriptors. Each descriptor may expose zero or
 //! more async event sources bridged into the global `EventSourceRegistry` via
 //! `event_sources()`. Command, status segment, and style span contributions will
 //! gain analogous composition seams once those feature phases begin.
 
-use core_events::AsyncEventSource;
+use core_events::{AsyncEventSource, StatusSegmentProvider};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod dispatch;
+pub mod wasm;
+pub use dispatch::{PluginCallError, PluginDispatcher};
+pub use wasm::WasmPluginHost;
+
+/// One ex command a plugin registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginCommand {
+    pub plugin: String,
+    pub name: String,
+}
+
+/// Normal-mode key sequence (Vim key notation) a plugin binds to one of its
+/// own commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginKeymap {
+    pub plugin: String,
+    pub lhs: String,
+    pub command: String,
+}
+
+/// What a plugin call asked the editor to do.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginEffects {
+    /// Last status message the plugin set.
+    pub status: Option<String>,
+    /// Timers the plugin started or stopped, in call order.
+    pub timers: Vec<PluginTimer>,
+}
+
+/// A plugin timer change. The plugin names its timers with tokens of its
+/// own choosing; starting one with a token in use replaces that timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginTimer {
+    Start {
+        token: i32,
+        delay: Duration,
+        repeat: bool,
+    },
+    Stop {
+        token: i32,
+    },
+}
 
 /// Trait representing a collection-oriented plugin host. Implementors are
 /// responsible for discovering zero or more plugins (from disk, config, or
@@ -39,6 +98,32 @@ pub trait PluginHost: Send + Sync {
     /// transferred to the caller. Subsequent calls after extraction SHOULD return
     /// an empty Vec.
     fn event_sources(&mut self) -> Vec<Box<dyn AsyncEventSource>>;
+    /// Ex commands contributed by loaded plugins.
+    fn commands(&self) -> Vec<PluginCommand> {
+        Vec::new()
+    }
+    /// Key bindings contributed by loaded plugins; each names a command
+    /// from `commands`.
+    fn keymaps(&self) -> Vec<PluginKeymap> {
+        Vec::new()
+    }
+    /// Run plugin command `name` against a snapshot of the active buffer.
+    fn run_command(&mut self, name: &str, _buffer_text: &str) -> anyhow::Result<PluginEffects> {
+        anyhow::bail!("no plugin command {name}")
+    }
+    /// Run the timer `plugin` started with `token`.
+    fn run_timer(
+        &mut self,
+        plugin: &str,
+        token: i32,
+        _buffer_text: &str,
+    ) -> anyhow::Result<PluginEffects> {
+        anyhow::bail!("plugin {plugin} has no timer {token}")
+    }
+    /// Status line segment providers contributed by loaded plugins.
+    fn status_segments(&mut self) -> Vec<Arc<dyn StatusSegmentProvider>> {
+        Vec::new()
+    }
 }
 
 impl<T: PluginHost + ?Sized> PluginHost for &mut T {
@@ -51,6 +136,26 @@ impl<T: PluginHost + ?Sized> PluginHost for &mut T {
     fn event_sources(&mut self) -> Vec<Box<dyn AsyncEventSource>> {
         (**self).event_sources()
     }
+    fn commands(&self) -> Vec<PluginCommand> {
+        (**self).commands()
+    }
+    fn keymaps(&self) -> Vec<PluginKeymap> {
+        (**self).keymaps()
+    }
+    fn run_command(&mut self, name: &str, buffer_text: &str) -> anyhow::Result<PluginEffects> {
+        (**self).run_command(name, buffer_text)
+    }
+    fn run_timer(
+        &mut self,
+        plugin: &str,
+        token: i32,
+        buffer_text: &str,
+    ) -> anyhow::Result<PluginEffects> {
+        (**self).run_timer(plugin, token, buffer_text)
+    }
+    fn status_segments(&mut self) -> Vec<Arc<dyn StatusSegmentProvider>> {
+        (**self).status_segments()
+    }
 }
 
 /// No‑op host used until real plugin discovery lands.
@@ -0,0 +1,680 @@
+//! WebAssembly plugin host.
+//!
+//! `WasmPluginHost` discovers `*.wasm` modules in one directory (sorted by
+//! file name; the file stem is the plugin name) and instantiates each in its
+//! own wasmtime `Store`. Plugins are sandboxed: no WASI or other imports
+//! beyond the host API below, linear memory capped at `MEMORY_LIMIT`, and
+//! every call into a plugin metered with `FUEL_PER_CALL` fuel so a runaway
+//! loop traps instead of stalling the editor. A module that fails to compile,
+//! link or initialize is skipped and recorded in `failures`.
+//!
+//! Host API, imported from module `"oxidized"` (strings are UTF-8
+//! `(ptr, len)` pairs in the plugin's exported `memory`):
+//! - `buffer_len() -> i32`: byte length of the buffer text snapshot.
+//! - `buffer_read(ptr, len) -> i32`: copy up to `len` bytes of the snapshot
+//!   to `ptr`; returns the count copied.
+//! - `set_status(ptr, len)`: show an ephemeral status message.
+//! - `register_command(ptr, len) -> i32`: claim an ex command name during
+//!   `init`; returns its id, or -1 when the name is invalid (user commands
+//!   start with an uppercase letter), taken, or registration is over.
+//! - `register_keymap(lhs_ptr, lhs_len, cmd_ptr, cmd_len) -> i32`: during
+//!   `init`, bind a Normal-mode key sequence to one of the plugin's own
+//!   commands; returns 0, or -1 when the command is not the plugin's.
+//! - `register_segment(interval_ms) -> i32`: during `init`, contribute a
+//!   status line segment named after the plugin, recomputed every
+//!   `interval_ms` (0: whenever the buffer changes); returns 0.
+//! - `timer_start(token, delay_ms, repeat) -> i32`: from a `command` or
+//!   `timer` call, have `timer(token)` called after `delay_ms`, and every
+//!   `delay_ms` after that when `repeat` is non-zero; a timer with the same
+//!   token is replaced. Returns 0, or -1 when the delay is negative, the
+//!   plugin exports no `timer`, or the call cannot start timers (`init`,
+//!   `segment`).
+//! - `timer_stop(token)`: stop the timer started with `token`.
+//!
+//! Plugin exports: `memory`, an optional `init()` run once at load,
+//! `command(id: i32)` run when one of its commands executes, `segment()`,
+//! whose `set_status` message becomes its status segment, and
+//! `timer(token: i32)` run when one of its timers fires.
+//!
+//! The host never touches editor state directly: callers pass the buffer
+//! text in and apply the returned `PluginEffects`, which keeps this crate
+//! free of `core-state` and the plugin call free of borrows into the editor.
+
+use crate::{PluginCommand, PluginEffects, PluginKeymap, PluginTimer};
+use anyhow::{Context, Result, anyhow, bail};
+use core_events::{RefreshHint, SegmentContext, StatusSegmentProvider};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{debug, warn};
+use wasmtime::{
+    Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+/// Fuel granted to each call into a plugin (roughly one unit per instruction).
+pub const FUEL_PER_CALL: u64 = 10_000_000;
+/// Linear memory cap per plugin instance.
+pub const MEMORY_LIMIT: usize = 16 << 20;
+/// Longest accepted status message, in bytes.
+const MAX_STATUS_BYTES: usize = 4096;
+
+/// Store data of one plugin instance.
+struct HostState {
+    limits: StoreLimits,
+    buffer: String,
+    effects: PluginEffects,
+    commands: Vec<String>,
+    /// `(lhs, command)` pairs from `register_keymap`.
+    keymaps: Vec<(String, String)>,
+    /// Refresh hint from `register_segment`.
+    segment: Option<RefreshHint>,
+    /// Registration only works while `init` runs.
+    registering: bool,
+    /// Whether the call in progress may start and stop timers.
+    timers: bool,
+}
+
+/// A loaded plugin. What it registered is copied out of the store after
+/// `init`, so listing never waits on a call in flight.
+struct WasmPlugin {
+    name: String,
+    commands: Vec<String>,
+    keymaps: Vec<(String, String)>,
+    segment: Option<RefreshHint>,
+    instance: Mutex<PluginInstance>,
+}
+
+struct PluginInstance {
+    store: Store<HostState>,
+    command: Option<TypedFunc<i32, ()>>,
+    segment: Option<TypedFunc<(), ()>>,
+    timer: Option<TypedFunc<i32, ()>>,
+}
+
+impl PluginInstance {
+    /// Call `func` with a fresh fuel budget and `buffer_text` as the
+    /// snapshot, returning the effects it produced. Only with `timers` may
+    /// it start and stop timers.
+    fn call<P: wasmtime::WasmParams>(
+        &mut self,
+        func: TypedFunc<P, ()>,
+        params: P,
+        buffer_text: &str,
+        timers: bool,
+    ) -> Result<PluginEffects> {
+        let state = self.store.data_mut();
+        state.buffer = buffer_text.to_string();
+        state.effects = PluginEffects::default();
+        state.timers = timers;
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        let result = func.call(&mut self.store, params);
+        let state = self.store.data_mut();
+        state.buffer.clear();
+        state.timers = false;
+        let effects = std::mem::take(&mut state.effects);
+        result?;
+        Ok(effects)
+    }
+}
+
+pub struct WasmPluginHost {
+    dir: PathBuf,
+    engine: Engine,
+    linker: Linker<HostState>,
+    plugins: Vec<Arc<WasmPlugin>>,
+    failures: Vec<(PathBuf, String)>,
+}
+
+impl WasmPluginHost {
+    /// Host for the modules in `dir`; nothing loads until `load_all`.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let mut linker = Linker::new(&engine);
+        define_host_api(&mut linker)?;
+        Ok(Self {
+            dir: dir.into(),
+            engine,
+            linker,
+            plugins: Vec::new(),
+            failures: Vec::new(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Names of the loaded plugins, in load order.
+    pub fn plugins(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(|p| p.name.as_str())
+    }
+
+    /// Modules that failed to load, with the reason.
+    pub fn failures(&self) -> &[(PathBuf, String)] {
+        &self.failures
+    }
+
+    /// Compile and initialize one module. Command names must be unique
+    /// across plugins; a later plugin's duplicate is refused.
+    pub fn load_module(&mut self, name: &str, bytes: &[u8]) -> Result<()> {
+        if self.plugins.iter().any(|p| p.name == name) {
+            bail!("plugin {name} is already loaded");
+        }
+        let module = Module::new(&self.engine, bytes)?;
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(MEMORY_LIMIT)
+                    .instances(1)
+                    .build(),
+                buffer: String::new(),
+                effects: PluginEffects::default(),
+                commands: Vec::new(),
+                keymaps: Vec::new(),
+                segment: None,
+                registering: false,
+                timers: false,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = self.linker.instantiate(&mut store, &module)?;
+        let command = instance
+            .get_typed_func::<i32, ()>(&mut store, "command")
+            .ok();
+        let segment = instance
+            .get_typed_func::<(), ()>(&mut store, "segment")
+            .ok();
+        let timer = instance.get_typed_func::<i32, ()>(&mut store, "timer").ok();
+        if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "init") {
+            store.data_mut().registering = true;
+            store.set_fuel(FUEL_PER_CALL)?;
+            let result = init.call(&mut store, ());
+            store.data_mut().registering = false;
+            result.context("init")?;
+        }
+        let state = store.data_mut();
+        if let Some(dup) = state
+            .commands
+            .iter()
+            .find(|c| self.plugins.iter().any(|p| p.commands.contains(c)))
+        {
+            bail!("command {dup} is already registered by another plugin");
+        }
+        if state.segment.is_some() && segment.is_none() {
+            bail!("registered a status segment but exports no `segment`");
+        }
+        let plugin = WasmPlugin {
+            name: name.to_string(),
+            commands: std::mem::take(&mut state.commands),
+            keymaps: std::mem::take(&mut state.keymaps),
+            segment: state.segment,
+            instance: Mutex::new(PluginInstance {
+                store,
+                command,
+                segment,
+                timer,
+            }),
+        };
+        debug!(
+            target: "plugin.wasm",
+            plugin = name,
+            commands = plugin.commands.len(),
+            segment = plugin.segment.is_some(),
+            "plugin_loaded"
+        );
+        self.plugins.push(Arc::new(plugin));
+        Ok(())
+    }
+}
+
+impl crate::PluginHost for WasmPluginHost {
+    fn name(&self) -> &'static str {
+        "wasm-plugin-host"
+    }
+
+    /// Load every `*.wasm` in the directory not loaded yet. A missing
+    /// directory loads nothing; an unreadable one is an error.
+    fn load_all(&mut self) -> Result<()> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| self.dir.display().to_string()),
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "wasm"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Some(name) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            if self.plugins.iter().any(|p| p.name == name)
+                || self.failures.iter().any(|(p, _)| *p == path)
+            {
+                continue;
+            }
+            let loaded = std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| self.load_module(&name, &bytes));
+            if let Err(e) = loaded {
+                warn!(target: "plugin.wasm", path = %path.display(), error = %format!("{e:#}"), "plugin_load_failed");
+                self.failures.push((path, format!("{e:#}")));
+            }
+        }
+        Ok(())
+    }
+
+    fn commands(&self) -> Vec<PluginCommand> {
+        self.plugins
+            .iter()
+            .flat_map(|p| {
+                p.commands.iter().map(|name| PluginCommand {
+                    plugin: p.name.clone(),
+                    name: name.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn keymaps(&self) -> Vec<PluginKeymap> {
+        self.plugins
+            .iter()
+            .flat_map(|p| {
+                p.keymaps.iter().map(|(lhs, command)| PluginKeymap {
+                    plugin: p.name.clone(),
+                    lhs: lhs.clone(),
+                    command: command.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn run_command(&mut self, name: &str, buffer_text: &str) -> Result<PluginEffects> {
+        let (plugin, id) = self
+            .plugins
+            .iter()
+            .find_map(|p| Some((p, p.commands.iter().position(|c| c == name)?)))
+            .ok_or_else(|| anyhow!("no plugin command {name}"))?;
+        let mut instance = plugin
+            .instance
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let func = instance
+            .command
+            .clone()
+            .ok_or_else(|| anyhow!("plugin {} exports no `command`", plugin.name))?;
+        instance
+            .call(func, id as i32, buffer_text, true)
+            .with_context(|| format!("plugin {} command {name}", plugin.name))
+    }
+
+    fn run_timer(&mut self, plugin: &str, token: i32, buffer_text: &str) -> Result<PluginEffects> {
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|p| p.name == plugin)
+            .ok_or_else(|| anyhow!("no plugin {plugin}"))?;
+        let mut instance = plugin
+            .instance
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let func = instance
+            .timer
+            .clone()
+            .ok_or_else(|| anyhow!("plugin {} exports no `timer`", plugin.name))?;
+        instance
+            .call(func, token, buffer_text, true)
+            .with_context(|| format!("plugin {} timer {token}", plugin.name))
+    }
+
+    /// One segment, named after the plugin, per plugin that registered one.
+    fn status_segments(&mut self) -> Vec<Arc<dyn StatusSegmentProvider>> {
+        self.plugins
+            .iter()
+            .filter(|p| p.segment.is_some())
+            .map(|p| Arc::new(WasmSegment(Arc::clone(p))) as Arc<dyn StatusSegmentProvider>)
+            .collect()
+    }
+
+    /// Wasm plugins contribute no event sources yet.
+    fn event_sources(&mut self) -> Vec<Box<dyn core_events::AsyncEventSource>> {
+        Vec::new()
+    }
+}
+
+/// Status segment of one plugin: its `segment` export's status message.
+struct WasmSegment(Arc<WasmPlugin>);
+
+impl StatusSegmentProvider for WasmSegment {
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    fn refresh_hint(&self) -> RefreshHint {
+        self.0.segment.unwrap_or(RefreshHint::Once)
+    }
+
+    fn segment(&self, _ctx: &SegmentContext) -> Option<String> {
+        let mut instance = self
+            .0
+            .instance
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let func = instance.segment.clone()?;
+        match instance.call(func, (), "", false) {
+            Ok(effects) => effects.status,
+            Err(e) => {
+                warn!(target: "plugin.wasm", plugin = %self.0.name, error = %format!("{e:#}"), "plugin_segment_failed");
+                None
+            }
+        }
+    }
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow!("plugin exports no memory"))
+}
+
+fn read_string(
+    caller: &mut Caller<'_, HostState>,
+    ptr: i32,
+    len: i32,
+    max: usize,
+) -> Result<String> {
+    let len = usize::try_from(len).context("negative length")?;
+    if len > max {
+        bail!("string of {len} bytes exceeds {max}");
+    }
+    let ptr = usize::try_from(ptr).context("negative pointer")?;
+    let mut bytes = vec![0; len];
+    memory(caller)?.read(&*caller, ptr, &mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Ex command names follow user-command rules: an uppercase letter, then
+/// letters and digits, so plugins cannot claim built-in names.
+fn valid_command_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase()) && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+fn define_host_api(linker: &mut Linker<HostState>) -> Result<()> {
+    linker.func_wrap("oxidized", "buffer_len", |caller: Caller<'_, HostState>| {
+        caller.data().buffer.len() as i32
+    })?;
+    linker.func_wrap(
+        "oxidized",
+        "buffer_read",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32> {
+            let ptr = usize::try_from(ptr).context("negative pointer")?;
+            let len = usize::try_from(len).context("negative length")?;
+            let text = std::mem::take(&mut caller.data_mut().buffer);
+            let n = len.min(text.len());
+            let written = memory(&mut caller)?.write(&mut caller, ptr, &text.as_bytes()[..n]);
+            caller.data_mut().buffer = text;
+            written?;
+            Ok(n as i32)
+        },
+    )?;
+    linker.func_wrap(
+        "oxidized",
+        "set_status",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<()> {
+            let status = read_string(&mut caller, ptr, len, MAX_STATUS_BYTES)?;
+            caller.data_mut().effects.status = Some(status);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "oxidized",
+        "register_command",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32> {
+            let name = read_string(&mut caller, ptr, len, 64)?;
+            let state = caller.data_mut();
+            if !state.registering || !valid_command_name(&name) || state.commands.contains(&name) {
+                return Ok(-1);
+            }
+            state.commands.push(name);
+            Ok(state.commands.len() as i32 - 1)
+        },
+    )?;
+    linker.func_wrap(
+        "oxidized",
+        "register_keymap",
+        |mut caller: Caller<'_, HostState>,
+         lhs_ptr: i32,
+         lhs_len: i32,
+         cmd_ptr: i32,
+         cmd_len: i32|
+         -> Result<i32> {
+            let lhs = read_string(&mut caller, lhs_ptr, lhs_len, 64)?;
+            let command = read_string(&mut caller, cmd_ptr, cmd_len, 64)?;
+            let state = caller.data_mut();
+            if !state.registering || lhs.is_empty() || !state.commands.contains(&command) {
+                return Ok(-1);
+            }
+            state.keymaps.push((lhs, command));
+            Ok(0)
+        },
+    )?;
+    linker.func_wrap(
+        "oxidized",
+        "register_segment",
+        |mut caller: Caller<'_, HostState>, interval_ms: i32| -> i32 {
+            let state = caller.data_mut();
+            if !state.registering {
+                return -1;
+            }
+            state.segment = Some(match u64::try_from(interval_ms) {
+                Ok(0) | Err(_) => RefreshHint::OnBufferChange,
+                Ok(ms) => RefreshHint::Every(Duration::from_millis(ms)),
+            });
+            0
+        },
+    )?;
+    linker.func_wrap(
+        "oxidized",
+        "timer_start",
+        |mut caller: Caller<'_, HostState>, token: i32, delay_ms: i32, repeat: i32| -> i32 {
+            let exported = caller.get_export("timer").is_some();
+            let state = caller.data_mut();
+            let Ok(ms) = u64::try_from(delay_ms) else {
+                return -1;
+            };
+            if !state.timers || !exported {
+                return -1;
+            }
+            state.effects.timers.push(PluginTimer::Start {
+                token,
+                delay: Duration::from_millis(ms),
+                repeat: repeat != 0,
+            });
+            0
+        },
+    )?;
+    linker.func_wrap(
+        "oxidized",
+        "timer_stop",
+        |mut caller: Caller<'_, HostState>, token: i32| {
+            let state = caller.data_mut();
+            if state.timers {
+                state.effects.timers.push(PluginTimer::Stop { token });
+            }
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PluginHost;
+
+    /// Registers `Head`, which shows the first five buffer bytes as status,
+    /// and binds it to `<leader>h`.
+    const HEAD_PLUGIN: &str = r#"
+        (module
+          (import "oxidized" "buffer_read" (func $read (param i32 i32) (result i32)))
+          (import "oxidized" "set_status" (func $status (param i32 i32)))
+          (import "oxidized" "register_command" (func $register (param i32 i32) (result i32)))
+          (import "oxidized" "register_keymap" (func $map (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "Head")
+          (data (i32.const 16) "<leader>h")
+          (func (export "init")
+            (drop (call $register (i32.const 0) (i32.const 4)))
+            (drop (call $map (i32.const 16) (i32.const 9) (i32.const 0) (i32.const 4))))
+          (func (export "command") (param $id i32)
+            (call $status (i32.const 64) (call $read (i32.const 64) (i32.const 5)))))
+    "#;
+
+    const SPIN_PLUGIN: &str = r#"
+        (module
+          (import "oxidized" "register_command" (func $register (param i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "Spin")
+          (func (export "init") (drop (call $register (i32.const 0) (i32.const 4))))
+          (func (export "command") (param i32) (loop $l (br $l))))
+    "#;
+
+    fn host_with(modules: &[(&str, &str)]) -> (tempfile::TempDir, WasmPluginHost) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, text) in modules {
+            let bytes = wat::parse_str(text).unwrap();
+            std::fs::write(dir.path().join(format!("{name}.wasm")), bytes).unwrap();
+        }
+        let mut host = WasmPluginHost::new(dir.path()).unwrap();
+        host.load_all().unwrap();
+        (dir, host)
+    }
+
+    #[test]
+    fn discovers_modules_and_runs_registered_commands() {
+        let (dir, mut host) = host_with(&[("head", HEAD_PLUGIN)]);
+        std::fs::write(dir.path().join("notes.txt"), "not a plugin").unwrap();
+        host.load_all().unwrap();
+        assert_eq!(host.plugins().collect::<Vec<_>>(), ["head"]);
+        assert_eq!(
+            host.commands(),
+            [PluginCommand {
+                plugin: "head".into(),
+                name: "Head".into()
+            }]
+        );
+        assert_eq!(
+            host.keymaps(),
+            [PluginKeymap {
+                plugin: "head".into(),
+                lhs: "<leader>h".into(),
+                command: "Head".into()
+            }]
+        );
+        let effects = host.run_command("Head", "héllo world").unwrap();
+        assert_eq!(effects.status.as_deref(), Some("héll"));
+        assert!(host.run_command("Nope", "").is_err());
+    }
+
+    #[test]
+    fn broken_and_runaway_plugins_are_contained() {
+        let (_dir, mut host) = host_with(&[
+            ("bad", "(module (import \"env\" \"system\" (func)))"),
+            ("spin", SPIN_PLUGIN),
+        ]);
+        assert_eq!(host.plugins().collect::<Vec<_>>(), ["spin"]);
+        assert_eq!(host.failures().len(), 1, "unknown imports are refused");
+        let err = host.run_command("Spin", "").unwrap_err();
+        assert!(format!("{err:#}").contains("fuel"), "{err:#}");
+    }
+
+    /// Segment reading "ok", refreshed every 500 ms.
+    const SEGMENT_PLUGIN: &str = r#"
+        (module
+          (import "oxidized" "set_status" (func $status (param i32 i32)))
+          (import "oxidized" "register_segment" (func $segment (param i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "ok")
+          (func (export "init") (drop (call $segment (i32.const 500))))
+          (func (export "segment") (call $status (i32.const 0) (i32.const 2))))
+    "#;
+
+    #[test]
+    fn registered_segments_become_providers() {
+        let (_dir, mut host) = host_with(&[("head", HEAD_PLUGIN), ("lint", SEGMENT_PLUGIN)]);
+        let providers = host.status_segments();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].name(), "lint");
+        assert_eq!(
+            providers[0].refresh_hint(),
+            RefreshHint::Every(Duration::from_millis(500))
+        );
+        let text = providers[0].segment(&SegmentContext::default());
+        assert_eq!(text.as_deref(), Some("ok"));
+    }
+
+    /// `Blink` starts timer 3 every 250 ms; the timer reports "tick" and
+    /// stops itself.
+    const TIMER_PLUGIN: &str = r#"
+        (module
+          (import "oxidized" "set_status" (func $status (param i32 i32)))
+          (import "oxidized" "register_command" (func $register (param i32 i32) (result i32)))
+          (import "oxidized" "timer_start" (func $start (param i32 i32 i32) (result i32)))
+          (import "oxidized" "timer_stop" (func $stop (param i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "Blink")
+          (data (i32.const 16) "tick")
+          (func (export "init") (drop (call $register (i32.const 0) (i32.const 5))))
+          (func (export "command") (param i32)
+            (drop (call $start (i32.const 3) (i32.const 250) (i32.const 1))))
+          (func (export "timer") (param $token i32)
+            (call $status (i32.const 16) (i32.const 4))
+            (call $stop (local.get $token))))
+    "#;
+
+    #[test]
+    fn plugins_start_timers_from_commands_and_timers() {
+        let (_dir, mut host) = host_with(&[("blink", TIMER_PLUGIN)]);
+        let effects = host.run_command("Blink", "").unwrap();
+        assert_eq!(
+            effects.timers,
+            [PluginTimer::Start {
+                token: 3,
+                delay: Duration::from_millis(250),
+                repeat: true
+            }]
+        );
+        let effects = host.run_timer("blink", 3, "").unwrap();
+        assert_eq!(effects.status.as_deref(), Some("tick"));
+        assert_eq!(effects.timers, [PluginTimer::Stop { token: 3 }]);
+        assert!(host.run_timer("head", 3, "").is_err());
+    }
+
+    #[test]
+    fn command_names_must_start_uppercase() {
+        assert!(valid_command_name("Head2"));
+        assert!(!valid_command_name("head"));
+        assert!(!valid_command_name("write"));
+        assert!(!valid_command_name("2Head"));
+        assert!(!valid_command_name(""));
+    }
+
+    #[test]
+    fn missing_directory_loads_nothing() {
+        let mut host = WasmPluginHost::new("/nonexistent/oxidized-plugins").unwrap();
+        host.load_all().unwrap();
+        assert_eq!(host.plugins().count(), 0);
+        assert!(host.failures().is_empty());
+    }
+}
@@ -15,6 +15,18 @@
 //! * Styled prints (reverse video sequences used for cursor) flush the
 //!   current batch first, then are emitted directly.
 //!
+//! Base style: a writer built `with_base` re-emits the theme's `Normal`
+//! sequence after every move and in place of every `\x1b[0m` reset, so plain
+//! cells and cleared lines keep the scheme's colors. It is not counted as a
+//! print command.
+//!
+//! Hardware cursor commands (shape, hide, show) flush the batch but are
+//! neither prints nor cells.
+//!
+//! `flush_timed` also reports how long queueing the commands (`write_ns`)
+//! and flushing stdout (`flush_ns`) took, for the stage profiler.
+//! `into_commands` hands the same sealed stream to a `CaptureWriter` instead.
+//!
 //! Metrics Semantics:
 //! * `print_commands` – number of terminal `Print` commands issued after
 //!   batching (the lower the better for throughput).
@@ -31,18 +43,29 @@
 //!
 use crate::writer::Command;
 use anyhow::Result;
+use core_terminal::CursorShape;
 use crossterm::{
-    cursor::MoveTo,
+    cursor::{Hide, MoveTo, Show},
     queue,
     style::Print,
     terminal::{Clear, ClearType},
 };
 use std::io::{Write, stdout};
 
+/// Outcome of `BatchWriter::flush_timed`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlushStats {
+    pub print_commands: u64,
+    pub cells_printed: u64,
+    pub write_ns: u64,
+    pub flush_ns: u64,
+}
+
 #[derive(Default)]
 pub struct BatchWriter {
     cmds: Vec<Command>,
     pending_plain: String,
+    base: Option<String>,
     pub print_commands: u64,
     pub cells_printed: u64,
 }
@@ -52,6 +75,14 @@ impl BatchWriter {
         Self::default()
     }
 
+    /// Writer restoring `base` (a full SGR sequence) after moves and resets.
+    pub fn with_base(base: Option<String>) -> Self {
+        Self {
+            base,
+            ..Self::default()
+        }
+    }
+
     #[inline]
     fn flush_pending(&mut self) {
         if self.pending_plain.is_empty() {
@@ -66,6 +97,9 @@ impl BatchWriter {
     pub fn move_to(&mut self, x: u16, y: u16) {
         self.flush_pending();
         self.cmds.push(Command::MoveTo(x, y));
+        if let Some(base) = &self.base {
+            self.cmds.push(Command::Print(base.clone()));
+        }
     }
 
     pub fn clear_line(&mut self, x: u16, y: u16) {
@@ -74,10 +108,15 @@ impl BatchWriter {
     }
 
     pub fn print<S: Into<String>>(&mut self, s: S) {
-        let s: String = s.into();
+        let mut s: String = s.into();
         if s.is_empty() {
             return;
         }
+        if let Some(base) = &self.base
+            && s.contains("\x1b[0m")
+        {
+            s = s.replace("\x1b[0m", base);
+        }
         let is_plain_single = s.len() == 1 && !s.contains('\x1b');
         if is_plain_single {
             self.pending_plain.push_str(&s);
@@ -91,8 +130,49 @@ impl BatchWriter {
         self.cells_printed += 1; // treat as one logical cell for baseline
     }
 
-    pub fn flush(mut self) -> Result<(u64, u64)> {
+    /// Hardware cursor commands; none of them prints a cell.
+    pub fn set_cursor_style(&mut self, shape: CursorShape) {
+        self.flush_pending();
+        self.cmds.push(Command::SetCursorStyle(shape));
+    }
+
+    pub fn hide_cursor(&mut self) {
+        self.flush_pending();
+        self.cmds.push(Command::HideCursor);
+    }
+
+    pub fn show_cursor(&mut self) {
         self.flush_pending();
+        self.cmds.push(Command::ShowCursor);
+    }
+
+    pub fn flush(self) -> Result<(u64, u64)> {
+        let stats = self.flush_timed()?;
+        Ok((stats.print_commands, stats.cells_printed))
+    }
+
+    /// Close the stream: emit the pending batch and the final reset.
+    fn seal(&mut self) {
+        self.flush_pending();
+        if self.base.is_some() {
+            self.cmds.push(Command::Print("\x1b[0m".to_string()));
+        }
+    }
+
+    /// The commands `flush_timed` would write, without touching stdout.
+    pub fn into_commands(mut self) -> (Vec<Command>, FlushStats) {
+        self.seal();
+        let stats = FlushStats {
+            print_commands: self.print_commands,
+            cells_printed: self.cells_printed,
+            ..FlushStats::default()
+        };
+        (self.cmds, stats)
+    }
+
+    pub fn flush_timed(mut self) -> Result<FlushStats> {
+        self.seal();
+        let start = std::time::Instant::now();
         let mut out = stdout();
         for c in self.cmds {
             match c {
@@ -105,10 +185,25 @@ impl BatchWriter {
                 Command::Print(s) => {
                     queue!(out, Print(s))?;
                 }
+                Command::SetCursorStyle(shape) => {
+                    queue!(out, shape.style())?;
+                }
+                Command::HideCursor => {
+                    queue!(out, Hide)?;
+                }
+                Command::ShowCursor => {
+                    queue!(out, Show)?;
+                }
             }
         }
+        let queued = std::time::Instant::now();
         out.flush()?;
-        Ok((self.print_commands, self.cells_printed))
+        Ok(FlushStats {
+            print_commands: self.print_commands,
+            cells_printed: self.cells_printed,
+            write_ns: queued.duration_since(start).as_nanos() as u64,
+            flush_ns: queued.elapsed().as_nanos() as u64,
+        })
     }
 }
 
@@ -130,4 +225,21 @@ mod tests {
         assert_eq!(print_cmds, 2);
         assert!(cells >= print_cmds);
     }
+
+    #[test]
+    fn base_style_follows_moves_and_resets() {
+        let mut w = BatchWriter::with_base(Some("\x1b[0;40m".to_string()));
+        w.move_to(0, 0);
+        w.print("\x1b[7mx\x1b[0m");
+        let printed: Vec<&str> = w
+            .cmds
+            .iter()
+            .filter_map(|c| match c {
+                Command::Print(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(printed, ["\x1b[0;40m", "\x1b[7mx\x1b[0;40m"]);
+        assert_eq!(w.print_commands, 1);
+    }
 }
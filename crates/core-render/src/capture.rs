@@ -0,0 +1,490 @@
+//! Capturing writer for render snapshot tests.
+//!
+//! `CaptureWriter` stands in for stdout: an engine built with
+//! `RenderEngine::capture_to` hands every flushed `BatchWriter` command
+//! stream to it instead of the terminal. The capture keeps the raw
+//! `Command` list of each frame and replays it onto a `ScreenGrid`, a
+//! minimal terminal model, so tests can assert what the screen shows after
+//! full, partial and scroll-shift frames rather than which bytes produced it.
+//!
+//! The model understands exactly what the render paths emit:
+//! * `MoveTo`, `ClearLine` (whole current row), cursor hide/show/shape.
+//! * Printed grapheme clusters, placed by `egc_width`. A wide cluster fills
+//!   a leader cell plus continuation cells; overwriting either half blanks
+//!   the other, as terminals do. Output past the right edge is dropped
+//!   (the renderer never relies on autowrap).
+//! * CSI sequences: SGR reverse video (`7` / `27` / `0`, skipping extended
+//!   color arguments), scroll regions (`r`) and region scrolls (`S` / `T`).
+//!   Any other escape is ignored.
+
+use crate::writer::Command;
+use core_terminal::CursorShape;
+use core_text::{egc_width, grapheme};
+use std::fmt;
+
+/// One terminal cell of a `ScreenGrid`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenCell {
+    /// Cluster printed at this cell; empty for continuation cells.
+    pub cluster: String,
+    /// Columns the cluster spans; `0` for continuation cells.
+    pub width: u8,
+    /// Printed under SGR reverse video (the software cursor, selections).
+    pub reverse: bool,
+}
+
+impl ScreenCell {
+    fn blank() -> Self {
+        Self {
+            cluster: " ".to_string(),
+            width: 1,
+            reverse: false,
+        }
+    }
+
+    fn continuation(reverse: bool) -> Self {
+        Self {
+            cluster: String::new(),
+            width: 0,
+            reverse,
+        }
+    }
+
+    #[inline]
+    pub fn is_leader(&self) -> bool {
+        self.width > 0
+    }
+}
+
+/// Terminal screen reconstructed from a command stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenGrid {
+    width: u16,
+    height: u16,
+    cells: Vec<ScreenCell>,
+    cursor: (u16, u16),
+    cursor_visible: bool,
+    cursor_shape: Option<CursorShape>,
+    reverse: bool,
+    /// Scroll region rows, inclusive and 0-based.
+    region: (u16, u16),
+}
+
+impl ScreenGrid {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![ScreenCell::blank(); width as usize * height as usize],
+            cursor: (0, 0),
+            cursor_visible: true,
+            cursor_shape: None,
+            reverse: false,
+            region: (0, height.saturating_sub(1)),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Terminal cursor position after the last command (column, row).
+    pub fn cursor(&self) -> (u16, u16) {
+        self.cursor
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Last hardware cursor shape set, if any.
+    pub fn cursor_shape(&self) -> Option<CursorShape> {
+        self.cursor_shape
+    }
+
+    pub fn cell(&self, x: u16, y: u16) -> Option<&ScreenCell> {
+        self.index(x, y).map(|i| &self.cells[i])
+    }
+
+    /// Text of row `y`: leader clusters in order, trailing blanks trimmed.
+    pub fn row_text(&self, y: u16) -> String {
+        if y >= self.height {
+            return String::new();
+        }
+        let start = y as usize * self.width as usize;
+        let row: String = self.cells[start..start + self.width as usize]
+            .iter()
+            .filter(|c| c.is_leader())
+            .map(|c| c.cluster.as_str())
+            .collect();
+        row.trim_end_matches(' ').to_string()
+    }
+
+    /// `row_text` of every row, top to bottom.
+    pub fn rows(&self) -> Vec<String> {
+        (0..self.height).map(|y| self.row_text(y)).collect()
+    }
+
+    /// Clusters of row `y` printed in reverse video, in order.
+    pub fn reversed_text(&self, y: u16) -> String {
+        if y >= self.height {
+            return String::new();
+        }
+        let start = y as usize * self.width as usize;
+        self.cells[start..start + self.width as usize]
+            .iter()
+            .filter(|c| c.is_leader() && c.reverse)
+            .map(|c| c.cluster.as_str())
+            .collect()
+    }
+
+    /// Panic with the whole screen unless row `y` reads `expected`
+    /// (trailing blanks ignored).
+    #[track_caller]
+    pub fn assert_row(&self, y: u16, expected: &str) {
+        let actual = self.row_text(y);
+        assert!(
+            actual == expected.trim_end_matches(' '),
+            "row {y} mismatch\n  expected: {expected:?}\n    actual: {actual:?}\nscreen:\n{self}"
+        );
+    }
+
+    /// Panic with both screens unless the leading rows read `expected`.
+    /// Rows past `expected` are not checked.
+    #[track_caller]
+    pub fn assert_rows(&self, expected: &[&str]) {
+        let actual = self.rows();
+        let matches = expected.len() <= actual.len()
+            && expected
+                .iter()
+                .zip(&actual)
+                .all(|(e, a)| e.trim_end_matches(' ') == a);
+        assert!(
+            matches,
+            "screen mismatch\nexpected:\n{}\nactual:\n{self}",
+            expected.join("\n")
+        );
+    }
+
+    /// Panic with both screens and the first differing cell unless this
+    /// grid equals `expected` cell for cell (reverse video included).
+    #[track_caller]
+    pub fn assert_matches(&self, expected: &ScreenGrid) {
+        assert_eq!(
+            (self.width, self.height),
+            (expected.width, expected.height),
+            "grid size mismatch"
+        );
+        let Some(i) = (0..self.cells.len()).find(|i| self.cells[*i] != expected.cells[*i]) else {
+            return;
+        };
+        let (x, y) = (i % self.width as usize, i / self.width as usize);
+        panic!(
+            "cell ({x}, {y}) mismatch\n  expected: {:?}\n    actual: {:?}\nexpected:\n{expected}actual:\n{self}",
+            expected.cells[i], self.cells[i]
+        );
+    }
+
+    /// Apply one command, as the terminal would.
+    pub fn apply(&mut self, command: &Command) {
+        match command {
+            Command::MoveTo(x, y) => {
+                self.cursor = (
+                    (*x).min(self.width.saturating_sub(1)),
+                    (*y).min(self.height.saturating_sub(1)),
+                );
+            }
+            Command::ClearLine(_, _) => {
+                let y = self.cursor.1;
+                for x in 0..self.width {
+                    if let Some(i) = self.index(x, y) {
+                        self.cells[i] = ScreenCell::blank();
+                    }
+                }
+            }
+            Command::Print(s) => self.print(s),
+            Command::SetCursorStyle(shape) => self.cursor_shape = Some(*shape),
+            Command::HideCursor => self.cursor_visible = false,
+            Command::ShowCursor => self.cursor_visible = true,
+        }
+    }
+
+    fn print(&mut self, s: &str) {
+        let mut rest = s;
+        while !rest.is_empty() {
+            match rest.find('\x1b') {
+                Some(0) => rest = self.escape(rest),
+                Some(at) => {
+                    self.print_text(&rest[..at]);
+                    rest = &rest[at..];
+                }
+                None => {
+                    self.print_text(rest);
+                    rest = "";
+                }
+            }
+        }
+    }
+
+    fn print_text(&mut self, text: &str) {
+        for cluster in grapheme::iter(text) {
+            let width = egc_width(cluster).max(1);
+            let (x, y) = self.cursor;
+            if x >= self.width || x + width > self.width {
+                // No autowrap: the cursor parks past the edge.
+                self.cursor.0 = self.width;
+                continue;
+            }
+            self.put(x, y, cluster, width);
+            self.cursor.0 = x + width;
+        }
+    }
+
+    fn put(&mut self, x: u16, y: u16, cluster: &str, width: u16) {
+        for dx in 0..width {
+            self.split_wide(x + dx, y);
+        }
+        let reverse = self.reverse;
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = ScreenCell {
+                cluster: cluster.to_string(),
+                width: width as u8,
+                reverse,
+            };
+        }
+        for dx in 1..width {
+            if let Some(i) = self.index(x + dx, y) {
+                self.cells[i] = ScreenCell::continuation(reverse);
+            }
+        }
+    }
+
+    /// Blank whatever wide cluster covers (x, y) before one half of it is
+    /// overwritten.
+    fn split_wide(&mut self, x: u16, y: u16) {
+        let Some(i) = self.index(x, y) else {
+            return;
+        };
+        let row_start = y as usize * self.width as usize;
+        let mut leader = i;
+        while !self.cells[leader].is_leader() && leader > row_start {
+            leader -= 1;
+        }
+        let span = self.cells[leader].width as usize;
+        if span > 1 && leader + span > i {
+            for cell in &mut self.cells[leader..leader + span] {
+                *cell = ScreenCell::blank();
+            }
+        }
+    }
+
+    /// Consume one escape sequence at the start of `s`; returns the rest.
+    fn escape<'s>(&mut self, s: &'s str) -> &'s str {
+        let Some(body) = s.strip_prefix("\x1b[") else {
+            // Not a CSI: skip ESC and the byte after it.
+            let mut chars = s.chars();
+            chars.next();
+            chars.next();
+            return chars.as_str();
+        };
+        let Some(end) = body.find(|c: char| ('\x40'..='\x7e').contains(&c)) else {
+            return "";
+        };
+        let params = &body[..end];
+        match body.as_bytes()[end] {
+            b'm' => self.sgr(params),
+            b'r' => self.set_region(params),
+            b'S' => self.scroll_up(count(params)),
+            b'T' => self.scroll_down(count(params)),
+            _ => {}
+        }
+        &body[end + 1..]
+    }
+
+    fn sgr(&mut self, params: &str) {
+        let mut args = params.split(';').map(|p| p.parse::<u16>().unwrap_or(0));
+        while let Some(arg) = args.next() {
+            match arg {
+                0 => self.reverse = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                38 | 48 | 58 => match args.next() {
+                    Some(5) => {
+                        args.next();
+                    }
+                    Some(2) => {
+                        args.nth(2);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn set_region(&mut self, params: &str) {
+        let last = self.height.saturating_sub(1);
+        self.region = match params.split_once(';') {
+            Some((top, bottom)) => {
+                let top = top.parse::<u16>().unwrap_or(1).saturating_sub(1);
+                let bottom = bottom.parse::<u16>().unwrap_or(self.height);
+                (top.min(last), bottom.saturating_sub(1).min(last))
+            }
+            None => (0, last),
+        };
+        // DECSTBM homes the cursor.
+        self.cursor = (0, 0);
+    }
+
+    fn scroll_up(&mut self, n: u16) {
+        let (top, bottom) = self.region;
+        for y in top..=bottom {
+            let from = y + n;
+            self.copy_row_or_blank(y, (from <= bottom).then_some(from));
+        }
+    }
+
+    fn scroll_down(&mut self, n: u16) {
+        let (top, bottom) = self.region;
+        for y in (top..=bottom).rev() {
+            let from = y.checked_sub(n).filter(|from| *from >= top);
+            self.copy_row_or_blank(y, from);
+        }
+    }
+
+    fn copy_row_or_blank(&mut self, to: u16, from: Option<u16>) {
+        let w = self.width as usize;
+        let dst = to as usize * w;
+        match from {
+            Some(from) => {
+                let src = from as usize * w;
+                let row = self.cells[src..src + w].to_vec();
+                self.cells[dst..dst + w].clone_from_slice(&row);
+            }
+            None => self.cells[dst..dst + w].fill(ScreenCell::blank()),
+        }
+    }
+
+    #[inline]
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y as usize * self.width as usize + x as usize)
+    }
+}
+
+/// CSI repeat count: missing or zero means one.
+fn count(params: &str) -> u16 {
+    params.parse::<u16>().unwrap_or(1).max(1)
+}
+
+impl fmt::Display for ScreenGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows().iter().enumerate() {
+            writeln!(f, "{y:>3}|{row}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Mock terminal output: records each flushed frame's commands and the
+/// screen they leave behind.
+#[derive(Debug, Clone)]
+pub struct CaptureWriter {
+    frames: Vec<Vec<Command>>,
+    grid: ScreenGrid,
+}
+
+impl CaptureWriter {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            frames: Vec::new(),
+            grid: ScreenGrid::new(width, height),
+        }
+    }
+
+    /// Record one flushed frame and apply it to the grid.
+    pub fn record(&mut self, commands: Vec<Command>) {
+        for command in &commands {
+            self.grid.apply(command);
+        }
+        self.frames.push(commands);
+    }
+
+    pub fn grid(&self) -> &ScreenGrid {
+        &self.grid
+    }
+
+    /// Command streams in flush order, one per frame (popups flush their own).
+    pub fn frames(&self) -> &[Vec<Command>] {
+        &self.frames
+    }
+
+    /// Commands of the most recent flush.
+    pub fn last_frame(&self) -> &[Command] {
+        self.frames.last().map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Forget recorded command streams; the grid keeps its contents, as
+    /// a terminal would.
+    pub fn clear_frames(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_after(width: u16, height: u16, commands: &[Command]) -> ScreenGrid {
+        let mut grid = ScreenGrid::new(width, height);
+        for command in commands {
+            grid.apply(command);
+        }
+        grid
+    }
+
+    #[test]
+    fn wide_cluster_halves_blank_each_other() {
+        let grid = grid_after(
+            6,
+            1,
+            &[
+                Command::MoveTo(0, 0),
+                Command::Print("a界b".into()),
+                Command::MoveTo(2, 0),
+                Command::Print("x".into()),
+            ],
+        );
+        grid.assert_row(0, "a xb");
+        assert_eq!(grid.cell(1, 0).unwrap().cluster, " ");
+    }
+
+    #[test]
+    fn sgr_reverse_skips_extended_color_arguments() {
+        let grid = grid_after(
+            4,
+            1,
+            &[
+                Command::Print("\x1b[38;5;7ma\x1b[0;7mb\x1b[0mc".into()),
+                Command::Print("\x1b[48;2;7;7;7md".into()),
+            ],
+        );
+        grid.assert_row(0, "abcd");
+        assert_eq!(grid.reversed_text(0), "b");
+    }
+
+    #[test]
+    fn scroll_region_shifts_only_region_rows() {
+        let mut commands = Vec::new();
+        for (y, row) in ["one", "two", "three", "status"].iter().enumerate() {
+            commands.push(Command::MoveTo(0, y as u16));
+            commands.push(Command::Print((*row).into()));
+        }
+        commands.push(Command::Print("\x1b[1;3r\x1b[1S\x1b[r".into()));
+        let grid = grid_after(8, 4, &commands);
+        grid.assert_rows(&["two", "three", "", "status"]);
+    }
+}
@@ -0,0 +1,336 @@
+//! Gutter left of the text: fold column, sign column and line numbers
+//! (`'number'` / `'relativenumber'`).
+//!
+//! The gutter sits left of the text in every view and shifts text columns
+//! (and the cursor span) right by its width. The `'foldcolumn'` cells come
+//! first (at most 12, as in Vim) and mark the view's folds: `+` on a closed
+//! fold, `-` where an open fold starts and `|` inside one. The two-cell
+//! sign column follows, only while the view's buffer has signs (see
+//! `core_state::signs`) or diagnostics. A line without a placed sign shows
+//! the sign of its most severe diagnostic (`E`, `W`, `I`, `H`). The number width follows Vim's default
+//! `'numberwidth'` of 4: room for the buffer's largest line number plus one
+//! separating space, never less than 4 columns. It therefore grows with the
+//! line count; render paths compare it against the width their cache was
+//! painted with and repaint everything on a change.
+//!
+//! Modes:
+//! * `number` – absolute line numbers.
+//! * `relativenumber` – distance from the cursor line (0 on the cursor line).
+//! * both (hybrid) – relative numbers, with the absolute number left-aligned
+//!   on the cursor line.
+//!
+//! Relative labels depend on the cursor line, so a cursor line change
+//! invalidates every row in relative and hybrid modes.
+
+use crate::{CellFlags, Frame};
+use core_model::View;
+use core_model::fold::Folds;
+use core_state::{
+    BufferId, DiagnosticStore, EditorState, SIGN_COLUMN_WIDTH, SignRegistry, SignStyle,
+};
+use core_text::grapheme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberMode {
+    Off,
+    Absolute,
+    Relative,
+    Hybrid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gutter {
+    pub mode: NumberMode,
+    /// Columns taken from the left of the text area (0 when off).
+    pub width: u16,
+    /// Leading fold column cells (`'foldcolumn'`).
+    pub fold_width: u16,
+    /// Leading sign column cells (0 when the buffer has no signs).
+    pub sign_width: u16,
+    /// Cursor line relative labels are measured from.
+    pub cursor_line: usize,
+    /// Buffer whose signs are drawn.
+    pub buffer: Option<BufferId>,
+}
+
+impl Gutter {
+    pub const NONE: Gutter = Gutter {
+        mode: NumberMode::Off,
+        width: 0,
+        fold_width: 0,
+        sign_width: 0,
+        cursor_line: 0,
+        buffer: None,
+    };
+
+    /// Gutter for `view`, sized for its own buffer. Hex views have none.
+    pub fn for_view(state: &EditorState, view: &View) -> Gutter {
+        let mode = match (
+            state.options.get_bool("number"),
+            state.options.get_bool("relativenumber"),
+        ) {
+            (false, false) => NumberMode::Off,
+            (true, false) => NumberMode::Absolute,
+            (false, true) => NumberMode::Relative,
+            (true, true) => NumberMode::Hybrid,
+        };
+        let Some(entry) = state.buffers.get(view.buffer_id) else {
+            return Gutter::NONE;
+        };
+        if entry.meta.hex_view && entry.meta.binary.is_some() {
+            return Gutter::NONE;
+        }
+        let fold_width = state.options.get_number("foldcolumn").clamp(0, 12) as u16;
+        let sign_width = if state.signs.has_signs(view.buffer_id)
+            || state.diagnostics.has_diagnostics(view.buffer_id)
+        {
+            SIGN_COLUMN_WIDTH
+        } else {
+            0
+        };
+        let number_width = if mode == NumberMode::Off {
+            0
+        } else {
+            let digits = entry.buffer.line_count().max(1).to_string().len() as u16;
+            (digits + 1).max(4)
+        };
+        if fold_width + sign_width + number_width == 0 {
+            return Gutter::NONE;
+        }
+        Gutter {
+            mode,
+            width: fold_width + sign_width + number_width,
+            fold_width,
+            sign_width,
+            cursor_line: view.cursor.line,
+            buffer: Some(view.buffer_id),
+        }
+    }
+
+    pub fn is_relative(&self) -> bool {
+        matches!(self.mode, NumberMode::Relative | NumberMode::Hybrid)
+    }
+
+    /// Gutter text for buffer line `line`, exactly `width` columns wide,
+    /// with the sign styled for direct terminal output.
+    pub fn label(
+        &self,
+        signs: &SignRegistry,
+        diagnostics: &DiagnosticStore,
+        folds: &Folds,
+        line: usize,
+    ) -> String {
+        let mut out = String::new();
+        if self.fold_width > 0 {
+            out.push(fold_mark(folds, line));
+            out.extend(std::iter::repeat_n(' ', self.fold_width as usize - 1));
+        }
+        if self.sign_width > 0 {
+            match self.sign_at(signs, diagnostics, line) {
+                Some((glyph, width, flags)) => {
+                    if flags.contains(CellFlags::REVERSE) {
+                        out.push_str(&format!("\x1b[7m{glyph}\x1b[0m"));
+                    } else {
+                        out.push_str(glyph);
+                    }
+                    out.extend(std::iter::repeat_n(' ', (self.sign_width - width) as usize));
+                }
+                None => out.extend(std::iter::repeat_n(' ', self.sign_width as usize)),
+            }
+        }
+        out.push_str(&self.number_label(line));
+        out
+    }
+
+    /// Line number part of the gutter for `line` (empty when numbers are off).
+    pub fn number_label(&self, line: usize) -> String {
+        let digits = self
+            .width
+            .saturating_sub(self.fold_width + self.sign_width + 1) as usize;
+        let relative = line.abs_diff(self.cursor_line);
+        match self.mode {
+            NumberMode::Off => String::new(),
+            NumberMode::Absolute => format!("{:>digits$} ", line + 1),
+            NumberMode::Relative => format!("{relative:>digits$} "),
+            NumberMode::Hybrid if relative == 0 => format!("{:<digits$} ", line + 1),
+            NumberMode::Hybrid => format!("{relative:>digits$} "),
+        }
+    }
+
+    /// Paint the gutter of `line` at row `y` starting at column `x`, clipped
+    /// to the frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint(
+        &self,
+        frame: &mut Frame,
+        signs: &SignRegistry,
+        diagnostics: &DiagnosticStore,
+        folds: &Folds,
+        x: u16,
+        y: u16,
+        line: usize,
+    ) {
+        for col in x..(x + self.fold_width).min(frame.width) {
+            let mark = if col == x {
+                fold_mark(folds, line)
+            } else {
+                ' '
+            };
+            frame.set_cluster(col, y, mark.encode_utf8(&mut [0; 4]), 1, CellFlags::empty());
+        }
+        let x = x + self.fold_width;
+        if self.sign_width > 0 {
+            for col in x..(x + self.sign_width).min(frame.width) {
+                frame.set_cluster(col, y, " ", 1, CellFlags::empty());
+            }
+            if let Some((glyph, width, flags)) = self.sign_at(signs, diagnostics, line)
+                && x + width <= frame.width
+            {
+                let mut col = x;
+                for cluster in grapheme::iter(glyph) {
+                    let w = grapheme::cluster_width(cluster) as u16;
+                    frame.set_cluster(col, y, cluster, w, flags);
+                    col += w;
+                }
+            }
+        }
+        for (i, ch) in self.number_label(line).chars().enumerate() {
+            let col = x + self.sign_width + i as u16;
+            if col >= frame.width {
+                break;
+            }
+            let mut buf = [0u8; 4];
+            frame.set_cluster(col, y, ch.encode_utf8(&mut buf), 1, CellFlags::empty());
+        }
+    }
+
+    /// Placed sign of `line`, else the sign of its most severe diagnostic.
+    fn sign_at<'a>(
+        &self,
+        signs: &'a SignRegistry,
+        diagnostics: &DiagnosticStore,
+        line: usize,
+    ) -> Option<(&'a str, u16, CellFlags)> {
+        let buffer = self.buffer?;
+        let Some(sign) = signs.at(buffer, line) else {
+            let severity = diagnostics.line_severity(buffer, line)?;
+            return Some((severity.sign_glyph(), 1, sign_flags(severity.sign_style())));
+        };
+        let width = grapheme::iter(&sign.glyph)
+            .map(grapheme::cluster_width)
+            .sum::<usize>() as u16;
+        Some((&sign.glyph, width, sign_flags(sign.style)))
+    }
+}
+
+/// Fold column mark of `line`.
+fn fold_mark(folds: &Folds, line: usize) -> char {
+    if folds.closed_at(line).is_some() {
+        return '+';
+    }
+    match folds.innermost(line) {
+        Some(fold) if fold.start == line => '-',
+        Some(_) => '|',
+        None => ' ',
+    }
+}
+
+/// Cell attributes for a sign. Reverse video is the only attribute the
+/// writer emits, so errors and warnings use it and other signs are plain.
+pub fn sign_flags(style: SignStyle) -> CellFlags {
+    match style {
+        SignStyle::Error | SignStyle::Warning => CellFlags::REVERSE,
+        _ => CellFlags::empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_model::ViewId;
+    use core_text::{Buffer, Position};
+
+    fn state_with_lines(n: usize) -> EditorState {
+        let text: String = (0..n).map(|i| format!("{i}\n")).collect();
+        EditorState::new(Buffer::from_str("t", &text).unwrap())
+    }
+
+    #[test]
+    fn width_grows_with_line_count() {
+        let mut st = state_with_lines(5);
+        let view = View::new(ViewId(0), st.active, Position::new(2, 0), 0);
+        assert_eq!(Gutter::for_view(&st, &view), Gutter::NONE);
+        st.options.apply_set("number").unwrap();
+        assert_eq!(Gutter::for_view(&st, &view).width, 4);
+        let mut big = state_with_lines(12_000);
+        big.options.apply_set("nu").unwrap();
+        let view = View::new(ViewId(0), big.active, Position::origin(), 0);
+        assert_eq!(Gutter::for_view(&big, &view).width, 6);
+    }
+
+    #[test]
+    fn labels_follow_mode() {
+        let signs = SignRegistry::new();
+        let diagnostics = DiagnosticStore::new();
+        let folds = Folds::default();
+        let mut g = Gutter {
+            mode: NumberMode::Absolute,
+            width: 4,
+            cursor_line: 4,
+            ..Gutter::NONE
+        };
+        assert_eq!(g.label(&signs, &diagnostics, &folds, 0), "  1 ");
+        g.mode = NumberMode::Relative;
+        assert_eq!(g.label(&signs, &diagnostics, &folds, 1), "  3 ");
+        assert_eq!(g.label(&signs, &diagnostics, &folds, 4), "  0 ");
+        g.mode = NumberMode::Hybrid;
+        assert_eq!(g.label(&signs, &diagnostics, &folds, 4), "5   ");
+        assert_eq!(g.label(&signs, &diagnostics, &folds, 6), "  2 ");
+    }
+
+    #[test]
+    fn sign_column_precedes_numbers() {
+        let mut st = state_with_lines(3);
+        let view = View::new(ViewId(0), st.active, Position::origin(), 0);
+        let folds = Folds::default();
+        st.signs.place(st.active, 1, "E", SignStyle::Error).unwrap();
+        let g = Gutter::for_view(&st, &view);
+        assert_eq!((g.width, g.sign_width), (2, 2));
+        assert_eq!(g.label(&st.signs, &st.diagnostics, &folds, 0), "  ");
+        assert_eq!(
+            g.label(&st.signs, &st.diagnostics, &folds, 1),
+            "\x1b[7mE\x1b[0m "
+        );
+
+        st.options.apply_set("number").unwrap();
+        let g = Gutter::for_view(&st, &view);
+        assert_eq!(g.width, 6);
+        let mut frame = Frame::new(8, 2);
+        g.paint(&mut frame, &st.signs, &st.diagnostics, &folds, 0, 1, 1);
+        assert_eq!(frame.line_clusters(1).concat(), "E   2   ");
+        assert!(frame.cells[8].flags.contains(CellFlags::REVERSE));
+    }
+
+    #[test]
+    fn fold_column_comes_first_and_marks_folds() {
+        let mut st = state_with_lines(6);
+        let mut view = View::new(ViewId(0), st.active, Position::origin(), 0);
+        view.folds.create(1, 4);
+        view.folds.create(2, 3);
+        view.folds.open(1).unwrap();
+        st.options.apply_set("foldcolumn=2").unwrap();
+        st.options.apply_set("number").unwrap();
+        let g = Gutter::for_view(&st, &view);
+        assert_eq!((g.width, g.fold_width), (6, 2));
+        let labels: Vec<String> = (0..6)
+            .map(|l| g.label(&st.signs, &st.diagnostics, &view.folds, l))
+            .collect();
+        assert_eq!(
+            labels,
+            ["    1 ", "-   2 ", "+   3 ", "+   4 ", "|   5 ", "    6 "]
+        );
+        let mut frame = Frame::new(6, 1);
+        g.paint(&mut frame, &st.signs, &st.diagnostics, &view.folds, 0, 0, 4);
+        assert_eq!(frame.line_clusters(0).concat(), "|   5 ");
+    }
+}
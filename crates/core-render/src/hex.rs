@@ -0,0 +1,42 @@
+//! Fixed-width hex row composition for binary buffers.
+//!
+//! The text path segments every line into grapheme clusters and measures
+//! their display width. Hex rows are pure ASCII with a known width, and the
+//! raw bytes of a binary file may not be valid text at all, so rows are
+//! formatted straight from the bytes (`core_state::binary::hex_row`) and laid
+//! into the frame one single-width cell per character.
+
+use crate::{CellFlags, Frame};
+use core_state::binary::{hex_row, hex_row_count};
+
+/// Paint hex rows `first_row..` into the top `height` rows of `frame`.
+pub fn paint_hex_rows(frame: &mut Frame, bytes: &[u8], first_row: usize, height: u16, w: u16) {
+    let end = (first_row + height as usize).min(hex_row_count(bytes.len()));
+    for (y, row) in (first_row..end).enumerate() {
+        let text = hex_row(bytes, row);
+        for (x, b) in text.bytes().take(w as usize).enumerate() {
+            let cell = [b];
+            // Row text is ASCII by construction.
+            let s = std::str::from_utf8(&cell).unwrap_or(".");
+            frame.set_cluster(x as u16, y as u16, s, 1, CellFlags::empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paints_rows_from_offset_and_clips_width() {
+        let bytes: Vec<u8> = (0u8..40).collect();
+        let mut frame = Frame::new(12, 3);
+        paint_hex_rows(&mut frame, &bytes, 1, 3, 12);
+        let row0: String = (0..12).map(|x| frame.cells[x].cluster.as_str()).collect();
+        assert_eq!(row0, "00000010: 10");
+        let row2: String = (0..12)
+            .map(|x| frame.cells[24 + x].cluster.as_str())
+            .collect();
+        assert_eq!(row2.trim_end(), "", "only three rows of data exist");
+    }
+}
@@ -0,0 +1,322 @@
+//! Frame latency histograms and the opt-in stage profiler.
+//!
+//! Every render path keeps a rolling histogram of its last
+//! `LATENCY_WINDOW` frame durations, so the overlay can show p50/p95/p99
+//! per path instead of a single last sample that hides outliers. Buckets
+//! are log-linear (four per power of two), so a percentile is reported as
+//! the upper bound of its bucket: at most 25% above the true value, and
+//! reading it costs a walk over a fixed bucket array rather than a sort.
+//!
+//! The profiler (`:metrics profile`) additionally splits the last
+//! `PROFILE_FRAMES` frames into stages: line hashing, composing cells and
+//! writer commands, writing them to the terminal and flushing it. It is off
+//! by default; while off no stage is timed.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Frames each histogram covers.
+pub const LATENCY_WINDOW: usize = 256;
+/// Frames the profiler keeps.
+pub const PROFILE_FRAMES: usize = 8;
+
+const SUB_BUCKETS: usize = 4;
+/// Values below `SUB_BUCKETS` get a bucket each; every power of two above
+/// is split into `SUB_BUCKETS`.
+const BUCKETS: usize = 63 * SUB_BUCKETS;
+
+fn bucket(ns: u64) -> usize {
+    if ns < SUB_BUCKETS as u64 {
+        return ns as usize;
+    }
+    let msb = 63 - ns.leading_zeros() as usize;
+    let sub = (ns >> (msb - 2)) as usize & (SUB_BUCKETS - 1);
+    (msb - 1) * SUB_BUCKETS + sub
+}
+
+/// Largest value falling in bucket `idx`.
+fn bucket_upper(idx: usize) -> u64 {
+    if idx + 1 < SUB_BUCKETS {
+        return idx as u64;
+    }
+    let next = idx + 1;
+    let msb = next / SUB_BUCKETS + 1;
+    if msb >= 64 {
+        return u64::MAX;
+    }
+    (((SUB_BUCKETS + next % SUB_BUCKETS) as u64) << (msb - 2)) - 1
+}
+
+/// p50/p95/p99 of a histogram, in nanoseconds (0 without samples).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50_ns: u64,
+    pub p95_ns: u64,
+    pub p99_ns: u64,
+    /// Frames in the window (at most `LATENCY_WINDOW`).
+    pub samples: u64,
+}
+
+/// Rolling histogram of the last `LATENCY_WINDOW` durations.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: [u16; BUCKETS],
+    /// Bucket of each sample in the window, oldest first from `next`.
+    window: [u8; LATENCY_WINDOW],
+    next: usize,
+    len: usize,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BUCKETS],
+            window: [0; LATENCY_WINDOW],
+            next: 0,
+            len: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, ns: u64) {
+        if self.len == LATENCY_WINDOW {
+            self.counts[self.window[self.next] as usize] -= 1;
+        } else {
+            self.len += 1;
+        }
+        let idx = bucket(ns);
+        self.counts[idx] += 1;
+        self.window[self.next] = idx as u8;
+        self.next = (self.next + 1) % LATENCY_WINDOW;
+    }
+
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        if self.len == 0 {
+            return LatencyPercentiles::default();
+        }
+        let ranks = [50, 95, 99].map(|q| (self.len * q).div_ceil(100).max(1));
+        let mut out = [0u64; 3];
+        let mut seen = 0usize;
+        let mut want = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += *count as usize;
+            while want < ranks.len() && seen >= ranks[want] {
+                out[want] = bucket_upper(idx);
+                want += 1;
+            }
+            if want == ranks.len() {
+                break;
+            }
+        }
+        LatencyPercentiles {
+            p50_ns: out[0],
+            p95_ns: out[1],
+            p99_ns: out[2],
+            samples: self.len as u64,
+        }
+    }
+}
+
+/// Render path a frame took, as the histograms are keyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePath {
+    /// Full frames, including split layouts composed whole.
+    Full,
+    CursorOnly,
+    Lines,
+    ScrollShift,
+}
+
+impl FramePath {
+    pub fn name(self) -> &'static str {
+        match self {
+            FramePath::Full => "full",
+            FramePath::CursorOnly => "cursor",
+            FramePath::Lines => "lines",
+            FramePath::ScrollShift => "scroll",
+        }
+    }
+}
+
+/// One histogram per `FramePath`.
+#[derive(Debug, Default)]
+pub struct FrameLatency {
+    pub full: LatencyHistogram,
+    pub cursor_only: LatencyHistogram,
+    pub lines: LatencyHistogram,
+    pub scroll_shift: LatencyHistogram,
+}
+
+impl FrameLatency {
+    pub fn record(&mut self, path: FramePath, ns: u64) {
+        match path {
+            FramePath::Full => self.full.record(ns),
+            FramePath::CursorOnly => self.cursor_only.record(ns),
+            FramePath::Lines => self.lines.record(ns),
+            FramePath::ScrollShift => self.scroll_shift.record(ns),
+        }
+    }
+}
+
+/// Stage split of one profiled frame. `compose_ns` is what the other
+/// stages leave of `total_ns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStages {
+    pub path: FramePath,
+    pub total_ns: u64,
+    pub hash_ns: u64,
+    pub compose_ns: u64,
+    pub write_ns: u64,
+    pub flush_ns: u64,
+}
+
+/// Stage timings of the frame in flight and the last `PROFILE_FRAMES`
+/// frames. The stage clocks are cells because the writer flushes from
+/// `&self` helpers.
+#[derive(Debug, Default)]
+pub struct FrameProfiler {
+    enabled: bool,
+    hash_ns: Cell<u64>,
+    write_ns: Cell<u64>,
+    flush_ns: Cell<u64>,
+    frames: VecDeque<FrameStages>,
+}
+
+impl FrameProfiler {
+    /// Start a frame, profiled when `enabled`. Turning profiling off drops
+    /// the frames kept so far.
+    pub fn begin(&mut self, enabled: bool) {
+        if !enabled {
+            self.frames.clear();
+        }
+        self.enabled = enabled;
+        self.hash_ns.set(0);
+        self.write_ns.set(0);
+        self.flush_ns.set(0);
+    }
+
+    /// Start of a timed stage; `None` while profiling is off.
+    pub fn clock(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    pub fn add_hash(&self, since: Option<Instant>) {
+        if let Some(since) = since {
+            let ns = since.elapsed().as_nanos() as u64;
+            self.hash_ns.set(self.hash_ns.get() + ns);
+        }
+    }
+
+    pub fn add_io(&self, write_ns: u64, flush_ns: u64) {
+        if self.enabled {
+            self.write_ns.set(self.write_ns.get() + write_ns);
+            self.flush_ns.set(self.flush_ns.get() + flush_ns);
+        }
+    }
+
+    /// Close the frame in flight, `total_ns` long.
+    pub fn finish(&mut self, path: FramePath, total_ns: u64) {
+        if !self.enabled {
+            return;
+        }
+        let (hash_ns, write_ns, flush_ns) = (
+            self.hash_ns.take(),
+            self.write_ns.take(),
+            self.flush_ns.take(),
+        );
+        if self.frames.len() == PROFILE_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(FrameStages {
+            path,
+            total_ns,
+            hash_ns,
+            compose_ns: total_ns.saturating_sub(hash_ns + write_ns + flush_ns),
+            write_ns,
+            flush_ns,
+        });
+    }
+
+    /// Profiled frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &FrameStages> {
+        self.frames.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_bound_their_values() {
+        for ns in [
+            0,
+            1,
+            3,
+            4,
+            7,
+            8,
+            9,
+            15,
+            16,
+            1000,
+            123_456,
+            u64::MAX / 3,
+            u64::MAX,
+        ] {
+            let idx = bucket(ns);
+            assert!(idx < BUCKETS);
+            assert!(ns <= bucket_upper(idx), "{ns}");
+            assert!(idx == 0 || ns > bucket_upper(idx - 1), "{ns}");
+        }
+        // Upper bounds stay within 25% of the values they stand for.
+        assert_eq!(bucket_upper(bucket(1000)), 1023);
+    }
+
+    #[test]
+    fn percentiles_follow_the_window() {
+        let mut h = LatencyHistogram::default();
+        assert_eq!(h.percentiles(), LatencyPercentiles::default());
+        for ns in 1..=100u64 {
+            h.record(ns * 1000);
+        }
+        let p = h.percentiles();
+        assert_eq!(p.samples, 100);
+        assert!((50_000..=62_500).contains(&p.p50_ns), "{p:?}");
+        assert!((95_000..=118_750).contains(&p.p95_ns), "{p:?}");
+        assert!((99_000..=123_750).contains(&p.p99_ns), "{p:?}");
+        // A full window of fast frames pushes the slow ones out.
+        for _ in 0..LATENCY_WINDOW {
+            h.record(10);
+        }
+        let p = h.percentiles();
+        assert_eq!(p.samples, LATENCY_WINDOW as u64);
+        assert_eq!((p.p50_ns, p.p99_ns), (11, 11));
+    }
+
+    #[test]
+    fn profiler_keeps_the_last_frames_while_enabled() {
+        let mut prof = FrameProfiler::default();
+        prof.begin(false);
+        assert!(prof.clock().is_none());
+        prof.add_io(5, 5);
+        prof.finish(FramePath::Full, 100);
+        assert_eq!(prof.frames().count(), 0);
+
+        for total in 0..PROFILE_FRAMES as u64 + 2 {
+            prof.begin(true);
+            prof.add_io(10, 5);
+            prof.finish(FramePath::Lines, 100 + total);
+        }
+        assert_eq!(prof.frames().count(), PROFILE_FRAMES);
+        let last = prof.frames().last().unwrap();
+        assert_eq!(last.total_ns, 100 + PROFILE_FRAMES as u64 + 1);
+        assert_eq!((last.write_ns, last.flush_ns), (10, 5));
+        assert_eq!(last.compose_ns, last.total_ns - 15);
+
+        prof.begin(false);
+        assert_eq!(prof.frames().count(), 0);
+    }
+}
@@ -24,8 +24,9 @@
 //! work in a single locus.
 //!
 //! Integration Points:
-//! - `Layout` (core-model): currently single-region; future multi-view fan-out will
-//!   iterate regions and invoke partial scheduling per viewport.
+//! - `Layout` (core-model): split layouts fan the scheduler decision out per
+//!   region (`RenderEngine::render_views`), each view keeping its own
+//!   `region_cache::RegionCache` of line hashes.
 //! - `TerminalCapabilities` (core-terminal): stub exposes `supports_scroll_region` and
 //!   will gate scroll-delta optimization & cache shifting in Phase 4.
 //! - `UndoEngine` (core-state) indirectly influences dirty marking via dispatcher edits.
@@ -40,9 +41,28 @@
 //! - `partial_cache` / `partial_diff`: viewport line hashing & change classification.
 //! - `writer`: terminal command abstraction (MoveTo, ClearLine, Print) used by partial
 //!   paths and (currently) full path translation for consistency.
+//! - `capture`: mock terminal that records the command stream in place of stdout
+//!   (`RenderEngine::capture_to`) and rebuilds the screen grid for golden tests.
 //! - `partial_metrics`: execution path counters & timing separate from semantic metrics.
 //! - `status`: builds status line string (mode, file, position, ephemeral messages).
 //! - `dirty`: dirty line tracker fed by dispatcher edit mutations.
+//! - `gutter`: sign and line number columns; its width shifts text and cursor
+//!   columns and a width change (or a relative-mode cursor line change) repaints
+//!   every row. Sign changes reach partial paths through `invalidate_lines`.
+//! - `popup`: z-ordered bordered popups painted after every path, with their own
+//!   damage list so dismissing one restores the cells it covered.
+//! - `region_cache`: per-view line hashes for split frames, so a `Lines` delta only
+//!   hashes and repaints the regions showing the edited buffer.
+//! - `wrap`: the screen rows each visible line occupies (several under `'wrap'`);
+//!   every text path lays rows out through it and partial paths repaint fully
+//!   when an edit changed how many rows a line takes or a fold opened or
+//!   closed. A closed fold takes one summary row.
+//! - `whitespace`: `'list'` glyphs, painted by the frame and writer paths alike.
+//! - Diagnostics (`core_state::diagnostics`): signs through `gutter`, underlined
+//!   spans on both text paths and error / warning counts through `status`.
+//! - `style::Palette`: the active color scheme resolved for the terminal's color
+//!   depth (`RenderEngine::set_theme`); every emission path styles cells with it and
+//!   the writer restores the `Normal` colors after each move and reset.
 //!
 //! Partial Pipeline (Phase 3 MVP):
 //! 1. Scheduler emits semantic delta (CursorOnly | Lines | Scroll | Full) after coalescing.
@@ -68,8 +88,8 @@
 //! - Dirty Funnel: `dirty_lines_marked` (pre-filter), `dirty_candidate_lines` (post
 //!   intersection + cursor injection), `dirty_lines_repainted` (actual repaints).
 //! - Escalation & Env: `escalated_large_set`, `resize_invalidations`.
-//! - Timing: `last_full_render_ns`, `last_partial_render_ns` (point samples; moving
-//!   averages deferred).
+//! - Timing: rolling p50/p95/p99 per path (`full_latency`, `cursor_only_latency`,
+//!   `lines_latency`, `scroll_shift_latency`; see `latency`).
 //!
 //!   Interpretation Signals:
 //! - High candidate vs repainted delta => hashing avoiding redundant repaints.
@@ -87,6 +107,8 @@
 //!   and reuse of partial cache via `shift_for_scroll` (lines saved metric).
 //! - Trimmed line diff heuristic (prefix/suffix skip) storing prior line text
 //!   (`prev_text`) and emitting only interior mutations when savings threshold met.
+//!   Edits that change a line's width (typing mid-line) repaint the changed
+//!   column span plus the shifted tail, blanking cells the tail vacates.
 //! - Status line skip cache (`prev_status`) increments `status_skipped` when content
 //!   unchanged across partial frames.
 //! - Unified helpers (`paint_content_trim`, `overlay_cursor_cluster`,
@@ -104,7 +126,7 @@
 //!
 //! Deferred (Future Phases): multi-line segmented diff trimming, selection highlight
 //! spans, syntax token colorization, command batching, moving average latency metrics,
-//! Unicode width caching, multi-viewport fan-out.
+//! Unicode width caching, scroll-region shifts inside split regions.
 //!
 //! Architectural Tenets Applied:
 //! - Breadth-first: feature order prioritized correctness & instrumentation before micro
@@ -121,9 +143,29 @@ pub mod style;
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct CellFlags: u8 {
+    pub struct CellFlags: u32 {
         const REVERSE = 0b0000_0001; // reverse-video (software cursor)
         const CURSOR  = 0b0000_0010; // marks cell part of cursor span
+        const STATUS  = 0b0000_0100; // status row (`StatusLine` theme group)
+        const SEARCH  = 0b0000_1000; // `hlsearch` match (`Search` theme group)
+        const CURSORLINE   = 0b0001_0000; // cursor row (`cursorline`, `CursorLine` group)
+        const CURSORCOLUMN = 0b0010_0000; // cursor column (`cursorcolumn`, `CursorColumn` group)
+        const WHITESPACE   = 0b0100_0000; // `list` glyph (`listchars`, `Whitespace` group)
+        const FOLDED       = 0b1000_0000; // closed fold summary row (`Folded` group)
+        // Diagnostic spans (`DiagnosticUnderline{Error,Warn,Info,Hint}` groups).
+        const DIAG_ERROR   = 0b0001_0000_0000;
+        const DIAG_WARNING = 0b0010_0000_0000;
+        const DIAG_INFO    = 0b0100_0000_0000;
+        const DIAG_HINT    = 0b1000_0000_0000;
+        const COLORCOLUMN  = 0b0001_0000_0000_0000; // `colorcolumn` ruler (`ColorColumn` group)
+        // End-of-line text: with a `DIAG_*` flag naming its severity a
+        // diagnostic message (`DiagnosticVirtualText*` instead of the
+        // underline), without one a `:blame` annotation (`GitBlame`).
+        const VIRTUAL_TEXT = 0b0010_0000_0000_0000;
+        // Diff mode lines (`DiffAdd`, `DiffChange`) and filler rows (`DiffDelete`).
+        const DIFF_ADD     = 0b0100_0000_0000_0000;
+        const DIFF_CHANGE  = 0b1000_0000_0000_0000;
+        const DIFF_DELETE  = 0b0001_0000_0000_0000_0000;
     }
 }
 
@@ -134,6 +176,8 @@ pub struct Cell {
     /// Visual width in terminal columns. `0` designates a continuation cell.
     pub width: u8,
     pub flags: CellFlags,
+    /// Syntax highlight class (`core_syntax::HighlightClass` index).
+    pub syntax: Option<u16>,
 }
 
 impl Cell {
@@ -143,6 +187,7 @@ impl Cell {
             cluster: cluster.to_string(),
             width: width.max(1) as u8,
             flags,
+            syntax: None,
         }
     }
     #[inline]
@@ -151,6 +196,7 @@ impl Cell {
             cluster: String::new(),
             width: 0,
             flags,
+            syntax: None,
         }
     }
     #[inline]
@@ -165,6 +211,15 @@ impl Cell {
     pub fn cluster(&self) -> &str {
         &self.cluster
     }
+    /// Cluster wrapped in the SGR sequences its flags and syntax class ask for
+    /// under the built-in color scheme.
+    pub fn styled(&self) -> String {
+        style::styled_cluster(&self.cluster, self.flags, self.syntax)
+    }
+    /// As `styled`, under `palette`.
+    pub fn styled_with(&self, palette: &style::Palette) -> String {
+        palette.styled(&self.cluster, self.flags, self.syntax)
+    }
 }
 
 impl Default for Cell {
@@ -174,6 +229,7 @@ impl Default for Cell {
             cluster: " ".to_string(),
             width: 1,
             flags: CellFlags::empty(),
+            syntax: None,
         }
     }
 }
@@ -231,6 +287,16 @@ impl Frame {
         }
     }
 
+    /// Set the syntax class of the cells in `[x, x + span_width)` on row `y`.
+    pub fn apply_syntax_span(&mut self, x: u16, y: u16, span_width: u16, class: u16) {
+        let span = span_width.min(self.width.saturating_sub(x));
+        for dx in 0..span {
+            if let Some(idx) = self.index(x + dx, y) {
+                self.cells[idx].syntax = Some(class);
+            }
+        }
+    }
+
     /// Iterate leader cells of a row, yielding (&str, width, flags, start_x).
     pub fn row_leaders<'a>(
         &'a self,
@@ -256,6 +322,22 @@ impl Frame {
         })
     }
 
+    /// Copy `src` into this frame with its top-left corner at (x,y), clipped
+    /// to this frame's bounds (split layouts compose per-view frames).
+    pub fn blit(&mut self, src: &Frame, x: u16, y: u16) {
+        for sy in 0..src.height {
+            let Some(dy) = y.checked_add(sy).filter(|dy| *dy < self.height) else {
+                break;
+            };
+            for sx in 0..src.width.min(self.width.saturating_sub(x)) {
+                let from = sy as usize * src.width as usize + sx as usize;
+                if let Some(to) = self.index(x + sx, dy) {
+                    self.cells[to] = src.cells[from].clone();
+                }
+            }
+        }
+    }
+
     /// Collect leader cluster strings for a given row (testing / diagnostics only).
     pub fn line_clusters(&self, y: u16) -> Vec<&str> {
         if y >= self.height {
@@ -270,14 +352,24 @@ impl Frame {
 
 pub mod apply; // Step 7: stable render entry points
 pub mod batch_writer; // Refactor R3 Step 7: batching writer wrapper
+pub mod capture; // mock terminal for render snapshot tests
 pub mod dirty; // Phase 3 Step 1: dirty line tracking (external to RenderDelta)
+pub mod gutter; // sign + line number columns left of the text
+pub mod hex; // fixed-width hex rows for binary buffers
+pub mod latency; // per-path frame latency histograms + stage profiler
 pub mod overlay; // Step 13 metrics overlay
+pub mod pacing; // frame pacing under input bursts
 pub mod partial_cache; // Phase 3 Step 2: line hash + cache skeleton
 pub mod partial_diff; // New module for partial differences
 pub mod partial_metrics; // Phase 3 Step 4: metrics scaffold
+pub mod popup; // floating bordered windows above the text grid
+pub mod region_cache; // per-view line hashes for split frames
 pub mod render_engine;
 pub mod scheduler;
 pub mod status;
+pub mod tabline; // tab page labels on the top row
 pub mod timing;
 pub mod viewport; // (placeholder for future viewport helpers)
+pub mod whitespace; // 'list' glyphs over tabs, trailing spaces and line ends
+pub mod wrap; // screen rows of a view under 'wrap'
 pub mod writer; // Phase 3 Step 6: terminal writer abstraction
@@ -1,21 +1,34 @@
 //! Overlay module (Refactor R4 Step 13)
 //!
-//! Provides fixed-line metrics overlay rendering above the status line.
-//! Breadth-first implementation: always repaints overlay rows each frame; no
-//! diffing or dynamic sizing yet. Future enhancement (Dynamic overlay auto-sizing)
-//! will introduce width-aware wrapping and per-field prioritization.
+//! Rows above the status line: the metrics overlay (`:metrics`), the
+//! multi-line message area, or the wildmenu row of command-line completion
+//! (which takes precedence while it is open). The metrics overlay shows one section at a time
+//! (render path, scheduler, operators, input telemetry, frame profile) under
+//! a header row,
+//! its fields wrapped to the terminal width. Its height is that of the
+//! largest section at the current width, so paging (`:metrics next`) keeps
+//! the text area where it is and the partial paths stay partial. Partial
+//! paths repaint only overlay rows whose content changed since they were
+//! last painted.
 
 use crate::batch_writer::BatchWriter;
-use core_state::{EditorState, OverlayMode};
+use core_state::{EditorState, MetricsSection, OverlayMode};
 
 /// Build overlay lines based on the current overlay mode.
-/// For Metrics mode we emit up to `max_lines` lines summarizing operator and
-/// render path metrics. Width is currently unused (future wrapping).
 pub fn build_overlay_lines(state: &EditorState, width: u16) -> Vec<String> {
+    if let Some(menu) = state.command_line.wildmenu() {
+        return vec![menu.row(width as usize)];
+    }
     let mode = state.overlay_mode();
     match mode {
         OverlayMode::None => Vec::new(),
-        OverlayMode::Metrics { lines } => build_metrics_lines(state, width, lines as usize),
+        OverlayMode::Metrics { section } => build_metrics_lines(state, width, section),
+        OverlayMode::Message { lines } => state
+            .message_lines
+            .iter()
+            .take(lines as usize)
+            .cloned()
+            .collect(),
     }
 }
 
@@ -24,19 +37,34 @@ pub fn overlay_line_count(state: &EditorState, width: u16) -> u16 {
     build_overlay_lines(state, width).len() as u16
 }
 
-/// Paint overlay rows (always dirty) into a BatchWriter for partial render paths.
+/// Paint overlay rows into a BatchWriter for partial render paths, skipping
+/// rows equal to the ones in `painted` (what the previous frame left on
+/// screen, empty when unknown); `painted` is updated to the new rows.
 /// Assumes caller already ensured `h > 0` and will paint status line afterwards.
-pub fn paint_overlay_rows_batch(writer: &mut BatchWriter, state: &EditorState, w: u16, h: u16) {
+pub fn paint_overlay_rows_batch(
+    writer: &mut BatchWriter,
+    state: &EditorState,
+    w: u16,
+    h: u16,
+    painted: &mut Vec<String>,
+) {
     if h == 0 {
         return;
     }
     let lines = build_overlay_lines(state, w);
     let count = lines.len() as u16;
     if count == 0 || count >= h {
+        painted.clear();
         return;
     }
+    if painted.len() != lines.len() {
+        painted.clear();
+    }
     let first_row = h - 1 - count; // top overlay row
     for (i, line) in lines.iter().enumerate() {
+        if painted.get(i) == Some(line) {
+            continue;
+        }
         let y = first_row + i as u16;
         writer.move_to(0, y);
         writer.clear_line(0, y);
@@ -51,54 +79,156 @@ pub fn paint_overlay_rows_batch(writer: &mut BatchWriter, state: &EditorState, w
             byte = next;
         }
     }
+    *painted = lines;
 }
 
-fn build_metrics_lines(state: &EditorState, _width: u16, max: usize) -> Vec<String> {
-    let mut out = Vec::new();
-    if max == 0 {
-        return out;
-    }
-    let op = state.operator_metrics_snapshot();
-    out.push(format!(
-        "ops d:{} y:{} c:{} reg_w:{} rot:{}",
-        op.operator_delete,
-        op.operator_yank,
-        op.operator_change,
-        op.register_writes,
-        op.numbered_ring_rotations
-    ));
-    if out.len() >= max {
-        return out;
-    }
-    if let Some(rp) = state.last_render_path {
-        out.push(format!(
-            "rp full:{} part:{} cur:{} lines:{} dirty:{} cand:{} rep:{} cells:{} statSkip:{}",
-            rp.full_frames,
-            rp.partial_frames,
-            rp.cursor_only_frames,
-            rp.lines_frames,
-            rp.dirty_lines_marked,
-            rp.dirty_candidate_lines,
-            rp.dirty_lines_repainted,
-            rp.cells_printed,
-            rp.status_skipped
-        ));
-    } else {
-        out.push("rp <none>".to_string());
-    }
-    if out.len() >= max {
-        return out;
+/// Header row, then the fields of `section`, padded with blank rows to the
+/// height of the largest section.
+fn build_metrics_lines(state: &EditorState, width: u16, section: MetricsSection) -> Vec<String> {
+    let height = MetricsSection::ALL
+        .into_iter()
+        .map(|s| wrap_fields(&section_fields(state, s), width).len())
+        .max()
+        .unwrap_or(0);
+    let mut out = vec![format!(
+        "metrics {}/{} {} (:metrics next|prev)",
+        section.index() + 1,
+        MetricsSection::ALL.len(),
+        section.name()
+    )];
+    out.extend(wrap_fields(&section_fields(state, section), width));
+    out.resize(height + 1, String::new());
+    out
+}
+
+/// `key:value` fields of `section`, from the snapshots the runtime copied
+/// into the state after the last frame.
+fn section_fields(state: &EditorState, section: MetricsSection) -> Vec<String> {
+    match section {
+        MetricsSection::Render => match state.last_render_path {
+            Some(rp) => vec![
+                format!("full:{}", rp.full_frames),
+                format!("part:{}", rp.partial_frames),
+                format!("cur:{}", rp.cursor_only_frames),
+                format!("lines:{}", rp.lines_frames),
+                format!("esc:{}", rp.escalated_large_set),
+                format!("resize:{}", rp.resize_invalidations),
+                format!("dirty:{}", rp.dirty_lines_marked),
+                format!("cand:{}", rp.dirty_candidate_lines),
+                format!("rep:{}", rp.dirty_lines_repainted),
+                format!("prints:{}", rp.print_commands),
+                format!("cells:{}", rp.cells_printed),
+                format!("shift:{}", rp.scroll_region_shifts),
+                format!("saved:{}", rp.scroll_region_lines_saved),
+                format!("degr:{}", rp.scroll_shift_degraded_full),
+                format!("trim:{}/{}", rp.trim_success, rp.trim_attempts),
+                format!("cols:{}", rp.cols_saved_total),
+                format!("statSkip:{}", rp.status_skipped),
+                format!("coal:{}/{}", rp.coalesced_frames, rp.coalesced_decisions),
+                latency_field("fullLat", rp.full_latency),
+                latency_field("curLat", rp.cursor_only_latency),
+                latency_field("linesLat", rp.lines_latency),
+                latency_field("scrollLat", rp.scroll_shift_latency),
+            ],
+            None => vec!["<none>".to_string()],
+        },
+        MetricsSection::Scheduler => match state.last_render_delta {
+            Some(rd) => vec![
+                format!("f:{}", rd.full),
+                format!("l:{}", rd.lines),
+                format!("sc:{}", rd.scroll),
+                format!("st:{}", rd.status_line),
+                format!("cur:{}", rd.cursor_only),
+                format!("collapsed:{}", rd.collapsed_scroll),
+                format!("suppressed:{}", rd.suppressed_scroll),
+                format!("escalated:{}", rd.scroll_escalated),
+                format!("sem:{}", rd.semantic_frames),
+            ],
+            None => vec!["<none>".to_string()],
+        },
+        MetricsSection::Operators => {
+            let op = state.operator_metrics_snapshot();
+            vec![
+                format!("d:{}", op.operator_delete),
+                format!("y:{}", op.operator_yank),
+                format!("c:{}", op.operator_change),
+                format!("reg_w:{}", op.register_writes),
+                format!("rot:{}", op.numbered_ring_rotations),
+            ]
+        }
+        MetricsSection::Input => match state.last_input_telemetry {
+            Some(it) => vec![
+                format!("keys:{}", it.keypress_total),
+                format!("repeat:{}", it.keypress_repeat),
+                format!("pastes:{}", it.paste_sessions),
+                format!("chunks:{}", it.paste_chunks),
+                format!("bytes:{}", it.paste_bytes),
+                format!("sendFail:{}", it.send_failures),
+                format!("blocking:{}", it.blocking_sends),
+                format!("preferred:{}", it.input_preferred),
+                format!("coalesced:{}", it.motions_coalesced),
+                format!("resizeSkip:{}", it.resizes_suppressed),
+            ],
+            None => vec!["<none>".to_string()],
+        },
+        MetricsSection::Profile if !state.profile_frames => {
+            vec!["off (:metrics profile)".to_string()]
+        }
+        MetricsSection::Profile if state.last_frame_profile.is_empty() => {
+            vec!["<none>".to_string()]
+        }
+        // One field per frame, newest last: total = hash + compose + write
+        // + flush.
+        MetricsSection::Profile => state
+            .last_frame_profile
+            .iter()
+            .map(|f| {
+                format!(
+                    "{}:{}us=h{}+c{}+w{}+f{}",
+                    f.path,
+                    f.total_ns / 1000,
+                    f.hash_ns / 1000,
+                    f.compose_ns / 1000,
+                    f.write_ns / 1000,
+                    f.flush_ns / 1000
+                )
+            })
+            .collect(),
     }
-    if let Some(rd) = state.last_render_delta {
-        out.push(format!(
-            "delta f:{} l:{} sc:{} st:{} cur:{} sem:{}",
-            rd.full, rd.lines, rd.scroll, rd.status_line, rd.cursor_only, rd.semantic_frames
-        ));
+}
+
+/// `key:p50/p95/p99us` of one render path (`key:-` before its first frame).
+fn latency_field(key: &str, lat: core_state::LatencyLite) -> String {
+    if lat.samples == 0 {
+        return format!("{key}:-");
     }
-    if out.len() > max {
-        out.truncate(max);
+    format!(
+        "{key}:{}/{}/{}us",
+        lat.p50_ns / 1000,
+        lat.p95_ns / 1000,
+        lat.p99_ns / 1000
+    )
+}
+
+/// Greedy fill of space-separated `fields` into rows of `width` columns
+/// (unbounded when 0); a field wider than a row gets a row of its own.
+fn wrap_fields(fields: &[String], width: u16) -> Vec<String> {
+    let width = if width == 0 {
+        usize::MAX
+    } else {
+        width as usize
+    };
+    let mut rows: Vec<String> = Vec::new();
+    for field in fields {
+        match rows.last_mut() {
+            Some(row) if row.len() + 1 + field.len() <= width => {
+                row.push(' ');
+                row.push_str(field);
+            }
+            _ => rows.push(field.clone()),
+        }
     }
-    out
+    rows
 }
 
 #[cfg(test)]
@@ -112,12 +242,99 @@ mod tests {
         assert!(build_overlay_lines(&st, 80).is_empty());
     }
 
+    #[test]
+    fn message_overlay_shows_message_lines() {
+        let mut st = core_state::EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        st.show_message_lines(vec!["one".into(), "two".into(), "three".into()], 2);
+        let lines = build_overlay_lines(&st, 80);
+        assert_eq!(lines, vec!["-- 2 more lines --", "three"]);
+        assert!(st.dismiss_message_lines());
+        assert!(build_overlay_lines(&st, 80).is_empty());
+    }
+
+    #[test]
+    fn wildmenu_row_takes_over_the_overlay() {
+        let mut st = core_state::EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        st.show_message_lines(vec!["one".into(), "two".into()], 2);
+        st.command_line.begin();
+        let matches = vec!["a.txt".to_string(), "b/".to_string()];
+        st.command_line
+            .open_wildmenu(core_state::Wildmenu::new(":e ", "", matches), false);
+        assert_eq!(build_overlay_lines(&st, 80), vec![" [a.txt] b/ "]);
+        st.command_line.push_char('x');
+        assert_eq!(overlay_line_count(&st, 80), 2);
+    }
+
     #[test]
     fn metrics_overlay_populates() {
         let mut st = core_state::EditorState::new(Buffer::from_str("t", "a\n").unwrap());
-        st.toggle_metrics_overlay(core_state::METRICS_OVERLAY_DEFAULT_LINES);
+        st.toggle_metrics_overlay();
+        st.show_metrics_section(MetricsSection::Operators);
         let lines = build_overlay_lines(&st, 80);
         assert!(!lines.is_empty());
-        assert!(lines[0].starts_with("ops"));
+        assert!(lines[0].starts_with("metrics 3/5 operators"));
+        assert!(lines[1].starts_with("d:0 y:0 c:0"));
+    }
+
+    #[test]
+    fn metrics_overlay_wraps_and_keeps_its_height_across_sections() {
+        let mut st = core_state::EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        st.last_render_path = Some(core_state::RenderPathSnapshotLite::default());
+        st.toggle_metrics_overlay();
+        let wide = build_overlay_lines(&st, 200);
+        let narrow = build_overlay_lines(&st, 40);
+        assert!(narrow.len() > wide.len());
+        assert!(narrow.iter().all(|l| l.len() <= 40 || !l.contains(' ')));
+        for section in MetricsSection::ALL {
+            st.show_metrics_section(section);
+            assert_eq!(overlay_line_count(&st, 40), narrow.len() as u16);
+        }
+    }
+
+    #[test]
+    fn render_and_profile_sections_show_latencies() {
+        let mut st = core_state::EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        st.last_render_path = Some(core_state::RenderPathSnapshotLite {
+            full_latency: core_state::LatencyLite {
+                p50_ns: 12_345,
+                p95_ns: 40_000,
+                p99_ns: 99_999,
+                samples: 3,
+            },
+            ..Default::default()
+        });
+        st.toggle_metrics_overlay();
+        let render = build_overlay_lines(&st, 400).join(" ");
+        assert!(render.contains("fullLat:12/40/99us curLat:-"), "{render}");
+
+        st.show_metrics_section(MetricsSection::Profile);
+        assert_eq!(build_overlay_lines(&st, 80)[1], "off (:metrics profile)");
+        st.profile_frames = true;
+        st.last_frame_profile = vec![core_state::FrameStagesLite {
+            path: "lines",
+            total_ns: 90_000,
+            hash_ns: 5_000,
+            compose_ns: 40_000,
+            write_ns: 30_000,
+            flush_ns: 15_000,
+        }];
+        assert_eq!(build_overlay_lines(&st, 80)[1], "lines:90us=h5+c40+w30+f15");
+    }
+
+    #[test]
+    fn partial_paint_skips_unchanged_rows() {
+        let mut st = core_state::EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        st.show_message_lines(vec!["one".into(), "two".into()], 2);
+        let mut painted = Vec::new();
+        let mut first = BatchWriter::new();
+        paint_overlay_rows_batch(&mut first, &st, 80, 10, &mut painted);
+        assert_eq!(first.cells_printed, 6);
+        let mut again = BatchWriter::new();
+        paint_overlay_rows_batch(&mut again, &st, 80, 10, &mut painted);
+        assert_eq!(again.cells_printed, 0);
+        st.show_message_lines(vec!["one".into(), "2".into()], 2);
+        let mut changed = BatchWriter::new();
+        paint_overlay_rows_batch(&mut changed, &st, 80, 10, &mut painted);
+        assert_eq!(changed.cells_printed, 1);
     }
 }
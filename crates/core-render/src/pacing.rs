@@ -0,0 +1,83 @@
+//! Frame pacing: coalesce render decisions during input bursts.
+//!
+//! Holding a key down (or pasting outside bracketed paste) can queue input
+//! faster than frames are drawn. Rendering after every event then paints
+//! intermediate states nobody sees. The pacer holds a ready frame back while
+//! more input is already queued and the previous frame is younger than the
+//! budget; the held damage stays in the scheduler and merges into the next
+//! frame. Once the queue drains, or the budget expires, the frame is drawn,
+//! so the screen always ends on the final state and never falls more than
+//! one budget behind.
+
+use std::time::{Duration, Instant};
+
+/// Minimum spacing of frames while input is queued (~120 fps).
+pub const FRAME_BUDGET: Duration = Duration::from_millis(8);
+
+#[derive(Debug)]
+pub struct FramePacer {
+    budget: Duration,
+    last_frame: Option<Instant>,
+    /// Decisions held back since the last frame.
+    held: u64,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(FRAME_BUDGET)
+    }
+}
+
+impl FramePacer {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            last_frame: None,
+            held: 0,
+        }
+    }
+
+    /// Whether the frame ready at `now` should wait for queued input
+    /// (`input_pending`).
+    pub fn hold(&mut self, now: Instant, input_pending: bool) -> bool {
+        let hold = input_pending
+            && self
+                .last_frame
+                .is_some_and(|last| now.saturating_duration_since(last) < self.budget);
+        if hold {
+            self.held += 1;
+        }
+        hold
+    }
+
+    /// Record a frame drawn at `now`; returns how many held decisions it
+    /// covers.
+    pub fn frame(&mut self, now: Instant) -> u64 {
+        self.last_frame = Some(now);
+        std::mem::take(&mut self.held)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_only_queued_input_within_the_budget() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(Duration::from_millis(8));
+        // The first frame is never held.
+        assert!(!pacer.hold(start, true));
+        assert_eq!(pacer.frame(start), 0);
+        let soon = start + Duration::from_millis(2);
+        assert!(pacer.hold(soon, true));
+        assert!(pacer.hold(soon, true));
+        // A drained queue draws at once, covering both held decisions.
+        assert!(!pacer.hold(soon, false));
+        assert_eq!(pacer.frame(soon), 2);
+        // So does an expired budget, however much input is queued.
+        assert!(pacer.hold(soon + Duration::from_millis(1), true));
+        assert!(!pacer.hold(soon + Duration::from_millis(8), true));
+        assert_eq!(pacer.frame(soon + Duration::from_millis(8)), 1);
+    }
+}
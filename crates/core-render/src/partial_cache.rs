@@ -7,10 +7,14 @@
 //!
 //! Hashing strategy: (len, ahash64) on raw UTF-8 line content with trailing
 //! newline removed. Length included to further reduce collision probability
-//! and allow short-circuit mismatch detection.
+//! and allow short-circuit mismatch detection. Search match ranges painted on
+//! a line (`hlsearch`) are hashed with its text, so a row whose highlighting
+//! changed never looks unchanged to a partial frame.
 
+use crate::wrap::ScreenRow;
 use ahash::AHasher;
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
 /// Snapshot hash metadata for a single visible line.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,6 +24,13 @@ pub struct ViewportLineHash {
 }
 
 impl ViewportLineHash {
+    /// Matches no real line (no line is `usize::MAX` bytes long); marks a row
+    /// that must repaint although its text is unchanged.
+    pub const STALE: ViewportLineHash = ViewportLineHash {
+        hash: 0,
+        len: usize::MAX,
+    };
+
     pub fn new(hash: u64, len: usize) -> Self {
         Self { hash, len }
     }
@@ -40,6 +51,14 @@ pub struct PartialCache {
     pub prev_text: Vec<Option<String>>,
     /// Previous frame's cursor line (for repaint of old cursor span). None if unknown or no prior frame.
     pub last_cursor_line: Option<usize>,
+    /// Previous frame's `cursorcolumn` stripe (screen columns); None when not shown.
+    pub last_cursor_col: Option<(u16, u16)>,
+    /// Gutter width the cached rows were painted with (0 when no gutter).
+    pub gutter_width: u16,
+    /// Text rows of the last frame (a line owns several rows under
+    /// `'wrap'`, a closed fold one row for all its lines); see
+    /// `crate::wrap::rows_match`.
+    pub rows: Vec<ScreenRow>,
 }
 
 impl PartialCache {
@@ -56,6 +75,9 @@ impl PartialCache {
         self.line_hashes.clear();
         self.prev_text.clear();
         self.last_cursor_line = None;
+        self.last_cursor_col = None;
+        self.gutter_width = 0;
+        self.rows.clear();
     }
 
     /// Reset cache to represent a new viewport slice (caller supplies vector capacity hint).
@@ -91,6 +113,21 @@ impl PartialCache {
         }
     }
 
+    /// Hash of a line as displayed: its text plus the byte ranges of its
+    /// search matches. Equals `compute_hash` for a line without matches.
+    pub fn compute_display_hash(line: &str, matches: &[Range<usize>]) -> ViewportLineHash {
+        if matches.is_empty() {
+            return Self::compute_hash(line);
+        }
+        let mut hasher = AHasher::default();
+        line.hash(&mut hasher);
+        matches.hash(&mut hasher);
+        ViewportLineHash {
+            hash: hasher.finish(),
+            len: line.len(),
+        }
+    }
+
     /// Access an entry by relative viewport row.
     pub fn get(&self, row: usize) -> Option<ViewportLineHash> {
         self.line_hashes.get(row).copied()
@@ -109,6 +146,21 @@ impl PartialCache {
         self.prev_text[row] = Some(text);
     }
 
+    /// Force buffer line `line` (if visible) to repaint on the next partial
+    /// frame: its hash no longer matches and its previous text is forgotten,
+    /// so the whole row (gutter included) is rewritten.
+    pub fn invalidate_line(&mut self, line: usize) {
+        let Some(row) = line.checked_sub(self.viewport_start) else {
+            return;
+        };
+        if let Some(entry) = self.line_hashes.get_mut(row) {
+            *entry = ViewportLineHash::STALE;
+        }
+        if let Some(text) = self.prev_text.get_mut(row) {
+            *text = None;
+        }
+    }
+
     /// Phase 4 Step 11: shift cache in-place for a scroll-region shift.
     ///
     /// Arguments:
@@ -116,21 +168,22 @@ impl PartialCache {
     ///   negative when viewport moved up (new lines enter at top).
     /// * `new_first` – new viewport starting buffer line.
     /// * `visible_rows` – number of text rows (excluding status line) represented in the cache.
-    /// * `buf_line_provider` – closure returning an optional raw buffer line by absolute line index.
+    /// * `line_hash` – closure returning the display hash of a buffer line by absolute line index
+    ///   (rows past the buffer end hash as empty lines).
     ///
     /// Behavior:
     /// * Reuses existing hash entries for lines that remain visible by shifting them in-place.
     /// * Recomputes hashes only for entering lines (top or bottom segment depending on delta).
-    /// * If `delta` magnitude >= visible_rows, caller should have degraded to full render
-    ///   before invoking (we assert to catch misuse in tests / debug builds).
+    /// * Any magnitude is accepted: a shift of `visible_rows` or more keeps no
+    ///   row, and every row is rehashed as entering (page motions).
     pub fn shift_for_scroll<F>(
         &mut self,
         delta: i32,
         new_first: usize,
         visible_rows: usize,
-        mut buf_line_provider: F,
+        mut line_hash: F,
     ) where
-        F: FnMut(usize) -> Option<String>,
+        F: FnMut(usize) -> ViewportLineHash,
     {
         debug_assert!(
             visible_rows == self.line_hashes.len(),
@@ -141,15 +194,10 @@ impl PartialCache {
             "prev_text size mismatch"
         );
         debug_assert!(delta != 0, "no-op delta passed to shift_for_scroll");
-        let abs = delta.unsigned_abs() as usize;
-        debug_assert!(
-            abs < visible_rows,
-            "degenerate shift should have escalated to full"
-        );
+        let entering = (delta.unsigned_abs() as usize).min(visible_rows);
 
         if delta > 0 {
             // Scroll down: viewport moved down, content moved up, new lines at bottom.
-            let entering = abs;
             // Shift existing reused lines up.
             for i in 0..(visible_rows - entering) {
                 let src = i + entering;
@@ -159,19 +207,11 @@ impl PartialCache {
             // Recompute hashes for entering lines (bottom segment).
             for i in 0..entering {
                 let row = visible_rows - entering + i;
-                let buf_line_index = new_first + row;
-                let vh = if let Some(raw_line) = buf_line_provider(buf_line_index) {
-                    let content_trim: &str = raw_line.trim_end_matches(['\n', '\r']);
-                    PartialCache::compute_hash(content_trim)
-                } else {
-                    PartialCache::compute_hash("")
-                };
-                self.line_hashes[row] = vh;
+                self.line_hashes[row] = line_hash(new_first + row);
                 self.prev_text[row] = None; // unknown until painted
             }
         } else {
             // Scroll up: new lines entering at top.
-            let entering = abs;
             // Shift existing reused lines down (iterate from bottom to avoid overwrite).
             for i in (0..(visible_rows - entering)).rev() {
                 let dst = i + entering;
@@ -180,14 +220,7 @@ impl PartialCache {
             }
             // Recompute top entering line hashes.
             for i in 0..entering {
-                let buf_line_index = new_first + i;
-                let vh = if let Some(raw_line) = buf_line_provider(buf_line_index) {
-                    let content_trim: &str = raw_line.trim_end_matches(['\n', '\r']);
-                    PartialCache::compute_hash(content_trim)
-                } else {
-                    PartialCache::compute_hash("")
-                };
-                self.line_hashes[i] = vh;
+                self.line_hashes[i] = line_hash(new_first + i);
                 self.prev_text[i] = None;
             }
         }
@@ -207,6 +240,18 @@ mod tests {
         assert_ne!(a, b, "different content must produce different (hash,len)");
     }
 
+    #[test]
+    fn display_hash_includes_search_matches() {
+        assert_eq!(
+            PartialCache::compute_display_hash("foo", &[]),
+            PartialCache::compute_hash("foo")
+        );
+        assert_ne!(
+            PartialCache::compute_display_hash("foo", &[0..1, 2..3]),
+            PartialCache::compute_hash("foo")
+        );
+    }
+
     #[test]
     fn reset_and_push_sequence() {
         let mut c = PartialCache::new();
@@ -219,4 +264,26 @@ mod tests {
         assert_eq!(c.prev_text.len(), 2);
         assert!(c.get(1).is_some());
     }
+
+    #[test]
+    fn shift_for_scroll_handles_page_deltas() {
+        let text = |line: usize| format!("line {line}");
+        let hash = |line: usize| PartialCache::compute_hash(&text(line));
+        let mut c = PartialCache::new();
+        c.reset(0, 80, 4);
+        for line in 0..4 {
+            c.push_line(hash(line));
+            c.set_prev_text(line, text(line));
+        }
+        // Half a page down: two rows shift up, two enter.
+        c.shift_for_scroll(2, 2, 4, hash);
+        assert_eq!(c.get(0), Some(hash(2)));
+        assert_eq!(c.get_prev_text(1), Some("line 3"));
+        assert_eq!(c.get(3), Some(hash(5)));
+        assert_eq!(c.get_prev_text(3), None);
+        // A page or more keeps nothing: every row enters.
+        c.shift_for_scroll(-7, 0, 4, hash);
+        assert_eq!(c.viewport_start, 0);
+        assert!((0..4).all(|row| c.get(row) == Some(hash(row)) && c.get_prev_text(row).is_none()));
+    }
 }
@@ -89,7 +89,10 @@ pub fn classify_viewport_changes(
         } else {
             line_content.as_str()
         };
-        let h = crate::partial_cache::PartialCache::compute_hash(trimmed);
+        let h = crate::partial_cache::PartialCache::compute_display_hash(
+            trimmed,
+            &crate::style::search_matches(state, trimmed),
+        );
         if cold {
             cache.push_line(h);
             changed.push(line_idx);
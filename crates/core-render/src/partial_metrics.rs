@@ -7,6 +7,7 @@
 //! Keeping them separate preserves diagnostic ability to correlate semantic
 //! intent vs chosen render strategy.
 
+use crate::latency::{FrameLatency, LatencyPercentiles};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, Default)]
@@ -29,10 +30,6 @@ pub struct RenderPathMetrics {
     pub dirty_candidate_lines: AtomicU64,
     /// Lines physically repainted (subset of candidates; includes forced cursor lines).
     pub dirty_lines_repainted: AtomicU64,
-    /// Duration (ns) of the most recent full frame render.
-    pub last_full_render_ns: AtomicU64,
-    /// Duration (ns) of the most recent partial frame render (cursor-only or lines).
-    pub last_partial_render_ns: AtomicU64,
     /// Number of terminal Print commands emitted after batching (Step 7 baseline).
     pub print_commands: AtomicU64,
     /// Logical cells printed (batched plain chars + styled/multi-char units).
@@ -51,6 +48,12 @@ pub struct RenderPathMetrics {
     pub cols_saved_total: AtomicU64,
     /// Status line repaints skipped because content unchanged (Phase 4 Step 13).
     pub status_skipped: AtomicU64,
+    /// Render decisions held back by frame pacing while input was queued.
+    pub coalesced_decisions: AtomicU64,
+    /// Frames that absorbed at least one held decision.
+    pub coalesced_frames: AtomicU64,
+    /// Rolling frame durations per render path.
+    pub latency: FrameLatency,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -64,8 +67,6 @@ pub struct RenderPathMetricsSnapshot {
     pub dirty_lines_marked: u64,
     pub dirty_candidate_lines: u64,
     pub dirty_lines_repainted: u64,
-    pub last_full_render_ns: u64,
-    pub last_partial_render_ns: u64,
     pub print_commands: u64,
     pub cells_printed: u64,
     pub scroll_region_shifts: u64,
@@ -75,6 +76,12 @@ pub struct RenderPathMetricsSnapshot {
     pub trim_success: u64,
     pub cols_saved_total: u64,
     pub status_skipped: u64,
+    pub coalesced_decisions: u64,
+    pub coalesced_frames: u64,
+    pub full_latency: LatencyPercentiles,
+    pub cursor_only_latency: LatencyPercentiles,
+    pub lines_latency: LatencyPercentiles,
+    pub scroll_shift_latency: LatencyPercentiles,
 }
 
 impl RenderPathMetrics {
@@ -89,8 +96,6 @@ impl RenderPathMetrics {
             dirty_lines_marked: self.dirty_lines_marked.load(Ordering::Relaxed),
             dirty_candidate_lines: self.dirty_candidate_lines.load(Ordering::Relaxed),
             dirty_lines_repainted: self.dirty_lines_repainted.load(Ordering::Relaxed),
-            last_full_render_ns: self.last_full_render_ns.load(Ordering::Relaxed),
-            last_partial_render_ns: self.last_partial_render_ns.load(Ordering::Relaxed),
             print_commands: self.print_commands.load(Ordering::Relaxed),
             cells_printed: self.cells_printed.load(Ordering::Relaxed),
             scroll_region_shifts: self.scroll_region_shifts.load(Ordering::Relaxed),
@@ -100,6 +105,12 @@ impl RenderPathMetrics {
             trim_success: self.trim_success.load(Ordering::Relaxed),
             cols_saved_total: self.cols_saved_total.load(Ordering::Relaxed),
             status_skipped: self.status_skipped.load(Ordering::Relaxed),
+            coalesced_decisions: self.coalesced_decisions.load(Ordering::Relaxed),
+            coalesced_frames: self.coalesced_frames.load(Ordering::Relaxed),
+            full_latency: self.latency.full.percentiles(),
+            cursor_only_latency: self.latency.cursor_only.percentiles(),
+            lines_latency: self.latency.lines.percentiles(),
+            scroll_shift_latency: self.latency.scroll_shift.percentiles(),
         }
     }
 }
@@ -0,0 +1,337 @@
+//! Floating popup layer: small bordered windows (completion menus, hover
+//! docs, which-key hints) composited above the text grid.
+//!
+//! Popups never enter the frames the render paths diff against; the engine
+//! paints them after every path's own emission, in ascending `z` order
+//! (equal `z` keeps show order), so whatever a partial path repainted
+//! underneath is covered again.
+//!
+//! Damage is tracked here, independently of `DirtyLinesTracker`: showing or
+//! updating a popup marks the layer dirty, and dismissing (or shrinking) one
+//! records the screen rectangle it covered so the engine can restore the
+//! obscured cells from the underlying frame.
+
+use crate::{CellFlags, Frame};
+use core_model::LayoutRegion;
+use core_state::hover::HOVER_ROWS;
+use core_state::{Completion, Hover};
+use core_text::grapheme;
+
+/// Most matches a completion popup lists at once; the list scrolls to keep
+/// the selection in view.
+pub const COMPLETION_ROWS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PopupId(pub u32);
+
+/// Content and placement of one popup. The inner size follows the content;
+/// the border adds one cell on every side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Popup {
+    /// Top-left corner of the border. Popups that would overflow the screen
+    /// are shifted back inside it.
+    pub x: u16,
+    pub y: u16,
+    pub z: i32,
+    pub lines: Vec<String>,
+    /// Line drawn in reverse video (the current completion item).
+    pub selected: Option<usize>,
+}
+
+impl Popup {
+    pub fn new(x: u16, y: u16, lines: Vec<String>) -> Self {
+        Self {
+            x,
+            y,
+            z: 0,
+            lines,
+            selected: None,
+        }
+    }
+
+    /// Popup listing `completion`'s matches (text, then kind) whose text
+    /// column starts at `x`, below row `y` when the text area (`bottom`
+    /// rows) has room for it, else above. `None` without matches.
+    pub fn completion(completion: &Completion, x: u16, y: u16, bottom: u16) -> Option<Self> {
+        let count = completion.match_count();
+        if count == 0 {
+            return None;
+        }
+        let first = completion
+            .selected()
+            .map_or(0, |s| (s + 1).saturating_sub(COMPLETION_ROWS));
+        let shown: Vec<_> = completion
+            .matches()
+            .skip(first)
+            .take(COMPLETION_ROWS)
+            .collect();
+        let width = |text: &str| grapheme::visual_col(text, text.len());
+        let text_width = shown.iter().map(|m| width(&m.text)).max().unwrap_or(0);
+        let lines = shown
+            .iter()
+            .map(|m| match &m.kind {
+                Some(kind) => {
+                    let pad = text_width - width(&m.text);
+                    format!("{}{} {kind}", m.text, " ".repeat(pad))
+                }
+                None => m.text.clone(),
+            })
+            .collect::<Vec<_>>();
+        let height = lines.len() as u16 + 2;
+        let top = if y + 1 + height <= bottom {
+            y + 1
+        } else {
+            y.saturating_sub(height)
+        };
+        let mut popup = Popup::new(x.saturating_sub(1), top, lines);
+        popup.selected = completion.selected().map(|s| s - first);
+        Some(popup)
+    }
+
+    /// Popup showing `hover`'s lines from its scroll position, below the
+    /// cursor cell (`x`, `y`) when the text area (`bottom` rows) has room
+    /// for all of them, else wherever more fit. At most `HOVER_ROWS` tall,
+    /// and as wide as the widest line so scrolling keeps the size.
+    pub fn hover(hover: &Hover, x: u16, y: u16, bottom: u16) -> Self {
+        let below = bottom.saturating_sub(y + 1 + 2) as usize;
+        let above = y.saturating_sub(2) as usize;
+        let wanted = hover.lines.len().min(HOVER_ROWS);
+        let rows = if below >= wanted || below >= above {
+            wanted.min(below)
+        } else {
+            wanted.min(above)
+        }
+        .max(1);
+        let first = hover.scroll.min(hover.lines.len().saturating_sub(rows));
+        let width = |text: &str| grapheme::visual_col(text, text.len());
+        let widest = hover.lines.iter().map(|l| width(l)).max().unwrap_or(0);
+        let lines = hover.lines[first..]
+            .iter()
+            .take(rows)
+            .map(|line| format!("{line}{}", " ".repeat(widest - width(line))))
+            .collect();
+        let top = if rows <= below {
+            y + 1
+        } else {
+            y.saturating_sub(rows as u16 + 2)
+        };
+        Popup::new(x, top, lines)
+    }
+
+    /// Screen rectangle (border included) on a `w` x `h` screen.
+    pub fn region(&self, w: u16, h: u16) -> LayoutRegion {
+        let inner_w = self
+            .lines
+            .iter()
+            .map(|l| grapheme::visual_col(l, l.len()))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let width = (inner_w as u16).saturating_add(2).min(w);
+        let height = (self.lines.len().max(1) as u16).saturating_add(2).min(h);
+        LayoutRegion::new(self.x.min(w - width), self.y.min(h - height), width, height)
+    }
+
+    /// Paint the bordered popup into `frame`, clipped to the frame.
+    pub fn paint(&self, frame: &mut Frame) {
+        let r = self.region(frame.width, frame.height);
+        if r.width < 2 || r.height < 2 {
+            return;
+        }
+        let (right, bottom) = (r.x + r.width - 1, r.y + r.height - 1);
+        for x in r.x + 1..right {
+            frame.set_cluster(x, r.y, "─", 1, CellFlags::empty());
+            frame.set_cluster(x, bottom, "─", 1, CellFlags::empty());
+        }
+        for y in r.y + 1..bottom {
+            frame.set_cluster(r.x, y, "│", 1, CellFlags::empty());
+            frame.set_cluster(right, y, "│", 1, CellFlags::empty());
+        }
+        frame.set_cluster(r.x, r.y, "┌", 1, CellFlags::empty());
+        frame.set_cluster(right, r.y, "┐", 1, CellFlags::empty());
+        frame.set_cluster(r.x, bottom, "└", 1, CellFlags::empty());
+        frame.set_cluster(right, bottom, "┘", 1, CellFlags::empty());
+        for (i, y) in (r.y + 1..bottom).enumerate() {
+            let flags = if self.selected == Some(i) {
+                CellFlags::REVERSE
+            } else {
+                CellFlags::empty()
+            };
+            let line = self.lines.get(i).map_or("", String::as_str);
+            let mut byte = 0usize;
+            let mut x = r.x + 1;
+            while byte < line.len() {
+                let next = grapheme::next_boundary(line, byte);
+                let cluster = &line[byte..next];
+                let width = grapheme::cluster_width(cluster).max(1) as u16;
+                if x + width > right {
+                    break;
+                }
+                frame.set_cluster(x, y, cluster, width, flags);
+                x += width;
+                byte = next;
+            }
+            for fill in x..right {
+                frame.set_cluster(fill, y, " ", 1, flags);
+            }
+        }
+    }
+}
+
+/// Damage accumulated since the last `take_damage`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PopupDamage {
+    /// Rectangles no longer (fully) covered by the popup that was there.
+    pub exposed: Vec<LayoutRegion>,
+    /// A popup was shown or changed.
+    pub changed: bool,
+}
+
+impl PopupDamage {
+    pub fn is_empty(&self) -> bool {
+        self.exposed.is_empty() && !self.changed
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PopupLayer {
+    popups: Vec<(PopupId, Popup)>,
+    next_id: u32,
+    damage: PopupDamage,
+    /// Screen size used to turn popups into exposed rectangles.
+    screen: (u16, u16),
+}
+
+impl PopupLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn show(&mut self, popup: Popup) -> PopupId {
+        let id = PopupId(self.next_id);
+        self.next_id += 1;
+        self.popups.push((id, popup));
+        self.damage.changed = true;
+        id
+    }
+
+    /// Replace the content of popup `id`; the area it no longer covers is
+    /// exposed. Returns false for an unknown id.
+    pub fn update(&mut self, id: PopupId, popup: Popup) -> bool {
+        let screen = self.screen;
+        let Some((_, slot)) = self.popups.iter_mut().find(|(p, _)| *p == id) else {
+            return false;
+        };
+        let old = slot.region(screen.0, screen.1);
+        if old != popup.region(screen.0, screen.1) {
+            self.damage.exposed.push(old);
+        }
+        *slot = popup;
+        self.damage.changed = true;
+        true
+    }
+
+    /// Remove popup `id`, exposing the cells it covered.
+    pub fn dismiss(&mut self, id: PopupId) -> bool {
+        let Some(idx) = self.popups.iter().position(|(p, _)| *p == id) else {
+            return false;
+        };
+        let (_, popup) = self.popups.remove(idx);
+        self.damage
+            .exposed
+            .push(popup.region(self.screen.0, self.screen.1));
+        true
+    }
+
+    pub fn dismiss_all(&mut self) {
+        let ids: Vec<PopupId> = self.popups.iter().map(|(id, _)| *id).collect();
+        for id in ids {
+            self.dismiss(id);
+        }
+    }
+
+    pub fn get(&self, id: PopupId) -> Option<&Popup> {
+        self.popups.iter().find(|(p, _)| *p == id).map(|(_, p)| p)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.popups.is_empty()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.damage.is_empty()
+    }
+
+    /// Record the screen size popups are placed on (clamping and exposed
+    /// rectangles depend on it).
+    pub fn set_screen(&mut self, w: u16, h: u16) {
+        self.screen = (w, h);
+    }
+
+    pub fn take_damage(&mut self) -> PopupDamage {
+        std::mem::take(&mut self.damage)
+    }
+
+    /// Popups bottom-most first.
+    pub fn ordered(&self) -> Vec<&Popup> {
+        let mut out: Vec<&Popup> = self.popups.iter().map(|(_, p)| p).collect();
+        out.sort_by_key(|p| p.z);
+        out
+    }
+
+    /// Composite every popup into `frame` in z order.
+    pub fn paint(&self, frame: &mut Frame) {
+        for popup in self.ordered() {
+            popup.paint(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(frame: &Frame, y: u16) -> String {
+        frame.line_clusters(y).concat()
+    }
+
+    #[test]
+    fn popups_are_bordered_clamped_and_z_ordered() {
+        let mut layer = PopupLayer::new();
+        layer.set_screen(12, 6);
+        let mut low = Popup::new(0, 0, vec!["aaaa".into(), "bb".into()]);
+        low.selected = Some(1);
+        let mut high = Popup::new(10, 1, vec!["zz".into()]);
+        high.z = 1;
+        layer.show(high.clone());
+        layer.show(low);
+        assert_eq!(high.region(12, 6), LayoutRegion::new(8, 1, 4, 3));
+
+        let mut frame = Frame::new(12, 6);
+        layer.paint(&mut frame);
+        assert_eq!(row(&frame, 0), "┌────┐      ");
+        assert_eq!(row(&frame, 1), "│aaaa│  ┌──┐");
+        assert_eq!(row(&frame, 2), "│bb  │  │zz│");
+        assert!(frame.cells[12 * 2 + 1].flags.contains(CellFlags::REVERSE));
+        assert_eq!(row(&frame, 3), "└────┘  └──┘");
+    }
+
+    #[test]
+    fn dismiss_and_shrink_expose_covered_cells() {
+        let mut layer = PopupLayer::new();
+        layer.set_screen(20, 10);
+        let id = layer.show(Popup::new(2, 2, vec!["long line".into()]));
+        assert_eq!(layer.take_damage().exposed, []);
+        assert!(!layer.is_dirty());
+
+        assert!(layer.update(id, Popup::new(2, 2, vec!["x".into()])));
+        let damage = layer.take_damage();
+        assert!(damage.changed);
+        assert_eq!(damage.exposed, [LayoutRegion::new(2, 2, 11, 3)]);
+
+        assert!(layer.dismiss(id));
+        assert!(!layer.dismiss(id));
+        assert_eq!(layer.take_damage().exposed, [LayoutRegion::new(2, 2, 3, 3)]);
+        assert!(layer.is_empty());
+    }
+}
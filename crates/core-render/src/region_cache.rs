@@ -0,0 +1,150 @@
+//! Per-view partial caches for split layouts (multi-region fan-out).
+//!
+//! The single-view `PartialCache` describes one viewport spanning the whole
+//! terminal. Split frames keep one `RegionCache` per `ViewId` instead,
+//! recording the region the view was last painted into together with the
+//! line hashes of that region, so a `Lines` delta only hashes and repaints
+//! rows of the views that show the edited buffer.
+//!
+//! An entry is warm only while the view keeps its region, buffer, viewport
+//! start, width and gutter width; anything else (resize, scroll, `:e` in
+//! that window, a line count crossing a power of ten with `'number'` set)
+//! makes the render path repaint the whole region and rebuild the entry.
+
+use crate::gutter::Gutter;
+use crate::partial_cache::{PartialCache, ViewportLineHash};
+use crate::wrap::{screen_rows, view_wrap};
+use core_model::{LayoutRegion, View, ViewId};
+use core_state::{BufferId, EditorState};
+use std::collections::HashMap;
+
+/// Hash metadata for the rows of one view's region.
+#[derive(Debug)]
+pub struct RegionCache {
+    pub region: LayoutRegion,
+    pub buffer: BufferId,
+    /// Row hashes; `viewport_start` / `width` mirror the view and region.
+    /// `last_cursor_line` is set only for the view that showed the cursor.
+    pub lines: PartialCache,
+}
+
+impl RegionCache {
+    /// True when the cached rows still describe `view` painted into `region`
+    /// next to `gutter`.
+    pub fn is_warm_for(&self, view: &View, region: LayoutRegion, gutter: &Gutter) -> bool {
+        self.region == region
+            && self.lines.gutter_width == gutter.width
+            && self.buffer == view.buffer_id
+            && self.lines.viewport_start == view.viewport_first_line
+            && self.lines.width == region.width
+            && self.lines.line_hashes.len() == region.height as usize
+    }
+}
+
+/// Region caches keyed by view.
+#[derive(Debug, Default)]
+pub struct RegionCaches {
+    entries: HashMap<ViewId, RegionCache>,
+}
+
+impl RegionCaches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn get(&self, view: ViewId) -> Option<&RegionCache> {
+        self.entries.get(&view)
+    }
+
+    pub fn get_mut(&mut self, view: ViewId) -> Option<&mut RegionCache> {
+        self.entries.get_mut(&view)
+    }
+
+    /// Drop entries for views that are no longer laid out.
+    pub fn retain_views(&mut self, views: &[ViewId]) {
+        self.entries.retain(|id, _| views.contains(id));
+    }
+
+    /// Mark rows showing `lines` of `buffer` stale in every region.
+    pub fn invalidate_lines(&mut self, buffer: BufferId, lines: &[usize]) {
+        for entry in self.entries.values_mut().filter(|e| e.buffer == buffer) {
+            for line in lines {
+                entry.lines.invalidate_line(*line);
+            }
+        }
+    }
+
+    /// Hash every row of `view` as painted into `region`, replacing any
+    /// previous entry. Rows past the end of the buffer hash as empty lines.
+    pub fn rebuild(&mut self, state: &EditorState, view: &View, region: LayoutRegion) {
+        let mut lines = PartialCache::new();
+        let first = view.viewport_first_line;
+        lines.reset(first, region.width, region.height as usize);
+        lines.gutter_width = Gutter::for_view(state, view).width;
+        for row in 0..region.height as usize {
+            lines.push_line(line_hash(state, view.buffer_id, first + row));
+        }
+        if let Some(entry) = state.buffers.get(view.buffer_id) {
+            let wrap = view_wrap(state, view, region.width);
+            lines.rows = screen_rows(
+                &entry.buffer,
+                wrap,
+                &view.folds,
+                first,
+                region.height as usize,
+            );
+        }
+        self.entries.insert(
+            view.id,
+            RegionCache {
+                region,
+                buffer: view.buffer_id,
+                lines,
+            },
+        );
+    }
+}
+
+/// Display hash of line `line` of buffer `buffer` without its line ending
+/// (text plus search matches).
+pub fn line_hash(state: &EditorState, buffer: BufferId, line: usize) -> ViewportLineHash {
+    let raw = state
+        .buffers
+        .get(buffer)
+        .and_then(|entry| entry.buffer.line(line))
+        .unwrap_or_default();
+    let text = raw.trim_end_matches(['\n', '\r']);
+    PartialCache::compute_display_hash(text, &crate::style::search_matches(state, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_text::{Buffer, Position};
+
+    #[test]
+    fn rebuild_hashes_rows_and_tracks_warmth() {
+        let state = EditorState::new(Buffer::from_str("t", "a\nb\n").unwrap());
+        let view = View::new(ViewId(0), state.active, Position::origin(), 0);
+        let region = LayoutRegion::new(0, 0, 10, 3);
+        let mut caches = RegionCaches::new();
+        caches.rebuild(&state, &view, region);
+        let entry = caches.get(view.id).unwrap();
+        let gutter = Gutter::for_view(&state, &view);
+        assert!(entry.is_warm_for(&view, region, &gutter));
+        assert_eq!(entry.lines.get(1), Some(PartialCache::compute_hash("b")));
+        assert_eq!(entry.lines.get(2), Some(PartialCache::compute_hash("")));
+
+        let scrolled = View::new(view.id, view.buffer_id, view.cursor, 1);
+        assert!(!entry.is_warm_for(&scrolled, region, &gutter));
+        assert!(!entry.is_warm_for(&view, LayoutRegion::new(0, 0, 11, 3), &gutter));
+        let numbered = Gutter { width: 4, ..gutter };
+        assert!(!entry.is_warm_for(&view, region, &numbered));
+        caches.retain_views(&[]);
+        assert!(caches.get(view.id).is_none());
+    }
+}
@@ -3,17 +3,36 @@
 //! cursor span metadata (no behavioral change yet).
 
 use crate::batch_writer::BatchWriter;
+use crate::capture::CaptureWriter;
+use crate::gutter::Gutter;
+use crate::latency::{FramePath, FrameProfiler, FrameStages};
 use crate::overlay::{build_overlay_lines, overlay_line_count, paint_overlay_rows_batch}; // Step 13 overlay integration
+use crate::pacing::FramePacer;
 use crate::partial_cache::PartialCache;
 use crate::partial_diff::classify_viewport_changes;
 use crate::partial_metrics::{RenderPathMetrics, RenderPathMetricsSnapshot};
-use crate::style::{StyleAttr, StyleLayer, StyleSpan};
+use crate::popup::{Popup, PopupId, PopupLayer};
+use crate::region_cache::{RegionCaches, line_hash};
+use crate::scheduler::RenderDelta;
+use crate::style::{
+    CursorShade, Palette, StyleAttr, StyleLayer, StyleProviders, StyleSpan, StyleSpanProvider,
+    diagnostic_flags, line_attr_at, line_virtual_text, search_matches,
+};
+use crate::tabline::{TabLine, paint_tabline};
+use crate::whitespace::LineGlyphs;
+use crate::wrap::{
+    Fillers, ScreenRow, cursor_cell_filled, fold_line, rows_match, screen_rows, screen_rows_filled,
+    view_wrap,
+};
 use crate::{CellFlags, Frame};
 use anyhow::Result;
-use core_model::{Layout, View};
-use core_state::EditorState;
-use core_terminal::TerminalCapabilities; // Step 10 capabilities stub
+use core_config::theme::Theme;
+use core_model::fold::Folds;
+use core_model::{Layout, LayoutRegion, SplitAxis, View, ViewId};
+use core_state::{BufferId, DiffKind, EditorState, Mode};
+use core_terminal::{ColorDepth, CursorShape, TerminalCapabilities}; // Step 10 capabilities stub
 use core_text::grapheme;
+use std::borrow::Cow;
 
 // Full vs Partial Render Grapheme Parity
 // --------------------------------------
@@ -48,6 +67,47 @@ pub struct RenderEngine {
     last_repaint_kind: Option<&'static str>,
     /// Cached last rendered status line text for skip optimization (Phase 4 Step 13).
     prev_status: String,
+    /// Overlay rows the last partial path painted; unchanged rows are
+    /// skipped. Cleared by the frame paths, which paint every row.
+    overlay_painted: Vec<String>,
+    /// Last frame emitted by `render_views`; split frames repaint only the
+    /// region rows that differ from it. Cleared by every single-view path.
+    split_frame: Option<Frame>,
+    /// Views whose region had at least one row repainted in the last split frame.
+    last_repaint_views: Vec<ViewId>,
+    /// Per-view row hashes backing the split `Lines` / cursor paths.
+    region_caches: RegionCaches,
+    /// Floating popups painted over every frame (see `finish_popups`).
+    popups: PopupLayer,
+    /// Popup listing the open Insert-mode completion's matches.
+    completion_popup: Option<PopupId>,
+    /// Popup showing `state.hover`, while one is.
+    hover_popup: Option<PopupId>,
+    /// Active color scheme resolved for `capabilities.color_depth`.
+    palette: Palette,
+    /// Highlight span sources merged into every text row.
+    styles: StyleProviders,
+    /// Show the terminal's own cursor, shaped per mode, instead of the
+    /// reverse-video cell (see `for_terminal`).
+    hardware_cursor: bool,
+    /// Screen cell of the cursor drawn by the last path that painted it.
+    cursor_cell: Option<(u16, u16)>,
+    /// Coalesces frames during input bursts (see `hold_frame`).
+    pacer: FramePacer,
+    /// Stage timings of recent frames while `EditorState::profile_frames`
+    /// is set (see `crate::latency`).
+    profiler: FrameProfiler,
+    /// Snapshot tests: flushed frames go here instead of stdout.
+    capture: Option<CaptureWriter>,
+}
+
+/// Hardware cursor shape for `mode`: a block outside Insert, a bar in it.
+/// `CursorShape::Underline` is reserved for Replace mode.
+pub fn cursor_shape(mode: Mode) -> CursorShape {
+    match mode {
+        Mode::Insert => CursorShape::Bar,
+        Mode::Normal | Mode::VisualChar => CursorShape::Block,
+    }
 }
 
 /// Phase 3 Step 10: proportion of visible text rows whose inclusion in the
@@ -66,92 +126,66 @@ impl RenderEngine {}
 
 /// Result of a successful trimmed diff heuristic (Phase 4 Step 12).
 struct TrimResult {
-    prefix_cols: u16,
-    interior: String,
-    clear_suffix: bool,
+    /// Text columns (gutter excluded) whose cells changed.
+    cols: std::ops::Range<u16>,
     cols_saved: u16,
 }
 
 impl RenderEngine {
+    /// Column span of a one-row line that changed from `old` (as painted) to
+    /// `new`: the clusters after the common prefix up to the common suffix
+    /// when the suffix keeps its columns (a replacement), else the whole
+    /// tail up to the wider of the two texts (an insertion or deletion
+    /// shifts the tail; the cells past the new end are blanked). `None` when
+    /// repainting the line whole costs about the same.
     fn try_trim_line(&self, old: &str, new: &str, width: u16) -> Option<TrimResult> {
         if old == new || width == 0 {
             return None;
         }
-        // Walk forward grapheme clusters to find longest common prefix (byte index).
-        let mut prefix_bytes = 0usize;
-        let mut b_old = 0usize;
-        let mut b_new = 0usize;
-        loop {
-            if b_old >= old.len() || b_new >= new.len() {
-                break; // one exhausted
-            }
-            let next_old = grapheme::next_boundary(old, b_old);
-            let next_new = grapheme::next_boundary(new, b_new);
-            let g_old = &old[b_old..next_old];
-            let g_new = &new[b_new..next_new];
-            if g_old == g_new {
-                prefix_bytes = next_old.min(next_new); // advance
-                b_old = next_old;
-                b_new = next_new;
-                continue;
-            }
-            break;
-        }
-
-        // Walk backward grapheme clusters for suffix (exclusive of prefix region).
-        let mut suffix_new_bytes = 0usize; // length in bytes of common suffix in new
-        let mut eo_old = old.len();
-        let mut eo_new = new.len();
-        while eo_old > prefix_bytes && eo_new > prefix_bytes {
-            // Find previous boundaries
-            let prev_old = grapheme::prev_boundary(old, eo_old);
-            let prev_new = grapheme::prev_boundary(new, eo_new);
-            let g_old = &old[prev_old..eo_old];
-            let g_new = &new[prev_new..eo_new];
-            if g_old == g_new {
-                // Would consuming this suffix remove the entire differing interior? ensure at least one differing cluster remains.
-                if prev_old <= prefix_bytes || prev_new <= prefix_bytes {
-                    break; // would overlap prefix -> no interior
-                }
-                suffix_new_bytes += eo_new - prev_new;
-                eo_old = prev_old;
-                eo_new = prev_new;
-                continue;
-            }
-            break;
-        }
-
-        // Interior slices spanning the changed region (prefix..suffix exclusive)
-        let new_interior = &new[prefix_bytes..new.len() - suffix_new_bytes];
-        if new_interior.is_empty() {
-            // For Step 12 we treat pure deletions as full repaint (need clear logic & potential EOL). Simpler fallback.
-            return None;
-        }
-        // Visual width computations.
-        let prefix_cols = grapheme::visual_col(old, prefix_bytes) as u16; // same for new at that boundary
-        if prefix_cols >= width {
+        let old_clusters: Vec<&str> = grapheme::iter(old).collect();
+        let new_clusters: Vec<&str> = grapheme::iter(new).collect();
+        let cols = |clusters: &[&str]| -> usize {
+            clusters
+                .iter()
+                .fold(0, |col, g| col + grapheme::cell_width(g, col))
+        };
+        let prefix = old_clusters
+            .iter()
+            .zip(&new_clusters)
+            .take_while(|(a, b)| a == b)
+            .count();
+        // The suffix leaves at least one cluster of either text to the
+        // interior, so it never overlaps the prefix.
+        let suffix = old_clusters[prefix..]
+            .iter()
+            .rev()
+            .zip(new_clusters[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(old_clusters.len().min(new_clusters.len()) - prefix);
+        let prefix_cols = cols(&new_clusters[..prefix]);
+        let old_cols = cols(&old_clusters);
+        let new_cols = cols(&new_clusters);
+        let old_interior_end = cols(&old_clusters[..old_clusters.len() - suffix]);
+        let new_interior_end = cols(&new_clusters[..new_clusters.len() - suffix]);
+        let end = if old_interior_end == new_interior_end {
+            new_interior_end
+        } else {
+            old_cols.max(new_cols)
+        };
+        let width = width as usize;
+        if prefix_cols >= width || end <= prefix_cols {
             return None;
         }
-        let full_cols_new = grapheme::visual_col(new, new.len()) as u16;
-        let interior_cols_new = grapheme::visual_col(new_interior, new_interior.len()) as u16;
-        let saved_cols = full_cols_new.saturating_sub(interior_cols_new);
-        const TRIM_MIN_SAVINGS_COLS: u16 = 4;
+        let end = end.min(width);
+        let saved_cols = new_cols.max(old_cols).min(width) - (end - prefix_cols);
+        const TRIM_MIN_SAVINGS_COLS: usize = 4;
         if saved_cols < TRIM_MIN_SAVINGS_COLS {
             return None;
         }
-        let clear_suffix = new.len() < old.len(); // line shrink or deletion beyond interior; safe to clear
-        // Phase 5 / Step 0.4: previously disabled trimmed diff for net insertions only.
-        // Phase 5 / Step 0.5: broaden guard – disable trimmed diff whenever the line length
-        // changes (net insertion OR shrink). Without terminal insert/delete cell ops (ICH/DCH)
-        // we rely on full repaints to keep suffix/prefix parity across undo + motion sequences.
-        if new.len() != old.len() {
-            return None;
-        }
         Some(TrimResult {
-            prefix_cols,
-            interior: new_interior.to_string(),
-            clear_suffix,
-            cols_saved: saved_cols,
+            cols: prefix_cols as u16..end as u16,
+            cols_saved: saved_cols as u16,
         })
     }
     pub fn new() -> Self {
@@ -163,6 +197,62 @@ impl RenderEngine {
             last_repaint_lines: Vec::new(),
             last_repaint_kind: None,
             prev_status: String::new(),
+            overlay_painted: Vec::new(),
+            split_frame: None,
+            last_repaint_views: Vec::new(),
+            region_caches: RegionCaches::new(),
+            popups: PopupLayer::new(),
+            completion_popup: None,
+            hover_popup: None,
+            palette: Palette::builtin().clone(),
+            styles: StyleProviders::default(),
+            hardware_cursor: false,
+            cursor_cell: None,
+            pacer: FramePacer::default(),
+            profiler: FrameProfiler::default(),
+            capture: None,
+        }
+    }
+
+    /// Route every flushed frame to `capture` instead of the terminal.
+    pub fn capture_to(&mut self, capture: CaptureWriter) {
+        self.capture = Some(capture);
+    }
+
+    /// The capture installed by `capture_to`.
+    pub fn capture(&self) -> Option<&CaptureWriter> {
+        self.capture.as_ref()
+    }
+
+    pub fn capture_mut(&mut self) -> Option<&mut CaptureWriter> {
+        self.capture.as_mut()
+    }
+
+    /// Engine for the real terminal: uses the hardware cursor when the
+    /// terminal can shape it and resolves `theme` for its color depth, or
+    /// for `colors` when the config forces one. `new` keeps the software
+    /// cursor and the 256-color palette, so output does not depend on `TERM`.
+    pub fn for_terminal(colors: Option<ColorDepth>, theme: &Theme) -> Self {
+        let mut engine = Self::new();
+        if let Some(depth) = colors {
+            engine.capabilities = engine.capabilities.with_color_depth(depth);
+        }
+        engine.hardware_cursor = engine.capabilities.cursor_shape;
+        engine.set_theme(theme);
+        engine
+    }
+
+    pub fn set_hardware_cursor(&mut self, on: bool) {
+        self.hardware_cursor = on;
+    }
+
+    /// Flags of the cursor span: reverse video unless the hardware cursor
+    /// marks the cell.
+    fn cursor_flags(&self) -> CellFlags {
+        if self.hardware_cursor {
+            CellFlags::CURSOR
+        } else {
+            CellFlags::REVERSE | CellFlags::CURSOR
         }
     }
 
@@ -176,36 +266,58 @@ impl RenderEngine {
         h: u16,
         status_line: &str,
     ) -> Result<()> {
+        self.split_frame = None;
         let start_time = std::time::Instant::now();
+        self.profiler.begin(state.profile_frames);
         if h == 0 {
             return Ok(());
         }
-        self.last_repaint_lines.clear();
-        self.last_repaint_kind = Some("cursor_only");
+        let (gutter, stale) = self.gutter_stale(state, view);
+        if stale {
+            return self.render_full(state, view, _layout, w, h, status_line);
+        }
         let overlay_lines = overlay_line_count(state, w);
         let text_height = h.saturating_sub(1 + overlay_lines); // reserve overlay + status
         let buf = state.active_buffer();
         let viewport_first = view.viewport_first_line;
-        let viewport_last_excl = viewport_first + text_height as usize;
-        let mut writer = BatchWriter::new();
+        let rows = screen_rows(
+            buf,
+            view_wrap(state, view, w),
+            &view.folds,
+            viewport_first,
+            text_height as usize,
+        );
+        if !rows_match(&rows, &self.cache.rows) {
+            return self.render_full(state, view, _layout, w, h, status_line);
+        }
+        self.last_repaint_lines.clear();
+        self.last_repaint_kind = Some("cursor_only");
+        let mut writer = self.writer();
 
         let prev_line_opt = self.cache.last_cursor_line;
         let curr_line = view.cursor.line;
+        let shade = CursorShade::for_view(state, view, w);
 
         let mut paint_line = |buf_line: usize| {
-            if buf_line < viewport_first || buf_line >= viewport_last_excl {
-                return;
-            }
-            let rel_y = (buf_line - viewport_first) as u16;
-            writer.move_to(0, rel_y);
-            writer.clear_line(0, rel_y);
             if let Some(raw_line) = buf.line(buf_line) {
                 let content_trim: &str = if raw_line.ends_with(['\n', '\r']) {
                     &raw_line[..raw_line.len() - 1]
                 } else {
                     raw_line.as_str()
                 };
-                Self::paint_content_trim(&mut writer, content_trim, w);
+                Self::paint_line(
+                    &mut writer,
+                    &self.palette,
+                    &self.styles,
+                    state,
+                    &gutter,
+                    &view.folds,
+                    &shade,
+                    &rows,
+                    buf_line,
+                    content_trim,
+                    w,
+                );
             }
         };
 
@@ -219,34 +331,126 @@ impl RenderEngine {
         if !self.last_repaint_lines.contains(&curr_line) {
             self.last_repaint_lines.push(curr_line);
         }
+        self.repaint_cursor_column(
+            &mut writer,
+            state,
+            &shade,
+            &rows,
+            &self.last_repaint_lines,
+            w,
+        );
 
-        if let Some(span) =
-            self.compute_cursor_span(state, view, viewport_first, viewport_last_excl)
+        self.cursor_cell = None;
+        if let Some((rel_y, span)) = self.compute_cursor_span(state, view, w, text_height as usize)
             && span.start_col < w
         {
-            writer.move_to(span.start_col, (span.line - viewport_first) as u16);
+            self.cursor_cell = Some((span.start_col, rel_y));
+            writer.move_to(span.start_col, rel_y);
             self.print_cursor_with_fallback(&mut writer, state, view);
         }
         // Paint overlay rows (always repaint) then status line.
-        paint_overlay_rows_batch(&mut writer, state, w, h);
-        self.maybe_apply_external_status_line(&mut writer, status_line, h);
-        let (print_cmds, cells) = writer.flush()?;
+        paint_overlay_rows_batch(&mut writer, state, w, h, &mut self.overlay_painted);
+        self.maybe_apply_external_status_line(&mut writer, status_line, w, h);
+        let (print_cmds, cells) = self.flush_writer(writer)?;
         let dur = start_time.elapsed().as_nanos() as u64;
         use std::sync::atomic::Ordering::Relaxed;
         self.metrics.partial_frames.fetch_add(1, Relaxed);
         self.metrics.cursor_only_frames.fetch_add(1, Relaxed);
-        self.metrics.last_partial_render_ns.store(dur, Relaxed);
+        self.record_frame(FramePath::CursorOnly, dur);
         self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
         self.metrics.cells_printed.fetch_add(cells, Relaxed);
         self.cache.last_cursor_line = Some(curr_line);
+        self.cache.last_cursor_col = shade.column;
+        self.cache.rows = rows;
+        self.finish_popups(state, view, w, h, status_line)?;
         Ok(())
     }
 
+    /// Frame pacing (`crate::pacing`): whether the frame ready at `now`
+    /// should wait for queued input. When it should not, the caller draws
+    /// it right away and it counts as covering every decision held before.
+    pub fn hold_frame(&mut self, now: std::time::Instant, input_pending: bool) -> bool {
+        use std::sync::atomic::Ordering::Relaxed;
+        if self.pacer.hold(now, input_pending) {
+            self.metrics.coalesced_decisions.fetch_add(1, Relaxed);
+            return true;
+        }
+        if self.pacer.frame(now) > 0 {
+            self.metrics.coalesced_frames.fetch_add(1, Relaxed);
+        }
+        false
+    }
+
+    /// Replace the pacing budget (`crate::pacing::FRAME_BUDGET` by default).
+    pub fn set_frame_budget(&mut self, budget: std::time::Duration) {
+        self.pacer = FramePacer::new(budget);
+    }
+
     /// Expose terminal capabilities (read-only) for scheduler decisions or tests.
     pub fn capabilities(&self) -> TerminalCapabilities {
         self.capabilities
     }
 
+    /// Switch to `theme`, resolved for the terminal's color depth. Cached rows
+    /// carry the old colors, so the caches are dropped and the caller must
+    /// schedule a full frame.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.palette = Palette::resolve(theme, self.capabilities.color_depth);
+        self.cache.clear();
+        self.split_frame = None;
+        self.region_caches.clear();
+        self.prev_status.clear();
+        self.overlay_painted.clear();
+        tracing::debug!(target: "render.engine", theme = %theme.name, "theme_applied");
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Register a highlight span source; false when its name is taken.
+    /// Like `set_theme`, drops the caches: the caller must schedule a full
+    /// frame.
+    pub fn add_style_provider(&mut self, provider: Box<dyn StyleSpanProvider>) -> bool {
+        let name = provider.name().to_string();
+        let added = self.styles.add(provider);
+        if added {
+            self.drop_styled_caches();
+            tracing::debug!(target: "render.engine", provider = %name, "style_provider_added");
+        }
+        added
+    }
+
+    /// Unregister the span source named `name` (see `add_style_provider`).
+    pub fn remove_style_provider(&mut self, name: &str) -> bool {
+        let removed = self.styles.remove(name);
+        if removed {
+            self.drop_styled_caches();
+        }
+        removed
+    }
+
+    pub fn style_providers(&self) -> &StyleProviders {
+        &self.styles
+    }
+
+    fn drop_styled_caches(&mut self) {
+        self.cache.clear();
+        self.split_frame = None;
+        self.region_caches.clear();
+    }
+
+    /// Writer starting every row from the scheme's `Normal` colors. The
+    /// hardware cursor is hidden while it paints; `finish_popups` puts it
+    /// back.
+    fn writer(&self) -> BatchWriter {
+        let mut writer = BatchWriter::with_base(self.palette.base());
+        if self.hardware_cursor {
+            writer.hide_cursor();
+        }
+        writer
+    }
+
     /// Build + render a full frame (current behavior; breadth-first guarantee).
     pub fn render_full(
         &mut self,
@@ -257,10 +461,15 @@ impl RenderEngine {
         h: u16,
         status_line: &str,
     ) -> Result<()> {
+        self.split_frame = None;
         let start = std::time::Instant::now();
+        self.profiler.begin(state.profile_frames);
         // Step 5: classify hash differences (still full frame output). We run this
         // before building the frame so the hashing path always executes each frame.
+        let hash_clock = self.profiler.clock();
         classify_viewport_changes(state, view, w, h, &mut self.cache, &self.metrics, None);
+        self.profiler.add_hash(hash_clock);
+        self.cache.gutter_width = Gutter::for_view(state, view).width;
 
         let _primary = layout.primary(); // reserved for future multi-region use
         let _caps = self.capabilities; // reserved for scroll-region path gating (future)
@@ -269,52 +478,31 @@ impl RenderEngine {
         let mut frame = Frame::new(w, h);
         let full_text_height = if h > 0 { h - 1 } else { 0 }; // exclude status
         let effective_text_height = full_text_height.saturating_sub(overlay_lines);
+        let shade = CursorShade::for_view(state, view, w);
+        // Text rows (gutter, wrapped rows, syntax and search colours, cursor
+        // shading) come from the same builder split regions use.
         if effective_text_height > 0 {
-            let buf = state.active_buffer();
-            let start = view.viewport_first_line;
-            let end = (start + effective_text_height as usize).min(buf.line_count());
-            for (screen_y, line_idx) in (start..end).enumerate() {
-                if (screen_y as u16) >= effective_text_height {
-                    break;
-                }
-                if let Some(line) = buf.line(line_idx) {
-                    let content_trim: &str = if line.ends_with(['\n', '\r']) {
-                        &line[..line.len() - 1]
-                    } else {
-                        &line
-                    };
-                    let mut byte = 0usize;
-                    let mut vis_col: u16 = 0;
-                    while byte < content_trim.len() && vis_col < w {
-                        let next = core_text::grapheme::next_boundary(content_trim, byte);
-                        let cluster = &content_trim[byte..next];
-                        let width = grapheme::cluster_width(cluster).max(1) as u16;
-                        frame.set_cluster(
-                            vis_col,
-                            screen_y as u16,
-                            cluster,
-                            width,
-                            CellFlags::empty(),
-                        );
-                        vis_col = vis_col.saturating_add(width);
-                        byte = next;
-                    }
-                }
-            }
+            frame.blit(
+                &build_view_frame_styled(state, view, w, effective_text_height, &self.styles),
+                0,
+                0,
+            );
         }
+        let rows = screen_rows(
+            state.active_buffer(),
+            view_wrap(state, view, w),
+            &view.folds,
+            view.viewport_first_line,
+            effective_text_height as usize,
+        );
         // Step 9: compute style layer (cursor span only for now) and apply; update cursor meta.
-        let viewport_start = view.viewport_first_line;
-        let viewport_end_excl = viewport_start + effective_text_height as usize; // text area excludes overlay + status
         let mut style_layer = StyleLayer::new();
-        if let Some(span) = self.compute_cursor_span(state, view, viewport_start, viewport_end_excl)
+        self.cursor_cell = None;
+        if let Some((rel_y, span)) =
+            self.compute_cursor_span(state, view, w, effective_text_height as usize)
         {
-            let rel_y = (span.line - viewport_start) as u16;
-            frame.apply_flags_span(
-                span.start_col,
-                rel_y,
-                span.width(),
-                CellFlags::REVERSE | CellFlags::CURSOR,
-            );
+            frame.apply_flags_span(span.start_col, rel_y, span.width(), self.cursor_flags());
+            self.cursor_cell = Some((span.start_col, rel_y));
             self.last_cursor = CursorSpanMeta {
                 line: Some(span.line),
                 start_col: Some(span.start_col),
@@ -329,36 +517,21 @@ impl RenderEngine {
         // full + partial paths share identical cluster emission semantics.
         if h > 0 {
             // Paint overlay rows (always repaint) just above the status line.
-            if overlay_lines > 0 && overlay_lines < h {
-                // Paint overlay into a temporary batch then replay into frame (simplistic approach):
-                // Simplicity: reuse existing cluster emission logic; performance non-critical for fixed small N.
-                // For now we duplicate minimal logic: just call build_overlay_lines and set clusters directly.
-                let lines = build_overlay_lines(state, w);
-                let mut row = h - 1 - overlay_lines;
-                for l in lines.iter().take(overlay_lines as usize) {
-                    let mut byte = 0usize;
-                    let mut x: u16 = 0;
-                    while byte < l.len() && x < w {
-                        let next = grapheme::next_boundary(l, byte);
-                        let cluster = &l[byte..next];
-                        let width = grapheme::cluster_width(cluster).max(1) as u16;
-                        frame.set_cluster(x, row, cluster, width, CellFlags::empty());
-                        x = x.saturating_add(width);
-                        byte = next;
-                    }
-                    row += 1;
-                }
-            }
+            paint_overlay_into_frame(&mut frame, state, overlay_lines, w, h);
             // Paint externally provided status line at bottom.
             apply_external_status_line(status_line, &mut frame, w, h);
+            frame.apply_flags_span(0, h - 1, w, CellFlags::STATUS);
             self.prev_status = status_line.to_string();
         } else {
             self.prev_status.clear();
         }
+        self.overlay_painted.clear();
         // Phase 3 Step 6: translate Frame into writer commands (still full repaint)
         let (print_cmds, cells) = self.render_via_writer(&frame)?;
         // Update last cursor line in cache.
         self.cache.last_cursor_line = Some(view.cursor.line);
+        self.cache.last_cursor_col = shade.column;
+        self.cache.rows = rows;
         // Populate prev_text shadow for all visible lines (text area only) for trimming in subsequent partial frames.
         if h > 0 {
             let text_height = h - 1;
@@ -387,9 +560,456 @@ impl RenderEngine {
         let dur = start.elapsed().as_nanos() as u64;
         use std::sync::atomic::Ordering::Relaxed;
         self.metrics.full_frames.fetch_add(1, Relaxed);
-        self.metrics.last_full_render_ns.store(dur, Relaxed);
+        self.record_frame(FramePath::Full, dur);
+        self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
+        self.metrics.cells_printed.fetch_add(cells, Relaxed);
+        self.finish_popups(state, view, w, h, status_line)?;
+        Ok(())
+    }
+
+    /// Frame for a split layout: each view paints into its region with its
+    /// own status row below it, the separators go between them, and the
+    /// overlay and `status_line` (the command line for split layouts) stay at
+    /// the bottom. Only the `active` view shows the cursor. A `tabline` is
+    /// painted on the top row, which the layout must leave free. The
+    /// single-view partial cache does not describe split frames, so it is
+    /// cleared.
+    ///
+    /// When the previous split frame had the same size only rows that
+    /// changed are emitted, clipped to the region (or separator) they belong
+    /// to, so an edit in one window leaves its siblings untouched. A resize
+    /// (or the first split frame) repaints everything.
+    ///
+    /// `Lines`, `CursorOnly` and `StatusLine` deltas skip composing the
+    /// views altogether (see `render_view_regions`).
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_views(
+        &mut self,
+        state: &EditorState,
+        views: &[View],
+        active: ViewId,
+        layout: &Layout,
+        delta: &RenderDelta,
+        tabline: Option<&TabLine>,
+        w: u16,
+        h: u16,
+        status_line: &str,
+    ) -> Result<()> {
+        let start = std::time::Instant::now();
+        self.profiler.begin(state.profile_frames);
+        let dirty = match delta {
+            // Region repaints know nothing of diff filler rows.
+            _ if state.diff.is_active() => {
+                return self.compose_views(
+                    state,
+                    views,
+                    active,
+                    layout,
+                    tabline,
+                    w,
+                    h,
+                    status_line,
+                );
+            }
+            RenderDelta::Lines(range) => Some(range.clone()),
+            RenderDelta::CursorOnly | RenderDelta::StatusLine => None,
+            _ => {
+                return self.compose_views(
+                    state,
+                    views,
+                    active,
+                    layout,
+                    tabline,
+                    w,
+                    h,
+                    status_line,
+                );
+            }
+        };
+        match self.split_frame.take() {
+            Some(prev) if prev.width == w && prev.height == h => self.render_view_regions(
+                prev,
+                state,
+                views,
+                active,
+                layout,
+                dirty,
+                tabline,
+                status_line,
+                start,
+            ),
+            _ => self.compose_views(state, views, active, layout, tabline, w, h, status_line),
+        }
+    }
+
+    /// Compose every view into a fresh frame and emit what differs from the
+    /// previous split frame (or all of it). Rebuilds the region caches.
+    #[allow(clippy::too_many_arguments)]
+    fn compose_views(
+        &mut self,
+        state: &EditorState,
+        views: &[View],
+        active: ViewId,
+        layout: &Layout,
+        tabline: Option<&TabLine>,
+        w: u16,
+        h: u16,
+        status_line: &str,
+    ) -> Result<()> {
+        let start = std::time::Instant::now();
+        self.profiler.begin(state.profile_frames);
+        let overlay_lines = overlay_line_count(state, w);
+        let mut frame = Frame::new(w, h);
+        self.last_cursor = CursorSpanMeta::default();
+        self.cursor_cell = None;
+        let focused = views.iter().find(|v| v.id == active);
+        for (region, id) in layout.regions().iter().zip(layout.views()) {
+            let Some(view) = views.iter().find(|v| v.id == *id) else {
+                continue;
+            };
+            let fillers = match focused {
+                Some(focused) if *id != active => Fillers::beside(state, view, focused),
+                _ => Fillers::for_view(state, view),
+            };
+            let mut sub = build_view_frame_filled(
+                state,
+                view,
+                region.width,
+                region.height,
+                &self.styles,
+                fillers,
+            );
+            if *id == active
+                && let Some((rel_y, span)) =
+                    self.compute_cursor_span(state, view, region.width, region.height as usize)
+            {
+                sub.apply_flags_span(span.start_col, rel_y, span.width(), self.cursor_flags());
+                self.cursor_cell = Some((region.x + span.start_col, region.y + rel_y));
+                self.last_cursor = CursorSpanMeta {
+                    line: Some(span.line),
+                    start_col: Some(region.x + span.start_col),
+                    width: Some(span.width()),
+                };
+            }
+            frame.blit(&sub, region.x, region.y);
+        }
+        for (region, id) in layout.statuses().iter().zip(layout.views()) {
+            if let Some(view) = views.iter().find(|v| v.id == *id) {
+                let text = build_view_status_line(state, view, *id == active);
+                paint_status_row(&mut frame, *region, &text);
+            }
+        }
+        for sep in layout.separators() {
+            let glyph = match sep.axis {
+                SplitAxis::Horizontal => "─",
+                SplitAxis::Vertical => "│",
+            };
+            let r = sep.region;
+            for y in r.y..r.y.saturating_add(r.height) {
+                for x in r.x..r.x.saturating_add(r.width) {
+                    frame.set_cluster(x, y, glyph, 1, CellFlags::empty());
+                }
+            }
+        }
+        if let Some(tabline) = tabline {
+            paint_tabline(tabline, &mut frame, 0);
+        }
+        if h > 0 {
+            paint_overlay_into_frame(&mut frame, state, overlay_lines, w, h);
+            apply_external_status_line(status_line, &mut frame, w, h);
+            self.prev_status = status_line.to_string();
+        }
+        self.overlay_painted.clear();
+        use std::sync::atomic::Ordering::Relaxed;
+        let prev = self.split_frame.take();
+        let (print_cmds, cells) = match prev {
+            Some(prev) if prev.width == w && prev.height == h => {
+                let mut areas: Vec<(Option<ViewId>, LayoutRegion)> = layout
+                    .views()
+                    .iter()
+                    .map(|id| Some(*id))
+                    .zip(layout.regions().iter().copied())
+                    .collect();
+                areas.extend(
+                    layout
+                        .views()
+                        .iter()
+                        .map(|id| Some(*id))
+                        .zip(layout.statuses().iter().copied()),
+                );
+                areas.extend(layout.separators().iter().map(|sep| (None, sep.region)));
+                let text_top = areas.iter().map(|(_, r)| r.y).min().unwrap_or(0);
+                let text_bottom = areas
+                    .iter()
+                    .map(|(_, r)| r.y.saturating_add(r.height))
+                    .max()
+                    .unwrap_or(0);
+                areas.push((None, LayoutRegion::new(0, 0, w, text_top)));
+                areas.push((
+                    None,
+                    LayoutRegion::new(0, text_bottom, w, h.saturating_sub(text_bottom)),
+                ));
+                let out = self.emit_changed_areas(&prev, &frame, &areas)?;
+                self.last_repaint_kind = Some("split_partial");
+                self.metrics.partial_frames.fetch_add(1, Relaxed);
+                out
+            }
+            _ => {
+                let out = self.render_via_writer(&frame)?;
+                self.last_repaint_views = layout.views().to_vec();
+                self.last_repaint_kind = Some("split_full");
+                self.metrics.full_frames.fetch_add(1, Relaxed);
+                out
+            }
+        };
+        // Composed whole either way, so both count as full in the histograms.
+        self.record_frame(FramePath::Full, start.elapsed().as_nanos() as u64);
+        self.split_frame = Some(frame);
+        self.cache.clear();
+        self.last_repaint_lines.clear();
+        self.region_caches.retain_views(layout.views());
+        for (region, id) in layout.regions().iter().zip(layout.views()) {
+            if let Some(view) = views.iter().find(|v| v.id == *id) {
+                self.region_caches.rebuild(state, view, *region);
+                if let Some(entry) = self.region_caches.get_mut(*id) {
+                    entry.lines.last_cursor_line = (*id == active).then_some(view.cursor.line);
+                    entry.lines.last_cursor_col =
+                        CursorShade::for_view(state, view, region.width).column;
+                }
+            }
+        }
+        self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
+        self.metrics.cells_printed.fetch_add(cells, Relaxed);
+        if let Some(view) = views.iter().find(|v| v.id == active) {
+            self.finish_popups(state, view, w, h, status_line)?;
+        }
+        Ok(())
+    }
+
+    /// Split-layout counterpart of the `Lines` / cursor-only partial paths,
+    /// fanned out per region. `dirty` buffer lines only concern views showing
+    /// the active buffer; in each such view the candidates (plus the old and
+    /// new cursor lines) are hashed against its `RegionCache` and only rows
+    /// that changed are repainted into a copy of `prev`. Views whose cache is
+    /// cold repaint their whole region. Status rows, the tabline and the
+    /// bottom rows are rebuilt (they are cheap) and emitted when they differ.
+    #[allow(clippy::too_many_arguments)]
+    fn render_view_regions(
+        &mut self,
+        prev: Frame,
+        state: &EditorState,
+        views: &[View],
+        active: ViewId,
+        layout: &Layout,
+        dirty: Option<std::ops::Range<usize>>,
+        tabline: Option<&TabLine>,
+        status_line: &str,
+        start: std::time::Instant,
+    ) -> Result<()> {
+        use std::sync::atomic::Ordering::Relaxed;
+        let (w, h) = (prev.width, prev.height);
+        let mut frame = prev.clone();
+        let mut areas: Vec<(Option<ViewId>, LayoutRegion)> = Vec::new();
+        let mut candidate_total = 0u64;
+        let mut repainted = 0u64;
+        self.last_cursor = CursorSpanMeta::default();
+        self.last_repaint_lines.clear();
+        for (region, id) in layout.regions().iter().zip(layout.views()) {
+            let Some(view) = views.iter().find(|v| v.id == *id) else {
+                continue;
+            };
+            let first = view.viewport_first_line;
+            let height = region.height as usize;
+            // Relative labels of the focused view follow its cursor line.
+            let gutter = Gutter::for_view(state, view);
+            let warm = self.region_caches.get(*id).is_some_and(|entry| {
+                entry.is_warm_for(view, *region, &gutter)
+                    && !(gutter.is_relative()
+                        && *id == active
+                        && entry.lines.last_cursor_line != Some(view.cursor.line))
+            });
+            let shade = CursorShade::for_view(state, view, region.width);
+            let wrap = view_wrap(state, view, region.width);
+            let screen = state
+                .buffers
+                .get(view.buffer_id)
+                .map(|entry| screen_rows(&entry.buffer, wrap, &view.folds, first, height))
+                .unwrap_or_default();
+            // Under 'wrap' or with folds, rows no longer map to lines one to
+            // one. Rows that moved (a line now wraps into more or fewer rows,
+            // a fold opened or closed) repaint the whole region.
+            let laid_out = wrap.is_some() || !view.folds.is_empty();
+            let warm = warm
+                && self
+                    .region_caches
+                    .get(*id)
+                    .is_some_and(|entry| rows_match(&screen, &entry.lines.rows));
+            let mut rows: Vec<usize> = Vec::new();
+            if let Some(entry) = self.region_caches.get_mut(*id).filter(|_| warm) {
+                let mut tracker = crate::dirty::DirtyLinesTracker::new();
+                if let Some(range) = dirty.as_ref().filter(|r| !r.is_empty())
+                    && view.buffer_id == state.active
+                {
+                    tracker.mark_range(range.start, range.end - 1);
+                }
+                let mut candidates = tracker.take_in_viewport(first, height);
+                let old_cursor = entry.lines.last_cursor_line;
+                let new_cursor = (*id == active).then_some(view.cursor.line);
+                candidates.extend(old_cursor.into_iter().chain(new_cursor));
+                candidates.retain(|l| (first..first + height).contains(l));
+                candidates.sort_unstable();
+                candidates.dedup();
+                candidate_total += candidates.len() as u64;
+                if candidates.len() as f32 >= height as f32 * LINES_ESCALATION_THRESHOLD_PCT
+                    && dirty.is_some()
+                {
+                    self.metrics.escalated_large_set.fetch_add(1, Relaxed);
+                    rows.extend(0..height);
+                } else {
+                    for line in candidates {
+                        let hash_clock = self.profiler.clock();
+                        let hash = line_hash(state, view.buffer_id, line);
+                        self.profiler.add_hash(hash_clock);
+                        let rel = line - first;
+                        let cursor_row = Some(line) == old_cursor || Some(line) == new_cursor;
+                        if cursor_row || entry.lines.get(rel) != Some(hash) {
+                            entry.lines.line_hashes[rel] = hash;
+                            if laid_out {
+                                rows.extend((0..screen.len()).filter(|y| screen[*y].line == line));
+                            } else {
+                                rows.push(rel);
+                            }
+                        }
+                    }
+                }
+                // A moved cursorcolumn stripe touches every row of the region.
+                if entry.lines.last_cursor_col != shade.column {
+                    rows = (0..height).collect();
+                }
+            } else {
+                rows.extend(0..height);
+            }
+            if rows.len() == height {
+                self.region_caches.rebuild(state, view, *region);
+            }
+            for rel in rows.iter().copied() {
+                let row = match screen.get(rel).filter(|_| laid_out) {
+                    Some(screen_row) => {
+                        let mut row = Frame::new(region.width, 1);
+                        paint_view_rows(
+                            &mut row,
+                            state,
+                            view,
+                            std::slice::from_ref(screen_row),
+                            &self.styles,
+                        );
+                        row
+                    }
+                    None => {
+                        let row_view = View {
+                            viewport_first_line: first + rel,
+                            ..view.clone()
+                        };
+                        build_view_frame_styled(state, &row_view, region.width, 1, &self.styles)
+                    }
+                };
+                frame.blit(&row, region.x, region.y + rel as u16);
+                areas.push((
+                    Some(*id),
+                    LayoutRegion::new(region.x, region.y + rel as u16, region.width, 1),
+                ));
+                let line = screen.get(rel).map_or(first + rel, |r| r.line);
+                if view.buffer_id == state.active && self.last_repaint_lines.last() != Some(&line) {
+                    self.last_repaint_lines.push(line);
+                }
+            }
+            repainted += rows.len() as u64;
+            let mut cursor_line = None;
+            if *id == active
+                && let Some((rel_y, span)) =
+                    self.compute_cursor_span(state, view, region.width, height)
+            {
+                frame.apply_flags_span(
+                    region.x + span.start_col,
+                    region.y + rel_y,
+                    span.width(),
+                    self.cursor_flags(),
+                );
+                self.cursor_cell = Some((region.x + span.start_col, region.y + rel_y));
+                self.last_cursor = CursorSpanMeta {
+                    line: Some(span.line),
+                    start_col: Some(region.x + span.start_col),
+                    width: Some(span.width()),
+                };
+                cursor_line = Some(view.cursor.line);
+            }
+            if let Some(entry) = self.region_caches.get_mut(*id) {
+                entry.lines.last_cursor_line = cursor_line;
+                entry.lines.last_cursor_col = shade.column;
+            }
+        }
+        for (region, id) in layout.statuses().iter().zip(layout.views()) {
+            if let Some(view) = views.iter().find(|v| v.id == *id) {
+                let text = build_view_status_line(state, view, *id == active);
+                paint_status_row(&mut frame, *region, &text);
+                areas.push((Some(*id), *region));
+            }
+        }
+        let text_top = layout.regions().iter().map(|r| r.y).min().unwrap_or(0);
+        let text_bottom = layout
+            .regions()
+            .iter()
+            .chain(layout.statuses())
+            .map(|r| r.y.saturating_add(r.height))
+            .max()
+            .unwrap_or(0);
+        if let Some(tabline) = tabline {
+            paint_tabline(tabline, &mut frame, 0);
+        }
+        frame.blit(
+            &Frame::new(w, h.saturating_sub(text_bottom)),
+            0,
+            text_bottom,
+        );
+        paint_overlay_into_frame(&mut frame, state, overlay_line_count(state, w), w, h);
+        apply_external_status_line(status_line, &mut frame, w, h);
+        self.prev_status = status_line.to_string();
+        self.overlay_painted.clear();
+        areas.push((None, LayoutRegion::new(0, 0, w, text_top)));
+        areas.push((
+            None,
+            LayoutRegion::new(0, text_bottom, w, h.saturating_sub(text_bottom)),
+        ));
+        let (print_cmds, cells) = self.emit_changed_areas(&prev, &frame, &areas)?;
+        self.split_frame = Some(frame);
+        self.last_repaint_kind = Some(if dirty.is_some() {
+            "split_lines"
+        } else {
+            "split_cursor"
+        });
+        self.metrics.partial_frames.fetch_add(1, Relaxed);
+        if dirty.is_some() {
+            self.metrics.lines_frames.fetch_add(1, Relaxed);
+        } else {
+            self.metrics.cursor_only_frames.fetch_add(1, Relaxed);
+        }
+        self.metrics
+            .dirty_candidate_lines
+            .fetch_add(candidate_total, Relaxed);
+        self.metrics
+            .dirty_lines_repainted
+            .fetch_add(repainted, Relaxed);
+        let path = if dirty.is_some() {
+            FramePath::Lines
+        } else {
+            FramePath::CursorOnly
+        };
+        self.record_frame(path, start.elapsed().as_nanos() as u64);
         self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
         self.metrics.cells_printed.fetch_add(cells, Relaxed);
+        if let Some(view) = views.iter().find(|v| v.id == active) {
+            self.finish_popups(state, view, w, h, status_line)?;
+        }
         Ok(())
     }
 
@@ -400,10 +1020,26 @@ impl RenderEngine {
     /// watcher to trigger a lightweight invalidation without immediately rendering.
     pub fn invalidate_for_resize(&mut self) {
         self.cache.clear();
+        self.overlay_painted.clear();
+        self.split_frame = None;
+        self.region_caches.clear();
         use std::sync::atomic::Ordering::Relaxed;
         self.metrics.resize_invalidations.fetch_add(1, Relaxed);
     }
 
+    /// Treat `lines` of `buffer` as dirty although their text is unchanged
+    /// (a sign was placed or removed): the cached rows showing them stop
+    /// matching, so the next `Lines` frame covering them repaints each row
+    /// whole. Only the active buffer's rows live in the single-view cache.
+    pub fn invalidate_lines(&mut self, state: &EditorState, buffer: BufferId, lines: &[usize]) {
+        if buffer == state.active {
+            for line in lines {
+                self.cache.invalidate_line(*line);
+            }
+        }
+        self.region_caches.invalidate_lines(buffer, lines);
+    }
+
     /// Placeholder for future partial rendering path (Phase 3).
     pub fn render_partial(
         &mut self,
@@ -434,8 +1070,10 @@ impl RenderEngine {
         dirty_tracker: &mut crate::dirty::DirtyLinesTracker,
         status_line: &str,
     ) -> Result<()> {
+        self.split_frame = None;
         use std::sync::atomic::Ordering::Relaxed;
         let start_time = std::time::Instant::now();
+        self.profiler.begin(state.profile_frames);
         if h == 0 {
             return Ok(());
         }
@@ -448,12 +1086,27 @@ impl RenderEngine {
         let viewport_last_excl = viewport_first + visible_rows;
 
         // If cache cold (viewport changed or width mismatch) fallback via full render (caller should have escalated).
-        if self.cache.viewport_start != viewport_first || self.cache.width != w {
+        // So does an edit that changed how many rows a wrapped line takes:
+        // every row below it moved.
+        let (gutter, gutter_stale) = self.gutter_stale(state, view);
+        let buf = state.active_buffer();
+        let rows = screen_rows(
+            buf,
+            view_wrap(state, view, w),
+            &view.folds,
+            viewport_first,
+            visible_rows,
+        );
+        if self.cache.viewport_start != viewport_first
+            || self.cache.width != w
+            || gutter_stale
+            || !rows_match(&rows, &self.cache.rows)
+        {
             return self.render_full(state, view, _layout, w, h, status_line);
         }
 
-        let mut writer = BatchWriter::new();
-        let buf = state.active_buffer();
+        let mut writer = self.writer();
+        let shade = CursorShade::for_view(state, view, w);
         // Collect dirty lines inside viewport.
         let mut candidates = dirty_tracker.take_in_viewport(viewport_first, visible_rows);
         // Always include old cursor line (if different & visible) and current cursor line.
@@ -493,7 +1146,12 @@ impl RenderEngine {
             if line_idx < viewport_first || line_idx >= viewport_last_excl {
                 continue;
             }
-            let rel_y = (line_idx - viewport_first) as u16;
+            // Screen row of the line when it takes exactly one (of its
+            // own: a fold summary row is not the line's text).
+            let single_row = rows
+                .iter()
+                .position(|r| r.line == line_idx && r.fold.is_none())
+                .filter(|y| rows.get(y + 1).is_none_or(|next| next.first));
             // Compute hash for this line to compare with cache entry.
             let mut changed = true; // default repaint for safety
             if let Some(raw_line) = buf.line(line_idx) {
@@ -502,7 +1160,13 @@ impl RenderEngine {
                 } else {
                     raw_line.as_str()
                 };
-                let vh = crate::partial_cache::PartialCache::compute_hash(content_trim);
+                let hash_clock = self.profiler.clock();
+                let matches = search_matches(state, content_trim);
+                let vh = crate::partial_cache::PartialCache::compute_display_hash(
+                    content_trim,
+                    &matches,
+                );
+                self.profiler.add_hash(hash_clock);
                 if let Some(entry) = self.cache.line_hashes.get(line_idx - viewport_first)
                     && entry.hash == vh.hash
                     && entry.len == vh.len
@@ -518,17 +1182,43 @@ impl RenderEngine {
                     self.metrics.trim_attempts.fetch_add(1, Relaxed);
                     let cache_row = line_idx - viewport_first;
                     let mut trimmed_success = false;
-                    if let Some(old_text) = self.cache.get_prev_text(cache_row)
-                        && let Some(tr) = self.try_trim_line(old_text, content_trim, w)
+                    // Only the changed span is painted, so lines whose
+                    // colours could also move before it (syntax, search
+                    // matches now or before, diagnostics, 'list' glyphs)
+                    // and shaded views repaint whole.
+                    if self
+                        .styles
+                        .line(state, state.active, line_idx, content_trim)
+                        .is_empty()
+                        && matches.is_empty()
+                        && state
+                            .diagnostics
+                            .line_spans(state.active, line_idx, content_trim)
+                            .is_empty()
+                        && line_virtual_text(state, state.active, line_idx, &shade).is_none()
+                        && crate::whitespace::listchars(state).is_none()
+                        && shade == CursorShade::default()
+                        && let Some(y) = single_row
+                        && let Some(old_text) = self.cache.get_prev_text(cache_row)
+                        && search_matches(state, old_text).is_empty()
+                        && let Some(tr) = self.try_trim_line(
+                            old_text,
+                            content_trim,
+                            w.saturating_sub(gutter.width),
+                        )
                     {
-                        // Emit: move to prefix, print interior, optionally clear suffix, then done.
-                        writer.move_to(tr.prefix_cols, rel_y);
-                        // Clear to end of line first if we need to guarantee removal of prior tail (line shrink case); conservative.
-                        if tr.clear_suffix {
-                            writer.clear_line(0, rel_y); // full clear to ensure no artifacts (simple for Phase 4)
-                            writer.move_to(tr.prefix_cols, rel_y);
-                        }
-                        writer.print(tr.interior);
+                        Self::paint_text_cells(
+                            &mut writer,
+                            &self.palette,
+                            &self.styles,
+                            state,
+                            &shade,
+                            gutter.width,
+                            &rows[y],
+                            content_trim,
+                            tr.cols.start + gutter.width..tr.cols.end + gutter.width,
+                            Some(y as u16),
+                        );
                         self.metrics.trim_success.fetch_add(1, Relaxed);
                         self.metrics
                             .cols_saved_total
@@ -536,9 +1226,19 @@ impl RenderEngine {
                         trimmed_success = true;
                     }
                     if !trimmed_success {
-                        writer.move_to(0, rel_y);
-                        writer.clear_line(0, rel_y);
-                        Self::paint_content_trim(&mut writer, content_trim, w);
+                        Self::paint_line(
+                            &mut writer,
+                            &self.palette,
+                            &self.styles,
+                            state,
+                            &gutter,
+                            &view.folds,
+                            &shade,
+                            &rows,
+                            line_idx,
+                            content_trim,
+                            w,
+                        );
                     }
                     // Update cache hash entry & stored text (store entire new content string).
                     if cache_row < self.cache.line_hashes.len()
@@ -555,32 +1255,45 @@ impl RenderEngine {
             }
         }
 
-        if let Some(span) =
-            self.compute_cursor_span(state, view, viewport_first, viewport_last_excl)
+        self.repaint_cursor_column(
+            &mut writer,
+            state,
+            &shade,
+            &rows,
+            &self.last_repaint_lines,
+            w,
+        );
+        self.cursor_cell = None;
+        if let Some((rel_y, span)) = self.compute_cursor_span(state, view, w, visible_rows)
             && span.start_col < w
         {
-            writer.move_to(span.start_col, (span.line - viewport_first) as u16);
+            self.cursor_cell = Some((span.start_col, rel_y));
+            writer.move_to(span.start_col, rel_y);
             self.print_cursor_with_fallback(&mut writer, state, view);
         }
         // Paint overlay (always repaint) then status line.
-        paint_overlay_rows_batch(&mut writer, state, w, h);
-        self.maybe_apply_external_status_line(&mut writer, status_line, h);
+        paint_overlay_rows_batch(&mut writer, state, w, h, &mut self.overlay_painted);
+        self.maybe_apply_external_status_line(&mut writer, status_line, w, h);
 
-        let (print_cmds, cells) = writer.flush()?;
+        let (print_cmds, cells) = self.flush_writer(writer)?;
         self.metrics
             .dirty_lines_repainted
             .fetch_add(repainted, Relaxed);
         let dur = start_time.elapsed().as_nanos() as u64;
-        self.metrics.last_partial_render_ns.store(dur, Relaxed);
+        self.record_frame(FramePath::Lines, dur);
         self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
         self.metrics.cells_printed.fetch_add(cells, Relaxed);
         self.cache.last_cursor_line = Some(curr_cursor);
+        self.cache.last_cursor_col = shade.column;
+        self.cache.rows = rows;
+        self.finish_popups(state, view, w, h, status_line)?;
         Ok(())
     }
 
     /// Phase 4 Step 10: scroll-region shift partial path. Assumes scheduler has
-    /// already gated on small delta (<= SCROLL_SHIFT_MAX). We perform a terminal
-    /// scroll (up/down) and repaint only the newly exposed lines plus the cursor line.
+    /// already gated on the delta (below the viewport height, so page motions
+    /// qualify). We perform a terminal scroll (up/down) and repaint only the
+    /// newly exposed lines plus the cursor line.
     /// If cache is invalid (viewport start/width mismatch) we fallback to full.
     #[allow(clippy::too_many_arguments)]
     pub fn render_scroll_shift(
@@ -594,6 +1307,7 @@ impl RenderEngine {
         new_first: usize,
         status_line: &str,
     ) -> Result<()> {
+        self.split_frame = None;
         use std::sync::atomic::Ordering::Relaxed;
         if h == 0 || w == 0 {
             return Ok(());
@@ -615,9 +1329,21 @@ impl RenderEngine {
         }
 
         // If cache is cold or mismatched (different width / start), fallback to full (safety first).
+        // The terminal shifts whole rows, so wrapped lines on either side
+        // (or folds) on either side of the scroll fall back too.
+        let (gutter, gutter_stale) = self.gutter_stale(state, view);
+        let rows = screen_rows(
+            state.active_buffer(),
+            view_wrap(state, view, w),
+            &view.folds,
+            new_first,
+            visible_rows,
+        );
         if self.cache.width != w
             || self.cache.viewport_start != old_first
             || self.cache.line_hashes.len() != visible_rows
+            || gutter_stale
+            || !(crate::wrap::unwrapped(&rows) && rows_match(&rows, &self.cache.rows))
         {
             self.metrics
                 .scroll_shift_degraded_full
@@ -632,10 +1358,11 @@ impl RenderEngine {
         }
 
         let start_time = std::time::Instant::now();
+        self.profiler.begin(state.profile_frames);
         self.last_repaint_lines.clear();
         self.last_repaint_kind = Some("scroll_shift");
 
-        let mut writer = BatchWriter::new();
+        let mut writer = self.writer();
         // 1. Set scroll region to text area (1-indexed rows in ANSI: top=1 bottom=text_height)
         // Reset at end to entire screen (CSI r).
         writer.print(format!("\x1b[1;{}r", text_height));
@@ -651,6 +1378,7 @@ impl RenderEngine {
         }
 
         let buf = state.active_buffer();
+        let shade = CursorShade::for_view(state, view, w);
         let entering_count = delta.unsigned_abs() as usize;
         let new_viewport_first = new_first;
         // Track how many lines we explicitly repaint (entering + potential old cursor line)
@@ -662,18 +1390,31 @@ impl RenderEngine {
             for i in 0..entering_count {
                 let row = visible_rows - entering_count + i; // viewport row index
                 let buf_line = new_viewport_first + row; // buffer line index
-                writer.move_to(0, row as u16);
-                writer.clear_line(0, row as u16);
                 if let Some(raw_line) = buf.line(buf_line) {
                     let content_trim: &str = if raw_line.ends_with(['\n', '\r']) {
                         &raw_line[..raw_line.len() - 1]
                     } else {
                         raw_line.as_str()
                     };
-                    Self::paint_content_trim(&mut writer, content_trim, w);
+                    Self::paint_line(
+                        &mut writer,
+                        &self.palette,
+                        &self.styles,
+                        state,
+                        &gutter,
+                        &view.folds,
+                        &shade,
+                        &rows,
+                        buf_line,
+                        content_trim,
+                        w,
+                    );
                     if row < self.cache.prev_text.len() {
                         self.cache.set_prev_text(row, content_trim.to_string());
                     }
+                } else {
+                    writer.move_to(0, row as u16);
+                    writer.clear_line(0, row as u16);
                 }
                 self.last_repaint_lines.push(buf_line);
             }
@@ -684,18 +1425,31 @@ impl RenderEngine {
                 // repaint top entering lines
                 let row = i; // viewport row index
                 let buf_line = new_viewport_first + row;
-                writer.move_to(0, row as u16);
-                writer.clear_line(0, row as u16);
                 if let Some(raw_line) = buf.line(buf_line) {
                     let content_trim: &str = if raw_line.ends_with(['\n', '\r']) {
                         &raw_line[..raw_line.len() - 1]
                     } else {
                         raw_line.as_str()
                     };
-                    Self::paint_content_trim(&mut writer, content_trim, w);
+                    Self::paint_line(
+                        &mut writer,
+                        &self.palette,
+                        &self.styles,
+                        state,
+                        &gutter,
+                        &view.folds,
+                        &shade,
+                        &rows,
+                        buf_line,
+                        content_trim,
+                        w,
+                    );
                     if row < self.cache.prev_text.len() {
                         self.cache.set_prev_text(row, content_trim.to_string());
                     }
+                } else {
+                    writer.move_to(0, row as u16);
+                    writer.clear_line(0, row as u16);
                 }
                 self.last_repaint_lines.push(buf_line);
             }
@@ -704,52 +1458,76 @@ impl RenderEngine {
         // 3b. Repaint old cursor line (if it remains visible and differs from current cursor line)
         // to clear stale reverse-video styling left by previous frame. All other partial paths
         // repaint the old cursor line first; scroll shift must do the same for invariant parity.
+        // With `cursorline` the new cursor row is repainted as well to take the shading.
         let old_cursor_opt = self.cache.last_cursor_line;
         let cursor_line = view.cursor.line; // (moved earlier from later overlay section)
-        if let Some(old_cursor) = old_cursor_opt
-            && old_cursor != cursor_line
-            && old_cursor >= new_viewport_first
-            && old_cursor < new_viewport_first + visible_rows
-            && !self.last_repaint_lines.contains(&old_cursor)
-        {
+        let stale_rows = old_cursor_opt
+            .filter(|old| *old != cursor_line)
+            .into_iter()
+            .chain(shade.line)
+            .chain(shade.blame);
+        for stale in stale_rows {
+            if stale < new_viewport_first
+                || stale >= new_viewport_first + visible_rows
+                || self.last_repaint_lines.contains(&stale)
+            {
+                continue;
+            }
             // Repaint full line content (without cursor styling yet) to erase old cursor highlight.
-            let rel_y = (old_cursor - new_viewport_first) as u16;
-            writer.move_to(0, rel_y);
-            writer.clear_line(0, rel_y);
-            if let Some(raw_line) = buf.line(old_cursor) {
+            if let Some(raw_line) = buf.line(stale) {
                 let content_trim: &str = if raw_line.ends_with(['\n', '\r']) {
                     &raw_line[..raw_line.len() - 1]
                 } else {
                     raw_line.as_str()
                 };
-                Self::paint_content_trim(&mut writer, content_trim, w);
-                let rel_row = old_cursor - new_viewport_first;
+                Self::paint_line(
+                    &mut writer,
+                    &self.palette,
+                    &self.styles,
+                    state,
+                    &gutter,
+                    &view.folds,
+                    &shade,
+                    &rows,
+                    stale,
+                    content_trim,
+                    w,
+                );
+                let rel_row = stale - new_viewport_first;
                 if rel_row < self.cache.prev_text.len() {
                     self.cache.set_prev_text(rel_row, content_trim.to_string());
                 }
             }
-            self.last_repaint_lines.push(old_cursor);
+            self.last_repaint_lines.push(stale);
             repainted_lines_count += 1;
         }
 
-        // 4. Cursor overlay (always ensure current cursor cluster styled on top of scrolled content).
-        if let Some(span) = self.compute_cursor_span(
+        // 3c. Shifted rows keep the previous `cursorcolumn` stripe.
+        self.repaint_cursor_column(
+            &mut writer,
             state,
-            view,
-            new_viewport_first,
-            new_viewport_first + visible_rows,
-        ) && span.start_col < w
+            &shade,
+            &rows,
+            &self.last_repaint_lines,
+            w,
+        );
+
+        // 4. Cursor overlay (always ensure current cursor cluster styled on top of scrolled content).
+        self.cursor_cell = None;
+        if let Some((rel_y, span)) = self.compute_cursor_span(state, view, w, visible_rows)
+            && span.start_col < w
         {
-            writer.move_to(span.start_col, (span.line - new_viewport_first) as u16);
+            self.cursor_cell = Some((span.start_col, rel_y));
+            writer.move_to(span.start_col, rel_y);
             self.print_cursor_with_fallback(&mut writer, state, view);
         }
 
         // 5. Status line repaint (cursor column, dirty flag, etc.) with skip logic.
         writer.print("\x1b[r");
-        paint_overlay_rows_batch(&mut writer, state, w, h);
-        self.maybe_apply_external_status_line(&mut writer, status_line, h);
+        paint_overlay_rows_batch(&mut writer, state, w, h, &mut self.overlay_painted);
+        self.maybe_apply_external_status_line(&mut writer, status_line, w, h);
 
-        let (print_cmds, cells) = writer.flush()?;
+        let (print_cmds, cells) = self.flush_writer(writer)?;
 
         // 6. Metrics & cache updates.
         self.metrics.partial_frames.fetch_add(1, Relaxed);
@@ -761,42 +1539,230 @@ impl RenderEngine {
         self.metrics
             .dirty_lines_repainted
             .fetch_add(repainted_lines_count as u64, Relaxed);
-        let dur = start_time.elapsed().as_nanos() as u64;
-        self.metrics.last_partial_render_ns.store(dur, Relaxed);
         self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
         self.metrics.cells_printed.fetch_add(cells, Relaxed);
 
         // Shift & update cache via dedicated API (Phase 4 Step 11 abstraction).
+        let hash_clock = self.profiler.clock();
         self.cache
-            .shift_for_scroll(delta, new_viewport_first, visible_rows, |idx| buf.line(idx));
+            .shift_for_scroll(delta, new_viewport_first, visible_rows, |idx| {
+                crate::region_cache::line_hash(state, state.active, idx)
+            });
+        self.profiler.add_hash(hash_clock);
+        let dur = start_time.elapsed().as_nanos() as u64;
+        self.record_frame(FramePath::ScrollShift, dur);
         self.cache.last_cursor_line = Some(cursor_line);
+        self.cache.last_cursor_col = shade.column;
+        self.cache.rows = rows;
+        self.finish_popups(state, view, w, h, status_line)?;
         Ok(())
     }
 
-    pub fn test_last_repaint_lines(&self) -> &[usize] {
-        &self.last_repaint_lines
-    }
-    pub fn test_last_repaint_kind(&self) -> Option<&'static str> {
-        self.last_repaint_kind
+    pub fn popups(&self) -> &PopupLayer {
+        &self.popups
     }
 
-    /// Phase 4 Step 11 test hook: expose current cache line hashes (immutable) for
-    /// verifying shift-for-scroll reuse and recompute behavior.
-    pub fn test_cache_hashes(&self) -> &[crate::partial_cache::ViewportLineHash] {
-        &self.cache.line_hashes
+    pub fn popups_mut(&mut self) -> &mut PopupLayer {
+        &mut self.popups
     }
 
-    /// Phase 4 Step 11 test hook: expose viewport_start for cache parity assertions.
-    pub fn test_cache_viewport_start(&self) -> usize {
-        self.cache.viewport_start
+    /// Repaint only popup damage (shown, changed or dismissed popups) when
+    /// nothing underneath changed.
+    pub fn render_popups(
+        &mut self,
+        state: &EditorState,
+        view: &View,
+        w: u16,
+        h: u16,
+        status_line: &str,
+    ) -> Result<()> {
+        self.finish_popups(state, view, w, h, status_line)
     }
 
-    /// Phase 4 Step 12 test hook: expose previously painted text for a relative row.
-    pub fn test_prev_text(&self, rel_row: usize) -> Option<&str> {
-        self.cache.prev_text.get(rel_row).and_then(|o| o.as_deref())
-    }
+    /// Last step of every render path: restore cells exposed by dismissed
+    /// popups from the frame underneath (the split frame, else the
+    /// single-view frame rebuilt for the occasion), then draw every popup in
+    /// z order over whatever the path just emitted. With the hardware
+    /// cursor it then places, shapes and shows the cursor.
+    fn finish_popups(
+        &mut self,
+        state: &EditorState,
+        view: &View,
+        w: u16,
+        h: u16,
+        status_line: &str,
+    ) -> Result<()> {
+        self.popups.set_screen(w, h);
+        self.sync_completion_popup(state, view, w, h);
+        self.sync_hover_popup(state, w, h);
+        let damage = self.popups.take_damage();
+        let popups = !(self.popups.is_empty() && damage.exposed.is_empty());
+        if !popups && !self.hardware_cursor {
+            return Ok(());
+        }
+        let mut writer = self.writer();
+        if !damage.exposed.is_empty() {
+            let under = match &self.split_frame {
+                Some(frame) if frame.width == w && frame.height == h => frame.clone(),
+                _ => self.single_view_underlay(state, view, w, h, status_line),
+            };
+            for r in &damage.exposed {
+                write_area(&mut writer, &self.palette, &under, *r);
+            }
+        }
+        if popups {
+            let mut top = Frame::new(w, h);
+            self.popups.paint(&mut top);
+            for popup in self.popups.ordered() {
+                write_area(&mut writer, &self.palette, &top, popup.region(w, h));
+            }
+        }
+        if self.hardware_cursor {
+            self.place_cursor(&mut writer, state, w, h);
+        }
+        let (print_cmds, cells) = self.flush_writer(writer)?;
+        use std::sync::atomic::Ordering::Relaxed;
+        self.metrics.print_commands.fetch_add(print_cmds, Relaxed);
+        self.metrics.cells_printed.fetch_add(cells, Relaxed);
+        Ok(())
+    }
+
+    /// Show, move or dismiss the completion popup to match
+    /// `state.completion`, its text column aligned with the completed word.
+    fn sync_completion_popup(&mut self, state: &EditorState, view: &View, w: u16, h: u16) {
+        let popup = state
+            .completion
+            .active()
+            .filter(|c| c.buffer == state.active && matches!(state.mode, Mode::Insert))
+            .zip(self.cursor_cell)
+            .and_then(|(completion, (x, y))| {
+                let line = state
+                    .active_buffer()
+                    .line(view.cursor.line)
+                    .unwrap_or_default();
+                let typed = grapheme::visual_col(&line, view.cursor.byte.min(line.len()))
+                    .saturating_sub(grapheme::visual_col(&line, completion.start.byte));
+                let bottom = h.saturating_sub(1 + overlay_line_count(state, w));
+                Popup::completion(completion, x.saturating_sub(typed as u16), y, bottom)
+            });
+        sync_popup(&mut self.popups, &mut self.completion_popup, popup);
+    }
+
+    /// Show, scroll or dismiss the hover popup to match `state.hover`.
+    fn sync_hover_popup(&mut self, state: &EditorState, w: u16, h: u16) {
+        let popup = state
+            .hover
+            .active()
+            .filter(|hover| hover.buffer == state.active)
+            .zip(self.cursor_cell)
+            .map(|(hover, (x, y))| {
+                let bottom = h.saturating_sub(1 + overlay_line_count(state, w));
+                Popup::hover(hover, x, y, bottom)
+            });
+        sync_popup(&mut self.popups, &mut self.hover_popup, popup);
+    }
+
+    /// Move the hardware cursor to the cursor cell and show it in the
+    /// mode's shape, unless a popup covers the cell.
+    fn place_cursor(&self, writer: &mut BatchWriter, state: &EditorState, w: u16, h: u16) {
+        let Some((x, y)) = self.cursor_cell.filter(|&(x, y)| x < w && y < h) else {
+            return;
+        };
+        let covered = self.popups.ordered().iter().any(|popup| {
+            let r = popup.region(w, h);
+            (r.x..r.x + r.width).contains(&x) && (r.y..r.y + r.height).contains(&y)
+        });
+        if covered {
+            return;
+        }
+        writer.move_to(x, y);
+        writer.set_cursor_style(cursor_shape(state.mode));
+        writer.show_cursor();
+    }
+
+    /// What the single-view paths show on screen: text, cursor, overlay and
+    /// status rows.
+    fn single_view_underlay(
+        &self,
+        state: &EditorState,
+        view: &View,
+        w: u16,
+        h: u16,
+        status_line: &str,
+    ) -> Frame {
+        let overlay_lines = overlay_line_count(state, w);
+        let text_height = h.saturating_sub(1 + overlay_lines);
+        let mut frame = Frame::new(w, h);
+        frame.blit(
+            &build_view_frame_styled(state, view, w, text_height, &self.styles),
+            0,
+            0,
+        );
+        if let Some((rel_y, span)) = self.compute_cursor_span(state, view, w, text_height as usize)
+            && span.start_col < w
+        {
+            frame.apply_flags_span(span.start_col, rel_y, span.width(), self.cursor_flags());
+        }
+        if h > 0 {
+            paint_overlay_into_frame(&mut frame, state, overlay_lines, w, h);
+            apply_external_status_line(status_line, &mut frame, w, h);
+            frame.apply_flags_span(0, h - 1, w, CellFlags::STATUS);
+        }
+        frame
+    }
+
+    pub fn test_last_repaint_lines(&self) -> &[usize] {
+        &self.last_repaint_lines
+    }
+    pub fn test_last_repaint_kind(&self) -> Option<&'static str> {
+        self.last_repaint_kind
+    }
+    pub fn test_last_repaint_views(&self) -> &[ViewId] {
+        &self.last_repaint_views
+    }
+
+    /// Phase 4 Step 11 test hook: expose current cache line hashes (immutable) for
+    /// verifying shift-for-scroll reuse and recompute behavior.
+    pub fn test_cache_hashes(&self) -> &[crate::partial_cache::ViewportLineHash] {
+        &self.cache.line_hashes
+    }
+
+    /// Phase 4 Step 11 test hook: expose viewport_start for cache parity assertions.
+    pub fn test_cache_viewport_start(&self) -> usize {
+        self.cache.viewport_start
+    }
+
+    /// Phase 4 Step 12 test hook: expose previously painted text for a relative row.
+    pub fn test_prev_text(&self, rel_row: usize) -> Option<&str> {
+        self.cache.prev_text.get(rel_row).and_then(|o| o.as_deref())
+    }
 
     /// Access a snapshot of current metrics (for tests and future status integration).
+    /// Flush `writer`, crediting its write and flush time to the profiler.
+    fn flush_writer(&mut self, writer: BatchWriter) -> Result<(u64, u64)> {
+        if let Some(capture) = self.capture.as_mut() {
+            let (commands, stats) = writer.into_commands();
+            capture.record(commands);
+            return Ok((stats.print_commands, stats.cells_printed));
+        }
+        let stats = writer.flush_timed()?;
+        self.profiler.add_io(stats.write_ns, stats.flush_ns);
+        Ok((stats.print_commands, stats.cells_printed))
+    }
+
+    /// Record a frame of `path` that took `ns`: a latency sample and, while
+    /// profiling, its stage split.
+    fn record_frame(&mut self, path: FramePath, ns: u64) {
+        self.metrics.latency.record(path, ns);
+        self.profiler.finish(path, ns);
+    }
+
+    /// Stage splits of the last profiled frames, oldest first (empty unless
+    /// `EditorState::profile_frames` is set).
+    pub fn frame_profile(&self) -> impl Iterator<Item = &FrameStages> {
+        self.profiler.frames()
+    }
+
     pub fn metrics_snapshot(&self) -> RenderPathMetricsSnapshot {
         self.metrics.snapshot()
     }
@@ -806,54 +1772,314 @@ impl RenderEngine {
         self.cache.last_cursor_line
     }
 
-    // Step 9: single source of truth for cursor style span.
+    /// Gutter for `view`, and whether the cached rows were painted with a
+    /// different one: its width changed, or relative labels were measured
+    /// from another cursor line.
+    fn gutter_stale(&self, state: &EditorState, view: &View) -> (Gutter, bool) {
+        let gutter = Gutter::for_view(state, view);
+        let stale = gutter.width != self.cache.gutter_width
+            || (gutter.is_relative() && self.cache.last_cursor_line != Some(view.cursor.line));
+        (gutter, stale)
+    }
+
+    // Step 9: single source of truth for cursor style span. Returns the
+    // cursor's row among the `height` text rows of `view` painted `w` columns
+    // wide, with its span (whose `line` is the buffer line).
     fn compute_cursor_span(
         &self,
         state: &EditorState,
         view: &View,
-        viewport_start: usize,
-        viewport_end_excl: usize,
-    ) -> Option<StyleSpan> {
+        w: u16,
+        height: usize,
+    ) -> Option<(u16, StyleSpan)> {
         let buf = state.active_buffer();
-        if view.cursor.line < viewport_start
-            || view.cursor.line >= viewport_end_excl
-            || view.cursor.line >= buf.line_count()
-        {
-            return None;
-        }
+        let fillers = Fillers::for_view(state, view);
+        let (row, col) = cursor_cell_filled(buf, view_wrap(state, view, w), view, fillers, height)?;
         let line_content = buf.line(view.cursor.line)?;
         let content_trim: &str = if line_content.ends_with(['\n', '\r']) {
             &line_content[..line_content.len() - 1]
         } else {
             line_content.as_str()
         };
-        let vis_col = grapheme::visual_col(content_trim, view.cursor.byte) as u16;
-        let next_byte = core_text::grapheme::next_boundary(content_trim, view.cursor.byte);
-        let cluster = &content_trim[view.cursor.byte..next_byte];
-        let width = grapheme::cluster_width(cluster).max(1) as u16;
-        Some(StyleSpan {
-            line: view.cursor.line,
-            start_col: vis_col,
-            end_col: vis_col + width,
-            attr: StyleAttr::InvertCursor,
-        })
+        let vis_col = Gutter::for_view(state, view).width + col;
+        let width = if view.folds.closed_at(view.cursor.line).is_some() {
+            1
+        } else {
+            let next_byte = core_text::grapheme::next_boundary(content_trim, view.cursor.byte);
+            let cluster = &content_trim[view.cursor.byte..next_byte];
+            let line_col = core_text::wrap::line_col(content_trim, view.cursor.byte);
+            grapheme::cell_width(cluster, line_col).max(1) as u16
+        };
+        Some((
+            row,
+            StyleSpan {
+                line: view.cursor.line,
+                start_col: vis_col,
+                end_col: vis_col + width,
+                attr: StyleAttr::InvertCursor,
+            },
+        ))
+    }
+
+    /// Clear and repaint every row of `rows` showing buffer line `line`
+    /// (text `content_trim`); under `'wrap'` a line may own several rows.
+    #[allow(clippy::too_many_arguments)]
+    fn paint_line(
+        writer: &mut BatchWriter,
+        palette: &Palette,
+        styles: &StyleProviders,
+        state: &EditorState,
+        gutter: &Gutter,
+        folds: &Folds,
+        shade: &CursorShade,
+        rows: &[ScreenRow],
+        line: usize,
+        content_trim: &str,
+        w: u16,
+    ) {
+        for (y, row) in rows.iter().enumerate().filter(|(_, r)| r.line == line) {
+            let y = y as u16;
+            writer.move_to(0, y);
+            writer.clear_line(0, y);
+            Self::paint_screen_row(
+                writer,
+                palette,
+                styles,
+                state,
+                gutter,
+                folds,
+                shade,
+                row,
+                content_trim,
+                w,
+            );
+        }
     }
 
-    // Phase 4 Step 16: helper to emit a trimmed line's content to the BatchWriter.
+    // Phase 4 Step 16: helper to emit one text row to the BatchWriter.
     // Mirrors logic previously duplicated across partial paths (cursor-only, lines, scroll).
-    fn paint_content_trim(writer: &mut BatchWriter, content_trim: &str, w: u16) {
-        let mut byte = 0usize;
-        let mut vis_col: u16 = 0;
-        while byte < content_trim.len() && vis_col < w {
+    // The gutter label of the row's line goes first (blank on wrapped
+    // continuation rows) and narrows the text width.
+    // Clusters carry the colour of the active buffer's merged provider spans
+    // and the `Search` colour inside search matches, underlined where a
+    // diagnostic covers them; `shade` adds the cursor row / column
+    // backgrounds, padding shaded blanks past the text.
+    #[allow(clippy::too_many_arguments)]
+    fn paint_screen_row(
+        writer: &mut BatchWriter,
+        palette: &Palette,
+        styles: &StyleProviders,
+        state: &EditorState,
+        gutter: &Gutter,
+        folds: &Folds,
+        shade: &CursorShade,
+        row: &ScreenRow,
+        content_trim: &str,
+        w: u16,
+    ) {
+        if row.first {
+            writer.print(gutter.label(&state.signs, &state.diagnostics, folds, row.line));
+        } else if gutter.width > 0 {
+            writer.print(" ".repeat(gutter.width as usize));
+        }
+        Self::paint_text_cells(
+            writer,
+            palette,
+            styles,
+            state,
+            shade,
+            gutter.width,
+            row,
+            content_trim,
+            gutter.width..w,
+            None,
+        );
+    }
+
+    /// Emit the clusters of `row` (its `'showbreak'` marker, then its part of
+    /// `content_trim`) overlapping screen columns `cols`, text starting at
+    /// column `text_start`, then the shaded blanks past its end. With `y`
+    /// the writer first moves to the first emitted cell on that screen row;
+    /// without it the writer must already sit at `cols.start`, which must be
+    /// `text_start`. Cluster-aware parity: each cluster is emitted once,
+    /// wide clusters occupy their columns intrinsically.
+    #[allow(clippy::too_many_arguments)]
+    fn paint_text_cells(
+        writer: &mut BatchWriter,
+        palette: &Palette,
+        styles: &StyleProviders,
+        state: &EditorState,
+        shade: &CursorShade,
+        text_start: u16,
+        row: &ScreenRow,
+        content_trim: &str,
+        cols: std::ops::Range<u16>,
+        y: Option<u16>,
+    ) {
+        let line = row.line;
+        let styled = styles.line(state, state.active, line, content_trim);
+        let matches = search_matches(state, content_trim);
+        let diagnostics = state
+            .diagnostics
+            .line_spans(state.active, line, content_trim);
+        let marker = if row.row.indent > 0 {
+            state.options.get_string("showbreak")
+        } else {
+            ""
+        };
+        let lcs = crate::whitespace::listchars(state).filter(|_| row.fold.is_none());
+        let glyphs = lcs.as_ref().map(|lcs| LineGlyphs::new(lcs, content_trim));
+        let summary = row
+            .fold
+            .as_ref()
+            .map(|fold| fold_line(content_trim, fold.end() - fold.start() + 1))
+            .unwrap_or_default();
+        // Marker and fold summary clusters carry no byte offset: no syntax
+        // or search colour. `'list'` glyphs stand in for the whitespace they
+        // cover and take its width; a tab is printed as blanks up to the
+        // next tab stop. The third field holds flags of the cluster's kind
+        // (`WHITESPACE` glyphs, diagnostic virtual text), the fourth its
+        // width.
+        let plain = |g: &str| grapheme::cluster_width(g).max(1) as u16;
+        let mut clusters: Vec<(Cow<str>, Option<usize>, CellFlags, u16)> = grapheme::iter(marker)
+            .chain(grapheme::iter(&summary))
+            .map(|g| (Cow::Borrowed(g), None, CellFlags::empty(), plain(g)))
+            .collect();
+        let mut byte = row.row.bytes.start;
+        let mut line_col = core_text::wrap::line_col(content_trim, byte);
+        while byte < row.row.bytes.end {
             let next = grapheme::next_boundary(content_trim, byte);
             let cluster = &content_trim[byte..next];
-            let width = grapheme::cluster_width(cluster).max(1) as u16;
-            // Cluster-aware parity: emit the full cluster exactly once. Wide clusters
-            // occupy multiple terminal columns intrinsically; no synthetic space padding.
-            writer.print(cluster.to_string());
-            vis_col += width;
+            let width = grapheme::cell_width(cluster, line_col).max(1) as u16;
+            line_col += usize::from(width);
+            clusters.push(match glyphs.and_then(|g| g.cluster(byte, cluster, width)) {
+                Some(glyph) => (Cow::Owned(glyph), Some(byte), CellFlags::WHITESPACE, width),
+                None if cluster == "\t" => {
+                    let blanks = " ".repeat(usize::from(width));
+                    (Cow::Owned(blanks), Some(byte), CellFlags::empty(), width)
+                }
+                None => (
+                    Cow::Borrowed(cluster),
+                    Some(byte),
+                    CellFlags::empty(),
+                    width,
+                ),
+            });
             byte = next;
         }
+        if let Some(eol) = glyphs.and_then(|g| g.eol(row.row.bytes.end)) {
+            let eol = eol.to_string();
+            let width = plain(&eol);
+            clusters.push((Cow::Owned(eol), None, CellFlags::WHITESPACE, width));
+        }
+        let virtual_text = line_virtual_text(state, state.active, line, shade)
+            .filter(|_| row.fold.is_none() && row.row.bytes.end >= content_trim.len());
+        if let Some((text, flags)) = &virtual_text {
+            clusters.push((Cow::Borrowed(" "), None, CellFlags::empty(), 1));
+            clusters
+                .extend(grapheme::iter(text).map(|g| (Cow::Borrowed(g), None, *flags, plain(g))));
+        }
+        let mut positioned = y.is_none();
+        let mut col = text_start;
+        for (cluster, byte, kind, width) in clusters {
+            if col >= cols.end {
+                break;
+            }
+            if col + width > cols.start {
+                if let Some(y) = y.filter(|_| !positioned) {
+                    writer.move_to(col, y);
+                    positioned = true;
+                }
+                let mut flags = shade.flags(line, col, width);
+                if byte.is_some_and(|b| matches.iter().any(|m| m.contains(&b))) {
+                    flags |= CellFlags::SEARCH;
+                }
+                flags |= kind;
+                if let Some(b) = byte {
+                    flags |= diagnostic_flags(&diagnostics, b);
+                }
+                if row.fold.is_some() {
+                    flags |= CellFlags::FOLDED;
+                }
+                let (attr_flags, syntax) = byte
+                    .and_then(|b| line_attr_at(&styled, b))
+                    .map_or((CellFlags::empty(), None), StyleAttr::cell_style);
+                flags |= attr_flags;
+                writer.print(palette.styled(&cluster, flags, syntax));
+            }
+            col += width;
+        }
+        // A full row was cleared beforehand; a column window overwrites its
+        // blanks, shaded or not. A fold summary fills its row with `-`.
+        let (fill, fill_flags, fill_end) = match y {
+            _ if row.fold.is_some() => ("-", CellFlags::FOLDED, cols.end),
+            Some(_) => (" ", CellFlags::empty(), cols.end),
+            None => (" ", CellFlags::empty(), shade.fill_end(line).min(cols.end)),
+        };
+        if col < fill_end {
+            let from = col.max(cols.start);
+            if let Some(y) = y {
+                writer.move_to(from, y);
+            }
+            for x in from..fill_end {
+                let flags = shade.flags(line, x, 1) | fill_flags;
+                writer.print(palette.styled(fill, flags, None));
+            }
+        }
+    }
+
+    /// `cursorcolumn` moved: repaint the old and the new stripe on every
+    /// text row of `rows` whose line is not in `skip` (lines repainted whole
+    /// this frame), instead of escalating to a full frame. Returns the rows
+    /// touched.
+    #[allow(clippy::too_many_arguments)]
+    fn repaint_cursor_column(
+        &self,
+        writer: &mut BatchWriter,
+        state: &EditorState,
+        shade: &CursorShade,
+        rows: &[ScreenRow],
+        skip: &[usize],
+        w: u16,
+    ) -> usize {
+        let old = self.cache.last_cursor_col;
+        if shade.column == old {
+            return 0;
+        }
+        let buf = state.active_buffer();
+        let mut touched = 0;
+        for (y, row) in rows.iter().enumerate() {
+            if skip.contains(&row.line) {
+                continue;
+            }
+            let Some(raw) = buf.line(row.line) else {
+                continue;
+            };
+            let text = raw.trim_end_matches(['\n', '\r']);
+            for (start, stop) in old.into_iter().chain(shade.column) {
+                Self::paint_text_cells(
+                    writer,
+                    &self.palette,
+                    &self.styles,
+                    state,
+                    shade,
+                    shade.text_start,
+                    row,
+                    text,
+                    start..stop.min(w),
+                    Some(y as u16),
+                );
+            }
+            touched += 1;
+        }
+        tracing::trace!(
+            target: "render.engine",
+            rows = touched,
+            ?old,
+            new = ?shade.column,
+            "cursor_column_repaint"
+        );
+        touched
     }
 
     /// Print cursor cluster with fallback reversed space when cluster slice is empty.
@@ -863,8 +2089,15 @@ impl RenderEngine {
         state: &EditorState,
         view: &View,
     ) {
+        if self.hardware_cursor {
+            // The row under it is already painted; `finish_popups` shows it.
+            return;
+        }
         let buf = state.active_buffer();
-        if let Some(line) = buf.line(view.cursor.line) {
+        if view.folds.closed_at(view.cursor.line).is_some() {
+            // First cell of the fold summary.
+            writer.print("\x1b[7m+\x1b[0m");
+        } else if let Some(line) = buf.line(view.cursor.line) {
             let content_trim: &str = if line.ends_with(['\n', '\r']) {
                 &line[..line.len() - 1]
             } else {
@@ -873,7 +2106,15 @@ impl RenderEngine {
             let cursor_byte = view.cursor.byte.min(content_trim.len());
             let next = grapheme::next_boundary(content_trim, cursor_byte);
             let cluster = &content_trim[cursor_byte..next];
-            let printable = if cluster.is_empty() { " " } else { cluster };
+            // The terminal would move a tab to its own tab stop.
+            let printable = match cluster {
+                "" => Cow::Borrowed(" "),
+                "\t" => {
+                    let line_col = core_text::wrap::line_col(content_trim, cursor_byte);
+                    Cow::Owned(" ".repeat(grapheme::cell_width(cluster, line_col)))
+                }
+                cluster => Cow::Borrowed(cluster),
+            };
             writer.print(format!("\x1b[7m{printable}\x1b[0m"));
         } else {
             writer.print("\x1b[7m \x1b[0m");
@@ -887,6 +2128,7 @@ impl RenderEngine {
         &mut self,
         writer: &mut BatchWriter,
         status_line: &str,
+        w: u16,
         h: u16,
     ) {
         if h == 0 {
@@ -896,7 +2138,7 @@ impl RenderEngine {
         if status_line != self.prev_status {
             writer.move_to(0, status_y);
             writer.clear_line(0, status_y);
-            writer.print(status_line.to_string());
+            writer.print(self.palette.status(status_line, w));
             self.prev_status = status_line.to_string();
         } else {
             use std::sync::atomic::Ordering::Relaxed;
@@ -904,11 +2146,48 @@ impl RenderEngine {
         }
     }
 
+    /// Emit the rows of each area whose cells differ between `prev` and
+    /// `next`, limited to the area's columns. Areas are painted from
+    /// independently built frames, so no cluster straddles an area edge.
+    fn emit_changed_areas(
+        &mut self,
+        prev: &Frame,
+        next: &Frame,
+        areas: &[(Option<ViewId>, LayoutRegion)],
+    ) -> Result<(u64, u64)> {
+        let mut writer = self.writer();
+        self.last_repaint_views.clear();
+        for (view, r) in areas {
+            let x_end = r.x.saturating_add(r.width).min(next.width) as usize;
+            let y_end = r.y.saturating_add(r.height).min(next.height);
+            let mut touched = false;
+            for y in r.y..y_end {
+                let row_start = y as usize * next.width as usize;
+                let span = row_start + r.x as usize..row_start + x_end;
+                if prev.cells[span.clone()] == next.cells[span.clone()] {
+                    continue;
+                }
+                touched = true;
+                writer.move_to(r.x, y);
+                for cell in next.cells[span].iter().filter(|c| c.width > 0) {
+                    writer.print(cell.styled_with(&self.palette));
+                }
+            }
+            if touched
+                && let Some(id) = view
+                && !self.last_repaint_views.contains(id)
+            {
+                self.last_repaint_views.push(*id);
+            }
+        }
+        self.flush_writer(writer)
+    }
+
     /// Full-frame translation using Writer (originally introduced in Step 6 as a
     /// temporary bridge). Legacy `Renderer` removed in Refactor R3 Step 12; this now
     /// serves as the canonical full-frame emission path (until scroll-region +
     /// diff optimizations arrive). Behavior remains stable and parity-tested.
-    fn render_via_writer(&self, frame: &Frame) -> Result<(u64, u64)> {
+    fn render_via_writer(&mut self, frame: &Frame) -> Result<(u64, u64)> {
         // Cluster-aware emission (Unicode Cluster Refactor Step 4): iterate only
         // leader cells per row (skipping continuation cells) and emit each full
         // grapheme cluster exactly once. Any styling (e.g., REVERSE cursor span)
@@ -920,18 +2199,52 @@ impl RenderEngine {
         // helpers (paint_content_trim) still pad wide clusters with spaces for
         // now; that will be reconciled in the subsequent "Adjust partial paths"
         // step to unify behavior.
-        let mut writer = BatchWriter::new();
+        let mut writer = self.writer();
         for y in 0..frame.height {
             writer.move_to(0, y);
-            for (cluster, _w, flags, _x) in frame.row_leaders(y) {
-                if flags.contains(CellFlags::REVERSE) {
-                    writer.print(format!("\x1b[7m{cluster}\x1b[0m"));
-                } else {
-                    writer.print(cluster.to_string());
-                }
+            let row_start = y as usize * frame.width as usize;
+            for cell in frame.cells[row_start..row_start + frame.width as usize]
+                .iter()
+                .filter(|c| c.is_leader())
+            {
+                writer.print(cell.styled_with(&self.palette));
+            }
+        }
+        self.flush_writer(writer)
+    }
+}
+
+/// Show, update or dismiss the popup in `slot` so it matches `popup`.
+fn sync_popup(layer: &mut PopupLayer, slot: &mut Option<PopupId>, popup: Option<Popup>) {
+    match (popup, *slot) {
+        (Some(popup), Some(id)) => {
+            if layer.get(id) != Some(&popup) {
+                layer.update(id, popup);
             }
         }
-        writer.flush()
+        (Some(popup), None) => *slot = Some(layer.show(popup)),
+        (None, Some(id)) => {
+            layer.dismiss(id);
+            *slot = None;
+        }
+        (None, None) => {}
+    }
+}
+
+/// Write the cells of `frame` inside `r` (clipped to the frame), row by row.
+fn write_area(writer: &mut BatchWriter, palette: &Palette, frame: &Frame, r: LayoutRegion) {
+    let x_end = r.x.saturating_add(r.width).min(frame.width) as usize;
+    let x_start = (r.x as usize).min(x_end);
+    let y_end = r.y.saturating_add(r.height).min(frame.height);
+    for y in r.y..y_end {
+        let row_start = y as usize * frame.width as usize;
+        writer.move_to(r.x, y);
+        for cell in frame.cells[row_start + x_start..row_start + x_end]
+            .iter()
+            .filter(|c| c.width > 0)
+        {
+            writer.print(cell.styled_with(palette));
+        }
     }
 }
 
@@ -940,12 +2253,10 @@ impl RenderEngine {
 pub fn build_full_frame_for_test(state: &EditorState, view: &View, w: u16, h: u16) -> Frame {
     let eng = RenderEngine::new();
     let mut frame = build_content_frame(state, view, w, h);
-    let viewport_start = view.viewport_first_line;
-    let viewport_end_excl = viewport_start + h.saturating_sub(1) as usize;
-    if let Some(span) = eng.compute_cursor_span(state, view, viewport_start, viewport_end_excl)
+    if let Some((rel_y, span)) =
+        eng.compute_cursor_span(state, view, w, h.saturating_sub(1) as usize)
         && span.start_col < w
     {
-        let rel_y = (span.line - viewport_start) as u16;
         frame.apply_flags_span(
             span.start_col,
             rel_y,
@@ -961,33 +2272,280 @@ pub fn build_full_frame_for_test(state: &EditorState, view: &View, w: u16, h: u1
 pub fn build_content_frame(state: &EditorState, view: &View, w: u16, h: u16) -> Frame {
     let mut frame = Frame::new(w, h);
     let text_height = if h > 0 { h - 1 } else { 0 };
-    let buf = state.active_buffer();
-    let start = view.viewport_first_line;
-    let height = text_height as usize;
-    let end = (start + height).min(buf.line_count());
-    for (screen_y, line_idx) in (start..end).enumerate() {
-        if (screen_y as u16) >= text_height {
-            break;
+    frame.blit(&build_view_frame(state, view, w, text_height), 0, 0);
+    frame
+}
+
+/// Text of `view`'s own buffer (which need not be the active one) filling
+/// all `h` rows of a `w` x `h` frame, colored by the built-in span
+/// providers.
+pub fn build_view_frame(state: &EditorState, view: &View, w: u16, h: u16) -> Frame {
+    build_view_frame_styled(state, view, w, h, StyleProviders::builtin())
+}
+
+/// `build_view_frame` with the spans of `styles`.
+pub fn build_view_frame_styled(
+    state: &EditorState,
+    view: &View,
+    w: u16,
+    h: u16,
+    styles: &StyleProviders,
+) -> Frame {
+    build_view_frame_filled(state, view, w, h, styles, Fillers::for_view(state, view))
+}
+
+/// `build_view_frame_styled` with the diff filler rows of `fillers`.
+fn build_view_frame_filled(
+    state: &EditorState,
+    view: &View,
+    w: u16,
+    h: u16,
+    styles: &StyleProviders,
+    fillers: Fillers,
+) -> Frame {
+    let mut frame = Frame::new(w, h);
+    let Some(entry) = state.buffers.get(view.buffer_id) else {
+        return frame;
+    };
+    if let Some(bytes) = entry.meta.binary.as_deref().filter(|_| entry.meta.hex_view) {
+        crate::hex::paint_hex_rows(&mut frame, bytes, view.viewport_first_line, h, w);
+        return frame;
+    }
+    let wrap = view_wrap(state, view, w);
+    let rows = screen_rows_filled(
+        &entry.buffer,
+        wrap,
+        &view.folds,
+        fillers,
+        view.viewport_first_line,
+        h as usize,
+    );
+    paint_view_rows(&mut frame, state, view, &rows, styles);
+    frame
+}
+
+/// Paint `rows` of `view`'s buffer into `frame`, `rows[i]` on frame row `i`:
+/// the gutter label (blank on wrapped continuation rows), the `'showbreak'`
+/// marker, then the clusters with their merged provider spans, search matches and
+/// diagnostic underlines, and finally the cursor shading. A closed fold's
+/// row shows its summary, a diff filler row dashes, and the lines of a diff
+/// hunk take its `DiffAdd` / `DiffChange` shading.
+fn paint_view_rows(
+    frame: &mut Frame,
+    state: &EditorState,
+    view: &View,
+    rows: &[ScreenRow],
+    styles: &StyleProviders,
+) {
+    let Some(entry) = state.buffers.get(view.buffer_id) else {
+        return;
+    };
+    let gutter = Gutter::for_view(state, view);
+    let w = frame.width;
+    let shade = CursorShade::for_view(state, view, w);
+    let marker = state.options.get_string("showbreak");
+    let lcs = crate::whitespace::listchars(state);
+    for (y, row) in rows.iter().enumerate() {
+        let screen_y = y as u16;
+        let line = entry.buffer.line(row.line).unwrap_or_default();
+        let content_trim = line.trim_end_matches(['\n', '\r']);
+        if row.first {
+            gutter.paint(
+                frame,
+                &state.signs,
+                &state.diagnostics,
+                &view.folds,
+                0,
+                screen_y,
+                row.line,
+            );
         }
-        if let Some(line) = buf.line(line_idx) {
-            let content_trim: &str = if line.ends_with('\n') || line.ends_with('\r') {
-                &line[..line.len() - 1]
-            } else {
-                &line
-            };
-            let mut byte = 0usize;
-            let mut vis_col: u16 = 0;
-            while byte < content_trim.len() && vis_col < w {
-                let next = core_text::grapheme::next_boundary(content_trim, byte);
-                let cluster = &content_trim[byte..next];
+        let mut vis_col: u16 = gutter.width;
+        if row.filler {
+            for x in vis_col..w {
+                frame.set_cluster(x, screen_y, "-", 1, CellFlags::DIFF_DELETE);
+            }
+            continue;
+        }
+        if let Some(fold) = &row.fold {
+            let summary = fold_line(content_trim, fold.end() - fold.start() + 1);
+            for cluster in grapheme::iter(&summary) {
+                let width = grapheme::cluster_width(cluster).max(1) as u16;
+                if vis_col + width > w {
+                    break;
+                }
+                frame.set_cluster(vis_col, screen_y, cluster, width, CellFlags::FOLDED);
+                vis_col += width;
+            }
+            for x in vis_col..w {
+                frame.set_cluster(x, screen_y, "-", 1, CellFlags::FOLDED);
+            }
+            continue;
+        }
+        if row.row.indent > 0 {
+            for cluster in grapheme::iter(marker) {
                 let width = grapheme::cluster_width(cluster).max(1) as u16;
-                frame.set_cluster(vis_col, screen_y as u16, cluster, width, CellFlags::empty());
+                frame.set_cluster(vis_col, screen_y, cluster, width, CellFlags::empty());
                 vis_col = vis_col.saturating_add(width);
-                byte = next;
+            }
+        }
+        let styled = styles.line(state, view.buffer_id, row.line, content_trim);
+        let matches = search_matches(state, content_trim);
+        let diagnostics = state
+            .diagnostics
+            .line_spans(view.buffer_id, row.line, content_trim);
+        let glyphs = lcs.as_ref().map(|lcs| LineGlyphs::new(lcs, content_trim));
+        let mut byte = row.row.bytes.start;
+        let mut line_col = core_text::wrap::line_col(content_trim, byte);
+        while byte < row.row.bytes.end && vis_col < w {
+            let next = core_text::grapheme::next_boundary(content_trim, byte);
+            let cluster = &content_trim[byte..next];
+            let width = grapheme::cell_width(cluster, line_col).max(1) as u16;
+            line_col += usize::from(width);
+            match glyphs.and_then(|g| g.cluster(byte, cluster, width)) {
+                Some(glyph) => {
+                    for (i, c) in glyph.chars().enumerate() {
+                        let cell = c.encode_utf8(&mut [0; 4]).to_string();
+                        frame.set_cluster(
+                            vis_col + i as u16,
+                            screen_y,
+                            &cell,
+                            1,
+                            CellFlags::empty(),
+                        );
+                    }
+                    frame.apply_flags_span(vis_col, screen_y, width, CellFlags::WHITESPACE);
+                }
+                // A tab is blank cells up to the next tab stop.
+                None if cluster == "\t" => {
+                    for x in vis_col..vis_col.saturating_add(width).min(w) {
+                        frame.set_cluster(x, screen_y, " ", 1, CellFlags::empty());
+                    }
+                }
+                None => frame.set_cluster(vis_col, screen_y, cluster, width, CellFlags::empty()),
+            }
+            if let Some(attr) = line_attr_at(&styled, byte) {
+                let (flags, class) = attr.cell_style();
+                if let Some(class) = class {
+                    frame.apply_syntax_span(vis_col, screen_y, width, class);
+                }
+                if !flags.is_empty() {
+                    frame.apply_flags_span(vis_col, screen_y, width, flags);
+                }
+            }
+            if matches.iter().any(|m| m.contains(&byte)) {
+                frame.apply_flags_span(vis_col, screen_y, width, CellFlags::SEARCH);
+            }
+            let underline = diagnostic_flags(&diagnostics, byte);
+            if !underline.is_empty() {
+                frame.apply_flags_span(vis_col, screen_y, width, underline);
+            }
+            vis_col = vis_col.saturating_add(width);
+            byte = next;
+        }
+        if let Some(eol) = glyphs.and_then(|g| g.eol(row.row.bytes.end))
+            && vis_col < w
+        {
+            frame.set_cluster(
+                vis_col,
+                screen_y,
+                eol.encode_utf8(&mut [0; 4]),
+                1,
+                CellFlags::WHITESPACE,
+            );
+            vis_col += 1;
+        }
+        if row.row.bytes.end >= content_trim.len()
+            && let Some((text, flags)) = line_virtual_text(state, view.buffer_id, row.line, &shade)
+        {
+            vis_col = vis_col.saturating_add(1);
+            for cluster in grapheme::iter(&text) {
+                let width = grapheme::cluster_width(cluster).max(1) as u16;
+                if vis_col + width > w {
+                    break;
+                }
+                frame.set_cluster(vis_col, screen_y, cluster, width, flags);
+                vis_col += width;
+            }
+        }
+        let diff = match state.diff.line_kind(view.buffer_id, row.line) {
+            Some(DiffKind::Added) => CellFlags::DIFF_ADD,
+            Some(DiffKind::Changed) => CellFlags::DIFF_CHANGE,
+            None => CellFlags::empty(),
+        };
+        if !diff.is_empty() {
+            frame.apply_flags_span(gutter.width, screen_y, w.saturating_sub(gutter.width), diff);
+        }
+    }
+    apply_cursor_shade(frame, &shade, rows);
+}
+
+/// Flag the cells `shade` covers on the frame rows showing `rows`: every
+/// row of the cursor line past the gutter, the cursor column and the
+/// `colorcolumn` rulers. A wide cluster straddling a column is flagged
+/// through its leader, which is the cell the writer styles.
+fn apply_cursor_shade(frame: &mut Frame, shade: &CursorShade, rows: &[ScreenRow]) {
+    let w = frame.width;
+    for (y, screen_row) in rows.iter().enumerate().take(frame.height as usize) {
+        let row = y as u16;
+        if shade.line == Some(screen_row.line) && !screen_row.filler {
+            frame.apply_flags_span(
+                shade.text_start,
+                row,
+                w.saturating_sub(shade.text_start),
+                CellFlags::CURSORLINE,
+            );
+        }
+        let columns = shade
+            .column
+            .map(|span| (span, CellFlags::CURSORCOLUMN))
+            .into_iter()
+            .chain(
+                shade
+                    .rulers
+                    .iter()
+                    .map(|x| ((*x, x + 1), CellFlags::COLORCOLUMN)),
+            );
+        for ((start, end), flags) in columns.filter(|((start, _), _)| *start < w) {
+            frame.apply_flags_span(start, row, end - start, flags);
+            let row_start = y * w as usize;
+            if !frame.cells[row_start + start as usize].is_leader()
+                && let Some(leader) = frame.cells[row_start..row_start + start as usize]
+                    .iter_mut()
+                    .rev()
+                    .find(|cell| cell.is_leader())
+            {
+                leader.flags |= flags;
             }
         }
     }
-    frame
+}
+
+/// Paint the metrics overlay rows just above the status row.
+fn paint_overlay_into_frame(
+    frame: &mut Frame,
+    state: &EditorState,
+    overlay_lines: u16,
+    w: u16,
+    h: u16,
+) {
+    if overlay_lines == 0 || overlay_lines >= h {
+        return;
+    }
+    let lines = build_overlay_lines(state, w);
+    let first_row = h - 1 - overlay_lines;
+    for (row, l) in (first_row..).zip(lines.iter().take(overlay_lines as usize)) {
+        let mut byte = 0usize;
+        let mut x: u16 = 0;
+        while byte < l.len() && x < w {
+            let next = grapheme::next_boundary(l, byte);
+            let cluster = &l[byte..next];
+            let width = grapheme::cluster_width(cluster).max(1) as u16;
+            frame.set_cluster(x, row, cluster, width, CellFlags::empty());
+            x = x.saturating_add(width);
+            byte = next;
+        }
+    }
 }
 
 fn apply_status_line(state: &EditorState, view: &View, frame: &mut Frame, w: u16, h: u16) {
@@ -1005,14 +2563,21 @@ fn apply_status_line(state: &EditorState, view: &View, frame: &mut Frame, w: u16
         &line_content
     };
     let col = grapheme::visual_col(content_trim, view.cursor.byte);
+    let segments = state.status_segments.visible();
     let status = crate::status::build_status(&crate::status::StatusContext {
         mode: state.mode,
         line: view.cursor.line,
         col,
+        byte: view.cursor.byte,
+        percent: file_percent(buf, view.cursor.line),
+        selection: state.selection_size(),
         command_active: state.command_line.is_active(),
         command_buffer: state.command_line.buffer(),
-        file_name: state.file_name.as_deref(),
-        dirty: state.dirty,
+        file_name: state.status_file_name(),
+        dirty: state.dirty(),
+        diagnostics: state.diagnostics.counts(state.active),
+        git: state.git.status.as_ref(),
+        segments: &segments,
     });
     for (i, ch) in status.chars().enumerate() {
         if (i as u16) < w {
@@ -1038,9 +2603,8 @@ fn apply_status_line(state: &EditorState, view: &View, frame: &mut Frame, w: u16
     }
 }
 
-// Step 10 (in-progress): external status line builder (will replace internal usage paths).
-pub fn build_status_line(state: &EditorState, view: &View) -> String {
-    let buf = state.active_buffer();
+/// Visual column of `view`'s cursor within `buf`.
+fn cursor_visual_col(buf: &core_text::Buffer, view: &View) -> usize {
     let line_content = buf.line(view.cursor.line).unwrap_or_default();
     let content_trim: &str = if line_content.ends_with("\r\n") {
         &line_content[..line_content.len() - 2]
@@ -1049,18 +2613,107 @@ pub fn build_status_line(state: &EditorState, view: &View) -> String {
     } else {
         &line_content
     };
-    let col = grapheme::visual_col(content_trim, view.cursor.byte);
-    crate::status::build_status(&crate::status::StatusContext {
-        mode: state.mode,
-        line: view.cursor.line,
+    grapheme::visual_col(content_trim, view.cursor.byte)
+}
+
+/// How far through `buf` line `line` is, in percent of the lines holding
+/// content (Vim's `CTRL-G`).
+fn file_percent(buf: &core_text::Buffer, line: usize) -> usize {
+    let count = buf.line_count();
+    let lines = if count > 1 && buf.line_byte_len(count - 1) == 0 {
+        count - 1
+    } else {
+        count
+    };
+    ((line + 1) * 100 / lines.max(1)).min(100)
+}
+
+// Step 10 (in-progress): external status line builder (will replace internal usage paths).
+pub fn build_status_line(state: &EditorState, view: &View) -> String {
+    let buf = state.active_buffer();
+    let col = cursor_visual_col(buf, view);
+    let segments = state.status_segments.visible();
+    crate::status::build_status(&crate::status::StatusContext {
+        mode: state.mode,
+        line: view.cursor.line,
         col,
+        byte: view.cursor.byte,
+        percent: file_percent(buf, view.cursor.line),
+        selection: state.selection_size(),
         command_active: state.command_line.is_active(),
         command_buffer: state.command_line.buffer(),
-        file_name: state.file_name.as_deref(),
-        dirty: state.dirty,
+        file_name: state.status_file_name(),
+        dirty: state.dirty(),
+        diagnostics: state.diagnostics.counts(state.active),
+        git: state.git.status.as_ref(),
+        segments: &segments,
+    })
+}
+
+/// Status row of one view in a split layout, read from the view's own
+/// buffer. The `focused` view's row also shows the mode.
+pub fn build_view_status_line(state: &EditorState, view: &View, focused: bool) -> String {
+    let Some(entry) = state.buffers.get(view.buffer_id) else {
+        return String::new();
+    };
+    let segments = state.status_segments.visible();
+    let (file_name, dirty) = if focused {
+        (state.status_file_name(), state.dirty())
+    } else {
+        (entry.meta.path.as_deref(), entry.meta.dirty)
+    };
+    crate::status::build_view_status(&crate::status::ViewStatusContext {
+        focused_mode: focused.then_some(state.mode),
+        line: view.cursor.line,
+        col: cursor_visual_col(&entry.buffer, view),
+        byte: view.cursor.byte,
+        percent: file_percent(&entry.buffer, view.cursor.line),
+        selection: state.selection_size(),
+        file_name,
+        dirty,
+        diagnostics: state.diagnostics.counts(view.buffer_id),
+        git: state.git.status.as_ref(),
+        segments: &segments,
     })
 }
 
+/// Bottom row of a split layout, where views carry their own status rows:
+/// the active command line, else the ephemeral message when it fits.
+pub fn build_command_line(state: &EditorState, width: u16) -> String {
+    if state.command_line.is_active() {
+        let buf = state.command_line.buffer();
+        return format!(":{}", buf.strip_prefix(':').unwrap_or(buf));
+    }
+    match &state.ephemeral_status {
+        Some(msg) if msg.text.chars().count() <= width as usize => msg.text.clone(),
+        _ => String::new(),
+    }
+}
+
+const STATUS_ROW: CellFlags = CellFlags::REVERSE.union(CellFlags::STATUS);
+
+/// Paint `text` into a one-row `region` in reverse video (or the scheme's
+/// `StatusLine`), padding the row.
+fn paint_status_row(frame: &mut Frame, region: LayoutRegion, text: &str) {
+    let end = region.x.saturating_add(region.width);
+    let mut byte = 0usize;
+    let mut x = region.x;
+    while byte < text.len() && x < end {
+        let next = grapheme::next_boundary(text, byte);
+        let cluster = &text[byte..next];
+        let width = grapheme::cluster_width(cluster).max(1) as u16;
+        if x + width > end {
+            break;
+        }
+        frame.set_cluster(x, region.y, cluster, width, STATUS_ROW);
+        x = x.saturating_add(width);
+        byte = next;
+    }
+    for fill in x..end {
+        frame.set_cluster(fill, region.y, " ", 1, STATUS_ROW);
+    }
+}
+
 pub fn build_status_line_with_ephemeral(state: &EditorState, view: &View, width: u16) -> String {
     // Base status using existing builder (mode, file, position, command buffer).
     let mut base = build_status_line(state, view);
@@ -1144,6 +2797,950 @@ mod tests {
         assert_eq!(eng.last_cursor_line(), Some(2));
     }
 
+    #[test]
+    fn view_frames_draw_their_own_buffer() {
+        let mut model = mk_state("first\n");
+        model.split_active_view(core_model::SplitAxis::Horizontal);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "second\n").unwrap(), None);
+        model.state_mut().switch_buffer(other);
+        model.active_view_mut().buffer_id = other;
+        let leader = |f: &Frame| f.cells[0].cluster.clone();
+        let firsts: Vec<String> = model
+            .views()
+            .iter()
+            .map(|v| leader(&build_view_frame(model.state(), v, 10, 3)))
+            .collect();
+        assert_eq!(firsts, ["f", "s"]);
+
+        let mut eng = RenderEngine::new();
+        let area = core_model::LayoutRegion::new(0, 0, 10, 7);
+        let layout = model.layout(area);
+        assert!(layout.separators().is_empty());
+        assert_eq!(layout.statuses().len(), 2);
+        let active = model.active_view().id;
+        eng.render_views(
+            model.state(),
+            model.views(),
+            active,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            10,
+            8,
+            "",
+        )
+        .unwrap();
+        assert_eq!(eng.metrics_snapshot().full_frames, 1);
+    }
+
+    #[test]
+    fn diff_views_line_up_with_filler_rows_and_shade_hunks() {
+        let mut model = mk_state("a\nb\nc\n");
+        let left = model.active_view().id;
+        let right = model.split_active_view(core_model::SplitAxis::Vertical);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "a\nx\nb\nC\n").unwrap(), None);
+        model.state_mut().switch_buffer(other);
+        model.active_view_mut().buffer_id = other;
+        let state = model.state_mut();
+        state.diff.start(core_state::BufferId(1), other);
+        assert!(state.diff.refresh(&state.buffers));
+        let layout = model.layout(core_model::LayoutRegion::new(0, 0, 21, 5));
+        let mut eng = RenderEngine::new();
+        eng.render_views(
+            model.state(),
+            model.views(),
+            right,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            21,
+            6,
+            "",
+        )
+        .unwrap();
+        let frame = eng.split_frame.as_ref().unwrap();
+        let cell = |id, y: u16| {
+            let region = layout.region_of(id).unwrap();
+            &frame.cells[(y * 21 + region.x) as usize]
+        };
+        let column = |id| -> String { (0..4).map(|y| cell(id, y).cluster.clone()).collect() };
+        assert_eq!(column(left), "a-bc");
+        assert_eq!(column(right), "axbC");
+        assert!(cell(left, 1).flags.contains(CellFlags::DIFF_DELETE));
+        assert!(cell(right, 1).flags.contains(CellFlags::DIFF_ADD));
+        assert!(cell(left, 3).flags.contains(CellFlags::DIFF_CHANGE));
+        assert!(cell(right, 3).flags.contains(CellFlags::DIFF_CHANGE));
+        assert!(
+            !cell(right, 2)
+                .flags
+                .intersects(CellFlags::DIFF_ADD | CellFlags::DIFF_CHANGE)
+        );
+    }
+
+    #[test]
+    fn split_views_paint_their_own_status_rows() {
+        let mut model = mk_state("first\n");
+        let below = model.active_view().id;
+        let above = model.split_active_view(core_model::SplitAxis::Horizontal);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "second\n").unwrap(), None);
+        model.state_mut().switch_buffer(other);
+        model.active_view_mut().buffer_id = other;
+        let layout = model.layout(core_model::LayoutRegion::new(0, 0, 30, 7));
+        let mut eng = RenderEngine::new();
+        eng.render_views(
+            model.state(),
+            model.views(),
+            above,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            30,
+            8,
+            "",
+        )
+        .unwrap();
+        let frame = eng.split_frame.as_ref().unwrap();
+        let row = |y: u16| -> String {
+            (0..30)
+                .map(|x| frame.cells[(y * 30 + x) as usize].cluster.clone())
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+        let status_of = |id| {
+            let (region, _) = layout
+                .statuses()
+                .iter()
+                .zip(layout.views())
+                .find(|(_, v)| **v == id)
+                .unwrap();
+            region.y
+        };
+        assert_eq!(row(status_of(above)), "[NORMAL] [No Name] 1:1 100%");
+        assert_eq!(row(status_of(below)), " [No Name] 1:1 100%");
+        assert!(
+            frame.cells[(status_of(below) * 30) as usize]
+                .flags
+                .contains(CellFlags::REVERSE)
+        );
+    }
+
+    #[test]
+    fn vsplit_edit_repaints_only_its_region() {
+        let mut model = mk_state("left\n");
+        let right = model.active_view().id;
+        let left = model.split_active_view(core_model::SplitAxis::Vertical);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "new\n").unwrap(), None);
+        model.state_mut().switch_buffer(other);
+        model.active_view_mut().buffer_id = other;
+        let area = core_model::LayoutRegion::new(0, 0, 21, 5);
+        let layout = model.layout(area);
+        assert_eq!(layout.region_of(left).map(|r| r.width), Some(10));
+        assert_eq!(layout.separators()[0].region.x, 10);
+        assert_eq!(layout.region_of(right).map(|r| r.x), Some(11));
+
+        let mut eng = RenderEngine::new();
+        eng.render_views(
+            model.state(),
+            model.views(),
+            left,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            21,
+            6,
+            "",
+        )
+        .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("split_full"));
+        let mut pos = core_text::Position::new(0, 3);
+        model
+            .state_mut()
+            .active_buffer_mut()
+            .insert_grapheme(&mut pos, "!");
+        eng.render_views(
+            model.state(),
+            model.views(),
+            left,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            21,
+            6,
+            "",
+        )
+        .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("split_partial"));
+        assert_eq!(eng.test_last_repaint_views(), [left]);
+
+        // A different size (terminal resize) redistributes and repaints all.
+        let layout = model.layout(core_model::LayoutRegion::new(0, 0, 31, 5));
+        eng.render_views(
+            model.state(),
+            model.views(),
+            left,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            31,
+            6,
+            "",
+        )
+        .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("split_full"));
+        assert_eq!(layout.region_of(right).map(|r| r.width), Some(15));
+    }
+
+    #[test]
+    fn split_lines_delta_hashes_only_regions_of_the_edited_buffer() {
+        let mut model = mk_state("left\nmore\n");
+        let right = model.active_view().id;
+        let left = model.split_active_view(core_model::SplitAxis::Vertical);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "new\n").unwrap(), None);
+        model.state_mut().switch_buffer(other);
+        model.active_view_mut().buffer_id = other;
+        let layout = model.layout(core_model::LayoutRegion::new(0, 0, 21, 5));
+        let mut eng = RenderEngine::new();
+        let render = |eng: &mut RenderEngine, model: &EditorModel, delta: &RenderDelta| {
+            eng.render_views(
+                model.state(),
+                model.views(),
+                left,
+                &layout,
+                delta,
+                None,
+                21,
+                6,
+                "",
+            )
+            .unwrap();
+        };
+        render(&mut eng, &model, &RenderDelta::Full);
+        let right_hashes = eng
+            .region_caches
+            .get(right)
+            .unwrap()
+            .lines
+            .line_hashes
+            .clone();
+
+        let mut pos = core_text::Position::new(0, 3);
+        model
+            .state_mut()
+            .active_buffer_mut()
+            .insert_grapheme(&mut pos, "!");
+        let before = eng.metrics_snapshot();
+        render(&mut eng, &model, &RenderDelta::Lines(0..1));
+        let after = eng.metrics_snapshot();
+        assert_eq!(eng.test_last_repaint_kind(), Some("split_lines"));
+        assert_eq!(eng.test_last_repaint_views(), [left]);
+        assert_eq!(eng.test_last_repaint_lines(), [0]);
+        // The edited line is also the cursor line: one candidate, one repaint.
+        assert_eq!(
+            after.dirty_candidate_lines - before.dirty_candidate_lines,
+            1
+        );
+        assert_eq!(
+            after.dirty_lines_repainted - before.dirty_lines_repainted,
+            1
+        );
+        assert_eq!(
+            eng.region_caches.get(right).unwrap().lines.line_hashes,
+            right_hashes
+        );
+
+        // The region-scoped frame matches a freshly composed one.
+        let mut fresh = RenderEngine::new();
+        fresh
+            .render_views(
+                model.state(),
+                model.views(),
+                left,
+                &layout,
+                &RenderDelta::Full,
+                None,
+                21,
+                6,
+                "",
+            )
+            .unwrap();
+        assert!(eng.split_frame.as_ref().unwrap().cells == fresh.split_frame.unwrap().cells);
+    }
+
+    #[test]
+    fn split_cursor_move_repaints_old_and_new_cursor_rows() {
+        let mut model = mk_state("one\ntwo\nthree\n");
+        let below = model.active_view().id;
+        let above = model.split_active_view(core_model::SplitAxis::Horizontal);
+        let layout = model.layout(core_model::LayoutRegion::new(0, 0, 20, 9));
+        let mut eng = RenderEngine::new();
+        eng.render_views(
+            model.state(),
+            model.views(),
+            above,
+            &layout,
+            &RenderDelta::Full,
+            None,
+            20,
+            10,
+            "",
+        )
+        .unwrap();
+        model.active_view_mut().cursor = core_text::Position::new(2, 0);
+        eng.render_views(
+            model.state(),
+            model.views(),
+            above,
+            &layout,
+            &RenderDelta::CursorOnly,
+            None,
+            20,
+            10,
+            "",
+        )
+        .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("split_cursor"));
+        assert_eq!(eng.test_last_repaint_lines(), [0, 2]);
+        assert_eq!(eng.test_last_repaint_views(), [above]);
+
+        // Moving focus clears the cursor from the window that loses it.
+        model.focus_view(core_model::FocusDirection::Down);
+        eng.render_views(
+            model.state(),
+            model.views(),
+            below,
+            &layout,
+            &RenderDelta::CursorOnly,
+            None,
+            20,
+            10,
+            "",
+        )
+        .unwrap();
+        assert_eq!(eng.test_last_repaint_lines(), [2, 0]);
+        assert!(eng.test_last_repaint_views().contains(&above));
+        assert!(eng.test_last_repaint_views().contains(&below));
+    }
+
+    #[test]
+    fn popups_paint_after_each_path_and_restore_on_dismiss() {
+        let model = mk_state("hello\nworld\n");
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 6);
+        let mut eng = RenderEngine::new();
+        let id = eng
+            .popups_mut()
+            .show(crate::popup::Popup::new(1, 0, vec!["menu".into()]));
+        assert!(eng.popups().is_dirty());
+        eng.render_full(model.state(), &view, &layout, 20, 6, "")
+            .unwrap();
+        assert!(!eng.popups().is_dirty());
+
+        let before = eng.metrics_snapshot().cells_printed;
+        eng.popups_mut().dismiss(id);
+        eng.render_popups(model.state(), &view, 20, 6, "").unwrap();
+        assert!(!eng.popups().is_dirty());
+        // The 6x3 rectangle is restored from the underlying text.
+        assert!(eng.metrics_snapshot().cells_printed - before >= 18);
+        let under = eng.single_view_underlay(model.state(), &view, 20, 6, "");
+        assert_eq!(under.line_clusters(1).concat().trim_end(), "world");
+    }
+
+    #[test]
+    fn syntax_spans_colour_full_and_split_frames() {
+        use core_syntax::{HighlightClass, SyntaxManager};
+        let mut model = mk_state("fn main() {}\n");
+        model.state_mut().set_file_name(Some("main.rs".into()));
+        let active = model.state().active;
+        SyntaxManager::new().sync(model.state_mut(), active);
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 4);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 4, "");
+        let keyword = Some(HighlightClass::Keyword as u16);
+        assert_eq!(frame.cells[1].syntax, keyword);
+        assert_eq!(frame.cells[2].syntax, None);
+        assert_eq!(frame.cells[0].styled(), "\x1b[7;35mf\x1b[0m");
+        let split = build_view_frame(model.state(), &view, 20, 1);
+        assert_eq!(split.cells[0].syntax, keyword);
+        assert_eq!(split.cells[3].syntax, Some(HighlightClass::Function as u16));
+    }
+
+    /// Marks byte 3 of every line as a search hit and the rest of bytes 1..5
+    /// as a string, above syntax; a span past the text is dropped.
+    struct Marker;
+
+    impl StyleSpanProvider for Marker {
+        fn name(&self) -> &str {
+            "marker"
+        }
+        fn priority(&self) -> u8 {
+            crate::style::SYNTAX_PRIORITY + 1
+        }
+        fn line_spans(
+            &self,
+            _state: &EditorState,
+            _buffer: BufferId,
+            _line: usize,
+            _text: &str,
+            out: &mut Vec<crate::style::LineSpan>,
+        ) {
+            use crate::style::LineSpan;
+            let string = StyleAttr::Syntax(core_syntax::HighlightClass::String as u16);
+            out.push(LineSpan {
+                start: 3,
+                end: 4,
+                attr: StyleAttr::Search,
+            });
+            out.push(LineSpan {
+                start: 1,
+                end: 5,
+                attr: string,
+            });
+            out.push(LineSpan {
+                start: 6,
+                end: 99,
+                attr: StyleAttr::Search,
+            });
+        }
+    }
+
+    #[test]
+    fn style_providers_merge_over_syntax_by_priority() {
+        use core_syntax::{HighlightClass, SyntaxManager};
+        let mut model = mk_state("fn main() {}\n");
+        model.state_mut().set_file_name(Some("main.rs".into()));
+        let active = model.state().active;
+        SyntaxManager::new().sync(model.state_mut(), active);
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 4);
+        let mut eng = RenderEngine::new();
+        assert!(eng.add_style_provider(Box::new(Marker)));
+        assert!(!eng.add_style_provider(Box::new(Marker)), "name taken");
+        assert_eq!(
+            eng.style_providers().names().collect::<Vec<_>>(),
+            ["syntax", "marker"]
+        );
+        eng.render_full(model.state(), &view, &layout, 20, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 4, "");
+        let string = Some(HighlightClass::String as u16);
+        assert_eq!(frame.cells[0].syntax, Some(HighlightClass::Keyword as u16));
+        assert_eq!(frame.cells[1].syntax, string, "provider wins over syntax");
+        assert_eq!(frame.cells[3].syntax, None);
+        assert!(frame.cells[3].flags.contains(CellFlags::SEARCH));
+        assert_eq!(frame.cells[4].syntax, string);
+        assert!(
+            !frame.cells[7].flags.contains(CellFlags::SEARCH),
+            "past the text"
+        );
+
+        assert!(eng.remove_style_provider("marker"));
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 4, "");
+        assert_eq!(frame.cells[1].syntax, Some(HighlightClass::Keyword as u16));
+    }
+
+    #[test]
+    fn search_matches_flag_cells_and_enter_line_hashes() {
+        let mut model = mk_state("a foo\nbar\n");
+        model.state_mut().record_search("foo", true);
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 4);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 4, "");
+        let flagged: Vec<bool> = (0..6)
+            .map(|x| frame.cells[x].flags.contains(CellFlags::SEARCH))
+            .collect();
+        assert_eq!(flagged, [false, false, true, true, true, false]);
+        assert_eq!(frame.cells[2].styled(), "\x1b[30;43mf\x1b[0m");
+        let split = build_view_frame(model.state(), &view, 20, 1);
+        assert!(split.cells[2].flags.contains(CellFlags::SEARCH));
+        let hashed = eng.test_cache_hashes()[0];
+        assert_ne!(
+            hashed,
+            crate::partial_cache::PartialCache::compute_hash("a foo")
+        );
+
+        // `:noh` changes the display hash, so the row can never be skipped.
+        model.state_mut().suspend_search_highlight();
+        assert_eq!(
+            crate::region_cache::line_hash(model.state(), view.buffer_id, 0),
+            crate::partial_cache::PartialCache::compute_hash("a foo")
+        );
+        eng.render_full(model.state(), &view, &layout, 20, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 4, "");
+        assert!(
+            frame
+                .cells
+                .iter()
+                .all(|c| !c.flags.contains(CellFlags::SEARCH))
+        );
+    }
+
+    #[test]
+    fn diagnostics_draw_signs_underlines_and_counts() {
+        let mut model = mk_state("a foo\nbar\n");
+        let active = model.state().active;
+        let diag = |severity, line, bytes: std::ops::Range<usize>| core_state::Diagnostic {
+            severity,
+            start: core_text::Position::new(line, bytes.start),
+            end: core_text::Position::new(line, bytes.end),
+            message: String::new(),
+        };
+        model.state_mut().diagnostics.set(
+            active,
+            vec![
+                diag(core_state::Severity::Error, 0, 2..5),
+                diag(core_state::Severity::Warning, 1, 0..0),
+            ],
+        );
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(24, 4);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 24, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 24, 4, "");
+        assert_eq!(frame.line_clusters(0).concat().trim_end(), "E a foo");
+        assert_eq!(frame.line_clusters(1).concat().trim_end(), "W bar");
+        let underlined: Vec<bool> = (0..8)
+            .map(|x| frame.cells[x].flags.contains(CellFlags::DIAG_ERROR))
+            .collect();
+        assert_eq!(
+            underlined,
+            [false, false, false, false, true, true, true, false]
+        );
+        assert_eq!(frame.cells[4].styled(), "\x1b[4;58;5;1mf\x1b[0m");
+        assert!(frame.cells[24 + 2].flags.contains(CellFlags::DIAG_WARNING));
+        assert_eq!(
+            build_status_line(model.state(), &view),
+            "[NORMAL] [No Name] E:1 W:1 1:1 50% :"
+        );
+    }
+
+    #[test]
+    fn diagnostic_virtual_text_follows_the_line_end() {
+        let mut model = mk_state("a foo\nbar\n");
+        let active = model.state().active;
+        let diag = |severity, line, message: &str| core_state::Diagnostic {
+            severity,
+            start: core_text::Position::new(line, 0),
+            end: core_text::Position::new(line, 1),
+            message: message.into(),
+        };
+        let state = model.state_mut();
+        state.diagnostics.set(
+            active,
+            vec![
+                diag(core_state::Severity::Hint, 0, "style"),
+                diag(core_state::Severity::Error, 0, "bad\nmore detail"),
+            ],
+        );
+        state.diagnostics.set_virtual_text(true);
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(24, 4);
+        let mut eng = RenderEngine::new();
+        eng.capture_to(crate::capture::CaptureWriter::new(24, 4));
+        eng.render_full(model.state(), &view, &layout, 24, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 24, 4, "");
+        assert_eq!(frame.line_clusters(0).concat().trim_end(), "E a foo ■ bad");
+        assert_eq!(frame.line_clusters(1).concat().trim_end(), "  bar");
+        let virtual_text = CellFlags::VIRTUAL_TEXT | CellFlags::DIAG_ERROR;
+        assert!(frame.cells[7].flags.is_empty());
+        assert_eq!(frame.cells[10].flags, virtual_text);
+        assert_eq!(frame.cells[10].styled(), "\x1b[31mb\x1b[0m");
+        let grid = eng.capture().unwrap().grid();
+        assert_eq!(grid.row_text(0).trim_end(), "E a foo ■ bad");
+    }
+
+    #[test]
+    fn blame_annotation_follows_the_cursor_line() {
+        let mut model = mk_state("a foo\nbar\n");
+        let active = model.state().active;
+        let git = &mut model.state_mut().git;
+        git.toggle_blame();
+        let path = std::path::Path::new("/r/a.rs");
+        git.blame_due(active, path);
+        git.apply_blame(
+            path,
+            "1f2e3d4c5b6a79881f2e3d4c5b6a79881f2e3d4c 1 1 2\nauthor Ann\nauthor-time 1700000000\nauthor-tz +0000\nsummary Init\n\ta foo\n1f2e3d4c5b6a79881f2e3d4c5b6a79881f2e3d4c 2 2\n\tbar\n",
+        );
+        let layout = core_model::Layout::single(40, 4);
+        let mut eng = RenderEngine::new();
+        eng.capture_to(crate::capture::CaptureWriter::new(40, 4));
+        let view = model.active_view().clone();
+        eng.render_full(model.state(), &view, &layout, 40, 4, "")
+            .unwrap();
+        let annotation = "1f2e3d4c Ann, 2023-11-14 • Init";
+        let grid = eng.capture().unwrap().grid();
+        assert_eq!(grid.row_text(0).trim_end(), format!("a foo {annotation}"));
+        assert_eq!(grid.row_text(1).trim_end(), "bar");
+        let frame = eng.single_view_underlay(model.state(), &view, 40, 4, "");
+        assert_eq!(frame.cells[6].flags, CellFlags::VIRTUAL_TEXT);
+
+        model.active_view_mut().cursor = core_text::Position::new(1, 0);
+        let view = model.active_view().clone();
+        eng.render_cursor_only(model.state(), &view, &layout, 40, 4, "")
+            .unwrap();
+        let grid = eng.capture().unwrap().grid();
+        assert_eq!(grid.row_text(0).trim_end(), "a foo");
+        assert_eq!(grid.row_text(1).trim_end(), format!("bar {annotation}"));
+    }
+
+    #[test]
+    fn completion_popup_lists_matches_under_the_word() {
+        let mut model = mk_state("x fo\nfoo fold\n\n\n\n\n\n");
+        let active = model.state().active;
+        let cursor = core_text::Position::new(0, 4);
+        model.active_view_mut().cursor = cursor;
+        let state = model.state_mut();
+        state.mode = Mode::Insert;
+        let case = state.completion.case;
+        let words = state
+            .completion
+            .words
+            .words(&state.buffers, active, cursor, "fo", case);
+        state.completion.open(
+            active,
+            core_text::Position::new(0, 2),
+            "fo".into(),
+            words,
+            None,
+        );
+        state.completion.select(true);
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 8);
+        let mut eng = RenderEngine::new();
+        eng.capture_to(crate::capture::CaptureWriter::new(20, 8));
+        eng.render_full(model.state(), &view, &layout, 20, 8, "")
+            .unwrap();
+        let grid = eng.capture().unwrap().grid();
+        // The item text lines up with the `fo` being completed.
+        assert_eq!(grid.row_text(0).trim_end(), "x fo");
+        assert_eq!(grid.row_text(1).trim_end(), "f┌───────────┐");
+        assert_eq!(grid.row_text(2).trim_end(), " │foo  Buffer│");
+        assert_eq!(grid.row_text(3).trim_end(), " │fold Buffer│");
+
+        model.state_mut().completion.close();
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 8, "")
+            .unwrap();
+        let grid = eng.capture().unwrap().grid();
+        assert_eq!(grid.row_text(1).trim_end(), "foo fold");
+        assert_eq!(grid.row_text(2).trim_end(), "");
+        assert!(eng.popups().is_empty());
+    }
+
+    #[test]
+    fn hover_popup_sits_below_the_cursor_and_scrolls() {
+        let mut model = mk_state("x\ny\n\n\n\n\n\n");
+        let active = model.state().active;
+        let cursor = core_text::Position::new(0, 0);
+        model.active_view_mut().cursor = cursor;
+        let lines = (1..=30).map(|i| format!("doc {i}")).collect();
+        let hover = &mut model.state_mut().hover;
+        hover.request(active, cursor);
+        let generation = hover.take_request().unwrap().generation;
+        hover.show(generation, lines);
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 8);
+        let mut eng = RenderEngine::new();
+        eng.capture_to(crate::capture::CaptureWriter::new(20, 8));
+        eng.render_full(model.state(), &view, &layout, 20, 8, "")
+            .unwrap();
+        let grid = eng.capture().unwrap().grid();
+        // Rows 1..=6 are the text area below the cursor: four lines fit.
+        assert_eq!(grid.row_text(1).trim_end(), "┌──────┐");
+        assert_eq!(grid.row_text(2).trim_end(), "│doc 1 │");
+        assert_eq!(grid.row_text(5).trim_end(), "│doc 4 │");
+        assert_eq!(grid.row_text(6).trim_end(), "└──────┘");
+
+        model.state_mut().hover.scroll(20);
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 8, "")
+            .unwrap();
+        let grid = eng.capture().unwrap().grid();
+        assert_eq!(grid.row_text(2).trim_end(), "│doc 11│");
+
+        model.state_mut().hover.close();
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 8, "")
+            .unwrap();
+        let grid = eng.capture().unwrap().grid();
+        assert_eq!(grid.row_text(1).trim_end(), "y");
+        assert!(eng.popups().is_empty());
+    }
+
+    #[test]
+    fn number_gutter_shifts_text_and_relative_moves_repaint_fully() {
+        let mut model = mk_state("a\n界b\nc\n");
+        model.state_mut().options.apply_set("number").unwrap();
+        let mut view = model.active_view().clone();
+        view.cursor = core_text::Position::new(1, "界".len());
+        let layout = core_model::Layout::single(20, 5);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 5, "");
+        assert_eq!(frame.line_clusters(0).concat().trim_end(), "  1 a");
+        assert_eq!(frame.line_clusters(1).concat().trim_end(), "  2 界b");
+        // The wide cluster pushes the cursor two columns past the gutter.
+        assert_eq!(eng.last_cursor.start_col, Some(6));
+        assert!(frame.cells[20 + 6].flags.contains(CellFlags::CURSOR));
+
+        // Absolute labels do not depend on the cursor: cursor-only stays partial.
+        view.cursor = core_text::Position::new(2, 0);
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        assert_eq!(eng.last_repaint_kind, Some("cursor_only"));
+
+        model
+            .state_mut()
+            .options
+            .apply_set("relativenumber")
+            .unwrap();
+        eng.render_full(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 5, "");
+        assert_eq!(frame.line_clusters(0).concat().trim_end(), "  2 a");
+        assert_eq!(frame.line_clusters(2).concat().trim_end(), "3   c");
+        let full_before = eng.metrics_snapshot().full_frames;
+        view.cursor = core_text::Position::new(0, 0);
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        assert_eq!(eng.metrics_snapshot().full_frames, full_before + 1);
+    }
+
+    #[test]
+    fn cursor_shade_flags_frame_and_moves_without_full_renders() {
+        let mut model = mk_state("ab\n界c\nd");
+        let options = &mut model.state_mut().options;
+        options.apply_set("cursorline").unwrap();
+        options.apply_set("cursorcolumn").unwrap();
+        let mut view = model.active_view().clone();
+        view.cursor = core_text::Position::new(0, 1);
+        let layout = core_model::Layout::single(20, 5);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 5, "");
+        assert!(
+            (0..20).all(|x| frame.cells[x].flags.contains(CellFlags::CURSORLINE)),
+            "the whole cursor row is shaded"
+        );
+        assert!(!frame.cells[20].flags.contains(CellFlags::CURSORLINE));
+        let column = |x: usize| frame.cells[x].flags.contains(CellFlags::CURSORCOLUMN);
+        assert!(column(1) && column(40 + 1) && !column(40));
+        // Column 1 is the continuation of `界`: its leader carries the shade.
+        assert!(column(20) && column(20 + 1));
+        // Filler rows past the buffer end stay plain.
+        assert!(!column(60 + 1));
+        assert_eq!(eng.cache.last_cursor_col, Some((1, 2)));
+
+        let full_before = eng.metrics_snapshot().full_frames;
+        view.cursor = core_text::Position::new(2, 0);
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("cursor_only"));
+        assert_eq!(eng.metrics_snapshot().full_frames, full_before);
+        assert_eq!(eng.cache.last_cursor_col, Some((0, 1)));
+        assert_eq!(eng.last_cursor_line(), Some(2));
+    }
+
+    #[test]
+    fn colorcolumn_shades_rulers_and_wide_clusters_over_them() {
+        let mut model = mk_state("a界b\nx");
+        model
+            .state_mut()
+            .options
+            .apply_set("colorcolumn=3,9,40")
+            .unwrap();
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(10, 4);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 10, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 10, 4, "");
+        let ruler = |x: usize| frame.cells[x].flags.contains(CellFlags::COLORCOLUMN);
+        // Column 3 is the second cell of `界`, whose leader takes the shade.
+        assert!(ruler(1) && ruler(2) && ruler(8) && !ruler(0) && !ruler(3));
+        // Blanks past a short line are shaded too; filler rows are not.
+        assert!(ruler(10 + 2) && ruler(10 + 8) && !ruler(20 + 2));
+        assert_eq!(frame.cells[1].styled(), "\x1b[48;5;52m界\x1b[0m");
+        let shade = CursorShade::for_view(model.state(), &view, 10);
+        assert_eq!(shade.rulers, [2, 8]);
+        assert_eq!(shade.fill_end(1), 9);
+    }
+
+    #[test]
+    fn hardware_cursor_replaces_reverse_video_with_a_mode_shape() {
+        let mut model = mk_state("ab\ncd");
+        let mut view = model.active_view().clone();
+        view.cursor = core_text::Position::new(1, 1);
+        let layout = core_model::Layout::single(10, 4);
+        let mut eng = RenderEngine::new();
+        eng.set_hardware_cursor(true);
+        eng.render_full(model.state(), &view, &layout, 10, 4, "")
+            .unwrap();
+        assert_eq!(eng.cursor_cell, Some((1, 1)));
+        let frame = eng.single_view_underlay(model.state(), &view, 10, 4, "");
+        assert_eq!(frame.cells[11].flags, CellFlags::CURSOR);
+        assert_eq!(cursor_shape(model.state().mode), CursorShape::Block);
+        model.state_mut().mode = Mode::Insert;
+        assert_eq!(cursor_shape(model.state().mode), CursorShape::Bar);
+    }
+
+    #[test]
+    fn long_lines_wrap_and_row_count_changes_repaint_fully() {
+        let mut model = mk_state("abcdefgh\nxy\nz");
+        let options = &mut model.state_mut().options;
+        options.apply_set("number").unwrap();
+        options.apply_set("showbreak=>").unwrap();
+        let mut view = model.active_view().clone();
+        view.cursor = core_text::Position::new(0, 7);
+        // Gutter "  1 " leaves 4 text columns: "abcd", ">efg", ">h".
+        let layout = core_model::Layout::single(8, 5);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 8, 5, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 8, 5, "");
+        let row = |y: u16| frame.line_clusters(y).concat();
+        assert_eq!(row(0).trim_end(), "  1 abcd");
+        assert_eq!(row(1).trim_end(), "    >efg");
+        assert_eq!(row(2).trim_end(), "    >h");
+        assert_eq!(row(3).trim_end(), "  2 xy");
+        assert_eq!(crate::wrap::row_lines(&eng.cache.rows), [0, 0, 0, 1]);
+        assert!(frame.cells[2 * 8 + 5].flags.contains(CellFlags::CURSOR));
+
+        // Moving within the same layout stays partial.
+        view.cursor = core_text::Position::new(1, 0);
+        eng.render_cursor_only(model.state(), &view, &layout, 8, 5, "")
+            .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("cursor_only"));
+
+        // Line 0 now takes one row less: every row below it moved.
+        let full_before = eng.metrics_snapshot().full_frames;
+        let active = model.state().active;
+        model.state_mut().active_buffer_mut().delete_bytes(0, 3);
+        eng.invalidate_lines(model.state(), active, &[0]);
+        let mut tracker = crate::dirty::DirtyLinesTracker::new();
+        tracker.mark_range(0, 0);
+        eng.render_lines_partial(model.state(), &view, &layout, 8, 5, &mut tracker, "")
+            .unwrap();
+        assert_eq!(eng.metrics_snapshot().full_frames, full_before + 1);
+        assert_eq!(crate::wrap::row_lines(&eng.cache.rows), [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn list_paints_whitespace_glyphs() {
+        let mut model = mk_state("a\tb  \nc");
+        let options = &mut model.state_mut().options;
+        options.apply_set("list").unwrap();
+        options.apply_set("lcs=tab:>-,trail:~,eol:$").unwrap();
+        let mut view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 4);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 4, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 4, "");
+        // The tab reaches the tab stop at column 8.
+        assert_eq!(frame.line_clusters(0).concat().trim_end(), "a>------b~~$");
+        assert_eq!(frame.line_clusters(1).concat().trim_end(), "c$");
+        let dim = |x: usize| frame.cells[x].flags.contains(CellFlags::WHITESPACE);
+        assert!(!dim(0) && (1..8).all(dim) && !dim(8) && dim(9) && dim(10) && dim(11));
+
+        // Cursor motion stays a partial repaint with `list` on.
+        view.cursor = core_text::Position::new(1, 0);
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 4, "")
+            .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("cursor_only"));
+    }
+
+    #[test]
+    fn closed_fold_paints_summary_row_and_reopening_repaints_fully() {
+        let mut model = mk_state("a\n  b\nc\nd\n");
+        model.state_mut().options.apply_set("foldcolumn=1").unwrap();
+        let mut view = model.active_view().clone();
+        view.folds.create(1, 2);
+        let layout = core_model::Layout::single(20, 5);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 5, "");
+        assert_eq!(frame.line_clusters(0).concat().trim_end(), " a");
+        assert_eq!(frame.line_clusters(1).concat(), "++--  2 lines: b----");
+        assert!(frame.cells[21].flags.contains(CellFlags::FOLDED));
+        assert!(!frame.cells[20].flags.contains(CellFlags::FOLDED));
+        assert_eq!(frame.line_clusters(2).concat().trim_end(), " d");
+
+        // Opening the fold moves every row below it.
+        let full_before = eng.metrics_snapshot().full_frames;
+        view.folds.open(1).unwrap();
+        eng.render_cursor_only(model.state(), &view, &layout, 20, 5, "")
+            .unwrap();
+        assert_eq!(eng.metrics_snapshot().full_frames, full_before + 1);
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 5, "");
+        assert_eq!(frame.line_clusters(1).concat().trim_end(), "-  b");
+        assert_eq!(frame.line_clusters(2).concat().trim_end(), "|c");
+    }
+
+    #[test]
+    fn sign_changes_repaint_rows_with_unchanged_text() {
+        use core_state::SignStyle;
+        let mut model = mk_state("a\nb\nc\nd\ne\nf\n");
+        let active = model.state().active;
+        model
+            .state_mut()
+            .signs
+            .place(active, 5, "+", SignStyle::Added)
+            .unwrap();
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(20, 10);
+        let mut eng = RenderEngine::new();
+        eng.render_full(model.state(), &view, &layout, 20, 10, "")
+            .unwrap();
+        let frame = eng.single_view_underlay(model.state(), &view, 20, 10, "");
+        assert_eq!(frame.line_clusters(5).concat().trim_end(), "+ f");
+
+        model
+            .state_mut()
+            .signs
+            .place(active, 3, "E", SignStyle::Error)
+            .unwrap();
+        let dirty: Vec<usize> = model
+            .state_mut()
+            .signs
+            .take_dirty()
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+        eng.invalidate_lines(model.state(), active, &dirty);
+        let mut tracker = crate::dirty::DirtyLinesTracker::new();
+        tracker.mark_range(3, 4);
+        eng.render_lines_partial(model.state(), &view, &layout, 20, 10, &mut tracker, "")
+            .unwrap();
+        assert_eq!(eng.last_repaint_kind, Some("lines"));
+        // Line 4 was marked but neither its text nor its sign changed.
+        assert_eq!(eng.last_repaint_lines, [0, 3]);
+    }
+
     #[test]
     fn metrics_full_frames_increment() {
         let model = mk_state("x\n");
@@ -1159,10 +3756,45 @@ mod tests {
         // Capture metrics after two full renders and assert they incremented.
         let snap = eng.metrics_snapshot();
         assert_eq!(snap.full_frames, 2, "two full frames should be counted");
-        assert!(
-            snap.last_full_render_ns > 0,
-            "timestamp for last full render recorded"
-        );
+        assert_eq!(snap.full_latency.samples, 2, "both full renders timed");
+        assert!(snap.full_latency.p50_ns > 0);
+        assert!(snap.full_latency.p50_ns <= snap.full_latency.p99_ns);
+    }
+
+    #[test]
+    fn profiling_splits_recent_frames_into_stages() {
+        let mut model = mk_state("a\nb\nc\n");
+        let mut eng = RenderEngine::new();
+        let view = model.active_view().clone();
+        let layout = core_model::Layout::single(40, 5);
+        let status_line = build_status_line(model.state(), &view);
+        eng.render_full(model.state(), &view, &layout, 40, 5, &status_line)
+            .unwrap();
+        assert_eq!(eng.frame_profile().count(), 0, "profiling is opt-in");
+
+        model.state_mut().profile_frames = true;
+        eng.render_full(model.state(), &view, &layout, 40, 5, &status_line)
+            .unwrap();
+        eng.render_cursor_only(model.state(), &view, &layout, 40, 5, &status_line)
+            .unwrap();
+        let frames: Vec<_> = eng.frame_profile().copied().collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].path, FramePath::Full);
+        assert_eq!(frames[1].path, FramePath::CursorOnly);
+        for f in &frames {
+            assert_eq!(
+                f.total_ns,
+                f.hash_ns + f.compose_ns + f.write_ns + f.flush_ns,
+                "{f:?}"
+            );
+        }
+        assert!(frames[0].hash_ns > 0, "full frames hash the viewport");
+
+        model.state_mut().profile_frames = false;
+        eng.render_full(model.state(), &view, &layout, 40, 5, &status_line)
+            .unwrap();
+        assert_eq!(eng.frame_profile().count(), 0);
+        assert_eq!(eng.metrics_snapshot().full_latency.samples, 3);
     }
 
     #[test]
@@ -1186,7 +3818,8 @@ mod tests {
         assert_eq!(snap.full_frames, 1, "only initial full frame counted");
         assert_eq!(snap.partial_frames, 1, "one partial frame executed");
         assert_eq!(snap.cursor_only_frames, 1, "cursor-only frame counted");
-        assert!(snap.last_partial_render_ns > 0);
+        assert_eq!(snap.cursor_only_latency.samples, 1);
+        assert!(snap.cursor_only_latency.p50_ns > 0);
         assert_eq!(eng.last_cursor_line(), Some(2));
     }
 
@@ -1309,7 +3942,7 @@ mod tests {
             let mut pos = core_text::Position::new(0, 0);
             buf.insert_grapheme(&mut pos, "x");
             // Explicitly mark state dirty so status line reflects change (adds '*').
-            model.state_mut().dirty = true;
+            model.state_mut().set_dirty(true);
         }
         // Move cursor so we take cursor-only path and status differs due to dirty flag.
         let mut view_move = view0.clone();
@@ -21,6 +21,13 @@
 //! - Example: `StatusLine + CursorOnly` => `StatusLine`.
 //! - Example: `Scroll{3->7} + Lines(10..11)` => `Scroll{3->7}` (lines suppressed by precedence).
 //!
+//! Scroll shift threshold: a merged `Scroll` executes as `Scroll` while it
+//! moves fewer lines than the view shows (`set_viewport_rows`), so page
+//! motions (`Ctrl-D` / `Ctrl-U`) shift the terminal's scroll region and
+//! repaint only the entering band. Larger moves leave nothing to reuse and
+//! escalate to `Full`, counted in `scroll_escalated`. Until a height is
+//! known the limit is `SCROLL_SHIFT_MAX`.
+//!
 //! Refactor R2 policy: renderer still performs a full redraw (flicker-free
 //! and simple) while instrumentation accumulates real semantic patterns.
 //! Phase 3 will branch on `decision.semantic` to drive incremental paints.
@@ -48,6 +55,10 @@ pub struct RenderScheduler {
     pending: Vec<RenderDelta>,
     /// Metrics accumulator (Refactor R2 Step 9).
     metrics: RenderDeltaMetrics,
+    /// `pending` spans more than one event (`hold`).
+    held: bool,
+    /// Largest scroll executed as a shift (see `set_viewport_rows`).
+    scroll_shift_max: usize,
 }
 
 /// Stable decision DTO (Step 2): minimal shape exposed to consumers.
@@ -72,6 +83,8 @@ pub struct RenderDeltaMetrics {
     cursor_only: std::sync::atomic::AtomicU64,
     collapsed_scroll: std::sync::atomic::AtomicU64,
     suppressed_scroll: std::sync::atomic::AtomicU64, // Refactor R3 Step 5: now counts Lines suppressed by Scroll precedence.
+    /// Scrolls past the shift threshold executed as `Full`.
+    scroll_escalated: std::sync::atomic::AtomicU64,
     /// Number of semantic collapse cycles processed (may diverge from
     /// executed frame strategy counts in `RenderPathMetrics`).
     semantic_frames: std::sync::atomic::AtomicU64,
@@ -86,6 +99,7 @@ pub struct RenderDeltaMetricsSnapshot {
     pub cursor_only: u64,
     pub collapsed_scroll: u64,
     pub suppressed_scroll: u64,
+    pub scroll_escalated: u64,
     pub semantic_frames: u64,
 }
 
@@ -100,6 +114,7 @@ impl RenderDeltaMetrics {
             cursor_only: self.cursor_only.load(Relaxed),
             collapsed_scroll: self.collapsed_scroll.load(Relaxed),
             suppressed_scroll: self.suppressed_scroll.load(Relaxed),
+            scroll_escalated: self.scroll_escalated.load(Relaxed),
             semantic_frames: self.semantic_frames.load(Relaxed),
         }
     }
@@ -131,6 +146,10 @@ impl RenderDeltaMetrics {
         self.suppressed_scroll
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
+    fn incr_scroll_escalated(&self) {
+        self.scroll_escalated
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
     fn incr_frame(&self) {
         self.semantic_frames
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -142,6 +161,8 @@ impl RenderScheduler {
         Self {
             pending: Vec::new(),
             metrics: RenderDeltaMetrics::default(),
+            held: false,
+            scroll_shift_max: Self::SCROLL_SHIFT_MAX,
         }
     }
 
@@ -152,6 +173,14 @@ impl RenderScheduler {
     /// can be driven by metrics once the path is exercised.
     pub const SCROLL_SHIFT_MAX: usize = 12;
 
+    /// Height of the scrolled view's text area: scrolls shorter than it
+    /// keep some rows on screen and execute as shifts.
+    pub fn set_viewport_rows(&mut self, rows: usize) {
+        if rows > 1 {
+            self.scroll_shift_max = rows - 1;
+        }
+    }
+
     /// Obtain a snapshot of current metrics (Refactor R2 Step 9).
     pub fn metrics_snapshot(&self) -> RenderDeltaMetricsSnapshot {
         self.metrics.snapshot()
@@ -171,6 +200,18 @@ impl RenderScheduler {
         self.mark(RenderDelta::StatusLine);
     }
 
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Keep the queued deltas for a later frame (frame pacing). Until that
+    /// frame, a `Scroll` merged with `Lines` executes as `Full`: the shift
+    /// path repaints only entering rows, so an earlier event's edits would
+    /// be lost.
+    pub fn hold(&mut self) {
+        self.held = !self.pending.is_empty();
+    }
+
     /// Collapse queued deltas and return a `Decision`.
     ///
     /// Refactor R2 behavior: always sets `effective = RenderDelta::Full` while still reporting the
@@ -181,6 +222,12 @@ impl RenderScheduler {
         }
         let merged = self.collapse();
         tracing::trace!(target: "render.scheduler", ?merged, "render_delta_collapse");
+        let mixed_scroll = std::mem::take(&mut self.held)
+            && matches!(merged, RenderDelta::Scroll { .. })
+            && self
+                .pending
+                .iter()
+                .any(|d| matches!(d, RenderDelta::Lines(_)));
         self.pending.clear();
         self.metrics.incr_semantic(&merged);
         self.metrics.incr_frame();
@@ -189,20 +236,22 @@ impl RenderScheduler {
             // Phase 3 Step 7: CursorOnly partial; Phase 3 Step 8: Lines partial path
             RenderDelta::CursorOnly => RenderDelta::CursorOnly,
             RenderDelta::Lines(r) => RenderDelta::Lines(r.clone()),
-            // Phase 4 Step 10: small scrolls (<= SCROLL_SHIFT_MAX) become an
-            // effective Scroll path. Larger scrolls still escalate to Full so
-            // we do not pay for shifting cache state & selective repaints.
+            // Phase 4 Step 10: scrolls up to the shift threshold become an
+            // effective Scroll path. Larger scrolls escalate to Full: no row
+            // of the old viewport would survive the shift.
+            RenderDelta::Scroll { .. } if mixed_scroll => RenderDelta::Full,
             RenderDelta::Scroll {
                 old_first,
                 new_first,
             } => {
                 let diff = new_first.abs_diff(*old_first);
-                if diff <= Self::SCROLL_SHIFT_MAX {
+                if diff <= self.scroll_shift_max {
                     RenderDelta::Scroll {
                         old_first: *old_first,
                         new_first: *new_first,
                     }
                 } else {
+                    self.metrics.incr_scroll_escalated();
                     RenderDelta::Full
                 }
             }
@@ -330,6 +379,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn held_scroll_with_edits_executes_full() {
+        let mut s = RenderScheduler::new();
+        s.mark(RenderDelta::Lines(3..4));
+        s.hold();
+        s.mark(RenderDelta::Scroll {
+            old_first: 0,
+            new_first: 1,
+        });
+        let decision = s.consume().unwrap();
+        assert!(matches!(decision.semantic, RenderDelta::Scroll { .. }));
+        assert_eq!(decision.effective, RenderDelta::Full);
+        // The hold lasts one frame.
+        s.mark(RenderDelta::Lines(3..4));
+        s.mark(RenderDelta::Scroll {
+            old_first: 1,
+            new_first: 2,
+        });
+        assert!(matches!(
+            s.consume().unwrap().effective,
+            RenderDelta::Scroll { .. }
+        ));
+    }
+
     #[test]
     fn scroll_multiple_merge() {
         let mut s = RenderScheduler::new();
@@ -483,6 +556,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn page_scrolls_shift_within_the_viewport_height() {
+        let mut s = RenderScheduler::new();
+        s.set_viewport_rows(40);
+        // A half page (Ctrl-D) and a near-full page both shift.
+        for delta in [20, 38] {
+            s.mark(RenderDelta::Scroll {
+                old_first: 0,
+                new_first: delta,
+            });
+            assert!(matches!(
+                s.consume().unwrap().effective,
+                RenderDelta::Scroll { .. }
+            ));
+        }
+        s.mark(RenderDelta::Scroll {
+            old_first: 40,
+            new_first: 0,
+        });
+        assert_eq!(s.consume().unwrap().effective, RenderDelta::Full);
+        assert_eq!(s.metrics_snapshot().scroll_escalated, 1);
+    }
+
     #[test]
     fn effective_large_scroll_escalates_full() {
         let mut s = RenderScheduler::new();
@@ -16,20 +16,71 @@
 //! future evolution (injecting VCS / diagnostics or truncation logic by manipulating segments).
 //! All prior direct string construction logic was replaced; tests verify exact equivalence to a
 //! "legacy" formatting function embedded in the test module.
+//!
+//! Ruler: the position reads `line:col` (1-based line and byte column), then
+//! `/vcol` when the screen column differs (tabs, wide or multi-byte
+//! clusters), then how far through the file the cursor line is, as Vim's
+//! `CTRL-G` reports it: `[NORMAL] main.rs 12:5/9 40% :`. While a selection
+//! is active its size follows: `3L` for the lines it spans, or `7C` for the
+//! characters of a one-line selection.
+//!
+//! Diagnostics: while the buffer has errors or warnings, ` E:n W:n` follows the file name
+//! (each count only when non-zero), so a clean buffer keeps the legacy format.
+//!
+//! Git: inside a repository the branch follows, in parentheses, with `*` when tracked files
+//! are modified: `[NORMAL] main.rs (main*) 12:5 40% :`. The runtime probes git off the event
+//! loop (`GitState`), so the segment simply appears once the answer is in.
+//!
+//! Provided segments: the texts plugins and other `StatusSegmentProvider`s contributed follow
+//! the branch, space separated, in `[statusline] segments` order: `main.rs (main) 12w 1:1`.
+//!
+//! Split windows: each view gets its own status row built from a `ViewStatusContext`
+//! (`[MODE]` only for the focused view, then name and position, no command segment); the
+//! bottom row then only carries the command line and messages.
 
-use core_state::{Mode, SelectionKind};
+use core_state::{DiagnosticCounts, GitStatus, Mode, SelectionSize};
 
 /// Simple DTO describing what we need to render a status line.
 pub struct StatusContext<'a> {
     pub mode: Mode,
     pub line: usize, // 0-based current line index
     pub col: usize,  // 0-based visual column
+    pub byte: usize, // 0-based byte column
+    /// Percentage of the file's lines up to the cursor line.
+    pub percent: usize,
+    /// Size of the active selection, if any.
+    pub selection: Option<SelectionSize>,
     pub command_active: bool,
     pub command_buffer: &'a str,
     /// Optional file name to display (Phase 2 Step 1). Only base file name shown for brevity.
     pub file_name: Option<&'a std::path::Path>,
     /// Dirty flag – when true, an asterisk is appended to the file name.
     pub dirty: bool,
+    /// Error and warning counts of the buffer's diagnostics.
+    pub diagnostics: DiagnosticCounts,
+    /// Branch of the file's repository, once probed.
+    pub git: Option<&'a GitStatus>,
+    /// Provider-contributed segments, in display order.
+    pub segments: &'a [&'a str],
+}
+
+/// Per-view status row input (split layouts).
+pub struct ViewStatusContext<'a> {
+    /// Mode label shown only on the focused view's row.
+    pub focused_mode: Option<Mode>,
+    pub line: usize, // 0-based current line index
+    pub col: usize,  // 0-based visual column
+    pub byte: usize, // 0-based byte column
+    pub percent: usize,
+    /// Selection size, shown on the focused view's row only.
+    pub selection: Option<SelectionSize>,
+    pub file_name: Option<&'a std::path::Path>,
+    pub dirty: bool,
+    pub diagnostics: DiagnosticCounts,
+    /// Branch of the active file's repository, shown on the focused view's row only.
+    pub git: Option<&'a GitStatus>,
+    /// Provider-contributed segments, shown on the focused view's row only.
+    pub segments: &'a [&'a str],
 }
 
 /// Discrete status line segments (order-sensitive). Refactor R4 Step 6 expands the model to include
@@ -42,14 +93,26 @@ pub enum StatusSegment<'a> {
     /// File name portion including leading space and optional trailing dirty marker `*` to preserve
     /// legacy formatting without additional glue logic.
     FileNameCow(std::borrow::Cow<'a, str>),
-    /// 1-based cursor line & column for display.
-    Position { line_1: usize, col_1: usize },
-    /// Indicates command line inactive; legacy formatting keeps a trailing colon already emitted by Position.
+    /// Diagnostic counts (only pushed when non-zero).
+    Diagnostics(DiagnosticCounts),
+    /// Branch and dirty marker of the file's repository.
+    Git(&'a GitStatus),
+    /// Text of a `StatusSegmentProvider` segment.
+    Provided(&'a str),
+    /// 1-based cursor line, byte column and screen column, and the percentage
+    /// through the file.
+    Ruler {
+        line_1: usize,
+        col_1: usize,
+        vcol_1: usize,
+        percent: usize,
+    },
+    /// Indicates command line inactive; legacy formatting keeps a trailing colon already emitted by Ruler.
     CommandInactive,
     /// Active command buffer content (without the leading ':' sentinel stored internally).
     CommandActive(&'a str),
-    /// Placeholder for an active selection kind (visual mode) – not yet populated.
-    Selection(Option<SelectionKind>),
+    /// Size of the active selection (visual mode); `None` renders nothing.
+    Selection(Option<SelectionSize>),
     /// Placeholder for an explicit register hint (e.g., pending yank to a named register) – unused.
     RegisterHint(Option<char>),
     /// Placeholder flag indicating overlay (metrics) active – unused until Step 13.
@@ -58,39 +121,57 @@ pub enum StatusSegment<'a> {
     Placeholder(&'static str),
 }
 
-/// Produce ordered segments representing the status line.
-pub fn compose_status<'a>(ctx: &'a StatusContext<'a>) -> Vec<StatusSegment<'a>> {
-    let mode_str = match ctx.mode {
+fn mode_label(mode: Mode) -> &'static str {
+    match mode {
         Mode::Normal => "NORMAL",
         Mode::Insert => "INSERT",
         Mode::VisualChar => "VISUAL",
-    };
-    let file_segment: std::borrow::Cow<'_, str> = if let Some(p) = ctx.file_name {
+    }
+}
+
+fn file_segment(file_name: Option<&std::path::Path>, dirty: bool) -> std::borrow::Cow<'_, str> {
+    if let Some(p) = file_name {
         if let Some(name) = p.file_name().and_then(|s| s.to_str()) {
-            if ctx.dirty {
+            if dirty {
                 format!(" {}*", name).into()
             } else {
                 format!(" {}", name).into()
             }
-        } else if ctx.dirty {
+        } else if dirty {
             " *".into()
         } else {
             "".into()
         }
-    } else if ctx.dirty {
+    } else if dirty {
         " [No Name]*".into()
     } else {
         " [No Name]".into()
-    };
+    }
+}
 
+/// Produce ordered segments representing the status line.
+pub fn compose_status<'a>(ctx: &'a StatusContext<'a>) -> Vec<StatusSegment<'a>> {
     // Capacity now accounts for future placeholders though only legacy-impacting first five are rendered.
     let mut out = Vec::with_capacity(8);
-    out.push(StatusSegment::Mode(mode_str));
-    out.push(StatusSegment::FileNameCow(file_segment));
-    out.push(StatusSegment::Position {
+    out.push(StatusSegment::Mode(mode_label(ctx.mode)));
+    out.push(StatusSegment::FileNameCow(file_segment(
+        ctx.file_name,
+        ctx.dirty,
+    )));
+    if !ctx.diagnostics.is_empty() {
+        out.push(StatusSegment::Diagnostics(ctx.diagnostics));
+    }
+    if let Some(git) = ctx.git {
+        out.push(StatusSegment::Git(git));
+    }
+    out.extend(ctx.segments.iter().map(|s| StatusSegment::Provided(s)));
+    out.push(StatusSegment::Ruler {
         line_1: ctx.line + 1,
-        col_1: ctx.col + 1,
+        col_1: ctx.byte + 1,
+        vcol_1: ctx.col + 1,
+        percent: ctx.percent,
     });
+    out.push(StatusSegment::Selection(ctx.selection));
     if ctx.command_active {
         let display = ctx
             .command_buffer
@@ -101,15 +182,45 @@ pub fn compose_status<'a>(ctx: &'a StatusContext<'a>) -> Vec<StatusSegment<'a>>
         out.push(StatusSegment::CommandInactive);
     }
     // Append scaffold placeholders with default inert values so tests can introspect presence.
-    out.push(StatusSegment::Selection(None));
     out.push(StatusSegment::RegisterHint(None));
     out.push(StatusSegment::OverlayActive(false));
     out
 }
 
+/// Segments of one view's status row (no command segment).
+pub fn compose_view_status<'a>(ctx: &'a ViewStatusContext<'a>) -> Vec<StatusSegment<'a>> {
+    let mut out = Vec::with_capacity(5);
+    if let Some(mode) = ctx.focused_mode {
+        out.push(StatusSegment::Mode(mode_label(mode)));
+    }
+    out.push(StatusSegment::FileNameCow(file_segment(
+        ctx.file_name,
+        ctx.dirty,
+    )));
+    if !ctx.diagnostics.is_empty() {
+        out.push(StatusSegment::Diagnostics(ctx.diagnostics));
+    }
+    if let (Some(git), Some(_)) = (ctx.git, ctx.focused_mode) {
+        out.push(StatusSegment::Git(git));
+    }
+    if ctx.focused_mode.is_some() {
+        out.extend(ctx.segments.iter().map(|s| StatusSegment::Provided(s)));
+    }
+    out.push(StatusSegment::Ruler {
+        line_1: ctx.line + 1,
+        col_1: ctx.byte + 1,
+        vcol_1: ctx.col + 1,
+        percent: ctx.percent,
+    });
+    if ctx.focused_mode.is_some() {
+        out.push(StatusSegment::Selection(ctx.selection));
+    }
+    out
+}
+
 /// Render ordered status segments into the final legacy string (exact match guaranteed by tests).
 pub fn format_status(segments: &[StatusSegment<'_>]) -> String {
-    // We know the approximate shape: [MODE]<file> Ln X, Col Y :<optional_cmd>
+    // We know the approximate shape: [MODE]<file> L:C/V P% :<optional_cmd>
     // Pre-compute a conservative capacity to avoid many reallocations.
     let mut s = String::with_capacity(48);
     for seg in segments {
@@ -120,17 +231,55 @@ pub fn format_status(segments: &[StatusSegment<'_>]) -> String {
                 s.push(']');
             }
             StatusSegment::FileNameCow(name) => s.push_str(name),
-            StatusSegment::Position { line_1, col_1 } => {
+            StatusSegment::Diagnostics(counts) => {
                 use std::fmt::Write as _;
-                let _ = write!(s, " Ln {}, Col {} :", line_1, col_1);
+                if counts.errors > 0 {
+                    let _ = write!(s, " E:{}", counts.errors);
+                }
+                if counts.warnings > 0 {
+                    let _ = write!(s, " W:{}", counts.warnings);
+                }
+            }
+            StatusSegment::Git(git) => {
+                s.push_str(" (");
+                s.push_str(&git.branch);
+                if git.dirty {
+                    s.push('*');
+                }
+                s.push(')');
+            }
+            StatusSegment::Provided(text) => {
+                s.push(' ');
+                s.push_str(text);
             }
-            StatusSegment::CommandInactive => { /* colon already appended by Position */ }
+            StatusSegment::Ruler {
+                line_1,
+                col_1,
+                vcol_1,
+                percent,
+            } => {
+                use std::fmt::Write as _;
+                let _ = write!(s, " {line_1}:{col_1}");
+                if vcol_1 != col_1 {
+                    let _ = write!(s, "/{vcol_1}");
+                }
+                let _ = write!(s, " {percent}%");
+            }
+            StatusSegment::CommandInactive => s.push_str(" :"),
             StatusSegment::CommandActive(cmd) => {
-                // Position added trailing colon; append command directly.
+                s.push_str(" :");
                 s.push_str(cmd);
             }
+            StatusSegment::Selection(Some(SelectionSize::Lines(n))) => {
+                use std::fmt::Write as _;
+                let _ = write!(s, " {n}L");
+            }
+            StatusSegment::Selection(Some(SelectionSize::Chars(n))) => {
+                use std::fmt::Write as _;
+                let _ = write!(s, " {n}C");
+            }
             // Placeholders intentionally not rendered in legacy string yet.
-            StatusSegment::Selection(_) => {}
+            StatusSegment::Selection(None) => {}
             StatusSegment::RegisterHint(_) => {}
             StatusSegment::OverlayActive(_) => {}
             StatusSegment::Placeholder(p) => s.push_str(p),
@@ -144,22 +293,151 @@ pub fn build_status(ctx: &StatusContext) -> String {
     format_status(&compose_status(ctx))
 }
 
+pub fn build_view_status(ctx: &ViewStatusContext) -> String {
+    format_status(&compose_view_status(ctx))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn view_status_shows_mode_only_when_focused() {
+        let mut ctx = ViewStatusContext {
+            focused_mode: Some(Mode::Insert),
+            line: 2,
+            col: 0,
+            byte: 0,
+            percent: 50,
+            selection: None,
+            file_name: Some(std::path::Path::new("lib.rs")),
+            dirty: true,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
+        };
+        assert_eq!(build_view_status(&ctx), "[INSERT] lib.rs* 3:1 50%");
+        ctx.focused_mode = None;
+        assert_eq!(build_view_status(&ctx), " lib.rs* 3:1 50%");
+    }
+
+    #[test]
+    fn ruler_shows_screen_column_percent_and_selection() {
+        let mut ctx = StatusContext {
+            mode: Mode::VisualChar,
+            line: 9,
+            col: 8,
+            byte: 1,
+            percent: 40,
+            selection: Some(SelectionSize::Chars(7)),
+            command_active: false,
+            command_buffer: "",
+            file_name: Some(std::path::Path::new("main.rs")),
+            dirty: false,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
+        };
+        assert_eq!(build_status(&ctx), "[VISUAL] main.rs 10:2/9 40% 7C :");
+        ctx.selection = Some(SelectionSize::Lines(3));
+        ctx.command_active = true;
+        ctx.command_buffer = ":'<,'>d";
+        assert_eq!(build_status(&ctx), "[VISUAL] main.rs 10:2/9 40% 3L :'<,'>d");
+    }
+
+    #[test]
+    fn diagnostic_counts_follow_the_file_name() {
+        let mut ctx = StatusContext {
+            mode: Mode::Normal,
+            line: 0,
+            col: 0,
+            byte: 0,
+            percent: 50,
+            selection: None,
+            command_active: false,
+            command_buffer: "",
+            file_name: Some(std::path::Path::new("main.rs")),
+            dirty: false,
+            diagnostics: DiagnosticCounts {
+                errors: 2,
+                warnings: 1,
+            },
+            git: None,
+            segments: &[],
+        };
+        assert_eq!(build_status(&ctx), "[NORMAL] main.rs E:2 W:1 1:1 50% :");
+        ctx.diagnostics.errors = 0;
+        assert_eq!(build_status(&ctx), "[NORMAL] main.rs W:1 1:1 50% :");
+    }
+
+    #[test]
+    fn git_branch_follows_file_and_diagnostics() {
+        let git = GitStatus {
+            branch: "main".into(),
+            dirty: true,
+        };
+        let ctx = StatusContext {
+            mode: Mode::Normal,
+            line: 0,
+            col: 0,
+            byte: 0,
+            percent: 50,
+            selection: None,
+            command_active: false,
+            command_buffer: "",
+            file_name: Some(std::path::Path::new("main.rs")),
+            dirty: false,
+            diagnostics: DiagnosticCounts {
+                errors: 1,
+                warnings: 0,
+            },
+            git: Some(&git),
+            segments: &[],
+        };
+        assert_eq!(build_status(&ctx), "[NORMAL] main.rs E:1 (main*) 1:1 50% :");
+        let mut view = ViewStatusContext {
+            focused_mode: Some(Mode::Normal),
+            line: 0,
+            col: 0,
+            byte: 0,
+            percent: 50,
+            selection: None,
+            file_name: Some(std::path::Path::new("main.rs")),
+            dirty: false,
+            diagnostics: DiagnosticCounts::default(),
+            git: Some(&git),
+            segments: &[],
+        };
+        assert_eq!(build_view_status(&view), "[NORMAL] main.rs (main*) 1:1 50%");
+        let provided = ["12w", "lsp:ok"];
+        view.segments = &provided;
+        assert_eq!(
+            build_view_status(&view),
+            "[NORMAL] main.rs (main*) 12w lsp:ok 1:1 50%"
+        );
+        // Unfocused views may show another repository's file.
+        view.focused_mode = None;
+        assert_eq!(build_view_status(&view), " main.rs 1:1 50%");
+    }
     #[test]
     fn builds_status_normal_no_cmd() {
         let ctx = StatusContext {
             mode: Mode::Normal,
             line: 0,
             col: 4,
+            byte: 4,
+            percent: 50,
+            selection: None,
             command_active: false,
             command_buffer: "",
             file_name: None,
             dirty: false,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
         };
         let s = format_status(&compose_status(&ctx));
-        assert_eq!(s, "[NORMAL] [No Name] Ln 1, Col 5 :");
+        assert_eq!(s, "[NORMAL] [No Name] 1:5 50% :");
     }
 
     #[test]
@@ -168,13 +446,19 @@ mod tests {
             mode: Mode::Insert,
             line: 2,
             col: 10,
+            byte: 10,
+            percent: 50,
+            selection: None,
             command_active: true,
             command_buffer: ":wq",
             file_name: Some(std::path::Path::new("file.rs")),
             dirty: true,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
         };
         let s = format_status(&compose_status(&ctx));
-        assert_eq!(s, "[INSERT] file.rs* Ln 3, Col 11 :wq");
+        assert_eq!(s, "[INSERT] file.rs* 3:11 50% :wq");
     }
 
     #[test]
@@ -183,13 +467,19 @@ mod tests {
             mode: Mode::Normal,
             line: 4,
             col: 0,
+            byte: 0,
+            percent: 50,
+            selection: None,
             command_active: false,
             command_buffer: "",
             file_name: Some(std::path::Path::new("main.rs")),
             dirty: false,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
         };
         let s = format_status(&compose_status(&ctx));
-        assert_eq!(s, "[NORMAL] main.rs Ln 5, Col 1 :");
+        assert_eq!(s, "[NORMAL] main.rs 5:1 50% :");
     }
 
     #[test]
@@ -198,13 +488,19 @@ mod tests {
             mode: Mode::Insert,
             line: 0,
             col: 0,
+            byte: 0,
+            percent: 50,
+            selection: None,
             command_active: false,
             command_buffer: "",
             file_name: None,
             dirty: true,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
         };
         let s = format_status(&compose_status(&ctx));
-        assert_eq!(s, "[INSERT] [No Name]* Ln 1, Col 1 :");
+        assert_eq!(s, "[INSERT] [No Name]* 1:1 50% :");
     }
 
     #[test]
@@ -213,13 +509,28 @@ mod tests {
             mode: Mode::Insert,
             line: 1,
             col: 2,
+            byte: 2,
+            percent: 50,
+            selection: None,
             command_active: true,
             command_buffer: ":e test.txt",
             file_name: None,
             dirty: false,
+            diagnostics: DiagnosticCounts::default(),
+            git: None,
+            segments: &[],
         };
         let s = format_status(&compose_status(&ctx));
-        assert_eq!(s, "[INSERT] [No Name] Ln 2, Col 3 :e test.txt");
+        assert_eq!(s, "[INSERT] [No Name] 2:3 50% :e test.txt");
+    }
+
+    fn legacy_ruler(ctx: &StatusContext) -> String {
+        if ctx.byte == ctx.col {
+            format!("{}:{} {}%", ctx.line + 1, ctx.byte + 1, ctx.percent)
+        } else {
+            let (line, byte, col) = (ctx.line + 1, ctx.byte + 1, ctx.col + 1);
+            format!("{line}:{byte}/{col} {}%", ctx.percent)
+        }
     }
 
     // Regression: compare segmented output with legacy formatting logic reproduction
@@ -252,21 +563,14 @@ mod tests {
                 .strip_prefix(':')
                 .unwrap_or(ctx.command_buffer);
             format!(
-                "[{}]{} Ln {}, Col {} :{}",
+                "[{}]{} {} :{}",
                 mode_str,
                 file_segment,
-                ctx.line + 1,
-                ctx.col + 1,
+                legacy_ruler(ctx),
                 display
             )
         } else {
-            format!(
-                "[{}]{} Ln {}, Col {} :",
-                mode_str,
-                file_segment,
-                ctx.line + 1,
-                ctx.col + 1
-            )
+            format!("[{}]{} {} :", mode_str, file_segment, legacy_ruler(ctx))
         }
     }
 
@@ -277,37 +581,61 @@ mod tests {
                 mode: Mode::Normal,
                 line: 0,
                 col: 0,
+                byte: 0,
+                percent: 50,
+                selection: None,
                 command_active: false,
                 command_buffer: "",
                 file_name: None,
                 dirty: false,
+                diagnostics: DiagnosticCounts::default(),
+                git: None,
+                segments: &[],
             },
             StatusContext {
                 mode: Mode::Insert,
                 line: 10,
                 col: 5,
+                byte: 5,
+                percent: 50,
+                selection: None,
                 command_active: false,
                 command_buffer: "",
                 file_name: None,
                 dirty: true,
+                diagnostics: DiagnosticCounts::default(),
+                git: None,
+                segments: &[],
             },
             StatusContext {
                 mode: Mode::Insert,
                 line: 2,
                 col: 7,
+                byte: 7,
+                percent: 50,
+                selection: None,
                 command_active: true,
                 command_buffer: ":x",
                 file_name: Some(std::path::Path::new("lib.rs")),
                 dirty: false,
+                diagnostics: DiagnosticCounts::default(),
+                git: None,
+                segments: &[],
             },
             StatusContext {
                 mode: Mode::Normal,
                 line: 4,
                 col: 9,
+                byte: 9,
+                percent: 50,
+                selection: None,
                 command_active: true,
                 command_buffer: ":write",
                 file_name: Some(std::path::Path::new("main.rs")),
                 dirty: true,
+                diagnostics: DiagnosticCounts::default(),
+                git: None,
+                segments: &[],
             },
         ];
         for ctx in cases {
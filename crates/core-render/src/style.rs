@@ -11,22 +11,63 @@
 //!   spans.
 //! * Spans are line-local (identified by `line`). Horizontal ranges use
 //!   half-open `[start_col, end_col)` semantics in visual columns.
-//! * Overlap semantics are undefined for now (later phases will reconcile by
-//!   z-order or layering rules). Step 8 guarantees at most one span (cursor).
+//! * Overlapping spans from different sources are reconciled by priority
+//!   (`merge_line_spans`); the cursor span is applied separately.
 //! * No allocation churn: a single `StyleLayer` is reused per frame via
 //!   `clear()`; later we may pool or smallvec optimize if profiling warrants.
 //!
+//! Themes: `Palette` resolves a `core_config::theme::Theme` for the terminal's
+//! color depth, degrading colors it cannot show to the nearest 256-color or
+//! ANSI entry (and dropping underline colors on sixteen-color terminals).
+//! Syntax spans take their class's group, `Selection` and
+//! `Search` the `Visual` and `Search` groups. `Search` spans mark the matches
+//! of the last search pattern while `hlsearch` is on; their colors replace
+//! the syntax colors of the cells they cover. `CursorShade` describes the
+//! `cursorline` / `cursorcolumn` cells of a view, which take the `CursorLine`
+//! and `CursorColumn` backgrounds under their syntax and search colors, and
+//! the `colorcolumn` rulers, which take `ColorColumn` (over `CursorLine`,
+//! under `CursorColumn`).
+//! `'list'` glyphs (`crate::whitespace`) take `Whitespace` instead of their
+//! syntax color, and closed fold summary rows take `Folded`. Diagnostic
+//! spans add their severity's `DiagnosticUnderline*` attributes on top of
+//! everything else, so the text keeps its colors under the underline. The
+//! end-of-line diagnostic message (`diagnostic_virtual_text`) takes
+//! `DiagnosticVirtualText*` instead; the cursor line's `:blame` annotation,
+//! shown after the line when it has no message, takes `GitBlame`.
+//!
+//! Span providers: colorization sources implement `StyleSpanProvider` and
+//! are registered with the engine (`RenderEngine::add_style_provider`)
+//! instead of reaching into the paint paths. For every visible line the
+//! engine collects each provider's byte spans and merges them: where spans
+//! overlap the higher priority wins, ties going to the provider registered
+//! last. Syntax highlighting is the built-in provider (`SyntaxSpans`, at
+//! `SYNTAX_PRIORITY`). Diagnostic underlines and `cursorline` shading
+//! compose with whatever the merge picks, so they stay outside it. The
+//! engine cannot see when a provider's answer changes without an edit;
+//! such a provider's owner invalidates the affected lines.
+//!
 //! Future extensions (documented up front to avoid ad hoc growth):
-//! * Syntax(u16) span classes mapped to theme index.
 //! * Selection / Visual mode multi-spans.
-//! * Overlay / diagnostics categories.
 //! * Per-span attribute bitflags (bold, italic, underline) if needed.
 
+use crate::CellFlags;
+use crate::gutter::Gutter;
+use core_config::theme::{Color, GroupStyle, Theme};
+use core_model::View;
+use core_state::diagnostics::{Severity, severity_at};
+use core_state::{BufferId, EditorState, HighlightSpan};
+use core_syntax::HighlightClass;
+use core_terminal::ColorDepth;
+use core_text::grapheme;
+use std::ops::Range;
+use std::sync::OnceLock;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum StyleAttr {
     InvertCursor,
     Syntax(u16),
     Selection,
+    Search,
     Overlay,
 }
 
@@ -66,6 +107,741 @@ impl StyleLayer {
     }
 }
 
+impl StyleAttr {
+    /// Cell flags and syntax class the attribute paints with. `Selection`
+    /// is reverse video, as in the built-in `Visual` group; `Overlay` adds
+    /// nothing.
+    pub fn cell_style(self) -> (CellFlags, Option<u16>) {
+        match self {
+            StyleAttr::InvertCursor | StyleAttr::Selection => (CellFlags::REVERSE, None),
+            StyleAttr::Syntax(class) => (CellFlags::empty(), Some(class)),
+            StyleAttr::Search => (CellFlags::SEARCH, None),
+            StyleAttr::Overlay => (CellFlags::empty(), None),
+        }
+    }
+}
+
+/// Priority of the built-in syntax spans. Providers above it paint over
+/// syntax colors; providers below it only fill the gaps between them.
+pub const SYNTAX_PRIORITY: u8 = 100;
+
+/// Bytes `[start, end)` of one line (line ending excluded) styled by `attr`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineSpan {
+    pub start: usize,
+    pub end: usize,
+    pub attr: StyleAttr,
+}
+
+/// A source of highlight spans (syntax, diagnostics, a plugin). Asked only
+/// for the lines a frame shows, on the render path, so it must answer from
+/// state it already holds rather than compute on demand.
+pub trait StyleSpanProvider: Send + Sync {
+    /// Provider name; a second provider with the same name is refused.
+    fn name(&self) -> &str;
+    /// Higher priorities win where spans overlap.
+    fn priority(&self) -> u8;
+    /// Push the spans of line `line` of `buffer`, whose text without its
+    /// line ending is `text`. The engine drops empty spans and spans that
+    /// end past `text` or cut a character.
+    fn line_spans(
+        &self,
+        state: &EditorState,
+        buffer: BufferId,
+        line: usize,
+        text: &str,
+        out: &mut Vec<LineSpan>,
+    );
+}
+
+/// Built-in provider for the buffer's syntax highlight spans.
+#[derive(Debug, Default)]
+pub struct SyntaxSpans;
+
+impl StyleSpanProvider for SyntaxSpans {
+    fn name(&self) -> &str {
+        "syntax"
+    }
+
+    fn priority(&self) -> u8 {
+        SYNTAX_PRIORITY
+    }
+
+    fn line_spans(
+        &self,
+        state: &EditorState,
+        buffer: BufferId,
+        line: usize,
+        _text: &str,
+        out: &mut Vec<LineSpan>,
+    ) {
+        out.extend(
+            state
+                .highlights
+                .line(buffer, line)
+                .iter()
+                .map(|s| LineSpan {
+                    start: s.start,
+                    end: s.end,
+                    attr: StyleAttr::Syntax(s.class),
+                }),
+        );
+    }
+}
+
+/// Registered providers in ascending priority (registration order within
+/// one priority).
+pub struct StyleProviders {
+    providers: Vec<Box<dyn StyleSpanProvider>>,
+}
+
+impl Default for StyleProviders {
+    fn default() -> Self {
+        Self {
+            providers: vec![Box::new(SyntaxSpans)],
+        }
+    }
+}
+
+impl StyleProviders {
+    /// The built-in set (syntax only); what the free frame builders use.
+    pub fn builtin() -> &'static StyleProviders {
+        static BUILTIN: OnceLock<StyleProviders> = OnceLock::new();
+        BUILTIN.get_or_init(StyleProviders::default)
+    }
+
+    /// Add `provider`; returns false (and drops it) when the name is taken.
+    pub fn add(&mut self, provider: Box<dyn StyleSpanProvider>) -> bool {
+        if self.providers.iter().any(|p| p.name() == provider.name()) {
+            return false;
+        }
+        let at = self
+            .providers
+            .partition_point(|p| p.priority() <= provider.priority());
+        self.providers.insert(at, provider);
+        true
+    }
+
+    /// Remove the provider named `name`; returns whether one was registered.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.providers.len();
+        self.providers.retain(|p| p.name() != name);
+        self.providers.len() != before
+    }
+
+    /// Provider names, lowest priority first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.providers.iter().map(|p| p.name())
+    }
+
+    /// Merged spans of line `line` of `buffer` (text `text`, line ending
+    /// excluded).
+    pub fn line(
+        &self,
+        state: &EditorState,
+        buffer: BufferId,
+        line: usize,
+        text: &str,
+    ) -> Vec<LineSpan> {
+        let mut scratch = Vec::new();
+        let layers: Vec<Vec<LineSpan>> = self
+            .providers
+            .iter()
+            .map(|p| {
+                scratch.clear();
+                p.line_spans(state, buffer, line, text, &mut scratch);
+                scratch
+                    .iter()
+                    .filter(|s| {
+                        s.start < s.end
+                            && s.end <= text.len()
+                            && text.is_char_boundary(s.start)
+                            && text.is_char_boundary(s.end)
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        merge_line_spans(&layers)
+    }
+}
+
+/// Merge `layers` (lowest priority first) into sorted, non-overlapping
+/// spans: each byte takes the attribute of the last layer covering it, and
+/// within a layer its first span covering it. Adjacent pieces with the
+/// same attribute are joined.
+pub fn merge_line_spans(layers: &[Vec<LineSpan>]) -> Vec<LineSpan> {
+    let mut cuts: Vec<usize> = layers
+        .iter()
+        .flatten()
+        .flat_map(|s| [s.start, s.end])
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    let mut merged: Vec<LineSpan> = Vec::new();
+    for piece in cuts.windows(2) {
+        let (start, end) = (piece[0], piece[1]);
+        let attr = layers.iter().rev().find_map(|layer| {
+            layer
+                .iter()
+                .find(|s| s.start <= start && end <= s.end)
+                .map(|s| s.attr)
+        });
+        let Some(attr) = attr else {
+            continue;
+        };
+        match merged.last_mut() {
+            Some(last) if last.end == start && last.attr == attr => last.end = end,
+            _ => merged.push(LineSpan { start, end, attr }),
+        }
+    }
+    merged
+}
+
+/// Attribute of the merged span covering byte `byte`, if any.
+pub fn line_attr_at(spans: &[LineSpan], byte: usize) -> Option<StyleAttr> {
+    let i = spans.partition_point(|s| s.end <= byte);
+    spans.get(i).filter(|s| s.start <= byte).map(|s| s.attr)
+}
+
+/// A color scheme resolved to SGR parameters for one terminal color depth.
+/// UI groups apply to semantic overlays (`StyleAttr`), status rows
+/// (`CellFlags::STATUS`) and, for `Normal`, the base every row starts from;
+/// syntax groups are indexed by `HighlightClass`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    name: String,
+    normal: Option<String>,
+    status_line: Option<String>,
+    visual: Option<String>,
+    search: Option<String>,
+    cursor_line: Option<String>,
+    cursor_column: Option<String>,
+    color_column: Option<String>,
+    whitespace: Option<String>,
+    folded: Option<String>,
+    /// `DiagnosticUnderline*` by `Severity`.
+    diagnostic: [Option<String>; 4],
+    /// `DiagnosticVirtualText*` by `Severity`.
+    virtual_text: [Option<String>; 4],
+    blame: Option<String>,
+    /// `DiffAdd`, `DiffChange`, `DiffDelete`.
+    diff: [Option<String>; 3],
+    syntax: Vec<Option<String>>,
+}
+
+impl Palette {
+    pub fn resolve(theme: &Theme, depth: ColorDepth) -> Palette {
+        let group = |name: &str| theme.group(name).and_then(|g| group_sgr(g, depth));
+        Palette {
+            name: theme.name.clone(),
+            normal: group("Normal"),
+            status_line: group("StatusLine"),
+            visual: group("Visual"),
+            search: group("Search"),
+            cursor_line: group("CursorLine"),
+            cursor_column: group("CursorColumn"),
+            color_column: group("ColorColumn"),
+            whitespace: group("Whitespace"),
+            folded: group("Folded"),
+            diagnostic: [
+                group("DiagnosticUnderlineError"),
+                group("DiagnosticUnderlineWarn"),
+                group("DiagnosticUnderlineInfo"),
+                group("DiagnosticUnderlineHint"),
+            ],
+            virtual_text: [
+                group("DiagnosticVirtualTextError"),
+                group("DiagnosticVirtualTextWarn"),
+                group("DiagnosticVirtualTextInfo"),
+                group("DiagnosticVirtualTextHint"),
+            ],
+            blame: group("GitBlame"),
+            diff: [group("DiffAdd"), group("DiffChange"), group("DiffDelete")],
+            syntax: HighlightClass::ALL
+                .iter()
+                .map(|class| group(class.name()))
+                .collect(),
+        }
+    }
+
+    /// The built-in scheme; what `Cell::styled` uses.
+    pub fn builtin() -> &'static Palette {
+        static BUILTIN: OnceLock<Palette> = OnceLock::new();
+        BUILTIN.get_or_init(|| Palette::resolve(&Theme::default(), ColorDepth::Ansi256))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Escape sequence every row starts from (`None` keeps the terminal's
+    /// default colors, as does the built-in scheme).
+    pub fn base(&self) -> Option<String> {
+        self.normal.as_ref().map(|sgr| format!("\x1b[0;{sgr}m"))
+    }
+
+    /// SGR parameters for a syntax class.
+    pub fn syntax_sgr(&self, class: u16) -> Option<&str> {
+        self.syntax.get(class as usize)?.as_deref()
+    }
+
+    /// SGR parameters for a semantic style attribute.
+    pub fn attr_sgr(&self, attr: StyleAttr) -> Option<&str> {
+        match attr {
+            StyleAttr::InvertCursor => Some("7"),
+            StyleAttr::Syntax(class) => self.syntax_sgr(class),
+            StyleAttr::Selection => self.visual.as_deref(),
+            StyleAttr::Search => self.search.as_deref(),
+            StyleAttr::Overlay => None,
+        }
+    }
+
+    /// `cluster` wrapped in the SGR sequence for its flags and syntax class
+    /// (plain when neither applies, so the writer can batch it). Status
+    /// cells take `StatusLine` instead of reverse video when it is set, and
+    /// search matches take `Search`, `'list'` glyphs `Whitespace` and fold
+    /// summaries `Folded` instead of their syntax color. Cursor shading
+    /// goes underneath both; `CursorColumn` wins where the cursor row and
+    /// column cross, and diff lines keep their `Diff*` shading over
+    /// `CursorLine`. A diagnostic underline goes last; virtual text takes
+    /// its severity's `DiagnosticVirtualText*` there instead, or `GitBlame`
+    /// without a severity.
+    pub fn styled(&self, cluster: &str, flags: CellFlags, syntax: Option<u16>) -> String {
+        let base = match &self.status_line {
+            Some(sgr) if flags.contains(CellFlags::STATUS) => Some(sgr.as_str()),
+            _ => flags.contains(CellFlags::REVERSE).then_some("7"),
+        };
+        let shade = if flags.contains(CellFlags::CURSORCOLUMN) {
+            self.cursor_column.as_deref()
+        } else if flags.contains(CellFlags::COLORCOLUMN) {
+            self.color_column.as_deref()
+        } else if let Some(i) = [
+            CellFlags::DIFF_ADD,
+            CellFlags::DIFF_CHANGE,
+            CellFlags::DIFF_DELETE,
+        ]
+        .iter()
+        .position(|f| flags.contains(*f))
+        {
+            self.diff[i].as_deref()
+        } else if flags.contains(CellFlags::CURSORLINE) {
+            self.cursor_line.as_deref()
+        } else {
+            None
+        };
+        let color = match (&self.search, &self.whitespace, &self.folded) {
+            (Some(sgr), _, _) if flags.contains(CellFlags::SEARCH) => Some(sgr.as_str()),
+            (_, Some(sgr), _) if flags.contains(CellFlags::WHITESPACE) => Some(sgr.as_str()),
+            (_, _, Some(sgr)) if flags.contains(CellFlags::FOLDED) => Some(sgr.as_str()),
+            _ => syntax.and_then(|c| self.syntax_sgr(c)),
+        };
+        let diagnostic = [
+            CellFlags::DIAG_ERROR,
+            CellFlags::DIAG_WARNING,
+            CellFlags::DIAG_INFO,
+            CellFlags::DIAG_HINT,
+        ]
+        .iter()
+        .position(|f| flags.contains(*f))
+        .and_then(|i| {
+            if flags.contains(CellFlags::VIRTUAL_TEXT) {
+                self.virtual_text[i].as_deref()
+            } else {
+                self.diagnostic[i].as_deref()
+            }
+        })
+        .or_else(|| {
+            flags
+                .contains(CellFlags::VIRTUAL_TEXT)
+                .then_some(self.blame.as_deref())
+                .flatten()
+        });
+        let params: Vec<&str> = [base, shade, color, diagnostic]
+            .into_iter()
+            .flatten()
+            .collect();
+        if params.is_empty() {
+            cluster.to_string()
+        } else {
+            format!("\x1b[{}m{cluster}\x1b[0m", params.join(";"))
+        }
+    }
+
+    /// A status line printed as one string, padded to `width` columns when
+    /// `StatusLine` colors it.
+    pub fn status(&self, text: &str, width: u16) -> String {
+        match &self.status_line {
+            None => text.to_string(),
+            Some(sgr) => {
+                let pad = (width as usize).saturating_sub(grapheme::visual_col(text, text.len()));
+                format!("\x1b[{sgr}m{text}{:pad$}\x1b[0m", "")
+            }
+        }
+    }
+}
+
+/// Cells of one view shaded by `cursorline` / `cursorcolumn` and the
+/// `colorcolumn` rulers.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CursorShade {
+    /// Buffer line whose text rows take `CursorLine`.
+    pub line: Option<usize>,
+    /// Screen columns `[start, end)` of the cursor cluster, which take
+    /// `CursorColumn` on every buffer row.
+    pub column: Option<(u16, u16)>,
+    /// Screen columns of the `colorcolumn` rulers, shaded on every buffer
+    /// row; a wide cluster covering one is shaded whole.
+    pub rulers: Vec<u16>,
+    /// First text column (the gutter width); the gutter is never shaded.
+    pub text_start: u16,
+    /// Buffer line showing its `:blame` annotation: the cursor line while
+    /// `:blame` is on.
+    pub blame: Option<usize>,
+}
+
+impl CursorShade {
+    /// Shading for `view`'s own cursor painted `w` columns wide (none for
+    /// hex views). Under `'wrap'` the column is the cursor's column on its
+    /// wrapped row and every row of the cursor line is shaded; rulers sit
+    /// at the same screen column on every row, wrapped or not.
+    pub fn for_view(state: &EditorState, view: &View, w: u16) -> CursorShade {
+        let line_on = state.options.get_bool("cursorline");
+        let column_on = state.options.get_bool("cursorcolumn");
+        let entry = state
+            .buffers
+            .get(view.buffer_id)
+            .filter(|entry| !(entry.meta.hex_view && entry.meta.binary.is_some()));
+        let columns = core_config::options::color_columns(state.options.get_string("colorcolumn"))
+            .unwrap_or_default();
+        let blame = entry
+            .filter(|_| state.git.blame_on())
+            .map(|_| view.cursor.line);
+        if entry.is_none() || (!line_on && !column_on && columns.is_empty()) {
+            return CursorShade {
+                blame,
+                ..CursorShade::default()
+            };
+        }
+        let text_start = Gutter::for_view(state, view).width;
+        let rulers = columns
+            .iter()
+            .map(|c| text_start.saturating_add(c - 1))
+            .filter(|x| *x < w)
+            .collect();
+        let raw = entry
+            .and_then(|entry| entry.buffer.line(view.cursor.line))
+            .filter(|_| line_on || column_on);
+        let Some(raw) = raw else {
+            return CursorShade {
+                rulers,
+                text_start,
+                blame,
+                ..CursorShade::default()
+            };
+        };
+        let text = raw.trim_end_matches(['\n', '\r']);
+        let byte = view.cursor.byte.min(text.len());
+        let col = match crate::wrap::view_wrap(state, view, w) {
+            Some(wrap) => wrap.locate(text, byte).1,
+            None => grapheme::visual_col(text, byte) as u16,
+        };
+        let start = text_start + col;
+        let next = grapheme::next_boundary(text, byte);
+        let line_col = core_text::wrap::line_col(text, byte);
+        let width = grapheme::cell_width(&text[byte..next], line_col).max(1) as u16;
+        CursorShade {
+            line: line_on.then_some(view.cursor.line),
+            column: column_on.then_some((start, start + width)),
+            rulers,
+            text_start,
+            blame,
+        }
+    }
+
+    /// Flags for the cells of buffer line `line` at screen columns
+    /// `[col, col + width)`.
+    pub fn flags(&self, line: usize, col: u16, width: u16) -> CellFlags {
+        let mut flags = CellFlags::empty();
+        if self.line == Some(line) {
+            flags |= CellFlags::CURSORLINE;
+        }
+        if let Some((start, end)) = self.column
+            && col < end
+            && start < col + width
+        {
+            flags |= CellFlags::CURSORCOLUMN;
+        }
+        if self.rulers.iter().any(|x| (col..col + width).contains(x)) {
+            flags |= CellFlags::COLORCOLUMN;
+        }
+        flags
+    }
+
+    /// Screen column up to which the blank cells after the text of `line`
+    /// are shaded (0 when none are).
+    pub fn fill_end(&self, line: usize) -> u16 {
+        if self.line == Some(line) {
+            return u16::MAX;
+        }
+        let column = self.column.map_or(0, |(_, end)| end);
+        let ruler = self.rulers.last().map_or(0, |x| x + 1);
+        column.max(ruler)
+    }
+}
+
+/// `cluster` styled by the built-in palette.
+pub fn styled_cluster(cluster: &str, flags: CellFlags, syntax: Option<u16>) -> String {
+    Palette::builtin().styled(cluster, flags, syntax)
+}
+
+/// SGR parameters (`1;38;5;208`) for a group; `None` when it sets nothing.
+pub fn group_sgr(style: &GroupStyle, depth: ColorDepth) -> Option<String> {
+    let mut params: Vec<String> = [
+        (style.bold, "1"),
+        (style.italic, "3"),
+        (style.underline, "4"),
+        (style.reverse, "7"),
+    ]
+    .iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, p)| p.to_string())
+    .collect();
+    params.extend(style.fg.map(|c| color_sgr(c, depth, false)));
+    params.extend(style.bg.map(|c| color_sgr(c, depth, true)));
+    params.extend(style.sp.and_then(|c| underline_color_sgr(c, depth)));
+    (!params.is_empty()).then(|| params.join(";"))
+}
+
+/// SGR parameters for an underline color (`58`), which has no short codes;
+/// sixteen-color terminals get the underline without a color.
+fn underline_color_sgr(color: Color, depth: ColorDepth) -> Option<String> {
+    let sgr = match (color, depth) {
+        (_, ColorDepth::Ansi16) => return None,
+        (Color::Indexed(i), _) => format!("58;5;{i}"),
+        (Color::Rgb(r, g, b), ColorDepth::TrueColor) => format!("58;2;{r};{g};{b}"),
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => format!("58;5;{}", rgb_to_ansi256(r, g, b)),
+    };
+    Some(sgr)
+}
+
+/// SGR parameters for one color: the sixteen ANSI colors keep their short
+/// codes, RGB falls back to the nearest 256-color entry without truecolor,
+/// and everything falls back to the nearest ANSI color with sixteen.
+fn color_sgr(color: Color, depth: ColorDepth, background: bool) -> String {
+    let (ansi, bright, extended) = if background {
+        (40, 100, 48)
+    } else {
+        (30, 90, 38)
+    };
+    let color = match (color, depth) {
+        (Color::Indexed(i @ 16..), ColorDepth::Ansi16) => {
+            let (r, g, b) = ansi256_to_rgb(i);
+            Color::Indexed(rgb_to_ansi16(r, g, b))
+        }
+        (Color::Rgb(r, g, b), ColorDepth::Ansi16) => Color::Indexed(rgb_to_ansi16(r, g, b)),
+        _ => color,
+    };
+    match color {
+        Color::Indexed(i @ 0..8) => (ansi + i as u16).to_string(),
+        Color::Indexed(i @ 8..16) => (bright + i as u16 - 8).to_string(),
+        Color::Indexed(i) => format!("{extended};5;{i}"),
+        Color::Rgb(r, g, b) => match depth {
+            ColorDepth::TrueColor => format!("{extended};2;{r};{g};{b}"),
+            _ => format!("{extended};5;{}", rgb_to_ansi256(r, g, b)),
+        },
+    }
+}
+
+/// xterm's default RGB values of the sixteen ANSI colors.
+const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// RGB of an xterm 256-color entry.
+pub fn ansi256_to_rgb(i: u8) -> (u8, u8, u8) {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match i {
+        0..16 => ANSI16_RGB[i as usize],
+        16..232 => {
+            let i = i - 16;
+            (
+                LEVELS[(i / 36) as usize],
+                LEVELS[(i / 6 % 6) as usize],
+                LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (i - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Nearest of the sixteen ANSI colors (by xterm's defaults).
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> u8 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    (0..16u8)
+        .min_by_key(|&i| {
+            let (r2, g2, b2) = ANSI16_RGB[i as usize];
+            d(r, r2) + d(g, g2) + d(b, b2)
+        })
+        .unwrap_or(0)
+}
+
+/// Nearest xterm 256-color index: the closer of the 6x6x6 cube entry and
+/// the grayscale ramp entry.
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let step = |v: u8| match v {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (v - 35) / 40,
+    };
+    let dist = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    let (cr, cg, cb) = (step(r), step(g), step(b));
+    let cube = (
+        LEVELS[cr as usize],
+        LEVELS[cg as usize],
+        LEVELS[cb as usize],
+    );
+    let avg = (r as u16 + g as u16 + b as u16) / 3;
+    let gray = if avg > 238 {
+        23
+    } else {
+        avg.saturating_sub(3) / 10
+    } as u8;
+    let level = 8 + 10 * gray;
+    if dist((level, level, level)) < dist(cube) {
+        232 + gray
+    } else {
+        16 + 36 * cr + 6 * cg + cb
+    }
+}
+
+/// Style spans for the highlight spans of buffer line `line`, whose text
+/// (line ending excluded) is `text` and which starts at screen column
+/// `col_offset`. Byte ranges become visual columns.
+pub fn syntax_style_spans(
+    line: usize,
+    text: &str,
+    spans: &[HighlightSpan],
+    col_offset: u16,
+) -> Vec<StyleSpan> {
+    spans
+        .iter()
+        .filter(|s| s.start < s.end && s.end <= text.len())
+        .map(|s| StyleSpan {
+            line,
+            start_col: col_offset + grapheme::visual_col(text, s.start) as u16,
+            end_col: col_offset + grapheme::visual_col(text, s.end) as u16,
+            attr: StyleAttr::Syntax(s.class),
+        })
+        .collect()
+}
+
+/// Byte ranges of the highlighted search matches in `text` (a line without
+/// its ending); empty while `hlsearch` shows nothing.
+pub fn search_matches(state: &EditorState, text: &str) -> Vec<Range<usize>> {
+    state
+        .search_highlight()
+        .map(|pattern| pattern.line_matches(text))
+        .unwrap_or_default()
+}
+
+/// Style spans for the search matches of buffer line `line` (see
+/// `syntax_style_spans`).
+pub fn search_style_spans(
+    line: usize,
+    text: &str,
+    matches: &[Range<usize>],
+    col_offset: u16,
+) -> Vec<StyleSpan> {
+    matches
+        .iter()
+        .map(|m| StyleSpan {
+            line,
+            start_col: col_offset + grapheme::visual_col(text, m.start) as u16,
+            end_col: col_offset + grapheme::visual_col(text, m.end) as u16,
+            attr: StyleAttr::Search,
+        })
+        .collect()
+}
+
+/// Underline flag of the most severe diagnostic covering byte `byte`.
+pub fn diagnostic_flags(spans: &[(Range<usize>, Severity)], byte: usize) -> CellFlags {
+    match severity_at(spans, byte) {
+        Some(Severity::Error) => CellFlags::DIAG_ERROR,
+        Some(Severity::Warning) => CellFlags::DIAG_WARNING,
+        Some(Severity::Info) => CellFlags::DIAG_INFO,
+        Some(Severity::Hint) => CellFlags::DIAG_HINT,
+        None => CellFlags::empty(),
+    }
+}
+
+/// End-of-line message of `line` when `virtual_text` is on: the text drawn
+/// one column after the line and its cell flags.
+pub fn diagnostic_virtual_text(
+    state: &EditorState,
+    buffer: BufferId,
+    line: usize,
+) -> Option<(String, CellFlags)> {
+    if !state.diagnostics.virtual_text() {
+        return None;
+    }
+    let (severity, message) = state.diagnostics.line_message(buffer, line)?;
+    let severity = match severity {
+        Severity::Error => CellFlags::DIAG_ERROR,
+        Severity::Warning => CellFlags::DIAG_WARNING,
+        Severity::Info => CellFlags::DIAG_INFO,
+        Severity::Hint => CellFlags::DIAG_HINT,
+    };
+    Some((format!("■ {message}"), CellFlags::VIRTUAL_TEXT | severity))
+}
+
+/// End-of-line text of `line`: its diagnostic message, else its `:blame`
+/// annotation when it is `shade`'s blame line.
+pub fn line_virtual_text(
+    state: &EditorState,
+    buffer: BufferId,
+    line: usize,
+    shade: &CursorShade,
+) -> Option<(String, CellFlags)> {
+    diagnostic_virtual_text(state, buffer, line).or_else(|| {
+        let blame = state
+            .git
+            .blame_line(buffer, line)
+            .filter(|_| shade.blame == Some(line))?;
+        Some((blame.annotation(), CellFlags::VIRTUAL_TEXT))
+    })
+}
+
+/// Syntax class covering byte `byte` of a line, if any.
+pub fn syntax_class_at(spans: &[HighlightSpan], byte: usize) -> Option<u16> {
+    spans
+        .iter()
+        .find(|s| s.start <= byte && byte < s.end)
+        .map(|s| s.class)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +859,136 @@ mod tests {
         assert_eq!(c.end_col, 3);
         assert_eq!(c.width(), 2);
     }
+
+    #[test]
+    fn merged_spans_take_the_highest_layer_per_byte() {
+        let span = |start, end, attr| LineSpan { start, end, attr };
+        let syntax = vec![
+            span(0, 2, StyleAttr::Syntax(0)),
+            span(3, 7, StyleAttr::Syntax(1)),
+        ];
+        let above = vec![
+            span(1, 4, StyleAttr::Search),
+            span(0, 9, StyleAttr::Overlay),
+        ];
+        let merged = merge_line_spans(&[syntax.clone(), above]);
+        assert_eq!(
+            merged,
+            [
+                span(0, 1, StyleAttr::Overlay),
+                span(1, 4, StyleAttr::Search),
+                span(4, 9, StyleAttr::Overlay),
+            ]
+        );
+        assert_eq!(line_attr_at(&merged, 3), Some(StyleAttr::Search));
+        assert_eq!(line_attr_at(&merged, 9), None);
+
+        let gaps = merge_line_spans(&[vec![span(0, 9, StyleAttr::Search)], syntax]);
+        assert_eq!(line_attr_at(&gaps, 1), Some(StyleAttr::Syntax(0)));
+        assert_eq!(line_attr_at(&gaps, 2), Some(StyleAttr::Search));
+        assert_eq!(gaps.len(), 4);
+    }
+
+    #[test]
+    fn syntax_spans_use_visual_columns() {
+        let text = "界 fn";
+        let spans = [HighlightSpan {
+            start: 4,
+            end: 6,
+            class: HighlightClass::Keyword as u16,
+        }];
+        let styled = syntax_style_spans(7, text, &spans, 4);
+        assert_eq!((styled[0].start_col, styled[0].end_col), (7, 9));
+        assert_eq!(styled[0].attr, StyleAttr::Syntax(0));
+        assert_eq!(syntax_class_at(&spans, 5), Some(0));
+        assert_eq!(
+            styled_cluster("f", CellFlags::REVERSE, Some(0)),
+            "\x1b[7;35mf\x1b[0m"
+        );
+        assert_eq!(styled_cluster("+", CellFlags::empty(), Some(8)), "+");
+    }
+
+    #[test]
+    fn search_spans_replace_syntax_color() {
+        let text = "界 foo fo";
+        let styled = search_style_spans(3, text, &[4..7, 8..10], 2);
+        assert_eq!((styled[0].start_col, styled[0].end_col), (5, 8));
+        assert_eq!(styled[0].attr, StyleAttr::Search);
+        assert_eq!((styled[1].start_col, styled[1].end_col), (9, 11));
+        assert_eq!(
+            styled_cluster("f", CellFlags::SEARCH, Some(0)),
+            "\x1b[30;43mf\x1b[0m"
+        );
+        assert_eq!(
+            styled_cluster("f", CellFlags::SEARCH | CellFlags::REVERSE, None),
+            "\x1b[7;30;43mf\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn cursor_shade_goes_under_syntax_and_search() {
+        let shade = CursorShade {
+            line: Some(2),
+            column: Some((5, 7)),
+            rulers: vec![9],
+            text_start: 4,
+            blame: None,
+        };
+        assert_eq!(shade.flags(2, 0, 1), CellFlags::CURSORLINE);
+        assert_eq!(shade.flags(1, 4, 2), CellFlags::CURSORCOLUMN);
+        assert_eq!(shade.flags(1, 7, 1), CellFlags::empty());
+        assert_eq!(shade.flags(1, 8, 2), CellFlags::COLORCOLUMN);
+        assert_eq!((shade.fill_end(2), shade.fill_end(1)), (u16::MAX, 10));
+        assert_eq!(
+            styled_cluster("f", CellFlags::CURSORLINE, Some(0)),
+            "\x1b[48;5;236;35mf\x1b[0m"
+        );
+        assert_eq!(
+            styled_cluster(" ", CellFlags::CURSORCOLUMN | CellFlags::SEARCH, None),
+            "\x1b[48;5;236;30;43m \x1b[0m"
+        );
+    }
+
+    #[test]
+    fn palettes_resolve_groups_for_the_color_depth() {
+        let theme = Theme::from_toml(
+            "night",
+            "[Normal]\nfg = \"#d0d0d0\"\nbg = 235\n\n[Keyword]\nfg = \"#ff8700\"\nbold = true\n\n[StatusLine]\nfg = \"black\"\nbg = \"brightblue\"\n",
+        )
+        .unwrap();
+        let true_color = Palette::resolve(&theme, ColorDepth::TrueColor);
+        assert_eq!(
+            true_color.base().unwrap(),
+            "\x1b[0;38;2;208;208;208;48;5;235m"
+        );
+        assert_eq!(true_color.syntax_sgr(0), Some("1;38;2;255;135;0"));
+        let fallback = Palette::resolve(&theme, ColorDepth::Ansi256);
+        assert_eq!(fallback.base().unwrap(), "\x1b[0;38;5;252;48;5;235m");
+        assert_eq!(fallback.syntax_sgr(0), Some("1;38;5;208"));
+        assert_eq!(fallback.syntax_sgr(HighlightClass::Comment as u16), None);
+        assert_eq!(
+            fallback.styled("x", CellFlags::STATUS | CellFlags::REVERSE, None),
+            "\x1b[30;104mx\x1b[0m"
+        );
+        assert_eq!(fallback.status("ab", 4), "\x1b[30;104mab  \x1b[0m");
+        assert_eq!(fallback.attr_sgr(StyleAttr::Selection), None);
+
+        let builtin = Palette::builtin();
+        assert_eq!(builtin.base(), None);
+        assert_eq!(builtin.attr_sgr(StyleAttr::Selection), Some("7"));
+        assert_eq!(builtin.attr_sgr(StyleAttr::Search), Some("30;43"));
+        assert_eq!(builtin.status("ab", 4), "ab");
+        assert_eq!(rgb_to_ansi256(0x80, 0x80, 0x80), 244);
+
+        let ansi16 = Palette::resolve(&theme, ColorDepth::Ansi16);
+        assert_eq!(ansi16.base().unwrap(), "\x1b[0;37;40m");
+        assert_eq!(ansi16.syntax_sgr(0), Some("1;33"));
+        assert_eq!(
+            group_sgr(
+                Theme::default().group("DiagnosticUnderlineError").unwrap(),
+                ColorDepth::Ansi16
+            ),
+            Some("4".to_string())
+        );
+    }
 }
@@ -0,0 +1,205 @@
+//! Tab page line / buffer bar on the top row.
+//!
+//! While more than one tab page exists the row lists them (Vim's default
+//! `'showtabline'=1`); otherwise, while more than one buffer is open, it
+//! lists the buffers in `:ls` order. The command-line window's scratch
+//! buffer is never listed.
+//!
+//! Tab labels read ` {n} {name}{+} ` and buffer labels ` {id} {name}{+} `
+//! (`id` is the buffer number): `name` is the base file name of the tab's
+//! focused buffer or of the buffer itself (`[No Name]` when unnamed) and `+`
+//! marks a modified buffer. The current label is drawn normally and the
+//! rest of the row in reverse video. Each label records the screen columns
+//! it covers, so a click can be mapped back to the tab or buffer it names.
+
+use crate::{CellFlags, Frame};
+use core_model::TabPage;
+use core_state::{BufferEntry, BufferId, CMDLINE_WINDOW_NAME, EditorState};
+use core_text::grapheme;
+
+/// What a label stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TablineTarget {
+    /// Tab page index (0-based).
+    Tab(usize),
+    Buffer(BufferId),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TablineLabel {
+    /// Screen columns the label covers.
+    pub columns: std::ops::Range<u16>,
+    pub target: TablineTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabLine {
+    pub text: String,
+    /// Byte range of the current label within `text`.
+    pub current: std::ops::Range<usize>,
+    pub labels: Vec<TablineLabel>,
+}
+
+impl TabLine {
+    /// Tab or buffer whose label covers screen column `col`.
+    pub fn target_at(&self, col: u16) -> Option<TablineTarget> {
+        self.labels
+            .iter()
+            .find(|l| l.columns.contains(&col))
+            .map(|l| l.target)
+    }
+
+    fn push_label(&mut self, target: TablineTarget, current: bool, entry: Option<&BufferEntry>) {
+        let name = entry
+            .and_then(|e| e.meta.path.as_deref())
+            .and_then(|p| p.file_name())
+            .map_or_else(|| "[No Name]".into(), |n| n.to_string_lossy());
+        let modified = if entry.is_some_and(|e| e.meta.dirty) {
+            "+"
+        } else {
+            ""
+        };
+        let number = match target {
+            TablineTarget::Tab(i) => (i + 1) as u64,
+            TablineTarget::Buffer(id) => id.0,
+        };
+        let start = self.text.len();
+        self.text.push_str(&format!(" {number} {name}{modified} "));
+        if current {
+            self.current = start..self.text.len();
+        }
+        let col = self.labels.last().map_or(0, |l| l.columns.end);
+        let width = cells(&self.text[start..]);
+        self.labels.push(TablineLabel {
+            columns: col..col.saturating_add(width),
+            target,
+        });
+    }
+}
+
+/// Columns `text` takes when painted (zero-width clusters get a cell).
+fn cells(text: &str) -> u16 {
+    let mut byte = 0;
+    let mut width = 0u16;
+    while byte < text.len() {
+        let next = grapheme::next_boundary(text, byte);
+        width = width.saturating_add(grapheme::cluster_width(&text[byte..next]).max(1) as u16);
+        byte = next;
+    }
+    width
+}
+
+/// Buffers the bar lists, in `:ls` order.
+fn listed_buffers(state: &EditorState) -> impl Iterator<Item = &BufferEntry> {
+    state
+        .buffers
+        .iter()
+        .filter(|e| e.buffer.name != CMDLINE_WINDOW_NAME)
+}
+
+/// Whether the top row is taken: more than one tab page or listed buffer.
+pub fn tabline_visible(state: &EditorState, tabs: &[TabPage]) -> bool {
+    tabs.len() > 1 || listed_buffers(state).nth(1).is_some()
+}
+
+/// Labels for the tab pages, or for the buffers while only one tab page
+/// exists.
+pub fn build_tabline(state: &EditorState, tabs: &[TabPage], current: usize) -> TabLine {
+    let mut tabline = TabLine {
+        text: String::new(),
+        current: 0..0,
+        labels: Vec::new(),
+    };
+    if tabs.len() > 1 {
+        for (i, tab) in tabs.iter().enumerate() {
+            let entry = state.buffers.get(tab.active_view().buffer_id);
+            tabline.push_label(TablineTarget::Tab(i), i == current, entry);
+        }
+    } else {
+        for entry in listed_buffers(state) {
+            let id = entry.id();
+            tabline.push_label(TablineTarget::Buffer(id), id == state.active, Some(entry));
+        }
+    }
+    tabline
+}
+
+/// Paint `tabline` across row `y` of `frame`, truncating at the frame width.
+pub fn paint_tabline(tabline: &TabLine, frame: &mut Frame, y: u16) {
+    let text = &tabline.text;
+    let mut byte = 0usize;
+    let mut x: u16 = 0;
+    while byte < text.len() && x < frame.width {
+        let next = grapheme::next_boundary(text, byte);
+        let cluster = &text[byte..next];
+        let width = grapheme::cluster_width(cluster).max(1) as u16;
+        let flags = if tabline.current.contains(&byte) {
+            CellFlags::empty()
+        } else {
+            CellFlags::REVERSE
+        };
+        frame.set_cluster(x, y, cluster, width, flags);
+        x = x.saturating_add(width);
+        byte = next;
+    }
+    for fill in x..frame.width {
+        frame.set_cluster(fill, y, " ", 1, CellFlags::REVERSE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_model::EditorModel;
+    use core_text::Buffer;
+
+    #[test]
+    fn labels_mark_current_and_modified_tabs() {
+        let st = EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        let mut model = EditorModel::new(st);
+        model.state_mut().set_file_name(Some("/tmp/one.txt".into()));
+        model.state_mut().set_dirty(true);
+        let other = model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("o", "b\n").unwrap(), None);
+        model.open_tab(other);
+        let tabline = build_tabline(model.state(), model.tabs(), model.current_tab());
+        assert_eq!(tabline.text, " 1 one.txt+  2 [No Name] ");
+        assert_eq!(&tabline.text[tabline.current.clone()], " 2 [No Name] ");
+
+        let mut frame = Frame::new(30, 1);
+        paint_tabline(&tabline, &mut frame, 0);
+        assert!(frame.cells[0].flags.contains(CellFlags::REVERSE));
+        assert!(frame.cells[14].flags.is_empty());
+        assert!(frame.cells[29].flags.contains(CellFlags::REVERSE));
+    }
+
+    #[test]
+    fn single_tab_lists_buffers_with_click_targets() {
+        let mut st = EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        let first = st.active;
+        assert!(!tabline_visible(&st, &[]));
+        // The command-line window's scratch buffer is not listed.
+        st.buffers
+            .open(Buffer::from_str(CMDLINE_WINDOW_NAME, "\n").unwrap(), None);
+        assert!(!tabline_visible(&st, &[]));
+        let second = st.buffers.open(
+            Buffer::from_str("界", "b\n").unwrap(),
+            Some("/tmp/界.rs".into()),
+        );
+        st.switch_buffer(second);
+        st.set_dirty(true);
+        assert!(tabline_visible(&st, &[]));
+
+        let tabline = build_tabline(&st, &[], 0);
+        assert_eq!(tabline.text, " 1 [No Name]  3 界.rs+ ");
+        assert_eq!(&tabline.text[tabline.current.clone()], " 3 界.rs+ ");
+        assert_eq!(tabline.target_at(0), Some(TablineTarget::Buffer(first)));
+        assert_eq!(tabline.target_at(12), Some(TablineTarget::Buffer(first)));
+        assert_eq!(tabline.target_at(13), Some(TablineTarget::Buffer(second)));
+        // The wide name takes two cells.
+        assert_eq!(tabline.labels[1].columns, 13..23);
+        assert_eq!(tabline.target_at(23), None);
+    }
+}
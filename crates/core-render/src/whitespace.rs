@@ -0,0 +1,75 @@
+//! `'list'` mode: glyphs painted over invisible whitespace.
+//!
+//! The glyphs come from `'listchars'` (`core_config::listchars`) and take
+//! the cell width of the whitespace they cover, so cursor columns, wrapping
+//! and cell parity between the frame and writer paths are unchanged. Cells
+//! carrying a glyph are flagged `CellFlags::WHITESPACE`.
+
+use core_config::listchars::ListChars;
+use core_state::EditorState;
+
+/// Glyphs in effect; `None` with `'nolist'`. A `'listchars'` value that
+/// does not parse (only possible through the config file) falls back to
+/// the default.
+pub fn listchars(state: &EditorState) -> Option<ListChars> {
+    if !state.options.get_bool("list") {
+        return None;
+    }
+    ListChars::parse(state.options.get_string("listchars")).or_else(|| ListChars::parse("eol:$"))
+}
+
+/// Glyph lookup for one line of text (without its line ending).
+#[derive(Debug, Clone, Copy)]
+pub struct LineGlyphs<'a> {
+    lcs: &'a ListChars,
+    /// Byte offset where the trailing spaces start.
+    trail_from: usize,
+    len: usize,
+}
+
+impl<'a> LineGlyphs<'a> {
+    pub fn new(lcs: &'a ListChars, text: &str) -> Self {
+        Self {
+            lcs,
+            trail_from: text.trim_end_matches(' ').len(),
+            len: text.len(),
+        }
+    }
+
+    /// What to paint instead of `cluster` (at `byte`, `width` cells), if
+    /// it is whitespace `'listchars'` covers.
+    pub fn cluster(&self, byte: usize, cluster: &str, width: u16) -> Option<String> {
+        match cluster {
+            "\t" => self.lcs.tab_glyphs(width as usize),
+            " " if byte >= self.trail_from => self.lcs.trail.map(String::from),
+            "\u{a0}" | "\u{202f}" => self.lcs.nbsp.map(String::from),
+            _ => None,
+        }
+    }
+
+    /// The end-of-line glyph, painted after a row ending at byte `end`
+    /// when that is the end of the line.
+    pub fn eol(&self, end: usize) -> Option<char> {
+        self.lcs.eol.filter(|_| end == self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_cover_tabs_trailing_spaces_and_eol() {
+        let lcs = ListChars::parse("tab:>-,trail:~,eol:$,nbsp:+").unwrap();
+        let text = "a\tb c\u{a0}  ";
+        let glyphs = LineGlyphs::new(&lcs, text);
+        assert_eq!(glyphs.cluster(1, "\t", 1).as_deref(), Some(">"));
+        // Inner spaces stay; only the trailing run is marked.
+        assert_eq!(glyphs.cluster(3, " ", 1), None);
+        assert_eq!(glyphs.cluster(7, " ", 1).as_deref(), Some("~"));
+        assert_eq!(glyphs.cluster(5, "\u{a0}", 1).as_deref(), Some("+"));
+        assert_eq!(glyphs.cluster(0, "a", 1), None);
+        assert_eq!(glyphs.eol(text.len()), Some('$'));
+        assert_eq!(glyphs.eol(3), None);
+    }
+}
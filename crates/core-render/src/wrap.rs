@@ -0,0 +1,411 @@
+//! Screen rows of a view: one per buffer line, or as many as a line wraps
+//! into under `'wrap'` (see `core_text::wrap`).
+//!
+//! Every text path lays its rows out through `screen_rows`, so full frames,
+//! split regions and partial repaints agree on where a line lands. A line's
+//! first row carries its gutter label; continuation rows get a blank gutter
+//! and the `'showbreak'` marker. The last line may be cut off at the bottom.
+//! A closed fold (`core_model::fold`) takes a single row showing its summary
+//! (`fold_line`) in place of all its lines. In diff mode (`core_state::diff`)
+//! filler rows (`Fillers`) stand above a line for the lines only the other
+//! buffer has; diff mode always composes whole frames, so only the full
+//! paths and the cursor lay them out.
+//!
+//! The partial caches stay keyed by logical line and additionally record the
+//! rows of the last frame (`PartialCache::rows`): a partial frame whose rows
+//! no longer match (an edit changed how many rows a line wraps into, or a
+//! fold opened or closed, so every row below moved) repaints fully.
+//! Scroll-region shifts move whole rows, so they only apply while every
+//! visible line fits one row and nothing is folded.
+
+use crate::gutter::Gutter;
+use core_model::View;
+use core_model::fold::Folds;
+use core_state::{BufferId, DiffState, EditorState};
+use core_text::wrap::{WrapRow, WrapWidth};
+use core_text::{Buffer, Position, grapheme};
+use std::ops::RangeInclusive;
+
+/// One text row of a view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenRow {
+    /// Buffer line shown on the row.
+    pub line: usize,
+    /// Part of the line on this row.
+    pub row: WrapRow,
+    /// First row of the line (the one with the gutter label).
+    pub first: bool,
+    /// Lines of the closed fold summarised on this row; `line` is its first.
+    pub fold: Option<RangeInclusive<usize>>,
+    /// Diff filler row above `line` (which is the line count below the
+    /// last line).
+    pub filler: bool,
+}
+
+/// Diff filler rows of a view: how many stand above each line, and at most
+/// `top` of them above the first line shown.
+#[derive(Debug, Clone, Copy)]
+pub struct Fillers<'a> {
+    diff: Option<(&'a DiffState, BufferId)>,
+    pub top: usize,
+}
+
+impl<'a> Fillers<'a> {
+    pub fn none() -> Self {
+        Self { diff: None, top: 0 }
+    }
+
+    /// Every filler row of `view`'s buffer when it is being compared.
+    pub fn for_view(state: &'a EditorState, view: &View) -> Self {
+        Self {
+            diff: state
+                .diff
+                .includes(view.buffer_id)
+                .then_some((&state.diff, view.buffer_id)),
+            top: usize::MAX,
+        }
+    }
+
+    /// `for_view` for a view next to `active`: when `active` shows the
+    /// other compared buffer, only as many rows above the first line as
+    /// keep the two level (the rest scrolled out of sight).
+    pub fn beside(state: &'a EditorState, view: &View, active: &View) -> Self {
+        let mut fillers = Self::for_view(state, view);
+        let diff = &state.diff;
+        if diff.other(active.buffer_id) == Some(view.buffer_id) {
+            let top = diff.top_row(active.buffer_id, active.viewport_first_line);
+            let row = diff.row_of(view.buffer_id, view.viewport_first_line);
+            fillers.top = row.saturating_sub(top);
+        }
+        fillers
+    }
+
+    pub fn above(&self, line: usize) -> usize {
+        self.diff
+            .map_or(0, |(diff, buffer)| diff.fillers_above(buffer, line))
+    }
+}
+
+/// Wrapping of `view` painted `w` columns wide; `None` with `'nowrap'`, for
+/// hex views and when the gutter leaves no text column.
+pub fn view_wrap(state: &EditorState, view: &View, w: u16) -> Option<WrapWidth> {
+    if !state.options.get_bool("wrap") {
+        return None;
+    }
+    let entry = state.buffers.get(view.buffer_id)?;
+    if entry.meta.hex_view && entry.meta.binary.is_some() {
+        return None;
+    }
+    let text = w.saturating_sub(Gutter::for_view(state, view).width);
+    (text > 0).then(|| WrapWidth::new(text, state.options.get_string("showbreak")))
+}
+
+/// Rows of `text` (one line without its ending); a single row holding the
+/// whole line without wrapping.
+pub fn line_rows(wrap: Option<WrapWidth>, text: &str) -> Vec<WrapRow> {
+    match wrap {
+        Some(wrap) => wrap.rows(text),
+        None => vec![WrapRow {
+            bytes: 0..text.len(),
+            indent: 0,
+        }],
+    }
+}
+
+/// The first `height` rows of a view of `buf` starting at line `first`
+/// (the start of the closed fold around it, if any). Rows past the end of
+/// the buffer are omitted.
+pub fn screen_rows(
+    buf: &Buffer,
+    wrap: Option<WrapWidth>,
+    folds: &Folds,
+    first: usize,
+    height: usize,
+) -> Vec<ScreenRow> {
+    screen_rows_filled(buf, wrap, folds, Fillers::none(), first, height)
+}
+
+/// `screen_rows` with the diff filler rows of `fillers`.
+pub fn screen_rows_filled(
+    buf: &Buffer,
+    wrap: Option<WrapWidth>,
+    folds: &Folds,
+    fillers: Fillers,
+    first: usize,
+    height: usize,
+) -> Vec<ScreenRow> {
+    let mut rows = Vec::with_capacity(height);
+    let mut line = folds.visible_start(first);
+    let mut top = fillers.top;
+    while rows.len() < height && line <= buf.line_count() {
+        let count = fillers.above(line).min(top);
+        top = usize::MAX;
+        for _ in 0..count.min(height - rows.len()) {
+            rows.push(ScreenRow {
+                line,
+                row: WrapRow {
+                    bytes: 0..0,
+                    indent: 0,
+                },
+                first: false,
+                fold: None,
+                filler: true,
+            });
+        }
+        if line == buf.line_count() {
+            break;
+        }
+        if let Some(fold) = folds.closed_at(line) {
+            let end = *fold.end();
+            rows.push(ScreenRow {
+                line,
+                row: WrapRow {
+                    bytes: 0..0,
+                    indent: 0,
+                },
+                first: true,
+                fold: Some(fold),
+                filler: false,
+            });
+            line = end + 1;
+            continue;
+        }
+        let raw = buf.line(line).unwrap_or_default();
+        let text = raw.trim_end_matches(['\n', '\r']);
+        for (i, row) in line_rows(wrap, text).into_iter().enumerate() {
+            if rows.len() == height {
+                break;
+            }
+            rows.push(ScreenRow {
+                line,
+                row,
+                first: i == 0,
+                fold: None,
+                filler: false,
+            });
+        }
+        line += 1;
+    }
+    rows
+}
+
+/// Row (from the top of the view) and text column of `view`'s cursor when
+/// it is among the first `height` rows. A cursor inside a closed fold sits
+/// at the start of the fold's row.
+pub fn cursor_cell(
+    buf: &Buffer,
+    wrap: Option<WrapWidth>,
+    view: &View,
+    height: usize,
+) -> Option<(u16, u16)> {
+    cursor_cell_filled(buf, wrap, view, Fillers::none(), height)
+}
+
+/// `cursor_cell` below the diff filler rows of `fillers`.
+pub fn cursor_cell_filled(
+    buf: &Buffer,
+    wrap: Option<WrapWidth>,
+    view: &View,
+    fillers: Fillers,
+    height: usize,
+) -> Option<(u16, u16)> {
+    let cursor = view.cursor;
+    if cursor.line >= buf.line_count() {
+        return None;
+    }
+    let rows = screen_rows_filled(
+        buf,
+        wrap,
+        &view.folds,
+        fillers,
+        view.viewport_first_line,
+        height,
+    );
+    let line = view.folds.visible_start(cursor.line);
+    let first_row = rows.iter().position(|r| r.line == line && !r.filler)?;
+    if rows[first_row].fold.is_some() {
+        return Some((first_row as u16, 0));
+    }
+    let raw = buf.line(line).unwrap_or_default();
+    let text = raw.trim_end_matches(['\n', '\r']);
+    let (row, col) = match wrap {
+        Some(wrap) => wrap.locate(text, cursor.byte),
+        None => {
+            let byte = cursor.byte.min(text.len());
+            (0, grapheme::visual_col(text, byte) as u16)
+        }
+    };
+    let row = first_row + row;
+    (row < rows.len()).then_some((row as u16, col))
+}
+
+/// Buffer position shown at text cell (`row`, `col`) of `view`, the inverse
+/// of `cursor_cell`. A row below the last line lands on the last row, a
+/// fold row on the fold's first line and a column past the end of a row on
+/// its last cluster; `None` for an empty view.
+pub fn screen_position(
+    buf: &Buffer,
+    wrap: Option<WrapWidth>,
+    view: &View,
+    height: usize,
+    row: u16,
+    col: u16,
+) -> Option<Position> {
+    screen_position_filled(buf, wrap, view, Fillers::none(), height, row, col)
+}
+
+/// `screen_position` over the diff filler rows of `fillers`; a filler row
+/// lands on the start of the line below it.
+pub fn screen_position_filled(
+    buf: &Buffer,
+    wrap: Option<WrapWidth>,
+    view: &View,
+    fillers: Fillers,
+    height: usize,
+    row: u16,
+    col: u16,
+) -> Option<Position> {
+    let rows = screen_rows_filled(
+        buf,
+        wrap,
+        &view.folds,
+        fillers,
+        view.viewport_first_line,
+        height,
+    );
+    let screen = rows.get(usize::from(row)).or(rows.last())?;
+    if screen.filler {
+        let line = screen.line.min(buf.line_count().saturating_sub(1));
+        return Some(Position::new(line, 0));
+    }
+    if screen.fold.is_some() {
+        return Some(Position::new(screen.line, 0));
+    }
+    let raw = buf.line(screen.line).unwrap_or_default();
+    let text = raw.trim_end_matches(['\n', '\r']);
+    let bytes = &screen.row.bytes;
+    let mut at = bytes.start;
+    let mut left = screen.row.indent;
+    let mut line_col = core_text::wrap::line_col(text, at);
+    while at < bytes.end {
+        let next = grapheme::next_boundary(text, at);
+        let width = grapheme::cell_width(&text[at..next], line_col).max(1);
+        let right = left.saturating_add(width as u16);
+        if col < right || next >= bytes.end {
+            break;
+        }
+        left = right;
+        line_col += width;
+        at = next;
+    }
+    Some(Position::new(screen.line, at))
+}
+
+/// Summary text of a closed fold over `lines` lines starting with `text`,
+/// as Vim draws it (`+--  5 lines: text`). Painters fill the rest of the
+/// row with `-`.
+pub fn fold_line(text: &str, lines: usize) -> String {
+    format!(
+        "+--{lines:>3} lines: {}",
+        text.trim_start().replace('\t', " ")
+    )
+}
+
+/// Buffer line of each row.
+pub fn row_lines(rows: &[ScreenRow]) -> Vec<usize> {
+    rows.iter().map(|r| r.line).collect()
+}
+
+/// True when every row shows a whole line of its own (nothing wraps or is
+/// folded), so row `i` shows line `first + i`.
+pub fn unwrapped(rows: &[ScreenRow]) -> bool {
+    rows.iter().all(|r| r.first && r.fold.is_none())
+}
+
+/// Whether `rows` can be repainted in place over a frame laid out as
+/// `cached`: nothing wraps or folds on either side (every line keeps row
+/// `line - first`), or each row still shows the same line (or fold).
+pub fn rows_match(rows: &[ScreenRow], cached: &[ScreenRow]) -> bool {
+    let key = |r: &ScreenRow| (r.line, r.fold.clone(), r.filler);
+    (unwrapped(rows) && unwrapped(cached)) || rows.iter().map(key).eq(cached.iter().map(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_model::ViewId;
+
+    #[test]
+    fn screen_rows_follow_wrap_option() {
+        let mut state = EditorState::new(Buffer::from_str("t", "abcdefgh\nxy\nz").unwrap());
+        let mut view = View::new(ViewId(0), state.active, Position::new(1, 1), 0);
+        let wrap = view_wrap(&state, &view, 3);
+        let folds = Folds::default();
+        let rows = screen_rows(state.active_buffer(), wrap, &folds, 0, 4);
+        assert_eq!(row_lines(&rows), [0, 0, 0, 1]);
+        assert_eq!(rows[1].row.bytes, 3..6);
+        assert!(!rows[1].first && !unwrapped(&rows));
+        assert_eq!(
+            cursor_cell(state.active_buffer(), wrap, &view, 4),
+            Some((3, 1))
+        );
+        assert_eq!(cursor_cell(state.active_buffer(), wrap, &view, 3), None);
+        view.cursor = Position::new(0, 7);
+        assert_eq!(
+            cursor_cell(state.active_buffer(), wrap, &view, 4),
+            Some((2, 1))
+        );
+        // Clicks map back: past a row's end onto its last cluster, below
+        // the text onto the last row.
+        let at = |row, col| screen_position(state.active_buffer(), wrap, &view, 4, row, col);
+        assert_eq!(at(2, 1), Some(Position::new(0, 7)));
+        assert_eq!(at(1, 2), Some(Position::new(0, 5)));
+        assert_eq!(at(0, 9), Some(Position::new(0, 2)));
+        assert_eq!(at(7, 0), Some(Position::new(1, 0)));
+
+        let wrapped = rows;
+        state.options.apply_set("nowrap").unwrap();
+        let wrap = view_wrap(&state, &view, 3);
+        assert_eq!(wrap, None);
+        let rows = screen_rows(state.active_buffer(), wrap, &folds, 0, 4);
+        assert_eq!(row_lines(&rows), [0, 1, 2]);
+        assert!(unwrapped(&rows));
+        // An unwrapped frame matches any unwrapped layout; a wrapped one
+        // only its own.
+        assert!(rows_match(&rows, &rows[..2]));
+        assert!(!rows_match(&rows, &wrapped));
+        assert_eq!(
+            cursor_cell(state.active_buffer(), wrap, &view, 4),
+            Some((0, 7))
+        );
+    }
+
+    #[test]
+    fn closed_fold_takes_one_row() {
+        let state = EditorState::new(Buffer::from_str("t", "a\n\tb\nc\nd\ne").unwrap());
+        let mut view = View::new(ViewId(0), state.active, Position::new(2, 0), 2);
+        view.folds.create(1, 3);
+        let buf = state.active_buffer();
+        // The first line is inside the fold: the view starts at its row.
+        let rows = screen_rows(buf, None, &view.folds, view.viewport_first_line, 3);
+        assert_eq!(row_lines(&rows), [1, 4]);
+        assert_eq!(rows[0].fold, Some(1..=3));
+        assert!(!unwrapped(&rows));
+        assert_eq!(cursor_cell(buf, None, &view, 3), Some((0, 0)));
+        assert_eq!(
+            screen_position(buf, None, &view, 3, 0, 5),
+            Some(Position::new(1, 0))
+        );
+        assert_eq!(
+            screen_position(buf, None, &view, 3, 1, 0),
+            Some(Position::new(4, 0))
+        );
+        assert_eq!(fold_line("\tb\tc", 3), "+--  3 lines: b c");
+
+        let open = screen_rows(buf, None, &Folds::default(), 1, 2);
+        assert!(!rows_match(&rows, &open), "a fold changes the layout");
+        view.folds.open(2).unwrap();
+        let reopened = screen_rows(buf, None, &view.folds, 1, 2);
+        assert!(rows_match(&reopened, &open));
+    }
+}
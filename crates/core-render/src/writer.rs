@@ -18,19 +18,24 @@
 //! Public API kept intentionally tiny until partial path activates.
 
 use anyhow::Result;
+use core_terminal::CursorShape;
 use crossterm::{
-    cursor::MoveTo,
+    cursor::{Hide, MoveTo, Show},
     queue,
     style::Print,
     terminal::{Clear, ClearType},
 };
 use std::io::{Write, stdout};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Command {
     MoveTo(u16, u16),
     ClearLine(u16, u16), // (x,y) start; clears full line before selective repaint (Step 7)
     Print(String),
+    /// Hardware cursor shape (DECSCUSR).
+    SetCursorStyle(CursorShape),
+    HideCursor,
+    ShowCursor,
 }
 
 #[derive(Default)]
@@ -70,6 +75,15 @@ impl Writer {
                 Command::Print(s) => {
                     queue!(out, Print(s))?;
                 }
+                Command::SetCursorStyle(shape) => {
+                    queue!(out, shape.style())?;
+                }
+                Command::HideCursor => {
+                    queue!(out, Hide)?;
+                }
+                Command::ShowCursor => {
+                    queue!(out, Show)?;
+                }
             }
         }
         out.flush()?;
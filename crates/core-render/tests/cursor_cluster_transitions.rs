@@ -14,9 +14,11 @@ fn setup(
     state.last_text_height = last_text_height; // pretend viewport text rows
     let view = core_model::View {
         id: core_model::ViewId(0),
-        buffer_index: 0,
+        buffer_id: core_state::BufferId(1),
         cursor: Position::new(0, 0),
         viewport_first_line: 0,
+        folds: Default::default(),
+        location_list: Default::default(),
     };
     (
         state,
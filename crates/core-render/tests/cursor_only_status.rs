@@ -11,9 +11,11 @@ fn setup(buffer_content: &str) -> (EditorState, View, RenderEngine, u16, u16) {
     state.last_text_height = 5; // pretend 6 rows incl status
     let view = View {
         id: core_model::ViewId(0),
-        buffer_index: 0,
+        buffer_id: core_state::BufferId(1),
         cursor: Position::new(0, 0),
         viewport_first_line: 0,
+        folds: Default::default(),
+        location_list: Default::default(),
     };
     (state, view, RenderEngine::new(), 40, 6)
 }
@@ -1,6 +1,6 @@
 use core_model::EditorModel;
 use core_render::render_engine::{RenderEngine, build_content_frame, build_status_line};
-use core_state::{EditorState, METRICS_OVERLAY_DEFAULT_LINES};
+use core_state::EditorState;
 use core_text::Buffer;
 
 fn mk_model(text: &str) -> EditorModel {
@@ -23,9 +23,7 @@ fn overlay_reduces_text_height_full() {
         .unwrap();
     let baseline_frame = build_content_frame(model.state(), &view, W, H);
     // Enable overlay
-    model
-        .state_mut()
-        .toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES);
+    model.state_mut().toggle_metrics_overlay();
     let status_line2 = build_status_line(model.state(), &view);
     eng.render_full(model.state(), &view, &layout, W, H, &status_line2)
         .unwrap();
@@ -41,9 +39,7 @@ fn overlay_reduces_text_height_full() {
 #[test]
 fn overlay_lines_contain_tokens() {
     let mut model = mk_model("x\n");
-    model
-        .state_mut()
-        .toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES);
+    model.state_mut().toggle_metrics_overlay();
     let mut eng = RenderEngine::new();
     let view = model.active_view().clone();
     let layout = core_model::Layout::single(W, H);
@@ -63,9 +59,7 @@ fn overlay_lines_contain_tokens() {
 #[test]
 fn status_skip_still_increments_with_overlay() {
     let mut model = mk_model("abc\n");
-    model
-        .state_mut()
-        .toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES);
+    model.state_mut().toggle_metrics_overlay();
     let mut eng = RenderEngine::new();
     let view = model.active_view().clone();
     let layout = core_model::Layout::single(W, H);
@@ -92,17 +86,13 @@ fn overlay_toggle_off_restores_behavior() {
     let view = model.active_view().clone();
     let layout = core_model::Layout::single(W, H);
     // Enable overlay, render
-    model
-        .state_mut()
-        .toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES);
+    model.state_mut().toggle_metrics_overlay();
     let status_line = build_status_line(model.state(), &view);
     eng.render_full(model.state(), &view, &layout, W, H, &status_line)
         .unwrap();
     let snap_with = eng.metrics_snapshot();
     // Disable overlay, render again
-    model
-        .state_mut()
-        .toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES); // toggles off
+    model.state_mut().toggle_metrics_overlay(); // toggles off
     let status_line2 = build_status_line(model.state(), &view);
     eng.render_full(model.state(), &view, &layout, W, H, &status_line2)
         .unwrap();
@@ -1,7 +1,7 @@
 use core_model::{EditorModel, Layout};
 use core_render::overlay::overlay_line_count;
 use core_render::render_engine::{RenderEngine, build_status_line_with_ephemeral};
-use core_state::{EditorState, METRICS_OVERLAY_DEFAULT_LINES};
+use core_state::EditorState;
 use core_text::Buffer;
 
 // Helper to fabricate a terminal size (w,h) and get overlay line count.
@@ -21,7 +21,7 @@ fn metrics_overlay_reduces_effective_text_height_scrolls() {
     let mut model = EditorModel::new(state);
     // Enable overlay.
     let st = model.state_mut();
-    st.toggle_metrics_overlay(METRICS_OVERLAY_DEFAULT_LINES);
+    st.toggle_metrics_overlay();
 
     // Simulate a terminal  (width arbitrary 80, height small) with overlay lines.
     let w = 80u16;
@@ -43,7 +43,7 @@ fn metrics_overlay_reduces_effective_text_height_scrolls() {
 
     // Force auto_scroll with effective height (mirrors updated main loop logic).
     let (st_mut, view_mut) = model.split_state_and_active_view();
-    let changed = view_mut.auto_scroll(st_mut, effective_text_height);
+    let changed = view_mut.auto_scroll(st_mut, effective_text_height, w);
     assert!(
         !changed,
         "Initial positioning within viewport should not scroll"
@@ -55,7 +55,7 @@ fn metrics_overlay_reduces_effective_text_height_scrolls() {
         view.cursor.line += 1; // crosses threshold (would require scroll if overlay consumed rows)
     }
     let (st_mut, view_mut) = model.split_state_and_active_view();
-    let changed2 = view_mut.auto_scroll(st_mut, effective_text_height);
+    let changed2 = view_mut.auto_scroll(st_mut, effective_text_height, w);
     assert!(
         changed2,
         "Crossing boundary above overlay should trigger scroll with overlay-aware height"
@@ -0,0 +1,188 @@
+//! Render snapshot tests: engines flush into a `CaptureWriter`, and the
+//! screen a partial or scroll-shift path leaves behind must match the
+//! screen a fresh full render of the same state paints.
+
+use core_model::{EditorModel, Layout};
+use core_render::capture::{CaptureWriter, ScreenGrid};
+use core_render::dirty::DirtyLinesTracker;
+use core_render::render_engine::{RenderEngine, build_status_line};
+use core_state::EditorState;
+use core_text::{Buffer, Position};
+
+const W: u16 = 24;
+const H: u16 = 6; // 5 text rows + status
+
+fn mk(text: &str) -> EditorModel {
+    EditorModel::new(EditorState::new(Buffer::from_str("test", text).unwrap()))
+}
+
+fn capturing_engine() -> RenderEngine {
+    let mut eng = RenderEngine::new();
+    eng.capture_to(CaptureWriter::new(W, H));
+    eng
+}
+
+fn render_full(eng: &mut RenderEngine, model: &EditorModel) {
+    let view = model.active_view().clone();
+    let status = build_status_line(model.state(), &view);
+    eng.render_full(model.state(), &view, &Layout::single(W, H), W, H, &status)
+        .unwrap();
+}
+
+/// What a cold engine paints for `model` in one full frame.
+fn full_grid(model: &EditorModel) -> ScreenGrid {
+    let mut eng = capturing_engine();
+    render_full(&mut eng, model);
+    eng.capture().unwrap().grid().clone()
+}
+
+fn grid(eng: &RenderEngine) -> &ScreenGrid {
+    eng.capture().unwrap().grid()
+}
+
+fn edit_line(model: &mut EditorModel, line: usize, text: &str) {
+    let st = model.state_mut();
+    let mut buf = st.active_buffer().clone();
+    while !buf.line(line).unwrap().trim_end_matches('\n').is_empty() {
+        buf.delete_grapheme_at(&mut Position { line, byte: 0 });
+    }
+    let mut pos = Position { line, byte: 0 };
+    for g in core_text::grapheme::iter(text) {
+        buf.insert_grapheme(&mut pos, g);
+    }
+    st.buffers[st.active] = buf;
+}
+
+fn render_lines(eng: &mut RenderEngine, model: &EditorModel, lines: &[usize]) {
+    let mut dirty = DirtyLinesTracker::new();
+    for line in lines {
+        dirty.mark(*line);
+    }
+    let view = model.active_view().clone();
+    let status = build_status_line(model.state(), &view);
+    eng.render_lines_partial(
+        model.state(),
+        &view,
+        &Layout::single(W, H),
+        W,
+        H,
+        &mut dirty,
+        &status,
+    )
+    .unwrap();
+}
+
+#[test]
+fn full_frame_paints_text_rows_and_cursor() {
+    let model = mk("alpha\nbeta\n");
+    let mut eng = capturing_engine();
+    render_full(&mut eng, &model);
+    let grid = grid(&eng);
+    grid.assert_rows(&["alpha", "beta", "", "", ""]);
+    assert!(grid.row_text(H - 1).starts_with("[NORMAL]"));
+    assert_eq!(grid.reversed_text(0), "a");
+    assert_eq!(eng.capture().unwrap().frames().len(), 1);
+}
+
+#[test]
+fn cursor_only_frame_matches_full_render() {
+    let mut model = mk("a\nb\nc\n");
+    let mut eng = capturing_engine();
+    render_full(&mut eng, &model);
+    model.active_view_mut().cursor.line = 2;
+    let view = model.active_view().clone();
+    let status = build_status_line(model.state(), &view);
+    eng.render_cursor_only(model.state(), &view, &Layout::single(W, H), W, H, &status)
+        .unwrap();
+    assert_eq!(eng.test_last_repaint_kind(), Some("cursor_only"));
+    grid(&eng).assert_matches(&full_grid(&model));
+    assert_eq!(grid(&eng).reversed_text(0), "");
+    assert_eq!(grid(&eng).reversed_text(2), "c");
+}
+
+#[test]
+fn lines_partial_edit_matches_full_render() {
+    let mut model = mk("one\ntwo\nthree\n");
+    let mut eng = capturing_engine();
+    render_full(&mut eng, &model);
+    edit_line(&mut model, 1, "two and then some");
+    render_lines(&mut eng, &model, &[1]);
+    grid(&eng).assert_row(1, "two and then some");
+    grid(&eng).assert_matches(&full_grid(&model));
+
+    // Shrinking the line must blank the cells the old tail covered.
+    edit_line(&mut model, 1, "tw");
+    render_lines(&mut eng, &model, &[1]);
+    grid(&eng).assert_row(1, "tw");
+    grid(&eng).assert_matches(&full_grid(&model));
+}
+
+#[test]
+fn wide_cluster_edits_match_full_render() {
+    let mut model = mk("x\n界界ab\n");
+    let mut eng = capturing_engine();
+    render_full(&mut eng, &model);
+    // Narrow over wide, then wide over narrow, then a ZWJ family.
+    for text in ["x界ab", "界界界ab", "a👨‍👩‍👧‍👦b界"] {
+        edit_line(&mut model, 1, text);
+        render_lines(&mut eng, &model, &[1]);
+        grid(&eng).assert_row(1, text);
+        grid(&eng).assert_matches(&full_grid(&model));
+    }
+    let cell = grid(&eng).cell(1, 1).unwrap();
+    assert_eq!((cell.cluster.as_str(), cell.width), ("👨‍👩‍👧‍👦", 2));
+    assert!(!grid(&eng).cell(2, 1).unwrap().is_leader());
+}
+
+#[test]
+fn tabs_expand_to_tab_stops() {
+    let mut model = mk("\tx\nab\tc\n");
+    let mut eng = capturing_engine();
+    render_full(&mut eng, &model);
+    grid(&eng).assert_rows(&["        x", "ab      c", "", "", ""]);
+    assert_eq!(grid(&eng).reversed_text(0), "        ");
+    model.active_view_mut().cursor = Position { line: 1, byte: 2 };
+    let view = model.active_view().clone();
+    let status = build_status_line(model.state(), &view);
+    eng.render_cursor_only(model.state(), &view, &Layout::single(W, H), W, H, &status)
+        .unwrap();
+    grid(&eng).assert_matches(&full_grid(&model));
+    assert_eq!(grid(&eng).reversed_text(1), "      ");
+    for text in ["abcdefg\tc", "abc\tc\td"] {
+        edit_line(&mut model, 1, text);
+        render_lines(&mut eng, &model, &[1]);
+        grid(&eng).assert_matches(&full_grid(&model));
+    }
+    grid(&eng).assert_row(1, "abc     c       d");
+}
+
+#[test]
+fn scroll_shift_matches_full_render() {
+    let text: String = (0..30).map(|i| format!("line {i}\n")).collect();
+    let mut model = mk(&text);
+    let mut eng = capturing_engine();
+    render_full(&mut eng, &model);
+    for (old_first, new_first) in [(0, 2), (2, 3), (3, 1)] {
+        {
+            let v = model.active_view_mut();
+            v.viewport_first_line = new_first;
+            v.cursor.line = new_first;
+        }
+        let view = model.active_view().clone();
+        let status = build_status_line(model.state(), &view);
+        eng.render_scroll_shift(
+            model.state(),
+            &view,
+            &Layout::single(W, H),
+            W,
+            H,
+            old_first,
+            new_first,
+            &status,
+        )
+        .unwrap();
+        assert_eq!(eng.test_last_repaint_kind(), Some("scroll_shift"));
+        grid(&eng).assert_row(0, &format!("line {new_first}"));
+        grid(&eng).assert_matches(&full_grid(&model));
+    }
+}
@@ -269,3 +269,56 @@ fn resize_invalidation_repopulates_prev_text() {
         .unwrap();
     assert!(eng.test_prev_text(0).is_some());
 }
+
+#[test]
+fn mid_line_insert_paints_only_the_shifted_tail() {
+    let long = format!("{}TAIL\n", "word ".repeat(14));
+    let (mut model, layout) = mk_state(&format!("top\n{long}{long}"));
+    let mut eng = RenderEngine::new();
+    let view0 = model.active_view().clone();
+    let status_line = core_render::render_engine::build_status_line(model.state(), &view0);
+    eng.render_full(model.state(), &view0, &layout, 80, 8, &status_line)
+        .unwrap();
+    let mut render_line = |model: &mut EditorModel, line: usize| {
+        let mut dirty = DirtyLinesTracker::new();
+        dirty.mark(line);
+        let view = model.active_view().clone();
+        let status_line = core_render::render_engine::build_status_line(model.state(), &view);
+        let before = eng.metrics_snapshot().cells_printed;
+        eng.render_lines_partial(
+            model.state(),
+            &view,
+            &layout,
+            80,
+            8,
+            &mut dirty,
+            &status_line,
+        )
+        .unwrap();
+        eng.metrics_snapshot().cells_printed - before
+    };
+    // Typing near the end of a 74-column line shifts only the last word.
+    {
+        let buf = model.state_mut().active_buffer_mut();
+        let mut pos = Position::new(1, 68);
+        buf.insert_grapheme(&mut pos, "x");
+    }
+    let typed = render_line(&mut model, 1);
+    // Deleting it again blanks the cell the tail vacates.
+    {
+        let buf = model.state_mut().active_buffer_mut();
+        let mut pos = Position::new(1, 68);
+        buf.delete_grapheme_at(&mut pos);
+    }
+    let deleted = render_line(&mut model, 1);
+    let snap = eng.metrics_snapshot();
+    assert_eq!(snap.trim_success, 2);
+    assert!(snap.cols_saved_total >= 2 * 68);
+    // A whole-line repaint would print all 74 cells; the cursor row
+    // (`top`) is repainted on every lines frame.
+    assert!(
+        typed <= 12 && deleted <= 12,
+        "typed {typed}, deleted {deleted}"
+    );
+    assert_eq!(eng.test_prev_text(1), Some(long.trim_end()));
+}
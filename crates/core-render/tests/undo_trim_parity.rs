@@ -11,7 +11,7 @@ fn undo_after_insert_then_cursor_motion_preserves_line() {
     let original = "# Oxidized\n";
     let buf = Buffer::from_str("test", original).unwrap();
     let mut state = EditorState::new(buf);
-    let mut view = View::new(ViewId(0), 0, Position::origin(), 0);
+    let mut view = View::new(ViewId(0), core_state::BufferId(1), Position::origin(), 0);
 
     // Insert run at byte 2 (after '# ').
     view.cursor.byte = 2;
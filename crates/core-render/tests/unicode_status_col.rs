@@ -26,10 +26,16 @@ fn status_line_str(model: &EditorModel) -> String {
         mode: model.state().mode,
         line: view.cursor.line,
         col,
+        byte: view.cursor.byte,
+        percent: 100,
+        selection: None,
         command_active: model.state().command_line.is_active(),
         command_buffer: model.state().command_line.buffer(),
-        file_name: model.state().file_name.as_deref(),
-        dirty: model.state().dirty,
+        file_name: model.state().file_name(),
+        dirty: model.state().dirty(),
+        diagnostics: model.state().diagnostics.counts(model.state().active),
+        git: None,
+        segments: &[],
     })
 }
 
@@ -63,10 +69,11 @@ fn cursor_only_unicode_column_correct_single_emoji() {
         &status_line_moved,
     )
     .unwrap();
-    // Rebuild status (full frame path) to compare textual column number. Should be Col 3 (1-based) if width 2.
+    // Rebuild status (full frame path) to compare textual column number. Should be 3 (1-based)
+    // if width 2, after byte column 5.
     let status = status_line_str(&model);
     assert!(
-        status.contains("Col 3"),
+        status.contains(" 1:5/3 "),
         "status line did not reflect visual column 3: {status}"
     );
 }
@@ -101,7 +108,7 @@ fn cursor_only_unicode_column_cjk_wide() {
     .unwrap();
     let status = status_line_str(&model);
     assert!(
-        status.contains("Col 3"),
+        status.contains(" 1:4/3 "),
         "CJK wide column incorrect: {status}"
     );
 }
@@ -135,9 +142,9 @@ fn cursor_only_unicode_column_combining_mark() {
     )
     .unwrap();
     let status = status_line_str(&model);
-    // After moving over first grapheme (e + combining), column should be 2 (1-based Col 2)
+    // After moving over first grapheme (e + combining), column should be 2 (1-based), byte 4
     assert!(
-        status.contains("Col 2"),
+        status.contains(" 1:4/2 "),
         "Combining mark column incorrect: {status}"
     );
 }
@@ -170,5 +177,5 @@ fn cursor_only_unicode_column_ascii() {
     )
     .unwrap();
     let status = status_line_str(&model);
-    assert!(status.contains("Col 2"), "ASCII column incorrect: {status}");
+    assert!(status.contains(" 1:2 "), "ASCII column incorrect: {status}");
 }
@@ -0,0 +1,92 @@
+//! Insert-mode abbreviations (`:iabbrev`, `[abbreviations]` in the config).
+//!
+//! Only Vim's "full-id" kind is supported: the left-hand side is made of
+//! keyword characters. Typing a non-keyword character (or `<CR>`, or
+//! `<Esc>`) right after a run of keyword characters that starts the line or
+//! follows a non-keyword character, and that equals an abbreviation,
+//! replaces the run with the right-hand side before the character goes in.
+
+use crate::completion::is_keyword_char;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default)]
+pub struct Abbreviations {
+    table: BTreeMap<String, String>,
+}
+
+impl Abbreviations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define (or redefine) `lhs`. Fails with E474 when `lhs` is not a
+    /// keyword or `rhs` is empty.
+    pub fn define(&mut self, lhs: &str, rhs: &str) -> Result<(), String> {
+        if lhs.is_empty() || !lhs.chars().all(is_keyword_char) || rhs.is_empty() {
+            return Err("E474: Invalid argument".to_string());
+        }
+        self.table.insert(lhs.to_string(), rhs.to_string());
+        Ok(())
+    }
+
+    /// Remove `lhs`. Returns whether it was defined.
+    pub fn remove(&mut self, lhs: &str) -> bool {
+        self.table.remove(lhs).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn get(&self, lhs: &str) -> Option<&str> {
+        self.table.get(lhs).map(String::as_str)
+    }
+
+    /// Abbreviations in `lhs` order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.table.iter().map(|(l, r)| (l.as_str(), r.as_str()))
+    }
+
+    /// The abbreviation ending `before` (the line up to the cursor), as the
+    /// byte length of its left-hand side and its right-hand side.
+    pub fn expansion(&self, before: &str) -> Option<(usize, &str)> {
+        if self.table.is_empty() {
+            return None;
+        }
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_keyword_char(c))
+            .last()
+            .map(|(i, _)| i)?;
+        let word = &before[start..];
+        self.get(word).map(|rhs| (word.len(), rhs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expansions_need_a_whole_keyword_before_the_cursor() {
+        let mut abbrev = Abbreviations::new();
+        abbrev.define("teh", "the").unwrap();
+        assert!(abbrev.define("a-b", "x").is_err());
+        assert!(abbrev.define("ab", "").is_err());
+
+        assert_eq!(abbrev.expansion("teh"), Some((3, "the")));
+        assert_eq!(abbrev.expansion("say (teh"), Some((3, "the")));
+        assert_eq!(abbrev.expansion("steh"), None);
+        assert_eq!(abbrev.expansion("teh "), None);
+        assert_eq!(abbrev.expansion(""), None);
+
+        assert!(abbrev.remove("teh"));
+        assert!(!abbrev.remove("teh"));
+        assert!(abbrev.is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Binary file detection and the read-only hex view.
+//!
+//! Files that look binary (a NUL byte in the leading block, or invalid
+//! UTF-8) are opened into a hex view instead of being decoded as text: the
+//! buffer holds one `xxd`-style row per 16 bytes so cursor motion, scrolling
+//! and the partial render paths keep working on plain ASCII lines, while the
+//! original bytes stay in `BufferMeta::binary` untouched. The renderer paints
+//! hex rows straight from those bytes (see `core_render::hex`).
+//!
+//! The view is read-only. `:set binary` is the escape hatch: it swaps the
+//! buffer to the (lossily decoded) raw text for editing; `:set nobinary`
+//! returns to the hex rows. Writing while the hex view is shown writes the
+//! original bytes back unchanged, and so does writing the raw text before it
+//! was edited. Once edited, raw text decoded from invalid UTF-8 is refused
+//! (E513) rather than saved with U+FFFD in place of the bytes it lost.
+
+use crate::EditorState;
+use core_text::Buffer;
+
+/// Bytes shown per hex row.
+pub const HEX_BYTES_PER_ROW: usize = 16;
+/// Status shown after opening a file detected as binary.
+pub const BINARY_OPENED_MSG: &str = "[binary] read-only hex view; :set binary to edit raw";
+/// Leading block inspected for NUL bytes (same heuristic as git).
+const SNIFF_LEN: usize = 8000;
+
+/// True when `bytes` should be shown as hex rather than text.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(SNIFF_LEN)].contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// Format row `row` of `bytes`: `00000010: 4865 6c6c 6f0a ...  Hello.`.
+/// Every row of a file has the same width (short final rows are padded).
+pub fn hex_row(bytes: &[u8], row: usize) -> String {
+    let start = row * HEX_BYTES_PER_ROW;
+    let chunk = &bytes[start.min(bytes.len())..(start + HEX_BYTES_PER_ROW).min(bytes.len())];
+    let mut out = format!("{start:08x}:");
+    for i in 0..HEX_BYTES_PER_ROW {
+        if i % 2 == 0 {
+            out.push(' ');
+        }
+        match chunk.get(i) {
+            Some(b) => out.push_str(&format!("{b:02x}")),
+            None => out.push_str("  "),
+        }
+    }
+    out.push_str("  ");
+    out.extend(chunk.iter().map(|&b| {
+        if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        }
+    }));
+    out
+}
+
+/// Number of hex rows for `len` bytes (an empty file still shows one row).
+pub fn hex_row_count(len: usize) -> usize {
+    len.div_ceil(HEX_BYTES_PER_ROW).max(1)
+}
+
+/// Full hex dump, one newline-terminated row per line.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for row in 0..hex_row_count(bytes.len()) {
+        out.push_str(&hex_row(bytes, row));
+        out.push('\n');
+    }
+    out
+}
+
+impl EditorState {
+    /// Original bytes when the active buffer is shown as a hex view.
+    pub fn hex_view(&self) -> Option<&[u8]> {
+        let meta = self.active_meta();
+        meta.binary.as_deref().filter(|_| meta.hex_view)
+    }
+
+    /// Reconcile the active buffer with the `'binary'` option: detected
+    /// binary files show hex rows unless `binary` is set. Returns true when
+    /// the buffer contents were swapped (the caller resets the cursor).
+    /// Unsaved raw edits are never thrown away: a modified buffer stays raw.
+    pub fn sync_binary_view(&mut self) -> bool {
+        let want_hex = !self.options.get_bool("binary");
+        let meta = self.active_meta();
+        let Some(bytes) = meta.binary.as_deref() else {
+            return false;
+        };
+        if meta.hex_view == want_hex || (want_hex && meta.dirty) {
+            return false;
+        }
+        let text = if want_hex {
+            hex_dump(bytes)
+        } else {
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+        let name = self.active_buffer().name.clone();
+        let Ok(buffer) = Buffer::from_str(name, &text) else {
+            return false;
+        };
+        self.buffers[self.active] = buffer;
+        let meta = self.active_meta_mut();
+        meta.hex_view = want_hex;
+        meta.dirty = false;
+        meta.undo = crate::undo::UndoEngine::new();
+        tracing::debug!(target: "state.binary", hex = want_hex, "binary_view_switched");
+        true
+    }
+
+    /// Load `bytes` into the active buffer as a binary file (hex view unless
+    /// `'binary'` is set).
+    pub fn load_binary(&mut self, bytes: Vec<u8>) {
+        // Start in the opposite state so the sync below rebuilds from the bytes.
+        let raw = self.options.get_bool("binary");
+        let meta = self.active_meta_mut();
+        meta.binary = Some(bytes);
+        meta.hex_view = raw;
+        self.sync_binary_view();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_nul_and_invalid_utf8() {
+        assert!(is_binary(b"ab\0cd"));
+        assert!(is_binary(&[0xff, 0xfe, b'a']));
+        assert!(!is_binary("héllo\n".as_bytes()));
+    }
+
+    #[test]
+    fn rows_have_fixed_width() {
+        let bytes: Vec<u8> = (0u8..20).chain(*b"Hi").collect();
+        let first = hex_row(&bytes, 0);
+        assert_eq!(
+            first,
+            "00000000: 0001 0203 0405 0607 0809 0a0b 0c0d 0e0f  ................"
+        );
+        let last = hex_row(&bytes, 1);
+        assert_eq!(
+            last,
+            "00000010: 1011 1213 4869                           ....Hi"
+        );
+        assert_eq!(hex_row_count(bytes.len()), 2);
+    }
+
+    #[test]
+    fn binary_option_toggles_between_hex_and_raw() {
+        let mut st = EditorState::new(Buffer::from_str("b", "").unwrap());
+        st.load_binary(b"a\0b".to_vec());
+        assert_eq!(st.hex_view(), Some(&b"a\0b"[..]));
+        assert!(
+            st.active_buffer()
+                .line(0)
+                .unwrap()
+                .starts_with("00000000: 6100 62")
+        );
+
+        st.options.apply_set("binary").unwrap();
+        assert!(st.sync_binary_view());
+        assert!(st.hex_view().is_none());
+        assert_eq!(st.active_buffer().line(0).unwrap(), "a\0b");
+        assert!(!st.sync_binary_view(), "already in sync");
+
+        st.set_dirty(true);
+        st.options.apply_set("nobinary").unwrap();
+        assert!(!st.sync_binary_view(), "raw edits are kept");
+    }
+}
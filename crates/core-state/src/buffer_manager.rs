@@ -0,0 +1,268 @@
+//! Buffer collection with stable ids and per-buffer metadata.
+//!
+//! Buffers were previously a bare `Vec<Buffer>` addressed by index, with file
+//! path, dirty flag, line-ending info and the undo engine stored once on
+//! `EditorState`. That only worked while exactly one buffer existed: closing
+//! a buffer would shift every later index and all buffers shared one undo
+//! history. `BufferManager` gives each buffer a `BufferId` that is never
+//! reused (numbered from 1 like Vim buffer numbers) and keeps the metadata
+//! next to the text it describes, so views can hold ids that stay valid
+//! across open/close.
+
+use crate::LineEnding;
+use crate::explorer::Explorer;
+use crate::quickfix::ListKind;
+use crate::undo::UndoEngine;
+use core_text::{Buffer, Position};
+use std::path::{Path, PathBuf};
+
+/// Stable buffer identifier (never reused within a session).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BufferId(pub u64);
+
+impl std::fmt::Display for BufferId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// State describing a buffer's backing file and edit history.
+pub struct BufferMeta {
+    pub path: Option<PathBuf>,
+    pub dirty: bool,
+    pub original_line_ending: LineEnding,
+    pub had_trailing_newline: bool,
+    /// Swap file written by this session (see `swap`).
+    pub swap: Option<PathBuf>,
+    /// Swap file left behind by another session, awaiting `:recover`.
+    pub stale_swap: Option<PathBuf>,
+    /// Original bytes of a file detected as binary (see `binary`).
+    pub binary: Option<Vec<u8>>,
+    /// True while `binary` bytes are shown as read-only hex rows.
+    pub hex_view: bool,
+    /// Directory listing shown instead of text (see `explorer`).
+    pub explorer: Option<Explorer>,
+    /// Quickfix or location list shown instead of text (see `quickfix`).
+    pub quickfix: Option<ListKind>,
+    /// Where Insert mode was last left (Vim's `'^` mark), for `gi`.
+    pub last_insert: Option<Position>,
+    pub(crate) undo: UndoEngine,
+}
+
+impl BufferMeta {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            dirty: false,
+            original_line_ending: LineEnding::Lf,
+            had_trailing_newline: false,
+            swap: None,
+            stale_swap: None,
+            binary: None,
+            hex_view: false,
+            explorer: None,
+            quickfix: None,
+            last_insert: None,
+            undo: UndoEngine::new(),
+        }
+    }
+}
+
+pub struct BufferEntry {
+    id: BufferId,
+    pub buffer: Buffer,
+    pub meta: BufferMeta,
+}
+
+impl BufferEntry {
+    pub fn id(&self) -> BufferId {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferError {
+    NoSuchBuffer(BufferId),
+    LastBuffer,
+    Modified(BufferId),
+}
+
+impl std::fmt::Display for BufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BufferError::NoSuchBuffer(id) => write!(f, "E86: Buffer {id} does not exist"),
+            BufferError::LastBuffer => write!(f, "E90: Cannot unload last buffer"),
+            BufferError::Modified(id) => write!(
+                f,
+                "E89: No write since last change for buffer {id} (add ! to override)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BufferError {}
+
+/// Owns every open buffer. Always holds at least one buffer.
+pub struct BufferManager {
+    entries: Vec<BufferEntry>,
+    next_id: u64,
+}
+
+impl BufferManager {
+    /// Create a manager holding `initial` as buffer 1.
+    pub fn new(initial: Buffer) -> Self {
+        let mut mgr = Self {
+            entries: Vec::new(),
+            next_id: 1,
+        };
+        mgr.open(initial, None);
+        mgr
+    }
+
+    /// Add a buffer, returning its new id.
+    pub fn open(&mut self, buffer: Buffer, path: Option<PathBuf>) -> BufferId {
+        let id = BufferId(self.next_id);
+        self.next_id += 1;
+        tracing::debug!(target: "state.buffers", id = id.0, name = %buffer.name, "buffer_open");
+        self.entries.push(BufferEntry {
+            id,
+            buffer,
+            meta: BufferMeta::new(path),
+        });
+        id
+    }
+
+    /// Remove a buffer. Modified buffers require `force`; the last buffer
+    /// can never be closed.
+    pub fn close(&mut self, id: BufferId, force: bool) -> Result<BufferEntry, BufferError> {
+        let idx = self.position(id).ok_or(BufferError::NoSuchBuffer(id))?;
+        if self.entries.len() == 1 {
+            return Err(BufferError::LastBuffer);
+        }
+        if self.entries[idx].meta.dirty && !force {
+            return Err(BufferError::Modified(id));
+        }
+        tracing::debug!(target: "state.buffers", id = id.0, force, "buffer_close");
+        Ok(self.entries.remove(idx))
+    }
+
+    /// Find an open buffer backed by `path`. Paths are compared after
+    /// canonicalization when both resolve, so `./a.txt` matches `a.txt`.
+    pub fn find_by_path(&self, path: &Path) -> Option<BufferId> {
+        let wanted = std::fs::canonicalize(path).ok();
+        self.entries
+            .iter()
+            .find(|e| match e.meta.path.as_deref() {
+                Some(p) if p == path => true,
+                Some(p) => wanted.is_some() && std::fs::canonicalize(p).ok() == wanted,
+                None => false,
+            })
+            .map(|e| e.id)
+    }
+
+    pub fn contains(&self, id: BufferId) -> bool {
+        self.position(id).is_some()
+    }
+
+    pub fn get(&self, id: BufferId) -> Option<&BufferEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: BufferId) -> Option<&mut BufferEntry> {
+        self.entries.iter_mut().find(|e| e.id == id)
+    }
+
+    /// Entries in creation order (the `:ls` order).
+    pub fn iter(&self) -> impl Iterator<Item = &BufferEntry> {
+        self.entries.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut BufferEntry> {
+        self.entries.iter_mut()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = BufferId> + '_ {
+        self.entries.iter().map(|e| e.id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Always false (the manager keeps at least one buffer); provided for API symmetry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, id: BufferId) -> Option<usize> {
+        self.entries.iter().position(|e| e.id == id)
+    }
+
+    /// Entry for `id`; panics on unknown ids (callers hold ids from this manager).
+    pub(crate) fn entry(&self, id: BufferId) -> &BufferEntry {
+        self.get(id)
+            .unwrap_or_else(|| panic!("unknown buffer id {id}"))
+    }
+
+    pub(crate) fn entry_mut(&mut self, id: BufferId) -> &mut BufferEntry {
+        self.get_mut(id)
+            .unwrap_or_else(|| panic!("unknown buffer id {id}"))
+    }
+}
+
+impl std::ops::Index<BufferId> for BufferManager {
+    type Output = Buffer;
+    fn index(&self, id: BufferId) -> &Buffer {
+        &self.entry(id).buffer
+    }
+}
+
+impl std::ops::IndexMut<BufferId> for BufferManager {
+    fn index_mut(&mut self, id: BufferId) -> &mut Buffer {
+        &mut self.entry_mut(id).buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buf(name: &str) -> Buffer {
+        Buffer::from_str(name, "x\n").unwrap()
+    }
+
+    #[test]
+    fn ids_are_stable_across_close() {
+        let mut mgr = BufferManager::new(buf("a"));
+        let b = mgr.open(buf("b"), None);
+        let c = mgr.open(buf("c"), None);
+        assert_eq!((b, c), (BufferId(2), BufferId(3)));
+        mgr.close(b, false).unwrap();
+        assert_eq!(mgr[c].name, "c");
+        let d = mgr.open(buf("d"), None);
+        assert_eq!(d, BufferId(4), "ids are never reused");
+        assert_eq!(mgr.ids().collect::<Vec<_>>(), [BufferId(1), c, d]);
+    }
+
+    #[test]
+    fn close_guards_dirty_and_last_buffer() {
+        let mut mgr = BufferManager::new(buf("a"));
+        let b = mgr.open(buf("b"), None);
+        mgr.get_mut(b).unwrap().meta.dirty = true;
+        assert_eq!(mgr.close(b, false).err(), Some(BufferError::Modified(b)));
+        assert!(mgr.close(b, true).is_ok());
+        assert_eq!(
+            mgr.close(BufferId(1), true).err(),
+            Some(BufferError::LastBuffer)
+        );
+        assert_eq!(mgr.close(b, true).err(), Some(BufferError::NoSuchBuffer(b)));
+    }
+
+    #[test]
+    fn find_by_path_matches_exact_path() {
+        let mut mgr = BufferManager::new(buf("a"));
+        let id = mgr.open(buf("b"), Some(PathBuf::from("/tmp/ox-b.txt")));
+        assert_eq!(mgr.find_by_path(Path::new("/tmp/ox-b.txt")), Some(id));
+        assert_eq!(mgr.find_by_path(Path::new("/tmp/other.txt")), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Command-line window (`q:`).
+//!
+//! Opening the window adds a scratch `[Command Line]` buffer holding the
+//! command history (plus an empty line for a new command) and makes it
+//! active. The scratch buffer carries its own undo history and dirty flag
+//! (see `BufferManager`), so editing inside the window never touches the
+//! origin buffer. Closing drops the scratch buffer and reactivates the origin.
+
+use crate::{BufferId, EditorState, Mode};
+use core_text::{Buffer, Position};
+
+/// Name shown for the scratch buffer (matches Vim).
+pub const CMDLINE_WINDOW_NAME: &str = "[Command Line]";
+
+/// Origin state stashed while the command-line window is open.
+pub struct CmdlineWindow {
+    scratch: BufferId,
+    origin_buffer: BufferId,
+    origin_cursor: Position,
+    origin_first_line: usize,
+}
+
+/// Where the origin view should return to after closing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CmdlineWindowReturn {
+    pub buffer: BufferId,
+    pub cursor: Position,
+    pub viewport_first_line: usize,
+}
+
+impl EditorState {
+    pub fn cmdline_window_active(&self) -> bool {
+        self.cmdline_window.is_some()
+    }
+
+    /// File name shown in the status line: the scratch buffer name while the
+    /// window is open, otherwise the origin file.
+    pub fn status_file_name(&self) -> Option<&std::path::Path> {
+        if self.cmdline_window.is_some() {
+            Some(std::path::Path::new(CMDLINE_WINDOW_NAME))
+        } else {
+            self.file_name()
+        }
+    }
+
+    /// Open the window. `cursor` / `first_line` describe the origin view so
+    /// it can be restored on close. Returns the scratch buffer id and the
+    /// cursor position (empty last line), or `None` when already open.
+    pub fn open_cmdline_window(
+        &mut self,
+        cursor: Position,
+        first_line: usize,
+    ) -> Option<(BufferId, Position)> {
+        if self.cmdline_window.is_some() {
+            return None;
+        }
+        let mut text = String::new();
+        for entry in self.command_line.history() {
+            text.push_str(entry);
+            text.push('\n');
+        }
+        let buffer = Buffer::from_str(CMDLINE_WINDOW_NAME, &text).ok()?;
+        let last = buffer.line_count().saturating_sub(1);
+        let scratch = self.buffers.open(buffer, None);
+        self.cmdline_window = Some(CmdlineWindow {
+            scratch,
+            origin_buffer: self.active,
+            origin_cursor: cursor,
+            origin_first_line: first_line,
+        });
+        self.switch_buffer(scratch);
+        self.mode = Mode::Normal;
+        self.command_line.clear();
+        tracing::debug!(target: "state.cmdline_window", entries = self.command_line.history().len(), "cmdline_window_open");
+        Some((scratch, Position::new(last, 0)))
+    }
+
+    /// Close the window, discarding the scratch buffer. Returns the origin
+    /// view placement, or `None` when the window is not open.
+    pub fn close_cmdline_window(&mut self) -> Option<CmdlineWindowReturn> {
+        let win = self.cmdline_window.take()?;
+        self.switch_buffer(win.origin_buffer);
+        self.mode = Mode::Normal;
+        let _ = self.close_buffer(win.scratch, true);
+        tracing::debug!(target: "state.cmdline_window", "cmdline_window_close");
+        Some(CmdlineWindowReturn {
+            buffer: self.active,
+            cursor: win.origin_cursor,
+            viewport_first_line: win.origin_first_line,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_lists_history_and_close_restores_origin() {
+        let mut st = EditorState::new(Buffer::from_str("t", "abc\n").unwrap());
+        st.command_line.record_history("set nu");
+        st.command_line.record_history("w");
+        st.push_discrete_edit_snapshot(Position::origin());
+        st.set_dirty(true);
+        let origin = st.active;
+
+        let (id, cursor) = st
+            .open_cmdline_window(Position::new(0, 1), 0)
+            .expect("opened");
+        assert_ne!(id, origin);
+        assert_eq!(st.active, id);
+        assert_eq!(st.active_buffer().name, CMDLINE_WINDOW_NAME);
+        assert_eq!(st.active_buffer().line(0).unwrap(), "set nu\n");
+        assert_eq!(cursor, Position::new(2, 0));
+        assert_eq!(st.undo_depth(), 0);
+        assert!(!st.dirty());
+        assert!(st.open_cmdline_window(cursor, 0).is_none());
+
+        let ret = st.close_cmdline_window().expect("closed");
+        assert_eq!(ret.buffer, origin);
+        assert_eq!(ret.cursor, Position::new(0, 1));
+        assert_eq!(st.buffers.len(), 1);
+        assert_eq!(st.undo_depth(), 1);
+        assert!(st.dirty());
+        assert!(st.close_cmdline_window().is_none());
+    }
+}
@@ -0,0 +1,557 @@
+//! Insert-mode completion: the candidates offered for the keyword before
+//! the cursor and which of them is inserted.
+//!
+//! `Ctrl-N` / `Ctrl-Space` (`Ctrl-P` backwards) open a completion seeded
+//! with the words of the open buffers (`WordIndex::words`) and, when a language
+//! server is attached to the buffer, queue a request for its items
+//! (`take_lsp_request`, sent by `core-lsp` on its next sync). Typing one of
+//! the server's trigger characters opens one with an empty prefix that only
+//! the server fills. Server items arrive later and go in front of the
+//! buffer words, provided the completion they were asked for is still the
+//! open one (`generation`).
+//!
+//! Cycling puts the selected candidate after `start` in place of what was
+//! there; cycling past either end brings back the typed prefix, as in Vim.
+//! Typing keyword characters narrows the matches, anything else closes the
+//! completion. The renderer lists the matches in a popup under the word
+//! and repaints when `take_changed` reports a change.
+//!
+//! Buffer words follow `ignorecase` / `smartcase`; with `infercase` a
+//! word found in another case is inserted in the case of what was typed
+//! (`CaseRules`). The `WordIndex` keeps each buffer's words per line and
+//! only re-scans lines whose text changed since the last completion.
+
+use crate::{BufferId, BufferManager};
+use core_text::Position;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Characters that make up a completed keyword.
+pub fn is_keyword_char(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionSource {
+    Buffer,
+    Lsp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    /// Inserted in place of the typed prefix.
+    pub text: String,
+    /// Matched against the prefix (LSP `filterText`, else the text).
+    pub filter: String,
+    /// Shown after the text (the LSP item kind, `Buffer` for words).
+    pub kind: Option<String>,
+    pub source: CompletionSource,
+}
+
+impl CompletionItem {
+    pub fn word(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            filter: text.to_string(),
+            kind: Some("Buffer".to_string()),
+            source: CompletionSource::Buffer,
+        }
+    }
+
+    /// Buffer words match the prefix by `case`; server items ignore case,
+    /// since servers already filter (often fuzzily) on their side.
+    fn matches(&self, prefix: &str, case: CaseRules) -> bool {
+        if self.text == prefix {
+            return false;
+        }
+        match self.source {
+            CompletionSource::Buffer if !case.ignores(prefix) => self.filter.starts_with(prefix),
+            _ => starts_with_ignoring_case(&self.filter, prefix),
+        }
+    }
+}
+
+/// Case handling of buffer words: `ignorecase`, `smartcase` and
+/// `infercase`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaseRules {
+    pub ignore: bool,
+    pub smart: bool,
+    pub infer: bool,
+}
+
+impl CaseRules {
+    /// Whether words match `prefix` regardless of case: `ignorecase`,
+    /// unless `smartcase` and the prefix has an uppercase letter.
+    pub fn ignores(self, prefix: &str) -> bool {
+        self.ignore && !(self.smart && prefix.chars().any(char::is_uppercase))
+    }
+
+    /// `word` as inserted after typing `typed`. With `infercase` (and case
+    /// ignored) the typed characters are kept and the rest of the word
+    /// follows them: lowercased after an all-lowercase prefix, uppercased
+    /// after two or more uppercase letters and no lowercase, else as found.
+    pub fn insert<'a>(self, typed: &str, word: &'a str) -> Cow<'a, str> {
+        if !(self.infer && self.ignores(typed)) || word.starts_with(typed) {
+            return Cow::Borrowed(word);
+        }
+        let Some((split, _)) = word.char_indices().nth(typed.chars().count()) else {
+            return Cow::Borrowed(word);
+        };
+        let rest = &word[split..];
+        let upper = typed.chars().filter(|c| c.is_uppercase()).count();
+        let lower = typed.chars().any(char::is_lowercase);
+        let rest = match (upper, lower) {
+            (0, true) => rest.to_lowercase(),
+            (2.., false) => rest.to_uppercase(),
+            _ => rest.to_string(),
+        };
+        Cow::Owned(format!("{typed}{rest}"))
+    }
+}
+
+fn starts_with_ignoring_case(text: &str, prefix: &str) -> bool {
+    text.to_lowercase().starts_with(&prefix.to_lowercase())
+}
+
+/// An open completion.
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub buffer: BufferId,
+    /// Start of the completed word; the text from here to the cursor is
+    /// what cycling replaces.
+    pub start: Position,
+    /// Text typed after `start`, restored by cycling past the ends.
+    pub prefix: String,
+    pub generation: u64,
+    pub case: CaseRules,
+    items: Vec<CompletionItem>,
+    /// Indices into `items` of the candidates matching `prefix`.
+    matches: Vec<usize>,
+    /// Index into `matches`; `None` while the prefix is shown.
+    selected: Option<usize>,
+}
+
+impl Completion {
+    pub fn matches(&self) -> impl Iterator<Item = &CompletionItem> {
+        self.matches.iter().map(|&i| &self.items[i])
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Text currently following `start`: the selected candidate (in the
+    /// case `infercase` gives it) or the prefix.
+    pub fn current_text(&self) -> Cow<'_, str> {
+        match self.selected {
+            Some(i) => self.inserted(&self.items[self.matches[i]]),
+            None => Cow::Borrowed(&self.prefix),
+        }
+    }
+
+    /// What accepting `item` puts after `start`.
+    pub fn inserted<'a>(&self, item: &'a CompletionItem) -> Cow<'a, str> {
+        match item.source {
+            CompletionSource::Buffer => self.case.insert(&self.prefix, &item.text),
+            CompletionSource::Lsp => Cow::Borrowed(&item.text),
+        }
+    }
+
+    fn refilter(&mut self) {
+        let prefix = &self.prefix;
+        self.matches = (0..self.items.len())
+            .filter(|&i| self.items[i].matches(prefix, self.case))
+            .collect();
+    }
+}
+
+/// A server request queued by `open`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspCompletionRequest {
+    pub buffer: BufferId,
+    /// Cursor position the items are asked for.
+    pub position: Position,
+    /// Trigger character typed, for trigger-opened completions.
+    pub trigger: Option<char>,
+    pub generation: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CompletionState {
+    active: Option<Completion>,
+    generation: u64,
+    request: Option<LspCompletionRequest>,
+    /// Buffers with a completing server attached, and its trigger characters.
+    servers: HashMap<BufferId, Vec<char>>,
+    changed: bool,
+    /// Case rules given to the completions opened from now on.
+    pub case: CaseRules,
+    pub words: WordIndex,
+}
+
+impl CompletionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn active(&self) -> Option<&Completion> {
+        self.active.as_ref()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Open a completion of `prefix` (typed after `start`, cursor at its
+    /// end) over `items`, asking the buffer's server too. `trigger` is the
+    /// character that opened it, if one did.
+    pub fn open(
+        &mut self,
+        buffer: BufferId,
+        start: Position,
+        prefix: String,
+        items: Vec<CompletionItem>,
+        trigger: Option<char>,
+    ) -> &mut Completion {
+        self.generation += 1;
+        self.request = self
+            .servers
+            .contains_key(&buffer)
+            .then_some(LspCompletionRequest {
+                buffer,
+                position: Position::new(start.line, start.byte + prefix.len()),
+                trigger,
+                generation: self.generation,
+            });
+        let mut completion = Completion {
+            buffer,
+            start,
+            prefix,
+            generation: self.generation,
+            case: self.case,
+            items,
+            matches: Vec::new(),
+            selected: None,
+        };
+        completion.refilter();
+        self.changed = true;
+        self.active.insert(completion)
+    }
+
+    /// Close the open completion, leaving the text as it is. Returns
+    /// whether one was open.
+    pub fn close(&mut self) -> bool {
+        self.request = None;
+        let was_open = self.active.take().is_some();
+        self.changed |= was_open;
+        was_open
+    }
+
+    /// Select the next (previous) match, wrapping through the prefix.
+    /// Returns the text to put after `start`, `None` without a completion.
+    pub fn select(&mut self, forward: bool) -> Option<Cow<'_, str>> {
+        let completion = self.active.as_mut()?;
+        let count = completion.matches.len();
+        completion.selected = match (completion.selected, forward) {
+            _ if count == 0 => None,
+            (None, true) => Some(0),
+            (None, false) => Some(count - 1),
+            (Some(i), true) => (i + 1 < count).then_some(i + 1),
+            (Some(i), false) => i.checked_sub(1),
+        };
+        self.changed = true;
+        Some(completion.current_text())
+    }
+
+    /// The text after `start` became `prefix` (typed or deleted): narrow
+    /// the matches and drop the selection.
+    pub fn set_prefix(&mut self, prefix: String) {
+        if let Some(completion) = self.active.as_mut() {
+            completion.prefix = prefix;
+            completion.selected = None;
+            completion.refilter();
+            self.changed = true;
+        }
+    }
+
+    /// Add a server's answer to completion `generation` in front of the
+    /// buffer words (dropping words it repeats). Returns false when that
+    /// completion is no longer open.
+    pub fn merge_lsp(&mut self, generation: u64, items: Vec<CompletionItem>) -> bool {
+        let Some(completion) = self.active.as_mut().filter(|c| c.generation == generation) else {
+            return false;
+        };
+        let selected = completion
+            .selected
+            .map(|_| completion.current_text().to_string());
+        let texts: HashSet<&str> = items.iter().map(|item| item.text.as_str()).collect();
+        let words = completion
+            .items
+            .drain(..)
+            .filter(|item| !texts.contains(item.text.as_str()));
+        let merged: Vec<CompletionItem> = items.iter().cloned().chain(words).collect();
+        completion.items = merged;
+        completion.refilter();
+        completion.selected = selected.and_then(|text| {
+            completion
+                .matches
+                .iter()
+                .position(|&i| completion.items[i].text == text)
+        });
+        self.changed = true;
+        true
+    }
+
+    /// Record that `buffer` is open on a server offering completion.
+    pub fn attach(&mut self, buffer: BufferId, triggers: Vec<char>) {
+        self.servers.insert(buffer, triggers);
+    }
+
+    pub fn detach(&mut self, buffer: BufferId) {
+        self.servers.remove(&buffer);
+    }
+
+    pub fn is_attached(&self, buffer: BufferId) -> bool {
+        self.servers.contains_key(&buffer)
+    }
+
+    /// Whether typing `c` in `buffer` should ask its server for items.
+    pub fn is_trigger(&self, buffer: BufferId, c: char) -> bool {
+        self.servers
+            .get(&buffer)
+            .is_some_and(|triggers| triggers.contains(&c))
+    }
+
+    /// Server request queued by the last `open`, if it wants one.
+    pub fn take_lsp_request(&mut self) -> Option<LspCompletionRequest> {
+        self.request.take()
+    }
+
+    /// Whether the completion changed since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+/// Words of one line and the hash of its text.
+#[derive(Debug)]
+struct LineWords {
+    hash: u64,
+    words: Vec<Box<str>>,
+}
+
+/// Keyword index of the open buffers, line by line. `refresh` re-scans
+/// only lines whose text is new to the buffer's index, so edits since the
+/// last completion cost about one hash per line.
+#[derive(Debug, Default)]
+pub struct WordIndex {
+    buffers: HashMap<BufferId, Vec<LineWords>>,
+}
+
+impl WordIndex {
+    /// Bring the index up to date with `buffers`, forgetting closed ones.
+    pub fn refresh(&mut self, buffers: &BufferManager) {
+        self.buffers.retain(|id, _| buffers.get(*id).is_some());
+        for entry in buffers.iter() {
+            if entry.meta.binary.is_some() {
+                self.buffers.remove(&entry.id());
+                continue;
+            }
+            let old = self.buffers.remove(&entry.id()).unwrap_or_default();
+            let mut reusable: HashMap<u64, Vec<LineWords>> = HashMap::new();
+            for line in old.into_iter().rev() {
+                reusable.entry(line.hash).or_default().push(line);
+            }
+            let lines = (0..entry.buffer.line_count())
+                .map(|i| {
+                    let text = entry.buffer.line(i).unwrap_or_default();
+                    let mut hasher = DefaultHasher::new();
+                    text.hash(&mut hasher);
+                    let hash = hasher.finish();
+                    reusable
+                        .get_mut(&hash)
+                        .and_then(Vec::pop)
+                        .unwrap_or_else(|| LineWords {
+                            hash,
+                            words: line_words(&text),
+                        })
+                })
+                .collect();
+            self.buffers.insert(entry.id(), lines);
+        }
+    }
+
+    /// Distinct words starting with `prefix` (and longer than it, ignoring
+    /// case when `case` says so) after a `refresh`: `active`'s first, from
+    /// line `from` onwards and wrapping, then the other buffers'.
+    pub fn words(
+        &mut self,
+        buffers: &BufferManager,
+        active: BufferId,
+        from: Position,
+        prefix: &str,
+        case: CaseRules,
+    ) -> Vec<CompletionItem> {
+        self.refresh(buffers);
+        let ignore = case.ignores(prefix);
+        let mut seen = HashSet::new();
+        let mut words = Vec::new();
+        let mut take = |lines: &[LineWords], first: usize| {
+            let count = lines.len();
+            for offset in 0..count {
+                for word in lines[(first + offset) % count].words.iter() {
+                    let found = if ignore {
+                        starts_with_ignoring_case(word, prefix)
+                    } else {
+                        word.starts_with(prefix)
+                    };
+                    if word.len() > prefix.len() && found && seen.insert(word.clone()) {
+                        words.push(CompletionItem::word(word));
+                    }
+                }
+            }
+        };
+        if let Some(lines) = self.buffers.get(&active) {
+            take(lines, from.line);
+        }
+        for entry in buffers.iter().filter(|entry| entry.id() != active) {
+            if let Some(lines) = self.buffers.get(&entry.id()) {
+                take(lines, 0);
+            }
+        }
+        words
+    }
+}
+
+fn line_words(line: &str) -> Vec<Box<str>> {
+    let mut words = Vec::new();
+    let mut rest = line;
+    while let Some(begin) = rest.find(is_keyword_char) {
+        rest = &rest[begin..];
+        let end = rest.find(|c| !is_keyword_char(c)).unwrap_or(rest.len());
+        words.push(rest[..end].into());
+        rest = &rest[end..];
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_text::Buffer;
+
+    fn lsp(text: &str) -> CompletionItem {
+        CompletionItem {
+            text: text.to_string(),
+            filter: text.to_string(),
+            kind: Some("Function".to_string()),
+            source: CompletionSource::Lsp,
+        }
+    }
+
+    #[test]
+    fn words_cycle_through_the_prefix_and_merge_server_items() {
+        let buffers =
+            BufferManager::new(Buffer::from_str("t", "fold fo_bar\nfoo fold fob\nx\n").unwrap());
+        let buffer = buffers.ids().next().unwrap();
+        let mut index = WordIndex::default();
+        let case = CaseRules::default();
+        let words = index.words(&buffers, buffer, Position::new(1, 0), "fo", case);
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, ["foo", "fold", "fob", "fo_bar"]);
+
+        let mut state = CompletionState::new();
+        state.open(buffer, Position::new(2, 0), "fo".into(), words, None);
+        assert_eq!(state.take_lsp_request(), None, "no server attached");
+        assert_eq!(state.select(true).as_deref(), Some("foo"));
+        assert_eq!(state.select(false).as_deref(), Some("fo"));
+        assert_eq!(state.select(false).as_deref(), Some("fo_bar"));
+        state.set_prefix("fol".into());
+        assert_eq!(state.active().unwrap().match_count(), 1);
+        assert!(state.take_changed());
+
+        state.attach(buffer, vec!['.']);
+        assert!(state.is_trigger(buffer, '.') && !state.is_trigger(buffer, ':'));
+        let words = index.words(&buffers, buffer, Position::origin(), "f", case);
+        let generation = state
+            .open(buffer, Position::new(2, 0), "f".into(), words, None)
+            .generation;
+        let request = state.take_lsp_request().unwrap();
+        assert_eq!(request.position, Position::new(2, 1));
+        state.select(true);
+        assert!(!state.merge_lsp(generation - 1, vec![lsp("stale")]));
+        assert!(state.merge_lsp(generation, vec![lsp("Fold"), lsp("fob")]));
+        let completion = state.active().unwrap();
+        let texts: Vec<&str> = completion.matches().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["Fold", "fob", "fold", "fo_bar", "foo"]);
+        assert_eq!(completion.current_text(), "fold", "selection kept");
+        assert!(state.close());
+        assert!(!state.is_active());
+    }
+
+    #[test]
+    fn word_index_rescans_changed_lines_and_infers_case() {
+        let mut buffers = BufferManager::new(
+            Buffer::from_str(
+                "t",
+                "HashMap hashing
+other
+",
+            )
+            .unwrap(),
+        );
+        let buffer = buffers.ids().next().unwrap();
+        let mut index = WordIndex::default();
+        let texts = |items: Vec<CompletionItem>| -> Vec<String> {
+            items.into_iter().map(|item| item.text).collect()
+        };
+        let exact = CaseRules::default();
+        let at = Position::origin();
+        assert_eq!(
+            texts(index.words(&buffers, buffer, at, "has", exact)),
+            ["hashing"]
+        );
+        buffers.get_mut(buffer).unwrap().buffer.insert_str(
+            0, "hasty
+",
+        );
+        assert_eq!(
+            texts(index.words(&buffers, buffer, at, "has", exact)),
+            ["hasty", "hashing"]
+        );
+        assert_eq!(index.buffers[&buffer].len(), 4, "one entry per line");
+
+        let infer = CaseRules {
+            ignore: true,
+            smart: true,
+            infer: true,
+        };
+        let words = index.words(&buffers, buffer, at, "has", infer);
+        assert_eq!(texts(words.clone()), ["hasty", "HashMap", "hashing"]);
+        let mut state = CompletionState {
+            case: infer,
+            ..CompletionState::default()
+        };
+        state.open(buffer, at, "has".into(), words, None);
+        state.select(true);
+        assert_eq!(state.select(true).as_deref(), Some("hashmap"));
+        assert!(
+            index.words(&buffers, buffer, at, "Has", infer).len() == 1,
+            "smartcase: an uppercase prefix matches exactly"
+        );
+        assert_eq!(infer.insert("Has", "hashmap"), "hashmap", "smartcase");
+        let infer = CaseRules {
+            smart: false,
+            ..infer
+        };
+        assert_eq!(infer.insert("HA", "hashmap"), "HASHMAP");
+        assert_eq!(infer.insert("Ha", "HASH"), "HaSH");
+        assert_eq!(infer.insert("ha", "HASH"), "hash");
+    }
+}
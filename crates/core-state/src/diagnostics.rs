@@ -0,0 +1,335 @@
+//! Diagnostics store: messages with a severity attached to buffer ranges.
+//!
+//! Producers (a linter, a language server via `core-lsp`) publish the full
+//! set for a buffer at once, as LSP's `publishDiagnostics` does; a publish
+//! replaces whatever the buffer had. The renderer draws a sign for the most
+//! severe diagnostic of each line (unless a placed sign covers it),
+//! underlines the offending spans in the `DiagnosticUnderline*` colors and
+//! shows error and warning counts on the status line. With `virtual_text`
+//! on (`[diagnostics] virtual_text`), the message of each line's most
+//! severe diagnostic follows the line's end in `DiagnosticVirtualText*`.
+//! `]d` / `[d` jump between diagnostics and `:diag` lists them.
+//!
+//! Like signs, ranges are not shifted by edits; producers republish after
+//! their source changes. The runtime repaints in full on a change
+//! (`take_changed`), since signs, underlines and counts can all move.
+
+use crate::BufferId;
+use crate::signs::SignStyle;
+use core_text::{Position, grapheme};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Ordered most severe first, so `min` picks the one to show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    /// Name shown in listings (`:diag`, `:ldiag`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        }
+    }
+
+    /// Sign column glyph.
+    pub fn sign_glyph(self) -> &'static str {
+        match self {
+            Severity::Error => "E",
+            Severity::Warning => "W",
+            Severity::Info => "I",
+            Severity::Hint => "H",
+        }
+    }
+
+    pub fn sign_style(self) -> SignStyle {
+        match self {
+            Severity::Error => SignStyle::Error,
+            Severity::Warning => SignStyle::Warning,
+            Severity::Info | Severity::Hint => SignStyle::Info,
+        }
+    }
+}
+
+/// One diagnostic over `start..end` (byte positions, end exclusive). An
+/// empty range marks the cluster at `start`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub start: Position,
+    pub end: Position,
+    pub message: String,
+}
+
+/// Diagnostics per severity shown on the status line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiagnosticCounts {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl DiagnosticCounts {
+    pub fn is_empty(&self) -> bool {
+        self.errors == 0 && self.warnings == 0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DiagnosticStore {
+    buffers: HashMap<BufferId, Vec<Diagnostic>>,
+    virtual_text: bool,
+    changed: bool,
+}
+
+impl DiagnosticStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the diagnostics of `buffer` (an empty set clears them).
+    pub fn set(&mut self, buffer: BufferId, mut diagnostics: Vec<Diagnostic>) {
+        for d in &mut diagnostics {
+            if (d.end.line, d.end.byte) < (d.start.line, d.start.byte) {
+                std::mem::swap(&mut d.start, &mut d.end);
+            }
+        }
+        diagnostics.sort_by_key(|d| (d.start.line, d.start.byte, d.severity));
+        let before = if diagnostics.is_empty() {
+            self.buffers.remove(&buffer)
+        } else {
+            self.buffers.insert(buffer, diagnostics)
+        };
+        self.changed |= before.as_deref() != self.buffers.get(&buffer).map(Vec::as_slice);
+        tracing::debug!(
+            target: "state.diagnostics",
+            buffer = buffer.0,
+            count = self.get(buffer).len(),
+            "diagnostics_set"
+        );
+    }
+
+    pub fn clear(&mut self, buffer: BufferId) {
+        self.set(buffer, Vec::new());
+    }
+
+    /// Diagnostics of `buffer`, in start order.
+    pub fn get(&self, buffer: BufferId) -> &[Diagnostic] {
+        self.buffers.get(&buffer).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn has_diagnostics(&self, buffer: BufferId) -> bool {
+        self.buffers.contains_key(&buffer)
+    }
+
+    /// Most severe diagnostic touching `line`: the one its sign shows.
+    pub fn line_severity(&self, buffer: BufferId, line: usize) -> Option<Severity> {
+        self.get(buffer)
+            .iter()
+            .filter(|d| d.start.line <= line && line <= d.end.line)
+            .map(|d| d.severity)
+            .min()
+    }
+
+    /// Message shown after the end of `line`: the first line of the most
+    /// severe diagnostic starting there (the first one among equals).
+    pub fn line_message(&self, buffer: BufferId, line: usize) -> Option<(Severity, &str)> {
+        self.get(buffer)
+            .iter()
+            .filter(|d| d.start.line == line)
+            .min_by_key(|d| d.severity)
+            .map(|d| (d.severity, d.message.lines().next().unwrap_or_default()))
+    }
+
+    pub fn virtual_text(&self) -> bool {
+        self.virtual_text
+    }
+
+    pub fn set_virtual_text(&mut self, on: bool) {
+        self.changed |= self.virtual_text != on;
+        self.virtual_text = on;
+    }
+
+    /// Start of the diagnostic after (`forward`) or before `from`, wrapping
+    /// around the buffer; the flag tells whether it wrapped. Diagnostics
+    /// starting at `from` itself are skipped.
+    pub fn next_start(
+        &self,
+        buffer: BufferId,
+        from: Position,
+        forward: bool,
+    ) -> Option<(Position, bool)> {
+        let key = |p: Position| (p.line, p.byte);
+        let all = self.get(buffer);
+        let found = if forward {
+            all.iter().find(|d| key(d.start) > key(from))
+        } else {
+            all.iter().rev().find(|d| key(d.start) < key(from))
+        };
+        match found {
+            Some(d) => Some((d.start, false)),
+            None if forward => all.first().map(|d| (d.start, true)),
+            None => all.last().map(|d| (d.start, true)),
+        }
+    }
+
+    /// Byte ranges of `text` (line `line` without its ending) covered by
+    /// diagnostics, with their severity. Empty ranges widen to the cluster
+    /// they sit on; one at the end of the line covers nothing.
+    pub fn line_spans(
+        &self,
+        buffer: BufferId,
+        line: usize,
+        text: &str,
+    ) -> Vec<(Range<usize>, Severity)> {
+        self.get(buffer)
+            .iter()
+            .filter(|d| d.start.line <= line && line <= d.end.line)
+            .filter_map(|d| {
+                let start = if d.start.line == line {
+                    d.start.byte.min(text.len())
+                } else {
+                    0
+                };
+                let mut end = if d.end.line == line {
+                    d.end.byte.min(text.len())
+                } else {
+                    text.len()
+                };
+                if start == end && d.start == d.end && text.is_char_boundary(start) {
+                    end = grapheme::next_boundary(text, start);
+                }
+                (start < end).then_some((start..end, d.severity))
+            })
+            .collect()
+    }
+
+    pub fn counts(&self, buffer: BufferId) -> DiagnosticCounts {
+        let mut counts = DiagnosticCounts::default();
+        for d in self.get(buffer) {
+            match d.severity {
+                Severity::Error => counts.errors += 1,
+                Severity::Warning => counts.warnings += 1,
+                Severity::Info | Severity::Hint => {}
+            }
+        }
+        counts
+    }
+
+    /// Whether any buffer's diagnostics changed since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+/// Most severe entry of `spans` covering byte `byte`.
+pub fn severity_at(spans: &[(Range<usize>, Severity)], byte: usize) -> Option<Severity> {
+    spans
+        .iter()
+        .filter(|(range, _)| range.contains(&byte))
+        .map(|(_, severity)| *severity)
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diag(severity: Severity, start: (usize, usize), end: (usize, usize)) -> Diagnostic {
+        Diagnostic {
+            severity,
+            start: Position::new(start.0, start.1),
+            end: Position::new(end.0, end.1),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn spans_signs_and_counts() {
+        let (a, b) = (BufferId(1), BufferId(2));
+        let mut store = DiagnosticStore::new();
+        store.set(
+            a,
+            vec![
+                diag(Severity::Warning, (0, 2), (1, 1)),
+                diag(Severity::Error, (1, 0), (1, 3)),
+                diag(Severity::Hint, (2, 1), (2, 1)),
+                diag(Severity::Info, (2, 5), (2, 5)),
+            ],
+        );
+        assert!(store.take_changed());
+        assert_eq!(store.line_severity(a, 1), Some(Severity::Error));
+        assert_eq!(store.line_severity(a, 0), Some(Severity::Warning));
+        assert_eq!(store.line_severity(a, 3), None);
+        assert_eq!(store.line_spans(a, 0, "abcd"), [(2..4, Severity::Warning)]);
+        let line1 = store.line_spans(a, 1, "xyz");
+        assert_eq!(line1, [(0..1, Severity::Warning), (0..3, Severity::Error)]);
+        assert_eq!(severity_at(&line1, 0), Some(Severity::Error));
+        assert_eq!(severity_at(&line1, 3), None);
+        // Empty ranges mark one cluster, none past the line end.
+        assert_eq!(store.line_spans(a, 2, "a界b"), [(1..4, Severity::Hint)]);
+        assert_eq!(
+            store.counts(a),
+            DiagnosticCounts {
+                errors: 1,
+                warnings: 1
+            }
+        );
+        assert!(store.counts(b).is_empty());
+
+        store.set(a, store.get(a).to_vec());
+        assert!(!store.take_changed());
+        store.clear(a);
+        assert!(store.take_changed());
+        assert!(!store.has_diagnostics(a));
+    }
+
+    #[test]
+    fn messages_and_jumps() {
+        let buffer = BufferId(1);
+        let mut store = DiagnosticStore::new();
+        let mut hint = diag(Severity::Hint, (0, 1), (0, 2));
+        hint.message = "unused".into();
+        let mut error = diag(Severity::Error, (0, 4), (0, 5));
+        error.message = "mismatched types\nexpected u8".into();
+        store.set(
+            buffer,
+            vec![hint, error, diag(Severity::Warning, (3, 0), (3, 1))],
+        );
+        assert_eq!(
+            store.line_message(buffer, 0),
+            Some((Severity::Error, "mismatched types"))
+        );
+        assert_eq!(store.line_message(buffer, 1), None);
+
+        let at = |line, byte| Position::new(line, byte);
+        assert_eq!(
+            store.next_start(buffer, at(0, 1), true),
+            Some((at(0, 4), false))
+        );
+        assert_eq!(
+            store.next_start(buffer, at(3, 0), true),
+            Some((at(0, 1), true))
+        );
+        assert_eq!(
+            store.next_start(buffer, at(2, 0), false),
+            Some((at(0, 4), false))
+        );
+        assert_eq!(
+            store.next_start(buffer, at(0, 0), false),
+            Some((at(3, 0), true))
+        );
+        assert_eq!(store.next_start(BufferId(2), at(0, 0), true), None);
+
+        store.take_changed();
+        store.set_virtual_text(true);
+        assert!(store.virtual_text() && store.take_changed());
+    }
+}
@@ -0,0 +1,358 @@
+//! Diff mode (`:diffsplit`, `:diffoff`, `do` / `dp`).
+//!
+//! Two buffers are compared line by line (Myers' O(ND) algorithm, after
+//! trimming the common prefix and suffix). The result is a list of hunks,
+//! each a pair of line ranges that differ: a hunk with lines on both sides
+//! is a change, one with an empty side an addition to the other side. The
+//! shorter side of a hunk gets filler rows after its lines (above the line
+//! following the hunk) so both windows stay aligned row for row.
+//!
+//! `refresh` recomputes the hunks when the text of either buffer changed
+//! since the last call; the runtime calls it after every edit and the
+//! `do` / `dp` handlers after theirs. Closing either buffer ends diff mode.
+
+use crate::BufferId;
+use crate::buffer_manager::BufferManager;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+
+/// Edit distance past which the middle of the buffers is reported as one
+/// hunk instead (bounds the time and the memory the comparison takes).
+const MAX_EDITS: usize = 1000;
+
+/// Lines `a` of the first buffer differ from lines `b` of the second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub a: Range<usize>,
+    pub b: Range<usize>,
+}
+
+impl Hunk {
+    /// Range of side `side` (0 for the first buffer, 1 for the second).
+    pub fn range(&self, side: usize) -> &Range<usize> {
+        if side == 0 { &self.a } else { &self.b }
+    }
+
+    /// Filler rows shown after the lines of side `side`.
+    fn fillers(&self, side: usize) -> usize {
+        self.range(1 - side)
+            .len()
+            .saturating_sub(self.range(side).len())
+    }
+}
+
+/// How a line of a compared buffer differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// Only this buffer has the line (`DiffAdd`).
+    Added,
+    /// Both buffers have different lines here (`DiffChange`).
+    Changed,
+}
+
+#[derive(Debug, Default)]
+pub struct DiffState {
+    buffers: Option<[BufferId; 2]>,
+    hunks: Vec<Hunk>,
+    /// Text hashes the hunks were computed from.
+    sources: Option<[u64; 2]>,
+}
+
+impl DiffState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare buffers `a` and `b` from the next `refresh` on.
+    pub fn start(&mut self, a: BufferId, b: BufferId) {
+        self.buffers = Some([a, b]);
+        self.hunks.clear();
+        self.sources = None;
+    }
+
+    /// Leave diff mode. Returns whether it was on.
+    pub fn stop(&mut self) -> bool {
+        self.hunks.clear();
+        self.sources = None;
+        self.buffers.take().is_some()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.buffers.is_some()
+    }
+
+    pub fn buffers(&self) -> Option<[BufferId; 2]> {
+        self.buffers
+    }
+
+    /// Whether `buffer` is one of the compared buffers.
+    pub fn includes(&self, buffer: BufferId) -> bool {
+        self.side(buffer).is_some()
+    }
+
+    /// The buffer `buffer` is compared with.
+    pub fn other(&self, buffer: BufferId) -> Option<BufferId> {
+        let side = self.side(buffer)?;
+        self.buffers.map(|pair| pair[1 - side])
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    fn side(&self, buffer: BufferId) -> Option<usize> {
+        self.buffers?.iter().position(|b| *b == buffer)
+    }
+
+    /// Recompute the hunks if the text of either buffer changed. Returns
+    /// whether they were recomputed; diff mode ends when a buffer is gone.
+    pub fn refresh(&mut self, buffers: &BufferManager) -> bool {
+        let Some(pair) = self.buffers else {
+            return false;
+        };
+        let (Some(a), Some(b)) = (buffers.get(pair[0]), buffers.get(pair[1])) else {
+            self.stop();
+            return true;
+        };
+        let (a, b) = (lines(&a.buffer), lines(&b.buffer));
+        let sources = [hash(&a), hash(&b)];
+        if self.sources == Some(sources) {
+            return false;
+        }
+        self.hunks = diff_lines(&a, &b);
+        self.sources = Some(sources);
+        tracing::debug!(target: "state.diff", a = pair[0].0, b = pair[1].0, hunks = self.hunks.len(), "diff_refresh");
+        true
+    }
+
+    /// Filler rows drawn above line `line` of `buffer`; `line` may be the
+    /// line count for those after the last line.
+    pub fn fillers_above(&self, buffer: BufferId, line: usize) -> usize {
+        let Some(side) = self.side(buffer) else {
+            return 0;
+        };
+        self.hunks
+            .iter()
+            .filter(|h| h.range(side).end == line)
+            .map(|h| h.fillers(side))
+            .sum()
+    }
+
+    /// How line `line` of `buffer` differs, `None` when it does not.
+    pub fn line_kind(&self, buffer: BufferId, line: usize) -> Option<DiffKind> {
+        let side = self.side(buffer)?;
+        let hunk = self.hunks.iter().find(|h| h.range(side).contains(&line))?;
+        Some(match hunk.range(1 - side).is_empty() {
+            true => DiffKind::Added,
+            false => DiffKind::Changed,
+        })
+    }
+
+    /// The hunk at line `line` of `buffer`: the one holding the line, or
+    /// one whose filler rows sit right above or below it.
+    pub fn hunk_at(&self, buffer: BufferId, line: usize) -> Option<&Hunk> {
+        let side = self.side(buffer)?;
+        self.hunks.iter().find(|h| {
+            let range = h.range(side);
+            range.contains(&line)
+                || (range.is_empty() && (range.start == line || range.start == line + 1))
+        })
+    }
+
+    /// Row of line `line` of `buffer` counting the filler rows above it:
+    /// level lines of the two buffers share a row.
+    pub fn row_of(&self, buffer: BufferId, line: usize) -> usize {
+        let Some(side) = self.side(buffer) else {
+            return line;
+        };
+        let fillers: usize = self
+            .hunks
+            .iter()
+            .filter(|h| h.range(side).end <= line)
+            .map(|h| h.fillers(side))
+            .sum();
+        line + fillers
+    }
+
+    /// Row of the top of a window of `buffer` starting at line `first`,
+    /// with every filler row above `first` shown.
+    pub fn top_row(&self, buffer: BufferId, first: usize) -> usize {
+        self.row_of(buffer, first) - self.fillers_above(buffer, first)
+    }
+
+    /// First of the `line_count` lines of `buffer` at or below row `row`
+    /// (the last line past the end).
+    pub fn line_at_row(&self, buffer: BufferId, row: usize, line_count: usize) -> usize {
+        // `row_of` grows with the line: binary search.
+        let (mut lo, mut hi) = (0, line_count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.row_of(buffer, mid) < row {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo.min(line_count.saturating_sub(1))
+    }
+}
+
+fn lines(buffer: &core_text::Buffer) -> Vec<String> {
+    (0..buffer.line_count())
+        .map(|i| {
+            let line = buffer.line(i).unwrap_or_default();
+            line.trim_end_matches(['\n', '\r']).to_string()
+        })
+        .collect()
+}
+
+fn hash(lines: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    lines.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hunks turning lines `a` into lines `b`, in order.
+pub fn diff_lines<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Hunk> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+    // Pairs of equal lines in the middle, then a sentinel closing it.
+    let mut matches = myers(&a[prefix..a_end], &b[prefix..b_end]).unwrap_or_default();
+    matches.push((a_end - prefix, b_end - prefix));
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (mx, my) in matches {
+        if mx > x || my > y {
+            hunks.push(Hunk {
+                a: prefix + x..prefix + mx,
+                b: prefix + y..prefix + my,
+            });
+        }
+        (x, y) = (mx + 1, my + 1);
+    }
+    hunks
+}
+
+/// Index pairs of the lines a shortest edit script from `a` to `b` keeps,
+/// in order; `None` past `MAX_EDITS` edits.
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<(usize, usize)>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = (a.len() + b.len()).min(MAX_EDITS) as isize;
+    let offset = limit + 1;
+    let mut v = vec![0isize; 2 * limit as usize + 3];
+    // `trace[d]` holds diagonals `-d..=d` as they were before round `d`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=limit {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = |k: isize| v[(offset + k) as usize];
+            let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                at(k + 1)
+            } else {
+                at(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let at = |k: isize| v[(k + d) as usize];
+            let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (at(prev_k), at(prev_k) - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    pairs.reverse();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunks_fillers_and_alignment() {
+        let a = ["a", "b", "c", "d", "e"];
+        let b = ["a", "B", "c", "x", "y", "e", "f"];
+        let hunks = diff_lines(&a, &b);
+        assert_eq!(
+            hunks,
+            [
+                Hunk { a: 1..2, b: 1..2 },
+                Hunk { a: 3..4, b: 3..5 },
+                Hunk { a: 5..5, b: 6..7 },
+            ]
+        );
+        assert!(diff_lines(&a, &a).is_empty());
+        assert_eq!(
+            diff_lines(&[] as &[&str], &["x"]),
+            [Hunk { a: 0..0, b: 0..1 }]
+        );
+
+        let buffer = |lines: &[&str]| core_text::Buffer::from_str("t", &lines.join("\n")).unwrap();
+        let mut buffers = BufferManager::new(buffer(&a));
+        let left = BufferId(1);
+        let right = buffers.open(buffer(&b), None);
+        let mut diff = DiffState::new();
+        diff.start(left, right);
+        assert!(diff.refresh(&buffers));
+        assert!(!diff.refresh(&buffers));
+        assert_eq!(diff.hunks().len(), 3);
+        assert_eq!(diff.other(left), Some(right));
+
+        // `d` of the left faces `x` and `y`: one filler below it, above `e`.
+        assert_eq!(diff.fillers_above(left, 4), 1);
+        assert_eq!(diff.fillers_above(left, 5), 1);
+        assert_eq!(diff.fillers_above(right, 5), 0);
+        assert_eq!(diff.line_kind(left, 1), Some(DiffKind::Changed));
+        assert_eq!(diff.line_kind(right, 6), Some(DiffKind::Added));
+        assert_eq!(diff.line_kind(left, 2), None);
+
+        // `e` sits on row 5 on both sides.
+        assert_eq!(diff.row_of(left, 4), 5);
+        assert_eq!(diff.row_of(right, 5), 5);
+        assert_eq!(diff.top_row(left, 4), 4);
+        assert_eq!(diff.line_at_row(right, diff.top_row(left, 4), 7), 4);
+        assert_eq!(diff.line_at_row(left, diff.row_of(right, 3), 5), 3);
+        assert_eq!(diff.line_at_row(left, 9, 5), 4);
+        assert_eq!(diff.hunk_at(left, 4), Some(&Hunk { a: 5..5, b: 6..7 }));
+        assert_eq!(diff.hunk_at(left, 0), None);
+
+        buffers.close(right, true).unwrap();
+        diff.refresh(&buffers);
+        assert!(!diff.is_active());
+    }
+}
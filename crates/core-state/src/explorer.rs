@@ -0,0 +1,268 @@
+//! Directory listings: the netrw-style file explorer.
+//!
+//! Opening a directory (`:edit`, `:split`, `:Explore` or the command line
+//! argument) fills the buffer with a listing of it instead of text. The
+//! listing is a header line, `../`, then one entry per line, directories
+//! first and marked with a trailing `/`. `BufferMeta::explorer` keeps the
+//! directory, its entries and how they are ordered, so the listing belongs
+//! to its buffer; like the hex view (`binary`) it is read-only, and its
+//! keys (`core_keymap::explorer_specs`) only apply while it is shown.
+//!
+//! Entries are sorted by name, modification time (newest first) or size
+//! (largest first), optionally reversed. Names starting with `.` are hidden
+//! until toggled on.
+
+use crate::EditorState;
+use core_text::Buffer;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Lines above the first entry: the header and `../`.
+pub const EXPLORER_HEADER_LINES: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExplorerSort {
+    #[default]
+    Name,
+    Time,
+    Size,
+}
+
+impl ExplorerSort {
+    /// The order `s` switches to.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Name => Self::Time,
+            Self::Time => Self::Size,
+            Self::Size => Self::Name,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Time => "time",
+            Self::Size => "size",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplorerEntry {
+    pub name: String,
+    pub dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    /// Absolute path of the listed directory.
+    pub dir: PathBuf,
+    entries: Vec<ExplorerEntry>,
+    pub sort: ExplorerSort,
+    pub reverse: bool,
+    pub show_hidden: bool,
+}
+
+impl Explorer {
+    /// Read `dir` with the default ordering.
+    pub fn read(dir: &Path) -> std::io::Result<Self> {
+        let mut explorer = Self {
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            sort: ExplorerSort::default(),
+            reverse: false,
+            show_hidden: false,
+        };
+        explorer.read_dir(dir)?;
+        Ok(explorer)
+    }
+
+    /// List `dir` instead, keeping the ordering and hidden-file settings.
+    pub fn read_dir(&mut self, dir: &Path) -> std::io::Result<()> {
+        let dir = std::fs::canonicalize(dir)?;
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            // Follow symlinks so a link to a directory opens as one.
+            let meta = std::fs::metadata(entry.path()).or_else(|_| entry.metadata())?;
+            entries.push(ExplorerEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                dir: meta.is_dir(),
+                size: meta.len(),
+                modified: meta.modified().ok(),
+            });
+        }
+        tracing::debug!(target: "state.explorer", dir = %dir.display(), entries = entries.len(), "explorer_read");
+        self.dir = dir;
+        self.entries = entries;
+        self.order();
+        Ok(())
+    }
+
+    /// Re-read the listed directory (after creating, renaming or deleting).
+    pub fn reload(&mut self) -> std::io::Result<()> {
+        let dir = self.dir.clone();
+        self.read_dir(&dir)
+    }
+
+    /// Sort the entries, directories first.
+    pub fn order(&mut self) {
+        let (sort, reverse) = (self.sort, self.reverse);
+        self.entries.sort_by(|a, b| {
+            let ord = match sort {
+                ExplorerSort::Name => a.name.cmp(&b.name),
+                ExplorerSort::Time => b.modified.cmp(&a.modified),
+                ExplorerSort::Size => b.size.cmp(&a.size),
+            }
+            .then_with(|| a.name.cmp(&b.name));
+            b.dir
+                .cmp(&a.dir)
+                .then(if reverse { ord.reverse() } else { ord })
+        });
+    }
+
+    /// Entries shown in the listing, in order.
+    pub fn visible(&self) -> impl Iterator<Item = &ExplorerEntry> {
+        self.entries
+            .iter()
+            .filter(|e| self.show_hidden || !e.name.starts_with('.'))
+    }
+
+    /// The buffer text: header, `../`, then the visible entries.
+    pub fn listing(&self) -> String {
+        let mut order = format!("sorted by {}", self.sort.name());
+        if self.reverse {
+            order.push_str(", reversed");
+        }
+        if !self.show_hidden {
+            order.push_str(", hiding dotfiles");
+        }
+        let mut text = format!("\" {} ({order})\n../\n", with_slash(&self.dir));
+        for entry in self.visible() {
+            text.push_str(&entry.name);
+            if entry.dir {
+                text.push('/');
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The entry shown on buffer line `line`.
+    pub fn entry_at(&self, line: usize) -> Option<&ExplorerEntry> {
+        self.visible().nth(line.checked_sub(EXPLORER_HEADER_LINES)?)
+    }
+
+    /// Buffer line of the entry called `name`.
+    pub fn line_of(&self, name: &str) -> Option<usize> {
+        self.visible()
+            .position(|e| e.name == name)
+            .map(|i| i + EXPLORER_HEADER_LINES)
+    }
+
+    /// What `<CR>` on `line` opens: the parent directory on `../`, the
+    /// entry's path on an entry, nothing on the header.
+    pub fn target_at(&self, line: usize) -> Option<PathBuf> {
+        match line {
+            0 => None,
+            1 => Some(self.dir.parent().unwrap_or(&self.dir).to_path_buf()),
+            _ => self.entry_at(line).map(|e| self.dir.join(&e.name)),
+        }
+    }
+}
+
+/// `dir` shown with a trailing separator.
+fn with_slash(dir: &Path) -> String {
+    let mut shown = dir.display().to_string();
+    if !shown.ends_with(std::path::MAIN_SEPARATOR) {
+        shown.push(std::path::MAIN_SEPARATOR);
+    }
+    shown
+}
+
+impl EditorState {
+    /// The listing shown in the active buffer, if it is a directory.
+    pub fn explorer(&self) -> Option<&Explorer> {
+        self.active_meta().explorer.as_ref()
+    }
+
+    /// Show `explorer` in the active buffer, replacing its text, file name
+    /// and history.
+    pub fn load_directory(&mut self, explorer: Explorer) {
+        let name = explorer.dir.display().to_string();
+        let Ok(buffer) = Buffer::from_str(name, &explorer.listing()) else {
+            return;
+        };
+        self.buffers[self.active] = buffer;
+        let meta = self.active_meta_mut();
+        meta.path = Some(explorer.dir.clone());
+        meta.dirty = false;
+        meta.binary = None;
+        meta.hex_view = false;
+        meta.undo = crate::undo::UndoEngine::new();
+        meta.quickfix = None;
+        meta.explorer = Some(explorer);
+    }
+
+    /// Re-read (`reload`) and redraw the active listing, or just redraw it
+    /// after its settings changed. Returns false when the directory could
+    /// not be read.
+    pub fn refresh_explorer(&mut self, reload: bool) -> bool {
+        let Some(mut explorer) = self.active_meta_mut().explorer.take() else {
+            return false;
+        };
+        let read = !reload || explorer.reload().is_ok();
+        explorer.order();
+        self.load_directory(explorer);
+        read
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_puts_directories_first_and_hides_dotfiles() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("b.txt"), "bbbb").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "").unwrap();
+
+        let mut explorer = Explorer::read(dir.path()).unwrap();
+        let lines = |e: &Explorer| -> Vec<String> {
+            e.listing().lines().skip(1).map(str::to_string).collect()
+        };
+        assert_eq!(lines(&explorer), ["../", "sub/", "a.txt", "b.txt"]);
+        assert_eq!(explorer.entry_at(3).unwrap().name, "a.txt");
+        assert_eq!(explorer.line_of("b.txt"), Some(4));
+        assert_eq!(explorer.target_at(1).as_deref(), explorer.dir.parent());
+        assert_eq!(explorer.target_at(0), None);
+
+        explorer.sort = ExplorerSort::Size;
+        explorer.show_hidden = true;
+        explorer.order();
+        assert_eq!(
+            lines(&explorer),
+            ["../", "sub/", "b.txt", "a.txt", ".hidden"]
+        );
+        explorer.reverse = true;
+        explorer.order();
+        assert_eq!(
+            lines(&explorer),
+            ["../", "sub/", ".hidden", "a.txt", "b.txt"]
+        );
+        assert!(explorer.listing().starts_with('"'));
+        assert!(
+            explorer
+                .listing()
+                .lines()
+                .next()
+                .unwrap()
+                .contains("size, reversed")
+        );
+    }
+}
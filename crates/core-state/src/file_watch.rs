@@ -0,0 +1,161 @@
+//! Files changed on disk from outside the editor.
+//!
+//! The runtime watches the file of every buffer and the directory of every
+//! listing (`watched_paths`) with a `core_events::FileWatchSource`. Each
+//! watched file has a `DiskStamp`: its modification time and size as the
+//! editor last read or wrote it. The editor's own writes `record` the new
+//! stamp as they finish, so when the watcher reports them `changed` finds
+//! the file as it was left and nothing needs reloading or a warning.
+
+use crate::{BufferId, EditorState, LineEnding};
+use core_text::Buffer;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A file's modification time and size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl DiskStamp {
+    /// `path` as it is on disk now; `None` when it does not exist.
+    pub fn read(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+        })
+    }
+}
+
+/// Stamps of the watched files.
+#[derive(Debug, Default)]
+pub struct FileWatch {
+    stamps: HashMap<PathBuf, Option<DiskStamp>>,
+}
+
+impl FileWatch {
+    /// The editor just read or wrote `path`.
+    pub fn record(&mut self, path: &Path) {
+        self.stamps
+            .insert(path.to_path_buf(), DiskStamp::read(path));
+    }
+
+    /// Watch exactly `files`. One not watched yet is stamped as it is now:
+    /// its buffer was just read.
+    pub fn track(&mut self, files: &BTreeSet<PathBuf>) {
+        self.stamps.retain(|path, _| files.contains(path));
+        for file in files {
+            if !self.stamps.contains_key(file) {
+                self.stamps.insert(file.clone(), DiskStamp::read(file));
+            }
+        }
+    }
+
+    /// Whether `path` is not as the editor left it, taking the new stamp
+    /// if so.
+    pub fn changed(&mut self, path: &Path) -> bool {
+        let now = DiskStamp::read(path);
+        match self.stamps.get_mut(path) {
+            Some(stamp) if *stamp == now => false,
+            Some(stamp) => {
+                *stamp = now;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl EditorState {
+    /// What the runtime watches: the files of the buffers, and the
+    /// directories of listings.
+    pub fn watched_paths(&self) -> (BTreeSet<PathBuf>, BTreeSet<PathBuf>) {
+        let mut files = BTreeSet::new();
+        let mut dirs = BTreeSet::new();
+        for entry in self.buffers.iter() {
+            match (&entry.meta.path, &entry.meta.explorer) {
+                (Some(_), Some(explorer)) => dirs.insert(explorer.dir.clone()),
+                (Some(path), None) => files.insert(path.clone()),
+                (None, _) => false,
+            };
+        }
+        (files, dirs)
+    }
+
+    /// Replace the text of buffer `id` with its file read again
+    /// (`'autoread'`). It is unmodified after, and its history starts over.
+    pub fn reload_buffer(
+        &mut self,
+        id: BufferId,
+        buffer: Buffer,
+        line_ending: LineEnding,
+        had_trailing_newline: bool,
+    ) -> bool {
+        let Some(entry) = self.buffers.get_mut(id) else {
+            return false;
+        };
+        entry.buffer = buffer;
+        let meta = &mut entry.meta;
+        meta.dirty = false;
+        meta.original_line_ending = line_ending;
+        meta.had_trailing_newline = had_trailing_newline;
+        meta.undo = crate::undo::UndoEngine::new();
+        tracing::debug!(target: "state.watch", ?id, "buffer_reloaded");
+        true
+    }
+
+    /// Re-read the listing shown in buffer `id`; false when it is not one
+    /// or the directory cannot be read.
+    pub fn reload_listing(&mut self, id: BufferId) -> bool {
+        let Some(entry) = self.buffers.get_mut(id) else {
+            return false;
+        };
+        let Some(explorer) = entry.meta.explorer.as_mut() else {
+            return false;
+        };
+        if explorer.reload().is_err() {
+            return false;
+        }
+        match Buffer::from_str(entry.buffer.name.clone(), &explorer.listing()) {
+            Ok(buffer) => {
+                entry.buffer = buffer;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_writes_are_not_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "one\n").unwrap();
+        let mut watch = FileWatch::default();
+        watch.track(&BTreeSet::from([path.clone()]));
+        assert!(!watch.changed(&path));
+
+        std::fs::write(&path, "written\n").unwrap();
+        watch.record(&path);
+        assert!(!watch.changed(&path));
+
+        std::fs::write(&path, "from outside\n").unwrap();
+        assert!(watch.changed(&path));
+        assert!(!watch.changed(&path), "reported once");
+        std::fs::remove_file(&path).unwrap();
+        assert!(watch.changed(&path));
+
+        // Untracked files are never changed.
+        watch.track(&BTreeSet::new());
+        std::fs::write(&path, "back\n").unwrap();
+        assert!(!watch.changed(&path));
+    }
+}
@@ -0,0 +1,343 @@
+//! Git branch and dirty state of the active file's repository.
+//!
+//! Asking git is a subprocess, so the runtime never does it inline: it asks
+//! `GitState::probe` which directory (if any) needs a fresh look, runs the
+//! probe as a one-shot event source, and hands the answer back through
+//! `GitState::apply`. A probe is due when the active file lives in another
+//! directory than the last one probed, or after `request_refresh` (a write,
+//! a window focus change). Answers for a directory that is no longer current
+//! are dropped, so a slow probe cannot overwrite a newer one.
+//!
+//! `:blame` works the same way: while it is on, `blame_due` names the file
+//! to run `git blame` on when the active buffer changed or after a refresh
+//! (so a write re-blames), and `apply_blame` parses the porcelain output.
+//! The renderer shows the cursor line's `BlameLine` after its end.
+
+use crate::BufferId;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// What the status line shows for a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitStatus {
+    /// Branch name, or the abbreviated commit on a detached HEAD.
+    pub branch: String,
+    /// Tracked files differ from HEAD (worktree or index).
+    pub dirty: bool,
+}
+
+/// Who last changed a line, from `git blame --porcelain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    /// Author time, seconds since the epoch.
+    pub time: i64,
+    /// Author time zone offset, in seconds east of UTC.
+    pub tz: i32,
+    pub summary: String,
+}
+
+impl BlameLine {
+    /// Lines changed in the worktree carry the all-zero commit.
+    pub fn is_committed(&self) -> bool {
+        !self.commit.bytes().all(|b| b == b'0')
+    }
+
+    /// `abbrev author, date • summary`, the end-of-line annotation.
+    pub fn annotation(&self) -> String {
+        if !self.is_committed() {
+            return "Not committed yet".to_string();
+        }
+        let commit: String = self.commit.chars().take(8).collect();
+        let (y, m, d) = civil_date(self.time + i64::from(self.tz));
+        format!(
+            "{commit} {}, {y:04}-{m:02}-{d:02} • {}",
+            self.author, self.summary
+        )
+    }
+}
+
+/// `(year, month, day)` of a time in seconds since the epoch.
+fn civil_date(secs: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's `civil_from_days`.
+    let z = secs.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Blame lines by line index from `git blame --porcelain` output. Each
+/// commit's details follow only its first header.
+pub fn parse_blame(out: &str) -> Vec<BlameLine> {
+    let mut commits: HashMap<&str, BlameLine> = HashMap::new();
+    let mut lines: Vec<Option<BlameLine>> = Vec::new();
+    let mut current: Option<(&str, usize)> = None;
+    for line in out.lines() {
+        if line.starts_with('\t') {
+            if let Some((commit, at)) = current.take()
+                && let Some(blame) = commits.get(commit)
+            {
+                if lines.len() <= at {
+                    lines.resize(at + 1, None);
+                }
+                lines[at] = Some(blame.clone());
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        match current {
+            Some((commit, _)) => {
+                let Some(entry) = commits.get_mut(commit) else {
+                    continue;
+                };
+                match key {
+                    "author" => entry.author = value.to_string(),
+                    "author-time" => entry.time = value.parse().unwrap_or(0),
+                    "author-tz" => entry.tz = parse_tz(value),
+                    "summary" => entry.summary = value.to_string(),
+                    _ => {}
+                }
+            }
+            None => {
+                let mut fields = value.split(' ');
+                let Some(at) = fields.nth(1).and_then(|n| n.parse::<usize>().ok()) else {
+                    continue;
+                };
+                commits.entry(key).or_insert_with(|| BlameLine {
+                    commit: key.to_string(),
+                    author: String::new(),
+                    time: 0,
+                    tz: 0,
+                    summary: String::new(),
+                });
+                current = Some((key, at.saturating_sub(1)));
+            }
+        }
+    }
+    lines.into_iter().flatten().collect()
+}
+
+/// `+0130` -> 5400.
+fn parse_tz(tz: &str) -> i32 {
+    let (sign, digits) = match tz.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, tz.trim_start_matches('+')),
+    };
+    let n: i32 = digits.parse().unwrap_or(0);
+    sign * ((n / 100) * 3600 + (n % 100) * 60)
+}
+
+#[derive(Debug, Default)]
+struct Blame {
+    on: bool,
+    /// Buffer and file blamed last.
+    target: Option<(BufferId, PathBuf)>,
+    lines: Vec<BlameLine>,
+    refresh: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GitState {
+    /// Status of the last probed directory; `None` outside a repository.
+    pub status: Option<GitStatus>,
+    probed: Option<PathBuf>,
+    refresh: bool,
+    blame: Blame,
+}
+
+impl GitState {
+    /// Re-probe the current directory even if it did not change, and
+    /// re-blame the current file.
+    pub fn request_refresh(&mut self) {
+        self.refresh = true;
+        self.blame.refresh = true;
+    }
+
+    /// Directory to probe for the active file `dir`, if one is due. The
+    /// directory is recorded as current.
+    pub fn probe(&mut self, dir: &Path) -> Option<PathBuf> {
+        if !self.refresh && self.probed.as_deref() == Some(dir) {
+            return None;
+        }
+        self.refresh = false;
+        if self.probed.as_deref() != Some(dir) {
+            // Do not show the old repository's branch while the new one is
+            // being looked up.
+            self.status = None;
+        }
+        self.probed = Some(dir.to_path_buf());
+        Some(dir.to_path_buf())
+    }
+
+    /// Store a probe answer for `dir`. Returns whether the shown status
+    /// changed; answers for a stale directory are ignored.
+    pub fn apply(&mut self, dir: &Path, status: Option<GitStatus>) -> bool {
+        if self.probed.as_deref() != Some(dir) || self.status == status {
+            return false;
+        }
+        self.status = status;
+        true
+    }
+
+    /// `:blame`: turn the annotations on or off. Returns whether they are
+    /// now on.
+    pub fn toggle_blame(&mut self) -> bool {
+        self.blame = Blame {
+            on: !self.blame.on,
+            ..Blame::default()
+        };
+        self.blame.on
+    }
+
+    pub fn blame_on(&self) -> bool {
+        self.blame.on
+    }
+
+    /// File to blame for `buffer` (open on `path`), if a run is due. The
+    /// pair is recorded as current.
+    pub fn blame_due(&mut self, buffer: BufferId, path: &Path) -> Option<PathBuf> {
+        let blame = &mut self.blame;
+        let current = blame
+            .target
+            .as_ref()
+            .is_some_and(|(b, p)| *b == buffer && p == path);
+        if !blame.on || (current && !blame.refresh) {
+            return None;
+        }
+        blame.refresh = false;
+        if !current {
+            blame.lines.clear();
+        }
+        blame.target = Some((buffer, path.to_path_buf()));
+        Some(path.to_path_buf())
+    }
+
+    /// Store `git blame --porcelain` output for `path`. Returns whether the
+    /// shown annotations changed; output for a stale file is ignored.
+    pub fn apply_blame(&mut self, path: &Path, out: &str) -> bool {
+        let blame = &mut self.blame;
+        if !blame.on || blame.target.as_ref().is_none_or(|(_, p)| p != path) {
+            return false;
+        }
+        let lines = parse_blame(out);
+        if lines == blame.lines {
+            return false;
+        }
+        blame.lines = lines;
+        true
+    }
+
+    /// `git blame` of `path` failed: turn `:blame` off if it was for the
+    /// current file. Returns whether it was.
+    pub fn fail_blame(&mut self, path: &Path) -> bool {
+        if !self.blame.on || self.blame.target.as_ref().is_none_or(|(_, p)| p != path) {
+            return false;
+        }
+        self.blame = Blame::default();
+        true
+    }
+
+    /// Annotation of `line` of `buffer`, while `:blame` is on.
+    pub fn blame_line(&self, buffer: BufferId, line: usize) -> Option<&BlameLine> {
+        let blame = &self.blame;
+        match &blame.target {
+            Some((b, _)) if blame.on && *b == buffer => blame.lines.get(line),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn main_branch(dirty: bool) -> Option<GitStatus> {
+        Some(GitStatus {
+            branch: "main".into(),
+            dirty,
+        })
+    }
+
+    #[test]
+    fn probes_on_directory_change_or_refresh() {
+        let mut git = GitState::default();
+        let repo = Path::new("/src/repo");
+        assert_eq!(git.probe(repo).as_deref(), Some(repo));
+        assert_eq!(git.probe(repo), None);
+        assert!(git.apply(repo, main_branch(false)));
+        assert!(!git.apply(repo, main_branch(false)));
+
+        git.request_refresh();
+        assert_eq!(git.probe(repo).as_deref(), Some(repo));
+        // A refresh of the same directory keeps the branch on screen.
+        assert_eq!(git.status, main_branch(false));
+        assert!(git.apply(repo, main_branch(true)));
+
+        let other = Path::new("/tmp");
+        assert!(git.probe(other).is_some());
+        assert_eq!(git.status, None);
+        // The old directory's answer arrives late and is dropped.
+        assert!(!git.apply(repo, main_branch(false)));
+        assert_eq!(git.status, None);
+    }
+
+    const PORCELAIN: &str = "\
+1f2e3d4c5b6a79881f2e3d4c5b6a79881f2e3d4c 1 1 1
+author Ann Example
+author-mail <ann@example.com>
+author-time 1700000000
+author-tz +0100
+committer Ann Example
+summary Add the parser
+filename src/lib.rs
+\tfn main() {
+0000000000000000000000000000000000000000 2 2 1
+author Not Committed Yet
+author-time 1700000100
+author-tz +0000
+summary Version of src/lib.rs from src/lib.rs
+filename src/lib.rs
+\t    todo!()
+1f2e3d4c5b6a79881f2e3d4c5b6a79881f2e3d4c 3 3
+\t}
+";
+
+    #[test]
+    fn blame_follows_the_buffer_and_parses_porcelain() {
+        let mut git = GitState::default();
+        let file = Path::new("/src/repo/src/lib.rs");
+        let buffer = BufferId(1);
+        assert_eq!(git.blame_due(buffer, file), None);
+        assert!(git.toggle_blame());
+        assert_eq!(git.blame_due(buffer, file).as_deref(), Some(file));
+        assert_eq!(git.blame_due(buffer, file), None);
+        assert!(!git.apply_blame(Path::new("/other.rs"), PORCELAIN));
+        assert!(git.apply_blame(file, PORCELAIN));
+
+        let first = git.blame_line(buffer, 0).unwrap();
+        assert_eq!(
+            first.annotation(),
+            "1f2e3d4c Ann Example, 2023-11-14 • Add the parser"
+        );
+        assert_eq!(
+            git.blame_line(buffer, 1).unwrap().annotation(),
+            "Not committed yet"
+        );
+        assert_eq!(git.blame_line(buffer, 2), Some(first));
+        assert_eq!(git.blame_line(BufferId(2), 0), None);
+
+        git.request_refresh();
+        assert!(git.blame_due(buffer, file).is_some());
+        assert!(!git.toggle_blame());
+        assert_eq!(git.blame_line(buffer, 0), None);
+    }
+}
@@ -0,0 +1,222 @@
+//! `:grep` / `:grepadd` and `:lgrep` / `:lgrepadd` searches.
+//!
+//! Like `shell`, the dispatcher only queues a `GrepRequest`; the runtime
+//! runs it as a streaming `core_events::GrepSource` and hands each batch of
+//! output lines to `grep_items`, then the exit status to `finish_grep`.
+//! Only the latest search is live: a new one supersedes a running one and
+//! the old one's lines are dropped. The items go to the list named by the
+//! search's `GrepTarget`; `core_actions` adds them there, since location
+//! lists belong to windows, and stores the status line summary here.
+
+use crate::EditorState;
+use crate::quickfix::{QuickfixItem, parse_grep_line};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepRequest {
+    pub id: u64,
+    pub command: String,
+}
+
+/// The list a search fills.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrepTarget {
+    Quickfix,
+    /// The location list of window `window` (a `core_model::ViewId`) of
+    /// tab page `tab`.
+    Location {
+        tab: usize,
+        window: usize,
+    },
+}
+
+/// The search whose output is being collected.
+#[derive(Debug, Clone)]
+struct GrepJob {
+    id: u64,
+    pattern: String,
+    target: GrepTarget,
+    /// Output has a column after the line number (`rg --vimgrep`).
+    column: bool,
+    /// Items of the list before this search (`:grepadd` keeps them).
+    first: usize,
+    /// Matches found so far.
+    found: usize,
+    /// Jump to the first match once it arrives.
+    jump: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GrepState {
+    next_id: u64,
+    pending: Option<GrepRequest>,
+    running: Option<GrepJob>,
+    /// Status line text describing the last search's list.
+    summary: Option<String>,
+}
+
+impl GrepState {
+    pub fn take(&mut self) -> Option<GrepRequest> {
+        self.pending.take()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Describe the list the last search filled (`QuickfixList::len` and
+    /// `file_count`): `grep: 3 matches in 2 files`, with `…` while the
+    /// search runs.
+    pub fn summarize(&mut self, matches: usize, files: usize) {
+        let plural = |n: usize, word: &str| match n {
+            1 => format!("1 {word}"),
+            n if word.ends_with("ch") => format!("{n} {word}es"),
+            n => format!("{n} {word}s"),
+        };
+        let mut summary = format!(
+            "grep: {} in {}",
+            plural(matches, "match"),
+            plural(files, "file")
+        );
+        if self.is_running() {
+            summary.push('…');
+        }
+        self.summary = Some(summary);
+    }
+
+    /// Status line summary of the last search's results.
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+}
+
+impl EditorState {
+    /// Queue `command` searching for `pattern` into `target`, whose list
+    /// has `first` items, superseding a running search.
+    pub fn start_grep(
+        &mut self,
+        command: String,
+        pattern: &str,
+        column: bool,
+        target: GrepTarget,
+        first: usize,
+        jump: bool,
+    ) -> u64 {
+        let grep = &mut self.grep;
+        grep.next_id += 1;
+        let id = grep.next_id;
+        tracing::debug!(target: "runtime.grep", id, command = %command, ?target, first, jump, "grep_request_queued");
+        grep.pending = Some(GrepRequest { id, command });
+        grep.running = Some(GrepJob {
+            id,
+            pattern: pattern.to_string(),
+            target,
+            column,
+            first,
+            found: 0,
+            jump,
+        });
+        id
+    }
+
+    /// The matches among `lines` of search `id` and the list they belong
+    /// to; `None` when the search is no longer live.
+    pub fn grep_items(
+        &mut self,
+        id: u64,
+        lines: &[String],
+    ) -> Option<(GrepTarget, Vec<QuickfixItem>)> {
+        let job = self.grep.running.as_mut().filter(|job| job.id == id)?;
+        let items: Vec<_> = lines
+            .iter()
+            .filter_map(|line| parse_grep_line(line, job.column))
+            .collect();
+        job.found += items.len();
+        Some((job.target, items))
+    }
+
+    /// The list item to jump to, once: the first match of a live search
+    /// that asked for the jump.
+    pub fn take_grep_jump(&mut self) -> Option<(GrepTarget, usize)> {
+        let job = self.grep.running.as_mut()?;
+        if !job.jump || job.found == 0 {
+            return None;
+        }
+        job.jump = false;
+        Some((job.target, job.first))
+    }
+
+    /// End search `id`: report a failure, or E480 when it found nothing.
+    pub fn finish_grep(
+        &mut self,
+        id: u64,
+        status: Option<i32>,
+        stderr: &str,
+        error: Option<&str>,
+    ) -> bool {
+        let Some(job) = self.grep.running.take_if(|job| job.id == id) else {
+            return false;
+        };
+        // grep and rg exit with 1 when nothing matched.
+        let msg = match (error, status) {
+            (Some(error), _) => Some(format!("grep: {error}")),
+            (None, Some(0 | 1)) if job.found == 0 => {
+                Some(format!("E480: No match: {}", job.pattern))
+            }
+            (None, Some(0 | 1)) => None,
+            (None, status) => Some(
+                stderr
+                    .lines()
+                    .find(|l| !l.trim().is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| match status {
+                        Some(code) => format!("shell returned {code}"),
+                        None => "grep: terminated".to_string(),
+                    }),
+            ),
+        };
+        tracing::debug!(target: "runtime.grep", id, found = job.found, ?status, "grep_done");
+        if let Some(msg) = msg {
+            self.set_ephemeral(msg, Duration::from_secs(3));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_the_live_search_and_jumps_once() {
+        let mut state = EditorState::new(core_text::Buffer::from_str("t", "").unwrap());
+        let target = GrepTarget::Quickfix;
+        let old = state.start_grep("rg --vimgrep x".into(), "x", true, target, 0, true);
+        let id = state.start_grep("rg --vimgrep y".into(), "y", true, target, 0, true);
+        assert_eq!(state.grep.take().map(|r| r.id), Some(id));
+        assert_eq!(state.grep_items(old, &["a:1:1:x".into()]), None);
+        assert_eq!(state.take_grep_jump(), None);
+
+        let lines = ["a:1:2:y".into(), "noise".into(), "b:4:1:y".into()];
+        let (to, items) = state.grep_items(id, &lines).unwrap();
+        assert_eq!((to, items.len()), (target, 2));
+        state.quickfix.items.extend(items);
+        assert_eq!(state.take_grep_jump(), Some((target, 0)));
+        assert_eq!(state.take_grep_jump(), None);
+        state
+            .grep
+            .summarize(state.quickfix.len(), state.quickfix.file_count());
+        assert_eq!(state.grep.summary(), Some("grep: 2 matches in 2 files…"));
+        assert!(state.finish_grep(id, Some(0), "", None));
+        state
+            .grep
+            .summarize(state.quickfix.len(), state.quickfix.file_count());
+        assert_eq!(state.grep.summary(), Some("grep: 2 matches in 2 files"));
+
+        // Nothing found is E480.
+        let id = state.start_grep("rg --vimgrep z".into(), "z", true, target, 2, true);
+        assert!(state.finish_grep(id, Some(1), "", None));
+        let msg = state.ephemeral_status.as_ref().unwrap();
+        assert_eq!(msg.text, "E480: No match: z");
+    }
+}
@@ -0,0 +1,58 @@
+//! Syntax highlight spans per buffer line.
+//!
+//! `core-syntax` owns the parsers and writes the spans here; the renderer
+//! only reads them, so render paths stay free of parser state. Spans are
+//! byte ranges within a line (line ending excluded), sorted and disjoint.
+//! `class` indexes `core_syntax::HighlightClass`.
+
+use crate::BufferId;
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub start: usize,
+    pub end: usize,
+    pub class: u16,
+}
+
+#[derive(Debug, Default)]
+pub struct Highlights {
+    buffers: HashMap<BufferId, Vec<Vec<HighlightSpan>>>,
+}
+
+impl Highlights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spans of `line` in `buffer` (empty when unknown or unhighlighted).
+    pub fn line(&self, buffer: BufferId, line: usize) -> &[HighlightSpan] {
+        self.buffers
+            .get(&buffer)
+            .and_then(|lines| lines.get(line))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn has(&self, buffer: BufferId) -> bool {
+        self.buffers.contains_key(&buffer)
+    }
+
+    /// Replace every line of `buffer`.
+    pub fn set(&mut self, buffer: BufferId, lines: Vec<Vec<HighlightSpan>>) {
+        self.buffers.insert(buffer, lines);
+    }
+
+    /// Replace the lines `old` of `buffer` with `new` (which may differ in
+    /// length when the edit added or removed lines).
+    pub fn splice(&mut self, buffer: BufferId, old: Range<usize>, new: Vec<Vec<HighlightSpan>>) {
+        let lines = self.buffers.entry(buffer).or_default();
+        let end = old.end.min(lines.len());
+        let start = old.start.min(end);
+        lines.splice(start..end, new);
+    }
+
+    pub fn remove(&mut self, buffer: BufferId) {
+        self.buffers.remove(&buffer);
+    }
+}
@@ -0,0 +1,173 @@
+//! Hover documentation shown by `K`.
+//!
+//! `K` on a buffer open on a language server with a hover provider
+//! (`attach`, recorded by `core-lsp`) queues a `HoverRequest`; `core-lsp`
+//! sends it as `textDocument/hover` on its next sync and hands the answer,
+//! already turned into plain lines, to `show`. Every request and every
+//! `close` starts a new generation, so an answer arriving after the cursor
+//! moved on is dropped. The renderer draws the shown hover in a popup above
+//! the cursor at most `HOVER_ROWS` lines tall; `scroll` moves through
+//! longer content.
+
+use crate::BufferId;
+use core_text::Position;
+use std::collections::HashSet;
+
+/// Most lines a hover popup shows at once.
+pub const HOVER_ROWS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HoverRequest {
+    pub buffer: BufferId,
+    pub position: Position,
+    pub generation: u64,
+}
+
+/// A hover on screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    pub buffer: BufferId,
+    /// Cursor position the documentation is for; the popup sits above it.
+    pub position: Position,
+    pub lines: Vec<String>,
+    /// First line shown.
+    pub scroll: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct HoverState {
+    active: Option<Hover>,
+    request: Option<HoverRequest>,
+    /// The request an answer must carry to be shown.
+    pending: Option<HoverRequest>,
+    generation: u64,
+    /// Buffers open on a server with a hover provider.
+    servers: HashSet<BufferId>,
+    changed: bool,
+}
+
+impl HoverState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn active(&self) -> Option<&Hover> {
+        self.active.as_ref()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Ask the buffer's server about `position`, closing what is shown.
+    pub fn request(&mut self, buffer: BufferId, position: Position) {
+        self.close();
+        let request = HoverRequest {
+            buffer,
+            position,
+            generation: self.generation,
+        };
+        self.request = Some(request);
+        self.pending = Some(request);
+    }
+
+    pub fn take_request(&mut self) -> Option<HoverRequest> {
+        self.request.take()
+    }
+
+    /// Show the answer to request `generation`. Returns false when a newer
+    /// request or a `close` superseded it; empty `lines` show nothing.
+    pub fn show(&mut self, generation: u64, lines: Vec<String>) -> bool {
+        let Some(request) = self.pending.filter(|p| p.generation == generation) else {
+            return false;
+        };
+        self.pending = None;
+        if !lines.is_empty() {
+            self.active = Some(Hover {
+                buffer: request.buffer,
+                position: request.position,
+                lines,
+                scroll: 0,
+            });
+        }
+        self.changed = true;
+        true
+    }
+
+    /// Close the hover and drop any answer still on its way. Returns
+    /// whether one was shown.
+    pub fn close(&mut self) -> bool {
+        self.generation += 1;
+        self.request = None;
+        self.pending = None;
+        let was_open = self.active.take().is_some();
+        self.changed |= was_open;
+        was_open
+    }
+
+    /// Scroll the shown hover by `delta` lines, keeping the last page
+    /// full. Returns whether a hover is shown.
+    pub fn scroll(&mut self, delta: isize) -> bool {
+        let Some(hover) = self.active.as_mut() else {
+            return false;
+        };
+        let last = hover.lines.len().saturating_sub(HOVER_ROWS);
+        let scroll = hover.scroll.saturating_add_signed(delta).min(last);
+        if scroll != hover.scroll {
+            hover.scroll = scroll;
+            self.changed = true;
+        }
+        true
+    }
+
+    pub fn attach(&mut self, buffer: BufferId) {
+        self.servers.insert(buffer);
+    }
+
+    pub fn detach(&mut self, buffer: BufferId) {
+        self.servers.remove(&buffer);
+    }
+
+    pub fn is_attached(&self, buffer: BufferId) -> bool {
+        self.servers.contains(&buffer)
+    }
+
+    /// Whether the hover changed since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_answers_are_dropped_and_scrolling_stops_at_the_last_page() {
+        let mut hover = HoverState::new();
+        let buffer = BufferId(1);
+        hover.request(buffer, Position::new(3, 2));
+        let first = hover.take_request().unwrap();
+        hover.request(buffer, Position::new(4, 0));
+        let second = hover.take_request().unwrap();
+        assert!(!hover.show(first.generation, vec!["old".into()]));
+        let lines: Vec<String> = (0..HOVER_ROWS + 5).map(|i| i.to_string()).collect();
+        assert!(hover.show(second.generation, lines));
+        assert_eq!(hover.active().unwrap().position, Position::new(4, 0));
+        assert!(hover.take_changed());
+
+        assert!(hover.scroll(100));
+        assert_eq!(hover.active().unwrap().scroll, 5);
+        hover.scroll(-3);
+        assert_eq!(hover.active().unwrap().scroll, 2);
+        assert!(hover.close());
+        assert!(!hover.scroll(1));
+
+        // An answer still on its way when the hover is closed is dropped.
+        hover.request(buffer, Position::origin());
+        let request = hover.take_request().unwrap();
+        hover.close();
+        assert!(!hover.show(request.generation, vec!["late".into()]));
+        assert!(!hover.is_active());
+    }
+}
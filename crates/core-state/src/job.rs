@@ -0,0 +1,240 @@
+//! Background jobs: `:make` and `:job`.
+//!
+//! Like `grep`, the dispatcher only queues a `JobRequest`; the runtime runs
+//! it as a streaming `core_events::JobSource`, hands each batch of output
+//! lines to `job_output` and the exit status to `finish_job`. Several jobs
+//! may run at once. A job's `JobSink` says where its output goes: `:make`
+//! reads it with `'errorformat'` into the quickfix list, `:job` appends it
+//! to an output buffer. `cancel_job` ends a job early; the runtime kills
+//! the process of every id `take_cancelled` returns, and output still
+//! arriving for it is dropped.
+
+use crate::quickfix::parse_errorformat;
+use crate::{BufferId, EditorState};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRequest {
+    pub id: u64,
+    pub command: String,
+}
+
+/// Where a job's output goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobSink {
+    /// `:make`: quickfix items; `jump` opens the first once the job ends.
+    Quickfix { jump: bool },
+    /// `:job`: appended to the buffer.
+    Buffer(BufferId),
+}
+
+#[derive(Debug, Clone)]
+struct Job {
+    id: u64,
+    command: String,
+    sink: JobSink,
+    /// `'errorformat'` when the job started.
+    errorformat: String,
+    /// Quickfix items added so far.
+    items: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct JobState {
+    next_id: u64,
+    pending: Vec<JobRequest>,
+    running: Vec<Job>,
+    cancelled: Vec<u64>,
+    /// Status line text: the latest job's command and how it ended.
+    summary: Option<String>,
+}
+
+impl JobState {
+    pub fn take(&mut self) -> Vec<JobRequest> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Jobs whose process should be killed.
+    pub fn take_cancelled(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.cancelled)
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// Id and command of the running jobs, oldest first.
+    pub fn running(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.running
+            .iter()
+            .map(|job| (job.id, job.command.as_str()))
+    }
+
+    /// Status line summary: `make…` while the latest job runs, then
+    /// `make: exit 2, 3 errors`.
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+}
+
+impl EditorState {
+    /// Queue `command` with its output going to `sink`. A new `:make`
+    /// cancels one still filling the quickfix list.
+    pub fn start_job(&mut self, command: String, sink: JobSink) -> u64 {
+        if matches!(sink, JobSink::Quickfix { .. }) {
+            let old: Vec<u64> = self
+                .jobs
+                .running
+                .iter()
+                .filter(|job| matches!(job.sink, JobSink::Quickfix { .. }))
+                .map(|job| job.id)
+                .collect();
+            for id in old {
+                self.jobs.running.retain(|job| job.id != id);
+                self.jobs.cancelled.push(id);
+            }
+        }
+        let jobs = &mut self.jobs;
+        jobs.next_id += 1;
+        let id = jobs.next_id;
+        tracing::debug!(target: "runtime.job", id, command = %command, ?sink, "job_request_queued");
+        jobs.summary = Some(format!("{command}…"));
+        jobs.pending.push(JobRequest {
+            id,
+            command: command.clone(),
+        });
+        jobs.running.push(Job {
+            id,
+            command,
+            sink,
+            errorformat: self.options.get_string("errorformat").to_string(),
+            items: 0,
+        });
+        id
+    }
+
+    /// Add `lines` of job `id` to its sink; `None` when the job is no
+    /// longer live.
+    pub fn job_output(&mut self, id: u64, lines: &[String]) -> Option<JobSink> {
+        let job = self.jobs.running.iter_mut().find(|job| job.id == id)?;
+        match job.sink {
+            JobSink::Quickfix { .. } => {
+                let items = lines
+                    .iter()
+                    .filter_map(|line| parse_errorformat(line, &job.errorformat));
+                let before = self.quickfix.len();
+                self.quickfix.items.extend(items);
+                job.items += self.quickfix.len() - before;
+            }
+            JobSink::Buffer(buffer) => {
+                if let Some(entry) = self.buffers.get_mut(buffer)
+                    && !lines.is_empty()
+                {
+                    let mut text = lines.join("\n");
+                    text.push('\n');
+                    let end = entry.buffer.len_bytes();
+                    entry.buffer.insert_str(end, &text);
+                    // Output is not an edit to undo.
+                    entry.meta.undo = crate::undo::UndoEngine::new();
+                }
+            }
+        }
+        Some(job.sink)
+    }
+
+    /// End job `id` and report how it exited in the status line; `None`
+    /// when it was no longer live.
+    pub fn finish_job(
+        &mut self,
+        id: u64,
+        status: Option<i32>,
+        error: Option<&str>,
+    ) -> Option<JobSink> {
+        let index = self.jobs.running.iter().position(|job| job.id == id)?;
+        let job = self.jobs.running.remove(index);
+        let mut msg = match (error, status) {
+            (Some(error), _) => format!("{}: {error}", job.command),
+            (None, Some(0)) => format!("{}: done", job.command),
+            (None, Some(code)) => format!("{}: exit {code}", job.command),
+            (None, None) => format!("{}: terminated", job.command),
+        };
+        if matches!(job.sink, JobSink::Quickfix { .. }) {
+            match job.items {
+                1 => msg.push_str(", 1 error"),
+                n => msg.push_str(&format!(", {n} errors")),
+            }
+        }
+        tracing::debug!(target: "runtime.job", id, ?status, items = job.items, "job_done");
+        self.set_ephemeral(msg.clone(), Duration::from_secs(3));
+        self.jobs.summary = Some(msg);
+        Some(job.sink)
+    }
+
+    /// Stop job `id` (`:jobstop`); false when it is not running.
+    pub fn cancel_job(&mut self, id: u64) -> bool {
+        let Some(index) = self.jobs.running.iter().position(|job| job.id == id) else {
+            return false;
+        };
+        let job = self.jobs.running.remove(index);
+        tracing::debug!(target: "runtime.job", id, "job_cancelled");
+        self.jobs.cancelled.push(id);
+        self.jobs.summary = Some(format!("{}: cancelled", job.command));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn make_output_fills_the_quickfix_list() {
+        let mut state = EditorState::new(core_text::Buffer::from_str("t", "").unwrap());
+        let old = state.start_job("make".into(), JobSink::Quickfix { jump: true });
+        let id = state.start_job("make all".into(), JobSink::Quickfix { jump: true });
+        // The second `:make` superseded the first.
+        assert_eq!(state.jobs.take_cancelled(), [old]);
+        assert_eq!(state.jobs.take().len(), 2);
+        assert_eq!(state.job_output(old, &lines(&["a.c:1: x"])), None);
+        assert_eq!(state.jobs.summary(), Some("make all…"));
+
+        let out = lines(&["cc a.c", "a.c:3:7: error: x", "a.c:9: warning: y"]);
+        assert!(state.job_output(id, &out).is_some());
+        assert_eq!(state.quickfix.len(), 2);
+        assert_eq!(
+            (state.quickfix.items[0].line, state.quickfix.items[0].col),
+            (2, 6)
+        );
+        let sink = state.finish_job(id, Some(2), None);
+        assert_eq!(sink, Some(JobSink::Quickfix { jump: true }));
+        assert_eq!(state.jobs.summary(), Some("make all: exit 2, 2 errors"));
+        assert!(!state.jobs.is_running());
+    }
+
+    #[test]
+    fn job_output_appends_to_its_buffer_until_cancelled() {
+        let mut state = EditorState::new(core_text::Buffer::from_str("t", "").unwrap());
+        let out = core_text::Buffer::from_str("[Job]", "").unwrap();
+        let buffer = state.buffers.open(out, None);
+        let id = state.start_job("./build.sh".into(), JobSink::Buffer(buffer));
+        state.job_output(id, &lines(&["one", "two"]));
+        state.job_output(id, &lines(&["three"]));
+        let text = &state.buffers.get(buffer).unwrap().buffer;
+        assert_eq!(text.line(2).as_deref(), Some("three\n"));
+
+        assert_eq!(
+            state.jobs.running().collect::<Vec<_>>(),
+            [(id, "./build.sh")]
+        );
+        assert!(state.cancel_job(id));
+        assert!(!state.cancel_job(id));
+        assert_eq!(state.jobs.take_cancelled(), [id]);
+        assert_eq!(state.job_output(id, &lines(&["four"])), None);
+        assert_eq!(state.finish_job(id, None, None), None);
+        assert_eq!(state.jobs.summary(), Some("./build.sh: cancelled"));
+    }
+}
@@ -28,14 +28,69 @@
 //! - Future kinds (mode transitions, structural operations) can opt-in to mode restoration.
 //!
 //! Telemetry Integration:
-//! - Snapshot lifecycle emits trace events (`push_snapshot`, `undo_pop`, `redo_pop`, stack trims, redo clear).
+//! - Snapshot lifecycle emits trace events (`push_snapshot`, `undo_pop`, `redo_pop`, `undo_travel`, trims).
+//! - History is a tree (see `undo`): edits after an undo start a new branch
+//!   rather than clearing redo; `g-`/`g+` and `:earlier`/`:later` move across branches.
 //! - Edit application spans (`edit_insert`, `edit_newline`, `edit_backspace`, `edit_delete_under`) and
 //!   navigation (`motion`) live in the dispatcher; undo/redo spans wrap calls into this module.
 
+use core_config::options::OptionTable;
+use core_config::theme::Theme;
 use core_text::{Buffer, Position};
+pub mod abbrev;
+pub mod binary;
+pub mod buffer_manager;
+pub mod cmdline_window;
+pub mod completion;
+pub mod diagnostics;
+pub mod diff;
+pub mod explorer;
+pub mod file_watch;
+pub mod git;
+pub mod grep;
+pub mod highlight;
+pub mod hover;
+pub mod job;
+pub mod metrics;
+pub mod persistence;
+pub mod quickfix;
+pub mod search;
+pub mod segments;
+pub mod shell;
+pub mod signs;
+pub mod swap;
+pub mod tags;
+pub mod timer;
 pub mod undo;
+pub mod wildmenu;
+pub use abbrev::Abbreviations;
+pub use buffer_manager::{BufferEntry, BufferError, BufferId, BufferManager, BufferMeta};
+pub use cmdline_window::{CMDLINE_WINDOW_NAME, CmdlineWindow, CmdlineWindowReturn};
+pub use completion::{Completion, CompletionItem, CompletionSource, CompletionState};
+pub use diagnostics::{Diagnostic, DiagnosticCounts, DiagnosticStore, Severity};
+pub use diff::{DiffKind, DiffState, Hunk};
+pub use explorer::{EXPLORER_HEADER_LINES, Explorer, ExplorerEntry, ExplorerSort};
+pub use file_watch::{DiskStamp, FileWatch};
+pub use git::{BlameLine, GitState, GitStatus};
+pub use grep::{GrepRequest, GrepState, GrepTarget};
+pub use highlight::{HighlightSpan, Highlights};
+pub use hover::{Hover, HoverState};
+pub use job::{JobRequest, JobSink, JobState};
+pub use metrics::{METRICS_JSON_VERSION, metrics_json};
+pub use persistence::{FilePositions, SHADA_VERSION, ShadaData, ShadaError, ShadaLimits};
+pub use quickfix::{ListKind, QuickfixItem, QuickfixList, parse_errorformat, parse_grep_line};
+pub use search::{SearchHit, SearchPattern};
+pub use segments::StatusSegments;
+pub use shell::{ShellOrigin, ShellQueue, ShellRequest, ShellTarget};
+pub use signs::{SIGN_COLUMN_WIDTH, Sign, SignError, SignId, SignRegistry, SignStyle};
+pub use swap::{SwapError, SwapRecord, SwapUpdate};
+pub use tags::{TagEntry, Tags};
+pub use timer::{Timer, TimerCallback, TimerRequest, TimerState};
 use undo::UndoEngine;
-pub use undo::{InsertRun, SnapshotKind, UNDO_HISTORY_MAX};
+pub use undo::{
+    InsertRun, SnapshotKind, UNDO_HISTORY_MAX, UndoNodeInfo, UndoTravel, UndoTreeSnapshot,
+};
+pub use wildmenu::Wildmenu;
 
 // Refactor R4 Step 2: Selection model scaffold
 // Minimal persistent selection representation (visual mode placeholder).
@@ -77,6 +132,27 @@ impl SelectionSpan {
     fn greater(a: &Position, b: &Position) -> bool {
         a.line > b.line || (a.line == b.line && a.byte > b.byte)
     }
+
+    /// Size of the selection as Vim's 'showcmd' reports it: lines when it
+    /// is linewise or spans several lines, else the grapheme clusters it
+    /// covers, the one under the cursor included. Only a one-line span is
+    /// scanned, so this stays cheap for the status line.
+    pub fn size(&self, buffer: &core_text::Buffer) -> SelectionSize {
+        let (start, end) = if Self::greater(&self.start, &self.end) {
+            (self.end, self.start)
+        } else {
+            (self.start, self.end)
+        };
+        if self.kind == SelectionKind::Linewise || start.line != end.line {
+            return SelectionSize::Lines(end.line - start.line + 1);
+        }
+        let line = buffer.line(start.line).unwrap_or_default();
+        let text = line.strip_suffix('\n').unwrap_or(&line);
+        let from = start.byte.min(text.len());
+        let to = core_text::grapheme::next_boundary(text, end.byte.min(text.len()));
+        let chars = core_text::grapheme::iter(&text[from..to.max(from)]).count();
+        SelectionSize::Chars(chars.max(1))
+    }
     /// Returns true if span is empty (start == end).
     pub fn is_empty(&self) -> bool {
         self.start == self.end
@@ -148,6 +224,13 @@ impl SelectionSpan {
     }
 }
 
+/// Selection extent shown in the status line (`SelectionSpan::size`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionSize {
+    Lines(usize),
+    Chars(usize),
+}
+
 /// Persistent (yet optionally empty) selection model.
 ///
 /// Refactor R4 Step 2 introduced a durable representation for a single active
@@ -186,19 +269,60 @@ impl SelectionModel {
 
 // Registers & Operator Metrics (Phase 4 Steps 3,6–9):
 // - Registers are now fully populated by delete/yank/change operators; unnamed
-//   always mirrors the latest textual payload; numbered ring rotates with fixed
-//   capacity (10) discarding oldest on overflow.
+//   always mirrors the latest textual payload; yanks fill `"0` and deletes
+//   shift through `"1`-`"9` (or land in `"-` when they stay within a line).
 // - Operator metrics track counts & register writes enabling `:metrics` surface
 //   correlation between editing patterns and repaint pipeline cost.
 // - Paste & explicit register selection remain deferred (Phase 5) preserving
 //   breadth-first progress while ensuring current operator semantics are testable.
+/// How register text is laid out when pasted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    /// Inserted inline at the cursor.
+    #[default]
+    Charwise,
+    /// Whole lines, opened below (`p`) or above (`P`) the cursor line.
+    Linewise,
+    /// Rectangle: each line goes to the same column of successive lines.
+    Blockwise,
+}
+
+impl RegisterKind {
+    /// Best guess for text without recorded kind: a trailing newline means
+    /// whole lines (the pre-metadata paste heuristic).
+    pub fn infer(text: &str) -> Self {
+        if text.ends_with('\n') {
+            RegisterKind::Linewise
+        } else {
+            RegisterKind::Charwise
+        }
+    }
+}
+
+impl From<SelectionKind> for RegisterKind {
+    fn from(kind: SelectionKind) -> Self {
+        match kind {
+            SelectionKind::Characterwise => RegisterKind::Charwise,
+            SelectionKind::Linewise => RegisterKind::Linewise,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Registers {
     pub unnamed: String,
-    numbered: Vec<String>, // newest at index 0, length <= 10
+    unnamed_kind: RegisterKind,
+    numbered: [String; 10], // `"0` (last yank), then `"1`-`"9` (deletes)
+    numbered_kinds: [RegisterKind; 10], // parallel to `numbered`
     // Phase 5 Step 5: Named registers (a-z). Uppercase variants (A-Z) append.
-    // Simpler than full Vim semantics (linewise nuances) for breadth-first path.
     named: [String; 26],
+    named_kinds: [RegisterKind; 26],
+    /// Last search pattern (`"/`), kept across sessions by `persistence`.
+    search: String,
+    /// Small delete register (`"-`): last delete within a single line.
+    small_delete: String,
+    /// Expression register (`"=`): result of the last evaluated expression.
+    expression: String,
 }
 
 // Phase 4 Step 9: Operator & register metrics counters
@@ -219,10 +343,14 @@ pub struct OperatorMetricsSnapshot {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PasteSource {
     Unnamed,
-    /// Numbered ring entry (0 newest). Bounds checked at call site.
+    /// Numbered register: `"0` is the last yank, `"1`-`"9` the last deletes.
     Numbered(usize),
     /// Named register (a–z, A–Z) – not yet populated (future macro/explicit yank targets).
     Named(char),
+    /// Small delete register (`"-`).
+    SmallDelete,
+    /// Expression register (`"=`), filled by the `=` prompt before pasting.
+    Expression,
     /// System clipboard integration placeholder.
     System,
 }
@@ -235,6 +363,16 @@ pub enum PasteError {
     Empty,
 }
 
+/// Operation behind a register write; decides which numbered register it fills.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterOp {
+    /// Copies into `"0`.
+    Yank,
+    /// Deletes and changes: multi-line or linewise text shifts `"1`-`"9`,
+    /// anything within one line goes to `"-`.
+    Delete,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct OperatorMetrics {
     operator_delete: u64,
@@ -275,8 +413,11 @@ impl OperatorMetrics {
 ///
 /// Step 6 objective: concentrate register semantics so callers no longer reach into
 /// `EditorState` for ad-hoc mutations. The facade accepts an optional register target
-/// (alphabetic for named slots; `None` routes to unnamed + numbered ring) and applies
-/// the correct write semantics while incrementing operator metrics.
+/// (alphabetic for named slots; `-` for the small delete register; `None` for the
+/// default routing) and applies Vim's write semantics while incrementing operator
+/// metrics: an unnamed yank fills `"0`, an unnamed delete or change shifts `"1`-`"9`
+/// when it is linewise or spans lines and fills `"-` otherwise. Every write also
+/// sets the unnamed register.
 pub struct RegistersFacade<'state> {
     registers: &'state mut Registers,
     metrics: &'state mut OperatorMetrics,
@@ -288,49 +429,60 @@ impl<'state> RegistersFacade<'state> {
     }
 
     /// Record delete payload. Named targets honor uppercase append semantics.
-    pub fn write_delete<S: Into<String>>(&mut self, payload: S, target: Option<char>) {
+    pub fn write_delete<S: Into<String>>(
+        &mut self,
+        payload: S,
+        kind: RegisterKind,
+        target: Option<char>,
+    ) {
         self.metrics.incr_delete();
-        let text = payload.into();
-        if let Some(named) = target.filter(|c| c.is_ascii_alphabetic()) {
-            self.registers
-                .record_delete_named(named, text, self.metrics);
-        } else {
-            self.registers.record_delete(text, self.metrics);
-        }
+        self.registers.record(
+            target,
+            payload.into(),
+            kind,
+            RegisterOp::Delete,
+            self.metrics,
+        );
     }
 
     /// Record yank payload. Named targets honor uppercase append semantics.
-    pub fn write_yank<S: Into<String>>(&mut self, payload: S, target: Option<char>) {
+    pub fn write_yank<S: Into<String>>(
+        &mut self,
+        payload: S,
+        kind: RegisterKind,
+        target: Option<char>,
+    ) {
         self.metrics.incr_yank();
-        let text = payload.into();
-        if let Some(named) = target.filter(|c| c.is_ascii_alphabetic()) {
-            self.registers.record_yank_named(named, text, self.metrics);
-        } else {
-            self.registers.record_yank(text, self.metrics);
-        }
+        self.registers
+            .record(target, payload.into(), kind, RegisterOp::Yank, self.metrics);
     }
 
     /// Record change payload (treated as delete for register semantics with a distinct metric).
-    pub fn write_change<S: Into<String>>(&mut self, payload: S, target: Option<char>) {
+    pub fn write_change<S: Into<String>>(
+        &mut self,
+        payload: S,
+        kind: RegisterKind,
+        target: Option<char>,
+    ) {
         self.metrics.incr_change();
-        let text = payload.into();
-        if let Some(named) = target.filter(|c| c.is_ascii_alphabetic()) {
-            self.registers
-                .record_delete_named(named, text, self.metrics);
-        } else {
-            self.registers.record_delete(text, self.metrics);
-        }
+        self.registers.record(
+            target,
+            payload.into(),
+            kind,
+            RegisterOp::Delete,
+            self.metrics,
+        );
     }
 
-    /// Retrieve paste payload for the given source (clone-on-read).
-    pub fn read_paste(&self, source: PasteSource) -> Result<String, PasteError> {
+    /// Retrieve paste payload and its layout for the given source (clone-on-read).
+    pub fn read_paste(&self, source: PasteSource) -> Result<(String, RegisterKind), PasteError> {
         let registers: &Registers = &*self.registers;
         match source {
             PasteSource::Unnamed => {
                 if registers.unnamed.is_empty() {
                     Err(PasteError::Empty)
                 } else {
-                    Ok(registers.unnamed.clone())
+                    Ok((registers.unnamed.clone(), registers.unnamed_kind))
                 }
             }
             PasteSource::Numbered(idx) => {
@@ -341,7 +493,7 @@ impl<'state> RegistersFacade<'state> {
                 if entry.is_empty() {
                     Err(PasteError::Empty)
                 } else {
-                    Ok(entry.clone())
+                    Ok((entry.clone(), registers.numbered_kinds[idx]))
                 }
             }
             PasteSource::Named(c) => {
@@ -354,7 +506,21 @@ impl<'state> RegistersFacade<'state> {
                 if entry.is_empty() {
                     Err(PasteError::Empty)
                 } else {
-                    Ok(entry.clone())
+                    Ok((entry.clone(), registers.named_kinds[idx]))
+                }
+            }
+            PasteSource::SmallDelete | PasteSource::Expression => {
+                let (entry, kind) = if matches!(source, PasteSource::SmallDelete) {
+                    (&registers.small_delete, RegisterKind::Charwise)
+                } else {
+                    // Like Vim, an expression result ending in a newline is linewise.
+                    let e = &registers.expression;
+                    (e, RegisterKind::infer(e))
+                };
+                if entry.is_empty() {
+                    Err(PasteError::Empty)
+                } else {
+                    Ok((entry.clone(), kind))
                 }
             }
             PasteSource::System => Err(PasteError::Unimplemented),
@@ -363,44 +529,153 @@ impl<'state> RegistersFacade<'state> {
 }
 
 impl Registers {
-    pub const MAX: usize = 10; // ring capacity
+    pub const MAX: usize = 10; // `"0`-`"9`
 
     pub fn new() -> Self {
         Self {
             unnamed: String::new(),
-            numbered: Vec::new(),
+            unnamed_kind: RegisterKind::Charwise,
+            numbered: std::array::from_fn(|_| String::new()),
+            numbered_kinds: [RegisterKind::Charwise; 10],
             named: std::array::from_fn(|_| String::new()),
+            named_kinds: [RegisterKind::Charwise; 26],
+            search: String::new(),
+            small_delete: String::new(),
+            expression: String::new(),
         }
     }
 
-    /// Push a yank (non-destructive copy). Mirrors into unnamed and ring[0].
+    /// Store `text` of layout `kind`, produced by `op`, into `target`: a
+    /// named register (uppercase appends), `-` (small delete), or `None` /
+    /// anything else for the numbered registers: `"0` for a yank, a shift
+    /// through `"1`-`"9` for a delete that is linewise or spans lines, `"-`
+    /// for any other delete. Like Vim, an explicit register leaves the
+    /// numbered ones alone. Every write also sets unnamed.
+    pub fn record(
+        &mut self,
+        target: Option<char>,
+        text: String,
+        kind: RegisterKind,
+        op: RegisterOp,
+        metrics: &mut OperatorMetrics,
+    ) {
+        let (payload, kind) = match target {
+            Some('-') => {
+                self.small_delete = text.clone();
+                self.unnamed = text;
+                self.unnamed_kind = kind;
+                metrics.note_register_write(false);
+                return;
+            }
+            Some(c) => match Self::named_index(c) {
+                Some(idx) => {
+                    if c.is_ascii_uppercase() && !self.named[idx].is_empty() {
+                        let prev = self.named_kinds[idx];
+                        // Appending lines to characterwise text starts a new line.
+                        if kind == RegisterKind::Linewise && !self.named[idx].ends_with('\n') {
+                            self.named[idx].push('\n');
+                        }
+                        self.named[idx].push_str(&text);
+                        if prev != RegisterKind::Linewise {
+                            self.named_kinds[idx] = kind;
+                        }
+                    } else {
+                        self.named[idx] = text;
+                        self.named_kinds[idx] = kind;
+                    }
+                    self.unnamed = self.named[idx].clone();
+                    self.unnamed_kind = self.named_kinds[idx];
+                    metrics.note_register_write(false);
+                    return;
+                }
+                None => (text, kind),
+            },
+            None => (text, kind),
+        };
+        self.unnamed = payload.clone();
+        self.unnamed_kind = kind;
+        let rotated = match op {
+            RegisterOp::Yank => {
+                self.numbered[0] = payload;
+                self.numbered_kinds[0] = kind;
+                false
+            }
+            RegisterOp::Delete if kind != RegisterKind::Charwise || payload.contains('\n') => {
+                self.shift_deletes(payload, kind)
+            }
+            RegisterOp::Delete => {
+                self.small_delete = payload;
+                false
+            }
+        };
+        metrics.note_register_write(rotated);
+    }
+
+    /// Record an unnamed yank into unnamed and `"0`. The layout is inferred
+    /// from the text (see `RegisterKind::infer`).
     pub fn record_yank<S: Into<String>>(&mut self, text: S, metrics: &mut OperatorMetrics) {
         let s = text.into();
-        self.unnamed = s.clone();
-        let rotated = self.unshift_numbered(s);
-        metrics.note_register_write(rotated);
+        let kind = RegisterKind::infer(&s);
+        self.record(None, s, kind, RegisterOp::Yank, metrics);
     }
 
-    /// Push a delete/change (destructive). Semantics identical for ring/unnamed at this stage.
+    /// Record an unnamed delete/change: into unnamed and either `"1` (shifting
+    /// `"1`-`"8` down) or `"-`, depending on its shape (see `record`).
     pub fn record_delete<S: Into<String>>(&mut self, text: S, metrics: &mut OperatorMetrics) {
         let s = text.into();
-        self.unnamed = s.clone();
-        let rotated = self.unshift_numbered(s);
-        metrics.note_register_write(rotated);
+        let kind = RegisterKind::infer(&s);
+        self.record(None, s, kind, RegisterOp::Delete, metrics);
+    }
+
+    /// Layout of register `name` (`"`, `0`-`9`, `a`-`z`, `-`, `=`).
+    pub fn kind(&self, name: char) -> RegisterKind {
+        match name {
+            '"' => self.unnamed_kind,
+            '0'..='9' => self.numbered_kinds[name as usize - '0' as usize],
+            '=' => RegisterKind::infer(&self.expression),
+            _ => Self::named_index(name)
+                .map(|i| self.named_kinds[i])
+                .unwrap_or_default(),
+        }
     }
 
-    /// Return immutable slice of numbered ring (newest first).
+    /// Numbered registers `"0`-`"9`, indexed by digit; unset ones are empty.
     pub fn numbered(&self) -> &[String] {
         &self.numbered
     }
 
-    fn unshift_numbered(&mut self, s: String) -> bool {
-        let rotated = self.numbered.len() == Self::MAX;
-        if rotated {
-            self.numbered.pop();
-        }
-        self.numbered.insert(0, s);
-        rotated
+    /// Move `"1`-`"8` down one slot (dropping `"9`) and store `s` in `"1`.
+    /// Returns whether a filled `"9` was dropped.
+    fn shift_deletes(&mut self, s: String, kind: RegisterKind) -> bool {
+        let dropped = !self.numbered[Self::MAX - 1].is_empty();
+        self.numbered[1..].rotate_right(1);
+        self.numbered_kinds[1..].rotate_right(1);
+        self.numbered[1] = s;
+        self.numbered_kinds[1] = kind;
+        dropped
+    }
+
+    /// Small delete register (`"-`).
+    pub fn small_delete(&self) -> &str {
+        &self.small_delete
+    }
+
+    /// Expression register (`"=`).
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    pub fn set_expression<S: Into<String>>(&mut self, value: S) {
+        self.expression = value.into();
+    }
+
+    /// Last search pattern (`"/`).
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    pub fn set_search<S: Into<String>>(&mut self, pattern: S) {
+        self.search = pattern.into();
     }
 
     // --- Phase 5 Step 5: Named register helpers ---
@@ -433,30 +708,21 @@ impl Registers {
     }
 
     /// Record yank into named register `c` (lowercase replace, uppercase append). Updates
-    /// unnamed + numbered ring identically to unnamed-only yanks (breadth-first simplification).
+    /// unnamed too; the numbered registers are left alone.
     pub fn record_yank_named<S: Into<String>>(
         &mut self,
         c: char,
         text: S,
         metrics: &mut OperatorMetrics,
     ) {
-        if let Some(idx) = Self::named_index(c) {
-            let mut payload = text.into();
-            let append = c.is_ascii_uppercase();
-            if append && !self.named[idx].is_empty() {
-                self.named[idx].push_str(&payload);
-                payload = self.named[idx].clone(); // full updated payload for unnamed/ring
-            } else {
-                self.named[idx] = payload.clone();
-            }
-            // Mirror to unnamed + ring
-            self.unnamed = payload.clone();
-            let rotated = self.unshift_numbered(payload);
-            metrics.note_register_write(rotated);
+        if Self::named_index(c).is_some() {
+            let s = text.into();
+            let kind = RegisterKind::infer(&s);
+            self.record(Some(c), s, kind, RegisterOp::Yank, metrics);
         }
     }
 
-    /// Record delete/change into named register `c` (same semantics as yank for now).
+    /// Record delete/change into named register `c` (same as a named yank).
     pub fn record_delete_named<S: Into<String>>(
         &mut self,
         c: char,
@@ -482,49 +748,159 @@ pub enum Mode {
 
 // Refactor R4 Step 13 (Metrics Overlay Scaffold)
 // OverlayMode controls optional diagnostic overlay rows rendered above the status
-// line. We begin with a fixed line allocation (breadth-first) to avoid destabilizing
-// partial diff invariants; dynamic sizing & wrapping land in a follow-up step.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// line. The metrics overlay shows one section at a time (`:metrics next`); its
+// height fits the largest section so paging never moves the text area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum OverlayMode {
+    #[default]
     None,
-    Metrics { lines: u16 }, // always dirty; unconditional repaint each frame
+    Metrics {
+        section: MetricsSection,
+    }, // rows repainted in place when their content changes
+    Message {
+        lines: u16,
+    }, // multi-line message area (`message_lines`), dismissed on next key
 }
 
-impl Default for OverlayMode {
-    fn default() -> Self {
-        Self::None
-    }
+/// Page of the metrics overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetricsSection {
+    #[default]
+    Render,
+    Scheduler,
+    Operators,
+    Input,
+    Profile,
 }
 
-/// Default fixed line allocation for metrics overlay (follow-up will compute dynamically).
-pub const METRICS_OVERLAY_DEFAULT_LINES: u16 = 2;
+impl MetricsSection {
+    pub const ALL: [MetricsSection; 5] = [
+        MetricsSection::Render,
+        MetricsSection::Scheduler,
+        MetricsSection::Operators,
+        MetricsSection::Input,
+        MetricsSection::Profile,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MetricsSection::Render => "render",
+            MetricsSection::Scheduler => "scheduler",
+            MetricsSection::Operators => "operators",
+            MetricsSection::Input => "input",
+            MetricsSection::Profile => "profile",
+        }
+    }
+
+    /// Section named `name` or a unique prefix of it (`:metrics sched`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut found = Self::ALL.into_iter().filter(|s| s.name().starts_with(name));
+        match (found.next(), found.next()) {
+            (Some(section), None) if !name.is_empty() => Some(section),
+            _ => None,
+        }
+    }
+
+    /// 0-based position among `ALL`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
 
-/// Top-level editor state container (single-buffer in Phase 0).
+    pub fn prev(self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// Top-level editor state container. Buffers (with their path, dirty flag,
+/// line-ending info and undo history) live in `buffers`; `active` names the
+/// buffer editing operations target.
 pub struct EditorState {
-    pub buffers: Vec<Buffer>,
-    pub active: usize,
+    pub buffers: BufferManager,
+    pub active: BufferId,
     pub last_text_height: usize,
     pub mode: Mode,
-    pub file_name: Option<std::path::PathBuf>,
-    pub dirty: bool,
-    undo: UndoEngine,
     pub command_line: CommandLineState,
     pub ephemeral_status: Option<EphemeralMessage>,
-    pub original_line_ending: LineEnding,
-    pub had_trailing_newline: bool,
     pub config_vertical_margin: usize,
     pub registers: Registers, // Phase 4: populated by yank/delete/change
+    /// Last cursor position of recently left files (see `persistence`).
+    pub file_positions: FilePositions,
     pub operator_metrics: OperatorMetrics, // Phase 4: operator + register counters
     // Phase 4 Step 15: last render/scheduler metrics snapshots captured post-render.
     // To avoid a circular dependency (`core-render` depends on `core-state`), we store
     // lightweight copies of the snapshot data instead of the original types.
     pub last_render_path: Option<RenderPathSnapshotLite>,
     pub last_render_delta: Option<RenderDeltaSnapshotLite>,
+    // Input counters (`core_events` statics) copied alongside, for the overlay.
+    pub last_input_telemetry: Option<InputTelemetryLite>,
+    // Per-stage frame profiling (`:metrics profile`), read by the render
+    // engine; the last profiled frames are copied back, oldest first.
+    pub profile_frames: bool,
+    pub last_frame_profile: Vec<FrameStagesLite>,
     // Refactor R4 Step 2: persistent selection model scaffold (visual mode placeholder)
     pub selection: SelectionModel,
     // Refactor R4 Step 13: optional overlay (metrics) configuration
     pub overlay_mode: OverlayMode,
     pub jump_mark: Option<Position>, // Previous jump location ('' mark)
+    // Options Step 1: runtime option table (`:set`), seeded from config at startup.
+    pub options: OptionTable,
+    // External command requests queued by `:!` / `:r !` / `:sh` (drained by runtime).
+    pub shell: ShellQueue,
+    // `:grep` searches queued for and streamed back through the runtime.
+    pub grep: GrepState,
+    // `:make` / `:job` commands queued for and streamed back through the runtime.
+    pub jobs: JobState,
+    // `:defer` / `:timer` and plugin timers, run as event sources by the runtime.
+    pub timers: TimerState,
+    // Disk stamps of the watched files, to tell external changes from the editor's own writes.
+    pub file_watch: FileWatch,
+    // The quickfix list, filled by `:grep` and `:make` (windows hold their location lists).
+    pub quickfix: QuickfixList,
+    // Content of the multi-line message area (`OverlayMode::Message`).
+    pub message_lines: Vec<String>,
+    // Gutter signs placed by integrations (git, diagnostics).
+    pub signs: SignRegistry,
+    // Diagnostics published by linters / language servers.
+    pub diagnostics: DiagnosticStore,
+    // Insert-mode completion (`Ctrl-N`), fed by buffer words and language servers.
+    pub completion: CompletionState,
+    // Tag stack and pending `gd` / `gr` lookups.
+    pub tags: Tags,
+    // Hover documentation requested by `K`.
+    pub hover: HoverState,
+    // Insert-mode abbreviations (`:iabbrev`, `[abbreviations]`).
+    pub abbreviations: Abbreviations,
+    // Buffers compared by `:diffsplit` and their hunks.
+    pub diff: DiffState,
+    // Branch and dirty state of the active file's repository (probed by the runtime).
+    pub git: GitState,
+    // Provider-contributed status line segments (answered through the runtime).
+    pub status_segments: StatusSegments,
+    // Syntax highlight spans maintained by `core-syntax`.
+    pub highlights: Highlights,
+    // Active color scheme (`:colorscheme`); the runtime hands changes to the renderer.
+    theme: Theme,
+    theme_changed: bool,
+    // Direction of the last `/` or `?` search (`n` repeats it).
+    search_forward: bool,
+    // `:noh` hides match highlighting until the next search or `n` / `N`.
+    hlsearch_off: bool,
+    search_highlight_changed: bool,
+    // Stashed origin state while the command-line window (`q:`) is open.
+    cmdline_window: Option<CmdlineWindow>,
+    // `"=p` / `"=P` waiting for the `=` expression prompt to be confirmed.
+    pub expr_paste: Option<PendingExprPaste>,
+}
+
+/// Paste requested from the expression register, pending the `=` prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingExprPaste {
+    pub before: bool,
+    pub count: u32,
 }
 
 /// Line ending style detected from source file (Phase 2 Step 9).
@@ -560,8 +936,6 @@ pub struct RenderPathSnapshotLite {
     pub dirty_lines_marked: u64,
     pub dirty_candidate_lines: u64,
     pub dirty_lines_repainted: u64,
-    pub last_full_render_ns: u64,
-    pub last_partial_render_ns: u64,
     pub print_commands: u64,
     pub cells_printed: u64,
     pub scroll_region_shifts: u64,
@@ -571,6 +945,33 @@ pub struct RenderPathSnapshotLite {
     pub trim_success: u64,
     pub cols_saved_total: u64,
     pub status_skipped: u64,
+    pub coalesced_decisions: u64,
+    pub coalesced_frames: u64,
+    pub full_latency: LatencyLite,
+    pub cursor_only_latency: LatencyLite,
+    pub lines_latency: LatencyLite,
+    pub scroll_shift_latency: LatencyLite,
+}
+
+/// Rolling frame latency percentiles of one render path, in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencyLite {
+    pub p50_ns: u64,
+    pub p95_ns: u64,
+    pub p99_ns: u64,
+    pub samples: u64,
+}
+
+/// Stage split of one profiled frame (`:metrics profile`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStagesLite {
+    /// Render path name (`full`, `cursor`, `lines`, `scroll`).
+    pub path: &'static str,
+    pub total_ns: u64,
+    pub hash_ns: u64,
+    pub compose_ns: u64,
+    pub write_ns: u64,
+    pub flush_ns: u64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -582,9 +983,31 @@ pub struct RenderDeltaSnapshotLite {
     pub cursor_only: u64,
     pub collapsed_scroll: u64,
     pub suppressed_scroll: u64,
+    pub scroll_escalated: u64,
     pub semantic_frames: u64,
 }
 
+/// Copy of the input task counters kept in `core_events` statics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputTelemetryLite {
+    pub keypress_total: u64,
+    pub keypress_repeat: u64,
+    pub paste_sessions: u64,
+    pub paste_chunks: u64,
+    pub paste_bytes: u64,
+    pub send_failures: u64,
+    pub blocking_sends: u64,
+    /// Input events taken ahead of queued background events.
+    pub input_preferred: u64,
+    /// Queued motion key presses folded into a counted motion.
+    pub motions_coalesced: u64,
+    /// Intermediate resizes never laid out.
+    pub resizes_suppressed: u64,
+    pub async_input_starts: u64,
+    /// Input task stops, whatever the cause.
+    pub async_input_stops: u64,
+}
+
 /// Result of normalizing line endings (Phase 2 Step 9).
 pub struct NormalizedText {
     pub normalized: String,         // LF-only content
@@ -687,16 +1110,34 @@ pub fn normalize_line_endings(input: &str) -> NormalizedText {
 
 /// Minimal command-line state container (Refactor R1 Step 2).
 /// Breadth-first: only stores raw buffer including leading ':' when active.
-/// Future (Phase 2+): history, cursor within command line, validation status, suggestion UI.
+/// Executed commands are kept in a bounded history (oldest first) for the
+/// command-line window (`q:`).
+/// `Tab` completion keeps its matches in `wildmenu` until the line is
+/// edited.
+/// Future (Phase 2+): cursor within command line, validation status.
 #[derive(Debug, Default, Clone)]
 pub struct CommandLineState {
     buf: String,
+    history: Vec<String>,
+    wildmenu: Option<Wildmenu>,
 }
 
+/// Maximum retained command history entries (Vim's default 'history').
+pub const COMMAND_HISTORY_MAX: usize = 50;
+
 impl CommandLineState {
-    /// Returns true if a command is being entered (buffer starts with ':').
+    /// Returns true if a command (buffer starts with ':'), an expression
+    /// (buffer starts with '=') or a search ('/' or '?') is being entered.
     pub fn is_active(&self) -> bool {
-        self.buf.starts_with(':')
+        self.buf.starts_with([':', '=', '/', '?'])
+    }
+    /// True while the expression register prompt (`"=`) is open.
+    pub fn is_expression(&self) -> bool {
+        self.buf.starts_with('=')
+    }
+    /// True while a search prompt (`/` or `?`) is open.
+    pub fn is_search(&self) -> bool {
+        self.buf.starts_with(['/', '?'])
     }
     /// Expose raw buffer for rendering/translation.
     pub fn buffer(&self) -> &str {
@@ -705,27 +1146,81 @@ impl CommandLineState {
     /// Clear command buffer (leave inactive state).
     pub fn clear(&mut self) {
         self.buf.clear();
+        self.wildmenu = None;
     }
     /// Begin a new command (resets existing content) – ensures leading ':'.
     pub fn begin(&mut self) {
         self.buf.clear();
+        self.wildmenu = None;
         self.buf.push(':');
     }
+    /// Open the search prompt (leading '/' forward, '?' backward).
+    pub fn begin_search(&mut self, forward: bool) {
+        self.buf.clear();
+        self.wildmenu = None;
+        self.buf.push(if forward { '/' } else { '?' });
+    }
+    /// Open the expression register prompt (leading '=').
+    pub fn begin_expression(&mut self) {
+        self.buf.clear();
+        self.wildmenu = None;
+        self.buf.push('=');
+    }
     /// Push a character (assumes already active or will auto-activate if empty and ch not ':').
     pub fn push_char(&mut self, ch: char) {
+        self.wildmenu = None;
         if self.buf.is_empty() && ch != ':' {
             self.buf.push(':');
         }
         self.buf.push(ch);
     }
+    /// Record an executed command (without the leading ':'). Re-running an
+    /// existing entry moves it to the end instead of duplicating it.
+    pub fn record_history(&mut self, cmd: &str) {
+        let cmd = cmd.trim();
+        if cmd.is_empty() {
+            return;
+        }
+        self.history.retain(|h| h != cmd);
+        self.history.push(cmd.to_string());
+        if self.history.len() > COMMAND_HISTORY_MAX {
+            self.history.remove(0);
+        }
+    }
+    /// Command history, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
     /// Backspace behavior inside command line (keeps ':' sentinel until removing last char resets activity).
     pub fn backspace(&mut self) {
+        self.wildmenu = None;
         if self.buf.len() > 1 {
             self.buf.pop();
         } else {
             self.buf.clear();
         }
     }
+    /// The completion matches shown above the command line, if any.
+    pub fn wildmenu(&self) -> Option<&Wildmenu> {
+        self.wildmenu.as_ref()
+    }
+    /// Show `menu` and put its first (last with `backward`) match on the
+    /// command line.
+    pub fn open_wildmenu(&mut self, mut menu: Wildmenu, backward: bool) {
+        menu.step(backward);
+        self.buf = menu.line();
+        self.wildmenu = Some(menu);
+    }
+    /// Put the next (previous) match of the open wildmenu on the command
+    /// line. Returns false when no menu is open.
+    pub fn step_wildmenu(&mut self, backward: bool) -> bool {
+        let Some(menu) = self.wildmenu.as_mut() else {
+            return false;
+        };
+        menu.step(backward);
+        self.buf = menu.line();
+        true
+    }
 }
 
 /// Ephemeral status message container (Phase 2 Step 6).
@@ -738,29 +1233,123 @@ pub struct EphemeralMessage {
 impl EditorState {
     /// Create a new state with a single active buffer.
     pub fn new(buffer: Buffer) -> Self {
+        let buffers = BufferManager::new(buffer);
+        let active = buffers.ids().next().expect("initial buffer");
         Self {
-            buffers: vec![buffer],
-            active: 0,
+            buffers,
+            active,
             last_text_height: 0,
             mode: Mode::Normal,
-            file_name: None,
-            dirty: false,
-            undo: UndoEngine::new(),
             command_line: CommandLineState::default(),
             ephemeral_status: None,
-            original_line_ending: LineEnding::Lf,
-            had_trailing_newline: false,
             config_vertical_margin: 0,
             registers: Registers::new(),
+            file_positions: FilePositions::default(),
             operator_metrics: OperatorMetrics::default(),
             last_render_path: None,  // Initialize last_render_path to None
             last_render_delta: None, // Initialize last_render_delta to None
+            last_input_telemetry: None,
+            profile_frames: false,
+            last_frame_profile: Vec::new(),
             selection: SelectionModel::default(),
             overlay_mode: OverlayMode::default(),
             jump_mark: None,
+            options: OptionTable::default(),
+            shell: ShellQueue::default(),
+            grep: GrepState::default(),
+            jobs: JobState::default(),
+            timers: TimerState::default(),
+            file_watch: FileWatch::default(),
+            quickfix: QuickfixList::default(),
+            message_lines: Vec::new(),
+            signs: SignRegistry::new(),
+            diagnostics: DiagnosticStore::new(),
+            completion: CompletionState::new(),
+            tags: Tags::new(),
+            hover: HoverState::new(),
+            abbreviations: Abbreviations::new(),
+            diff: DiffState::new(),
+            git: GitState::default(),
+            status_segments: StatusSegments::default(),
+            highlights: Highlights::new(),
+            theme: Theme::default(),
+            theme_changed: false,
+            search_forward: true,
+            hlsearch_off: false,
+            search_highlight_changed: false,
+            cmdline_window: None,
+            expr_paste: None,
+        }
+    }
+
+    /// Active color scheme.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Replace the color scheme; `take_theme_change` reports it once.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.theme_changed = true;
+    }
+
+    /// Whether the scheme changed since the last call.
+    pub fn take_theme_change(&mut self) -> bool {
+        std::mem::take(&mut self.theme_changed)
+    }
+
+    /// The last search pattern with the case rule of `ignorecase` /
+    /// `smartcase`.
+    pub fn search_pattern(&self) -> SearchPattern<'_> {
+        SearchPattern::new(
+            self.registers.search(),
+            self.options.get_bool("ignorecase"),
+            self.options.get_bool("smartcase"),
+        )
+    }
+
+    /// Whether `n` searches forward (the last search was `/`).
+    pub fn search_forward(&self) -> bool {
+        self.search_forward
+    }
+
+    /// Pattern whose matches are highlighted: the last search while
+    /// `hlsearch` is set and `:noh` has not hidden it.
+    pub fn search_highlight(&self) -> Option<SearchPattern<'_>> {
+        let pattern = self.search_pattern();
+        (self.options.get_bool("hlsearch") && !self.hlsearch_off && !pattern.text.is_empty())
+            .then_some(pattern)
+    }
+
+    /// Remember a `/` or `?` search; its matches become highlighted.
+    pub fn record_search(&mut self, pattern: &str, forward: bool) {
+        self.registers.set_search(pattern);
+        self.search_forward = forward;
+        self.hlsearch_off = false;
+        self.search_highlight_changed = true;
+    }
+
+    /// `n` / `N` bring back highlighting hidden by `:noh`.
+    pub fn resume_search_highlight(&mut self) {
+        if self.hlsearch_off {
+            self.hlsearch_off = false;
+            self.search_highlight_changed = true;
+        }
+    }
+
+    /// `:noh[lsearch]`.
+    pub fn suspend_search_highlight(&mut self) {
+        if !self.hlsearch_off {
+            self.hlsearch_off = true;
+            self.search_highlight_changed = true;
         }
     }
 
+    /// Whether the highlighted matches may have changed since the last call.
+    pub fn take_search_highlight_change(&mut self) -> bool {
+        std::mem::take(&mut self.search_highlight_changed)
+    }
+
     /// Set an ephemeral status message with a fixed timeout duration.
     pub fn set_ephemeral<S: Into<String>>(&mut self, msg: S, ttl: std::time::Duration) {
         self.ephemeral_status = Some(EphemeralMessage {
@@ -805,21 +1394,81 @@ impl EditorState {
         &self.buffers[self.active]
     }
 
-    /// Mutable accessor for the active buffer (Phase 1: single buffer only).
+    /// Mutable accessor for the active buffer.
     /// All text mutations in editing paths should flow through this to keep
-    /// future invariants (multi-buffer, dirty tracking) centralized.
+    /// invariants (dirty tracking, undo) centralized.
     pub fn active_buffer_mut(&mut self) -> &mut Buffer {
         &mut self.buffers[self.active]
     }
 
+    /// Metadata (path, dirty flag, line endings) of the active buffer.
+    pub fn active_meta(&self) -> &BufferMeta {
+        &self.buffers.entry(self.active).meta
+    }
+
+    pub fn active_meta_mut(&mut self) -> &mut BufferMeta {
+        &mut self.buffers.entry_mut(self.active).meta
+    }
+
+    /// True when the active buffer has unsaved changes.
+    pub fn dirty(&self) -> bool {
+        self.active_meta().dirty
+    }
+
+    pub fn set_dirty(&mut self, dirty: bool) {
+        self.active_meta_mut().dirty = dirty;
+    }
+
+    /// Backing file of the active buffer.
+    pub fn file_name(&self) -> Option<&std::path::Path> {
+        self.active_meta().path.as_deref()
+    }
+
+    pub fn set_file_name(&mut self, path: Option<std::path::PathBuf>) {
+        self.active_meta_mut().path = path;
+    }
+
+    /// Make `id` the active buffer. Returns false for unknown ids.
+    pub fn switch_buffer(&mut self, id: BufferId) -> bool {
+        if !self.buffers.contains(id) {
+            return false;
+        }
+        if self.active != id {
+            self.undo_mut().end_insert_coalescing();
+            self.active = id;
+            self.selection.clear();
+        }
+        true
+    }
+
+    /// Delete buffer `id` (see `BufferManager::close`) along with its signs
+    /// and swap file, so they do not outlive it.
+    pub fn close_buffer(&mut self, id: BufferId, force: bool) -> Result<BufferEntry, BufferError> {
+        let entry = self.buffers.close(id, force)?;
+        self.signs.unplace_buffer(id);
+        if let Some(swap) = &entry.meta.swap {
+            let _ = std::fs::remove_file(swap);
+        }
+        Ok(entry)
+    }
+
+    fn undo_ref(&self) -> &UndoEngine {
+        &self.buffers.entry(self.active).meta.undo
+    }
+
+    fn undo_mut(&mut self) -> &mut UndoEngine {
+        &mut self.buffers.entry_mut(self.active).meta.undo
+    }
+
     /// Capture a snapshot of the current editable state (active single buffer only in Phase 1).
     pub fn push_snapshot(&mut self, kind: SnapshotKind, cursor: Position) {
         // Avoid double-borrow of &self by cloning buffer ref first
         let mode = self.mode;
-        let buf_clone = self.active_buffer().clone();
-        self.undo.push_snapshot(kind, cursor, &buf_clone, mode);
-        // Replace active buffer clone to maintain identical behavior (original implementation cloned internally)
-        self.buffers[self.active] = buf_clone;
+        let entry = self.buffers.entry_mut(self.active);
+        entry
+            .meta
+            .undo
+            .push_snapshot(kind, cursor, &entry.buffer, mode);
     }
 
     /// Begin an Insert-mode coalescing run: push a pre-edit snapshot only once.
@@ -836,9 +1485,11 @@ impl EditorState {
     /// exactly once.
     pub fn begin_insert_coalescing(&mut self, cursor: Position) {
         let mode = self.mode;
-        let buf_clone = self.active_buffer().clone();
-        self.undo.begin_insert_coalescing(cursor, &buf_clone, mode);
-        self.buffers[self.active] = buf_clone;
+        let entry = self.buffers.entry_mut(self.active);
+        entry
+            .meta
+            .undo
+            .begin_insert_coalescing(cursor, &entry.buffer, mode);
     }
 
     /// Ends the current Insert-mode coalescing run. Boundary triggers:
@@ -849,7 +1500,7 @@ impl EditorState {
     /// The next Insert mutation will start a new run and thus push a new snapshot via
     /// `begin_insert_coalescing`.
     pub fn end_insert_coalescing(&mut self) {
-        self.undo.end_insert_coalescing();
+        self.undo_mut().end_insert_coalescing();
     }
 
     /// Push a discrete edit snapshot (used for Normal mode edits like `x` or
@@ -875,11 +1526,8 @@ impl EditorState {
     ) -> String {
         // Push snapshot of pre-delete state for undo. Use current cursor (before mutation).
         self.push_discrete_edit_snapshot(*cursor);
-        let buf = self.active_buffer().clone(); // snapshot already cloned within push
-        let mut working = buf.clone();
-        let removed = working.delete_bytes(start, end);
-        // Replace buffer
-        self.buffers[self.active] = working;
+        // Edited in place so the buffer's edit log keeps the deletion.
+        let removed = self.active_buffer_mut().delete_bytes(start, end);
         // Recompute cursor line/byte from absolute start (simple linear scan using public APIs).
         *cursor = absolute_position(self.active_buffer(), start);
         removed
@@ -887,34 +1535,60 @@ impl EditorState {
 
     /// Increment the edit counter for an active insert run (used for diagnostics / future heuristics).
     pub fn note_insert_edit(&mut self) {
-        self.undo.note_insert_edit();
+        self.undo_mut().note_insert_edit();
     }
     /// Restore previously captured snapshot (caller ensures existence). Returns true if restored.
     pub fn undo(&mut self, cursor: &mut Position) -> bool {
-        let buffer = &mut self.buffers[self.active];
-        self.undo.undo(cursor, buffer, &mut self.mode)
+        let entry = self.buffers.entry_mut(self.active);
+        entry
+            .meta
+            .undo
+            .undo(cursor, &mut entry.buffer, &mut self.mode)
     }
 
     /// Redo previously undone snapshot. Returns true if applied.
     pub fn redo(&mut self, cursor: &mut Position) -> bool {
-        let buffer = &mut self.buffers[self.active];
-        self.undo.redo(cursor, buffer, &mut self.mode)
+        let entry = self.buffers.entry_mut(self.active);
+        entry
+            .meta
+            .undo
+            .redo(cursor, &mut entry.buffer, &mut self.mode)
+    }
+
+    /// Move chronologically through the undo tree (`g-`/`g+`,
+    /// `:earlier`/`:later`). Returns true if the text state changed.
+    pub fn undo_travel(&mut self, travel: UndoTravel, cursor: &mut Position) -> bool {
+        let entry = self.buffers.entry_mut(self.active);
+        entry
+            .meta
+            .undo
+            .travel(travel, cursor, &mut entry.buffer, &mut self.mode)
+    }
+
+    /// Undo tree structure of the active buffer (for visualizers).
+    pub fn undo_tree(&self) -> UndoTreeSnapshot {
+        self.undo_ref().tree_snapshot()
+    }
+
+    /// Change number of the active buffer's current text state.
+    pub fn undo_seq(&self) -> u64 {
+        self.undo_ref().current_seq()
     }
 
     /// Number of successive identical snapshots skipped (Phase 3 Step 11).
     pub fn undo_snapshots_skipped(&self) -> u64 {
-        self.undo.snapshots_skipped()
+        self.undo_ref().snapshots_skipped()
     }
 
     // Test/metrics helpers
     pub fn undo_depth(&self) -> usize {
-        self.undo.undo_depth()
+        self.undo_ref().undo_depth()
     }
     pub fn redo_depth(&self) -> usize {
-        self.undo.redo_depth()
+        self.undo_ref().redo_depth()
     }
     pub fn insert_run(&self) -> &InsertRun {
-        self.undo.insert_run()
+        self.undo_ref().insert_run()
     }
 
     /// Mutable access to registers (operators populate these)
@@ -968,29 +1642,56 @@ impl EditorState {
         source: PasteSource,
         before: bool,
         cursor: &mut Position,
+    ) -> Result<bool, PasteError> {
+        self.paste_repeated(source, before, 1, cursor)
+    }
+
+    /// `{count}p` / `{count}P`: paste `count` copies in a row under a single
+    /// snapshot, so one undo removes them all.
+    pub fn paste_repeated(
+        &mut self,
+        source: PasteSource,
+        before: bool,
+        count: u32,
+        cursor: &mut Position,
     ) -> Result<bool, PasteError> {
         if !matches!(self.mode, Mode::Normal) {
             return Err(PasteError::Unimplemented);
         }
-        let text = self.registers_facade().read_paste(source)?;
+        let (text, kind) = self.registers_facade().read_paste(source)?;
         self.push_discrete_edit_snapshot(*cursor);
-        let structural = self.paste_with_text(&text, before, cursor);
+        let mut structural = false;
+        for _ in 0..count.max(1) {
+            structural |= self.paste_with_text(&text, kind, before, cursor);
+        }
         Ok(structural)
     }
 
     /// Shared paste implementation that assumes an edit snapshot has already been pushed.
-    /// Returns true when the inserted text is structural (multi-line).
-    pub fn paste_with_text(&mut self, text: &str, before: bool, cursor: &mut Position) -> bool {
+    /// `kind` decides the layout: linewise text opens new lines, blockwise text
+    /// is inserted as a rectangle at the cursor column, charwise text goes
+    /// inline. Returns true when the inserted text is structural (multi-line).
+    pub fn paste_with_text(
+        &mut self,
+        text: &str,
+        kind: RegisterKind,
+        before: bool,
+        cursor: &mut Position,
+    ) -> bool {
         if text.is_empty() {
             return false;
         }
-        if text.ends_with('\n') {
-            return self.paste_linewise(text, before, cursor);
-        }
-        if text.contains('\n') {
-            return self.paste_charwise_multiline(text, before, cursor);
+        match kind {
+            RegisterKind::Linewise if text.ends_with('\n') => {
+                self.paste_linewise(text, before, cursor)
+            }
+            RegisterKind::Linewise => self.paste_linewise(&format!("{text}\n"), before, cursor),
+            RegisterKind::Blockwise => self.paste_blockwise(text, before, cursor),
+            RegisterKind::Charwise if text.contains('\n') => {
+                self.paste_charwise_multiline(text, before, cursor)
+            }
+            RegisterKind::Charwise => self.paste_charwise_single(text, before, cursor),
         }
-        self.paste_charwise_single(text, before, cursor)
     }
 
     fn paste_charwise_single(&mut self, text: &str, before: bool, cursor: &mut Position) -> bool {
@@ -1020,8 +1721,8 @@ impl EditorState {
             insert_pos.byte = insert_pos.byte.saturating_sub(last_len);
         }
         *cursor = insert_pos;
-        if !self.dirty {
-            self.dirty = true;
+        if !self.dirty() {
+            self.set_dirty(true);
         }
         false
     }
@@ -1066,11 +1767,10 @@ impl EditorState {
             }
         };
         let mut last_insert_pos = insert_pos;
-        for (i, segment) in text.split_inclusive('\n').enumerate() {
+        for (i, frag) in text.split('\n').enumerate() {
             if i > 0 {
                 buffer.insert_newline(&mut last_insert_pos);
             }
-            let frag = segment.strip_suffix('\n').unwrap_or(segment);
             if frag.is_empty() {
                 continue;
             }
@@ -1094,12 +1794,89 @@ impl EditorState {
             }
         }
         *cursor = last_insert_pos;
-        if !self.dirty {
-            self.dirty = true;
+        if !self.dirty() {
+            self.set_dirty(true);
         }
         true
     }
 
+    /// Insert each line of `text` at the same display column on successive
+    /// lines, starting at the cursor line. Short lines are padded with spaces
+    /// to reach the column, missing lines are appended, and block pieces are
+    /// padded to the block width when text follows them so the rectangle
+    /// stays aligned. The cursor lands on the block's top-left corner.
+    fn paste_blockwise(&mut self, text: &str, before: bool, cursor: &mut Position) -> bool {
+        let pieces: Vec<&str> = text.split('\n').collect();
+        let width = pieces
+            .iter()
+            .map(|p| {
+                core_text::grapheme::iter(p)
+                    .map(core_text::grapheme::cluster_width)
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0);
+        let buffer = self.active_buffer_mut();
+        let first = buffer.line(cursor.line).unwrap_or_default();
+        let first = first.strip_suffix('\n').unwrap_or(&first);
+        let mut col = core_text::grapheme::visual_col(first, cursor.byte.min(first.len()));
+        if !before && cursor.byte < first.len() {
+            let next = core_text::grapheme::next_boundary(first, cursor.byte);
+            col += core_text::grapheme::cell_width(&first[cursor.byte..next], col);
+        }
+        let mut top_left = *cursor;
+        for (i, piece) in pieces.iter().enumerate() {
+            let line_idx = cursor.line + i;
+            // Ropey reports a trailing empty line after a final newline; treat
+            // it (and anything past it) as a fresh line to append.
+            let phantom = line_idx + 1 >= buffer.line_count()
+                && buffer.line_byte_len(line_idx) == 0
+                && line_idx > 0;
+            let line = if phantom {
+                String::new()
+            } else {
+                buffer.line(line_idx).unwrap_or_default()
+            };
+            let content = line.strip_suffix('\n').unwrap_or(&line);
+            let mut byte = 0;
+            let mut line_col = 0;
+            for g in core_text::grapheme::iter(content) {
+                if line_col >= col {
+                    break;
+                }
+                line_col += core_text::grapheme::cell_width(g, line_col);
+                byte += g.len();
+            }
+            let mut insert = " ".repeat(col.saturating_sub(line_col));
+            insert.push_str(piece);
+            if byte < content.len() {
+                let piece_width: usize = core_text::grapheme::iter(piece)
+                    .map(core_text::grapheme::cluster_width)
+                    .sum();
+                insert.push_str(&" ".repeat(width - piece_width));
+            }
+            let start = if phantom {
+                insert.push('\n');
+                let len = buffer.len_bytes();
+                if len > 0 && !buffer.slice_bytes(len - 1, len).ends_with('\n') {
+                    buffer.insert_str(len, "\n");
+                }
+                buffer.len_bytes()
+            } else {
+                buffer.line_to_byte(line_idx) + byte
+            };
+            buffer.insert_str(start, &insert);
+            if i == 0 {
+                top_left = Position::new(line_idx, byte + col.saturating_sub(line_col));
+            }
+        }
+        *cursor = top_left;
+        if !self.dirty() {
+            self.set_dirty(true);
+        }
+        pieces.len() > 1
+    }
+
     fn paste_linewise(&mut self, text: &str, before: bool, cursor: &mut Position) -> bool {
         let (inserted_start_line, first_line) = {
             let buffer = self.active_buffer_mut();
@@ -1148,8 +1925,8 @@ impl EditorState {
             (inserted_start_line, first_line)
         };
 
-        if !self.dirty {
-            self.dirty = true;
+        if !self.dirty() {
+            self.set_dirty(true);
         }
 
         let line_str = first_line.as_str();
@@ -1184,16 +1961,102 @@ impl EditorState {
     pub fn set_overlay_mode(&mut self, mode: OverlayMode) {
         self.overlay_mode = mode;
     }
-    /// Toggle metrics overlay. Returns the new mode.
-    pub fn toggle_metrics_overlay(&mut self, default_lines: u16) -> OverlayMode {
+    /// Toggle metrics overlay (opening on its first section). Returns the
+    /// new mode.
+    pub fn toggle_metrics_overlay(&mut self) -> OverlayMode {
         self.overlay_mode = match self.overlay_mode {
-            OverlayMode::None => OverlayMode::Metrics {
-                lines: default_lines,
-            },
             OverlayMode::Metrics { .. } => OverlayMode::None,
+            OverlayMode::None | OverlayMode::Message { .. } => OverlayMode::Metrics {
+                section: MetricsSection::default(),
+            },
         };
         self.overlay_mode
     }
+
+    /// Show `section` of the metrics overlay, opening it if needed.
+    pub fn show_metrics_section(&mut self, section: MetricsSection) {
+        self.overlay_mode = OverlayMode::Metrics { section };
+    }
+
+    /// Section the metrics overlay shows, when open.
+    pub fn metrics_section(&self) -> Option<MetricsSection> {
+        match self.overlay_mode {
+            OverlayMode::Metrics { section } => Some(section),
+            OverlayMode::None | OverlayMode::Message { .. } => None,
+        }
+    }
+
+    /// Show multi-line output in the message area. At most `max_lines` rows
+    /// are shown; longer output keeps its tail and reports the elided count.
+    pub fn show_message_lines(&mut self, lines: Vec<String>, max_lines: usize) {
+        let max_lines = max_lines.max(1);
+        let mut lines = lines;
+        if lines.len() > max_lines {
+            let skipped = lines.len() - (max_lines - 1);
+            lines.drain(..skipped);
+            lines.insert(0, format!("-- {skipped} more lines --"));
+        }
+        self.overlay_mode = OverlayMode::Message {
+            lines: lines.len() as u16,
+        };
+        self.message_lines = lines;
+    }
+
+    /// Dismiss the message area. Returns true when a message was showing.
+    pub fn dismiss_message_lines(&mut self) -> bool {
+        if matches!(self.overlay_mode, OverlayMode::Message { .. }) {
+            self.overlay_mode = OverlayMode::None;
+            self.message_lines.clear();
+            return true;
+        }
+        false
+    }
+
+    /// Size of the active selection, while there is one.
+    pub fn selection_size(&self) -> Option<SelectionSize> {
+        self.selection
+            .active
+            .map(|span| span.size(self.active_buffer()))
+    }
+
+    /// Index of the last line holding content (a trailing newline produces an
+    /// empty final rope line which ex ranges ignore).
+    pub fn last_content_line(&self) -> usize {
+        let buffer = self.active_buffer();
+        let count = buffer.line_count();
+        if count > 1 && buffer.line_byte_len(count - 1) == 0 {
+            count - 2
+        } else {
+            count.saturating_sub(1)
+        }
+    }
+
+    /// Replace lines `start..=end` with `text` (linewise, newline terminated)
+    /// as a single undoable edit. Returns the removed text. The cursor moves
+    /// to the first replaced line.
+    pub fn replace_lines_with_snapshot(
+        &mut self,
+        cursor: &mut Position,
+        start: usize,
+        end: usize,
+        text: &str,
+    ) -> String {
+        self.push_discrete_edit_snapshot(*cursor);
+        let buffer = self.active_buffer_mut();
+        let start_b = buffer.line_to_byte(start);
+        let end_b = buffer.line_to_byte(end + 1);
+        let removed = buffer.delete_bytes(start_b, end_b);
+        let mut insert = text.to_string();
+        if !removed.ends_with('\n') && insert.ends_with('\n') {
+            // Final line had no newline; keep it that way.
+            insert.pop();
+        }
+        buffer.insert_str(start_b, &insert);
+        let line_count = buffer.line_count();
+        *cursor = Position::new(start.min(line_count.saturating_sub(1)), 0);
+        self.set_dirty(true);
+        removed
+    }
 }
 
 // Compute a stable hash for the entire buffer content (Phase 3 Step 11).
@@ -1209,13 +2072,24 @@ mod tests {
     use super::*;
     use core_text::Buffer;
 
+    #[test]
+    fn close_buffer_drops_its_signs() {
+        let mut st = EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        let other = st.buffers.open(Buffer::from_str("u", "b\n").unwrap(), None);
+        st.signs.place(other, 0, "E", SignStyle::Error).unwrap();
+        st.signs.take_dirty();
+        st.close_buffer(other, false).unwrap();
+        assert!(!st.signs.has_signs(other));
+        assert_eq!(st.signs.take_dirty(), vec![(other, 0)]);
+    }
+
     #[test]
     fn paste_single_line_after() {
         let buf = Buffer::from_str("t", "abc\n").unwrap();
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("X", None);
+            regs.write_yank("X", RegisterKind::Charwise, None);
         }
         let mut cursor = Position { line: 0, byte: 1 }; // on 'b' boundary after 'a'
         let structural = st.paste(PasteSource::Unnamed, false, &mut cursor).unwrap();
@@ -1231,7 +2105,7 @@ mod tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("Z", None);
+            regs.write_yank("Z", RegisterKind::Charwise, None);
         }
         let mut cursor = Position { line: 0, byte: 2 }; // before 'c'
         let structural = st.paste(PasteSource::Unnamed, true, &mut cursor).unwrap();
@@ -1252,7 +2126,7 @@ mod tests {
         st.push_discrete_edit_snapshot(cursor);
         {
             let mut regs = st.registers_facade();
-            regs.write_delete("O".to_string(), None);
+            regs.write_delete("O".to_string(), RegisterKind::Charwise, None);
         }
         st.active_buffer_mut().delete_grapheme_at(&mut cursor); // removes 'O'; cursor stays at 0
         // Paste after (p semantics) should insert after current grapheme (which is now 'x')
@@ -1272,7 +2146,7 @@ mod tests {
         st.push_discrete_edit_snapshot(cursor);
         {
             let mut regs = st.registers_facade();
-            regs.write_delete("O".to_string(), None);
+            regs.write_delete("O".to_string(), RegisterKind::Charwise, None);
         }
         st.active_buffer_mut().delete_grapheme_at(&mut cursor);
         // Paste before (P) inserts at cursor (before current 'x'), restoring original
@@ -1290,7 +2164,7 @@ mod tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("X\nY\n", None);
+            regs.write_yank("X\nY\n", RegisterKind::Linewise, None);
         }
         let mut cursor = Position { line: 0, byte: 1 }; // after 'a'
         let structural = st.paste(PasteSource::Unnamed, false, &mut cursor).unwrap();
@@ -1314,7 +2188,7 @@ mod tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("  paste\n", None);
+            regs.write_yank("  paste\n", RegisterKind::Linewise, None);
         }
         let mut cursor = Position { line: 0, byte: 1 };
         let structural = st.paste(PasteSource::Unnamed, false, &mut cursor).unwrap();
@@ -1331,7 +2205,7 @@ mod tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("inserted\n", None);
+            regs.write_yank("inserted\n", RegisterKind::Linewise, None);
         }
         let mut cursor = Position { line: 1, byte: 0 };
         let structural = st.paste(PasteSource::Unnamed, true, &mut cursor).unwrap();
@@ -1349,7 +2223,7 @@ mod tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("block\n", None);
+            regs.write_yank("block\n", RegisterKind::Linewise, None);
         }
         let mut cursor = Position { line: 0, byte: 0 };
         for _ in 0..2 {
@@ -1363,14 +2237,50 @@ mod tests {
         assert_eq!(cursor.byte, 0);
     }
 
+    #[test]
+    fn paste_honors_recorded_kind_over_trailing_newline() {
+        let buf = Buffer::from_str("t", "ab\ncd\n").unwrap();
+        let mut st = EditorState::new(buf);
+        // `v$y` style: charwise text that happens to end in a newline.
+        st.registers_facade()
+            .write_yank("X\n", RegisterKind::Charwise, None);
+        let mut cursor = Position { line: 0, byte: 0 };
+        assert!(st.paste(PasteSource::Unnamed, false, &mut cursor).unwrap());
+        assert_eq!(st.active_buffer().line(0).unwrap(), "aX\n");
+        assert_eq!(st.active_buffer().line(1).unwrap(), "b\n");
+
+        // Linewise text without a trailing newline still opens a new line.
+        st.registers_facade()
+            .write_yank("last", RegisterKind::Linewise, None);
+        let mut cursor = Position { line: 2, byte: 0 };
+        st.paste(PasteSource::Unnamed, true, &mut cursor).unwrap();
+        assert_eq!(st.active_buffer().line(2).unwrap(), "last\n");
+        assert_eq!(st.active_buffer().line(3).unwrap(), "cd\n");
+    }
+
+    #[test]
+    fn paste_blockwise_inserts_rectangle() {
+        let buf = Buffer::from_str("t", "abcd\nx\nefgh\n").unwrap();
+        let mut st = EditorState::new(buf);
+        st.registers_facade()
+            .write_yank("12\n3\n45\n67", RegisterKind::Blockwise, None);
+        let mut cursor = Position { line: 0, byte: 1 };
+        assert!(st.paste(PasteSource::Unnamed, false, &mut cursor).unwrap());
+        let lines: Vec<String> = (0..st.active_buffer().line_count())
+            .filter_map(|i| st.active_buffer().line(i))
+            .collect();
+        assert_eq!(lines, ["ab12cd\n", "x 3\n", "ef45gh\n", "  67\n", ""]);
+        assert_eq!(cursor, Position::new(0, 2));
+    }
+
     #[test]
     fn paste_does_not_rotate_numbered_ring() {
         let buf = Buffer::from_str("t", "base\n").unwrap();
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_delete("one\n".to_string(), None);
-            regs.write_delete("two\n".to_string(), None);
+            regs.write_delete("one\n".to_string(), RegisterKind::Linewise, None);
+            regs.write_delete("two\n".to_string(), RegisterKind::Linewise, None);
         }
         let before: Vec<String> = st.registers.numbered().to_vec();
         let mut cursor = Position { line: 0, byte: 0 };
@@ -1847,6 +2757,29 @@ mod tests {
         m.clear();
         assert!(!m.is_active());
     }
+
+    #[test]
+    fn selection_size_counts_clusters_on_one_line_else_lines() {
+        let buf = Buffer::from_str("t", "a界e\u{301}z\nb\nc\n").unwrap();
+        let at = |line, byte| Position { line, byte };
+        let chars = |a, b| SelectionSpan::anchored(a, b, SelectionKind::Characterwise).size(&buf);
+        assert_eq!(chars(at(0, 0), at(0, 0)), SelectionSize::Chars(1));
+        // The cluster under the end is included, whichever end the cursor is.
+        assert_eq!(chars(at(0, 4), at(0, 0)), SelectionSize::Chars(3));
+        assert_eq!(chars(at(0, 1), at(0, 7)), SelectionSize::Chars(3));
+        assert_eq!(chars(at(2, 0), at(0, 3)), SelectionSize::Lines(3));
+        let lines = SelectionSpan::new(at(1, 0), at(1, 0), SelectionKind::Linewise);
+        assert_eq!(lines.size(&buf), SelectionSize::Lines(1));
+
+        let mut st = EditorState::new(buf);
+        assert_eq!(st.selection_size(), None);
+        st.selection.set(SelectionSpan::new(
+            at(0, 0),
+            at(1, 0),
+            SelectionKind::Characterwise,
+        ));
+        assert_eq!(st.selection_size(), Some(SelectionSize::Lines(2)));
+    }
 }
 
 // Test module for span deletion API (Phase 4 Step 5)
@@ -1944,31 +2877,53 @@ fn absolute_position(buffer: &Buffer, abs: usize) -> Position {
 
 #[cfg(test)]
 mod register_tests {
-    use super::{OperatorMetrics, Registers};
+    use super::{OperatorMetrics, RegisterKind, RegisterOp, Registers};
 
     #[test]
-    fn yank_populates_unnamed_and_ring() {
+    fn yank_populates_unnamed_and_register_zero() {
         let mut r = Registers::new();
         let mut m = OperatorMetrics::default();
         r.record_yank("alpha", &mut m);
         assert_eq!(r.unnamed, "alpha");
-        assert_eq!(r.numbered(), &["alpha".to_string()]);
+        assert_eq!(r.numbered()[0], "alpha");
+        assert!(r.numbered()[1..].iter().all(String::is_empty));
+        r.record_yank("beta\n", &mut m);
+        assert_eq!(r.numbered()[0], "beta\n");
+        assert_eq!(r.kind('0'), RegisterKind::Linewise);
+        assert!(r.numbered()[1].is_empty(), "yanks never shift the deletes");
     }
 
     #[test]
-    fn delete_rotates_ring_capped() {
+    fn line_deletes_shift_through_one_to_nine() {
         let mut r = Registers::new();
         let mut m = OperatorMetrics::default();
         for i in 0..12 {
-            // exceed capacity intentionally
-            r.record_delete(format!("d{i}"), &mut m);
+            // more deletes than "1-"9 can hold
+            r.record_delete(format!("d{i}\n"), &mut m);
         }
         assert_eq!(r.numbered().len(), Registers::MAX);
-        // Newest at 0
-        assert_eq!(r.numbered()[0], "d11");
-        // Oldest retained should be d2 (d0,d1 dropped after overflow)
-        assert_eq!(r.numbered().last().unwrap(), "d2");
-        assert_eq!(r.unnamed, "d11");
+        assert!(r.numbered()[0].is_empty());
+        assert_eq!(r.numbered()[1], "d11\n");
+        // d0-d2 were shifted out past "9.
+        assert_eq!(r.numbered()[9], "d3\n");
+        assert_eq!(r.unnamed, "d11\n");
+    }
+
+    #[test]
+    fn small_deletes_go_to_minus_without_shifting() {
+        let mut r = Registers::new();
+        let mut m = OperatorMetrics::default();
+        r.record_delete("one\n", &mut m);
+        r.record_delete("word", &mut m);
+        assert_eq!(r.small_delete(), "word");
+        assert_eq!(r.unnamed, "word");
+        assert_eq!(r.numbered()[1], "one\n");
+        assert!(r.numbered()[2].is_empty());
+        // Charwise text spanning lines is not small.
+        r.record_delete("end\nstart", &mut m);
+        assert_eq!(r.numbered()[1], "end\nstart");
+        assert_eq!(r.numbered()[2], "one\n");
+        assert_eq!(r.small_delete(), "word");
     }
 
     #[test]
@@ -1976,76 +2931,41 @@ mod register_tests {
         let mut r = Registers::new();
         let mut m = OperatorMetrics::default();
         r.record_yank("y1", &mut m);
-        r.record_delete("d1", &mut m);
+        r.record_delete("d1\n", &mut m);
         r.record_yank("y2", &mut m);
-        let ring: Vec<_> = r.numbered().iter().map(|s| s.as_str()).collect();
-        assert_eq!(ring, vec!["y2", "d1", "y1"]);
-        assert_eq!(r.unnamed, "y2");
+        r.record_delete("d2\n", &mut m);
+        let regs: Vec<_> = r.numbered()[..4].iter().map(|s| s.as_str()).collect();
+        assert_eq!(regs, vec!["y2", "d2\n", "d1\n", ""]);
+        assert_eq!(r.unnamed, "d2\n");
     }
 
-    // --- Additional invariant tests (Refactor R4 Step 12) ---
-
     #[test]
-    fn mixed_overflow_sequence_invariants() {
+    fn unnamed_always_matches_last_write() {
         let mut r = Registers::new();
         let mut m = OperatorMetrics::default();
-        // Sequence longer than capacity with interleaved yanks/deletes.
         for i in 0..(Registers::MAX + 5) {
             if i % 2 == 0 {
                 r.record_yank(format!("y{i}"), &mut m);
+                assert_eq!(r.unnamed, r.numbered()[0]);
             } else {
-                r.record_delete(format!("d{i}"), &mut m);
+                r.record_delete(format!("d{i}\n"), &mut m);
+                assert_eq!(r.unnamed, r.numbered()[1]);
             }
-            // Head must always equal unnamed.
-            assert_eq!(r.unnamed, r.numbered()[0]);
-            assert!(r.numbered().len() <= Registers::MAX);
+            assert_eq!(r.numbered().len(), Registers::MAX);
         }
-        assert_eq!(r.unnamed, r.numbered()[0]);
-        // Oldest entry is within capacity and is one of the expected prefixes.
-        let oldest = r.numbered().last().unwrap();
-        assert!(oldest.starts_with('y') || oldest.starts_with('d'));
     }
 
     #[test]
-    fn unnamed_always_matches_head_across_ops() {
+    fn metrics_shift_and_writes_counts() {
         let mut r = Registers::new();
         let mut m = OperatorMetrics::default();
-        for i in 0..30 {
-            // multiple overflows via deletes
-            r.record_delete(format!("del{i}"), &mut m);
-            assert_eq!(r.unnamed, r.numbered()[0]);
-        }
-        for i in 0..30 {
-            // then yanks
-            r.record_yank(format!("yank{i}"), &mut m);
-            assert_eq!(r.unnamed, r.numbered()[0]);
-        }
-    }
-
-    #[test]
-    fn duplicate_payload_does_not_merge_order_preserved() {
-        let mut r = Registers::new();
-        let mut m = OperatorMetrics::default();
-        r.record_yank("same", &mut m);
-        r.record_delete("same", &mut m); // identical payload via different op
-        r.record_yank("same", &mut m);
-        let ring = r.numbered();
-        assert_eq!(ring.len(), 3);
-        assert_eq!(ring[0], "same");
-        assert_eq!(ring[1], "same");
-        assert_eq!(ring[2], "same");
-    }
-
-    #[test]
-    fn metrics_rotation_and_writes_counts() {
-        let mut r = Registers::new();
-        let mut m = OperatorMetrics::default();
-        for i in 0..(Registers::MAX + 3) {
-            // overflow three times
-            r.record_delete(format!("x{i}"), &mut m);
+        for i in 0..(Registers::MAX + 2) {
+            r.record_delete(format!("x{i}\n"), &mut m);
         }
-        assert_eq!(m.register_writes, (Registers::MAX + 3) as u64);
-        // First MAX fills without rotation; remaining 3 trigger rotation metric increments.
+        r.record_delete("small", &mut m);
+        r.record_yank("y", &mut m);
+        assert_eq!(m.register_writes, (Registers::MAX + 4) as u64);
+        // The first nine line deletes fill "1-"9; the rest each drop "9.
         assert_eq!(m.numbered_ring_rotations, 3);
     }
 
@@ -2056,9 +2976,9 @@ mod register_tests {
         let mut m = OperatorMetrics::default();
         r.record_yank_named('a', "alpha", &mut m);
         assert_eq!(r.get_named('a'), Some("alpha"));
-        // unnamed mirrors named payload
+        // unnamed mirrors named payload; "0 is only for unnamed yanks
         assert_eq!(r.unnamed, "alpha");
-        assert_eq!(r.numbered()[0], "alpha");
+        assert!(r.numbered()[0].is_empty());
     }
 
     #[test]
@@ -2070,7 +2990,7 @@ mod register_tests {
         assert_eq!(r.get_named('a'), Some("foobar"));
         assert_eq!(r.get_named('A'), Some("foobar"));
         assert_eq!(r.unnamed, "foobar");
-        assert_eq!(r.numbered()[0], "foobar");
+        assert!(r.numbered()[0].is_empty());
     }
 
     #[test]
@@ -2093,35 +3013,40 @@ mod register_tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_change("removed", None);
+            regs.write_change("removed", RegisterKind::Charwise, None);
         }
         assert_eq!(st.registers.unnamed, "removed");
-        assert_eq!(st.registers.numbered()[0], "removed");
+        assert_eq!(st.registers.small_delete(), "removed");
+        {
+            let mut regs = st.registers_facade();
+            regs.write_change("line\n", RegisterKind::Linewise, None);
+        }
+        assert_eq!(st.registers.numbered()[1], "line\n");
         let metrics = st.operator_metrics_snapshot();
-        assert_eq!(metrics.operator_change, 1);
-        assert_eq!(metrics.register_writes, 1);
+        assert_eq!(metrics.operator_change, 2);
+        assert_eq!(metrics.register_writes, 2);
     }
 
     #[test]
-    fn facade_delete_rotates_ring_and_metrics() {
+    fn facade_delete_shifts_numbered_and_metrics() {
         use super::EditorState;
         use core_text::Buffer;
         let buf = Buffer::from_str("t", "").unwrap();
         let mut st = EditorState::new(buf);
         for i in 0..(Registers::MAX + 2) {
             let mut regs = st.registers_facade();
-            regs.write_delete(format!("d{i}"), None);
+            regs.write_delete(format!("d{i}"), RegisterKind::Linewise, None);
         }
         assert_eq!(st.registers.numbered().len(), Registers::MAX);
         assert_eq!(
-            st.registers.numbered()[0],
+            st.registers.numbered()[1],
             format!("d{}", Registers::MAX + 1)
         );
         assert_eq!(st.registers.unnamed, format!("d{}", Registers::MAX + 1));
         let metrics = st.operator_metrics_snapshot();
         assert_eq!(metrics.operator_delete, (Registers::MAX + 2) as u64);
         assert_eq!(metrics.register_writes, (Registers::MAX + 2) as u64);
-        assert_eq!(metrics.numbered_ring_rotations, 2);
+        assert_eq!(metrics.numbered_ring_rotations, 3);
     }
 
     #[test]
@@ -2132,18 +3057,50 @@ mod register_tests {
         let mut st = EditorState::new(buf);
         {
             let mut regs = st.registers_facade();
-            regs.write_yank("foo", Some('a'));
+            regs.write_yank("foo", RegisterKind::Charwise, Some('a'));
         }
         {
             let mut regs = st.registers_facade();
-            regs.write_change("bar", Some('A'));
+            regs.write_change("bar", RegisterKind::Charwise, Some('A'));
         }
         assert_eq!(st.registers.get_named('a'), Some("foobar"));
         assert_eq!(st.registers.unnamed, "foobar");
-        assert_eq!(st.registers.numbered()[0], "foobar");
+        assert!(st.registers.numbered()[0].is_empty());
         let metrics = st.operator_metrics_snapshot();
         assert_eq!(metrics.operator_yank, 1);
         assert_eq!(metrics.operator_change, 1);
         assert_eq!(metrics.register_writes, 2);
     }
+    #[test]
+    fn linewise_append_to_charwise_register_starts_new_line() {
+        let mut r = Registers::new();
+        let mut m = OperatorMetrics::default();
+        let yank = RegisterOp::Yank;
+        r.record(
+            Some('q'),
+            "word".into(),
+            RegisterKind::Charwise,
+            yank,
+            &mut m,
+        );
+        r.record(
+            Some('Q'),
+            "line\n".into(),
+            RegisterKind::Linewise,
+            yank,
+            &mut m,
+        );
+        assert_eq!(r.get_named('q'), Some("word\nline\n"));
+        assert_eq!(r.kind('q'), RegisterKind::Linewise);
+        assert_eq!(r.kind('"'), RegisterKind::Linewise);
+        let delete = RegisterOp::Delete;
+        r.record(None, "a\n".into(), RegisterKind::Linewise, delete, &mut m);
+        r.record(None, "b\nc".into(), RegisterKind::Charwise, delete, &mut m);
+        assert_eq!(r.kind('1'), RegisterKind::Charwise);
+        assert_eq!(
+            r.kind('2'),
+            RegisterKind::Linewise,
+            "kinds shift with the text"
+        );
+    }
 }
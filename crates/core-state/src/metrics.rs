@@ -0,0 +1,160 @@
+//! Metrics snapshot export as JSON (`:metrics dump`, the `[metrics]` sink).
+//!
+//! Serializes the snapshots the runtime copies into the state after every
+//! frame (render path, scheduler deltas, input counters) plus the operator
+//! counters, so a perf regression can be captured headlessly (CI, bug
+//! reports) and diffed. Every value is an unsigned counter or nanosecond
+//! reading under a fixed ASCII key, so the JSON is written by hand:
+//!
+//! ```text
+//! {"version":2,"unix_ms":1700000000000,
+//! "render_path":{"full_frames":3,...},
+//! "scheduler":{"full":1,...},
+//! "operators":{"delete":0,...},
+//! "input":{"keypress_total":42,...}}
+//! ```
+//!
+//! A section with no snapshot yet (nothing rendered) is `null`. The compact
+//! form is one line, for JSON Lines sinks; the pretty form breaks after each
+//! section.
+
+use crate::EditorState;
+
+/// Bumped when keys are renamed or removed (adding keys keeps it).
+pub const METRICS_JSON_VERSION: u32 = 2;
+
+/// JSON document of the current metrics; `unix_ms` stamps the capture.
+pub fn metrics_json(state: &EditorState, unix_ms: u128, pretty: bool) -> String {
+    let rp = state.last_render_path.map(|rp| {
+        object(&[
+            ("full_frames", rp.full_frames),
+            ("partial_frames", rp.partial_frames),
+            ("cursor_only_frames", rp.cursor_only_frames),
+            ("lines_frames", rp.lines_frames),
+            ("escalated_large_set", rp.escalated_large_set),
+            ("resize_invalidations", rp.resize_invalidations),
+            ("dirty_lines_marked", rp.dirty_lines_marked),
+            ("dirty_candidate_lines", rp.dirty_candidate_lines),
+            ("dirty_lines_repainted", rp.dirty_lines_repainted),
+            ("print_commands", rp.print_commands),
+            ("cells_printed", rp.cells_printed),
+            ("scroll_region_shifts", rp.scroll_region_shifts),
+            ("scroll_region_lines_saved", rp.scroll_region_lines_saved),
+            ("scroll_shift_degraded_full", rp.scroll_shift_degraded_full),
+            ("trim_attempts", rp.trim_attempts),
+            ("trim_success", rp.trim_success),
+            ("cols_saved_total", rp.cols_saved_total),
+            ("status_skipped", rp.status_skipped),
+            ("coalesced_decisions", rp.coalesced_decisions),
+            ("coalesced_frames", rp.coalesced_frames),
+            ("full_p50_ns", rp.full_latency.p50_ns),
+            ("full_p95_ns", rp.full_latency.p95_ns),
+            ("full_p99_ns", rp.full_latency.p99_ns),
+            ("full_samples", rp.full_latency.samples),
+            ("cursor_p50_ns", rp.cursor_only_latency.p50_ns),
+            ("cursor_p95_ns", rp.cursor_only_latency.p95_ns),
+            ("cursor_p99_ns", rp.cursor_only_latency.p99_ns),
+            ("cursor_samples", rp.cursor_only_latency.samples),
+            ("lines_p50_ns", rp.lines_latency.p50_ns),
+            ("lines_p95_ns", rp.lines_latency.p95_ns),
+            ("lines_p99_ns", rp.lines_latency.p99_ns),
+            ("lines_samples", rp.lines_latency.samples),
+            ("scroll_p50_ns", rp.scroll_shift_latency.p50_ns),
+            ("scroll_p95_ns", rp.scroll_shift_latency.p95_ns),
+            ("scroll_p99_ns", rp.scroll_shift_latency.p99_ns),
+            ("scroll_samples", rp.scroll_shift_latency.samples),
+        ])
+    });
+    let rd = state.last_render_delta.map(|rd| {
+        object(&[
+            ("full", rd.full),
+            ("lines", rd.lines),
+            ("scroll", rd.scroll),
+            ("status_line", rd.status_line),
+            ("cursor_only", rd.cursor_only),
+            ("collapsed_scroll", rd.collapsed_scroll),
+            ("suppressed_scroll", rd.suppressed_scroll),
+            ("scroll_escalated", rd.scroll_escalated),
+            ("semantic_frames", rd.semantic_frames),
+        ])
+    });
+    let op = state.operator_metrics_snapshot();
+    let op = object(&[
+        ("delete", op.operator_delete),
+        ("yank", op.operator_yank),
+        ("change", op.operator_change),
+        ("register_writes", op.register_writes),
+        ("numbered_ring_rotations", op.numbered_ring_rotations),
+    ]);
+    let input = state.last_input_telemetry.map(|it| {
+        object(&[
+            ("keypress_total", it.keypress_total),
+            ("keypress_repeat", it.keypress_repeat),
+            ("paste_sessions", it.paste_sessions),
+            ("paste_chunks", it.paste_chunks),
+            ("paste_bytes", it.paste_bytes),
+            ("channel_send_failures", it.send_failures),
+            ("channel_blocking_sends", it.blocking_sends),
+            ("input_preferred", it.input_preferred),
+            ("motions_coalesced", it.motions_coalesced),
+            ("resizes_suppressed", it.resizes_suppressed),
+            ("async_input_starts", it.async_input_starts),
+            ("async_input_stops", it.async_input_stops),
+        ])
+    });
+    let null = || "null".to_string();
+    let sep = if pretty { ",\n" } else { "," };
+    format!(
+        "{{\"version\":{METRICS_JSON_VERSION},\"unix_ms\":{unix_ms}{sep}\"render_path\":{}{sep}\"scheduler\":{}{sep}\"operators\":{op}{sep}\"input\":{}}}",
+        rp.unwrap_or_else(null),
+        rd.unwrap_or_else(null),
+        input.unwrap_or_else(null),
+    )
+}
+
+fn object(fields: &[(&str, u64)]) -> String {
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("\"{key}\":{value}"))
+        .collect();
+    format!("{{{}}}", body.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InputTelemetryLite, LatencyLite, RenderPathSnapshotLite};
+    use core_text::Buffer;
+
+    #[test]
+    fn exports_every_section() {
+        let mut st = EditorState::new(Buffer::from_str("t", "a\n").unwrap());
+        let cold = metrics_json(&st, 5, false);
+        assert!(cold.starts_with("{\"version\":2,\"unix_ms\":5,\"render_path\":null,"));
+        assert!(cold.contains("\"operators\":{\"delete\":0,"));
+        assert!(!cold.contains('\n'));
+
+        st.last_render_path = Some(RenderPathSnapshotLite {
+            full_frames: 2,
+            cells_printed: 80,
+            full_latency: LatencyLite {
+                p50_ns: 1023,
+                samples: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        st.last_input_telemetry = Some(InputTelemetryLite {
+            keypress_total: 7,
+            ..Default::default()
+        });
+        let pretty = metrics_json(&st, 5, true);
+        assert_eq!(pretty.lines().count(), 5);
+        assert!(pretty.contains("\"full_frames\":2,"));
+        assert!(pretty.contains("\"cells_printed\":80,"));
+        assert!(pretty.contains("\"full_p50_ns\":1023,"));
+        assert!(pretty.contains("\"full_samples\":2,"));
+        assert!(pretty.contains("\"input\":{\"keypress_total\":7,"));
+        assert!(pretty.ends_with("}}"));
+    }
+}
@@ -0,0 +1,475 @@
+//! Session state persistence across restarts (ShaDa-style).
+//!
+//! On orderly shutdown the runtime writes the unnamed, numbered, named and
+//! search registers, the command history and the last cursor position of
+//! recently edited files to a small state file; on startup it reads the
+//! file back. Like Vim's `'shada'` `<` and `s` items, register contents
+//! over the configured line or byte limit are skipped instead of truncated,
+//! and history is capped to the newest entries. Like the `'` item, only the
+//! most recently left files keep their position.
+//!
+//! A file's position (Vim's `'"` mark) is remembered whenever a window
+//! leaves it and, unless `[shada] restore_cursor` is off, put back when it
+//! is opened again, clamped to the text as it is now.
+//!
+//! Format (versioned, plain text so it survives hand inspection):
+//!
+//! ```text
+//! oxidized shada 1
+//! reg a 6
+//! hello
+//!
+//! hist : 6
+//! set nu
+//! mark " 17
+//! 41 4 /src/main.rs
+//! ```
+//!
+//! Each record is a `{kind} {key} {len}` header line followed by exactly
+//! `len` bytes of payload and a newline, so payloads may contain newlines.
+//! Unknown record kinds are ignored so later versions can add records
+//! without breaking older readers; a different format version is rejected.
+
+use crate::{BufferId, COMMAND_HISTORY_MAX, EditorState, RegisterKind, Registers};
+use core_text::Position;
+use std::path::{Path, PathBuf};
+
+/// Current on-disk format version.
+pub const SHADA_VERSION: u32 = 1;
+const SHADA_MAGIC: &str = "oxidized shada";
+
+/// Size limits applied when writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShadaLimits {
+    /// Registers larger than this many bytes are not saved (Vim `s10`).
+    pub max_item_bytes: usize,
+    /// Registers with more lines than this are not saved (Vim `<50`).
+    pub max_lines: usize,
+    /// Newest command history entries kept.
+    pub max_history: usize,
+    /// Most recently left files whose cursor position is kept (Vim `'100`).
+    pub max_files: usize,
+}
+
+impl Default for ShadaLimits {
+    fn default() -> Self {
+        Self {
+            max_item_bytes: 10 * 1024,
+            max_lines: 50,
+            max_history: COMMAND_HISTORY_MAX,
+            max_files: 100,
+        }
+    }
+}
+
+/// Decoded state file contents.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShadaData {
+    pub unnamed: String,
+    /// Numbered registers indexed by digit (`"0` first); unset ones are empty.
+    pub numbered: Vec<String>,
+    /// Non-empty named registers (`a`-`z`).
+    pub named: Vec<(char, String)>,
+    pub search: String,
+    /// Command history, oldest first.
+    pub history: Vec<String>,
+    /// Last cursor position per absolute file path, newest first.
+    pub files: Vec<(PathBuf, Position)>,
+}
+
+/// Last cursor position of recently left files, newest first.
+#[derive(Debug, Clone)]
+pub struct FilePositions {
+    /// Put the cursor back when a file is opened (`[shada] restore_cursor`).
+    pub restore: bool,
+    entries: Vec<(PathBuf, Position)>,
+}
+
+impl Default for FilePositions {
+    fn default() -> Self {
+        Self {
+            restore: true,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl FilePositions {
+    /// Record `pos` as the last position in `path`.
+    pub fn remember(&mut self, path: &Path, pos: Position) {
+        let path = absolute(path);
+        self.entries.retain(|(p, _)| *p != path);
+        self.entries.insert(0, (path, pos));
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Position> {
+        let path = absolute(path);
+        self.entries
+            .iter()
+            .find_map(|(p, pos)| (*p == path).then_some(*pos))
+    }
+
+    pub fn entries(&self) -> &[(PathBuf, Position)] {
+        &self.entries
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[derive(Debug)]
+pub enum ShadaError {
+    Read(std::io::Error),
+    Write(std::io::Error),
+    Corrupt(String),
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for ShadaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShadaError::Read(e) => write!(f, "E195: Cannot open ShaDa file for reading: {e}"),
+            ShadaError::Write(e) => write!(f, "E138: Can't write ShaDa file: {e}"),
+            ShadaError::Corrupt(why) => write!(f, "E575: Error while reading ShaDa file: {why}"),
+            ShadaError::UnsupportedVersion(v) => write!(
+                f,
+                "E575: Error while reading ShaDa file: unsupported version {v}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShadaError {}
+
+fn push_record(out: &mut String, kind: &str, key: char, payload: &str) {
+    out.push_str(&format!("{kind} {key} {}\n{payload}\n", payload.len()));
+}
+
+impl ShadaData {
+    pub fn encode(&self) -> String {
+        let mut out = format!("{SHADA_MAGIC} {SHADA_VERSION}\n");
+        if !self.unnamed.is_empty() {
+            push_record(&mut out, "reg", '"', &self.unnamed);
+        }
+        for (idx, text) in self.numbered.iter().enumerate().take(Registers::MAX) {
+            if !text.is_empty() {
+                push_record(&mut out, "reg", (b'0' + idx as u8) as char, text);
+            }
+        }
+        for (name, text) in &self.named {
+            push_record(&mut out, "reg", *name, text);
+        }
+        if !self.search.is_empty() {
+            push_record(&mut out, "reg", '/', &self.search);
+        }
+        for cmd in &self.history {
+            push_record(&mut out, "hist", ':', cmd);
+        }
+        for (path, pos) in &self.files {
+            let payload = format!("{} {} {}", pos.line, pos.byte, path.display());
+            push_record(&mut out, "mark", '"', &payload);
+        }
+        out
+    }
+
+    pub fn decode(text: &str) -> Result<Self, ShadaError> {
+        let corrupt = |why: &str| ShadaError::Corrupt(why.to_string());
+        let (header, mut rest) = text.split_once('\n').ok_or_else(|| corrupt("empty file"))?;
+        let version = header
+            .strip_prefix(SHADA_MAGIC)
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .ok_or_else(|| corrupt("missing header"))?;
+        if version != SHADA_VERSION {
+            return Err(ShadaError::UnsupportedVersion(version));
+        }
+        let mut data = ShadaData::default();
+        while !rest.is_empty() {
+            let (line, after) = rest
+                .split_once('\n')
+                .ok_or_else(|| corrupt("truncated record header"))?;
+            let mut parts = line.splitn(3, ' ');
+            let (Some(kind), Some(key), Some(len)) = (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(corrupt("malformed record header"));
+            };
+            let mut key_chars = key.chars();
+            let (Some(key), None) = (key_chars.next(), key_chars.next()) else {
+                return Err(corrupt("malformed record key"));
+            };
+            let len: usize = len.parse().map_err(|_| corrupt("malformed length"))?;
+            let payload = after
+                .get(..len)
+                .ok_or_else(|| corrupt("truncated record"))?
+                .to_string();
+            rest = after[len..]
+                .strip_prefix('\n')
+                .ok_or_else(|| corrupt("missing record terminator"))?;
+            match (kind, key) {
+                ("reg", '"') => data.unnamed = payload,
+                ("reg", '/') => data.search = payload,
+                ("reg", d @ '0'..='9') => {
+                    let idx = d as usize - '0' as usize;
+                    if data.numbered.len() <= idx {
+                        data.numbered.resize(idx + 1, String::new());
+                    }
+                    data.numbered[idx] = payload;
+                }
+                ("reg", c @ 'a'..='z') => data.named.push((c, payload)),
+                ("hist", ':') => data.history.push(payload),
+                ("mark", '"') => {
+                    let mut parts = payload.splitn(3, ' ');
+                    let (Some(line), Some(byte), Some(path)) =
+                        (parts.next(), parts.next(), parts.next())
+                    else {
+                        return Err(corrupt("malformed file position"));
+                    };
+                    let (Ok(line), Ok(byte)) = (line.parse(), byte.parse()) else {
+                        return Err(corrupt("malformed file position"));
+                    };
+                    data.files
+                        .push((PathBuf::from(path), Position::new(line, byte)));
+                }
+                _ => {
+                    tracing::debug!(target: "state.shada", kind, %key, "shada_record_skipped");
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+impl EditorState {
+    /// Registers and history to persist, with `limits` applied.
+    pub fn shada_snapshot(&self, limits: ShadaLimits) -> ShadaData {
+        let fits = |text: &str| {
+            text.len() <= limits.max_item_bytes && text.lines().count() <= limits.max_lines
+        };
+        let regs = &self.registers;
+        let history = self.command_line.history();
+        ShadaData {
+            unnamed: Some(regs.unnamed.clone())
+                .filter(|t| fits(t))
+                .unwrap_or_default(),
+            // Indexed by digit: an oversized register is left empty in place.
+            numbered: {
+                let mut numbered: Vec<String> = regs
+                    .numbered()
+                    .iter()
+                    .map(|t| if fits(t) { t.clone() } else { String::new() })
+                    .collect();
+                while numbered.last().is_some_and(String::is_empty) {
+                    numbered.pop();
+                }
+                numbered
+            },
+            named: regs
+                .named_snapshot()
+                .into_iter()
+                .filter(|(_, t)| fits(t))
+                .map(|(c, t)| (c, t.to_string()))
+                .collect(),
+            search: Some(regs.search().to_string())
+                .filter(|t| fits(t))
+                .unwrap_or_default(),
+            history: history[history.len().saturating_sub(limits.max_history)..].to_vec(),
+            files: self
+                .file_positions
+                .entries()
+                .iter()
+                .take(limits.max_files)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Replace registers and command history with persisted `data`.
+    pub fn restore_shada(&mut self, data: ShadaData) {
+        let regs = &mut self.registers;
+        // Kinds are not persisted; the trailing-newline heuristic recovers
+        // linewise vs charwise for everything but blockwise text.
+        regs.unnamed_kind = RegisterKind::infer(&data.unnamed);
+        regs.unnamed = data.unnamed;
+        regs.numbered = std::array::from_fn(|_| String::new());
+        for (slot, text) in regs.numbered.iter_mut().zip(data.numbered) {
+            *slot = text;
+        }
+        regs.numbered_kinds = std::array::from_fn(|i| RegisterKind::infer(&regs.numbered[i]));
+        regs.named = std::array::from_fn(|_| String::new());
+        regs.named_kinds = [RegisterKind::Charwise; 26];
+        for (c, text) in data.named {
+            if let Some(idx) = Registers::named_index(c) {
+                regs.named_kinds[idx] = RegisterKind::infer(&text);
+                regs.named[idx] = text;
+            }
+        }
+        regs.set_search(data.search);
+        for cmd in &data.history {
+            self.command_line.record_history(cmd);
+        }
+        self.file_positions.entries = data.files;
+    }
+
+    /// Remember `pos` as the cursor position in buffer `id`'s file, which a
+    /// window is leaving. Buffers without a file and directory listings
+    /// have none.
+    pub fn remember_cursor(&mut self, id: BufferId, pos: Position) {
+        let Some(meta) = self.buffers.get(id).map(|entry| &entry.meta) else {
+            return;
+        };
+        if meta.explorer.is_some() {
+            return;
+        }
+        if let Some(path) = meta.path.clone() {
+            self.file_positions.remember(&path, pos);
+        }
+    }
+
+    /// Where to put the cursor in the active buffer's just opened file: its
+    /// remembered position moved onto the text, unless restoring is off.
+    pub fn restored_cursor(&self) -> Option<Position> {
+        if !self.file_positions.restore || self.explorer().is_some() {
+            return None;
+        }
+        let pos = self.file_positions.get(self.file_name()?)?;
+        let buffer = self.active_buffer();
+        let line = pos.line.min(buffer.line_count().saturating_sub(1));
+        let text = buffer.line(line).unwrap_or_default();
+        let text = text.trim_end_matches(['\n', '\r']);
+        // Normal mode: on the last character at most.
+        let mut byte = pos.byte.min(text.len().saturating_sub(1));
+        while !text.is_char_boundary(byte) {
+            byte -= 1;
+        }
+        Some(Position::new(line, byte))
+    }
+
+    /// Write the state file (via a temporary file renamed into place so a
+    /// crash mid-write never leaves a truncated file behind).
+    pub fn write_shada(&self, path: &Path, limits: ShadaLimits) -> Result<(), ShadaError> {
+        let data = self.shada_snapshot(limits).encode();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(ShadaError::Write)?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(ShadaError::Write)?;
+        tracing::debug!(target: "state.shada", path = %path.display(), "shada_written");
+        Ok(())
+    }
+
+    /// Load the state file if it exists. Returns false when there is none.
+    pub fn read_shada(&mut self, path: &Path) -> Result<bool, ShadaError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(ShadaError::Read(e)),
+        };
+        let data = ShadaData::decode(&text)?;
+        tracing::debug!(
+            target: "state.shada",
+            path = %path.display(),
+            named = data.named.len(),
+            history = data.history.len(),
+            "shada_read"
+        );
+        self.restore_shada(data);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OperatorMetrics;
+    use core_text::Buffer;
+
+    fn state() -> EditorState {
+        EditorState::new(Buffer::from_str("t", "").unwrap())
+    }
+
+    #[test]
+    fn round_trips_registers_and_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("shada");
+        let mut st = state();
+        let mut m = OperatorMetrics::default();
+        st.registers.record_yank("one\n", &mut m);
+        st.registers.record_delete("two\nlines\n", &mut m);
+        st.registers.record_yank_named('q', "named", &mut m);
+        st.registers.set_search("fo+");
+        st.command_line.record_history("set nu");
+        st.command_line.record_history("w");
+        let file = dir.path().join("a b.txt");
+        st.file_positions.remember(&file, Position::new(3, 1));
+        st.write_shada(&path, ShadaLimits::default()).unwrap();
+
+        let mut fresh = state();
+        assert!(fresh.read_shada(&path).unwrap());
+        assert_eq!(fresh.registers.unnamed, "named");
+        assert_eq!(fresh.registers.numbered(), st.registers.numbered());
+        assert_eq!(fresh.registers.get_named('q'), Some("named"));
+        assert_eq!(fresh.registers.search(), "fo+");
+        assert_eq!(fresh.command_line.history(), ["set nu", "w"]);
+        assert_eq!(fresh.file_positions.get(&file), Some(Position::new(3, 1)));
+        assert!(!fresh.read_shada(&dir.path().join("missing")).unwrap());
+    }
+
+    #[test]
+    fn limits_skip_large_items_and_trim_history() {
+        let mut st = state();
+        let mut m = OperatorMetrics::default();
+        st.registers.record_yank_named('a', "x\n".repeat(3), &mut m);
+        st.registers.record_yank_named('b', "ok", &mut m);
+        st.registers.record_yank("ok", &mut m);
+        st.registers.record_delete("y\n".repeat(3), &mut m);
+        for cmd in ["a", "b", "c"] {
+            st.command_line.record_history(cmd);
+        }
+        let limits = ShadaLimits {
+            max_item_bytes: 100,
+            max_lines: 2,
+            max_history: 2,
+            max_files: 1,
+        };
+        let data = st.shada_snapshot(limits);
+        assert_eq!(data.named, vec![('b', "ok".to_string())]);
+        assert_eq!(data.numbered, vec!["ok".to_string()]);
+        assert_eq!(data.history, ["b", "c"]);
+    }
+
+    #[test]
+    fn restores_the_last_position_clamped_to_the_text() {
+        let mut st = EditorState::new(Buffer::from_str("t", "one\nfour").unwrap());
+        let path = Path::new("some/file.txt");
+        st.set_file_name(Some(path.to_path_buf()));
+        assert_eq!(st.restored_cursor(), None);
+        let active = st.active;
+        st.remember_cursor(active, Position::new(1, 2));
+        st.file_positions
+            .remember(Path::new("other"), Position::new(0, 0));
+        assert_eq!(st.restored_cursor(), Some(Position::new(1, 2)));
+        // Newest first.
+        let newest = &st.file_positions.entries()[0].0;
+        assert!(newest.ends_with("other"));
+        st.remember_cursor(active, Position::new(7, 9));
+        assert_eq!(st.restored_cursor(), Some(Position::new(1, 3)));
+        st.file_positions.restore = false;
+        assert_eq!(st.restored_cursor(), None);
+    }
+
+    #[test]
+    fn rejects_other_versions_and_corruption() {
+        assert!(matches!(
+            ShadaData::decode("oxidized shada 99\n"),
+            Err(ShadaError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            ShadaData::decode("oxidized shada 1\nreg a 50\nshort\n"),
+            Err(ShadaError::Corrupt(_))
+        ));
+        let data = ShadaData::decode("oxidized shada 1\nmark x 1\n!\nreg a 2\nhi\n").unwrap();
+        assert_eq!(data.named, vec![('a', "hi".to_string())]);
+    }
+}
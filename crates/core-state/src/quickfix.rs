@@ -0,0 +1,347 @@
+//! Quickfix and location lists: positions in files with a message.
+//!
+//! There is one quickfix list (`EditorState::quickfix`), filled by `:grep`
+//! or `:make`, and every window has its own location list
+//! (`core_model::View`), filled by `:lgrep` or `:ldiag`. Both are the same
+//! `QuickfixList`: items keep the path as the producer printed it (relative
+//! to the working directory) with 0-based line and byte column, and `index`
+//! is the item last jumped to. `:make` output is read with
+//! `parse_errorformat`.
+//!
+//! `:copen` / `:lopen` show a list in a read-only buffer, one
+//! `file|line col c| text` line per item; `BufferMeta::quickfix` records
+//! which list the buffer shows so it can be redrawn as the list changes.
+
+use crate::{BufferId, EditorState};
+use core_text::Buffer;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickfixItem {
+    pub path: PathBuf,
+    /// 0-based line.
+    pub line: usize,
+    /// 0-based byte column.
+    pub col: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuickfixList {
+    /// The command that produced the list (`:grep foo`).
+    pub title: String,
+    pub items: Vec<QuickfixItem>,
+    /// Current item.
+    pub index: usize,
+}
+
+impl QuickfixList {
+    /// Empty the list for the results of `title`.
+    pub fn reset(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+        self.items.clear();
+        self.index = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Number of different files the items are in.
+    pub fn file_count(&self) -> usize {
+        let mut paths: Vec<&Path> = self.items.iter().map(|i| i.path.as_path()).collect();
+        paths.sort();
+        paths.dedup();
+        paths.len()
+    }
+
+    /// The item after (or before) the current one; `None` at the end.
+    pub fn step(&self, forward: bool) -> Option<usize> {
+        match forward {
+            true => Some(self.index + 1).filter(|&i| i < self.items.len()),
+            false => self.index.checked_sub(1).filter(|_| !self.is_empty()),
+        }
+    }
+
+    /// Text of a list window: one `file|line col c| text` line per item.
+    pub fn listing(&self) -> String {
+        self.items
+            .iter()
+            .map(|item| {
+                format!(
+                    "{}|{} col {}| {}\n",
+                    item.path.display(),
+                    item.line + 1,
+                    item.col + 1,
+                    item.text.trim()
+                )
+            })
+            .collect()
+    }
+}
+
+/// Which list a list window shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    Quickfix,
+    /// The location list of window `window` (a `core_model::ViewId`) in
+    /// the current tab page.
+    Location {
+        window: usize,
+    },
+}
+
+impl ListKind {
+    /// Buffer name of the list window.
+    pub fn name(self) -> &'static str {
+        match self {
+            ListKind::Quickfix => "[Quickfix List]",
+            ListKind::Location { .. } => "[Location List]",
+        }
+    }
+}
+
+impl EditorState {
+    /// The list shown in the active buffer, if it is a list window.
+    pub fn quickfix_window(&self) -> Option<ListKind> {
+        self.active_meta().quickfix
+    }
+
+    /// Show `listing` (`QuickfixList::listing`) in buffer `id` as the
+    /// `kind` list window, replacing its text and history.
+    pub fn fill_list_buffer(&mut self, id: BufferId, kind: ListKind, listing: &str) {
+        let Some(entry) = self.buffers.get_mut(id) else {
+            return;
+        };
+        let Ok(buffer) = Buffer::from_str(kind.name(), listing) else {
+            return;
+        };
+        entry.buffer = buffer;
+        let meta = &mut entry.meta;
+        meta.path = None;
+        meta.dirty = false;
+        meta.binary = None;
+        meta.hex_view = false;
+        meta.explorer = None;
+        meta.undo = crate::undo::UndoEngine::new();
+        meta.quickfix = Some(kind);
+    }
+}
+
+/// Parse a `grep -n` (`file:line:text`) or, with `column`, a
+/// `rg --vimgrep` (`file:line:col:text`) output line. Lines of another
+/// shape (`Binary file … matches`, warnings) give `None`.
+pub fn parse_grep_line(line: &str, column: bool) -> Option<QuickfixItem> {
+    // The first `:digits:` ends the file name, so names containing `:` (but
+    // not `:digits:`) still parse.
+    let mut from = 0;
+    let (path, rest) = loop {
+        let colon = from + line[from..].find(':')?;
+        let rest = &line[colon + 1..];
+        if rest.split(':').next().is_some_and(is_number) && colon > 0 {
+            break (&line[..colon], rest);
+        }
+        from = colon + 1;
+    };
+    let (line_nr, mut text) = rest.split_once(':')?;
+    let mut col: usize = 1;
+    if column {
+        let (c, t) = text.split_once(':')?;
+        col = c.parse().ok()?;
+        text = t;
+    }
+    Some(QuickfixItem {
+        path: PathBuf::from(path),
+        line: line_nr.parse::<usize>().ok()?.saturating_sub(1),
+        col: col.saturating_sub(1),
+        text: text.trim_end_matches('\r').to_string(),
+    })
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// One item of an `'errorformat'` pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EfmToken {
+    Literal(char),
+    /// `%f`: a file name, without whitespace.
+    File,
+    /// `%l`: line number.
+    Line,
+    /// `%c`: column number (bytes).
+    Col,
+    /// `%m`: the message.
+    Message,
+}
+
+/// What a pattern matched.
+#[derive(Debug, Default)]
+struct EfmMatch<'a> {
+    file: &'a str,
+    line: &'a str,
+    col: &'a str,
+    message: &'a str,
+}
+
+/// Parse an output line of `:make` with the first pattern of `efm` (the
+/// `'errorformat'` option) matching all of it. Patterns are separated by
+/// commas (`\,` is a literal one) and consist of text matched literally
+/// and `%f` (file name), `%l` (line), `%c` (column), `%m` (message) and
+/// `%%` (a `%`). A pattern without `%f` and `%l` never yields an item.
+pub fn parse_errorformat(line: &str, efm: &str) -> Option<QuickfixItem> {
+    let line = line.trim_end_matches('\r');
+    efm_patterns(efm).iter().find_map(|tokens| {
+        let mut found = EfmMatch::default();
+        if !efm_match(tokens, line, &mut found) || found.file.is_empty() {
+            return None;
+        }
+        let col: usize = found.col.parse().unwrap_or(1);
+        Some(QuickfixItem {
+            path: PathBuf::from(found.file),
+            line: found.line.parse::<usize>().ok()?.saturating_sub(1),
+            col: col.saturating_sub(1),
+            text: found.message.trim().to_string(),
+        })
+    })
+}
+
+fn efm_patterns(efm: &str) -> Vec<Vec<EfmToken>> {
+    let mut patterns = vec![Vec::new()];
+    let mut chars = efm.chars();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            ',' => {
+                patterns.push(Vec::new());
+                continue;
+            }
+            '\\' => EfmToken::Literal(chars.next().unwrap_or('\\')),
+            '%' => match chars.next() {
+                Some('f') => EfmToken::File,
+                Some('l') => EfmToken::Line,
+                Some('c') => EfmToken::Col,
+                Some('m') => EfmToken::Message,
+                Some(other) => EfmToken::Literal(other),
+                None => EfmToken::Literal('%'),
+            },
+            c => EfmToken::Literal(c),
+        };
+        if let Some(pattern) = patterns.last_mut() {
+            pattern.push(token);
+        }
+    }
+    patterns.retain(|p| !p.is_empty());
+    patterns
+}
+
+/// Match all of `s` against `tokens`, filling `found` on success. `%f` and
+/// `%m` take as little as lets the rest match (`%m` at the end takes the
+/// rest); numbers take all their digits.
+fn efm_match<'a>(tokens: &[EfmToken], s: &'a str, found: &mut EfmMatch<'a>) -> bool {
+    let Some((&token, rest)) = tokens.split_first() else {
+        return s.is_empty();
+    };
+    match token {
+        EfmToken::Literal(c) => s
+            .strip_prefix(c)
+            .is_some_and(|tail| efm_match(rest, tail, found)),
+        EfmToken::Line | EfmToken::Col => {
+            let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+            if end == 0 || !efm_match(rest, &s[end..], found) {
+                return false;
+            }
+            match token {
+                EfmToken::Line => found.line = &s[..end],
+                _ => found.col = &s[..end],
+            }
+            true
+        }
+        EfmToken::File | EfmToken::Message => {
+            let file = token == EfmToken::File;
+            let limit = match file {
+                true => s.find(char::is_whitespace).unwrap_or(s.len()),
+                false => s.len(),
+            };
+            let ends = s[..limit]
+                .char_indices()
+                .map(|(i, _)| i)
+                .skip(usize::from(file))
+                .chain([limit]);
+            for end in ends {
+                if efm_match(rest, &s[end..], found) {
+                    match file {
+                        true => found.file = &s[..end],
+                        false => found.message = &s[..end],
+                    }
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_grep_and_vimgrep_lines() {
+        let item = parse_grep_line("src/a.rs:12:let x = 1;", false).unwrap();
+        assert_eq!(item.path, PathBuf::from("src/a.rs"));
+        assert_eq!((item.line, item.col), (11, 0));
+        assert_eq!(item.text, "let x = 1;");
+
+        let item = parse_grep_line("src/a.rs:3:5:fn main() {", true).unwrap();
+        assert_eq!((item.line, item.col), (2, 4));
+        assert_eq!(item.text, "fn main() {");
+
+        // Text after the line number may itself contain `:`.
+        let item = parse_grep_line("a:b.txt:7:x: 1:2", false).unwrap();
+        assert_eq!(item.path, PathBuf::from("a:b.txt"));
+        assert_eq!(item.text, "x: 1:2");
+
+        assert_eq!(parse_grep_line("Binary file x matches", false), None);
+        assert_eq!(parse_grep_line("a.rs:3:text", true), None);
+    }
+
+    #[test]
+    fn parses_errorformat_lines() {
+        let efm = "%f:%l:%c: %m,%f:%l: %m";
+        let item = parse_errorformat("src/a.c:12:5: error: x undeclared", efm).unwrap();
+        assert_eq!(item.path, PathBuf::from("src/a.c"));
+        assert_eq!((item.line, item.col), (11, 4));
+        assert_eq!(item.text, "error: x undeclared");
+
+        // The second pattern, without a column.
+        let item = parse_errorformat("lib.rs:3: warning: unused", efm).unwrap();
+        assert_eq!((item.line, item.col), (2, 0));
+        assert_eq!(item.text, "warning: unused");
+
+        // File names hold no whitespace, so make's own lines do not match.
+        assert_eq!(
+            parse_errorformat("make: *** [Makefile:2: all] Error 1", efm),
+            None
+        );
+        assert_eq!(parse_errorformat("cc -o a a.c", efm), None);
+
+        let item = parse_errorformat("a.py(7), got %d", "%f(%l)\\, %m").unwrap();
+        assert_eq!((item.line, item.text.as_str()), (6, "got %d"));
+        assert!(parse_errorformat("50% done", "%m").is_none());
+    }
+
+    #[test]
+    fn counts_files() {
+        let mut list = QuickfixList::default();
+        list.reset(":grep x");
+        for line in ["a:1:x", "a:2:x", "b:1:x"] {
+            list.items.push(parse_grep_line(line, false).unwrap());
+        }
+        assert_eq!((list.len(), list.file_count()), (3, 2));
+    }
+}
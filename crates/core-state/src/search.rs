@@ -0,0 +1,160 @@
+//! Pattern search (`/`, `?`, `n`, `N`) and the match state `hlsearch` paints.
+//!
+//! Patterns are literal text (no Vim regex atoms yet). Case folding follows
+//! `ignorecase` / `smartcase` and is ASCII-only so match byte ranges are
+//! always ranges of the original line. Searches wrap around the buffer end
+//! (`wrapscan` behavior).
+
+use core_text::{Buffer, Position};
+use std::ops::Range;
+
+/// A pattern with the case rule resolved from the options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchPattern<'a> {
+    pub text: &'a str,
+    pub ignore_case: bool,
+}
+
+impl<'a> SearchPattern<'a> {
+    /// `ignorecase` folds case unless `smartcase` sees an uppercase letter.
+    pub fn new(text: &'a str, ignorecase: bool, smartcase: bool) -> Self {
+        let ignore_case = ignorecase && !(smartcase && text.chars().any(char::is_uppercase));
+        Self { text, ignore_case }
+    }
+
+    fn matches_at(&self, hay: &[u8], at: usize) -> bool {
+        let needle = self.text.as_bytes();
+        hay.get(at..at + needle.len()).is_some_and(|window| {
+            if self.ignore_case {
+                window.eq_ignore_ascii_case(needle)
+            } else {
+                window == needle
+            }
+        })
+    }
+
+    /// Byte ranges of the non-overlapping matches in `line`, left to right.
+    pub fn line_matches(&self, line: &str) -> Vec<Range<usize>> {
+        let len = self.text.len();
+        let mut out = Vec::new();
+        if len == 0 {
+            return out;
+        }
+        let hay = line.as_bytes();
+        let mut at = 0;
+        while at + len <= hay.len() {
+            if line.is_char_boundary(at) && self.matches_at(hay, at) {
+                out.push(at..at + len);
+                at += len;
+            } else {
+                at += 1;
+            }
+        }
+        out
+    }
+}
+
+/// Where a search landed and whether it wrapped past the buffer end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchHit {
+    pub position: Position,
+    pub wrapped: bool,
+}
+
+/// The first match strictly after (`forward`) or before `from`, wrapping
+/// around the buffer once.
+pub fn find_match(
+    buffer: &Buffer,
+    pattern: SearchPattern<'_>,
+    from: Position,
+    forward: bool,
+) -> Option<SearchHit> {
+    let lines = buffer.line_count();
+    if lines == 0 || pattern.text.is_empty() {
+        return None;
+    }
+    let matches_of = |line: usize| {
+        let text = buffer.line(line).unwrap_or_default();
+        pattern.line_matches(text.trim_end_matches(['\n', '\r']))
+    };
+    let hit = |line: usize, byte: usize, wrapped: bool| SearchHit {
+        position: Position::new(line, byte),
+        wrapped,
+    };
+    // Lines after (or before) the cursor line, then the wrapped remainder,
+    // ending back on the cursor line for matches on its far side.
+    for step in 0..=lines {
+        let wrapped = if forward {
+            from.line + step >= lines
+        } else {
+            step > from.line
+        };
+        let line = if forward {
+            (from.line + step) % lines
+        } else {
+            (from.line + lines - step % lines) % lines
+        };
+        let found = matches_of(line);
+        let candidate = match (step, forward) {
+            (0, true) => found.iter().find(|m| m.start > from.byte),
+            (0, false) => found.iter().rev().find(|m| m.start < from.byte),
+            (s, true) if s == lines => found.iter().find(|m| m.start <= from.byte),
+            (s, false) if s == lines => found.iter().rev().find(|m| m.start >= from.byte),
+            (_, true) => found.first(),
+            (_, false) => found.last(),
+        };
+        if let Some(m) = candidate {
+            return Some(hit(line, m.start, wrapped));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_matches_follow_case_options() {
+        let p = SearchPattern::new("ab", false, false);
+        assert_eq!(p.line_matches("ab aB abab"), [0..2, 6..8, 8..10]);
+        let p = SearchPattern::new("ab", true, true);
+        assert_eq!(p.line_matches("ab aB"), [0..2, 3..5]);
+        // smartcase: an uppercase letter makes the pattern case sensitive.
+        let p = SearchPattern::new("aB", true, true);
+        assert_eq!(p.line_matches("ab aB aB"), [3..5, 6..8]);
+        assert_eq!(
+            SearchPattern::new("é", false, false).line_matches("éé"),
+            [0..2, 2..4]
+        );
+    }
+
+    #[test]
+    fn find_match_moves_and_wraps_both_ways() {
+        let buf = Buffer::from_str("t", "foo x\nbar\nx foo\n").unwrap();
+        let p = SearchPattern::new("foo", false, false);
+        let at = |line, byte| Position::new(line, byte);
+        let next = find_match(&buf, p, at(0, 0), true).unwrap();
+        assert_eq!((next.position, next.wrapped), (at(2, 2), false));
+        let next = find_match(&buf, p, at(2, 2), true).unwrap();
+        assert_eq!((next.position, next.wrapped), (at(0, 0), true));
+        let prev = find_match(&buf, p, at(2, 2), false).unwrap();
+        assert_eq!((prev.position, prev.wrapped), (at(0, 0), false));
+        let prev = find_match(&buf, p, at(0, 0), false).unwrap();
+        assert_eq!((prev.position, prev.wrapped), (at(2, 2), true));
+
+        // A single match is found again from itself after wrapping.
+        let p = SearchPattern::new("bar", false, false);
+        let again = find_match(&buf, p, at(1, 0), true).unwrap();
+        assert_eq!((again.position, again.wrapped), (at(1, 0), true));
+        assert_eq!(
+            find_match(
+                &buf,
+                SearchPattern::new("zzz", false, false),
+                at(0, 0),
+                true
+            ),
+            None
+        );
+    }
+}
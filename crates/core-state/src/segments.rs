@@ -0,0 +1,86 @@
+//! Text of provider-contributed status line segments.
+//!
+//! The runtime stores each `StatusSegmentProvider` answer here; the status
+//! line shows the non-empty ones after the git branch. With an explicit
+//! order (`[statusline] segments`), only the listed names show, in that
+//! order; otherwise every segment shows in the order it first answered.
+
+#[derive(Debug, Default)]
+pub struct StatusSegments {
+    order: Option<Vec<String>>,
+    texts: Vec<(String, String)>,
+}
+
+impl StatusSegments {
+    /// Show only `names`, in this order.
+    pub fn set_order(&mut self, names: Vec<String>) {
+        self.order = Some(names);
+    }
+
+    /// Store (or with `None` remove) a segment's text. Returns whether the
+    /// visible status line changed.
+    pub fn set(&mut self, name: &str, text: Option<String>) -> bool {
+        let text = text.filter(|t| !t.is_empty());
+        let existing = self.texts.iter().position(|(n, _)| n == name);
+        let changed = match (existing, text) {
+            (Some(i), Some(text)) if self.texts[i].1 != text => {
+                self.texts[i].1 = text;
+                true
+            }
+            (Some(_), Some(_)) => false,
+            (Some(i), None) => {
+                self.texts.remove(i);
+                true
+            }
+            (None, Some(text)) => {
+                self.texts.push((name.to_string(), text));
+                true
+            }
+            (None, None) => false,
+        };
+        changed && self.shown(name)
+    }
+
+    fn shown(&self, name: &str) -> bool {
+        self.order
+            .as_ref()
+            .is_none_or(|order| order.iter().any(|n| n == name))
+    }
+
+    /// Visible segment texts in display order.
+    pub fn visible(&self) -> Vec<&str> {
+        match &self.order {
+            Some(order) => order
+                .iter()
+                .filter_map(|name| {
+                    self.texts
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, t)| t.as_str())
+                })
+                .collect(),
+            None => self.texts.iter().map(|(_, t)| t.as_str()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_order_filters_and_sorts() {
+        let mut segments = StatusSegments::default();
+        assert!(segments.set("wc", Some("12w".into())));
+        assert!(segments.set("lsp", Some("rust-analyzer".into())));
+        assert!(!segments.set("wc", Some("12w".into())));
+        assert_eq!(segments.visible(), ["12w", "rust-analyzer"]);
+
+        segments.set_order(vec!["lsp".into(), "wc".into()]);
+        assert_eq!(segments.visible(), ["rust-analyzer", "12w"]);
+        segments.set_order(vec!["wc".into()]);
+        assert!(!segments.set("lsp", None), "hidden segment changes nothing");
+        assert!(segments.set("wc", Some(String::new())));
+        assert!(segments.visible().is_empty());
+    }
+}
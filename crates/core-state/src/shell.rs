@@ -0,0 +1,101 @@
+//! External command requests (`:!`, `:r !`, `:{range}!`, `:sh`).
+//!
+//! The dispatcher is synchronous, so ex commands that run external programs
+//! only *queue* a `ShellRequest` here. The runtime drains the queue after each
+//! dispatch, runs the command on a background task, and feeds the captured
+//! output back through `Event::ShellOutput`. The `target` recorded at request
+//! time decides how the output is applied (message area, read below a line,
+//! or filter a line range). Output for a buffer goes to the buffer the
+//! command was issued in, and only while its text is unchanged.
+
+use crate::{BufferId, EditorState};
+use core_text::EditCursor;
+
+/// Buffer and window a `:r !` / `:{range}!` was issued in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShellOrigin {
+    pub buffer: BufferId,
+    /// Window (a `core_model::ViewId`) of the current tab page whose cursor
+    /// follows the output.
+    pub window: usize,
+    /// The buffer's edit log position when the command was queued.
+    pub seen: EditCursor,
+}
+
+impl ShellOrigin {
+    /// The active buffer of `state`, shown in `window`, as it is now.
+    pub fn active(state: &EditorState, window: usize) -> Self {
+        Self {
+            buffer: state.active,
+            window,
+            seen: state.active_buffer().edit_cursor(),
+        }
+    }
+
+    /// Whether the buffer is still open with the text the command saw.
+    pub fn is_current(&self, state: &EditorState) -> bool {
+        state
+            .buffers
+            .get(self.buffer)
+            .and_then(|entry| entry.buffer.edits_since(self.seen))
+            .is_some_and(|edits| edits.is_empty())
+    }
+}
+
+/// Where captured command output goes once the job completes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellTarget {
+    /// `:!cmd` — show output in the message area.
+    Message,
+    /// `:[line]r !cmd` — insert stdout linewise below `line`.
+    ReadAfter { origin: ShellOrigin, line: usize },
+    /// `:{range}!cmd` — replace lines `start..=end` with stdout (the lines
+    /// are fed to the command on stdin).
+    Filter {
+        origin: ShellOrigin,
+        start: usize,
+        end: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellRequest {
+    pub id: u64,
+    pub command: String,
+    pub stdin: Option<String>,
+    pub target: ShellTarget,
+}
+
+/// Queue of pending external command requests plus the interactive shell flag.
+#[derive(Debug, Default)]
+pub struct ShellQueue {
+    next_id: u64,
+    pending: Vec<ShellRequest>,
+    /// `:sh` requested; the runtime suspends the terminal and runs an
+    /// interactive shell before resuming.
+    pub interactive: bool,
+}
+
+impl ShellQueue {
+    /// Queue a request, returning its id.
+    pub fn push(&mut self, command: String, stdin: Option<String>, target: ShellTarget) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        tracing::debug!(target: "runtime.shell", id, command = %command, ?target, "shell_request_queued");
+        self.pending.push(ShellRequest {
+            id,
+            command,
+            stdin,
+            target,
+        });
+        id
+    }
+
+    pub fn take(&mut self) -> Vec<ShellRequest> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty() || self.interactive
+    }
+}
@@ -0,0 +1,200 @@
+//! Sign column registry (`:sign place` / `:sign unplace`).
+//!
+//! A sign is a one- or two-cell glyph attached to a buffer line, drawn in a
+//! two-cell column left of the line numbers. The column only exists while
+//! the buffer has at least one sign (Vim's `signcolumn=auto`). Producers such
+//! as git change markers or diagnostics place and remove signs here; the
+//! runtime drains `take_dirty` each cycle and turns the affected lines into
+//! render dirt, since a row whose text did not change still has to repaint.
+//!
+//! Signs keep their line number across edits; producers re-place them when
+//! their source data changes.
+
+use crate::BufferId;
+use core_text::grapheme;
+
+/// Cells reserved for the sign column when a buffer has signs.
+pub const SIGN_COLUMN_WIDTH: u16 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SignId(pub u32);
+
+impl std::fmt::Display for SignId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What a sign marks; the renderer maps this to cell attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignStyle {
+    #[default]
+    Default,
+    Error,
+    Warning,
+    Info,
+    Added,
+    Changed,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sign {
+    pub id: SignId,
+    pub buffer: BufferId,
+    pub line: usize,
+    pub glyph: String,
+    pub style: SignStyle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignError {
+    /// Glyph empty or wider than the sign column.
+    InvalidText(String),
+    InvalidId(SignId),
+}
+
+impl std::fmt::Display for SignError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignError::InvalidText(text) => write!(f, "E239: Invalid sign text: {text}"),
+            SignError::InvalidId(id) => write!(f, "E157: Invalid sign ID: {id}"),
+        }
+    }
+}
+
+impl std::error::Error for SignError {}
+
+#[derive(Debug, Default)]
+pub struct SignRegistry {
+    signs: Vec<Sign>,
+    next_id: u32,
+    /// Lines whose sign changed since the last `take_dirty`.
+    dirty: Vec<(BufferId, usize)>,
+}
+
+impl SignRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Place `glyph` on `line` of `buffer`. A later sign on the same line is
+    /// drawn over earlier ones.
+    pub fn place(
+        &mut self,
+        buffer: BufferId,
+        line: usize,
+        glyph: &str,
+        style: SignStyle,
+    ) -> Result<SignId, SignError> {
+        let width: usize = grapheme::iter(glyph).map(grapheme::cluster_width).sum();
+        if width == 0 || width > SIGN_COLUMN_WIDTH as usize || glyph.contains(char::is_control) {
+            return Err(SignError::InvalidText(glyph.to_string()));
+        }
+        let id = SignId(self.next_id);
+        self.next_id += 1;
+        self.signs.push(Sign {
+            id,
+            buffer,
+            line,
+            glyph: glyph.to_string(),
+            style,
+        });
+        self.dirty.push((buffer, line));
+        Ok(id)
+    }
+
+    pub fn unplace(&mut self, id: SignId) -> Result<Sign, SignError> {
+        let idx = self
+            .signs
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or(SignError::InvalidId(id))?;
+        let sign = self.signs.remove(idx);
+        self.dirty.push((sign.buffer, sign.line));
+        Ok(sign)
+    }
+
+    /// Remove every sign of `buffer`; returns how many were removed.
+    pub fn unplace_buffer(&mut self, buffer: BufferId) -> usize {
+        let before = self.signs.len();
+        let dirty = &mut self.dirty;
+        self.signs.retain(|s| {
+            let keep = s.buffer != buffer;
+            if !keep {
+                dirty.push((s.buffer, s.line));
+            }
+            keep
+        });
+        before - self.signs.len()
+    }
+
+    pub fn get(&self, id: SignId) -> Option<&Sign> {
+        self.signs.iter().find(|s| s.id == id)
+    }
+
+    /// Sign drawn on `line` of `buffer` (the most recently placed one).
+    pub fn at(&self, buffer: BufferId, line: usize) -> Option<&Sign> {
+        self.signs
+            .iter()
+            .rev()
+            .find(|s| s.buffer == buffer && s.line == line)
+    }
+
+    /// True when `buffer` shows a sign column.
+    pub fn has_signs(&self, buffer: BufferId) -> bool {
+        self.signs.iter().any(|s| s.buffer == buffer)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Drain the lines whose sign changed, deduplicated.
+    pub fn take_dirty(&mut self) -> Vec<(BufferId, usize)> {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.sort_unstable();
+        dirty.dedup();
+        dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn place_unplace_and_dirt() {
+        let (a, b) = (BufferId(1), BufferId(2));
+        let mut signs = SignRegistry::new();
+        let first = signs.place(a, 3, ">", SignStyle::Default).unwrap();
+        let second = signs.place(a, 3, "E", SignStyle::Error).unwrap();
+        signs.place(b, 0, "+", SignStyle::Added).unwrap();
+        assert_eq!(signs.at(a, 3).unwrap().id, second);
+        assert_eq!(signs.take_dirty(), [(a, 3), (b, 0)]);
+        assert!(!signs.is_dirty());
+
+        assert_eq!(signs.unplace(second).unwrap().glyph, "E");
+        assert_eq!(signs.at(a, 3).unwrap().id, first);
+        assert_eq!(
+            signs.unplace(second).unwrap_err().to_string(),
+            "E157: Invalid sign ID: 1"
+        );
+        assert_eq!(signs.unplace_buffer(a), 1);
+        assert!(!signs.has_signs(a));
+        assert!(signs.has_signs(b));
+        assert_eq!(signs.take_dirty(), [(a, 3)]);
+    }
+
+    #[test]
+    fn glyph_must_fit_the_column() {
+        let mut signs = SignRegistry::new();
+        assert!(signs.place(BufferId(1), 0, "界", SignStyle::Info).is_ok());
+        for bad in ["", "abc", "界x", "\t"] {
+            assert_eq!(
+                signs.place(BufferId(1), 0, bad, SignStyle::Info),
+                Err(SignError::InvalidText(bad.to_string()))
+            );
+        }
+    }
+}
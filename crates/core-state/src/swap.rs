@@ -0,0 +1,368 @@
+//! Swap (recovery) files for crash protection.
+//!
+//! While a named buffer has unsaved changes the runtime periodically (after
+//! `'updatetime'` ms of idle time, when `'swapfile'` is set) snapshots its
+//! text into a sidecar `.{name}.swp` next to the file, mirroring Vim's
+//! naming. Swap files are removed once the buffer is clean again and on
+//! orderly shutdown, so a swap file found when opening a path was left
+//! behind by a crashed (or still running) session.
+//!
+//! Stale swap files are never overwritten implicitly: the buffer records the
+//! stale path and skips swap updates until the user either restores it
+//! (`:recover`) or discards it (`:recover!`).
+//!
+//! When the editor panics, `emergency_dump` writes every modified buffer
+//! at once: a named one to its swap file, so the next session offers
+//! `:recover`, an unnamed one to the recovery directory.
+//!
+//! The snapshot holds the full normalized buffer text behind a small header
+//! (format version, writer pid, original path). Buffers are small relative
+//! to the idle interval, and a full snapshot can always be restored without
+//! the original file being intact.
+
+use crate::{BufferId, EditorState};
+use core_text::{Buffer, Position};
+use std::path::{Path, PathBuf};
+
+const SWAP_MAGIC: &str = "oxidized swap v1";
+
+/// Decoded swap file contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapRecord {
+    pub pid: u32,
+    pub path: PathBuf,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub enum SwapError {
+    NotFound(PathBuf),
+    Corrupt(PathBuf),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for SwapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwapError::NotFound(p) => write!(f, "E305: No swap file found for {}", p.display()),
+            SwapError::Corrupt(p) => {
+                write!(f, "E307: {} does not look like a swap file", p.display())
+            }
+            SwapError::Io(e) => write!(f, "E306: Cannot open swap file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SwapError {}
+
+impl From<std::io::Error> for SwapError {
+    fn from(e: std::io::Error) -> Self {
+        SwapError::Io(e)
+    }
+}
+
+/// Sidecar swap path for `path`: `dir/.name.swp`.
+pub fn swap_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "noname".to_string());
+    path.with_file_name(format!(".{name}.swp"))
+}
+
+pub fn encode_swap(path: &Path, content: &str) -> String {
+    format!(
+        "{SWAP_MAGIC}\npid={}\npath={}\n\n{content}",
+        std::process::id(),
+        path.display()
+    )
+}
+
+pub fn read_swap(swap: &Path) -> Result<SwapRecord, SwapError> {
+    let raw = std::fs::read_to_string(swap)?;
+    let corrupt = || SwapError::Corrupt(swap.to_path_buf());
+    let (header, content) = raw.split_once("\n\n").ok_or_else(corrupt)?;
+    let mut lines = header.lines();
+    if lines.next() != Some(SWAP_MAGIC) {
+        return Err(corrupt());
+    }
+    let mut pid = None;
+    let mut path = None;
+    for line in lines {
+        match line.split_once('=') {
+            Some(("pid", v)) => pid = v.parse().ok(),
+            Some(("path", v)) => path = Some(PathBuf::from(v)),
+            _ => {}
+        }
+    }
+    Ok(SwapRecord {
+        pid: pid.ok_or_else(corrupt)?,
+        path: path.ok_or_else(corrupt)?,
+        content: content.to_string(),
+    })
+}
+
+fn buffer_text(buffer: &Buffer) -> String {
+    (0..buffer.line_count())
+        .filter_map(|i| buffer.line(i))
+        .collect()
+}
+
+/// Counts from one `update_swap_files` pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SwapUpdate {
+    pub written: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+impl EditorState {
+    /// Record a stale swap file for the active buffer if one exists for its
+    /// path. Returns the swap path when found.
+    pub fn detect_stale_swap(&mut self) -> Option<PathBuf> {
+        let swap = swap_path(self.file_name()?);
+        if !swap.exists() {
+            return None;
+        }
+        tracing::warn!(target: "state.swap", swap = %swap.display(), "stale_swap_detected");
+        self.active_meta_mut().stale_swap = Some(swap.clone());
+        Some(swap)
+    }
+
+    /// Snapshot dirty named buffers into their swap files and remove swap
+    /// files of buffers that are clean again. Buffers with an unresolved
+    /// stale swap file are left alone.
+    pub fn update_swap_files(&mut self) -> SwapUpdate {
+        let mut update = SwapUpdate::default();
+        for entry in self.buffers.iter_mut() {
+            let meta = &mut entry.meta;
+            if meta.stale_swap.is_some() {
+                continue;
+            }
+            let Some(path) = meta.path.as_deref() else {
+                continue;
+            };
+            if meta.dirty {
+                let swap = swap_path(path);
+                let data = encode_swap(path, &buffer_text(&entry.buffer));
+                match std::fs::write(&swap, data) {
+                    Ok(()) => {
+                        meta.swap = Some(swap);
+                        update.written += 1;
+                    }
+                    Err(e) => {
+                        tracing::error!(target: "state.swap", ?e, swap = %swap.display(), "swap_write_failed");
+                        update.failed += 1;
+                    }
+                }
+            } else if let Some(swap) = meta.swap.take() {
+                let _ = std::fs::remove_file(&swap);
+                update.removed += 1;
+            }
+        }
+        if update != SwapUpdate::default() {
+            tracing::debug!(
+                target: "state.swap",
+                written = update.written,
+                removed = update.removed,
+                failed = update.failed,
+                "swap_update"
+            );
+        }
+        update
+    }
+
+    /// Remove every swap file written by this session (orderly shutdown).
+    pub fn remove_swap_files(&mut self) {
+        for entry in self.buffers.iter_mut() {
+            if let Some(swap) = entry.meta.swap.take() {
+                let _ = std::fs::remove_file(&swap);
+            }
+        }
+    }
+
+    /// Replace the active buffer's text with its stale swap snapshot as one
+    /// undoable edit. The buffer becomes dirty; the stale file is removed
+    /// (the next swap update writes a fresh one).
+    pub fn recover_swap(&mut self, cursor: &mut Position) -> Result<PathBuf, SwapError> {
+        let swap = self.pending_swap()?;
+        let record = read_swap(&swap)?;
+        self.push_discrete_edit_snapshot(*cursor);
+        let buffer = self.active_buffer_mut();
+        let len = buffer.len_bytes();
+        buffer.delete_bytes(0, len);
+        buffer.insert_str(0, &record.content);
+        *cursor = Position::origin();
+        self.set_dirty(true);
+        let _ = std::fs::remove_file(&swap);
+        self.active_meta_mut().stale_swap = None;
+        tracing::info!(target: "state.swap", swap = %swap.display(), pid = record.pid, "swap_recovered");
+        Ok(swap)
+    }
+
+    /// Delete the active buffer's stale swap file without restoring it.
+    pub fn discard_stale_swap(&mut self) -> Result<PathBuf, SwapError> {
+        let swap = self.pending_swap()?;
+        std::fs::remove_file(&swap)?;
+        self.active_meta_mut().stale_swap = None;
+        tracing::info!(target: "state.swap", swap = %swap.display(), "swap_discarded");
+        Ok(swap)
+    }
+
+    fn pending_swap(&self) -> Result<PathBuf, SwapError> {
+        self.active_meta().stale_swap.clone().ok_or_else(|| {
+            SwapError::NotFound(
+                self.file_name()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(self.active_buffer().name.clone())),
+            )
+        })
+    }
+
+    /// Save every modified buffer on the way down from a panic, returning
+    /// the files written. A named buffer goes to its swap file, or to
+    /// `recovery_dir` when an older session's swap file is still waiting
+    /// for `:recover`; an unnamed one goes to `recovery_dir`. Listings and
+    /// the command-line window are skipped.
+    pub fn emergency_dump(&self, recovery_dir: &Path) -> Vec<PathBuf> {
+        let mut written = Vec::new();
+        for entry in self.buffers.iter() {
+            let meta = &entry.meta;
+            if !meta.dirty
+                || meta.explorer.is_some()
+                || entry.buffer.name == crate::CMDLINE_WINDOW_NAME
+            {
+                continue;
+            }
+            let text = buffer_text(&entry.buffer);
+            let (file, data) = match meta.path.as_deref() {
+                Some(path) if meta.stale_swap.is_none() => {
+                    (swap_path(path), encode_swap(path, &text))
+                }
+                path => {
+                    let name = path
+                        .and_then(Path::file_name)
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| entry.buffer.name.clone());
+                    let file = recovery_dir.join(format!("{name}.{}.recover", entry.id()));
+                    (file, text)
+                }
+            };
+            let result = std::fs::create_dir_all(file.parent().unwrap_or(recovery_dir))
+                .and_then(|()| std::fs::write(&file, data));
+            match result {
+                Ok(()) => written.push(file),
+                Err(e) => {
+                    tracing::error!(target: "state.swap", ?e, file = %file.display(), "emergency_dump_failed")
+                }
+            }
+        }
+        tracing::info!(target: "state.swap", written = written.len(), "emergency_dump");
+        written
+    }
+
+    /// Swap file currently owned by buffer `id`, if any.
+    pub fn swap_file(&self, id: BufferId) -> Option<&Path> {
+        self.buffers.get(id)?.meta.swap.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_for(path: &Path, text: &str) -> EditorState {
+        let mut st = EditorState::new(Buffer::from_str("t", text).unwrap());
+        st.set_file_name(Some(path.to_path_buf()));
+        st
+    }
+
+    #[test]
+    fn swap_written_while_dirty_and_removed_when_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        let mut st = state_for(&path, "one\n");
+        assert_eq!(
+            st.update_swap_files().written,
+            0,
+            "clean buffer has no swap"
+        );
+
+        st.set_dirty(true);
+        assert_eq!(st.update_swap_files().written, 1);
+        let swap = dir.path().join(".a.txt.swp");
+        assert_eq!(st.swap_file(st.active), Some(swap.as_path()));
+        let record = read_swap(&swap).unwrap();
+        assert_eq!(record.content, "one\n");
+        assert_eq!(record.path, path);
+
+        st.set_dirty(false);
+        assert_eq!(st.update_swap_files().removed, 1);
+        assert!(!swap.exists());
+    }
+
+    #[test]
+    fn stale_swap_blocks_updates_until_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("b.txt");
+        let swap = swap_path(&path);
+        std::fs::write(&swap, encode_swap(&path, "saved\nwork\n")).unwrap();
+
+        let mut st = state_for(&path, "old\n");
+        assert_eq!(st.detect_stale_swap(), Some(swap.clone()));
+        st.set_dirty(true);
+        assert_eq!(st.update_swap_files().written, 0);
+        assert_eq!(read_swap(&swap).unwrap().content, "saved\nwork\n");
+
+        let mut cursor = Position::new(0, 2);
+        st.recover_swap(&mut cursor).unwrap();
+        assert_eq!(st.active_buffer().line(1).unwrap(), "work\n");
+        assert_eq!(cursor, Position::origin());
+        assert_eq!(st.undo_depth(), 1);
+        assert!(st.dirty());
+        assert!(matches!(
+            st.recover_swap(&mut cursor),
+            Err(SwapError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn discard_removes_stale_swap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("c.txt");
+        let swap = swap_path(&path);
+        std::fs::write(&swap, "garbage").unwrap();
+        let mut st = state_for(&path, "x\n");
+        st.detect_stale_swap();
+        assert!(matches!(read_swap(&swap), Err(SwapError::Corrupt(_))));
+        st.discard_stale_swap().unwrap();
+        assert!(!swap.exists());
+    }
+
+    #[test]
+    fn emergency_dump_saves_every_modified_buffer() {
+        let dir = tempfile::tempdir().unwrap();
+        let recovery = dir.path().join("recovery");
+        let path = dir.path().join("d.txt");
+        let mut st = state_for(&path, "edited\n");
+        st.set_dirty(true);
+        let scratch = st
+            .buffers
+            .open(Buffer::from_str("notes", "draft\n").unwrap(), None);
+        st.buffers.get_mut(scratch).unwrap().meta.dirty = true;
+        st.buffers
+            .open(Buffer::from_str("clean", "x\n").unwrap(), None);
+
+        let written = st.emergency_dump(&recovery);
+        let swap = swap_path(&path);
+        let copy = recovery.join(format!("notes.{scratch}.recover"));
+        assert_eq!(written, [swap.clone(), copy.clone()]);
+        assert_eq!(read_swap(&swap).unwrap().content, "edited\n");
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "draft\n");
+        assert_eq!(
+            std::fs::read_to_string(&path).ok(),
+            None,
+            "the file is not touched"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Tag stack and symbol lookups behind `gd` / `gr` / `<C-w>d` and
+//! `<C-t>` / `<C-o>`.
+//!
+//! Each jump to a definition or reference pushes the location it left onto
+//! the tag stack (at most `TAG_STACK_MAX` entries, the oldest dropped);
+//! `<C-t>` pops back to it. Lookups go to the language server attached to
+//! the buffer (`attach`, recorded by `core-lsp` for servers with a
+//! definition or references provider): the dispatcher queues a
+//! `GotoRequest`, `core-lsp` sends it on its next sync and leaves a
+//! `GotoAnswer` for the runtime to apply. Buffers without a server, and
+//! servers answering with nothing, fall back to ctags `tags` files
+//! (`find_tags`), which only know definitions.
+
+use crate::BufferId;
+use core_text::Position;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Depth of the tag stack, as in Vim.
+pub const TAG_STACK_MAX: usize = 20;
+
+/// Where a jump started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagEntry {
+    pub buffer: BufferId,
+    pub position: Position,
+}
+
+/// A jump target. `character` counts UTF-16 code units, as LSP positions
+/// do; it is turned into a byte offset once the file is loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub character: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GotoRequest {
+    pub references: bool,
+    /// Open the target in a new split (`<C-w>d`).
+    pub split: bool,
+    /// Identifier under the cursor, looked up in `tags` files on fallback.
+    pub word: String,
+    pub origin: TagEntry,
+}
+
+/// A server's answer to a `GotoRequest`; no locations means "try tags".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GotoAnswer {
+    pub request: GotoRequest,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Default)]
+pub struct Tags {
+    stack: Vec<TagEntry>,
+    request: Option<GotoRequest>,
+    answer: Option<GotoAnswer>,
+    /// Buffers open on a server that resolves definitions or references.
+    servers: HashSet<BufferId>,
+}
+
+impl Tags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, entry: TagEntry) {
+        if self.stack.len() == TAG_STACK_MAX {
+            self.stack.remove(0);
+        }
+        self.stack.push(entry);
+    }
+
+    /// Pop `count` entries and return the oldest of them (where the jump
+    /// back lands); `None` on an empty stack. A count past the bottom
+    /// stops there.
+    pub fn pop(&mut self, count: u32) -> Option<TagEntry> {
+        let keep = self.stack.len().saturating_sub(count.max(1) as usize);
+        self.stack.drain(keep..).next()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn attach(&mut self, buffer: BufferId) {
+        self.servers.insert(buffer);
+    }
+
+    pub fn detach(&mut self, buffer: BufferId) {
+        self.servers.remove(&buffer);
+    }
+
+    pub fn is_attached(&self, buffer: BufferId) -> bool {
+        self.servers.contains(&buffer)
+    }
+
+    /// Queue `request` for the buffer's server, replacing an unsent one.
+    pub fn request(&mut self, request: GotoRequest) {
+        self.request = Some(request);
+    }
+
+    pub fn take_request(&mut self) -> Option<GotoRequest> {
+        self.request.take()
+    }
+
+    pub fn answer(&mut self, answer: GotoAnswer) {
+        self.answer = Some(answer);
+    }
+
+    pub fn has_answer(&self) -> bool {
+        self.answer.is_some()
+    }
+
+    pub fn take_answer(&mut self) -> Option<GotoAnswer> {
+        self.answer.take()
+    }
+}
+
+/// Definitions of `name` listed in the ctags file `tags_file`, in file
+/// order. Paths are relative to the tags file's directory; a search
+/// address (`/^fn main() {$/`) is resolved against the file on disk, and
+/// the column is where `name` appears on the found line.
+pub fn find_tags(tags_file: &Path, name: &str) -> Vec<Location> {
+    let Ok(text) = std::fs::read_to_string(tags_file) else {
+        return Vec::new();
+    };
+    let dir = tags_file.parent().unwrap_or(Path::new(""));
+    text.lines()
+        .filter(|line| !line.starts_with("!_TAG_"))
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            if fields.next()? != name {
+                return None;
+            }
+            let path = dir.join(fields.next()?);
+            let address = fields.next()?;
+            let address = address.split(";\"").next().unwrap_or(address);
+            resolve_address(&path, address, name)
+        })
+        .collect()
+}
+
+/// Line (and column of `name`) a tags address points at in `path`.
+fn resolve_address(path: &Path, address: &str, name: &str) -> Option<Location> {
+    let location = |line: usize, text: &str| Location {
+        path: path.to_path_buf(),
+        line,
+        character: text
+            .find(name)
+            .map_or(0, |byte| text[..byte].encode_utf16().count() as u64),
+    };
+    let content = std::fs::read_to_string(path).ok()?;
+    if let Ok(number) = address.trim().parse::<usize>() {
+        let line = number.saturating_sub(1);
+        return Some(location(line, content.lines().nth(line).unwrap_or("")));
+    }
+    let delimiter = address.chars().next().filter(|c| matches!(c, '/' | '?'))?;
+    let pattern = address[1..].strip_suffix(delimiter)?;
+    let (anchored_start, pattern) = match pattern.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (anchored_end, pattern) = match pattern.strip_suffix('$') {
+        Some(rest) if !rest.ends_with('\\') => (true, rest),
+        _ => (false, pattern),
+    };
+    let pattern = unescape(pattern, delimiter);
+    content.lines().enumerate().find_map(|(number, text)| {
+        let found = match (anchored_start, anchored_end) {
+            (true, true) => text == pattern,
+            (true, false) => text.starts_with(&pattern),
+            (false, true) => text.ends_with(&pattern),
+            (false, false) => text.contains(&pattern),
+        };
+        found.then(|| location(number, text))
+    })
+}
+
+/// Drop the backslashes ctags puts before the delimiter and backslashes.
+fn unescape(pattern: &str, delimiter: char) -> String {
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next)) if next == delimiter || next == '\\' => {
+                out.push(next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_resolve_patterns_and_line_numbers() {
+        let dir = std::env::temp_dir().join(format!("oxidized-tags-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            "// a/b\nstruct Point;\nfn main() {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("tags"),
+            "!_TAG_FILE_FORMAT\t2\n\
+             Point\tsrc/lib.rs\t/^struct Point;$/;\"\ts\n\
+             main\tsrc/lib.rs\t3;\"\tf\n\
+             b\tsrc/lib.rs\t/a\\/b/\n",
+        )
+        .unwrap();
+        let tags = dir.join("tags");
+        let at = |line, character| Location {
+            path: dir.join("src/lib.rs"),
+            line,
+            character,
+        };
+        assert_eq!(find_tags(&tags, "Point"), [at(1, 7)]);
+        assert_eq!(find_tags(&tags, "main"), [at(2, 3)]);
+        assert_eq!(find_tags(&tags, "b"), [at(0, 5)]);
+        assert_eq!(find_tags(&tags, "missing"), []);
+        std::fs::remove_dir_all(&dir).ok();
+
+        let mut stack = Tags::new();
+        assert_eq!(stack.pop(1), None);
+        let entry = |line| TagEntry {
+            buffer: BufferId(1),
+            position: Position::new(line, 0),
+        };
+        for line in 0..=TAG_STACK_MAX {
+            stack.push(entry(line));
+        }
+        assert_eq!(stack.depth(), TAG_STACK_MAX);
+        assert_eq!(stack.pop(2), Some(entry(TAG_STACK_MAX - 1)));
+        assert_eq!(stack.pop(99), Some(entry(1)), "oldest kept entry");
+        assert_eq!(stack.depth(), 0);
+    }
+}
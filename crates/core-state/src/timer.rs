@@ -0,0 +1,170 @@
+//! Timers: `:defer`, `:timer` and the timers plugins start.
+//!
+//! Like a job, a timer is only queued here as a `TimerRequest`; the runtime
+//! runs it as a `core_events::TimerSource` and hands each tick to
+//! `timer_fired`, which returns the callback to run on the main loop. A
+//! one-shot timer is gone once it fired. `stop_timer` ends one early; the
+//! runtime aborts the source of every id `take_cancelled` returns, and a
+//! tick still in flight for it finds no callback.
+
+use crate::EditorState;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimerRequest {
+    pub id: u64,
+    pub delay: Duration,
+    pub repeat: bool,
+}
+
+/// What runs when a timer fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimerCallback {
+    /// An ex command line (`:defer`, `:timer`).
+    Ex(String),
+    /// The `timer` export of `plugin`, passed the token the plugin chose
+    /// when it started the timer.
+    Plugin { plugin: String, token: i32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timer {
+    pub id: u64,
+    pub delay: Duration,
+    pub repeat: bool,
+    pub callback: TimerCallback,
+}
+
+#[derive(Debug, Default)]
+pub struct TimerState {
+    next_id: u64,
+    pending: Vec<TimerRequest>,
+    active: Vec<Timer>,
+    cancelled: Vec<u64>,
+}
+
+impl TimerState {
+    pub fn take(&mut self) -> Vec<TimerRequest> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Timers whose source should be aborted.
+    pub fn take_cancelled(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.cancelled)
+    }
+
+    /// The timers not stopped or fired yet, oldest first.
+    pub fn active(&self) -> &[Timer] {
+        &self.active
+    }
+}
+
+impl EditorState {
+    /// Queue a timer running `callback` after `delay`, and every `delay`
+    /// after that when it repeats. A plugin starting a timer with a token
+    /// it already uses replaces that timer.
+    pub fn start_timer(&mut self, delay: Duration, repeat: bool, callback: TimerCallback) -> u64 {
+        if let TimerCallback::Plugin { plugin, token } = &callback {
+            self.stop_plugin_timer(plugin, *token);
+        }
+        let timers = &mut self.timers;
+        timers.next_id += 1;
+        let id = timers.next_id;
+        tracing::debug!(target: "runtime.timer", id, ?delay, repeat, ?callback, "timer_queued");
+        timers.pending.push(TimerRequest { id, delay, repeat });
+        timers.active.push(Timer {
+            id,
+            delay,
+            repeat,
+            callback,
+        });
+        id
+    }
+
+    /// Stop timer `id` (`:timerstop`); false when it is not active.
+    pub fn stop_timer(&mut self, id: u64) -> bool {
+        let timers = &mut self.timers;
+        let Some(index) = timers.active.iter().position(|t| t.id == id) else {
+            return false;
+        };
+        timers.active.remove(index);
+        timers.pending.retain(|request| request.id != id);
+        timers.cancelled.push(id);
+        tracing::debug!(target: "runtime.timer", id, "timer_stopped");
+        true
+    }
+
+    /// Stop the timer `plugin` started with `token`; false when there is
+    /// none.
+    pub fn stop_plugin_timer(&mut self, plugin: &str, token: i32) -> bool {
+        let id = self.timers.active.iter().find_map(|t| match &t.callback {
+            TimerCallback::Plugin {
+                plugin: p,
+                token: k,
+            } if p == plugin && *k == token => Some(t.id),
+            _ => None,
+        });
+        id.is_some_and(|id| self.stop_timer(id))
+    }
+
+    /// Timer `id` fired: what to run, `None` when it was stopped. A
+    /// one-shot timer is done.
+    pub fn timer_fired(&mut self, id: u64) -> Option<TimerCallback> {
+        let timers = &mut self.timers;
+        let index = timers.active.iter().position(|t| t.id == id)?;
+        if timers.active[index].repeat {
+            return Some(timers.active[index].callback.clone());
+        }
+        Some(timers.active.remove(index).callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_fire_until_done_or_stopped() {
+        let mut state = EditorState::new(core_text::Buffer::from_str("t", "").unwrap());
+        let ms = Duration::from_millis;
+        let once = state.start_timer(ms(10), false, TimerCallback::Ex("echo".into()));
+        let every = state.start_timer(ms(20), true, TimerCallback::Ex("w".into()));
+        let ids: Vec<u64> = state.timers.take().iter().map(|r| r.id).collect();
+        assert_eq!(ids, [once, every]);
+
+        assert_eq!(
+            state.timer_fired(once),
+            Some(TimerCallback::Ex("echo".into()))
+        );
+        assert_eq!(state.timer_fired(once), None, "one-shot timers fire once");
+        assert!(state.timer_fired(every).is_some());
+        assert!(state.timer_fired(every).is_some());
+        assert!(state.stop_timer(every));
+        assert!(!state.stop_timer(every));
+        assert_eq!(state.timers.take_cancelled(), [every]);
+        assert_eq!(state.timer_fired(every), None);
+        assert!(state.timers.active().is_empty());
+    }
+
+    #[test]
+    fn plugin_tokens_name_one_timer() {
+        let mut state = EditorState::new(core_text::Buffer::from_str("t", "").unwrap());
+        let blink = |token| TimerCallback::Plugin {
+            plugin: "blink".into(),
+            token,
+        };
+        let first = state.start_timer(Duration::from_millis(500), true, blink(1));
+        let second = state.start_timer(Duration::from_millis(250), true, blink(1));
+        state.start_timer(Duration::from_millis(250), true, blink(2));
+        assert_eq!(state.timers.take_cancelled(), [first]);
+        assert_eq!(
+            state.timers.take().len(),
+            2,
+            "the replaced request is dropped"
+        );
+        assert!(state.stop_plugin_timer("blink", 1));
+        assert!(!state.stop_plugin_timer("other", 2));
+        assert_eq!(state.timers.take_cancelled(), [second]);
+        assert_eq!(state.timers.active().len(), 1);
+    }
+}
@@ -1,4 +1,19 @@
+//! Undo history as a tree of text states.
+//!
+//! Every change creates a node holding the text state *after* it; the root
+//! holds the state before the first recorded change. Undo moves to the
+//! parent, redo to the child most recently visited, so making a new edit
+//! after undoing starts a sibling branch instead of discarding the redo
+//! path (Vim's undo tree). Nodes are numbered by a monotonically increasing
+//! change sequence which `g-` / `g+` and `:earlier` / `:later` walk
+//! chronologically across branches.
+//!
+//! States are full buffer snapshots (Phase 1 coarse clone). The current node
+//! never stores its state (it is the live buffer); it is captured when the
+//! node is left, so each change costs exactly one clone.
+
 use core_text::{Buffer, Position};
+use std::collections::BTreeMap;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -38,9 +53,52 @@ pub enum InsertRun {
     },
 }
 
+/// Upper bound on nodes kept across all branches; oldest side-branch
+/// leaves are dropped first.
+pub const UNDO_TREE_MAX_NODES: usize = UNDO_HISTORY_MAX * 2;
+
+/// Chronological navigation request (`g-`, `g+`, `:earlier`, `:later`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoTravel {
+    /// Move by this many change numbers (negative = older).
+    Steps(i64),
+    /// Move by wall-clock time (negative = older).
+    Seconds(i64),
+}
+
+struct UndoNode {
+    parent: Option<u64>,
+    children: Vec<u64>,
+    /// Child that redo enters (most recently visited branch).
+    redo_child: Option<u64>,
+    time: std::time::Instant,
+    /// Text state after this change; `None` only for the current node.
+    state: Option<EditSnapshot>,
+}
+
+/// Read-only view of one tree node for visualizers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoNodeInfo {
+    pub seq: u64,
+    pub parent: Option<u64>,
+    pub children: Vec<u64>,
+    /// Time since the change was made.
+    pub age: std::time::Duration,
+}
+
+/// Snapshot of the whole tree (nodes ordered by change number).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoTreeSnapshot {
+    pub nodes: Vec<UndoNodeInfo>,
+    pub root: u64,
+    pub current: u64,
+}
+
 pub struct UndoEngine {
-    undo_stack: Vec<EditSnapshot>,
-    redo_stack: Vec<EditSnapshot>,
+    nodes: BTreeMap<u64, UndoNode>,
+    root: u64,
+    current: u64,
+    next_seq: u64,
     insert_run: InsertRun,
     /// Count of snapshots skipped due to identical successive state (Phase 3 Step 11).
     undo_snapshots_skipped: AtomicU64,
@@ -54,19 +112,58 @@ impl Default for UndoEngine {
 
 impl UndoEngine {
     pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(
+            0,
+            UndoNode {
+                parent: None,
+                children: Vec::new(),
+                redo_child: None,
+                time: std::time::Instant::now(),
+                state: None,
+            },
+        );
         Self {
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            nodes,
+            root: 0,
+            current: 0,
+            next_seq: 1,
             insert_run: InsertRun::Inactive,
             undo_snapshots_skipped: AtomicU64::new(0),
         }
     }
 
+    fn node(&self, seq: u64) -> &UndoNode {
+        &self.nodes[&seq]
+    }
+
+    fn node_mut(&mut self, seq: u64) -> &mut UndoNode {
+        self.nodes.get_mut(&seq).expect("undo node")
+    }
+
+    /// Number of undo steps available on the current branch.
     pub fn undo_depth(&self) -> usize {
-        self.undo_stack.len()
+        let mut depth = 0;
+        let mut seq = self.current;
+        while let Some(parent) = self.node(seq).parent {
+            depth += 1;
+            seq = parent;
+        }
+        depth
     }
+    /// Number of redo steps along the most recently visited branch.
     pub fn redo_depth(&self) -> usize {
-        self.redo_stack.len()
+        let mut depth = 0;
+        let mut seq = self.current;
+        while let Some(child) = self.node(seq).redo_child {
+            depth += 1;
+            seq = child;
+        }
+        depth
+    }
+    /// Change number of the current text state (0 = original text).
+    pub fn current_seq(&self) -> u64 {
+        self.current
     }
     pub fn insert_run(&self) -> &InsertRun {
         &self.insert_run
@@ -75,6 +172,8 @@ impl UndoEngine {
         self.undo_snapshots_skipped.load(Ordering::Relaxed)
     }
 
+    /// Record the state *before* an edit. The live buffer becomes the
+    /// current node's stored state and a new (current) child node is added.
     pub fn push_snapshot(
         &mut self,
         kind: SnapshotKind,
@@ -83,11 +182,12 @@ impl UndoEngine {
         mode: Mode,
     ) {
         let current_hash = buffer_hash(buffer);
-        if let Some(last) = self.undo_stack.last()
-            && last.hash == current_hash
+        // The previous change left the text untouched: keep using its node.
+        if let Some(parent) = self.node(self.current).parent
+            && self.node(parent).state.as_ref().map(|s| s.hash) == Some(current_hash)
         {
             self.undo_snapshots_skipped.fetch_add(1, Ordering::Relaxed);
-            trace!(target: "state.undo", undo_depth = self.undo_stack.len(), redo_depth = self.redo_stack.len(), hash = current_hash, "snapshot_dedupe_skip");
+            trace!(target: "state.undo", undo_depth = self.undo_depth(), redo_depth = self.redo_depth(), hash = current_hash, "snapshot_dedupe_skip");
             return;
         }
         let snap = EditSnapshot {
@@ -97,15 +197,70 @@ impl UndoEngine {
             mode,
             hash: current_hash,
         };
-        let rope_lines_before = buffer.line_count();
-        self.undo_stack.push(snap);
-        trace!(target: "state.undo", undo_depth = self.undo_stack.len(), redo_depth = self.redo_stack.len(), lines = rope_lines_before, hash = current_hash, "push_snapshot");
-        if self.undo_stack.len() > UNDO_HISTORY_MAX {
-            let _ = self.undo_stack.remove(0);
-            trace!(target: "state.undo", "undo_stack_trimmed");
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let parent = self.current;
+        let node = self.node_mut(parent);
+        node.state = Some(snap);
+        node.children.push(seq);
+        node.redo_child = Some(seq);
+        self.nodes.insert(
+            seq,
+            UndoNode {
+                parent: Some(parent),
+                children: Vec::new(),
+                redo_child: None,
+                time: std::time::Instant::now(),
+                state: None,
+            },
+        );
+        self.current = seq;
+        trace!(target: "state.undo", seq, parent, lines = buffer.line_count(), hash = current_hash, "push_snapshot");
+        self.trim();
+    }
+
+    /// Enforce `UNDO_HISTORY_MAX` (depth) and `UNDO_TREE_MAX_NODES` (size).
+    fn trim(&mut self) {
+        while self.undo_depth() > UNDO_HISTORY_MAX {
+            // Re-root at the root's child on the current path, dropping the
+            // old root together with every other branch hanging off it.
+            let mut seq = self.current;
+            while self.node(seq).parent != Some(self.root) {
+                seq = self.node(seq).parent.expect("path to root");
+            }
+            let old_root = self.nodes.remove(&self.root).expect("root");
+            for child in old_root.children.into_iter().filter(|c| *c != seq) {
+                self.remove_subtree(child);
+            }
+            self.node_mut(seq).parent = None;
+            self.root = seq;
+            trace!(target: "state.undo", root = seq, "undo_stack_trimmed");
+        }
+        while self.nodes.len() > UNDO_TREE_MAX_NODES {
+            let oldest_leaf = self
+                .nodes
+                .iter()
+                .find(|(seq, n)| n.children.is_empty() && **seq != self.current)
+                .map(|(seq, _)| *seq);
+            let Some(leaf) = oldest_leaf else { break };
+            self.remove_subtree(leaf);
+            trace!(target: "state.undo", leaf, "undo_branch_trimmed");
+        }
+    }
+
+    fn remove_subtree(&mut self, seq: u64) {
+        let Some(node) = self.nodes.remove(&seq) else {
+            return;
+        };
+        if let Some(parent) = node.parent.and_then(|p| self.nodes.get_mut(&p)) {
+            parent.children.retain(|c| *c != seq);
+            if parent.redo_child == Some(seq) {
+                parent.redo_child = parent.children.last().copied();
+            }
+        }
+        for child in node.children {
+            self.remove_subtree(child);
         }
-        self.redo_stack.clear();
-        trace!(target: "state.undo", "redo_stack_cleared_on_new_edit");
     }
 
     pub fn begin_insert_coalescing(&mut self, cursor: Position, buffer: &Buffer, mode: Mode) {
@@ -133,51 +288,152 @@ impl UndoEngine {
         }
     }
 
+    /// Make `target` the current node: store the live state in the node being
+    /// left and load `target`'s state into the buffer.
+    fn enter(
+        &mut self,
+        target: u64,
+        cursor: &mut Position,
+        buffer: &mut Buffer,
+        mode: &mut Mode,
+    ) -> bool {
+        if target == self.current || !self.nodes.contains_key(&target) {
+            return false;
+        }
+        let Some(next) = self.node_mut(target).state.take() else {
+            return false;
+        };
+        let leaving = EditSnapshot {
+            kind: next.kind,
+            buffer: buffer.clone(),
+            position: *cursor,
+            mode: *mode,
+            hash: buffer_hash(buffer),
+        };
+        self.node_mut(self.current).state = Some(leaving);
+        // Redo from any ancestor now follows the branch leading here.
+        let mut seq = target;
+        while let Some(parent) = self.node(seq).parent {
+            self.node_mut(parent).redo_child = Some(seq);
+            seq = parent;
+        }
+        self.current = target;
+        *buffer = next.buffer;
+        *cursor = next.position;
+        if !matches!(next.kind, SnapshotKind::Edit) {
+            *mode = next.mode;
+        }
+        true
+    }
+
     pub fn undo(&mut self, cursor: &mut Position, buffer: &mut Buffer, mode: &mut Mode) -> bool {
-        if let Some(last) = self.undo_stack.pop() {
-            trace!(target: "state.undo", undo_depth = self.undo_stack.len(), redo_depth = self.redo_stack.len(), "undo_pop");
-            let current = EditSnapshot {
-                kind: last.kind,
-                buffer: buffer.clone(),
-                position: *cursor,
-                mode: *mode,
-                hash: buffer_hash(buffer),
-            };
-            self.redo_stack.push(current);
-            trace!(target: "state.undo", redo_depth = self.redo_stack.len(), "redo_push_from_undo");
-            *buffer = last.buffer;
-            *cursor = last.position;
-            if !matches!(last.kind, SnapshotKind::Edit) {
-                *mode = last.mode;
-            }
-            true
-        } else {
-            false
+        let Some(parent) = self.node(self.current).parent else {
+            return false;
+        };
+        let from = self.current;
+        let done = self.enter(parent, cursor, buffer, mode);
+        if done {
+            // Redo returns to the branch just undone.
+            self.node_mut(parent).redo_child = Some(from);
+            trace!(target: "state.undo", undo_depth = self.undo_depth(), redo_depth = self.redo_depth(), "undo_pop");
         }
+        done
     }
 
     pub fn redo(&mut self, cursor: &mut Position, buffer: &mut Buffer, mode: &mut Mode) -> bool {
-        if let Some(next) = self.redo_stack.pop() {
-            trace!(target: "state.undo", redo_depth = self.redo_stack.len(), undo_depth = self.undo_stack.len(), "redo_pop");
-            let current = EditSnapshot {
-                kind: next.kind,
-                buffer: buffer.clone(),
-                position: *cursor,
-                mode: *mode,
-                hash: buffer_hash(buffer),
-            };
-            self.undo_stack.push(current);
-            trace!(target: "state.undo", undo_depth = self.undo_stack.len(), "undo_push_from_redo");
-            *buffer = next.buffer;
-            *cursor = next.position;
-            if !matches!(next.kind, SnapshotKind::Edit) {
-                *mode = next.mode;
+        let Some(child) = self.node(self.current).redo_child else {
+            return false;
+        };
+        let done = self.enter(child, cursor, buffer, mode);
+        if done {
+            trace!(target: "state.undo", undo_depth = self.undo_depth(), redo_depth = self.redo_depth(), "redo_pop");
+        }
+        done
+    }
+
+    /// Jump chronologically (`g-`/`g+`, `:earlier`/`:later`). Returns false
+    /// when already at the oldest/newest state in that direction.
+    pub fn travel(
+        &mut self,
+        travel: UndoTravel,
+        cursor: &mut Position,
+        buffer: &mut Buffer,
+        mode: &mut Mode,
+    ) -> bool {
+        let target = match travel {
+            UndoTravel::Steps(0) | UndoTravel::Seconds(0) => return false,
+            UndoTravel::Steps(n) if n < 0 => {
+                let want = self.current.saturating_sub(n.unsigned_abs());
+                // Trimmed change numbers fall back to the oldest kept state.
+                self.nodes
+                    .range(..=want)
+                    .next_back()
+                    .map(|(s, _)| *s)
+                    .unwrap_or(self.root)
             }
-            true
-        } else {
-            false
+            UndoTravel::Steps(n) => {
+                let want = self.current.saturating_add(n as u64);
+                self.nodes
+                    .range(..=want)
+                    .next_back()
+                    .map(|(s, _)| *s)
+                    .unwrap_or(self.current)
+            }
+            UndoTravel::Seconds(secs) => {
+                let now_time = self.node(self.current).time;
+                let delta = std::time::Duration::from_secs(secs.unsigned_abs());
+                if secs < 0 {
+                    let limit = now_time.checked_sub(delta);
+                    // Newest state made at or before `limit` (else the oldest).
+                    self.nodes
+                        .iter()
+                        .rev()
+                        .find(|(_, n)| limit.is_some_and(|l| n.time <= l))
+                        .map(|(s, _)| *s)
+                        .unwrap_or(self.root)
+                } else {
+                    let limit = now_time + delta;
+                    // Oldest state made at or after `limit` (else the newest).
+                    self.nodes
+                        .iter()
+                        .find(|(s, n)| **s > self.current && n.time >= limit)
+                        .or_else(|| self.nodes.iter().next_back())
+                        .map(|(s, _)| *s)
+                        .unwrap_or(self.current)
+                }
+            }
+        };
+        let moved = self.enter(target, cursor, buffer, mode);
+        if moved {
+            trace!(target: "state.undo", ?travel, seq = target, "undo_travel");
+        }
+        moved
+    }
+
+    /// Structure of the tree for visualizers.
+    pub fn tree_snapshot(&self) -> UndoTreeSnapshot {
+        let now = std::time::Instant::now();
+        UndoTreeSnapshot {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(seq, n)| UndoNodeInfo {
+                    seq: *seq,
+                    parent: n.parent,
+                    children: n.children.clone(),
+                    age: now.saturating_duration_since(n.time),
+                })
+                .collect(),
+            root: self.root,
+            current: self.current,
         }
     }
+
+    #[cfg(test)]
+    fn backdate(&mut self, seq: u64, by: std::time::Duration) {
+        let node = self.node_mut(seq);
+        node.time = node.time.checked_sub(by).unwrap_or(node.time);
+    }
 }
 
 fn buffer_hash(buf: &Buffer) -> u64 {
@@ -189,3 +445,67 @@ fn buffer_hash(buf: &Buffer) -> u64 {
     }
     h.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(b: &Buffer) -> String {
+        (0..b.line_count()).filter_map(|i| b.line(i)).collect()
+    }
+
+    /// Apply `new_text` as one recorded change.
+    fn edit(engine: &mut UndoEngine, buffer: &mut Buffer, new_text: &str) {
+        engine.push_discrete_edit_snapshot(Position::origin(), buffer, Mode::Normal);
+        *buffer = Buffer::from_str("t", new_text).unwrap();
+    }
+
+    #[test]
+    fn new_edit_after_undo_keeps_redo_branch() {
+        let mut e = UndoEngine::new();
+        let mut b = Buffer::from_str("t", "a").unwrap();
+        let (mut c, mut m) = (Position::origin(), Mode::Normal);
+        edit(&mut e, &mut b, "ab"); // seq 1
+        edit(&mut e, &mut b, "abc"); // seq 2
+        assert!(e.undo(&mut c, &mut b, &mut m));
+        edit(&mut e, &mut b, "abX"); // seq 3, sibling of 2
+        assert_eq!(e.redo_depth(), 0);
+
+        // g- walks chronologically: 3 -> 2 (other branch) -> 1 -> 0.
+        assert!(e.travel(UndoTravel::Steps(-1), &mut c, &mut b, &mut m));
+        assert_eq!((e.current_seq(), text(&b)), (2, "abc".into()));
+        assert!(e.travel(UndoTravel::Steps(-2), &mut c, &mut b, &mut m));
+        assert_eq!(text(&b), "a");
+        assert!(!e.travel(UndoTravel::Steps(-1), &mut c, &mut b, &mut m));
+        // Redo follows the most recently visited branch (seq 2).
+        assert!(e.redo(&mut c, &mut b, &mut m));
+        assert!(e.redo(&mut c, &mut b, &mut m));
+        assert_eq!(text(&b), "abc");
+        assert!(e.travel(UndoTravel::Steps(5), &mut c, &mut b, &mut m));
+        assert_eq!(text(&b), "abX");
+
+        let tree = e.tree_snapshot();
+        assert_eq!(tree.current, 3);
+        assert_eq!(tree.nodes[1].children, vec![2, 3]);
+    }
+
+    #[test]
+    fn time_travel_uses_change_times() {
+        let mut e = UndoEngine::new();
+        let mut b = Buffer::from_str("t", "0").unwrap();
+        let (mut c, mut m) = (Position::origin(), Mode::Normal);
+        edit(&mut e, &mut b, "1");
+        edit(&mut e, &mut b, "2");
+        edit(&mut e, &mut b, "3");
+        let minute = std::time::Duration::from_secs(60);
+        e.backdate(0, minute * 10);
+        e.backdate(1, minute * 10);
+        e.backdate(2, minute * 5);
+        assert!(e.travel(UndoTravel::Seconds(-120), &mut c, &mut b, &mut m));
+        assert_eq!(text(&b), "2");
+        assert!(e.travel(UndoTravel::Seconds(-3600), &mut c, &mut b, &mut m));
+        assert_eq!(text(&b), "0");
+        assert!(e.travel(UndoTravel::Seconds(3600), &mut c, &mut b, &mut m));
+        assert_eq!(text(&b), "3");
+    }
+}
@@ -0,0 +1,158 @@
+//! Command-line completion: the matches `Tab` cycles through and the
+//! wildmenu row listing them.
+//!
+//! `core_actions` finds the matches for the last word of the command line
+//! (a file name after `:edit`, an option after `:set`) and opens a
+//! `Wildmenu` with them. `Tab` / `Shift-Tab` put the next or previous match
+//! in place of the word; cycling past either end brings back what was
+//! typed, as in Vim. Typing or deleting a character closes the menu. While
+//! it is open the renderer shows the matches in one row above the command
+//! line (`row`), the selected one in brackets.
+
+/// Matches for the word being completed and which of them is shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wildmenu {
+    /// Command line text before the word, leading `:` included.
+    prefix: String,
+    /// The word as typed.
+    typed: String,
+    matches: Vec<String>,
+    /// `None` while the typed word is shown.
+    selected: Option<usize>,
+}
+
+impl Wildmenu {
+    pub fn new(prefix: impl Into<String>, typed: impl Into<String>, matches: Vec<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            typed: typed.into(),
+            matches,
+            selected: None,
+        }
+    }
+
+    pub fn matches(&self) -> &[String] {
+        &self.matches
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Select the next (previous with `backward`) match; one step past
+    /// either end selects the typed word again.
+    pub fn step(&mut self, backward: bool) {
+        let last = self.matches.len().checked_sub(1);
+        self.selected = match (self.selected, backward) {
+            (None, false) => last.map(|_| 0),
+            (None, true) => last,
+            (Some(i), false) => Some(i + 1).filter(|&i| Some(i) <= last),
+            (Some(i), true) => i.checked_sub(1),
+        };
+    }
+
+    /// The command line with the selected match (or the typed word).
+    pub fn line(&self) -> String {
+        let word = match self.selected {
+            Some(i) => &self.matches[i],
+            None => &self.typed,
+        };
+        format!("{}{word}", self.prefix)
+    }
+
+    /// The row shown above the command line: the matches' last path
+    /// components, the selected one in `[ ]`. Matches that do not fit in
+    /// `width` columns are paged; `<` and `>` mark the pages before and
+    /// after.
+    pub fn row(&self, width: usize) -> String {
+        let cells: Vec<String> = self
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(i, m)| match self.selected == Some(i) {
+                true => format!("[{}]", tail(m)),
+                false => format!(" {} ", tail(m)),
+            })
+            .collect();
+        let widths: Vec<usize> = cells
+            .iter()
+            .map(|c| core_text::grapheme::visual_col(c, c.len()))
+            .collect();
+        // One column on each side for the page markers.
+        let room = width.saturating_sub(2);
+        let page_end = |start: usize| {
+            let mut used = 0;
+            let mut end = start;
+            while end < cells.len() && used + widths[end] <= room {
+                used += widths[end];
+                end += 1;
+            }
+            end.max(start + 1).min(cells.len())
+        };
+        let target = self.selected.unwrap_or(0);
+        let mut start = 0;
+        let mut end = page_end(start);
+        while end <= target && end < cells.len() {
+            start = end;
+            end = page_end(start);
+        }
+        let mut row = String::from(if start > 0 { "<" } else { " " });
+        for cell in &cells[start..end] {
+            row.push_str(cell);
+        }
+        if end < cells.len() {
+            row.push('>');
+        }
+        row
+    }
+}
+
+/// Last component of a completed path (`src/main.rs` -> `main.rs`,
+/// `src/bin/` -> `bin/`); other words are shown whole.
+fn tail(word: &str) -> &str {
+    let trimmed = word.strip_suffix('/').unwrap_or(word);
+    match trimmed.rfind('/') {
+        Some(i) => &word[i + 1..],
+        None => word,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu(matches: &[&str]) -> Wildmenu {
+        let matches = matches.iter().map(|m| m.to_string()).collect();
+        Wildmenu::new(":e ", "src/", matches)
+    }
+
+    #[test]
+    fn cycles_back_to_the_typed_word() {
+        let mut menu = menu(&["src/a.rs", "src/bin/"]);
+        menu.step(false);
+        assert_eq!(menu.line(), ":e src/a.rs");
+        menu.step(false);
+        assert_eq!(menu.line(), ":e src/bin/");
+        menu.step(false);
+        assert_eq!(
+            (menu.selected(), menu.line()),
+            (None, ":e src/".to_string())
+        );
+        menu.step(true);
+        assert_eq!(menu.selected(), Some(1));
+    }
+
+    #[test]
+    fn row_shows_tails_and_pages_to_the_selection() {
+        let mut menu = menu(&["src/a.rs", "src/bin/", "src/lib.rs", "src/main.rs"]);
+        menu.step(false);
+        assert_eq!(menu.row(80), " [a.rs] bin/  lib.rs  main.rs ");
+        // Two cells fit in 16 columns; the others are on later pages.
+        assert_eq!(menu.row(16), " [a.rs] bin/ >");
+        menu.step(false);
+        menu.step(false);
+        assert_eq!(menu.row(16), "<[lib.rs]>");
+        menu.step(false);
+        assert_eq!(menu.row(16), "<[main.rs]");
+    }
+}
@@ -0,0 +1,84 @@
+//! Edits fed to the incremental parser.
+//!
+//! `core_text::Buffer` logs every insertion and removal as a `TextEdit`
+//! (`Buffer::edits_since`). Each becomes the `InputEdit` tree-sitter applies
+//! to the old tree before reparsing, and moves the line ranges a sync has
+//! yet to re-highlight, so the cost of a sync follows the edits rather than
+//! the size of the buffer.
+
+use core_text::TextEdit;
+use std::ops::Range;
+use tree_sitter::{InputEdit, Point};
+
+pub fn input_edit(edit: &TextEdit) -> InputEdit {
+    let point = |p: core_text::Position| Point {
+        row: p.line,
+        column: p.byte,
+    };
+    InputEdit {
+        start_byte: edit.start_byte,
+        old_end_byte: edit.old_end_byte,
+        new_end_byte: edit.new_end_byte,
+        start_position: point(edit.start),
+        old_end_position: point(edit.old_end),
+        new_end_position: point(edit.new_end),
+    }
+}
+
+/// The lines `edit` rewrote: `old` before it, `new` after it.
+pub fn edited_lines(edit: &TextEdit) -> (Range<usize>, Range<usize>) {
+    let start = edit.start.line;
+    (start..edit.old_end.line + 1, start..edit.new_end.line + 1)
+}
+
+/// Move `lines` (coordinates before `edit`) past it: lines below shift by
+/// the lines it added or removed, and a range reaching into it grows to
+/// cover all of its new lines.
+pub fn shift_lines(lines: Range<usize>, edit: &TextEdit) -> Range<usize> {
+    let (old, new) = edited_lines(edit);
+    let map = |line: usize| match line {
+        l if l <= old.start => l,
+        l if l >= old.end => l - old.end + new.end,
+        _ => new.end,
+    };
+    let start = if lines.start > old.start && lines.start < old.end {
+        new.start
+    } else {
+        map(lines.start)
+    };
+    start..map(lines.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_text::{Buffer, Position};
+
+    #[test]
+    fn edits_move_the_lines_after_them() {
+        let mut b = Buffer::from_str("t", "a\nb\nc\nd\n").unwrap();
+        let seen = b.edit_cursor();
+        b.insert_str(2, "x\ny\n");
+        let [insert] = b.edits_since(seen).unwrap()[..] else {
+            panic!("one edit");
+        };
+        assert_eq!(edited_lines(&insert), (1..2, 1..4));
+        assert_eq!(insert.new_end, Position::new(3, 0));
+        assert_eq!(shift_lines(0..1, &insert), 0..1);
+        assert_eq!(shift_lines(2..3, &insert), 4..5);
+        assert_eq!(
+            input_edit(&insert).new_end_position,
+            Point { row: 3, column: 0 }
+        );
+
+        let seen = b.edit_cursor();
+        b.delete_bytes(0, 6);
+        let [delete] = b.edits_since(seen).unwrap()[..] else {
+            panic!("one edit");
+        };
+        assert_eq!(edited_lines(&delete), (0..4, 0..1));
+        // A range inside the deleted text collapses onto what replaced it.
+        assert_eq!(shift_lines(1..4, &delete), 0..1);
+        assert_eq!(shift_lines(4..6, &delete), 1..3);
+    }
+}
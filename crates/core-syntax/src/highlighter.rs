@@ -0,0 +1,132 @@
+//! Parse state of one buffer and highlight span extraction.
+
+use crate::Language;
+use crate::edit::input_edit;
+use core_state::HighlightSpan;
+use core_text::{Buffer, EditCursor, TextEdit};
+use std::ops::Range;
+use tree_sitter::{Node, Parser, QueryCursor, StreamingIterator, Tree};
+
+/// The parse tree of one buffer. The text is never copied: parsing and
+/// highlighting read it from the buffer's rope, chunk by chunk.
+pub struct SyntaxBuffer {
+    language: Language,
+    parser: Parser,
+    tree: Tree,
+    /// Where in the buffer's edit log the tree is.
+    seen: EditCursor,
+}
+
+impl SyntaxBuffer {
+    /// Parse `text` from scratch. `None` if the parser gives up (it only
+    /// does so on cancellation or timeout, neither of which is set).
+    pub fn new(language: Language, text: &Buffer) -> Option<SyntaxBuffer> {
+        let mut parser = Parser::new();
+        parser.set_language(&language.config().language).ok()?;
+        let tree = parse(&mut parser, text, None)?;
+        Some(SyntaxBuffer {
+            language,
+            parser,
+            tree,
+            seen: text.edit_cursor(),
+        })
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// The place in the buffer's edit log the tree was last brought to.
+    pub fn seen(&self) -> EditCursor {
+        self.seen
+    }
+
+    /// Apply `edits` (which must turn the text last parsed into `text`) and
+    /// reparse incrementally. Returns the lines (in new coordinates) whose
+    /// syntax changed beyond the edited ones, e.g. everything after an
+    /// unterminated string or block comment.
+    pub fn apply(&mut self, edits: &[TextEdit], text: &Buffer) -> Vec<Range<usize>> {
+        for edit in edits {
+            self.tree.edit(&input_edit(edit));
+        }
+        self.seen = text.edit_cursor();
+        let Some(tree) = parse(&mut self.parser, text, Some(&self.tree)) else {
+            return Vec::new();
+        };
+        let changed = self
+            .tree
+            .changed_ranges(&tree)
+            .map(|r| r.start_point.row..r.end_point.row + 1)
+            .collect();
+        self.tree = tree;
+        changed
+    }
+
+    /// Highlight spans for `lines`, one entry per line (clamped to the
+    /// buffer). Nested captures override enclosing ones; for captures of
+    /// the same node the earlier query pattern wins.
+    pub fn highlight_lines(&self, text: &Buffer, lines: Range<usize>) -> Vec<Vec<HighlightSpan>> {
+        let end_line = lines.end.min(text.line_count());
+        let first = lines.start.min(end_line);
+        if first == end_line {
+            return Vec::new();
+        }
+        let byte_range = text.line_to_byte(first)..content_end(text, end_line - 1);
+        let config = self.language.config();
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(byte_range);
+        let mut captures: Vec<(usize, usize, usize, Option<u16>)> = Vec::new();
+        let source = |node: Node| text.chunks(node.byte_range()).map(str::as_bytes);
+        let mut matches = cursor.captures(&config.query, self.tree.root_node(), source);
+        while let Some((m, idx)) = matches.next() {
+            let capture = m.captures[*idx];
+            let node = capture.node;
+            let class = config.classes[capture.index as usize].map(|c| c as u16);
+            captures.push((node.start_byte(), node.end_byte(), m.pattern_index, class));
+        }
+        captures.sort_by_key(|&(start, end, pattern, _)| (start, std::cmp::Reverse(end), pattern));
+        captures.dedup_by_key(|&mut (start, end, _, _)| (start, end));
+
+        let mut out = Vec::with_capacity(end_line - first);
+        for line in first..end_line {
+            let (line_start, line_end) = (text.line_to_byte(line), content_end(text, line));
+            let mut classes: Vec<Option<u16>> = vec![None; line_end - line_start];
+            for &(start, end, _, class) in &captures {
+                let (s, e) = (start.max(line_start), end.min(line_end));
+                if s < e {
+                    classes[s - line_start..e - line_start].fill(class);
+                }
+            }
+            out.push(compress(&classes));
+        }
+        out
+    }
+}
+
+fn parse(parser: &mut Parser, text: &Buffer, old: Option<&Tree>) -> Option<Tree> {
+    parser.parse_with_options(&mut |byte, _| text.chunk_at(byte).as_bytes(), old, None)
+}
+
+/// End of `line`'s text (line ending excluded).
+fn content_end(text: &Buffer, line: usize) -> usize {
+    let content = text.line(line).unwrap_or_default();
+    let content = content.strip_suffix('\n').unwrap_or(&content);
+    text.line_to_byte(line) + content.strip_suffix('\r').unwrap_or(content).len()
+}
+
+/// Runs of equal classes as spans (unclassed bytes produce no span).
+fn compress(classes: &[Option<u16>]) -> Vec<HighlightSpan> {
+    let mut spans: Vec<HighlightSpan> = Vec::new();
+    for (i, class) in classes.iter().enumerate() {
+        let Some(class) = *class else { continue };
+        match spans.last_mut() {
+            Some(last) if last.end == i && last.class == class => last.end = i + 1,
+            _ => spans.push(HighlightSpan {
+                start: i,
+                end: i + 1,
+                class,
+            }),
+        }
+    }
+    spans
+}
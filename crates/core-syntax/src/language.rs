@@ -0,0 +1,108 @@
+//! Bundled grammars and their highlight queries.
+//!
+//! Grammars are linked in, but a language's `tree_sitter::Language` and its
+//! compiled highlight query are only built the first time a buffer of that
+//! language is parsed (query compilation dominates start-up cost otherwise).
+
+use crate::HighlightClass;
+use std::path::Path;
+use std::sync::OnceLock;
+use tree_sitter::Query;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    Rust,
+    Markdown,
+    Toml,
+}
+
+/// Parser language plus the highlight query and the class of each capture.
+pub struct LanguageConfig {
+    pub language: tree_sitter::Language,
+    pub query: Query,
+    /// Indexed by capture index; `None` captures claim a node without a class.
+    pub classes: Vec<Option<HighlightClass>>,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::Rust, Language::Markdown, Language::Toml];
+
+    /// Language for a file name, by extension.
+    pub fn from_path(path: &Path) -> Option<Language> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Language::Rust),
+            "md" | "markdown" => Some(Language::Markdown),
+            "toml" => Some(Language::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Markdown => "markdown",
+            Language::Toml => "toml",
+        }
+    }
+
+    /// Grammar and compiled query, built on first use.
+    pub fn config(self) -> &'static LanguageConfig {
+        static RUST: OnceLock<LanguageConfig> = OnceLock::new();
+        static MARKDOWN: OnceLock<LanguageConfig> = OnceLock::new();
+        static TOML: OnceLock<LanguageConfig> = OnceLock::new();
+        let (cell, language, source) = match self {
+            Language::Rust => (
+                &RUST,
+                tree_sitter_rust::LANGUAGE,
+                tree_sitter_rust::HIGHLIGHTS_QUERY,
+            ),
+            Language::Markdown => (
+                &MARKDOWN,
+                tree_sitter_md::LANGUAGE,
+                tree_sitter_md::HIGHLIGHT_QUERY_BLOCK,
+            ),
+            Language::Toml => (
+                &TOML,
+                tree_sitter_toml_ng::LANGUAGE,
+                tree_sitter_toml_ng::HIGHLIGHTS_QUERY,
+            ),
+        };
+        cell.get_or_init(|| {
+            let language = tree_sitter::Language::new(language);
+            // Bundled queries are tested against their grammars below.
+            let query = Query::new(&language, source).expect("bundled highlight query");
+            let classes = query
+                .capture_names()
+                .iter()
+                .map(|name| HighlightClass::from_capture(name))
+                .collect();
+            tracing::debug!(target: "syntax", language = self.name(), "grammar_loaded");
+            LanguageConfig {
+                language,
+                query,
+                classes,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_by_extension_and_queries_compile() {
+        assert_eq!(
+            Language::from_path(Path::new("src/main.rs")),
+            Some(Language::Rust)
+        );
+        assert_eq!(
+            Language::from_path(Path::new("Cargo.toml")),
+            Some(Language::Toml)
+        );
+        assert_eq!(Language::from_path(Path::new("README")), None);
+        for language in Language::ALL {
+            assert!(!language.config().classes.is_empty(), "{}", language.name());
+        }
+    }
+}
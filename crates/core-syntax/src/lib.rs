@@ -0,0 +1,383 @@
+//! Tree-sitter syntax highlighting for Oxidized.
+//!
+//! Modules:
+//! - `language`: bundled grammars (Rust, Markdown, TOML), detected by file
+//!   extension and loaded lazily on first use.
+//! - `edit`: the `core_text::TextEdit`s a buffer logged since the last sync,
+//!   fed to the parser so reparsing after an edit is incremental.
+//! - `highlighter`: `SyntaxBuffer`, the parse tree of one buffer and the
+//!   per-line spans extracted from it with the language's highlight query.
+//!
+//! `SyntaxManager` ties them to editor state: after a dispatch changed
+//! buffers the runtime calls `sync_all`, which replays each buffer's edits
+//! into its tree, reparses and rewrites the affected lines of
+//! `EditorState::highlights`. A buffer without edits costs nothing. The
+//! renderer reads those spans into its style layer (`StyleAttr::Syntax`)
+//! and never touches parser state.
+
+pub mod edit;
+pub mod highlighter;
+pub mod language;
+
+pub use highlighter::SyntaxBuffer;
+pub use language::{Language, LanguageConfig};
+
+use core_state::{BufferId, EditorState};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Highlight classes capture names map to; the discriminant is the `class`
+/// stored in `core_state::HighlightSpan`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum HighlightClass {
+    Keyword,
+    Function,
+    Type,
+    String,
+    Escape,
+    Comment,
+    Constant,
+    Number,
+    Operator,
+    Punctuation,
+    Property,
+    Attribute,
+    Label,
+    Variable,
+    Heading,
+    Link,
+}
+
+impl HighlightClass {
+    pub const ALL: [HighlightClass; 16] = [
+        HighlightClass::Keyword,
+        HighlightClass::Function,
+        HighlightClass::Type,
+        HighlightClass::String,
+        HighlightClass::Escape,
+        HighlightClass::Comment,
+        HighlightClass::Constant,
+        HighlightClass::Number,
+        HighlightClass::Operator,
+        HighlightClass::Punctuation,
+        HighlightClass::Property,
+        HighlightClass::Attribute,
+        HighlightClass::Label,
+        HighlightClass::Variable,
+        HighlightClass::Heading,
+        HighlightClass::Link,
+    ];
+
+    pub fn from_index(index: u16) -> Option<HighlightClass> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Highlight group name color schemes use for this class.
+    pub fn name(self) -> &'static str {
+        match self {
+            HighlightClass::Keyword => "Keyword",
+            HighlightClass::Function => "Function",
+            HighlightClass::Type => "Type",
+            HighlightClass::String => "String",
+            HighlightClass::Escape => "Escape",
+            HighlightClass::Comment => "Comment",
+            HighlightClass::Constant => "Constant",
+            HighlightClass::Number => "Number",
+            HighlightClass::Operator => "Operator",
+            HighlightClass::Punctuation => "Punctuation",
+            HighlightClass::Property => "Property",
+            HighlightClass::Attribute => "Attribute",
+            HighlightClass::Label => "Label",
+            HighlightClass::Variable => "Variable",
+            HighlightClass::Heading => "Heading",
+            HighlightClass::Link => "Link",
+        }
+    }
+
+    /// Class for a query capture name such as `function.method`; `None` for
+    /// `@none` and names without a class.
+    pub fn from_capture(name: &str) -> Option<HighlightClass> {
+        let class = match name {
+            "string.escape" | "escape" => HighlightClass::Escape,
+            "variable.builtin" => HighlightClass::Keyword,
+            "constructor" => HighlightClass::Type,
+            "boolean" => HighlightClass::Constant,
+            "text.title" => HighlightClass::Heading,
+            "text.uri" | "text.reference" => HighlightClass::Link,
+            "text.literal" => HighlightClass::String,
+            _ => match name.split('.').next().unwrap_or(name) {
+                "keyword" => HighlightClass::Keyword,
+                "function" => HighlightClass::Function,
+                "type" => HighlightClass::Type,
+                "string" => HighlightClass::String,
+                "comment" => HighlightClass::Comment,
+                "constant" => HighlightClass::Constant,
+                "number" => HighlightClass::Number,
+                "operator" => HighlightClass::Operator,
+                "punctuation" => HighlightClass::Punctuation,
+                "property" => HighlightClass::Property,
+                "attribute" => HighlightClass::Attribute,
+                "label" => HighlightClass::Label,
+                "variable" => HighlightClass::Variable,
+                _ => return None,
+            },
+        };
+        Some(class)
+    }
+}
+
+/// Parse state of every highlighted buffer.
+#[derive(Default)]
+pub struct SyntaxManager {
+    buffers: HashMap<BufferId, SyntaxBuffer>,
+}
+
+impl SyntaxManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn language_of(&self, buffer: BufferId) -> Option<Language> {
+        self.buffers.get(&buffer).map(SyntaxBuffer::language)
+    }
+
+    /// Bring `buffer` up to date with its text: the first call (or a
+    /// language change, or text replaced wholesale) parses and highlights
+    /// everything, later calls apply the edits since the previous one
+    /// incrementally and re-highlight the edited lines plus the ranges
+    /// whose syntax changed. Returns the lines whose highlighting may differ
+    /// (new coordinates), which the caller turns into render dirt.
+    pub fn sync(&mut self, state: &mut EditorState, buffer: BufferId) -> Vec<Range<usize>> {
+        let Some(entry) = state.buffers.get(buffer) else {
+            self.forget(state, buffer);
+            return Vec::new();
+        };
+        let language = entry
+            .meta
+            .path
+            .as_deref()
+            .filter(|_| entry.meta.binary.is_none())
+            .and_then(Language::from_path);
+        let Some(language) = language else {
+            self.forget(state, buffer);
+            return Vec::new();
+        };
+        let text = &entry.buffer;
+        let pending = |syntax: &SyntaxBuffer| text.edits_since(syntax.seen());
+
+        match self.buffers.get_mut(&buffer) {
+            Some(syntax)
+                if syntax.language() == language
+                    && let Some(edits) = pending(syntax) =>
+            {
+                if edits.is_empty() {
+                    return Vec::new();
+                }
+                // Keep the highlight lines aligned with the text edit by
+                // edit; what the edits rewrote is highlighted after the
+                // reparse.
+                let mut edited: Vec<Range<usize>> = Vec::new();
+                for edit in edits {
+                    let (old, new) = edit::edited_lines(edit);
+                    state
+                        .highlights
+                        .splice(buffer, old, vec![Vec::new(); new.len()]);
+                    for lines in &mut edited {
+                        *lines = edit::shift_lines(lines.clone(), edit);
+                    }
+                    edited.push(new);
+                }
+                let mut changed = syntax.apply(edits, text);
+                changed.append(&mut edited);
+                for range in &changed {
+                    let fresh = syntax.highlight_lines(text, range.clone());
+                    let range = range.start..range.start + fresh.len();
+                    state.highlights.splice(buffer, range, fresh);
+                }
+                tracing::trace!(
+                    target: "syntax",
+                    %buffer,
+                    edits = edits.len(),
+                    ranges = changed.len(),
+                    "incremental_parse"
+                );
+                changed
+            }
+            _ => {
+                let Some(syntax) = SyntaxBuffer::new(language, text) else {
+                    return Vec::new();
+                };
+                let all = 0..text.line_count();
+                state
+                    .highlights
+                    .set(buffer, syntax.highlight_lines(text, all.clone()));
+                tracing::debug!(
+                    target: "syntax",
+                    %buffer,
+                    language = language.name(),
+                    lines = all.end,
+                    "full_parse"
+                );
+                self.buffers.insert(buffer, syntax);
+                vec![all]
+            }
+        }
+    }
+
+    /// `sync` every open buffer, dropping the parse state of closed ones.
+    /// Returns the buffers whose highlighting changed, with their lines.
+    pub fn sync_all(&mut self, state: &mut EditorState) -> Vec<(BufferId, Vec<Range<usize>>)> {
+        let closed: Vec<BufferId> = self
+            .buffers
+            .keys()
+            .copied()
+            .filter(|id| state.buffers.get(*id).is_none())
+            .collect();
+        for buffer in closed {
+            self.forget(state, buffer);
+        }
+        let ids: Vec<BufferId> = state.buffers.ids().collect();
+        ids.into_iter()
+            .map(|buffer| (buffer, self.sync(state, buffer)))
+            .filter(|(_, changed)| !changed.is_empty())
+            .collect()
+    }
+
+    /// Drop the parse state and highlights of `buffer`.
+    pub fn forget(&mut self, state: &mut EditorState, buffer: BufferId) {
+        self.buffers.remove(&buffer);
+        state.highlights.remove(buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_text::Buffer;
+
+    fn rust_state(text: &str) -> EditorState {
+        let mut st = EditorState::new(Buffer::from_str("t", text).unwrap());
+        st.set_file_name(Some("lib.rs".into()));
+        st
+    }
+
+    fn sync(syntax: &mut SyntaxManager, st: &mut EditorState) -> Vec<Range<usize>> {
+        let active = st.active;
+        syntax.sync(st, active)
+    }
+
+    fn classes(st: &EditorState, line: usize) -> Vec<(usize, usize, HighlightClass)> {
+        st.highlights
+            .line(st.active, line)
+            .iter()
+            .map(|s| (s.start, s.end, HighlightClass::from_index(s.class).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn full_parse_highlights_rust() {
+        let mut st = rust_state("fn main() {}\n// note\n");
+        let mut syntax = SyntaxManager::new();
+        assert_eq!(sync(&mut syntax, &mut st), vec![0..3_usize]);
+        assert_eq!(syntax.language_of(st.active), Some(Language::Rust));
+        let line0 = classes(&st, 0);
+        assert_eq!(line0[0], (0, 2, HighlightClass::Keyword));
+        assert!(line0.contains(&(3, 7, HighlightClass::Function)));
+        assert_eq!(classes(&st, 1), [(0, 7, HighlightClass::Comment)]);
+    }
+
+    #[test]
+    fn edits_reparse_incrementally_and_report_changed_lines() {
+        let mut st = rust_state("let a = 1;\nlet b = 2;\nlet c = 3;\n");
+        let mut syntax = SyntaxManager::new();
+        sync(&mut syntax, &mut st);
+        assert_eq!(sync(&mut syntax, &mut st), Vec::<Range<usize>>::new());
+
+        // Opening a block comment on line 0 recolours the lines after it.
+        st.active_buffer_mut().insert_str(0, "/* ");
+        let changed = sync(&mut syntax, &mut st);
+        assert!(changed.iter().any(|r| r.contains(&2)), "{changed:?}");
+        assert_eq!(classes(&st, 2), [(0, 10, HighlightClass::Comment)]);
+
+        // Inserting a line keeps later lines aligned with the buffer.
+        st.active_buffer_mut().delete_bytes(0, 3);
+        st.active_buffer_mut().insert_str(0, "// x\n");
+        sync(&mut syntax, &mut st);
+        assert_eq!(classes(&st, 0), [(0, 4, HighlightClass::Comment)]);
+        assert_eq!(classes(&st, 1)[0], (0, 3, HighlightClass::Keyword));
+        assert_eq!(classes(&st, 3)[0], (0, 3, HighlightClass::Keyword));
+    }
+
+    #[test]
+    fn sync_all_replays_every_buffers_edits() {
+        let mut st = rust_state("let a = 1;\nlet b = 2;\n");
+        let other = st.buffers.open(
+            Buffer::from_str("t", "fn g() {}\n").unwrap(),
+            Some("g.rs".into()),
+        );
+        let mut syntax = SyntaxManager::new();
+        assert_eq!(syntax.sync_all(&mut st).len(), 2);
+        assert!(syntax.sync_all(&mut st).is_empty());
+
+        // Two edits in one sync, the second moving the first down a line.
+        st.active_buffer_mut().insert_str(11, "// ");
+        st.active_buffer_mut().insert_str(0, "fn f() {}\n");
+        let edit = |st: &mut EditorState| {
+            st.buffers
+                .get_mut(other)
+                .unwrap()
+                .buffer
+                .insert_str(0, "// ")
+        };
+        edit(&mut st);
+        let changed = syntax.sync_all(&mut st);
+        assert_eq!(changed.len(), 2, "{changed:?}");
+        assert_eq!(classes(&st, 0)[0], (0, 2, HighlightClass::Keyword));
+        assert_eq!(classes(&st, 1)[0], (0, 3, HighlightClass::Keyword));
+        assert_eq!(classes(&st, 2), [(0, 13, HighlightClass::Comment)]);
+        let comment = HighlightClass::Comment as u16;
+        assert_eq!(st.highlights.line(other, 0)[0].class, comment);
+
+        // A buffer swapped in wholesale is parsed again.
+        *st.active_buffer_mut() = Buffer::from_str("t", "// all\n").unwrap();
+        syntax.sync_all(&mut st);
+        assert_eq!(classes(&st, 0), [(0, 6, HighlightClass::Comment)]);
+        assert_eq!(classes(&st, 1), []);
+
+        st.buffers.close(other, true).unwrap();
+        syntax.sync_all(&mut st);
+        assert!(!st.highlights.has(other));
+    }
+
+    #[test]
+    fn unknown_extensions_are_not_highlighted() {
+        let mut st = rust_state("fn a() {}\n");
+        let mut syntax = SyntaxManager::new();
+        sync(&mut syntax, &mut st);
+        st.set_file_name(Some("notes.txt".into()));
+        assert_eq!(sync(&mut syntax, &mut st), Vec::<Range<usize>>::new());
+        assert!(!st.highlights.has(st.active));
+        assert_eq!(syntax.language_of(st.active), None);
+    }
+
+    #[test]
+    fn markdown_and_toml_highlight() {
+        let mut st = EditorState::new(Buffer::from_str("t", "# Title\n").unwrap());
+        st.set_file_name(Some("README.md".into()));
+        let mut syntax = SyntaxManager::new();
+        sync(&mut syntax, &mut st);
+        assert!(
+            classes(&st, 0)
+                .iter()
+                .any(|c| c.2 == HighlightClass::Heading)
+        );
+
+        let mut st = EditorState::new(Buffer::from_str("t", "name = \"ox\"\n").unwrap());
+        st.set_file_name(Some("Cargo.toml".into()));
+        sync(&mut syntax, &mut st);
+        let line = classes(&st, 0);
+        assert!(line.contains(&(0, 4, HighlightClass::Type)), "{line:?}");
+        assert!(line.contains(&(7, 11, HighlightClass::String)), "{line:?}");
+    }
+}
@@ -14,17 +14,96 @@
 //!
 //! Future extensions (Phase 4+):
 //! * Distinguish between absolute & relative scroll support.
-//! * Detect truecolor vs 256-color fallbacks.
 //! * Query bracketed paste / focus events / kitty keyboard protocols.
 //! * Terminal width change debounce timings.
 //!
+//! Color depth, first match wins:
+//! 1. the `colors` key of the config file (`ColorDepth::from_name`),
+//!    applied by the caller through `with_color_depth`;
+//! 2. `COLORTERM=truecolor` (or `24bit`);
+//! 3. the `colors` number of the terminfo entry for `TERM` (`terminfo`);
+//! 4. the `TERM` name itself: `*-direct` is truecolor, `*-256color` is 256,
+//!    and the Linux console, `dumb`, `ansi`, `vt*` and `*-16color` are the
+//!    sixteen ANSI colors;
+//! 5. the 256-color palette, which nearly every emulator supports.
+//!
+//! The theme resolver degrades colors to the depth found, so a terminal is
+//! never sent SGR codes it does not understand.
+//!
+//! Cursor shapes: DECSCUSR (`CSI n SP q`) is understood by every terminal
+//! emulator in use except the Linux console and dumb terminals, so those are
+//! the only `TERM` values that turn `cursor_shape` off. Windows terminals
+//! support it through their VT layer.
+//!
 //! Testing approach: current test asserts the optimistic defaults. Platform
 //! divergence logic (when added) will come with targeted tests per branch.
 
+/// Colors the terminal can display, fewest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// The sixteen ANSI colors (`30`-`37`, `90`-`97`).
+    Ansi16,
+    /// xterm 256-color palette (`38;5;n`).
+    Ansi256,
+    /// 24-bit RGB (`38;2;r;g;b`).
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Depth advertised by a `COLORTERM` value.
+    pub fn from_colorterm(colorterm: Option<&str>) -> Self {
+        match colorterm {
+            Some(v) if v.eq_ignore_ascii_case("truecolor") || v.eq_ignore_ascii_case("24bit") => {
+                ColorDepth::TrueColor
+            }
+            _ => ColorDepth::Ansi256,
+        }
+    }
+
+    /// Depth named by the config file's `colors` key: `truecolor` (or
+    /// `24bit`), `256`, or `16` (also `8`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "16" | "8" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+
+    /// Depth from the environment: `COLORTERM`, then the terminfo `colors`
+    /// number, then the `TERM` name.
+    pub fn detect(
+        colorterm: Option<&str>,
+        term: Option<&str>,
+        terminfo_colors: Option<u32>,
+    ) -> Self {
+        if ColorDepth::from_colorterm(colorterm) == ColorDepth::TrueColor {
+            return ColorDepth::TrueColor;
+        }
+        match terminfo_colors {
+            Some(n) if n >= 1 << 24 => return ColorDepth::TrueColor,
+            Some(n) if n >= 256 => return ColorDepth::Ansi256,
+            Some(_) => return ColorDepth::Ansi16,
+            None => {}
+        }
+        match term {
+            Some(t) if t.ends_with("-direct") => ColorDepth::TrueColor,
+            Some(t) if t.contains("256color") => ColorDepth::Ansi256,
+            Some("linux" | "dumb" | "ansi") => ColorDepth::Ansi16,
+            Some(t) if t.starts_with("vt") || t.ends_with("-16color") => ColorDepth::Ansi16,
+            _ => ColorDepth::Ansi256,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub struct TerminalCapabilities {
     pub supports_scroll_region: bool,
+    pub color_depth: ColorDepth,
+    /// Cursor shape sequences (DECSCUSR) are honored.
+    pub cursor_shape: bool,
 }
 
 impl TerminalCapabilities {
@@ -32,10 +111,30 @@ impl TerminalCapabilities {
         // Phase 3 policy: assume scroll region support. This unblocks early
         // integration of scroll optimization code paths gated by this flag
         // without prematurely implementing round-trip probing.
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        let terminfo_colors = term.as_deref().and_then(crate::terminfo::colors);
         Self {
             supports_scroll_region: true,
+            color_depth: ColorDepth::detect(colorterm.as_deref(), term.as_deref(), terminfo_colors),
+            cursor_shape: cursor_shape_from_term(term.as_deref()),
         }
     }
+
+    /// Capabilities with the color depth forced (config override).
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = depth;
+        self
+    }
+}
+
+/// Whether a terminal with this `TERM` value honors DECSCUSR.
+pub fn cursor_shape_from_term(term: Option<&str>) -> bool {
+    match term {
+        Some("linux" | "dumb") => false,
+        Some(_) => true,
+        None => cfg!(windows),
+    }
 }
 
 #[cfg(test)]
@@ -47,4 +146,52 @@ mod tests {
         let caps = TerminalCapabilities::detect();
         assert!(caps.supports_scroll_region);
     }
+
+    #[test]
+    fn colorterm_selects_truecolor() {
+        assert_eq!(
+            ColorDepth::from_colorterm(Some("truecolor")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_colorterm(Some("24bit")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(ColorDepth::from_colorterm(Some("yes")), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_colorterm(None), ColorDepth::Ansi256);
+    }
+
+    #[test]
+    fn depth_falls_back_from_colorterm_to_terminfo_to_term() {
+        let detect = ColorDepth::detect;
+        assert_eq!(
+            detect(Some("truecolor"), Some("linux"), Some(8)),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect(None, Some("xterm"), Some(8)), ColorDepth::Ansi16);
+        assert_eq!(
+            detect(None, Some("xterm-direct"), Some(1 << 24)),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect(None, Some("foot"), Some(256)), ColorDepth::Ansi256);
+        // Without a terminfo entry the name decides.
+        assert_eq!(
+            detect(None, Some("kitty-direct"), None),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect(None, Some("linux"), None), ColorDepth::Ansi16);
+        assert_eq!(detect(None, Some("vt100"), None), ColorDepth::Ansi16);
+        assert_eq!(detect(None, Some("xterm"), None), ColorDepth::Ansi256);
+        assert_eq!(detect(None, None, None), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::from_name("24bit"), Some(ColorDepth::TrueColor));
+        assert_eq!(ColorDepth::from_name("16"), Some(ColorDepth::Ansi16));
+        assert_eq!(ColorDepth::from_name("many"), None);
+    }
+
+    #[test]
+    fn cursor_shapes_off_for_console_and_dumb_terminals() {
+        assert!(cursor_shape_from_term(Some("xterm-256color")));
+        assert!(!cursor_shape_from_term(Some("linux")));
+        assert!(!cursor_shape_from_term(Some("dumb")));
+    }
 }
@@ -2,11 +2,17 @@
 //!
 //! Refactor R3: Introduced `TerminalCapabilities` stub (scroll region support flag)
 //! consumed by the render engine to gate forthcoming scroll-delta optimizations.
+//!
+//! Cursor shapes: the renderer sets a `CursorShape` per mode while the
+//! terminal supports it; leaving (including through `TerminalGuard`) puts
+//! back the user's own shape.
 
 use anyhow::Result;
 use crossterm::{
     cursor::Hide,
+    cursor::SetCursorStyle,
     cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{
         EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode, enable_raw_mode,
@@ -15,7 +21,8 @@ use crossterm::{
 use std::io::stdout;
 
 pub mod capabilities;
-pub use capabilities::TerminalCapabilities;
+pub mod terminfo;
+pub use capabilities::{ColorDepth, TerminalCapabilities};
 
 pub trait TerminalBackend {
     fn enter(&mut self) -> Result<()>;
@@ -23,8 +30,28 @@ pub trait TerminalBackend {
     fn set_title(&mut self, title: &str) -> Result<()>;
 }
 
+/// Terminal cursor shape (DECSCUSR), steady rather than blinking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    Block,
+    Bar,
+    Underline,
+}
+
+impl CursorShape {
+    pub fn style(self) -> SetCursorStyle {
+        match self {
+            CursorShape::Block => SetCursorStyle::SteadyBlock,
+            CursorShape::Bar => SetCursorStyle::SteadyBar,
+            CursorShape::Underline => SetCursorStyle::SteadyUnderScore,
+        }
+    }
+}
+
 pub struct CrosstermBackend {
     entered: bool,
+    /// Reset the cursor to the user's shape on leave.
+    restore_shape: bool,
 }
 
 /// RAII guard ensuring terminal state restoration even if caller early-returns or panics.
@@ -41,7 +68,10 @@ impl Default for CrosstermBackend {
 
 impl CrosstermBackend {
     pub fn new() -> Self {
-        Self { entered: false }
+        Self {
+            entered: false,
+            restore_shape: TerminalCapabilities::detect().cursor_shape,
+        }
     }
 
     /// Enter and return a guard that will leave on drop.
@@ -58,7 +88,7 @@ impl TerminalBackend for CrosstermBackend {
     fn enter(&mut self) -> Result<()> {
         if !self.entered {
             enable_raw_mode()?;
-            execute!(stdout(), EnterAlternateScreen, Hide)?;
+            execute!(stdout(), EnterAlternateScreen, EnableMouseCapture, Hide)?;
             self.entered = true;
         }
         Ok(())
@@ -66,7 +96,10 @@ impl TerminalBackend for CrosstermBackend {
 
     fn leave(&mut self) -> Result<()> {
         if self.entered {
-            execute!(stdout(), LeaveAlternateScreen, Show)?;
+            if self.restore_shape {
+                execute!(stdout(), SetCursorStyle::DefaultUserShape)?;
+            }
+            execute!(stdout(), DisableMouseCapture, LeaveAlternateScreen, Show)?;
             disable_raw_mode()?;
             self.entered = false;
         }
@@ -85,6 +118,19 @@ impl Drop for CrosstermBackend {
     }
 }
 
+impl<'a> TerminalGuard<'a> {
+    /// Temporarily restore cooked mode / the main screen so an interactive
+    /// child process (`:sh`) can own the terminal. Pair with `resume`.
+    pub fn suspend(&mut self) -> Result<()> {
+        self.backend.leave()
+    }
+
+    /// Re-enter raw mode and the alternate screen after `suspend`.
+    pub fn resume(&mut self) -> Result<()> {
+        self.backend.enter()
+    }
+}
+
 impl<'a> Drop for TerminalGuard<'a> {
     fn drop(&mut self) {
         if self.active {
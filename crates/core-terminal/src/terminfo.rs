@@ -0,0 +1,130 @@
+//! Minimal terminfo reader: the `colors` (max_colors) number of a compiled
+//! entry.
+//!
+//! Only what color detection needs is read, so there is no full terminfo
+//! parser and no dependency on ncurses. Entries are looked up the way
+//! ncurses does (`$TERMINFO`, `~/.terminfo`, `$TERMINFO_DIRS`, then the
+//! system directories), in both the `x/xterm` and the `78/xterm` layouts.
+//! Both the legacy (16-bit numbers) and the extended (32-bit numbers)
+//! formats are understood; the latter is what `*-direct` entries use to
+//! advertise 2^24 colors.
+
+use std::path::PathBuf;
+
+/// Magic of the legacy format (16-bit numbers).
+const MAGIC_16: u16 = 0o432;
+/// Magic of the extended number format (32-bit numbers).
+const MAGIC_32: u16 = 0o1036;
+/// Index of `colors` among the numeric capabilities.
+const MAX_COLORS: usize = 13;
+
+const SYSTEM_DIRS: [&str; 4] = [
+    "/etc/terminfo",
+    "/lib/terminfo",
+    "/usr/share/terminfo",
+    "/usr/lib/terminfo",
+];
+
+/// `colors` of the entry for `term`, when one is installed and sets it.
+pub fn colors(term: &str) -> Option<u32> {
+    let first = term.chars().next()?;
+    if term.contains(['/', '\\']) {
+        return None;
+    }
+    search_dirs().into_iter().find_map(|dir| {
+        let letter = dir.join(first.to_string()).join(term);
+        let hex = dir.join(format!("{:x}", first as u32)).join(term);
+        let bytes = std::fs::read(letter).or_else(|_| std::fs::read(hex)).ok()?;
+        parse_colors(&bytes)
+    })
+}
+
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = std::env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = std::env::var("TERMINFO_DIRS") {
+        // An empty element stands for the system directories.
+        dirs.extend(list.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    }
+    dirs.extend(SYSTEM_DIRS.iter().map(PathBuf::from));
+    dirs
+}
+
+/// `colors` of a compiled entry; `None` when the data is not terminfo or
+/// the capability is absent.
+pub fn parse_colors(bytes: &[u8]) -> Option<u32> {
+    let short = |i: usize| {
+        let b = bytes.get(i * 2..i * 2 + 2)?;
+        Some(u16::from_le_bytes([b[0], b[1]]))
+    };
+    let width = match short(0)? {
+        MAGIC_16 => 2,
+        MAGIC_32 => 4,
+        _ => return None,
+    };
+    let names = short(1)? as usize;
+    let bools = short(2)? as usize;
+    let numbers = short(3)? as usize;
+    if numbers <= MAX_COLORS {
+        return None;
+    }
+    // Numbers start on an even offset after the names and booleans.
+    let mut offset = 12 + names + bools;
+    offset += offset % 2;
+    let at = offset + MAX_COLORS * width;
+    let raw = bytes.get(at..at + width)?;
+    let value = if width == 2 {
+        i16::from_le_bytes([raw[0], raw[1]]) as i32
+    } else {
+        i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]])
+    };
+    u32::try_from(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A compiled entry with `names` and 15 numbers, `colors` set to
+    /// `colors` and the rest absent.
+    fn entry(magic: u16, names: &str, colors: i32) -> Vec<u8> {
+        let width = if magic == MAGIC_16 { 2 } else { 4 };
+        let mut out = Vec::new();
+        let names_len = names.len() + 1;
+        for v in [magic, names_len as u16, 1, 15, 0, 0] {
+            out.extend(v.to_le_bytes());
+        }
+        out.extend(names.as_bytes());
+        out.push(0);
+        out.push(1); // one boolean
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+        for i in 0..15 {
+            let v = if i == MAX_COLORS { colors } else { -1 };
+            out.extend(&v.to_le_bytes()[..width]);
+        }
+        out
+    }
+
+    #[test]
+    fn reads_colors_from_both_number_formats() {
+        assert_eq!(
+            parse_colors(&entry(MAGIC_16, "xterm-256color", 256)),
+            Some(256)
+        );
+        assert_eq!(parse_colors(&entry(MAGIC_16, "linux", 8)), Some(8));
+        assert_eq!(
+            parse_colors(&entry(MAGIC_32, "xterm-direct", 1 << 24)),
+            Some(1 << 24)
+        );
+        assert_eq!(parse_colors(&entry(MAGIC_16, "dumb", -1)), None);
+        assert_eq!(parse_colors(b"not terminfo"), None);
+        assert_eq!(colors("../escape"), None);
+    }
+}
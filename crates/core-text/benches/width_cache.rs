@@ -0,0 +1,64 @@
+//! Width cache benchmark: measuring every cluster of an emoji-dense buffer,
+//! as a frame does, with and without the cache.
+//!
+//! Run with `cargo bench -p core-text --bench width_cache`. Plain timing
+//! loop (no harness) so it needs no extra dependencies.
+
+use core_text::width::{egc_width, egc_width_uncached};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+
+const LINES: usize = 200;
+const FRAMES: usize = 50;
+
+fn buffer() -> Vec<String> {
+    let pieces = [
+        "😀",
+        "界",
+        "👍🏽",
+        "🇯🇵",
+        "e\u{0301}",
+        "👨‍👩‍👧",
+        "字",
+        "1️⃣",
+        "✈️",
+        "ok ",
+    ];
+    (0..LINES)
+        .map(|i| {
+            (0..40)
+                .map(|j| pieces[(i + j * 7) % pieces.len()])
+                .collect()
+        })
+        .collect()
+}
+
+/// Segmentation is done once up front: only the width lookups are timed.
+fn run(lines: &[Vec<&str>], width: fn(&str) -> u16) -> Duration {
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        for line in lines {
+            let cols: u32 = line.iter().map(|g| width(black_box(g)) as u32).sum();
+            black_box(cols);
+        }
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let text = buffer();
+    let lines: Vec<Vec<&str>> = text.iter().map(|l| l.graphemes(true).collect()).collect();
+    // Warm the cache (and the CPU) once.
+    run(&lines, egc_width);
+    let uncached = run(&lines, egc_width_uncached);
+    let cached = run(&lines, egc_width);
+    let clusters: usize = lines.iter().map(Vec::len).sum();
+    println!(
+        "width_cache: {} clusters x {FRAMES} frames: uncached {:?}, cached {:?} ({:.1}x)",
+        clusters,
+        uncached,
+        cached,
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
@@ -0,0 +1,201 @@
+//! Fuzz-friendly edit sequences over a `Buffer`.
+//!
+//! `edits_from_bytes` decodes arbitrary input (a libFuzzer / cargo-fuzz
+//! corpus entry, a proptest vector) into cursor-relative edits drawn from
+//! the clusters that stress byte offsets most: combining marks, ZWJ
+//! families, flags, wide CJK. `apply_edits` runs them through the same
+//! primitives the dispatcher uses and checks `Buffer::check_invariants`
+//! plus the cursor position after every step, so a failure names the first
+//! edit that broke the buffer.
+//!
+//! A fuzz target is a one-liner:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| { core_text::fuzz::run(data).unwrap() });
+//! ```
+
+use crate::invariants::InvariantViolation;
+use crate::{Buffer, Position, grapheme};
+
+/// Clusters inserted by the decoded edits. A lone combining mark or ZWJ
+/// merges with whatever precedes it, leaving the cursor mid-cluster.
+pub const CLUSTERS: &[&str] = &[
+    "a",
+    " ",
+    "\t",
+    "é",
+    "e\u{301}",
+    "\u{301}",
+    "\u{200d}",
+    "界",
+    "👍🏽",
+    "👨‍👩‍👧‍👦",
+    "🇯🇵",
+    "⚙\u{fe0f}",
+];
+
+/// Text `run` starts from.
+pub const SEED: &str = "alpha βeta\n界界 👨‍👩‍👧‍👦\n\ne\u{301}nd";
+
+/// One cursor-relative edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditOp {
+    /// `Buffer::insert_grapheme` at the cursor.
+    InsertGrapheme(&'static str),
+    /// `Buffer::insert_newline` at the cursor.
+    InsertNewline,
+    /// `Buffer::delete_grapheme_before` (backspace, joining lines).
+    DeleteBefore,
+    /// `Buffer::delete_grapheme_at` (`x`).
+    DeleteAt,
+    /// Put the cursor on grapheme boundary `boundary` of line `line`, both
+    /// taken modulo what exists.
+    MoveTo { line: usize, boundary: usize },
+    /// `Buffer::delete_bytes` from the cursor across `clusters` clusters,
+    /// newlines included (an operator span).
+    DeleteSpan { clusters: usize },
+    /// `Buffer::insert_str` at the cursor, the cursor ending after it (a paste).
+    InsertText(String),
+}
+
+/// Decode `data` into edits; every input decodes to something.
+pub fn edits_from_bytes(data: &[u8]) -> Vec<EditOp> {
+    let mut bytes = data.iter().copied();
+    let mut next = move || bytes.next();
+    let mut ops = Vec::new();
+    while let Some(op) = next() {
+        let arg = next().unwrap_or(0) as usize;
+        ops.push(match op % 7 {
+            0 => EditOp::InsertGrapheme(CLUSTERS[arg % CLUSTERS.len()]),
+            1 => EditOp::InsertNewline,
+            2 => EditOp::DeleteBefore,
+            3 => EditOp::DeleteAt,
+            4 => EditOp::MoveTo {
+                line: arg,
+                boundary: next().unwrap_or(0) as usize,
+            },
+            5 => EditOp::DeleteSpan { clusters: arg % 8 },
+            _ => {
+                let mut text = String::new();
+                for _ in 0..arg % 4 + 1 {
+                    match next() {
+                        Some(b) if b % 5 == 0 => text.push('\n'),
+                        Some(b) => text.push_str(CLUSTERS[b as usize % CLUSTERS.len()]),
+                        None => break,
+                    }
+                }
+                EditOp::InsertText(text)
+            }
+        });
+    }
+    ops
+}
+
+/// Apply `ops` from `cursor`, checking the buffer and the cursor after each
+/// one. On failure returns the index of the offending edit and the violation.
+pub fn apply_edits(
+    buffer: &mut Buffer,
+    cursor: &mut Position,
+    ops: &[EditOp],
+) -> Result<(), (usize, InvariantViolation)> {
+    for (i, op) in ops.iter().enumerate() {
+        apply_edit(buffer, cursor, op);
+        buffer
+            .check_invariants()
+            .and_then(|()| buffer.check_position(*cursor))
+            .map_err(|violation| (i, violation))?;
+    }
+    Ok(())
+}
+
+/// Decode `data` and apply it to a buffer holding `SEED`.
+pub fn run(data: &[u8]) -> Result<Buffer, (usize, InvariantViolation)> {
+    let mut buffer = Buffer::from_str("fuzz", SEED).expect("seed text");
+    let mut cursor = Position::origin();
+    apply_edits(&mut buffer, &mut cursor, &edits_from_bytes(data))?;
+    Ok(buffer)
+}
+
+fn apply_edit(buffer: &mut Buffer, cursor: &mut Position, op: &EditOp) {
+    match op {
+        EditOp::InsertGrapheme(g) => buffer.insert_grapheme(cursor, g),
+        EditOp::InsertNewline => buffer.insert_newline(cursor),
+        EditOp::DeleteBefore => buffer.delete_grapheme_before(cursor),
+        EditOp::DeleteAt => buffer.delete_grapheme_at(cursor),
+        EditOp::MoveTo { line, boundary } => {
+            let line = line % buffer.line_count();
+            let text = buffer.line_content_string(line);
+            let boundaries: Vec<usize> = grapheme::iter(&text)
+                .scan(0, |at, g| {
+                    let start = *at;
+                    *at += g.len();
+                    Some(start)
+                })
+                .chain([text.len()])
+                .collect();
+            *cursor = Position::new(line, boundaries[boundary % boundaries.len()]);
+        }
+        EditOp::DeleteSpan { clusters } => {
+            let start = buffer.line_to_byte(cursor.line) + cursor.byte;
+            let rest = buffer.slice_bytes(start, buffer.len_bytes());
+            let len: usize = grapheme::iter(&rest).take(*clusters).map(str::len).sum();
+            buffer.delete_bytes(start, start + len);
+        }
+        EditOp::InsertText(text) => {
+            let start = buffer.line_to_byte(cursor.line) + cursor.byte;
+            buffer.insert_str(start, text);
+            match text.rfind('\n') {
+                Some(last) => {
+                    cursor.line += text.matches('\n').count();
+                    cursor.byte = text.len() - last - 1;
+                }
+                None => cursor.byte += text.len(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn every_op_kind_decodes() {
+        let ops = edits_from_bytes(&[0, 7, 1, 0, 2, 0, 3, 0, 4, 1, 2, 5, 3, 6, 1, 5, 17]);
+        assert_eq!(
+            ops,
+            vec![
+                EditOp::InsertGrapheme("界"),
+                EditOp::InsertNewline,
+                EditOp::DeleteBefore,
+                EditOp::DeleteAt,
+                EditOp::MoveTo {
+                    line: 1,
+                    boundary: 2
+                },
+                EditOp::DeleteSpan { clusters: 3 },
+                EditOp::InsertText("\n\u{301}".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn combining_mark_after_cursor_move_keeps_invariants() {
+        // Attach a combining mark to the first 界, backspace over the merged
+        // cluster, then join line 1 and the blank line after it onto line 0.
+        let buffer = run(&[4, 1, 1, 0, 5, 2, 0, 2, 0, 4, 1, 0, 2, 0]).unwrap();
+        assert_eq!(buffer.line(0).unwrap(), "alpha βeta界 👨‍👩‍👧‍👦\n");
+        assert_eq!(buffer.line_count(), 2);
+    }
+
+    proptest! {
+        #[test]
+        fn random_edit_sequences_keep_invariants(data in proptest::collection::vec(any::<u8>(), 0..256)) {
+            if let Err((i, violation)) = run(&data) {
+                let ops = edits_from_bytes(&data);
+                prop_assert!(false, "edit {i} ({:?}) broke the buffer: {violation}", ops[i]);
+            }
+        }
+    }
+}
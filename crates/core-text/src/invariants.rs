@@ -0,0 +1,159 @@
+//! Structural invariants of a `Buffer` and the positions stored against it.
+//!
+//! Every edit primitive addresses text by `(line, byte)` and converts to
+//! rope char indices on the assumption that lines end only at `\n` and that
+//! byte offsets sit on UTF-8 boundaries. Grapheme-heavy editing is where an
+//! off-by-some byte offset slips in, so these checks exist to catch the
+//! first operation that breaks either assumption. They walk the whole rope:
+//! call them from tests, fuzzing (`crate::fuzz`) and the dispatcher's
+//! `check-invariants` feature, not from hot paths.
+
+use crate::{Buffer, Position};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The rope splits lines somewhere other than `\n` (lone `\r`, U+2028, …).
+    LineCount {
+        rope_lines: usize,
+        newline_lines: usize,
+    },
+    /// A `\r` survived line-ending normalization.
+    CarriageReturn { line: usize },
+    /// A position names a line past the end of the buffer.
+    LineOutOfRange { pos: Position, line_count: usize },
+    /// A position's byte offset lies past its line's content.
+    ByteOutOfRange { pos: Position, line_len: usize },
+    /// A position's byte offset splits a UTF-8 sequence.
+    NotCharBoundary { pos: Position },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::LineCount {
+                rope_lines,
+                newline_lines,
+            } => write!(
+                f,
+                "rope reports {rope_lines} lines but the text has {newline_lines}"
+            ),
+            InvariantViolation::CarriageReturn { line } => {
+                write!(f, "carriage return in line {line}")
+            }
+            InvariantViolation::LineOutOfRange { pos, line_count } => write!(
+                f,
+                "position {}:{} is past the last line ({line_count} lines)",
+                pos.line, pos.byte
+            ),
+            InvariantViolation::ByteOutOfRange { pos, line_len } => write!(
+                f,
+                "position {}:{} is past the end of its line ({line_len} bytes)",
+                pos.line, pos.byte
+            ),
+            InvariantViolation::NotCharBoundary { pos } => write!(
+                f,
+                "position {}:{} is not on a UTF-8 boundary",
+                pos.line, pos.byte
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+impl Buffer {
+    /// Verify the rope agrees with the `\n`-only line model: its line count
+    /// matches the newline count and no `\r` remains.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        let mut line = 0;
+        for chunk in self.rope.chunks() {
+            for b in chunk.bytes() {
+                match b {
+                    b'\n' => line += 1,
+                    b'\r' => return Err(InvariantViolation::CarriageReturn { line }),
+                    _ => {}
+                }
+            }
+        }
+        let rope_lines = self.rope.len_lines();
+        if rope_lines != line + 1 {
+            return Err(InvariantViolation::LineCount {
+                rope_lines,
+                newline_lines: line + 1,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verify `pos` names an existing line and a UTF-8 boundary within (or
+    /// at the end of) that line's content.
+    pub fn check_position(&self, pos: Position) -> Result<(), InvariantViolation> {
+        let line_count = self.line_count();
+        if pos.line >= line_count {
+            return Err(InvariantViolation::LineOutOfRange { pos, line_count });
+        }
+        let line = self.line_content_string(pos.line);
+        if pos.byte > line.len() {
+            return Err(InvariantViolation::ByteOutOfRange {
+                pos,
+                line_len: line.len(),
+            });
+        }
+        if !line.is_char_boundary(pos.byte) {
+            return Err(InvariantViolation::NotCharBoundary { pos });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_text_passes() {
+        let buf = Buffer::from_str("t", "a界\n👨‍👩‍👧‍👦\n\n").unwrap();
+        assert_eq!(buf.check_invariants(), Ok(()));
+        for pos in [
+            Position::new(0, 4),
+            Position::new(1, 0),
+            Position::new(3, 0),
+        ] {
+            assert_eq!(buf.check_position(pos), Ok(()));
+        }
+    }
+
+    #[test]
+    fn carriage_returns_and_foreign_line_breaks_fail() {
+        let crlf = Buffer::from_str("t", "a\nb\r\n").unwrap();
+        assert_eq!(
+            crlf.check_invariants(),
+            Err(InvariantViolation::CarriageReturn { line: 1 })
+        );
+        let separator = Buffer::from_str("t", "a\u{2028}b\n").unwrap();
+        assert!(matches!(
+            separator.check_invariants(),
+            Err(InvariantViolation::LineCount { .. })
+        ));
+    }
+
+    #[test]
+    fn positions_must_sit_on_boundaries_inside_lines() {
+        let buf = Buffer::from_str("t", "a界\nb").unwrap();
+        assert_eq!(
+            buf.check_position(Position::new(0, 2)),
+            Err(InvariantViolation::NotCharBoundary {
+                pos: Position::new(0, 2)
+            })
+        );
+        assert!(matches!(
+            buf.check_position(Position::new(1, 2)),
+            Err(InvariantViolation::ByteOutOfRange { line_len: 1, .. })
+        ));
+        assert!(matches!(
+            buf.check_position(Position::new(2, 0)),
+            Err(InvariantViolation::LineOutOfRange { line_count: 2, .. })
+        ));
+    }
+}
@@ -1,13 +1,75 @@
 //! Rope-based text buffer abstraction.
+//!
+//! Every insertion and removal is also recorded as a `TextEdit`, so a
+//! consumer that keeps its own view of the text (the syntax tree, a view's
+//! folds) can catch up from the edits since it last looked
+//! (`edits_since`) instead of comparing whole texts.
 
 use anyhow::Result;
 use ropey::Rope;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Edits the log keeps; a reader further behind (or one huge substitution)
+/// finds the text unknown and starts over from it.
+pub const EDIT_LOG_LEN: usize = 1024;
 
 /// A text buffer backed by a `ropey::Rope`.
-#[derive(Clone)]
 pub struct Buffer {
     rope: Rope,
     pub name: String,
+    log: EditLog,
+}
+
+/// A clone is a different buffer as far as the edit log goes: whoever
+/// swaps it in for the original (undo, reload) replaced the text wholesale.
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        Self {
+            rope: self.rope.clone(),
+            name: self.name.clone(),
+            log: EditLog::new(),
+        }
+    }
+}
+
+/// The most recent edits of one buffer. Every new or cloned buffer gets its
+/// own generation, so a cursor taken on one never reads another's edits.
+struct EditLog {
+    generation: u64,
+    /// Edits dropped from the front of `edits` to bound it.
+    dropped: usize,
+    edits: Vec<TextEdit>,
+}
+
+impl EditLog {
+    fn new() -> Self {
+        static GENERATIONS: AtomicU64 = AtomicU64::new(0);
+        Self {
+            generation: GENERATIONS.fetch_add(1, Ordering::Relaxed),
+            dropped: 0,
+            edits: Vec::new(),
+        }
+    }
+}
+
+/// A reader's place in a buffer's edit log (`Buffer::edit_cursor`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditCursor {
+    generation: u64,
+    seq: usize,
+}
+
+/// One replaced byte range, with the positions of its ends before
+/// (`old_end`) and after (`new_end`) the edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start_byte: usize,
+    pub old_end_byte: usize,
+    pub new_end_byte: usize,
+    pub start: Position,
+    pub old_end: Position,
+    pub new_end: Position,
 }
 
 /// A position inside a buffer expressed as (line index, byte offset within that line).
@@ -45,13 +107,17 @@ impl Position {
     }
 }
 
+pub mod fuzz; // random edit sequences checked against `invariants`
+pub mod invariants; // rope / position consistency checks
 pub mod motion;
 pub mod segment;
 pub mod width; // Step 4.1: unified grapheme width indirection
 #[cfg(feature = "term-probe")]
 pub mod width_probe; // Step 4.4: runtime terminal probe scaffold // Step 4: centralized normalization + segmentation adapter
+pub mod wrap;
 
 // Re-export primary width function for convenience in callers that already depend on core-text.
+pub use invariants::InvariantViolation;
 pub use width::egc_width;
 
 impl Buffer {
@@ -60,9 +126,94 @@ impl Buffer {
         Ok(Self {
             rope: Rope::from_str(content),
             name: name.into(),
+            log: EditLog::new(),
         })
     }
 
+    /// The place after every edit so far, for a later `edits_since`.
+    pub fn edit_cursor(&self) -> EditCursor {
+        EditCursor {
+            generation: self.log.generation,
+            seq: self.log.dropped + self.log.edits.len(),
+        }
+    }
+
+    /// The edits made since `cursor` was taken, oldest first, or `None` when
+    /// they are not known (the cursor belongs to text this buffer replaced,
+    /// or is more than `EDIT_LOG_LEN` edits behind).
+    pub fn edits_since(&self, cursor: EditCursor) -> Option<&[TextEdit]> {
+        if cursor.generation != self.log.generation {
+            return None;
+        }
+        let start = cursor.seq.checked_sub(self.log.dropped)?;
+        self.log.edits.get(start..)
+    }
+
+    /// The text from absolute `byte` to the end of the rope chunk holding
+    /// it; empty at the end of the buffer.
+    pub fn chunk_at(&self, byte: usize) -> &str {
+        if byte >= self.rope.len_bytes() {
+            return "";
+        }
+        let (chunk, chunk_start, _, _) = self.rope.chunk_at_byte(byte);
+        &chunk[byte - chunk_start..]
+    }
+
+    /// The text of the absolute byte range `range`, chunk by chunk.
+    pub fn chunks(&self, range: Range<usize>) -> impl Iterator<Item = &str> {
+        let end = range.end.min(self.rope.len_bytes());
+        self.rope.byte_slice(range.start.min(end)..end).chunks()
+    }
+
+    /// (line, byte in line) of absolute `byte`.
+    fn position_of(&self, byte: usize) -> Position {
+        let line = self.rope.byte_to_line(byte);
+        Position::new(line, byte - self.rope.line_to_byte(line))
+    }
+
+    fn record(&mut self, edit: TextEdit) {
+        let log = &mut self.log;
+        if log.edits.len() == EDIT_LOG_LEN {
+            let half = EDIT_LOG_LEN / 2;
+            log.edits.drain(..half);
+            log.dropped += half;
+        }
+        log.edits.push(edit);
+    }
+
+    /// Insert `text` at `char_index`, recording the edit.
+    fn insert_at(&mut self, char_index: usize, text: &str) {
+        let start_byte = self.rope.char_to_byte(char_index);
+        let start = self.position_of(start_byte);
+        self.rope.insert(char_index, text);
+        let new_end_byte = start_byte + text.len();
+        let new_end = self.position_of(new_end_byte);
+        self.record(TextEdit {
+            start_byte,
+            old_end_byte: start_byte,
+            new_end_byte,
+            start,
+            old_end: start,
+            new_end,
+        });
+    }
+
+    /// Remove the characters `chars`, recording the edit.
+    fn remove(&mut self, chars: Range<usize>) {
+        let start_byte = self.rope.char_to_byte(chars.start);
+        let old_end_byte = self.rope.char_to_byte(chars.end);
+        let (start, old_end) = (self.position_of(start_byte), self.position_of(old_end_byte));
+        self.rope.remove(chars);
+        self.record(TextEdit {
+            start_byte,
+            old_end_byte,
+            new_end_byte: start_byte,
+            start,
+            old_end,
+            new_end: start,
+        });
+    }
+
     /// Total number of lines in the buffer.
     pub fn line_count(&self) -> usize {
         self.rope.len_lines()
@@ -123,14 +274,14 @@ impl Buffer {
     /// Insert a grapheme cluster string (may be multi-byte) at the given position; advances position by its byte length.
     pub fn insert_grapheme(&mut self, pos: &mut Position, g: &str) {
         let char_index = self.byte_to_char_index(pos.line, pos.byte);
-        self.rope.insert(char_index, g);
+        self.insert_at(char_index, g);
         pos.byte += g.len();
     }
 
     /// Insert a newline at the given position, splitting the current line. Cursor moves to start of new line.
     pub fn insert_newline(&mut self, pos: &mut Position) {
         let char_index = self.byte_to_char_index(pos.line, pos.byte);
-        self.rope.insert(char_index, "\n");
+        self.insert_at(char_index, "\n");
         pos.line += 1;
         pos.byte = 0;
     }
@@ -149,7 +300,7 @@ impl Buffer {
             let prev_line_start_byte = self.rope.char_to_byte(line_start_char_prev);
             let newline_byte = prev_line_start_byte + prev_len; // the '\n'
             let newline_char_index = self.rope.byte_to_char(newline_byte);
-            self.rope.remove(newline_char_index..newline_char_index + 1);
+            self.remove(newline_char_index..newline_char_index + 1);
             pos.line = prev_line;
             pos.byte = prev_len;
             return;
@@ -166,7 +317,7 @@ impl Buffer {
         let abs_end = self.absolute_byte_index(pos);
         let start_char = self.rope.byte_to_char(abs_start);
         let end_char = self.rope.byte_to_char(abs_end);
-        self.rope.remove(start_char..end_char);
+        self.remove(start_char..end_char);
         pos.byte = prev;
     }
 
@@ -188,7 +339,7 @@ impl Buffer {
         });
         let start_char = self.rope.byte_to_char(abs_start);
         let end_char = self.rope.byte_to_char(abs_end);
-        self.rope.remove(start_char..end_char);
+        self.remove(start_char..end_char);
         // Position stays at same byte (now pointing at next cluster or EOL)
     }
 
@@ -213,6 +364,28 @@ impl Buffer {
         self.rope.slice(start_char..end_char).to_string()
     }
 
+    /// Total byte length of the buffer contents.
+    pub fn len_bytes(&self) -> usize {
+        self.rope.len_bytes()
+    }
+
+    /// Absolute byte offset of the start of `line`. Lines past the end clamp
+    /// to the buffer length so `line_to_byte(line_count())` is a valid end bound.
+    pub fn line_to_byte(&self, line: usize) -> usize {
+        if line >= self.rope.len_lines() {
+            return self.rope.len_bytes();
+        }
+        self.rope.line_to_byte(line)
+    }
+
+    /// Insert `text` at absolute byte offset `byte` (clamped to buffer length).
+    /// Caller guarantees `byte` lies on a character boundary.
+    pub fn insert_str(&mut self, byte: usize, text: &str) {
+        let b = byte.min(self.rope.len_bytes());
+        let char_index = self.rope.byte_to_char(b);
+        self.insert_at(char_index, text);
+    }
+
     /// Delete the UTF-8 slice in absolute byte range `[start,end)` (clamped).
     /// Returns the removed text for register / undo integration.
     pub fn delete_bytes(&mut self, start: usize, end: usize) -> String {
@@ -230,16 +403,40 @@ impl Buffer {
         debug_assert_eq!(self.rope.char_to_byte(start_char), s);
         debug_assert_eq!(self.rope.char_to_byte(end_char), e);
         let removed = self.rope.slice(start_char..end_char).to_string();
-        self.rope.remove(start_char..end_char);
+        self.remove(start_char..end_char);
         removed
     }
 }
 
 /// Grapheme and width utilities (Phase 1). These are pure helpers operating on a single line.
+///
+/// A tab is as wide as it takes to reach the next multiple of `'tabstop'`
+/// (`cell_width`), so its width depends on the column it starts at. The
+/// runtime keeps the per-thread value in step with the option
+/// (`set_tabstop`), like the width cache it is kept on the thread that
+/// renders.
 pub mod grapheme {
     use crate::egc_width;
+    use std::cell::Cell;
     use unicode_segmentation::UnicodeSegmentation; // unified width function
 
+    /// `'tabstop'` before the runtime sets it (Vim's default).
+    pub const DEFAULT_TABSTOP: usize = 8;
+
+    thread_local! {
+        static TABSTOP: Cell<usize> = const { Cell::new(DEFAULT_TABSTOP) };
+    }
+
+    /// Use `tabstop` (at least 1) for tabs measured on this thread.
+    pub fn set_tabstop(tabstop: usize) {
+        TABSTOP.set(tabstop.max(1));
+    }
+
+    /// The `'tabstop'` tabs are measured with on this thread.
+    pub fn tabstop() -> usize {
+        TABSTOP.get()
+    }
+
     /// Iterate grapheme clusters in a line.
     pub fn iter(line: &str) -> impl Iterator<Item = &str> {
         line.graphemes(true)
@@ -280,7 +477,7 @@ pub mod grapheme {
             if idx >= byte {
                 break;
             }
-            col += egc_width(g) as usize;
+            col += cell_width(g, col);
         }
         col
     }
@@ -290,6 +487,18 @@ pub mod grapheme {
         egc_width(g) as usize
     }
 
+    /// Width in terminal cells of cluster `g` starting at visual column
+    /// `col` of its line: a tab reaches the next tab stop.
+    pub fn cell_width(g: &str, col: usize) -> usize {
+        match g {
+            "\t" => {
+                let tabstop = tabstop();
+                tabstop - col % tabstop
+            }
+            _ => egc_width(g) as usize,
+        }
+    }
+
     /// Word classification aligned with Vim's default `iskeyword` plus apostrophes for contractions.
     pub fn is_word(g: &str) -> bool {
         g.chars()
@@ -312,6 +521,50 @@ mod tests {
         assert_eq!(b.line(1).unwrap(), "world");
     }
 
+    #[test]
+    fn edits_are_logged_for_every_cursor() {
+        let mut b = Buffer::from_str("t", "xab\ncd\n").unwrap();
+        let start = b.edit_cursor();
+        let mut pos = Position::new(1, 1);
+        b.insert_newline(&mut pos);
+        b.delete_bytes(1, 4);
+        let p = Position::new;
+        assert_eq!(
+            b.edits_since(start).unwrap(),
+            [
+                TextEdit {
+                    start_byte: 5,
+                    old_end_byte: 5,
+                    new_end_byte: 6,
+                    start: p(1, 1),
+                    old_end: p(1, 1),
+                    new_end: p(2, 0),
+                },
+                TextEdit {
+                    start_byte: 1,
+                    old_end_byte: 4,
+                    new_end_byte: 1,
+                    start: p(0, 1),
+                    old_end: p(1, 0),
+                    new_end: p(0, 1),
+                },
+            ]
+        );
+        assert_eq!(b.edits_since(b.edit_cursor()), Some(&[][..]));
+        let copy = b.clone();
+        assert_eq!(
+            copy.edits_since(start),
+            None,
+            "a clone replaces text wholesale"
+        );
+        assert_eq!(b.chunks(1..4).collect::<String>(), "c\nd");
+        assert_eq!(b.chunk_at(b.len_bytes()), "");
+        for _ in 0..EDIT_LOG_LEN {
+            b.insert_str(0, "x");
+        }
+        assert_eq!(b.edits_since(start), None, "dropped from the log");
+    }
+
     #[test]
     fn grapheme_basic_emoji() {
         let s = "a😀b"; // 😀 is single cluster width 2 usually
@@ -353,6 +606,20 @@ mod tests {
         assert_eq!(grapheme::prev_boundary(s, second), first);
     }
 
+    #[test]
+    fn tabs_reach_the_next_tab_stop() {
+        assert_eq!(grapheme::visual_col("\tx", 1), 8);
+        assert_eq!(grapheme::visual_col("ab\tx", 3), 8);
+        assert_eq!(grapheme::visual_col("12345678\tx", 9), 16);
+        grapheme::set_tabstop(4);
+        assert_eq!(grapheme::visual_col("a\t\tx", 3), 8);
+        assert_eq!(grapheme::cell_width("\t", 5), 3);
+        assert_eq!(grapheme::cell_width("x", 5), 1);
+        grapheme::set_tabstop(0);
+        assert_eq!(grapheme::tabstop(), 1);
+        grapheme::set_tabstop(grapheme::DEFAULT_TABSTOP);
+    }
+
     #[test]
     fn visual_col_mixed_sequences() {
         // Sequence: ASCII, emoji, combining mark, CJK, family emoji, ASCII
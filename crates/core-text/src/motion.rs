@@ -135,7 +135,7 @@ fn byte_for_visual_col(buf: &Buffer, line: usize, target_col: usize) -> usize {
         let mut col = 0;
         let mut byte = 0;
         for (b, g) in unicode_segmentation::UnicodeSegmentation::grapheme_indices(content, true) {
-            let w = grapheme::cluster_width(g);
+            let w = grapheme::cell_width(g, col);
             if col + w > target_col {
                 return b;
             }
@@ -330,6 +330,32 @@ pub fn word_backward(buf: &Buffer, pos: &mut Position) {
     }
 }
 
+/// Byte where Insert-mode `Ctrl-W` stops when deleting back from `byte` in
+/// `line` (no trailing newline): blanks before the cursor go first, then the
+/// run of word or punctuation clusters before them. Never leaves the line.
+pub fn word_start_before(line: &str, byte: usize) -> usize {
+    let mut byte = byte.min(line.len());
+    while byte > 0 {
+        let prev = grapheme::prev_boundary(line, byte);
+        if classify_cluster(&line[prev..byte]) != ClusterKind::Blank {
+            break;
+        }
+        byte = prev;
+    }
+    if byte == 0 {
+        return 0;
+    }
+    let kind = classify_cluster(&line[grapheme::prev_boundary(line, byte)..byte]);
+    while byte > 0 {
+        let prev = grapheme::prev_boundary(line, byte);
+        if classify_cluster(&line[prev..byte]) != kind {
+            break;
+        }
+        byte = prev;
+    }
+    byte
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -452,4 +478,16 @@ mod tests {
         assert_eq!(pos.line, 2);
         assert_eq!(pos.byte, gamma_idx);
     }
+
+    #[test]
+    fn word_start_before_stays_in_line() {
+        let line = "let foo.bar  ";
+        assert_eq!(word_start_before(line, line.len()), 8);
+        assert_eq!(word_start_before(line, 8), 7);
+        assert_eq!(word_start_before(line, 7), 4);
+        assert_eq!(word_start_before("    ", 4), 0);
+        assert_eq!(word_start_before("", 0), 0);
+        let wide = "x 😀😀";
+        assert_eq!(word_start_before(wide, wide.len()), 2);
+    }
 }
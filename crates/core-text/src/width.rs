@@ -31,6 +31,16 @@
 //! 5. Bump documented Unicode version (future constant) once introduced.
 //! 6. Run full suite (nextest) + clippy + fmt; commit as Step 4.x maintenance.
 //!
+//! Width Cache:
+//! - Rendering measures the same clusters every frame, so results are kept
+//!   in a small per-thread cache keyed by the cluster (two generations of
+//!   512 entries, an approximate LRU). Widths are pure functions of the
+//!   cluster, so the cache never needs invalidating; a future runtime probe
+//!   that changes answers must clear it.
+//! - Single ASCII bytes bypass it, as do clusters over 32 bytes.
+//! - `cargo bench -p core-text --bench width_cache` compares cached and
+//!   uncached measurement on an emoji-dense buffer.
+//!
 //! Invariants:
 //! - No caller bypasses `egc_width` for display width decisions.
 //! - Classifier favors over-estimation to avoid render drift.
@@ -51,6 +61,9 @@
 //! - The static override table still holds sequences whose structure alone is
 //!   insufficient or whose width must remain forced for stability.
 
+use ahash::AHashMap;
+use std::cell::RefCell;
+
 // Step 4.2: generated override table (sequence->width) is compiled here.
 // Not yet applied to egc_width logic; included to validate build generation.
 // Provide a rust-analyzer stub to avoid transient OUT_DIR diagnostics during pre-build parsing.
@@ -67,6 +80,44 @@ mod overrides {
     include!(concat!(env!("OUT_DIR"), "/generated_width_overrides.rs"));
 }
 
+// -------- Width cache ----------------------------------------------------------
+
+/// Clusters longer than this (bytes) skip the cache; long ZWJ sequences are
+/// rare and would crowd out common entries.
+const CACHE_MAX_KEY: usize = 32;
+/// Entries per generation.
+const CACHE_GENERATION: usize = 512;
+
+thread_local! {
+    static CACHE: RefCell<WidthCache> = RefCell::new(WidthCache::default());
+}
+
+/// Two-generation approximate LRU: hits in the old generation move to the
+/// current one; a full current generation becomes the old one, dropping
+/// whatever was not used since the previous swap.
+#[derive(Default)]
+struct WidthCache {
+    current: AHashMap<Box<str>, u16>,
+    old: AHashMap<Box<str>, u16>,
+}
+
+impl WidthCache {
+    fn width(&mut self, egc: &str) -> u16 {
+        if let Some(&w) = self.current.get(egc) {
+            return w;
+        }
+        let w = match self.old.remove(egc) {
+            Some(w) => w,
+            None => egc_width_uncached(egc),
+        };
+        if self.current.len() >= CACHE_GENERATION {
+            self.old = std::mem::take(&mut self.current);
+        }
+        self.current.insert(egc.into(), w);
+        w
+    }
+}
+
 // -------- Step 4.3: Classifier -------------------------------------------------
 
 /// Semantic classification of a single grapheme cluster (EGC).
@@ -280,8 +331,22 @@ fn width_for_kind(kind: EgcKind) -> u16 {
 /// Behavior (Step 4.1): passthrough to `unicode_width` crate. Empty input
 /// returns 0. Multi-grapheme input is not validated (debug asserts may be
 /// added in later hardening).
+///
+/// Single ASCII bytes are answered directly; other clusters go through the
+/// per-thread cache (see "Width Cache" above).
 #[inline]
 pub fn egc_width(egc: &str) -> u16 {
+    match egc.as_bytes() {
+        [] => 0,
+        [b] if b.is_ascii() => 1,
+        _ if egc.len() > CACHE_MAX_KEY => egc_width_uncached(egc),
+        _ => CACHE.with(|cache| cache.borrow_mut().width(egc)),
+    }
+}
+
+/// `egc_width` without the cache: the full precedence walk on every call.
+/// Exposed for the benchmark and parity tests.
+pub fn egc_width_uncached(egc: &str) -> u16 {
     if egc.is_empty() {
         return 0;
     }
@@ -476,4 +541,24 @@ mod tests {
             assert_eq!(egc_width(seq), *w, "override mismatch for {}", seq);
         }
     }
+
+    #[test]
+    fn cache_matches_uncached_and_stays_bounded() {
+        let samples = ["界", "😀", "e\u{0301}", "🇺🇸", "⚙️", "👨‍👩‍👧‍👦", "\t"];
+        for _ in 0..2 {
+            for s in samples {
+                assert_eq!(egc_width(s), egc_width_uncached(s), "{s:?}");
+            }
+        }
+        // Push every sample out of both generations, then measure again.
+        for c in ('\u{4e00}'..).take(2 * CACHE_GENERATION + 1) {
+            egc_width(&c.to_string());
+        }
+        CACHE.with(|cache| {
+            let cache = cache.borrow();
+            assert!(cache.current.len() <= CACHE_GENERATION);
+            assert!(!cache.current.contains_key("😀") && !cache.old.contains_key("😀"));
+        });
+        assert_eq!(egc_width("😀"), 2);
+    }
 }
@@ -0,0 +1,191 @@
+//! Soft wrapping (`'wrap'`): the screen rows a line occupies.
+//!
+//! A line breaks before the first grapheme cluster that no longer fits in
+//! the text width, so clusters are never split and a wide cluster that would
+//! straddle the right edge starts the next row instead. Continuation rows
+//! begin after the `'showbreak'` marker. A cluster wider than a whole row
+//! still gets a row of its own (and is clipped by the renderer). Widths
+//! follow the renderer: every cluster takes at least one cell, and a tab
+//! reaches the next tab stop counted from the start of the line, whatever
+//! row it lands on.
+
+use crate::grapheme;
+use std::ops::Range;
+
+/// One screen row of a wrapped line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapRow {
+    /// Bytes of the line shown on this row.
+    pub bytes: Range<usize>,
+    /// Columns before the text: 0 on the first row, the `'showbreak'`
+    /// marker width on continuation rows.
+    pub indent: u16,
+}
+
+/// Wrapping geometry of one view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrapWidth {
+    /// Text columns of a row (the view width minus its gutter).
+    pub text: u16,
+    /// Width of the `'showbreak'` marker; 0 when it would fill the row.
+    pub showbreak: u16,
+}
+
+impl WrapWidth {
+    pub fn new(text: u16, showbreak: &str) -> Self {
+        let marker = grapheme::iter(showbreak)
+            .map(|g| grapheme::cluster_width(g).max(1) as u16)
+            .sum::<u16>();
+        Self {
+            text,
+            showbreak: if marker < text { marker } else { 0 },
+        }
+    }
+
+    /// Rows of `line` (without its line ending); always at least one.
+    pub fn rows(&self, line: &str) -> Vec<WrapRow> {
+        let mut rows = Vec::new();
+        let mut start = 0;
+        let mut indent = 0;
+        let mut col = 0u16;
+        let mut vcol = 0;
+        let mut byte = 0;
+        while byte < line.len() {
+            let next = grapheme::next_boundary(line, byte);
+            let width = grapheme::cell_width(&line[byte..next], vcol).max(1) as u16;
+            vcol += usize::from(width);
+            if byte > start && indent + col + width > self.text {
+                rows.push(WrapRow {
+                    bytes: start..byte,
+                    indent,
+                });
+                start = byte;
+                indent = self.showbreak;
+                col = 0;
+            }
+            col = col.saturating_add(width);
+            byte = next;
+        }
+        rows.push(WrapRow {
+            bytes: start..line.len(),
+            indent,
+        });
+        rows
+    }
+
+    /// Number of rows `line` wraps into.
+    pub fn row_count(&self, line: &str) -> usize {
+        self.rows(line).len()
+    }
+
+    /// Row (within the line) and text column of byte offset `byte`. The
+    /// end-of-line position of a full row stays on its last column.
+    pub fn locate(&self, line: &str, byte: usize) -> (usize, u16) {
+        let rows = self.rows(line);
+        let index = rows
+            .iter()
+            .rposition(|row| row.bytes.start <= byte)
+            .unwrap_or(0);
+        let row = &rows[index];
+        let mut col = row.indent;
+        let mut vcol = line_col(line, row.bytes.start);
+        let mut at = row.bytes.start;
+        while at < byte.min(line.len()) {
+            let next = grapheme::next_boundary(line, at);
+            let width = grapheme::cell_width(&line[at..next], vcol).max(1);
+            col = col.saturating_add(width as u16);
+            vcol += width;
+            at = next;
+        }
+        (index, col.min(self.text.saturating_sub(1)))
+    }
+}
+
+/// Cells before byte `byte` of `line` as `rows` counts them (every
+/// cluster at least one cell), the column tabs are measured from.
+pub fn line_col(line: &str, byte: usize) -> usize {
+    let mut col = 0;
+    let mut at = 0;
+    while at < byte.min(line.len()) {
+        let next = grapheme::next_boundary(line, at);
+        col += grapheme::cell_width(&line[at..next], col).max(1);
+        at = next;
+    }
+    col
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_break_before_clusters_that_do_not_fit() {
+        let wrap = WrapWidth::new(4, "");
+        let ranges = |line: &str| {
+            wrap.rows(line)
+                .into_iter()
+                .map(|r| r.bytes)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(wrap.row_count(""), 1);
+        assert_eq!(wrap.rows("abcd")[0].bytes, 0..4);
+        assert_eq!(wrap.row_count("abcd"), 1);
+        assert_eq!(ranges("abcdefghi"), [0..4, 4..8, 8..9]);
+        // `界` would straddle the edge after "abc": it starts the next row.
+        assert_eq!(ranges("abc界d"), [0..3, 3..7]);
+        // Combining marks stay with their base character.
+        assert_eq!(ranges("abce\u{301}f"), [0..6, 6..7]);
+    }
+
+    #[test]
+    fn showbreak_indents_continuation_rows() {
+        let wrap = WrapWidth::new(4, "> ");
+        let rows = wrap.rows("abcdefgh");
+        assert_eq!(
+            rows,
+            [
+                WrapRow {
+                    bytes: 0..4,
+                    indent: 0
+                },
+                WrapRow {
+                    bytes: 4..6,
+                    indent: 2
+                },
+                WrapRow {
+                    bytes: 6..8,
+                    indent: 2
+                },
+            ]
+        );
+        assert_eq!(wrap.locate("abcdefgh", 5), (1, 3));
+        assert_eq!(wrap.locate("abcdefgh", 0), (0, 0));
+        // A marker as wide as the row is dropped.
+        assert_eq!(WrapWidth::new(2, ">>").showbreak, 0);
+    }
+
+    #[test]
+    fn locate_maps_bytes_to_rows() {
+        let wrap = WrapWidth::new(4, "");
+        let line = "ab界cdef";
+        assert_eq!(wrap.row_count(line), 2);
+        assert_eq!(wrap.locate(line, 2), (0, 2));
+        assert_eq!(wrap.locate(line, 5), (1, 0));
+        assert_eq!(wrap.locate(line, line.len()), (1, 3));
+        assert_eq!(wrap.locate("abcd", 4), (0, 3));
+    }
+
+    #[test]
+    fn tabs_are_measured_from_the_start_of_the_line() {
+        let wrap = WrapWidth::new(10, "");
+        // "abcdef" then a tab to column 8, "gh" fills the row.
+        let line = "abcdef\tghij";
+        assert_eq!(wrap.rows(line)[0].bytes, 0..9);
+        assert_eq!(wrap.locate(line, 7), (0, 8));
+        // The second tab starts the next row at column 10: it is 6 wide.
+        let line = "abcdef\tgh\tx";
+        assert_eq!(wrap.rows(line)[1].bytes, 9..11);
+        assert_eq!(wrap.locate(line, 10), (1, 6));
+        assert_eq!(line_col(line, 10), 16);
+    }
+}
@@ -0,0 +1,225 @@
+//! Crash handling: a panic in the event loop must neither lose unsaved
+//! work nor leave the terminal in raw mode on the alternate screen.
+//!
+//! `install` extends the panic hook. The runtime handles every event under
+//! `contain`; a panic there is only recorded by the hook, as a
+//! `CrashReport` holding the message, a backtrace and the last
+//! `RECENT_EVENTS` tracing events kept by the `RecentEvents` layer. Once
+//! the unwind is caught the runtime restores the terminal, saves the
+//! modified buffers (`EditorState::emergency_dump`), writes the report next
+//! to them, prints where everything went and re-raises the panic. Panics
+//! anywhere else (event source tasks, startup) reach the default hook as
+//! before.
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Tracing events a crash report includes.
+pub const RECENT_EVENTS: usize = 256;
+
+thread_local! {
+    /// Whether a panic on this thread is caught by `contain`.
+    static CONTAINED: Cell<bool> = const { Cell::new(false) };
+    /// The report of the last panic `contain` caught on this thread, until
+    /// the runtime takes it.
+    static LAST_PANIC: RefCell<Option<CrashReport>> = const { RefCell::new(None) };
+}
+
+/// Ring buffer of the latest tracing events, formatted as they happen.
+#[derive(Clone)]
+pub struct RecentEvents {
+    start: Instant,
+    events: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Default for RecentEvents {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(RECENT_EVENTS))),
+        }
+    }
+}
+
+impl RecentEvents {
+    /// The kept events, oldest first.
+    pub fn snapshot(&self) -> Vec<String> {
+        // Never wait in the panic hook: the panicking thread may hold it.
+        match self.events.try_lock() {
+            Ok(events) => events.iter().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for RecentEvents {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut line = format!(
+            "{:>10.3}s {:>5} {}:",
+            self.start.elapsed().as_secs_f64(),
+            meta.level(),
+            meta.target()
+        );
+        event.record(&mut FieldWriter(&mut line));
+        let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+        if events.len() == RECENT_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(line);
+    }
+}
+
+/// Appends ` message key=value…` to a line.
+struct FieldWriter<'a>(&'a mut String);
+
+impl Visit for FieldWriter<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => write!(self.0, " {value}"),
+            name => write!(self.0, " {name}={value:?}"),
+        }
+        .ok();
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "message" => write!(self.0, " {value:?}"),
+            name => write!(self.0, " {name}={value:?}"),
+        }
+        .ok();
+    }
+}
+
+/// What a contained panic left for the crash report.
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// `panicked at file:line:col:` and the panic message.
+    pub message: String,
+    pub backtrace: String,
+    pub events: Vec<String>,
+}
+
+impl CrashReport {
+    /// The report of the panic `contain` just caught.
+    pub fn take() -> Option<Self> {
+        LAST_PANIC.take()
+    }
+
+    /// Write the report to `dir` as `crash-{time}-{pid}.log`, listing the
+    /// `recovered` files.
+    pub fn write(&self, dir: &Path, recovered: &[PathBuf]) -> std::io::Result<PathBuf> {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = dir.join(format!("crash-{secs}-{}.log", std::process::id()));
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, self.render(recovered))?;
+        Ok(path)
+    }
+
+    fn render(&self, recovered: &[PathBuf]) -> String {
+        let mut out = format!(
+            "oxidized {} {}\n\nRecovery files:\n",
+            env!("CARGO_PKG_VERSION"),
+            self.message
+        );
+        for path in recovered {
+            let _ = writeln!(out, "  {}", path.display());
+        }
+        if recovered.is_empty() {
+            out.push_str("  (no modified buffers)\n");
+        }
+        let _ = write!(out, "\nBacktrace:\n{}\n", self.backtrace);
+        out.push_str("\nRecent events (oldest first):\n");
+        for event in &self.events {
+            let _ = writeln!(out, "{event}");
+        }
+        out
+    }
+}
+
+/// Extend the panic hook (once): contained panics are recorded for the
+/// crash report instead of printed; others go to the previous hook.
+pub fn install(recent: RecentEvents) {
+    static HOOK: std::sync::Once = std::sync::Once::new();
+    HOOK.call_once(|| {
+        let default_panic = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            tracing::error!(target: "runtime.panic", ?info, "panic");
+            if !CONTAINED.get() {
+                default_panic(info);
+                return;
+            }
+            let report = CrashReport {
+                message: info.to_string(),
+                backtrace: Backtrace::force_capture().to_string(),
+                events: recent.snapshot(),
+            };
+            LAST_PANIC.set(Some(report));
+        }));
+    });
+}
+
+/// Run `f`, catching a panic in it with its report left for
+/// `CrashReport::take`.
+pub fn contain<T>(f: impl FnOnce() -> T) -> Result<T, Box<dyn Any + Send>> {
+    let outer = CONTAINED.replace(true);
+    let result = catch_unwind(AssertUnwindSafe(f));
+    CONTAINED.set(outer);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn contained_panics_leave_a_report_with_recent_events() {
+        let recent = RecentEvents::default();
+        let subscriber = tracing_subscriber::registry().with(recent.clone());
+        install(recent.clone());
+        let caught = tracing::subscriber::with_default(subscriber, || {
+            for n in 0..RECENT_EVENTS + 2 {
+                tracing::info!(target: "runtime.test", n, "step");
+            }
+            contain(|| panic!("dispatch blew up"))
+        });
+        assert!(caught.is_err());
+        let report = CrashReport::take().expect("the hook recorded the panic");
+        assert!(
+            report.message.contains("dispatch blew up"),
+            "{}",
+            report.message
+        );
+        // The oldest steps fell out; the hook logged the panic itself last.
+        assert_eq!(report.events.len(), RECENT_EVENTS);
+        assert!(
+            report.events[0].ends_with("runtime.test: step n=3"),
+            "{}",
+            report.events[0]
+        );
+        assert!(report.events[RECENT_EVENTS - 1].contains("runtime.panic: panic"));
+        assert!(CrashReport::take().is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let saved = [PathBuf::from("/tmp/.a.txt.swp")];
+        let path = report.write(dir.path(), &saved).unwrap();
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(
+            text.contains("Recovery files:\n  /tmp/.a.txt.swp\n"),
+            "{text}"
+        );
+        assert!(text.contains("Backtrace:\n"));
+    }
+}
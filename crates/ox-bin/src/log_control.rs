@@ -0,0 +1,124 @@
+//! `:loglevel` and `:logpath`: the filter of `oxidized.log`, changed while
+//! the editor runs.
+//!
+//! The log file is written through an `EnvFilter` (`RUST_LOG` at startup)
+//! behind a `reload` layer. `:loglevel {directive}…` adds directives in
+//! `RUST_LOG` syntax (`render.engine=debug`, `warn`): one for a target the
+//! filter already names replaces it, a bare level replaces the default.
+//! `:loglevel!` replaces the whole filter, so `:loglevel!` alone goes back
+//! to errors only. `:loglevel` shows the filter and `:logpath` where the
+//! file is.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing_subscriber::filter::{Directive, EnvFilter, LevelFilter};
+use tracing_subscriber::{Registry, reload};
+
+/// The reload handle of the log file's filter and where the file is.
+#[derive(Clone)]
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    path: PathBuf,
+    /// The directives in effect, in the order they were given.
+    directives: Arc<Mutex<Vec<String>>>,
+}
+
+impl LogControl {
+    /// The filter of `RUST_LOG` and its handle, for the log file at `path`.
+    pub fn from_env(path: &Path) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::from_default_env());
+        let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty() && d.parse::<Directive>().is_ok())
+            .map(String::from)
+            .collect();
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let control = Self {
+            handle,
+            path,
+            directives: Arc::new(Mutex::new(directives)),
+        };
+        (layer, control)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The filter in effect, as `RUST_LOG` would spell it.
+    pub fn filter(&self) -> String {
+        let directives = self
+            .directives
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match directives.is_empty() {
+            true => LevelFilter::ERROR.to_string().to_lowercase(),
+            false => directives.join(","),
+        }
+    }
+
+    /// Add `directives` to the filter, or make them the filter when
+    /// `replace`. Returns the first one that does not parse.
+    pub fn set(&self, directives: &[String], replace: bool) -> Result<String, String> {
+        if let Some(bad) = directives.iter().find(|d| d.parse::<Directive>().is_err()) {
+            return Err(bad.clone());
+        }
+        let mut current = self
+            .directives
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut next = if replace { Vec::new() } else { current.clone() };
+        for directive in directives {
+            next.retain(|d| scope(d) != scope(directive));
+            next.push(directive.clone());
+        }
+        let filter = EnvFilter::try_new(next.join(",")).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())?;
+        *current = next;
+        drop(current);
+        tracing::info!(target: "runtime", filter = %self.filter(), "log_filter_changed");
+        Ok(self.filter())
+    }
+}
+
+/// What a directive filters: everything before the level, empty for a bare
+/// level (the default).
+fn scope(directive: &str) -> &str {
+    match directive.rsplit_once('=') {
+        Some((scope, level)) if level.parse::<LevelFilter>().is_ok() => scope,
+        _ if directive.parse::<LevelFilter>().is_ok() => "",
+        _ => directive,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_replace_the_ones_for_their_target() {
+        let (_layer, control) = LogControl::from_env(Path::new("oxidized.log"));
+        assert!(control.path().is_absolute());
+        let set = |ds: &[&str], replace| {
+            let ds: Vec<String> = ds.iter().map(|d| d.to_string()).collect();
+            control.set(&ds, replace)
+        };
+        assert_eq!(set(&["warn"], true).as_deref(), Ok("warn"));
+        assert_eq!(
+            set(&["render.engine=debug", "runtime"], false).as_deref(),
+            Ok("warn,render.engine=debug,runtime")
+        );
+        assert_eq!(
+            set(&["render.engine=trace", "info"], false).as_deref(),
+            Ok("runtime,render.engine=trace,info")
+        );
+        assert_eq!(
+            set(&["render.engine=loud"], false),
+            Err("render.engine=loud".into())
+        );
+        assert_eq!(control.filter(), "runtime,render.engine=trace,info");
+        assert_eq!(set(&[], true).as_deref(), Ok("error"));
+    }
+}
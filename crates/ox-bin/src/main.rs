@@ -1,14 +1,35 @@
 //! Oxidized entrypoint.
 use anyhow::Result;
-use clap::Parser;
-use core_actions::dispatcher::dispatch;
-use core_actions::{Action, ActionObserver, EditKind, NgiResolution, NgiTranslator, PendingState};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use core_actions::dispatcher::file_watch::apply_file_change;
+use core_actions::dispatcher::goto::apply_goto_answer;
+use core_actions::dispatcher::job::apply_job_output;
+use core_actions::dispatcher::quickfix::apply_grep_output;
+use core_actions::dispatcher::shell::apply_shell_output;
+use core_actions::dispatcher::{DispatchResult, dispatch_with_commands};
+use core_actions::io_ops::{IdleTimer, autosave, recovery_dir};
+use core_actions::{
+    Action, ActionObserver, ArgSpec, CommandRegistry, CommandSpec, EditKind, MotionKind,
+    NgiResolution, NgiTranslator, NormalKeys, PendingState,
+};
+use core_config::theme::Theme;
 use core_config::{ConfigContext, ConfigPlatformTraits, load_from};
+use core_events::rpc::Value as RpcValue;
 use core_events::{
-    CommandEvent, EVENT_CHANNEL_CAP, Event, EventHooks, EventSourceRegistry, InputEvent,
-    KeyEventExt, NoopEventHooks, TickEventSource,
+    CommandEvent, Event, EventChannels, EventHooks, EventReceiver, EventRecorder,
+    EventSourceRegistry, FileWatchHandle, FileWatchSource, GitBlame, GitBlameSource, GitInfo,
+    GitInfoSource, GrepOutput, GrepSource, InputEvent, JobOutput, JobSource, KeyEventExt, KeyToken,
+    LspMessage, LspMessageKind, MouseButton, MouseEvent, MouseEventKind, NoopEventHooks,
+    ReplayEventSource, RpcHub, RpcRequest, RpcServerSource, SegmentContext, SegmentRunner,
+    SegmentUpdate, ShellCommandSource, ShellOutput, TelemetrySource, TickEventSource, TimerSource,
+    WatchSet,
 };
+use core_lsp::LspSessions;
 use core_model::EditorModel;
+use core_plugin::{
+    PluginCallError, PluginDispatcher, PluginEffects, PluginHost, PluginKeymap, PluginTimer,
+    WasmPluginHost,
+};
 use core_render::apply::{
     CursorOnlyFrame, FrameSnapshot, LinesPartialFrame, ScrollShiftFrame, apply_cursor_only,
     apply_full, apply_lines_partial, apply_scroll_shift,
@@ -16,20 +37,30 @@ use core_render::apply::{
 use core_render::render_engine::RenderEngine;
 use core_render::scheduler::{RenderDelta, RenderDeltaMetricsSnapshot, RenderScheduler};
 use core_state::Mode;
-use core_state::{EditorState, normalize_line_endings};
-use core_terminal::{CrosstermBackend, TerminalBackend, TerminalCapabilities};
+use core_state::binary::{BINARY_OPENED_MSG, is_binary};
+use core_state::{EditorState, ShadaLimits, ShellTarget, TimerCallback, normalize_line_endings};
+use core_syntax::SyntaxManager;
+use core_terminal::{ColorDepth, CrosstermBackend, TerminalBackend, TerminalCapabilities};
 use core_text::Buffer;
 use core_text::segment::normalize_and_segment;
+use std::collections::HashMap;
 use std::fmt;
 use std::mem::Discriminant;
 use std::path::{Path, PathBuf};
-use std::sync::Once;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
-use tracing::{error, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Layer as _, SubscriberExt as _};
+use tracing_subscriber::util::SubscriberInitExt as _;
+
+mod crash;
+mod log_control;
 
 const STATUS_ROWS: u16 = 1;
+/// How long the terminal size must stay put before a resize is laid out.
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(50);
 
 #[inline]
 fn log_paste_commit(content: &str, grapheme_count: usize) {
@@ -59,18 +90,95 @@ struct Args {
     /// Optional configuration file path (overrides discovery of `oxidized.toml`).
     #[arg(long = "config")]
     pub config: Option<PathBuf>,
+    /// Record every input event to this file, for `--replay`.
+    #[arg(long = "record", value_name = "FILE")]
+    pub record: Option<PathBuf>,
+    /// With `--record`, keep only the length of pasted text.
+    #[arg(long = "record-redact-paste", requires = "record")]
+    pub record_redact_paste: bool,
+    /// Feed the input events of a `--record` file back at their recorded pace.
+    #[arg(long = "replay", value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Run `--ex` / `--keys` steps against the file without a terminal, then exit.
+    #[arg(long = "headless")]
+    pub headless: bool,
+    /// With `--headless`, an ex command to run (repeatable; leading `:` optional).
+    #[arg(long = "ex", value_name = "CMD", requires = "headless")]
+    pub ex: Vec<String>,
+    /// With `--headless`, keys to type, in map notation (repeatable).
+    #[arg(long = "keys", value_name = "KEYS", requires = "headless")]
+    pub keys: Vec<String>,
+    /// With `--headless`, read and write the shada file like an interactive
+    /// session (headless runs leave it alone by default).
+    #[arg(long = "shada", requires = "headless")]
+    pub shada: bool,
+    /// Serve msgpack-rpc clients on this unix socket (like `nvim --listen`).
+    #[arg(long = "listen", value_name = "SOCKET", conflicts_with = "headless")]
+    pub listen: Option<PathBuf>,
+}
+
+/// One `--ex` or `--keys` argument of a headless run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HeadlessStep {
+    Ex(String),
+    Keys(String),
+}
+
+/// The `--ex` and `--keys` steps in the order they appeared on the command line.
+fn headless_steps(matches: &ArgMatches) -> Vec<HeadlessStep> {
+    let mut steps = Vec::new();
+    for (id, step) in [
+        ("ex", HeadlessStep::Ex as fn(String) -> HeadlessStep),
+        ("keys", HeadlessStep::Keys),
+    ] {
+        if let (Some(indices), Some(values)) =
+            (matches.indices_of(id), matches.get_many::<String>(id))
+        {
+            steps.extend(indices.zip(values.map(|v| step(v.clone()))));
+        }
+    }
+    steps.sort_by_key(|(index, _)| *index);
+    steps.into_iter().map(|(_, step)| step).collect()
+}
+
+/// Vim-numbered messages (`E37: No write since last change`) are errors.
+fn is_error_message(line: &str) -> bool {
+    line.strip_prefix('E')
+        .and_then(|rest| rest.split_once(':'))
+        .is_some_and(|(code, _)| !code.is_empty() && code.bytes().all(|b| b.is_ascii_digit()))
 }
 
 struct AppStartup {
     backend: CrosstermBackend,
     log_guard: Option<WorkerGuard>,
+    /// Set once `oxidized.log` is written to.
+    log: Option<log_control::LogControl>,
 }
 
 struct RuntimeContext<'a> {
     model: EditorModel,
     config: core_config::Config,
     platform_traits: ConfigPlatformTraits,
-    terminal_guard: core_terminal::TerminalGuard<'a>,
+    /// `None` for `--headless`, which never enters the alternate screen.
+    terminal_guard: Option<core_terminal::TerminalGuard<'a>>,
+    /// `--headless`: the steps to run instead of the event loop.
+    headless: Option<Vec<HeadlessStep>>,
+    /// `--record`: observes the event loop in place of the no-op hooks.
+    recorder: Option<EventRecorder>,
+    /// `--replay`: registered with the other event sources.
+    replay: Option<ReplayEventSource>,
+    /// `[plugins]`: loaded wasm plugins, `None` when disabled.
+    plugins: Option<WasmPluginHost>,
+    /// `--listen`: registered with the other event sources.
+    rpc_server: Option<RpcServerSource>,
+    /// `--listen`: the server's clients, answered by the runtime.
+    rpc: Option<RpcHub>,
+    /// The config file read at startup, or that would have been.
+    config_path: PathBuf,
+    /// Set once the file watcher is registered with the other event sources.
+    file_watch: Option<FileWatchHandle>,
+    /// `:loglevel`, `:logpath`; `None` when another subscriber was installed.
+    log: Option<log_control::LogControl>,
 }
 
 #[derive(Debug, Clone)]
@@ -102,19 +210,42 @@ impl AppStartup {
         Self {
             backend: CrosstermBackend::new(),
             log_guard: None,
+            log: None,
         }
     }
 
     fn run<'a>(&'a mut self) -> Result<RuntimeContext<'a>> {
-        self.configure_logging()?;
-        Self::install_panic_hook();
+        let recent = self.configure_logging()?;
+        crash::install(recent);
 
         info!(target: "runtime", "startup");
-        self.backend.set_title("Oxidized")?;
-        let guard = self.backend.enter_guard()?;
+        let matches = Args::command().get_matches();
+        let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        let headless = args.headless.then(|| headless_steps(&matches));
+        let guard = if headless.is_some() {
+            None
+        } else {
+            self.backend.set_title("Oxidized")?;
+            Some(self.backend.enter_guard()?)
+        };
 
-        let args = Args::parse();
         let bootstrap = Self::load_editor_state(&args)?;
+        let recorder = args
+            .record
+            .as_deref()
+            .map(|path| EventRecorder::create(path, args.record_redact_paste))
+            .transpose()?;
+        let replay = args
+            .replay
+            .as_deref()
+            .map(ReplayEventSource::open)
+            .transpose()?;
+        let rpc_server = args
+            .listen
+            .as_deref()
+            .map(RpcServerSource::bind)
+            .transpose()?;
+        let rpc = rpc_server.as_ref().map(RpcServerSource::hub);
 
         let path_str = bootstrap
             .telemetry
@@ -136,10 +267,21 @@ impl AppStartup {
             config: bootstrap.config,
             platform_traits: bootstrap.platform_traits,
             terminal_guard: guard,
+            headless,
+            recorder,
+            replay,
+            plugins: bootstrap.plugins,
+            rpc_server,
+            rpc,
+            config_path: args.config.clone().unwrap_or_else(core_config::discover),
+            file_watch: None,
+            log: self.log.clone(),
         })
     }
 
-    fn configure_logging(&mut self) -> Result<()> {
+    /// Log to `oxidized.log` through a filter `:loglevel` can change,
+    /// keeping the latest events (`INFO` and up) for a crash report as well.
+    fn configure_logging(&mut self) -> Result<crash::RecentEvents> {
         let log_dir = Path::new(".");
         let log_path = log_dir.join("oxidized.log");
         if log_path.exists() {
@@ -148,38 +290,46 @@ impl AppStartup {
 
         let file_appender = tracing_appender::rolling::never(log_dir, "oxidized.log");
         let (nb_writer, guard) = tracing_appender::non_blocking(file_appender);
-        match tracing_subscriber::fmt()
-            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        let recent = crash::RecentEvents::default();
+        let (filter, log) = log_control::LogControl::from_env(&log_path);
+        let file_layer = tracing_subscriber::fmt::layer()
             .with_writer(nb_writer)
+            .with_filter(filter);
+        match tracing_subscriber::registry()
+            .with(file_layer)
+            .with(recent.clone().with_filter(LevelFilter::INFO))
             .try_init()
         {
             Ok(_) => {
                 self.log_guard = Some(guard);
+                self.log = Some(log);
             }
             Err(_err) => {
                 // Global tracing subscriber already installed; drop guard so writer shuts down.
             }
         }
 
-        Ok(())
-    }
-
-    fn install_panic_hook() {
-        static HOOK: Once = Once::new();
-        HOOK.call_once(|| {
-            let default_panic = std::panic::take_hook();
-            std::panic::set_hook(Box::new(move |info| {
-                tracing::error!(target: "runtime.panic", ?info, "panic");
-                default_panic(info);
-            }));
-        });
+        Ok(recent)
     }
 
     fn load_editor_state(args: &Args) -> Result<EditorBootstrap> {
         let mut open_failed = false;
-        let (buffer, file_name, norm_meta) = if let Some(path) = args.path.as_ref() {
-            match std::fs::read_to_string(path) {
-                Ok(content) => {
+        let mut binary = None;
+        // A directory opens as a listing (`core_state::explorer`).
+        let directory = args.path.clone().filter(|path| path.is_dir());
+        let (buffer, file_name, norm_meta) = if let Some(path) =
+            args.path.as_ref().filter(|_| directory.is_none())
+        {
+            match std::fs::read(path) {
+                Ok(bytes) if is_binary(&bytes) => {
+                    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("file");
+                    tracing::debug!(target: "io", file=%path.display(), size_bytes = bytes.len(), "binary_file_detected");
+                    binary = Some(bytes);
+                    (Buffer::from_str(name, "")?, Some(path.clone()), None)
+                }
+                Ok(bytes) => {
+                    // Not binary, so valid UTF-8.
+                    let content = String::from_utf8_lossy(&bytes);
                     let size_bytes = content.len();
                     let norm = normalize_line_endings(&content);
                     let line_count = norm.normalized.lines().count();
@@ -204,21 +354,52 @@ impl AppStartup {
         let mut model = EditorModel::new(EditorState::new(buffer));
         {
             let state = model.state_mut();
-            state.file_name = file_name;
+            state.set_file_name(file_name);
             if let Some(n) = norm_meta {
-                state.original_line_ending = n.original;
-                state.had_trailing_newline = n.had_trailing_newline;
+                state.active_meta_mut().original_line_ending = n.original;
+                state.active_meta_mut().had_trailing_newline = n.had_trailing_newline;
                 if n.mixed {
                     tracing::warn!(target: "io", "mixed_line_endings_detected_startup");
                 }
             }
-            state.dirty = false;
+            state.set_dirty(false);
+            if let Some(bytes) = binary {
+                state.load_binary(bytes);
+                state.set_ephemeral(BINARY_OPENED_MSG, std::time::Duration::from_secs(3));
+            }
+            if let Some(dir) = directory {
+                match core_state::Explorer::read(&dir) {
+                    Ok(explorer) => state.load_directory(explorer),
+                    Err(e) => {
+                        error!(target: "io", ?e, "directory_open_error");
+                        open_failed = true;
+                    }
+                }
+            }
             if open_failed {
                 state.set_ephemeral("Open failed", std::time::Duration::from_secs(3));
+            } else if let Some(swap) = state.detect_stale_swap() {
+                let name = swap.file_name().unwrap_or_default().to_string_lossy();
+                state.set_ephemeral(
+                    format!(
+                        "E325: ATTENTION: Found a swap file {name}; :recover to restore, :recover! to delete"
+                    ),
+                    std::time::Duration::from_secs(10),
+                );
             }
         }
+        if model.state().explorer().is_some() {
+            let last = model.state().active_buffer().line_count().saturating_sub(1);
+            model.active_view_mut().cursor =
+                core_text::Position::new(core_state::EXPLORER_HEADER_LINES.min(last), 0);
+        }
 
         let mut config = load_from(args.config.clone())?;
+        // Like Vim's `-es`: a script's result must not depend on earlier
+        // sessions, nor overwrite their registers, history and positions.
+        if args.headless && !args.shada {
+            config.file.shada.enabled = false;
+        }
         let terminal_caps = TerminalCapabilities::detect();
         let platform_traits =
             ConfigPlatformTraits::new(cfg!(windows), terminal_caps.supports_scroll_region);
@@ -227,12 +408,47 @@ impl AppStartup {
             config.apply_context(ctx);
         }
         model.state_mut().config_vertical_margin = config.effective_vertical_margin as usize;
+        model.state_mut().options = config.option_table();
+        core_text::grapheme::set_tabstop(
+            model.state().options.get_number("tabstop").max(1) as usize
+        );
+        for (lhs, rhs) in &config.file.abbreviations {
+            if let Err(e) = model.state_mut().abbreviations.define(lhs, rhs) {
+                warn!(target: "config", abbreviation = %lhs, error = %e, "config_abbreviation_rejected");
+            }
+        }
+        if let Some(name) = &config.file.colorscheme {
+            match Theme::load(name) {
+                Ok(theme) => model.state_mut().set_theme(theme),
+                Err(e) => {
+                    tracing::warn!(target: "config", theme = %name, error = %e, "colorscheme_load_failed");
+                    model
+                        .state_mut()
+                        .set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+                }
+            }
+        }
+        if let Some(path) = config.file.shada.resolved_path()
+            && let Err(e) = model.state_mut().read_shada(&path)
+        {
+            tracing::warn!(target: "state.shada", path = %path.display(), error = %e, "shada_read_failed");
+            model
+                .state_mut()
+                .set_ephemeral(e.to_string(), std::time::Duration::from_secs(3));
+        }
+        model.state_mut().file_positions.restore = config.file.shada.restore_cursor;
+        if model.state().hex_view().is_none()
+            && let Some(pos) = model.state().restored_cursor()
+        {
+            model.active_view_mut().cursor = pos;
+        }
+
+        let plugins = load_plugins(&config.file.plugins, model.state_mut());
 
         let telemetry = StartupTelemetry::new(
             model
                 .state()
-                .file_name
-                .as_ref()
+                .file_name()
                 .and_then(|p| p.file_name())
                 .and_then(|s| s.to_str())
                 .unwrap_or("untitled")
@@ -247,6 +463,7 @@ impl AppStartup {
             config,
             platform_traits,
             telemetry,
+            plugins,
         })
     }
 }
@@ -256,6 +473,7 @@ struct EditorBootstrap {
     config: core_config::Config,
     platform_traits: ConfigPlatformTraits,
     telemetry: StartupTelemetry,
+    plugins: Option<WasmPluginHost>,
 }
 
 struct EditorRuntime<'a> {
@@ -265,18 +483,113 @@ struct EditorRuntime<'a> {
     scheduler: RenderScheduler,
     render_engine: RenderEngine,
     render_metrics: RenderMetricsLedger,
+    /// Parse state behind `EditorState::highlights`.
+    syntax: SyntaxManager,
+    /// A dispatch may have edited or switched the active buffer since the
+    /// last `sync_syntax`.
+    syntax_pending: bool,
     sticky_visual_col: Option<usize>,
     paste: PasteSession,
     ngi_timeout: NgiTimeoutState,
+    resize: ResizeDebounce,
     translator: NgiTranslator,
     observers: Vec<Box<dyn ActionObserver>>,
+    commands: CommandRegistry,
     hooks: Box<dyn EventHooks>,
-    rx: mpsc::Receiver<Event>,
-    tx: Option<mpsc::Sender<Event>>,
+    rx: EventReceiver,
+    tx: Option<EventChannels>,
     source_handles: Vec<tokio::task::JoinHandle<()>>,
+    /// In-flight external commands keyed by request id.
+    shell_jobs: HashMap<u64, ShellTarget>,
+    /// The running `:grep`; a newer one aborts it.
+    grep_job: Option<tokio::task::JoinHandle<()>>,
+    /// Running `:make` / `:job` commands keyed by job id; aborting one
+    /// kills its process.
+    jobs: HashMap<u64, tokio::task::JoinHandle<()>>,
+    /// Running `:defer` / `:timer` and plugin timers keyed by timer id;
+    /// aborting one stops it.
+    timers: HashMap<u64, tokio::task::JoinHandle<()>>,
+    /// The loaded plugins, shared with the handlers of their commands;
+    /// plugin timers call back into them.
+    plugins: Option<Arc<Mutex<PluginDispatcher>>>,
+    /// Status segment providers (plugins), polled from the event loop.
+    segments: SegmentRunner,
+    /// `--listen` clients: replies to their requests and event notifications.
+    rpc: Option<RpcHub>,
+    /// Hot reloaded when it changes on disk.
+    config_path: PathBuf,
+    /// What the `FileWatchSource` watches: the open files and listings and
+    /// the config file. `None` in headless runs.
+    file_watch: Option<FileWatchHandle>,
+    /// Language servers of `[lsp]`, started as their files are opened.
+    lsp: LspSessions,
+    /// The active buffer may have been edited or switched since the last
+    /// `sync_lsp`.
+    lsp_pending: bool,
+    autosave: IdleTimer,
+    swap_timer: IdleTimer,
+    metrics_sink: Option<MetricsSink>,
+    /// Whether the last frame reserved the top row for the tabline.
+    tabline_shown: bool,
+    /// Whether the last frame reserved a row for the wildmenu.
+    wildmenu_shown: bool,
+    /// The last left click landed on text, so dragging selects from it.
+    mouse_drag: bool,
     input_task: Option<tokio::task::JoinHandle<()>>,
     input_shutdown: Option<core_input::AsyncInputShutdown>,
-    _terminal_guard: Option<core_terminal::TerminalGuard<'a>>,
+    terminal_guard: Option<core_terminal::TerminalGuard<'a>>,
+}
+
+/// The state `--listen` clients can subscribe to, compared across one
+/// event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RpcSnapshot {
+    mode: &'static str,
+    /// `:e` may load a file into the active buffer slot, so the path counts.
+    buffer: (core_state::BufferId, Option<PathBuf>),
+    modified: bool,
+    cursor: (usize, usize),
+}
+
+impl RpcSnapshot {
+    fn capture(model: &EditorModel) -> Self {
+        let state = model.state();
+        let cursor = model.active_view().cursor;
+        Self {
+            mode: match state.mode {
+                Mode::Normal => "normal",
+                Mode::Insert => "insert",
+                Mode::VisualChar => "visual",
+            },
+            buffer: (state.active, state.file_name().map(Path::to_path_buf)),
+            modified: state.dirty(),
+            cursor: (cursor.line, cursor.byte),
+        }
+    }
+}
+
+/// `buffer_lines [start, end]`: lines `start..end` of the active buffer
+/// without their endings. Both default to the whole buffer; a negative
+/// `end` counts from past the last line (`-1` is through the last line).
+fn rpc_buffer_lines(state: &EditorState, params: &[RpcValue]) -> Result<RpcValue, String> {
+    let buffer = state.active_buffer();
+    let count = buffer.line_count() as i64;
+    let bound = |i: usize, default: i64| match params.get(i) {
+        None | Some(RpcValue::Nil) => Ok(default),
+        Some(v) => v
+            .as_i64()
+            .ok_or_else(|| "E475: Invalid argument".to_string()),
+    };
+    let start = bound(0, 0)?;
+    let end = match bound(1, -1)? {
+        end if end < 0 => count + 1 + end,
+        end => end,
+    };
+    let lines = (start.clamp(0, count)..end.clamp(0, count))
+        .filter_map(|i| buffer.line(i as usize))
+        .map(|line| RpcValue::from(line.trim_end_matches(['\n', '\r'])))
+        .collect();
+    Ok(RpcValue::Array(lines))
 }
 
 #[derive(Clone)]
@@ -286,6 +599,10 @@ struct StatusSnapshot {
     command_buffer: String,
     ephemeral: Option<String>,
     dirty: bool,
+    /// The ruler's percentage and the selection size change without the
+    /// cursor moving (an edit below it, `o` in Visual mode).
+    line_count: usize,
+    selection: Option<core_state::SelectionSize>,
 }
 
 impl StatusSnapshot {
@@ -295,7 +612,9 @@ impl StatusSnapshot {
             command_active: state.command_line.is_active(),
             command_buffer: state.command_line.buffer().to_string(),
             ephemeral: state.ephemeral_status.as_ref().map(|m| m.text.clone()),
-            dirty: state.dirty,
+            dirty: state.dirty(),
+            line_count: state.active_buffer().line_count(),
+            selection: state.selection_size(),
         }
     }
 
@@ -305,6 +624,8 @@ impl StatusSnapshot {
             || self.command_buffer != other.command_buffer
             || self.ephemeral != other.ephemeral
             || self.dirty != other.dirty
+            || self.line_count != other.line_count
+            || self.selection != other.selection
     }
 }
 
@@ -338,7 +659,7 @@ impl CommandContextSnapshot {
     }
 
     fn colon_active(&self) -> bool {
-        self.command_active && self.pending_buffer.starts_with(':')
+        self.command_active && self.pending_buffer.starts_with([':', '=', '/', '?'])
     }
 }
 
@@ -396,6 +717,80 @@ impl RenderMetricsLedger {
     fn apply_to_state(&self, state: &mut EditorState) {
         state.last_render_delta = self.last_delta;
         state.last_render_path = self.last_path;
+        state.last_input_telemetry = Some(input_telemetry());
+    }
+}
+
+/// `[metrics] export`: one compact `metrics_json` line per
+/// `Event::Telemetry` (every `interval_ms`, from a `TelemetrySource`) and
+/// one at shutdown, to stderr or appended to a file.
+struct MetricsSink {
+    out: Box<dyn std::io::Write>,
+}
+
+impl MetricsSink {
+    fn from_config(config: &core_config::MetricsConfig) -> Option<Self> {
+        let target = config.export.as_deref()?;
+        let out: Box<dyn std::io::Write> = if target == "stderr" {
+            Box::new(std::io::stderr())
+        } else {
+            match std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(target)
+            {
+                Ok(file) => Box::new(file),
+                Err(e) => {
+                    warn!(target: "runtime.metrics", ?e, path = target, "metrics_export_open_failed");
+                    return None;
+                }
+            }
+        };
+        info!(target: "runtime.metrics", sink = target, interval_ms = config.interval_ms, "metrics_export_enabled");
+        Some(Self { out })
+    }
+
+    /// Append a snapshot. Returns false after a failed write; the caller
+    /// drops the sink.
+    fn write(&mut self, state: &EditorState) -> bool {
+        let unix_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let line = core_state::metrics_json(state, unix_ms, false);
+        match writeln!(self.out, "{line}").and_then(|()| self.out.flush()) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(target: "runtime.metrics", ?e, "metrics_export_write_failed");
+                false
+            }
+        }
+    }
+}
+
+/// Current input task counters, for the metrics overlay.
+fn input_telemetry() -> core_state::InputTelemetryLite {
+    use std::sync::atomic::Ordering::Relaxed;
+    core_state::InputTelemetryLite {
+        keypress_total: core_events::KEYPRESS_TOTAL.load(Relaxed),
+        keypress_repeat: core_events::KEYPRESS_REPEAT.load(Relaxed),
+        paste_sessions: core_events::PASTE_SESSIONS.load(Relaxed),
+        paste_chunks: core_events::PASTE_CHUNKS.load(Relaxed),
+        paste_bytes: core_events::PASTE_BYTES.load(Relaxed),
+        send_failures: core_events::CHANNEL_SEND_FAILURES.load(Relaxed),
+        blocking_sends: core_events::CHANNEL_BLOCKING_SENDS.load(Relaxed),
+        input_preferred: core_events::INPUT_PREFERRED.load(Relaxed),
+        motions_coalesced: core_events::MOTIONS_COALESCED.load(Relaxed),
+        resizes_suppressed: core_events::RESIZES_SUPPRESSED.load(Relaxed),
+        async_input_starts: core_events::ASYNC_INPUT_STARTS.load(Relaxed),
+        async_input_stops: [
+            &core_events::ASYNC_INPUT_STOP_SIGNAL,
+            &core_events::ASYNC_INPUT_STOP_CHANNEL,
+            &core_events::ASYNC_INPUT_STOP_STREAM,
+            &core_events::ASYNC_INPUT_STOP_ERROR,
+        ]
+        .iter()
+        .map(|c| c.load(Relaxed))
+        .sum(),
     }
 }
 
@@ -512,6 +907,33 @@ impl NgiTimeoutState {
     }
 }
 
+/// A terminal size not laid out yet. Dragging a window edge sends a
+/// resize per step; only the last size is applied, `RESIZE_DEBOUNCE` after
+/// it arrived or as soon as another kind of event comes in.
+#[derive(Debug, Clone, Copy, Default)]
+struct ResizeDebounce {
+    pending: Option<(u16, u16)>,
+    deadline: Option<Instant>,
+}
+
+impl ResizeDebounce {
+    /// Note a resize at `now`; true when it replaces one not applied yet.
+    fn push(&mut self, width: u16, height: u16, now: Instant) -> bool {
+        self.deadline = Some(now + RESIZE_DEBOUNCE);
+        self.pending.replace((width, height)).is_some()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// The size to apply, if one is waiting.
+    fn take(&mut self) -> Option<(u16, u16)> {
+        self.deadline = None;
+        self.pending.take()
+    }
+}
+
 struct TimeoutFlushResult {
     action: Option<Action>,
     pending: PendingState,
@@ -552,15 +974,39 @@ impl<'a> RenderInvoker<'a> {
         model: &mut EditorModel,
         decision: &core_render::scheduler::Decision,
     ) -> Result<()> {
-        let (state, view) = model.split_state_and_active_view();
-        let path_snapshot = render(self.engine, state, &*view, decision)?;
+        let path_snapshot = render(self.engine, model, decision)?;
         let delta_snapshot = convert_delta_snapshot(self.scheduler.metrics_snapshot());
         self.metrics.store(delta_snapshot, path_snapshot);
-        self.metrics.apply_to_state(state);
+        self.metrics.apply_to_state(model.state_mut());
+        let state = model.state_mut();
+        state.last_frame_profile.clear();
+        state
+            .last_frame_profile
+            .extend(
+                self.engine
+                    .frame_profile()
+                    .map(|f| core_state::FrameStagesLite {
+                        path: f.path.name(),
+                        total_ns: f.total_ns,
+                        hash_ns: f.hash_ns,
+                        compose_ns: f.compose_ns,
+                        write_ns: f.write_ns,
+                        flush_ns: f.flush_ns,
+                    }),
+            );
         Ok(())
     }
 }
 
+fn latency_lite(p: core_render::latency::LatencyPercentiles) -> core_state::LatencyLite {
+    core_state::LatencyLite {
+        p50_ns: p.p50_ns,
+        p95_ns: p.p95_ns,
+        p99_ns: p.p99_ns,
+        samples: p.samples,
+    }
+}
+
 enum LoopControl {
     Continue { lines_changed: usize },
     Break { reason: ShutdownReason },
@@ -624,7 +1070,7 @@ impl PasteSession {
         }
     }
 
-    fn finish(&mut self) -> Option<(String, Vec<String>)> {
+    fn finish(&mut self) -> Option<String> {
         let Some(buffer) = self.buffer.take() else {
             trace!(target: "input.paste", "paste_finish_empty");
             return None;
@@ -632,75 +1078,258 @@ impl PasteSession {
 
         let (normalized, graphemes) = normalize_into_graphemes(&buffer);
         log_paste_commit(&normalized, graphemes.len());
-        Some((normalized, graphemes))
+        Some(normalized)
     }
 }
 
 impl<'a> EditorRuntime<'a> {
     fn new(
         context: RuntimeContext<'a>,
-        tx: mpsc::Sender<Event>,
-        rx: mpsc::Receiver<Event>,
-        input_task: tokio::task::JoinHandle<()>,
-        input_shutdown: core_input::AsyncInputShutdown,
+        tx: EventChannels,
+        rx: EventReceiver,
+        input_task: Option<tokio::task::JoinHandle<()>>,
+        input_shutdown: Option<core_input::AsyncInputShutdown>,
         source_handles: Vec<tokio::task::JoinHandle<()>>,
     ) -> Self {
         let RuntimeContext {
-            model,
+            mut model,
             config,
             platform_traits,
             terminal_guard,
+            headless: _,
+            recorder,
+            replay: _,
+            plugins,
+            rpc_server: _,
+            rpc,
+            config_path,
+            file_watch,
+            log,
         } = context;
+        let mut commands = build_command_registry(&config);
+        if let Some(log) = log {
+            register_log_commands(&mut commands, log);
+        }
+        let mut keymap = config.file.keymap.clone();
+        let mut segments =
+            SegmentRunner::new(Duration::from_millis(config.file.statusline.timeout_ms));
+        if let Some(order) = &config.file.statusline.segments {
+            model.state_mut().status_segments.set_order(order.clone());
+        }
+        let plugins = plugins.map(|mut host| {
+            for provider in host.status_segments() {
+                segments.register(provider);
+            }
+            let dispatcher = PluginDispatcher::new(Box::new(host));
+            add_plugin_keymaps(&mut keymap, &dispatcher.keymaps());
+            let dispatcher = Arc::new(Mutex::new(dispatcher));
+            register_plugin_commands(&mut commands, &dispatcher);
+            dispatcher
+        });
+        let translator = build_translator(&keymap);
+        let autosave = IdleTimer::new(config.file.files.autosave_ms, Instant::now());
+        // The configured scheme is resolved once, for the terminal's depth.
+        model.state_mut().take_theme_change();
+        let render_engine =
+            RenderEngine::for_terminal(color_depth_override(&config), model.state().theme());
+        let metrics_sink = MetricsSink::from_config(&config.file.metrics);
+        model
+            .state_mut()
+            .diagnostics
+            .set_virtual_text(config.file.diagnostics.virtual_text);
+        let lsp = LspSessions::new(
+            config.file.lsp.clone(),
+            std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        );
         Self {
             model,
             config,
             platform_traits,
             scheduler: RenderScheduler::new(),
-            render_engine: RenderEngine::new(),
+            render_engine,
             render_metrics: RenderMetricsLedger::default(),
+            syntax: SyntaxManager::new(),
+            syntax_pending: true,
             sticky_visual_col: None,
             paste: PasteSession::new(),
             ngi_timeout: NgiTimeoutState::default(),
-            translator: NgiTranslator::new(),
+            resize: ResizeDebounce::default(),
+            translator,
             observers: Vec::new(),
-            hooks: Box::new(NoopEventHooks),
+            commands,
+            hooks: match recorder {
+                Some(recorder) => Box::new(recorder),
+                None => Box::new(NoopEventHooks),
+            },
             rx,
             tx: Some(tx),
             source_handles,
-            input_task: Some(input_task),
-            input_shutdown: Some(input_shutdown),
-            _terminal_guard: Some(terminal_guard),
+            shell_jobs: HashMap::new(),
+            grep_job: None,
+            jobs: HashMap::new(),
+            timers: HashMap::new(),
+            plugins,
+            segments,
+            rpc,
+            config_path,
+            file_watch,
+            lsp,
+            lsp_pending: true,
+            autosave,
+            swap_timer: IdleTimer::new(0, Instant::now()),
+            metrics_sink,
+            tabline_shown: false,
+            wildmenu_shown: false,
+            mouse_drag: false,
+            input_task,
+            input_shutdown,
+            terminal_guard,
+        }
+    }
+
+    /// `--headless`: run the steps against the loaded buffer with no
+    /// terminal and no input task, echoing each step's messages to stdout
+    /// (errors to stderr). Returns the exit status: 1 if any step reported
+    /// an `E<n>:` error, else 0.
+    async fn run_headless(&mut self, steps: Vec<HeadlessStep>) -> i32 {
+        let leader = core_keymap::user::parse_leader(&self.config.file.keymap.leader)
+            .unwrap_or(KeyToken::Char(core_keymap::user::DEFAULT_LEADER));
+        let mut failed = false;
+        let mut shutdown_reason = ShutdownReason::ChannelClosed;
+        for step in steps {
+            debug!(target: "runtime.headless", ?step, "headless_step");
+            let control = match &step {
+                HeadlessStep::Ex(cmd) => self.run_headless_ex(cmd),
+                HeadlessStep::Keys(notation) => {
+                    match core_keymap::user::parse_keys(notation, &leader) {
+                        Ok(keys) => self.run_headless_keys(keys),
+                        Err(e) => {
+                            self.model.state_mut().set_ephemeral(
+                                format!("E475: Invalid argument: {e}"),
+                                Duration::from_secs(3),
+                            );
+                            LoopControl::Continue { lines_changed: 0 }
+                        }
+                    }
+                }
+            };
+            self.await_shell_jobs().await;
+            failed |= self.flush_headless_messages();
+            if let LoopControl::Break { reason } = control {
+                shutdown_reason = reason;
+                break;
+            }
+        }
+        self.rx.close();
+        self.finalize_shutdown(shutdown_reason).await;
+        info!(target: "runtime.headless", failed, "headless_complete");
+        i32::from(failed)
+    }
+
+    fn run_headless_ex(&mut self, cmd: &str) -> LoopControl {
+        let cmd = cmd.strip_prefix(':').unwrap_or(cmd);
+        let mut outcome = self.process_action(Action::CommandStart);
+        outcome.absorb(self.process_action(Action::CommandExecute(format!(":{cmd}"))));
+        let quit = outcome.quit;
+        let lines_changed = self.apply_dispatch_outcome(outcome);
+        if quit {
+            LoopControl::Break {
+                reason: ShutdownReason::CommandQuit,
+            }
+        } else {
+            LoopControl::Continue { lines_changed }
+        }
+    }
+
+    fn run_headless_keys(&mut self, keys: Vec<KeyToken>) -> LoopControl {
+        for key in keys {
+            if let brk @ LoopControl::Break { .. } = self.handle_key_press(&KeyEventExt::new(key)) {
+                return brk;
+            }
+        }
+        // The end of a script is a typing pause: settle an ambiguous prefix.
+        let now = Instant::now();
+        match self.translator.flush_pending_literal(&self.config, now) {
+            Some(resolution) => {
+                let control = self.apply_resolution(resolution, KeypressMeta::new(false, now));
+                self.replay_mapped_keys(control, now)
+            }
+            None => LoopControl::Continue { lines_changed: 0 },
+        }
+    }
+
+    /// Wait for the `:!` / filter jobs, `:grep` and background jobs a step
+    /// started; headless runs have no other event sources.
+    async fn await_shell_jobs(&mut self) {
+        while !self.shell_jobs.is_empty()
+            || self.model.state().grep.is_running()
+            || self.model.state().jobs.is_running()
+        {
+            match self.rx.recv().await {
+                Some(Event::ShellOutput(output)) => {
+                    self.handle_shell_output(&output);
+                }
+                Some(Event::GrepOutput(output)) => {
+                    self.handle_grep_output(&output);
+                }
+                Some(Event::JobOutput(output)) => {
+                    self.handle_job_output(&output);
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+    }
+
+    /// Print and clear the messages the last step left; true if any was an error.
+    fn flush_headless_messages(&mut self) -> bool {
+        let state = self.model.state_mut();
+        let mut lines = std::mem::take(&mut state.message_lines);
+        state.dismiss_message_lines();
+        lines.extend(state.ephemeral_status.take().map(|msg| msg.text));
+        let mut failed = false;
+        for line in lines.into_iter().filter(|line| !line.is_empty()) {
+            if is_error_message(&line) {
+                failed = true;
+                eprintln!("{line}");
+            } else {
+                println!("{line}");
+            }
         }
+        failed
     }
 
     async fn run(&mut self) -> Result<()> {
-        self.perform_initial_render();
+        self.guarded(Self::perform_initial_render);
 
         let render_span = tracing::debug_span!(target: "runtime", "event_loop");
         let _enter_loop = render_span.enter();
 
         let mut shutdown_reason = ShutdownReason::ChannelClosed;
-        while let Some(event) = self.rx.recv().await {
-            self.hooks.pre_handle(&event);
+        while let Some(event) = self.next_event().await {
+            let (control, view_before, rpc_before) = self.guarded(|rt| {
+                rt.hooks.pre_handle(&event);
+                // Other events see the terminal as it is now.
+                if !matches!(event, Event::Input(InputEvent::Resize(..))) {
+                    rt.apply_resize();
+                }
+                let view_before = rt.model.active_view().clone();
+                let rpc_before = rt.rpc.is_some().then(|| RpcSnapshot::capture(&rt.model));
+                (rt.handle_event(&event), view_before, rpc_before)
+            });
 
-            let control = match &event {
-                Event::Input(input) => self.handle_input_event(input),
-                Event::Command(cmd) => self.handle_command_event(cmd),
-                Event::RenderRequested => self.handle_render_requested(),
-                Event::Tick => self.handle_tick(),
-                Event::Shutdown => self.handle_shutdown(),
-            };
+            if self.model.state().shell.interactive {
+                self.run_interactive_shell().await;
+            }
 
             match control {
                 LoopControl::Break { reason } => {
                     shutdown_reason = reason;
                     break;
                 }
-                LoopControl::Continue { lines_changed } => {
-                    let scrolled = self.auto_scroll();
-                    self.finish_cycle(lines_changed, scrolled);
-                    self.hooks.post_handle(&event);
-                }
+                LoopControl::Continue { lines_changed } => self.guarded(|rt| {
+                    rt.end_cycle(&event, lines_changed, &view_before, rpc_before.as_ref())
+                }),
             }
         }
 
@@ -709,8 +1338,95 @@ impl<'a> EditorRuntime<'a> {
         Ok(())
     }
 
+    fn handle_event(&mut self, event: &Event) -> LoopControl {
+        match event {
+            Event::Input(input) => self.handle_input_event(input),
+            Event::Command(cmd) => self.handle_command_event(cmd),
+            Event::RenderRequested => self.handle_render_requested(),
+            Event::Tick => self.handle_tick(),
+            Event::ShellOutput(output) => self.handle_shell_output(output),
+            Event::GrepOutput(output) => self.handle_grep_output(output),
+            Event::JobOutput(output) => self.handle_job_output(output),
+            Event::FileChanged(path) => self.handle_file_changed(path),
+            Event::Timer(id) => self.handle_timer(*id),
+            Event::Telemetry => {
+                self.export_metrics();
+                LoopControl::Continue { lines_changed: 0 }
+            }
+            Event::GitInfo(info) => self.handle_git_info(info),
+            Event::GitBlame(blame) => self.handle_git_blame(blame),
+            Event::StatusSegment(update) => self.handle_status_segment(update),
+            Event::Rpc(request) => self.handle_rpc(request),
+            Event::Lsp(message) => self.handle_lsp(message),
+            Event::Shutdown => self.handle_shutdown(),
+        }
+    }
+
+    /// After an event the loop goes on with: follow-up requests, scrolling
+    /// and the frame.
+    fn end_cycle(
+        &mut self,
+        event: &Event,
+        lines_changed: usize,
+        view_before: &core_model::View,
+        rpc_before: Option<&RpcSnapshot>,
+    ) {
+        if self.model.active_view().id != view_before.id {
+            self.model.state_mut().git.request_refresh();
+            self.lsp_pending = true;
+        }
+        self.spawn_git_probe();
+        self.spawn_git_blame();
+        self.poll_segments(Instant::now());
+        self.sync_lsp();
+        self.sync_file_watch();
+        self.apply_goto();
+        if let Some(before) = rpc_before {
+            self.publish_rpc_events(before);
+        }
+        let scrolled = self.auto_scroll(view_before);
+        let scrolled = self.scroll_bind(view_before) || scrolled;
+        self.finish_cycle(lines_changed, scrolled);
+        self.hooks.post_handle(event);
+    }
+
+    /// Run `f` under `crash::contain`. A panic restores the terminal,
+    /// saves the modified buffers and a crash report, says where they went
+    /// and is raised again.
+    fn guarded<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        match crash::contain(|| f(self)) {
+            Ok(value) => value,
+            Err(payload) => {
+                self.crash();
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    fn crash(&mut self) {
+        let report = crash::CrashReport::take();
+        // Leaves raw mode and the alternate screen.
+        drop(self.terminal_guard.take());
+        let dir = recovery_dir();
+        let recovered =
+            crash::contain(|| self.model.state().emergency_dump(&dir)).unwrap_or_default();
+        error!(target: "runtime.panic", recovered = recovered.len(), "crashed");
+        let Some(report) = report else {
+            return;
+        };
+        eprintln!("oxidized {}", report.message);
+        for path in &recovered {
+            eprintln!("Saved {}", path.display());
+        }
+        match report.write(&dir, &recovered) {
+            Ok(path) => eprintln!("Crash report: {}", path.display()),
+            Err(e) => eprintln!("Crash report not written: {e}"),
+        }
+    }
+
     async fn finalize_shutdown(&mut self, reason: ShutdownReason) {
         log_shutdown_stage(reason, "begin");
+        self.export_metrics();
         if let Some(tx) = self.tx.take() {
             trace!(
                 target: "runtime.shutdown",
@@ -719,6 +1435,16 @@ impl<'a> EditorRuntime<'a> {
             );
             drop(tx);
         }
+        if let Some(grep) = self.grep_job.take() {
+            // A search has nothing to finish; stop it with its child.
+            grep.abort();
+        }
+        for (_, job) in self.jobs.drain() {
+            job.abort();
+        }
+        for (_, timer) in self.timers.drain() {
+            timer.abort();
+        }
 
         while let Some(handle) = self.source_handles.pop() {
             match tokio::time::timeout(Duration::from_millis(200), handle).await {
@@ -776,10 +1502,42 @@ impl<'a> EditorRuntime<'a> {
             }
         }
 
+        self.model.state_mut().remove_swap_files();
+        self.remember_cursors();
+        if let Some(path) = self.config.file.shada.resolved_path()
+            && let Err(e) = self
+                .model
+                .state()
+                .write_shada(&path, shada_limits(&self.config.file.shada))
+        {
+            error!(target: "state.shada", path = %path.display(), error = %e, "shada_write_failed");
+        }
         log_shutdown_stage(reason, "complete");
     }
 
+    /// Record the cursor of every window as its file's last position, the
+    /// active window's last so it counts as the most recent.
+    fn remember_cursors(&mut self) {
+        let active = self.model.active_view();
+        let active = (active.buffer_id, active.cursor);
+        let cursors: Vec<_> = self
+            .model
+            .tabs()
+            .iter()
+            .flat_map(|tab| tab.view_manager().views())
+            .map(|view| (view.buffer_id, view.cursor))
+            .chain([active])
+            .collect();
+        let state = self.model.state_mut();
+        for (buffer, cursor) in cursors {
+            state.remember_cursor(buffer, cursor);
+        }
+    }
+
     fn perform_initial_render(&mut self) {
+        // The first frame is full anyway; only the highlights are needed.
+        self.syntax_pending = false;
+        self.syntax.sync_all(self.model.state_mut());
         let decision = core_render::scheduler::Decision {
             semantic: RenderDelta::Full,
             effective: RenderDelta::Full,
@@ -796,6 +1554,9 @@ impl<'a> EditorRuntime<'a> {
     }
 
     fn handle_input_event(&mut self, input: &InputEvent) -> LoopControl {
+        let now = Instant::now();
+        self.autosave.note_activity(now);
+        self.swap_timer.note_activity(now);
         match input {
             InputEvent::KeyPress(keypress) => self.handle_key_press(keypress),
             InputEvent::CtrlC => self.handle_ctrl_c(),
@@ -811,7 +1572,7 @@ impl<'a> EditorRuntime<'a> {
             InputEvent::PasteStart => self.handle_paste_start(),
             InputEvent::PasteChunk(chunk) => self.handle_paste_chunk(chunk),
             InputEvent::PasteEnd => self.handle_paste_end(),
-            InputEvent::Mouse(_) => LoopControl::Continue { lines_changed: 0 },
+            InputEvent::Mouse(mouse) => self.handle_mouse(mouse),
             InputEvent::FocusGained
             | InputEvent::FocusLost
             | InputEvent::RawBytes(_)
@@ -832,21 +1593,126 @@ impl<'a> EditorRuntime<'a> {
             timestamp = ?keypress.timestamp
         );
 
+        // Any key dismisses multi-line command output; the key is still
+        // processed so `:` immediately starts the next command.
+        if self.model.state_mut().dismiss_message_lines() {
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+        // Esc dismisses the hover popup (other keys do once they resolve
+        // to an action); `apply_hover_change` repaints.
+        if keypress.token == KeyToken::Named(core_events::NamedKey::Esc) {
+            self.model.state_mut().hover.close();
+        }
+
         let ctx = self.command_context();
+        self.translator
+            .set_normal_keys(NormalKeys::for_state(self.model.state()));
         let resolution = self.translator.ingest_keypress(
             ctx.mode(),
             ctx.pending_buffer(),
             keypress,
             &self.config,
         );
+        let resolution = self.coalesce_motion(resolution, keypress);
 
-        self.apply_resolution(
+        let control = self.apply_resolution(
             resolution,
             KeypressMeta::new(keypress.repeat, keypress.timestamp),
-        )
+        );
+        self.replay_mapped_keys(control, keypress.timestamp)
+    }
+
+    /// When presses of the same key are already queued behind `keypress`
+    /// (a motion key held down faster than frames are drawn), take them now
+    /// and move once by their number instead of dispatching and rendering
+    /// each. Only a motion the key completed on its own is folded, with no
+    /// count or mapped keys pending, so every queued press would resolve to
+    /// it again; half-page motions are left alone since their count sets
+    /// the scroll amount.
+    fn coalesce_motion(
+        &mut self,
+        resolution: NgiResolution,
+        keypress: &KeyEventExt,
+    ) -> NgiResolution {
+        let Some(Action::Motion(motion)) = resolution.action else {
+            return resolution;
+        };
+        if matches!(motion, MotionKind::PageHalfDown | MotionKind::PageHalfUp)
+            || resolution.pending_state != PendingState::Idle
+            || self.translator.has_mapped_keys()
+        {
+            return resolution;
+        }
+        let mut count: u32 = 1;
+        while let Some(event) = self.rx.next_input_if(|event| {
+            matches!(event, Event::Input(InputEvent::KeyPress(next)) if next.token == keypress.token)
+        }) {
+            // Recorded like any handled event, so a replay presses it too.
+            self.hooks.pre_handle(&event);
+            count += 1;
+        }
+        if count == 1 {
+            return resolution;
+        }
+        core_events::MOTIONS_COALESCED
+            .fetch_add(u64::from(count - 1), std::sync::atomic::Ordering::Relaxed);
+        debug!(target: "runtime.input", ?motion, count, "motion_presses_coalesced");
+        NgiResolution {
+            action: Some(Action::MotionWithCount { motion, count }),
+            ..resolution
+        }
+    }
+
+    /// Feed the keys a user mapping expanded to, one at a time so each
+    /// sees the mode and command line the previous one left.
+    fn replay_mapped_keys(&mut self, mut control: LoopControl, timestamp: Instant) -> LoopControl {
+        while let LoopControl::Continue { lines_changed } = control {
+            let Some(mapped) = self.translator.take_mapped_key() else {
+                break;
+            };
+            let ctx = self.command_context();
+            self.translator
+                .set_normal_keys(NormalKeys::for_state(self.model.state()));
+            let resolution = self.translator.translate_mapped(
+                ctx.mode(),
+                ctx.pending_buffer(),
+                &mapped,
+                &self.config,
+                timestamp,
+            );
+            control = match self.apply_resolution(resolution, KeypressMeta::new(false, timestamp)) {
+                LoopControl::Continue {
+                    lines_changed: more,
+                } => LoopControl::Continue {
+                    lines_changed: lines_changed + more,
+                },
+                brk => brk,
+            };
+        }
+        if self.translator.take_expansion_aborted() {
+            self.model
+                .state_mut()
+                .set_ephemeral("E223: Recursive mapping", Duration::from_secs(3));
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        if let Some(name) = self.translator.take_invalid_register() {
+            self.model.state_mut().set_ephemeral(
+                format!("E354: Invalid register name: '{name}'"),
+                Duration::from_secs(3),
+            );
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        control
     }
 
     fn handle_ctrl_c(&mut self) -> LoopControl {
+        // CTRL-C leaves the command-line window instead of quitting (Vim parity).
+        if self.model.state().cmdline_window_active() {
+            let outcome = self.process_action(Action::CmdlineWindowClose);
+            let lines_changed = self.apply_dispatch_outcome(outcome);
+            return LoopControl::Continue { lines_changed };
+        }
         info!(target: "runtime", "shutdown");
         LoopControl::Break {
             reason: ShutdownReason::CtrlC,
@@ -876,7 +1742,38 @@ impl<'a> EditorRuntime<'a> {
         }
     }
 
+    /// The next event to handle. While a resize waits (`ResizeDebounce`),
+    /// it is laid out and drawn once its deadline passes without one.
+    async fn next_event(&mut self) -> Option<Event> {
+        while let Some(deadline) = self.resize.deadline() {
+            match tokio::time::timeout_at(deadline.into(), self.rx.recv()).await {
+                Ok(event) => return event,
+                Err(_) => {
+                    self.apply_resize();
+                    self.finish_cycle(0, false);
+                }
+            }
+        }
+        self.rx.recv().await
+    }
+
+    /// Hold the new size until the resizes stop (`ResizeDebounce`); the
+    /// sizes it replaces are never laid out.
     fn handle_resize(&mut self, width: u16, height: u16) -> LoopControl {
+        if self.resize.push(width, height, Instant::now()) {
+            core_events::RESIZES_SUPPRESSED.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+        trace!(target: "runtime.input", width, height, "resize_deferred");
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// Lay out the waiting resize, if any: drop the render caches and
+    /// recompute the configured margin for the new size.
+    fn apply_resize(&mut self) {
+        let Some((width, height)) = self.resize.take() else {
+            return;
+        };
+        debug!(target: "runtime.input", width, height, "resize_applied");
         self.render_engine.invalidate_for_resize();
         self.scheduler.mark(RenderDelta::Full);
         let ctx = ConfigContext::new(width, height, STATUS_ROWS, 0, self.platform_traits);
@@ -884,45 +1781,252 @@ impl<'a> EditorRuntime<'a> {
             self.model.state_mut().config_vertical_margin = new_margin as usize;
             self.scheduler.mark(RenderDelta::StatusLine);
         }
-        LoopControl::Continue { lines_changed: 0 }
     }
 
-    fn handle_tick(&mut self) -> LoopControl {
-        let mut lines_changed = 0;
+    fn handle_mouse(&mut self, mouse: &MouseEvent) -> LoopControl {
+        let Ok((width, height)) = crossterm::terminal::size() else {
+            return LoopControl::Continue { lines_changed: 0 };
+        };
+        let (col, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.click_at(width, height, col, row),
+            MouseEventKind::Drag(MouseButton::Left) => self.drag_to(width, height, col, row),
+            MouseEventKind::ScrollUp => self.wheel_at(width, height, col, row, false),
+            MouseEventKind::ScrollDown => self.wheel_at(width, height, col, row, true),
+            _ => {}
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
 
-        if self.model.state_mut().tick_ephemeral() {
+    /// Left click at screen cell (`col`, `row`) of a `width` x `height`
+    /// terminal: end Visual mode, focus the split under it and, on its text
+    /// rows, move that view's cursor to the clicked character. A click on a
+    /// split's status row only focuses it; clicks outside any split are
+    /// ignored.
+    fn click_at(&mut self, width: u16, height: u16, col: u16, row: u16) {
+        self.mouse_drag = false;
+        let layout = self.model.layout(text_area(&self.model, width, height));
+        let hit = |r: &core_model::LayoutRegion| {
+            (r.x..r.x.saturating_add(r.width)).contains(&col)
+                && (r.y..r.y.saturating_add(r.height)).contains(&row)
+        };
+        let target = layout
+            .regions()
+            .iter()
+            .zip(layout.views())
+            .find(|(r, _)| hit(r))
+            .map(|(r, id)| (Some(*r), *id))
+            .or_else(|| {
+                layout
+                    .statuses()
+                    .iter()
+                    .zip(layout.views())
+                    .find(|(r, _)| hit(r))
+                    .map(|(_, id)| (None, *id))
+            });
+        let Some((region, id)) = target else {
+            return;
+        };
+        let state = self.model.state_mut();
+        if state.mode == Mode::VisualChar {
+            state.selection.clear();
+            state.mode = Mode::Normal;
             self.scheduler.mark(RenderDelta::StatusLine);
         }
-
-        let now = Instant::now();
-        if let Some(result) = self.ngi_timeout.poll_expired(now, || {
-            self.translator
-                .flush_pending_literal(&self.config, now)
-                .map(TimeoutFlushResult::from_resolution)
-        }) && let Some(action) = result.action
-        {
-            let outcome = self.process_action(action);
-            let quit = outcome.quit;
-            lines_changed = self.apply_dispatch_outcome(outcome);
-            if quit {
-                return LoopControl::Break {
-                    reason: ShutdownReason::ActionQuit,
-                };
+        if id != self.model.active_view().id {
+            if self.model.focus_view_id(id).is_err() {
+                return;
             }
+            self.scheduler.mark(RenderDelta::Full);
+        }
+        let Some(region) = region else {
+            return;
+        };
+        let Some(pos) = self.position_at(region, col, row) else {
+            return;
+        };
+        tracing::trace!(target: "input.mouse", line = pos.line, byte = pos.byte, "click_moves_cursor");
+        self.mouse_drag = true;
+        let view = self.model.active_view_mut();
+        if view.cursor != pos {
+            view.cursor = pos;
+            self.scheduler.mark(RenderDelta::CursorOnly);
         }
-
-        LoopControl::Continue { lines_changed }
     }
 
-    fn handle_text_commit(&mut self, text: &str) -> LoopControl {
-        let (normalized, graphemes) = normalize_into_graphemes(text);
+    /// Left drag to (`col`, `row`) after a click on text: select
+    /// characterwise from the clicked position (entering Visual mode from
+    /// Normal) to the character under the pointer, which is clamped into the
+    /// focused split. Ignored in Insert mode.
+    fn drag_to(&mut self, width: u16, height: u16, col: u16, row: u16) {
+        if !self.mouse_drag || self.model.state().mode == Mode::Insert {
+            return;
+        }
+        let area = text_area(&self.model, width, height);
+        let active = self.model.active_view().id;
+        let Some(region) = self.model.layout(area).region_of(active) else {
+            return;
+        };
+        if region.width == 0 || region.height == 0 {
+            return;
+        }
+        let col = col.clamp(region.x, region.x + region.width - 1);
+        let row = row.clamp(region.y, region.y + region.height - 1);
+        let Some(pos) = self.position_at(region, col, row) else {
+            return;
+        };
+        let (state, view) = self.model.split_state_and_active_view();
+        if state.mode == Mode::Normal {
+            state.selection.anchor = Some(view.cursor);
+            state.mode = Mode::VisualChar;
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        let anchor = *state.selection.anchor.get_or_insert(view.cursor);
+        if view.cursor != pos || state.selection.active.is_none() {
+            view.cursor = pos;
+            state.selection.set(core_state::SelectionSpan::new(
+                anchor,
+                pos,
+                core_state::SelectionKind::Characterwise,
+            ));
+            self.scheduler.mark(RenderDelta::CursorOnly);
+        }
+    }
+
+    /// Scroll the split under (`col`, `row`) by `'mousescroll'` lines
+    /// without focusing it. The focused view's move is marked as a scroll by
+    /// `auto_scroll`; another view repaints fully.
+    fn wheel_at(&mut self, width: u16, height: u16, col: u16, row: u16, down: bool) {
+        let lines = core_config::options::mouse_scroll_lines(
+            self.model.state().options.get_string("mousescroll"),
+        )
+        .unwrap_or(0);
+        if lines == 0 {
+            return;
+        }
+        let area = text_area(&self.model, width, height);
+        let layout = self.model.layout(area);
+        let Some(id) = layout
+            .regions()
+            .iter()
+            .zip(layout.views())
+            .find(|(r, _)| {
+                (r.x..r.x.saturating_add(r.width)).contains(&col)
+                    && (r.y..r.y.saturating_add(r.height)).contains(&row)
+            })
+            .map(|(_, id)| *id)
+        else {
+            return;
+        };
+        let delta = if down {
+            lines as isize
+        } else {
+            -(lines as isize)
+        };
+        if self.model.scroll_view(id, delta, area).is_some() && id != self.model.active_view().id {
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// Buffer position under text cell (`col`, `row`) of the active view
+    /// painted in `region`.
+    fn position_at(
+        &mut self,
+        region: core_model::LayoutRegion,
+        col: u16,
+        row: u16,
+    ) -> Option<core_text::Position> {
+        let (state, view) = self.model.split_state_and_active_view();
+        let gutter = core_render::gutter::Gutter::for_view(state, view).width;
+        let wrap = core_render::wrap::view_wrap(state, view, region.width);
+        core_render::wrap::screen_position_filled(
+            state.active_buffer(),
+            wrap,
+            view,
+            core_render::wrap::Fillers::for_view(state, view),
+            region.height as usize,
+            row - region.y,
+            (col - region.x).saturating_sub(gutter),
+        )
+    }
+
+    fn handle_tick(&mut self) -> LoopControl {
+        let mut lines_changed = 0;
+
+        if self.model.state_mut().tick_ephemeral() {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+
+        let now = Instant::now();
+        if self.autosave.poll(now) {
+            self.run_autosave();
+        }
+        self.update_swap_files(now);
+
+        if let Some(result) = self.ngi_timeout.poll_expired(now, || {
+            self.translator
+                .flush_pending_literal(&self.config, now)
+                .map(TimeoutFlushResult::from_resolution)
+        }) && let Some(action) = result.action
+        {
+            let outcome = self.process_action(action);
+            let quit = outcome.quit;
+            lines_changed = self.apply_dispatch_outcome(outcome);
+            if quit {
+                return LoopControl::Break {
+                    reason: ShutdownReason::ActionQuit,
+                };
+            }
+        }
+
+        self.replay_mapped_keys(LoopControl::Continue { lines_changed }, now)
+    }
+
+    /// Background save after the `[files] autosave_ms` idle period.
+    /// Append a metrics snapshot to the `[metrics]` sink, with the input
+    /// counters as they are now even when nothing was rendered lately.
+    fn export_metrics(&mut self) {
+        let Some(sink) = self.metrics_sink.as_mut() else {
+            return;
+        };
+        let state = self.model.state_mut();
+        state.last_input_telemetry = Some(input_telemetry());
+        if !sink.write(state) {
+            self.metrics_sink = None;
+        }
+    }
+
+    fn run_autosave(&mut self) {
+        let state = self.model.state_mut();
+        let report = autosave(state, &recovery_dir());
+        if !report.is_empty() {
+            state.set_ephemeral(report.message(), std::time::Duration::from_secs(2));
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+    }
+
+    /// Refresh swap files after `'updatetime'` ms of idle time.
+    fn update_swap_files(&mut self, now: Instant) {
+        let state = self.model.state_mut();
+        if !state.options.get_bool("swapfile") {
+            return;
+        }
+        let updatetime = state.options.get_number("updatetime").max(0) as u64;
+        self.swap_timer.set_interval_ms(updatetime);
+        if self.swap_timer.poll(now) {
+            state.update_swap_files();
+        }
+    }
+
+    fn handle_text_commit(&mut self, text: &str) -> LoopControl {
+        let (normalized, graphemes) = normalize_into_graphemes(text);
         tracing::debug!(
             target: "input.normalize",
             grapheme_count = graphemes.len(),
             bytes = normalized.len(),
             "text_commit"
         );
-        self.replay_text_input(&normalized, &graphemes)
+        self.replay_text_input(&normalized)
     }
 
     fn handle_paste_start(&mut self) -> LoopControl {
@@ -936,8 +2040,8 @@ impl<'a> EditorRuntime<'a> {
     }
 
     fn handle_paste_end(&mut self) -> LoopControl {
-        if let Some((normalized, graphemes)) = self.paste.finish() {
-            self.replay_text_input(&normalized, &graphemes)
+        if let Some(normalized) = self.paste.finish() {
+            self.replay_text_input(&normalized)
         } else {
             LoopControl::Continue { lines_changed: 0 }
         }
@@ -955,28 +2059,451 @@ impl<'a> EditorRuntime<'a> {
         }
     }
 
+    fn handle_shell_output(&mut self, output: &ShellOutput) -> LoopControl {
+        let Some(target) = self.shell_jobs.remove(&output.id) else {
+            warn!(target: "runtime.shell", id = output.id, "shell_output_unknown_job");
+            return LoopControl::Continue { lines_changed: 0 };
+        };
+        let result = apply_shell_output(&mut self.model, &target, output);
+        self.lsp_pending = true;
+        self.render_engine.invalidate_for_resize();
+        self.scheduler.mark(RenderDelta::Full);
+        if result.buffer_replaced {
+            self.sticky_visual_col = None;
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// A batch of `:grep` output: add its matches to their list, open the
+    /// first one if asked to, and refresh the status line summary.
+    fn handle_grep_output(&mut self, output: &GrepOutput) -> LoopControl {
+        let result = apply_grep_output(output, &mut self.model);
+        if !result.dirty {
+            debug!(target: "runtime.grep", id = output.id, "grep_output_dropped");
+            return LoopControl::Continue { lines_changed: 0 };
+        }
+        if result.buffer_replaced {
+            self.lsp_pending = true;
+            self.sticky_visual_col = None;
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+        let state = self.model.state_mut();
+        if output.done.is_some() && !state.grep.is_running() {
+            self.grep_job = None;
+        }
+        let summary = state.grep.summary().map(str::to_string);
+        state.status_segments.set("grep", summary);
+        self.scheduler.mark(RenderDelta::StatusLine);
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// Timer `id` fired: run its callback, unless it was stopped meanwhile.
+    fn handle_timer(&mut self, id: u64) -> LoopControl {
+        let state = self.model.state_mut();
+        let callback = state.timer_fired(id);
+        if !state.timers.active().iter().any(|timer| timer.id == id) {
+            self.timers.remove(&id);
+        }
+        let control = match callback {
+            Some(TimerCallback::Ex(cmd)) => self.run_timer_ex(&cmd),
+            Some(TimerCallback::Plugin { plugin, token }) => {
+                self.run_plugin_timer(&plugin, token);
+                LoopControl::Continue { lines_changed: 0 }
+            }
+            None => {
+                debug!(target: "runtime.timer", id, "timer_tick_dropped");
+                LoopControl::Continue { lines_changed: 0 }
+            }
+        };
+        self.spawn_timers();
+        control
+    }
+
+    /// Run a timer's ex command as if typed. A command line being typed
+    /// is set aside meanwhile, so it survives and the callback stays out
+    /// of its history.
+    fn run_timer_ex(&mut self, cmd: &str) -> LoopControl {
+        let typed = std::mem::take(&mut self.model.state_mut().command_line);
+        let cmd = cmd.strip_prefix(':').unwrap_or(cmd);
+        let outcome = self.process_action(Action::CommandExecute(format!(":{cmd}")));
+        self.model.state_mut().command_line = typed;
+        let quit = outcome.quit;
+        let lines_changed = self.apply_dispatch_outcome(outcome);
+        if quit {
+            LoopControl::Break {
+                reason: ShutdownReason::CommandQuit,
+            }
+        } else {
+            LoopControl::Continue { lines_changed }
+        }
+    }
+
+    /// Call the `timer` of `plugin` with `token`. A timer whose call fails
+    /// is stopped rather than failing again on every tick.
+    fn run_plugin_timer(&mut self, plugin: &str, token: i32) {
+        let Some(dispatcher) = &self.plugins else {
+            return;
+        };
+        let state = self.model.state_mut();
+        let buffer = state.active_buffer();
+        let text = buffer.slice_bytes(0, buffer.len_bytes());
+        let outcome = dispatcher
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .call_timer(plugin, token, &text);
+        match outcome {
+            Ok(effects) => apply_plugin_effects(plugin, effects, state),
+            Err(e) => {
+                forget_contained_panic(&e);
+                warn!(target: "plugin", plugin, token, error = %e, "plugin_timer_failed");
+                state.stop_plugin_timer(plugin, token);
+                state.set_ephemeral(e.to_string(), Duration::from_secs(3));
+            }
+        }
+        self.scheduler.mark(RenderDelta::StatusLine);
+    }
+
+    /// A batch of `:make` / `:job` output: add it to its quickfix list or
+    /// buffer and, when the job ended, show its exit status.
+    fn handle_job_output(&mut self, output: &JobOutput) -> LoopControl {
+        let result = apply_job_output(output, &mut self.model);
+        if output.done.is_some() {
+            self.jobs.remove(&output.id);
+        }
+        if !result.dirty && !result.buffer_replaced {
+            debug!(target: "runtime.job", id = output.id, "job_output_dropped");
+            return LoopControl::Continue { lines_changed: 0 };
+        }
+        if result.buffer_replaced {
+            self.lsp_pending = true;
+            self.sticky_visual_col = None;
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+        self.update_job_segment();
+        self.scheduler.mark(RenderDelta::StatusLine);
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// The `job` status segment: the latest job's command, with `…` while
+    /// it runs and how it exited after.
+    fn update_job_segment(&mut self) {
+        let state = self.model.state_mut();
+        let summary = state.jobs.summary().map(str::to_string);
+        if state.status_segments.set("job", summary) {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+    }
+
+    /// A watched file or directory changed on disk: hot reload the config
+    /// file, re-read a listing, reload or warn about a buffer's file.
+    fn handle_file_changed(&mut self, path: &Path) -> LoopControl {
+        if path == self.config_path {
+            self.reload_config();
+        }
+        let result = apply_file_change(path, &mut self.model);
+        if result.buffer_replaced {
+            self.syntax_pending = true;
+            self.lsp_pending = true;
+            self.sticky_visual_col = None;
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        } else if result.dirty {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// Take the settings of the changed config file that apply while
+    /// running: option defaults (options set with `:set` keep their value),
+    /// abbreviations, the colorscheme and the scroll margin. Keymaps,
+    /// plugins, language servers, the status line and the shada file keep
+    /// their startup configuration. A file that does not parse changes nothing.
+    fn reload_config(&mut self) {
+        let mut config = match core_config::reload(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!(target: "config", path = %self.config_path.display(), error = %e, "config_reload_failed");
+                self.model
+                    .state_mut()
+                    .set_ephemeral(format!("Config not reloaded: {e}"), Duration::from_secs(3));
+                self.scheduler.mark(RenderDelta::StatusLine);
+                return;
+            }
+        };
+        let state = self.model.state_mut();
+        state.options.reseed(&config.option_table());
+        // The option table holds what is in effect, `:set` included.
+        let options = &state.options;
+        config.file.input.timeout = options.get_bool("timeout");
+        config.file.input.timeoutlen =
+            options.get_number("timeoutlen").clamp(0, u32::MAX as i64) as u32;
+        config.file.scroll.margin.vertical =
+            options.get_number("scrolloff").clamp(0, u16::MAX as i64) as u16;
+        for lhs in self.config.file.abbreviations.keys() {
+            if !config.file.abbreviations.contains_key(lhs) {
+                state.abbreviations.remove(lhs);
+            }
+        }
+        for (lhs, rhs) in &config.file.abbreviations {
+            if let Err(e) = state.abbreviations.define(lhs, rhs) {
+                warn!(target: "config", abbreviation = %lhs, error = %e, "config_abbreviation_rejected");
+            }
+        }
+        let mut msg = "Config reloaded".to_string();
+        if let Some(name) = &config.file.colorscheme
+            && self.config.file.colorscheme.as_ref() != Some(name)
+        {
+            match Theme::load(name) {
+                Ok(theme) => state.set_theme(theme),
+                Err(e) => {
+                    warn!(target: "config", theme = %name, error = %e, "colorscheme_load_failed");
+                    msg = e.to_string();
+                }
+            }
+        }
+        if let Ok((w, h)) = crossterm::terminal::size() {
+            let ctx = ConfigContext::new(w, h, STATUS_ROWS, 0, self.platform_traits);
+            config.apply_context(ctx);
+        }
+        state.config_vertical_margin = config.effective_vertical_margin as usize;
+        // Read at startup, written at exit: the startup table stays in force.
+        config.file.shada = self.config.file.shada.clone();
+        state.set_ephemeral(msg, Duration::from_secs(3));
+        info!(target: "config", path = %self.config_path.display(), "config_reloaded");
+        self.config = config;
+        self.apply_option_changes();
+        self.render_engine.invalidate_for_resize();
+        self.scheduler.mark(RenderDelta::Full);
+    }
+
+    /// Watch the files and listings of the open buffers and the config
+    /// file. A file starts being watched as its buffer last read it.
+    fn sync_file_watch(&mut self) {
+        let Some(watch) = &self.file_watch else {
+            return;
+        };
+        let state = self.model.state_mut();
+        let (mut files, dirs) = state.watched_paths();
+        state.file_watch.track(&files);
+        files.insert(self.config_path.clone());
+        if watch.set(WatchSet { files, dirs }) {
+            debug!(target: "runtime.watch", "watch_set_changed");
+        }
+    }
+
+    fn handle_status_segment(&mut self, update: &SegmentUpdate) -> LoopControl {
+        if self.segments.accepts(update)
+            && self
+                .model
+                .state_mut()
+                .status_segments
+                .set(&update.name, update.text.clone())
+        {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// A language server's message. Diagnostics repaint through
+    /// `apply_diagnostics_change`; a failed or exited server leaves a
+    /// message on the status line.
+    fn handle_lsp(&mut self, message: &LspMessage) -> LoopControl {
+        if self.lsp.handle(message, self.model.state_mut()) {
+            self.lsp_pending = true;
+        }
+        if matches!(message.kind, LspMessageKind::Exited { .. }) {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    fn handle_git_info(&mut self, info: &GitInfo) -> LoopControl {
+        let status = info.branch.clone().map(|branch| core_state::GitStatus {
+            branch,
+            dirty: info.dirty,
+        });
+        if self.model.state_mut().git.apply(&info.dir, status) {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// `git blame` output for `:blame`. A failure turns `:blame` off and
+    /// shows git's message.
+    fn handle_git_blame(&mut self, blame: &GitBlame) -> LoopControl {
+        let git = &mut self.model.state_mut().git;
+        match &blame.output {
+            Ok(out) => {
+                if git.apply_blame(&blame.path, out) {
+                    self.scheduler.mark(RenderDelta::Full);
+                }
+            }
+            Err(msg) => {
+                if git.fail_blame(&blame.path) {
+                    warn!(target: "runtime.git", path = %blame.path.display(), error = %msg, "git_blame_failed");
+                    self.model
+                        .state_mut()
+                        .set_ephemeral(msg.clone(), Duration::from_secs(3));
+                    self.scheduler.mark(RenderDelta::Full);
+                }
+            }
+        }
+        LoopControl::Continue { lines_changed: 0 }
+    }
+
+    /// A `--listen` client's request: `command` runs an ex command, `open`
+    /// edits a file and `buffer_lines` reads lines of the active buffer.
+    /// Unknown methods and `E<n>:` messages go back as the error.
+    fn handle_rpc(&mut self, request: &RpcRequest) -> LoopControl {
+        debug!(target: "runtime.rpc", client = request.client, method = %request.method, "rpc_request");
+        let unchanged = LoopControl::Continue { lines_changed: 0 };
+        let invalid = || Err("E475: Invalid argument".to_string());
+        let (control, result) = match request.method.as_str() {
+            "command" => match request.str_param(0) {
+                Some(cmd) => self.run_rpc_ex(cmd),
+                None => (unchanged, invalid()),
+            },
+            "open" => match request.str_param(0) {
+                Some(path) => self.run_rpc_ex(&format!("e {path}")),
+                None => (unchanged, invalid()),
+            },
+            "buffer_lines" => (
+                unchanged,
+                rpc_buffer_lines(self.model.state(), &request.params),
+            ),
+            method => (unchanged, Err(format!("unknown method {method}"))),
+        };
+        if let (Some(hub), Some(msgid)) = (&self.rpc, request.msgid) {
+            hub.reply(request.client, msgid, result);
+        }
+        control
+    }
+
+    /// Run `cmd` as typed on the command line; its error message, if any,
+    /// is the result.
+    fn run_rpc_ex(&mut self, cmd: &str) -> (LoopControl, Result<RpcValue, String>) {
+        self.model.state_mut().ephemeral_status = None;
+        let control = self.run_headless_ex(cmd);
+        let error = self
+            .model
+            .state()
+            .ephemeral_status
+            .as_ref()
+            .map(|msg| msg.text.clone())
+            .filter(|text| is_error_message(text));
+        (control, error.map_or(Ok(RpcValue::Nil), Err))
+    }
+
+    /// Notify subscribed `--listen` clients of what changed since `before`.
+    fn publish_rpc_events(&self, before: &RpcSnapshot) {
+        let Some(hub) = &self.rpc else {
+            return;
+        };
+        let after = RpcSnapshot::capture(&self.model);
+        if after.mode != before.mode {
+            hub.notify("mode_changed", vec![RpcValue::from(after.mode)]);
+        }
+        if after.buffer != before.buffer {
+            let name = (after.buffer.1.as_deref())
+                .map_or(RpcValue::Nil, |p| RpcValue::from(p.display().to_string()));
+            hub.notify("buf_enter", vec![name]);
+        }
+        if after.modified != before.modified {
+            hub.notify("modified", vec![RpcValue::from(after.modified)]);
+        }
+        if after.cursor != before.cursor {
+            let (line, byte) = after.cursor;
+            hub.notify(
+                "cursor_moved",
+                vec![RpcValue::from(line as u64), RpcValue::from(byte as u64)],
+            );
+        }
+    }
+
+    /// `:sh` — hand the terminal to an interactive shell. Input capture is
+    /// stopped first so the child receives every keystroke, then restarted
+    /// and the screen repainted once the shell exits.
+    async fn run_interactive_shell(&mut self) {
+        self.model.state_mut().shell.interactive = false;
+        let shell = self.shell_program();
+        info!(target: "runtime.shell", shell = %shell, "interactive_shell_start");
+
+        if let Some(shutdown) = self.input_shutdown.take() {
+            shutdown.signal();
+        }
+        if let Some(handle) = self.input_task.take() {
+            let _ = handle.await;
+        }
+        if let Some(guard) = self.terminal_guard.as_mut()
+            && let Err(e) = guard.suspend()
+        {
+            error!(target: "runtime.shell", ?e, "terminal_suspend_failed");
+        }
+
+        let status = tokio::process::Command::new(&shell).status().await;
+
+        if let Some(guard) = self.terminal_guard.as_mut()
+            && let Err(e) = guard.resume()
+        {
+            error!(target: "runtime.shell", ?e, "terminal_resume_failed");
+        }
+        if let Some(tx) = self.tx.as_ref() {
+            let (task, shutdown) = core_input::spawn_async_input(tx.input.clone());
+            self.input_task = Some(task);
+            self.input_shutdown = Some(shutdown);
+        }
+
+        match status {
+            Ok(status) => {
+                info!(target: "runtime.shell", code = ?status.code(), "interactive_shell_exit");
+            }
+            Err(e) => {
+                error!(target: "runtime.shell", ?e, "interactive_shell_failed");
+                self.model.state_mut().set_ephemeral(
+                    format!("E282: Cannot execute shell: {e}"),
+                    Duration::from_secs(3),
+                );
+            }
+        }
+        self.render_engine.invalidate_for_resize();
+        self.scheduler.mark(RenderDelta::Full);
+        self.finish_cycle(0, false);
+    }
+
     fn handle_shutdown(&mut self) -> LoopControl {
         LoopControl::Break {
             reason: ShutdownReason::ShutdownEvent,
         }
     }
 
-    fn auto_scroll(&mut self) -> bool {
+    /// Keep the cursor visible and mark the viewport's move since `before`
+    /// (the active view before the event) as a scroll. Page motions
+    /// (`Ctrl-D` / `Ctrl-U` / `Ctrl-F` / `Ctrl-B`) move the viewport
+    /// themselves, so the scroll is measured from the event's start rather
+    /// than from this call.
+    fn auto_scroll(&mut self, before: &core_model::View) -> bool {
         if let Ok((width, height)) = crossterm::terminal::size() {
-            let overlay_rows = if height > 0 {
-                core_render::overlay::overlay_line_count(self.model.state(), width)
+            let area = text_area(&self.model, width, height);
+            let active = self.model.active_view().id;
+            let region = self.model.layout(area).region_of(active).unwrap_or(area);
+            self.scheduler.set_viewport_rows(region.height as usize);
+            let same_view =
+                active == before.id && self.model.active_view().buffer_id == before.buffer_id;
+            let before_first = if same_view {
+                before.viewport_first_line
             } else {
-                0
-            } as usize;
-            let base_text_height = if height > 0 { (height - 1) as usize } else { 0 };
-            let effective_text_height = base_text_height.saturating_sub(overlay_rows);
-            let before_first = self.model.active_view().viewport_first_line;
-            let scroll_changed = {
-                let (state, view) = self.model.split_state_and_active_view();
-                view.auto_scroll(state, effective_text_height)
+                self.model.active_view().viewport_first_line
             };
-            if scroll_changed {
-                let after_first = self.model.active_view().viewport_first_line;
+            {
+                let (state, view) = self.model.split_state_and_active_view();
+                let gutter = core_render::gutter::Gutter::for_view(state, view);
+                let text_width = region.width.saturating_sub(gutter.width);
+                view.auto_scroll(state, region.height as usize, text_width);
+            }
+            let after_first = self.model.active_view().viewport_first_line;
+            if after_first != before_first {
                 self.scheduler.mark(RenderDelta::Scroll {
                     old_first: before_first,
                     new_first: after_first,
@@ -987,12 +2514,64 @@ impl<'a> EditorRuntime<'a> {
         false
     }
 
+    /// Follow a scroll of the focused window (motion or auto-scroll) in the
+    /// other windows of its `'scrollbind'` group, marking a scroll for each
+    /// window that moved.
+    fn scroll_bind(&mut self, before: &core_model::View) -> bool {
+        let after = self.model.active_view();
+        if after.id != before.id || after.buffer_id != before.buffer_id {
+            return false;
+        }
+        let delta = after.viewport_first_line as isize - before.viewport_first_line as isize;
+        let Ok((width, height)) = crossterm::terminal::size() else {
+            return false;
+        };
+        let area = text_area(&self.model, width, height);
+        let moved = self.model.scroll_bound_siblings(delta, area);
+        for (id, old_first) in &moved {
+            let new_first = self
+                .model
+                .views()
+                .iter()
+                .find(|v| v.id == *id)
+                .map_or(*old_first, |v| v.viewport_first_line);
+            self.scheduler.mark(RenderDelta::Scroll {
+                old_first: *old_first,
+                new_first,
+            });
+        }
+        !moved.is_empty()
+    }
+
     fn finish_cycle(&mut self, lines_changed: usize, scrolled: bool) {
         debug_assert!(
             self.model.active_view().cursor.line < self.model.state().active_buffer().line_count(),
             "cursor must be within buffer before scheduling render"
         );
-
+        if self.syntax_pending {
+            self.sync_syntax();
+        }
+        if self.model.state().signs.is_dirty() {
+            self.apply_sign_changes();
+        }
+        self.apply_theme_change();
+        self.apply_tabline_change();
+        self.apply_wildmenu_change();
+        self.apply_search_highlight_change();
+        self.apply_diagnostics_change();
+        self.apply_completion_change();
+        self.apply_hover_change();
+
+        // Input already queued gets handled before drawing, within the
+        // frame budget; its damage merges into the held decision.
+        if self.scheduler.has_pending()
+            && self
+                .render_engine
+                .hold_frame(Instant::now(), self.rx.has_input())
+        {
+            self.scheduler.hold();
+            return;
+        }
         if let Some(decision) = self.scheduler.consume() {
             log_render_decision(&decision, lines_changed, scrolled);
             if let Err(e) = RenderInvoker::new(
@@ -1007,7 +2586,9 @@ impl<'a> EditorRuntime<'a> {
         }
     }
 
-    fn replay_text_input(&mut self, normalized: &str, graphemes: &[String]) -> LoopControl {
+    /// Feed committed or pasted text to the command line a key at a time,
+    /// or insert it whole in Insert mode (one edit, one undo step).
+    fn replay_text_input(&mut self, normalized: &str) -> LoopControl {
         let ctx = self.command_context();
         if ctx.colon_active() {
             let mut outcome = DispatchOutcome::default();
@@ -1025,12 +2606,8 @@ impl<'a> EditorRuntime<'a> {
                 LoopControl::Continue { lines_changed }
             }
         } else if matches!(ctx.mode(), Mode::Insert) {
-            let mut outcome = DispatchOutcome::default();
-            for grapheme in graphemes {
-                let single =
-                    self.process_action(Action::Edit(EditKind::InsertGrapheme(grapheme.clone())));
-                outcome.absorb(single);
-            }
+            let outcome =
+                self.process_action(Action::Edit(EditKind::InsertText(normalized.to_string())));
             let quit = outcome.quit;
             let lines_changed = self.apply_dispatch_outcome(outcome);
             if quit {
@@ -1056,13 +2633,21 @@ impl<'a> EditorRuntime<'a> {
             action = ?action
         );
         let result = span.in_scope(|| {
-            dispatch(
+            dispatch_with_commands(
                 action,
                 &mut self.model,
                 &mut self.sticky_visual_col,
                 &self.observers,
+                &self.commands,
             )
         });
+        if self.model.state().options.has_pending_changes() {
+            self.apply_option_changes();
+        }
+        self.spawn_shell_jobs();
+        self.spawn_grep_job();
+        self.spawn_jobs();
+        self.spawn_timers();
         let post_status = StatusSnapshot::capture(self.model.state());
         if pre_status.mode_disc != post_status.mode_disc {
             let new_mode = self.model.state().mode;
@@ -1096,7 +2681,400 @@ impl<'a> EditorRuntime<'a> {
         outcome
     }
 
+    /// Options Step 1: drain `:set` change notifications and push new values
+    /// into the subsystems that cache them (input timeouts, scroll margin,
+    /// tab width, render output).
+    fn apply_option_changes(&mut self) {
+        use core_config::options::{OptionEffect, OptionValue};
+        let changes = self.model.state_mut().options.take_changes();
+        let mut full_render = false;
+        for change in changes {
+            info!(
+                target: "runtime.options",
+                name = change.name,
+                old = ?change.old,
+                new = ?change.new,
+                "option_applied"
+            );
+            match (change.effect, &change.new) {
+                (OptionEffect::Input, OptionValue::Bool(b)) if change.name == "timeout" => {
+                    self.config.file.input.timeout = *b;
+                }
+                (OptionEffect::Input, OptionValue::Number(n)) if change.name == "timeoutlen" => {
+                    self.config.file.input.timeoutlen = (*n).clamp(0, u32::MAX as i64) as u32;
+                }
+                (OptionEffect::Scroll, OptionValue::Number(n)) => {
+                    self.config.file.scroll.margin.vertical = (*n).clamp(0, u16::MAX as i64) as u16;
+                    if let Ok((w, h)) = crossterm::terminal::size() {
+                        let ctx = ConfigContext::new(w, h, STATUS_ROWS, 0, self.platform_traits);
+                        self.config.apply_context(ctx);
+                    }
+                    self.model.state_mut().config_vertical_margin =
+                        self.config.effective_vertical_margin as usize;
+                }
+                (OptionEffect::Window, OptionValue::Bool(b)) if change.name == "scrollbind" => {
+                    self.model.set_scroll_bind(*b);
+                }
+                (OptionEffect::Render, OptionValue::Number(n)) if change.name == "tabstop" => {
+                    core_text::grapheme::set_tabstop((*n).max(1) as usize);
+                    full_render = true;
+                }
+                (OptionEffect::Render, _) => full_render = true,
+                _ => {}
+            }
+        }
+        if full_render {
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// Replay the buffers' edits into their parse trees and turn lines
+    /// whose highlighting changed into line dirt, like sign changes below
+    /// (their text may not have changed, e.g. lines after a newly opened
+    /// block comment).
+    fn sync_syntax(&mut self) {
+        self.syntax_pending = false;
+        // Edits made outside a dispatch (RPC, jobs) move folds too.
+        self.model.follow_edits();
+        let active = self.model.state().active;
+        let mut other_buffer = false;
+        for (buffer, changed) in self.syntax.sync_all(self.model.state_mut()) {
+            if buffer != active {
+                other_buffer = true;
+                continue;
+            }
+            let (Some(start), Some(end)) = (
+                changed.iter().map(|r| r.start).min(),
+                changed.iter().map(|r| r.end).max(),
+            ) else {
+                continue;
+            };
+            let lines: Vec<usize> = changed.into_iter().flatten().collect();
+            self.render_engine
+                .invalidate_lines(self.model.state(), active, &lines);
+            self.scheduler.mark(RenderDelta::Lines(start..end));
+        }
+        if other_buffer && self.model.views().len() > 1 {
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// Hand a `:colorscheme` change to the renderer; every cached row
+    /// carries the old colors, so the next frame is full.
+    fn apply_theme_change(&mut self) {
+        if self.model.state_mut().take_theme_change() {
+            self.render_engine.set_theme(self.model.state().theme());
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// The tabline row appearing or going away (a second buffer opened, the
+    /// last but one closed) moves every text row by one.
+    fn apply_tabline_change(&mut self) {
+        let shown = core_render::tabline::tabline_visible(self.model.state(), self.model.tabs());
+        if shown != self.tabline_shown {
+            self.tabline_shown = shown;
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// The wildmenu row opening or closing above the command line takes
+    /// the last text row or gives it back; while it stays open the partial
+    /// paths repaint it with the overlay rows.
+    fn apply_wildmenu_change(&mut self) {
+        let shown = self.model.state().command_line.wildmenu().is_some();
+        if shown != self.wildmenu_shown {
+            self.wildmenu_shown = shown;
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// A new search pattern or `:noh` changes which cells carry match
+    /// highlighting anywhere in the viewport (and in other splits).
+    fn apply_search_highlight_change(&mut self) {
+        if self.model.state_mut().take_search_highlight_change() {
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// Published diagnostics move signs (possibly opening or closing the
+    /// sign column), underlines and the status counts at once.
+    fn apply_diagnostics_change(&mut self) {
+        if self.model.state_mut().diagnostics.take_changed() {
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// The completion popup follows `state.completion` on the next frame;
+    /// a server answer arriving on its own still needs one.
+    fn apply_completion_change(&mut self) {
+        if self.model.state_mut().completion.take_changed() {
+            self.scheduler.mark(RenderDelta::CursorOnly);
+        }
+    }
+
+    /// The hover popup follows `state.hover` on the next frame; the status
+    /// line carries "No information available" for an empty answer.
+    fn apply_hover_change(&mut self) {
+        if self.model.state_mut().hover.take_changed() {
+            self.scheduler.mark(RenderDelta::StatusLine);
+        }
+    }
+
+    /// Turn sign placements since the last frame into line dirt: the rows
+    /// are invalidated in the render caches (their text did not change) and
+    /// scheduled as a `Lines` delta. Signs in a buffer other than the active
+    /// one can only be visible in another split, which repaints in full.
+    fn apply_sign_changes(&mut self) {
+        let active = self.model.state().active;
+        let changed = self.model.state_mut().signs.take_dirty();
+        let mut lines: Vec<usize> = Vec::new();
+        let mut other_buffer = false;
+        for (buffer, line) in changed {
+            if buffer == active {
+                lines.push(line);
+            } else {
+                other_buffer = true;
+            }
+        }
+        trace!(
+            target: "render.engine",
+            lines = lines.len(),
+            other_buffer,
+            "sign_changes"
+        );
+        self.render_engine
+            .invalidate_lines(self.model.state(), active, &lines);
+        if let (Some(first), Some(last)) = (lines.first(), lines.last()) {
+            self.scheduler.mark(RenderDelta::Lines(*first..*last + 1));
+        }
+        if other_buffer && self.model.views().len() > 1 {
+            self.scheduler.mark(RenderDelta::Full);
+        }
+    }
+
+    /// Launch queued `:!` / `:r !` / filter commands as one-shot event
+    /// sources. Output returns through `Event::ShellOutput`.
+    fn spawn_shell_jobs(&mut self) {
+        let requests = self.model.state_mut().shell.take();
+        if requests.is_empty() {
+            return;
+        }
+        let shell = self.shell_program();
+        for request in requests {
+            let Some(tx) = self.tx.as_ref() else {
+                warn!(target: "runtime.shell", id = request.id, "shell_request_dropped");
+                continue;
+            };
+            let mut source = ShellCommandSource::new(request.id, shell.clone(), request.command);
+            if let Some(input) = request.stdin {
+                source = source.with_stdin(input);
+            }
+            self.source_handles
+                .push(core_events::AsyncEventSource::spawn(
+                    Box::new(source),
+                    tx.background.clone(),
+                ));
+            self.shell_jobs.insert(request.id, request.target);
+        }
+        // Finished one-shot jobs no longer need joining at shutdown.
+        self.source_handles.retain(|h| !h.is_finished());
+    }
+
+    /// Launch a queued `:grep`, stopping the one still running. Its output
+    /// streams back through `Event::GrepOutput`.
+    fn spawn_grep_job(&mut self) {
+        let Some(request) = self.model.state_mut().grep.take() else {
+            return;
+        };
+        if let Some(old) = self.grep_job.take() {
+            // The child is killed with the task.
+            old.abort();
+        }
+        let Some(tx) = self.tx.as_ref() else {
+            warn!(target: "runtime.grep", id = request.id, "grep_request_dropped");
+            return;
+        };
+        let source = GrepSource::new(request.id, self.shell_program(), request.command);
+        self.grep_job = Some(core_events::AsyncEventSource::spawn(
+            Box::new(source),
+            tx.background.clone(),
+        ));
+        let state = self.model.state_mut();
+        let summary = state.grep.summary().map(str::to_string);
+        state.status_segments.set("grep", summary);
+        self.scheduler.mark(RenderDelta::StatusLine);
+    }
+
+    /// Launch queued `:make` / `:job` commands and kill stopped ones. Output
+    /// streams back through `Event::JobOutput`.
+    fn spawn_jobs(&mut self) {
+        let jobs = &mut self.model.state_mut().jobs;
+        let (cancelled, requests) = (jobs.take_cancelled(), jobs.take());
+        if cancelled.is_empty() && requests.is_empty() {
+            return;
+        }
+        for id in cancelled {
+            if let Some(job) = self.jobs.remove(&id) {
+                debug!(target: "runtime.job", id, "job_killed");
+                job.abort();
+            }
+        }
+        for request in requests {
+            let Some(tx) = self.tx.as_ref() else {
+                warn!(target: "runtime.job", id = request.id, "job_request_dropped");
+                continue;
+            };
+            let source = JobSource::new(request.id, self.shell_program(), request.command);
+            let handle =
+                core_events::AsyncEventSource::spawn(Box::new(source), tx.background.clone());
+            self.jobs.insert(request.id, handle);
+        }
+        self.update_job_segment();
+    }
+
+    /// Start queued timers and abort stopped ones. Each tick comes back
+    /// as `Event::Timer`.
+    fn spawn_timers(&mut self) {
+        let timers = &mut self.model.state_mut().timers;
+        for id in timers.take_cancelled() {
+            if let Some(timer) = self.timers.remove(&id) {
+                timer.abort();
+            }
+        }
+        for request in timers.take() {
+            let Some(tx) = self.tx.as_ref() else {
+                warn!(target: "runtime.timer", id = request.id, "timer_request_dropped");
+                continue;
+            };
+            let source = TimerSource::new(request.id, request.delay, request.repeat);
+            let handle =
+                core_events::AsyncEventSource::spawn(Box::new(source), tx.background.clone());
+            self.timers.insert(request.id, handle);
+        }
+    }
+
+    /// Probe the active file's repository when its directory changed or a
+    /// refresh was requested (write, window focus). The answer returns
+    /// through `Event::GitInfo`; unnamed buffers use the working directory.
+    fn spawn_git_probe(&mut self) {
+        let dir = match self.model.state().file_name().and_then(|p| p.parent()) {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => std::path::PathBuf::from("."),
+        };
+        let Some(dir) = self.model.state_mut().git.probe(&dir) else {
+            return;
+        };
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+        debug!(target: "runtime.git", dir = %dir.display(), "git_probe_spawned");
+        self.source_handles
+            .push(core_events::AsyncEventSource::spawn(
+                Box::new(GitInfoSource::new(dir)),
+                tx.background.clone(),
+            ));
+        self.source_handles.retain(|h| !h.is_finished());
+    }
+
+    /// Blame the active file while `:blame` is on and its buffer changed or
+    /// a refresh was requested (write). The output returns through
+    /// `Event::GitBlame`.
+    fn spawn_git_blame(&mut self) {
+        let state = self.model.state_mut();
+        let Some(path) = state.file_name().map(Path::to_path_buf) else {
+            return;
+        };
+        let active = state.active;
+        let Some(path) = state.git.blame_due(active, &path) else {
+            return;
+        };
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+        debug!(target: "runtime.git", path = %path.display(), "git_blame_spawned");
+        self.source_handles
+            .push(core_events::AsyncEventSource::spawn(
+                Box::new(GitBlameSource::new(path)),
+                tx.background.clone(),
+            ));
+        self.source_handles.retain(|h| !h.is_finished());
+    }
+
+    /// Start the status segment providers that are due. Answers return
+    /// through `Event::StatusSegment`.
+    fn poll_segments(&mut self, now: Instant) {
+        if self.segments.is_empty() {
+            return;
+        }
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+        let state = self.model.state();
+        let ctx = SegmentContext {
+            file: state.file_name().map(Path::to_path_buf),
+            line_count: state.active_buffer().line_count(),
+            dirty: state.dirty(),
+        };
+        let started = self.segments.poll(now, &ctx, &tx.background);
+        if !started.is_empty() {
+            self.source_handles.extend(started);
+            self.source_handles.retain(|h| !h.is_finished());
+        }
+    }
+
+    /// Open or update the active buffer on its language server, starting
+    /// the server on first use. Messages return through `Event::Lsp`.
+    fn sync_lsp(&mut self) {
+        if !std::mem::take(&mut self.lsp_pending) || self.lsp.is_empty() {
+            return;
+        }
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+        let started = self.lsp.sync(self.model.state_mut(), &tx.background);
+        if !started.is_empty() {
+            self.source_handles.extend(started);
+            self.source_handles.retain(|h| !h.is_finished());
+        }
+    }
+
+    /// Finish a `gd` / `gr` whose server answer (or empty fallback) is in.
+    fn apply_goto(&mut self) {
+        if !self.model.state().tags.has_answer() {
+            return;
+        }
+        let result = apply_goto_answer(&mut self.model);
+        if result.dirty || result.buffer_replaced {
+            self.lsp_pending = true;
+            self.render_engine.invalidate_for_resize();
+            self.scheduler.mark(RenderDelta::Full);
+        }
+        if result.buffer_replaced {
+            self.sticky_visual_col = None;
+        }
+    }
+
+    fn shell_program(&self) -> String {
+        match self.model.state().options.get_string("shell") {
+            "" => "sh".to_string(),
+            shell => shell.to_string(),
+        }
+    }
+
     fn apply_dispatch_outcome(&mut self, outcome: DispatchOutcome) -> usize {
+        self.syntax_pending |= outcome.dirty || outcome.buffer_replaced;
+        self.lsp_pending |= outcome.dirty || outcome.buffer_replaced;
+        if outcome.dirty || outcome.buffer_replaced {
+            self.segments.buffer_changed();
+            let state = self.model.state_mut();
+            if state.diff.refresh(&state.buffers) {
+                self.scheduler.mark(RenderDelta::Full);
+            }
+        }
         if outcome.buffer_replaced {
             self.render_engine.invalidate_for_resize();
             self.scheduler.mark(RenderDelta::Full);
@@ -1124,24 +3102,59 @@ impl<'a> EditorRuntime<'a> {
 #[tokio::main]
 async fn main() -> Result<()> {
     let mut startup = AppStartup::new();
-    let context = startup.run()?;
-    let (tx, rx) = mpsc::channel::<Event>(EVENT_CHANNEL_CAP);
-    let (input_task, input_shutdown) = core_input::spawn_async_input(tx.clone());
+    let mut context = startup.run()?;
+    let (tx, rx) = core_events::event_channel();
+    if let Some(steps) = context.headless.take() {
+        let code = EditorRuntime::new(context, tx, rx, None, None, Vec::new())
+            .run_headless(steps)
+            .await;
+        // `exit` skips destructors; the log writer must flush first.
+        drop(startup);
+        std::process::exit(code);
+    }
+    let (input_task, input_shutdown) = core_input::spawn_async_input(tx.input.clone());
     let mut registry = EventSourceRegistry::new();
     registry.register(TickEventSource::new(std::time::Duration::from_millis(250)));
+    if let Some(replay) = context.replay.take() {
+        info!(target: "runtime.record", events = replay.len(), "replay_started");
+        registry.register(replay);
+    }
+    if let Some(host) = context.plugins.as_mut() {
+        for source in host.event_sources() {
+            registry.register_boxed(source);
+        }
+    }
+    if let Some(server) = context.rpc_server.take() {
+        info!(target: "runtime.rpc", path = %server.path().display(), "rpc_server_started");
+        registry.register(server);
+    }
+    if context.config.file.metrics.export.is_some() {
+        let interval = context.config.file.metrics.interval_ms;
+        registry.register(TelemetrySource::new(Duration::from_millis(interval)));
+    }
+    let watcher = FileWatchSource::new();
+    context.file_watch = Some(watcher.handle());
+    registry.register(watcher);
     let source_handles = registry.spawn_all(&tx);
 
-    let mut runtime =
-        EditorRuntime::new(context, tx, rx, input_task, input_shutdown, source_handles);
+    let mut runtime = EditorRuntime::new(
+        context,
+        tx,
+        rx,
+        Some(input_task),
+        Some(input_shutdown),
+        source_handles,
+    );
     runtime.run().await
 }
 
 fn render(
     engine: &mut RenderEngine,
-    state: &mut EditorState,
-    view: &core_model::View,
+    model: &EditorModel,
     decision: &core_render::scheduler::Decision,
 ) -> Result<core_state::RenderPathSnapshotLite> {
+    let state = model.state();
+    let view = model.active_view();
     use core_render::timing::record_last_render_ns;
     use crossterm::terminal::size;
     use std::time::Instant;
@@ -1152,11 +3165,39 @@ fn render(
     // Refactor R2 Step 11: capture render duration.
     let start = Instant::now();
     let layout = core_model::Layout::single(w, h);
+    let tabline_visible = core_render::tabline::tabline_visible(state, model.tabs());
     let res = match &decision.effective {
+        // Split layouts (and the tabline row) fan the decision out per region;
+        // the single-view partial paths below assume one viewport spanning
+        // the terminal and no diff filler rows.
+        _ if model.views().len() > 1 || tabline_visible || state.diff.is_active() => {
+            // Split windows carry their own status rows; the bottom row is
+            // left to the command line.
+            let status_line = if model.views().len() > 1 {
+                core_render::render_engine::build_command_line(state, w)
+            } else {
+                core_render::render_engine::build_status_line_with_ephemeral(state, view, w)
+            };
+            let split = model.layout(text_area(model, w, h));
+            let tabline = tabline_visible.then(|| {
+                core_render::tabline::build_tabline(state, model.tabs(), model.current_tab())
+            });
+            engine.render_views(
+                state,
+                model.views(),
+                view.id,
+                &split,
+                &decision.effective,
+                tabline.as_ref(),
+                w,
+                h,
+                &status_line,
+            )
+        }
         core_render::scheduler::RenderDelta::CursorOnly => {
             let status_line =
                 core_render::render_engine::build_status_line_with_ephemeral(state, view, w);
-            let snapshot = FrameSnapshot::new(&*state, view, &layout, w, h, &status_line);
+            let snapshot = FrameSnapshot::new(state, view, &layout, w, h, &status_line);
             apply_cursor_only(engine, CursorOnlyFrame::new(snapshot))
         }
         core_render::scheduler::RenderDelta::Lines(dirty_lines) => {
@@ -1166,7 +3207,7 @@ fn render(
             for line in dirty_lines.start..dirty_lines.end {
                 tracker.mark(line);
             }
-            let snapshot = FrameSnapshot::new(&*state, view, &layout, w, h, &status_line);
+            let snapshot = FrameSnapshot::new(state, view, &layout, w, h, &status_line);
             apply_lines_partial(engine, LinesPartialFrame::new(snapshot, &mut tracker))
         }
         core_render::scheduler::RenderDelta::Scroll {
@@ -1175,7 +3216,7 @@ fn render(
         } => {
             let status_line =
                 core_render::render_engine::build_status_line_with_ephemeral(state, view, w);
-            let snapshot = FrameSnapshot::new(&*state, view, &layout, w, h, &status_line);
+            let snapshot = FrameSnapshot::new(state, view, &layout, w, h, &status_line);
             apply_scroll_shift(
                 engine,
                 ScrollShiftFrame::new(snapshot, *old_first, *new_first),
@@ -1184,7 +3225,7 @@ fn render(
         _ => {
             let status_line =
                 core_render::render_engine::build_status_line_with_ephemeral(state, view, w);
-            let snapshot = FrameSnapshot::new(&*state, view, &layout, w, h, &status_line);
+            let snapshot = FrameSnapshot::new(state, view, &layout, w, h, &status_line);
             apply_full(engine, snapshot)
         }
     };
@@ -1204,8 +3245,6 @@ fn render(
                 dirty_lines_marked: snap.dirty_lines_marked,
                 dirty_candidate_lines: snap.dirty_candidate_lines,
                 dirty_lines_repainted: snap.dirty_lines_repainted,
-                last_full_render_ns: snap.last_full_render_ns,
-                last_partial_render_ns: snap.last_partial_render_ns,
                 print_commands: snap.print_commands,
                 cells_printed: snap.cells_printed,
                 scroll_region_shifts: snap.scroll_region_shifts,
@@ -1215,12 +3254,225 @@ fn render(
                 trim_success: snap.trim_success,
                 cols_saved_total: snap.cols_saved_total,
                 status_skipped: snap.status_skipped,
+                coalesced_decisions: snap.coalesced_decisions,
+                coalesced_frames: snap.coalesced_frames,
+                full_latency: latency_lite(snap.full_latency),
+                cursor_only_latency: latency_lite(snap.cursor_only_latency),
+                lines_latency: latency_lite(snap.lines_latency),
+                scroll_shift_latency: latency_lite(snap.scroll_shift_latency),
             })
         }
-        Err(e) => Err(e),
+        Err(e) => Err(e),
+    }
+}
+
+/// Screen area available to views: everything below the tabline (tab pages
+/// or buffer bar) and above the overlay and status rows.
+fn text_area(model: &EditorModel, w: u16, h: u16) -> core_model::LayoutRegion {
+    let overlay_rows = if h > 0 {
+        core_render::overlay::overlay_line_count(model.state(), w)
+    } else {
+        0
+    };
+    // The tabline takes the top row while more than one tab page or buffer exists.
+    let top = u16::from(core_render::tabline::tabline_visible(
+        model.state(),
+        model.tabs(),
+    ));
+    core_model::LayoutRegion::new(0, top, w, h.saturating_sub(1 + overlay_rows + top))
+}
+
+/// Write-side limits from the `[shada]` table.
+fn shada_limits(cfg: &core_config::ShadaConfig) -> ShadaLimits {
+    ShadaLimits {
+        max_item_bytes: cfg.max_item_kb.saturating_mul(1024),
+        max_lines: cfg.max_lines,
+        max_history: cfg.history,
+        max_files: cfg.files,
+    }
+}
+
+/// Color depth forced by the config file's `colors` key; unknown values
+/// are logged and leave detection in charge.
+fn color_depth_override(config: &core_config::Config) -> Option<ColorDepth> {
+    let name = config.file.colors.as_deref()?;
+    let depth = ColorDepth::from_name(name);
+    if depth.is_none() {
+        warn!(target: "config", colors = %name, "config_colors_rejected");
+    }
+    depth
+}
+
+/// Build the user command registry from `[commands]` config aliases.
+fn build_command_registry(config: &core_config::Config) -> CommandRegistry {
+    let mut registry = CommandRegistry::new();
+    for (name, expansion) in &config.file.commands {
+        if let Err(e) = registry.register_alias(name.as_str(), expansion.as_str()) {
+            warn!(target: "config", command = %name, error = %e, "config_command_rejected");
+        }
+    }
+    registry
+}
+
+/// `:loglevel[!] [{directive}…]` and `:logpath` (`log_control`).
+fn register_log_commands(registry: &mut CommandRegistry, log: log_control::LogControl) {
+    let control = log.clone();
+    let loglevel = CommandSpec::new("loglevel")
+        .args(ArgSpec::Any)
+        .bang(true)
+        .description("show or change the log filter");
+    let registered = registry.register_builtin(loglevel, move |inv, state, _view| {
+        let msg = if inv.args.is_empty() && !inv.bang {
+            format!("loglevel={}", control.filter())
+        } else {
+            match control.set(&inv.args, inv.bang) {
+                Ok(filter) => format!("loglevel={filter}"),
+                Err(bad) => format!("E475: Invalid argument: {bad}"),
+            }
+        };
+        state.set_ephemeral(msg, Duration::from_secs(3));
+        DispatchResult::dirty()
+    });
+    let logpath = CommandSpec::new("logpath").description("show where the log is written");
+    let registered = registered.and_then(|()| {
+        registry.register_builtin(logpath, move |_inv, state, _view| {
+            let msg = log.path().display().to_string();
+            state.set_ephemeral(msg, Duration::from_secs(3));
+            DispatchResult::dirty()
+        })
+    });
+    if let Err(e) = registered {
+        warn!(target: "runtime", error = %e, "log_command_rejected");
+    }
+}
+
+/// Load the `[plugins]` directory. Failures are logged and summarized in
+/// the status line; the editor starts either way.
+fn load_plugins(
+    cfg: &core_config::PluginsConfig,
+    state: &mut EditorState,
+) -> Option<WasmPluginHost> {
+    let dir = cfg.resolved_dir()?;
+    let mut host = match WasmPluginHost::new(&dir) {
+        Ok(host) => host,
+        Err(e) => {
+            warn!(target: "plugin", error = %e, "plugin_host_init_failed");
+            return None;
+        }
+    };
+    if let Err(e) = host.load_all() {
+        warn!(target: "plugin", dir = %dir.display(), error = %e, "plugin_dir_unreadable");
+    }
+    info!(
+        target: "plugin",
+        dir = %dir.display(),
+        loaded = host.plugins().count(),
+        failed = host.failures().len(),
+        "plugins_loaded"
+    );
+    if let Some((path, reason)) = host.failures().first() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        state.set_ephemeral(
+            format!("Plugin {name} failed to load: {reason}"),
+            Duration::from_secs(3),
+        );
+    }
+    Some(host)
+}
+
+/// Expose every plugin command as a user command. The handlers share the
+/// dispatcher; a call hands the plugin the active buffer's text and
+/// applies its effects. Plugin errors and panics surface as status
+/// messages.
+fn register_plugin_commands(
+    registry: &mut CommandRegistry,
+    dispatcher: &Arc<Mutex<PluginDispatcher>>,
+) {
+    let commands = dispatcher
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .commands()
+        .to_vec();
+    for command in commands {
+        let dispatcher = Arc::clone(dispatcher);
+        let plugin = command.plugin.clone();
+        let spec = CommandSpec::new(command.name.as_str())
+            .description(format!("plugin {}", command.plugin));
+        let registered = registry.register(spec, move |inv, state, _view| {
+            let buffer = state.active_buffer();
+            let text = buffer.slice_bytes(0, buffer.len_bytes());
+            let outcome = dispatcher
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .call(&inv.name, &text);
+            match outcome {
+                Ok(effects) => apply_plugin_effects(&plugin, effects, state),
+                Err(e) => {
+                    forget_contained_panic(&e);
+                    warn!(target: "plugin", command = %inv.name, error = %e, "plugin_command_failed");
+                    state.set_ephemeral(e.to_string(), Duration::from_secs(3));
+                }
+            }
+            DispatchResult::dirty()
+        });
+        if let Err(e) = registered {
+            warn!(target: "plugin", plugin = %command.plugin, error = %e, "plugin_command_rejected");
+        }
+    }
+}
+
+/// A plugin panic the dispatcher caught is not a crash: drop the report
+/// the panic hook recorded for it, or the next real crash would not be the
+/// only one left to take.
+fn forget_contained_panic(error: &PluginCallError) {
+    if matches!(error, PluginCallError::Panicked { .. }) {
+        crash::CrashReport::take();
+    }
+}
+
+/// Show the status `plugin` set and start or stop the timers it asked for;
+/// they fire back into the plugin through `EditorRuntime::handle_timer`.
+fn apply_plugin_effects(plugin: &str, effects: PluginEffects, state: &mut EditorState) {
+    if let Some(status) = effects.status {
+        state.set_ephemeral(status, Duration::from_secs(3));
+    }
+    for timer in effects.timers {
+        match timer {
+            PluginTimer::Start {
+                token,
+                delay,
+                repeat,
+            } => {
+                let plugin = plugin.to_string();
+                state.start_timer(delay, repeat, TimerCallback::Plugin { plugin, token });
+            }
+            PluginTimer::Stop { token } => {
+                state.stop_plugin_timer(plugin, token);
+            }
+        }
     }
 }
 
+/// Bind plugin keymaps in Normal mode as `:Command<CR>`; a `[keymap]`
+/// entry for the same keys wins.
+fn add_plugin_keymaps(keymap: &mut core_config::KeymapConfig, plugin_keymaps: &[PluginKeymap]) {
+    for k in plugin_keymaps {
+        keymap
+            .normal
+            .entry(k.lhs.clone())
+            .or_insert_with(|| format!(":{}<CR>", k.command).as_str().into());
+    }
+}
+
+/// Build the key translator from the `[keymap]` user mappings.
+fn build_translator(keymap: &core_config::KeymapConfig) -> NgiTranslator {
+    let (translator, issues) = NgiTranslator::from_keymap(keymap);
+    for issue in &issues {
+        warn!(target: "config", %issue, "config_keymap_issue");
+    }
+    translator
+}
+
 #[inline]
 fn log_render_decision(
     decision: &core_render::scheduler::Decision,
@@ -1251,6 +3503,7 @@ fn convert_delta_snapshot(
         cursor_only: metrics.cursor_only,
         collapsed_scroll: metrics.collapsed_scroll,
         suppressed_scroll: metrics.suppressed_scroll,
+        scroll_escalated: metrics.scroll_escalated,
         semantic_frames: metrics.semantic_frames,
     })
 }
@@ -1261,6 +3514,7 @@ fn convert_delta_snapshot(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core_actions::dispatcher::dispatch;
     use core_actions::{Action, EditKind, ModeChange, MotionKind, OperatorKind, PendingState};
     use core_events::{KeyCode, KeyEvent, KeyEventExt, KeyModifiers, KeyToken, ModMask, NamedKey};
     use core_render::render_engine::{RenderEngine, build_content_frame};
@@ -1340,7 +3594,7 @@ mod tests {
         let buffer = Buffer::from_str("test", initial).unwrap();
         let state = EditorState::new(buffer);
         let model = EditorModel::new(state);
-        let (tx, rx) = mpsc::channel::<Event>(8);
+        let (tx, rx) = core_events::channel::with_capacity(8, 8);
         EditorRuntime {
             model,
             config: core_config::Config::default(),
@@ -1348,21 +3602,460 @@ mod tests {
             scheduler: RenderScheduler::new(),
             render_engine: RenderEngine::new(),
             render_metrics: RenderMetricsLedger::default(),
+            syntax: SyntaxManager::new(),
+            syntax_pending: true,
             sticky_visual_col: None,
             paste: PasteSession::new(),
             ngi_timeout: NgiTimeoutState::default(),
+            resize: ResizeDebounce::default(),
             translator: NgiTranslator::new(),
             observers: Vec::new(),
+            commands: CommandRegistry::new(),
             hooks: Box::new(NoopEventHooks),
             rx,
             tx: Some(tx),
             source_handles: Vec::new(),
+            shell_jobs: HashMap::new(),
+            grep_job: None,
+            jobs: HashMap::new(),
+            timers: HashMap::new(),
+            plugins: None,
+            segments: SegmentRunner::new(core_events::DEFAULT_SEGMENT_TIMEOUT),
+            rpc: None,
+            config_path: PathBuf::from("oxidized.toml"),
+            file_watch: None,
+            lsp: LspSessions::new(Default::default(), PathBuf::from(".")),
+            lsp_pending: false,
+            autosave: IdleTimer::new(0, Instant::now()),
+            swap_timer: IdleTimer::new(0, Instant::now()),
+            metrics_sink: None,
+            tabline_shown: false,
+            wildmenu_shown: false,
+            mouse_drag: false,
             input_task: None,
             input_shutdown: None,
-            _terminal_guard: None,
+            terminal_guard: None,
+        }
+    }
+
+    #[test]
+    fn status_snapshot_tracks_ruler_and_selection_size() {
+        use core_state::{SelectionKind, SelectionSpan};
+        let mut state = EditorState::new(Buffer::from_str("t", "abc\ndef\n").unwrap());
+        let before = StatusSnapshot::capture(&state);
+        let at = |line, byte| core_text::Position { line, byte };
+        state.selection.set(SelectionSpan::new(
+            at(0, 0),
+            at(0, 1),
+            SelectionKind::Characterwise,
+        ));
+        let selected = StatusSnapshot::capture(&state);
+        assert!(selected.differs(&before));
+        state.selection.set(SelectionSpan::new(
+            at(0, 1),
+            at(0, 2),
+            SelectionKind::Characterwise,
+        ));
+        assert!(!StatusSnapshot::capture(&state).differs(&selected));
+        let mut cursor = at(1, 3);
+        state.active_buffer_mut().insert_newline(&mut cursor);
+        assert!(StatusSnapshot::capture(&state).differs(&selected));
+    }
+
+    #[test]
+    fn metrics_sink_appends_a_line_per_snapshot() {
+        let mut runtime = runtime_for_input_tests("a\n");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.jsonl");
+        let config = core_config::MetricsConfig {
+            export: Some(path.display().to_string()),
+            interval_ms: 100,
+        };
+        runtime.metrics_sink = MetricsSink::from_config(&config);
+        runtime.handle_event(&Event::Telemetry);
+        runtime.handle_tick();
+        runtime.handle_event(&Event::Telemetry);
+        let out = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(out.lines().count(), 2, "ticks write nothing");
+        assert!(out.lines().all(|l| l.starts_with("{\"version\":2,")));
+        // Input counters are current even though nothing was rendered.
+        assert!(
+            out.lines()
+                .all(|l| l.contains("\"input\":{\"keypress_total\":"))
+        );
+        assert!(runtime.metrics_sink.is_some());
+    }
+
+    #[test]
+    fn git_info_for_the_active_directory_reaches_the_status_line() {
+        let mut runtime = runtime_for_input_tests("a\n");
+        let state = runtime.model.state_mut();
+        state.set_file_name(Some(PathBuf::from("/src/repo/main.rs")));
+        // What `spawn_git_probe` records before launching the probe.
+        state.git.probe(Path::new("/src/repo"));
+        let stale = GitInfo {
+            dir: PathBuf::from("/elsewhere"),
+            branch: Some("old".into()),
+            dirty: false,
+        };
+        runtime.handle_git_info(&stale);
+        assert!(!runtime.scheduler.has_pending());
+        let info = GitInfo {
+            dir: PathBuf::from("/src/repo"),
+            branch: Some("topic".into()),
+            dirty: true,
+        };
+        runtime.handle_git_info(&info);
+        assert!(runtime.scheduler.has_pending());
+        let status = core_render::render_engine::build_status_line(
+            runtime.model.state(),
+            runtime.model.active_view(),
+        );
+        assert!(status.contains(" main.rs (topic*) "), "{status}");
+    }
+
+    /// Segment provider reporting the buffer's line count.
+    struct LineCountSegment;
+
+    impl core_events::StatusSegmentProvider for LineCountSegment {
+        fn name(&self) -> &str {
+            "lines"
+        }
+        fn refresh_hint(&self) -> core_events::RefreshHint {
+            core_events::RefreshHint::OnBufferChange
+        }
+        fn segment(&self, ctx: &SegmentContext) -> Option<String> {
+            Some(format!("{}L", ctx.line_count))
+        }
+    }
+
+    #[tokio::test]
+    async fn segment_answers_reach_the_status_line_after_edits() {
+        let mut runtime = runtime_for_input_tests("a\nb\n");
+        runtime
+            .segments
+            .register(std::sync::Arc::new(LineCountSegment));
+        for (keys, expected) in [("", " 3L "), ("dd", " 2L ")] {
+            for ch in keys.chars() {
+                runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char(ch)));
+            }
+            runtime.poll_segments(Instant::now());
+            let Some(Event::StatusSegment(update)) = runtime.rx.recv().await else {
+                panic!("expected a segment answer");
+            };
+            runtime.handle_status_segment(&update);
+            let status = core_render::render_engine::build_status_line(
+                runtime.model.state(),
+                runtime.model.active_view(),
+            );
+            assert!(status.contains(expected), "{status}");
+        }
+    }
+
+    /// Next message from the server, buffering partial reads in `pending`.
+    async fn rpc_read(remote: &mut tokio::io::DuplexStream, pending: &mut Vec<u8>) -> RpcValue {
+        use tokio::io::AsyncReadExt;
+        loop {
+            let mut cursor = std::io::Cursor::new(&pending[..]);
+            if let Ok(value) = rmpv::decode::read_value(&mut cursor) {
+                pending.drain(..cursor.position() as usize);
+                return value;
+            }
+            let mut chunk = [0u8; 1024];
+            let n = tokio::time::timeout(Duration::from_millis(500), remote.read(&mut chunk))
+                .await
+                .expect("server answers")
+                .unwrap();
+            assert!(n > 0, "server closed the connection");
+            pending.extend_from_slice(&chunk[..n]);
         }
     }
 
+    /// Send request `msgid` and run it through the runtime as the event
+    /// loop would.
+    async fn rpc_request(
+        runtime: &mut EditorRuntime<'static>,
+        remote: &mut tokio::io::DuplexStream,
+        msgid: u32,
+        method: &str,
+        params: Vec<RpcValue>,
+    ) {
+        use tokio::io::AsyncWriteExt;
+        let message = RpcValue::Array(vec![
+            RpcValue::from(0),
+            RpcValue::from(msgid),
+            RpcValue::from(method),
+            RpcValue::Array(params),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &message).unwrap();
+        remote.write_all(&bytes).await.unwrap();
+        if let Ok(Some(Event::Rpc(request))) =
+            tokio::time::timeout(Duration::from_millis(50), runtime.rx.recv()).await
+        {
+            let before = RpcSnapshot::capture(&runtime.model);
+            runtime.handle_rpc(&request);
+            runtime.publish_rpc_events(&before);
+        }
+    }
+
+    #[tokio::test]
+    async fn rpc_clients_drive_the_editor_and_hear_its_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("remote.txt");
+        std::fs::write(&file, "alpha\nbeta\n").unwrap();
+        let mut runtime = runtime_for_input_tests("one\n");
+        let hub = RpcHub::default();
+        runtime.rpc = Some(hub.clone());
+        let (mut remote, local) = tokio::io::duplex(4096);
+        let (read, write) = tokio::io::split(local);
+        let tx = runtime.tx.as_ref().unwrap().background.clone();
+        core_events::rpc::serve(read, write, hub, tx);
+        let mut pending = Vec::new();
+        let text = |s: &str| RpcValue::from(s);
+
+        rpc_request(
+            &mut runtime,
+            &mut remote,
+            1,
+            "subscribe",
+            vec![text("buf_enter")],
+        )
+        .await;
+        assert_eq!(rpc_read(&mut remote, &mut pending).await[2], RpcValue::Nil);
+        rpc_request(&mut runtime, &mut remote, 2, "command", vec![text("w")]).await;
+        let reply = rpc_read(&mut remote, &mut pending).await;
+        assert_eq!(reply[2], text("E32: No file name"));
+
+        let path = text(&file.display().to_string());
+        rpc_request(&mut runtime, &mut remote, 3, "open", vec![path.clone()]).await;
+        let reply = rpc_read(&mut remote, &mut pending).await;
+        assert_eq!((&reply[1], &reply[2]), (&RpcValue::from(3), &RpcValue::Nil));
+        let notification = rpc_read(&mut remote, &mut pending).await;
+        assert_eq!(notification[1], text("buf_enter"));
+        assert_eq!(notification[2], RpcValue::Array(vec![path]));
+
+        let range = vec![RpcValue::from(0), RpcValue::from(2)];
+        rpc_request(&mut runtime, &mut remote, 4, "buffer_lines", range).await;
+        let reply = rpc_read(&mut remote, &mut pending).await;
+        assert_eq!(reply[3], RpcValue::Array(vec![text("alpha"), text("beta")]));
+        rpc_request(&mut runtime, &mut remote, 5, "eval", Vec::new()).await;
+        let reply = rpc_read(&mut remote, &mut pending).await;
+        assert_eq!(reply[2], text("unknown method eval"));
+    }
+
+    #[test]
+    fn click_focuses_the_split_and_moves_its_cursor() {
+        let mut runtime = runtime_for_input_tests("one\ntwo\nthree\n");
+        let right = runtime.model.active_view().id;
+        runtime
+            .model
+            .split_active_view(core_model::SplitAxis::Vertical);
+        let left = runtime.model.active_view().id;
+        let layout = runtime.model.layout(text_area(&runtime.model, 40, 10));
+        let region = layout.region_of(right).unwrap();
+        assert!(region.x > 0);
+
+        runtime.click_at(40, 10, region.x + 3, region.y + 2);
+        assert_eq!(runtime.model.active_view().id, right);
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(2, 3)
+        );
+        assert!(runtime.scheduler.has_pending());
+
+        // Past the end of a line lands on its last character; below the
+        // text on the last line.
+        runtime.click_at(40, 10, 10, 0);
+        assert_eq!(runtime.model.active_view().id, left);
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(0, 2)
+        );
+        runtime.click_at(40, 10, 1, 6);
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(3, 0)
+        );
+
+        // The status row of the right split only focuses it.
+        let status = layout.statuses()[layout.views().iter().position(|v| *v == right).unwrap()];
+        runtime.click_at(40, 10, status.x, status.y);
+        assert_eq!(runtime.model.active_view().id, right);
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(2, 3)
+        );
+    }
+
+    #[test]
+    fn drag_selects_and_wheel_scrolls() {
+        let text: String = (0..50).map(|i| format!("line{i}\n")).collect();
+        let mut runtime = runtime_for_input_tests(&text);
+        runtime.click_at(40, 10, 1, 0);
+        runtime.drag_to(40, 10, 0, 2);
+        let state = runtime.model.state();
+        assert_eq!(state.mode, Mode::VisualChar);
+        let span = state.selection.active.unwrap();
+        assert_eq!(
+            (span.start, span.end),
+            (
+                core_text::Position::new(0, 1),
+                core_text::Position::new(2, 0)
+            )
+        );
+        // Dragging past the split keeps to its last row.
+        runtime.drag_to(40, 10, 2, 30);
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(8, 2)
+        );
+        assert_eq!(
+            runtime.model.state().selection.active.unwrap().start,
+            core_text::Position::new(0, 1)
+        );
+        runtime.click_at(40, 10, 0, 0);
+        assert_eq!(runtime.model.state().mode, Mode::Normal);
+        assert!(!runtime.model.state().selection.is_active());
+
+        // The wheel moves the viewport and drags the cursor along.
+        runtime.wheel_at(40, 10, 0, 0, true);
+        let view = runtime.model.active_view();
+        assert_eq!(view.viewport_first_line, 3);
+        assert_eq!(view.cursor, core_text::Position::new(3, 0));
+        runtime.wheel_at(40, 10, 0, 0, false);
+        assert_eq!(runtime.model.active_view().viewport_first_line, 0);
+        runtime
+            .model
+            .state_mut()
+            .options
+            .apply_set("mousescroll=ver:0")
+            .unwrap();
+        runtime.wheel_at(40, 10, 0, 0, true);
+        assert_eq!(runtime.model.active_view().viewport_first_line, 0);
+    }
+
+    #[test]
+    fn second_buffer_reserves_the_tabline_row() {
+        let mut runtime = runtime_for_input_tests("a\n");
+        assert_eq!(text_area(&runtime.model, 80, 24).y, 0);
+        runtime.apply_tabline_change();
+        assert!(!runtime.scheduler.has_pending());
+
+        runtime
+            .model
+            .state_mut()
+            .buffers
+            .open(Buffer::from_str("b", "b\n").unwrap(), None);
+        let area = text_area(&runtime.model, 80, 24);
+        assert_eq!((area.y, area.height), (1, 22));
+        runtime.apply_tabline_change();
+        assert!(runtime.tabline_shown);
+        assert!(runtime.scheduler.has_pending());
+    }
+
+    #[test]
+    fn idle_tick_autosaves_dirty_buffer() {
+        let mut runtime = runtime_for_input_tests("a\n");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("auto.txt");
+        let state = runtime.model.state_mut();
+        state.set_file_name(Some(path.clone()));
+        state.set_dirty(true);
+        runtime.autosave = IdleTimer::new(1, Instant::now());
+        runtime.autosave.note_activity(Instant::now());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        runtime.handle_tick();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n");
+        assert!(!runtime.model.state().dirty());
+        let eph = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(eph.text, "[autosave] 1 written");
+    }
+
+    #[test]
+    fn shell_output_is_routed_to_recorded_target() {
+        let mut runtime = runtime_for_input_tests("a\nb\n");
+        let origin = core_state::ShellOrigin::active(
+            runtime.model.state(),
+            runtime.model.active_view().id.0,
+        );
+        runtime
+            .shell_jobs
+            .insert(3, ShellTarget::ReadAfter { origin, line: 0 });
+        let output = ShellOutput {
+            id: 3,
+            status: Some(0),
+            stdout: "x\n".into(),
+            stderr: String::new(),
+            error: None,
+        };
+        runtime.handle_shell_output(&output);
+        assert!(runtime.shell_jobs.is_empty());
+        assert_eq!(
+            runtime.model.state().active_buffer().line(1).unwrap(),
+            "x\n"
+        );
+        // Unknown ids are ignored.
+        runtime.handle_shell_output(&output);
+        assert_eq!(runtime.model.state().active_buffer().line_count(), 4);
+    }
+
+    #[test]
+    fn grep_output_fills_the_list_and_opens_the_first_match() {
+        let mut runtime = runtime_for_input_tests("a\n");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hit.txt");
+        std::fs::write(&path, "one\ntwo needle\n").unwrap();
+        let id = runtime.model.state_mut().start_grep(
+            "rg --vimgrep needle".into(),
+            "needle",
+            true,
+            core_state::GrepTarget::Quickfix,
+            0,
+            true,
+        );
+        let batch = |lines: Vec<String>, done: Option<core_events::GrepDone>| GrepOutput {
+            id,
+            lines,
+            done,
+        };
+        runtime.handle_grep_output(&batch(
+            vec![format!("{}:2:5:two needle", path.display())],
+            None,
+        ));
+        let state = runtime.model.state();
+        assert_eq!(state.file_name(), Some(path.as_path()));
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(1, 4)
+        );
+        assert_eq!(
+            state.status_segments.visible(),
+            ["grep: 1 match in 1 file…"]
+        );
+
+        let done = core_events::GrepDone {
+            status: Some(0),
+            stderr: String::new(),
+            error: None,
+        };
+        runtime.handle_grep_output(&batch(
+            vec![format!("{}:1:1:x", path.display())],
+            Some(done),
+        ));
+        let state = runtime.model.state();
+        assert!(!state.grep.is_running());
+        assert_eq!(state.quickfix.len(), 2);
+        assert_eq!(
+            state.status_segments.visible(),
+            ["grep: 2 matches in 1 file"]
+        );
+        // The jump happens once.
+        assert_eq!(runtime.model.active_view().cursor.line, 1);
+    }
+
     #[test]
     fn keypress_char_updates_state_and_emits_motion() {
         let mut runtime = runtime_for_input_tests("abc");
@@ -1650,6 +4343,258 @@ chord:ctrl|char|r repeat=false ts=5
         )));
     }
 
+    #[test]
+    fn set_command_updates_runtime_timeoutlen() {
+        let mut runtime = runtime_for_input_tests("abc");
+        runtime.process_action(Action::CommandExecute(":set timeoutlen=40".to_string()));
+        assert_eq!(runtime.config.file.input.timeoutlen, 40);
+        runtime.process_action(Action::CommandExecute(":set noto".to_string()));
+        assert!(!runtime.config.file.input.timeout);
+        assert!(!runtime.model.state().options.has_pending_changes());
+    }
+
+    #[test]
+    fn set_render_option_schedules_full_repaint() {
+        let mut runtime = runtime_for_input_tests("abc");
+        runtime.process_action(Action::CommandExecute(":set number".to_string()));
+        let decision = runtime.scheduler.consume().expect("render scheduled");
+        assert!(matches!(decision.semantic, RenderDelta::Full));
+    }
+
+    #[test]
+    fn edits_resync_syntax_before_rendering() {
+        let mut runtime = runtime_for_input_tests("let a = 1;\nlet b = 2;\n");
+        runtime
+            .model
+            .state_mut()
+            .set_file_name(Some("lib.rs".into()));
+        runtime.sync_syntax();
+        let active = runtime.model.state().active;
+        assert!(runtime.model.state().highlights.has(active));
+        runtime.scheduler.consume();
+
+        runtime.process_action(Action::ModeChange(ModeChange::EnterInsert));
+        for grapheme in ["/", "/", " "] {
+            let outcome =
+                runtime.process_action(Action::Edit(EditKind::InsertGrapheme(grapheme.into())));
+            runtime.apply_dispatch_outcome(outcome);
+        }
+        assert!(runtime.syntax_pending);
+        runtime.finish_cycle(0, false);
+        assert!(!runtime.syntax_pending);
+        let spans = runtime.model.state().highlights.line(active, 0);
+        assert_eq!((spans[0].start, spans[0].end), (0, 13), "{spans:?}");
+    }
+
+    #[test]
+    fn queued_input_coalesces_frames() {
+        let mut runtime = runtime_for_input_tests("a\nb\n");
+        // A generous budget keeps the test independent of render speed.
+        runtime
+            .render_engine
+            .set_frame_budget(Duration::from_secs(60));
+        runtime.scheduler.mark(RenderDelta::Full);
+        runtime.finish_cycle(0, false);
+        let tx = runtime.tx.as_ref().unwrap();
+        // Background events do not hold a frame; input does.
+        tx.background.try_send(Event::Tick).unwrap();
+        runtime.scheduler.mark(RenderDelta::CursorOnly);
+        runtime.finish_cycle(0, false);
+        assert!(!runtime.scheduler.has_pending());
+        runtime.rx.try_recv().unwrap();
+        let key = KeyEventExt::new(KeyToken::Char('j'));
+        let tx = runtime.tx.as_ref().unwrap();
+        tx.input
+            .try_send(Event::Input(InputEvent::KeyPress(key)))
+            .unwrap();
+        runtime.scheduler.mark(RenderDelta::CursorOnly);
+        runtime.finish_cycle(0, false);
+        assert!(
+            runtime.scheduler.has_pending(),
+            "frame held for queued input"
+        );
+        runtime.rx.try_recv().unwrap();
+        runtime.finish_cycle(0, false);
+        assert!(!runtime.scheduler.has_pending());
+        let metrics = runtime.render_engine.metrics_snapshot();
+        assert_eq!(metrics.coalesced_decisions, 1);
+        assert_eq!(metrics.coalesced_frames, 1);
+    }
+
+    #[test]
+    fn queued_motion_presses_move_once_by_their_number() {
+        use std::sync::atomic::Ordering::Relaxed;
+        let mut runtime = runtime_for_input_tests("1\n2\n3\n4\n5\n6\n");
+        let press = |c| Event::Input(InputEvent::KeyPress(KeyEventExt::new(KeyToken::Char(c))));
+        let tx = runtime.tx.as_ref().unwrap();
+        for c in ['j', 'j', 'j', 'k'] {
+            tx.input.try_send(press(c)).unwrap();
+        }
+        let before = core_events::MOTIONS_COALESCED.load(Relaxed);
+        runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char('j')));
+        assert_eq!(runtime.model.active_view().cursor.line, 4);
+        assert!(core_events::MOTIONS_COALESCED.load(Relaxed) >= before + 3);
+        // A different key ends the run and is still handled next.
+        assert!(matches!(
+            runtime.rx.try_recv(),
+            Ok(Event::Input(InputEvent::KeyPress(k))) if k.token == KeyToken::Char('k')
+        ));
+
+        // A count typed before the key is not multiplied.
+        let tx = runtime.tx.as_ref().unwrap();
+        tx.input.try_send(press('k')).unwrap();
+        runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char('2')));
+        runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char('k')));
+        assert_eq!(runtime.model.active_view().cursor.line, 2);
+        assert!(runtime.rx.has_input());
+    }
+
+    #[test]
+    fn changed_config_file_is_hot_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oxidized.toml");
+        let mut runtime = runtime_for_input_tests("a\n");
+        runtime.config_path = path.clone();
+        runtime
+            .model
+            .state_mut()
+            .options
+            .apply_set("scrolloff=1")
+            .unwrap();
+        std::fs::write(
+            &path,
+            "[input]\ntimeoutlen = 300\n[scroll.margin]\nvertical = 5\n[abbreviations]\nteh = \"the\"\n",
+        )
+        .unwrap();
+        runtime.handle_file_changed(&path);
+        let state = runtime.model.state();
+        assert_eq!(state.options.get_number("timeoutlen"), 300);
+        assert_eq!(runtime.config.file.input.timeoutlen, 300);
+        // Set with `:set`, so the config's default does not apply.
+        assert_eq!(state.options.get_number("scrolloff"), 1);
+        assert_eq!(state.abbreviations.get("teh"), Some("the"));
+
+        // A half-written file leaves the running config alone.
+        std::fs::write(&path, "[input\n").unwrap();
+        runtime.handle_file_changed(&path);
+        let state = runtime.model.state();
+        assert_eq!(state.options.get_number("timeoutlen"), 300);
+        let msg = state.ephemeral_status.as_ref().unwrap();
+        assert!(msg.text.starts_with("Config not reloaded"), "{}", msg.text);
+    }
+
+    #[tokio::test]
+    async fn resize_storm_is_laid_out_once() {
+        use std::sync::atomic::Ordering::Relaxed;
+        let mut runtime = runtime_for_input_tests("a\n");
+        runtime.scheduler.consume();
+        fn runtime_invalidations(runtime: &EditorRuntime) -> u64 {
+            runtime
+                .render_engine
+                .metrics_snapshot()
+                .resize_invalidations
+        }
+        let before = runtime_invalidations(&runtime);
+        let suppressed = core_events::RESIZES_SUPPRESSED.load(Relaxed);
+        for size in [(100, 30), (110, 35), (120, 40)] {
+            runtime.handle_input_event(&InputEvent::Resize(size.0, size.1));
+        }
+        assert_eq!(runtime_invalidations(&runtime), before);
+        assert!(!runtime.scheduler.has_pending());
+        assert!(core_events::RESIZES_SUPPRESSED.load(Relaxed) >= suppressed + 2);
+
+        // Once the sizes stop, the last one is laid out before the next
+        // event is handed over.
+        let tx = runtime.tx.as_ref().unwrap().background.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(RESIZE_DEBOUNCE * 4).await;
+            tx.send(Event::Tick).await.unwrap();
+        });
+        assert!(matches!(runtime.next_event().await, Some(Event::Tick)));
+        assert_eq!(runtime_invalidations(&runtime), before + 1);
+        assert_eq!(runtime.resize.deadline(), None);
+
+        // The run loop applies a waiting size before any other event; a
+        // size is laid out only once.
+        runtime.handle_input_event(&InputEvent::Resize(80, 24));
+        runtime.apply_resize();
+        runtime.apply_resize();
+        assert_eq!(runtime_invalidations(&runtime), before + 2);
+    }
+
+    #[test]
+    fn colorscheme_change_repaints_fully() {
+        let mut runtime = runtime_for_input_tests("a\n");
+        runtime.scheduler.consume();
+        let outcome = runtime.process_action(Action::CommandExecute(":colo default".to_string()));
+        runtime.apply_dispatch_outcome(outcome);
+        runtime.scheduler.consume();
+        runtime.apply_theme_change();
+        let decision = runtime.scheduler.consume().expect("render scheduled");
+        assert_eq!(decision.semantic, RenderDelta::Full);
+        assert_eq!(runtime.render_engine.palette().name(), "default");
+        runtime.apply_theme_change();
+        assert!(runtime.scheduler.consume().is_none());
+    }
+
+    #[test]
+    fn search_highlight_change_repaints_fully() {
+        let mut runtime = runtime_for_input_tests("foo\nbar\n");
+        runtime.process_action(Action::SearchStart { forward: true });
+        for ch in "bar".chars() {
+            runtime.process_action(Action::CommandChar(ch));
+        }
+        let outcome = runtime.process_action(Action::CommandExecute("/bar".to_string()));
+        runtime.apply_dispatch_outcome(outcome);
+        runtime.scheduler.consume();
+        runtime.apply_search_highlight_change();
+        let decision = runtime.scheduler.consume().expect("render scheduled");
+        assert_eq!(decision.semantic, RenderDelta::Full);
+        runtime.apply_search_highlight_change();
+        assert!(runtime.scheduler.consume().is_none());
+
+        let outcome = runtime.process_action(Action::CommandExecute(":noh".to_string()));
+        runtime.apply_dispatch_outcome(outcome);
+        runtime.scheduler.consume();
+        runtime.apply_search_highlight_change();
+        assert!(runtime.scheduler.consume().is_some());
+    }
+
+    #[test]
+    fn diagnostics_changes_repaint_in_full() {
+        let mut runtime = runtime_for_input_tests("a\nb\n");
+        let active = runtime.model.state().active;
+        runtime.model.state_mut().diagnostics.set(
+            active,
+            vec![core_state::Diagnostic {
+                severity: core_state::Severity::Error,
+                start: core_text::Position::new(1, 0),
+                end: core_text::Position::new(1, 1),
+                message: "unexpected token".to_string(),
+            }],
+        );
+        runtime.scheduler.consume();
+        runtime.apply_diagnostics_change();
+        let decision = runtime.scheduler.consume().expect("render scheduled");
+        assert_eq!(decision.semantic, RenderDelta::Full);
+        runtime.apply_diagnostics_change();
+        assert!(runtime.scheduler.consume().is_none());
+    }
+
+    #[test]
+    fn sign_changes_schedule_their_lines() {
+        let mut runtime = runtime_for_input_tests("a\nb\nc\n");
+        let active = runtime.model.state().active;
+        let signs = &mut runtime.model.state_mut().signs;
+        signs
+            .place(active, 2, "W", core_state::SignStyle::Warning)
+            .unwrap();
+        runtime.apply_sign_changes();
+        assert!(!runtime.model.state().signs.is_dirty());
+        let decision = runtime.scheduler.consume().expect("render scheduled");
+        assert_eq!(decision.semantic, RenderDelta::Lines(2..3));
+    }
+
     #[test]
     fn tick_flushes_pending_literal_via_translator() {
         let mut runtime = runtime_for_input_tests("");
@@ -1679,6 +4624,192 @@ chord:ctrl|char|r repeat=false ts=5
         assert!(matches!(runtime.ngi_timeout.pending(), PendingState::Idle));
     }
 
+    #[test]
+    fn user_mappings_replay_through_the_runtime() {
+        let mut runtime = runtime_for_input_tests("abc\n");
+        let mut keymap = core_config::KeymapConfig::default();
+        keymap.insert.insert("jk".into(), "<Esc>".into());
+        keymap.normal.insert("<leader>x".into(), "dd".into());
+        runtime.translator = NgiTranslator::from_keymap(&keymap).0;
+
+        for ch in ['i', 'j', 'k'] {
+            runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char(ch)));
+        }
+        assert_eq!(runtime.model.state().mode, Mode::Normal);
+        assert_eq!(
+            runtime.model.state().active_buffer().line(0).unwrap(),
+            "abc\n"
+        );
+
+        for ch in ['\\', 'x'] {
+            runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char(ch)));
+        }
+        assert_eq!(runtime.model.state().active_buffer().line(0).unwrap(), "");
+    }
+
+    /// Native host: `Greet` echoes the buffer's first line, `Boom` panics,
+    /// `Blink` starts a repeating timer that reports its token.
+    struct TestPluginHost;
+
+    impl PluginHost for TestPluginHost {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        fn load_all(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn event_sources(&mut self) -> Vec<Box<dyn core_events::AsyncEventSource>> {
+            Vec::new()
+        }
+        fn commands(&self) -> Vec<core_plugin::PluginCommand> {
+            ["Greet", "Boom", "Blink"]
+                .map(|name| core_plugin::PluginCommand {
+                    plugin: name.to_lowercase(),
+                    name: name.into(),
+                })
+                .into()
+        }
+        fn keymaps(&self) -> Vec<PluginKeymap> {
+            vec![PluginKeymap {
+                plugin: "greet".into(),
+                lhs: "<leader>g".into(),
+                command: "Greet".into(),
+            }]
+        }
+        fn run_command(&mut self, name: &str, text: &str) -> Result<core_plugin::PluginEffects> {
+            match name {
+                "Boom" => panic!("boom"),
+                "Blink" => Ok(PluginEffects {
+                    timers: vec![PluginTimer::Start {
+                        token: 7,
+                        delay: Duration::from_secs(60),
+                        repeat: true,
+                    }],
+                    ..PluginEffects::default()
+                }),
+                _ => Ok(PluginEffects {
+                    status: text.lines().next().map(|l| format!("hello {l}")),
+                    ..PluginEffects::default()
+                }),
+            }
+        }
+        fn run_timer(&mut self, plugin: &str, token: i32, _text: &str) -> Result<PluginEffects> {
+            Ok(PluginEffects {
+                status: Some(format!("{plugin} tick {token}")),
+                ..PluginEffects::default()
+            })
+        }
+    }
+
+    #[test]
+    fn plugin_commands_and_keymaps_run_behind_the_dispatch_boundary() {
+        let mut runtime = runtime_for_input_tests("abc\n");
+        let dispatcher = PluginDispatcher::new(Box::new(TestPluginHost));
+        let mut keymap = core_config::KeymapConfig::default();
+        add_plugin_keymaps(&mut keymap, &dispatcher.keymaps());
+        register_plugin_commands(&mut runtime.commands, &Arc::new(Mutex::new(dispatcher)));
+        runtime.translator = NgiTranslator::from_keymap(&keymap).0;
+
+        for ch in ['\\', 'g'] {
+            runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char(ch)));
+        }
+        let status = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(status.text, "hello abc");
+
+        crash::install(crash::RecentEvents::default());
+        crash::contain(|| runtime.run_headless_ex("Boom")).unwrap();
+        let status = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert!(
+            status
+                .text
+                .starts_with("Plugin boom panicked and was disabled"),
+            "{}",
+            status.text
+        );
+        assert_eq!(runtime.model.state().mode, Mode::Normal);
+        assert!(
+            crash::CrashReport::take().is_none(),
+            "a contained plugin panic leaves no crash report"
+        );
+    }
+
+    #[tokio::test]
+    async fn timers_call_back_on_the_main_loop() {
+        let mut runtime = runtime_for_input_tests("abc\n");
+        let dispatcher = PluginDispatcher::new(Box::new(TestPluginHost));
+        let dispatcher = Arc::new(Mutex::new(dispatcher));
+        register_plugin_commands(&mut runtime.commands, &dispatcher);
+        runtime.plugins = Some(dispatcher);
+
+        runtime.run_headless_ex("defer 60000 set number");
+        let id = runtime.model.state().timers.active()[0].id;
+        assert!(runtime.timers.contains_key(&id));
+        // Fired while a command line is being typed.
+        let state = runtime.model.state_mut();
+        state.command_line.begin();
+        state.command_line.push_char('e');
+        runtime.handle_timer(id);
+        let state = runtime.model.state();
+        assert!(state.options.get_bool("number"));
+        assert_eq!(state.command_line.buffer(), ":e");
+        assert!(
+            !state
+                .command_line
+                .history()
+                .iter()
+                .any(|c| c == "set number")
+        );
+        assert!(runtime.timers.is_empty(), "one-shot timers are done");
+        runtime.model.state_mut().command_line.clear();
+
+        runtime.run_headless_ex("Blink");
+        let id = runtime.model.state().timers.active()[0].id;
+        assert!(runtime.timers.contains_key(&id));
+        runtime.handle_timer(id);
+        runtime.handle_timer(id);
+        let status = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(status.text, "blink tick 7");
+        runtime.run_headless_ex("timerstop");
+        assert!(runtime.timers.is_empty());
+        runtime.handle_timer(id);
+        let status = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(status.text, "1 timer stopped");
+    }
+
+    #[test]
+    fn recursive_mapping_loop_aborts_with_e223() {
+        let mut runtime = runtime_for_input_tests("abc\n");
+        let mut keymap = core_config::KeymapConfig::default();
+        keymap.normal.insert(
+            "Q".into(),
+            core_config::MappingValue::Table {
+                keys: "lQ".into(),
+                remap: true,
+            },
+        );
+        runtime.translator = NgiTranslator::from_keymap(&keymap).0;
+        runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char('Q')));
+        let status = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(status.text, "E223: Recursive mapping");
+        assert!(runtime.translator.take_mapped_key().is_none());
+    }
+
+    #[test]
+    fn invalid_register_name_reports_e354() {
+        let mut runtime = runtime_for_input_tests("abc\n");
+        for ch in ['"', '!', 'x'] {
+            runtime.handle_key_press(&KeyEventExt::new(KeyToken::Char(ch)));
+        }
+        let status = runtime.model.state().ephemeral_status.as_ref().unwrap();
+        assert_eq!(status.text, "E354: Invalid register name: '!'");
+        // The `x` after it ran unprefixed.
+        assert_eq!(
+            runtime.model.state().active_buffer().line(0).unwrap(),
+            "bc\n"
+        );
+        assert_eq!(runtime.model.state().registers.unnamed, "a");
+    }
+
     #[test]
     fn runtime_keypress_tracing_uses_runtime_input_target() {
         let capture = Capture::default();
@@ -2472,6 +5603,28 @@ chord:ctrl|char|r repeat=false ts=5
         assert_eq!(line, norm);
     }
 
+    #[test]
+    fn bracketed_paste_inserts_in_one_edit() {
+        let mut runtime = runtime_for_input_tests("end\n");
+        runtime.process_action(Action::ModeChange(core_actions::ModeChange::EnterInsert));
+        let payload: String = (0..2000).map(|i| format!("line {i}\n")).collect();
+        runtime.handle_paste_start();
+        for chunk in payload.as_bytes().chunks(4096) {
+            runtime.handle_paste_chunk(std::str::from_utf8(chunk).unwrap());
+        }
+        runtime.handle_paste_end();
+        let buf = runtime.model.state().active_buffer();
+        assert_eq!(buf.line_count(), 2002);
+        assert_eq!(buf.line(1999).unwrap(), "line 1999\n");
+        assert_eq!(
+            runtime.model.active_view().cursor,
+            core_text::Position::new(2000, 0)
+        );
+        runtime.process_action(Action::ModeChange(core_actions::ModeChange::LeaveInsert));
+        runtime.process_action(Action::Undo { count: 1 });
+        assert_eq!(runtime.model.state().active_buffer().line_count(), 2);
+    }
+
     #[test]
     fn paste_like_commandline_appends() {
         // Simulate a paste commit in command-line by normalizing and dispatching chars.
@@ -2744,4 +5897,113 @@ chord:ctrl|char|r repeat=false ts=5
             RenderDelta::StatusLine | RenderDelta::Full | RenderDelta::Lines(_)
         ));
     }
+
+    #[test]
+    fn headless_steps_keep_command_line_order() {
+        let matches = Args::command()
+            .try_get_matches_from([
+                "oxidized",
+                "--headless",
+                "--ex",
+                "1",
+                "--keys",
+                "dd",
+                "--ex",
+                ":w",
+            ])
+            .unwrap();
+        assert_eq!(
+            headless_steps(&matches),
+            vec![
+                HeadlessStep::Ex("1".into()),
+                HeadlessStep::Keys("dd".into()),
+                HeadlessStep::Ex(":w".into()),
+            ]
+        );
+        assert!(
+            Args::command()
+                .try_get_matches_from(["oxidized", "--ex", "w"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn headless_runs_leave_shada_alone_unless_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let shada = dir.path().join("shada");
+        let config = dir.path().join("oxidized.toml");
+        std::fs::write(
+            &config,
+            format!("[shada]\npath = {:?}\n", shada.display().to_string()),
+        )
+        .unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "a\n").unwrap();
+        let bootstrap = |extra: &[&str]| {
+            let mut argv = vec!["oxidized", "--headless", "--config"];
+            argv.push(config.to_str().unwrap());
+            argv.extend_from_slice(extra);
+            argv.push(file.to_str().unwrap());
+            let args = Args::try_parse_from(argv).unwrap();
+            AppStartup::load_editor_state(&args).unwrap()
+        };
+        assert_eq!(bootstrap(&[]).config.file.shada.resolved_path(), None);
+        assert_eq!(
+            bootstrap(&["--shada"]).config.file.shada.resolved_path(),
+            Some(shada)
+        );
+        assert!(
+            Args::command()
+                .try_get_matches_from(["oxidized", "--shada"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn error_messages_are_vim_numbered() {
+        assert!(is_error_message("E37: No write since last change"));
+        assert!(!is_error_message("Error: nope"));
+        assert!(!is_error_message("E: nope"));
+        assert!(!is_error_message("\"a.txt\" 2L written"));
+    }
+
+    #[tokio::test]
+    async fn headless_runs_keys_and_ex_steps_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.txt");
+        let mut runtime = runtime_for_input_tests("one\ntwo\nthree\n");
+        runtime.config.file.shada.enabled = false;
+        let code = runtime
+            .run_headless(vec![
+                HeadlessStep::Keys("jdd".into()),
+                HeadlessStep::Ex(format!("w {}", out.display())),
+                HeadlessStep::Ex("q!".into()),
+                HeadlessStep::Keys("dd".into()),
+            ])
+            .await;
+        assert_eq!(code, 0);
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "one\nthree\n");
+        // `:q!` ends the run before the last step.
+        assert_eq!(runtime.model.state().active_buffer().line_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn headless_exit_status_reports_errors() {
+        let mut runtime = runtime_for_input_tests("abc\n");
+        runtime.config.file.shada.enabled = false;
+        let code = runtime
+            .run_headless(vec![
+                HeadlessStep::Keys("x".into()),
+                HeadlessStep::Ex("q".into()),
+            ])
+            .await;
+        assert_eq!(code, 1);
+
+        let mut runtime = runtime_for_input_tests("abc\n");
+        runtime.config.file.shada.enabled = false;
+        let code = runtime
+            .run_headless(vec![HeadlessStep::Keys("<Bogus>".into())])
+            .await;
+        assert_eq!(code, 1);
+    }
 }